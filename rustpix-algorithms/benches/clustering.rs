@@ -0,0 +1,91 @@
+//! Clustering and extraction benchmarks on synthetic pulses.
+//!
+//! Run with `cargo bench -p rustpix-algorithms`; criterion compares every
+//! run with the previous one and reports regressions. Filter with e.g.
+//! `cargo bench -p rustpix-algorithms -- cluster/Grid`.
+
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion, Throughput};
+use rustpix_algorithms::synthetic::{generate, ClusterSizes, SyntheticConfig, SyntheticHits};
+use rustpix_algorithms::{
+    cluster_batch, extract_batch, AlgorithmParams, ClusteringAlgorithm, ClusteringConfig,
+};
+use rustpix_core::extraction::ExtractionConfig;
+
+const ALGORITHMS: [ClusteringAlgorithm; 6] = [
+    ClusteringAlgorithm::Abs,
+    ClusteringAlgorithm::Dbscan,
+    ClusteringAlgorithm::Grid,
+    ClusteringAlgorithm::Streaming,
+    ClusteringAlgorithm::Hierarchical,
+    ClusteringAlgorithm::ConnectedComponents,
+];
+
+/// Named workloads: a default thermal pulse, larger events at a lower rate,
+/// and a noisy pulse.
+fn workloads() -> Vec<(&'static str, SyntheticHits)> {
+    let thermal = SyntheticConfig::default();
+    let large = SyntheticConfig {
+        event_rate_hz: 250_000.0,
+        cluster_sizes: ClusterSizes::Poisson { mean: 16.0 },
+        ..SyntheticConfig::default()
+    };
+    let noisy = SyntheticConfig {
+        noise_fraction: 0.3,
+        ..SyntheticConfig::default()
+    };
+    vec![
+        ("thermal", generate(&thermal)),
+        ("large", generate(&large)),
+        ("noisy", generate(&noisy)),
+    ]
+}
+
+fn bench_clustering(c: &mut Criterion) {
+    let clustering = ClusteringConfig::default();
+    let params = AlgorithmParams::default();
+    let mut group = c.benchmark_group("cluster");
+    group.sample_size(10);
+    for (name, pulse) in workloads() {
+        group.throughput(Throughput::Elements(pulse.hits.len() as u64));
+        for algorithm in ALGORITHMS {
+            group.bench_with_input(
+                BenchmarkId::new(format!("{algorithm:?}"), name),
+                &pulse.hits,
+                |b, hits| {
+                    b.iter_batched(
+                        || hits.clone(),
+                        |mut batch| cluster_batch(&mut batch, algorithm, &clustering, &params),
+                        BatchSize::LargeInput,
+                    );
+                },
+            );
+        }
+    }
+    group.finish();
+}
+
+fn bench_extraction(c: &mut Criterion) {
+    let extraction = ExtractionConfig::default();
+    let mut group = c.benchmark_group("extract");
+    for (name, mut pulse) in workloads() {
+        let clusters = cluster_batch(
+            &mut pulse.hits,
+            ClusteringAlgorithm::Grid,
+            &ClusteringConfig::default(),
+            &AlgorithmParams::default(),
+        )
+        .expect("clustering synthetic hits");
+        group.throughput(Throughput::Elements(pulse.hits.len() as u64));
+        group.bench_with_input(
+            BenchmarkId::new("centroid", name),
+            &pulse.hits,
+            |b, hits| {
+                b.iter(|| extract_batch(hits, clusters, &extraction));
+            },
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_clustering, bench_extraction);
+criterion_main!(benches);
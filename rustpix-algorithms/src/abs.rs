@@ -351,6 +351,32 @@ impl AbsClustering {
                     continue;
                 };
                 let gidx = neighbor_y * ctx.grid_w + neighbor_x;
+                #[cfg(feature = "simd")]
+                if let Some(cell) = state.grid.get(gidx) {
+                    let bounds = |bidx: usize| {
+                        let bucket: &Bucket = &state.buckets[bidx];
+                        bucket.is_active.then(|| crate::simd::BucketBounds {
+                            x_min: i32::from(bucket.x_min),
+                            x_max: i32::from(bucket.x_max),
+                            y_min: i32::from(bucket.y_min),
+                            y_max: i32::from(bucket.y_max),
+                            start_tof: bucket.start_tof,
+                        })
+                    };
+                    let found = crate::simd::find_bucket(
+                        cell,
+                        bounds,
+                        ix,
+                        iy,
+                        tof,
+                        ctx.radius_i32,
+                        ctx.window_tof,
+                    );
+                    if found.is_some() {
+                        return found;
+                    }
+                }
+                #[cfg(not(feature = "simd"))]
                 if let Some(cell) = state.grid.get(gidx) {
                     for &bidx in cell {
                         let bucket = &state.buckets[bidx];
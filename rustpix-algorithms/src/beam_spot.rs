@@ -0,0 +1,313 @@
+//! Beam spot (direct beam center) finding.
+//!
+//! Locates the brightest blob in a counts image and reports its centroid
+//! after rejecting outliers, for alignment checks.
+
+use std::collections::VecDeque;
+
+use rustpix_core::soa::HitBatch;
+
+/// Configuration for [`find_beam_spot`].
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BeamSpotConfig {
+    /// Fraction of the (smoothed) peak a pixel must reach to join the blob.
+    pub threshold_fraction: f64,
+    /// Pixels farther than this many standard deviations from the centroid
+    /// are rejected.
+    pub outlier_sigma: f64,
+    /// Pixel weights are capped at this multiple of the blob's median count,
+    /// so hot pixels cannot pull the centroid.
+    pub hot_pixel_factor: f64,
+    /// Maximum rejection passes.
+    pub max_iterations: usize,
+}
+
+impl Default for BeamSpotConfig {
+    fn default() -> Self {
+        Self {
+            threshold_fraction: 0.5,
+            outlier_sigma: 3.0,
+            hot_pixel_factor: 10.0,
+            max_iterations: 10,
+        }
+    }
+}
+
+/// Location and extent of the beam spot, in detector pixels.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BeamSpot {
+    /// Centroid X.
+    pub x: f64,
+    /// Centroid Y.
+    pub y: f64,
+    /// Weighted standard deviation along X.
+    pub sigma_x: f64,
+    /// Weighted standard deviation along Y.
+    pub sigma_y: f64,
+    /// Pixels kept in the blob.
+    pub pixels: usize,
+    /// Pixels rejected as outliers.
+    pub rejected: usize,
+    /// Total counts in the kept pixels.
+    pub counts: u64,
+}
+
+/// Find the beam spot in a counts image laid out as `[y][x]`.
+///
+/// The peak is taken from a 3×3 median-filtered copy of the image, so
+/// isolated hot pixels cannot be mistaken for it. The blob is the
+/// 4-connected region of pixels around the peak with at least
+/// `threshold_fraction` of the peak's counts; its centroid is then refined
+/// by repeatedly dropping pixels beyond `outlier_sigma`.
+///
+/// Returns `None` if the image is empty or has no counts.
+///
+/// # Panics
+/// Panics if `counts.len()` is not `width * height`.
+#[must_use]
+pub fn find_beam_spot(
+    counts: &[u64],
+    width: usize,
+    height: usize,
+    config: &BeamSpotConfig,
+) -> Option<BeamSpot> {
+    assert_eq!(counts.len(), width * height, "image size mismatch");
+    let smoothed = median_smooth(counts, width, height);
+    // Spots smaller than the filter vanish from the smoothed image; fall
+    // back to the raw counts for those.
+    let (peak_index, peak) = match brightest(&smoothed)? {
+        (_, 0) => brightest(counts)?,
+        found => found,
+    };
+    if peak == 0 {
+        return None;
+    }
+
+    #[allow(clippy::cast_precision_loss)]
+    let threshold = peak as f64 * config.threshold_fraction;
+    let blob = flood_fill(counts, width, height, peak_index, |value| {
+        #[allow(clippy::cast_precision_loss)]
+        let value = value as f64;
+        value >= threshold
+    });
+
+    let mut raw: Vec<u64> = blob.iter().map(|&index| counts[index]).collect();
+    raw.sort_unstable();
+    let median = raw[raw.len() / 2];
+    #[allow(clippy::cast_precision_loss)]
+    let cap = (median.max(1) as f64 * config.hot_pixel_factor).max(1.0);
+
+    #[allow(clippy::cast_precision_loss)]
+    let mut members: Vec<(f64, f64, f64, u64)> = blob
+        .iter()
+        .map(|&index| {
+            let count = counts[index];
+            let x = (index % width) as f64;
+            let y = (index / width) as f64;
+            (x, y, (count as f64).min(cap), count)
+        })
+        .filter(|&(_, _, weight, _)| weight > 0.0)
+        .collect();
+    let total = members.len();
+
+    let mut moments = weighted_moments(&members)?;
+    for _ in 0..config.max_iterations {
+        let (cx, cy, sx, sy) = moments;
+        let before = members.len();
+        members.retain(|&(x, y, _, _)| {
+            let dx = if sx > 0.0 { (x - cx) / sx } else { 0.0 };
+            let dy = if sy > 0.0 { (y - cy) / sy } else { 0.0 };
+            (dx * dx + dy * dy).sqrt() <= config.outlier_sigma
+        });
+        if members.len() == before {
+            break;
+        }
+        moments = weighted_moments(&members)?;
+    }
+
+    let (x, y, sigma_x, sigma_y) = moments;
+    Some(BeamSpot {
+        x,
+        y,
+        sigma_x,
+        sigma_y,
+        pixels: members.len(),
+        rejected: total - members.len(),
+        counts: members.iter().map(|member| member.3).sum(),
+    })
+}
+
+/// Find the beam spot in the hit density of `batch`.
+///
+/// The image covers pixels `0..=max x` by `0..=max y` of the batch.
+#[must_use]
+pub fn find_beam_spot_in_hits(batch: &HitBatch, config: &BeamSpotConfig) -> Option<BeamSpot> {
+    let width = usize::from(*batch.x.iter().max()?) + 1;
+    let height = usize::from(*batch.y.iter().max()?) + 1;
+    let mut counts = vec![0u64; width * height];
+    for (&x, &y) in batch.x.iter().zip(&batch.y) {
+        counts[usize::from(y) * width + usize::from(x)] += 1;
+    }
+    find_beam_spot(&counts, width, height, config)
+}
+
+/// Index and value of the largest entry (the first, on ties).
+fn brightest(values: &[u64]) -> Option<(usize, u64)> {
+    values
+        .iter()
+        .copied()
+        .enumerate()
+        .max_by_key(|&(index, value)| (value, std::cmp::Reverse(index)))
+}
+
+/// Median over each pixel's 3×3 neighborhood (clipped at the edges).
+fn median_smooth(counts: &[u64], width: usize, height: usize) -> Vec<u64> {
+    let mut smoothed = vec![0u64; counts.len()];
+    let mut window = Vec::with_capacity(9);
+    for y in 0..height {
+        for x in 0..width {
+            window.clear();
+            for ny in y.saturating_sub(1)..=(y + 1).min(height - 1) {
+                for nx in x.saturating_sub(1)..=(x + 1).min(width - 1) {
+                    window.push(counts[ny * width + nx]);
+                }
+            }
+            let middle = window.len() / 2;
+            smoothed[y * width + x] = *window.select_nth_unstable(middle).1;
+        }
+    }
+    smoothed
+}
+
+/// Indices of the 4-connected region around `start` whose values pass `keep`.
+fn flood_fill(
+    values: &[u64],
+    width: usize,
+    height: usize,
+    start: usize,
+    keep: impl Fn(u64) -> bool,
+) -> Vec<usize> {
+    let mut visited = vec![false; values.len()];
+    let mut queue = VecDeque::from([start]);
+    visited[start] = true;
+    let mut region = Vec::new();
+    while let Some(index) = queue.pop_front() {
+        region.push(index);
+        let (x, y) = (index % width, index / width);
+        let neighbors = [
+            (x > 0).then(|| index - 1),
+            (x + 1 < width).then(|| index + 1),
+            (y > 0).then(|| index - width),
+            (y + 1 < height).then(|| index + width),
+        ];
+        for neighbor in neighbors.into_iter().flatten() {
+            if !visited[neighbor] && keep(values[neighbor]) {
+                visited[neighbor] = true;
+                queue.push_back(neighbor);
+            }
+        }
+    }
+    region
+}
+
+/// Weighted centroid and standard deviations of `(x, y, weight, _)` pixels.
+fn weighted_moments(members: &[(f64, f64, f64, u64)]) -> Option<(f64, f64, f64, f64)> {
+    let total: f64 = members.iter().map(|member| member.2).sum();
+    if total <= 0.0 {
+        return None;
+    }
+    let cx = members.iter().map(|&(x, _, w, _)| x * w).sum::<f64>() / total;
+    let cy = members.iter().map(|&(_, y, w, _)| y * w).sum::<f64>() / total;
+    let vx = members
+        .iter()
+        .map(|&(x, _, w, _)| (x - cx).powi(2) * w)
+        .sum::<f64>()
+        / total;
+    let vy = members
+        .iter()
+        .map(|&(_, y, w, _)| (y - cy).powi(2) * w)
+        .sum::<f64>()
+        / total;
+    Some((cx, cy, vx.sqrt(), vy.sqrt()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gaussian_image(width: usize, height: usize, cx: f64, cy: f64, sigma: f64) -> Vec<u64> {
+        let mut counts = vec![0u64; width * height];
+        for y in 0..height {
+            for x in 0..width {
+                #[allow(clippy::cast_precision_loss)]
+                let r2 = (x as f64 - cx).powi(2) + (y as f64 - cy).powi(2);
+                #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+                let value = (1000.0 * (-r2 / (2.0 * sigma * sigma)).exp()) as u64;
+                counts[y * width + x] = value;
+            }
+        }
+        counts
+    }
+
+    #[test]
+    fn test_finds_gaussian_center() {
+        let (width, height) = (64, 48);
+        let counts = gaussian_image(width, height, 40.3, 20.7, 4.0);
+        let spot = find_beam_spot(&counts, width, height, &BeamSpotConfig::default()).unwrap();
+        assert!((spot.x - 40.3).abs() < 0.2, "x = {}", spot.x);
+        assert!((spot.y - 20.7).abs() < 0.2, "y = {}", spot.y);
+        assert!(spot.sigma_x > 1.0 && spot.sigma_x < 4.0);
+        assert!(spot.pixels > 0);
+    }
+
+    #[test]
+    fn test_ignores_hot_pixel_and_second_blob() {
+        let (width, height) = (64, 48);
+        let mut counts = gaussian_image(width, height, 20.0, 24.0, 4.0);
+        let dim = gaussian_image(width, height, 50.0, 10.0, 3.0);
+        for (count, extra) in counts.iter_mut().zip(dim) {
+            *count += extra / 4;
+        }
+        // A single hot pixel brighter than the whole beam, far away...
+        counts[45 * width + 5] = 50_000;
+        // ...and one inside the beam, which must not drag the centroid.
+        counts[24 * width + 22] = 50_000;
+
+        let spot = find_beam_spot(&counts, width, height, &BeamSpotConfig::default()).unwrap();
+        assert!((spot.x - 20.0).abs() < 0.5, "x = {}", spot.x);
+        assert!((spot.y - 24.0).abs() < 0.5, "y = {}", spot.y);
+    }
+
+    #[test]
+    fn test_empty_image_has_no_spot() {
+        let config = BeamSpotConfig::default();
+        assert!(find_beam_spot(&[0; 16], 4, 4, &config).is_none());
+        assert!(find_beam_spot_in_hits(&HitBatch::default(), &config).is_none());
+    }
+
+    #[test]
+    fn test_spot_from_hits() {
+        let mut batch = HitBatch::default();
+        for y in 10..14u16 {
+            for x in 10..14u16 {
+                for _ in 0..5 {
+                    batch.push((x, y, 0, 10, 0, 0));
+                }
+            }
+        }
+        let spot = find_beam_spot_in_hits(&batch, &BeamSpotConfig::default()).unwrap();
+        assert!((spot.x - 11.5).abs() < 1e-9);
+        assert!((spot.y - 11.5).abs() < 1e-9);
+        assert_eq!(spot.counts, 80);
+
+        // Spots smaller than the median filter are still found.
+        let mut counts = vec![0u64; 16];
+        counts[5] = 3;
+        counts[6] = 3;
+        let spot = find_beam_spot(&counts, 4, 4, &BeamSpotConfig::default()).unwrap();
+        assert!((spot.x - 1.5).abs() < 1e-9);
+        assert!((spot.y - 1.0).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,369 @@
+//! Connected-component labeling of touching pixels.
+//!
+//! Thermal neutrons leave compact blobs of adjacent pixels, which need no
+//! radius search: two hits belong together when their pixels touch (share an
+//! edge with [`Connectivity::Four`], an edge or a corner with
+//! [`Connectivity::Eight`], or are the same pixel) and their TOF differ by at
+//! most `temporal_window_ns`. Hits are swept in TOF order with a pixel map of
+//! the latest hit on every pixel, so each hit looks at most at nine map
+//! entries and the whole batch is labelled in linear time.
+//!
+//! Linking only to the latest hit of a neighbouring pixel loses nothing: an
+//! earlier hit on that pixel within the window is also within the window of
+//! the latest one, to which it is linked through the pixel itself.
+
+use rustpix_core::clustering::ClusteringError;
+use rustpix_core::soa::HitBatch;
+
+/// Pixel neighbourhood of connected-component clustering.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum Connectivity {
+    /// Pixels sharing an edge.
+    Four,
+    /// Pixels sharing an edge or a corner.
+    #[default]
+    Eight,
+}
+
+impl Connectivity {
+    /// Number of neighbours of a pixel (4 or 8).
+    #[must_use]
+    pub fn neighbors(self) -> u8 {
+        match self {
+            Self::Four => 4,
+            Self::Eight => 8,
+        }
+    }
+
+    /// Connectivity with `neighbors` neighbours, if 4 or 8.
+    #[must_use]
+    pub fn from_neighbors(neighbors: u8) -> Option<Self> {
+        match neighbors {
+            4 => Some(Self::Four),
+            8 => Some(Self::Eight),
+            _ => None,
+        }
+    }
+
+    /// Pixel offsets linked to a hit, the pixel itself first.
+    fn offsets(self) -> &'static [(i32, i32)] {
+        const EIGHT: [(i32, i32); 9] = [
+            (0, 0),
+            (-1, 0),
+            (1, 0),
+            (0, -1),
+            (0, 1),
+            (-1, -1),
+            (1, -1),
+            (-1, 1),
+            (1, 1),
+        ];
+        match self {
+            Self::Four => &EIGHT[..5],
+            Self::Eight => &EIGHT,
+        }
+    }
+}
+
+/// Configuration for connected-component clustering.
+#[derive(Clone, Debug)]
+pub struct ConnectedComponentConfig {
+    /// Pixel neighbourhood linking hits.
+    pub connectivity: Connectivity,
+    /// Largest TOF difference between linked hits (nanoseconds).
+    pub temporal_window_ns: f64,
+    /// Minimum cluster size to keep.
+    pub min_cluster_size: u16,
+}
+
+impl Default for ConnectedComponentConfig {
+    fn default() -> Self {
+        Self {
+            connectivity: Connectivity::Eight,
+            temporal_window_ns: 75.0,
+            min_cluster_size: 1,
+        }
+    }
+}
+
+/// Reusable connected-component clustering state.
+#[derive(Default)]
+pub struct ConnectedComponentState {
+    /// Number of hits processed.
+    pub hits_processed: usize,
+    /// Number of clusters found.
+    pub clusters_found: usize,
+    /// Latest hit (index + 1, 0 = none) on each pixel; all 0 between calls.
+    latest: Vec<usize>,
+    order: Vec<usize>,
+    parent: Vec<usize>,
+    sizes: Vec<usize>,
+    labels: Vec<i32>,
+}
+
+/// Connected-component clustering; see the module docs.
+pub struct ConnectedComponentClustering {
+    config: ConnectedComponentConfig,
+}
+
+impl ConnectedComponentClustering {
+    /// Create with custom configuration.
+    #[must_use]
+    pub fn new(config: ConnectedComponentConfig) -> Self {
+        Self { config }
+    }
+
+    /// Cluster a batch of hits in-place.
+    ///
+    /// Updates `cluster_id` field in `batch`. Labels are numbered in order
+    /// of the first hit of each cluster.
+    ///
+    /// # Errors
+    /// Returns an error if a label does not fit in `i32`.
+    pub fn cluster(
+        &self,
+        batch: &mut HitBatch,
+        state: &mut ConnectedComponentState,
+    ) -> Result<usize, ClusteringError> {
+        state.hits_processed = 0;
+        state.clusters_found = 0;
+        if batch.is_empty() {
+            return Ok(0);
+        }
+
+        let width = usize::from(batch.x.iter().copied().max().unwrap_or(0)) + 1;
+        let height = usize::from(batch.y.iter().copied().max().unwrap_or(0)) + 1;
+        if state.latest.len() < width * height {
+            state.latest.resize(width * height, 0);
+        }
+
+        state.order.clear();
+        state.order.extend(0..batch.len());
+        if !batch.tof.is_sorted() {
+            let tof = &batch.tof;
+            state.order.sort_by_key(|&i| tof[i]);
+        }
+        state.parent.clear();
+        state.parent.extend(0..batch.len());
+
+        self.link(batch, state, width, height);
+        let clusters = self.relabel(batch, state)?;
+
+        state.hits_processed = batch.len();
+        state.clusters_found = clusters;
+        Ok(clusters)
+    }
+
+    /// Union every hit with the latest hit on each linked pixel.
+    fn link(
+        &self,
+        batch: &HitBatch,
+        state: &mut ConnectedComponentState,
+        width: usize,
+        height: usize,
+    ) {
+        let ConnectedComponentState {
+            latest,
+            order,
+            parent,
+            ..
+        } = state;
+        let window = float_to_u32((self.config.temporal_window_ns / 25.0).ceil());
+        let offsets = self.config.connectivity.offsets();
+
+        for &i in order.iter() {
+            let (x, y, tof) = (i32::from(batch.x[i]), i32::from(batch.y[i]), batch.tof[i]);
+            for &(dx, dy) in offsets {
+                let (Ok(nx), Ok(ny)) = (usize::try_from(x + dx), usize::try_from(y + dy)) else {
+                    continue;
+                };
+                if nx >= width || ny >= height {
+                    continue;
+                }
+                let Some(j) = latest[ny * width + nx].checked_sub(1) else {
+                    continue;
+                };
+                if tof - batch.tof[j] <= window {
+                    union(parent, i, j);
+                }
+            }
+            latest[usize::from(batch.y[i]) * width + usize::from(batch.x[i])] = i + 1;
+        }
+
+        for (&x, &y) in batch.x.iter().zip(&batch.y) {
+            latest[usize::from(y) * width + usize::from(x)] = 0;
+        }
+    }
+
+    /// Label the components, dropping those below the minimum size; returns
+    /// the cluster count.
+    fn relabel(
+        &self,
+        batch: &mut HitBatch,
+        state: &mut ConnectedComponentState,
+    ) -> Result<usize, ClusteringError> {
+        let ConnectedComponentState {
+            parent,
+            sizes,
+            labels,
+            ..
+        } = state;
+        let n = batch.len();
+        sizes.clear();
+        sizes.resize(n, 0);
+        for i in 0..n {
+            let root = find(parent, i);
+            sizes[root] += 1;
+        }
+
+        let min_size = usize::from(self.config.min_cluster_size);
+        labels.clear();
+        labels.resize(n, -1);
+        let mut next_label = 0usize;
+        for i in 0..n {
+            let root = find(parent, i);
+            if sizes[root] < min_size {
+                batch.cluster_id[i] = -1;
+                continue;
+            }
+            if labels[root] < 0 {
+                labels[root] = i32::try_from(next_label).map_err(|_| {
+                    ClusteringError::StateError("more than i32::MAX clusters".to_string())
+                })?;
+                next_label += 1;
+            }
+            batch.cluster_id[i] = labels[root];
+        }
+        Ok(next_label)
+    }
+}
+
+impl Default for ConnectedComponentClustering {
+    fn default() -> Self {
+        Self::new(ConnectedComponentConfig::default())
+    }
+}
+
+fn find(parent: &mut [usize], i: usize) -> usize {
+    let mut root = i;
+    while root != parent[root] {
+        root = parent[root];
+    }
+    let mut curr = i;
+    while curr != root {
+        let next = parent[curr];
+        parent[curr] = root;
+        curr = next;
+    }
+    root
+}
+
+fn union(parent: &mut [usize], a: usize, b: usize) {
+    let root_a = find(parent, a);
+    let root_b = find(parent, b);
+    if root_a != root_b {
+        let (low, high) = (root_a.min(root_b), root_a.max(root_b));
+        parent[high] = low;
+    }
+}
+
+fn float_to_u32(value: f64) -> u32 {
+    if value <= 0.0 {
+        return 0;
+    }
+    if value >= f64::from(u32::MAX) {
+        return u32::MAX;
+    }
+    format!("{value:.0}").parse::<u32>().unwrap_or(u32::MAX)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{GridClustering, GridState};
+
+    fn clusters(batch: &mut HitBatch, connectivity: Connectivity) -> usize {
+        let algo = ConnectedComponentClustering::new(ConnectedComponentConfig {
+            connectivity,
+            ..ConnectedComponentConfig::default()
+        });
+        algo.cluster(batch, &mut ConnectedComponentState::default())
+            .unwrap()
+    }
+
+    #[test]
+    fn test_connectivity_decides_diagonal_links() {
+        let mut batch = HitBatch::default();
+        // A diagonal line of three pixels and a separate 2x2 blob.
+        for k in 0..3u16 {
+            batch.push((10 + k, 10 + k, 100 + u32::from(k), 10, 0, 0));
+        }
+        for (x, y) in [(50, 50), (51, 50), (50, 51), (51, 51)] {
+            batch.push((x, y, 100, 10, 0, 0));
+        }
+        batch.sort_by_tof();
+
+        assert_eq!(clusters(&mut batch, Connectivity::Eight), 2);
+        assert_eq!(clusters(&mut batch, Connectivity::Four), 4);
+        let blob = batch.cluster_id[batch.x.iter().position(|&x| x == 50).unwrap()];
+        let blob_hits = batch.cluster_id.iter().filter(|&&id| id == blob).count();
+        assert_eq!(blob_hits, 4);
+    }
+
+    #[test]
+    fn test_temporal_window_and_unsorted_input() {
+        let mut batch = HitBatch::default();
+        // Two neutrons on the same pixels 20 ticks (500 ns) apart, not
+        // sorted by TOF.
+        batch.push((21, 20, 122, 10, 0, 0));
+        batch.push((20, 20, 100, 10, 0, 0));
+        batch.push((20, 20, 120, 10, 0, 0));
+        batch.push((21, 20, 102, 10, 0, 0));
+
+        assert_eq!(clusters(&mut batch, Connectivity::Four), 2);
+        assert_eq!(batch.cluster_id, vec![0, 1, 0, 1]);
+
+        let wide = ConnectedComponentClustering::new(ConnectedComponentConfig {
+            temporal_window_ns: 500.0,
+            ..ConnectedComponentConfig::default()
+        });
+        let mut state = ConnectedComponentState::default();
+        assert_eq!(wide.cluster(&mut batch, &mut state).unwrap(), 1);
+    }
+
+    #[test]
+    fn test_matches_grid_on_touching_pixels() {
+        let mut batch = HitBatch::default();
+        let mut state = 0x9E37_79B9_7F4A_7C15u64;
+        for _ in 0..2_000 {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            let x = u16::try_from(state % 300).unwrap();
+            let y = u16::try_from((state >> 16) % 300).unwrap();
+            let tof = u32::try_from((state >> 32) % 5_000).unwrap();
+            batch.push((x, y, tof, 10, 0, 0));
+        }
+        batch.sort_by_tof();
+        let mut grid = batch.clone();
+
+        let expected = GridClustering::new(crate::GridConfig {
+            radius: 1.5,
+            ..crate::GridConfig::default()
+        })
+        .cluster(&mut grid, &mut GridState::default())
+        .unwrap();
+        let mut state = ConnectedComponentState::default();
+        let algo = ConnectedComponentClustering::default();
+        assert_eq!(algo.cluster(&mut batch, &mut state).unwrap(), expected);
+        assert_eq!(batch.cluster_id, grid.cluster_id);
+        assert_eq!(state.hits_processed, 2_000);
+        // The pixel map is cleared for the next batch.
+        assert!(state.latest.iter().all(|&hit| hit == 0));
+    }
+}
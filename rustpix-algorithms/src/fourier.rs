@@ -0,0 +1,270 @@
+//! Power spectrum and autocorrelation of counts images.
+//!
+//! Periodic detector artifacts (readout patterns, chip gaps) and grating
+//! fringes show up as sharp peaks in the 2D power spectrum and as repeated
+//! maxima in the autocorrelation. Both come from the FFT of the
+//! mean-subtracted image, so the image is treated as periodic.
+
+use rustfft::num_complex::Complex;
+use rustfft::FftPlanner;
+
+/// A map over frequencies or lags, laid out as `[y][x]` with zero frequency
+/// (or zero lag) at `(width / 2, height / 2)`.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FourierMap {
+    /// Number of columns (image width).
+    pub width: usize,
+    /// Number of rows (image height).
+    pub height: usize,
+    /// Map values, row-major.
+    pub values: Vec<f64>,
+}
+
+impl FourierMap {
+    /// Value at offset `(dx, dy)` from the center: a frequency index for
+    /// power spectra, a lag in pixels for autocorrelations.
+    #[must_use]
+    pub fn at(&self, dx: isize, dy: isize) -> Option<f64> {
+        let x = (self.width / 2).checked_add_signed(dx)?;
+        let y = (self.height / 2).checked_add_signed(dy)?;
+        (x < self.width && y < self.height).then(|| self.values[y * self.width + x])
+    }
+}
+
+/// A peak in the power spectrum, i.e. a periodic pattern in the image.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PeriodicPeak {
+    /// Frequency along X (cycles per pixel).
+    pub fx: f64,
+    /// Frequency along Y (cycles per pixel).
+    pub fy: f64,
+    /// Repeat distance in pixels.
+    pub period: f64,
+    /// Direction of the modulation in degrees in `[0, 180)`, from +x towards
+    /// +y (0 for stripes repeating along X).
+    pub angle_deg: f64,
+    /// Power at the peak.
+    pub power: f64,
+    /// Share of the non-DC power in this peak and its mirror image.
+    pub fraction: f64,
+}
+
+/// Power spectrum `|F|² / N` of the mean-subtracted image.
+///
+/// # Panics
+/// Panics if `counts.len()` is not `width * height`.
+#[must_use]
+pub fn power_spectrum(counts: &[u64], width: usize, height: usize) -> FourierMap {
+    let Some(spectrum) = spectrum(counts, width, height) else {
+        return FourierMap::default();
+    };
+    #[allow(clippy::cast_precision_loss)]
+    let scale = counts.len() as f64;
+    centered(width, height, |index| spectrum[index].norm_sqr() / scale)
+}
+
+/// Autocorrelation of the mean-subtracted image, normalized to 1 at zero lag
+/// (all zeros for a flat image).
+///
+/// # Panics
+/// Panics if `counts.len()` is not `width * height`.
+#[must_use]
+pub fn autocorrelation(counts: &[u64], width: usize, height: usize) -> FourierMap {
+    let Some(mut spectrum) = spectrum(counts, width, height) else {
+        return FourierMap::default();
+    };
+    for value in &mut spectrum {
+        *value = Complex::new(value.norm_sqr(), 0.0);
+    }
+    fft_2d(&mut spectrum, width, height, true);
+    let zero_lag = spectrum[0].re;
+    let scale = if zero_lag > 0.0 { zero_lag } else { 1.0 };
+    centered(width, height, |index| spectrum[index].re / scale)
+}
+
+/// The `max_peaks` strongest local maxima of a power spectrum, strongest
+/// first.
+///
+/// The spectrum of a real image is symmetric, so each peak is reported once,
+/// with `fy > 0` (or `fy == 0` and `fx > 0`).
+#[must_use]
+pub fn periodic_peaks(spectrum: &FourierMap, max_peaks: usize) -> Vec<PeriodicPeak> {
+    let (width, height) = (spectrum.width, spectrum.height);
+    let (cx, cy) = (width / 2, height / 2);
+    let total: f64 = spectrum.values.iter().sum::<f64>() - spectrum.at(0, 0).unwrap_or(0.0);
+    if total <= 0.0 {
+        return Vec::new();
+    }
+
+    let mut peaks = Vec::new();
+    for y in cy..height {
+        for x in 0..width {
+            if y == cy && x <= cx {
+                continue;
+            }
+            let index = y * width + x;
+            let power = spectrum.values[index];
+            if power <= 0.0 || !is_local_max(spectrum, x, y) {
+                continue;
+            }
+            #[allow(clippy::cast_precision_loss)]
+            let (fx, fy) = (
+                (x as f64 - cx as f64) / width as f64,
+                (y as f64 - cy as f64) / height as f64,
+            );
+            peaks.push(PeriodicPeak {
+                fx,
+                fy,
+                period: 1.0 / fx.hypot(fy),
+                angle_deg: fy.atan2(fx).to_degrees().rem_euclid(180.0),
+                power,
+                fraction: (2.0 * power / total).min(1.0),
+            });
+        }
+    }
+    peaks.sort_by(|a, b| b.power.total_cmp(&a.power));
+    peaks.truncate(max_peaks);
+    peaks
+}
+
+/// Whether `(x, y)` is at least as large as its 8 neighbors, and strictly
+/// larger than those before it, so plateaus yield one peak.
+fn is_local_max(map: &FourierMap, x: usize, y: usize) -> bool {
+    let index = y * map.width + x;
+    let value = map.values[index];
+    for ny in y.saturating_sub(1)..=(y + 1).min(map.height - 1) {
+        for nx in x.saturating_sub(1)..=(x + 1).min(map.width - 1) {
+            let neighbor_index = ny * map.width + nx;
+            let neighbor = map.values[neighbor_index];
+            if neighbor > value || (neighbor_index < index && neighbor >= value) {
+                return false;
+            }
+        }
+    }
+    true
+}
+
+/// FFT of the mean-subtracted image, or `None` for an empty image.
+fn spectrum(counts: &[u64], width: usize, height: usize) -> Option<Vec<Complex<f64>>> {
+    assert_eq!(counts.len(), width * height, "image size mismatch");
+    if counts.is_empty() {
+        return None;
+    }
+    #[allow(clippy::cast_precision_loss)]
+    let mean = counts.iter().sum::<u64>() as f64 / counts.len() as f64;
+    #[allow(clippy::cast_precision_loss)]
+    let mut buffer: Vec<Complex<f64>> = counts
+        .iter()
+        .map(|&count| Complex::new(count as f64 - mean, 0.0))
+        .collect();
+    fft_2d(&mut buffer, width, height, false);
+    Some(buffer)
+}
+
+/// In-place unnormalized 2D FFT of a `[y][x]` buffer.
+pub(crate) fn fft_2d(buffer: &mut [Complex<f64>], width: usize, height: usize, inverse: bool) {
+    let mut planner = FftPlanner::new();
+    let (rows, columns) = if inverse {
+        (
+            planner.plan_fft_inverse(width),
+            planner.plan_fft_inverse(height),
+        )
+    } else {
+        (
+            planner.plan_fft_forward(width),
+            planner.plan_fft_forward(height),
+        )
+    };
+    rows.process(buffer);
+
+    let mut transposed = vec![Complex::default(); buffer.len()];
+    for y in 0..height {
+        for x in 0..width {
+            transposed[x * height + y] = buffer[y * width + x];
+        }
+    }
+    columns.process(&mut transposed);
+    for y in 0..height {
+        for x in 0..width {
+            buffer[y * width + x] = transposed[x * height + y];
+        }
+    }
+}
+
+/// Build a map with index 0 moved to the center.
+fn centered(width: usize, height: usize, value: impl Fn(usize) -> f64) -> FourierMap {
+    let mut values = vec![0.0; width * height];
+    for y in 0..height {
+        for x in 0..width {
+            let shifted = ((y + height / 2) % height) * width + (x + width / 2) % width;
+            values[shifted] = value(y * width + x);
+        }
+    }
+    FourierMap {
+        width,
+        height,
+        values,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Stripes repeating every `period` pixels along X.
+    #[allow(
+        clippy::cast_precision_loss,
+        clippy::cast_possible_truncation,
+        clippy::cast_sign_loss
+    )]
+    fn stripes(width: usize, height: usize, period: f64) -> Vec<u64> {
+        (0..width * height)
+            .map(|index| {
+                let x = (index % width) as f64;
+                (100.0 + 50.0 * (std::f64::consts::TAU * x / period).cos()).round() as u64
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_power_spectrum_finds_stripe_period() {
+        let counts = stripes(64, 32, 8.0);
+        let spectrum = power_spectrum(&counts, 64, 32);
+        assert_eq!(spectrum.values.len(), 64 * 32);
+        assert!(spectrum.at(0, 0).unwrap().abs() < 1e-6);
+
+        let peaks = periodic_peaks(&spectrum, 3);
+        let peak = &peaks[0];
+        assert!((peak.fx - 0.125).abs() < 1e-12);
+        assert!(peak.fy.abs() < 1e-12);
+        assert!((peak.period - 8.0).abs() < 1e-9);
+        assert!(peak.angle_deg.abs() < 1e-9);
+        assert!(peak.fraction > 0.99, "fraction = {}", peak.fraction);
+    }
+
+    #[test]
+    fn test_autocorrelation_repeats_with_period() {
+        let counts = stripes(64, 32, 8.0);
+        let correlation = autocorrelation(&counts, 64, 32);
+        assert!((correlation.at(0, 0).unwrap() - 1.0).abs() < 1e-9);
+        assert!((correlation.at(8, 0).unwrap() - 1.0).abs() < 1e-3);
+        assert!((correlation.at(4, 0).unwrap() + 1.0).abs() < 1e-3);
+        assert!((correlation.at(0, 5).unwrap() - 1.0).abs() < 1e-9);
+        assert!(correlation.at(40, 0).is_none());
+    }
+
+    #[test]
+    fn test_flat_and_empty_images() {
+        let flat = vec![7u64; 16 * 8];
+        let spectrum = power_spectrum(&flat, 16, 8);
+        assert!(spectrum.values.iter().all(|&value| value.abs() < 1e-9));
+        assert!(periodic_peaks(&spectrum, 5).is_empty());
+        assert!(autocorrelation(&flat, 16, 8)
+            .values
+            .iter()
+            .all(|&value| value.abs() < 1e-9));
+        assert_eq!(power_spectrum(&[], 0, 0), FourierMap::default());
+    }
+}
@@ -0,0 +1,491 @@
+//! GPU (wgpu) grid clustering.
+//!
+//! Same neighbor rule as [`GridClustering`](crate::GridClustering): hits are
+//! linked when they are within the spatial radius and, scanning forward in
+//! the (TOF-sorted) batch, within the temporal window. The grid index is
+//! built on the CPU; the neighbor search and union-find run in a compute
+//! shader (`gpu.wgsl`). Components are rooted at their lowest hit index, so
+//! labels come out in the same order as the CPU grid algorithm.
+
+use std::sync::{mpsc, OnceLock};
+
+use rustpix_core::clustering::ClusteringError;
+use rustpix_core::soa::HitBatch;
+use wgpu::util::DeviceExt;
+
+/// Hits per workgroup; must match `@workgroup_size` in `gpu.wgsl`.
+const WORKGROUP_SIZE: u32 = 256;
+
+/// Configuration for GPU clustering.
+#[derive(Clone, Debug)]
+pub struct GpuConfig {
+    /// Spatial radius for neighbor detection (pixels).
+    pub radius: f64,
+    /// Temporal correlation window (nanoseconds).
+    pub temporal_window_ns: f64,
+    /// Minimum cluster size to keep.
+    pub min_cluster_size: u16,
+    /// Grid cell size (pixels).
+    pub cell_size: usize,
+}
+
+impl Default for GpuConfig {
+    fn default() -> Self {
+        Self {
+            radius: 5.0,
+            temporal_window_ns: 75.0,
+            min_cluster_size: 1,
+            cell_size: 32,
+        }
+    }
+}
+
+/// Reusable GPU clustering state.
+#[derive(Default)]
+pub struct GpuState {
+    /// Number of hits processed.
+    pub hits_processed: usize,
+    /// Number of clusters found.
+    pub clusters_found: usize,
+    cell_start: Vec<u32>,
+    cell_hits: Vec<u32>,
+    cluster_sizes: Vec<u32>,
+    root_to_label: Vec<i32>,
+}
+
+/// Device, queue and compiled pipelines, created once per process.
+struct GpuContext {
+    adapter_name: String,
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    link: wgpu::ComputePipeline,
+    compress: wgpu::ComputePipeline,
+}
+
+/// Clustering on the GPU via a wgpu compute shader.
+pub struct GpuClustering {
+    config: GpuConfig,
+    context: &'static GpuContext,
+}
+
+impl GpuClustering {
+    /// Create with custom configuration on the default GPU adapter.
+    ///
+    /// The adapter and device are requested on first use and shared by all
+    /// instances.
+    ///
+    /// # Errors
+    /// Returns an error if no GPU adapter or device is available.
+    pub fn new(config: GpuConfig) -> Result<Self, ClusteringError> {
+        static CONTEXT: OnceLock<Result<GpuContext, String>> = OnceLock::new();
+        let context = CONTEXT
+            .get_or_init(|| pollster::block_on(GpuContext::create()))
+            .as_ref()
+            .map_err(|err| ClusteringError::StateError(err.clone()))?;
+        Ok(Self { config, context })
+    }
+
+    /// Name of the adapter clustering runs on.
+    #[must_use]
+    pub fn adapter_name(&self) -> &str {
+        &self.context.adapter_name
+    }
+
+    /// Cluster a batch of hits in-place.
+    ///
+    /// Updates `cluster_id` field in `batch`.
+    ///
+    /// # Errors
+    /// Returns an error if the batch exceeds the device's buffer limits or
+    /// the device fails.
+    pub fn cluster(
+        &self,
+        batch: &mut HitBatch,
+        state: &mut GpuState,
+    ) -> Result<usize, ClusteringError> {
+        state.hits_processed = 0;
+        state.clusters_found = 0;
+        if batch.is_empty() {
+            return Ok(0);
+        }
+        let n = batch.len();
+        let n_u32 = u32::try_from(n)
+            .map_err(|_| ClusteringError::InvalidConfig(format!("{n} hits exceed u32 indices")))?;
+        self.check_limits(n)?;
+
+        let cell_size = self.config.cell_size.max(1);
+        let (cells_x, cells_y) = build_cells(batch, cell_size, state);
+        let params = [
+            n_u32,
+            cells_x,
+            cells_y,
+            u32::try_from(cell_size).unwrap_or(u32::MAX),
+            reach(self.config.radius, cell_size),
+            float_to_u32((self.config.temporal_window_ns / 25.0).ceil()),
+            float_to_u32((self.config.radius * self.config.radius).floor()),
+            0,
+        ];
+        let coords: Vec<u32> = batch
+            .x
+            .iter()
+            .zip(&batch.y)
+            .map(|(&x, &y)| u32::from(x) | (u32::from(y) << 16))
+            .collect();
+        let roots = self.run(&params, &coords, &batch.tof, state)?;
+
+        let clusters = assign_labels(
+            batch,
+            &roots,
+            state,
+            u32::from(self.config.min_cluster_size),
+        );
+        state.hits_processed = n;
+        state.clusters_found = clusters;
+        Ok(clusters)
+    }
+
+    fn check_limits(&self, n: usize) -> Result<(), ClusteringError> {
+        let limits = self.context.device.limits();
+        let max_binding =
+            u64::from(limits.max_storage_buffer_binding_size).min(limits.max_buffer_size);
+        let bytes = (n as u64).saturating_mul(4);
+        if bytes > max_binding {
+            return Err(ClusteringError::InvalidConfig(format!(
+                "batch of {n} hits exceeds the GPU buffer limit of {} hits; process smaller batches",
+                max_binding / 4
+            )));
+        }
+        Ok(())
+    }
+
+    /// Upload the batch, run both kernels and read back each hit's root.
+    fn run(
+        &self,
+        params: &[u32; 8],
+        coords: &[u32],
+        tof: &[u32],
+        state: &GpuState,
+    ) -> Result<Vec<u32>, ClusteringError> {
+        let GpuContext {
+            device,
+            queue,
+            link,
+            compress,
+            ..
+        } = self.context;
+        let n = coords.len();
+        let storage = |label: &str, contents: &[u32], usage: wgpu::BufferUsages| {
+            device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some(label),
+                contents: bytemuck::cast_slice(contents),
+                usage: wgpu::BufferUsages::STORAGE | usage,
+            })
+        };
+        let params_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("rustpix params"),
+            contents: bytemuck::cast_slice(params),
+            usage: wgpu::BufferUsages::UNIFORM,
+        });
+        let coords_buffer = storage("rustpix coords", coords, wgpu::BufferUsages::empty());
+        let tof_buffer = storage("rustpix tof", tof, wgpu::BufferUsages::empty());
+        let cell_start_buffer = storage(
+            "rustpix cell start",
+            &state.cell_start,
+            wgpu::BufferUsages::empty(),
+        );
+        let cell_hits_buffer = storage(
+            "rustpix cell hits",
+            &state.cell_hits,
+            wgpu::BufferUsages::empty(),
+        );
+        let identity: Vec<u32> = (0..params[0]).collect();
+        let parent_buffer = storage("rustpix parent", &identity, wgpu::BufferUsages::COPY_SRC);
+        let size = (n * 4) as u64;
+        let readback = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("rustpix readback"),
+            size,
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let link_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("rustpix link"),
+            layout: &link.get_bind_group_layout(0),
+            entries: &[
+                binding(0, &params_buffer),
+                binding(1, &coords_buffer),
+                binding(2, &tof_buffer),
+                binding(3, &cell_start_buffer),
+                binding(4, &cell_hits_buffer),
+                binding(5, &parent_buffer),
+            ],
+        });
+        let compress_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("rustpix compress"),
+            layout: &compress.get_bind_group_layout(0),
+            entries: &[binding(0, &params_buffer), binding(5, &parent_buffer)],
+        });
+
+        let (groups_x, groups_y) = dispatch_size(
+            params[0],
+            device.limits().max_compute_workgroups_per_dimension,
+        );
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("rustpix clustering"),
+        });
+        for (pipeline, group) in [(link, &link_group), (compress, &compress_group)] {
+            let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                label: None,
+                timestamp_writes: None,
+            });
+            pass.set_pipeline(pipeline);
+            pass.set_bind_group(0, group, &[]);
+            pass.dispatch_workgroups(groups_x, groups_y, 1);
+        }
+        encoder.copy_buffer_to_buffer(&parent_buffer, 0, &readback, 0, size);
+        queue.submit(Some(encoder.finish()));
+
+        let slice = readback.slice(..);
+        let (tx, rx) = mpsc::channel();
+        slice.map_async(wgpu::MapMode::Read, move |result| {
+            let _ = tx.send(result);
+        });
+        device.poll(wgpu::Maintain::Wait);
+        rx.recv()
+            .map_err(|err| ClusteringError::StateError(err.to_string()))?
+            .map_err(|err| ClusteringError::StateError(err.to_string()))?;
+        let roots = bytemuck::cast_slice(&slice.get_mapped_range()).to_vec();
+        readback.unmap();
+        Ok(roots)
+    }
+}
+
+impl GpuContext {
+    async fn create() -> Result<Self, String> {
+        let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor::default());
+        let adapter = instance
+            .request_adapter(&wgpu::RequestAdapterOptions {
+                power_preference: wgpu::PowerPreference::HighPerformance,
+                compatible_surface: None,
+                force_fallback_adapter: false,
+            })
+            .await
+            .ok_or_else(|| "no GPU adapter available".to_string())?;
+        let (device, queue) = adapter
+            .request_device(
+                &wgpu::DeviceDescriptor {
+                    label: Some("rustpix clustering"),
+                    required_features: wgpu::Features::empty(),
+                    required_limits: adapter.limits(),
+                    memory_hints: wgpu::MemoryHints::Performance,
+                },
+                None,
+            )
+            .await
+            .map_err(|err| format!("GPU device request failed: {err}"))?;
+        let module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("rustpix clustering"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("gpu.wgsl").into()),
+        });
+        let pipeline = |entry_point: &str| {
+            device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                label: Some(entry_point),
+                layout: None,
+                module: &module,
+                entry_point: Some(entry_point),
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+                cache: None,
+            })
+        };
+        let link = pipeline("link");
+        let compress = pipeline("compress");
+        Ok(Self {
+            adapter_name: adapter.get_info().name,
+            device,
+            queue,
+            link,
+            compress,
+        })
+    }
+}
+
+fn binding(index: u32, buffer: &wgpu::Buffer) -> wgpu::BindGroupEntry<'_> {
+    wgpu::BindGroupEntry {
+        binding: index,
+        resource: buffer.as_entire_binding(),
+    }
+}
+
+/// Workgroup grid covering `n` hits within the per-dimension dispatch limit.
+fn dispatch_size(n: u32, max_per_dimension: u32) -> (u32, u32) {
+    let groups = n.div_ceil(WORKGROUP_SIZE);
+    let max_x = max_per_dimension.max(1);
+    if groups <= max_x {
+        (groups.max(1), 1)
+    } else {
+        (max_x, groups.div_ceil(max_x))
+    }
+}
+
+/// Neighbor cells to scan on each side so the radius is always covered.
+fn reach(radius: f64, cell_size: usize) -> u32 {
+    #[allow(clippy::cast_precision_loss)]
+    let cells = (radius / cell_size as f64).ceil();
+    float_to_u32(cells).max(1)
+}
+
+/// Index hits by grid cell (CSR), keeping batch order within each cell.
+fn build_cells(batch: &HitBatch, cell_size: usize, state: &mut GpuState) -> (u32, u32) {
+    let max_x = batch.x.iter().copied().max().unwrap_or(0);
+    let max_y = batch.y.iter().copied().max().unwrap_or(0);
+    let cells_x = usize::from(max_x) / cell_size + 1;
+    let cells_y = usize::from(max_y) / cell_size + 1;
+    let cell_of = |i: usize| {
+        usize::from(batch.y[i]) / cell_size * cells_x + usize::from(batch.x[i]) / cell_size
+    };
+
+    let cell_start = &mut state.cell_start;
+    cell_start.clear();
+    cell_start.resize(cells_x * cells_y + 1, 0);
+    for i in 0..batch.len() {
+        cell_start[cell_of(i) + 1] += 1;
+    }
+    for cell in 1..cell_start.len() {
+        cell_start[cell] += cell_start[cell - 1];
+    }
+
+    let cell_hits = &mut state.cell_hits;
+    cell_hits.clear();
+    cell_hits.resize(batch.len(), 0);
+    let mut next: Vec<u32> = cell_start[..cells_x * cells_y].to_vec();
+    for i in 0..batch.len() {
+        let slot = &mut next[cell_of(i)];
+        cell_hits[*slot as usize] = u32::try_from(i).unwrap_or(u32::MAX);
+        *slot += 1;
+    }
+    (
+        u32::try_from(cells_x).unwrap_or(u32::MAX),
+        u32::try_from(cells_y).unwrap_or(u32::MAX),
+    )
+}
+
+/// Label components by first appearance, dropping those below the minimum size.
+fn assign_labels(
+    batch: &mut HitBatch,
+    roots: &[u32],
+    state: &mut GpuState,
+    min_cluster_size: u32,
+) -> usize {
+    let n = roots.len();
+    let GpuState {
+        cluster_sizes,
+        root_to_label,
+        ..
+    } = state;
+    cluster_sizes.clear();
+    cluster_sizes.resize(n, 0);
+    for &root in roots {
+        cluster_sizes[root as usize] += 1;
+    }
+    root_to_label.clear();
+    root_to_label.resize(n, -1);
+
+    let mut next_label = 0;
+    for (i, &root) in roots.iter().enumerate() {
+        let root = root as usize;
+        if cluster_sizes[root] < min_cluster_size {
+            batch.cluster_id[i] = -1;
+        } else {
+            if root_to_label[root] < 0 {
+                root_to_label[root] = next_label;
+                next_label += 1;
+            }
+            batch.cluster_id[i] = root_to_label[root];
+        }
+    }
+    usize::try_from(next_label).unwrap_or(0)
+}
+
+fn float_to_u32(value: f64) -> u32 {
+    if value <= 0.0 {
+        return 0;
+    }
+    if value >= f64::from(u32::MAX) {
+        return u32::MAX;
+    }
+    format!("{value:.0}").parse::<u32>().unwrap_or(u32::MAX)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{GridClustering, GridConfig, GridState};
+
+    /// GPU clustering, or `None` on machines without an adapter.
+    fn gpu(config: GpuConfig) -> Option<GpuClustering> {
+        match GpuClustering::new(config) {
+            Ok(algo) => Some(algo),
+            Err(err) => {
+                eprintln!("skipping GPU test: {err}");
+                None
+            }
+        }
+    }
+
+    #[test]
+    fn test_cells_keep_batch_order() {
+        let mut batch = HitBatch::default();
+        batch.push((40, 0, 100, 5, 0, 0));
+        batch.push((1, 1, 101, 5, 0, 0));
+        batch.push((41, 2, 102, 5, 0, 0));
+        batch.push((2, 40, 103, 5, 0, 0));
+        let mut state = GpuState::default();
+        let (cells_x, cells_y) = build_cells(&batch, 32, &mut state);
+        assert_eq!((cells_x, cells_y), (2, 2));
+        assert_eq!(state.cell_start, [0, 1, 3, 4, 4]);
+        assert_eq!(state.cell_hits, [1, 0, 2, 3]);
+        assert_eq!(reach(5.0, 32), 1);
+        assert_eq!(reach(40.0, 32), 2);
+    }
+
+    #[test]
+    fn test_gpu_matches_grid() {
+        let Some(algo) = gpu(GpuConfig::default()) else {
+            return;
+        };
+        let mut batch = HitBatch::default();
+        for i in 0..5_000u32 {
+            let x = u16::try_from((i * 37) % 256).unwrap();
+            let y = u16::try_from((i * 91) % 256).unwrap();
+            batch.push((x, y, i / 4, 5, 0, 0));
+        }
+        let mut expected = batch.clone();
+        let grid_count = GridClustering::new(GridConfig::default())
+            .cluster(&mut expected, &mut GridState::default())
+            .unwrap();
+
+        let mut state = GpuState::default();
+        let count = algo.cluster(&mut batch, &mut state).unwrap();
+        assert_eq!(count, grid_count);
+        assert_eq!(batch.cluster_id, expected.cluster_id);
+        assert_eq!(state.hits_processed, 5_000);
+    }
+
+    #[test]
+    fn test_gpu_min_cluster_size() {
+        let Some(algo) = gpu(GpuConfig {
+            min_cluster_size: 2,
+            ..GpuConfig::default()
+        }) else {
+            return;
+        };
+        let mut batch = HitBatch::default();
+        batch.push((10, 10, 100, 5, 0, 0));
+        batch.push((11, 11, 102, 5, 0, 0));
+        batch.push((50, 50, 100, 5, 0, 0));
+        batch.push((100, 100, 10_000, 5, 0, 0));
+        let count = algo.cluster(&mut batch, &mut GpuState::default()).unwrap();
+        assert_eq!(count, 1);
+        assert_eq!(batch.cluster_id, [0, 0, -1, -1]);
+    }
+}
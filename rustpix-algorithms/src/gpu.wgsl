@@ -0,0 +1,121 @@
+// Grid neighbor search and lock-free union-find for GpuClustering.
+//
+// Hits are indexed by grid cell (CSR: `cell_start` / `cell_hits`, indices
+// ascending within a cell). `link` unions each hit with the later hits of
+// nearby cells inside the time window, always hooking the larger root onto
+// the smaller one with `atomicMin`, so every component ends up rooted at its
+// lowest index. `compress` then points every hit straight at its root.
+
+struct Params {
+    n: u32,
+    cells_x: u32,
+    cells_y: u32,
+    cell_size: u32,
+    reach: u32,
+    window_tof: u32,
+    radius_sq: u32,
+    _pad: u32,
+}
+
+@group(0) @binding(0) var<uniform> params: Params;
+@group(0) @binding(1) var<storage, read> coords: array<u32>;
+@group(0) @binding(2) var<storage, read> tof: array<u32>;
+@group(0) @binding(3) var<storage, read> cell_start: array<u32>;
+@group(0) @binding(4) var<storage, read> cell_hits: array<u32>;
+@group(0) @binding(5) var<storage, read_write> parent: array<atomic<u32>>;
+
+const WORKGROUP_SIZE: u32 = 256u;
+
+fn hit_index(id: vec3<u32>, groups: vec3<u32>) -> u32 {
+    return id.y * groups.x * WORKGROUP_SIZE + id.x;
+}
+
+fn find_root(i: u32) -> u32 {
+    var root = i;
+    var next = atomicLoad(&parent[i]);
+    while next != root {
+        root = next;
+        next = atomicLoad(&parent[root]);
+    }
+    return root;
+}
+
+fn unite(a: u32, b: u32) {
+    var root_a = find_root(a);
+    var root_b = find_root(b);
+    loop {
+        if root_a == root_b {
+            return;
+        }
+        let high = max(root_a, root_b);
+        let low = min(root_a, root_b);
+        // Hook `high` onto `low`. If another thread hooked `high` first, its
+        // previous parent still has to be joined with `low`.
+        let previous = atomicMin(&parent[high], low);
+        if previous == high {
+            return;
+        }
+        root_a = find_root(previous);
+        root_b = find_root(low);
+    }
+}
+
+@compute @workgroup_size(256)
+fn link(@builtin(global_invocation_id) id: vec3<u32>, @builtin(num_workgroups) groups: vec3<u32>) {
+    let i = hit_index(id, groups);
+    if i >= params.n {
+        return;
+    }
+    let xi = i32(coords[i] & 0xffffu);
+    let yi = i32(coords[i] >> 16u);
+    let ti = tof[i];
+    let cell_x = xi / i32(params.cell_size);
+    let cell_y = yi / i32(params.cell_size);
+    let reach = i32(params.reach);
+
+    for (var dy = -reach; dy <= reach; dy++) {
+        let cy = cell_y + dy;
+        if cy < 0 || cy >= i32(params.cells_y) {
+            continue;
+        }
+        for (var dx = -reach; dx <= reach; dx++) {
+            let cx = cell_x + dx;
+            if cx < 0 || cx >= i32(params.cells_x) {
+                continue;
+            }
+            let cell = u32(cy) * params.cells_x + u32(cx);
+            // First entry of the cell with an index above `i`.
+            var lo = cell_start[cell];
+            var hi = cell_start[cell + 1u];
+            while lo < hi {
+                let mid = (lo + hi) / 2u;
+                if cell_hits[mid] <= i {
+                    lo = mid + 1u;
+                } else {
+                    hi = mid;
+                }
+            }
+            let end = cell_start[cell + 1u];
+            for (var k = lo; k < end; k++) {
+                let j = cell_hits[k];
+                if tof[j] - ti > params.window_tof {
+                    break;
+                }
+                let ddx = xi - i32(coords[j] & 0xffffu);
+                let ddy = yi - i32(coords[j] >> 16u);
+                if u32(ddx * ddx + ddy * ddy) <= params.radius_sq {
+                    unite(i, j);
+                }
+            }
+        }
+    }
+}
+
+@compute @workgroup_size(256)
+fn compress(@builtin(global_invocation_id) id: vec3<u32>, @builtin(num_workgroups) groups: vec3<u32>) {
+    let i = hit_index(id, groups);
+    if i >= params.n {
+        return;
+    }
+    atomicStore(&parent[i], find_root(i));
+}
@@ -244,6 +244,16 @@ impl GridClustering {
                     if let Some(cell) = grid.get_cell_slice(px, py) {
                         let start = cell.partition_point(|&idx| idx <= i);
 
+                        #[cfg(feature = "simd")]
+                        crate::simd::for_each_grid_neighbor(
+                            batch,
+                            i,
+                            &cell[start..],
+                            ctx.window_tof,
+                            ctx.radius_sq,
+                            |j| union_sets(parent, rank, i, j),
+                        );
+                        #[cfg(not(feature = "simd"))]
                         for &j in &cell[start..] {
                             let dt = batch.tof[j].wrapping_sub(batch.tof[i]);
                             if dt > ctx.window_tof {
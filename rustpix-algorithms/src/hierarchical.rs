@@ -0,0 +1,411 @@
+//! Agglomerative clustering with a time-then-space linkage.
+//!
+//! Sparse ionization tracks leave gaps wider than the radius the fixed
+//! epsilon algorithms link hits over, so one track comes out as several
+//! clusters. Hierarchical clustering starts from the Grid clusters (hits
+//! within `radius` and `temporal_window_ns`) and then merges clusters:
+//! two clusters are linked when their TOF ranges are at most
+//! `merge_window_ns` apart and, only then, their bounding boxes are at most
+//! `merge_distance` pixels apart. A merged cluster takes the union of both
+//! ranges and boxes, so merging repeats until no pair is linked.
+
+use rustpix_core::clustering::ClusteringError;
+use rustpix_core::soa::HitBatch;
+
+use crate::{GridClustering, GridConfig, GridState};
+
+/// Configuration for hierarchical clustering.
+#[derive(Clone, Debug)]
+pub struct HierarchicalConfig {
+    /// Spatial radius for linking hits into seed clusters (pixels).
+    pub radius: f64,
+    /// Temporal window for linking hits into seed clusters (nanoseconds).
+    pub temporal_window_ns: f64,
+    /// Largest gap between the bounding boxes of merged clusters (pixels).
+    pub merge_distance: f64,
+    /// Largest gap between the TOF ranges of merged clusters (nanoseconds).
+    pub merge_window_ns: f64,
+    /// Minimum cluster size to keep after merging.
+    pub min_cluster_size: u16,
+    /// Clusters are not merged past this size (None = unlimited).
+    pub max_cluster_size: Option<usize>,
+    /// Grid cell size of the seed clustering (pixels).
+    pub cell_size: usize,
+}
+
+impl Default for HierarchicalConfig {
+    fn default() -> Self {
+        Self {
+            radius: 5.0,
+            temporal_window_ns: 75.0,
+            merge_distance: 10.0,
+            merge_window_ns: 150.0,
+            min_cluster_size: 1,
+            max_cluster_size: None,
+            cell_size: 32,
+        }
+    }
+}
+
+/// Reusable hierarchical clustering state.
+#[derive(Default)]
+pub struct HierarchicalState {
+    /// Number of hits processed.
+    pub hits_processed: usize,
+    /// Number of seed clusters before merging.
+    pub seed_clusters: usize,
+    /// Number of merges performed.
+    pub merges: usize,
+    /// Number of clusters found.
+    pub clusters_found: usize,
+    seeds: GridState,
+    extents: Vec<Extent>,
+    order: Vec<usize>,
+    parent: Vec<usize>,
+    labels: Vec<i32>,
+}
+
+/// TOF range, bounding box and size of a cluster.
+#[derive(Clone, Copy)]
+struct Extent {
+    tof_min: u32,
+    tof_max: u32,
+    x_min: u16,
+    x_max: u16,
+    y_min: u16,
+    y_max: u16,
+    size: usize,
+}
+
+impl Extent {
+    fn new(x: u16, y: u16, tof: u32) -> Self {
+        Self {
+            tof_min: tof,
+            tof_max: tof,
+            x_min: x,
+            x_max: x,
+            y_min: y,
+            y_max: y,
+            size: 0,
+        }
+    }
+
+    fn merge(&mut self, other: &Self) {
+        self.tof_min = self.tof_min.min(other.tof_min);
+        self.tof_max = self.tof_max.max(other.tof_max);
+        self.x_min = self.x_min.min(other.x_min);
+        self.x_max = self.x_max.max(other.x_max);
+        self.y_min = self.y_min.min(other.y_min);
+        self.y_max = self.y_max.max(other.y_max);
+        self.size += other.size;
+    }
+
+    /// Gap between the TOF ranges, 0 if they overlap.
+    fn time_gap(&self, other: &Self) -> u32 {
+        self.tof_min
+            .max(other.tof_min)
+            .saturating_sub(self.tof_max.min(other.tof_max))
+    }
+
+    /// Squared gap between the bounding boxes, 0 if they overlap.
+    fn box_gap_sq(&self, other: &Self) -> f64 {
+        let dx = f64::from(
+            self.x_min
+                .max(other.x_min)
+                .saturating_sub(self.x_max.min(other.x_max)),
+        );
+        let dy = f64::from(
+            self.y_min
+                .max(other.y_min)
+                .saturating_sub(self.y_max.min(other.y_max)),
+        );
+        dx * dx + dy * dy
+    }
+}
+
+/// Hierarchical (agglomerative) clustering; see the module docs.
+pub struct HierarchicalClustering {
+    config: HierarchicalConfig,
+}
+
+impl HierarchicalClustering {
+    /// Create with custom configuration.
+    #[must_use]
+    pub fn new(config: HierarchicalConfig) -> Self {
+        Self { config }
+    }
+
+    /// Cluster a batch of hits in-place.
+    ///
+    /// Updates `cluster_id` field in `batch`. Labels are numbered in order
+    /// of the first hit of each cluster.
+    ///
+    /// # Errors
+    /// Returns an error if the seed clustering fails.
+    pub fn cluster(
+        &self,
+        batch: &mut HitBatch,
+        state: &mut HierarchicalState,
+    ) -> Result<usize, ClusteringError> {
+        state.hits_processed = 0;
+        state.seed_clusters = 0;
+        state.merges = 0;
+        state.clusters_found = 0;
+        if batch.is_empty() {
+            return Ok(0);
+        }
+
+        let seeding = GridClustering::new(GridConfig {
+            radius: self.config.radius,
+            temporal_window_ns: self.config.temporal_window_ns,
+            min_cluster_size: 1,
+            max_cluster_size: None,
+            cell_size: self.config.cell_size,
+        });
+        let seeds = seeding.cluster(batch, &mut state.seeds)?;
+
+        Self::measure_seeds(batch, seeds, &mut state.extents);
+        state.order.clear();
+        state.order.extend(0..seeds);
+        let extents = &state.extents;
+        state.order.sort_by_key(|&seed| extents[seed].tof_min);
+        state.parent.clear();
+        state.parent.extend(0..seeds);
+
+        let merges = self.merge_seeds(state);
+        let clusters = self.relabel(batch, state);
+
+        state.hits_processed = batch.len();
+        state.seed_clusters = seeds;
+        state.merges = merges;
+        state.clusters_found = clusters;
+        Ok(clusters)
+    }
+
+    /// Extents of the `seeds` seed clusters labelled in `batch`.
+    fn measure_seeds(batch: &HitBatch, seeds: usize, extents: &mut Vec<Extent>) {
+        extents.clear();
+        extents.resize(seeds, Extent::new(u16::MAX, u16::MAX, u32::MAX));
+        for i in 0..batch.len() {
+            let Ok(seed) = usize::try_from(batch.cluster_id[i]) else {
+                continue;
+            };
+            let hit = Extent {
+                size: 1,
+                ..Extent::new(batch.x[i], batch.y[i], batch.tof[i])
+            };
+            let extent = &mut extents[seed];
+            if extent.size == 0 {
+                *extent = hit;
+            } else {
+                extent.merge(&hit);
+            }
+        }
+    }
+
+    /// Merge linked seeds until no pair is linked; returns the merge count.
+    ///
+    /// Seeds are visited by start time, so the candidates of a cluster end
+    /// at the first seed starting more than the merge window after it.
+    fn merge_seeds(&self, state: &mut HierarchicalState) -> usize {
+        let HierarchicalState {
+            extents,
+            order,
+            parent,
+            ..
+        } = state;
+        let window = float_to_u32((self.config.merge_window_ns / 25.0).ceil());
+        let distance_sq = self.config.merge_distance * self.config.merge_distance;
+        let max_size = self.config.max_cluster_size.unwrap_or(usize::MAX);
+
+        let mut merges = 0;
+        loop {
+            let mut changed = false;
+            for a in 0..order.len() {
+                let root_a = find(parent, order[a]);
+                for &seed_b in &order[a + 1..] {
+                    if extents[seed_b].tof_min > extents[root_a].tof_max.saturating_add(window) {
+                        break;
+                    }
+                    let root_b = find(parent, seed_b);
+                    if root_a == root_b {
+                        continue;
+                    }
+                    let (cluster_a, cluster_b) = (extents[root_a], extents[root_b]);
+                    let linked = cluster_a.time_gap(&cluster_b) <= window
+                        && cluster_a.box_gap_sq(&cluster_b) <= distance_sq
+                        && cluster_a.size + cluster_b.size <= max_size;
+                    if linked {
+                        parent[root_b] = root_a;
+                        extents[root_a].merge(&cluster_b);
+                        merges += 1;
+                        changed = true;
+                    }
+                }
+            }
+            if !changed {
+                return merges;
+            }
+        }
+    }
+
+    /// Replace seed labels with merged cluster labels, dropping clusters
+    /// below the minimum size; returns the cluster count.
+    fn relabel(&self, batch: &mut HitBatch, state: &mut HierarchicalState) -> usize {
+        let HierarchicalState {
+            extents,
+            parent,
+            labels,
+            ..
+        } = state;
+        let min_size = usize::from(self.config.min_cluster_size);
+        labels.clear();
+        labels.resize(parent.len(), -1);
+        let mut next_label = 0;
+        for id in &mut batch.cluster_id {
+            let Ok(seed) = usize::try_from(*id) else {
+                continue;
+            };
+            let root = find(parent, seed);
+            if extents[root].size < min_size {
+                *id = -1;
+                continue;
+            }
+            if labels[root] < 0 {
+                labels[root] = next_label;
+                next_label += 1;
+            }
+            *id = labels[root];
+        }
+        usize::try_from(next_label).unwrap_or(0)
+    }
+}
+
+impl Default for HierarchicalClustering {
+    fn default() -> Self {
+        Self::new(HierarchicalConfig::default())
+    }
+}
+
+fn find(parent: &mut [usize], i: usize) -> usize {
+    let mut root = i;
+    while root != parent[root] {
+        root = parent[root];
+    }
+    let mut curr = i;
+    while curr != root {
+        let next = parent[curr];
+        parent[curr] = root;
+        curr = next;
+    }
+    root
+}
+
+fn float_to_u32(value: f64) -> u32 {
+    if value <= 0.0 {
+        return 0;
+    }
+    if value >= f64::from(u32::MAX) {
+        return u32::MAX;
+    }
+    format!("{value:.0}").parse::<u32>().unwrap_or(u32::MAX)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A track along x with a hit every `step` pixels and 1 tick apart.
+    fn track(batch: &mut HitBatch, x0: u16, y: u16, tof0: u32, hits: u16, step: u16) {
+        for k in 0..hits {
+            batch.push((x0 + k * step, y, tof0 + u32::from(k), 10, 0, 0));
+        }
+    }
+
+    #[test]
+    fn test_merges_sparse_track_split_by_radius() {
+        let mut batch = HitBatch::default();
+        // Two dense segments of one track, 8 pixels apart.
+        track(&mut batch, 10, 50, 100, 4, 1);
+        track(&mut batch, 21, 50, 104, 4, 1);
+        // An unrelated event far away at the same time.
+        track(&mut batch, 200, 200, 100, 3, 1);
+        batch.sort_by_tof();
+
+        let mut grid = batch.clone();
+        let seeds = GridClustering::default()
+            .cluster(&mut grid, &mut GridState::default())
+            .unwrap();
+        assert_eq!(seeds, 3);
+
+        let algo = HierarchicalClustering::default();
+        let mut state = HierarchicalState::default();
+        let clusters = algo.cluster(&mut batch, &mut state).unwrap();
+        assert_eq!(clusters, 2);
+        assert_eq!(state.seed_clusters, 3);
+        assert_eq!(state.merges, 1);
+        let track_label = batch.cluster_id[batch.x.iter().position(|&x| x == 10).unwrap()];
+        for (&x, &id) in batch.x.iter().zip(&batch.cluster_id) {
+            assert_eq!(id == track_label, x < 100, "x = {x}");
+        }
+    }
+
+    #[test]
+    fn test_time_gap_is_checked_before_space() {
+        let mut batch = HitBatch::default();
+        track(&mut batch, 10, 50, 100, 3, 1);
+        // Next to the first segment in space, but 20 ticks (500 ns) later.
+        track(&mut batch, 20, 50, 120, 3, 1);
+        batch.sort_by_tof();
+
+        let algo = HierarchicalClustering::default();
+        let mut state = HierarchicalState::default();
+        assert_eq!(algo.cluster(&mut batch, &mut state).unwrap(), 2);
+
+        let wide = HierarchicalClustering::new(HierarchicalConfig {
+            merge_window_ns: 500.0,
+            ..HierarchicalConfig::default()
+        });
+        assert_eq!(wide.cluster(&mut batch, &mut state).unwrap(), 1);
+    }
+
+    #[test]
+    fn test_merges_chain_and_apply_size_limits() {
+        let mut batch = HitBatch::default();
+        // Four segments, each within the merge distance of the next only.
+        for segment in 0..4 {
+            track(
+                &mut batch,
+                10 + segment * 9,
+                50,
+                100 + u32::from(segment),
+                2,
+                1,
+            );
+        }
+        batch.sort_by_tof();
+
+        let mut state = HierarchicalState::default();
+        let algo = HierarchicalClustering::new(HierarchicalConfig {
+            merge_distance: 8.0,
+            ..HierarchicalConfig::default()
+        });
+        assert_eq!(algo.cluster(&mut batch, &mut state).unwrap(), 1);
+        assert_eq!(state.merges, 3);
+
+        let capped = HierarchicalClustering::new(HierarchicalConfig {
+            merge_distance: 8.0,
+            max_cluster_size: Some(4),
+            ..HierarchicalConfig::default()
+        });
+        assert_eq!(capped.cluster(&mut batch, &mut state).unwrap(), 2);
+
+        let min_size = HierarchicalClustering::new(HierarchicalConfig {
+            merge_distance: 1.0,
+            min_cluster_size: 3,
+            ..HierarchicalConfig::default()
+        });
+        assert_eq!(min_size.cluster(&mut batch, &mut state).unwrap(), 0);
+        assert!(batch.cluster_id.iter().all(|&id| id == -1));
+    }
+}
@@ -0,0 +1,247 @@
+//! Hot pixels that flicker only during part of the TOF range.
+//!
+//! A pixel can be quiet over a whole run and still light up in a few TOF
+//! bins, e.g. from a chip that misbehaves at one phase of the pulse, which a
+//! mask made from the TOF-summed image misses. Here every TOF bin is judged
+//! on its own: the counts of a sliding window of bins around it are summed
+//! per pixel and pixels above `mean + sigma * spread` of the live pixels are
+//! flagged in that bin. The spread is the standard deviation of the window
+//! sums but at least the Poisson noise of their mean, so sparse windows of
+//! single counts do not flag every pixel with two.
+
+/// Hot pixels of each TOF bin of a `[tof][y][x]` histogram.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TofHotPixels {
+    width: usize,
+    height: usize,
+    window: usize,
+    sigma: f64,
+    /// Hot pixel indices (`y * width + x`) of each TOF bin, ascending.
+    bins: Vec<Vec<u32>>,
+}
+
+impl TofHotPixels {
+    /// Width of the histogram the pixels were found in.
+    #[must_use]
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Height of the histogram the pixels were found in.
+    #[must_use]
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Number of TOF bins.
+    #[must_use]
+    pub fn tof_bins(&self) -> usize {
+        self.bins.len()
+    }
+
+    /// Number of TOF bins summed around each bin.
+    #[must_use]
+    pub fn window(&self) -> usize {
+        self.window
+    }
+
+    /// Threshold in multiples of the spread above the mean.
+    #[must_use]
+    pub fn sigma(&self) -> f64 {
+        self.sigma
+    }
+
+    /// Hot pixel indices (`y * width + x`) of TOF bin `bin`, ascending.
+    #[must_use]
+    pub fn hot_in_bin(&self, bin: usize) -> &[u32] {
+        self.bins.get(bin).map_or(&[], Vec::as_slice)
+    }
+
+    /// Whether pixel `index` (`y * width + x`) is hot in TOF bin `bin`.
+    #[must_use]
+    pub fn is_hot(&self, bin: usize, index: usize) -> bool {
+        u32::try_from(index).is_ok_and(|index| self.hot_in_bin(bin).binary_search(&index).is_ok())
+    }
+
+    /// Number of flagged (TOF bin, pixel) pairs.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.bins.iter().map(Vec::len).sum()
+    }
+
+    /// Whether no pixel is hot in any bin.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.bins.iter().all(Vec::is_empty)
+    }
+
+    /// Number of distinct pixels that are hot in at least one bin.
+    #[must_use]
+    pub fn pixel_count(&self) -> usize {
+        let mut pixels: Vec<u32> = self.bins.iter().flatten().copied().collect();
+        pixels.sort_unstable();
+        pixels.dedup();
+        pixels.len()
+    }
+
+    /// Zero the flagged cells of `counts`, a `[tof][y][x]` histogram of the
+    /// same shape; returns the counts removed.
+    ///
+    /// # Panics
+    /// Panics if `counts` is not `tof_bins * width * height` long.
+    pub fn apply<T: Copy + Default + Into<u64>>(&self, counts: &mut [T]) -> u64 {
+        let pixels = self.width * self.height;
+        assert_eq!(
+            counts.len(),
+            self.bins.len() * pixels,
+            "histogram size mismatch"
+        );
+        let mut removed = 0u64;
+        for (page, hot) in counts.chunks_mut(pixels.max(1)).zip(&self.bins) {
+            for &index in hot {
+                let cell = &mut page[index as usize];
+                removed = removed.saturating_add((*cell).into());
+                *cell = T::default();
+            }
+        }
+        removed
+    }
+}
+
+/// Hot pixels of every TOF bin of `counts`, a `[tof][y][x]` histogram of
+/// `tof_bins` pages of `width × height`.
+///
+/// Each bin is judged on the sum of the `window` bins around it (shifted
+/// inwards at the ends; 1 judges bins alone) and a pixel is hot when that sum exceeds
+/// `mean + sigma * spread` of the pixels with counts in the window.
+///
+/// # Panics
+/// Panics if `counts` is not `tof_bins * width * height` long.
+#[must_use]
+pub fn find_tof_hot_pixels<T: Copy + Into<u64>>(
+    counts: &[T],
+    width: usize,
+    height: usize,
+    tof_bins: usize,
+    window: usize,
+    sigma: f64,
+) -> TofHotPixels {
+    let pixels = width * height;
+    assert_eq!(counts.len(), tof_bins * pixels, "histogram size mismatch");
+    let window = window.clamp(1, tof_bins.max(1));
+    let page = |bin: usize| &counts[bin * pixels..(bin + 1) * pixels];
+
+    // Running per-pixel sums over bins `first..end`.
+    let mut sums = vec![0u64; pixels];
+    let (mut first, mut end) = (0, 0);
+    let mut bins = Vec::with_capacity(tof_bins);
+    for bin in 0..tof_bins {
+        let start = bin.saturating_sub((window - 1) / 2).min(tof_bins - window);
+        let stop = start + window;
+        while end < stop {
+            for (sum, &count) in sums.iter_mut().zip(page(end)) {
+                *sum += count.into();
+            }
+            end += 1;
+        }
+        while first < start {
+            for (sum, &count) in sums.iter_mut().zip(page(first)) {
+                *sum -= count.into();
+            }
+            first += 1;
+        }
+        bins.push(hot_pixels(&sums, sigma));
+    }
+    TofHotPixels {
+        width,
+        height,
+        window,
+        sigma,
+        bins,
+    }
+}
+
+/// Indices of the pixels of `sums` above `mean + sigma * spread` of the
+/// non-zero ones.
+#[allow(clippy::cast_precision_loss)]
+fn hot_pixels(sums: &[u64], sigma: f64) -> Vec<u32> {
+    let (mut n, mut sum, mut sum_sq) = (0.0f64, 0.0f64, 0.0f64);
+    for &value in sums.iter().filter(|&&value| value > 0) {
+        let value = value as f64;
+        n += 1.0;
+        sum += value;
+        sum_sq += value * value;
+    }
+    if n == 0.0 {
+        return Vec::new();
+    }
+    let mean = sum / n;
+    let std_dev = (sum_sq / n - mean * mean).max(0.0).sqrt();
+    let threshold = mean + sigma * std_dev.max(mean.sqrt());
+    sums.iter()
+        .enumerate()
+        .filter(|&(_, &value)| value as f64 > threshold)
+        .filter_map(|(index, _)| u32::try_from(index).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rustpix_core::SplitMix64;
+
+    /// 20×20 pages over 10 TOF bins of 100 ± 32 counts, with pixel 9 up by
+    /// 250 in bin 3 only.
+    fn flicker() -> Vec<u64> {
+        // Seeded per cell, for noise that is independent from bin to bin.
+        let noise = |cell: u64| SplitMix64::new(cell).next_u64() >> 58;
+        let mut counts: Vec<u64> = (0..10 * 400).map(|cell| 68 + noise(cell)).collect();
+        counts[3 * 400 + 9] += 250;
+        counts
+    }
+
+    #[test]
+    fn test_flags_only_the_flickering_bins() {
+        let counts = flicker();
+        let hot = find_tof_hot_pixels(&counts, 20, 20, 10, 1, 5.0);
+        assert_eq!(hot.tof_bins(), 10);
+        assert_eq!(hot.len(), 1);
+        assert_eq!(hot.pixel_count(), 1);
+        assert!(hot.is_hot(3, 9));
+        assert!(!hot.is_hot(4, 9) && !hot.is_hot(3, 10));
+
+        // Summed over the whole run the flicker drowns in the noise.
+        let whole = find_tof_hot_pixels(&counts, 20, 20, 10, 10, 5.0);
+        assert!(whole.is_empty());
+
+        // A window of three sees it in the bins whose window holds bin 3.
+        let windowed = find_tof_hot_pixels(&counts, 20, 20, 10, 3, 5.0);
+        assert_eq!(windowed.window(), 3);
+        assert_eq!(windowed.len(), 3);
+        assert!((2..=4).all(|bin| windowed.is_hot(bin, 9)));
+    }
+
+    #[test]
+    fn test_apply_zeroes_flagged_cells() {
+        let counts = flicker();
+        let hot = find_tof_hot_pixels(&counts, 20, 20, 10, 1, 5.0);
+        let mut narrow: Vec<u32> = counts
+            .iter()
+            .map(|&count| u32::try_from(count).unwrap())
+            .collect();
+        assert_eq!(hot.apply(&mut narrow), counts[3 * 400 + 9]);
+        assert_eq!(narrow[3 * 400 + 9], 0);
+        assert_eq!(u64::from(narrow[2 * 400 + 9]), counts[2 * 400 + 9]);
+    }
+
+    #[test]
+    fn test_sparse_and_empty_histograms() {
+        let mut counts = vec![0u64; 4 * 16];
+        counts[5] = 1;
+        counts[16 + 6] = 1;
+        counts[16 + 7] = 2;
+        assert!(find_tof_hot_pixels(&counts, 4, 4, 4, 1, 3.0).is_empty());
+        assert!(find_tof_hot_pixels::<u64>(&[], 0, 0, 0, 1, 3.0).is_empty());
+    }
+}
@@ -2,26 +2,82 @@
 //!
 //! This crate provides various clustering algorithms:
 //! - **ABS** (Age-Based Spatial) - O(n) average, bucket-based primary
+//! - **Connected components** - 4- or 8-connected pixel labeling in TOF
+//!   order, the fastest choice for compact thermal-neutron blobs
 //! - **DBSCAN** - Density-based with noise handling
 //! - **Graph** - Union-Find connected components
 //! - **Grid** - Detector geometry optimized
+//! - **Hierarchical** - Grid clusters merged by a time-then-space linkage,
+//!   for sparse tracks the fixed-radius algorithms split
+//! - **GPU** - Grid neighbor search and union-find in a wgpu compute shader
+//!   (`gpu` feature)
+//! - **Streaming** - Online ABS-style clustering that emits clusters as they
+//!   age out of the temporal window, with bounded memory
+//!
+//! The `simd` feature checks ABS and Grid neighbor candidates in `wide`
+//! vector lanes instead of one at a time, with identical labels.
+//!
+//! Any of them except GPU can cluster a large batch in parallel time
+//! sections (`AlgorithmParams::section_hits`) with deterministic labels.
+//!
+//! It also locates the beam spot (direct beam center) for alignment checks
+//! and integrates radial and azimuthal profiles around it. FFT power spectra
+//! and autocorrelations of counts images quantify periodic artifacts, and
+//! phase correlation finds the shift between two images of the same scene.
+//! Hot pixels that flicker only in some TOF bins are found bin by bin.
+//! Chi-square and Kolmogorov-Smirnov tests check whether two TOF spectra are
+//! consistent.
+//!
+//! [`synthetic`] generates seeded pulses of hits (Poisson event arrivals,
+//! a cluster size distribution and a noise fraction) for the criterion
+//! benchmarks in `benches/` and for benchmarking without data files.
 //!
 #![warn(missing_docs)]
 
 mod abs;
+mod beam_spot;
+mod connected;
 mod dbscan;
+mod fourier;
+#[cfg(feature = "gpu")]
+mod gpu;
 mod grid;
+mod hierarchical;
+mod hot_pixels;
 mod processing;
+mod radial;
+mod registration;
+mod sections;
+#[cfg(feature = "simd")]
+mod simd;
 pub mod spatial;
+mod spectrum;
+mod streaming;
+pub mod synthetic;
 
 pub use abs::{AbsClustering, AbsConfig, AbsState};
+pub use beam_spot::{find_beam_spot, find_beam_spot_in_hits, BeamSpot, BeamSpotConfig};
+pub use connected::{
+    ConnectedComponentClustering, ConnectedComponentConfig, ConnectedComponentState, Connectivity,
+};
 pub use dbscan::{DbscanClustering, DbscanConfig, DbscanState};
+pub use fourier::{autocorrelation, periodic_peaks, power_spectrum, FourierMap, PeriodicPeak};
+#[cfg(feature = "gpu")]
+pub use gpu::{GpuClustering, GpuConfig, GpuState};
 pub use grid::{GridClustering, GridConfig, GridState};
+pub use hierarchical::{HierarchicalClustering, HierarchicalConfig, HierarchicalState};
+pub use hot_pixels::{find_tof_hot_pixels, TofHotPixels};
 pub use processing::{
-    cluster_and_extract, cluster_and_extract_batch, cluster_and_extract_stream,
-    cluster_and_extract_stream_iter, AlgorithmParams, ClusterAndExtractStream, ClusteringAlgorithm,
+    cluster_and_extract, cluster_and_extract_batch, cluster_and_extract_records,
+    cluster_and_extract_stream, cluster_and_extract_stream_iter, cluster_batch, extract_batch,
+    AlgorithmParams, ClusterAndExtractStream, ClusteringAlgorithm,
 };
+pub use radial::{azimuthal_profile, radial_profile, IntegratedProfile};
+pub use registration::{register_translation, shift_image, ImageShift};
+pub use sections::is_unsectionable;
 pub use spatial::SpatialGrid;
+pub use spectrum::{compare_spectra, SpectrumComparison};
+pub use streaming::{ClusteredHits, StreamingClustering, StreamingConfig, StreamingState};
 
 // Re-export core clustering traits
 pub use rustpix_core::clustering::{ClusteringConfig, ClusteringStatistics};
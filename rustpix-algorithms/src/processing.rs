@@ -1,15 +1,28 @@
 //! High-level processing helpers that combine clustering and extraction.
 
+use crate::sections::cluster_sections;
 use crate::{AbsClustering, AbsConfig, AbsState, DbscanClustering, DbscanConfig, DbscanState};
+use crate::{
+    ConnectedComponentClustering, ConnectedComponentConfig, ConnectedComponentState, Connectivity,
+};
+#[cfg(feature = "gpu")]
+use crate::{GpuClustering, GpuConfig, GpuState};
 use crate::{GridClustering, GridConfig, GridState};
+use crate::{HierarchicalClustering, HierarchicalConfig, HierarchicalState};
+use crate::{StreamingClustering, StreamingConfig, StreamingState};
 use rustpix_core::clustering::ClusteringConfig;
 use rustpix_core::error::Result;
 use rustpix_core::extraction::{ExtractionConfig, NeutronExtraction, SimpleCentroidExtraction};
 use rustpix_core::neutron::{Neutron, NeutronBatch};
-use rustpix_core::soa::HitBatch;
+use rustpix_core::soa::{HitBatch, HitRecord};
 
 /// Supported clustering algorithms.
 #[derive(Clone, Copy, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
 pub enum ClusteringAlgorithm {
     /// Age-Based Spatial clustering.
     Abs,
@@ -17,6 +30,15 @@ pub enum ClusteringAlgorithm {
     Dbscan,
     /// Grid-based clustering.
     Grid,
+    /// Grid-based clustering on the GPU (wgpu compute shader).
+    #[cfg(feature = "gpu")]
+    Gpu,
+    /// Streaming clustering with bounded memory.
+    Streaming,
+    /// Grid clusters merged agglomeratively by a time-then-space linkage.
+    Hierarchical,
+    /// Connected-component labeling of touching pixels.
+    ConnectedComponents,
 }
 
 /// Algorithm-specific tuning parameters.
@@ -28,6 +50,17 @@ pub struct AlgorithmParams {
     pub dbscan_min_points: usize,
     /// Grid cell size (pixels).
     pub grid_cell_size: usize,
+    /// Hierarchical: largest gap between merged clusters' bounding boxes
+    /// (pixels).
+    pub hierarchical_merge_distance: f64,
+    /// Hierarchical: largest gap between merged clusters' TOF ranges
+    /// (nanoseconds).
+    pub hierarchical_merge_window_ns: f64,
+    /// Connected components: pixel neighbourhood linking hits.
+    pub connectivity: Connectivity,
+    /// Minimum hits per time section when clustering a batch in parallel
+    /// sections; 0 clusters the whole batch on one thread.
+    pub section_hits: usize,
 }
 
 impl Default for AlgorithmParams {
@@ -36,6 +69,10 @@ impl Default for AlgorithmParams {
             abs_scan_interval: 100,
             dbscan_min_points: 2,
             grid_cell_size: 32,
+            hierarchical_merge_distance: 10.0,
+            hierarchical_merge_window_ns: 150.0,
+            connectivity: Connectivity::Eight,
+            section_hits: 0,
         }
     }
 }
@@ -91,17 +128,41 @@ where
     }
 }
 
-/// Cluster hits in-place, then extract neutrons using the configured algorithm.
+/// Cluster hits in-place, assigning `cluster_id` labels, and return the cluster count.
+///
+/// Split from extraction so callers can cache labels and re-run only the
+/// extraction stage when extraction parameters change.
+///
+/// With [`AlgorithmParams::section_hits`] set, a TOF-sorted batch larger
+/// than that is cut into time sections that no cluster spans, which are
+/// clustered in parallel; the labels do not depend on the thread count.
+/// GPU clustering always runs on the whole batch.
 ///
 /// # Errors
-/// Returns an error if clustering or extraction fails.
-pub fn cluster_and_extract(
+/// Returns an error if clustering fails.
+pub fn cluster_batch(
     batch: &mut HitBatch,
     algorithm: ClusteringAlgorithm,
     clustering: &ClusteringConfig,
-    extraction: &ExtractionConfig,
     params: &AlgorithmParams,
-) -> Result<Vec<Neutron>> {
+) -> Result<usize> {
+    #[cfg(feature = "gpu")]
+    if matches!(algorithm, ClusteringAlgorithm::Gpu) {
+        return cluster_whole(batch, algorithm, clustering, params);
+    }
+    if params.section_hits > 0 && batch.len() > params.section_hits {
+        return cluster_sections(batch, algorithm, clustering, params);
+    }
+    cluster_whole(batch, algorithm, clustering, params)
+}
+
+/// Cluster the whole batch at once with `algorithm`.
+pub(crate) fn cluster_whole(
+    batch: &mut HitBatch,
+    algorithm: ClusteringAlgorithm,
+    clustering: &ClusteringConfig,
+    params: &AlgorithmParams,
+) -> Result<usize> {
     let num_clusters = match algorithm {
         ClusteringAlgorithm::Abs => {
             let algo = AbsClustering::new(AbsConfig {
@@ -134,60 +195,62 @@ pub fn cluster_and_extract(
             let mut state = GridState::default();
             algo.cluster(batch, &mut state)?
         }
-    };
-
-    let mut extractor = SimpleCentroidExtraction::new();
-    extractor.configure(extraction.clone());
-    extractor
-        .extract_soa(batch, num_clusters)
-        .map_err(Into::into)
-}
-
-/// Cluster hits in-place, then extract neutrons into a `NeutronBatch`.
-///
-/// # Errors
-/// Returns an error if clustering or extraction fails.
-pub fn cluster_and_extract_batch(
-    batch: &mut HitBatch,
-    algorithm: ClusteringAlgorithm,
-    clustering: &ClusteringConfig,
-    extraction: &ExtractionConfig,
-    params: &AlgorithmParams,
-) -> Result<NeutronBatch> {
-    let num_clusters = match algorithm {
-        ClusteringAlgorithm::Abs => {
-            let algo = AbsClustering::new(AbsConfig {
+        #[cfg(feature = "gpu")]
+        ClusteringAlgorithm::Gpu => {
+            let algo = GpuClustering::new(GpuConfig {
                 radius: clustering.radius,
-                neutron_correlation_window_ns: clustering.temporal_window_ns,
+                temporal_window_ns: clustering.temporal_window_ns,
                 min_cluster_size: clustering.min_cluster_size,
-                scan_interval: params.abs_scan_interval,
-            });
-            let mut state = AbsState::default();
+                cell_size: params.grid_cell_size,
+            })?;
+            let mut state = GpuState::default();
             algo.cluster(batch, &mut state)?
         }
-        ClusteringAlgorithm::Dbscan => {
-            let algo = DbscanClustering::new(DbscanConfig {
-                epsilon: clustering.radius,
+        ClusteringAlgorithm::Streaming => {
+            let algo = StreamingClustering::new(StreamingConfig {
+                radius: clustering.radius,
                 temporal_window_ns: clustering.temporal_window_ns,
-                min_points: params.dbscan_min_points,
                 min_cluster_size: clustering.min_cluster_size,
             });
-            let mut state = DbscanState::default();
+            let mut state = StreamingState::default();
             algo.cluster(batch, &mut state)?
         }
-        ClusteringAlgorithm::Grid => {
-            let algo = GridClustering::new(GridConfig {
+        ClusteringAlgorithm::Hierarchical => {
+            let algo = HierarchicalClustering::new(HierarchicalConfig {
                 radius: clustering.radius,
                 temporal_window_ns: clustering.temporal_window_ns,
+                merge_distance: params.hierarchical_merge_distance,
+                merge_window_ns: params.hierarchical_merge_window_ns,
                 min_cluster_size: clustering.min_cluster_size,
+                max_cluster_size: clustering.max_cluster_size.map(usize::from),
                 cell_size: params.grid_cell_size,
-                max_cluster_size: clustering.max_cluster_size.map(|value| value as usize),
             });
-            let mut state = GridState::default();
+            let mut state = HierarchicalState::default();
+            algo.cluster(batch, &mut state)?
+        }
+        ClusteringAlgorithm::ConnectedComponents => {
+            let algo = ConnectedComponentClustering::new(ConnectedComponentConfig {
+                connectivity: params.connectivity,
+                temporal_window_ns: clustering.temporal_window_ns,
+                min_cluster_size: clustering.min_cluster_size,
+            });
+            let mut state = ConnectedComponentState::default();
             algo.cluster(batch, &mut state)?
         }
     };
 
+    Ok(num_clusters)
+}
+
+/// Extract neutrons from a batch whose hits already carry cluster labels.
+///
+/// # Errors
+/// Returns an error if extraction fails.
+pub fn extract_batch(
+    batch: &HitBatch,
+    num_clusters: usize,
+    extraction: &ExtractionConfig,
+) -> Result<NeutronBatch> {
     let mut extractor = SimpleCentroidExtraction::new();
     extractor.configure(extraction.clone());
     extractor
@@ -195,6 +258,59 @@ pub fn cluster_and_extract_batch(
         .map_err(Into::into)
 }
 
+/// Cluster hits in-place, then extract neutrons using the configured algorithm.
+///
+/// # Errors
+/// Returns an error if clustering or extraction fails.
+pub fn cluster_and_extract(
+    batch: &mut HitBatch,
+    algorithm: ClusteringAlgorithm,
+    clustering: &ClusteringConfig,
+    extraction: &ExtractionConfig,
+    params: &AlgorithmParams,
+) -> Result<Vec<Neutron>> {
+    let num_clusters = cluster_batch(batch, algorithm, clustering, params)?;
+    let mut extractor = SimpleCentroidExtraction::new();
+    extractor.configure(extraction.clone());
+    extractor
+        .extract_soa(batch, num_clusters)
+        .map_err(Into::into)
+}
+
+/// Cluster hits in-place, then extract neutrons into a `NeutronBatch`.
+///
+/// # Errors
+/// Returns an error if clustering or extraction fails.
+pub fn cluster_and_extract_batch(
+    batch: &mut HitBatch,
+    algorithm: ClusteringAlgorithm,
+    clustering: &ClusteringConfig,
+    extraction: &ExtractionConfig,
+    params: &AlgorithmParams,
+) -> Result<NeutronBatch> {
+    let num_clusters = cluster_batch(batch, algorithm, clustering, params)?;
+    extract_batch(batch, num_clusters, extraction)
+}
+
+/// Cluster `AoS` hit records and extract neutrons into a `NeutronBatch`.
+///
+/// Adapter for callers that hold hits as tuples: the records are copied into
+/// a `HitBatch` once and sorted by TOF before clustering.
+///
+/// # Errors
+/// Returns an error if clustering or extraction fails.
+pub fn cluster_and_extract_records(
+    hits: &[HitRecord],
+    algorithm: ClusteringAlgorithm,
+    clustering: &ClusteringConfig,
+    extraction: &ExtractionConfig,
+    params: &AlgorithmParams,
+) -> Result<NeutronBatch> {
+    let mut batch = HitBatch::from_records(hits);
+    batch.sort_by_tof();
+    cluster_and_extract_batch(&mut batch, algorithm, clustering, extraction, params)
+}
+
 /// Cluster hits in batches, then extract and append neutrons into a single batch.
 ///
 /// # Errors
@@ -279,4 +395,30 @@ mod tests {
 
         assert!(iter.next().is_none());
     }
+
+    #[test]
+    fn test_records_adapter_matches_batch() {
+        let records = [
+            (11, 10, 102, 20, 1_002, 0),
+            (10, 10, 100, 30, 1_000, 0),
+            (40, 40, 300, 25, 3_000, 0),
+        ];
+        let algorithm = ClusteringAlgorithm::Grid;
+        let clustering = ClusteringConfig::default();
+        let extraction = ExtractionConfig::default();
+        let params = AlgorithmParams::default();
+
+        let mut batch: HitBatch = records.iter().copied().collect();
+        batch.sort_by_tof();
+        let expected =
+            cluster_and_extract_batch(&mut batch, algorithm, &clustering, &extraction, &params)
+                .unwrap();
+        let neutrons =
+            cluster_and_extract_records(&records, algorithm, &clustering, &extraction, &params)
+                .unwrap();
+        assert_eq!(neutrons.len(), 2);
+        assert_eq!(neutrons.x, expected.x);
+        assert_eq!(neutrons.tof, expected.tof);
+        assert_eq!(neutrons.n_hits, expected.n_hits);
+    }
 }
@@ -0,0 +1,207 @@
+//! Radial profiles and azimuthal integration.
+//!
+//! Integrates a counts image laid out as `[y][x]` into rings or sectors
+//! around a center, for quick small-angle scattering checks. Pixel `(x, y)`
+//! sits at coordinate `(x, y)`, as in [`crate::find_beam_spot`]. For per-TOF
+//! profiles, call these on each TOF slice of a histogram.
+
+use std::ops::Range;
+
+/// Counts integrated into radius or azimuth bins.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct IntegratedProfile {
+    /// Bin centers: radius in pixels, or azimuth in degrees.
+    pub centers: Vec<f64>,
+    /// Total counts per bin.
+    pub counts: Vec<u64>,
+    /// Number of pixels per bin.
+    pub pixels: Vec<u64>,
+}
+
+impl IntegratedProfile {
+    fn with_bins(centers: Vec<f64>) -> Self {
+        let bins = centers.len();
+        Self {
+            centers,
+            counts: vec![0; bins],
+            pixels: vec![0; bins],
+        }
+    }
+
+    /// Number of bins.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.centers.len()
+    }
+
+    /// Whether the profile has no bins.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.centers.is_empty()
+    }
+
+    /// Mean counts per pixel in each bin (zero for bins without pixels).
+    #[must_use]
+    #[allow(clippy::cast_precision_loss)]
+    pub fn mean(&self) -> Vec<f64> {
+        self.counts
+            .iter()
+            .zip(&self.pixels)
+            .map(|(&counts, &pixels)| counts as f64 / pixels.max(1) as f64)
+            .collect()
+    }
+}
+
+/// Radial profile around `center` in rings `bin_width` pixels wide.
+///
+/// Ring `i` holds the pixels with `i * bin_width <= r < (i + 1) * bin_width`;
+/// rings extend to the farthest image corner.
+///
+/// # Panics
+/// Panics if `counts.len()` is not `width * height` or `bin_width` is not
+/// positive.
+#[must_use]
+pub fn radial_profile(
+    counts: &[u64],
+    width: usize,
+    height: usize,
+    center: (f64, f64),
+    bin_width: f64,
+) -> IntegratedProfile {
+    assert_eq!(counts.len(), width * height, "image size mismatch");
+    assert!(bin_width > 0.0, "bin width must be positive");
+    if counts.is_empty() {
+        return IntegratedProfile::default();
+    }
+
+    #[allow(clippy::cast_precision_loss)]
+    let (max_x, max_y) = ((width - 1) as f64, (height - 1) as f64);
+    let max_radius = [(0.0, 0.0), (max_x, 0.0), (0.0, max_y), (max_x, max_y)]
+        .iter()
+        .map(|&(x, y)| (x - center.0).hypot(y - center.1))
+        .fold(0.0, f64::max);
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    let bins = (max_radius / bin_width) as usize + 1;
+    #[allow(clippy::cast_precision_loss)]
+    let centers = (0..bins).map(|i| (i as f64 + 0.5) * bin_width).collect();
+    let mut profile = IntegratedProfile::with_bins(centers);
+
+    for (index, &count) in counts.iter().enumerate() {
+        #[allow(clippy::cast_precision_loss)]
+        let (x, y) = ((index % width) as f64, (index / width) as f64);
+        let radius = (x - center.0).hypot(y - center.1);
+        #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+        let bin = ((radius / bin_width) as usize).min(bins - 1);
+        profile.counts[bin] += count;
+        profile.pixels[bin] += 1;
+    }
+    profile
+}
+
+/// Azimuthal integration around `center` into `sectors` equal sectors.
+///
+/// Only pixels with `radii.start <= r < radii.end` are included. Angles are
+/// in degrees in `[0, 360)`, measured from the +x axis towards +y.
+///
+/// # Panics
+/// Panics if `counts.len()` is not `width * height` or `sectors` is zero.
+#[must_use]
+pub fn azimuthal_profile(
+    counts: &[u64],
+    width: usize,
+    height: usize,
+    center: (f64, f64),
+    radii: Range<f64>,
+    sectors: usize,
+) -> IntegratedProfile {
+    assert_eq!(counts.len(), width * height, "image size mismatch");
+    assert!(sectors > 0, "sector count must be positive");
+
+    #[allow(clippy::cast_precision_loss)]
+    let sector_width = 360.0 / sectors as f64;
+    #[allow(clippy::cast_precision_loss)]
+    let centers = (0..sectors)
+        .map(|i| (i as f64 + 0.5) * sector_width)
+        .collect();
+    let mut profile = IntegratedProfile::with_bins(centers);
+
+    for (index, &count) in counts.iter().enumerate() {
+        #[allow(clippy::cast_precision_loss)]
+        let (dx, dy) = (
+            (index % width) as f64 - center.0,
+            (index / width) as f64 - center.1,
+        );
+        if !radii.contains(&dx.hypot(dy)) {
+            continue;
+        }
+        let angle = dy.atan2(dx).to_degrees().rem_euclid(360.0);
+        #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+        let sector = ((angle / sector_width) as usize).min(sectors - 1);
+        profile.counts[sector] += count;
+        profile.pixels[sector] += 1;
+    }
+    profile
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[allow(
+        clippy::cast_precision_loss,
+        clippy::cast_possible_truncation,
+        clippy::cast_sign_loss
+    )]
+    fn test_radial_profile_rings() {
+        // Counts of ten times the distance from the center pixel.
+        let (width, height) = (21, 21);
+        let counts: Vec<u64> = (0..width * height)
+            .map(|index| {
+                let (x, y) = ((index % width) as f64 - 10.0, (index / width) as f64 - 10.0);
+                (x.hypot(y) * 10.0) as u64
+            })
+            .collect();
+        let profile = radial_profile(&counts, width, height, (10.0, 10.0), 1.0);
+
+        assert_eq!(profile.len(), 15);
+        assert_eq!(profile.pixels.iter().sum::<u64>(), 441);
+        assert_eq!(
+            profile.counts.iter().sum::<u64>(),
+            counts.iter().sum::<u64>()
+        );
+        assert_eq!((profile.pixels[0], profile.counts[0]), (1, 0));
+        assert!((profile.centers[2] - 2.5).abs() < 1e-12);
+        let mean = profile.mean();
+        for (ring, value) in mean.iter().enumerate().take(10).skip(1) {
+            let ring = ring as f64;
+            assert!(*value >= ring * 10.0 && *value < (ring + 1.0) * 10.0);
+        }
+    }
+
+    #[test]
+    fn test_azimuthal_profile_sectors() {
+        let (width, height) = (9, 9);
+        let mut counts = vec![0u64; width * height];
+        // One pixel on each axis, 3 px from the center.
+        counts[4 * width + 7] = 1; // +x
+        counts[7 * width + 4] = 2; // +y
+        counts[4 * width + 1] = 4; // -x
+        counts[width + 4] = 8; // -y
+        counts[4 * width + 4] = 100; // center, outside the radius range
+
+        let profile = azimuthal_profile(&counts, width, height, (4.0, 4.0), 1.0..4.0, 8);
+        assert_eq!(profile.len(), 8);
+        assert_eq!(profile.counts, vec![1, 0, 2, 0, 4, 0, 8, 0]);
+        assert!((profile.centers[0] - 22.5).abs() < 1e-12);
+        assert!(profile.pixels.iter().all(|&pixels| pixels > 0));
+    }
+
+    #[test]
+    fn test_empty_image_profile() {
+        assert!(radial_profile(&[], 0, 0, (0.0, 0.0), 1.0).is_empty());
+        let profile = azimuthal_profile(&[0; 4], 2, 2, (0.5, 0.5), 0.0..1.0, 4);
+        assert_eq!(profile.mean(), vec![0.0; 4]);
+    }
+}
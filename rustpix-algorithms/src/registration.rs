@@ -0,0 +1,258 @@
+//! Translation-only registration of counts images by phase correlation.
+//!
+//! Two images of the same scene moved against each other differ in their
+//! Fourier transforms only by a phase ramp, so the inverse FFT of the
+//! normalized cross-power spectrum is a single peak at the shift. The images
+//! are mean-subtracted and Hann-windowed first, so the detector edges do not
+//! pull the peak towards zero, and the peak is refined to sub-pixel
+//! precision with a parabola through its neighbors.
+
+use crate::fourier::fft_2d;
+use rustfft::num_complex::Complex;
+
+/// Cross-power terms weaker than this fraction of the strongest are dropped
+/// rather than normalized, so flat parts of the spectrum add no noise.
+const MIN_RELATIVE_POWER: f64 = 1e-9;
+
+/// Shift of one image against another.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ImageShift {
+    /// Shift along X in pixels: `moving(x, y) ≈ reference(x - dx, y - dy)`.
+    pub dx: f64,
+    /// Shift along Y in pixels.
+    pub dy: f64,
+    /// Height of the correlation peak: close to 1 for shifted copies of one
+    /// image, close to 0 for unrelated or flat images.
+    pub peak: f64,
+}
+
+impl ImageShift {
+    /// The shift rounded to whole pixels.
+    #[must_use]
+    #[allow(clippy::cast_possible_truncation)]
+    pub fn whole_pixels(&self) -> (isize, isize) {
+        (self.dx.round() as isize, self.dy.round() as isize)
+    }
+}
+
+/// Shift of `moving` against `reference`, both `[y][x]` images of
+/// `width × height` pixels.
+///
+/// Shifts are found modulo the image size and reported in
+/// `(-size / 2, size / 2]`. Undo them with
+/// `shift_image(moving, width, height, -dx, -dy)`.
+///
+/// # Panics
+/// Panics if either image is not `width * height` long.
+#[must_use]
+pub fn register_translation(
+    reference: &[u64],
+    moving: &[u64],
+    width: usize,
+    height: usize,
+) -> ImageShift {
+    assert_eq!(reference.len(), width * height, "image size mismatch");
+    assert_eq!(moving.len(), width * height, "image size mismatch");
+    if reference.is_empty() {
+        return ImageShift::default();
+    }
+
+    let window = hann_window(width, height);
+    let mut reference = windowed(reference, &window);
+    let mut moving = windowed(moving, &window);
+    fft_2d(&mut reference, width, height, false);
+    fft_2d(&mut moving, width, height, false);
+
+    let mut cross: Vec<Complex<f64>> = moving
+        .iter()
+        .zip(&reference)
+        .map(|(moving, reference)| moving * reference.conj())
+        .collect();
+    let max_norm = cross.iter().map(|value| value.norm()).fold(0.0, f64::max);
+    for value in &mut cross {
+        let norm = value.norm();
+        *value = if norm > max_norm * MIN_RELATIVE_POWER {
+            *value / norm
+        } else {
+            Complex::default()
+        };
+    }
+    fft_2d(&mut cross, width, height, true);
+
+    // The first maximum, so flat images report no shift.
+    let mut best = 0;
+    for (index, value) in cross.iter().enumerate() {
+        if value.re > cross[best].re {
+            best = index;
+        }
+    }
+    let (x, y) = (best % width, best / width);
+    let value = |x: usize, y: usize| cross[y * width + x].re;
+    let center = value(x, y);
+    let sub_x = parabola_offset(
+        value((x + width - 1) % width, y),
+        center,
+        value((x + 1) % width, y),
+    );
+    let sub_y = parabola_offset(
+        value(x, (y + height - 1) % height),
+        center,
+        value(x, (y + 1) % height),
+    );
+    #[allow(clippy::cast_precision_loss)]
+    let peak = center / cross.len() as f64;
+    ImageShift {
+        dx: signed_offset(x, width) + sub_x,
+        dy: signed_offset(y, height) + sub_y,
+        peak,
+    }
+}
+
+/// `image` moved by `(dx, dy)` whole pixels; pixels moved in from outside
+/// are `T::default()`.
+///
+/// # Panics
+/// Panics if `image` is not `width * height` long.
+#[must_use]
+pub fn shift_image<T: Copy + Default>(
+    image: &[T],
+    width: usize,
+    height: usize,
+    dx: isize,
+    dy: isize,
+) -> Vec<T> {
+    assert_eq!(image.len(), width * height, "image size mismatch");
+    let mut shifted = vec![T::default(); image.len()];
+    for y in 0..height {
+        let Some(source_y) = y
+            .checked_add_signed(dy.saturating_neg())
+            .filter(|&y| y < height)
+        else {
+            continue;
+        };
+        for x in 0..width {
+            if let Some(source_x) = x
+                .checked_add_signed(dx.saturating_neg())
+                .filter(|&x| x < width)
+            {
+                shifted[y * width + x] = image[source_y * width + source_x];
+            }
+        }
+    }
+    shifted
+}
+
+/// Separable Hann window, 1 for axes of a single pixel.
+fn hann_window(width: usize, height: usize) -> Vec<f64> {
+    let axis = |size: usize| -> Vec<f64> {
+        if size < 2 {
+            return vec![1.0; size];
+        }
+        #[allow(clippy::cast_precision_loss)]
+        let last = (size - 1) as f64;
+        (0..size)
+            .map(|i| {
+                #[allow(clippy::cast_precision_loss)]
+                let phase = std::f64::consts::TAU * i as f64 / last;
+                0.5 - 0.5 * phase.cos()
+            })
+            .collect()
+    };
+    let (columns, rows) = (axis(width), axis(height));
+    rows.iter()
+        .flat_map(|row| columns.iter().map(move |column| row * column))
+        .collect()
+}
+
+/// Mean-subtracted image times `window`, as complex values.
+fn windowed(counts: &[u64], window: &[f64]) -> Vec<Complex<f64>> {
+    #[allow(clippy::cast_precision_loss)]
+    let mean = counts.iter().sum::<u64>() as f64 / counts.len() as f64;
+    counts
+        .iter()
+        .zip(window)
+        .map(|(&count, &weight)| {
+            #[allow(clippy::cast_precision_loss)]
+            let value = count as f64 - mean;
+            Complex::new(value * weight, 0.0)
+        })
+        .collect()
+}
+
+/// Offset in `[-0.5, 0.5]` of the vertex of the parabola through three
+/// samples around a maximum.
+fn parabola_offset(left: f64, center: f64, right: f64) -> f64 {
+    let curvature = left - 2.0 * center + right;
+    if curvature >= 0.0 {
+        return 0.0;
+    }
+    (0.5 * (left - right) / curvature).clamp(-0.5, 0.5)
+}
+
+/// Index `index` of a periodic axis of `size` as an offset in
+/// `(-size / 2, size / 2]`.
+fn signed_offset(index: usize, size: usize) -> f64 {
+    #[allow(clippy::cast_precision_loss)]
+    let (index, size_f) = (index as f64, size as f64);
+    if index > size_f / 2.0 {
+        index - size_f
+    } else {
+        index
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Two Gaussian spots at `(x, y)` and `(x + 12, y + 9)`.
+    #[allow(
+        clippy::cast_precision_loss,
+        clippy::cast_possible_truncation,
+        clippy::cast_sign_loss
+    )]
+    fn spots(width: usize, height: usize, x: f64, y: f64) -> Vec<u64> {
+        (0..width * height)
+            .map(|index| {
+                let (px, py) = ((index % width) as f64, (index / width) as f64);
+                let spot = |cx: f64, cy: f64, sigma: f64| {
+                    (-((px - cx).powi(2) + (py - cy).powi(2)) / (2.0 * sigma * sigma)).exp()
+                };
+                (1000.0 * spot(x, y, 2.0) + 600.0 * spot(x + 12.0, y + 9.0, 3.0)).round() as u64
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_register_translation_finds_shift() {
+        let reference = spots(64, 48, 24.0, 18.0);
+        let moving = spots(64, 48, 29.0, 15.0);
+        let shift = register_translation(&reference, &moving, 64, 48);
+        assert!((shift.dx - 5.0).abs() < 0.25, "dx = {}", shift.dx);
+        assert!((shift.dy + 3.0).abs() < 0.25, "dy = {}", shift.dy);
+        assert!(shift.peak > 0.3, "peak = {}", shift.peak);
+        assert_eq!(shift.whole_pixels(), (5, -3));
+
+        let aligned = shift_image(&moving, 64, 48, -5, 3);
+        let same = register_translation(&reference, &aligned, 64, 48);
+        assert_eq!(same.whole_pixels(), (0, 0));
+    }
+
+    #[test]
+    fn test_shift_image_fills_with_default() {
+        let image = [1u32, 2, 3, 4, 5, 6];
+        assert_eq!(shift_image(&image, 3, 2, 1, 0), vec![0, 1, 2, 0, 4, 5]);
+        assert_eq!(shift_image(&image, 3, 2, -1, 1), vec![0, 0, 0, 2, 3, 0]);
+        assert_eq!(shift_image(&image, 3, 2, 5, 0), vec![0; 6]);
+    }
+
+    #[test]
+    fn test_flat_and_empty_images() {
+        let flat = vec![7u64; 16 * 8];
+        let shift = register_translation(&flat, &flat, 16, 8);
+        assert_eq!(shift.whole_pixels(), (0, 0));
+        assert!(shift.peak.abs() < 1e-9);
+        assert_eq!(register_translation(&[], &[], 0, 0), ImageShift::default());
+    }
+}
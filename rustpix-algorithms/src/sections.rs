@@ -0,0 +1,314 @@
+//! Parallel clustering of independent time sections.
+//!
+//! Every algorithm only links hits at most one temporal window apart (for
+//! Hierarchical, the larger of the temporal and merge windows), so a
+//! TOF-sorted batch can be cut wherever two consecutive hits are more than a
+//! window apart without splitting any cluster. Sections of at least
+//! [`AlgorithmParams::section_hits`] hits are cut at the first such gap past
+//! their nominal end, clustered on the rayon pool and stitched back by
+//! shifting each section's labels past the clusters of the sections before
+//! it. The cuts depend only on the data and `section_hits`, so the labels are
+//! the same for any number of threads.
+
+use rayon::prelude::*;
+use rustpix_core::error::{ClusteringError, Result};
+use rustpix_core::soa::HitBatch;
+
+use crate::processing::cluster_whole;
+use crate::{AlgorithmParams, ClusteringAlgorithm, ClusteringConfig};
+
+/// Whether [`cluster_batch`](crate::cluster_batch) is asked to cut `batch`
+/// into sections but has to cluster it whole, because the batch is not
+/// sorted by TOF or has no gap longer than the linking window past its
+/// first [`AlgorithmParams::section_hits`] hits.
+///
+/// Sections are only ever cut at such gaps, so dense or unsorted pulses
+/// gain nothing from `section_hits`; callers use this to warn about it.
+/// Batches of at most `section_hits` hits and GPU clustering, which never
+/// run in sections, do not count.
+#[must_use]
+pub fn is_unsectionable(
+    batch: &HitBatch,
+    algorithm: ClusteringAlgorithm,
+    clustering: &ClusteringConfig,
+    params: &AlgorithmParams,
+) -> bool {
+    #[cfg(feature = "gpu")]
+    if matches!(algorithm, ClusteringAlgorithm::Gpu) {
+        return false;
+    }
+    params.section_hits > 0
+        && batch.len() > params.section_hits
+        && batch_section_starts(batch, algorithm, clustering, params).is_none()
+}
+
+/// Cluster `batch` in sections on the rayon pool; see the module docs.
+///
+/// Falls back to clustering the whole batch at once when it is not sorted
+/// by TOF or has no gap to cut at.
+pub(crate) fn cluster_sections(
+    batch: &mut HitBatch,
+    algorithm: ClusteringAlgorithm,
+    clustering: &ClusteringConfig,
+    params: &AlgorithmParams,
+) -> Result<usize> {
+    let Some(starts) = batch_section_starts(batch, algorithm, clustering, params) else {
+        return cluster_whole(batch, algorithm, clustering, params);
+    };
+
+    let bounds: Vec<(usize, usize)> = starts
+        .iter()
+        .zip(starts.iter().skip(1).chain([&batch.len()]))
+        .map(|(&start, &end)| (start, end))
+        .collect();
+    let source = &*batch;
+    let sections = bounds
+        .par_iter()
+        .map(|&(start, end)| {
+            let mut section = slice(source, start, end);
+            let clusters = cluster_whole(&mut section, algorithm, clustering, params)?;
+            Ok((section.cluster_id, clusters))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let mut offset = 0usize;
+    for (&(start, end), (labels, clusters)) in bounds.iter().zip(sections) {
+        let shift = i32::try_from(offset).map_err(|_| {
+            ClusteringError::StateError("more than i32::MAX clusters in one batch".to_string())
+        })?;
+        for (id, label) in batch.cluster_id[start..end].iter_mut().zip(labels) {
+            *id = if label >= 0 { label + shift } else { -1 };
+        }
+        offset += clusters;
+    }
+    Ok(offset)
+}
+
+/// Section starts of `batch`, or `None` when it is unsorted or cannot be
+/// cut at all.
+fn batch_section_starts(
+    batch: &HitBatch,
+    algorithm: ClusteringAlgorithm,
+    clustering: &ClusteringConfig,
+    params: &AlgorithmParams,
+) -> Option<Vec<usize>> {
+    if !batch.tof.is_sorted() {
+        return None;
+    }
+    let starts = section_starts(
+        &batch.tof,
+        params.section_hits,
+        window_ticks(link_window_ns(algorithm, clustering, params)),
+    );
+    (starts.len() >= 2).then_some(starts)
+}
+
+/// Largest TOF difference over which `algorithm` links hits (nanoseconds).
+fn link_window_ns(
+    algorithm: ClusteringAlgorithm,
+    clustering: &ClusteringConfig,
+    params: &AlgorithmParams,
+) -> f64 {
+    match algorithm {
+        ClusteringAlgorithm::Hierarchical => clustering
+            .temporal_window_ns
+            .max(params.hierarchical_merge_window_ns),
+        _ => clustering.temporal_window_ns,
+    }
+}
+
+/// Temporal window in 25 ns TOF ticks, rounded up as the algorithms do.
+fn window_ticks(temporal_window_ns: f64) -> u32 {
+    let ticks = (temporal_window_ns / 25.0).ceil();
+    if ticks <= 0.0 {
+        0
+    } else if ticks >= f64::from(u32::MAX) {
+        u32::MAX
+    } else {
+        format!("{ticks:.0}").parse().unwrap_or(u32::MAX)
+    }
+}
+
+/// Start indices of the sections of the sorted `tof` column: each section
+/// holds at least `section_hits` hits and ends at a gap of more than
+/// `window` ticks (the last one ends with the column).
+fn section_starts(tof: &[u32], section_hits: usize, window: u32) -> Vec<usize> {
+    let step = section_hits.max(1);
+    let mut starts = vec![0];
+    let mut cut = step;
+    while cut < tof.len() {
+        let Some(start) = (cut..tof.len()).find(|&i| tof[i] - tof[i - 1] > window) else {
+            break;
+        };
+        starts.push(start);
+        cut = start + step;
+    }
+    starts
+}
+
+/// Hits `start..end` of `batch`, without the raw columns.
+fn slice(batch: &HitBatch, start: usize, end: usize) -> HitBatch {
+    HitBatch {
+        x: batch.x[start..end].to_vec(),
+        y: batch.y[start..end].to_vec(),
+        tof: batch.tof[start..end].to_vec(),
+        tot: batch.tot[start..end].to_vec(),
+        timestamp: batch.timestamp[start..end].to_vec(),
+        chip_id: batch.chip_id[start..end].to_vec(),
+        cluster_id: batch.cluster_id[start..end].to_vec(),
+        ..HitBatch::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cluster_batch;
+    use std::collections::HashMap;
+
+    /// Clusters of 1-6 hits at pseudo-random pixels, 0-5 ticks apart, so
+    /// neighbours often touch in time and gaps above the window are rare.
+    fn busy_pulse(hits: usize) -> HitBatch {
+        let mut state = 0x2545_F491_4F6C_DD1Du64;
+        let mut next = move || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state
+        };
+        let mut batch = HitBatch::with_capacity(hits);
+        let mut tof = 0u32;
+        while batch.len() < hits {
+            tof += u32::try_from(next() % 6).unwrap();
+            let x = u16::try_from(next() % 500).unwrap();
+            let y = u16::try_from(next() % 500).unwrap();
+            for k in 0..=next() % 5 {
+                let k = u16::try_from(k).unwrap();
+                batch.push((x + k % 2, y + k / 2, tof + u32::from(k), 10 + k, 0, 0));
+            }
+        }
+        batch.sort_by_tof();
+        batch
+    }
+
+    /// Labels renumbered in order of first appearance.
+    fn canonical(labels: &[i32]) -> Vec<i32> {
+        let mut seen = HashMap::new();
+        labels
+            .iter()
+            .map(|&label| {
+                if label < 0 {
+                    return -1;
+                }
+                let next = i32::try_from(seen.len()).unwrap();
+                *seen.entry(label).or_insert(next)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_section_starts_cut_only_at_gaps() {
+        let tof = [0, 1, 2, 3, 10, 11, 12, 20, 21, 30];
+        assert_eq!(section_starts(&tof, 2, 3), vec![0, 4, 7, 9]);
+        assert_eq!(section_starts(&tof, 5, 3), vec![0, 7]);
+        assert_eq!(section_starts(&tof, 2, 8), vec![0, 9]);
+        assert_eq!(section_starts(&tof, 100, 3), vec![0]);
+        assert_eq!(section_starts(&[], 2, 3), vec![0]);
+        assert_eq!(window_ticks(75.0), 3);
+    }
+
+    #[test]
+    fn test_sections_match_whole_batch_for_any_thread_count() {
+        let clustering = ClusteringConfig::default();
+        let whole_params = AlgorithmParams::default();
+        let params = AlgorithmParams {
+            section_hits: 500,
+            ..AlgorithmParams::default()
+        };
+        for algorithm in [
+            ClusteringAlgorithm::Abs,
+            ClusteringAlgorithm::Grid,
+            ClusteringAlgorithm::Dbscan,
+            ClusteringAlgorithm::Streaming,
+            ClusteringAlgorithm::Hierarchical,
+            ClusteringAlgorithm::ConnectedComponents,
+        ] {
+            let mut whole = busy_pulse(20_000);
+            assert!(section_starts(&whole.tof, 500, 3).len() > 10);
+            let expected =
+                cluster_batch(&mut whole, algorithm, &clustering, &whole_params).unwrap();
+
+            let mut runs = Vec::new();
+            for threads in [1, 3, 8] {
+                let pool = rayon::ThreadPoolBuilder::new()
+                    .num_threads(threads)
+                    .build()
+                    .unwrap();
+                let mut batch = busy_pulse(20_000);
+                let clusters =
+                    pool.install(|| cluster_batch(&mut batch, algorithm, &clustering, &params));
+                assert_eq!(clusters.unwrap(), expected, "{algorithm:?}");
+                runs.push(batch.cluster_id);
+            }
+            assert!(
+                runs.windows(2).all(|pair| pair[0] == pair[1]),
+                "{algorithm:?}"
+            );
+            assert_eq!(
+                canonical(&runs[0]),
+                canonical(&whole.cluster_id),
+                "{algorithm:?}"
+            );
+            let max = runs[0].iter().copied().max().unwrap();
+            assert_eq!(usize::try_from(max).unwrap() + 1, expected, "{algorithm:?}");
+        }
+    }
+
+    #[test]
+    fn test_unsorted_batch_is_clustered_whole() {
+        let mut batch = busy_pulse(2_000);
+        batch.tof.swap(0, 1_000);
+        let mut whole = batch.clone();
+        let clustering = ClusteringConfig::default();
+        let params = AlgorithmParams {
+            section_hits: 100,
+            ..AlgorithmParams::default()
+        };
+        let algorithm = ClusteringAlgorithm::Grid;
+        let expected = cluster_batch(
+            &mut whole,
+            algorithm,
+            &clustering,
+            &AlgorithmParams::default(),
+        );
+        assert!(is_unsectionable(&batch, algorithm, &clustering, &params));
+        let clusters = cluster_batch(&mut batch, algorithm, &clustering, &params);
+        assert_eq!(clusters.unwrap(), expected.unwrap());
+        assert_eq!(batch.cluster_id, whole.cluster_id);
+    }
+
+    #[test]
+    fn test_unsectionable_only_without_a_cut() {
+        let clustering = ClusteringConfig::default();
+        let algorithm = ClusteringAlgorithm::Grid;
+        let params = AlgorithmParams {
+            section_hits: 3,
+            ..AlgorithmParams::default()
+        };
+        let mut gaps = HitBatch::default();
+        let mut dense = HitBatch::default();
+        for i in 0..8u16 {
+            gaps.push((i, 0, u32::from(i) * 100, 10, 0, 0));
+            dense.push((i, 0, u32::from(i), 10, 0, 0));
+        }
+        assert!(!is_unsectionable(&gaps, algorithm, &clustering, &params));
+        assert!(is_unsectionable(&dense, algorithm, &clustering, &params));
+        let small = AlgorithmParams {
+            section_hits: 8,
+            ..params
+        };
+        assert!(!is_unsectionable(&dense, algorithm, &clustering, &small));
+        let off = AlgorithmParams::default();
+        assert!(!is_unsectionable(&dense, algorithm, &clustering, &off));
+    }
+}
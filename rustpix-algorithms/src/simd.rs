@@ -0,0 +1,236 @@
+//! Lane-parallel neighbor checks for ABS and Grid clustering (`simd` feature).
+//!
+//! Both scans gather the candidates' fields into `wide` vectors and compare a
+//! chunk of candidates at once, then walk the matching lanes in order, so the
+//! labels are identical to the scalar loops'. The gathers are scattered
+//! loads, which is why the scalar loops remain the default; see the crate
+//! README for the criterion measurements.
+
+use rustpix_core::soa::HitBatch;
+use wide::{f64x4, i32x8, CmpGt, CmpLe, CmpLt};
+
+/// Call `on_match` with each of `candidates` (hit indices in TOF order)
+/// within `radius_sq` of hit `i`, stopping at the first candidate more than
+/// `window_tof` after it, like Grid's scalar scan.
+pub(crate) fn for_each_grid_neighbor(
+    batch: &HitBatch,
+    i: usize,
+    candidates: &[usize],
+    window_tof: u32,
+    radius_sq: f64,
+    mut on_match: impl FnMut(usize),
+) {
+    let tof = batch.tof[i];
+    let x = f64::from(batch.x[i]);
+    let y = f64::from(batch.y[i]);
+    let xs = f64x4::splat(x);
+    let ys = f64x4::splat(y);
+    let windows = f64x4::splat(f64::from(window_tof));
+    let radii_sq = f64x4::splat(radius_sq);
+
+    let mut chunks = candidates.chunks_exact(4);
+    for chunk in &mut chunks {
+        let dt = f64x4::new(lanes(chunk, |j| f64::from(batch.tof[j].wrapping_sub(tof))));
+        let dx = xs - f64x4::new(lanes(chunk, |j| f64::from(batch.x[j])));
+        let dy = ys - f64x4::new(lanes(chunk, |j| f64::from(batch.y[j])));
+
+        let in_window = dt.cmp_le(windows).move_mask();
+        // Lanes before the first one past the window; the scan ends there.
+        let open = (1 << (!in_window).trailing_zeros().min(4)) - 1;
+        let close = (dx * dx + dy * dy).cmp_le(radii_sq).move_mask();
+        for_each_lane(close & open, |lane| on_match(chunk[lane]));
+        if open != 0b1111 {
+            return;
+        }
+    }
+    for &j in chunks.remainder() {
+        if batch.tof[j].wrapping_sub(tof) > window_tof {
+            return;
+        }
+        let dx = x - f64::from(batch.x[j]);
+        let dy = y - f64::from(batch.y[j]);
+        if dx * dx + dy * dy <= radius_sq {
+            on_match(j);
+        }
+    }
+}
+
+/// Box and start time of an open ABS bucket.
+#[derive(Clone, Copy, Default)]
+pub(crate) struct BucketBounds {
+    pub x_min: i32,
+    pub x_max: i32,
+    pub y_min: i32,
+    pub y_max: i32,
+    pub start_tof: u32,
+}
+
+impl BucketBounds {
+    fn accepts(&self, x: i32, y: i32, tof: u32, radius: i32, window_tof: u32) -> bool {
+        x >= self.x_min - radius
+            && x <= self.x_max + radius
+            && y >= self.y_min - radius
+            && y <= self.y_max + radius
+            && tof.wrapping_sub(self.start_tof) <= window_tof
+    }
+}
+
+/// First of `candidates` (bucket indices) whose box, grown by `radius`,
+/// contains `(x, y)` and whose time window includes `tof`, like ABS's scalar
+/// search. `bounds` gives a bucket's bounds, or `None` if it is closed.
+pub(crate) fn find_bucket(
+    candidates: &[usize],
+    bounds: impl Fn(usize) -> Option<BucketBounds>,
+    x: i32,
+    y: i32,
+    tof: u32,
+    radius: i32,
+    window_tof: u32,
+) -> Option<usize> {
+    let xs = i32x8::splat(x);
+    let ys = i32x8::splat(y);
+    let windows = i32x8::splat(order_preserving(window_tof));
+
+    let mut chunks = candidates.chunks_exact(8);
+    for chunk in &mut chunks {
+        let buckets: [Option<BucketBounds>; 8] = lanes(chunk, &bounds);
+        // A closed bucket gets an empty box and an endless age, which
+        // reject every hit.
+        let x_min = field(&buckets, i32::MAX, |b| b.x_min) - radius;
+        let x_max = field(&buckets, i32::MIN, |b| b.x_max) + radius;
+        let y_min = field(&buckets, i32::MAX, |b| b.y_min) - radius;
+        let y_max = field(&buckets, i32::MIN, |b| b.y_max) + radius;
+        let dt = field(&buckets, i32::MAX, |b| {
+            order_preserving(tof.wrapping_sub(b.start_tof))
+        });
+
+        let outside = xs.cmp_lt(x_min)
+            | xs.cmp_gt(x_max)
+            | ys.cmp_lt(y_min)
+            | ys.cmp_gt(y_max)
+            | dt.cmp_gt(windows);
+        let inside = (!outside).move_mask();
+        if inside != 0 {
+            return Some(chunk[lane_index(inside.trailing_zeros())]);
+        }
+    }
+    chunks
+        .remainder()
+        .iter()
+        .copied()
+        .find(|&bidx| bounds(bidx).is_some_and(|b| b.accepts(x, y, tof, radius, window_tof)))
+}
+
+/// One field of eight buckets, `closed` for closed ones.
+fn field(
+    buckets: &[Option<BucketBounds>; 8],
+    closed: i32,
+    get: impl Fn(&BucketBounds) -> i32,
+) -> i32x8 {
+    i32x8::new(lanes(buckets, |bucket| {
+        bucket.as_ref().map_or(closed, &get)
+    }))
+}
+
+/// One value per lane, from a chunk of exactly `N` items.
+fn lanes<T: Copy, U: Copy + Default, const N: usize>(chunk: &[T], f: impl Fn(T) -> U) -> [U; N] {
+    let mut lanes = [U::default(); N];
+    for (lane, &item) in lanes.iter_mut().zip(chunk) {
+        *lane = f(item);
+    }
+    lanes
+}
+
+/// Map `u32` to `i32` so that signed comparisons order them as unsigned.
+fn order_preserving(value: u32) -> i32 {
+    (value ^ (1 << 31)).cast_signed()
+}
+
+/// Call `f` with the index of each set bit of `mask`, lowest first.
+fn for_each_lane(mut mask: i32, mut f: impl FnMut(usize)) {
+    while mask != 0 {
+        f(lane_index(mask.trailing_zeros()));
+        mask &= mask - 1;
+    }
+}
+
+fn lane_index(bit: u32) -> usize {
+    usize::try_from(bit).unwrap_or(usize::MAX)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rustpix_core::SplitMix64;
+
+    #[test]
+    fn test_grid_neighbors_match_scalar() {
+        let mut rng = SplitMix64::new(7);
+        let mut batch = HitBatch::default();
+        let mut tof = u32::MAX - 500;
+        for _ in 0..200 {
+            // Crosses the u32 rollover, as TOFs do between pulses.
+            tof = tof.wrapping_add(rng.below(6u32));
+            batch.push((rng.below(20u16), rng.below(20u16), tof, 5, 0, 0));
+        }
+        let candidates: Vec<usize> = (0..batch.len()).collect();
+        for (window_tof, radius_sq) in [(0, 2.0), (3, 8.0), (40, 25.0), (u32::MAX, 400.0)] {
+            for i in 0..batch.len() {
+                let mut expected = Vec::new();
+                for &j in &candidates[i + 1..] {
+                    if batch.tof[j].wrapping_sub(batch.tof[i]) > window_tof {
+                        break;
+                    }
+                    let dx = f64::from(batch.x[i]) - f64::from(batch.x[j]);
+                    let dy = f64::from(batch.y[i]) - f64::from(batch.y[j]);
+                    if dx * dx + dy * dy <= radius_sq {
+                        expected.push(j);
+                    }
+                }
+                let mut found = Vec::new();
+                for_each_grid_neighbor(
+                    &batch,
+                    i,
+                    &candidates[i + 1..],
+                    window_tof,
+                    radius_sq,
+                    |j| found.push(j),
+                );
+                assert_eq!(found, expected, "hit {i}, window {window_tof}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_find_bucket_matches_scalar() {
+        let mut rng = SplitMix64::new(11);
+        let buckets: Vec<Option<BucketBounds>> = (0..64)
+            .map(|_| {
+                (rng.below(4u8) != 0).then(|| {
+                    let x_min = i32::from(rng.below(60u16));
+                    let y_min = i32::from(rng.below(60u16));
+                    BucketBounds {
+                        x_min,
+                        x_max: x_min + i32::from(rng.below(4u16)),
+                        y_min,
+                        y_max: y_min + i32::from(rng.below(4u16)),
+                        start_tof: rng.below(200u32).wrapping_sub(100),
+                    }
+                })
+            })
+            .collect();
+        let bounds = |bidx: usize| buckets[bidx];
+        for _ in 0..2_000 {
+            let x = i32::from(rng.below(64u16));
+            let y = i32::from(rng.below(64u16));
+            let tof = rng.below(200u32).wrapping_sub(100);
+            let len = usize::from(rng.below(64u8)) + 1;
+            let candidates: Vec<usize> = (0..len).map(|_| usize::from(rng.below(64u8))).collect();
+            let expected = candidates
+                .iter()
+                .copied()
+                .find(|&bidx| bounds(bidx).is_some_and(|b| b.accepts(x, y, tof, 2, 30)));
+            assert_eq!(find_bucket(&candidates, bounds, x, y, tof, 2, 30), expected);
+        }
+    }
+}
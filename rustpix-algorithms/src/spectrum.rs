@@ -0,0 +1,237 @@
+//! Statistical comparison of two TOF spectra.
+//!
+//! Answers "are these runs consistent?" with a two-sample chi-square test
+//! on the binned counts (Poisson errors, each spectrum scaled to the other's
+//! total) and a Kolmogorov-Smirnov test on the cumulative distributions.
+//! Both tests compare shapes only, so runs of different length can be
+//! compared directly.
+
+/// Result of comparing two spectra with the same binning.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SpectrumComparison {
+    /// Chi-square of the normalized bin differences.
+    pub chi_square: f64,
+    /// Bins with counts in either spectrum, minus one for the normalization.
+    pub degrees_of_freedom: usize,
+    /// Probability of a chi-square at least this large for consistent runs.
+    pub chi_square_p: f64,
+    /// Largest difference of the normalized cumulative distributions.
+    pub ks_statistic: f64,
+    /// Probability of a KS statistic at least this large for consistent runs.
+    /// Binning makes it conservative (too large) for coarse bins.
+    pub ks_p: f64,
+}
+
+impl SpectrumComparison {
+    /// Chi-square per degree of freedom (about 1 for consistent runs).
+    #[must_use]
+    pub fn reduced_chi_square(&self) -> f64 {
+        if self.degrees_of_freedom == 0 {
+            return 0.0;
+        }
+        #[allow(clippy::cast_precision_loss)]
+        let dof = self.degrees_of_freedom as f64;
+        self.chi_square / dof
+    }
+
+    /// Whether neither test rejects consistency at significance `alpha`.
+    #[must_use]
+    pub fn consistent(&self, alpha: f64) -> bool {
+        self.chi_square_p >= alpha && self.ks_p >= alpha
+    }
+}
+
+/// Compare two spectra with the same binning.
+///
+/// Returns `None` if the spectra have different lengths or either is empty.
+#[must_use]
+pub fn compare_spectra(a: &[u64], b: &[u64]) -> Option<SpectrumComparison> {
+    if a.len() != b.len() {
+        return None;
+    }
+    let total_a: u64 = a.iter().sum();
+    let total_b: u64 = b.iter().sum();
+    if total_a == 0 || total_b == 0 {
+        return None;
+    }
+    #[allow(clippy::cast_precision_loss)]
+    let (total_a, total_b) = (total_a as f64, total_b as f64);
+    let (scale_a, scale_b) = ((total_b / total_a).sqrt(), (total_a / total_b).sqrt());
+
+    let mut chi_square = 0.0;
+    let mut filled_bins = 0usize;
+    let (mut cumulative_a, mut cumulative_b) = (0.0, 0.0);
+    let mut ks_statistic: f64 = 0.0;
+    for (&count_a, &count_b) in a.iter().zip(b) {
+        #[allow(clippy::cast_precision_loss)]
+        let (count_a, count_b) = (count_a as f64, count_b as f64);
+        if count_a + count_b > 0.0 {
+            chi_square += (scale_a * count_a - scale_b * count_b).powi(2) / (count_a + count_b);
+            filled_bins += 1;
+        }
+        cumulative_a += count_a / total_a;
+        cumulative_b += count_b / total_b;
+        ks_statistic = ks_statistic.max((cumulative_a - cumulative_b).abs());
+    }
+
+    let degrees_of_freedom = filled_bins.saturating_sub(1);
+    let chi_square_p = if degrees_of_freedom == 0 {
+        1.0
+    } else {
+        #[allow(clippy::cast_precision_loss)]
+        let half_dof = degrees_of_freedom as f64 / 2.0;
+        gamma_q(half_dof, chi_square / 2.0)
+    };
+    let effective = (total_a * total_b / (total_a + total_b)).sqrt();
+    let ks_p = kolmogorov_q((effective + 0.12 + 0.11 / effective) * ks_statistic);
+
+    Some(SpectrumComparison {
+        chi_square,
+        degrees_of_freedom,
+        chi_square_p,
+        ks_statistic,
+        ks_p,
+    })
+}
+
+/// Kolmogorov distribution tail `Q(λ) = 2 Σ (-1)^(k-1) exp(-2 k² λ²)`.
+fn kolmogorov_q(lambda: f64) -> f64 {
+    // The series converges slowly near zero, where Q is 1 to double precision.
+    if lambda < 0.2 {
+        return 1.0;
+    }
+    let mut sum = 0.0;
+    let mut sign = 1.0;
+    for k in 1..=100u32 {
+        let term = sign * (-2.0 * f64::from(k * k) * lambda * lambda).exp();
+        sum += term;
+        if term.abs() < 1e-12 * sum.abs() {
+            break;
+        }
+        sign = -sign;
+    }
+    (2.0 * sum).clamp(0.0, 1.0)
+}
+
+/// Regularized upper incomplete gamma function `Q(a, x)`, the chi-square
+/// tail probability for `a = dof / 2`, `x = chi² / 2`.
+fn gamma_q(a: f64, x: f64) -> f64 {
+    if x <= 0.0 {
+        return 1.0;
+    }
+    let log_prefactor = a * x.ln() - x - ln_gamma(a);
+    if x < a + 1.0 {
+        // Series for P(a, x).
+        let mut term = 1.0 / a;
+        let mut sum = term;
+        let mut n = a;
+        for _ in 0..1000 {
+            n += 1.0;
+            term *= x / n;
+            sum += term;
+            if term.abs() < sum.abs() * 1e-15 {
+                break;
+            }
+        }
+        (1.0 - sum * log_prefactor.exp()).clamp(0.0, 1.0)
+    } else {
+        // Continued fraction for Q(a, x) (modified Lentz).
+        let tiny = f64::MIN_POSITIVE / f64::EPSILON;
+        let mut b_n = x + 1.0 - a;
+        let mut c_n = 1.0 / tiny;
+        let mut d_n = 1.0 / b_n;
+        let mut fraction = d_n;
+        for i in 1..1000u32 {
+            let i = f64::from(i);
+            let a_n = -i * (i - a);
+            b_n += 2.0;
+            d_n = a_n * d_n + b_n;
+            if d_n.abs() < tiny {
+                d_n = tiny;
+            }
+            c_n = b_n + a_n / c_n;
+            if c_n.abs() < tiny {
+                c_n = tiny;
+            }
+            d_n = 1.0 / d_n;
+            let delta = d_n * c_n;
+            fraction *= delta;
+            if (delta - 1.0).abs() < 1e-15 {
+                break;
+            }
+        }
+        (log_prefactor.exp() * fraction).clamp(0.0, 1.0)
+    }
+}
+
+/// Natural log of the gamma function (Lanczos approximation, `x > 0`).
+fn ln_gamma(x: f64) -> f64 {
+    const COEFFICIENTS: [f64; 6] = [
+        76.180_091_729_471_46,
+        -86.505_320_329_416_77,
+        24.014_098_240_830_91,
+        -1.231_739_572_450_155,
+        0.001_208_650_973_866_179,
+        -0.000_005_395_239_384_953,
+    ];
+    let tmp = x + 5.5;
+    let tmp = tmp - (x + 0.5) * tmp.ln();
+    let mut series = 1.000_000_000_190_015;
+    let mut y = x;
+    for coefficient in COEFFICIENTS {
+        y += 1.0;
+        series += coefficient / y;
+    }
+    -tmp + (2.506_628_274_631_000_5 * series / x).ln()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_identical_and_scaled_spectra_are_consistent() {
+        let a = [10, 40, 90, 40, 10];
+        let same = compare_spectra(&a, &a).unwrap();
+        assert!(same.chi_square.abs() < 1e-12);
+        assert_eq!(same.degrees_of_freedom, 4);
+        assert!((same.chi_square_p - 1.0).abs() < 1e-12);
+        assert!(same.ks_statistic.abs() < 1e-12);
+        assert!(same.consistent(0.05));
+
+        let doubled: Vec<u64> = a.iter().map(|&count| count * 2).collect();
+        let scaled = compare_spectra(&a, &doubled).unwrap();
+        assert!(scaled.chi_square.abs() < 1e-9);
+        assert!(scaled.consistent(0.05));
+    }
+
+    #[test]
+    fn test_different_shapes_are_inconsistent() {
+        let a = [1000, 2000, 3000, 2000, 1000];
+        let b = [3000, 2000, 1000, 2000, 3000];
+        let comparison = compare_spectra(&a, &b).unwrap();
+        assert!(comparison.reduced_chi_square() > 100.0);
+        assert!(comparison.chi_square_p < 1e-6);
+        assert!(comparison.ks_p < 1e-6);
+        assert!(!comparison.consistent(0.05));
+    }
+
+    #[test]
+    fn test_rejects_mismatched_or_empty_spectra() {
+        assert!(compare_spectra(&[1, 2], &[1, 2, 3]).is_none());
+        assert!(compare_spectra(&[0, 0], &[1, 2]).is_none());
+        assert!(compare_spectra(&[], &[]).is_none());
+    }
+
+    #[test]
+    fn test_tail_probabilities() {
+        // Two degrees of freedom: Q(1, x) = exp(-x).
+        assert!((gamma_q(1.0, 1.5) - (-1.5f64).exp()).abs() < 1e-12);
+        assert!((gamma_q(1.0, 0.25) - (-0.25f64).exp()).abs() < 1e-12);
+        // Chi-square with 10 dof at its 5% critical value.
+        assert!((gamma_q(5.0, 18.307 / 2.0) - 0.05).abs() < 1e-4);
+        assert!((kolmogorov_q(1.0) - 0.269_999_7).abs() < 1e-6);
+        assert!((kolmogorov_q(1.358) - 0.05).abs() < 1e-3);
+    }
+}
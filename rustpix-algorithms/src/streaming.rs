@@ -0,0 +1,475 @@
+//! Streaming (online) clustering with bounded memory.
+//!
+//! Hits are consumed one at a time. As in ABS, each hit joins the first open
+//! cluster whose bounding box, grown by the radius, contains it and whose
+//! first hit is at most one temporal window away, or else starts a new
+//! cluster. Once the stream has moved more than a window past a cluster's
+//! first hit no later hit can join it, so the cluster is closed and handed
+//! out right away: only the clusters of the active window are held, however
+//! long the stream runs.
+//!
+//! [`StreamingClustering::cluster`] labels a whole batch in place by TOF like
+//! the other algorithms. [`StreamingClustering::push`] continues a stream
+//! across batches by `ToA` (`timestamp`), which keeps increasing from pulse to
+//! pulse, for live acquisition and inputs larger than memory.
+
+use rustpix_core::clustering::ClusteringError;
+use rustpix_core::soa::{HitBatch, HitRecord};
+use std::collections::VecDeque;
+
+/// Grid cell size (pixels) of the open-cluster index.
+const CELL_SIZE: usize = 32;
+
+/// Configuration for streaming clustering.
+#[derive(Clone, Debug)]
+pub struct StreamingConfig {
+    /// Spatial radius for neighbor detection (pixels).
+    pub radius: f64,
+    /// Temporal correlation window (nanoseconds).
+    pub temporal_window_ns: f64,
+    /// Minimum cluster size to keep.
+    pub min_cluster_size: u16,
+}
+
+impl Default for StreamingConfig {
+    fn default() -> Self {
+        Self {
+            radius: 5.0,
+            temporal_window_ns: 75.0,
+            min_cluster_size: 1,
+        }
+    }
+}
+
+/// Hits of closed clusters, labelled `0..num_clusters` in closing order.
+///
+/// Hits of clusters below the minimum size are left out.
+#[derive(Clone, Debug, Default)]
+pub struct ClusteredHits {
+    /// Hits with their `cluster_id` set.
+    pub hits: HitBatch,
+    /// Number of clusters in `hits`.
+    pub num_clusters: usize,
+}
+
+struct OpenCluster {
+    x_min: u16,
+    x_max: u16,
+    y_min: u16,
+    y_max: u16,
+    start: u32,
+    cell: (usize, usize),
+    /// Hits with their position in the input.
+    hits: Vec<(usize, HitRecord)>,
+}
+
+/// Open clusters and counters carried between [`StreamingClustering::push`]
+/// calls.
+#[derive(Default)]
+pub struct StreamingState {
+    /// Number of hits processed.
+    pub hits_processed: usize,
+    /// Number of clusters closed with at least the minimum size.
+    pub clusters_found: usize,
+    /// Largest number of hits held in open clusters at once.
+    pub peak_open_hits: usize,
+    /// Open clusters, oldest first; `open[k]` has sequence `first_seq + k`.
+    open: VecDeque<OpenCluster>,
+    first_seq: usize,
+    open_hits: usize,
+    /// Latest time seen; clusters more than a window before it are closed.
+    latest: Option<u32>,
+    /// Cluster sequences by insertion cell; entries below `first_seq` are
+    /// stale and skipped.
+    grid: Vec<Vec<usize>>,
+    grid_w: usize,
+    grid_h: usize,
+    closed: Vec<OpenCluster>,
+}
+
+impl StreamingState {
+    /// Hits currently held in open clusters.
+    #[must_use]
+    pub fn open_hits(&self) -> usize {
+        self.open_hits
+    }
+
+    fn reset(&mut self) {
+        self.hits_processed = 0;
+        self.clusters_found = 0;
+        self.peak_open_hits = 0;
+        self.open.clear();
+        self.first_seq = 0;
+        self.open_hits = 0;
+        self.latest = None;
+        for cell in &mut self.grid {
+            cell.clear();
+        }
+        self.closed.clear();
+    }
+
+    /// Grow the cell index to include cell `(cx, cy)`.
+    fn ensure_cell(&mut self, cx: usize, cy: usize) {
+        if cx < self.grid_w && cy < self.grid_h {
+            return;
+        }
+        self.grid_w = self.grid_w.max(cx + 1);
+        self.grid_h = self.grid_h.max(cy + 1);
+        self.grid = vec![Vec::new(); self.grid_w * self.grid_h];
+        for (offset, cluster) in self.open.iter().enumerate() {
+            let (x, y) = cluster.cell;
+            self.grid[y * self.grid_w + x].push(self.first_seq + offset);
+        }
+    }
+
+    /// Move clusters that started more than `window` before the latest time
+    /// to `closed`.
+    fn age_out(&mut self, window: u32) {
+        let Some(latest) = self.latest else {
+            return;
+        };
+        while self
+            .open
+            .front()
+            .is_some_and(|cluster| time_delta(latest, cluster.start) > i64::from(window))
+        {
+            if let Some(cluster) = self.open.pop_front() {
+                self.first_seq += 1;
+                self.open_hits -= cluster.hits.len();
+                self.closed.push(cluster);
+            }
+        }
+    }
+
+    fn close_all(&mut self) {
+        self.first_seq += self.open.len();
+        self.open_hits = 0;
+        self.closed.extend(self.open.drain(..));
+    }
+}
+
+/// Signed difference `a - b` of two wrapping 25 ns times.
+fn time_delta(a: u32, b: u32) -> i64 {
+    let forward = a.wrapping_sub(b);
+    if forward <= u32::MAX / 2 {
+        i64::from(forward)
+    } else {
+        -i64::from(b.wrapping_sub(a))
+    }
+}
+
+/// Streaming clustering implementation.
+pub struct StreamingClustering {
+    config: StreamingConfig,
+}
+
+impl Default for StreamingClustering {
+    fn default() -> Self {
+        Self::new(StreamingConfig::default())
+    }
+}
+
+impl StreamingClustering {
+    /// Create a streaming clustering instance with the provided configuration.
+    #[must_use]
+    pub fn new(config: StreamingConfig) -> Self {
+        Self { config }
+    }
+
+    /// Cluster a batch in place by TOF, assigning `cluster_id` labels, and
+    /// return the cluster count.
+    ///
+    /// The batch is one closed stream: `state` is reset first and every
+    /// cluster is closed at the end.
+    ///
+    /// # Errors
+    /// Returns an error if internal state limits are exceeded.
+    pub fn cluster(
+        &self,
+        batch: &mut HitBatch,
+        state: &mut StreamingState,
+    ) -> Result<usize, ClusteringError> {
+        state.reset();
+        batch.cluster_id.fill(-1);
+        let mut labels = 0i32;
+        for i in 0..batch.len() {
+            let hit = (
+                batch.x[i],
+                batch.y[i],
+                batch.tof[i],
+                batch.tot[i],
+                batch.timestamp[i],
+                batch.chip_id[i],
+            );
+            self.add_hit(state, hit, batch.tof[i]);
+            labels = self.label_closed(state, &mut batch.cluster_id, labels)?;
+        }
+        state.close_all();
+        labels = self.label_closed(state, &mut batch.cluster_id, labels)?;
+        Ok(usize::try_from(labels).unwrap_or(0))
+    }
+
+    /// Continue the stream with `hits` in `ToA` order and return the clusters
+    /// that can no longer grow.
+    ///
+    /// Hits that arrive slightly out of order are still clustered, but a
+    /// cluster is only closed once the clusters opened before it are.
+    ///
+    /// # Errors
+    /// Returns an error if internal state limits are exceeded.
+    pub fn push(
+        &self,
+        hits: &HitBatch,
+        state: &mut StreamingState,
+    ) -> Result<ClusteredHits, ClusteringError> {
+        let mut out = ClusteredHits::default();
+        for i in 0..hits.len() {
+            let hit = (
+                hits.x[i],
+                hits.y[i],
+                hits.tof[i],
+                hits.tot[i],
+                hits.timestamp[i],
+                hits.chip_id[i],
+            );
+            self.add_hit(state, hit, hits.timestamp[i]);
+            self.emit_closed(state, &mut out)?;
+        }
+        Ok(out)
+    }
+
+    /// Close every open cluster at the end of the stream.
+    ///
+    /// # Errors
+    /// Returns an error if internal state limits are exceeded.
+    pub fn flush(&self, state: &mut StreamingState) -> Result<ClusteredHits, ClusteringError> {
+        let mut out = ClusteredHits::default();
+        state.close_all();
+        self.emit_closed(state, &mut out)?;
+        state.latest = None;
+        Ok(out)
+    }
+
+    fn add_hit(&self, state: &mut StreamingState, hit: HitRecord, time: u32) {
+        let (x, y, ..) = hit;
+        let window = self.window_tof();
+        let radius = self.radius_as_i32();
+        if state
+            .latest
+            .is_none_or(|latest| time_delta(time, latest) > 0)
+        {
+            state.latest = Some(time);
+        }
+        state.age_out(window);
+
+        let (cx, cy) = (usize::from(x) / CELL_SIZE, usize::from(y) / CELL_SIZE);
+        state.ensure_cell(cx, cy);
+        let found = Self::find_cluster(state, x, y, time, cx, cy, window, radius);
+        let seq = found.unwrap_or_else(|| {
+            let seq = state.first_seq + state.open.len();
+            let first_seq = state.first_seq;
+            let cell = &mut state.grid[cy * state.grid_w + cx];
+            cell.retain(|&entry| entry >= first_seq);
+            cell.push(seq);
+            state.open.push_back(OpenCluster {
+                x_min: x,
+                x_max: x,
+                y_min: y,
+                y_max: y,
+                start: time,
+                cell: (cx, cy),
+                hits: Vec::new(),
+            });
+            seq
+        });
+
+        let cluster = &mut state.open[seq - state.first_seq];
+        cluster.x_min = cluster.x_min.min(x);
+        cluster.x_max = cluster.x_max.max(x);
+        cluster.y_min = cluster.y_min.min(y);
+        cluster.y_max = cluster.y_max.max(y);
+        cluster.hits.push((state.hits_processed, hit));
+        state.hits_processed += 1;
+        state.open_hits += 1;
+        state.peak_open_hits = state.peak_open_hits.max(state.open_hits);
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn find_cluster(
+        state: &StreamingState,
+        x: u16,
+        y: u16,
+        time: u32,
+        cx: usize,
+        cy: usize,
+        window: u32,
+        radius: i32,
+    ) -> Option<usize> {
+        let (ix, iy) = (i32::from(x), i32::from(y));
+        for ny in cy.saturating_sub(1)..=(cy + 1).min(state.grid_h - 1) {
+            for nx in cx.saturating_sub(1)..=(cx + 1).min(state.grid_w - 1) {
+                for &seq in &state.grid[ny * state.grid_w + nx] {
+                    let Some(cluster) = seq
+                        .checked_sub(state.first_seq)
+                        .and_then(|offset| state.open.get(offset))
+                    else {
+                        continue;
+                    };
+                    if ix >= i32::from(cluster.x_min) - radius
+                        && ix <= i32::from(cluster.x_max) + radius
+                        && iy >= i32::from(cluster.y_min) - radius
+                        && iy <= i32::from(cluster.y_max) + radius
+                        && time_delta(time, cluster.start).unsigned_abs() <= u64::from(window)
+                    {
+                        return Some(seq);
+                    }
+                }
+            }
+        }
+        None
+    }
+
+    /// Label the hits of closed clusters in `cluster_id` by input position.
+    fn label_closed(
+        &self,
+        state: &mut StreamingState,
+        cluster_id: &mut [i32],
+        mut labels: i32,
+    ) -> Result<i32, ClusteringError> {
+        let min_size = usize::from(self.config.min_cluster_size);
+        for cluster in state.closed.drain(..) {
+            if cluster.hits.len() < min_size {
+                continue;
+            }
+            for &(position, _) in &cluster.hits {
+                cluster_id[position] = labels;
+            }
+            labels = labels
+                .checked_add(1)
+                .ok_or_else(|| ClusteringError::StateError("cluster id overflow".to_string()))?;
+            state.clusters_found += 1;
+        }
+        Ok(labels)
+    }
+
+    /// Append the hits of closed clusters to `out`.
+    fn emit_closed(
+        &self,
+        state: &mut StreamingState,
+        out: &mut ClusteredHits,
+    ) -> Result<(), ClusteringError> {
+        let min_size = usize::from(self.config.min_cluster_size);
+        for cluster in state.closed.drain(..) {
+            if cluster.hits.len() < min_size {
+                continue;
+            }
+            let label = i32::try_from(out.num_clusters)
+                .map_err(|_| ClusteringError::StateError("cluster id overflow".to_string()))?;
+            for &(_, hit) in &cluster.hits {
+                out.hits.push(hit);
+                if let Some(id) = out.hits.cluster_id.last_mut() {
+                    *id = label;
+                }
+            }
+            out.num_clusters += 1;
+            state.clusters_found += 1;
+        }
+        Ok(())
+    }
+
+    fn window_tof(&self) -> u32 {
+        let window = (self.config.temporal_window_ns / 25.0).ceil();
+        if window <= 0.0 {
+            return 0;
+        }
+        if window >= f64::from(u32::MAX) {
+            return u32::MAX;
+        }
+        format!("{window:.0}").parse::<u32>().unwrap_or(u32::MAX)
+    }
+
+    fn radius_as_i32(&self) -> i32 {
+        let radius = self.config.radius.ceil();
+        if radius <= 0.0 {
+            return 0;
+        }
+        if radius >= f64::from(i32::MAX) {
+            return i32::MAX;
+        }
+        format!("{radius:.0}").parse::<i32>().unwrap_or(i32::MAX)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_batch_clustering() {
+        let mut batch = HitBatch::default();
+        batch.push((10, 10, 100, 5, 0, 0));
+        batch.push((11, 11, 102, 5, 0, 0));
+        batch.push((50, 50, 101, 5, 0, 0));
+        batch.push((10, 10, 10_000, 5, 0, 0));
+
+        let algo = StreamingClustering::default();
+        let mut state = StreamingState::default();
+        let count = algo.cluster(&mut batch, &mut state).unwrap();
+
+        assert_eq!(count, 3);
+        assert_eq!(batch.cluster_id[0], batch.cluster_id[1]);
+        assert_ne!(batch.cluster_id[0], batch.cluster_id[2]);
+        assert_ne!(batch.cluster_id[0], batch.cluster_id[3]);
+        assert_eq!(state.hits_processed, 4);
+
+        let algo = StreamingClustering::new(StreamingConfig {
+            min_cluster_size: 2,
+            ..StreamingConfig::default()
+        });
+        assert_eq!(algo.cluster(&mut batch, &mut state).unwrap(), 1);
+        assert_eq!(batch.cluster_id, vec![0, 0, -1, -1]);
+    }
+
+    #[test]
+    fn test_push_emits_aged_clusters_and_bounds_memory() {
+        let algo = StreamingClustering::default();
+        let mut state = StreamingState::default();
+        let mut emitted = 0;
+        // 1000 two-hit clusters, one every 100 ticks, across ToA rollover.
+        for k in 0..1000u32 {
+            let toa = (u32::MAX - 50_000).wrapping_add(k * 100);
+            let mut hits = HitBatch::default();
+            hits.push((20, 20, k, 5, toa, 0));
+            hits.push((21, 20, k + 1, 5, toa.wrapping_add(1), 0));
+            let closed = algo.push(&hits, &mut state).unwrap();
+            assert_eq!(closed.hits.len(), 2 * closed.num_clusters);
+            emitted += closed.num_clusters;
+        }
+        assert_eq!(emitted, 999);
+        assert_eq!(state.open_hits(), 2);
+        assert!(state.peak_open_hits <= 4);
+
+        let closed = algo.flush(&mut state).unwrap();
+        assert_eq!(closed.num_clusters, 1);
+        assert_eq!(closed.hits.cluster_id, vec![0, 0]);
+        assert_eq!(state.clusters_found, 1000);
+        assert_eq!(state.open_hits(), 0);
+    }
+
+    #[test]
+    fn test_push_accepts_late_hits() {
+        let algo = StreamingClustering::default();
+        let mut state = StreamingState::default();
+        let mut hits = HitBatch::default();
+        hits.push((30, 30, 0, 5, 1_000, 0));
+        hits.push((60, 60, 0, 5, 1_002, 0));
+        hits.push((31, 30, 0, 5, 999, 0));
+        let closed = algo.push(&hits, &mut state).unwrap();
+        assert_eq!(closed.num_clusters, 0);
+
+        let closed = algo.flush(&mut state).unwrap();
+        assert_eq!(closed.num_clusters, 2);
+        assert_eq!(closed.hits.timestamp, vec![1_000, 999, 1_002]);
+        assert_eq!(closed.hits.cluster_id, vec![0, 0, 1]);
+    }
+}
@@ -0,0 +1,259 @@
+//! Synthetic hits for benchmarks and tests.
+//!
+//! [`generate`] builds one pulse of hits without a data file: neutron events
+//! arrive as a Poisson process at `event_rate_hz` over the pulse, and each
+//! leaves a compact blob of touching pixels (square rings around a random
+//! pixel) whose size is drawn from [`ClusterSizes`], with `ToT` falling off
+//! from the center and TOF spread over two ticks, well inside the default
+//! 75 ns window. A `noise_fraction` of the hits are single hits at random
+//! pixels and times. The generator is seeded, so a config always gives the
+//! same hits.
+
+use rustpix_core::soa::HitBatch;
+use rustpix_core::SplitMix64;
+
+/// Distribution of the number of hits per event.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ClusterSizes {
+    /// Every event has this many hits.
+    Fixed(u16),
+    /// Uniform between `min` and `max` hits (inclusive).
+    Uniform {
+        /// Smallest size.
+        min: u16,
+        /// Largest size.
+        max: u16,
+    },
+    /// Poisson with this mean, at least one hit.
+    Poisson {
+        /// Mean size.
+        mean: f64,
+    },
+}
+
+/// Configuration of a synthetic pulse.
+#[derive(Clone, Debug)]
+pub struct SyntheticConfig {
+    /// Length of the pulse (nanoseconds); TOF runs from 0 to this.
+    pub pulse_length_ns: f64,
+    /// Mean neutron event rate (events per second).
+    pub event_rate_hz: f64,
+    /// Hits per event.
+    pub cluster_sizes: ClusterSizes,
+    /// Fraction of all hits that are single noise hits (0 to below 1).
+    pub noise_fraction: f64,
+    /// Detector width (pixels).
+    pub width: u16,
+    /// Detector height (pixels).
+    pub height: u16,
+    /// Random seed.
+    pub seed: u64,
+}
+
+impl Default for SyntheticConfig {
+    /// One 60 Hz pulse at 1 MHz events of about 4 hits, 5% noise, on a
+    /// 512x512 detector.
+    fn default() -> Self {
+        Self {
+            pulse_length_ns: 1e9 / 60.0,
+            event_rate_hz: 1e6,
+            cluster_sizes: ClusterSizes::Poisson { mean: 4.0 },
+            noise_fraction: 0.05,
+            width: 512,
+            height: 512,
+            seed: 0x5EED,
+        }
+    }
+}
+
+/// A synthetic pulse and what went into it.
+#[derive(Clone, Debug, Default)]
+pub struct SyntheticHits {
+    /// Hits sorted by TOF.
+    pub hits: HitBatch,
+    /// Number of neutron events.
+    pub events: usize,
+    /// Number of noise hits.
+    pub noise_hits: usize,
+}
+
+/// Generate one pulse of hits; see the module docs.
+///
+/// TOF and timestamps are in 25 ns ticks from the pulse start, and the chip
+/// is the 256-pixel quadrant of the hit.
+#[must_use]
+pub fn generate(config: &SyntheticConfig) -> SyntheticHits {
+    let mut rng = SplitMix64::new(config.seed);
+    let width = config.width.max(1);
+    let height = config.height.max(1);
+    let length_ticks = (config.pulse_length_ns / 25.0).max(1.0);
+    let mean_gap_ticks = if config.event_rate_hz > 0.0 {
+        1e9 / 25.0 / config.event_rate_hz
+    } else {
+        f64::INFINITY
+    };
+
+    let mut hits = HitBatch::default();
+    let mut events = 0;
+    let mut time = rng.exponential(mean_gap_ticks);
+    while time < length_ticks {
+        let size = config.cluster_sizes.sample(&mut rng);
+        let center = (rng.below(width), rng.below(height));
+        let tof = ticks(time);
+        for (ring, (dx, dy)) in blob_offsets().take(usize::from(size)) {
+            let x = offset(center.0, dx, width);
+            let y = offset(center.1, dy, height);
+            let jitter = u32::from(rng.below(3u16));
+            let tot = (200 / (1 + ring)).max(1) + rng.below(16);
+            push(&mut hits, x, y, tof.saturating_add(jitter), tot);
+        }
+        events += 1;
+        time += rng.exponential(mean_gap_ticks);
+    }
+
+    let noise_fraction = config.noise_fraction.clamp(0.0, 0.99);
+    #[allow(clippy::cast_precision_loss)]
+    let noise_hits = round_to_usize(hits.len() as f64 * noise_fraction / (1.0 - noise_fraction));
+    for _ in 0..noise_hits {
+        let tof = ticks(rng.unit() * length_ticks);
+        let tot = 1 + rng.below(64);
+        push(&mut hits, rng.below(width), rng.below(height), tof, tot);
+    }
+
+    hits.sort_by_tof();
+    SyntheticHits {
+        hits,
+        events,
+        noise_hits,
+    }
+}
+
+impl ClusterSizes {
+    fn sample(self, rng: &mut SplitMix64) -> u16 {
+        match self {
+            Self::Fixed(size) => size.max(1),
+            Self::Uniform { min, max } => {
+                let (low, high) = (min.min(max).max(1), max.max(min).max(1));
+                low + rng.below(high - low + 1)
+            }
+            Self::Poisson { mean } => {
+                // Knuth's method; fine for the small means of real clusters.
+                let limit = (-mean.max(0.0)).exp();
+                let mut size = 0u16;
+                let mut product = rng.unit();
+                while product > limit && size < u16::MAX {
+                    size += 1;
+                    product *= rng.unit();
+                }
+                size.max(1)
+            }
+        }
+    }
+}
+
+/// Pixel offsets in growing square rings around the center, with their
+/// ring; every cell touches the ring inside it, so any prefix is a blob.
+fn blob_offsets() -> impl Iterator<Item = (u16, (i32, i32))> {
+    (0..=i32::from(u8::MAX)).flat_map(|ring| {
+        let side =
+            (-ring..=ring).flat_map(move |a| [(a, -ring), (ring, a), (-a, ring), (-ring, -a)]);
+        let cells: Vec<_> = if ring == 0 {
+            vec![(0, 0)]
+        } else {
+            let mut cells: Vec<_> = side.collect();
+            cells.sort_unstable();
+            cells.dedup();
+            cells.sort_by_key(|&(dx, dy)| dx.abs() + dy.abs());
+            cells
+        };
+        let ring = u16::try_from(ring).unwrap_or(u16::MAX);
+        cells.into_iter().map(move |cell| (ring, cell))
+    })
+}
+
+fn offset(center: u16, delta: i32, size: u16) -> u16 {
+    let value = (i32::from(center) + delta).clamp(0, i32::from(size) - 1);
+    u16::try_from(value).unwrap_or(0)
+}
+
+fn push(hits: &mut HitBatch, x: u16, y: u16, tof: u32, tot: u16) {
+    let chip = u8::from(x >= 256) + 2 * u8::from(y >= 256);
+    hits.push((x, y, tof, tot, tof, chip));
+}
+
+fn ticks(time: f64) -> u32 {
+    u32::try_from(round_to_usize(time.floor())).unwrap_or(u32::MAX)
+}
+
+fn round_to_usize(value: f64) -> usize {
+    if value <= 0.0 {
+        return 0;
+    }
+    format!("{value:.0}").parse().unwrap_or(usize::MAX)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{cluster_batch, AlgorithmParams, ClusteringAlgorithm, ClusteringConfig};
+
+    #[test]
+    #[allow(clippy::cast_precision_loss)]
+    fn test_generate_is_seeded_and_sorted() {
+        let config = SyntheticConfig::default();
+        let pulse = generate(&config);
+        let again = generate(&config);
+        assert_eq!(pulse.hits.tof, again.hits.tof);
+        assert_eq!(pulse.hits.x, again.hits.x);
+        assert!(pulse.hits.tof.is_sorted());
+
+        // 1 MHz over 1/60 s.
+        assert!((16_000..17_400).contains(&pulse.events), "{}", pulse.events);
+        let noise = pulse.noise_hits as f64 / pulse.hits.len() as f64;
+        assert!((noise - 0.05).abs() < 0.001, "{noise}");
+        let mean_size = (pulse.hits.len() - pulse.noise_hits) as f64 / pulse.events as f64;
+        assert!((mean_size - 4.0).abs() < 0.1, "{mean_size}");
+
+        let other = generate(&SyntheticConfig { seed: 1, ..config });
+        assert_ne!(pulse.hits.x, other.hits.x);
+    }
+
+    #[test]
+    fn test_events_are_touching_blobs() {
+        let config = SyntheticConfig {
+            event_rate_hz: 20_000.0,
+            cluster_sizes: ClusterSizes::Uniform { min: 1, max: 12 },
+            noise_fraction: 0.0,
+            ..SyntheticConfig::default()
+        };
+        let mut pulse = generate(&config);
+        assert!(pulse.events > 200);
+        assert!(pulse.hits.x.iter().all(|&x| x < 512));
+
+        let clusters = cluster_batch(
+            &mut pulse.hits,
+            ClusteringAlgorithm::ConnectedComponents,
+            &ClusteringConfig::default(),
+            &AlgorithmParams::default(),
+        )
+        .unwrap();
+        assert_eq!(clusters, pulse.events);
+    }
+
+    #[test]
+    fn test_cluster_sizes() {
+        let mut rng = SplitMix64::new(7);
+        assert_eq!(ClusterSizes::Fixed(0).sample(&mut rng), 1);
+        for _ in 0..1_000 {
+            let size = ClusterSizes::Uniform { min: 3, max: 5 }.sample(&mut rng);
+            assert!((3..=5).contains(&size));
+        }
+        let first: Vec<_> = blob_offsets().take(9).map(|(ring, _)| ring).collect();
+        assert_eq!(first, vec![0, 1, 1, 1, 1, 1, 1, 1, 1]);
+        let cells: Vec<_> = blob_offsets().take(25).map(|(_, cell)| cell).collect();
+        let mut unique = cells.clone();
+        unique.sort_unstable();
+        unique.dedup();
+        assert_eq!(unique.len(), 25);
+    }
+}
@@ -0,0 +1,325 @@
+//! Region rate alarms for the `tui` command.
+//!
+//! Each alarm watches a rectangle of the detector (in pixels) and compares
+//! its neutron rate against a lower and/or upper bound, e.g. to catch the
+//! beam spot dimming. Rates are measured in detector time from the pulse
+//! TDC timestamps, over fixed windows, so they reflect the beam rather than
+//! how fast the file is processed. An alarm fires when a window's rate is out
+//! of bounds and clears on the first window back within them; both
+//! transitions are reported as events.
+
+use rustpix_core::neutron::NeutronBatch;
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
+
+/// Length of one TDC tick in seconds.
+const TICK_SECONDS: f64 = 25e-9;
+
+/// A detector region with rate bounds, parsed from
+/// `NAME:X0,Y0,X1,Y1:MIN[:MAX]`.
+///
+/// The region covers `X0 <= x < X1`, `Y0 <= y < Y1` in detector pixels.
+/// Either bound may be left empty (`beam:0,0,512,512::2e6`).
+#[derive(Debug, Clone, PartialEq)]
+pub struct RateAlarm {
+    /// Name shown in the dashboard and log.
+    pub name: String,
+    /// Region as `[x0, y0, x1, y1]` in pixels.
+    pub region: [f64; 4],
+    /// Fire when the rate drops below this (neutrons/s).
+    pub min_rate: Option<f64>,
+    /// Fire when the rate rises above this (neutrons/s).
+    pub max_rate: Option<f64>,
+}
+
+impl RateAlarm {
+    fn contains(&self, x: f64, y: f64) -> bool {
+        let [x0, y0, x1, y1] = self.region;
+        (x0..x1).contains(&x) && (y0..y1).contains(&y)
+    }
+
+    /// Why `rate` is out of bounds, or `None` if it is within them.
+    fn violation(&self, rate: f64) -> Option<String> {
+        match (self.min_rate, self.max_rate) {
+            (Some(min), _) if rate < min => Some(format!("below {min}/s")),
+            (_, Some(max)) if rate > max => Some(format!("above {max}/s")),
+            _ => None,
+        }
+    }
+}
+
+impl FromStr for RateAlarm {
+    type Err = String;
+
+    fn from_str(spec: &str) -> Result<Self, Self::Err> {
+        let parts: Vec<&str> = spec.split(':').collect();
+        if !(3..=4).contains(&parts.len()) || parts[0].is_empty() {
+            return Err(format!(
+                "invalid alarm '{spec}', expected NAME:X0,Y0,X1,Y1:MIN[:MAX]"
+            ));
+        }
+        let coords: Vec<f64> = parts[1]
+            .split(',')
+            .map(|value| value.trim().parse::<f64>())
+            .collect::<Result<_, _>>()
+            .map_err(|err| format!("invalid alarm region '{}': {err}", parts[1]))?;
+        let [x0, y0, x1, y1] = coords[..] else {
+            return Err(format!(
+                "invalid alarm region '{}', expected X0,Y0,X1,Y1",
+                parts[1]
+            ));
+        };
+        if x1 <= x0 || y1 <= y0 {
+            return Err(format!("alarm region '{}' is empty", parts[1]));
+        }
+        let bound = |text: &str| -> Result<Option<f64>, String> {
+            if text.trim().is_empty() {
+                return Ok(None);
+            }
+            text.trim()
+                .parse::<f64>()
+                .map(Some)
+                .map_err(|err| format!("invalid alarm rate '{text}': {err}"))
+        };
+        let min_rate = bound(parts[2])?;
+        let max_rate = parts.get(3).map_or(Ok(None), |text| bound(text))?;
+        if min_rate.is_none() && max_rate.is_none() {
+            return Err(format!("alarm '{}' needs a MIN or MAX rate", parts[0]));
+        }
+        Ok(Self {
+            name: parts[0].to_string(),
+            region: [x0, y0, x1, y1],
+            min_rate,
+            max_rate,
+        })
+    }
+}
+
+/// Current state of one alarm.
+#[derive(Debug, Clone)]
+pub struct AlarmStatus {
+    /// The alarm definition.
+    pub alarm: RateAlarm,
+    /// Rate over the last completed window, if any.
+    pub rate: Option<f64>,
+    /// Whether the alarm is firing.
+    pub firing: bool,
+}
+
+/// An alarm firing or clearing.
+#[derive(Debug, Clone)]
+pub struct AlarmEvent {
+    /// Alarm name.
+    pub name: String,
+    /// End of the window, in seconds since the first pulse of the file.
+    pub detector_time_s: f64,
+    /// Rate over the window (neutrons/s).
+    pub rate: f64,
+    /// `Some(reason)` when the alarm fired, `None` when it cleared.
+    pub reason: Option<String>,
+}
+
+impl fmt::Display for AlarmEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.reason {
+            Some(reason) => write!(
+                f,
+                "ALARM {} at {:.3} s: {:.1}/s {reason}",
+                self.name, self.detector_time_s, self.rate
+            ),
+            None => write!(
+                f,
+                "CLEAR {} at {:.3} s: {:.1}/s",
+                self.name, self.detector_time_s, self.rate
+            ),
+        }
+    }
+}
+
+/// Counts neutrons per alarm region over detector-time windows.
+pub struct AlarmMonitor {
+    status: Vec<AlarmStatus>,
+    window_25ns: u64,
+    first_pulse: Option<u64>,
+    window_start: Option<u64>,
+    counts: Vec<u64>,
+}
+
+impl AlarmMonitor {
+    /// Monitor `alarms` over windows of `window` detector time.
+    #[must_use]
+    pub fn new(alarms: Vec<RateAlarm>, window: Duration) -> Self {
+        let window_25ns = u64::try_from(window.as_nanos() / 25)
+            .unwrap_or(u64::MAX)
+            .max(1);
+        Self {
+            counts: vec![0; alarms.len()],
+            status: alarms
+                .into_iter()
+                .map(|alarm| AlarmStatus {
+                    alarm,
+                    rate: None,
+                    firing: false,
+                })
+                .collect(),
+            window_25ns,
+            first_pulse: None,
+            window_start: None,
+        }
+    }
+
+    /// Returns true if no alarms are configured.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.status.is_empty()
+    }
+
+    /// Current state of every alarm.
+    #[must_use]
+    pub fn status(&self) -> &[AlarmStatus] {
+        &self.status
+    }
+
+    /// Start over at the beginning of a new file, whose TDC timestamps are
+    /// unrelated to the previous one. A partial window is dropped; alarm
+    /// states are kept.
+    pub fn start_file(&mut self) {
+        self.first_pulse = None;
+        self.window_start = None;
+        self.counts.fill(0);
+    }
+
+    /// Add the neutrons of the pulse at `tdc_25ns`, given their coordinates
+    /// are scaled by `scale` (super-resolution factor).
+    ///
+    /// When the pulse lies past the current window, that window's rates are
+    /// evaluated first and any alarm transitions are returned. A gap with no
+    /// pulses at all therefore lowers the rate of the window it falls in.
+    pub fn observe(
+        &mut self,
+        tdc_25ns: u64,
+        neutrons: &NeutronBatch,
+        scale: f64,
+    ) -> Vec<AlarmEvent> {
+        if self.status.is_empty() {
+            return Vec::new();
+        }
+        let first = *self.first_pulse.get_or_insert(tdc_25ns);
+        let mut events = Vec::new();
+        match self.window_start {
+            Some(start) if tdc_25ns < start => self.window_start = Some(tdc_25ns),
+            Some(start) if tdc_25ns - start >= self.window_25ns => {
+                let elapsed = tdc_25ns - start;
+                events = self.evaluate(elapsed, tdc_25ns.saturating_sub(first));
+                self.window_start = Some(tdc_25ns);
+            }
+            Some(_) => {}
+            None => self.window_start = Some(tdc_25ns),
+        }
+
+        for (&x, &y) in neutrons.x.iter().zip(&neutrons.y) {
+            let (x, y) = (x / scale, y / scale);
+            for (count, status) in self.counts.iter_mut().zip(&self.status) {
+                if status.alarm.contains(x, y) {
+                    *count += 1;
+                }
+            }
+        }
+        events
+    }
+
+    fn evaluate(&mut self, elapsed_25ns: u64, since_first_25ns: u64) -> Vec<AlarmEvent> {
+        #[allow(clippy::cast_precision_loss)]
+        let seconds = elapsed_25ns as f64 * TICK_SECONDS;
+        #[allow(clippy::cast_precision_loss)]
+        let detector_time_s = since_first_25ns as f64 * TICK_SECONDS;
+        let mut events = Vec::new();
+        for (count, status) in self.counts.iter_mut().zip(&mut self.status) {
+            #[allow(clippy::cast_precision_loss)]
+            let rate = *count as f64 / seconds;
+            *count = 0;
+            status.rate = Some(rate);
+            let violation = status.alarm.violation(rate);
+            if violation.is_some() != status.firing {
+                status.firing = violation.is_some();
+                events.push(AlarmEvent {
+                    name: status.alarm.name.clone(),
+                    detector_time_s,
+                    rate,
+                    reason: violation,
+                });
+            }
+        }
+        events
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn neutrons_at(points: &[(f64, f64)]) -> NeutronBatch {
+        let mut batch = NeutronBatch::default();
+        for &(x, y) in points {
+            batch.x.push(x);
+            batch.y.push(y);
+        }
+        batch
+    }
+
+    #[test]
+    #[allow(clippy::float_cmp)]
+    fn test_parse_alarm_spec() {
+        let alarm: RateAlarm = "beam:100,100,200,150:5000".parse().unwrap();
+        assert_eq!(alarm.name, "beam");
+        assert_eq!(alarm.region, [100.0, 100.0, 200.0, 150.0]);
+        assert_eq!(alarm.min_rate, Some(5000.0));
+        assert_eq!(alarm.max_rate, None);
+
+        let alarm: RateAlarm = "edge:0,0,10,10::2e6".parse().unwrap();
+        assert_eq!((alarm.min_rate, alarm.max_rate), (None, Some(2e6)));
+
+        assert!("beam:0,0,10:5".parse::<RateAlarm>().is_err());
+        assert!("beam:10,0,0,10:5".parse::<RateAlarm>().is_err());
+        assert!("beam:0,0,10,10:".parse::<RateAlarm>().is_err());
+    }
+
+    #[test]
+    fn test_monitor_fires_and_clears_on_detector_time() {
+        let alarm: RateAlarm = "spot:0,0,10,10:20".parse().unwrap();
+        // 0.5 s windows of 40 pulses each.
+        let mut monitor = AlarmMonitor::new(vec![alarm], Duration::from_millis(500));
+        let pulse = 500_000u64; // 12.5 ms in 25 ns ticks
+        let inside = neutrons_at(&[(5.0, 5.0), (50.0, 5.0)]);
+        let empty = NeutronBatch::default();
+
+        // First window: one neutron per pulse in the region (80/s).
+        let mut events = Vec::new();
+        for i in 0..40 {
+            events.extend(monitor.observe(i * pulse, &inside, 1.0));
+        }
+        // Second window: beam spot gone.
+        for i in 40..80 {
+            events.extend(monitor.observe(i * pulse, &empty, 1.0));
+        }
+        assert!(events.is_empty());
+        assert!(!monitor.status()[0].firing);
+        assert!((monitor.status()[0].rate.unwrap() - 80.0).abs() < 1e-9);
+
+        // Third window starts: the empty window fires the alarm.
+        let events = monitor.observe(80 * pulse, &inside, 1.0);
+        assert_eq!(events.len(), 1);
+        assert!(events[0].reason.is_some());
+        assert!((events[0].detector_time_s - 1.0).abs() < 1e-9);
+        assert!(monitor.status()[0].firing);
+
+        for i in 81..120 {
+            monitor.observe(i * pulse, &inside, 1.0);
+        }
+        let events = monitor.observe(120 * pulse, &inside, 1.0);
+        assert_eq!(events.len(), 1);
+        assert!(events[0].reason.is_none());
+        assert!(!monitor.status()[0].firing);
+    }
+}
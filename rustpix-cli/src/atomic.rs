@@ -0,0 +1,162 @@
+//! Atomic output writes for the `process` command.
+//!
+//! Outputs are written to a hidden `.<name>.partial` file next to their final
+//! path and renamed into place only once processing has succeeded. A rename
+//! within one directory is atomic, so downstream pipelines see either the
+//! previous file or the complete new one, never a truncated output of an
+//! interrupted run. Partial files of a failed run are removed, unless the run
+//! is checkpointed for resuming; those of a killed run are left behind under
+//! their hidden name.
+
+use crate::{CliError, Result};
+use clap::ValueEnum;
+use std::path::{Path, PathBuf};
+
+/// What to do when an output already exists.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum OverwritePolicy {
+    /// Replace existing outputs once the new ones are complete
+    #[default]
+    Replace,
+    /// Fail before processing if an output already exists
+    Error,
+    /// Skip the input if an output already exists
+    Skip,
+}
+
+impl OverwritePolicy {
+    /// Check `outputs` against the policy before processing.
+    ///
+    /// Returns `false` if the input should be skipped.
+    ///
+    /// # Errors
+    /// Returns an error under [`Self::Error`] if an output exists.
+    pub fn check(self, outputs: &[&Path]) -> Result<bool> {
+        let Some(existing) = outputs.iter().find(|path| path.exists()) else {
+            return Ok(true);
+        };
+        match self {
+            Self::Replace => Ok(true),
+            Self::Error => Err(CliError::InvalidInput(format!(
+                "{} already exists (use --overwrite replace to replace it)",
+                existing.display()
+            ))),
+            Self::Skip => {
+                println!("Skipping: {} already exists", existing.display());
+                Ok(false)
+            }
+        }
+    }
+}
+
+/// The hidden file `path` is written to until it is complete.
+#[must_use]
+pub fn partial_path(path: &Path) -> PathBuf {
+    let name = path
+        .file_name()
+        .map_or_else(|| "output".into(), |name| name.to_string_lossy());
+    path.with_file_name(format!(".{name}.partial"))
+}
+
+/// Outputs being written under their partial paths.
+///
+/// Dropping the set without [`Self::commit`] removes the partial files.
+#[derive(Debug, Default)]
+pub struct PendingOutputs {
+    paths: Vec<PathBuf>,
+    /// Outputs whose partial files survive a failed run.
+    kept: Vec<PathBuf>,
+}
+
+impl PendingOutputs {
+    /// Register `path` as an output and return the path to write it to.
+    pub fn stage(&mut self, path: &Path) -> PathBuf {
+        self.paths.push(path.to_path_buf());
+        partial_path(path)
+    }
+
+    /// Like [`Self::stage`], but the partial file is left in place if the
+    /// outputs are dropped without a commit, so a checkpointed run can be
+    /// resumed.
+    pub fn stage_resumable(&mut self, path: &Path) -> PathBuf {
+        self.kept.push(path.to_path_buf());
+        self.stage(path)
+    }
+
+    /// Leave every staged output under its partial path, for a run that was
+    /// stopped before it finished.
+    pub fn abandon(mut self) {
+        self.paths.clear();
+    }
+
+    /// Rename every staged output into place.
+    ///
+    /// # Errors
+    /// Returns an error if a rename fails; outputs not yet renamed are
+    /// removed.
+    pub fn commit(mut self) -> Result<()> {
+        while let Some(path) = self.paths.first() {
+            std::fs::rename(partial_path(path), path)?;
+            self.paths.remove(0);
+        }
+        Ok(())
+    }
+}
+
+impl Drop for PendingOutputs {
+    fn drop(&mut self) {
+        for path in self.paths.iter().filter(|path| !self.kept.contains(path)) {
+            let _ = std::fs::remove_file(partial_path(path));
+        }
+    }
+}
+
+/// Write `contents` to `path` through a partial file.
+///
+/// # Errors
+/// Returns an error if the file cannot be written or renamed.
+pub fn write(path: &Path, contents: impl AsRef<[u8]>) -> Result<()> {
+    let mut pending = PendingOutputs::default();
+    std::fs::write(pending.stage(path), contents)?;
+    pending.commit()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_outputs_appear_only_on_commit() {
+        let dir = tempfile::tempdir().unwrap();
+        let output = dir.path().join("neutrons.csv");
+        std::fs::write(&output, "old").unwrap();
+
+        let mut pending = PendingOutputs::default();
+        let partial = pending.stage(&output);
+        assert_eq!(partial, dir.path().join(".neutrons.csv.partial"));
+        std::fs::write(&partial, "new").unwrap();
+        assert_eq!(std::fs::read_to_string(&output).unwrap(), "old");
+        pending.commit().unwrap();
+        assert_eq!(std::fs::read_to_string(&output).unwrap(), "new");
+        assert!(!partial.exists());
+
+        let mut failed = PendingOutputs::default();
+        std::fs::write(failed.stage(&output), "truncated").unwrap();
+        drop(failed);
+        assert_eq!(std::fs::read_to_string(&output).unwrap(), "new");
+        assert!(!partial.exists());
+
+        let mut stopped = PendingOutputs::default();
+        std::fs::write(stopped.stage(&output), "stopped").unwrap();
+        stopped.abandon();
+        assert_eq!(std::fs::read_to_string(&partial).unwrap(), "stopped");
+        assert_eq!(std::fs::read_to_string(&output).unwrap(), "new");
+
+        assert!(OverwritePolicy::Replace.check(&[&output]).unwrap());
+        assert!(OverwritePolicy::Error.check(&[&output]).is_err());
+        assert!(!OverwritePolicy::Skip.check(&[&output]).unwrap());
+        assert!(OverwritePolicy::Error
+            .check(&[&dir.path().join("missing.csv")])
+            .unwrap());
+    }
+}
@@ -0,0 +1,340 @@
+//! Checkpoints of the `process` command, so long jobs can be resumed.
+//!
+//! A checkpoint is a JSON file recording how far a run has got: the inputs
+//! already complete with their totals, the position in the current input
+//! (the offset of the section decoding resumes from, the TDC state of each
+//! chip there and the last pulse written), and the length of the partial
+//! output at that point. It is saved after each input and every interval
+//! within one, once the output has been flushed, and replaced atomically.
+//!
+//! Pulses are clustered independently, so no clustering state crosses a
+//! checkpoint. A resumed run truncates the partial output to the recorded
+//! length, skips the completed inputs and continues the current one after
+//! its last written pulse, so the final output matches an uninterrupted run.
+//! Merged runs and hit lists are resumed from the start of the input.
+
+use crate::checksum::to_hex;
+use crate::runs::Input;
+use crate::timing::FileTiming;
+use crate::{atomic, CliError, Result};
+use rustpix_core::classification::ClassCounts;
+use rustpix_io::StreamPosition;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+/// Version of the checkpoint layout.
+const VERSION: u32 = 1;
+
+#[cfg(test)]
+thread_local! {
+    /// Saves a test lets through before the next one fails as if the run
+    /// had been killed.
+    static SAVES_BEFORE_INTERRUPT: std::cell::Cell<Option<usize>> =
+        const { std::cell::Cell::new(None) };
+}
+
+/// Make the checkpoint save after the next `saves` fail, or stop doing so
+/// with `None`, to interrupt a run in tests.
+#[cfg(test)]
+pub fn interrupt_after_saves(saves: Option<usize>) {
+    SAVES_BEFORE_INTERRUPT.with(|left| left.set(saves));
+}
+
+/// Where checkpoints are saved and whether to resume from one.
+#[derive(Debug, Clone)]
+pub struct CheckpointOptions {
+    /// Checkpoint file.
+    pub path: PathBuf,
+    /// Time between checkpoints within an input.
+    pub interval: Duration,
+    /// Continue the run recorded in `path` instead of starting over.
+    pub resume: bool,
+}
+
+/// Totals of an input, kept so a resumed run reports the whole run.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct InputTotals {
+    /// Input path as given on the command line.
+    pub path: String,
+    /// Input size in bytes.
+    pub bytes: usize,
+    /// Hits processed.
+    pub hits: usize,
+    /// Neutrons extracted.
+    pub neutrons: usize,
+    /// Extracted events per class, when a classifier was set.
+    pub classes: Option<ClassCounts>,
+}
+
+impl InputTotals {
+    fn from_timing(file: &FileTiming) -> Self {
+        Self {
+            path: file.path.clone(),
+            bytes: file.bytes,
+            hits: file.hits,
+            neutrons: file.neutrons,
+            classes: file.classes,
+        }
+    }
+
+    /// Timing entry of an input completed before the checkpoint.
+    pub fn to_timing(&self) -> FileTiming {
+        FileTiming {
+            path: self.path.clone(),
+            bytes: self.bytes,
+            hits: self.hits,
+            neutrons: self.neutrons,
+            classes: self.classes,
+            ..FileTiming::default()
+        }
+    }
+}
+
+/// Progress through the input being processed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InputProgress {
+    /// Where its pulse stream resumes.
+    pub position: StreamPosition,
+    /// Totals of the pulses written so far.
+    pub totals: InputTotals,
+}
+
+/// State of a run as saved in a checkpoint file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Checkpoint {
+    /// Layout version.
+    pub version: u32,
+    /// Digest of the inputs, output and settings of the run.
+    pub fingerprint: String,
+    /// Inputs completed, in order.
+    pub completed: Vec<InputTotals>,
+    /// Progress through the next input, if any of it was written.
+    pub current: Option<InputProgress>,
+    /// Bytes of the partial neutron output that belong to the run.
+    pub output_bytes: u64,
+    /// Whether the CSV header is among those bytes.
+    pub wrote_header: bool,
+}
+
+/// Digest identifying a run, from its input files (paths and sizes), its
+/// output and a description of its settings.
+///
+/// # Errors
+/// Returns an error if an input cannot be read.
+pub fn fingerprint(inputs: &[Input], output: &Path, settings: &str) -> Result<String> {
+    let mut hasher = Sha256::new();
+    for input in inputs {
+        let files = match input {
+            Input::File(path) => std::slice::from_ref(path),
+            Input::Run { files, .. } => files.as_slice(),
+        };
+        for path in files {
+            let bytes = std::fs::metadata(path)?.len();
+            hasher.update(format!("{}\0{bytes}\0", path.display()));
+        }
+        hasher.update("\n");
+    }
+    hasher.update(format!("{}\0{settings}", output.display()));
+    Ok(to_hex(&hasher.finalize()))
+}
+
+/// Saves checkpoints of a run.
+pub struct Checkpointer {
+    path: PathBuf,
+    interval: Duration,
+    last_save: Instant,
+    resumed: bool,
+    state: Checkpoint,
+}
+
+impl Checkpointer {
+    /// Start checkpointing a run, loading the checkpoint to resume if
+    /// requested.
+    ///
+    /// # Errors
+    /// Returns an error if the checkpoint to resume cannot be read or was
+    /// written by a different run.
+    pub fn start(options: &CheckpointOptions, fingerprint: String) -> Result<Self> {
+        let state = if options.resume {
+            let invalid = |err: &dyn std::fmt::Display| {
+                CliError::InvalidInput(format!(
+                    "{}: cannot read checkpoint: {err}",
+                    options.path.display()
+                ))
+            };
+            let text = std::fs::read_to_string(&options.path).map_err(|err| invalid(&err))?;
+            let state: Checkpoint = serde_json::from_str(&text).map_err(|err| invalid(&err))?;
+            if state.version != VERSION {
+                return Err(CliError::InvalidInput(format!(
+                    "{}: unsupported checkpoint version {}",
+                    options.path.display(),
+                    state.version
+                )));
+            }
+            if state.fingerprint != fingerprint {
+                return Err(CliError::InvalidInput(format!(
+                    "{}: checkpoint was written by a different run; resume with the same inputs, output and options",
+                    options.path.display()
+                )));
+            }
+            state
+        } else {
+            Checkpoint {
+                version: VERSION,
+                fingerprint,
+                completed: Vec::new(),
+                current: None,
+                output_bytes: 0,
+                wrote_header: false,
+            }
+        };
+        Ok(Self {
+            path: options.path.clone(),
+            interval: options.interval,
+            last_save: Instant::now(),
+            resumed: options.resume,
+            state,
+        })
+    }
+
+    /// The checkpointed state.
+    pub fn state(&self) -> &Checkpoint {
+        &self.state
+    }
+
+    /// Whether the run continues an earlier one.
+    pub fn is_resumed(&self) -> bool {
+        self.resumed
+    }
+
+    /// Totals of input `index` if it was completed before the checkpoint.
+    pub fn completed(&self, index: usize) -> Option<&InputTotals> {
+        self.state.completed.get(index)
+    }
+
+    /// Whether a checkpoint is due within the current input.
+    pub fn due(&self) -> bool {
+        self.last_save.elapsed() >= self.interval
+    }
+
+    /// Save progress through the current input.
+    ///
+    /// # Errors
+    /// Returns an error if the checkpoint cannot be written.
+    pub fn save_progress(
+        &mut self,
+        position: StreamPosition,
+        file: &FileTiming,
+        output_bytes: u64,
+        wrote_header: bool,
+    ) -> Result<()> {
+        self.state.current = Some(InputProgress {
+            position,
+            totals: InputTotals::from_timing(file),
+        });
+        self.save(output_bytes, wrote_header)
+    }
+
+    /// Record the current input as complete and save.
+    ///
+    /// # Errors
+    /// Returns an error if the checkpoint cannot be written.
+    pub fn finish_input(
+        &mut self,
+        file: &FileTiming,
+        output_bytes: u64,
+        wrote_header: bool,
+    ) -> Result<()> {
+        self.state.completed.push(InputTotals::from_timing(file));
+        self.state.current = None;
+        self.save(output_bytes, wrote_header)
+    }
+
+    /// Remove the checkpoint once the run has completed.
+    ///
+    /// # Errors
+    /// Returns an error if the file exists but cannot be removed.
+    pub fn remove(self) -> Result<()> {
+        match std::fs::remove_file(&self.path) {
+            Err(err) if err.kind() != std::io::ErrorKind::NotFound => Err(err.into()),
+            _ => Ok(()),
+        }
+    }
+
+    fn save(&mut self, output_bytes: u64, wrote_header: bool) -> Result<()> {
+        #[cfg(test)]
+        SAVES_BEFORE_INTERRUPT.with(|left| match left.get() {
+            Some(0) => Err(CliError::InvalidInput("run interrupted".to_string())),
+            saves => {
+                left.set(saves.map(|saves| saves - 1));
+                Ok(())
+            }
+        })?;
+        self.state.output_bytes = output_bytes;
+        self.state.wrote_header = wrote_header;
+        atomic::write(&self.path, serde_json::to_string_pretty(&self.state)?)?;
+        self.last_save = Instant::now();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resume_checks_fingerprint() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("run.tpx3");
+        std::fs::write(&input, [0u8; 16]).unwrap();
+        let inputs = [Input::File(input.clone())];
+        let output = dir.path().join("neutrons.csv");
+        let fingerprint = fingerprint(&inputs, &output, "radius 5").unwrap();
+        assert_ne!(
+            fingerprint,
+            super::fingerprint(&inputs, &output, "radius 6").unwrap()
+        );
+
+        let mut options = CheckpointOptions {
+            path: dir.path().join("run.checkpoint"),
+            interval: Duration::ZERO,
+            resume: false,
+        };
+        let mut checkpointer = Checkpointer::start(&options, fingerprint.clone()).unwrap();
+        assert!(checkpointer.due());
+        let file = FileTiming {
+            path: input.display().to_string(),
+            hits: 40,
+            neutrons: 3,
+            ..FileTiming::default()
+        };
+        let position = StreamPosition {
+            offset: 64,
+            emitted_through: 1000,
+            ..StreamPosition::default()
+        };
+        checkpointer
+            .save_progress(position.clone(), &file, 120, true)
+            .unwrap();
+
+        options.resume = true;
+        let resumed = Checkpointer::start(&options, fingerprint).unwrap();
+        assert!(resumed.is_resumed());
+        assert!(resumed.completed(0).is_none());
+        let state = resumed.state();
+        assert_eq!((state.output_bytes, state.wrote_header), (120, true));
+        let current = state.current.as_ref().unwrap();
+        assert_eq!(current.position, position);
+        assert_eq!(current.totals.neutrons, 3);
+
+        std::fs::write(&input, [0u8; 24]).unwrap();
+        let changed = super::fingerprint(&inputs, &output, "radius 5").unwrap();
+        let err = Checkpointer::start(&options, changed).err().unwrap();
+        assert!(err.to_string().contains("different run"));
+
+        resumed.remove().unwrap();
+        assert!(!options.path.exists());
+    }
+}
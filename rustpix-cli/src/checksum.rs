@@ -0,0 +1,142 @@
+//! Checksum manifests for processed outputs.
+//!
+//! A manifest lists every output file with its size and SHA-256 digest so
+//! results can be shipped with a transfer tool and verified on arrival
+//! without a separate checksumming pass. Manifests ending in `.json` are
+//! written as JSON; any other extension produces `sha256sum`-compatible
+//! lines (`<digest>  <path>`), which can be checked with `sha256sum -c`.
+
+use crate::Result;
+use sha2::{Digest, Sha256};
+use std::fmt::Write as _;
+use std::fs::File;
+use std::io::{ErrorKind, Read};
+use std::path::{Path, PathBuf};
+
+/// Read buffer used while hashing.
+const HASH_BUFFER_BYTES: usize = 1024 * 1024;
+
+/// One file in a checksum manifest.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct ManifestEntry {
+    /// Path as recorded in the manifest.
+    pub path: String,
+    /// File size in bytes.
+    pub size: u64,
+    /// Lower-case hex SHA-256 digest.
+    pub sha256: String,
+}
+
+impl ManifestEntry {
+    /// Hash `local` and record it under `name`.
+    ///
+    /// # Errors
+    /// Returns an error if the file cannot be read.
+    pub fn from_file(local: &Path, name: String) -> Result<Self> {
+        let mut file = File::open(local)?;
+        let mut hasher = Sha256::new();
+        let mut buffer = vec![0u8; HASH_BUFFER_BYTES];
+        let mut size = 0u64;
+        loop {
+            let read = match file.read(&mut buffer) {
+                Ok(0) => break,
+                Ok(n) => n,
+                Err(err) if err.kind() == ErrorKind::Interrupted => continue,
+                Err(err) => return Err(err.into()),
+            };
+            hasher.update(&buffer[..read]);
+            size += read as u64;
+        }
+        Ok(Self {
+            path: name,
+            size,
+            sha256: to_hex(&hasher.finalize()),
+        })
+    }
+}
+
+/// Name to record for `path`: relative to the manifest's directory when the
+/// file lives under it, otherwise as given.
+#[must_use]
+pub fn entry_name(path: &Path, manifest: &Path) -> String {
+    let base = manifest.parent().unwrap_or(Path::new(""));
+    path.strip_prefix(base)
+        .unwrap_or(path)
+        .to_string_lossy()
+        .into_owned()
+}
+
+/// Hash local `files` and write the manifest.
+///
+/// # Errors
+/// Returns an error if a file cannot be hashed or the manifest written.
+pub fn write_for_files(manifest: &Path, files: &[PathBuf]) -> Result<()> {
+    let entries = files
+        .iter()
+        .map(|file| ManifestEntry::from_file(file, entry_name(file, manifest)))
+        .collect::<Result<Vec<_>>>()?;
+    write_manifest(manifest, &entries)
+}
+
+/// Write `entries` to `manifest` (JSON or `sha256sum` format by extension).
+///
+/// # Errors
+/// Returns an error if the manifest cannot be written.
+pub fn write_manifest(manifest: &Path, entries: &[ManifestEntry]) -> Result<()> {
+    let is_json = manifest
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("json"));
+    let contents = if is_json {
+        let total_bytes: u64 = entries.iter().map(|entry| entry.size).sum();
+        serde_json::to_string_pretty(&serde_json::json!({
+            "algorithm": "sha256",
+            "file_count": entries.len(),
+            "total_bytes": total_bytes,
+            "files": entries,
+        }))?
+    } else {
+        let mut text = String::new();
+        for entry in entries {
+            let _ = writeln!(text, "{}  {}", entry.sha256, entry.path);
+        }
+        text
+    };
+    crate::atomic::write(manifest, contents)?;
+    Ok(())
+}
+
+/// Lower-case hex encoding of `bytes`.
+pub fn to_hex(bytes: &[u8]) -> String {
+    let mut hex = String::with_capacity(bytes.len() * 2);
+    for byte in bytes {
+        let _ = write!(hex, "{byte:02x}");
+    }
+    hex
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_manifest_entries_and_formats() {
+        let dir = tempfile::tempdir().unwrap();
+        let output = dir.path().join("out.csv");
+        std::fs::write(&output, b"abc").unwrap();
+
+        let manifest = dir.path().join("manifest.sha256");
+        write_for_files(&manifest, std::slice::from_ref(&output)).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&manifest).unwrap(),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad  out.csv\n"
+        );
+
+        let manifest = dir.path().join("manifest.json");
+        write_for_files(&manifest, &[output]).unwrap();
+        let json: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(&manifest).unwrap()).unwrap();
+        assert_eq!(json["total_bytes"], 3);
+        assert_eq!(json["files"][0]["path"], "out.csv");
+    }
+}
@@ -0,0 +1,649 @@
+//! Event-level comparison of two neutron outputs.
+//!
+//! Events from a reference and a candidate set are matched greedily in TOF
+//! order: each reference event takes the nearest unmatched candidate of the
+//! same pulse within the position and TOF tolerances. TOF restarts with
+//! every pulse, so events of different pulses are never paired. Residuals of matched pairs are
+//! summarised and histogrammed so ports can be validated against legacy
+//! results. The TOF spectra of both sets are also compared with chi-square
+//! and Kolmogorov-Smirnov tests, which tell whether two runs are consistent
+//! even when their events cannot be matched one to one. Runs taken with the
+//! detector or sample moved can be aligned first: the shift between images
+//! of their positions is found by phase correlation and taken off the
+//! candidate positions.
+
+use crate::{usize_to_f64, CliError, Result};
+use rustpix_algorithms::{compare_spectra, register_translation, ImageShift, SpectrumComparison};
+use rustpix_core::neutron::NeutronBatch;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+
+/// Number of bins in each residual histogram.
+const RESIDUAL_BINS: usize = 21;
+/// Significance level below which the spectra are reported as inconsistent.
+const CONSISTENCY_ALPHA: f64 = 0.05;
+/// Longest side of the position images used for alignment.
+const ALIGNMENT_IMAGE_SIZE: f64 = 512.0;
+
+/// Matching tolerances.
+#[derive(Debug, Clone, Copy)]
+pub struct Tolerance {
+    /// Maximum |dx| and |dy| (output position units).
+    pub position: f64,
+    /// Maximum |dTOF| (25 ns ticks).
+    pub tof: u32,
+}
+
+/// Neutrons with the pulse each one belongs to.
+#[derive(Debug, Clone, Default)]
+pub struct PulsedNeutrons {
+    /// The neutrons.
+    pub neutrons: NeutronBatch,
+    /// Pulse of each neutron (TDC timestamp or pulse number), or `None` if
+    /// the source does not record pulses.
+    pub pulses: Option<Vec<u64>>,
+}
+
+/// Histogram of residuals over `[-limit, limit]`.
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct ResidualHistogram {
+    /// Half-width of the histogram range.
+    pub limit: f64,
+    /// Counts per bin, lowest residual first.
+    pub counts: Vec<u64>,
+    /// Mean residual.
+    pub mean: f64,
+    /// Standard deviation of residuals.
+    pub std_dev: f64,
+}
+
+impl ResidualHistogram {
+    fn new(limit: f64, residuals: &[f64]) -> Self {
+        let mut counts = vec![0u64; RESIDUAL_BINS];
+        let limit = limit.max(f64::EPSILON);
+        let bins = usize_to_f64(RESIDUAL_BINS);
+        for &value in residuals {
+            let fraction = ((value + limit) / (2.0 * limit)).clamp(0.0, 1.0);
+            #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+            let bin = ((fraction * bins) as usize).min(RESIDUAL_BINS - 1);
+            counts[bin] += 1;
+        }
+        let n = usize_to_f64(residuals.len()).max(1.0);
+        let mean = residuals.iter().sum::<f64>() / n;
+        let variance = residuals.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / n;
+        Self {
+            limit,
+            counts,
+            mean,
+            std_dev: variance.sqrt(),
+        }
+    }
+}
+
+/// Result of comparing two event sets.
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct DiffReport {
+    /// Events in the reference set.
+    pub reference_events: usize,
+    /// Events in the candidate set.
+    pub candidate_events: usize,
+    /// Matched pairs.
+    pub matched: usize,
+    /// Reference events with no candidate within tolerance.
+    pub unmatched_reference: usize,
+    /// Candidate events left unmatched.
+    pub unmatched_candidate: usize,
+    /// Whether events were only matched within their pulse; `false` if
+    /// either set lacks pulse information.
+    pub within_pulses: bool,
+    /// X residuals (candidate − reference).
+    pub dx: ResidualHistogram,
+    /// Y residuals (candidate − reference).
+    pub dy: ResidualHistogram,
+    /// TOF residuals in 25 ns ticks (candidate − reference).
+    pub dtof: ResidualHistogram,
+    /// Number of TOF spectrum bins compared.
+    pub spectrum_bins: usize,
+    /// Chi-square and KS comparison of the TOF spectra (`None` if either set
+    /// is empty).
+    pub spectrum: Option<SpectrumComparison>,
+    /// Shift taken off the candidate positions before matching, in output
+    /// position units, if the sets were aligned.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub alignment: Option<ImageShift>,
+}
+
+impl DiffReport {
+    /// Fraction of reference events that were matched.
+    #[must_use]
+    pub fn match_rate(&self) -> f64 {
+        if self.reference_events == 0 {
+            return 1.0;
+        }
+        usize_to_f64(self.matched) / usize_to_f64(self.reference_events)
+    }
+
+    /// Print the summary and residual histograms.
+    pub fn print(&self) {
+        println!("Reference events: {}", self.reference_events);
+        println!("Candidate events: {}", self.candidate_events);
+        println!(
+            "Matched:          {} ({:.2}% of reference)",
+            self.matched,
+            self.match_rate() * 100.0
+        );
+        println!("Unmatched (ref):  {}", self.unmatched_reference);
+        println!("Unmatched (cand): {}", self.unmatched_candidate);
+        if self.within_pulses {
+            println!("Matching:         within pulses");
+        } else {
+            println!("Matching:         across pulses (no pulse information)");
+        }
+        if let Some(shift) = &self.alignment {
+            println!(
+                "Aligned:          candidate shifted by ({:+.3}, {:+.3}), correlation peak {:.3}",
+                -shift.dx, -shift.dy, shift.peak
+            );
+        }
+        println!();
+        self.print_spectrum();
+        for (name, hist) in [("dx", &self.dx), ("dy", &self.dy), ("dtof", &self.dtof)] {
+            println!();
+            println!(
+                "{name} residuals: mean {:+.4}, std {:.4}",
+                hist.mean, hist.std_dev
+            );
+            print_histogram(hist);
+        }
+    }
+
+    fn print_spectrum(&self) {
+        let Some(spectrum) = &self.spectrum else {
+            println!("TOF spectra: not compared (no events)");
+            return;
+        };
+        println!("TOF spectra ({} bins):", self.spectrum_bins);
+        println!(
+            "  chi2/ndf {:.3} (chi2 {:.1}, ndf {}, p = {:.3e})",
+            spectrum.reduced_chi_square(),
+            spectrum.chi_square,
+            spectrum.degrees_of_freedom,
+            spectrum.chi_square_p
+        );
+        println!(
+            "  KS D {:.5} (p = {:.3e})",
+            spectrum.ks_statistic, spectrum.ks_p
+        );
+        if spectrum.consistent(CONSISTENCY_ALPHA) {
+            println!("  Consistent at the {CONSISTENCY_ALPHA} level");
+        } else {
+            println!("  INCONSISTENT at the {CONSISTENCY_ALPHA} level");
+        }
+    }
+}
+
+fn print_histogram(hist: &ResidualHistogram) {
+    const BAR_WIDTH: u64 = 40;
+    let max = hist.counts.iter().copied().max().unwrap_or(0).max(1);
+    let step = 2.0 * hist.limit / usize_to_f64(RESIDUAL_BINS);
+    for (bin, &count) in hist.counts.iter().enumerate() {
+        let lower = -hist.limit + step * usize_to_f64(bin);
+        let width = count * BAR_WIDTH / max;
+        let bar = "#".repeat(usize::try_from(width).unwrap_or(0));
+        println!("  {lower:>+9.3} | {bar:<40} {count}");
+    }
+}
+
+/// Match `candidate` events against `reference` events and compare their
+/// TOF spectra over `spectrum_bins` bins.
+///
+/// Events are only matched within the same pulse if both sets record
+/// pulses; otherwise all events are treated as one pulse.
+#[must_use]
+pub fn compare(
+    reference: &PulsedNeutrons,
+    candidate: &PulsedNeutrons,
+    tol: Tolerance,
+    spectrum_bins: usize,
+) -> DiffReport {
+    let within_pulses = reference.pulses.is_some() && candidate.pulses.is_some();
+    let key = |events: &PulsedNeutrons, i: usize| {
+        let pulse = match &events.pulses {
+            Some(pulses) if within_pulses => pulses[i],
+            _ => 0,
+        };
+        (pulse, events.neutrons.tof[i])
+    };
+    let (reference_set, candidate_set) = (&reference.neutrons, &candidate.neutrons);
+
+    let mut order: Vec<usize> = (0..candidate_set.len()).collect();
+    order.sort_unstable_by_key(|&i| key(candidate, i));
+    let sorted_keys: Vec<(u64, u32)> = order.iter().map(|&i| key(candidate, i)).collect();
+    let mut taken = vec![false; order.len()];
+
+    let mut reference_order: Vec<usize> = (0..reference_set.len()).collect();
+    reference_order.sort_unstable_by_key(|&i| key(reference, i));
+
+    let mut dx = Vec::new();
+    let mut dy = Vec::new();
+    let mut dtof = Vec::new();
+    for r in reference_order {
+        let (pulse, rt) = key(reference, r);
+        let (rx, ry) = (reference_set.x[r], reference_set.y[r]);
+        let lo = sorted_keys.partition_point(|&k| k < (pulse, rt.saturating_sub(tol.tof)));
+        let hi = sorted_keys.partition_point(|&k| k <= (pulse, rt.saturating_add(tol.tof)));
+
+        let mut best: Option<(usize, f64)> = None;
+        for slot in lo..hi {
+            if taken[slot] {
+                continue;
+            }
+            let c = order[slot];
+            let (ddx, ddy) = (candidate_set.x[c] - rx, candidate_set.y[c] - ry);
+            if ddx.abs() > tol.position || ddy.abs() > tol.position {
+                continue;
+            }
+            let dt = f64::from(candidate_set.tof[c]) - f64::from(rt);
+            let distance = ddx.hypot(ddy) + dt.abs() / f64::from(tol.tof.max(1));
+            if best.is_none_or(|(_, d)| distance < d) {
+                best = Some((slot, distance));
+            }
+        }
+
+        if let Some((slot, _)) = best {
+            taken[slot] = true;
+            let c = order[slot];
+            dx.push(candidate_set.x[c] - rx);
+            dy.push(candidate_set.y[c] - ry);
+            dtof.push(f64::from(candidate_set.tof[c]) - f64::from(rt));
+        }
+    }
+
+    let matched = dx.len();
+    let position_limit = tol.position.max(f64::EPSILON);
+    let tof_limit = f64::from(tol.tof.max(1));
+    DiffReport {
+        reference_events: reference_set.len(),
+        candidate_events: candidate_set.len(),
+        matched,
+        unmatched_reference: reference_set.len() - matched,
+        unmatched_candidate: candidate_set.len() - matched,
+        within_pulses,
+        dx: ResidualHistogram::new(position_limit, &dx),
+        dy: ResidualHistogram::new(position_limit, &dy),
+        dtof: ResidualHistogram::new(tof_limit, &dtof),
+        spectrum_bins,
+        spectrum: compare_tof_spectra(reference_set, candidate_set, spectrum_bins),
+        alignment: None,
+    }
+}
+
+/// Find the shift of `candidate` against `reference` from images of their
+/// positions and take it off the candidate positions.
+///
+/// Positions are binned into images of at most 512 pixels a side, so the
+/// shift is found to a fraction of a bin of the larger of 1 output unit and
+/// the position range / 512.
+pub fn align(reference: &NeutronBatch, candidate: &mut NeutronBatch) -> ImageShift {
+    let extent = reference
+        .x
+        .iter()
+        .chain(&reference.y)
+        .chain(&candidate.x)
+        .chain(&candidate.y)
+        .fold(0.0_f64, |extent, &value| extent.max(value));
+    let bin = (extent / ALIGNMENT_IMAGE_SIZE).max(1.0);
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    let size = (extent / bin).floor() as usize + 1;
+    let image = |batch: &NeutronBatch| {
+        let mut counts = vec![0u64; size * size];
+        for (&x, &y) in batch.x.iter().zip(&batch.y) {
+            if x < 0.0 || y < 0.0 {
+                continue;
+            }
+            #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+            let (column, row) = ((x / bin) as usize, (y / bin) as usize);
+            counts[row.min(size - 1) * size + column.min(size - 1)] += 1;
+        }
+        counts
+    };
+    let shift = register_translation(&image(reference), &image(candidate), size, size);
+    let shift = ImageShift {
+        dx: shift.dx * bin,
+        dy: shift.dy * bin,
+        peak: shift.peak,
+    };
+    candidate.x.iter_mut().for_each(|x| *x -= shift.dx);
+    candidate.y.iter_mut().for_each(|y| *y -= shift.dy);
+    shift
+}
+
+/// Compare the TOF spectra of two event sets, binned over their common
+/// range.
+fn compare_tof_spectra(
+    reference: &NeutronBatch,
+    candidate: &NeutronBatch,
+    bins: usize,
+) -> Option<SpectrumComparison> {
+    let all = reference.tof.iter().chain(&candidate.tof);
+    let min = all.clone().copied().min()?;
+    let max = all.copied().max()?;
+    let bins = bins.max(1);
+    let span = u64::from(max - min) + 1;
+    let bins_u64 = u64::try_from(bins).unwrap_or(u64::MAX);
+    let histogram = |tof: &[u32]| {
+        let mut counts = vec![0u64; bins];
+        for &t in tof {
+            let bin = u64::from(t - min) * bins_u64 / span;
+            counts[usize::try_from(bin).unwrap_or(bins - 1)] += 1;
+        }
+        counts
+    };
+    compare_spectra(&histogram(&reference.tof), &histogram(&candidate.tof))
+}
+
+/// Read a neutron output written by `process` (CSV, 28-byte binary,
+/// `.rpxd` or `.rpxc`).
+///
+/// CSV files need a header naming at least the `x`, `y` and `tof` columns;
+/// other columns are optional, so exports from other tools can be compared
+/// as long as they use the same column names. Positions are multiplied by
+/// `scale` to bring both sides into the same units.
+///
+/// # Errors
+/// Returns an error if the file cannot be read or is malformed.
+pub fn read_neutrons(path: &Path, scale: f64) -> Result<NeutronBatch> {
+    read_pulsed_neutrons(path, scale, None).map(|events| events.neutrons)
+}
+
+/// Read a neutron output like [`read_neutrons`], with the pulse of each
+/// event.
+///
+/// Pulses come from `event_index`, an `--event-index` file written with the
+/// output, or else from the `pulse_id` column of a CSV output; either way
+/// they are pulse numbers, so both sources can be compared. Events of
+/// synthetic pulses (empty `pulse_id`) are matched among themselves.
+///
+/// # Errors
+/// Returns an error if a file cannot be read or is malformed, or the index
+/// does not fit the output.
+pub fn read_pulsed_neutrons(
+    path: &Path,
+    scale: f64,
+    event_index: Option<&Path>,
+) -> Result<PulsedNeutrons> {
+    let extension = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(str::to_ascii_lowercase);
+    let (mut batch, mut pulses) = if extension.as_deref() == Some("csv") {
+        let reader = BufReader::new(File::open(path)?);
+        let (batch, ids) = rustpix_io::read_neutron_batch_csv_with_pulse_ids(reader)
+            .map_err(|err| csv_error(path, err))?;
+        let pulses = ids.map(|ids| {
+            ids.into_iter()
+                .map(|id| id.map_or(u64::MAX, u64::from))
+                .collect()
+        });
+        (batch, pulses)
+    } else {
+        (
+            read_format(path, extension.as_deref().unwrap_or("bin"))?,
+            None,
+        )
+    };
+    if let Some(index) = event_index {
+        pulses = Some(read_event_index(index, batch.len())?);
+    }
+    if (scale - 1.0).abs() > f64::EPSILON {
+        batch.x.iter_mut().for_each(|x| *x *= scale);
+        batch.y.iter_mut().for_each(|y| *y *= scale);
+    }
+    Ok(PulsedNeutrons {
+        neutrons: batch,
+        pulses,
+    })
+}
+
+/// Pulse number of each of `events` events from an `--event-index` file.
+fn read_event_index(path: &Path, events: usize) -> Result<Vec<u64>> {
+    let bad = |message: String| CliError::InvalidInput(format!("{}: {message}", path.display()));
+    let mut lines = BufReader::new(File::open(path)?).lines();
+    let header = lines.next().transpose()?.unwrap_or_default();
+    let Some(column) = header
+        .split(',')
+        .position(|name| name.trim() == "event_index")
+    else {
+        return Err(bad("header has no event_index column".to_string()));
+    };
+    let mut pulses = Vec::with_capacity(events);
+    let mut pulse = 0u64;
+    for (line_no, line) in lines.enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let first: usize = line
+            .split(',')
+            .nth(column)
+            .and_then(|field| field.trim().parse().ok())
+            .ok_or_else(|| bad(format!("line {}: malformed row", line_no + 2)))?;
+        if first < pulses.len() || first > events {
+            return Err(bad(format!(
+                "line {}: event index {first} does not fit an output of {events} events",
+                line_no + 2
+            )));
+        }
+        // The previous pulse owns the events up to this pulse's first one.
+        pulses.resize(first, pulse.saturating_sub(1));
+        pulse += 1;
+    }
+    if pulse == 0 && events > 0 {
+        return Err(bad("no pulses for a non-empty output".to_string()));
+    }
+    pulses.resize(events, pulse.saturating_sub(1));
+    Ok(pulses)
+}
+
+/// Read a neutron output in `format` (`csv`, `rpxd`, `rpxc`, otherwise
+/// binary).
+///
+/// # Errors
+/// Returns an error if the file cannot be read or is malformed.
+pub fn read_format(path: &Path, format: &str) -> Result<NeutronBatch> {
+    match format {
+        "csv" => read_csv(path),
+        "rpxd" => read_packed(path),
+        "rpxc" => Ok(rustpix_io::ContainerReader::open(path)?.read_all_neutrons()?),
+        _ => read_binary(path),
+    }
+}
+
+fn read_csv(path: &Path) -> Result<NeutronBatch> {
+    let reader = BufReader::new(File::open(path)?);
+    rustpix_io::read_neutron_batch_csv(reader).map_err(|err| csv_error(path, err))
+}
+
+fn csv_error(path: &Path, err: rustpix_io::Error) -> CliError {
+    match err {
+        rustpix_io::Error::InvalidFormat(message) => {
+            CliError::InvalidInput(format!("{}: {message}", path.display()))
+        }
+        other => other.into(),
+    }
+}
+
+fn read_binary(path: &Path) -> Result<NeutronBatch> {
+    let reader = BufReader::new(File::open(path)?);
+    rustpix_io::read_neutron_batch_binary(reader).map_err(Into::into)
+}
+
+fn read_packed(path: &Path) -> Result<NeutronBatch> {
+    let reader = BufReader::new(File::open(path)?);
+    rustpix_io::read_neutron_batch_packed(reader).map_err(Into::into)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rustpix_core::neutron::Neutron;
+
+    fn batch(events: &[(f64, f64, u32)]) -> NeutronBatch {
+        let mut batch = NeutronBatch::default();
+        for &(x, y, tof) in events {
+            batch.push(Neutron::new(x, y, tof, 0, 1, 0));
+        }
+        batch
+    }
+
+    fn unpulsed(events: &[(f64, f64, u32)]) -> PulsedNeutrons {
+        PulsedNeutrons {
+            neutrons: batch(events),
+            pulses: None,
+        }
+    }
+
+    #[test]
+    fn test_compare_matches_within_tolerance() {
+        let reference = unpulsed(&[(10.0, 10.0, 100), (50.0, 50.0, 200), (90.0, 90.0, 300)]);
+        let candidate = unpulsed(&[(10.5, 9.5, 101), (50.0, 50.0, 200), (0.0, 0.0, 900)]);
+        let report = compare(
+            &reference,
+            &candidate,
+            Tolerance {
+                position: 1.0,
+                tof: 2,
+            },
+            10,
+        );
+        assert_eq!(report.matched, 2);
+        assert_eq!(report.unmatched_reference, 1);
+        assert_eq!(report.unmatched_candidate, 1);
+        assert!((report.dx.mean - 0.25).abs() < 1e-9);
+        assert_eq!(report.dtof.counts.iter().sum::<u64>(), 2);
+    }
+
+    #[test]
+    fn test_compare_does_not_reuse_candidates() {
+        let reference = unpulsed(&[(10.0, 10.0, 100), (10.2, 10.0, 100)]);
+        let candidate = unpulsed(&[(10.1, 10.0, 100)]);
+        let report = compare(
+            &reference,
+            &candidate,
+            Tolerance {
+                position: 1.0,
+                tof: 0,
+            },
+            10,
+        );
+        assert_eq!(report.matched, 1);
+        assert_eq!(report.unmatched_reference, 1);
+        assert_eq!(report.unmatched_candidate, 0);
+    }
+
+    #[test]
+    fn test_compare_tof_spectra() {
+        let tofs: Vec<(f64, f64, u32)> = (0..400).map(|i| (0.0, 0.0, i % 100)).collect();
+        let reference = unpulsed(&tofs);
+        let shifted: Vec<(f64, f64, u32)> = tofs.iter().map(|&(x, y, t)| (x, y, t + 50)).collect();
+        let tolerance = Tolerance {
+            position: 1.0,
+            tof: 0,
+        };
+
+        let same = compare(&reference, &reference, tolerance, 10);
+        let spectrum = same.spectrum.unwrap();
+        assert_eq!(spectrum.degrees_of_freedom, 9);
+        assert!(spectrum.consistent(CONSISTENCY_ALPHA));
+
+        let report = compare(&reference, &unpulsed(&shifted), tolerance, 10);
+        assert!(!report.spectrum.unwrap().consistent(CONSISTENCY_ALPHA));
+        assert!(
+            compare(&reference, &PulsedNeutrons::default(), tolerance, 10)
+                .spectrum
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_align_removes_offset() {
+        let spots = [
+            (20.0, 30.0),
+            (21.0, 30.0),
+            (60.0, 45.0),
+            (60.0, 46.0),
+            (35.0, 80.0),
+        ];
+        let events = |dx: f64, dy: f64| {
+            let mut events = Vec::new();
+            for (i, &(x, y)) in spots.iter().enumerate() {
+                for tof in 0..20 {
+                    events.push((x + dx, y + dy, tof * 10 + u32::try_from(i).unwrap()));
+                }
+            }
+            unpulsed(&events)
+        };
+        let reference = events(0.0, 0.0);
+        let mut candidate = events(6.0, -4.0);
+        let tolerance = Tolerance {
+            position: 1.0,
+            tof: 0,
+        };
+        assert_eq!(compare(&reference, &candidate, tolerance, 10).matched, 0);
+
+        let shift = align(&reference.neutrons, &mut candidate.neutrons);
+        assert!((shift.dx - 6.0).abs() < 0.5, "dx = {}", shift.dx);
+        assert!((shift.dy + 4.0).abs() < 0.5, "dy = {}", shift.dy);
+        let report = compare(&reference, &candidate, tolerance, 10);
+        assert_eq!(report.matched, reference.neutrons.len());
+    }
+
+    #[test]
+    fn test_compare_matches_within_pulses() {
+        let events = [(10.0, 10.0, 100), (10.0, 10.0, 100)];
+        let reference = PulsedNeutrons {
+            neutrons: batch(&events),
+            pulses: Some(vec![0, 1]),
+        };
+        let candidate = PulsedNeutrons {
+            neutrons: batch(&events),
+            pulses: Some(vec![1, 2]),
+        };
+        let tolerance = Tolerance {
+            position: 1.0,
+            tof: 0,
+        };
+        let report = compare(&reference, &candidate, tolerance, 10);
+        assert!(report.within_pulses);
+        assert_eq!(report.matched, 1);
+        assert_eq!(report.unmatched_reference, 1);
+
+        let unknown = PulsedNeutrons {
+            pulses: None,
+            ..candidate
+        };
+        let report = compare(&reference, &unknown, tolerance, 10);
+        assert!(!report.within_pulses);
+        assert_eq!(report.matched, 2);
+    }
+
+    #[test]
+    fn test_read_event_index() {
+        let dir = tempfile::tempdir().unwrap();
+        let index = dir.path().join("pulses.csv");
+        std::fs::write(
+            &index,
+            "event_time_zero_ns,event_index\n0,0\n100,2\n200,2\n300,3\n",
+        )
+        .unwrap();
+        assert_eq!(read_event_index(&index, 5).unwrap(), vec![0, 0, 2, 3, 3]);
+        assert!(read_event_index(&index, 2).is_err());
+
+        let output = dir.path().join("neutrons.csv");
+        std::fs::write(&output, "x,y,tof,pulse_id\n1,2,3,4\n5,6,7,\n").unwrap();
+        let events = read_pulsed_neutrons(&output, 2.0, None).unwrap();
+        assert_eq!(events.pulses, Some(vec![4, u64::MAX]));
+        assert_eq!(events.neutrons.x, vec![2.0, 10.0]);
+    }
+}
@@ -0,0 +1,86 @@
+//! Power spectrum and autocorrelation maps for the `fft` command.
+//!
+//! Maps are written like transmission stacks: a little-endian `f32` file in
+//! `[y][x]` order with zero frequency (or lag) at the center, plus a JSON
+//! sidecar describing the shape and the periodic peaks found.
+
+use crate::Result;
+use rustpix_algorithms::{FourierMap, PeriodicPeak};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+/// JSON description of a periodic peak.
+pub fn peak_json(peak: &PeriodicPeak) -> serde_json::Value {
+    serde_json::json!({
+        "fx": peak.fx,
+        "fy": peak.fy,
+        "period_px": peak.period,
+        "angle_deg": peak.angle_deg,
+        "power": peak.power,
+        "fraction": peak.fraction,
+    })
+}
+
+/// Write `map` to `output` with a JSON sidecar next to it.
+///
+/// Returns the paths of the map and the sidecar.
+///
+/// # Errors
+/// Returns an error if either file cannot be written.
+pub fn write_map(
+    output: &Path,
+    source: &Path,
+    map: &FourierMap,
+    kind: &str,
+    peaks: &[PeriodicPeak],
+) -> Result<Vec<PathBuf>> {
+    let mut writer = BufWriter::new(File::create(output)?);
+    for &value in &map.values {
+        #[allow(clippy::cast_possible_truncation)]
+        let value = value as f32;
+        writer.write_all(&value.to_le_bytes())?;
+    }
+    writer.flush()?;
+
+    let meta = serde_json::json!({
+        "source": source.display().to_string(),
+        "map": kind,
+        "dtype": "float32",
+        "byte_order": "little",
+        "shape": [map.height, map.width],
+        "center": [map.width / 2, map.height / 2],
+        "peaks": peaks.iter().map(peak_json).collect::<Vec<_>>(),
+    });
+    let meta_path = output.with_extension("json");
+    std::fs::write(&meta_path, serde_json::to_string_pretty(&meta)?)?;
+
+    Ok(vec![output.to_path_buf(), meta_path])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_write_map_layout_and_sidecar() {
+        let dir = tempfile::tempdir().unwrap();
+        let output = dir.path().join("power.bin");
+        let map = FourierMap {
+            width: 2,
+            height: 1,
+            values: vec![1.5, -2.0],
+        };
+        let written = write_map(&output, Path::new("run.tpx3"), &map, "power", &[]).unwrap();
+
+        let bytes = std::fs::read(&written[0]).unwrap();
+        assert_eq!(bytes.len(), 2 * 4);
+        assert_eq!(&bytes[4..8], &(-2.0f32).to_le_bytes());
+
+        let meta: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(&written[1]).unwrap()).unwrap();
+        assert_eq!(meta["shape"], serde_json::json!([1, 2]));
+        assert_eq!(meta["center"], serde_json::json!([1, 0]));
+        assert_eq!(meta["map"], "power");
+    }
+}
@@ -0,0 +1,102 @@
+//! Frame-mode export for the `frames` command.
+//!
+//! Frame-based acquisitions are written as an image stack in the same
+//! layout as transmission stacks: a little-endian binary file with one
+//! `[y][x]` image per frame, plus a JSON sidecar describing the shape and
+//! the per-frame packet counts.
+
+use crate::Result;
+use rustpix_tpx::frame::{FrameImage, FrameValue};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+/// Name of a frame value in the JSON sidecar.
+fn value_name(value: FrameValue) -> &'static str {
+    match value {
+        FrameValue::EventCount => "event_count",
+        FrameValue::IntegratedTot => "integrated_tot",
+        FrameValue::Packets => "packets",
+    }
+}
+
+/// Write `frames` to `output` as little-endian `u32` in `[frame][y][x]`
+/// order, with a JSON sidecar next to it.
+///
+/// Returns the paths of the stack and the sidecar.
+///
+/// # Errors
+/// Returns an error if either file cannot be written.
+pub fn write_stack(
+    output: &Path,
+    source: &Path,
+    frames: &[FrameImage],
+    value: FrameValue,
+) -> Result<Vec<PathBuf>> {
+    let (width, height) = frames
+        .first()
+        .map_or((0, 0), |frame| (frame.width, frame.height));
+
+    let mut writer = BufWriter::new(File::create(output)?);
+    for frame in frames {
+        for pixel in &frame.values {
+            writer.write_all(&pixel.to_le_bytes())?;
+        }
+    }
+    writer.flush()?;
+
+    let meta = serde_json::json!({
+        "source": source.display().to_string(),
+        "value": value_name(value),
+        "dtype": "uint32",
+        "byte_order": "little",
+        "shape": [frames.len(), height, width],
+        "frames": frames
+            .iter()
+            .map(|frame| serde_json::json!({
+                "index": frame.index,
+                "pixel_packets": frame.pixel_packets,
+                "total": frame.total(),
+            }))
+            .collect::<Vec<_>>(),
+    });
+    let meta_path = output.with_extension("json");
+    std::fs::write(&meta_path, serde_json::to_string_pretty(&meta)?)?;
+
+    Ok(vec![output.to_path_buf(), meta_path])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_write_stack_layout_and_sidecar() {
+        let frame = |index, first| FrameImage {
+            index,
+            width: 2,
+            height: 1,
+            pixel_packets: 1,
+            values: vec![first, 0],
+        };
+        let dir = tempfile::tempdir().unwrap();
+        let output = dir.path().join("frames.bin");
+        let written = write_stack(
+            &output,
+            Path::new("run.tpx3"),
+            &[frame(0, 5), frame(1, 7)],
+            FrameValue::EventCount,
+        )
+        .unwrap();
+
+        let bytes = std::fs::read(&written[0]).unwrap();
+        assert_eq!(bytes.len(), 4 * 4);
+        assert_eq!(&bytes[8..12], &7u32.to_le_bytes());
+
+        let meta: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(&written[1]).unwrap()).unwrap();
+        assert_eq!(meta["shape"], serde_json::json!([2, 1, 2]));
+        assert_eq!(meta["value"], "event_count");
+        assert_eq!(meta["frames"][1]["total"], 7);
+    }
+}
@@ -0,0 +1,267 @@
+//! TOF-binned neutron histograms for the `histogram` command.
+//!
+//! Neutrons are clustered from a TPX3 file, or read from a neutron output of
+//! `process`, binned into a TOF × Y × X histogram and written as a TIFF
+//! stack (one page per TOF bin), the same stack the GUI exports, so batch
+//! jobs can produce it without the GUI. FITS output writes the same data as
+//! a cube with WCS axis keywords for astronomy tooling, and `--projection`
+//! sums over TOF to a single 2D image in either format. `--tof-hot-sigma`
+//! zeroes pixels that flicker in some TOF bins before anything is written.
+
+use crate::atomic::PendingOutputs;
+use crate::lock::OutputLock;
+use crate::{diff, open_reader, HistogramFormat, Result};
+use rustpix_algorithms::{cluster_batch, extract_batch, AlgorithmParams, ClusteringAlgorithm};
+use rustpix_core::clustering::ClusteringConfig;
+use rustpix_core::extraction::ExtractionConfig;
+use rustpix_core::neutron::NeutronBatch;
+use rustpix_io::{
+    write_fits_cube, write_fits_image, write_tiff_stack, FitsOptions, FitsValue, TiffBitDepth,
+    TofHistogram,
+};
+use rustpix_tpx::DetectorConfig;
+use std::path::Path;
+
+/// Binning and clustering settings for a histogram.
+pub struct HistogramSettings {
+    pub format: HistogramFormat,
+    pub tof_bins: usize,
+    /// Sum over TOF into one 2D image.
+    pub projection: bool,
+    /// Pixel pitch written to FITS headers.
+    pub pixel_size_mm: f64,
+    /// Detector layout and TDC frequency of TPX3 inputs.
+    pub detector: DetectorConfig,
+    pub bit_depth: TiffBitDepth,
+    pub algorithm: ClusteringAlgorithm,
+    pub clustering: ClusteringConfig,
+    pub extraction: ExtractionConfig,
+    pub params: AlgorithmParams,
+    /// Window (TOF bins) and sigma for zeroing pixels hot in some bins.
+    pub tof_hot_pixels: Option<(usize, f64)>,
+    pub verbose: bool,
+}
+
+/// Histogram the neutrons of `input` and write them to `output` as a TIFF
+/// stack or FITS cube.
+///
+/// # Errors
+/// Returns an error if the input cannot be read or processed, or the stack
+/// cannot be written.
+pub fn run(input: &Path, output: &Path, settings: &HistogramSettings) -> Result<()> {
+    let (mut histogram, neutrons) = histogram_input(input, settings)?;
+    let (width, height) = (histogram.width(), histogram.height());
+    for warning in histogram.warnings().iter() {
+        eprintln!(
+            "warning[{}]: {}: {}",
+            warning.kind,
+            input.display(),
+            warning.message
+        );
+    }
+    if settings.verbose {
+        eprintln!(
+            "{}: {neutrons} neutrons in {} TOF bins",
+            input.display(),
+            histogram.tof_bins()
+        );
+    }
+
+    if let Some((window, sigma)) = settings.tof_hot_pixels {
+        let (hot, removed) = histogram.mask_tof_hot_pixels(window, sigma);
+        eprintln!(
+            "{}: zeroed {} pixels hot in some TOF bins ({} pixel-bins, {removed} counts)",
+            input.display(),
+            hot.pixel_count(),
+            hot.len()
+        );
+    }
+
+    let projection = settings.projection.then(|| histogram.projection());
+    let (counts, planes) = match &projection {
+        Some(image) => (image.as_slice(), 1),
+        None => (histogram.counts(), histogram.tof_bins()),
+    };
+
+    let _lock = OutputLock::acquire(output)?;
+    let mut pending = PendingOutputs::default();
+    let staged = pending.stage(output);
+    let clamped = match settings.format {
+        HistogramFormat::Tiff => {
+            write_tiff_stack(&staged, counts, width, height, settings.bit_depth)?.clamped
+        }
+        HistogramFormat::Fits => {
+            let options = fits_options(input, neutrons, &histogram, settings);
+            if projection.is_some() {
+                write_fits_image(&staged, counts, width, height, &options)?;
+            } else {
+                write_fits_cube(&staged, counts, width, height, &options)?;
+            }
+            counts.iter().any(|&count| count > u64::from(u32::MAX))
+        }
+    };
+    pending.commit()?;
+    if projection.is_some() {
+        println!(
+            "Wrote TOF projection ({width}x{height}) to {}",
+            output.display()
+        );
+    } else {
+        println!(
+            "Wrote {planes} TOF bins ({width}x{height}) to {}",
+            output.display()
+        );
+    }
+    if clamped
+        && matches!(settings.format, HistogramFormat::Tiff)
+        && settings.bit_depth == TiffBitDepth::Bit16
+    {
+        eprintln!("Warning: counts above 65535 were clamped; use --bit-depth 32");
+    } else if clamped {
+        eprintln!("Warning: counts above 4294967295 were clamped");
+    }
+    Ok(())
+}
+
+/// Cluster a TPX3 input, or read a neutron output, into a histogram, with
+/// the number of neutrons counted.
+fn histogram_input(input: &Path, settings: &HistogramSettings) -> Result<(TofHistogram, usize)> {
+    let config = &settings.detector;
+    let (width, height) = config.detector_dimensions();
+    let factor = settings.extraction.super_resolution_factor;
+    let new_histogram = |width, height, tof_max| {
+        TofHistogram::new(settings.tof_bins, tof_max, width, height)
+            .with_super_resolution_factor(factor)
+    };
+
+    let is_tpx3 = input
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("tpx3"));
+    if is_tpx3 {
+        let mut histogram = new_histogram(width, height, config.tdc_correction_25ns());
+        let reader = open_reader(input)?.with_config(config.clone());
+        let mut neutrons = 0;
+        for pulse in reader.stream_time_ordered_events()? {
+            let mut batch = pulse.hits;
+            let num_clusters = cluster_batch(
+                &mut batch,
+                settings.algorithm,
+                &settings.clustering,
+                &settings.params,
+            )?;
+            let batch = extract_batch(&batch, num_clusters, &settings.extraction)?;
+            neutrons += batch.len();
+            histogram.accumulate(&batch);
+        }
+        Ok((histogram, neutrons))
+    } else {
+        // Neutron outputs may come from hit lists of other detectors, so the
+        // histogram grows past the detector and TDC period to fit them.
+        let batch = diff::read_neutrons(input, 1.0)?;
+        let (width, height, tof_max) = fit_extent(
+            &batch,
+            factor,
+            (width, height),
+            config.tdc_correction_25ns(),
+        );
+        let mut histogram = new_histogram(width, height, tof_max);
+        histogram.accumulate(&batch);
+        Ok((histogram, batch.len()))
+    }
+}
+
+/// Width, height and TOF span (25 ns ticks) that hold every neutron of
+/// `batch`: at least those of the detector, grown to the largest rounded
+/// pixel position and TOF.
+fn fit_extent(
+    batch: &NeutronBatch,
+    factor: f64,
+    (width, height): (usize, usize),
+    tof_max: u32,
+) -> (usize, usize, u32) {
+    let (mut width, mut height) = (width, height);
+    for i in 0..batch.len() {
+        let (x, y) = batch.pixel_coords(i, factor);
+        // Positions come from `u16` pixel indices, so they fit `usize`.
+        #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+        let (x, y) = (x.round().max(0.0) as usize, y.round().max(0.0) as usize);
+        width = width.max(x + 1);
+        height = height.max(y + 1);
+    }
+    let tof_max = batch
+        .tof
+        .iter()
+        .map(|&tof| tof.saturating_add(1))
+        .fold(tof_max, u32::max);
+    (width, height, tof_max)
+}
+
+/// FITS axis calibration and acquisition metadata of a histogram.
+fn fits_options(
+    input: &Path,
+    neutrons: usize,
+    histogram: &TofHistogram,
+    settings: &HistogramSettings,
+) -> FitsOptions {
+    let count = |value: usize| FitsValue::Int(i64::try_from(value).unwrap_or(i64::MAX));
+    let name = input
+        .file_name()
+        .map_or_else(String::new, |name| name.to_string_lossy().into_owned());
+    let options = FitsOptions {
+        pixel_size_mm: settings.pixel_size_mm,
+        tof_bin_width_ns: histogram.tof_bin_width_ns(),
+        ..FitsOptions::default()
+    }
+    .with_card("FILENAME", FitsValue::Str(name), "input file")
+    .with_card("NEVENTS", count(neutrons), "neutrons histogrammed")
+    .with_card(
+        "TOFBINS",
+        count(histogram.tof_bins()),
+        "TOF bins before projection",
+    )
+    .with_card(
+        "TOFSUM",
+        FitsValue::Bool(settings.projection),
+        "summed over TOF",
+    );
+    let detector = &settings.detector;
+    match detector.flight_path_m {
+        Some(flight_path_m) => options
+            .with_card(
+                "FLTPATH",
+                FitsValue::Float(flight_path_m),
+                "flight path [m]",
+            )
+            .with_card(
+                "TOFDELAY",
+                FitsValue::Float(detector.tof_offset_ns),
+                "TOF offset [ns]",
+            ),
+        None => options,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fit_extent_grows_to_the_neutrons() {
+        let mut batch = NeutronBatch {
+            x: vec![8.0, 5_000.0 * 8.0],
+            y: vec![16.0, 24.0],
+            tof: vec![10, 900_000],
+            ..NeutronBatch::default()
+        };
+        let (width, height, tof_max) = fit_extent(&batch, 8.0, (514, 514), 666_667);
+        assert_eq!((width, height, tof_max), (5_001, 514, 900_001));
+
+        batch.x.truncate(1);
+        batch.y.truncate(1);
+        batch.tof.truncate(1);
+        assert_eq!(
+            fit_extent(&batch, 8.0, (514, 514), 666_667),
+            (514, 514, 666_667)
+        );
+    }
+}
@@ -0,0 +1,102 @@
+//! Advisory locks on outputs.
+//!
+//! Before writing, `process`, `tui` and `watch` take an exclusive OS lock on
+//! a hidden `.<name>.lock` file next to each output and record their process
+//! id and command line in it. A second rustpix writing the same output (two
+//! watch daemons, or a daemon and a manual run) fails up front, naming the
+//! process that holds it, instead of interleaving writes. The OS releases
+//! the lock when the holder exits, even if it crashes, so a leftover lock
+//! file never blocks a later run.
+
+use crate::{CliError, Result};
+use std::fs::{File, OpenOptions, TryLockError};
+use std::io::{Read, Seek, Write};
+use std::path::{Path, PathBuf};
+
+/// Exclusive lock on one output, held until dropped.
+#[derive(Debug)]
+pub struct OutputLock {
+    _file: File,
+}
+
+impl OutputLock {
+    /// Lock `output` for writing.
+    ///
+    /// # Errors
+    /// Returns an error if another process holds the lock or the lock file
+    /// cannot be created.
+    pub fn acquire(output: &Path) -> Result<Self> {
+        let path = lock_path(output);
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&path)?;
+        match file.try_lock() {
+            Ok(()) => {}
+            Err(TryLockError::WouldBlock) => {
+                let mut holder = String::new();
+                let _ = file.read_to_string(&mut holder);
+                let holder = holder.trim();
+                let holder = if holder.is_empty() {
+                    "another process".to_string()
+                } else {
+                    format!("process {holder}")
+                };
+                return Err(CliError::InvalidInput(format!(
+                    "{} is being written by {holder} (lock {})",
+                    output.display(),
+                    path.display()
+                )));
+            }
+            Err(TryLockError::Error(err)) => return Err(err.into()),
+        }
+        file.set_len(0)?;
+        file.rewind()?;
+        let command: Vec<String> = std::env::args().collect();
+        writeln!(file, "{} ({})", std::process::id(), command.join(" "))?;
+        file.flush()?;
+        Ok(Self { _file: file })
+    }
+}
+
+/// Lock every output in `outputs`.
+///
+/// # Errors
+/// Returns an error naming the first output that another process holds.
+pub fn lock_outputs(outputs: &[&Path]) -> Result<Vec<OutputLock>> {
+    outputs
+        .iter()
+        .map(|path| OutputLock::acquire(path))
+        .collect()
+}
+
+/// The lock file of `output`.
+#[must_use]
+pub fn lock_path(output: &Path) -> PathBuf {
+    let name = output
+        .file_name()
+        .map_or_else(|| "output".into(), |name| name.to_string_lossy());
+    output.with_file_name(format!(".{name}.lock"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_second_writer_is_refused() {
+        let dir = tempfile::tempdir().unwrap();
+        let output = dir.path().join("neutrons.bin");
+
+        let held = OutputLock::acquire(&output).unwrap();
+        assert_eq!(lock_path(&output), dir.path().join(".neutrons.bin.lock"));
+        let err = OutputLock::acquire(&output).unwrap_err().to_string();
+        assert!(err.contains(&std::process::id().to_string()), "{err}");
+        assert!(lock_outputs(&[&dir.path().join("other.bin"), &output]).is_err());
+
+        drop(held);
+        assert!(lock_outputs(&[&dir.path().join("other.bin"), &output]).is_ok());
+    }
+}
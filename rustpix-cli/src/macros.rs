@@ -0,0 +1,270 @@
+//! Headless replay of macros recorded in the GUI.
+//!
+//! Steps run in order against one session: `load` selects the input file,
+//! `set_parameters` replaces the processing parameters, `process` clusters
+//! the file out-of-core and keeps the neutrons, and `export` writes them.
+
+use crate::{CliError, Result};
+use rustpix_core::neutron::NeutronBatch;
+use rustpix_io::{
+    out_of_core_neutron_stream, Macro, MacroExport, MacroExportFormat, MacroParameters, MacroStep,
+    OutOfCoreConfig, Tpx3FileReader, MACRO_FORMAT_VERSION,
+};
+use std::path::{Path, PathBuf};
+
+/// Neutrons of one pulse kept between `process` and `export`.
+#[cfg_attr(not(feature = "hdf5"), allow(dead_code))]
+struct PulseNeutrons {
+    tdc_timestamp_25ns: u64,
+    neutrons: NeutronBatch,
+}
+
+/// State carried from step to step.
+#[derive(Default)]
+struct Session {
+    input: Option<PathBuf>,
+    parameters: MacroParameters,
+    neutrons: Option<Vec<PulseNeutrons>>,
+}
+
+/// Read a macro, replacing the file of every `load` step with `input`.
+///
+/// # Errors
+/// Returns an error if the file cannot be read or parsed, or was written by
+/// a newer format version.
+pub fn load_macro(path: &Path, input: Option<&Path>) -> Result<Macro> {
+    let mut recorded: Macro = serde_json::from_str(&std::fs::read_to_string(path)?)?;
+    if recorded.version > MACRO_FORMAT_VERSION {
+        return Err(CliError::InvalidInput(format!(
+            "{}: macro format version {} is newer than supported version {MACRO_FORMAT_VERSION}",
+            path.display(),
+            recorded.version
+        )));
+    }
+    if let Some(input) = input {
+        for step in &mut recorded.steps {
+            if let MacroStep::Load { path } = step {
+                *path = input.to_path_buf();
+            }
+        }
+    }
+    Ok(recorded)
+}
+
+/// Run every step of a macro.
+///
+/// # Errors
+/// Returns an error naming the step that failed.
+pub fn run(recorded: &Macro, verbose: bool) -> Result<()> {
+    let mut session = Session::default();
+    for (index, step) in recorded.steps.iter().enumerate() {
+        if verbose {
+            println!("Step {}: {}", index + 1, describe(step));
+        }
+        session.run_step(step).map_err(|err| {
+            CliError::InvalidInput(format!("step {} ({}): {err}", index + 1, describe(step)))
+        })?;
+    }
+    Ok(())
+}
+
+fn describe(step: &MacroStep) -> String {
+    match step {
+        MacroStep::Load { path } => format!("load {}", path.display()),
+        MacroStep::SetParameters(_) => "set_parameters".to_string(),
+        MacroStep::Process => "process".to_string(),
+        MacroStep::Export(export) => format!("export {}", export.path.display()),
+    }
+}
+
+impl Session {
+    fn run_step(&mut self, step: &MacroStep) -> Result<()> {
+        match step {
+            MacroStep::Load { path } => {
+                Tpx3FileReader::open(path)?;
+                self.input = Some(path.clone());
+                self.neutrons = None;
+            }
+            MacroStep::SetParameters(parameters) => {
+                self.parameters = parameters.as_ref().clone();
+            }
+            MacroStep::Process => self.process()?,
+            MacroStep::Export(export) => self.export(export)?,
+        }
+        Ok(())
+    }
+
+    fn reader(&self) -> Result<Tpx3FileReader> {
+        let input = self
+            .input
+            .as_ref()
+            .ok_or_else(|| CliError::InvalidInput("no file loaded".to_string()))?;
+        Ok(Tpx3FileReader::open(input)?.with_config(self.parameters.detector.clone()))
+    }
+
+    fn process(&mut self) -> Result<()> {
+        let reader = self.reader()?;
+        let stream = out_of_core_neutron_stream(
+            &reader,
+            self.parameters.algorithm,
+            &self.parameters.clustering(),
+            &self.parameters.extraction(),
+            &self.parameters.algorithm_params(),
+            &OutOfCoreConfig::default(),
+        )?;
+        let mut pulses = Vec::new();
+        for batch in stream {
+            let batch = batch?;
+            pulses.push(PulseNeutrons {
+                tdc_timestamp_25ns: batch.tdc_timestamp_25ns,
+                neutrons: batch.neutrons,
+            });
+        }
+        self.neutrons = Some(pulses);
+        Ok(())
+    }
+
+    fn export(&self, export: &MacroExport) -> Result<()> {
+        if export.format != MacroExportFormat::Hdf5 {
+            eprintln!(
+                "Warning: skipping {:?} export to {}: only HDF5 exports can be replayed",
+                export.format,
+                export.path.display()
+            );
+            return Ok(());
+        }
+        if export.histogram {
+            eprintln!("Warning: the histogram is not replayed; writing events only");
+        }
+        if export.neutrons && self.neutrons.is_none() {
+            return Err(CliError::InvalidInput(
+                "neutron export before a process step".to_string(),
+            ));
+        }
+        if !export.hits && !export.neutrons {
+            eprintln!(
+                "Warning: skipping export to {}: no event datasets selected",
+                export.path.display()
+            );
+            return Ok(());
+        }
+        self.write_hdf5(export)
+    }
+
+    #[cfg(feature = "hdf5")]
+    fn write_hdf5(&self, export: &MacroExport) -> Result<()> {
+        use rustpix_io::hdf5::{
+            write_combined_hdf5_batches, HitWriteOptions, NeutronEventBatch, NeutronWriteOptions,
+        };
+
+        // The export's recorded conversion constants win over the detector's.
+        let detector = &self.parameters.detector;
+        let flight_path_m = export.flight_path_m.or(detector.flight_path_m);
+        let tof_offset_ns = export
+            .tof_offset_ns
+            .or(detector.flight_path_m.map(|_| detector.tof_offset_ns));
+        let hit_options = HitWriteOptions {
+            flight_path_m,
+            tof_offset_ns,
+            ..HitWriteOptions::from_detector_config(detector)
+        };
+        let neutron_options = NeutronWriteOptions {
+            super_resolution_factor: self.parameters.super_resolution_factor,
+            flight_path_m,
+            tof_offset_ns,
+            ..NeutronWriteOptions::from_detector_config(detector)
+        };
+        let hits: Vec<rustpix_io::EventBatch> = if export.hits {
+            self.reader()?.stream_time_ordered_events()?.collect()
+        } else {
+            Vec::new()
+        };
+        let neutrons: Vec<NeutronEventBatch> = match &self.neutrons {
+            Some(pulses) if export.neutrons => pulses
+                .iter()
+                .map(|pulse| NeutronEventBatch {
+                    tdc_timestamp_25ns: pulse.tdc_timestamp_25ns,
+                    neutrons: pulse.neutrons.clone(),
+                })
+                .collect(),
+            _ => Vec::new(),
+        };
+        write_combined_hdf5_batches(
+            &export.path,
+            export.hits.then_some((hits.as_slice(), &hit_options)),
+            export
+                .neutrons
+                .then_some((neutrons.as_slice(), &neutron_options)),
+            None,
+            None,
+        )?;
+        Ok(())
+    }
+
+    #[cfg(not(feature = "hdf5"))]
+    #[allow(clippy::unused_self)]
+    fn write_hdf5(&self, export: &MacroExport) -> Result<()> {
+        Err(CliError::InvalidInput(format!(
+            "{}: rustpix was built without the hdf5 feature needed for HDF5 output",
+            export.path.display()
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn export(neutrons: bool) -> MacroExport {
+        MacroExport {
+            path: PathBuf::from("out.h5"),
+            format: MacroExportFormat::Hdf5,
+            hits: false,
+            neutrons,
+            histogram: false,
+            flight_path_m: None,
+            tof_offset_ns: None,
+        }
+    }
+
+    #[test]
+    fn test_input_override_and_version_check() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("session.json");
+        std::fs::write(
+            &path,
+            r#"{"version": 1, "steps": [{"action": "load", "path": "a.tpx3"}, {"action": "process"}]}"#,
+        )
+        .unwrap();
+        let recorded = load_macro(&path, Some(Path::new("b.tpx3"))).unwrap();
+        assert!(
+            matches!(&recorded.steps[0], MacroStep::Load { path } if path == Path::new("b.tpx3"))
+        );
+
+        std::fs::write(&path, r#"{"version": 99, "steps": []}"#).unwrap();
+        assert!(load_macro(&path, None).is_err());
+    }
+
+    #[test]
+    fn test_steps_need_a_loaded_file() {
+        let mut session = Session::default();
+        assert!(session.run_step(&MacroStep::Process).is_err());
+        assert!(session.run_step(&MacroStep::Export(export(true))).is_err());
+        let missing = MacroStep::Load {
+            path: PathBuf::from("/nonexistent/run.tpx3"),
+        };
+        assert!(session.run_step(&missing).is_err());
+        assert!(session.input.is_none());
+    }
+
+    #[test]
+    fn test_non_event_exports_are_skipped() {
+        let session = Session::default();
+        assert!(session.export(&export(false)).is_ok());
+        let tiff = MacroExport {
+            format: MacroExportFormat::TiffStack,
+            ..export(true)
+        };
+        assert!(session.export(&tiff).is_ok());
+    }
+}
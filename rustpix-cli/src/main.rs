@@ -1,20 +1,54 @@
 //!
 //! This binary will provide a CLI for processing pixel detector data.
 
-use clap::{Parser, Subcommand, ValueEnum};
+use clap::{Args, Parser, Subcommand, ValueEnum};
 
-use rustpix_algorithms::{cluster_and_extract_batch, AlgorithmParams, ClusteringAlgorithm};
+use rustpix_algorithms::synthetic::{self, SyntheticConfig};
+use rustpix_algorithms::{
+    autocorrelation, azimuthal_profile, find_beam_spot, find_beam_spot_in_hits, periodic_peaks,
+    power_spectrum, radial_profile, register_translation, BeamSpotConfig,
+};
 use rustpix_algorithms::{
     AbsClustering, AbsState, DbscanClustering, DbscanState, GridClustering, GridState,
+    StreamingClustering, StreamingState,
 };
+use rustpix_algorithms::{AlgorithmParams, ClusteringAlgorithm};
 use rustpix_core::clustering::ClusteringConfig;
-use rustpix_core::extraction::ExtractionConfig;
+use rustpix_core::extraction::{validate_super_resolution, ExtractionConfig};
+use rustpix_core::neutron::NeutronBatch;
 use rustpix_core::soa::HitBatch;
 use rustpix_io::{out_of_core_neutron_stream, OutOfCoreConfig, Tpx3FileReader};
-use std::path::PathBuf;
-use std::time::Instant;
+use rustpix_tpx::frame::FrameValue;
+use rustpix_tpx::instrument::InstrumentProfile;
+use rustpix_tpx::tdc::TdcDiagnostics;
+use rustpix_tpx::DetectorConfig;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
 use thiserror::Error;
 
+mod alarms;
+mod atomic;
+mod checkpoint;
+mod checksum;
+mod diff;
+mod fourier;
+mod frames;
+mod histogram;
+mod lock;
+mod macros;
+mod neutron_output;
+mod output_template;
+mod process;
+mod profile;
+mod pulses;
+mod runs;
+mod split;
+mod timing;
+mod transmission;
+mod tui;
+mod verify;
+mod watch;
+
 /// Result type for CLI operations.
 type Result<T> = std::result::Result<T, CliError>;
 
@@ -35,6 +69,15 @@ enum CliError {
 
     #[error("Extraction error: {0}")]
     Extraction(#[from] rustpix_core::ExtractionError),
+
+    #[error("JSON error: {0}")]
+    Json(#[from] serde_json::Error),
+
+    #[error("Invalid input: {0}")]
+    InvalidInput(String),
+
+    #[error("Cancelled: {0}")]
+    Cancelled(String),
 }
 
 /// Clustering algorithm selection.
@@ -46,6 +89,136 @@ enum Algorithm {
     Dbscan,
     /// Grid-based clustering with spatial indexing
     Grid,
+    /// Grid-based clustering on the GPU (wgpu)
+    #[cfg(feature = "gpu")]
+    Gpu,
+    /// Online clustering that closes clusters as they age out (bounded memory)
+    Streaming,
+    /// Grid clusters merged by a time-then-space linkage (sparse tracks)
+    Hierarchical,
+    /// Labeling of touching pixels (fastest for compact thermal blobs)
+    ConnectedComponents,
+}
+
+/// Per-pixel value of frame-mode images.
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum FrameValueArg {
+    /// Event count (event count & iToT mode)
+    EventCount,
+    /// Integrated `ToT` (event count & iToT mode)
+    Itot,
+    /// Number of pixel packets
+    Packets,
+}
+
+/// File format of a TOF histogram.
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum HistogramFormat {
+    /// Multi-page TIFF stack, one page per TOF bin (`ImageJ` compatible)
+    Tiff,
+    /// FITS cube (or image) with WCS axis keywords, for astronomy tooling
+    Fits,
+}
+
+/// Pixel type of TIFF pages.
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum BitDepthArg {
+    /// 16-bit unsigned (counts above 65535 are clamped)
+    #[value(name = "16")]
+    Bit16,
+    /// 32-bit unsigned
+    #[value(name = "32")]
+    Bit32,
+}
+
+/// Map written by the `fft` command.
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum FftMapArg {
+    /// Power spectrum of the mean-subtracted image
+    Power,
+    /// Autocorrelation, normalized to 1 at zero lag
+    Autocorrelation,
+}
+
+/// Neutron output file format.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum OutputFormat {
+    /// rustpix binary records
+    Bin,
+    /// CSV with a header row
+    Csv,
+    /// Packed records (.rpxd)
+    Rpxd,
+    /// Container of CRC-checked blocks (.rpxc), zstd-compressed with the zstd feature
+    Rpxc,
+    /// Legacy C++ (mcpevent2hist) event layout
+    Legacy,
+    /// `NeXus` `NXevent_data` that Mantid can load (requires the hdf5 feature)
+    Nexus,
+    /// Apache Parquet (requires the parquet feature)
+    Parquet,
+    /// ROOT `TTree` (requires the root feature)
+    Root,
+}
+
+impl OutputFormat {
+    /// File extension for `{ext}` in output templates.
+    fn extension(self) -> &'static str {
+        match self {
+            Self::Bin | Self::Legacy => "bin",
+            Self::Csv => "csv",
+            Self::Rpxd => "rpxd",
+            Self::Rpxc => "rpxc",
+            Self::Nexus => "nxs",
+            Self::Parquet => "parquet",
+            Self::Root => "root",
+        }
+    }
+
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Bin => "bin",
+            Self::Csv => "csv",
+            Self::Rpxd => "rpxd",
+            Self::Rpxc => "rpxc",
+            Self::Legacy => "legacy",
+            Self::Nexus => "nexus",
+            Self::Parquet => "parquet",
+            Self::Root => "root",
+        }
+    }
+}
+
+/// Clustering algorithm and parameters shared by the commands that cluster
+/// hits.
+#[derive(Args)]
+struct ClusteringArgs {
+    /// Clustering algorithm to use
+    #[arg(short, long, value_enum, default_value = "abs")]
+    algorithm: Algorithm,
+
+    /// Spatial radius for clustering (pixels)
+    #[arg(long, default_value = "5.0")]
+    radius: f64,
+
+    /// Temporal window for clustering (nanoseconds)
+    #[arg(long, default_value = "75.0")]
+    temporal_window_ns: f64,
+
+    /// Minimum cluster size
+    #[arg(long, default_value = "1")]
+    min_cluster_size: u16,
+}
+
+impl ClusteringArgs {
+    fn config(&self) -> ClusteringConfig {
+        ClusteringConfig {
+            radius: self.radius,
+            temporal_window_ns: self.temporal_window_ns,
+            min_cluster_size: self.min_cluster_size,
+            max_cluster_size: None,
+        }
+    }
 }
 
 /// High-performance pixel detector data processor.
@@ -57,77 +230,219 @@ struct Cli {
     command: Commands,
 }
 
+// Parsed once per invocation, so the size of `Process` does not matter.
+#[allow(clippy::large_enum_variant)]
 #[derive(Subcommand)]
 enum Commands {
     /// Process TPX3 files to extract neutron events
     Process {
-        /// Input TPX3 file(s)
-        #[arg(required = true)]
-        input: Vec<PathBuf>,
+        #[command(flatten)]
+        options: process::ProcessOptions,
+    },
 
-        /// Output file path
+    /// Show information about a TPX3 file
+    Info {
+        /// Input TPX3 file
+        input: PathBuf,
+    },
+
+    /// List the built-in instrument profiles
+    Instruments,
+
+    /// Export a frame-mode (shutter) TPX3 file as one image per frame
+    Frames {
+        /// Input TPX3 file
+        input: PathBuf,
+
+        /// Output image stack (little-endian u32, JSON sidecar written next to it)
         #[arg(short, long)]
         output: PathBuf,
 
-        /// Clustering algorithm to use
-        #[arg(short, long, value_enum, default_value = "abs")]
-        algorithm: Algorithm,
+        /// Value accumulated per pixel
+        #[arg(long, value_enum, default_value = "event-count")]
+        value: FrameValueArg,
 
-        /// Spatial radius for clustering (pixels)
-        #[arg(long, default_value = "5.0")]
-        radius: f64,
+        /// Write a checksum manifest (sizes and SHA-256) of the outputs
+        #[arg(long)]
+        checksum_manifest: Option<PathBuf>,
+    },
 
-        /// Temporal window for clustering (nanoseconds)
-        #[arg(long, default_value = "75.0")]
-        temporal_window_ns: f64,
+    /// Bin neutrons into a TOF x Y x X histogram and write it as an image stack
+    Histogram {
+        /// Input TPX3 file, or a neutron output of `process` (csv, bin or rpxd)
+        input: PathBuf,
 
-        /// Minimum cluster size
-        #[arg(long, default_value = "1")]
-        min_cluster_size: u16,
+        /// Output file
+        #[arg(short, long)]
+        output: PathBuf,
 
-        /// Enable out-of-core processing (pulse-bounded)
-        #[arg(long, default_value_t = true, action = clap::ArgAction::Set)]
-        out_of_core: bool,
+        /// Output format
+        #[arg(long, value_enum, default_value = "tiff")]
+        format: HistogramFormat,
 
-        /// Fraction of available memory to target for out-of-core processing
-        #[arg(long, default_value = "0.5")]
-        memory_fraction: f64,
+        /// Number of TOF bins (pages) over one TDC period
+        #[arg(long, default_value = "400")]
+        tof_bins: usize,
 
-        /// Explicit memory budget in bytes (overrides `memory_fraction`)
+        /// Bits per pixel of TIFF pages
+        #[arg(long, value_enum, default_value = "16")]
+        bit_depth: BitDepthArg,
+
+        /// Sum over TOF and write a single 2D image instead of a stack
         #[arg(long)]
-        memory_budget_bytes: Option<usize>,
+        projection: bool,
 
-        /// Worker threads for out-of-core slice processing
+        /// Pixel pitch in millimetres, written to FITS headers [default: the
+        /// instrument's, else 0.055]
         #[arg(long)]
-        parallelism: Option<usize>,
+        pixel_size_mm: Option<f64>,
 
-        /// Bounded queue depth for out-of-core pipeline stages
-        #[arg(long, default_value = "2")]
-        queue_depth: usize,
+        /// Instrument profile (see `rustpix instruments`) or profile JSON file
+        /// for the detector layout, TDC frequency and pixel pitch
+        #[arg(long, value_name = "NAME")]
+        instrument: Option<String>,
 
-        /// Enable async reader/worker pipeline
-        #[arg(long, default_value_t = false, action = clap::ArgAction::Set)]
-        async_io: bool,
+        #[command(flatten)]
+        clustering: ClusteringArgs,
+
+        /// Super-resolution factor of neutron positions: 1, 2, 4, 8 or 16
+        /// sub-pixels per detector pixel
+        #[arg(long, default_value_t = 8.0, value_parser = parse_super_resolution)]
+        super_resolution: f64,
+
+        /// Zero pixels that are hot only in some TOF bins: counts more than
+        /// this many standard deviations above the mean of the bin
+        #[arg(long, value_name = "SIGMA")]
+        tof_hot_sigma: Option<f64>,
+
+        /// TOF bins summed around each bin when looking for hot pixels
+        #[arg(long, default_value_t = 1, requires = "tof_hot_sigma")]
+        tof_hot_window: usize,
 
         /// Verbose output
         #[arg(short, long)]
         verbose: bool,
     },
 
-    /// Show information about a TPX3 file
-    Info {
+    /// Find the beam spot (direct beam center) for alignment checks
+    BeamSpot {
+        /// Input TPX3 file
+        input: PathBuf,
+
+        /// Fraction of the peak counts a pixel needs to belong to the spot
+        #[arg(long, default_value = "0.5")]
+        threshold: f64,
+
+        /// Reject spot pixels farther than this many standard deviations
+        #[arg(long, default_value = "3.0")]
+        outlier_sigma: f64,
+
+        /// Expected center as X,Y (detector pixels); exit with an error if
+        /// the spot is farther than --tolerance from it
+        #[arg(long, value_name = "X,Y", value_parser = parse_point)]
+        expected: Option<(f64, f64)>,
+
+        /// Allowed distance from --expected (detector pixels)
+        #[arg(long, default_value = "2.0")]
+        tolerance: f64,
+
+        /// Also write the result as JSON
+        #[arg(long)]
+        json: Option<PathBuf>,
+    },
+
+    /// Write radial profiles or azimuthal integrations of the hit image as CSV
+    RadialProfile {
         /// Input TPX3 file
         input: PathBuf,
+
+        /// Output CSV file
+        #[arg(short, long)]
+        output: PathBuf,
+
+        /// Center as X,Y (detector pixels); defaults to the beam spot
+        #[arg(long, value_name = "X,Y", value_parser = parse_point)]
+        center: Option<(f64, f64)>,
+
+        /// Ring width (pixels)
+        #[arg(long, default_value = "1.0")]
+        bin_width: f64,
+
+        /// Integrate over azimuth into this many sectors instead of rings
+        #[arg(long)]
+        sectors: Option<usize>,
+
+        /// Inner radius for azimuthal integration (pixels)
+        #[arg(long, default_value = "0.0")]
+        r_min: f64,
+
+        /// Outer radius for azimuthal integration (pixels, default: no limit)
+        #[arg(long)]
+        r_max: Option<f64>,
+
+        /// Write one profile per TOF bin, with this many bins
+        #[arg(long)]
+        tof_bins: Option<usize>,
+    },
+
+    /// Find periodic artifacts in the hit image from its 2D power spectrum
+    Fft {
+        /// Input TPX3 file
+        input: PathBuf,
+
+        /// Output map (little-endian f32, JSON sidecar written next to it)
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+
+        /// Map written to --output
+        #[arg(long, value_enum, default_value = "power")]
+        map: FftMapArg,
+
+        /// Number of periodic peaks to report
+        #[arg(long, default_value = "5")]
+        peaks: usize,
+
+        /// Also write the peaks as JSON
+        #[arg(long)]
+        json: Option<PathBuf>,
+    },
+
+    /// Find the shift between the hit images of two runs (e.g. before and
+    /// after a sample change) by phase correlation
+    Register {
+        /// Reference TPX3 file
+        reference: PathBuf,
+
+        /// TPX3 file whose shift against the reference is measured
+        moving: PathBuf,
+
+        /// Also write the shift as JSON
+        #[arg(long)]
+        json: Option<PathBuf>,
     },
 
     /// Benchmark clustering algorithms
     Benchmark {
         /// Input TPX3 file
-        input: PathBuf,
+        #[arg(required_unless_present = "synthetic")]
+        input: Option<PathBuf>,
 
         /// Number of iterations
         #[arg(short, long, default_value = "3")]
         iterations: usize,
+
+        /// Benchmark on a generated pulse instead of a file (60 Hz pulse,
+        /// events of about 4 hits)
+        #[arg(long, conflicts_with = "input")]
+        synthetic: bool,
+
+        /// Event rate of the generated pulse (events per second)
+        #[arg(long, default_value = "1000000", requires = "synthetic")]
+        event_rate: f64,
+
+        /// Fraction of noise hits in the generated pulse
+        #[arg(long, default_value = "0.05", requires = "synthetic")]
+        noise_fraction: f64,
     },
 
     /// Benchmark out-of-core single vs multi-threaded processing
@@ -135,21 +450,8 @@ enum Commands {
         /// Input TPX3 file
         input: PathBuf,
 
-        /// Clustering algorithm to use
-        #[arg(short, long, value_enum, default_value = "abs")]
-        algorithm: Algorithm,
-
-        /// Spatial radius for clustering (pixels)
-        #[arg(long, default_value = "5.0")]
-        radius: f64,
-
-        /// Temporal window for clustering (nanoseconds)
-        #[arg(long, default_value = "75.0")]
-        temporal_window_ns: f64,
-
-        /// Minimum cluster size
-        #[arg(long, default_value = "1")]
-        min_cluster_size: u16,
+        #[command(flatten)]
+        clustering: ClusteringArgs,
 
         /// Number of benchmark iterations
         #[arg(short, long, default_value = "3")]
@@ -178,259 +480,563 @@ enum Commands {
 
     /// Ordering benchmark (deprecated; no-op)
     OrderingBenchmark,
+
+    /// Pair sample and open-beam runs and write transmission stacks
+    Transmission {
+        /// CSV manifest with `sample,open_beam[,name]` rows
+        #[arg(long, conflicts_with = "input")]
+        manifest: Option<PathBuf>,
+
+        /// Input TPX3 files to pair by file-name convention
+        #[arg(required_unless_present = "manifest")]
+        input: Vec<PathBuf>,
+
+        /// Output directory for transmission stacks
+        #[arg(short, long)]
+        output: PathBuf,
+
+        /// Number of TOF bins
+        #[arg(long, default_value = "200")]
+        tof_bins: usize,
+
+        /// TDC frequency in Hz
+        #[arg(long, default_value = "60.0")]
+        tdc_frequency: f64,
+
+        #[command(flatten)]
+        clustering: ClusteringArgs,
+
+        /// Write a checksum manifest (sizes and SHA-256) of the outputs
+        #[arg(long)]
+        checksum_manifest: Option<PathBuf>,
+
+        /// Shift each sample onto its open beam (phase correlation) first
+        #[arg(long)]
+        align: bool,
+
+        /// Verbose output
+        #[arg(short, long)]
+        verbose: bool,
+    },
+
+    /// Compare two neutron outputs, or two algorithms on one TPX3 file
+    Diff {
+        /// Reference neutron output (CSV or binary), or a TPX3 file
+        reference: PathBuf,
+
+        /// Candidate neutron output (omit to compare algorithms on a TPX3 reference)
+        candidate: Option<PathBuf>,
+
+        /// Clustering algorithm for the reference run (TPX3 input)
+        #[arg(short, long, value_enum, default_value = "abs")]
+        algorithm: Algorithm,
+
+        /// Clustering algorithm for the candidate run (TPX3 input)
+        #[arg(long, value_enum)]
+        compare_algorithm: Option<Algorithm>,
+
+        /// Spatial radius for clustering (pixels)
+        #[arg(long, default_value = "5.0")]
+        radius: f64,
+
+        /// Temporal window for clustering (nanoseconds)
+        #[arg(long, default_value = "75.0")]
+        temporal_window_ns: f64,
+
+        /// Minimum cluster size
+        #[arg(long, default_value = "1")]
+        min_cluster_size: u16,
+
+        /// Maximum |dx|/|dy| for a match, in output position units
+        #[arg(long, default_value = "8.0")]
+        position_tolerance: f64,
+
+        /// Maximum |dTOF| for a match, in 25 ns ticks
+        #[arg(long, default_value = "4")]
+        tof_tolerance: u32,
+
+        /// Factor applied to reference positions (e.g. to match super-resolution)
+        #[arg(long, default_value = "1.0")]
+        reference_scale: f64,
+
+        /// Factor applied to candidate positions
+        #[arg(long, default_value = "1.0")]
+        candidate_scale: f64,
+
+        /// `--event-index` file of the reference output, giving its pulses
+        #[arg(long)]
+        reference_index: Option<PathBuf>,
+
+        /// `--event-index` file of the candidate output, giving its pulses
+        #[arg(long)]
+        candidate_index: Option<PathBuf>,
+
+        /// Number of TOF bins for the chi-square / KS spectrum comparison
+        #[arg(long, default_value = "200")]
+        spectrum_bins: usize,
+
+        /// Shift candidate positions onto the reference (phase correlation) before matching
+        #[arg(long)]
+        align: bool,
+
+        /// Also write the report as JSON
+        #[arg(long)]
+        json: Option<PathBuf>,
+    },
+
+    /// Re-open outputs and check their internal consistency; exits non-zero
+    /// if any check fails
+    VerifyOutput {
+        /// Outputs of `process` (event files, HDF5/NeXus, or split indexes
+        /// `*.index.json`)
+        #[arg(required_unless_present = "manifest")]
+        outputs: Vec<PathBuf>,
+
+        /// Format of the outputs (default: detected from contents and extension)
+        #[arg(long, value_enum)]
+        format: Option<OutputFormat>,
+
+        /// Checksum manifest (from --checksum-manifest) to check files against
+        #[arg(long)]
+        manifest: Option<PathBuf>,
+    },
+
+    /// Process TPX3 files with an interactive terminal dashboard
+    Tui {
+        /// Input TPX3 file(s)
+        #[arg(required = true)]
+        input: Vec<PathBuf>,
+
+        /// Output file path
+        #[arg(short, long)]
+        output: PathBuf,
+
+        #[command(flatten)]
+        clustering: ClusteringArgs,
+
+        /// Super-resolution factor of neutron positions: 1, 2, 4, 8 or 16
+        /// sub-pixels per detector pixel
+        #[arg(long, default_value_t = 8.0, value_parser = parse_super_resolution)]
+        super_resolution: f64,
+
+        /// Fraction of available memory to target for out-of-core processing
+        #[arg(long, default_value = "0.5")]
+        memory_fraction: f64,
+
+        /// Worker threads for out-of-core slice processing
+        #[arg(long)]
+        parallelism: Option<usize>,
+
+        /// Region rate alarm as NAME:X0,Y0,X1,Y1:MIN[:MAX] (detector pixels,
+        /// neutrons/s; leave MIN empty for an upper bound only). Repeatable.
+        #[arg(long = "alarm", value_name = "SPEC")]
+        alarms: Vec<alarms::RateAlarm>,
+
+        /// Detector-time window over which alarm rates are measured (seconds)
+        #[arg(long, default_value = "1.0")]
+        alarm_window: f64,
+
+        /// Append alarm and clear events to this file as they happen
+        #[arg(long, value_name = "PATH")]
+        alarm_log: Option<PathBuf>,
+    },
+
+    /// Watch a directory (or one file) and process TPX3 files as the DAQ
+    /// writes them
+    Watch {
+        /// Directory to watch for `.tpx3` files, or a single TPX3 file to
+        /// follow
+        input: PathBuf,
+
+        /// Output file that neutrons from all files are appended to (bin,
+        /// csv, legacy or rpxd, from the extension)
+        #[arg(short, long)]
+        output: PathBuf,
+
+        #[command(flatten)]
+        clustering: ClusteringArgs,
+
+        /// Super-resolution factor of neutron positions: 1, 2, 4, 8 or 16
+        /// sub-pixels per detector pixel
+        #[arg(long, default_value_t = 8.0, value_parser = parse_super_resolution)]
+        super_resolution: f64,
+
+        /// Seconds between directory polls
+        #[arg(long, default_value = "1.0")]
+        poll_interval: f64,
+
+        /// Seconds without growth after which a file counts as complete and
+        /// its last pulses are processed
+        #[arg(long, default_value = "30.0")]
+        settle_time: f64,
+
+        /// Process the files present now as complete files, then exit
+        #[arg(long)]
+        once: bool,
+
+        /// Instrument profile (see `rustpix instruments`) or profile JSON file
+        /// for files without a sidecar detector config (`<stem>.json`)
+        #[arg(long, value_name = "NAME")]
+        instrument: Option<String>,
+
+        /// Replace the output if it exists and is not empty, instead of
+        /// refusing to start
+        #[arg(long)]
+        overwrite: bool,
+
+        /// Verbose output
+        #[arg(short, long)]
+        verbose: bool,
+    },
+
+    /// Replay a macro recorded in the GUI (load, parameters, process, export)
+    RunMacro {
+        /// Macro JSON file saved from the GUI command history
+        macro_file: PathBuf,
+
+        /// Process this TPX3 file instead of the recorded one(s)
+        #[arg(short, long)]
+        input: Option<PathBuf>,
+
+        /// Verbose output
+        #[arg(short, long)]
+        verbose: bool,
+    },
 }
 
+#[allow(clippy::too_many_lines)]
 fn main() -> Result<()> {
     let cli = Cli::parse();
 
     match cli.command {
-        Commands::Process {
-            input,
-            output,
-            algorithm,
-            radius,
-            temporal_window_ns,
-            min_cluster_size,
-            out_of_core,
-            memory_fraction,
-            memory_budget_bytes,
-            parallelism,
-            queue_depth,
-            async_io,
-            verbose,
-        } => run_process(
-            &input,
-            &output,
-            algorithm,
-            radius,
-            temporal_window_ns,
-            min_cluster_size,
-            out_of_core,
-            memory_fraction,
-            memory_budget_bytes,
-            parallelism,
-            queue_depth,
-            async_io,
-            verbose,
-        ),
+        Commands::Process { options } => process::run(&options),
 
         Commands::Info { input } => run_info(&input),
 
-        Commands::Benchmark { input, iterations } => run_benchmark(&input, iterations),
+        Commands::Instruments => {
+            run_instruments();
+            Ok(())
+        }
 
-        Commands::OutOfCoreBenchmark {
+        Commands::Frames {
             input,
-            algorithm,
-            radius,
-            temporal_window_ns,
-            min_cluster_size,
-            iterations,
-            memory_fraction,
-            memory_budget_bytes,
-            parallelism,
-            queue_depth,
-            async_io,
-        } => run_out_of_core_benchmark(
-            &input,
-            algorithm,
-            radius,
-            temporal_window_ns,
-            min_cluster_size,
-            iterations,
-            memory_fraction,
-            memory_budget_bytes,
-            parallelism,
-            queue_depth,
-            async_io,
-        ),
-
-        Commands::OrderingBenchmark => run_ordering_benchmark(),
-    }
-}
+            output,
+            value,
+            checksum_manifest,
+        } => run_frames(&input, &output, value, checksum_manifest.as_deref()),
 
-#[allow(clippy::too_many_arguments)]
-fn run_process(
-    input: &[PathBuf],
-    output: &PathBuf,
-    algorithm: Algorithm,
-    radius: f64,
-    temporal_window_ns: f64,
-    min_cluster_size: u16,
-    out_of_core: bool,
-    memory_fraction: f64,
-    memory_budget_bytes: Option<usize>,
-    parallelism: Option<usize>,
-    queue_depth: usize,
-    async_io: bool,
-    verbose: bool,
-) -> Result<()> {
-    if verbose {
-        eprintln!("Processing {} file(s)...", input.len());
-        eprintln!("Algorithm: {algorithm:?}");
-        eprintln!("Radius: {radius} pixels");
-        eprintln!("Temporal window: {temporal_window_ns} ns");
-        eprintln!("Min cluster size: {min_cluster_size}");
-        eprintln!("Out-of-core: {out_of_core}");
-        if out_of_core {
-            eprintln!("Memory fraction: {memory_fraction}");
-            if let Some(bytes) = memory_budget_bytes {
-                eprintln!("Memory budget override: {bytes} bytes");
+        Commands::Histogram {
+            input,
+            output,
+            format,
+            tof_bins,
+            bit_depth,
+            projection,
+            pixel_size_mm,
+            instrument,
+            clustering,
+            super_resolution,
+            tof_hot_sigma,
+            tof_hot_window,
+            verbose,
+        } => {
+            if tof_bins == 0 {
+                return Err(CliError::InvalidInput(
+                    "--tof-bins must be positive".to_string(),
+                ));
             }
-            if let Some(threads) = parallelism {
-                eprintln!("Parallelism: {threads} threads");
+            let instrument = instrument.as_deref().map(instrument_profile).transpose()?;
+            let pixel_size_mm = pixel_size_mm
+                .or(instrument.as_ref().map(|profile| profile.pixel_pitch_mm))
+                .unwrap_or(0.055);
+            if !(pixel_size_mm.is_finite() && pixel_size_mm > 0.0) {
+                return Err(CliError::InvalidInput(
+                    "--pixel-size-mm must be positive".to_string(),
+                ));
+            }
+            if tof_hot_sigma.is_some_and(|sigma| !(sigma.is_finite() && sigma > 0.0)) {
+                return Err(CliError::InvalidInput(
+                    "--tof-hot-sigma must be positive".to_string(),
+                ));
+            }
+            if tof_hot_window == 0 {
+                return Err(CliError::InvalidInput(
+                    "--tof-hot-window must be positive".to_string(),
+                ));
             }
-            eprintln!("Queue depth: {queue_depth}");
-            eprintln!("Async IO: {async_io}");
+            let settings = histogram::HistogramSettings {
+                format,
+                tof_bins,
+                projection,
+                pixel_size_mm,
+                detector: instrument
+                    .map_or_else(DetectorConfig::default, |profile| profile.detector),
+                bit_depth: match bit_depth {
+                    BitDepthArg::Bit16 => rustpix_io::TiffBitDepth::Bit16,
+                    BitDepthArg::Bit32 => rustpix_io::TiffBitDepth::Bit32,
+                },
+                algorithm: resolve_algorithm(clustering.algorithm),
+                clustering: clustering.config(),
+                extraction: ExtractionConfig::default().with_super_resolution(super_resolution),
+                params: AlgorithmParams::default(),
+                tof_hot_pixels: tof_hot_sigma.map(|sigma| (tof_hot_window, sigma)),
+                verbose,
+            };
+            histogram::run(&input, &output, &settings)
         }
-    }
 
-    let start = Instant::now();
-    let algo = resolve_algorithm(algorithm);
-    let clustering = ClusteringConfig {
-        radius,
-        temporal_window_ns,
-        min_cluster_size,
-        max_cluster_size: None,
-    };
-    let extraction = ExtractionConfig::default();
-    let params = AlgorithmParams::default();
+        Commands::BeamSpot {
+            input,
+            threshold,
+            outlier_sigma,
+            expected,
+            tolerance,
+            json,
+        } => {
+            let config = BeamSpotConfig {
+                threshold_fraction: threshold,
+                outlier_sigma,
+                ..BeamSpotConfig::default()
+            };
+            run_beam_spot(&input, &config, expected, tolerance, json.as_deref())
+        }
 
-    let mut writer = rustpix_io::DataFileWriter::create(output)?;
-    if verbose {
-        eprintln!("Writing output to: {}", output.display());
-    }
-    let output_format = output
-        .extension()
-        .and_then(|ext| ext.to_str())
-        .map_or_else(|| "bin".to_string(), str::to_lowercase);
-    let mut wrote_header = false;
-    let mut warned_unknown = false;
+        Commands::RadialProfile {
+            input,
+            output,
+            center,
+            bin_width,
+            sectors,
+            r_min,
+            r_max,
+            tof_bins,
+        } => run_radial_profile(
+            &input,
+            &output,
+            center,
+            bin_width,
+            sectors
+                .map(|sectors| (sectors, r_min..r_max.unwrap_or(f64::INFINITY)))
+                .as_ref(),
+            tof_bins,
+        ),
 
-    let mut total_hits = 0usize;
-    let mut total_neutrons = 0usize;
-    for path in input {
-        if verbose {
-            eprintln!("Reading: {}", path.display());
+        Commands::Fft {
+            input,
+            output,
+            map,
+            peaks,
+            json,
+        } => run_fft(&input, output.as_deref(), map, peaks, json.as_deref()),
+
+        Commands::Register {
+            reference,
+            moving,
+            json,
+        } => run_register(&reference, &moving, json.as_deref()),
+
+        Commands::Benchmark {
+            input,
+            iterations,
+            synthetic,
+            event_rate,
+            noise_fraction,
+        } => {
+            let base_batch = match input {
+                Some(input) if !synthetic => Tpx3FileReader::open(input)?.read_batch()?,
+                _ => synthetic_pulse(event_rate, noise_fraction)?,
+            };
+            run_benchmark(&base_batch, iterations)
         }
 
-        let (file_hits, file_neutrons) = process_input_file(
-            path,
-            algo,
+        Commands::OutOfCoreBenchmark {
+            input,
+            clustering,
+            iterations,
+            memory_fraction,
+            memory_budget_bytes,
+            parallelism,
+            queue_depth,
+            async_io,
+        } => run_out_of_core_benchmark(
+            &input,
             &clustering,
-            &extraction,
-            &params,
-            &mut writer,
-            &output_format,
-            &mut wrote_header,
-            &mut warned_unknown,
-            out_of_core,
+            iterations,
             memory_fraction,
             memory_budget_bytes,
             parallelism,
             queue_depth,
             async_io,
-            verbose,
-        )?;
+        ),
 
-        total_hits = total_hits.saturating_add(file_hits);
-        total_neutrons = total_neutrons.saturating_add(file_neutrons);
+        Commands::OrderingBenchmark => run_ordering_benchmark(),
 
-        if verbose {
-            eprintln!("  {file_hits} hits processed");
-            eprintln!("  {file_neutrons} neutrons extracted");
-        }
-    }
+        Commands::Transmission {
+            manifest,
+            input,
+            output,
+            tof_bins,
+            tdc_frequency,
+            clustering,
+            checksum_manifest,
+            align,
+            verbose,
+        } => run_transmission(
+            manifest.as_deref(),
+            &input,
+            &output,
+            tof_bins,
+            tdc_frequency,
+            clustering.algorithm,
+            &clustering.config(),
+            checksum_manifest.as_deref(),
+            align,
+            verbose,
+        ),
 
-    let elapsed = start.elapsed();
-    println!(
-        "Processed {} files in {:.2}s",
-        input.len(),
-        elapsed.as_secs_f64()
-    );
-    println!("Total hits: {total_hits}");
-    println!("Total neutrons: {total_neutrons}");
-    Ok(())
-}
+        Commands::Diff {
+            reference,
+            candidate,
+            algorithm,
+            compare_algorithm,
+            radius,
+            temporal_window_ns,
+            min_cluster_size,
+            position_tolerance,
+            tof_tolerance,
+            reference_scale,
+            candidate_scale,
+            reference_index,
+            candidate_index,
+            spectrum_bins,
+            align,
+            json,
+        } => {
+            let clustering = ClusteringConfig {
+                radius,
+                temporal_window_ns,
+                min_cluster_size,
+                max_cluster_size: None,
+            };
+            run_diff(
+                &reference,
+                candidate.as_deref(),
+                algorithm,
+                compare_algorithm,
+                &clustering,
+                diff::Tolerance {
+                    position: position_tolerance,
+                    tof: tof_tolerance,
+                },
+                (reference_scale, candidate_scale),
+                (reference_index.as_deref(), candidate_index.as_deref()),
+                spectrum_bins,
+                align,
+                json.as_deref(),
+            )
+        }
 
-#[allow(clippy::too_many_arguments)]
-fn process_input_file(
-    path: &PathBuf,
-    algo: ClusteringAlgorithm,
-    clustering: &ClusteringConfig,
-    extraction: &ExtractionConfig,
-    params: &AlgorithmParams,
-    writer: &mut rustpix_io::DataFileWriter,
-    output_format: &str,
-    wrote_header: &mut bool,
-    warned_unknown: &mut bool,
-    out_of_core: bool,
-    memory_fraction: f64,
-    memory_budget_bytes: Option<usize>,
-    parallelism: Option<usize>,
-    queue_depth: usize,
-    async_io: bool,
-    verbose: bool,
-) -> Result<(usize, usize)> {
-    let reader = Tpx3FileReader::open(path)?;
-    let mut file_hits = 0usize;
-    let mut file_neutrons = 0usize;
+        Commands::VerifyOutput {
+            outputs,
+            format,
+            manifest,
+        } => verify::run(&outputs, format, manifest.as_deref()),
 
-    if out_of_core {
-        let mut memory = OutOfCoreConfig::default().with_memory_fraction(memory_fraction);
-        if let Some(bytes) = memory_budget_bytes {
-            memory = memory.with_memory_budget_bytes(bytes);
-        }
-        if let Some(threads) = parallelism {
-            memory = memory.with_parallelism(threads);
+        Commands::Tui {
+            input,
+            output,
+            clustering,
+            super_resolution,
+            memory_fraction,
+            parallelism,
+            alarms,
+            alarm_window,
+            alarm_log,
+        } => {
+            if !(alarm_window.is_finite() && alarm_window > 0.0) {
+                return Err(CliError::InvalidInput(
+                    "--alarm-window must be a positive number of seconds".to_string(),
+                ));
+            }
+            let mut memory = OutOfCoreConfig::default().with_memory_fraction(memory_fraction);
+            if let Some(threads) = parallelism {
+                memory = memory.with_parallelism(threads);
+            }
+            let settings = tui::TuiSettings {
+                algorithm: resolve_algorithm(clustering.algorithm),
+                clustering: clustering.config(),
+                extraction: ExtractionConfig::default().with_super_resolution(super_resolution),
+                params: AlgorithmParams::default(),
+                memory,
+                alarms,
+                alarm_window: std::time::Duration::from_secs_f64(alarm_window),
+                alarm_log,
+            };
+            tui::run(&input, &output, settings)
         }
-        memory = memory.with_queue_depth(queue_depth).with_async_io(async_io);
-
-        let stream =
-            out_of_core_neutron_stream(&reader, algo, clustering, extraction, params, &memory)?;
-
-        for batch in stream {
-            let batch = batch?;
-            file_hits = file_hits.saturating_add(batch.hits_processed);
-            file_neutrons = file_neutrons.saturating_add(batch.neutrons.len());
-            write_neutrons(
-                writer,
-                output_format,
-                &batch.neutrons,
-                wrote_header,
-                warned_unknown,
+
+        Commands::Watch {
+            input,
+            output,
+            clustering,
+            super_resolution,
+            poll_interval,
+            settle_time,
+            once,
+            instrument,
+            overwrite,
+            verbose,
+        } => {
+            let seconds = |value: f64, flag: &str| {
+                Duration::try_from_secs_f64(value).map_err(|_| {
+                    CliError::InvalidInput(format!(
+                        "{flag} must be a non-negative number of seconds"
+                    ))
+                })
+            };
+            let settings = watch::WatchSettings {
+                algorithm: resolve_algorithm(clustering.algorithm),
+                clustering: clustering.config(),
+                extraction: ExtractionConfig::default().with_super_resolution(super_resolution),
+                params: AlgorithmParams::default(),
+                detector: instrument
+                    .as_deref()
+                    .map(instrument_profile)
+                    .transpose()?
+                    .map_or_else(DetectorConfig::default, |profile| profile.detector),
+                poll_interval: seconds(poll_interval, "--poll-interval")?,
+                settle_time: seconds(settle_time, "--settle-time")?,
+                once,
+                overwrite,
                 verbose,
-            )?;
+            };
+            watch::run(&input, &output, &settings)
         }
-    } else {
-        let stream = reader.stream_time_ordered()?;
-        for mut batch in stream {
-            file_hits = file_hits.saturating_add(batch.len());
-            let neutrons =
-                cluster_and_extract_batch(&mut batch, algo, clustering, extraction, params)?;
-            file_neutrons = file_neutrons.saturating_add(neutrons.len());
-            write_neutrons(
-                writer,
-                output_format,
-                &neutrons,
-                wrote_header,
-                warned_unknown,
-                verbose,
-            )?;
+
+        Commands::RunMacro {
+            macro_file,
+            input,
+            verbose,
+        } => {
+            let recorded = macros::load_macro(&macro_file, input.as_deref())?;
+            macros::run(&recorded, verbose)
         }
     }
-
-    Ok((file_hits, file_neutrons))
 }
 
 fn write_neutrons(
     writer: &mut rustpix_io::DataFileWriter,
     output_format: &str,
-    neutrons: &rustpix_core::neutron::NeutronBatch,
+    neutrons: &NeutronBatch,
+    super_resolution_factor: f64,
     wrote_header: &mut bool,
     warned_unknown: &mut bool,
     verbose: bool,
-) -> Result<()> {
+) -> rustpix_io::Result<()> {
     match output_format {
+        "legacy" => {
+            writer.write_neutron_batch_legacy(neutrons, super_resolution_factor)?;
+        }
         "csv" => {
             writer.write_neutron_batch_csv(neutrons, !*wrote_header)?;
             *wrote_header = true;
@@ -438,6 +1044,10 @@ fn write_neutrons(
         "bin" | "dat" => {
             writer.write_neutron_batch_binary(neutrons)?;
         }
+        "rpxd" => {
+            writer
+                .write_neutron_batch_packed(neutrons, rustpix_io::DEFAULT_PACKED_POSITION_SCALE)?;
+        }
         _ => {
             if verbose && !*warned_unknown {
                 eprintln!("Unknown extension '{output_format}', defaulting to binary");
@@ -450,16 +1060,138 @@ fn write_neutrons(
     Ok(())
 }
 
+/// Returns the URI if `path` names an object in remote storage.
+fn remote_uri(path: &Path) -> Option<&str> {
+    path.to_str().filter(|uri| uri.starts_with("s3://"))
+}
+
+/// Open a TPX3 input from a local path or an `s3://` URI.
+fn open_reader(path: &Path) -> Result<Tpx3FileReader> {
+    match remote_uri(path) {
+        #[cfg(feature = "object-store")]
+        Some(uri) => Ok(rustpix_io::remote::open_tpx3(uri)?),
+        #[cfg(not(feature = "object-store"))]
+        Some(uri) => Err(object_store_disabled(uri)),
+        None => Ok(Tpx3FileReader::open(path)?),
+    }
+}
+
+/// Resolve an `--instrument` name or profile file.
+fn instrument_profile(spec: &str) -> Result<InstrumentProfile> {
+    InstrumentProfile::lookup(spec).map_err(|err| CliError::InvalidInput(err.to_string()))
+}
+
+#[cfg(not(feature = "object-store"))]
+fn object_store_disabled(uri: &str) -> CliError {
+    CliError::InvalidInput(format!(
+        "{uri}: rustpix was built without the object-store feature"
+    ))
+}
+
 fn resolve_algorithm(algorithm: Algorithm) -> ClusteringAlgorithm {
     match algorithm {
         Algorithm::Abs => ClusteringAlgorithm::Abs,
         Algorithm::Dbscan => ClusteringAlgorithm::Dbscan,
         Algorithm::Grid => ClusteringAlgorithm::Grid,
+        #[cfg(feature = "gpu")]
+        Algorithm::Gpu => ClusteringAlgorithm::Gpu,
+        Algorithm::Streaming => ClusteringAlgorithm::Streaming,
+        Algorithm::Hierarchical => ClusteringAlgorithm::Hierarchical,
+        Algorithm::ConnectedComponents => ClusteringAlgorithm::ConnectedComponents,
     }
 }
 
-fn run_info(input: &PathBuf) -> Result<()> {
-    let reader = Tpx3FileReader::open(input)?;
+fn run_frames(
+    input: &Path,
+    output: &Path,
+    value: FrameValueArg,
+    checksum_manifest: Option<&Path>,
+) -> Result<()> {
+    let reader = open_reader(input)?;
+    let counts = reader.frame_packet_counts();
+    if counts.frame_pixels == 0 {
+        return Err(CliError::InvalidInput(format!(
+            "{} has no frame-based pixel packets ({} data-driven hits); use `process` instead",
+            input.display(),
+            counts.hits
+        )));
+    }
+    let value = match value {
+        FrameValueArg::EventCount => FrameValue::EventCount,
+        FrameValueArg::Itot => FrameValue::IntegratedTot,
+        FrameValueArg::Packets => FrameValue::Packets,
+    };
+    let frame_images = reader.read_frames(value);
+    let written = frames::write_stack(output, input, &frame_images, value)?;
+    println!(
+        "Wrote {} frames to {}",
+        frame_images.len(),
+        output.display()
+    );
+    if let Some(manifest) = checksum_manifest {
+        checksum::write_for_files(manifest, &written)?;
+        println!("Wrote checksum manifest {}", manifest.display());
+    }
+    Ok(())
+}
+
+/// Sections with anomalous TDCs listed by `info`.
+const MAX_LISTED_TDC_SECTIONS: usize = 10;
+
+fn print_tdc_diagnostics(diagnostics: &TdcDiagnostics) {
+    let anomalous: Vec<_> = diagnostics.anomalous_sections().collect();
+    println!(
+        "TDC diagnostics: {} TDCs, {} missing, {} duplicate, {} irregular ({} of {} sections affected)",
+        diagnostics.tdcs(),
+        diagnostics.missing(),
+        diagnostics.duplicate(),
+        diagnostics.irregular(),
+        anomalous.len(),
+        diagnostics.sections.len()
+    );
+    for section in anomalous.iter().take(MAX_LISTED_TDC_SECTIONS) {
+        println!(
+            "  section {} (chip {}, offset {}): {} missing, {} duplicate, {} irregular",
+            section.section,
+            section.chip_id,
+            section.start_offset,
+            section.missing,
+            section.duplicate,
+            section.irregular
+        );
+    }
+    if anomalous.len() > MAX_LISTED_TDC_SECTIONS {
+        println!(
+            "  ... {} more sections",
+            anomalous.len() - MAX_LISTED_TDC_SECTIONS
+        );
+    }
+}
+
+/// Print the shipped instrument profiles, one per line.
+fn run_instruments() {
+    println!(
+        "{:<8} {:<14} {:>7} {:>10} {:>10}",
+        "NAME", "INSTRUMENT", "TDC Hz", "FLIGHT m", "PITCH mm"
+    );
+    for profile in rustpix_tpx::instrument::profiles() {
+        let flight_path = profile
+            .detector
+            .flight_path_m
+            .map_or_else(|| "-".to_string(), |length| format!("{length:.2}"));
+        println!(
+            "{:<8} {:<14} {:>7.1} {:>10} {:>10.3}",
+            profile.name,
+            profile.label(),
+            profile.detector.tdc_frequency_hz,
+            flight_path,
+            profile.pixel_pitch_mm
+        );
+    }
+}
+
+fn run_info(input: &Path) -> Result<()> {
+    let reader = open_reader(input)?;
     let file_size = reader.file_size();
     let packet_count = reader.packet_count();
 
@@ -470,6 +1202,32 @@ fn run_info(input: &PathBuf) -> Result<()> {
         usize_to_f64(file_size) / 1_000_000.0
     );
     println!("Packets: {packet_count}");
+    if let Some(estimate) = reader.estimate_tdc_frequency() {
+        println!(
+            "TDC frequency: {:.3} Hz (measured, chip {})",
+            estimate.frequency_hz, estimate.chip_id
+        );
+    }
+
+    if let Some(report) = reader.pulse_gaps() {
+        println!(
+            "Pulses: {} ({} dropped, {:.3} s beam time lost)",
+            report.pulses,
+            report.missing_pulses(),
+            report.lost_time().as_secs_f64()
+        );
+    }
+    if let Some(diagnostics) = reader.tdc_diagnostics() {
+        print_tdc_diagnostics(&diagnostics);
+    }
+
+    let frame_counts = reader.frame_packet_counts();
+    if frame_counts.frame_pixels > 0 {
+        println!(
+            "Frame mode: {} pixel packets, {} end-of-readout",
+            frame_counts.frame_pixels, frame_counts.end_of_readout
+        );
+    }
 
     let batch = reader.read_batch()?;
     println!("Hits: {}", batch.len());
@@ -490,10 +1248,288 @@ fn run_info(input: &PathBuf) -> Result<()> {
     Ok(())
 }
 
-fn run_benchmark(input: &PathBuf, iterations: usize) -> Result<()> {
-    let reader = Tpx3FileReader::open(input)?;
-    let base_batch = reader.read_batch()?;
+fn run_beam_spot(
+    input: &Path,
+    config: &BeamSpotConfig,
+    expected: Option<(f64, f64)>,
+    tolerance: f64,
+    json: Option<&Path>,
+) -> Result<()> {
+    let batch = open_reader(input)?.read_batch()?;
+    let spot = find_beam_spot_in_hits(&batch, config).ok_or_else(|| {
+        CliError::InvalidInput(format!(
+            "{} has no hits to locate a beam spot",
+            input.display()
+        ))
+    })?;
+
+    println!("File: {}", input.display());
+    println!("Beam center: ({:.2}, {:.2}) px", spot.x, spot.y);
+    println!(
+        "Spot size: sigma {:.2} x {:.2} px, {} pixels, {} hits",
+        spot.sigma_x, spot.sigma_y, spot.pixels, spot.counts
+    );
+    if spot.rejected > 0 {
+        println!("Rejected outlier pixels: {}", spot.rejected);
+    }
+
+    let offset = expected.map(|(x, y)| (spot.x - x, spot.y - y));
+    if let Some((dx, dy)) = offset {
+        println!(
+            "Offset from expected: ({dx:+.2}, {dy:+.2}) px, distance {:.2} px (tolerance {tolerance})",
+            dx.hypot(dy)
+        );
+    }
+
+    if let Some(path) = json {
+        let mut report = serde_json::json!({
+            "input": input.display().to_string(),
+            "x": spot.x,
+            "y": spot.y,
+            "sigma_x": spot.sigma_x,
+            "sigma_y": spot.sigma_y,
+            "pixels": spot.pixels,
+            "rejected_pixels": spot.rejected,
+            "hits": spot.counts,
+        });
+        if let (Some((x, y)), Some((dx, dy))) = (expected, offset) {
+            report["expected"] = serde_json::json!([x, y]);
+            report["offset"] = serde_json::json!([dx, dy]);
+            report["within_tolerance"] = serde_json::json!(dx.hypot(dy) <= tolerance);
+        }
+        std::fs::write(path, serde_json::to_string_pretty(&report)?)?;
+        println!("Report written to {}", path.display());
+    }
+
+    if let Some((dx, dy)) = offset {
+        if dx.hypot(dy) > tolerance {
+            return Err(CliError::InvalidInput(format!(
+                "beam spot is {:.2} px from the expected center (tolerance {tolerance})",
+                dx.hypot(dy)
+            )));
+        }
+    }
+    Ok(())
+}
+
+fn run_radial_profile(
+    input: &Path,
+    output: &Path,
+    center: Option<(f64, f64)>,
+    bin_width: f64,
+    azimuthal: Option<&(usize, std::ops::Range<f64>)>,
+    tof_bins: Option<usize>,
+) -> Result<()> {
+    if !(bin_width.is_finite() && bin_width > 0.0) {
+        return Err(CliError::InvalidInput(
+            "--bin-width must be positive".to_string(),
+        ));
+    }
+    if azimuthal.is_some_and(|(sectors, _)| *sectors == 0) {
+        return Err(CliError::InvalidInput(
+            "--sectors must be positive".to_string(),
+        ));
+    }
+    let reader = open_reader(input)?;
+    let (width, height) = reader.config().detector_dimensions();
+    let batch = reader.read_batch()?;
+    let images = profile::TofImages::from_hits(&batch, width, height, tof_bins.unwrap_or(1));
+    let total = images.total();
+
+    let (cx, cy) = if let Some(center) = center {
+        center
+    } else {
+        let spot =
+            find_beam_spot(&total, width, height, &BeamSpotConfig::default()).ok_or_else(|| {
+                CliError::InvalidInput(format!(
+                    "{} has no hits to locate a beam spot; pass --center",
+                    input.display()
+                ))
+            })?;
+        (spot.x, spot.y)
+    };
+    println!("File: {}", input.display());
+    println!("Center: ({cx:.2}, {cy:.2}) px");
+
+    let integrate = |image: &[u64]| match azimuthal {
+        Some((sectors, radii)) => {
+            azimuthal_profile(image, width, height, (cx, cy), radii.clone(), *sectors)
+        }
+        None => radial_profile(image, width, height, (cx, cy), bin_width),
+    };
+    let profiles: Vec<_> = if tof_bins.is_some() {
+        images.images.iter().map(|image| integrate(image)).collect()
+    } else {
+        vec![integrate(&total)]
+    };
+    profile::write_csv(
+        output,
+        &profiles,
+        azimuthal.is_some(),
+        tof_bins.map(|_| images.bin_ticks),
+    )?;
+    println!(
+        "Wrote {} profile(s) of {} bins to {}",
+        profiles.len(),
+        profiles
+            .first()
+            .map_or(0, rustpix_algorithms::IntegratedProfile::len),
+        output.display()
+    );
+    Ok(())
+}
+
+fn run_fft(
+    input: &Path,
+    output: Option<&Path>,
+    map: FftMapArg,
+    max_peaks: usize,
+    json: Option<&Path>,
+) -> Result<()> {
+    let reader = open_reader(input)?;
+    let (width, height) = reader.config().detector_dimensions();
+    let batch = reader.read_batch()?;
+    let image = profile::TofImages::from_hits(&batch, width, height, 1).total();
+    let spectrum = power_spectrum(&image, width, height);
+    let peaks = periodic_peaks(&spectrum, max_peaks);
+
+    println!("File: {}", input.display());
+    if peaks.is_empty() {
+        println!("No periodic peaks (flat or empty image)");
+    } else {
+        println!("Periodic peaks (strongest first):");
+        for peak in &peaks {
+            println!(
+                "  period {:8.2} px  angle {:5.1} deg  f = ({:+.4}, {:+.4}) cycles/px  {:5.1}% of power",
+                peak.period,
+                peak.angle_deg,
+                peak.fx,
+                peak.fy,
+                peak.fraction * 100.0
+            );
+        }
+    }
+
+    if let Some(output) = output {
+        let (values, kind) = match map {
+            FftMapArg::Power => (spectrum, "power"),
+            FftMapArg::Autocorrelation => {
+                (autocorrelation(&image, width, height), "autocorrelation")
+            }
+        };
+        let written = fourier::write_map(output, input, &values, kind, &peaks)?;
+        println!("Wrote {kind} map to {}", written[0].display());
+    }
+
+    if let Some(path) = json {
+        let report = serde_json::json!({
+            "input": input.display().to_string(),
+            "width": width,
+            "height": height,
+            "peaks": peaks.iter().map(fourier::peak_json).collect::<Vec<_>>(),
+        });
+        std::fs::write(path, serde_json::to_string_pretty(&report)?)?;
+        println!("Report written to {}", path.display());
+    }
+    Ok(())
+}
+
+/// Correlation peak below which `register` warns that the shift may be
+/// meaningless.
+const WEAK_CORRELATION_PEAK: f64 = 0.05;
+
+fn run_register(reference: &Path, moving: &Path, json: Option<&Path>) -> Result<()> {
+    let summed = |path: &Path| -> Result<(Vec<u64>, usize, usize)> {
+        let reader = open_reader(path)?;
+        let (width, height) = reader.config().detector_dimensions();
+        let batch = reader.read_batch()?;
+        let image = profile::TofImages::from_hits(&batch, width, height, 1).total();
+        Ok((image, width, height))
+    };
+    let (reference_image, width, height) = summed(reference)?;
+    let (moving_image, moving_width, moving_height) = summed(moving)?;
+    if (moving_width, moving_height) != (width, height) {
+        return Err(CliError::InvalidInput(format!(
+            "{}: detector is {moving_width}x{moving_height}, the reference is {width}x{height}",
+            moving.display()
+        )));
+    }
+    let shift = register_translation(&reference_image, &moving_image, width, height);
+
+    println!("Reference: {}", reference.display());
+    println!("Moving:    {}", moving.display());
+    let (dx, dy) = shift.whole_pixels();
+    println!(
+        "Shift: ({:+.2}, {:+.2}) px, ({dx:+}, {dy:+}) in whole pixels",
+        shift.dx, shift.dy
+    );
+    println!("Correlation peak: {:.3}", shift.peak);
+    if shift.peak < WEAK_CORRELATION_PEAK {
+        println!("  Weak correlation: the images may not show the same scene");
+    }
+
+    if let Some(path) = json {
+        let report = serde_json::json!({
+            "reference": reference.display().to_string(),
+            "moving": moving.display().to_string(),
+            "width": width,
+            "height": height,
+            "shift": shift,
+        });
+        std::fs::write(path, serde_json::to_string_pretty(&report)?)?;
+        println!("Report written to {}", path.display());
+    }
+    Ok(())
+}
+
+/// Parse a super-resolution factor, one of 1, 2, 4, 8 or 16.
+fn parse_super_resolution(value: &str) -> std::result::Result<f64, String> {
+    let factor = value
+        .trim()
+        .parse::<f64>()
+        .map_err(|_| format!("'{value}' is not a number"))?;
+    validate_super_resolution(factor).map_err(|err| err.to_string())?;
+    Ok(factor)
+}
+
+/// Parse an `X,Y` pair of coordinates.
+fn parse_point(value: &str) -> std::result::Result<(f64, f64), String> {
+    let (x, y) = value
+        .split_once(',')
+        .ok_or_else(|| format!("expected X,Y, got '{value}'"))?;
+    let coordinate = |text: &str| {
+        text.trim()
+            .parse::<f64>()
+            .map_err(|_| format!("'{text}' is not a number"))
+    };
+    Ok((coordinate(x)?, coordinate(y)?))
+}
+
+/// One generated pulse for `benchmark --synthetic`.
+fn synthetic_pulse(event_rate_hz: f64, noise_fraction: f64) -> Result<HitBatch> {
+    if !(event_rate_hz.is_finite() && event_rate_hz > 0.0) {
+        return Err(CliError::InvalidInput(
+            "--event-rate must be positive".to_string(),
+        ));
+    }
+    if !(0.0..1.0).contains(&noise_fraction) {
+        return Err(CliError::InvalidInput(
+            "--noise-fraction must be at least 0 and below 1".to_string(),
+        ));
+    }
+    let pulse = synthetic::generate(&SyntheticConfig {
+        event_rate_hz,
+        noise_fraction,
+        ..SyntheticConfig::default()
+    });
+    println!(
+        "Generated {} events and {} noise hits",
+        pulse.events, pulse.noise_hits
+    );
+    Ok(pulse.hits)
+}
 
+fn run_benchmark(base_batch: &HitBatch, iterations: usize) -> Result<()> {
     println!(
         "Benchmarking with {} hits, {} iterations",
         base_batch.len(),
@@ -504,7 +1540,22 @@ fn run_benchmark(input: &PathBuf, iterations: usize) -> Result<()> {
         (Algorithm::Abs, "ABS"),
         (Algorithm::Dbscan, "DBSCAN"),
         (Algorithm::Grid, "Grid"),
+        (Algorithm::Streaming, "Streaming"),
+        (Algorithm::Hierarchical, "Hierarchical"),
+        (Algorithm::ConnectedComponents, "Connected"),
     ];
+    #[cfg(feature = "gpu")]
+    let algorithms = {
+        let mut all = algorithms.to_vec();
+        match rustpix_algorithms::GpuClustering::new(rustpix_algorithms::GpuConfig::default()) {
+            Ok(gpu) => {
+                println!("GPU adapter: {}", gpu.adapter_name());
+                all.push((Algorithm::Gpu, "GPU"));
+            }
+            Err(err) => println!("Skipping GPU: {err}"),
+        }
+        all
+    };
 
     println!(
         "{:<10} | {:<15} | {:<15} | {:<15}",
@@ -513,8 +1564,8 @@ fn run_benchmark(input: &PathBuf, iterations: usize) -> Result<()> {
     println!("{:-<65}", "");
 
     for (algo_enum, name) in algorithms {
-        warmup_algorithm(algo_enum, &base_batch);
-        let times = benchmark_algorithm(algo_enum, &base_batch, iterations)?;
+        warmup_algorithm(algo_enum, base_batch);
+        let times = benchmark_algorithm(algo_enum, base_batch, iterations)?;
 
         let min_time = times.iter().fold(f64::INFINITY, |a, &b| a.min(b));
         let max_time = times.iter().fold(f64::NEG_INFINITY, |a, &b| a.max(b));
@@ -529,10 +1580,7 @@ fn run_benchmark(input: &PathBuf, iterations: usize) -> Result<()> {
 #[allow(clippy::too_many_arguments)]
 fn run_out_of_core_benchmark(
     input: &PathBuf,
-    algorithm: Algorithm,
-    radius: f64,
-    temporal_window_ns: f64,
-    min_cluster_size: u16,
+    clustering: &ClusteringArgs,
     iterations: usize,
     memory_fraction: f64,
     memory_budget_bytes: Option<usize>,
@@ -540,13 +1588,8 @@ fn run_out_of_core_benchmark(
     queue_depth: usize,
     async_io: bool,
 ) -> Result<()> {
-    let algo = resolve_algorithm(algorithm);
-    let clustering = ClusteringConfig {
-        radius,
-        temporal_window_ns,
-        min_cluster_size,
-        max_cluster_size: None,
-    };
+    let algo = resolve_algorithm(clustering.algorithm);
+    let clustering = clustering.config();
     let extraction = ExtractionConfig::default();
     let params = AlgorithmParams::default();
 
@@ -557,9 +1600,7 @@ fn run_out_of_core_benchmark(
 
     let mut multi_config = single_config.clone().with_queue_depth(queue_depth);
     let threads = parallelism.unwrap_or_else(|| {
-        std::thread::available_parallelism()
-            .map(std::num::NonZeroUsize::get)
-            .unwrap_or(1)
+        std::thread::available_parallelism().map_or(1, std::num::NonZeroUsize::get)
     });
     multi_config = multi_config.with_parallelism(threads);
     multi_config = multi_config.with_async_io(async_io);
@@ -690,10 +1731,177 @@ fn run_cluster_once(algo_enum: Algorithm, batch: &mut HitBatch) -> Result<()> {
             let mut state = GridState::default();
             let _ = algo.cluster(batch, &mut state)?;
         }
+        #[cfg(feature = "gpu")]
+        Algorithm::Gpu => {
+            let algo = rustpix_algorithms::GpuClustering::new(rustpix_algorithms::GpuConfig {
+                radius: 5.0,
+                temporal_window_ns: 75.0,
+                min_cluster_size: 1,
+                cell_size: 32,
+            })?;
+            let mut state = rustpix_algorithms::GpuState::default();
+            let _ = algo.cluster(batch, &mut state)?;
+        }
+        Algorithm::Streaming => {
+            let algo = StreamingClustering::new(rustpix_algorithms::StreamingConfig {
+                radius: 5.0,
+                temporal_window_ns: 75.0,
+                min_cluster_size: 1,
+            });
+            let mut state = StreamingState::default();
+            let _ = algo.cluster(batch, &mut state)?;
+        }
+        Algorithm::Hierarchical => {
+            let algo = rustpix_algorithms::HierarchicalClustering::default();
+            let mut state = rustpix_algorithms::HierarchicalState::default();
+            let _ = algo.cluster(batch, &mut state)?;
+        }
+        Algorithm::ConnectedComponents => {
+            let algo = rustpix_algorithms::ConnectedComponentClustering::default();
+            let mut state = rustpix_algorithms::ConnectedComponentState::default();
+            let _ = algo.cluster(batch, &mut state)?;
+        }
+    }
+    Ok(())
+}
+
+#[allow(clippy::too_many_arguments)]
+fn run_transmission(
+    manifest: Option<&std::path::Path>,
+    input: &[PathBuf],
+    output: &Path,
+    tof_bins: usize,
+    tdc_frequency: f64,
+    algorithm: Algorithm,
+    clustering: &ClusteringConfig,
+    checksum_manifest: Option<&Path>,
+    align: bool,
+    verbose: bool,
+) -> Result<()> {
+    let pairs = match manifest {
+        Some(path) => transmission::parse_manifest(path)?,
+        None => transmission::pair_by_convention(input)?,
+    };
+    if pairs.is_empty() {
+        return Err(CliError::InvalidInput(
+            "no sample runs to process".to_string(),
+        ));
+    }
+    println!("Matched {} sample/open-beam pair(s)", pairs.len());
+
+    let config = DetectorConfig {
+        tdc_frequency_hz: tdc_frequency,
+        ..DetectorConfig::default()
+    };
+    let written = transmission::run_pairs(
+        &pairs,
+        output,
+        &config,
+        tof_bins,
+        resolve_algorithm(algorithm),
+        clustering,
+        align,
+        verbose,
+    )?;
+    if let Some(manifest) = checksum_manifest {
+        checksum::write_for_files(manifest, &written)?;
+        println!("Wrote checksum manifest {}", manifest.display());
+    }
+    Ok(())
+}
+
+#[allow(clippy::too_many_arguments)]
+fn run_diff(
+    reference: &std::path::Path,
+    candidate: Option<&std::path::Path>,
+    algorithm: Algorithm,
+    compare_algorithm: Option<Algorithm>,
+    clustering: &ClusteringConfig,
+    tolerance: diff::Tolerance,
+    (reference_scale, candidate_scale): (f64, f64),
+    (reference_index, candidate_index): (Option<&std::path::Path>, Option<&std::path::Path>),
+    spectrum_bins: usize,
+    align: bool,
+    json: Option<&std::path::Path>,
+) -> Result<()> {
+    let (reference_events, mut candidate_events) = if let Some(candidate) = candidate {
+        println!("Reference: {}", reference.display());
+        println!("Candidate: {}", candidate.display());
+        (
+            diff::read_pulsed_neutrons(reference, reference_scale, reference_index)?,
+            diff::read_pulsed_neutrons(candidate, candidate_scale, candidate_index)?,
+        )
+    } else {
+        let compare_algorithm = compare_algorithm.ok_or_else(|| {
+            CliError::InvalidInput(
+                "give a candidate output, or --compare-algorithm to compare two algorithm runs"
+                    .to_string(),
+            )
+        })?;
+        println!(
+            "Comparing {algorithm:?} (reference) vs {compare_algorithm:?} (candidate) on {}",
+            reference.display()
+        );
+        (
+            extract_all_neutrons(reference, resolve_algorithm(algorithm), clustering)?,
+            extract_all_neutrons(reference, resolve_algorithm(compare_algorithm), clustering)?,
+        )
+    };
+
+    let alignment =
+        align.then(|| diff::align(&reference_events.neutrons, &mut candidate_events.neutrons));
+    let mut report = diff::compare(
+        &reference_events,
+        &candidate_events,
+        tolerance,
+        spectrum_bins,
+    );
+    report.alignment = alignment;
+    println!(
+        "Tolerance: position {}, TOF {} ticks",
+        tolerance.position, tolerance.tof
+    );
+    println!();
+    report.print();
+
+    if let Some(path) = json {
+        let file = std::fs::File::create(path)?;
+        serde_json::to_writer_pretty(std::io::BufWriter::new(file), &report)?;
+        println!();
+        println!("Report written to {}", path.display());
     }
     Ok(())
 }
 
+/// Extract every neutron of a TPX3 file, tagged with its pulse's TDC
+/// timestamp.
+fn extract_all_neutrons(
+    path: &std::path::Path,
+    algo: ClusteringAlgorithm,
+    clustering: &ClusteringConfig,
+) -> Result<diff::PulsedNeutrons> {
+    let reader = Tpx3FileReader::open(path)?;
+    let stream = out_of_core_neutron_stream(
+        &reader,
+        algo,
+        clustering,
+        &ExtractionConfig::default(),
+        &AlgorithmParams::default(),
+        &OutOfCoreConfig::default(),
+    )?;
+    let mut neutrons = rustpix_core::neutron::NeutronBatch::default();
+    let mut pulses = Vec::new();
+    for batch in stream {
+        let batch = batch?;
+        neutrons.append(&batch.neutrons);
+        pulses.resize(neutrons.len(), batch.tdc_timestamp_25ns);
+    }
+    Ok(diff::PulsedNeutrons {
+        neutrons,
+        pulses: Some(pulses),
+    })
+}
+
 #[allow(clippy::unnecessary_wraps)]
 fn run_ordering_benchmark() -> Result<()> {
     println!("Ordering benchmark removed: read_batch now uses the time-ordered stream.");
@@ -0,0 +1,442 @@
+//! Neutron and hit outputs of the `process` command.
+//!
+//! [`NeutronSink`] takes each pulse's neutrons and writes them to the
+//! output file in its format, splitting it into parts, numbering pulses and
+//! recording the event index on the way, optionally on a write-behind
+//! thread. [`HitExport`] writes the clustered hits of `--hits-output`.
+
+use crate::{pulses, split, write_neutrons, CliError, Result};
+use rustpix_core::neutron::NeutronBatch;
+use rustpix_core::pulse::PulseIndex;
+use rustpix_core::soa::HitBatch;
+use rustpix_tpx::instrument::InstrumentProfile;
+use std::path::Path;
+
+/// Block compression of `.rpxc` outputs.
+#[cfg(feature = "zstd")]
+pub const CONTAINER_COMPRESSION: rustpix_io::Compression = rustpix_io::Compression::Zstd(3);
+#[cfg(not(feature = "zstd"))]
+pub const CONTAINER_COMPRESSION: rustpix_io::Compression = rustpix_io::Compression::None;
+
+/// File that neutrons are written to.
+pub enum NeutronFile {
+    Data(rustpix_io::DataFileWriter),
+    Container(rustpix_io::ContainerWriter),
+    #[cfg(feature = "hdf5")]
+    Nexus(rustpix_io::NexusEventWriter),
+    #[cfg(feature = "parquet")]
+    Parquet(Box<rustpix_io::ParquetNeutronWriter>),
+    #[cfg(feature = "root")]
+    Root(Box<rustpix_io::RootNeutronWriter>),
+}
+
+impl NeutronFile {
+    /// Flush buffered output once nothing more will be written to this file.
+    fn finish(&mut self) -> rustpix_io::Result<()> {
+        match self {
+            Self::Data(writer) => writer.flush(),
+            Self::Container(writer) => writer.sync(),
+            #[cfg(feature = "hdf5")]
+            Self::Nexus(writer) => writer.flush(),
+            #[cfg(feature = "parquet")]
+            Self::Parquet(writer) => writer.finish(),
+            #[cfg(feature = "root")]
+            Self::Root(writer) => writer.finish(),
+        }
+    }
+}
+
+/// Open a `NeXus` event file for neutrons, sized for and named after the
+/// `--instrument` profile if one was given.
+pub fn create_nexus(
+    path: &Path,
+    super_resolution_factor: f64,
+    instrument: Option<&InstrumentProfile>,
+) -> Result<NeutronFile> {
+    #[cfg(feature = "hdf5")]
+    {
+        let mut options = match instrument {
+            Some(profile) => rustpix_io::NexusWriteOptions {
+                instrument_name: profile.title.clone(),
+                ..rustpix_io::NexusWriteOptions::from_detector_config(&profile.detector)
+            },
+            None => rustpix_io::NexusWriteOptions::from_detector_config(&DetectorConfig::default()),
+        };
+        options.super_resolution_factor = super_resolution_factor;
+        Ok(NeutronFile::Nexus(rustpix_io::NexusEventWriter::create(
+            path, options,
+        )?))
+    }
+    #[cfg(not(feature = "hdf5"))]
+    {
+        let _ = (super_resolution_factor, instrument);
+        Err(CliError::InvalidInput(format!(
+            "{}: rustpix was built without the hdf5 feature needed for NeXus output",
+            path.display()
+        )))
+    }
+}
+
+/// Open a Parquet file for neutrons.
+pub fn create_parquet(path: &Path) -> Result<NeutronFile> {
+    #[cfg(feature = "parquet")]
+    {
+        Ok(NeutronFile::Parquet(Box::new(
+            rustpix_io::ParquetNeutronWriter::create(
+                path,
+                &rustpix_io::ParquetWriteOptions::default(),
+            )?,
+        )))
+    }
+    #[cfg(not(feature = "parquet"))]
+    {
+        Err(CliError::InvalidInput(format!(
+            "{}: rustpix was built without the parquet feature needed for Parquet output",
+            path.display()
+        )))
+    }
+}
+
+/// Open a ROOT file for neutrons.
+pub fn create_root(path: &Path) -> Result<NeutronFile> {
+    #[cfg(feature = "root")]
+    {
+        Ok(NeutronFile::Root(Box::new(
+            rustpix_io::RootNeutronWriter::create(path)?,
+        )))
+    }
+    #[cfg(not(feature = "root"))]
+    {
+        Err(CliError::InvalidInput(format!(
+            "{}: rustpix was built without the root feature needed for ROOT output",
+            path.display()
+        )))
+    }
+}
+
+/// Bind the `--publish` socket.
+#[cfg(feature = "zmq")]
+pub fn bind_publisher(
+    endpoint: &str,
+    super_resolution_factor: f64,
+    verbose: bool,
+) -> Result<rustpix_io::NeutronPublisher> {
+    let publisher = rustpix_io::NeutronPublisher::bind(endpoint)
+        .map_err(|err| CliError::InvalidInput(format!("--publish {endpoint}: {err}")))?
+        .with_super_resolution_factor(super_resolution_factor);
+    if verbose {
+        eprintln!("Publishing neutrons on {}", publisher.local_addr());
+    }
+    Ok(publisher)
+}
+
+/// Neutron output file with its format and header state.
+pub struct NeutronOutput {
+    pub writer: NeutronFile,
+    pub format: String,
+    pub super_resolution_factor: f64,
+    pub wrote_header: bool,
+    pub warned_unknown: bool,
+    pub verbose: bool,
+    /// Numbered parts when splitting is enabled; `writer` is the current one.
+    pub parts: Option<split::SplitParts>,
+    /// Pulses of the current input, when CSV rows get a `pulse_id` column.
+    pub pulses: Option<PulseIndex>,
+    /// Pulse partition of the written neutrons, for `--event-index`.
+    pub event_index: Option<Box<pulses::EventIndexRecorder>>,
+    /// Live subscribers that also get every batch.
+    #[cfg(feature = "zmq")]
+    pub publisher: Option<rustpix_io::NeutronPublisher>,
+}
+
+impl NeutronOutput {
+    /// Flush the current file, writing the CSV header first if no neutron
+    /// reached it, so that runs without neutrons still give a valid CSV.
+    fn finish(&mut self) -> rustpix_io::Result<()> {
+        if self.format == "csv" && !self.wrote_header {
+            self.write_part(0, &NeutronBatch::default())?;
+        }
+        self.writer.finish()
+    }
+
+    /// Start the next input, whose pulses are `pulses`.
+    fn set_pulses(&mut self, pulses: PulseIndex) {
+        if let Some(recorder) = &mut self.event_index {
+            recorder.start_input(pulses.clone());
+        }
+        if self.pulses.is_some() {
+            self.pulses = Some(pulses);
+        }
+    }
+
+    fn write(
+        &mut self,
+        tdc_timestamp_25ns: u64,
+        neutrons: &NeutronBatch,
+    ) -> rustpix_io::Result<()> {
+        if let Some(recorder) = &mut self.event_index {
+            recorder.record(tdc_timestamp_25ns, neutrons.len());
+        }
+        #[cfg(feature = "zmq")]
+        if let Some(publisher) = &self.publisher {
+            publisher.publish(tdc_timestamp_25ns, neutrons);
+        }
+        if self.parts.is_none() {
+            return self.write_part(tdc_timestamp_25ns, neutrons);
+        }
+        let mut start = 0;
+        while start < neutrons.len() {
+            let Some(parts) = self.parts.as_mut() else {
+                break;
+            };
+            let (count, bytes) = parts.fit(neutrons, start, !self.wrote_header);
+            if count == 0 {
+                self.writer.finish()?;
+                self.writer = NeutronFile::Data(parts.start_part()?);
+                self.wrote_header = false;
+                continue;
+            }
+            parts.record(count, bytes);
+            let end = start + count;
+            if start == 0 && end == neutrons.len() {
+                self.write_part(tdc_timestamp_25ns, neutrons)?;
+            } else {
+                self.write_part(tdc_timestamp_25ns, &split::slice(neutrons, start, end))?;
+            }
+            start = end;
+        }
+        Ok(())
+    }
+
+    fn write_part(
+        &mut self,
+        tdc_timestamp_25ns: u64,
+        neutrons: &NeutronBatch,
+    ) -> rustpix_io::Result<()> {
+        match &mut self.writer {
+            NeutronFile::Data(writer) if self.format == "csv" && self.pulses.is_some() => {
+                let pulse_id = self
+                    .pulses
+                    .as_ref()
+                    .and_then(|pulses| pulses.id_of(tdc_timestamp_25ns));
+                writer.write_neutron_batch_csv_with_pulse_id(
+                    neutrons,
+                    pulse_id,
+                    !self.wrote_header,
+                )?;
+                self.wrote_header = true;
+                Ok(())
+            }
+            NeutronFile::Data(writer) => write_neutrons(
+                writer,
+                &self.format,
+                neutrons,
+                self.super_resolution_factor,
+                &mut self.wrote_header,
+                &mut self.warned_unknown,
+                self.verbose,
+            ),
+            NeutronFile::Container(writer) => writer.write_neutrons(neutrons),
+            #[cfg(feature = "hdf5")]
+            NeutronFile::Nexus(writer) => writer.write_neutrons(tdc_timestamp_25ns, neutrons),
+            #[cfg(feature = "parquet")]
+            NeutronFile::Parquet(writer) => writer.write_neutrons(neutrons),
+            #[cfg(feature = "root")]
+            NeutronFile::Root(writer) => writer.write_neutrons(neutrons),
+        }
+    }
+}
+
+/// Where `process` sends neutron batches: written inline, or handed to a
+/// writer thread through a bounded queue.
+pub enum NeutronSink {
+    Direct(Box<NeutronOutput>),
+    WriteBehind(rustpix_io::WriteBehind<NeutronOutput>),
+}
+
+impl NeutronSink {
+    /// Number the pulses of the next input by `pulses`.
+    pub fn set_pulses(&mut self, pulses: PulseIndex) -> Result<()> {
+        match self {
+            Self::Direct(output) => output.set_pulses(pulses),
+            Self::WriteBehind(writer) => {
+                writer.submit(move |output| {
+                    output.set_pulses(pulses);
+                    Ok(())
+                })?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn write(&mut self, tdc_timestamp_25ns: u64, neutrons: NeutronBatch) -> Result<()> {
+        match self {
+            Self::Direct(output) => output.write(tdc_timestamp_25ns, &neutrons)?,
+            Self::WriteBehind(writer) => {
+                writer.submit(move |output| output.write(tdc_timestamp_25ns, &neutrons))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Flush the output for a checkpoint; returns its length and whether
+    /// the CSV header has been written.
+    pub fn flushed_output(&mut self) -> Result<(u64, bool)> {
+        let Self::Direct(output) = self else {
+            return Err(CliError::InvalidInput(
+                "checkpoints cannot be taken with --write-queue-depth".to_string(),
+            ));
+        };
+        let NeutronFile::Data(writer) = &mut output.writer else {
+            return Err(CliError::InvalidInput(format!(
+                "checkpoints cannot be taken of {} outputs",
+                output.format
+            )));
+        };
+        let len = writer.flushed_len()?;
+        Ok((len, output.wrote_header))
+    }
+
+    /// Drain pending writes and flush; returns the output and queue stats
+    /// for write-behind.
+    pub fn finish(self) -> Result<(NeutronOutput, Option<rustpix_io::WriteBehindStats>)> {
+        let (mut output, stats) = match self {
+            Self::Direct(output) => (*output, None),
+            Self::WriteBehind(writer) => {
+                let (output, stats) = writer.finish()?;
+                (output, Some(stats))
+            }
+        };
+        output.finish()?;
+        Ok((output, stats))
+    }
+}
+
+/// File that clustered hits are written to.
+enum HitFile {
+    Csv(rustpix_io::DataFileWriter),
+    #[cfg(feature = "parquet")]
+    Parquet(Box<rustpix_io::ParquetHitWriter>),
+    #[cfg(feature = "root")]
+    Root(rustpix_io::RootHitWriter),
+}
+
+/// Hit-level export with cluster ids kept unique across batches.
+pub struct HitExport {
+    writer: HitFile,
+    wrote_header: bool,
+    next_cluster_id: i32,
+    /// Read hits with their raw fields so they are exported too.
+    pub raw_fields: bool,
+    /// Pulses of the current input, when CSV rows get a `pulse_id` column.
+    pub pulses: Option<PulseIndex>,
+}
+
+impl HitExport {
+    /// Create a CSV export, or Parquet or ROOT for `.parquet` and `.root`
+    /// paths, written to `staged` until it is committed to `path`. Pulse ids
+    /// are only written to CSV.
+    pub fn create(path: &Path, staged: &Path, raw_fields: bool, pulse_ids: bool) -> Result<Self> {
+        let has_extension = |wanted: &str| {
+            path.extension()
+                .is_some_and(|ext| ext.eq_ignore_ascii_case(wanted))
+        };
+        if pulse_ids && (has_extension("parquet") || has_extension("root")) {
+            return Err(CliError::InvalidInput(format!(
+                "{}: --pulse-ids needs a CSV hit output",
+                path.display()
+            )));
+        }
+        let writer = if has_extension("parquet") {
+            #[cfg(feature = "parquet")]
+            {
+                HitFile::Parquet(Box::new(rustpix_io::ParquetHitWriter::create(
+                    staged,
+                    &rustpix_io::ParquetWriteOptions::default(),
+                )?))
+            }
+            #[cfg(not(feature = "parquet"))]
+            {
+                return Err(CliError::InvalidInput(format!(
+                    "{}: rustpix was built without the parquet feature needed for Parquet output",
+                    path.display()
+                )));
+            }
+        } else if has_extension("root") {
+            #[cfg(feature = "root")]
+            {
+                HitFile::Root(rustpix_io::RootHitWriter::create(staged)?)
+            }
+            #[cfg(not(feature = "root"))]
+            {
+                return Err(CliError::InvalidInput(format!(
+                    "{}: rustpix was built without the root feature needed for ROOT output",
+                    path.display()
+                )));
+            }
+        } else {
+            HitFile::Csv(rustpix_io::DataFileWriter::create(staged)?)
+        };
+        Ok(Self {
+            writer,
+            wrote_header: false,
+            next_cluster_id: 0,
+            raw_fields,
+            pulses: pulse_ids.then(PulseIndex::default),
+        })
+    }
+
+    /// Write the labelled batch of the pulse at `tdc_timestamp_25ns`; must be
+    /// called after extraction, since the batch's cluster ids are shifted in
+    /// place.
+    pub fn write(
+        &mut self,
+        tdc_timestamp_25ns: u64,
+        batch: &mut HitBatch,
+        num_clusters: usize,
+    ) -> Result<()> {
+        batch.offset_cluster_ids(self.next_cluster_id);
+        match &mut self.writer {
+            HitFile::Csv(writer) => match &self.pulses {
+                Some(pulses) => writer.write_hit_batch_csv_with_pulse_id(
+                    batch,
+                    pulses.id_of(tdc_timestamp_25ns),
+                    !self.wrote_header,
+                )?,
+                None => writer.write_hit_batch_csv(batch, !self.wrote_header)?,
+            },
+            #[cfg(feature = "parquet")]
+            HitFile::Parquet(writer) => writer.write_hits(batch)?,
+            #[cfg(feature = "root")]
+            HitFile::Root(writer) => writer.write_hits(batch)?,
+        }
+        self.wrote_header = true;
+        let num_clusters = i32::try_from(num_clusters).unwrap_or(i32::MAX);
+        self.next_cluster_id = self.next_cluster_id.saturating_add(num_clusters);
+        Ok(())
+    }
+
+    /// Write out anything still buffered, and the CSV header if no hit was
+    /// written.
+    pub fn finish(&mut self) -> Result<()> {
+        match &mut self.writer {
+            HitFile::Csv(writer) if !self.wrote_header => {
+                match self.pulses {
+                    Some(_) => writer.write_hit_batch_csv_with_pulse_id(
+                        &HitBatch::default(),
+                        None,
+                        true,
+                    )?,
+                    None => writer.write_hit_batch_csv(&HitBatch::default(), true)?,
+                }
+                self.wrote_header = true;
+            }
+            HitFile::Csv(writer) => writer.flush()?,
+            #[cfg(feature = "parquet")]
+            HitFile::Parquet(writer) => writer.finish()?,
+            #[cfg(feature = "root")]
+            HitFile::Root(writer) => writer.finish()?,
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,148 @@
+//! Output file name templates for the `process` command.
+//!
+//! With `--output-template "{stem}_{algo}_{radius}px.{ext}"` every input
+//! (file, directory or pattern) is processed into its own output, named
+//! from the input and the processing parameters, so batch runs do not need
+//! wrapper scripts to compute file names. The same tokens are expanded in
+//! the hit export, checksum manifest, timing and pulse report paths.
+
+use crate::runs::Input;
+use crate::{CliError, Result};
+use std::path::{Path, PathBuf};
+
+/// Tokens recognized in output templates, for help and error messages.
+pub const TEMPLATE_TOKENS: &str = "{stem} {algo} {radius} {window} {min_size} {ext}";
+
+/// Values substituted into an output template.
+#[derive(Debug, Clone)]
+pub struct TemplateValues {
+    /// Input file stem, or the run name for directories and patterns.
+    pub stem: String,
+    /// Clustering algorithm name as given on the command line.
+    pub algo: String,
+    /// Clustering radius in pixels.
+    pub radius: f64,
+    /// Clustering temporal window in nanoseconds.
+    pub window_ns: f64,
+    /// Minimum cluster size.
+    pub min_cluster_size: u16,
+    /// File extension of the output format, without the dot.
+    pub ext: String,
+}
+
+/// Check that a template only uses known tokens.
+///
+/// # Errors
+/// Returns an error naming the first unknown or unterminated token.
+pub fn validate(template: &str) -> Result<()> {
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        let Some(len) = rest[start..].find('}') else {
+            return Err(CliError::InvalidInput(format!(
+                "output template '{template}' has an unterminated token"
+            )));
+        };
+        let token = &rest[start..=start + len];
+        if !TEMPLATE_TOKENS.split(' ').any(|known| known == token) {
+            return Err(CliError::InvalidInput(format!(
+                "unknown output template token {token} (expected one of {TEMPLATE_TOKENS})"
+            )));
+        }
+        rest = &rest[start + len + 1..];
+    }
+    Ok(())
+}
+
+/// Expand the tokens of a validated template.
+pub fn expand(template: &str, values: &TemplateValues) -> PathBuf {
+    PathBuf::from(
+        template
+            .replace("{stem}", &values.stem)
+            .replace("{algo}", &values.algo)
+            .replace("{radius}", &values.radius.to_string())
+            .replace("{window}", &values.window_ns.to_string())
+            .replace("{min_size}", &values.min_cluster_size.to_string())
+            .replace("{ext}", &values.ext),
+    )
+}
+
+/// Name an input for `{stem}`.
+///
+/// A file gives its stem, a directory its name, and a pattern the common
+/// prefix of its files' stems without the trailing file number (`run` for
+/// `run_000.tpx3` and `run_001.tpx3`).
+pub fn input_stem(input: &Input) -> String {
+    match input {
+        Input::File(path) => file_stem(path),
+        Input::Run { source, .. } if source.is_dir() => source
+            .file_name()
+            .map_or_else(|| "run".to_string(), |name| name.to_string_lossy().into()),
+        Input::Run { files, .. } => {
+            let stems: Vec<String> = files.iter().map(|path| file_stem(path)).collect();
+            let mut prefix = stems.first().map_or("", String::as_str);
+            for stem in stems.iter().skip(1) {
+                let len = prefix
+                    .chars()
+                    .zip(stem.chars())
+                    .take_while(|(a, b)| a == b)
+                    .map(|(a, _)| a.len_utf8())
+                    .sum();
+                prefix = &prefix[..len];
+            }
+            let name = prefix.trim_end_matches(|c: char| c.is_ascii_digit() || "_-. ".contains(c));
+            if name.is_empty() {
+                "run".to_string()
+            } else {
+                name.to_string()
+            }
+        }
+    }
+}
+
+fn file_stem(path: &Path) -> String {
+    path.file_stem().map_or_else(
+        || "output".to_string(),
+        |stem| stem.to_string_lossy().into(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expand_template() {
+        let values = TemplateValues {
+            stem: "sample_01".to_string(),
+            algo: "abs".to_string(),
+            radius: 5.0,
+            window_ns: 62.5,
+            min_cluster_size: 2,
+            ext: "csv".to_string(),
+        };
+        let template = "out/{stem}_{algo}_{radius}px_{window}ns_min{min_size}.{ext}";
+        validate(template).unwrap();
+        assert_eq!(
+            expand(template, &values),
+            PathBuf::from("out/sample_01_abs_5px_62.5ns_min2.csv")
+        );
+        assert!(validate("{stem}_{alg}.bin").is_err());
+        assert!(validate("{stem.bin").is_err());
+    }
+
+    #[test]
+    fn test_input_stem() {
+        assert_eq!(
+            input_stem(&Input::File(PathBuf::from("data/run_0042.tpx3"))),
+            "run_0042"
+        );
+        let run = Input::Run {
+            source: PathBuf::from("data/run_*.tpx3"),
+            files: vec![
+                PathBuf::from("data/run_000.tpx3"),
+                PathBuf::from("data/run_001.tpx3"),
+            ],
+        };
+        assert_eq!(input_stem(&run), "run");
+    }
+}
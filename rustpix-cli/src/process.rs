@@ -0,0 +1,2244 @@
+//! The `process` command: neutron events from TPX3 files, runs and hit lists.
+//!
+//! [`run`] turns `--output-template` into one job per input, or runs a
+//! single job for `--output`. A job locks and checks its outputs, stages
+//! them (see [`crate::atomic`]), clusters its inputs one at a time into a
+//! [`NeutronSink`], saving checkpoints on the way if asked to, and finally
+//! commits the staged outputs, uploads a remote output and reports totals.
+
+use crate::neutron_output::{
+    create_nexus, create_parquet, create_root, HitExport, NeutronFile, NeutronOutput, NeutronSink,
+    CONTAINER_COMPRESSION,
+};
+use crate::{
+    atomic, checkpoint, checksum, lock, open_reader, output_template, parse_point,
+    parse_super_resolution, pulses, remote_uri, resolve_algorithm, runs, split, timing, Algorithm,
+    CliError, ClusteringArgs, OutputFormat, Result,
+};
+use clap::{Args, ValueEnum};
+use rustpix_algorithms::{
+    cluster_batch, extract_batch, is_unsectionable, AlgorithmParams, ClusteringAlgorithm,
+    Connectivity,
+};
+use rustpix_core::classification::{ClassCounts, EventClass, EventClassifier};
+use rustpix_core::clustering::ClusteringConfig;
+use rustpix_core::extraction::ExtractionConfig;
+use rustpix_core::filter::{HitFilter, HitRegion};
+use rustpix_core::neutron::NeutronBatch;
+use rustpix_core::pulse::PulseIndex;
+use rustpix_core::soa::HitBatch;
+use rustpix_core::warnings::{WarningKind, Warnings};
+use rustpix_io::{
+    out_of_core_neutron_stream, EventBatch, GenericHitOptions, HitColumns, OutOfCoreConfig,
+    PixelMask, Tpx3FileReader,
+};
+use rustpix_tpx::instrument::InstrumentProfile;
+use rustpix_tpx::tdc::{CoverageFlag, DEFAULT_TDC_FREQUENCY_TOLERANCE};
+use rustpix_tpx::validation::{ValidationConfig, ValidationReport};
+use rustpix_tpx::{
+    DetectorConfig, NoTdcFallback, OutOfBoundsCounts, OutOfBoundsPolicy, TdcCorrectionPolicy,
+    TOT_SATURATED,
+};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Options of the `process` command.
+#[derive(Args)]
+pub struct ProcessOptions {
+    /// Input TPX3 file(s), directories or glob patterns (`run_*.tpx3`);
+    /// the files of a directory or pattern are merged into one
+    /// time-ordered run, each with its sidecar `<stem>.json` detector
+    /// config if present. `.csv`, `.h5` and `.hdf5` files are read as
+    /// hit lists from other software or generic event-mode detectors
+    /// (`x`, `y`, `t` and optional `amplitude` columns; positions are pixel
+    /// indices from 0 to 65535)
+    #[arg(required = true)]
+    pub input: Vec<PathBuf>,
+
+    #[command(flatten)]
+    pub outputs: OutputArgs,
+
+    #[command(flatten)]
+    pub clustering: ClusteringArgs,
+
+    /// Super-resolution factor of neutron positions: 1, 2, 4, 8 or 16
+    /// sub-pixels per detector pixel
+    #[arg(long, default_value_t = 8.0, value_parser = parse_super_resolution)]
+    pub super_resolution: f64,
+
+    #[command(flatten)]
+    pub memory: MemoryArgs,
+
+    #[command(flatten)]
+    pub algorithm_params: AlgorithmArgs,
+
+    #[command(flatten)]
+    pub checks: InputArgs,
+
+    /// Check processing invariants and report violations with file offsets
+    /// (processes whole pulses, without out-of-core splitting)
+    #[arg(long)]
+    pub validate: bool,
+
+    /// Label events as neutron, gamma or noise with a classifier (JSON
+    /// thresholds or decision tree) and report per-class counts
+    #[arg(long)]
+    pub classifier: Option<PathBuf>,
+
+    /// Only write events of these classes (e.g. `neutron` or
+    /// `gamma,noise`)
+    #[arg(long, value_enum, value_delimiter = ',', requires = "classifier")]
+    pub keep_class: Vec<EventClassArg>,
+
+    /// Verbose output (includes a per-stage timing breakdown)
+    #[arg(short, long)]
+    pub verbose: bool,
+}
+
+/// Where `process` writes neutrons and its side outputs, and how.
+#[derive(Args)]
+pub struct OutputArgs {
+    /// Output file path
+    #[arg(short, long, required_unless_present = "output_template")]
+    pub output: Option<PathBuf>,
+
+    /// Write one output per input, named from a template such as
+    /// `{stem}_{algo}_{radius}px.{ext}` (tokens: `{stem}`, `{algo}`,
+    /// `{radius}`, `{window}`, `{min_size}`, `{ext}`); also expanded in
+    /// --hits-output, --checksum-manifest, --timing-json and
+    /// --pulse-report
+    #[arg(long, conflicts_with = "output")]
+    pub output_template: Option<String>,
+
+    /// Write output on a dedicated thread with this many batches queued,
+    /// so slow destinations do not stall processing
+    #[arg(long)]
+    pub write_queue_depth: Option<usize>,
+
+    /// Write neutrons in the legacy C++ (mcpevent2hist) event layout
+    #[arg(long)]
+    pub legacy_format: bool,
+
+    /// Output format (default: from the output file extension)
+    #[arg(long, value_enum, conflicts_with = "legacy_format")]
+    pub output_format: Option<OutputFormat>,
+
+    /// Split neutron output into numbered parts of at most this many
+    /// events (e.g. 1e8), with an index file listing the parts
+    #[arg(long, value_parser = split::parse_event_count)]
+    pub split_every: Option<u64>,
+
+    /// Split neutron output into numbered parts of at most this size
+    /// (e.g. 2GB, 512MiB), with an index file listing the parts
+    #[arg(long, value_parser = split::parse_size)]
+    pub split_size: Option<u64>,
+
+    /// What to do when an output already exists. Outputs are written to
+    /// hidden `.partial` files and renamed into place on success either way
+    #[arg(long, value_enum, default_value = "replace")]
+    pub overwrite: atomic::OverwritePolicy,
+
+    /// Save a checkpoint of the run to this file after each input and
+    /// every `--checkpoint-interval` seconds, and keep the partial output
+    /// if the run fails, so that `--resume` can continue it
+    #[arg(long, conflicts_with_all = [
+        "output_template", "split_every", "split_size", "hits_output",
+        "write_queue_depth", "validate", "pulse_report", "event_index",
+    ])]
+    pub checkpoint: Option<PathBuf>,
+
+    /// Seconds between checkpoints within an input
+    #[arg(long, default_value_t = 60)]
+    pub checkpoint_interval: u64,
+
+    /// Continue the run recorded in this checkpoint, from the last pulse
+    /// written, saving further checkpoints to it. The inputs, output and
+    /// options must be those of the interrupted run
+    #[arg(long, conflicts_with_all = [
+        "checkpoint", "output_template", "split_every", "split_size", "hits_output",
+        "write_queue_depth", "validate", "pulse_report", "event_index",
+    ])]
+    pub resume: Option<PathBuf>,
+
+    /// Also write clustered hits with their cluster ids as CSV, or as
+    /// Parquet or ROOT for `.parquet` and `.root` paths (processes whole
+    /// pulses, without out-of-core splitting)
+    #[arg(long)]
+    pub hits_output: Option<PathBuf>,
+
+    /// Add each hit's chip-local coordinates and raw `ToA` (before
+    /// rollover correction) to the hit export, for debugging geometry
+    /// and timing
+    #[arg(long, requires = "hits_output")]
+    pub raw_hit_fields: bool,
+
+    /// Write a checksum manifest (sizes and SHA-256) of the outputs
+    #[arg(long)]
+    pub checksum_manifest: Option<PathBuf>,
+
+    /// Write per-stage and per-file timing as JSON
+    #[arg(long)]
+    pub timing_json: Option<PathBuf>,
+
+    /// Write dropped-pulse times and lost beam time per input as JSON
+    #[arg(long)]
+    pub pulse_report: Option<PathBuf>,
+
+    /// Add a `pulse_id` column to CSV neutron and hit outputs: the number
+    /// of each event's pulse in its input, counted from the TDC packets
+    /// so that pulses without hits are numbered too
+    #[arg(long, conflicts_with = "split_size")]
+    pub pulse_ids: bool,
+
+    /// Write the pulse partition of the neutron output as CSV: one
+    /// `event_time_zero_ns,event_index` row per pulse, including pulses
+    /// without neutrons, as in `NeXus` outputs
+    #[arg(long, value_name = "PATH", conflicts_with_all = ["split_every", "split_size"])]
+    pub event_index: Option<PathBuf>,
+
+    /// Also publish each pulse of neutrons on a `ZeroMQ` PUB socket bound
+    /// to this endpoint (`tcp://*:5556`), for live viewers (requires the
+    /// zmq feature)
+    #[arg(long, value_name = "ENDPOINT")]
+    pub publish: Option<String>,
+}
+
+impl OutputArgs {
+    fn split_limit(&self) -> split::SplitLimit {
+        split::SplitLimit {
+            max_events: self.split_every,
+            max_bytes: self.split_size,
+        }
+    }
+
+    /// `--checkpoint` or `--resume`, if either was given.
+    fn checkpointing(&self) -> Option<checkpoint::CheckpointOptions> {
+        let (path, resume) = match (&self.resume, &self.checkpoint) {
+            (Some(path), _) => (path.clone(), true),
+            (None, Some(path)) => (path.clone(), false),
+            (None, None) => return None,
+        };
+        Some(checkpoint::CheckpointOptions {
+            path,
+            interval: Duration::from_secs(self.checkpoint_interval),
+            resume,
+        })
+    }
+
+    /// Format name of the neutron output at `output`: `--output-format`,
+    /// `legacy`, or its extension.
+    fn format_of(&self, output: &Path) -> String {
+        if let Some(format) = self.output_format {
+            format.as_str().to_string()
+        } else if self.legacy_format {
+            "legacy".to_string()
+        } else {
+            output
+                .extension()
+                .and_then(|ext| ext.to_str())
+                .map_or_else(|| "bin".to_string(), str::to_lowercase)
+        }
+    }
+}
+
+/// Out-of-core processing: how inputs are cut into chunks that fit in
+/// memory, and how the chunks are pipelined.
+#[derive(Args)]
+pub struct MemoryArgs {
+    /// Enable out-of-core processing (pulse-bounded)
+    #[arg(long, default_value_t = true, action = clap::ArgAction::Set)]
+    pub out_of_core: bool,
+
+    /// Fraction of available memory to target for out-of-core processing
+    #[arg(long, default_value = "0.5")]
+    pub memory_fraction: f64,
+
+    /// Explicit memory budget in bytes (overrides `memory_fraction`)
+    #[arg(long)]
+    pub memory_budget_bytes: Option<usize>,
+
+    /// Worker threads for out-of-core slice processing
+    #[arg(long)]
+    pub parallelism: Option<usize>,
+
+    /// Bounded queue depth for out-of-core pipeline stages
+    #[arg(long, default_value = "2")]
+    pub queue_depth: usize,
+
+    /// Enable async reader/worker pipeline
+    #[arg(long, default_value_t = false, action = clap::ArgAction::Set)]
+    pub async_io: bool,
+
+    /// Split out-of-core chunks by hit count instead of memory budget alone
+    #[arg(long)]
+    pub target_hits_per_chunk: Option<usize>,
+}
+
+impl MemoryArgs {
+    fn config(&self) -> OutOfCoreConfig {
+        let mut memory = OutOfCoreConfig::default().with_memory_fraction(self.memory_fraction);
+        if let Some(bytes) = self.memory_budget_bytes {
+            memory = memory.with_memory_budget_bytes(bytes);
+        }
+        if let Some(threads) = self.parallelism {
+            memory = memory.with_parallelism(threads);
+        }
+        if let Some(hits) = self.target_hits_per_chunk {
+            memory = memory.with_target_hits_per_chunk(hits);
+        }
+        memory
+            .with_queue_depth(self.queue_depth)
+            .with_async_io(self.async_io)
+    }
+
+    fn print(&self) {
+        eprintln!("Out-of-core: {}", self.out_of_core);
+        if !self.out_of_core {
+            return;
+        }
+        eprintln!("Memory fraction: {}", self.memory_fraction);
+        if let Some(bytes) = self.memory_budget_bytes {
+            eprintln!("Memory budget override: {bytes} bytes");
+        }
+        if let Some(threads) = self.parallelism {
+            eprintln!("Parallelism: {threads} threads");
+        }
+        eprintln!("Queue depth: {}", self.queue_depth);
+        eprintln!("Async IO: {}", self.async_io);
+        if let Some(hits) = self.target_hits_per_chunk {
+            eprintln!("Target hits per chunk: {hits}");
+        }
+    }
+}
+
+/// Settings of individual clustering algorithms.
+#[derive(Args)]
+pub struct AlgorithmArgs {
+    /// Cluster pulses of more than this many hits in parallel time
+    /// sections of at least this size (labels do not depend on the
+    /// thread count). Sections are only cut at TOF gaps longer than the
+    /// temporal window; pulses without one are clustered whole with an
+    /// `unsectioned_pulse` warning
+    #[arg(long, value_name = "HITS")]
+    pub section_hits: Option<usize>,
+
+    /// Hierarchical algorithm: merge clusters whose bounding boxes are at
+    /// most this far apart [default: 10]
+    #[arg(long, value_name = "PIXELS")]
+    pub merge_distance: Option<f64>,
+
+    /// Hierarchical algorithm: merge clusters whose TOF ranges are at
+    /// most this far apart [default: 150]
+    #[arg(long, value_name = "NS")]
+    pub merge_window_ns: Option<f64>,
+
+    /// Connected-components algorithm: link pixels sharing an edge (4)
+    /// or also a corner (8) [default: 8]
+    #[arg(long)]
+    pub connectivity: Option<ConnectivityArg>,
+}
+
+impl AlgorithmArgs {
+    fn params(&self) -> AlgorithmParams {
+        let defaults = AlgorithmParams::default();
+        AlgorithmParams {
+            section_hits: self.section_hits.unwrap_or(0),
+            hierarchical_merge_distance: self
+                .merge_distance
+                .unwrap_or(defaults.hierarchical_merge_distance),
+            hierarchical_merge_window_ns: self
+                .merge_window_ns
+                .unwrap_or(defaults.hierarchical_merge_window_ns),
+            connectivity: match self.connectivity {
+                Some(ConnectivityArg::Four) => Connectivity::Four,
+                Some(ConnectivityArg::Eight) => Connectivity::Eight,
+                None => defaults.connectivity,
+            },
+            ..defaults
+        }
+    }
+}
+
+/// Detector config, TDC and out-of-bounds handling, hit cuts and hit list
+/// import settings applied to every input.
+#[derive(Args)]
+pub struct InputArgs {
+    /// Instrument profile whose detector config (TDC frequency, chip
+    /// layout) replaces the VENUS default: a shipped name (see
+    /// `rustpix instruments`) or a profile JSON file. Sidecar configs of
+    /// run files still take precedence
+    #[arg(long, value_name = "NAME")]
+    pub instrument: Option<String>,
+
+    /// Use the TDC frequency measured from each file when it disagrees
+    /// with the configured one (otherwise only warn)
+    #[arg(long)]
+    pub auto_tdc_frequency: bool,
+
+    /// Relative difference between measured and configured TDC frequency
+    /// that triggers a warning
+    #[arg(long, default_value_t = DEFAULT_TDC_FREQUENCY_TOLERANCE)]
+    pub tdc_frequency_tolerance: f64,
+
+    /// What to do with hits that have no preceding TDC, e.g. in files
+    /// recorded without a trigger signal
+    #[arg(long, value_enum, default_value = "drop")]
+    pub no_tdc_fallback: NoTdcFallbackArg,
+
+    /// TOF correction for hits after a missing TDC (default: the
+    /// detector config's setting, extrapolate)
+    #[arg(long, value_enum)]
+    pub tdc_correction: Option<TdcCorrectionArg>,
+
+    /// What to do with hits the chip transforms map outside the
+    /// detector, e.g. with a misconfigured chip size (default: the
+    /// detector config's setting, clamp)
+    #[arg(long, value_enum)]
+    pub out_of_bounds: Option<OutOfBoundsArg>,
+
+    /// Hot/dead pixel mask (JSON or TIFF, as saved from the GUI) whose
+    /// pixels' hits are dropped before clustering; implies whole-pulse
+    /// processing
+    #[arg(long)]
+    pub pixel_mask: Option<PathBuf>,
+
+    /// Keep only hits in this detector region, in pixels: `X0,Y0,X1,Y1`
+    /// for a rectangle or `X,Y;X,Y;X,Y;...` for a polygon. Repeat for
+    /// several regions; implies whole-pulse processing
+    #[arg(long, value_name = "REGION", value_parser = parse_roi)]
+    pub roi: Vec<HitRegion>,
+
+    /// Keep only hits with a TOF within `MIN,MAX` milliseconds; implies
+    /// whole-pulse processing
+    #[arg(long, value_name = "MIN,MAX", value_parser = parse_range::<f64>)]
+    pub tof_range: Option<(f64, f64)>,
+
+    /// Keep only hits with a `ToT` within `MIN,MAX` (inclusive); implies
+    /// whole-pulse processing
+    #[arg(long, value_name = "MIN,MAX", value_parser = parse_range::<u16>)]
+    pub tot_range: Option<(u16, u16)>,
+
+    /// Nanoseconds per unit of the `t` column of hit list inputs
+    #[arg(long, default_value_t = 1.0)]
+    pub import_time_unit_ns: f64,
+
+    /// Factor applied to the `amplitude` column of hit list inputs
+    /// before it is used as `ToT`
+    #[arg(long, default_value_t = 1.0)]
+    pub import_amplitude_scale: f64,
+
+    /// Column names of hit list inputs, as `field=column` pairs, e.g.
+    /// `x=col,y=row,t=toa,amplitude=adc` (default: found by name)
+    #[arg(long)]
+    pub import_columns: Option<HitColumns>,
+
+    /// HDF5 group holding the hit list columns (default: the first
+    /// group that has them)
+    #[arg(long)]
+    pub import_group: Option<String>,
+}
+
+impl InputArgs {
+    /// Load the instrument profile and pixel mask and check the hit cuts.
+    fn checks(&self) -> Result<InputChecks> {
+        Ok(InputChecks {
+            instrument: self
+                .instrument
+                .as_deref()
+                .map(crate::instrument_profile)
+                .transpose()?,
+            tolerance: self.tdc_frequency_tolerance,
+            adopt: self.auto_tdc_frequency,
+            no_tdc_fallback: match self.no_tdc_fallback {
+                NoTdcFallbackArg::Drop => NoTdcFallback::Drop,
+                NoTdcFallbackArg::FirstHit => NoTdcFallback::FirstHit,
+            },
+            tdc_correction: self.tdc_correction.map(|policy| match policy {
+                TdcCorrectionArg::None => TdcCorrectionPolicy::None,
+                TdcCorrectionArg::Extrapolate => TdcCorrectionPolicy::ExtrapolateLastPeriod,
+                TdcCorrectionArg::Interpolate => TdcCorrectionPolicy::InterpolateNeighbors,
+                TdcCorrectionArg::Drop => TdcCorrectionPolicy::DropSection,
+            }),
+            out_of_bounds: self.out_of_bounds.map(|policy| match policy {
+                OutOfBoundsArg::Clamp => OutOfBoundsPolicy::Clamp,
+                OutOfBoundsArg::Drop => OutOfBoundsPolicy::Drop,
+                OutOfBoundsArg::Error => OutOfBoundsPolicy::Error,
+            }),
+            pixel_mask: self.pixel_mask.as_ref().map(PixelMask::load).transpose()?,
+            hit_filter: hit_filter(self.roi.clone(), self.tof_range, self.tot_range)?,
+            import: GenericHitOptions {
+                time_unit_ns: self.import_time_unit_ns,
+                amplitude_scale: self.import_amplitude_scale,
+                columns: self.import_columns.clone(),
+            },
+            import_group: self.import_group.clone(),
+        })
+    }
+}
+
+/// Pixel neighbourhood of connected-component clustering.
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum ConnectivityArg {
+    /// Pixels sharing an edge
+    #[value(name = "4")]
+    Four,
+    /// Pixels sharing an edge or a corner
+    #[value(name = "8")]
+    Eight,
+}
+
+/// TOF reference for hits without a preceding TDC.
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum NoTdcFallbackArg {
+    /// Drop the hits
+    Drop,
+    /// Start pulses at the configured TDC frequency from the first hit
+    FirstHit,
+}
+
+/// TOF correction for hits after a missing TDC, for `--tdc-correction`.
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum TdcCorrectionArg {
+    /// Keep the TOF measured from the last TDC
+    None,
+    /// Subtract one configured period
+    Extrapolate,
+    /// Space the missing pulses evenly between the TDCs around the gap
+    Interpolate,
+    /// Drop hits until the next TDC
+    Drop,
+}
+
+/// Handling of hits mapped outside the detector, for `--out-of-bounds`.
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum OutOfBoundsArg {
+    /// Move them to the nearest detector pixel
+    Clamp,
+    /// Drop them
+    Drop,
+    /// Stop processing with an error
+    Error,
+}
+
+/// Event class selected by `--keep-class`.
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum EventClassArg {
+    /// Neutron events
+    Neutron,
+    /// Gamma events
+    Gamma,
+    /// Noise events
+    Noise,
+}
+
+impl From<EventClassArg> for EventClass {
+    fn from(arg: EventClassArg) -> Self {
+        match arg {
+            EventClassArg::Neutron => Self::Neutron,
+            EventClassArg::Gamma => Self::Gamma,
+            EventClassArg::Noise => Self::Noise,
+        }
+    }
+}
+
+/// Run `process`: one job for `--output`, or one per input for
+/// `--output-template`.
+pub fn run(options: &ProcessOptions) -> Result<()> {
+    let settings = Settings {
+        checks: options.checks.checks()?,
+        params: options.algorithm_params.params(),
+        checkpointing: options.outputs.checkpointing(),
+        keep_classes: options
+            .keep_class
+            .iter()
+            .map(|&class| EventClass::from(class))
+            .collect(),
+    };
+    let Some(template) = &options.outputs.output_template else {
+        let output = options.outputs.output.clone().ok_or_else(|| {
+            CliError::InvalidInput("--output or --output-template is required".to_string())
+        })?;
+        let outputs = JobOutputs {
+            neutrons: output,
+            hits: options.outputs.hits_output.clone(),
+            checksum_manifest: options.outputs.checksum_manifest.clone(),
+            timing_json: options.outputs.timing_json.clone(),
+            pulse_report: options.outputs.pulse_report.clone(),
+            event_index: options.outputs.event_index.clone(),
+        };
+        return run_job(options, &settings, &options.input, &outputs);
+    };
+    for (source, outputs) in template_jobs(options, template)? {
+        for path in outputs
+            .all()
+            .into_iter()
+            .flatten()
+            .filter(|path| remote_uri(path).is_none())
+        {
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+        }
+        run_job(options, &settings, &[source], &outputs)?;
+    }
+    Ok(())
+}
+
+/// Settings derived once from the options and shared by every job.
+struct Settings {
+    checks: InputChecks,
+    params: AlgorithmParams,
+    checkpointing: Option<checkpoint::CheckpointOptions>,
+    keep_classes: Vec<EventClass>,
+}
+
+/// Output paths of one job.
+struct JobOutputs {
+    neutrons: PathBuf,
+    hits: Option<PathBuf>,
+    checksum_manifest: Option<PathBuf>,
+    timing_json: Option<PathBuf>,
+    pulse_report: Option<PathBuf>,
+    event_index: Option<PathBuf>,
+}
+
+impl JobOutputs {
+    /// The neutron output followed by the side outputs that were asked for.
+    fn all(&self) -> [Option<&Path>; 6] {
+        [
+            Some(self.neutrons.as_path()),
+            self.hits.as_deref(),
+            self.checksum_manifest.as_deref(),
+            self.timing_json.as_deref(),
+            self.pulse_report.as_deref(),
+            self.event_index.as_deref(),
+        ]
+    }
+}
+
+/// The inputs and output paths of each job of an output template, failing
+/// if two inputs would be written to the same output.
+fn template_jobs(options: &ProcessOptions, template: &str) -> Result<Vec<(PathBuf, JobOutputs)>> {
+    let args = &options.outputs;
+    let side_outputs = [
+        &args.hits_output,
+        &args.checksum_manifest,
+        &args.timing_json,
+        &args.pulse_report,
+        &args.event_index,
+    ];
+    output_template::validate(template)?;
+    for path in side_outputs.into_iter().flatten() {
+        output_template::validate(&path.to_string_lossy())?;
+    }
+    let clustering = &options.clustering;
+    let mut values = output_template::TemplateValues {
+        stem: String::new(),
+        algo: clustering
+            .algorithm
+            .to_possible_value()
+            .map_or_else(String::new, |value| value.get_name().to_string()),
+        radius: clustering.radius,
+        window_ns: clustering.temporal_window_ns,
+        min_cluster_size: clustering.min_cluster_size,
+        ext: args
+            .output_format
+            .map_or("bin", OutputFormat::extension)
+            .to_string(),
+    };
+    let mut jobs = Vec::new();
+    let mut seen = std::collections::HashSet::new();
+    for input in runs::expand_inputs(&options.input)? {
+        values.stem = output_template::input_stem(&input);
+        let output = output_template::expand(template, &values);
+        if !seen.insert(output.clone()) {
+            return Err(CliError::InvalidInput(format!(
+                "output template gives {} for more than one input; add {{stem}}",
+                output.display()
+            )));
+        }
+        let [hits, checksum_manifest, timing_json, pulse_report, event_index] =
+            side_outputs.map(|path| {
+                path.as_ref()
+                    .map(|path| output_template::expand(&path.to_string_lossy(), &values))
+            });
+        let source = match input {
+            runs::Input::File(path) => path,
+            runs::Input::Run { source, .. } => source,
+        };
+        let outputs = JobOutputs {
+            neutrons: output,
+            hits,
+            checksum_manifest,
+            timing_json,
+            pulse_report,
+            event_index,
+        };
+        jobs.push((source, outputs));
+    }
+    Ok(jobs)
+}
+
+/// Clustering settings and input handling shared by the inputs of a job.
+struct Pipeline<'a> {
+    algorithm: ClusteringAlgorithm,
+    clustering: ClusteringConfig,
+    extraction: ExtractionConfig,
+    params: &'a AlgorithmParams,
+    checks: &'a InputChecks,
+    /// Classes of events written; empty keeps all.
+    keep_classes: &'a [EventClass],
+    /// Number pulses for `--pulse-ids` or `--event-index`.
+    track_pulses: bool,
+    verbose: bool,
+}
+
+impl<'a> Pipeline<'a> {
+    fn new(options: &ProcessOptions, settings: &'a Settings, outputs: &JobOutputs) -> Result<Self> {
+        let mut extraction =
+            ExtractionConfig::default().with_super_resolution(options.super_resolution);
+        if let Some(path) = &options.classifier {
+            extraction = extraction.with_event_classifier(Arc::new(load_classifier(path)?));
+        }
+        Ok(Self {
+            algorithm: resolve_algorithm(options.clustering.algorithm),
+            clustering: options.clustering.config(),
+            extraction,
+            params: &settings.params,
+            checks: &settings.checks,
+            keep_classes: &settings.keep_classes,
+            track_pulses: options.outputs.pulse_ids || outputs.event_index.is_some(),
+            verbose: options.verbose,
+        })
+    }
+}
+
+/// Process `input` into `outputs`.
+fn run_job(
+    options: &ProcessOptions,
+    settings: &Settings,
+    input: &[PathBuf],
+    outputs: &JobOutputs,
+) -> Result<()> {
+    let args = &options.outputs;
+    let output = outputs.neutrons.as_path();
+    let inputs = expand_inputs(input, output, options.validate)?;
+    let file_count: usize = inputs.iter().map(runs::Input::file_count).sum();
+    let split_limit = args.split_limit();
+    let Some(_locks) = lock_outputs(outputs, split_limit, args.overwrite)? else {
+        return Ok(());
+    };
+    if options.verbose {
+        print_settings(options, &settings.checks, file_count);
+    }
+
+    let start = Instant::now();
+    let pipeline = Pipeline::new(options, settings, outputs)?;
+    let memory = out_of_core_memory(options, settings, outputs);
+
+    let remote_output = remote_uri(output);
+    let local_output = match remote_output {
+        Some(uri) => staging_path(uri),
+        None => output.to_path_buf(),
+    };
+    let format = args.format_of(output);
+    check_output_options(
+        &format,
+        args,
+        outputs,
+        remote_output.is_some(),
+        settings.checkpointing.is_some(),
+    )?;
+    let mut checkpointer = settings
+        .checkpointing
+        .as_ref()
+        .map(|checkpointing| {
+            start_checkpointer(checkpointing, options, &pipeline, &inputs, output, &format)
+        })
+        .transpose()?;
+    let mut pending = atomic::PendingOutputs::default();
+    let mut sink = open_neutron_sink(
+        options,
+        &pipeline,
+        outputs,
+        format,
+        &local_output,
+        remote_output.is_some(),
+        checkpointer.as_ref(),
+        &mut pending,
+    )?;
+    let mut hit_export = outputs
+        .hits
+        .as_deref()
+        .map(|path| {
+            HitExport::create(
+                path,
+                &pending.stage(path),
+                args.raw_hit_fields,
+                args.pulse_ids,
+            )
+        })
+        .transpose()?;
+    let mut pulse_runs = outputs.pulse_report.as_ref().map(|_| Vec::new());
+
+    let mut timing = timing::ProcessTiming {
+        files: process_inputs(
+            &inputs,
+            &pipeline,
+            &mut sink,
+            memory.as_ref(),
+            hit_export.as_mut(),
+            pulse_runs.as_mut(),
+            checkpointer.as_mut(),
+            options.validate,
+        )?,
+        ..timing::ProcessTiming::default()
+    };
+
+    let finalize_start = Instant::now();
+    if let Some(export) = hit_export.as_mut() {
+        export.finish()?;
+    }
+    let (split_files, write_behind) =
+        finish_neutrons(sink, outputs, input, &mut pending, options.verbose)?;
+    timing.write_behind = write_behind;
+    pending.commit()?;
+    if let Some(checkpointer) = checkpointer {
+        checkpointer.remove()?;
+    }
+    publish_outputs(
+        outputs,
+        &local_output,
+        remote_output,
+        split_files.as_deref(),
+        options.verbose,
+    )?;
+    timing.finalize = finalize_start.elapsed();
+    timing.wall = start.elapsed();
+
+    report(
+        &timing,
+        file_count,
+        outputs,
+        pulse_runs.as_deref(),
+        options.verbose,
+    )
+}
+
+/// Out-of-core settings of a job, or `None` when it processes whole pulses.
+fn out_of_core_memory(
+    options: &ProcessOptions,
+    settings: &Settings,
+    outputs: &JobOutputs,
+) -> Option<OutOfCoreConfig> {
+    let whole_pulses = outputs.hits.is_some()
+        || options.validate
+        || settings.checks.pixel_mask.is_some()
+        || settings.checks.hit_filter.is_some();
+    if options.verbose && options.memory.out_of_core && whole_pulses {
+        eprintln!(
+            "Hit export, validation, a pixel mask or a hit filter requested: processing whole pulses without out-of-core splitting"
+        );
+    }
+    (options.memory.out_of_core && !whole_pulses).then(|| options.memory.config())
+}
+
+/// Process each input of a job in turn, skipping those a resumed
+/// checkpoint records as complete.
+#[allow(clippy::too_many_arguments)]
+fn process_inputs(
+    inputs: &[runs::Input],
+    pipeline: &Pipeline,
+    sink: &mut NeutronSink,
+    memory: Option<&OutOfCoreConfig>,
+    mut hit_export: Option<&mut HitExport>,
+    mut pulse_runs: Option<&mut Vec<pulses::PulseRun>>,
+    mut checkpointer: Option<&mut checkpoint::Checkpointer>,
+    validate: bool,
+) -> Result<Vec<timing::FileTiming>> {
+    if let Some(state) = checkpointer
+        .as_deref()
+        .filter(|checkpointer| checkpointer.is_resumed())
+        .map(checkpoint::Checkpointer::state)
+    {
+        println!(
+            "Resuming run: {} of {} input(s) complete",
+            state.completed.len(),
+            inputs.len()
+        );
+    }
+
+    let mut files = Vec::with_capacity(inputs.len());
+    for (index, input) in inputs.iter().enumerate() {
+        if let Some(totals) = checkpointer
+            .as_deref()
+            .and_then(|checkpointer| checkpointer.completed(index))
+        {
+            if pipeline.verbose {
+                eprintln!("Completed before the checkpoint: {}", totals.path);
+            }
+            files.push(totals.to_timing());
+            continue;
+        }
+        let (file_timing, validation) = process_input(
+            input,
+            pipeline,
+            sink,
+            memory,
+            hit_export.as_deref_mut(),
+            pulse_runs.as_deref_mut(),
+            checkpointer.as_deref_mut(),
+            validate,
+        )?;
+        if pipeline.verbose {
+            eprintln!("  {} hits processed", file_timing.hits);
+            eprintln!("  {} neutrons extracted", file_timing.neutrons);
+        }
+        if let Some((path, report)) = &validation {
+            print_validation(path, report);
+        }
+        file_timing.print_warnings();
+        checkpoint_input(checkpointer.as_deref_mut(), sink, &file_timing)?;
+        files.push(file_timing);
+    }
+    Ok(files)
+}
+
+/// Flush the neutron output and write its event index and split index,
+/// returning the split parts and index, and write-behind statistics.
+fn finish_neutrons(
+    sink: NeutronSink,
+    outputs: &JobOutputs,
+    input: &[PathBuf],
+    pending: &mut atomic::PendingOutputs,
+    verbose: bool,
+) -> Result<(Option<Vec<PathBuf>>, Option<rustpix_io::WriteBehindStats>)> {
+    let (mut output_file, write_behind) = sink.finish()?;
+    if let (Some(path), Some(recorder)) = (&outputs.event_index, output_file.event_index.take()) {
+        write_event_index(path, *recorder, pending, verbose)?;
+    }
+    let split_files = output_file
+        .parts
+        .as_mut()
+        .map(|parts| parts.write_index(input))
+        .transpose()?;
+    Ok((split_files, write_behind))
+}
+
+/// Report the split parts, write the checksum manifest and upload a remote
+/// output once the outputs of a job are committed.
+fn publish_outputs(
+    outputs: &JobOutputs,
+    local_output: &Path,
+    remote_output: Option<&str>,
+    split_files: Option<&[PathBuf]>,
+    verbose: bool,
+) -> Result<()> {
+    let output = outputs.neutrons.as_path();
+    if let Some(files) = split_files {
+        println!(
+            "Wrote {} output part(s), index {}",
+            files.len() - 1,
+            split::index_path(output).display()
+        );
+    }
+    if let Some(manifest) = &outputs.checksum_manifest {
+        write_checksum_manifest(manifest, output, local_output, split_files, verbose)?;
+    }
+    if let Some(uri) = remote_output {
+        upload_staged_output(local_output, uri, verbose)?;
+    }
+    Ok(())
+}
+
+/// Write the pulse partition of the neutron output for `--event-index`.
+fn write_event_index(
+    path: &Path,
+    recorder: pulses::EventIndexRecorder,
+    pending: &mut atomic::PendingOutputs,
+    verbose: bool,
+) -> Result<()> {
+    let index = recorder.finish();
+    rustpix_io::DataFileWriter::create(pending.stage(path))?.write_event_index_csv(&index)?;
+    if verbose {
+        eprintln!(
+            "Wrote event index of {} pulse(s): {}",
+            index.len(),
+            path.display()
+        );
+    }
+    Ok(())
+}
+
+/// Upload the locally staged output to `uri` and remove the local copy,
+/// keeping it if the upload fails.
+fn upload_staged_output(local_output: &Path, uri: &str, verbose: bool) -> Result<()> {
+    if verbose {
+        eprintln!("Uploading output to: {uri}");
+    }
+    if let Err(err) = upload_output(local_output, uri) {
+        eprintln!(
+            "Upload to {uri} failed; the output is kept at {}",
+            local_output.display()
+        );
+        return Err(err);
+    }
+    let _ = std::fs::remove_file(local_output);
+    Ok(())
+}
+
+/// Expand the inputs of a job, failing early on what it cannot read.
+fn expand_inputs(input: &[PathBuf], output: &Path, validate: bool) -> Result<Vec<runs::Input>> {
+    // Fail before any work rather than when the first remote file is reached.
+    #[cfg(not(feature = "object-store"))]
+    if let Some(uri) = input
+        .iter()
+        .map(PathBuf::as_path)
+        .chain([output])
+        .find_map(remote_uri)
+    {
+        return Err(crate::object_store_disabled(uri));
+    }
+    #[cfg(feature = "object-store")]
+    let _ = output;
+    let inputs = runs::expand_inputs(input)?;
+    if validate
+        && inputs
+            .iter()
+            .any(|input| matches!(input, runs::Input::Run { .. }))
+    {
+        return Err(CliError::InvalidInput(
+            "--validate checks files one at a time; list the files of a directory or pattern instead"
+                .to_string(),
+        ));
+    }
+    Ok(inputs)
+}
+
+/// Lock the local outputs of a job against other rustpix runs, and apply
+/// the overwrite policy to them; `None` if the job is to be skipped.
+fn lock_outputs(
+    outputs: &JobOutputs,
+    split_limit: split::SplitLimit,
+    overwrite: atomic::OverwritePolicy,
+) -> Result<Option<Vec<lock::OutputLock>>> {
+    let split_outputs = (!split_limit.is_unlimited()).then(|| {
+        [
+            split::index_path(&outputs.neutrons),
+            split::part_path(&outputs.neutrons, 0),
+        ]
+    });
+    let mut paths: Vec<&Path> = match &split_outputs {
+        Some(paths) => paths.iter().map(PathBuf::as_path).collect(),
+        None => vec![&outputs.neutrons],
+    };
+    paths.extend(outputs.all().into_iter().skip(1).flatten());
+    paths.retain(|path| remote_uri(path).is_none());
+    let locks = lock::lock_outputs(&paths)?;
+    Ok(overwrite.check(&paths)?.then_some(locks))
+}
+
+fn print_settings(options: &ProcessOptions, checks: &InputChecks, file_count: usize) {
+    let clustering = &options.clustering;
+    eprintln!("Processing {file_count} file(s)...");
+    eprintln!("Algorithm: {:?}", clustering.algorithm);
+    eprintln!("Radius: {} pixels", clustering.radius);
+    eprintln!("Temporal window: {} ns", clustering.temporal_window_ns);
+    eprintln!("Min cluster size: {}", clustering.min_cluster_size);
+    eprintln!("Super-resolution: x{}", options.super_resolution);
+    if let Some(mask) = &checks.pixel_mask {
+        eprintln!(
+            "Pixel mask: {} dead, {} hot pixels (created {})",
+            mask.dead_pixels.len(),
+            mask.hot_pixels.len(),
+            mask.created
+        );
+    }
+    if let Some(filter) = &checks.hit_filter {
+        eprintln!(
+            "Hit filter: {} region(s), TOF {:?}, ToT {:?}",
+            filter.regions.len(),
+            filter.tof_range,
+            filter.tot_range
+        );
+    }
+    options.memory.print();
+    if let Some(depth) = options.outputs.write_queue_depth {
+        eprintln!("Write-behind queue depth: {depth}");
+    }
+}
+
+/// Reject output options that cannot be combined with the output format
+/// or with each other.
+fn check_output_options(
+    format: &str,
+    args: &OutputArgs,
+    outputs: &JobOutputs,
+    remote: bool,
+    checkpointing: bool,
+) -> Result<()> {
+    let split = !args.split_limit().is_unlimited();
+    if remote && split {
+        return Err(CliError::InvalidInput(
+            "--split-every/--split-size cannot be used with remote outputs".to_string(),
+        ));
+    }
+    if format == "rpxd" && args.split_size.is_some() {
+        return Err(CliError::InvalidInput(
+            "--split-size cannot be used with packed (.rpxd) outputs; use --split-every"
+                .to_string(),
+        ));
+    }
+    let unsplittable = match format {
+        "nexus" => Some("NeXus"),
+        "parquet" => Some("Parquet"),
+        "root" => Some("ROOT"),
+        "rpxc" => Some("container (.rpxc)"),
+        _ => None,
+    };
+    if let Some(name) = unsplittable.filter(|_| split) {
+        return Err(CliError::InvalidInput(format!(
+            "--split-every/--split-size cannot be used with {name} outputs"
+        )));
+    }
+    if args.pulse_ids && format != "csv" {
+        return Err(CliError::InvalidInput(format!(
+            "--pulse-ids needs CSV output, not {format}; NeXus outputs record pulses in event_time_zero and event_index"
+        )));
+    }
+    if !checkpointing {
+        return Ok(());
+    }
+    if outputs.event_index.is_some() {
+        return Err(CliError::InvalidInput(
+            "--event-index cannot be used with --checkpoint/--resume".to_string(),
+        ));
+    }
+    if remote {
+        return Err(CliError::InvalidInput(
+            "--checkpoint/--resume cannot be used with remote outputs".to_string(),
+        ));
+    }
+    if unsplittable.is_some() {
+        return Err(CliError::InvalidInput(format!(
+            "--checkpoint/--resume cannot be used with {format} outputs"
+        )));
+    }
+    Ok(())
+}
+
+/// Start checkpointing a job, or resume it, with a fingerprint of its
+/// inputs, output and every setting that changes the output.
+fn start_checkpointer(
+    checkpointing: &checkpoint::CheckpointOptions,
+    options: &ProcessOptions,
+    pipeline: &Pipeline,
+    inputs: &[runs::Input],
+    output: &Path,
+    format: &str,
+) -> Result<checkpoint::Checkpointer> {
+    let algorithm = options.clustering.algorithm;
+    let params = pipeline.params;
+    let merge = match algorithm {
+        Algorithm::Hierarchical => format!(
+            " {} {}",
+            params.hierarchical_merge_distance, params.hierarchical_merge_window_ns
+        ),
+        Algorithm::ConnectedComponents => format!(" {:?}", params.connectivity),
+        _ => String::new(),
+    };
+    let settings = format!(
+        "{format} {algorithm:?} {:?} {} {:?} {:?} {:?} {} {}{merge}",
+        pipeline.clustering,
+        pipeline.extraction.super_resolution_factor,
+        pipeline.checks,
+        options.classifier.as_deref(),
+        pipeline.keep_classes,
+        options.memory.out_of_core,
+        options.outputs.pulse_ids,
+    );
+    let fingerprint = checkpoint::fingerprint(inputs, output, &settings)?;
+    checkpoint::Checkpointer::start(checkpointing, fingerprint)
+}
+
+/// Open the neutron output of a job, staged in `pending`, or continue the
+/// partial output of a resumed checkpoint.
+#[allow(clippy::too_many_arguments)]
+fn open_neutron_sink(
+    options: &ProcessOptions,
+    pipeline: &Pipeline,
+    outputs: &JobOutputs,
+    format: String,
+    local_output: &Path,
+    remote: bool,
+    checkpointer: Option<&checkpoint::Checkpointer>,
+    pending: &mut atomic::PendingOutputs,
+) -> Result<NeutronSink> {
+    let args = &options.outputs;
+    let output = outputs.neutrons.as_path();
+    let super_resolution_factor = pipeline.extraction.super_resolution_factor;
+    let resumed = checkpointer
+        .filter(|checkpointer| checkpointer.is_resumed())
+        .map(checkpoint::Checkpointer::state);
+    let split_limit = args.split_limit();
+    let mut parts =
+        (!split_limit.is_unlimited()).then(|| split::SplitParts::new(output, &format, split_limit));
+    let writer = if let Some(parts) = parts.as_mut() {
+        NeutronFile::Data(parts.start_part()?)
+    } else {
+        let path = if remote {
+            local_output.to_path_buf()
+        } else if checkpointer.is_some() {
+            pending.stage_resumable(output)
+        } else {
+            pending.stage(output)
+        };
+        match format.as_str() {
+            "nexus" => create_nexus(
+                &path,
+                super_resolution_factor,
+                pipeline.checks.instrument.as_ref(),
+            )?,
+            "parquet" => create_parquet(&path)?,
+            "root" => create_root(&path)?,
+            "rpxc" => NeutronFile::Container(rustpix_io::ContainerWriter::create(
+                &path,
+                CONTAINER_COMPRESSION,
+            )?),
+            _ => NeutronFile::Data(match resumed {
+                Some(state) => rustpix_io::DataFileWriter::append_at(&path, state.output_bytes)
+                    .map_err(|err| {
+                        CliError::InvalidInput(format!(
+                            "{}: cannot resume the partial output: {err}",
+                            path.display()
+                        ))
+                    })?,
+                None => rustpix_io::DataFileWriter::create(&path)?,
+            }),
+        }
+    };
+    if options.verbose {
+        if parts.is_some() {
+            eprintln!(
+                "Writing output parts to: {}",
+                split::part_path(output, 0).display()
+            );
+        } else {
+            eprintln!("Writing output to: {}", output.display());
+        }
+    }
+    let output_file = NeutronOutput {
+        writer,
+        format,
+        super_resolution_factor,
+        wrote_header: resumed.is_some_and(|state| state.wrote_header),
+        warned_unknown: false,
+        verbose: options.verbose,
+        parts,
+        pulses: args.pulse_ids.then(PulseIndex::default),
+        event_index: outputs.event_index.as_ref().map(|_| Box::default()),
+        #[cfg(feature = "zmq")]
+        publisher: args
+            .publish
+            .as_deref()
+            .map(|endpoint| {
+                crate::neutron_output::bind_publisher(
+                    endpoint,
+                    super_resolution_factor,
+                    options.verbose,
+                )
+            })
+            .transpose()?,
+    };
+    #[cfg(not(feature = "zmq"))]
+    if let Some(endpoint) = &args.publish {
+        return Err(CliError::InvalidInput(format!(
+            "{endpoint}: rustpix was built without the zmq feature needed for --publish"
+        )));
+    }
+    Ok(match args.write_queue_depth {
+        Some(depth) => {
+            NeutronSink::WriteBehind(rustpix_io::WriteBehind::spawn(output_file, depth)?)
+        }
+        None => NeutronSink::Direct(Box::new(output_file)),
+    })
+}
+
+/// Write the checksum manifest of the neutron output, or of its parts and
+/// index when split.
+fn write_checksum_manifest(
+    manifest: &Path,
+    output: &Path,
+    local_output: &Path,
+    split_files: Option<&[PathBuf]>,
+    verbose: bool,
+) -> Result<()> {
+    if let Some(files) = split_files {
+        checksum::write_for_files(manifest, files)?;
+    } else {
+        let name = match remote_uri(output) {
+            Some(uri) => uri.to_string(),
+            None => checksum::entry_name(output, manifest),
+        };
+        let entry = checksum::ManifestEntry::from_file(local_output, name)?;
+        checksum::write_manifest(manifest, &[entry])?;
+    }
+    if verbose {
+        eprintln!("Wrote checksum manifest: {}", manifest.display());
+    }
+    Ok(())
+}
+
+/// Print the totals of a job and write its timing and pulse reports.
+fn report(
+    timing: &timing::ProcessTiming,
+    file_count: usize,
+    outputs: &JobOutputs,
+    pulse_runs: Option<&[pulses::PulseRun]>,
+    verbose: bool,
+) -> Result<()> {
+    let total = timing.total();
+    println!(
+        "Processed {file_count} files in {:.2}s",
+        timing.wall.as_secs_f64()
+    );
+    println!("Total hits: {}", total.hits);
+    println!("Total neutrons: {}", total.neutrons);
+    if let Some(classes) = total.classes {
+        println!(
+            "Events by class: {} neutron, {} gamma, {} noise",
+            classes.neutron, classes.gamma, classes.noise
+        );
+    }
+    if verbose {
+        timing.print();
+    }
+    if let Some(path) = &outputs.timing_json {
+        atomic::write(path, serde_json::to_string_pretty(&timing.to_json())?)?;
+    }
+    if let (Some(path), Some(runs)) = (&outputs.pulse_report, pulse_runs) {
+        pulses::write_json(path, runs)?;
+        if verbose {
+            eprintln!("Wrote pulse report: {}", path.display());
+        }
+    }
+    Ok(())
+}
+
+/// Process one input of a job: a TPX3 file, a run of files merged by TDC
+/// timestamp, or a hit list. Returns the file's validation report with
+/// `validate`.
+#[allow(clippy::too_many_arguments)]
+fn process_input<'a>(
+    input: &'a runs::Input,
+    pipeline: &Pipeline,
+    sink: &mut NeutronSink,
+    memory: Option<&OutOfCoreConfig>,
+    hit_export: Option<&mut HitExport>,
+    pulse_runs: Option<&mut Vec<pulses::PulseRun>>,
+    checkpointer: Option<&mut checkpoint::Checkpointer>,
+    validate: bool,
+) -> Result<(timing::FileTiming, Option<(&'a Path, ValidationReport)>)> {
+    let path = match input {
+        runs::Input::File(path) => path,
+        runs::Input::Run { source, files } => {
+            if pipeline.verbose {
+                eprintln!("Reading run: {} ({} files)", source.display(), files.len());
+                if memory.is_some() {
+                    eprintln!("  Merged runs are processed a whole pulse at a time");
+                }
+            }
+            let file = process_input_run(source, files, pipeline, sink, hit_export, pulse_runs)?;
+            return Ok((file, None));
+        }
+    };
+    if pipeline.verbose {
+        eprintln!("Reading: {}", path.display());
+    }
+    if is_hit_list(path) {
+        return Ok((process_hit_list(path, pipeline, sink, hit_export)?, None));
+    }
+    let mut validation = if validate {
+        Some(open_input_reader(path, pipeline.checks)?.validate(&ValidationConfig::default())?)
+    } else {
+        None
+    };
+    let file = process_input_file(
+        path,
+        pipeline,
+        sink,
+        memory,
+        hit_export,
+        validation.as_mut(),
+        pulse_runs,
+        checkpointer,
+    )?;
+    Ok((file, validation.map(|report| (path.as_path(), report))))
+}
+
+#[allow(clippy::too_many_arguments, clippy::too_many_lines)]
+fn process_input_file(
+    path: &Path,
+    pipeline: &Pipeline,
+    sink: &mut NeutronSink,
+    memory: Option<&OutOfCoreConfig>,
+    mut hit_export: Option<&mut HitExport>,
+    validation: Option<&mut ValidationReport>,
+    pulse_runs: Option<&mut Vec<pulses::PulseRun>>,
+    checkpointer: Option<&mut checkpoint::Checkpointer>,
+) -> Result<timing::FileTiming> {
+    let file_start = Instant::now();
+    let checks = pipeline.checks;
+    let raw_fields = hit_export.as_ref().is_some_and(|export| export.raw_fields);
+    let out_of_bounds = OutOfBoundsCounts::default();
+    let (reader, flags, warnings) = prepare_reader(
+        path,
+        open_input_reader(path, checks)?,
+        checks,
+        &out_of_bounds,
+        raw_fields,
+        pulse_runs,
+        pipeline.verbose,
+    );
+    check_pixel_mask(path, checks, reader.config())?;
+    let out_of_bounds = OutOfBounds {
+        policy: reader.config().out_of_bounds,
+        counts: out_of_bounds,
+    };
+    let mut file = timing::FileTiming {
+        path: path.display().to_string(),
+        bytes: reader.file_size(),
+        flags: flags.iter().map(ToString::to_string).collect(),
+        warnings,
+        ..timing::FileTiming::default()
+    };
+    // Continue after the last pulse of this input in the checkpoint, if any.
+    let progress = checkpointer
+        .as_ref()
+        .and_then(|checkpointer| checkpointer.state().current.clone());
+    if let Some(progress) = &progress {
+        file.hits = progress.totals.hits;
+        file.neutrons = progress.totals.neutrons;
+        file.classes = progress.totals.classes;
+    }
+    let resume = |err: rustpix_io::Error| {
+        CliError::InvalidInput(format!("{}: cannot resume: {err}", path.display()))
+    };
+    let index = (checkpointer.is_some() || pipeline.track_pulses).then(|| reader.section_index());
+    if let Some(index) = index.as_ref().filter(|_| pipeline.track_pulses) {
+        number_pulses(sink, hit_export.as_deref_mut(), index.pulses())?;
+    }
+    file.stages.read = file_start.elapsed();
+
+    if let Some(memory) = memory {
+        let mut next_start = Instant::now();
+        let stream = match &progress {
+            Some(progress) => rustpix_io::out_of_core_neutron_stream_from(
+                &reader,
+                &progress.position,
+                pipeline.algorithm,
+                &pipeline.clustering,
+                &pipeline.extraction,
+                pipeline.params,
+                memory,
+            )
+            .map_err(resume)?,
+            None => out_of_core_neutron_stream(
+                &reader,
+                pipeline.algorithm,
+                &pipeline.clustering,
+                &pipeline.extraction,
+                pipeline.params,
+                memory,
+            )?,
+        };
+        let mut checkpoint = checkpointer.zip(index.as_ref());
+
+        for batch in stream {
+            let batch = batch?;
+            out_of_bounds.check(path)?;
+            // Time waiting for the batch not spent clustering/extracting is
+            // parsing (and, with the threaded pipeline, queueing).
+            let waited = next_start.elapsed();
+            file.stages.parse += waited.saturating_sub(batch.timings.total());
+            file.stages.cluster += batch.timings.cluster;
+            file.stages.extract += batch.timings.extract;
+            file.chunks.merge(&batch.chunks);
+            file.warnings.merge(&batch.warnings);
+            file.hits = file.hits.saturating_add(batch.hits_processed);
+            file.neutrons = file.neutrons.saturating_add(batch.neutrons.len());
+            let mut neutrons = batch.neutrons;
+            tally_classes(&mut file, &mut neutrons, pipeline.keep_classes);
+
+            let write_start = Instant::now();
+            sink.write(batch.tdc_timestamp_25ns, neutrons)?;
+            if let Some((checkpointer, index)) = checkpoint.as_mut() {
+                save_progress(checkpointer, index, batch.tdc_timestamp_25ns, &file, sink)?;
+            }
+            file.stages.write += write_start.elapsed();
+            next_start = Instant::now();
+        }
+    } else {
+        let stream = match &progress {
+            Some(progress) => reader
+                .stream_time_ordered_events_from(&progress.position)
+                .map_err(resume)?,
+            None => reader.stream_time_ordered_events()?,
+        };
+        process_pulses(
+            stream,
+            &mut file,
+            pipeline,
+            sink,
+            hit_export,
+            validation,
+            checkpointer.zip(index.as_ref()),
+            &out_of_bounds,
+            TOT_SATURATED,
+        )?;
+    }
+
+    out_of_bounds.check(path)?;
+    out_of_bounds.record(&mut file.warnings);
+    file.wall = file_start.elapsed();
+    Ok(file)
+}
+
+/// Process the files of a run as one stream of pulses merged by TDC
+/// timestamp.
+fn process_input_run(
+    source: &Path,
+    paths: &[PathBuf],
+    pipeline: &Pipeline,
+    sink: &mut NeutronSink,
+    mut hit_export: Option<&mut HitExport>,
+    mut pulse_runs: Option<&mut Vec<pulses::PulseRun>>,
+) -> Result<timing::FileTiming> {
+    let file_start = Instant::now();
+    let checks = pipeline.checks;
+    let raw_fields = hit_export.as_ref().is_some_and(|export| export.raw_fields);
+    let mut readers = Vec::with_capacity(paths.len());
+    let mut flags = Vec::new();
+    let mut warnings = Warnings::default();
+    let out_of_bounds = OutOfBoundsCounts::default();
+    for path in paths {
+        let mut reader = open_input_reader(path, checks)?;
+        if let Some(config) = runs::sidecar_config(path)? {
+            if pipeline.verbose {
+                eprintln!("  {}: using sidecar detector config", path.display());
+            }
+            reader = reader.with_config(config);
+        }
+        let (reader, file_flags, file_warnings) = prepare_reader(
+            path,
+            reader,
+            checks,
+            &out_of_bounds,
+            raw_fields,
+            pulse_runs.as_deref_mut(),
+            pipeline.verbose,
+        );
+        flags.extend(
+            file_flags
+                .iter()
+                .map(|flag| format!("{}: {flag}", path.display())),
+        );
+        warnings.merge(&file_warnings);
+        check_pixel_mask(path, checks, reader.config())?;
+        readers.push(reader);
+    }
+    let out_of_bounds = OutOfBounds {
+        policy: readers.first().map_or(OutOfBoundsPolicy::Clamp, |reader| {
+            reader.config().out_of_bounds
+        }),
+        counts: out_of_bounds,
+    };
+    let run = rustpix_io::Tpx3RunReader::from_readers(readers)?;
+    let mut file = timing::FileTiming {
+        path: source.display().to_string(),
+        bytes: run.file_size(),
+        flags,
+        warnings,
+        ..timing::FileTiming::default()
+    };
+    if pipeline.track_pulses {
+        number_pulses(sink, hit_export.as_deref_mut(), &run.pulse_index())?;
+    }
+    file.stages.read = file_start.elapsed();
+
+    process_pulses(
+        run.stream_time_ordered_events()?,
+        &mut file,
+        pipeline,
+        sink,
+        hit_export,
+        None,
+        None,
+        &out_of_bounds,
+        TOT_SATURATED,
+    )?;
+
+    out_of_bounds.check(source)?;
+    out_of_bounds.record(&mut file.warnings);
+    file.wall = file_start.elapsed();
+    Ok(file)
+}
+
+/// Process the hits of a CSV or HDF5 hit list as a single pulse, numbered 0
+/// for `--pulse-ids` and `--event-index`.
+fn process_hit_list(
+    path: &Path,
+    pipeline: &Pipeline,
+    sink: &mut NeutronSink,
+    mut hit_export: Option<&mut HitExport>,
+) -> Result<timing::FileTiming> {
+    let file_start = Instant::now();
+    let bytes = usize::try_from(std::fs::metadata(path)?.len()).unwrap_or(usize::MAX);
+    let hits = read_hit_list(path, pipeline.checks)?;
+    let mut file = timing::FileTiming {
+        path: path.display().to_string(),
+        bytes,
+        ..timing::FileTiming::default()
+    };
+    if pipeline.track_pulses {
+        number_pulses(sink, hit_export.as_deref_mut(), &PulseIndex::new(vec![0]))?;
+    }
+    file.stages.read = file_start.elapsed();
+
+    // Positions come from the file as they are; amplitudes are clamped to
+    // the `u16` range, not the 10-bit Timepix `ToT` register.
+    let out_of_bounds = OutOfBounds {
+        policy: OutOfBoundsPolicy::Clamp,
+        counts: OutOfBoundsCounts::default(),
+    };
+    process_pulses(
+        std::iter::once(EventBatch {
+            tdc_timestamp_25ns: 0,
+            hits,
+        }),
+        &mut file,
+        pipeline,
+        sink,
+        hit_export,
+        None,
+        None,
+        &out_of_bounds,
+        u16::MAX,
+    )?;
+    file.wall = file_start.elapsed();
+    Ok(file)
+}
+
+/// Whether `path` is read as a CSV or HDF5 hit list rather than TPX3.
+fn is_hit_list(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| {
+        ["csv", "h5", "hdf5"]
+            .iter()
+            .any(|hit_list| ext.eq_ignore_ascii_case(hit_list))
+    })
+}
+
+/// Read the hits of a CSV or HDF5 hit list.
+fn read_hit_list(path: &Path, checks: &InputChecks) -> Result<HitBatch> {
+    let is_csv = path
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("csv"));
+    let hits = if is_csv {
+        let input = std::fs::File::open(path)?;
+        rustpix_io::read_generic_csv_hits(std::io::BufReader::new(input), &checks.import)
+    } else {
+        #[cfg(feature = "hdf5")]
+        {
+            rustpix_io::read_generic_hdf5_hits(path, checks.import_group.as_deref(), &checks.import)
+        }
+        #[cfg(not(feature = "hdf5"))]
+        {
+            let _ = &checks.import_group;
+            return Err(CliError::InvalidInput(format!(
+                "{}: rustpix was built without the hdf5 feature needed for HDF5 hit lists",
+                path.display()
+            )));
+        }
+    };
+    hits.map_err(|err| CliError::InvalidInput(format!("{}: {err}", path.display())))
+}
+
+/// Check a reader's TDC frequency and coverage, set its out-of-bounds
+/// policy and counts and its missing-TDC correction, enable raw hit fields if requested, and record its
+/// dropped pulses, both as a warning and in `pulse_runs`.
+fn prepare_reader(
+    path: &Path,
+    reader: Tpx3FileReader,
+    checks: &InputChecks,
+    out_of_bounds: &OutOfBoundsCounts,
+    raw_fields: bool,
+    pulse_runs: Option<&mut Vec<pulses::PulseRun>>,
+    verbose: bool,
+) -> (Tpx3FileReader, Vec<CoverageFlag>, Warnings) {
+    let reader = check_tdc_frequency(path, reader, checks, verbose);
+    let (mut reader, flags) = check_tdc_coverage(path, reader, checks.no_tdc_fallback);
+    let config = DetectorConfig {
+        out_of_bounds: checks
+            .out_of_bounds
+            .unwrap_or(reader.config().out_of_bounds),
+        out_of_bounds_counts: out_of_bounds.clone(),
+        tdc_correction_policy: checks
+            .tdc_correction
+            .unwrap_or(reader.config().tdc_correction_policy),
+        ..reader.config().clone()
+    };
+    reader = reader.with_config(config);
+    if raw_fields {
+        let config = DetectorConfig {
+            retain_raw_hits: true,
+            ..reader.config().clone()
+        };
+        reader = reader.with_config(config);
+    }
+    let report = reader.pulse_gaps();
+    let mut warnings = Warnings::default();
+    if let Some(report) = &report {
+        let missing = report.missing_pulses();
+        warnings.record(WarningKind::TdcGap, missing, || {
+            format!(
+                "{missing} pulses missing from the TDC stream in {} gaps ({:.3} s of beam)",
+                report.gaps.len(),
+                report.lost_time().as_secs_f64()
+            )
+        });
+    }
+    if let Some(runs) = pulse_runs {
+        let run = pulses::PulseRun {
+            path: path.display().to_string(),
+            report,
+        };
+        if verbose {
+            run.print();
+        }
+        runs.push(run);
+    }
+    (reader, flags, warnings)
+}
+
+/// Cluster, extract and write whole pulses, accumulating into `file`.
+///
+/// Hits on pixels of the pixel mask and hits failing the hit filter are
+/// dropped first, and hits with a `ToT` of `tot_saturated` or more are
+/// counted as saturated. With a checkpointer, progress is saved at its
+/// interval, with positions taken from the file's pulse index.
+#[allow(clippy::too_many_arguments)]
+fn process_pulses(
+    stream: impl Iterator<Item = rustpix_io::EventBatch>,
+    file: &mut timing::FileTiming,
+    pipeline: &Pipeline,
+    sink: &mut NeutronSink,
+    mut hit_export: Option<&mut HitExport>,
+    mut validation: Option<&mut ValidationReport>,
+    mut checkpoint: Option<(&mut checkpoint::Checkpointer, &rustpix_io::SectionIndex)>,
+    out_of_bounds: &OutOfBounds,
+    tot_saturated: u16,
+) -> Result<()> {
+    let keep_pixel = pipeline
+        .checks
+        .pixel_mask
+        .as_ref()
+        .map(PixelMask::keep_filter);
+    let mut next_start = Instant::now();
+    for event in stream {
+        out_of_bounds.check(Path::new(&file.path))?;
+        let tdc_timestamp_25ns = event.tdc_timestamp_25ns;
+        let mut batch = event.hits;
+        if let Some(keep) = &keep_pixel {
+            batch.retain_pixels(keep);
+        }
+        if let Some(filter) = &pipeline.checks.hit_filter {
+            filter.apply(&mut batch);
+        }
+        file.stages.parse += next_start.elapsed();
+        file.chunks.record(batch.len());
+        file.hits = file.hits.saturating_add(batch.len());
+        let saturated = batch
+            .tot
+            .iter()
+            .filter(|&&tot| tot >= tot_saturated)
+            .count();
+        file.warnings
+            .record(WarningKind::SaturatedPixel, saturated as u64, || {
+                format!("{saturated} hits saturated the ToT register in one pulse")
+            });
+        if is_unsectionable(
+            &batch,
+            pipeline.algorithm,
+            &pipeline.clustering,
+            pipeline.params,
+        ) {
+            let hits = batch.len();
+            file.warnings.record(WarningKind::UnsectionedPulse, 1, || {
+                format!("a pulse of {hits} hits could not be cut into sections")
+            });
+        }
+
+        let stage_start = Instant::now();
+        let num_clusters = cluster_batch(
+            &mut batch,
+            pipeline.algorithm,
+            &pipeline.clustering,
+            pipeline.params,
+        )?;
+        file.stages.cluster += stage_start.elapsed();
+        if let Some(report) = validation.as_deref_mut() {
+            report.check_labels(&batch.cluster_id, num_clusters);
+        }
+
+        let stage_start = Instant::now();
+        let mut neutrons = extract_batch(&batch, num_clusters, &pipeline.extraction)?;
+        file.stages.extract += stage_start.elapsed();
+        file.neutrons = file.neutrons.saturating_add(neutrons.len());
+        tally_classes(file, &mut neutrons, pipeline.keep_classes);
+
+        let stage_start = Instant::now();
+        sink.write(tdc_timestamp_25ns, neutrons)?;
+        if let Some(export) = hit_export.as_deref_mut() {
+            export.write(tdc_timestamp_25ns, &mut batch, num_clusters)?;
+        }
+        if let Some((checkpointer, index)) = checkpoint.as_mut() {
+            save_progress(checkpointer, index, tdc_timestamp_25ns, file, sink)?;
+        }
+        file.stages.write += stage_start.elapsed();
+        next_start = Instant::now();
+    }
+    Ok(())
+}
+
+/// Number the pulses of the next input by `pulses` in the `pulse_id` columns
+/// and the event index of the outputs that have them.
+fn number_pulses(
+    sink: &mut NeutronSink,
+    hit_export: Option<&mut HitExport>,
+    pulses: &PulseIndex,
+) -> Result<()> {
+    sink.set_pulses(pulses.clone())?;
+    if let Some(export) = hit_export.filter(|export| export.pulses.is_some()) {
+        export.pulses = Some(pulses.clone());
+    }
+    Ok(())
+}
+
+/// Save progress through an input once the checkpoint interval has passed,
+/// after the pulse at `tdc_timestamp_25ns` has been written.
+fn save_progress(
+    checkpointer: &mut checkpoint::Checkpointer,
+    index: &rustpix_io::SectionIndex,
+    tdc_timestamp_25ns: u64,
+    file: &timing::FileTiming,
+    sink: &mut NeutronSink,
+) -> Result<()> {
+    if checkpointer.due() {
+        let (output_bytes, wrote_header) = sink.flushed_output()?;
+        let position = index.position(tdc_timestamp_25ns);
+        checkpointer.save_progress(position, file, output_bytes, wrote_header)?;
+    }
+    Ok(())
+}
+
+/// Record a completed input in the checkpoint, once its neutrons are on
+/// disk.
+fn checkpoint_input(
+    checkpointer: Option<&mut checkpoint::Checkpointer>,
+    sink: &mut NeutronSink,
+    file: &timing::FileTiming,
+) -> Result<()> {
+    if let Some(checkpointer) = checkpointer {
+        let (output_bytes, wrote_header) = sink.flushed_output()?;
+        checkpointer.finish_input(file, output_bytes, wrote_header)?;
+    }
+    Ok(())
+}
+
+/// Read a JSON event classifier.
+fn load_classifier(path: &Path) -> Result<EventClassifier> {
+    serde_json::from_str(&std::fs::read_to_string(path)?).map_err(|err| {
+        CliError::InvalidInput(format!("{}: invalid classifier: {err}", path.display()))
+    })
+}
+
+/// Count the events of each class and drop those not in `keep` (an empty
+/// `keep` keeps everything).
+fn tally_classes(file: &mut timing::FileTiming, neutrons: &mut NeutronBatch, keep: &[EventClass]) {
+    if let Some(counts) = neutrons.class_counts() {
+        file.classes
+            .get_or_insert_with(ClassCounts::default)
+            .merge(&counts);
+    }
+    if !keep.is_empty() {
+        neutrons.retain_classes(keep);
+    }
+}
+
+/// How to react to problems found in an input: a measured TDC frequency
+/// that disagrees with the configured one, hits without a TDC, and hits
+/// mapped outside the detector; and how to read hit list inputs.
+#[derive(Clone, Debug)]
+struct InputChecks {
+    /// Instrument whose detector config replaces the default one.
+    instrument: Option<InstrumentProfile>,
+    /// Relative difference that counts as a disagreement.
+    tolerance: f64,
+    /// Switch to the measured frequency instead of only warning.
+    adopt: bool,
+    /// TOF reference for hits without a preceding TDC.
+    no_tdc_fallback: NoTdcFallback,
+    /// Missing-TDC correction overriding the detector config's.
+    tdc_correction: Option<TdcCorrectionPolicy>,
+    /// Out-of-bounds policy overriding the detector config's.
+    out_of_bounds: Option<OutOfBoundsPolicy>,
+    /// Pixels whose hits are dropped before clustering.
+    pixel_mask: Option<PixelMask>,
+    /// Region, TOF and `ToT` cuts applied before clustering.
+    hit_filter: Option<HitFilter>,
+    /// Units and columns of hit list inputs.
+    import: GenericHitOptions,
+    /// HDF5 group of hit list inputs.
+    import_group: Option<String>,
+}
+
+/// Fail if the pixel mask was made for a detector of another size than the
+/// one `config` describes.
+fn check_pixel_mask(path: &Path, checks: &InputChecks, config: &DetectorConfig) -> Result<()> {
+    let Some(mask) = &checks.pixel_mask else {
+        return Ok(());
+    };
+    let (width, height) = config.detector_dimensions();
+    if (mask.width, mask.height) == (width, height) {
+        return Ok(());
+    }
+    Err(CliError::InvalidInput(format!(
+        "{}: pixel mask is {}x{} but the detector is {width}x{height}",
+        path.display(),
+        mask.width,
+        mask.height
+    )))
+}
+
+/// Hits mapped outside the detector while reading one input.
+struct OutOfBounds {
+    policy: OutOfBoundsPolicy,
+    counts: OutOfBoundsCounts,
+}
+
+impl OutOfBounds {
+    /// Fail if hits fell outside the detector under the error policy.
+    fn check(&self, path: &Path) -> Result<()> {
+        let total = self.counts.total();
+        if self.policy != OutOfBoundsPolicy::Error || total == 0 {
+            return Ok(());
+        }
+        Err(CliError::InvalidInput(format!(
+            "{}: {total} hits mapped outside the detector ({}); check the chip size and \
+             transforms of the detector config, or pass --out-of-bounds clamp or drop",
+            path.display(),
+            self.counts
+        )))
+    }
+
+    /// Record the hits that were clamped or dropped as a warning.
+    fn record(&self, warnings: &mut Warnings) {
+        let total = self.counts.total();
+        let action = match self.policy {
+            OutOfBoundsPolicy::Clamp => "clamped to the edge",
+            OutOfBoundsPolicy::Drop | OutOfBoundsPolicy::Error => "dropped",
+        };
+        warnings.record(WarningKind::OutOfBounds, total, || {
+            format!(
+                "{total} hits mapped outside the detector were {action} ({})",
+                self.counts
+            )
+        });
+    }
+}
+
+/// Compare the configured TDC frequency with the one measured from the file.
+fn check_tdc_frequency(
+    path: &Path,
+    reader: Tpx3FileReader,
+    check: &InputChecks,
+    verbose: bool,
+) -> Tpx3FileReader {
+    let Some(estimate) = reader.estimate_tdc_frequency() else {
+        return reader;
+    };
+    let configured = reader.config().tdc_frequency_hz;
+    if verbose {
+        eprintln!(
+            "  Measured TDC frequency: {:.3} Hz (median of {} intervals on chip {})",
+            estimate.frequency_hz, estimate.intervals, estimate.chip_id
+        );
+    }
+    if !estimate.disagrees_with(configured, check.tolerance) {
+        return reader;
+    }
+    if check.adopt {
+        eprintln!(
+            "{}: using measured TDC frequency {:.3} Hz instead of configured {configured} Hz",
+            path.display(),
+            estimate.frequency_hz
+        );
+        let config = DetectorConfig {
+            tdc_frequency_hz: estimate.frequency_hz,
+            ..reader.config().clone()
+        };
+        reader.with_config(config)
+    } else {
+        eprintln!(
+            "Warning: {}: measured TDC frequency {:.3} Hz differs from configured {configured} Hz by {:.1}% (pass --auto-tdc-frequency to use it)",
+            path.display(),
+            estimate.frequency_hz,
+            estimate.relative_difference(configured) * 100.0
+        );
+        reader
+    }
+}
+
+/// Warn about empty files and hits without a TDC, and apply the fallback
+/// for the latter.
+fn check_tdc_coverage(
+    path: &Path,
+    reader: Tpx3FileReader,
+    fallback: NoTdcFallback,
+) -> (Tpx3FileReader, Vec<CoverageFlag>) {
+    let flags = reader.tdc_coverage().flags();
+    for flag in &flags {
+        let hint = match flag {
+            CoverageFlag::NoTdc | CoverageFlag::HitsBeforeTdc(_)
+                if fallback == NoTdcFallback::Drop =>
+            {
+                "; they are dropped (pass --no-tdc-fallback first-hit to keep them)"
+            }
+            _ => "",
+        };
+        eprintln!("Warning: {}: {flag}{hint}", path.display());
+    }
+    let config = DetectorConfig {
+        no_tdc_fallback: fallback,
+        ..reader.config().clone()
+    };
+    (reader.with_config(config), flags)
+}
+
+fn print_validation(path: &Path, report: &ValidationReport) {
+    eprintln!(
+        "Validation of {}: {} sections, {} hits, {} labels checked",
+        path.display(),
+        report.sections_checked,
+        report.hits_checked,
+        report.labels_checked
+    );
+    if report.is_clean() {
+        eprintln!("  no violations");
+        return;
+    }
+    eprintln!(
+        "  {} timestamp, {} bounds, {} label violation(s)",
+        report.timestamp_violations, report.bounds_violations, report.label_violations
+    );
+    for violation in &report.violations {
+        eprintln!("  {violation}");
+    }
+    let hidden = report.total_violations() - report.violations.len();
+    if hidden > 0 {
+        eprintln!("  ... and {hidden} more");
+    }
+}
+
+/// Open a TPX3 input with the detector config of the `--instrument`
+/// profile, if one was given.
+fn open_input_reader(path: &Path, checks: &InputChecks) -> Result<Tpx3FileReader> {
+    let reader = open_reader(path)?;
+    Ok(match &checks.instrument {
+        Some(profile) => reader.with_config(profile.detector.clone()),
+        None => reader,
+    })
+}
+
+/// Local file that a remote output is written to before upload.
+fn staging_path(uri: &str) -> PathBuf {
+    let name = uri.rsplit('/').next().unwrap_or("output");
+    std::env::temp_dir().join(format!("rustpix-{}-{name}", std::process::id()))
+}
+
+/// Upload a finished local output to an `s3://` URI.
+fn upload_output(local: &Path, uri: &str) -> Result<()> {
+    #[cfg(feature = "object-store")]
+    {
+        let (store, path) = rustpix_io::remote::resolve(uri)?;
+        rustpix_io::remote::upload_file(store.as_ref(), local, &path)?;
+        Ok(())
+    }
+    #[cfg(not(feature = "object-store"))]
+    {
+        let _ = local;
+        Err(crate::object_store_disabled(uri))
+    }
+}
+
+/// Parse a `MIN,MAX` range.
+fn parse_range<T: std::str::FromStr + PartialOrd>(
+    value: &str,
+) -> std::result::Result<(T, T), String> {
+    let (min, max) = value
+        .split_once(',')
+        .ok_or_else(|| format!("expected MIN,MAX, got '{value}'"))?;
+    let bound = |text: &str| {
+        text.trim()
+            .parse::<T>()
+            .map_err(|_| format!("'{text}' is not a valid bound"))
+    };
+    let (min, max) = (bound(min)?, bound(max)?);
+    if min > max {
+        return Err(format!("range '{value}' is empty"));
+    }
+    Ok((min, max))
+}
+
+/// Parse a hit region: `X0,Y0,X1,Y1` for a rectangle or `X,Y;X,Y;X,Y;...`
+/// for a polygon.
+fn parse_roi(value: &str) -> std::result::Result<HitRegion, String> {
+    let region = if value.contains(';') {
+        let vertices = value
+            .split(';')
+            .filter(|vertex| !vertex.trim().is_empty())
+            .map(parse_point)
+            .collect::<std::result::Result<_, _>>()?;
+        HitRegion::Polygon { vertices }
+    } else {
+        let coords: Vec<f64> = value
+            .split(',')
+            .map(|text| {
+                text.trim()
+                    .parse::<f64>()
+                    .map_err(|_| format!("'{text}' is not a number"))
+            })
+            .collect::<std::result::Result<_, _>>()?;
+        let [x_min, y_min, x_max, y_max] = coords[..] else {
+            return Err(format!(
+                "expected X0,Y0,X1,Y1 or X,Y;X,Y;X,Y, got '{value}'"
+            ));
+        };
+        HitRegion::Rectangle {
+            x_min,
+            y_min,
+            x_max,
+            y_max,
+        }
+    };
+    HitFilter::new()
+        .with_region(region.clone())
+        .validate()
+        .map_err(|err| err.to_string())?;
+    Ok(region)
+}
+
+/// Hit filter of the `--roi`, `--tof-range` (ms) and `--tot-range` flags, or
+/// `None` if none was given.
+fn hit_filter(
+    regions: Vec<HitRegion>,
+    tof_range_ms: Option<(f64, f64)>,
+    tot_range: Option<(u16, u16)>,
+) -> Result<Option<HitFilter>> {
+    let mut filter = HitFilter {
+        regions,
+        ..HitFilter::default()
+    };
+    if let Some((min, max)) = tof_range_ms {
+        filter = filter.with_tof_range_ns(min * 1e6, max * 1e6);
+    }
+    if let Some((min, max)) = tot_range {
+        filter = filter.with_tot_range(min, max);
+    }
+    filter
+        .validate()
+        .map_err(|err| CliError::InvalidInput(format!("hit filter: {err}")))?;
+    Ok((!filter.is_empty()).then_some(filter))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+    use rustpix_tpx::synthetic::{self, SyntheticTpx3Config};
+
+    #[derive(Parser)]
+    struct Command {
+        #[command(flatten)]
+        options: ProcessOptions,
+    }
+
+    fn process(args: &[&str]) -> Result<()> {
+        let command =
+            Command::try_parse_from(std::iter::once("process").chain(args.iter().copied()))
+                .unwrap();
+        run(&command.options)
+    }
+
+    #[test]
+    fn test_resumed_run_matches_uninterrupted_run() {
+        const PULSES: usize = 6;
+        let dir = tempfile::tempdir().unwrap();
+        let inputs: Vec<String> = (0..2)
+            .map(|seed| {
+                let path = dir.path().join(format!("run_{seed}.tpx3"));
+                let config = SyntheticTpx3Config {
+                    pulses: PULSES,
+                    neutrons_per_pulse: 20,
+                    seed,
+                    ..SyntheticTpx3Config::default()
+                };
+                synthetic::generate(&config, &DetectorConfig::default())
+                    .write(&path)
+                    .unwrap();
+                path.display().to_string()
+            })
+            .collect();
+        let path = |name: &str| dir.path().join(name).display().to_string();
+
+        for ext in ["csv", "bin"] {
+            let full = path(&format!("full.{ext}"));
+            process(&[&inputs[0], &inputs[1], "-o", &full]).unwrap();
+
+            // Stop halfway through the second input: after a save per pulse
+            // of the first, its completion and half the pulses of the second.
+            let output = path(&format!("resumed.{ext}"));
+            let checkpoint = path(&format!("{ext}.checkpoint"));
+            let args = [
+                &inputs[0],
+                &inputs[1],
+                "-o",
+                &output,
+                "--checkpoint-interval",
+                "0",
+            ];
+            checkpoint::interrupt_after_saves(Some(PULSES + 1 + PULSES / 2));
+            let err = process(&[&args[..], &["--checkpoint", &checkpoint]].concat());
+            checkpoint::interrupt_after_saves(None);
+            assert!(err.unwrap_err().to_string().contains("interrupted"));
+
+            let state: checkpoint::Checkpoint =
+                serde_json::from_str(&std::fs::read_to_string(&checkpoint).unwrap()).unwrap();
+            assert_eq!(state.completed.len(), 1);
+            assert!(state.current.is_some());
+            // The pulse written after the last checkpoint is truncated away.
+            let partial = atomic::partial_path(Path::new(&output));
+            assert!(std::fs::metadata(&partial).unwrap().len() > state.output_bytes);
+            assert!(!Path::new(&output).exists());
+
+            process(&[&args[..], &["--resume", &checkpoint]].concat()).unwrap();
+            assert_eq!(
+                std::fs::read(&output).unwrap(),
+                std::fs::read(&full).unwrap()
+            );
+            assert!(!Path::new(&checkpoint).exists());
+            assert!(!partial.exists());
+        }
+    }
+}
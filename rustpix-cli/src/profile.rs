@@ -0,0 +1,88 @@
+//! Radial and azimuthal profiles for the `radial-profile` command.
+
+use crate::Result;
+use rustpix_algorithms::IntegratedProfile;
+use rustpix_core::soa::HitBatch;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+/// Hit counts images, one per TOF bin, each laid out as `[y][x]`.
+pub struct TofImages {
+    pub width: usize,
+    pub height: usize,
+    /// Width of a TOF bin in 25 ns ticks.
+    pub bin_ticks: f64,
+    pub images: Vec<Vec<u64>>,
+}
+
+impl TofImages {
+    /// Histogram `batch` into `tof_bins` images spanning its TOF range.
+    /// Hits outside `width` × `height` are dropped.
+    pub fn from_hits(batch: &HitBatch, width: usize, height: usize, tof_bins: usize) -> Self {
+        let tof_bins = tof_bins.max(1);
+        let max_tof = batch.tof.iter().copied().max().unwrap_or(0);
+        #[allow(clippy::cast_precision_loss)]
+        let bin_ticks = (f64::from(max_tof) + 1.0) / tof_bins as f64;
+        let mut images = vec![vec![0u64; width * height]; tof_bins];
+        for ((&x, &y), &tof) in batch.x.iter().zip(&batch.y).zip(&batch.tof) {
+            let (x, y) = (usize::from(x), usize::from(y));
+            if x >= width || y >= height {
+                continue;
+            }
+            #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+            let bin = ((f64::from(tof) / bin_ticks) as usize).min(tof_bins - 1);
+            images[bin][y * width + x] += 1;
+        }
+        Self {
+            width,
+            height,
+            bin_ticks,
+            images,
+        }
+    }
+
+    /// All TOF bins summed into one image.
+    pub fn total(&self) -> Vec<u64> {
+        let mut total = vec![0u64; self.width * self.height];
+        for image in &self.images {
+            for (sum, &count) in total.iter_mut().zip(image) {
+                *sum += count;
+            }
+        }
+        total
+    }
+}
+
+/// Write profiles as CSV. With `tof_bin_ticks`, each profile is one TOF bin
+/// and its bin index and range (ns) lead every row.
+pub fn write_csv(
+    output: &Path,
+    profiles: &[IntegratedProfile],
+    azimuthal: bool,
+    tof_bin_ticks: Option<f64>,
+) -> Result<()> {
+    let mut writer = BufWriter::new(File::create(output)?);
+    let axis = if azimuthal { "angle_deg" } else { "radius_px" };
+    if tof_bin_ticks.is_some() {
+        write!(writer, "tof_bin,tof_start_ns,tof_end_ns,")?;
+    }
+    writeln!(writer, "{axis},counts,pixels,mean")?;
+    for (bin, profile) in profiles.iter().enumerate() {
+        let mean = profile.mean();
+        for (i, &center) in profile.centers.iter().enumerate() {
+            if let Some(ticks) = tof_bin_ticks {
+                #[allow(clippy::cast_precision_loss)]
+                let start = bin as f64 * ticks * 25.0;
+                write!(writer, "{bin},{start},{},", start + ticks * 25.0)?;
+            }
+            writeln!(
+                writer,
+                "{center},{},{},{}",
+                profile.counts[i], profile.pixels[i], mean[i]
+            )?;
+        }
+    }
+    writer.flush()?;
+    Ok(())
+}
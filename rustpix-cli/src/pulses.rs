@@ -0,0 +1,213 @@
+//! Dropped-pulse diagnostics for the `info` and `process` commands, and the
+//! per-pulse event index of `process --event-index`.
+//!
+//! Missing pulses show up as TDC intervals of a whole number of periods.
+//! Each run lists where pulses were dropped (seconds since its first TDC)
+//! and how much beam time they account for, so normalization can be
+//! corrected instead of discovered wrong afterwards.
+
+use crate::Result;
+use rustpix_core::pulse::{EventIndex, PulseIndex};
+use rustpix_tpx::tdc::PulseGapReport;
+use std::path::Path;
+use std::time::Duration;
+
+/// Gaps printed per run before the list is cut short.
+const MAX_LISTED_GAPS: usize = 20;
+
+/// Dropped-pulse result for one input file.
+#[derive(Debug, Clone)]
+pub struct PulseRun {
+    /// Input path as given on the command line.
+    pub path: String,
+    /// Gap report, `None` when the file has no TDC packets.
+    pub report: Option<PulseGapReport>,
+}
+
+impl PulseRun {
+    /// Print a summary and the first gaps to stderr.
+    pub fn print(&self) {
+        let Some(report) = &self.report else {
+            eprintln!("{}: no TDC packets, pulse gaps not checked", self.path);
+            return;
+        };
+        eprintln!(
+            "{}: {} pulses, {} dropped ({:.3} s beam time lost)",
+            self.path,
+            report.pulses,
+            report.missing_pulses(),
+            report.lost_time().as_secs_f64()
+        );
+        for gap in report.gaps.iter().take(MAX_LISTED_GAPS) {
+            eprintln!(
+                "  {} missing after {:.6} s",
+                gap.missing,
+                ticks_to_seconds(gap.after_25ns)
+            );
+        }
+        if report.gaps.len() > MAX_LISTED_GAPS {
+            eprintln!(
+                "  ... and {} more gaps",
+                report.gaps.len() - MAX_LISTED_GAPS
+            );
+        }
+    }
+
+    fn to_json(&self) -> serde_json::Value {
+        let Some(report) = &self.report else {
+            return serde_json::json!({ "path": self.path, "pulses": 0 });
+        };
+        serde_json::json!({
+            "path": self.path,
+            "chip_id": report.chip_id,
+            "period_s": ticks_to_seconds(u64::from(report.period_25ns)),
+            "pulses": report.pulses,
+            "missing_pulses": report.missing_pulses(),
+            "lost_time_s": report.lost_time().as_secs_f64(),
+            "gaps": report
+                .gaps
+                .iter()
+                .map(|gap| serde_json::json!({
+                    "after_s": ticks_to_seconds(gap.after_25ns),
+                    "missing": gap.missing,
+                }))
+                .collect::<Vec<_>>(),
+        })
+    }
+}
+
+/// Write all runs as a JSON report.
+///
+/// # Errors
+/// Returns an error if the report cannot be serialized or written.
+pub fn write_json(path: &Path, runs: &[PulseRun]) -> Result<()> {
+    let missing: u64 = runs
+        .iter()
+        .filter_map(|run| run.report.as_ref())
+        .map(PulseGapReport::missing_pulses)
+        .sum();
+    let lost: f64 = runs
+        .iter()
+        .filter_map(|run| run.report.as_ref())
+        .map(|report| report.lost_time().as_secs_f64())
+        .sum();
+    let json = serde_json::json!({
+        "runs": runs.iter().map(PulseRun::to_json).collect::<Vec<_>>(),
+        "missing_pulses": missing,
+        "lost_time_s": lost,
+    });
+    crate::atomic::write(path, serde_json::to_string_pretty(&json)?)?;
+    Ok(())
+}
+
+/// Records the pulse of every neutron batch written, for `--event-index`.
+///
+/// Pulses of an input's TDC stream that give no neutrons (or have no hits
+/// at all) are filled in with no events, so the index lists every pulse of
+/// the run, as `NXevent_data` consumers expect. Inputs follow each other in
+/// the neutron output, and so do their pulses in the index.
+#[derive(Debug, Default)]
+pub struct EventIndexRecorder {
+    index: EventIndex,
+    pulses: PulseIndex,
+    next: usize,
+}
+
+impl EventIndexRecorder {
+    /// Start the next input, whose pulses are `pulses`.
+    pub fn start_input(&mut self, pulses: PulseIndex) {
+        self.fill_until(None);
+        self.pulses = pulses;
+        self.next = 0;
+    }
+
+    /// Record `events` neutrons written for the pulse at `start_time_25ns`.
+    pub fn record(&mut self, start_time_25ns: u64, events: usize) {
+        self.fill_until(Some(start_time_25ns));
+        if self.pulses.start_times_25ns().get(self.next) == Some(&start_time_25ns) {
+            self.next += 1;
+        }
+        self.index.push(start_time_25ns, events);
+    }
+
+    /// The index, with the remaining pulses of the last input filled in.
+    pub fn finish(mut self) -> EventIndex {
+        self.fill_until(None);
+        self.index
+    }
+
+    /// Add the pulses of the input before `time_25ns` (all if `None`) that
+    /// were not written, without events.
+    fn fill_until(&mut self, time_25ns: Option<u64>) {
+        while let Some(&start) = self.pulses.start_times_25ns().get(self.next) {
+            if time_25ns.is_some_and(|time| start >= time) {
+                break;
+            }
+            self.index.push(start, 0);
+            self.next += 1;
+        }
+    }
+}
+
+fn ticks_to_seconds(ticks: u64) -> f64 {
+    Duration::from_nanos(ticks.saturating_mul(25)).as_secs_f64()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rustpix_tpx::tdc::PulseGap;
+
+    #[test]
+    fn test_json_report_totals() {
+        let runs = [
+            PulseRun {
+                path: "run_1.tpx3".to_string(),
+                report: Some(PulseGapReport {
+                    chip_id: 0,
+                    period_25ns: 40_000_000,
+                    pulses: 10,
+                    gaps: vec![PulseGap {
+                        after_25ns: 80_000_000,
+                        missing: 2,
+                    }],
+                }),
+            },
+            PulseRun {
+                path: "run_2.tpx3".to_string(),
+                report: None,
+            },
+        ];
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("pulses.json");
+        write_json(&path, &runs).unwrap();
+
+        let json: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(json["missing_pulses"], 2);
+        assert_eq!(json["lost_time_s"], 2.0);
+        assert_eq!(json["runs"][0]["gaps"][0]["after_s"], 2.0);
+        assert_eq!(json["runs"][1]["pulses"], 0);
+    }
+
+    #[test]
+    fn test_event_index_fills_pulses_without_neutrons() {
+        let mut recorder = EventIndexRecorder::default();
+        recorder.start_input(PulseIndex::new(vec![100, 200, 300, 400]));
+        recorder.record(200, 3);
+        recorder.record(400, 1);
+        // The next input starts its pulses again.
+        recorder.start_input(PulseIndex::new(vec![50, 150]));
+        recorder.record(50, 2);
+        // A synthetic pulse not in the TDC stream.
+        recorder.record(60, 1);
+        let index = recorder.finish();
+
+        assert_eq!(
+            index.event_time_zero_25ns(),
+            &[100, 200, 300, 400, 50, 60, 150]
+        );
+        assert_eq!(index.event_index(), &[0, 0, 3, 3, 4, 6, 7]);
+        assert_eq!(index.event_count(), 7);
+    }
+}
@@ -0,0 +1,210 @@
+//! Multi-file runs for the `process` command.
+//!
+//! An input that is a directory or a glob pattern (`data/run_*.tpx3`) names
+//! one acquisition split across chip or quad files. Its files are merged
+//! into a single time-ordered pulse stream with [`rustpix_io::Tpx3RunReader`]
+//! instead of being processed one after another. A file can carry its own
+//! detector configuration (e.g. the chip transforms of its quad) in a
+//! sidecar JSON next to it with the same stem (`run_001.json` for
+//! `run_001.tpx3`).
+
+use crate::{CliError, Result};
+use rustpix_tpx::DetectorConfig;
+use std::path::{Path, PathBuf};
+
+/// One entry of the `process` input list after expansion.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Input {
+    /// A single file, processed on its own.
+    File(PathBuf),
+    /// Files of one run, merged by TDC timestamp.
+    Run {
+        /// Directory or pattern as given on the command line.
+        source: PathBuf,
+        /// Matching files in name order.
+        files: Vec<PathBuf>,
+    },
+}
+
+impl Input {
+    /// Number of files this input reads.
+    pub fn file_count(&self) -> usize {
+        match self {
+            Self::File(_) => 1,
+            Self::Run { files, .. } => files.len(),
+        }
+    }
+}
+
+/// Expand directories and glob patterns in the input list.
+///
+/// A directory contributes its `.tpx3` files, a pattern the files in its
+/// parent directory whose names match it (`*` and `?` wildcards). Either
+/// becomes a run when it matches more than one file. Other paths, including
+/// `s3://` URIs, are kept as single files.
+///
+/// # Errors
+/// Returns an error if a directory cannot be listed or a directory or
+/// pattern matches no files.
+pub fn expand_inputs(inputs: &[PathBuf]) -> Result<Vec<Input>> {
+    inputs
+        .iter()
+        .map(|input| {
+            if crate::remote_uri(input).is_some() {
+                return Ok(Input::File(input.clone()));
+            }
+            let files = if input.is_dir() {
+                tpx3_files(input)?
+            } else if let Some(pattern) = file_pattern(input) {
+                let dir = match input.parent() {
+                    Some(dir) if !dir.as_os_str().is_empty() => dir,
+                    _ => Path::new("."),
+                };
+                list_files(dir, |name| matches_pattern(pattern, name))?
+            } else {
+                return Ok(Input::File(input.clone()));
+            };
+            match files.len() {
+                0 => Err(CliError::InvalidInput(format!(
+                    "{}: no matching TPX3 files",
+                    input.display()
+                ))),
+                1 => Ok(Input::File(files.into_iter().next().unwrap_or_default())),
+                _ => Ok(Input::Run {
+                    source: input.clone(),
+                    files,
+                }),
+            }
+        })
+        .collect()
+}
+
+/// Load the sidecar detector configuration of a run file, if it has one.
+///
+/// # Errors
+/// Returns an error if the sidecar exists but is not a valid detector
+/// configuration.
+pub fn sidecar_config(path: &Path) -> Result<Option<DetectorConfig>> {
+    let sidecar = path.with_extension("json");
+    if !sidecar.is_file() {
+        return Ok(None);
+    }
+    DetectorConfig::from_file(&sidecar)
+        .map(Some)
+        .map_err(|err| {
+            CliError::InvalidInput(format!(
+                "{}: invalid detector config: {err}",
+                sidecar.display()
+            ))
+        })
+}
+
+/// The `.tpx3` files in `dir`, sorted by name.
+///
+/// # Errors
+/// Returns an error if the directory cannot be listed.
+pub fn tpx3_files(dir: &Path) -> Result<Vec<PathBuf>> {
+    list_files(dir, |name| {
+        Path::new(name)
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("tpx3"))
+    })
+}
+
+/// The file name of `path` if it contains wildcards.
+fn file_pattern(path: &Path) -> Option<&str> {
+    path.file_name()
+        .and_then(|name| name.to_str())
+        .filter(|name| name.contains(['*', '?']))
+}
+
+/// Regular files in `dir` whose names pass `keep`, sorted by name.
+fn list_files(dir: &Path, keep: impl Fn(&str) -> bool) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        let path = entry.path();
+        if path.is_file() && entry.file_name().to_str().is_some_and(&keep) {
+            files.push(path);
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// Match a file name against a pattern where `*` matches any run of
+/// characters and `?` any single character.
+pub fn matches_pattern(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    let (mut p, mut n) = (0, 0);
+    // Position after the last `*` and the name position it was tried at.
+    let mut backtrack: Option<(usize, usize)> = None;
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                p += 1;
+                backtrack = Some((p, n));
+            }
+            Some(&c) if c == '?' || c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match backtrack {
+                Some((star_p, star_n)) => {
+                    p = star_p;
+                    n = star_n + 1;
+                    backtrack = Some((star_p, star_n + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_matches_pattern() {
+        assert!(matches_pattern("run_*.tpx3", "run_000.tpx3"));
+        assert!(matches_pattern("run_00?.tpx3", "run_001.tpx3"));
+        assert!(matches_pattern("*", "anything"));
+        assert!(matches_pattern("*a*b", "xxaxxb"));
+        assert!(!matches_pattern("run_*.tpx3", "run_000.json"));
+        assert!(!matches_pattern("run_?.tpx3", "run_10.tpx3"));
+    }
+
+    #[test]
+    fn test_expand_inputs() {
+        let dir = tempfile::tempdir().unwrap();
+        for name in ["run_001.tpx3", "run_000.tpx3", "run_000.json", "other.tpx3"] {
+            std::fs::write(dir.path().join(name), b"").unwrap();
+        }
+        let single = dir.path().join("other.tpx3");
+        let inputs = expand_inputs(&[
+            dir.path().join("run_*.tpx3"),
+            dir.path().to_path_buf(),
+            single.clone(),
+            dir.path().join("oth*"),
+        ])
+        .unwrap();
+
+        assert_eq!(
+            inputs[0],
+            Input::Run {
+                source: dir.path().join("run_*.tpx3"),
+                files: vec![
+                    dir.path().join("run_000.tpx3"),
+                    dir.path().join("run_001.tpx3")
+                ],
+            }
+        );
+        assert_eq!(inputs[1].file_count(), 3);
+        assert_eq!(inputs[2], Input::File(single.clone()));
+        assert_eq!(inputs[3], Input::File(single));
+        assert!(expand_inputs(&[dir.path().join("none_*")]).is_err());
+    }
+}
@@ -0,0 +1,366 @@
+//! Output splitting for the `process` command.
+//!
+//! With `--split-every` or `--split-size`, neutrons are written to numbered
+//! parts (`neutrons.0000.bin`, `neutrons.0001.bin`, ...) instead of a single
+//! file, so tools with file-size limits can read the results. Parts are cut
+//! between events, never inside one, and CSV parts each get their own header.
+//! An index (`neutrons.index.json`) lists the parts in order with their event
+//! ranges and sizes.
+
+use crate::atomic::{self, PendingOutputs};
+use rustpix_core::classification::EventClass;
+use rustpix_core::features::ClusterFeatures;
+use rustpix_core::neutron::NeutronBatch;
+use rustpix_io::{DataFileWriter, LEGACY_RECORD_BYTES, NEUTRON_RECORD_BYTES};
+use std::path::{Path, PathBuf};
+
+/// CSV header written at the top of every part.
+const CSV_HEADER: &str = "x,y,tof,tot,n_hits,chip_id\n";
+
+/// When to start a new part. A part ends at whichever limit is hit first.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SplitLimit {
+    /// Maximum events per part.
+    pub max_events: Option<u64>,
+    /// Maximum bytes per part.
+    pub max_bytes: Option<u64>,
+}
+
+impl SplitLimit {
+    /// Returns true if neither limit is set.
+    #[must_use]
+    pub fn is_unlimited(&self) -> bool {
+        self.max_events.is_none() && self.max_bytes.is_none()
+    }
+}
+
+/// Parse an event count such as `100000000` or `1e8`.
+///
+/// # Errors
+/// Returns an error if the value is not a positive whole number.
+pub fn parse_event_count(text: &str) -> Result<u64, String> {
+    let text = text.trim();
+    if let Ok(count) = text.parse::<u64>() {
+        return if count > 0 {
+            Ok(count)
+        } else {
+            Err("event count must be positive".to_string())
+        };
+    }
+    let value = text
+        .parse::<f64>()
+        .map_err(|_| format!("invalid event count '{text}'"))?;
+    #[allow(clippy::cast_precision_loss)]
+    let in_range = (1.0..=u64::MAX as f64).contains(&value);
+    if !in_range || value.fract() > 0.0 {
+        return Err(format!(
+            "invalid event count '{text}', expected a positive whole number"
+        ));
+    }
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    Ok(value as u64)
+}
+
+/// Parse a file size such as `2GB`, `512MiB` or `1000000` (bytes).
+///
+/// `KB`/`MB`/`GB`/`TB` are powers of 1000, `KiB`/`MiB`/`GiB`/`TiB` powers
+/// of 1024.
+///
+/// # Errors
+/// Returns an error if the value or unit is not recognised, or the size is
+/// zero.
+pub fn parse_size(text: &str) -> Result<u64, String> {
+    let text = text.trim();
+    let split = text
+        .find(|c: char| c.is_ascii_alphabetic())
+        .unwrap_or(text.len());
+    let (number, unit) = text.split_at(split);
+    let multiplier: u64 = match unit.trim().to_ascii_lowercase().as_str() {
+        "" | "b" => 1,
+        "k" | "kb" => 1_000,
+        "m" | "mb" => 1_000_000,
+        "g" | "gb" => 1_000_000_000,
+        "t" | "tb" => 1_000_000_000_000,
+        "kib" => 1 << 10,
+        "mib" => 1 << 20,
+        "gib" => 1 << 30,
+        "tib" => 1 << 40,
+        _ => return Err(format!("invalid size unit in '{text}'")),
+    };
+    let value = number
+        .trim()
+        .parse::<f64>()
+        .map_err(|_| format!("invalid size '{text}'"))?;
+    #[allow(clippy::cast_precision_loss)]
+    let bytes = value * multiplier as f64;
+    #[allow(clippy::cast_precision_loss)]
+    let in_range = (1.0..=u64::MAX as f64).contains(&bytes);
+    if !in_range {
+        return Err(format!("invalid size '{text}', expected at least 1 byte"));
+    }
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    Ok(bytes as u64)
+}
+
+/// Path of part `index` of `output`: `neutrons.bin` -> `neutrons.0003.bin`.
+#[must_use]
+pub fn part_path(output: &Path, index: usize) -> PathBuf {
+    let stem = output
+        .file_stem()
+        .map_or_else(|| "neutrons".into(), |stem| stem.to_string_lossy());
+    let name = match output.extension() {
+        Some(ext) => format!("{stem}.{index:04}.{}", ext.to_string_lossy()),
+        None => format!("{stem}.{index:04}"),
+    };
+    output.with_file_name(name)
+}
+
+/// Path of the index file of `output`: `neutrons.bin` -> `neutrons.index.json`.
+#[must_use]
+pub fn index_path(output: &Path) -> PathBuf {
+    output.with_extension("index.json")
+}
+
+/// Size of event `i` of `batch` in the given output format.
+fn event_bytes(format: &str, batch: &NeutronBatch, i: usize) -> u64 {
+    let bytes = match format {
+        "legacy" => LEGACY_RECORD_BYTES,
+        // Same row layout as `DataFileWriter::write_neutron_batch_csv`.
+        "csv" => format!(
+            "{},{},{},{},{},{}\n",
+            batch.x[i], batch.y[i], batch.tof[i], batch.tot[i], batch.n_hits[i], batch.chip_id[i]
+        )
+        .len(),
+        _ => NEUTRON_RECORD_BYTES,
+    };
+    bytes as u64
+}
+
+/// Copy events `start..end` of `batch`.
+#[must_use]
+pub fn slice(batch: &NeutronBatch, start: usize, end: usize) -> NeutronBatch {
+    NeutronBatch {
+        x: batch.x[start..end].to_vec(),
+        y: batch.y[start..end].to_vec(),
+        tof: batch.tof[start..end].to_vec(),
+        tot: batch.tot[start..end].to_vec(),
+        n_hits: batch.n_hits[start..end].to_vec(),
+        chip_id: batch.chip_id[start..end].to_vec(),
+        energy_kev: batch
+            .energy_kev
+            .get(start..end)
+            .map_or_else(Vec::new, <[f64]>::to_vec),
+        event_class: batch
+            .event_class
+            .get(start..end)
+            .map_or_else(Vec::new, <[EventClass]>::to_vec),
+        features: batch
+            .features
+            .get(start..end)
+            .map_or_else(Vec::new, <[ClusterFeatures]>::to_vec),
+        super_resolution_factor: batch.super_resolution_factor,
+    }
+}
+
+/// One written part.
+#[derive(Clone, Debug)]
+struct Part {
+    path: PathBuf,
+    first_event: u64,
+    events: u64,
+    bytes: u64,
+}
+
+/// Numbered output parts and how full the current one is.
+pub struct SplitParts {
+    output: PathBuf,
+    format: String,
+    limit: SplitLimit,
+    parts: Vec<Part>,
+    /// Parts written under their partial paths until the index is written.
+    pending: PendingOutputs,
+}
+
+impl SplitParts {
+    /// Parts of `output` written in `format`, cut at `limit`.
+    #[must_use]
+    pub fn new(output: &Path, format: &str, limit: SplitLimit) -> Self {
+        Self {
+            output: output.to_path_buf(),
+            format: format.to_string(),
+            limit,
+            parts: Vec::new(),
+            pending: PendingOutputs::default(),
+        }
+    }
+
+    /// Create the next part file and return its writer.
+    ///
+    /// # Errors
+    /// Returns an error if the file cannot be created.
+    pub fn start_part(&mut self) -> rustpix_io::Result<DataFileWriter> {
+        let first_event = self
+            .parts
+            .last()
+            .map_or(0, |part| part.first_event + part.events);
+        let path = part_path(&self.output, self.parts.len());
+        let writer = DataFileWriter::create(self.pending.stage(&path))?;
+        self.parts.push(Part {
+            path,
+            first_event,
+            events: 0,
+            bytes: 0,
+        });
+        Ok(writer)
+    }
+
+    /// How many events of `batch` from `start` on fit in the current part,
+    /// and how many bytes they take (including a CSV header if `header`).
+    ///
+    /// Returns at least one event when the part is empty, so an event larger
+    /// than the size limit still gets written.
+    #[must_use]
+    pub fn fit(&self, batch: &NeutronBatch, start: usize, header: bool) -> (usize, u64) {
+        let Some(part) = self.parts.last() else {
+            return (0, 0);
+        };
+        let max_events = self.limit.max_events.unwrap_or(u64::MAX);
+        let max_bytes = self.limit.max_bytes.unwrap_or(u64::MAX);
+        let mut bytes = if header && self.format == "csv" {
+            CSV_HEADER.len() as u64
+        } else {
+            0
+        };
+        let mut count = 0;
+        for i in start..batch.len() {
+            let size = event_bytes(&self.format, batch, i);
+            let events = part.events + count as u64 + 1;
+            let total = part.bytes + bytes + size;
+            let empty = part.events == 0 && count == 0;
+            if !empty && (events > max_events || total > max_bytes) {
+                break;
+            }
+            bytes += size;
+            count += 1;
+        }
+        (count, bytes)
+    }
+
+    /// Record `events` events and `bytes` bytes written to the current part.
+    pub fn record(&mut self, events: usize, bytes: u64) {
+        if let Some(part) = self.parts.last_mut() {
+            part.events += events as u64;
+            part.bytes += bytes;
+        }
+    }
+
+    /// Move the finished parts into place, write the index file, and return
+    /// the paths of all parts and the index.
+    ///
+    /// # Errors
+    /// Returns an error if a part cannot be renamed or the index written.
+    pub fn write_index(&mut self, inputs: &[PathBuf]) -> crate::Result<Vec<PathBuf>> {
+        let file_name = |path: &Path| {
+            path.file_name()
+                .map_or_else(String::new, |name| name.to_string_lossy().into_owned())
+        };
+        let total_events: u64 = self.parts.iter().map(|part| part.events).sum();
+        let index = serde_json::json!({
+            "output": file_name(&self.output),
+            "format": self.format,
+            "max_events_per_part": self.limit.max_events,
+            "max_bytes_per_part": self.limit.max_bytes,
+            "total_events": total_events,
+            "inputs": inputs
+                .iter()
+                .map(|path| path.display().to_string())
+                .collect::<Vec<_>>(),
+            "parts": self
+                .parts
+                .iter()
+                .map(|part| serde_json::json!({
+                    "path": file_name(&part.path),
+                    "first_event": part.first_event,
+                    "events": part.events,
+                    "bytes": part.bytes,
+                }))
+                .collect::<Vec<_>>(),
+        });
+        std::mem::take(&mut self.pending).commit()?;
+        let path = index_path(&self.output);
+        atomic::write(&path, serde_json::to_string_pretty(&index)?)?;
+
+        let mut written: Vec<PathBuf> = self.parts.iter().map(|part| part.path.clone()).collect();
+        written.push(path);
+        Ok(written)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_limits() {
+        assert_eq!(parse_event_count("1e8"), Ok(100_000_000));
+        assert_eq!(parse_event_count("2500"), Ok(2500));
+        assert!(parse_event_count("0").is_err());
+        assert!(parse_event_count("1.5").is_err());
+
+        assert_eq!(parse_size("2GB"), Ok(2_000_000_000));
+        assert_eq!(parse_size("512MiB"), Ok(512 << 20));
+        assert_eq!(parse_size("1.5 kb"), Ok(1500));
+        assert_eq!(parse_size("4096"), Ok(4096));
+        assert!(parse_size("2XB").is_err());
+        assert!(parse_size("0GB").is_err());
+
+        assert_eq!(
+            part_path(Path::new("out/neutrons.bin"), 3),
+            PathBuf::from("out/neutrons.0003.bin")
+        );
+        assert_eq!(
+            index_path(Path::new("out/neutrons.bin")),
+            PathBuf::from("out/neutrons.index.json")
+        );
+    }
+
+    #[test]
+    fn test_fit_respects_both_limits() {
+        let mut batch = NeutronBatch::default();
+        for i in 0..10u32 {
+            batch.x.push(1.0);
+            batch.y.push(2.0);
+            batch.tof.push(i);
+            batch.tot.push(3);
+            batch.n_hits.push(1);
+            batch.chip_id.push(0);
+        }
+        let limit = SplitLimit {
+            max_events: Some(4),
+            max_bytes: Some(3 * NEUTRON_RECORD_BYTES as u64),
+        };
+        let dir = tempfile::tempdir().unwrap();
+        let mut parts = SplitParts::new(&dir.path().join("neutrons.bin"), "bin", limit);
+        parts.start_part().unwrap();
+        assert_eq!(parts.fit(&batch, 0, false), (3, 84));
+        parts.record(3, 84);
+        assert_eq!(parts.fit(&batch, 3, false).0, 0);
+
+        parts.start_part().unwrap();
+        let limit = SplitLimit {
+            max_events: Some(4),
+            max_bytes: None,
+        };
+        parts.limit = limit;
+        assert_eq!(parts.fit(&batch, 3, false).0, 4);
+        parts.record(4, 112);
+
+        let written = parts.write_index(&[PathBuf::from("run.tpx3")]).unwrap();
+        assert_eq!(written.len(), 3);
+        let index: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(&written[2]).unwrap()).unwrap();
+        assert_eq!(index["total_events"], 7);
+        assert_eq!(index["parts"][1]["path"], "neutrons.0001.bin");
+        assert_eq!(index["parts"][1]["first_event"], 3);
+    }
+}
@@ -0,0 +1,330 @@
+//! Per-stage timing and chunk statistics for the `process` command.
+//!
+//! Each input file records wall time for reading (open / map / download),
+//! parsing (packet decode and time ordering), clustering, extraction and
+//! writing, plus the sizes of the chunks it was clustered in. With parallel
+//! out-of-core processing the cluster and extract times are summed over
+//! workers and overlap with parsing, so they can add up to more than the
+//! file's wall time. With write-behind output, the write stage only covers
+//! handing batches to the writer thread (including waiting for queue space);
+//! the writer's own time is reported separately.
+
+use crate::usize_to_f64;
+use rustpix_core::classification::ClassCounts;
+use rustpix_core::warnings::Warnings;
+use rustpix_io::{ChunkStats, WriteBehindStats};
+use std::time::Duration;
+
+/// Time spent in each pipeline stage.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct StageTimes {
+    /// Opening the input (mmap or object download).
+    pub read: Duration,
+    /// Packet parsing and time ordering.
+    pub parse: Duration,
+    /// Cluster labelling.
+    pub cluster: Duration,
+    /// Neutron extraction.
+    pub extract: Duration,
+    /// Writing neutrons to the output.
+    pub write: Duration,
+}
+
+impl StageTimes {
+    fn accumulate(&mut self, other: &Self) {
+        self.read += other.read;
+        self.parse += other.parse;
+        self.cluster += other.cluster;
+        self.extract += other.extract;
+        self.write += other.write;
+    }
+}
+
+/// Timing and counts for one input file.
+#[derive(Debug, Clone, Default)]
+pub struct FileTiming {
+    /// Input path as given on the command line.
+    pub path: String,
+    /// Input size in bytes.
+    pub bytes: usize,
+    /// Hits processed.
+    pub hits: usize,
+    /// Neutrons extracted.
+    pub neutrons: usize,
+    /// Wall time for the whole file.
+    pub wall: Duration,
+    /// Per-stage breakdown.
+    pub stages: StageTimes,
+    /// Sizes of the chunks the hits were clustered in.
+    pub chunks: ChunkStats,
+    /// Conditions that left the output empty or incomplete, such as a file
+    /// without TDC packets.
+    pub flags: Vec<String>,
+    /// Extracted events per class, when a classifier was set.
+    pub classes: Option<ClassCounts>,
+    /// Recoverable oddities such as saturated hits or missing pulses.
+    pub warnings: Warnings,
+}
+
+impl FileTiming {
+    /// Print each warning to stderr on its own `warning[kind]` line.
+    pub fn print_warnings(&self) {
+        for warning in self.warnings.iter() {
+            eprint!(
+                "warning[{}]: {}: {}",
+                warning.kind, self.path, warning.message
+            );
+            if warning.count > 1 {
+                eprint!(" ({} total)", warning.count);
+            }
+            eprintln!();
+        }
+    }
+
+    fn print_stages(&self, indent: &str) {
+        let stages = &self.stages;
+        let rows = [
+            ("read", stages.read, mb_per_sec(self.bytes, stages.read)),
+            ("parse", stages.parse, hits_per_sec(self.hits, stages.parse)),
+            (
+                "cluster",
+                stages.cluster,
+                hits_per_sec(self.hits, stages.cluster),
+            ),
+            (
+                "extract",
+                stages.extract,
+                hits_per_sec(self.hits, stages.extract),
+            ),
+            (
+                "write",
+                stages.write,
+                format!(
+                    "{:.2} M neutrons/s",
+                    rate(self.neutrons, stages.write) / 1e6
+                ),
+            ),
+        ];
+        for (name, time, throughput) in rows {
+            eprintln!(
+                "{indent}{name:<8} {:>9.3}s  {throughput}",
+                time.as_secs_f64()
+            );
+        }
+        let chunks = &self.chunks;
+        eprintln!(
+            "{indent}chunks   {} (hits min {}, mean {:.0}, max {})",
+            chunks.chunks,
+            chunks.min_hits,
+            chunks.mean_hits(),
+            chunks.max_hits
+        );
+    }
+
+    fn to_json(&self) -> serde_json::Value {
+        let stages = &self.stages;
+        serde_json::json!({
+            "path": self.path,
+            "bytes": self.bytes,
+            "hits": self.hits,
+            "neutrons": self.neutrons,
+            "wall_s": self.wall.as_secs_f64(),
+            "stages": {
+                "read": stage_json(stages.read, "bytes_per_s", rate(self.bytes, stages.read)),
+                "parse": stage_json(stages.parse, "hits_per_s", rate(self.hits, stages.parse)),
+                "cluster": stage_json(stages.cluster, "hits_per_s", rate(self.hits, stages.cluster)),
+                "extract": stage_json(stages.extract, "hits_per_s", rate(self.hits, stages.extract)),
+                "write": stage_json(stages.write, "neutrons_per_s", rate(self.neutrons, stages.write)),
+            },
+            "chunks": {
+                "count": self.chunks.chunks,
+                "min_hits": self.chunks.min_hits,
+                "mean_hits": self.chunks.mean_hits(),
+                "max_hits": self.chunks.max_hits,
+            },
+            "flags": self.flags,
+            "classes": self.classes.map(|counts| serde_json::json!({
+                "neutron": counts.neutron,
+                "gamma": counts.gamma,
+                "noise": counts.noise,
+            })),
+            "warnings": self.warnings.iter().map(|warning| serde_json::json!({
+                "kind": warning.kind.as_str(),
+                "count": warning.count,
+                "message": warning.message,
+            })).collect::<Vec<_>>(),
+        })
+    }
+}
+
+/// Timing for a whole `process` run.
+#[derive(Debug, Clone, Default)]
+pub struct ProcessTiming {
+    /// Per-file timings in input order.
+    pub files: Vec<FileTiming>,
+    /// Flushing and (for remote outputs) uploading the output.
+    pub finalize: Duration,
+    /// Wall time for the whole run.
+    pub wall: Duration,
+    /// Queue statistics when output was written on a writer thread.
+    pub write_behind: Option<WriteBehindStats>,
+}
+
+impl ProcessTiming {
+    /// Totals over all files; `finalize` is counted as write time.
+    #[must_use]
+    pub fn total(&self) -> FileTiming {
+        let mut total = FileTiming {
+            path: "total".to_string(),
+            wall: self.wall,
+            ..FileTiming::default()
+        };
+        for file in &self.files {
+            total.bytes = total.bytes.saturating_add(file.bytes);
+            total.hits = total.hits.saturating_add(file.hits);
+            total.neutrons = total.neutrons.saturating_add(file.neutrons);
+            total.stages.accumulate(&file.stages);
+            total.chunks.merge(&file.chunks);
+            if let Some(classes) = &file.classes {
+                total
+                    .classes
+                    .get_or_insert_with(ClassCounts::default)
+                    .merge(classes);
+            }
+            total.warnings.merge(&file.warnings);
+        }
+        total.stages.write += self.finalize;
+        total
+    }
+
+    /// Print the per-file and total breakdown to stderr.
+    pub fn print(&self) {
+        eprintln!("Stage timing:");
+        if self.files.len() > 1 {
+            for file in &self.files {
+                eprintln!("  {} ({:.3}s)", file.path, file.wall.as_secs_f64());
+                file.print_stages("    ");
+            }
+            eprintln!("  total ({:.3}s)", self.wall.as_secs_f64());
+            self.total().print_stages("    ");
+        } else {
+            self.total().print_stages("  ");
+        }
+        if let Some(stats) = &self.write_behind {
+            eprintln!(
+                "  write-behind: {} batches, max queued {}/{}, producer blocked {} times ({:.3}s), writer busy {:.3}s",
+                stats.jobs,
+                stats.max_queued,
+                stats.queue_depth,
+                stats.blocked_submits,
+                stats.blocked.as_secs_f64(),
+                stats.write_time.as_secs_f64()
+            );
+        }
+    }
+
+    /// JSON form of the report.
+    #[must_use]
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "files": self.files.iter().map(FileTiming::to_json).collect::<Vec<_>>(),
+            "finalize_s": self.finalize.as_secs_f64(),
+            "total": self.total().to_json(),
+            "write_behind": self.write_behind.map(|stats| serde_json::json!({
+                "queue_depth": stats.queue_depth,
+                "batches": stats.jobs,
+                "max_queued": stats.max_queued,
+                "blocked_submits": stats.blocked_submits,
+                "blocked_s": stats.blocked.as_secs_f64(),
+                "writer_busy_s": stats.write_time.as_secs_f64(),
+            })),
+        })
+    }
+}
+
+fn stage_json(time: Duration, rate_key: &str, rate: f64) -> serde_json::Value {
+    let mut value = serde_json::json!({ "seconds": time.as_secs_f64() });
+    value[rate_key] = serde_json::json!(rate);
+    value
+}
+
+fn rate(count: usize, time: Duration) -> f64 {
+    let secs = time.as_secs_f64();
+    if secs > 0.0 {
+        usize_to_f64(count) / secs
+    } else {
+        0.0
+    }
+}
+
+fn mb_per_sec(bytes: usize, time: Duration) -> String {
+    format!("{:.1} MB/s", rate(bytes, time) / 1e6)
+}
+
+fn hits_per_sec(hits: usize, time: Duration) -> String {
+    format!("{:.2} M hits/s", rate(hits, time) / 1e6)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rustpix_core::warnings::WarningKind;
+
+    #[test]
+    fn test_total_sums_files_and_finalize() {
+        let file = |hits, millis| FileTiming {
+            path: "run.tpx3".to_string(),
+            bytes: 8 * hits,
+            hits,
+            neutrons: hits / 4,
+            wall: Duration::from_millis(millis),
+            stages: StageTimes {
+                cluster: Duration::from_millis(millis / 2),
+                write: Duration::from_millis(1),
+                ..StageTimes::default()
+            },
+            chunks: ChunkStats {
+                chunks: 1,
+                total_hits: hits,
+                min_hits: hits,
+                max_hits: hits,
+            },
+            flags: Vec::new(),
+            classes: Some(ClassCounts {
+                neutron: hits / 4,
+                gamma: hits / 8,
+                noise: 0,
+            }),
+            warnings: {
+                let mut warnings = Warnings::default();
+                warnings.record(WarningKind::SaturatedPixel, hits as u64 / 100, || {
+                    format!(
+                        "{} hits saturated the ToT register in one pulse",
+                        hits / 100
+                    )
+                });
+                warnings
+            },
+        };
+        let timing = ProcessTiming {
+            files: vec![file(1000, 10), file(3000, 30)],
+            finalize: Duration::from_millis(5),
+            wall: Duration::from_millis(50),
+            write_behind: None,
+        };
+        let total = timing.total();
+        assert_eq!(total.hits, 4000);
+        assert_eq!(total.stages.cluster, Duration::from_millis(20));
+        assert_eq!(total.stages.write, Duration::from_millis(7));
+        assert_eq!((total.chunks.min_hits, total.chunks.max_hits), (1000, 3000));
+
+        let json = timing.to_json();
+        assert_eq!(json["files"].as_array().unwrap().len(), 2);
+        assert_eq!(json["total"]["stages"]["cluster"]["hits_per_s"], 200_000.0);
+        assert_eq!(json["files"][0]["flags"], serde_json::json!([]));
+        assert_eq!(total.classes.unwrap().gamma, 500);
+        assert_eq!(json["total"]["classes"]["neutron"], 1000);
+        assert_eq!(json["total"]["warnings"][0]["kind"], "saturated_pixel");
+        assert_eq!(json["total"]["warnings"][0]["count"], 40);
+    }
+}
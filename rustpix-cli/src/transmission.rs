@@ -0,0 +1,529 @@
+//! Sample/open-beam run pairing and transmission stack generation.
+//!
+//! Runs are paired either from an explicit CSV manifest or by file-name
+//! convention, then the neutrons of each run are histogrammed (TOF × Y × X)
+//! and the pair is divided bin-by-bin, each run normalized by its number of
+//! TDC pulses, to produce a transmission stack. With alignment, the sample
+//! is first moved onto the open beam by the whole-pixel shift between their
+//! TOF-summed images, found by phase correlation.
+
+use crate::{CliError, Result};
+use rustpix_algorithms::{
+    register_translation, shift_image, AlgorithmParams, ClusteringAlgorithm, ImageShift,
+};
+use rustpix_core::clustering::ClusteringConfig;
+use rustpix_core::extraction::ExtractionConfig;
+use rustpix_io::{out_of_core_neutron_stream, OutOfCoreConfig, Tpx3FileReader};
+use rustpix_tpx::DetectorConfig;
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+/// File-name tokens that mark a run as an open-beam measurement.
+const OPEN_BEAM_TOKENS: [&str; 4] = ["open_beam", "openbeam", "ob", "flat"];
+
+/// A sample run matched with its open-beam reference.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RunPair {
+    /// Output name for the transmission stack.
+    pub name: String,
+    /// Sample run path.
+    pub sample: PathBuf,
+    /// Open-beam run path.
+    pub open_beam: PathBuf,
+}
+
+/// Parse a CSV manifest of `sample,open_beam[,name]` rows.
+///
+/// Blank lines and lines starting with `#` are ignored, as is a leading
+/// header row whose first column is `sample`. Relative paths are resolved
+/// against the manifest's directory.
+///
+/// # Errors
+/// Returns an error if the manifest cannot be read, a row is malformed, or
+/// two rows have the same output name.
+pub fn parse_manifest(path: &Path) -> Result<Vec<RunPair>> {
+    let base = path.parent().unwrap_or_else(|| Path::new("."));
+    let reader = BufReader::new(File::open(path)?);
+    let mut pairs = Vec::new();
+    let mut names = HashSet::new();
+
+    for (line_no, line) in reader.lines().enumerate() {
+        let line = line?;
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+        let fields: Vec<&str> = trimmed.split(',').map(str::trim).collect();
+        if pairs.is_empty() && fields[0].eq_ignore_ascii_case("sample") {
+            continue;
+        }
+        if fields.len() < 2 || fields[0].is_empty() || fields[1].is_empty() {
+            return Err(CliError::InvalidInput(format!(
+                "{}:{}: expected `sample,open_beam[,name]`",
+                path.display(),
+                line_no + 1
+            )));
+        }
+
+        let sample = resolve_path(base, fields[0]);
+        let open_beam = resolve_path(base, fields[1]);
+        let name = fields
+            .get(2)
+            .filter(|name| !name.is_empty())
+            .map_or_else(|| file_stem(&sample), |name| (*name).to_string());
+        if !names.insert(name.clone()) {
+            return Err(CliError::InvalidInput(format!(
+                "{}:{}: output name `{name}` is used by an earlier row",
+                path.display(),
+                line_no + 1
+            )));
+        }
+        pairs.push(RunPair {
+            name,
+            sample,
+            open_beam,
+        });
+    }
+
+    Ok(pairs)
+}
+
+/// Pair runs by file-name convention.
+///
+/// Files whose stem contains an open-beam token (`ob`, `open_beam`,
+/// `openbeam`, `flat`) are treated as open beams. Each sample is matched
+/// with the open beam whose stem is identical once the token is removed;
+/// when exactly one open beam is present it is used for every sample.
+///
+/// # Errors
+/// Returns an error if no open beam is found, a sample has no match, or two
+/// samples have the same file stem.
+pub fn pair_by_convention(inputs: &[PathBuf]) -> Result<Vec<RunPair>> {
+    let mut samples = Vec::new();
+    let mut open_beams = Vec::new();
+    for path in inputs {
+        let stem = file_stem(path);
+        match strip_open_beam_token(&stem) {
+            Some(key) => open_beams.push((key, path.clone())),
+            None => samples.push((normalize_key(&stem), path.clone())),
+        }
+    }
+
+    if open_beams.is_empty() {
+        return Err(CliError::InvalidInput(
+            "no open-beam runs found (expected `ob`, `open_beam` or `flat` in file name)"
+                .to_string(),
+        ));
+    }
+
+    let mut pairs = Vec::with_capacity(samples.len());
+    let mut names = HashSet::new();
+    for (key, sample) in samples {
+        let open_beam = if open_beams.len() == 1 {
+            open_beams[0].1.clone()
+        } else {
+            open_beams
+                .iter()
+                .find(|(ob_key, _)| *ob_key == key)
+                .map(|(_, path)| path.clone())
+                .ok_or_else(|| {
+                    CliError::InvalidInput(format!(
+                        "no open-beam run matches sample {}",
+                        sample.display()
+                    ))
+                })?
+        };
+        let name = file_stem(&sample);
+        if !names.insert(name.clone()) {
+            return Err(CliError::InvalidInput(format!(
+                "samples share the output name `{name}`; pair them with a manifest instead"
+            )));
+        }
+        pairs.push(RunPair {
+            name,
+            sample,
+            open_beam,
+        });
+    }
+
+    Ok(pairs)
+}
+
+/// Dense neutron-count histogram (TOF × Y × X) for a single run.
+pub struct RunHistogram {
+    /// Number of TOF bins.
+    pub tof_bins: usize,
+    /// Detector width in pixels.
+    pub width: usize,
+    /// Detector height in pixels.
+    pub height: usize,
+    /// Counts laid out as `[tof][y][x]`.
+    pub counts: Vec<u32>,
+    /// Number of TDC pulses in the run.
+    pub pulses: usize,
+}
+
+impl RunHistogram {
+    /// Cluster a TPX3 file and histogram its neutrons at whole-pixel
+    /// positions.
+    ///
+    /// # Errors
+    /// Returns an error if the file cannot be opened, parsed or clustered.
+    pub fn from_file(
+        path: &Path,
+        config: &DetectorConfig,
+        tof_bins: usize,
+        algorithm: ClusteringAlgorithm,
+        clustering: &ClusteringConfig,
+    ) -> Result<Self> {
+        let (width, height) = config.detector_dimensions();
+        let tof_bins = tof_bins.max(1);
+        let tof_max = u64::from(config.tdc_correction_25ns().max(1));
+        let tof_bins_u64 = u64::try_from(tof_bins).unwrap_or(u64::MAX);
+        let mut counts = vec![0u32; tof_bins * width * height];
+
+        let reader = Tpx3FileReader::open(path)?.with_config(config.clone());
+        let pulses = reader.pulse_index().len();
+        let stream = out_of_core_neutron_stream(
+            &reader,
+            algorithm,
+            clustering,
+            &ExtractionConfig::default().with_super_resolution(1.0),
+            &AlgorithmParams::default(),
+            &OutOfCoreConfig::default(),
+        )?;
+        for batch in stream {
+            let neutrons = batch?.neutrons;
+            for i in 0..neutrons.len() {
+                let (x, y) = (neutrons.x[i].round(), neutrons.y[i].round());
+                if x < 0.0 || y < 0.0 {
+                    continue;
+                }
+                #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+                let (x, y) = (x as usize, y as usize);
+                if x >= width || y >= height {
+                    continue;
+                }
+                let scaled = u64::from(neutrons.tof[i]) * tof_bins_u64 / tof_max;
+                let bin = usize::try_from(scaled).unwrap_or(usize::MAX);
+                if bin >= tof_bins {
+                    continue;
+                }
+                let idx = (bin * height + y) * width + x;
+                counts[idx] = counts[idx].saturating_add(1);
+            }
+        }
+
+        Ok(Self {
+            tof_bins,
+            width,
+            height,
+            counts,
+            pulses,
+        })
+    }
+
+    /// Counts summed over TOF, as a `[y][x]` image.
+    #[must_use]
+    pub fn projection(&self) -> Vec<u64> {
+        let mut image = vec![0u64; self.width * self.height];
+        for slice in self.counts.chunks_exact(self.width * self.height) {
+            for (pixel, &count) in image.iter_mut().zip(slice) {
+                *pixel += u64::from(count);
+            }
+        }
+        image
+    }
+
+    /// Move every TOF slice by `(dx, dy)` pixels; pixels moved in from
+    /// outside the detector are 0.
+    pub fn shift(&mut self, dx: isize, dy: isize) {
+        let pixels = self.width * self.height;
+        for slice in self.counts.chunks_exact_mut(pixels) {
+            let shifted = shift_image(slice, self.width, self.height, dx, dy);
+            slice.copy_from_slice(&shifted);
+        }
+    }
+
+    /// Bin-wise ratio of the per-pulse counts, `(sample / sample pulses) /
+    /// (open_beam / open-beam pulses)`; bins with no open-beam counts are 0.
+    ///
+    /// # Errors
+    /// Returns an error if either run has no TDC pulses to normalize by.
+    pub fn transmission(&self, open_beam: &RunHistogram) -> Result<Vec<f32>> {
+        if self.pulses == 0 || open_beam.pulses == 0 {
+            return Err(CliError::InvalidInput(
+                "cannot normalize a run without TDC pulses".to_string(),
+            ));
+        }
+        #[allow(clippy::cast_precision_loss)]
+        let scale = open_beam.pulses as f64 / self.pulses as f64;
+        Ok(self
+            .counts
+            .iter()
+            .zip(&open_beam.counts)
+            .map(|(&sample, &ob)| {
+                if ob == 0 {
+                    0.0
+                } else {
+                    #[allow(clippy::cast_possible_truncation)]
+                    {
+                        (f64::from(sample) / f64::from(ob) * scale) as f32
+                    }
+                }
+            })
+            .collect())
+    }
+}
+
+/// Process every pair and write `<name>_transmission.bin` stacks plus a JSON sidecar.
+///
+/// Stacks are little-endian `f32` in `[tof][y][x]` order. With `align`, each
+/// sample is moved onto its open beam by whole pixels first and the shift is
+/// recorded in the sidecar, along with both runs' pulse counts.
+///
+/// Returns the paths of all files written.
+///
+/// # Errors
+/// Returns an error if any run fails to load, has no TDC pulses, or an
+/// output cannot be written.
+#[allow(clippy::too_many_arguments)]
+pub fn run_pairs(
+    pairs: &[RunPair],
+    output_dir: &Path,
+    config: &DetectorConfig,
+    tof_bins: usize,
+    algorithm: ClusteringAlgorithm,
+    clustering: &ClusteringConfig,
+    align: bool,
+    verbose: bool,
+) -> Result<Vec<PathBuf>> {
+    std::fs::create_dir_all(output_dir)?;
+    let mut written = Vec::with_capacity(pairs.len() * 2);
+
+    for pair in pairs {
+        if verbose {
+            eprintln!(
+                "Pairing {} with open beam {}",
+                pair.sample.display(),
+                pair.open_beam.display()
+            );
+        }
+        let mut sample =
+            RunHistogram::from_file(&pair.sample, config, tof_bins, algorithm, clustering)?;
+        let open_beam =
+            RunHistogram::from_file(&pair.open_beam, config, tof_bins, algorithm, clustering)?;
+        let alignment = align.then(|| align_to(&mut sample, &open_beam));
+        if let Some(shift) = &alignment {
+            println!(
+                "{}: sample shifted by ({:+.2}, {:+.2}) px, correlation peak {:.3}",
+                pair.name, -shift.dx, -shift.dy, shift.peak
+            );
+        }
+        let stack = sample.transmission(&open_beam).map_err(|_| {
+            CliError::InvalidInput(format!(
+                "{}: sample or open beam has no TDC pulses to normalize by",
+                pair.name
+            ))
+        })?;
+
+        let stack_path = output_dir.join(format!("{}_transmission.bin", pair.name));
+        let mut writer = BufWriter::new(File::create(&stack_path)?);
+        for value in &stack {
+            writer.write_all(&value.to_le_bytes())?;
+        }
+        writer.flush()?;
+
+        let mut meta = serde_json::json!({
+            "sample": pair.sample.display().to_string(),
+            "open_beam": pair.open_beam.display().to_string(),
+            "dtype": "float32",
+            "byte_order": "little",
+            "shape": [sample.tof_bins, sample.height, sample.width],
+            "tof_max_25ns": config.tdc_correction_25ns(),
+            "sample_pulses": sample.pulses,
+            "open_beam_pulses": open_beam.pulses,
+        });
+        if let Some(shift) = alignment {
+            meta["alignment"] = serde_json::to_value(shift)?;
+        }
+        let meta_path = output_dir.join(format!("{}_transmission.json", pair.name));
+        std::fs::write(&meta_path, serde_json::to_string_pretty(&meta)?)?;
+
+        println!("Wrote {}", stack_path.display());
+        written.push(stack_path);
+        written.push(meta_path);
+    }
+
+    Ok(written)
+}
+
+/// Register `sample` against `open_beam` and move it back by the whole
+/// pixels of the shift found.
+fn align_to(sample: &mut RunHistogram, open_beam: &RunHistogram) -> ImageShift {
+    let shift = register_translation(
+        &open_beam.projection(),
+        &sample.projection(),
+        sample.width,
+        sample.height,
+    );
+    let (dx, dy) = shift.whole_pixels();
+    sample.shift(-dx, -dy);
+    shift
+}
+
+fn resolve_path(base: &Path, value: &str) -> PathBuf {
+    let path = PathBuf::from(value);
+    if path.is_absolute() {
+        path
+    } else {
+        base.join(path)
+    }
+}
+
+fn file_stem(path: &Path) -> String {
+    path.file_stem()
+        .and_then(|stem| stem.to_str())
+        .unwrap_or("run")
+        .to_string()
+}
+
+fn normalize_key(stem: &str) -> String {
+    stem.to_lowercase()
+        .split(['_', '-', '.', ' '])
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("_")
+}
+
+fn strip_open_beam_token(stem: &str) -> Option<String> {
+    let key = normalize_key(stem);
+    for token in OPEN_BEAM_TOKENS {
+        let parts: Vec<&str> = key.split('_').collect();
+        if token.contains('_') {
+            if let Some(pos) = key.find(token) {
+                let stripped = format!("{}{}", &key[..pos], &key[pos + token.len()..]);
+                return Some(normalize_key(&stripped));
+            }
+        } else if parts.contains(&token) {
+            let kept: Vec<&str> = parts.into_iter().filter(|part| *part != token).collect();
+            return Some(kept.join("_"));
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pair_by_convention_matches_stems() {
+        let inputs = vec![
+            PathBuf::from("run_sampleA.tpx3"),
+            PathBuf::from("run_sampleB.tpx3"),
+            PathBuf::from("run_sampleA_OB.tpx3"),
+            PathBuf::from("run_sampleB_open_beam.tpx3"),
+        ];
+        let pairs = pair_by_convention(&inputs).unwrap();
+        assert_eq!(pairs.len(), 2);
+        assert_eq!(pairs[0].open_beam, PathBuf::from("run_sampleA_OB.tpx3"));
+        assert_eq!(
+            pairs[1].open_beam,
+            PathBuf::from("run_sampleB_open_beam.tpx3")
+        );
+    }
+
+    #[test]
+    fn test_single_open_beam_is_shared() {
+        let inputs = vec![
+            PathBuf::from("sample_1.tpx3"),
+            PathBuf::from("sample_2.tpx3"),
+            PathBuf::from("ob.tpx3"),
+        ];
+        let pairs = pair_by_convention(&inputs).unwrap();
+        assert_eq!(pairs.len(), 2);
+        assert!(pairs.iter().all(|p| p.open_beam == Path::new("ob.tpx3")));
+    }
+
+    #[test]
+    fn test_align_moves_sample_onto_open_beam() {
+        let (width, height) = (32, 24);
+        let histogram = |x0: usize, y0: usize| {
+            let mut counts = vec![0u32; 2 * width * height];
+            for bin in 0..2 {
+                for (dy, dx) in [(0, 0), (0, 1), (1, 0), (3, 5), (4, 5)] {
+                    counts[(bin * height + y0 + dy) * width + x0 + dx] = 10;
+                }
+            }
+            RunHistogram {
+                tof_bins: 2,
+                width,
+                height,
+                counts,
+                pulses: 1,
+            }
+        };
+        let open_beam = histogram(10, 8);
+        let mut sample = histogram(13, 6);
+        let shift = align_to(&mut sample, &open_beam);
+        assert_eq!(shift.whole_pixels(), (3, -2));
+        assert_eq!(sample.counts, open_beam.counts);
+    }
+
+    #[test]
+    fn test_transmission_normalizes_by_pulses() {
+        let histogram = |counts: Vec<u32>, pulses| RunHistogram {
+            tof_bins: 1,
+            width: 3,
+            height: 1,
+            counts,
+            pulses,
+        };
+        // The open beam ran for four times as many pulses.
+        let sample = histogram(vec![2, 1, 5], 1);
+        let open_beam = histogram(vec![4, 8, 0], 4);
+        assert_eq!(
+            sample.transmission(&open_beam).unwrap(),
+            vec![2.0, 0.5, 0.0]
+        );
+        assert!(sample.transmission(&histogram(vec![1; 3], 0)).is_err());
+    }
+
+    #[test]
+    fn test_duplicate_names_are_rejected() {
+        let dir = tempfile::tempdir().unwrap();
+        let manifest = dir.path().join("runs.csv");
+        std::fs::write(&manifest, "a.tpx3,ob.tpx3,run\nb.tpx3,ob.tpx3,run\n").unwrap();
+        let err = parse_manifest(&manifest).unwrap_err().to_string();
+        assert!(err.contains("runs.csv:2"), "{err}");
+
+        let inputs = vec![
+            PathBuf::from("day1/sample.tpx3"),
+            PathBuf::from("day2/sample.tpx3"),
+            PathBuf::from("ob.tpx3"),
+        ];
+        assert!(pair_by_convention(&inputs).is_err());
+    }
+
+    #[test]
+    fn test_parse_manifest() {
+        let dir = tempfile::tempdir().unwrap();
+        let dir = dir.path();
+        let manifest = dir.join("runs.csv");
+        std::fs::write(
+            &manifest,
+            "sample,open_beam,name\n# comment\na.tpx3,ob.tpx3,first\nb.tpx3, ob.tpx3\n",
+        )
+        .unwrap();
+
+        let pairs = parse_manifest(&manifest).unwrap();
+        assert_eq!(pairs.len(), 2);
+        assert_eq!(pairs[0].name, "first");
+        assert_eq!(pairs[0].sample, dir.join("a.tpx3"));
+        assert_eq!(pairs[1].name, "b");
+        assert_eq!(pairs[1].open_beam, dir.join("ob.tpx3"));
+    }
+}
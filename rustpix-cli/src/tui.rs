@@ -0,0 +1,689 @@
+//! Interactive terminal UI for processing over SSH.
+//!
+//! Processing runs on a worker thread that streams pulse batches through the
+//! out-of-core pipeline and reports progress over a channel; the main thread
+//! redraws a progress gauge, throughput figures and a coarse density map of
+//! the extracted neutrons until processing finishes or the user quits.
+//! Region rate alarms (see [`crate::alarms`]) are evaluated on the worker,
+//! appended to the alarm log as they happen, and shown in their own panel.
+
+use crate::alarms::{AlarmEvent, AlarmMonitor, AlarmStatus, RateAlarm};
+use crate::atomic::PendingOutputs;
+use crate::lock::OutputLock;
+use crate::{usize_to_f64, write_neutrons, CliError, Result};
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Color, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Gauge, Paragraph};
+use ratatui::{DefaultTerminal, Frame};
+use rustpix_algorithms::{AlgorithmParams, ClusteringAlgorithm};
+use rustpix_core::clustering::ClusteringConfig;
+use rustpix_core::extraction::ExtractionConfig;
+use rustpix_core::neutron::NeutronBatch;
+use rustpix_io::{out_of_core_neutron_stream, OutOfCoreConfig, Tpx3FileReader};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender, TryRecvError};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// Density map resolution (columns × rows).
+const MAP_COLS: usize = 64;
+const MAP_ROWS: usize = 32;
+
+/// Glyphs from empty to densest.
+const DENSITY_RAMP: &[u8] = b" .:-=+*#%@";
+
+/// Redraw interval while waiting for progress events.
+const TICK: Duration = Duration::from_millis(100);
+
+/// Clustering and pipeline settings for a TUI run.
+pub struct TuiSettings {
+    pub algorithm: ClusteringAlgorithm,
+    pub clustering: ClusteringConfig,
+    pub extraction: ExtractionConfig,
+    pub params: AlgorithmParams,
+    pub memory: OutOfCoreConfig,
+    pub alarms: Vec<RateAlarm>,
+    pub alarm_window: Duration,
+    pub alarm_log: Option<PathBuf>,
+}
+
+/// Messages sent from the processing thread to the UI.
+enum Progress {
+    FileStarted {
+        index: usize,
+        path: PathBuf,
+        packets: usize,
+    },
+    Batch {
+        hits: usize,
+        neutrons: usize,
+        density: Vec<u32>,
+    },
+    Alarms {
+        status: Vec<AlarmStatus>,
+        events: Vec<AlarmEvent>,
+    },
+    FileFinished,
+    Failed(String),
+    Done,
+}
+
+/// Run state shown by the UI.
+struct TuiState {
+    files: usize,
+    current_file: Option<(usize, PathBuf)>,
+    file_packets: usize,
+    file_hits: usize,
+    files_done: usize,
+    total_hits: usize,
+    total_neutrons: usize,
+    density: Vec<u64>,
+    alarms: Vec<AlarmStatus>,
+    alarm_events: Vec<AlarmEvent>,
+    started: Instant,
+    last_sample: (Instant, usize, usize),
+    hit_rate: f64,
+    neutron_rate: f64,
+    finished: Option<Instant>,
+    error: Option<String>,
+    cancelled: bool,
+}
+
+impl TuiState {
+    fn new(files: usize, alarms: &[RateAlarm]) -> Self {
+        let now = Instant::now();
+        Self {
+            files,
+            current_file: None,
+            file_packets: 0,
+            file_hits: 0,
+            files_done: 0,
+            total_hits: 0,
+            total_neutrons: 0,
+            density: vec![0; MAP_COLS * MAP_ROWS],
+            alarms: alarms
+                .iter()
+                .map(|alarm| AlarmStatus {
+                    alarm: alarm.clone(),
+                    rate: None,
+                    firing: false,
+                })
+                .collect(),
+            alarm_events: Vec::new(),
+            started: now,
+            last_sample: (now, 0, 0),
+            hit_rate: 0.0,
+            neutron_rate: 0.0,
+            finished: None,
+            error: None,
+            cancelled: false,
+        }
+    }
+
+    fn apply(&mut self, progress: Progress) {
+        match progress {
+            Progress::FileStarted {
+                index,
+                path,
+                packets,
+            } => {
+                self.current_file = Some((index, path));
+                self.file_packets = packets;
+                self.file_hits = 0;
+            }
+            Progress::Batch {
+                hits,
+                neutrons,
+                density,
+            } => {
+                self.file_hits = self.file_hits.saturating_add(hits);
+                self.total_hits = self.total_hits.saturating_add(hits);
+                self.total_neutrons = self.total_neutrons.saturating_add(neutrons);
+                for (cell, count) in self.density.iter_mut().zip(density) {
+                    *cell += u64::from(count);
+                }
+            }
+            Progress::Alarms { status, events } => {
+                self.alarms = status;
+                self.alarm_events.extend(events);
+            }
+            Progress::FileFinished => self.files_done += 1,
+            Progress::Failed(message) => {
+                self.error = Some(message);
+                self.finished = Some(Instant::now());
+            }
+            Progress::Done => self.finished = Some(Instant::now()),
+        }
+    }
+
+    /// Refresh the instantaneous rates about once per second.
+    fn update_rates(&mut self, now: Instant) {
+        let (at, hits, neutrons) = self.last_sample;
+        let dt = now.duration_since(at).as_secs_f64();
+        if dt < 1.0 {
+            return;
+        }
+        self.hit_rate = usize_to_f64(self.total_hits - hits) / dt;
+        self.neutron_rate = usize_to_f64(self.total_neutrons - neutrons) / dt;
+        self.last_sample = (now, self.total_hits, self.total_neutrons);
+    }
+
+    /// Overall progress in `[0, 1]`.
+    ///
+    /// Within a file this is approximated by hits over packets, since the
+    /// pipeline reports hits processed rather than bytes read.
+    fn progress(&self) -> f64 {
+        if self.files == 0 {
+            return 1.0;
+        }
+        let in_file =
+            if self.finished.is_some() || self.files_done == self.files || self.file_packets == 0 {
+                0.0
+            } else {
+                (usize_to_f64(self.file_hits) / usize_to_f64(self.file_packets)).min(1.0)
+            };
+        ((usize_to_f64(self.files_done) + in_file) / usize_to_f64(self.files)).min(1.0)
+    }
+
+    fn alarm_firing(&self) -> bool {
+        self.alarms.iter().any(|status| status.firing)
+    }
+
+    fn elapsed(&self) -> Duration {
+        self.finished
+            .unwrap_or_else(Instant::now)
+            .duration_since(self.started)
+    }
+}
+
+/// Process `input` into `output` while showing the interactive UI.
+///
+/// The output is written under its hidden partial name (see
+/// [`crate::atomic`]) and renamed into place once every file is processed.
+/// Press `q` or `Esc` to cancel; the output written so far is then left under
+/// the partial name.
+///
+/// # Errors
+/// Returns an error if the terminal cannot be driven, processing fails, or
+/// the user cancels.
+pub fn run(input: &[PathBuf], output: &Path, settings: TuiSettings) -> Result<()> {
+    let _lock = OutputLock::acquire(output)?;
+    let mut pending = PendingOutputs::default();
+    let staged = pending.stage(output);
+    let cancel = Arc::new(AtomicBool::new(false));
+    let (tx, rx) = mpsc::channel();
+    let mut state = TuiState::new(input.len(), &settings.alarms);
+    let worker = {
+        let input = input.to_vec();
+        // The staged path ends in `.partial`; the format follows the final one.
+        let output_format = output
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .map_or_else(|| "bin".to_string(), str::to_lowercase);
+        let output = staged.clone();
+        let cancel = Arc::clone(&cancel);
+        thread::spawn(move || {
+            let result = process_files(&input, &output, &output_format, &settings, &cancel, &tx);
+            let _ = tx.send(match &result {
+                Ok(()) => Progress::Done,
+                Err(err) => Progress::Failed(err.to_string()),
+            });
+            result
+        })
+    };
+
+    let mut terminal = ratatui::init();
+    let result = event_loop(&mut terminal, &rx, &cancel, &mut state);
+    ratatui::restore();
+
+    cancel.store(true, Ordering::Relaxed);
+    let processed = worker
+        .join()
+        .map_err(|_| CliError::InvalidInput("processing thread panicked".to_string()))?;
+    result?;
+    processed?;
+
+    let elapsed = state.elapsed().as_secs_f64();
+    if state.cancelled {
+        println!("Cancelled after {elapsed:.2}s");
+    } else {
+        println!("Processed {} files in {elapsed:.2}s", state.files_done);
+    }
+    println!("Total hits: {}", state.total_hits);
+    println!("Total neutrons: {}", state.total_neutrons);
+    if !state.alarm_events.is_empty() {
+        println!("Alarm events:");
+        for event in &state.alarm_events {
+            println!("  {event}");
+        }
+    }
+    if state.cancelled {
+        pending.abandon();
+        return Err(CliError::Cancelled(format!(
+            "partial output left at {}",
+            staged.display()
+        )));
+    }
+    pending.commit()
+}
+
+fn event_loop(
+    terminal: &mut DefaultTerminal,
+    rx: &Receiver<Progress>,
+    cancel: &AtomicBool,
+    state: &mut TuiState,
+) -> Result<()> {
+    loop {
+        loop {
+            match rx.try_recv() {
+                Ok(progress) => state.apply(progress),
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    if state.finished.is_none() {
+                        state.finished = Some(Instant::now());
+                    }
+                    break;
+                }
+            }
+        }
+        state.update_rates(Instant::now());
+        terminal.draw(|frame| draw(frame, state))?;
+
+        if event::poll(TICK)? {
+            if let Event::Key(key) = event::read()? {
+                if key.kind != KeyEventKind::Press {
+                    continue;
+                }
+                match key.code {
+                    KeyCode::Char('q') | KeyCode::Esc => {
+                        if state.finished.is_none() {
+                            cancel.store(true, Ordering::Relaxed);
+                            state.cancelled = true;
+                            state.finished = Some(Instant::now());
+                        }
+                        return Ok(());
+                    }
+                    KeyCode::Enter if state.finished.is_some() => return Ok(()),
+                    _ => {}
+                }
+            }
+        }
+    }
+}
+
+fn process_files(
+    input: &[PathBuf],
+    output: &Path,
+    output_format: &str,
+    settings: &TuiSettings,
+    cancel: &AtomicBool,
+    tx: &Sender<Progress>,
+) -> Result<()> {
+    let mut writer = rustpix_io::DataFileWriter::create(output)?;
+    let mut wrote_header = false;
+    let mut warned_unknown = false;
+    let scale = settings
+        .extraction
+        .super_resolution_factor
+        .max(f64::EPSILON);
+    let mut alarms = AlarmMonitor::new(settings.alarms.clone(), settings.alarm_window);
+    let mut alarm_log = settings
+        .alarm_log
+        .as_ref()
+        .map(|path| File::options().create(true).append(true).open(path))
+        .transpose()?
+        .map(BufWriter::new);
+
+    for (index, path) in input.iter().enumerate() {
+        let reader = Tpx3FileReader::open(path)?;
+        let dimensions = reader.config().detector_dimensions();
+        let _ = tx.send(Progress::FileStarted {
+            index,
+            path: path.clone(),
+            packets: reader.packet_count(),
+        });
+        alarms.start_file();
+        let stream = out_of_core_neutron_stream(
+            &reader,
+            settings.algorithm,
+            &settings.clustering,
+            &settings.extraction,
+            &settings.params,
+            &settings.memory,
+        )?;
+        for batch in stream {
+            if cancel.load(Ordering::Relaxed) {
+                return Ok(());
+            }
+            let batch = batch?;
+            write_neutrons(
+                &mut writer,
+                output_format,
+                &batch.neutrons,
+                settings.extraction.super_resolution_factor,
+                &mut wrote_header,
+                &mut warned_unknown,
+                false,
+            )?;
+            let _ = tx.send(Progress::Batch {
+                hits: batch.hits_processed,
+                neutrons: batch.neutrons.len(),
+                density: bin_density(&batch.neutrons, scale, dimensions),
+            });
+            if !alarms.is_empty() {
+                let events = alarms.observe(batch.tdc_timestamp_25ns, &batch.neutrons, scale);
+                if let Some(log) = alarm_log.as_mut() {
+                    log_alarm_events(log, path, &events)?;
+                }
+                let _ = tx.send(Progress::Alarms {
+                    status: alarms.status().to_vec(),
+                    events,
+                });
+            }
+        }
+        let _ = tx.send(Progress::FileFinished);
+    }
+    Ok(())
+}
+
+/// Append alarm events to the log, flushing so each one is on disk as soon
+/// as it happens.
+fn log_alarm_events(log: &mut impl Write, path: &Path, events: &[AlarmEvent]) -> Result<()> {
+    if events.is_empty() {
+        return Ok(());
+    }
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs_f64();
+    for event in events {
+        writeln!(log, "{now:.3}\t{}\t{event}", path.display())?;
+    }
+    log.flush()?;
+    Ok(())
+}
+
+/// Bin neutron positions on a `width × height` pixel detector into the
+/// coarse `MAP_COLS × MAP_ROWS` grid.
+fn bin_density(neutrons: &NeutronBatch, scale: f64, (width, height): (usize, usize)) -> Vec<u32> {
+    let mut density = vec![0u32; MAP_COLS * MAP_ROWS];
+    let cols = usize_to_f64(MAP_COLS);
+    let rows = usize_to_f64(MAP_ROWS);
+    let (width, height) = (usize_to_f64(width.max(1)), usize_to_f64(height.max(1)));
+    for (&x, &y) in neutrons.x.iter().zip(&neutrons.y) {
+        let fx = x / scale / width;
+        let fy = y / scale / height;
+        if !(0.0..1.0).contains(&fx) || !(0.0..1.0).contains(&fy) {
+            continue;
+        }
+        #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+        let (col, row) = ((fx * cols) as usize, (fy * rows) as usize);
+        density[row * MAP_COLS + col] += 1;
+    }
+    density
+}
+
+/// Render the density grid as text scaled to `width × height` cells.
+///
+/// Counts are log-scaled against the densest cell so that sparse regions
+/// remain visible next to the beam spot.
+fn density_lines(density: &[u64], width: usize, height: usize) -> Vec<Line<'static>> {
+    let max = density.iter().copied().max().unwrap_or(0);
+    #[allow(clippy::cast_precision_loss)]
+    let log_max = (max as f64).ln_1p();
+    let levels = DENSITY_RAMP.len() - 1;
+    (0..height)
+        .map(|line| {
+            let row = line * MAP_ROWS / height.max(1);
+            let text: String = (0..width)
+                .map(|column| {
+                    let col = column * MAP_COLS / width.max(1);
+                    let count = density[row * MAP_COLS + col];
+                    if count == 0 || log_max <= 0.0 {
+                        return ' ';
+                    }
+                    #[allow(clippy::cast_precision_loss)]
+                    let fraction = (count as f64).ln_1p() / log_max;
+                    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+                    let level =
+                        ((fraction * usize_to_f64(levels)).round() as usize).clamp(1, levels);
+                    char::from(DENSITY_RAMP[level])
+                })
+                .collect();
+            Line::from(text)
+        })
+        .collect()
+}
+
+fn alarm_line(status: &AlarmStatus) -> Line<'static> {
+    let alarm = &status.alarm;
+    let bounds = match (alarm.min_rate, alarm.max_rate) {
+        (Some(min), Some(max)) => format!("{min}..{max}/s"),
+        (Some(min), None) => format!(">= {min}/s"),
+        (None, Some(max)) => format!("<= {max}/s"),
+        (None, None) => String::new(),
+    };
+    let rate = status
+        .rate
+        .map_or_else(|| "-".to_string(), |rate| format!("{rate:.0}/s"));
+    let (label, color) = if status.firing {
+        ("ALARM", Color::Red)
+    } else if status.rate.is_some() {
+        ("ok", Color::Green)
+    } else {
+        ("waiting", Color::DarkGray)
+    };
+    Line::from(vec![
+        Span::raw(format!("{:<12} {rate:>12}  {bounds:<20} ", alarm.name)),
+        Span::styled(label, Style::default().fg(color)),
+    ])
+}
+
+#[allow(clippy::too_many_lines)]
+fn draw(frame: &mut Frame, state: &TuiState) {
+    let alarm_rows = if state.alarms.is_empty() {
+        0
+    } else {
+        u16::try_from(state.alarms.len() + 2).unwrap_or(u16::MAX)
+    };
+    let [gauge_area, stats_area, alarm_area, map_area, help_area] = Layout::vertical([
+        Constraint::Length(3),
+        Constraint::Length(6),
+        Constraint::Length(alarm_rows),
+        Constraint::Min(4),
+        Constraint::Length(1),
+    ])
+    .areas(frame.area());
+
+    let file_label = match &state.current_file {
+        Some((index, path)) => format!(
+            "File {}/{}: {}",
+            index + 1,
+            state.files,
+            path.file_name().map_or_else(
+                || path.display().to_string(),
+                |n| n.to_string_lossy().into()
+            )
+        ),
+        None => "Opening input…".to_string(),
+    };
+    let gauge_color = if state.error.is_some() {
+        Color::Red
+    } else if state.finished.is_some() {
+        Color::Green
+    } else {
+        Color::Cyan
+    };
+    let progress = state.progress();
+    frame.render_widget(
+        Gauge::default()
+            .block(Block::bordered().title(file_label))
+            .gauge_style(Style::default().fg(gauge_color))
+            .ratio(progress)
+            .label(format!("{:.1}%", progress * 100.0)),
+        gauge_area,
+    );
+
+    let elapsed = state.elapsed().as_secs_f64();
+    let status = match (&state.error, state.finished, state.cancelled) {
+        (Some(message), _, _) => format!("Failed: {message}"),
+        (None, Some(_), true) => "Cancelled".to_string(),
+        (None, Some(_), false) => "Finished".to_string(),
+        (None, None, _) => "Processing".to_string(),
+    };
+    let throughput = vec![
+        Line::from(format!("Status:   {status}  ({elapsed:.1}s elapsed)")),
+        Line::from(format!(
+            "Hits:     {:>14}  {:>12.0}/s",
+            state.total_hits, state.hit_rate
+        )),
+        Line::from(format!(
+            "Neutrons: {:>14}  {:>12.0}/s",
+            state.total_neutrons, state.neutron_rate
+        )),
+        Line::from(format!(
+            "Average:  {:>14.0} hits/s",
+            usize_to_f64(state.total_hits) / elapsed.max(f64::EPSILON)
+        )),
+    ];
+    frame.render_widget(
+        Paragraph::new(throughput).block(Block::bordered().title("Throughput")),
+        stats_area,
+    );
+
+    if !state.alarms.is_empty() {
+        let (title, border) = if state.alarm_firing() {
+            ("Alarms: FIRING", Color::Red)
+        } else {
+            ("Alarms", Color::Reset)
+        };
+        frame.render_widget(
+            Paragraph::new(state.alarms.iter().map(alarm_line).collect::<Vec<_>>()).block(
+                Block::bordered()
+                    .title(title)
+                    .border_style(Style::default().fg(border)),
+            ),
+            alarm_area,
+        );
+    }
+
+    let map_block = Block::bordered().title("Neutron density (log)");
+    let inner = map_block.inner(map_area);
+    frame.render_widget(
+        Paragraph::new(density_lines(
+            &state.density,
+            usize::from(inner.width),
+            usize::from(inner.height),
+        ))
+        .block(map_block),
+        map_area,
+    );
+
+    let help = if state.finished.is_some() {
+        "Enter/q: exit"
+    } else {
+        "q/Esc: cancel"
+    };
+    frame.render_widget(
+        Paragraph::new(help).style(Style::default().fg(Color::DarkGray)),
+        help_area,
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(line: &Line) -> String {
+        line.spans
+            .iter()
+            .map(|span| span.content.as_ref())
+            .collect()
+    }
+
+    #[test]
+    fn test_bin_density_spans_the_detector() {
+        let scale = 8.0;
+        let neutrons = NeutronBatch {
+            // Corners of a 514-pixel detector, and a neutron off it.
+            x: vec![0.0, 513.0 * scale, 600.0 * scale],
+            y: vec![0.0, 513.0 * scale, 10.0 * scale],
+            ..NeutronBatch::default()
+        };
+        let density = bin_density(&neutrons, scale, (514, 514));
+        assert_eq!(density.iter().sum::<u32>(), 2);
+        assert_eq!(density[0], 1);
+        assert_eq!(density[MAP_COLS * MAP_ROWS - 1], 1);
+
+        // A wider detector maps the same pixel to an earlier column.
+        let density = bin_density(&neutrons, scale, (1028, 514));
+        assert_eq!(density[(MAP_ROWS - 1) * MAP_COLS + MAP_COLS / 2 - 1], 1);
+    }
+
+    #[test]
+    fn test_density_lines_log_scale() {
+        let mut density = vec![0u64; MAP_COLS * MAP_ROWS];
+        density[0] = 10_000;
+        density[1] = 1;
+        let lines = density_lines(&density, MAP_COLS, MAP_ROWS);
+        assert_eq!(lines.len(), MAP_ROWS);
+        let first = text(&lines[0]);
+        assert_eq!(first.chars().count(), MAP_COLS);
+        assert!(first.starts_with('@'));
+        // The sparsest non-empty cell stays visible next to the densest.
+        assert_ne!(first.chars().nth(1), Some(' '));
+        assert!(lines[1..].iter().all(|line| text(line).trim().is_empty()));
+
+        // Scaled down, cells are sampled rather than dropped off the edge.
+        let lines = density_lines(&density, 8, 4);
+        assert_eq!(lines.len(), 4);
+        assert!(text(&lines[0]).starts_with('@'));
+        assert!(density_lines(&[0; MAP_COLS * MAP_ROWS], 8, 4)
+            .iter()
+            .all(|line| text(line).trim().is_empty()));
+    }
+
+    #[test]
+    #[allow(clippy::float_cmp)]
+    fn test_progress_counts_files_and_hits() {
+        assert_eq!(TuiState::new(0, &[]).progress(), 1.0);
+
+        let mut state = TuiState::new(2, &[]);
+        assert_eq!(state.progress(), 0.0);
+        state.apply(Progress::FileStarted {
+            index: 0,
+            path: PathBuf::from("a.tpx3"),
+            packets: 100,
+        });
+        state.apply(Progress::Batch {
+            hits: 50,
+            neutrons: 5,
+            density: Vec::new(),
+        });
+        assert_eq!(state.progress(), 0.25);
+        // Hits never count for more than their file.
+        state.apply(Progress::Batch {
+            hits: 500,
+            neutrons: 5,
+            density: Vec::new(),
+        });
+        assert_eq!(state.progress(), 0.5);
+        state.apply(Progress::FileFinished);
+        state.apply(Progress::FileStarted {
+            index: 1,
+            path: PathBuf::from("b.tpx3"),
+            packets: 0,
+        });
+        assert_eq!(state.progress(), 0.5);
+        state.apply(Progress::FileFinished);
+        state.apply(Progress::Done);
+        assert_eq!(state.progress(), 1.0);
+        assert_eq!((state.total_hits, state.total_neutrons), (550, 10));
+    }
+}
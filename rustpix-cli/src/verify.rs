@@ -0,0 +1,615 @@
+//! Readback validation for the `verify-output` command.
+//!
+//! Outputs are re-opened and checked for internal consistency, so an
+//! automated reduction can end with a step that fails on a truncated or
+//! inconsistent file instead of passing it downstream:
+//!
+//! - event files (binary, legacy, CSV, `.rpxd`) must decode completely, with
+//!   finite, non-negative positions and times of flight;
+//! - containers (`.rpxc`) must have intact block checksums and no trailing
+//!   partial block, and then pass the event file checks;
+//! - HDF5 and `NeXus` files must have per-event datasets of one length, a
+//!   pulse index that stays within the events and never decreases, and pulse
+//!   times that only restart where another run was appended;
+//! - split indexes (`*.index.json`) must list contiguous parts whose event
+//!   counts and sizes match the files;
+//! - checksum manifests must match the size and SHA-256 of every file.
+
+use crate::checksum::ManifestEntry;
+use crate::{diff, CliError, OutputFormat, Result};
+use rustpix_io::LEGACY_RECORD_BYTES;
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::{Path, PathBuf};
+
+/// Leading bytes of every HDF5 file.
+const HDF5_SIGNATURE: &[u8] = b"\x89HDF\r\n\x1a\n";
+
+/// Leading bytes of every Parquet file.
+const PARQUET_MAGIC: &[u8] = b"PAR1";
+
+/// Leading bytes of every ROOT file.
+const ROOT_MAGIC: &[u8] = b"root";
+
+/// Leading bytes of every rustpix container.
+const CONTAINER_MAGIC: &[u8] = b"RPXC";
+
+/// Outcome of verifying one output.
+#[derive(Debug, Default)]
+pub struct Verification {
+    /// What was read, e.g. `1200 neutrons`.
+    pub summary: String,
+    /// Consistency problems; empty if the output verified.
+    pub problems: Vec<String>,
+}
+
+impl Verification {
+    fn failed(problem: impl Into<String>) -> Self {
+        Self {
+            summary: String::new(),
+            problems: vec![problem.into()],
+        }
+    }
+
+    /// Whether no problems were found.
+    #[must_use]
+    pub fn is_ok(&self) -> bool {
+        self.problems.is_empty()
+    }
+}
+
+/// Verify `outputs` and `manifest`, print one line per file, and fail if any
+/// did not verify.
+///
+/// # Errors
+/// Returns an error naming the number of outputs that failed.
+pub fn run(
+    outputs: &[PathBuf],
+    format: Option<OutputFormat>,
+    manifest: Option<&Path>,
+) -> Result<()> {
+    let mut results: Vec<(&Path, Verification)> = outputs
+        .iter()
+        .map(|output| (output.as_path(), verify(output, format)))
+        .collect();
+    if let Some(manifest) = manifest {
+        results.push((manifest, verify_manifest(manifest)));
+    }
+
+    let mut failed = 0;
+    for (path, result) in &results {
+        if result.is_ok() {
+            println!("{}: OK ({})", path.display(), result.summary);
+            continue;
+        }
+        failed += 1;
+        if result.summary.is_empty() {
+            println!("{}: FAILED", path.display());
+        } else {
+            println!("{}: FAILED ({})", path.display(), result.summary);
+        }
+        for problem in &result.problems {
+            println!("  {problem}");
+        }
+    }
+    if failed > 0 {
+        return Err(CliError::InvalidInput(format!(
+            "{failed} of {} outputs failed verification",
+            results.len()
+        )));
+    }
+    Ok(())
+}
+
+/// Verify one output, detecting its format from its contents or extension
+/// unless `format` is given.
+#[must_use]
+pub fn verify(path: &Path, format: Option<OutputFormat>) -> Verification {
+    if !path.is_file() {
+        return Verification::failed("file not found");
+    }
+    let is_index = path
+        .file_name()
+        .is_some_and(|name| name.to_string_lossy().ends_with(".index.json"));
+    if format.is_none() && is_index {
+        return verify_index(path);
+    }
+    let format = match format {
+        Some(format) => format.as_str().to_string(),
+        None => detect_format(path),
+    };
+    match format.as_str() {
+        "nexus" => verify_hdf5(path),
+        "parquet" => Verification::failed("Parquet outputs cannot be read back"),
+        "root" => Verification::failed("ROOT outputs cannot be read back"),
+        "rpxc" => verify_container(path),
+        _ => {
+            let mut problems = Vec::new();
+            match check_event_file(path, &format, &mut problems) {
+                Ok(events) => Verification {
+                    summary: format!("{events} neutrons"),
+                    problems,
+                },
+                Err(err) => Verification::failed(format!("unreadable: {err}")),
+            }
+        }
+    }
+}
+
+/// Format of `path`: HDF5, Parquet, ROOT and containers by signature,
+/// anything else by extension as `process` chooses it.
+fn detect_format(path: &Path) -> String {
+    let mut magic = [0u8; 8];
+    let read = File::open(path)
+        .and_then(|mut file| file.read(&mut magic))
+        .unwrap_or(0);
+    if magic[..read].starts_with(HDF5_SIGNATURE) {
+        return "nexus".to_string();
+    }
+    if magic[..read].starts_with(PARQUET_MAGIC) {
+        return "parquet".to_string();
+    }
+    if magic[..read].starts_with(ROOT_MAGIC) {
+        return "root".to_string();
+    }
+    if magic[..read].starts_with(CONTAINER_MAGIC) {
+        return "rpxc".to_string();
+    }
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .map_or_else(|| "bin".to_string(), str::to_lowercase)
+}
+
+/// Decode every event of `path` and return the event count.
+fn check_event_file(path: &Path, format: &str, problems: &mut Vec<String>) -> Result<usize> {
+    if std::fs::metadata(path)?.len() == 0 {
+        return Ok(0);
+    }
+    if format == "legacy" {
+        return check_legacy(path, problems);
+    }
+    let batch = diff::read_format(path, format)?;
+    let bad_position = (0..batch.len()).find(|&i| {
+        !(batch.x[i].is_finite()
+            && batch.y[i].is_finite()
+            && batch.x[i] >= 0.0
+            && batch.y[i] >= 0.0)
+    });
+    if let Some(i) = bad_position {
+        problems.push(format!(
+            "event {i} has position ({}, {})",
+            batch.x[i], batch.y[i]
+        ));
+    }
+    Ok(batch.len())
+}
+
+/// Check the block checksums of a container, then its events.
+fn verify_container(path: &Path) -> Verification {
+    let report =
+        match rustpix_io::ContainerReader::open(path).and_then(|mut reader| reader.verify()) {
+            Ok(report) => report,
+            Err(err) => return Verification::failed(format!("unreadable: {err}")),
+        };
+    let mut problems: Vec<String> = report
+        .corrupt_blocks
+        .iter()
+        .map(|block| format!("block {block} fails its checksum or does not decode"))
+        .collect();
+    if report.trailing_bytes > 0 {
+        problems.push(format!(
+            "{} bytes after the last complete block (interrupted write)",
+            report.trailing_bytes
+        ));
+    }
+    let blocks = report.valid_blocks + report.corrupt_blocks.len();
+    if !problems.is_empty() {
+        return Verification {
+            summary: format!("{blocks} blocks"),
+            problems,
+        };
+    }
+    match check_event_file(path, "rpxc", &mut problems) {
+        Ok(events) => Verification {
+            summary: format!("{events} neutrons in {blocks} blocks"),
+            problems,
+        },
+        Err(err) => Verification::failed(format!("unreadable: {err}")),
+    }
+}
+
+/// Check the records of a legacy (`mcpevent2hist`) file.
+fn check_legacy(path: &Path, problems: &mut Vec<String>) -> Result<usize> {
+    let mut reader = BufReader::new(File::open(path)?);
+    let mut record = [0u8; LEGACY_RECORD_BYTES];
+    let mut events = 0;
+    let mut reported = false;
+    loop {
+        let mut filled = 0;
+        while filled < record.len() {
+            match reader.read(&mut record[filled..])? {
+                0 => break,
+                n => filled += n,
+            }
+        }
+        if filled == 0 {
+            return Ok(events);
+        }
+        if filled < record.len() {
+            return Err(CliError::InvalidInput(format!(
+                "truncated record after {events} events ({filled} of {LEGACY_RECORD_BYTES} bytes)"
+            )));
+        }
+        let field = |at: usize| f64::from_le_bytes(record[at..at + 8].try_into().unwrap_or([0; 8]));
+        let (x, y, tof) = (field(0), field(8), field(16));
+        let valid = [x, y, tof]
+            .iter()
+            .all(|value| value.is_finite() && *value >= 0.0);
+        if !valid && !reported {
+            problems.push(format!("event {events} has x {x}, y {y}, tof {tof} ns"));
+            reported = true;
+        }
+        events += 1;
+    }
+}
+
+/// Check that the parts of a split index exist, are contiguous, and hold
+/// the events and bytes the index records.
+fn verify_index(path: &Path) -> Verification {
+    let index: serde_json::Value = match std::fs::read_to_string(path)
+        .map_err(CliError::from)
+        .and_then(|text| serde_json::from_str(&text).map_err(CliError::from))
+    {
+        Ok(index) => index,
+        Err(err) => return Verification::failed(format!("unreadable: {err}")),
+    };
+    let format = index["format"].as_str().unwrap_or("bin");
+    let Some(parts) = index["parts"].as_array() else {
+        return Verification::failed("index has no parts list");
+    };
+    let dir = path.parent().unwrap_or(Path::new(""));
+    let mut problems = Vec::new();
+    let mut next_event = 0u64;
+    for part in parts {
+        let name = part["path"].as_str().unwrap_or_default();
+        let (Some(first_event), Some(events), Some(bytes)) = (
+            part["first_event"].as_u64(),
+            part["events"].as_u64(),
+            part["bytes"].as_u64(),
+        ) else {
+            problems.push(format!("{name}: incomplete index entry"));
+            continue;
+        };
+        if first_event != next_event {
+            problems.push(format!(
+                "{name}: starts at event {first_event}, expected {next_event}"
+            ));
+        }
+        next_event = first_event + events;
+        let part_path = dir.join(name);
+        match std::fs::metadata(&part_path) {
+            Ok(meta) if meta.len() != bytes => problems.push(format!(
+                "{name}: {} bytes, index records {bytes}",
+                meta.len()
+            )),
+            Ok(_) => {}
+            Err(_) => {
+                problems.push(format!("{name}: file not found"));
+                continue;
+            }
+        }
+        let mut part_problems = Vec::new();
+        match check_event_file(&part_path, format, &mut part_problems) {
+            Ok(read) if read as u64 != events => {
+                problems.push(format!("{name}: {read} events, index records {events}"));
+            }
+            Ok(_) => {}
+            Err(err) => problems.push(format!("{name}: unreadable: {err}")),
+        }
+        problems.extend(
+            part_problems
+                .into_iter()
+                .map(|problem| format!("{name}: {problem}")),
+        );
+    }
+    if let Some(total) = index["total_events"].as_u64() {
+        if total != next_event {
+            problems.push(format!("total_events is {total}, parts hold {next_event}"));
+        }
+    }
+    Verification {
+        summary: format!("{next_event} neutrons in {} parts", parts.len()),
+        problems,
+    }
+}
+
+/// Check every file of a checksum manifest (JSON or `sha256sum` lines).
+fn verify_manifest(manifest: &Path) -> Verification {
+    let text = match std::fs::read_to_string(manifest) {
+        Ok(text) => text,
+        Err(err) => return Verification::failed(format!("unreadable: {err}")),
+    };
+    let is_json = manifest
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("json"));
+    // (path, size if recorded, digest)
+    let entries: Vec<(String, Option<u64>, String)> = if is_json {
+        let json: serde_json::Value = match serde_json::from_str(&text) {
+            Ok(json) => json,
+            Err(err) => return Verification::failed(format!("unreadable: {err}")),
+        };
+        json["files"]
+            .as_array()
+            .map(|files| {
+                files
+                    .iter()
+                    .map(|file| {
+                        (
+                            file["path"].as_str().unwrap_or_default().to_string(),
+                            file["size"].as_u64(),
+                            file["sha256"].as_str().unwrap_or_default().to_string(),
+                        )
+                    })
+                    .collect()
+            })
+            .unwrap_or_default()
+    } else {
+        text.lines()
+            .filter_map(|line| line.split_once("  "))
+            .map(|(digest, path)| (path.to_string(), None, digest.to_string()))
+            .collect()
+    };
+
+    let dir = manifest.parent().unwrap_or(Path::new(""));
+    let mut problems = Vec::new();
+    for (name, size, digest) in &entries {
+        let path = Path::new(name);
+        let local = if path.is_absolute() {
+            path.to_path_buf()
+        } else {
+            dir.join(path)
+        };
+        let entry = match ManifestEntry::from_file(&local, name.clone()) {
+            Ok(entry) => entry,
+            Err(err) => {
+                problems.push(format!("{name}: unreadable: {err}"));
+                continue;
+            }
+        };
+        if size.is_some_and(|size| size != entry.size) {
+            problems.push(format!(
+                "{name}: {} bytes, manifest records {}",
+                entry.size,
+                size.unwrap_or_default()
+            ));
+        } else if !entry.sha256.eq_ignore_ascii_case(digest) {
+            problems.push(format!("{name}: SHA-256 mismatch"));
+        }
+    }
+    if entries.is_empty() {
+        problems.push("manifest lists no files".to_string());
+    }
+    Verification {
+        summary: format!("{} files", entries.len()),
+        problems,
+    }
+}
+
+#[cfg(feature = "hdf5")]
+fn verify_hdf5(path: &Path) -> Verification {
+    let layouts = match rustpix_io::hdf5::read_event_group_layouts(path) {
+        Ok(layouts) => layouts,
+        Err(err) => return Verification::failed(format!("unreadable: {err}")),
+    };
+    if layouts.is_empty() {
+        return Verification::failed("no event groups under entry");
+    }
+    let mut problems = Vec::new();
+    let mut summary = Vec::new();
+    for layout in &layouts {
+        let name = &layout.name;
+        for (column, len) in &layout.columns {
+            if *len != layout.events {
+                problems.push(format!(
+                    "{name}: {column} has {len} values for {} events",
+                    layout.events
+                ));
+            }
+        }
+        if let Some(i) = layout
+            .event_time_offset
+            .iter()
+            .position(|tof| !(tof.is_finite() && *tof >= 0.0))
+        {
+            problems.push(format!(
+                "{name}: event {i} has time of flight {}",
+                layout.event_time_offset[i]
+            ));
+        }
+        check_pulses(
+            name,
+            layout.events,
+            &layout.event_index,
+            &layout.event_time_zero,
+            layout.run_count.unwrap_or(1),
+            &mut problems,
+        );
+        summary.push(format!(
+            "{name}: {} events in {} pulses",
+            layout.events,
+            layout.event_index.len()
+        ));
+    }
+    Verification {
+        summary: summary.join(", "),
+        problems,
+    }
+}
+
+#[cfg(not(feature = "hdf5"))]
+fn verify_hdf5(_path: &Path) -> Verification {
+    Verification::failed("rustpix was built without the hdf5 feature needed to read HDF5 outputs")
+}
+
+/// Check a pulse index: one time per pulse, event offsets that start at 0,
+/// never decrease and stay within `events`, and pulse times that decrease
+/// at most once per appended run.
+#[cfg_attr(not(feature = "hdf5"), allow(dead_code))]
+fn check_pulses(
+    name: &str,
+    events: usize,
+    event_index: &[i64],
+    event_time_zero: &[f64],
+    runs: u32,
+    problems: &mut Vec<String>,
+) {
+    if event_index.len() != event_time_zero.len() {
+        problems.push(format!(
+            "{name}: {} pulse indices but {} pulse times",
+            event_index.len(),
+            event_time_zero.len()
+        ));
+    }
+    if event_index.first().is_some_and(|&first| first != 0) {
+        problems.push(format!("{name}: event_index starts at {}", event_index[0]));
+    }
+    if let Some(pulse) = event_index.windows(2).position(|pair| pair[1] < pair[0]) {
+        problems.push(format!(
+            "{name}: event_index decreases at pulse {}",
+            pulse + 1
+        ));
+    }
+    let events = i64::try_from(events).unwrap_or(i64::MAX);
+    if let Some(&last) = event_index.iter().max() {
+        if last > events {
+            problems.push(format!(
+                "{name}: event_index points to event {last} of {events}"
+            ));
+        }
+    }
+    if event_time_zero.iter().any(|time| !time.is_finite()) {
+        problems.push(format!("{name}: pulse times are not finite"));
+    }
+    let restarts = event_time_zero
+        .windows(2)
+        .filter(|pair| pair[1] < pair[0])
+        .count();
+    if restarts >= usize::try_from(runs).unwrap_or(usize::MAX).max(1) {
+        let pulse = event_time_zero
+            .windows(2)
+            .position(|pair| pair[1] < pair[0])
+            .map_or(0, |pulse| pulse + 1);
+        problems.push(format!(
+            "{name}: pulse times decrease at pulse {pulse} ({restarts} times for {runs} runs)"
+        ));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::checksum;
+    use rustpix_core::neutron::{Neutron, NeutronBatch};
+
+    #[test]
+    fn test_split_outputs_and_manifest_verify() {
+        let dir = tempfile::tempdir().unwrap();
+        let part = dir.path().join("neutrons.0000.bin");
+        let mut batch = NeutronBatch::default();
+        batch.push(Neutron::new(1.0, 2.0, 100, 5, 2, 0));
+        batch.push(Neutron::new(3.0, 4.0, 50, 5, 2, 0));
+        let mut writer = rustpix_io::DataFileWriter::create(&part).unwrap();
+        writer.write_neutron_batch_binary(&batch).unwrap();
+        writer.flush().unwrap();
+        drop(writer);
+
+        let index = dir.path().join("neutrons.index.json");
+        let write_index = |events: u64| {
+            let json = serde_json::json!({
+                "format": "bin",
+                "total_events": events,
+                "parts": [{"path": "neutrons.0000.bin", "first_event": 0, "events": events, "bytes": 56}],
+            });
+            std::fs::write(&index, json.to_string()).unwrap();
+        };
+        write_index(2);
+        assert!(verify(&part, None).is_ok());
+        assert!(verify(&index, None).is_ok());
+        write_index(3);
+        let result = verify(&index, None);
+        assert!(result.problems[0].contains("2 events, index records 3"));
+
+        let manifest = dir.path().join("manifest.json");
+        checksum::write_for_files(&manifest, std::slice::from_ref(&part)).unwrap();
+        assert!(verify_manifest(&manifest).is_ok());
+        std::fs::write(&part, [0u8; 30]).unwrap();
+        assert!(!verify_manifest(&manifest).is_ok());
+        assert!(verify(&part, None).problems[0].contains("unreadable"));
+    }
+
+    #[test]
+    fn test_container_verify() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("neutrons.dat");
+        let mut batch = NeutronBatch::default();
+        batch.push(Neutron::new(1.0, 2.0, 100, 5, 2, 0));
+        let mut writer =
+            rustpix_io::ContainerWriter::create(&path, rustpix_io::Compression::None).unwrap();
+        writer.write_neutrons(&batch).unwrap();
+        writer.write_neutrons(&batch).unwrap();
+        writer.finish().unwrap();
+
+        // Detected by its signature despite the extension.
+        let result = verify(&path, None);
+        assert!(result.is_ok(), "{:?}", result.problems);
+        assert_eq!(result.summary, "2 neutrons in 2 blocks");
+
+        let mut bytes = std::fs::read(&path).unwrap();
+        let last = bytes.len() - 1;
+        bytes[last] ^= 0xFF;
+        bytes.extend_from_slice(b"BLK1");
+        std::fs::write(&path, &bytes).unwrap();
+        let result = verify(&path, Some(OutputFormat::Rpxc));
+        assert_eq!(result.problems.len(), 2, "{:?}", result.problems);
+        assert!(result.problems[0].contains("block 1"));
+        assert!(result.problems[1].contains("4 bytes"));
+    }
+
+    #[test]
+    fn test_pulse_index_checks() {
+        let mut problems = Vec::new();
+        check_pulses(
+            "neutrons",
+            5,
+            &[0, 2, 2, 4],
+            &[1.0, 2.0, 3.0, 4.0],
+            1,
+            &mut problems,
+        );
+        assert!(problems.is_empty());
+
+        check_pulses(
+            "neutrons",
+            5,
+            &[0, 3, 2, 6],
+            &[1.0, 2.0, 0.5, 4.0],
+            1,
+            &mut problems,
+        );
+        assert_eq!(problems.len(), 3, "{problems:?}");
+        assert!(problems[0].contains("decreases at pulse 2"));
+
+        // An appended run restarts its pulse times once.
+        problems.clear();
+        check_pulses(
+            "neutrons",
+            5,
+            &[0, 2, 2, 4],
+            &[1.0, 2.0, 0.5, 4.0],
+            2,
+            &mut problems,
+        );
+        assert!(problems.is_empty());
+    }
+}
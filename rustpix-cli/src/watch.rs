@@ -0,0 +1,364 @@
+//! Live processing of a directory the DAQ writes into (`rustpix watch`).
+//!
+//! The directory is polled for `.tpx3` files; a single file can be watched
+//! instead, to follow one acquisition. Every file that grew since the
+//! last poll is read incrementally with [`IncrementalTpx3Reader`], which
+//! returns each pulse once and holds the newest ones back until more data
+//! arrives. A file that has not grown for the settle time is complete: its
+//! remaining pulses are processed and it is not read again. Files are parsed
+//! with their sidecar detector config (`<stem>.json`) if they have one, else
+//! with the `--instrument` one. Neutrons from all files are appended to one
+//! output, flushed after every poll, so stopping the watcher (Ctrl-C) keeps
+//! everything written so far. A restarted watcher reads every file again,
+//! so it will not start over a non-empty output unless told to replace it.
+
+use crate::lock::OutputLock;
+use crate::{runs, write_neutrons, CliError, Result};
+use rustpix_algorithms::{cluster_batch, extract_batch, AlgorithmParams, ClusteringAlgorithm};
+use rustpix_core::clustering::ClusteringConfig;
+use rustpix_core::extraction::ExtractionConfig;
+use rustpix_io::{DataFileWriter, IncrementalTpx3Reader};
+use rustpix_tpx::DetectorConfig;
+use std::collections::btree_map::{BTreeMap, Entry};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+/// Clustering and polling settings for a watch run.
+pub struct WatchSettings {
+    pub algorithm: ClusteringAlgorithm,
+    pub clustering: ClusteringConfig,
+    pub extraction: ExtractionConfig,
+    pub params: AlgorithmParams,
+    /// Detector config of files without a sidecar config.
+    pub detector: DetectorConfig,
+    /// Time between directory polls.
+    pub poll_interval: Duration,
+    /// Time without growth after which a file is complete.
+    pub settle_time: Duration,
+    /// Process the files present now as complete, then exit.
+    pub once: bool,
+    /// Replace an existing non-empty output.
+    pub overwrite: bool,
+    pub verbose: bool,
+}
+
+/// A file seen in the watched directory.
+struct WatchedFile {
+    reader: IncrementalTpx3Reader,
+    /// Size at the last poll that read it.
+    len: u64,
+    last_growth: Instant,
+    hits: usize,
+    neutrons: usize,
+    done: bool,
+}
+
+impl WatchedFile {
+    /// Start reading `path` with its sidecar detector config, if any.
+    fn new(path: &Path, settings: &WatchSettings) -> Result<Self> {
+        let config = match runs::sidecar_config(path)? {
+            Some(config) => {
+                if settings.verbose {
+                    eprintln!("{}: using sidecar detector config", path.display());
+                }
+                config
+            }
+            None => settings.detector.clone(),
+        };
+        Ok(Self {
+            reader: IncrementalTpx3Reader::new(path).with_config(config),
+            len: 0,
+            last_growth: Instant::now(),
+            hits: 0,
+            neutrons: 0,
+            done: false,
+        })
+    }
+}
+
+/// Neutron output shared by all watched files.
+struct WatchOutput {
+    writer: DataFileWriter,
+    format: String,
+    super_resolution_factor: f64,
+    wrote_header: bool,
+    warned_unknown: bool,
+    verbose: bool,
+}
+
+/// Watch `input` (a directory, or a single TPX3 file) and append neutrons
+/// from its TPX3 files to `output`.
+///
+/// Runs until interrupted, or with `once` until the files present at the
+/// start are processed.
+///
+/// # Errors
+/// Returns an error if the directory cannot be listed, a file cannot be
+/// read, processing fails, or the output cannot be written or is not empty
+/// and `overwrite` is not set.
+pub fn run(input: &Path, output: &Path, settings: &WatchSettings) -> Result<()> {
+    let single_file = input.is_file();
+    if !single_file && !input.is_dir() {
+        return Err(CliError::InvalidInput(format!(
+            "{}: not a directory or file",
+            input.display()
+        )));
+    }
+    let format = output
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map_or_else(|| "bin".to_string(), str::to_lowercase);
+    if matches!(format.as_str(), "nexus" | "nxs" | "h5" | "parquet" | "root") {
+        return Err(CliError::InvalidInput(format!(
+            "{}: watch appends to bin, csv, legacy or rpxd outputs",
+            output.display()
+        )));
+    }
+    let _lock = OutputLock::acquire(output)?;
+    if !settings.overwrite && std::fs::metadata(output).is_ok_and(|meta| meta.len() > 0) {
+        return Err(CliError::InvalidInput(format!(
+            "{} already exists and is not empty (use --overwrite to replace it)",
+            output.display()
+        )));
+    }
+    let mut out = WatchOutput {
+        writer: DataFileWriter::create(output)?,
+        format,
+        super_resolution_factor: settings.extraction.super_resolution_factor,
+        wrote_header: false,
+        warned_unknown: false,
+        verbose: settings.verbose,
+    };
+    eprintln!(
+        "Watching {} (every {:.1}s); writing to {}",
+        input.display(),
+        settings.poll_interval.as_secs_f64(),
+        output.display()
+    );
+
+    let mut files: BTreeMap<PathBuf, WatchedFile> = BTreeMap::new();
+    loop {
+        let paths = if single_file {
+            vec![input.to_path_buf()]
+        } else {
+            runs::tpx3_files(input)?
+        };
+        for path in paths {
+            if let Entry::Vacant(entry) = files.entry(path) {
+                let file = WatchedFile::new(entry.key(), settings)?;
+                entry.insert(file);
+            }
+        }
+        for (path, file) in files.iter_mut().filter(|(_, file)| !file.done) {
+            let len = std::fs::metadata(path).map_or(file.len, |meta| meta.len());
+            let grew = len > file.len;
+            if grew {
+                file.len = len;
+                file.last_growth = Instant::now();
+            }
+            let finished = settings.once || file.last_growth.elapsed() >= settings.settle_time;
+            if !grew && !finished {
+                continue;
+            }
+            process_pulses(file, finished, settings, &mut out)?;
+            if finished {
+                file.done = true;
+                println!(
+                    "{}: {} hits, {} neutrons",
+                    path.display(),
+                    file.hits,
+                    file.neutrons
+                );
+            }
+        }
+        flush(&mut out)?;
+        if settings.once {
+            let (hits, neutrons) = files
+                .values()
+                .fold((0, 0), |(h, n), file| (h + file.hits, n + file.neutrons));
+            println!(
+                "Processed {} files: {hits} hits, {neutrons} neutrons",
+                files.len()
+            );
+            return Ok(());
+        }
+        std::thread::sleep(settings.poll_interval);
+    }
+}
+
+/// Cluster and write the pulses a file has ready.
+fn process_pulses(
+    file: &mut WatchedFile,
+    finished: bool,
+    settings: &WatchSettings,
+    out: &mut WatchOutput,
+) -> Result<()> {
+    let pulses = file.reader.poll(finished)?;
+    if settings.verbose && !pulses.is_empty() {
+        eprintln!(
+            "{}: {} new pulse(s), {} bytes read",
+            file.reader.path().display(),
+            pulses.len(),
+            file.reader.bytes_read()
+        );
+    }
+    for pulse in pulses {
+        let mut batch = pulse.hits;
+        let num_clusters = cluster_batch(
+            &mut batch,
+            settings.algorithm,
+            &settings.clustering,
+            &settings.params,
+        )?;
+        let neutrons = extract_batch(&batch, num_clusters, &settings.extraction)?;
+        file.hits += batch.len();
+        file.neutrons += neutrons.len();
+        write_neutrons(
+            &mut out.writer,
+            &out.format,
+            &neutrons,
+            out.super_resolution_factor,
+            &mut out.wrote_header,
+            &mut out.warned_unknown,
+            out.verbose,
+        )?;
+    }
+    Ok(())
+}
+
+/// Flush the output so readers see everything processed so far.
+fn flush(out: &mut WatchOutput) -> Result<()> {
+    if out.format == "csv" && !out.wrote_header {
+        write_neutrons(
+            &mut out.writer,
+            &out.format,
+            &rustpix_core::neutron::NeutronBatch::default(),
+            out.super_resolution_factor,
+            &mut out.wrote_header,
+            &mut out.warned_unknown,
+            out.verbose,
+        )?;
+    }
+    out.writer.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rustpix_tpx::synthetic::{self, SyntheticTpx3Config};
+    use rustpix_tpx::ChipTransform;
+
+    fn settings() -> WatchSettings {
+        WatchSettings {
+            algorithm: ClusteringAlgorithm::Abs,
+            clustering: ClusteringConfig::default(),
+            extraction: ExtractionConfig::default(),
+            params: AlgorithmParams::default(),
+            detector: DetectorConfig::default(),
+            poll_interval: Duration::ZERO,
+            settle_time: Duration::ZERO,
+            once: true,
+            overwrite: false,
+            verbose: false,
+        }
+    }
+
+    #[test]
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    fn test_growing_file_is_processed_with_its_sidecar_config() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("run.tpx3");
+        // One chip without the offset the default layout gives chip 0.
+        let detector = DetectorConfig {
+            chip_transforms: vec![ChipTransform::identity()],
+            ..DetectorConfig::default()
+        };
+        let config = SyntheticTpx3Config {
+            pulses: 6,
+            neutrons_per_pulse: 10,
+            ..SyntheticTpx3Config::default()
+        };
+        let synthetic = synthetic::generate(&config, &detector);
+        std::fs::write(
+            path.with_extension("json"),
+            detector.to_json_string().unwrap(),
+        )
+        .unwrap();
+        // The DAQ has written half of the file.
+        let half = synthetic.data.len() / 16 * 8;
+        std::fs::write(&path, &synthetic.data[..half]).unwrap();
+
+        let settings = settings();
+        let mut file = WatchedFile::new(&path, &settings).unwrap();
+        let output = dir.path().join("neutrons.csv");
+        let mut out = WatchOutput {
+            writer: DataFileWriter::create(&output).unwrap(),
+            format: "csv".to_string(),
+            super_resolution_factor: settings.extraction.super_resolution_factor,
+            wrote_header: false,
+            warned_unknown: false,
+            verbose: false,
+        };
+        process_pulses(&mut file, false, &settings, &mut out).unwrap();
+        assert!(file.neutrons < synthetic.neutrons.len());
+        synthetic.write(&path).unwrap();
+        process_pulses(&mut file, true, &settings, &mut out).unwrap();
+        flush(&mut out).unwrap();
+        assert_eq!(file.neutrons, synthetic.neutrons.len());
+
+        let neutrons = crate::diff::read_neutrons(&output, 1.0).unwrap();
+        let scale = settings.extraction.super_resolution_factor;
+        let mut found: Vec<(u32, u32)> = neutrons
+            .x
+            .iter()
+            .zip(&neutrons.y)
+            .map(|(x, y)| ((x / scale).round() as u32, (y / scale).round() as u32))
+            .collect();
+        let mut expected: Vec<(u32, u32)> = synthetic
+            .neutrons
+            .iter()
+            .map(|neutron| (neutron.x as u32, neutron.y as u32))
+            .collect();
+        found.sort_unstable();
+        expected.sort_unstable();
+        assert_eq!(found, expected);
+    }
+
+    #[test]
+    fn test_flush_writes_csv_header_without_neutrons() {
+        let dir = tempfile::tempdir().unwrap();
+        let output = dir.path().join("neutrons.csv");
+        let mut out = WatchOutput {
+            writer: DataFileWriter::create(&output).unwrap(),
+            format: "csv".to_string(),
+            super_resolution_factor: 8.0,
+            wrote_header: false,
+            warned_unknown: false,
+            verbose: false,
+        };
+        flush(&mut out).unwrap();
+        flush(&mut out).unwrap();
+        let text = std::fs::read_to_string(&output).unwrap();
+        assert_eq!(text.lines().count(), 1);
+        assert!(text.starts_with("x,y,tof"));
+    }
+
+    #[test]
+    fn test_non_empty_output_needs_overwrite() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("daq");
+        std::fs::create_dir(&input).unwrap();
+        let output = dir.path().join("neutrons.bin");
+        std::fs::write(&output, [1u8; 28]).unwrap();
+
+        let mut settings = settings();
+        let err = run(&input, &output, &settings).unwrap_err();
+        assert!(err.to_string().contains("--overwrite"));
+        assert_eq!(std::fs::metadata(&output).unwrap().len(), 28);
+
+        settings.overwrite = true;
+        run(&input, &output, &settings).unwrap();
+        assert_eq!(std::fs::metadata(&output).unwrap().len(), 0);
+    }
+}
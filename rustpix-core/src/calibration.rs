@@ -0,0 +1,290 @@
+//! Per-pixel `ToT`-to-energy calibration.
+//!
+//! Test-pulse calibrations describe each pixel's response with the surrogate
+//! function `ToT(E) = a·E + b − c / (E − t)`. [`TotCalibration`] inverts it to
+//! turn a raw `ToT` count into deposited energy in keV.
+
+use std::fmt;
+use std::path::Path;
+
+use crate::error::IoError;
+
+/// Calibration surface parameters for one pixel.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TotSurface {
+    /// Slope of the linear region (`ToT` counts per keV).
+    pub a: f64,
+    /// Offset of the linear region (`ToT` counts).
+    pub b: f64,
+    /// Curvature near threshold (`ToT` counts · keV).
+    pub c: f64,
+    /// Asymptote near threshold (keV).
+    pub t: f64,
+}
+
+impl TotSurface {
+    /// Create a surface from its `a`, `b`, `c` and `t` parameters.
+    #[must_use]
+    pub fn new(a: f64, b: f64, c: f64, t: f64) -> Self {
+        Self { a, b, c, t }
+    }
+
+    /// Energy in keV for a raw `ToT` count.
+    ///
+    /// Solves `a·E² + (b − a·t − ToT)·E + (ToT·t − b·t − c) = 0` for the root
+    /// above the threshold asymptote; with `a` zero the equation is linear.
+    #[must_use]
+    pub fn energy_kev(&self, tot: u16) -> f64 {
+        let tot = f64::from(tot);
+        let p = self.b - self.a * self.t - tot;
+        let q = tot * self.t - self.b * self.t - self.c;
+        if self.a.abs() < f64::EPSILON {
+            return if p.abs() < f64::EPSILON { 0.0 } else { -q / p };
+        }
+        let discriminant = (p * p - 4.0 * self.a * q).max(0.0);
+        (discriminant.sqrt() - p) / (2.0 * self.a)
+    }
+}
+
+/// `ToT`-to-energy calibration for a detector.
+///
+/// Pixels are addressed in global detector coordinates (the `x`/`y` of a
+/// `HitBatch`). Pixels without their own surface use the default surface, if
+/// one is set.
+#[derive(Clone, Default)]
+pub struct TotCalibration {
+    width: usize,
+    height: usize,
+    pixels: Vec<Option<TotSurface>>,
+    default: Option<TotSurface>,
+}
+
+impl fmt::Debug for TotCalibration {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TotCalibration")
+            .field("width", &self.width)
+            .field("height", &self.height)
+            .field("calibrated_pixels", &self.calibrated_pixels())
+            .field("default", &self.default)
+            .finish_non_exhaustive()
+    }
+}
+
+impl TotCalibration {
+    /// Calibration applying the same surface to every pixel.
+    #[must_use]
+    pub fn uniform(surface: TotSurface) -> Self {
+        Self {
+            default: Some(surface),
+            ..Self::default()
+        }
+    }
+
+    /// Empty calibration over a `width` × `height` pixel grid.
+    #[must_use]
+    pub fn with_size(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            pixels: vec![None; width * height],
+            default: None,
+        }
+    }
+
+    /// Grid width in pixels.
+    #[must_use]
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Grid height in pixels.
+    #[must_use]
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Number of pixels with their own surface.
+    #[must_use]
+    pub fn calibrated_pixels(&self) -> usize {
+        self.pixels.iter().filter(|pixel| pixel.is_some()).count()
+    }
+
+    /// Surface used for pixels without their own.
+    #[must_use]
+    pub fn default_surface(&self) -> Option<TotSurface> {
+        self.default
+    }
+
+    /// Set the surface used for pixels without their own.
+    pub fn set_default(&mut self, surface: Option<TotSurface>) {
+        self.default = surface;
+    }
+
+    /// Set the surface of pixel (`x`, `y`), growing the grid if needed.
+    pub fn set_pixel(&mut self, x: usize, y: usize, surface: TotSurface) {
+        if x >= self.width || y >= self.height {
+            let width = self.width.max(x + 1);
+            let height = self.height.max(y + 1);
+            let mut pixels = vec![None; width * height];
+            for row in 0..self.height {
+                let src = row * self.width;
+                pixels[row * width..row * width + self.width]
+                    .copy_from_slice(&self.pixels[src..src + self.width]);
+            }
+            self.width = width;
+            self.height = height;
+            self.pixels = pixels;
+        }
+        self.pixels[y * self.width + x] = Some(surface);
+    }
+
+    /// Surface for pixel (`x`, `y`), falling back to the default.
+    #[inline]
+    #[must_use]
+    pub fn surface(&self, x: u16, y: u16) -> Option<TotSurface> {
+        let (x, y) = (usize::from(x), usize::from(y));
+        if x < self.width && y < self.height {
+            if let Some(surface) = self.pixels[y * self.width + x] {
+                return Some(surface);
+            }
+        }
+        self.default
+    }
+
+    /// Energy in keV deposited by a hit, or `None` if its pixel has no surface.
+    #[inline]
+    #[must_use]
+    pub fn energy_kev(&self, x: u16, y: u16, tot: u16) -> Option<f64> {
+        self.surface(x, y).map(|surface| surface.energy_kev(tot))
+    }
+
+    /// Load a calibration file.
+    ///
+    /// The file is text with one pixel per line, `x y a b c t`, separated by
+    /// whitespace or commas. A line `* a b c t` sets the default surface.
+    /// Text after `#` is a comment.
+    ///
+    /// # Errors
+    /// Returns an error if the file cannot be read or a line is malformed.
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, IoError> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path).map_err(|err| {
+            if err.kind() == std::io::ErrorKind::NotFound {
+                IoError::FileNotFound(path.display().to_string())
+            } else {
+                IoError::Io(err)
+            }
+        })?;
+        Self::parse(&text)
+    }
+
+    /// Parse calibration text in the format read by [`Self::from_file`].
+    ///
+    /// # Errors
+    /// Returns an error if a line is malformed.
+    pub fn parse(text: &str) -> Result<Self, IoError> {
+        let mut default = None;
+        let mut pixels = Vec::new();
+        for (line_no, line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or_default().trim();
+            if line.is_empty() {
+                continue;
+            }
+            let invalid = |message: &str| {
+                IoError::InvalidFormat(format!("calibration line {}: {message}", line_no + 1))
+            };
+            let fields: Vec<&str> = line
+                .split(|c: char| c == ',' || c.is_whitespace())
+                .filter(|field| !field.is_empty())
+                .collect();
+            let number = |field: &str| {
+                field
+                    .parse::<f64>()
+                    .ok()
+                    .filter(|value| value.is_finite())
+                    .ok_or_else(|| invalid(&format!("'{field}' is not a number")))
+            };
+            let surface = |params: &[&str]| -> Result<TotSurface, IoError> {
+                Ok(TotSurface::new(
+                    number(params[0])?,
+                    number(params[1])?,
+                    number(params[2])?,
+                    number(params[3])?,
+                ))
+            };
+            match fields.as_slice() {
+                ["*", params @ ..] if params.len() == 4 => {
+                    default = Some(surface(params)?);
+                }
+                [x, y, params @ ..] if params.len() == 4 => {
+                    let coordinate = |field: &str| {
+                        field
+                            .parse::<u16>()
+                            .map_err(|_| invalid(&format!("'{field}' is not a pixel coordinate")))
+                    };
+                    let (x, y) = (coordinate(x)?, coordinate(y)?);
+                    pixels.push((usize::from(x), usize::from(y), surface(params)?));
+                }
+                _ => return Err(invalid("expected 'x y a b c t' or '* a b c t'")),
+            }
+        }
+
+        let width = pixels.iter().map(|&(x, _, _)| x + 1).max().unwrap_or(0);
+        let height = pixels.iter().map(|&(_, y, _)| y + 1).max().unwrap_or(0);
+        let mut calibration = Self::with_size(width, height);
+        calibration.default = default;
+        for (x, y, surface) in pixels {
+            calibration.pixels[y * width + x] = Some(surface);
+        }
+        Ok(calibration)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_surface_inverts_surrogate_function() {
+        let surface = TotSurface::new(2.0, 30.0, 150.0, 3.0);
+        // Energies above the threshold region, where the surrogate ToT is positive.
+        for energy in [10.0_f64, 30.0, 60.0, 120.0] {
+            let tot = surface.a * energy + surface.b - surface.c / (energy - surface.t);
+            #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+            let tot = tot.round() as u16;
+            // Rounding ToT to whole counts costs up to half a count of energy.
+            assert!((surface.energy_kev(tot) - energy).abs() < 0.5);
+        }
+
+        let linear = TotSurface::new(1.0, 0.0, 0.0, 0.0);
+        assert!((linear.energy_kev(42) - 42.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_parse_pixels_and_default() {
+        let calibration = TotCalibration::parse(
+            "# x y a b c t\n\
+             * 1 0 0 0\n\
+             2, 1, 2, 0, 0, 0 # trailing comment\n\
+             \n\
+             0 3 0.5 0 0 0\n",
+        )
+        .unwrap();
+        assert_eq!((calibration.width(), calibration.height()), (3, 4));
+        assert_eq!(calibration.calibrated_pixels(), 2);
+
+        let energy = |x, y, tot| calibration.energy_kev(x, y, tot).unwrap();
+        assert!((energy(2, 1, 40) - 20.0).abs() < 1e-9);
+        assert!((energy(0, 3, 40) - 80.0).abs() < 1e-9);
+        // Uncalibrated and out-of-grid pixels use the default surface.
+        assert!((energy(1, 1, 40) - 40.0).abs() < 1e-9);
+        assert!((energy(500, 500, 40) - 40.0).abs() < 1e-9);
+
+        let mut without_default = calibration;
+        without_default.set_default(None);
+        assert!(without_default.energy_kev(1, 1, 40).is_none());
+
+        assert!(TotCalibration::parse("1 2 3").is_err());
+        assert!(TotCalibration::parse("1 2 a 0 0 0").is_err());
+    }
+}
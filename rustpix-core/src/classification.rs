@@ -0,0 +1,421 @@
+//! Post-clustering event classification.
+//!
+//! Gamma rays and electronic noise also fire the detector and end up as
+//! clusters next to the neutrons. [`EventClassifier`] labels each cluster as
+//! a neutron, gamma or noise event from its [`ClusterShape`], either with
+//! plain thresholds or with a decision tree trained offline.
+
+use std::fmt;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::features::{cluster_features, ClusterFeatures};
+use crate::soa::HitBatch;
+
+/// Label assigned to a cluster.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum EventClass {
+    /// Neutron capture event.
+    #[default]
+    Neutron,
+    /// Gamma-ray (or other minimum-ionizing) event.
+    Gamma,
+    /// Electronic noise.
+    Noise,
+}
+
+impl EventClass {
+    /// All classes, in label order.
+    pub const ALL: [Self; 3] = [Self::Neutron, Self::Gamma, Self::Noise];
+
+    /// Lower-case name of the class.
+    #[must_use]
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Neutron => "neutron",
+            Self::Gamma => "gamma",
+            Self::Noise => "noise",
+        }
+    }
+}
+
+impl fmt::Display for EventClass {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Shape features of one cluster, computed from its hits in pixel space.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ClusterShape {
+    /// Number of hits.
+    pub n_hits: u32,
+    /// Summed `ToT` of the hits.
+    pub total_tot: u64,
+    /// Root-mean-square distance of the hits from their centroid (pixels).
+    pub rms_radius: f64,
+    /// `(λ1 − λ2) / (λ1 + λ2)` of the position covariance eigenvalues: 0 for
+    /// round clusters, approaching 1 for tracks.
+    pub elongation: f64,
+}
+
+impl ClusterShape {
+    /// Value of one feature.
+    #[must_use]
+    #[allow(clippy::cast_precision_loss)]
+    pub fn feature(&self, feature: ShapeFeature) -> f64 {
+        match feature {
+            ShapeFeature::NHits => f64::from(self.n_hits),
+            ShapeFeature::TotalTot => self.total_tot as f64,
+            ShapeFeature::RmsRadius => self.rms_radius,
+            ShapeFeature::Elongation => self.elongation,
+        }
+    }
+}
+
+impl From<&ClusterFeatures> for ClusterShape {
+    fn from(features: &ClusterFeatures) -> Self {
+        let major = features.major_axis * features.major_axis;
+        let minor = features.minor_axis * features.minor_axis;
+        Self {
+            n_hits: features.n_hits,
+            total_tot: features.total_tot,
+            rms_radius: features.rms_radius,
+            elongation: if major > 0.0 {
+                ((major - minor) / (major + minor)).min(1.0)
+            } else {
+                0.0
+            },
+        }
+    }
+}
+
+/// Cluster feature tested by a decision-tree split.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum ShapeFeature {
+    /// [`ClusterShape::n_hits`].
+    NHits,
+    /// [`ClusterShape::total_tot`].
+    TotalTot,
+    /// [`ClusterShape::rms_radius`].
+    RmsRadius,
+    /// [`ClusterShape::elongation`].
+    Elongation,
+}
+
+/// Threshold cuts applied in order: noise first, then gamma.
+///
+/// The defaults accept every cluster as a neutron.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct ClassifierThresholds {
+    /// Clusters with a total `ToT` below this are noise.
+    pub min_tot: u64,
+    /// Clusters with fewer hits than this are gammas.
+    pub min_neutron_hits: u32,
+    /// Clusters with a total `ToT` below this are gammas.
+    pub min_neutron_tot: u64,
+    /// Clusters more elongated than this are gammas (None = no cut).
+    pub max_neutron_elongation: Option<f64>,
+}
+
+impl ClassifierThresholds {
+    /// Label a cluster.
+    #[must_use]
+    pub fn classify(&self, shape: &ClusterShape) -> EventClass {
+        if shape.total_tot < self.min_tot {
+            return EventClass::Noise;
+        }
+        let elongated = self
+            .max_neutron_elongation
+            .is_some_and(|max| shape.elongation > max);
+        if shape.n_hits < self.min_neutron_hits
+            || shape.total_tot < self.min_neutron_tot
+            || elongated
+        {
+            return EventClass::Gamma;
+        }
+        EventClass::Neutron
+    }
+}
+
+/// Node of a binary decision tree.
+///
+/// In JSON a leaf is `{"class": "gamma"}` and a split is
+/// `{"feature": "total_tot", "threshold": 50, "below": ..., "above": ...}`.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(untagged))]
+pub enum DecisionNode {
+    /// Terminal node.
+    Leaf {
+        /// Label of clusters reaching this node.
+        class: EventClass,
+    },
+    /// Internal node.
+    Split {
+        /// Feature compared against the threshold.
+        feature: ShapeFeature,
+        /// Values below the threshold go to `below`, others to `above`.
+        threshold: f64,
+        /// Subtree for values below the threshold.
+        below: Box<DecisionNode>,
+        /// Subtree for values at or above the threshold.
+        above: Box<DecisionNode>,
+    },
+}
+
+impl DecisionNode {
+    /// Label a cluster by walking the tree.
+    #[must_use]
+    pub fn classify(&self, shape: &ClusterShape) -> EventClass {
+        let mut node = self;
+        loop {
+            match node {
+                Self::Leaf { class } => return *class,
+                Self::Split {
+                    feature,
+                    threshold,
+                    below,
+                    above,
+                } => {
+                    node = if shape.feature(*feature) < *threshold {
+                        below
+                    } else {
+                        above
+                    };
+                }
+            }
+        }
+    }
+}
+
+/// Cluster classifier.
+///
+/// In JSON the variant is the single key, e.g. `{"thresholds": {...}}` or
+/// `{"tree": {...}}`.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum EventClassifier {
+    /// User-configured cuts.
+    Thresholds(ClassifierThresholds),
+    /// Decision-tree model.
+    Tree(DecisionNode),
+}
+
+impl Default for EventClassifier {
+    fn default() -> Self {
+        Self::Thresholds(ClassifierThresholds::default())
+    }
+}
+
+impl EventClassifier {
+    /// Label a cluster.
+    #[must_use]
+    pub fn classify(&self, shape: &ClusterShape) -> EventClass {
+        match self {
+            Self::Thresholds(thresholds) => thresholds.classify(shape),
+            Self::Tree(root) => root.classify(shape),
+        }
+    }
+}
+
+/// Number of events of each class.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ClassCounts {
+    /// Neutron events.
+    pub neutron: usize,
+    /// Gamma events.
+    pub gamma: usize,
+    /// Noise events.
+    pub noise: usize,
+}
+
+impl ClassCounts {
+    /// Count one event.
+    pub fn add(&mut self, class: EventClass) {
+        match class {
+            EventClass::Neutron => self.neutron += 1,
+            EventClass::Gamma => self.gamma += 1,
+            EventClass::Noise => self.noise += 1,
+        }
+    }
+
+    /// Add the counts of another tally.
+    pub fn merge(&mut self, other: &Self) {
+        self.neutron += other.neutron;
+        self.gamma += other.gamma;
+        self.noise += other.noise;
+    }
+
+    /// Count of one class.
+    #[must_use]
+    pub fn get(&self, class: EventClass) -> usize {
+        match class {
+            EventClass::Neutron => self.neutron,
+            EventClass::Gamma => self.gamma,
+            EventClass::Noise => self.noise,
+        }
+    }
+
+    /// Total number of events.
+    #[must_use]
+    pub fn total(&self) -> usize {
+        self.neutron + self.gamma + self.noise
+    }
+}
+
+/// Shape of every cluster of a labelled batch, indexed by cluster id.
+///
+/// Hits below `min_tot` are ignored, as in extraction; clusters left without
+/// hits have `n_hits == 0`.
+#[must_use]
+pub fn cluster_shapes(batch: &HitBatch, num_clusters: usize, min_tot: u16) -> Vec<ClusterShape> {
+    cluster_features(batch, num_clusters, min_tot)
+        .iter()
+        .map(ClusterShape::from)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn labelled(hits: &[(u16, u16, u16, i32)]) -> HitBatch {
+        let mut batch = HitBatch::with_capacity(hits.len());
+        for (i, &(x, y, tot, cluster)) in hits.iter().enumerate() {
+            batch.push((x, y, 0, tot, 0, 0));
+            batch.cluster_id[i] = cluster;
+        }
+        batch
+    }
+
+    #[test]
+    fn test_cluster_shapes() {
+        let batch = labelled(&[
+            (10, 10, 20, 0),
+            (11, 10, 20, 0),
+            (10, 11, 20, 0),
+            (11, 11, 20, 0),
+            (0, 0, 20, 1),
+            (1, 0, 20, 1),
+            (2, 0, 20, 1),
+            (3, 0, 5, 1), // below the ToT threshold
+            (50, 50, 5, 2),
+            (60, 60, 30, -1),
+        ]);
+        let shapes = cluster_shapes(&batch, 3, 10);
+        assert_eq!(shapes[0].n_hits, 4);
+        assert_eq!(shapes[0].total_tot, 80);
+        assert!((shapes[0].rms_radius - 0.5_f64.sqrt()).abs() < 1e-9);
+        assert!(shapes[0].elongation.abs() < 1e-9);
+        assert_eq!(shapes[1].n_hits, 3);
+        assert!((shapes[1].elongation - 1.0).abs() < 1e-9);
+        assert_eq!(shapes[2], ClusterShape::default());
+    }
+
+    #[test]
+    fn test_thresholds() {
+        let thresholds = ClassifierThresholds {
+            min_tot: 20,
+            min_neutron_hits: 3,
+            min_neutron_tot: 100,
+            max_neutron_elongation: Some(0.8),
+        };
+        let shape = |n_hits, total_tot, elongation| ClusterShape {
+            n_hits,
+            total_tot,
+            rms_radius: 1.0,
+            elongation,
+        };
+        assert_eq!(thresholds.classify(&shape(1, 10, 0.0)), EventClass::Noise);
+        assert_eq!(thresholds.classify(&shape(2, 500, 0.0)), EventClass::Gamma);
+        assert_eq!(thresholds.classify(&shape(5, 50, 0.0)), EventClass::Gamma);
+        assert_eq!(thresholds.classify(&shape(5, 500, 0.9)), EventClass::Gamma);
+        assert_eq!(
+            thresholds.classify(&shape(5, 500, 0.2)),
+            EventClass::Neutron
+        );
+        assert_eq!(
+            EventClassifier::default().classify(&shape(1, 0, 1.0)),
+            EventClass::Neutron
+        );
+    }
+
+    #[test]
+    fn test_decision_tree() {
+        let tree = EventClassifier::Tree(DecisionNode::Split {
+            feature: ShapeFeature::NHits,
+            threshold: 3.0,
+            below: Box::new(DecisionNode::Leaf {
+                class: EventClass::Gamma,
+            }),
+            above: Box::new(DecisionNode::Split {
+                feature: ShapeFeature::RmsRadius,
+                threshold: 4.0,
+                below: Box::new(DecisionNode::Leaf {
+                    class: EventClass::Neutron,
+                }),
+                above: Box::new(DecisionNode::Leaf {
+                    class: EventClass::Noise,
+                }),
+            }),
+        });
+        let shape = |n_hits, rms_radius| ClusterShape {
+            n_hits,
+            total_tot: 100,
+            rms_radius,
+            elongation: 0.0,
+        };
+        assert_eq!(tree.classify(&shape(2, 1.0)), EventClass::Gamma);
+        assert_eq!(tree.classify(&shape(3, 1.0)), EventClass::Neutron);
+        assert_eq!(tree.classify(&shape(6, 5.0)), EventClass::Noise);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_classifier_json() {
+        let thresholds: EventClassifier =
+            serde_json::from_str(r#"{"thresholds": {"min_tot": 20, "min_neutron_hits": 3}}"#)
+                .unwrap();
+        let EventClassifier::Thresholds(thresholds) = &thresholds else {
+            panic!("expected thresholds");
+        };
+        assert_eq!(thresholds.min_neutron_hits, 3);
+        assert_eq!(thresholds.max_neutron_elongation, None);
+
+        let tree: EventClassifier = serde_json::from_str(
+            r#"{"tree": {"feature": "total_tot", "threshold": 50,
+                "below": {"class": "gamma"}, "above": {"class": "neutron"}}}"#,
+        )
+        .unwrap();
+        let shape = ClusterShape {
+            n_hits: 4,
+            total_tot: 40,
+            ..ClusterShape::default()
+        };
+        assert_eq!(tree.classify(&shape), EventClass::Gamma);
+    }
+
+    #[test]
+    fn test_class_counts() {
+        let mut counts = ClassCounts::default();
+        for class in [EventClass::Neutron, EventClass::Gamma, EventClass::Neutron] {
+            counts.add(class);
+        }
+        let mut total = counts;
+        total.merge(&counts);
+        assert_eq!(total.get(EventClass::Neutron), 4);
+        assert_eq!(total.gamma, 2);
+        assert_eq!(total.total(), 6);
+        assert_eq!(EventClass::Noise.to_string(), "noise");
+    }
+}
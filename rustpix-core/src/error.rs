@@ -28,6 +28,16 @@ pub enum ExtractionError {
     /// Invalid extraction configuration.
     #[error("invalid configuration: {0}")]
     InvalidConfig(String),
+
+    /// Neutrons were scaled by a different super-resolution factor than a
+    /// later stage expects.
+    #[error("super-resolution mismatch: neutrons are at x{found}, expected x{expected}")]
+    SuperResolutionMismatch {
+        /// Factor the stage was configured with.
+        expected: f64,
+        /// Factor the neutrons were extracted with.
+        found: f64,
+    },
 }
 
 /// Errors during I/O operations.
@@ -1,18 +1,51 @@
 //! Neutron extraction traits and configuration.
 //!
 
+use std::sync::Arc;
+
+use crate::calibration::TotCalibration;
+use crate::classification::{ClusterShape, EventClass, EventClassifier};
 use crate::error::ExtractionError;
+use crate::features::{cluster_features, ClusterFeatures};
 use crate::neutron::{Neutron, NeutronBatch};
 
+/// Super-resolution factors extraction supports, from native pixels (×1)
+/// to 1/16 pixel.
+pub const SUPER_RESOLUTION_FACTORS: [f64; 5] = [1.0, 2.0, 4.0, 8.0, 16.0];
+
+/// Checks that `factor` is one of [`SUPER_RESOLUTION_FACTORS`].
+///
+/// # Errors
+/// Returns [`ExtractionError::InvalidConfig`] for any other value.
+pub fn validate_super_resolution(factor: f64) -> Result<(), ExtractionError> {
+    if SUPER_RESOLUTION_FACTORS.contains(&factor) {
+        Ok(())
+    } else {
+        Err(ExtractionError::InvalidConfig(format!(
+            "super-resolution factor must be 1, 2, 4, 8 or 16, got {factor}"
+        )))
+    }
+}
+
 /// Configuration for neutron extraction.
 #[derive(Clone, Debug)]
 pub struct ExtractionConfig {
-    /// Sub-pixel resolution multiplier (default: 8.0).
+    /// Sub-pixel resolution multiplier, one of [`SUPER_RESOLUTION_FACTORS`]
+    /// (default: 8.0).
     pub super_resolution_factor: f64,
     /// Weight centroids by TOT values.
     pub weighted_by_tot: bool,
     /// Minimum TOT threshold (0 = disabled).
     pub min_tot_threshold: u16,
+    /// Per-pixel `ToT` calibration; when set, batch extraction also reports
+    /// each neutron's deposited energy in `NeutronBatch::energy_kev`.
+    pub tot_calibration: Option<Arc<TotCalibration>>,
+    /// Cluster classifier; when set, batch extraction also labels each
+    /// neutron in `NeutronBatch::event_class`.
+    pub event_classifier: Option<Arc<EventClassifier>>,
+    /// When set, batch extraction also reports each neutron's cluster shape
+    /// in `NeutronBatch::features`.
+    pub cluster_features: bool,
 }
 
 impl Default for ExtractionConfig {
@@ -21,6 +54,9 @@ impl Default for ExtractionConfig {
             super_resolution_factor: 8.0,
             weighted_by_tot: true,
             min_tot_threshold: 10,
+            tot_calibration: None,
+            event_classifier: None,
+            cluster_features: false,
         }
     }
 }
@@ -52,6 +88,36 @@ impl ExtractionConfig {
         self.min_tot_threshold = threshold;
         self
     }
+
+    /// Set the `ToT` calibration used to report deposited energy.
+    #[must_use]
+    pub fn with_tot_calibration(mut self, calibration: Arc<TotCalibration>) -> Self {
+        self.tot_calibration = Some(calibration);
+        self
+    }
+
+    /// Set the classifier used to label neutron, gamma and noise clusters.
+    #[must_use]
+    pub fn with_event_classifier(mut self, classifier: Arc<EventClassifier>) -> Self {
+        self.event_classifier = Some(classifier);
+        self
+    }
+
+    /// Set whether to report per-cluster shape features.
+    #[must_use]
+    pub fn with_cluster_features(mut self, enabled: bool) -> Self {
+        self.cluster_features = enabled;
+        self
+    }
+
+    /// Checks the configuration before extraction.
+    ///
+    /// # Errors
+    /// Returns [`ExtractionError::InvalidConfig`] if the super-resolution
+    /// factor is not supported.
+    pub fn validate(&self) -> Result<(), ExtractionError> {
+        validate_super_resolution(self.super_resolution_factor)
+    }
 }
 
 /// Trait for neutron extraction algorithms.
@@ -94,10 +160,13 @@ struct ClusterAccumulator {
     raw_sum_x: f64,
     raw_sum_y: f64,
     sum_tot: u64,
+    sum_energy: f64,
     count: u32,
     max_tot: u16,
     rep_tof: u32,
     rep_chip: u8,
+    class: Option<EventClass>,
+    features: Option<ClusterFeatures>,
 }
 
 /// Simple centroid extraction using TOT-weighted averages.
@@ -145,6 +214,7 @@ impl NeutronExtraction for SimpleCentroidExtraction {
         batch: &crate::soa::HitBatch,
         num_clusters: usize,
     ) -> Result<Vec<Neutron>, ExtractionError> {
+        self.config.validate()?;
         let mut accumulators = vec![ClusterAccumulator::default(); num_clusters];
         if self.config.weighted_by_tot {
             accumulate_weighted(
@@ -182,6 +252,7 @@ impl SimpleCentroidExtraction {
         batch: &crate::soa::HitBatch,
         num_clusters: usize,
     ) -> Result<NeutronBatch, ExtractionError> {
+        self.config.validate()?;
         let mut accumulators = vec![ClusterAccumulator::default(); num_clusters];
         if self.config.weighted_by_tot {
             accumulate_weighted(
@@ -190,9 +261,12 @@ impl SimpleCentroidExtraction {
                 num_clusters,
                 self.config.min_tot_threshold,
             );
+            self.accumulate_energy(&mut accumulators, batch, num_clusters);
+            self.describe_clusters(&mut accumulators, batch, num_clusters);
             Ok(build_neutron_batch_weighted(
                 accumulators,
                 self.config.super_resolution_factor,
+                self.config.tot_calibration.is_some(),
             ))
         } else {
             accumulate_unweighted(
@@ -201,16 +275,165 @@ impl SimpleCentroidExtraction {
                 num_clusters,
                 self.config.min_tot_threshold,
             );
+            self.accumulate_energy(&mut accumulators, batch, num_clusters);
+            self.describe_clusters(&mut accumulators, batch, num_clusters);
             Ok(build_neutron_batch_unweighted(
                 accumulators,
                 self.config.super_resolution_factor,
+                self.config.tot_calibration.is_some(),
             ))
         }
     }
+
+    /// Compute shape features and label each cluster, if requested.
+    fn describe_clusters(
+        &self,
+        accumulators: &mut [ClusterAccumulator],
+        batch: &crate::soa::HitBatch,
+        num_clusters: usize,
+    ) {
+        let classifier = self.config.event_classifier.as_deref();
+        if classifier.is_none() && !self.config.cluster_features {
+            return;
+        }
+        let features = cluster_features(batch, num_clusters, self.config.min_tot_threshold);
+        for (acc, features) in accumulators.iter_mut().zip(features) {
+            acc.class = classifier.map(|c| c.classify(&ClusterShape::from(&features)));
+            if self.config.cluster_features {
+                acc.features = Some(features);
+            }
+        }
+    }
+
+    /// Sum calibrated hit energies per cluster, if a calibration is set.
+    ///
+    /// Hits on pixels without a calibration surface contribute no energy.
+    fn accumulate_energy(
+        &self,
+        accumulators: &mut [ClusterAccumulator],
+        batch: &crate::soa::HitBatch,
+        num_clusters: usize,
+    ) {
+        let Some(calibration) = self.config.tot_calibration.as_deref() else {
+            return;
+        };
+        let min_tot = self.config.min_tot_threshold;
+        for i in 0..batch.len() {
+            let Some(cluster_idx) = cluster_index(batch.cluster_id[i], num_clusters) else {
+                continue;
+            };
+            let tot = batch.tot[i];
+            if tot < min_tot {
+                continue;
+            }
+            if let Some(energy) = calibration.energy_kev(batch.x[i], batch.y[i], tot) {
+                accumulators[cluster_idx].sum_energy += energy;
+            }
+        }
+    }
+}
+
+/// Back-reference index from extracted neutrons to their constituent hits.
+///
+/// Built from the same labelled batch, cluster count and configuration passed
+/// to [`SimpleCentroidExtraction::extract_soa_batch`], neutron `i` of that
+/// output was computed from the hits [`Self::hits_for`]`(i)`. Hits dropped by
+/// `min_tot_threshold` are not included. Stored in compressed-row form:
+/// neutron `i` owns `hit_indices[offsets[i]..offsets[i + 1]]`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct NeutronHitIndex {
+    /// Row offsets into `hit_indices` (one more entry than neutrons).
+    pub offsets: Vec<usize>,
+    /// Hit indices into the labelled batch, grouped by neutron.
+    pub hit_indices: Vec<usize>,
+}
+
+impl NeutronHitIndex {
+    /// Build the index for a labelled hit batch.
+    #[must_use]
+    pub fn build(
+        batch: &crate::soa::HitBatch,
+        num_clusters: usize,
+        config: &ExtractionConfig,
+    ) -> Self {
+        let min_tot = config.min_tot_threshold;
+        let member = |i: usize| {
+            cluster_index(batch.cluster_id[i], num_clusters)
+                .filter(|_| min_tot == 0 || batch.tot[i] >= min_tot)
+        };
+
+        let mut counts = vec![0usize; num_clusters];
+        for i in 0..batch.len() {
+            if let Some(cluster_idx) = member(i) {
+                counts[cluster_idx] += 1;
+            }
+        }
+
+        // Clusters left empty produce no neutron, matching extraction.
+        let mut offsets = Vec::with_capacity(num_clusters + 1);
+        offsets.push(0);
+        let mut cursors = counts;
+        let mut total = 0usize;
+        for count in &mut cursors {
+            if *count > 0 {
+                let start = total;
+                total += *count;
+                offsets.push(total);
+                *count = start;
+            }
+        }
+
+        let mut hit_indices = vec![0usize; total];
+        for i in 0..batch.len() {
+            if let Some(cluster_idx) = member(i) {
+                hit_indices[cursors[cluster_idx]] = i;
+                cursors[cluster_idx] += 1;
+            }
+        }
+
+        Self {
+            offsets,
+            hit_indices,
+        }
+    }
+
+    /// Number of neutrons in the index.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.offsets.len().saturating_sub(1)
+    }
+
+    /// Returns true if the index has no neutrons.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Hit indices for neutron `neutron`.
+    ///
+    /// # Panics
+    /// Panics if `neutron` is out of range.
+    #[must_use]
+    pub fn hits_for(&self, neutron: usize) -> &[usize] {
+        &self.hit_indices[self.offsets[neutron]..self.offsets[neutron + 1]]
+    }
+
+    /// Append another index whose hit indices refer to a batch concatenated
+    /// after `hit_offset` hits.
+    pub fn append(&mut self, other: &Self, hit_offset: usize) {
+        if self.offsets.is_empty() {
+            self.offsets.push(0);
+        }
+        let base = self.hit_indices.len();
+        self.offsets
+            .extend(other.offsets.iter().skip(1).map(|&offset| base + offset));
+        self.hit_indices
+            .extend(other.hit_indices.iter().map(|&hit| hit + hit_offset));
+    }
 }
 
 #[inline]
-fn cluster_index(label: i32, num_clusters: usize) -> Option<usize> {
+pub(crate) fn cluster_index(label: i32, num_clusters: usize) -> Option<usize> {
     if label < 0 {
         return None;
     }
@@ -415,8 +638,13 @@ fn build_neutrons_unweighted(accumulators: Vec<ClusterAccumulator>, scale: f64)
     neutrons
 }
 
-fn build_neutron_batch_weighted(accumulators: Vec<ClusterAccumulator>, scale: f64) -> NeutronBatch {
+fn build_neutron_batch_weighted(
+    accumulators: Vec<ClusterAccumulator>,
+    scale: f64,
+    with_energy: bool,
+) -> NeutronBatch {
     let mut batch = NeutronBatch::with_capacity(accumulators.len());
+    batch.super_resolution_factor = Some(scale);
     for acc in accumulators {
         if acc.count == 0 {
             continue;
@@ -440,6 +668,15 @@ fn build_neutron_batch_weighted(accumulators: Vec<ClusterAccumulator>, scale: f6
             u16::try_from(acc.count).unwrap_or(u16::MAX),
             acc.rep_chip,
         ));
+        if with_energy {
+            batch.energy_kev.push(acc.sum_energy);
+        }
+        if let Some(class) = acc.class {
+            batch.event_class.push(class);
+        }
+        if let Some(features) = acc.features {
+            batch.features.push(features);
+        }
     }
     batch
 }
@@ -447,8 +684,10 @@ fn build_neutron_batch_weighted(accumulators: Vec<ClusterAccumulator>, scale: f6
 fn build_neutron_batch_unweighted(
     accumulators: Vec<ClusterAccumulator>,
     scale: f64,
+    with_energy: bool,
 ) -> NeutronBatch {
     let mut batch = NeutronBatch::with_capacity(accumulators.len());
+    batch.super_resolution_factor = Some(scale);
     for acc in accumulators {
         if acc.count == 0 {
             continue;
@@ -465,6 +704,15 @@ fn build_neutron_batch_unweighted(
             u16::try_from(acc.count).unwrap_or(u16::MAX),
             acc.rep_chip,
         ));
+        if with_energy {
+            batch.energy_kev.push(acc.sum_energy);
+        }
+        if let Some(class) = acc.class {
+            batch.event_class.push(class);
+        }
+        if let Some(features) = acc.features {
+            batch.features.push(features);
+        }
     }
     batch
 }
@@ -483,6 +731,119 @@ mod tests {
         batch
     }
 
+    #[test]
+    fn test_neutron_hit_index_matches_extraction() {
+        // Cluster 1 loses all hits to the TOT threshold and produces no neutron.
+        let batch = make_batch(&[
+            (1000, 10, 10, 0, 40, 0, 2),
+            (1000, 10, 11, 0, 5, 0, 1),
+            (1001, 11, 10, 0, 30, 0, 0),
+            (1002, 50, 50, 0, 20, 0, -1),
+            (1003, 11, 11, 0, 25, 0, 2),
+        ]);
+        let config = ExtractionConfig::default().with_min_tot_threshold(10);
+        let extractor = SimpleCentroidExtraction::with_config(config.clone());
+        let neutrons = extractor.extract_soa_batch(&batch, 3).unwrap();
+
+        let index = NeutronHitIndex::build(&batch, 3, &config);
+        assert_eq!(index.len(), neutrons.len());
+        assert_eq!(index.hits_for(0), &[2]);
+        assert_eq!(index.hits_for(1), &[0, 4]);
+        for i in 0..index.len() {
+            assert_eq!(index.hits_for(i).len(), usize::from(neutrons.n_hits[i]));
+        }
+
+        let mut combined = NeutronHitIndex::default();
+        combined.append(&index, 0);
+        combined.append(&index, batch.len());
+        assert_eq!(combined.len(), 4);
+        assert_eq!(combined.hits_for(3), &[5, 9]);
+    }
+
+    #[test]
+    fn test_tot_calibration_reports_energy() {
+        use crate::calibration::TotSurface;
+
+        let batch = make_batch(&[
+            (1000, 0, 0, 500, 30, 0, 0),
+            (1000, 1, 0, 500, 10, 0, 0),
+            (1000, 2, 0, 500, 5, 0, 0), // below the TOT threshold
+            (2000, 9, 9, 500, 20, 0, 1),
+        ]);
+        let mut calibration = TotCalibration::uniform(TotSurface::new(2.0, 0.0, 0.0, 0.0));
+        calibration.set_pixel(1, 0, TotSurface::new(0.5, 0.0, 0.0, 0.0));
+
+        let plain = SimpleCentroidExtraction::new()
+            .extract_soa_batch(&batch, 2)
+            .unwrap();
+        assert!(!plain.has_energy());
+
+        let extractor = SimpleCentroidExtraction::with_config(
+            ExtractionConfig::default().with_tot_calibration(Arc::new(calibration)),
+        );
+        let neutrons = extractor.extract_soa_batch(&batch, 2).unwrap();
+        assert_eq!(neutrons.energy_kev.len(), neutrons.len());
+        // 30 / 2 + 10 / 0.5 keV; raw ToT sums are unchanged.
+        assert!((neutrons.energy_kev[0] - 35.0).abs() < 1e-9);
+        assert!((neutrons.energy_kev[1] - 10.0).abs() < 1e-9);
+        assert_eq!(neutrons.tot, plain.tot);
+    }
+
+    #[test]
+    fn test_event_classifier_labels_neutrons() {
+        use crate::classification::ClassifierThresholds;
+
+        let batch = make_batch(&[
+            (1000, 0, 0, 500, 30, 0, 0),
+            (1000, 1, 0, 500, 30, 0, 0),
+            (1000, 0, 1, 500, 30, 0, 0),
+            (2000, 9, 9, 500, 40, 0, 1),
+            (3000, 20, 20, 500, 12, 0, 2),
+        ]);
+        let classifier = EventClassifier::Thresholds(ClassifierThresholds {
+            min_tot: 20,
+            min_neutron_hits: 2,
+            ..ClassifierThresholds::default()
+        });
+        let extractor = SimpleCentroidExtraction::with_config(
+            ExtractionConfig::default().with_event_classifier(Arc::new(classifier)),
+        );
+        let neutrons = extractor.extract_soa_batch(&batch, 3).unwrap();
+        assert_eq!(
+            neutrons.event_class,
+            [EventClass::Neutron, EventClass::Gamma, EventClass::Noise]
+        );
+        assert!(!SimpleCentroidExtraction::new()
+            .extract_soa_batch(&batch, 3)
+            .unwrap()
+            .has_classes());
+    }
+
+    #[test]
+    fn test_cluster_features_follow_neutrons() {
+        // Cluster 1 loses its only hit to the TOT threshold and is skipped.
+        let batch = make_batch(&[
+            (1000, 0, 0, 0, 30, 0, 0),
+            (1004, 3, 0, 0, 30, 0, 0),
+            (2000, 9, 9, 0, 5, 0, 1),
+            (3000, 20, 20, 0, 12, 0, 2),
+        ]);
+        let extractor = SimpleCentroidExtraction::with_config(
+            ExtractionConfig::default().with_cluster_features(true),
+        );
+        let neutrons = extractor.extract_soa_batch(&batch, 3).unwrap();
+        assert_eq!(neutrons.features.len(), 2);
+        assert_eq!(neutrons.features[0].n_hits, 2);
+        assert!((neutrons.features[0].major_axis - 1.5).abs() < 1e-9);
+        assert_eq!(neutrons.features[0].time_spread, 4);
+        assert_eq!(neutrons.features[1].n_hits, 1);
+        assert!(!neutrons.has_classes());
+        assert!(!SimpleCentroidExtraction::new()
+            .extract_soa_batch(&batch, 3)
+            .unwrap()
+            .has_features());
+    }
+
     #[test]
     fn test_single_hit_extraction() {
         let batch = make_batch(&[(1000, 100, 200, 500, 50, 0, 0)]);
@@ -694,5 +1055,23 @@ mod tests {
         assert_eq!(neutrons.len(), 1);
         assert!((neutrons[0].x - 8.0).abs() < f64::EPSILON);
         assert!((neutrons[0].y - 12.0).abs() < f64::EPSILON);
+        let neutrons = extractor.extract_soa_batch(&batch, 1).unwrap();
+        assert_eq!(neutrons.super_resolution_factor, Some(4.0));
+    }
+
+    #[test]
+    fn test_unsupported_super_resolution_factor() {
+        let batch = make_batch(&[(1000, 2, 3, 500, 20, 0, 0)]);
+        for factor in SUPER_RESOLUTION_FACTORS {
+            assert!(validate_super_resolution(factor).is_ok());
+        }
+        let extractor = SimpleCentroidExtraction::with_config(
+            ExtractionConfig::default().with_super_resolution(3.0),
+        );
+        assert!(matches!(
+            extractor.extract_soa_batch(&batch, 1),
+            Err(ExtractionError::InvalidConfig(_))
+        ));
+        assert!(extractor.extract_soa(&batch, 1).is_err());
     }
 }
@@ -0,0 +1,202 @@
+//! Per-cluster shape features.
+//!
+//! [`ClusterFeatures`] summarizes how a cluster's hits spread in space and
+//! time, for event discrimination and detector diagnostics downstream of
+//! extraction. Positions are in pixels and times in 25 ns ticks.
+
+use crate::extraction::cluster_index;
+use crate::soa::HitBatch;
+
+/// Shape features of one cluster, computed from its hits.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ClusterFeatures {
+    /// Number of hits.
+    pub n_hits: u32,
+    /// Summed `ToT` of the hits.
+    pub total_tot: u64,
+    /// Root-mean-square distance of the hits from their centroid (pixels).
+    pub rms_radius: f64,
+    /// `ToT`-weighted root-mean-square distance of the hits from the
+    /// `ToT`-weighted centroid (pixels); unweighted when all `ToT`s are 0.
+    pub tot_weighted_rms_radius: f64,
+    /// Standard deviation along the cluster's long axis (pixels).
+    pub major_axis: f64,
+    /// Standard deviation across the cluster's long axis (pixels).
+    pub minor_axis: f64,
+    /// `sqrt(1 − minor² / major²)`: 0 for round clusters, approaching 1 for
+    /// tracks.
+    pub eccentricity: f64,
+    /// Time between the first and last hit (25 ns ticks).
+    pub time_spread: u32,
+}
+
+#[derive(Clone, Copy)]
+struct FeatureMoments {
+    count: u32,
+    sum_tot: u64,
+    sum_x: f64,
+    sum_y: f64,
+    sum_xx: f64,
+    sum_yy: f64,
+    sum_xy: f64,
+    weighted_x: f64,
+    weighted_y: f64,
+    weighted_rr: f64,
+    min_tof: u32,
+    max_tof: u32,
+}
+
+impl Default for FeatureMoments {
+    fn default() -> Self {
+        Self {
+            count: 0,
+            sum_tot: 0,
+            sum_x: 0.0,
+            sum_y: 0.0,
+            sum_xx: 0.0,
+            sum_yy: 0.0,
+            sum_xy: 0.0,
+            weighted_x: 0.0,
+            weighted_y: 0.0,
+            weighted_rr: 0.0,
+            min_tof: u32::MAX,
+            max_tof: 0,
+        }
+    }
+}
+
+impl FeatureMoments {
+    #[allow(clippy::cast_precision_loss)]
+    fn features(&self) -> ClusterFeatures {
+        if self.count == 0 {
+            return ClusterFeatures::default();
+        }
+        let n = f64::from(self.count);
+        let (mean_x, mean_y) = (self.sum_x / n, self.sum_y / n);
+        let var_x = (self.sum_xx / n - mean_x * mean_x).max(0.0);
+        let var_y = (self.sum_yy / n - mean_y * mean_y).max(0.0);
+        let cov = self.sum_xy / n - mean_x * mean_y;
+        let trace = var_x + var_y;
+        // Eigenvalues of the 2x2 position covariance matrix.
+        let spread = ((var_x - var_y).powi(2) + 4.0 * cov * cov).sqrt();
+        let major = f64::midpoint(trace, spread);
+        let minor = ((trace - spread) / 2.0).max(0.0);
+
+        let tot_weighted_rms_radius = if self.sum_tot > 0 {
+            let weight = self.sum_tot as f64;
+            let (wx, wy) = (self.weighted_x / weight, self.weighted_y / weight);
+            (self.weighted_rr / weight - wx * wx - wy * wy)
+                .max(0.0)
+                .sqrt()
+        } else {
+            trace.sqrt()
+        };
+
+        ClusterFeatures {
+            n_hits: self.count,
+            total_tot: self.sum_tot,
+            rms_radius: trace.sqrt(),
+            tot_weighted_rms_radius,
+            major_axis: major.sqrt(),
+            minor_axis: minor.sqrt(),
+            eccentricity: if major > 0.0 {
+                (1.0 - minor / major).max(0.0).sqrt()
+            } else {
+                0.0
+            },
+            time_spread: self.max_tof - self.min_tof,
+        }
+    }
+}
+
+/// Features of every cluster of a labelled batch, indexed by cluster id.
+///
+/// Hits below `min_tot` are ignored, as in extraction; clusters left without
+/// hits have `n_hits == 0`.
+#[must_use]
+pub fn cluster_features(
+    batch: &HitBatch,
+    num_clusters: usize,
+    min_tot: u16,
+) -> Vec<ClusterFeatures> {
+    let mut moments = vec![FeatureMoments::default(); num_clusters];
+    for i in 0..batch.len() {
+        let Some(cluster_idx) = cluster_index(batch.cluster_id[i], num_clusters) else {
+            continue;
+        };
+        let tot = batch.tot[i];
+        if tot < min_tot {
+            continue;
+        }
+        let x = f64::from(batch.x[i]);
+        let y = f64::from(batch.y[i]);
+        let w = f64::from(tot);
+        let tof = batch.tof[i];
+        let m = &mut moments[cluster_idx];
+        m.count += 1;
+        m.sum_tot += u64::from(tot);
+        m.sum_x += x;
+        m.sum_y += y;
+        m.sum_xx += x * x;
+        m.sum_yy += y * y;
+        m.sum_xy += x * y;
+        m.weighted_x += w * x;
+        m.weighted_y += w * y;
+        m.weighted_rr += w * (x * x + y * y);
+        m.min_tof = m.min_tof.min(tof);
+        m.max_tof = m.max_tof.max(tof);
+    }
+    moments.iter().map(FeatureMoments::features).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn labelled(hits: &[(u16, u16, u32, u16, i32)]) -> HitBatch {
+        let mut batch = HitBatch::with_capacity(hits.len());
+        for (i, &(x, y, tof, tot, cluster)) in hits.iter().enumerate() {
+            batch.push((x, y, tof, tot, tof, 0));
+            batch.cluster_id[i] = cluster;
+        }
+        batch
+    }
+
+    #[test]
+    fn test_cluster_features() {
+        let batch = labelled(&[
+            // Round 2x2 cluster with one bright pixel.
+            (10, 10, 100, 20, 0),
+            (11, 10, 102, 20, 0),
+            (10, 11, 101, 20, 0),
+            (11, 11, 104, 140, 0),
+            // Straight track along x.
+            (0, 0, 50, 20, 1),
+            (2, 0, 50, 20, 1),
+            (4, 0, 51, 20, 1),
+            (6, 0, 900, 5, 1), // below the ToT threshold
+            (50, 50, 0, 5, 2),
+        ]);
+        let features = cluster_features(&batch, 3, 10);
+
+        let round = &features[0];
+        assert_eq!(round.n_hits, 4);
+        assert_eq!(round.total_tot, 200);
+        assert!((round.rms_radius - 0.5_f64.sqrt()).abs() < 1e-9);
+        assert!(round.tot_weighted_rms_radius < round.rms_radius);
+        assert!((round.major_axis - 0.5).abs() < 1e-9);
+        assert!((round.minor_axis - 0.5).abs() < 1e-9);
+        assert!(round.eccentricity.abs() < 1e-9);
+        assert_eq!(round.time_spread, 4);
+
+        let track = &features[1];
+        assert_eq!(track.n_hits, 3);
+        assert!((track.major_axis - (8.0_f64 / 3.0).sqrt()).abs() < 1e-9);
+        assert!(track.minor_axis.abs() < 1e-9);
+        assert!((track.eccentricity - 1.0).abs() < 1e-9);
+        assert!((track.tot_weighted_rms_radius - track.rms_radius).abs() < 1e-9);
+        assert_eq!(track.time_spread, 1);
+
+        assert_eq!(features[2], ClusterFeatures::default());
+    }
+}
@@ -0,0 +1,296 @@
+//! Hit-level filtering by detector region, time of flight, `ToT` and chip.
+//!
+//! A [`HitFilter`] collects the cuts a hit has to pass and is applied to a
+//! [`HitBatch`] right after reading, before clustering, so every later stage
+//! works on the reduced data. Cuts combine with AND; regions combine with OR,
+//! so a hit is kept if its pixel lies in any of them. An empty filter keeps
+//! everything.
+
+use crate::error::ProcessingError;
+use crate::soa::HitBatch;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// Length of one TOF tick in nanoseconds.
+const TICK_NS: f64 = 25.0;
+
+/// Detector region in pixel coordinates. A pixel is inside when its center
+/// is.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum HitRegion {
+    /// Pixels with `x_min <= x < x_max` and `y_min <= y < y_max`.
+    Rectangle {
+        /// Left edge.
+        x_min: f64,
+        /// Top edge.
+        y_min: f64,
+        /// Right edge (exclusive).
+        x_max: f64,
+        /// Bottom edge (exclusive).
+        y_max: f64,
+    },
+    /// Pixels inside a polygon of at least three vertices.
+    Polygon {
+        /// Vertices as `(x, y)`, in order around the polygon.
+        vertices: Vec<(f64, f64)>,
+    },
+}
+
+impl HitRegion {
+    /// Whether pixel `(x, y)` lies in the region.
+    #[must_use]
+    pub fn contains(&self, x: u16, y: u16) -> bool {
+        let (x, y) = (f64::from(x) + 0.5, f64::from(y) + 0.5);
+        match self {
+            Self::Rectangle {
+                x_min,
+                y_min,
+                x_max,
+                y_max,
+            } => (*x_min..*x_max).contains(&x) && (*y_min..*y_max).contains(&y),
+            Self::Polygon { vertices } => point_in_polygon(x, y, vertices),
+        }
+    }
+
+    fn validate(&self) -> Result<(), ProcessingError> {
+        match self {
+            Self::Rectangle {
+                x_min,
+                y_min,
+                x_max,
+                y_max,
+            } if x_max <= x_min || y_max <= y_min => Err(ProcessingError::Config(format!(
+                "region {x_min},{y_min},{x_max},{y_max} is empty"
+            ))),
+            Self::Polygon { vertices } if vertices.len() < 3 => Err(ProcessingError::Config(
+                format!("polygon has {} vertices, needs at least 3", vertices.len()),
+            )),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Cuts applied to hits before clustering.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct HitFilter {
+    /// Regions to keep; hits outside all of them are dropped. Empty keeps
+    /// the whole detector.
+    pub regions: Vec<HitRegion>,
+    /// Inclusive TOF window in 25 ns ticks.
+    pub tof_range: Option<(u32, u32)>,
+    /// Inclusive `ToT` range.
+    pub tot_range: Option<(u16, u16)>,
+    /// Chips to keep (None = all).
+    pub chips: Option<Vec<u8>>,
+}
+
+impl HitFilter {
+    /// Filter that keeps every hit.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Keep hits in `region` (in addition to any other region).
+    #[must_use]
+    pub fn with_region(mut self, region: HitRegion) -> Self {
+        self.regions.push(region);
+        self
+    }
+
+    /// Keep hits in the rectangle `x_min <= x < x_max`, `y_min <= y < y_max`.
+    #[must_use]
+    pub fn with_rectangle(self, x_min: f64, y_min: f64, x_max: f64, y_max: f64) -> Self {
+        self.with_region(HitRegion::Rectangle {
+            x_min,
+            y_min,
+            x_max,
+            y_max,
+        })
+    }
+
+    /// Keep hits inside the polygon through `vertices`.
+    #[must_use]
+    pub fn with_polygon(self, vertices: Vec<(f64, f64)>) -> Self {
+        self.with_region(HitRegion::Polygon { vertices })
+    }
+
+    /// Set the inclusive TOF window in 25 ns ticks.
+    #[must_use]
+    pub fn with_tof_range(mut self, min: u32, max: u32) -> Self {
+        self.tof_range = Some((min, max));
+        self
+    }
+
+    /// Set the TOF window in nanoseconds; ticks partly outside it are cut.
+    #[must_use]
+    pub fn with_tof_range_ns(self, min_ns: f64, max_ns: f64) -> Self {
+        self.with_tof_range(
+            whole_ticks((min_ns / TICK_NS).ceil()),
+            whole_ticks((max_ns / TICK_NS).floor()),
+        )
+    }
+
+    /// Set the inclusive `ToT` range.
+    #[must_use]
+    pub fn with_tot_range(mut self, min: u16, max: u16) -> Self {
+        self.tot_range = Some((min, max));
+        self
+    }
+
+    /// Keep only hits from `chips`.
+    #[must_use]
+    pub fn with_chips(mut self, chips: impl IntoIterator<Item = u8>) -> Self {
+        let mut chips: Vec<u8> = chips.into_iter().collect();
+        chips.sort_unstable();
+        chips.dedup();
+        self.chips = Some(chips);
+        self
+    }
+
+    /// Whether the filter keeps every hit.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.regions.is_empty()
+            && self.tof_range.is_none()
+            && self.tot_range.is_none()
+            && self.chips.is_none()
+    }
+
+    /// Check that regions are not empty and ranges are ordered.
+    ///
+    /// # Errors
+    /// Returns [`ProcessingError::Config`] describing the first bad cut.
+    pub fn validate(&self) -> Result<(), ProcessingError> {
+        for region in &self.regions {
+            region.validate()?;
+        }
+        if let Some((min, max)) = self.tof_range.filter(|(min, max)| min > max) {
+            return Err(ProcessingError::Config(format!(
+                "TOF range {min}..={max} is empty"
+            )));
+        }
+        if let Some((min, max)) = self.tot_range.filter(|(min, max)| min > max) {
+            return Err(ProcessingError::Config(format!(
+                "ToT range {min}..={max} is empty"
+            )));
+        }
+        Ok(())
+    }
+
+    /// Whether a hit with these fields passes every cut.
+    #[must_use]
+    pub fn accepts(&self, x: u16, y: u16, tof: u32, tot: u16, chip_id: u8) -> bool {
+        self.tof_range
+            .is_none_or(|(min, max)| (min..=max).contains(&tof))
+            && self
+                .tot_range
+                .is_none_or(|(min, max)| (min..=max).contains(&tot))
+            && self
+                .chips
+                .as_ref()
+                .is_none_or(|chips| chips.binary_search(&chip_id).is_ok())
+            && (self.regions.is_empty() || self.regions.iter().any(|region| region.contains(x, y)))
+    }
+
+    /// Drop the hits of `batch` that fail a cut, keeping the rest in order.
+    ///
+    /// Returns the number of hits removed.
+    pub fn apply(&self, batch: &mut HitBatch) -> usize {
+        if self.is_empty() {
+            return 0;
+        }
+        let keep: Vec<bool> = (0..batch.len())
+            .map(|i| {
+                self.accepts(
+                    batch.x[i],
+                    batch.y[i],
+                    batch.tof[i],
+                    batch.tot[i],
+                    batch.chip_id[i],
+                )
+            })
+            .collect();
+        batch.retain_hits(|i| keep[i])
+    }
+}
+
+/// Whole ticks as `u32`, saturating at the ends of its range.
+#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+fn whole_ticks(ticks: f64) -> u32 {
+    ticks.clamp(0.0, f64::from(u32::MAX)) as u32
+}
+
+fn point_in_polygon(x: f64, y: f64, vertices: &[(f64, f64)]) -> bool {
+    if vertices.len() < 3 {
+        return false;
+    }
+    let mut inside = false;
+    let mut j = vertices.len() - 1;
+    for (i, &(xi, yi)) in vertices.iter().enumerate() {
+        let (xj, yj) = vertices[j];
+        if (yi > y) != (yj > y) && x < (xj - xi) * (y - yi) / (yj - yi) + xi {
+            inside = !inside;
+        }
+        j = i;
+    }
+    inside
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn batch() -> HitBatch {
+        let mut batch = HitBatch::default();
+        batch.push((10, 10, 100, 5, 0, 0));
+        batch.push((20, 10, 200, 50, 1, 1));
+        batch.push((10, 30, 300, 500, 2, 2));
+        batch.push((40, 40, 400, 50, 3, 3));
+        batch
+    }
+
+    #[test]
+    fn test_empty_filter_keeps_everything() {
+        let filter = HitFilter::new();
+        assert!(filter.is_empty());
+        let mut hits = batch();
+        assert_eq!(filter.apply(&mut hits), 0);
+        assert_eq!(hits.len(), 4);
+    }
+
+    #[test]
+    fn test_cuts_combine() {
+        let mut hits = batch();
+        let filter = HitFilter::new()
+            .with_rectangle(0.0, 0.0, 25.0, 35.0)
+            .with_polygon(vec![(35.0, 35.0), (45.0, 35.0), (40.0, 45.0)])
+            .with_tot_range(10, 100);
+        assert_eq!(filter.apply(&mut hits), 2);
+        assert_eq!(hits.x, vec![20, 40]);
+
+        let mut hits = batch();
+        let filter = HitFilter::new()
+            .with_tof_range_ns(2_500.0, 7_500.0)
+            .with_chips([3, 0, 2]);
+        assert_eq!(filter.tof_range, Some((100, 300)));
+        assert_eq!(filter.apply(&mut hits), 2);
+        assert_eq!(hits.chip_id, vec![0, 2]);
+    }
+
+    #[test]
+    fn test_validate() {
+        assert!(HitFilter::new().with_tot_range(5, 10).validate().is_ok());
+        assert!(HitFilter::new().with_tof_range(10, 5).validate().is_err());
+        assert!(HitFilter::new()
+            .with_rectangle(5.0, 0.0, 5.0, 10.0)
+            .validate()
+            .is_err());
+        assert!(HitFilter::new()
+            .with_polygon(vec![(0.0, 0.0), (1.0, 1.0)])
+            .validate()
+            .is_err());
+    }
+}
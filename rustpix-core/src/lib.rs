@@ -5,13 +5,35 @@
 //!
 #![warn(missing_docs)]
 
+pub mod calibration;
+pub mod classification;
 pub mod clustering;
 pub mod error;
 pub mod extraction;
+pub mod features;
+pub mod filter;
+pub mod gaussian_fit;
 pub mod neutron;
+pub mod pulse;
+pub mod rng;
 pub mod soa;
+pub mod warnings;
 
+pub use calibration::{TotCalibration, TotSurface};
+pub use classification::{
+    ClassCounts, ClassifierThresholds, ClusterShape, DecisionNode, EventClass, EventClassifier,
+    ShapeFeature,
+};
 pub use clustering::{ClusteringConfig, ClusteringStatistics};
 pub use error::{ClusteringError, Error, ExtractionError, IoError, ProcessingError, Result};
-pub use extraction::{ExtractionConfig, NeutronExtraction, SimpleCentroidExtraction};
+pub use extraction::{
+    validate_super_resolution, ExtractionConfig, NeutronExtraction, NeutronHitIndex,
+    SimpleCentroidExtraction, SUPER_RESOLUTION_FACTORS,
+};
+pub use features::ClusterFeatures;
+pub use filter::{HitFilter, HitRegion};
+pub use gaussian_fit::GaussianFitExtraction;
 pub use neutron::{ClusterSize, Neutron, NeutronBatch, NeutronStatistics};
+pub use pulse::{EventIndex, Pulse, PulseIndex, Pulses};
+pub use rng::SplitMix64;
+pub use warnings::{Warning, WarningKind, Warnings};
@@ -1,9 +1,14 @@
 //! Neutron event output type.
 //!
 
+use crate::classification::{ClassCounts, EventClass};
+use crate::error::ExtractionError;
+use crate::features::ClusterFeatures;
+
 /// A detected neutron event after clustering and centroid extraction.
 ///
-/// Coordinates are in super-resolution space (default 8x pixel resolution).
+/// Coordinates are in super-resolution space (default 8x pixel resolution);
+/// [`NeutronBatch::super_resolution_factor`] records the factor.
 #[derive(Clone, Copy, Debug, Default, PartialEq)]
 #[repr(C)]
 pub struct Neutron {
@@ -109,7 +114,7 @@ pub struct NeutronStatistics {
 }
 
 /// Structure-of-arrays neutron output.
-#[derive(Clone, Debug, Default)]
+#[derive(Clone, Debug, Default, PartialEq)]
 pub struct NeutronBatch {
     /// X coordinates (super-resolution space).
     pub x: Vec<f64>,
@@ -123,6 +128,24 @@ pub struct NeutronBatch {
     pub n_hits: Vec<u16>,
     /// Chip ID per neutron.
     pub chip_id: Vec<u8>,
+    /// Deposited energy per neutron in keV.
+    ///
+    /// Only filled when extraction ran with a `ToT` calibration; empty otherwise.
+    pub energy_kev: Vec<f64>,
+    /// Event class per neutron.
+    ///
+    /// Only filled when extraction ran with an event classifier; empty otherwise.
+    pub event_class: Vec<EventClass>,
+    /// Cluster shape features per neutron.
+    ///
+    /// Only filled when extraction ran with cluster features enabled; empty
+    /// otherwise.
+    pub features: Vec<ClusterFeatures>,
+    /// Super-resolution factor the positions were scaled by.
+    ///
+    /// Set by extraction; `None` for batches read from files that do not
+    /// record it.
+    pub super_resolution_factor: Option<f64>,
 }
 
 impl NeutronBatch {
@@ -136,6 +159,10 @@ impl NeutronBatch {
             tot: Vec::with_capacity(capacity),
             n_hits: Vec::with_capacity(capacity),
             chip_id: Vec::with_capacity(capacity),
+            energy_kev: Vec::new(),
+            event_class: Vec::new(),
+            features: Vec::new(),
+            super_resolution_factor: None,
         }
     }
 
@@ -151,6 +178,90 @@ impl NeutronBatch {
         self.x.is_empty()
     }
 
+    /// Returns true when the batch carries calibrated energies.
+    #[must_use]
+    pub fn has_energy(&self) -> bool {
+        !self.energy_kev.is_empty()
+    }
+
+    /// Returns true when the batch carries event classes.
+    #[must_use]
+    pub fn has_classes(&self) -> bool {
+        !self.event_class.is_empty()
+    }
+
+    /// Returns true when the batch carries cluster shape features.
+    #[must_use]
+    pub fn has_features(&self) -> bool {
+        !self.features.is_empty()
+    }
+
+    /// Positions in detector pixels, dividing by the batch's
+    /// super-resolution factor or, when it is unknown, by `fallback`.
+    #[must_use]
+    pub fn pixel_coords(&self, index: usize, fallback: f64) -> (f64, f64) {
+        let scale = self.super_resolution_factor.unwrap_or(fallback);
+        (self.x[index] / scale, self.y[index] / scale)
+    }
+
+    /// Checks that the positions were scaled by `factor`, for stages that
+    /// convert them back to pixels. Batches of unknown factor pass.
+    ///
+    /// # Errors
+    /// Returns [`ExtractionError::SuperResolutionMismatch`] if the batch
+    /// records a different factor.
+    pub fn check_super_resolution(&self, factor: f64) -> Result<(), ExtractionError> {
+        match self.super_resolution_factor {
+            Some(found) if (found - factor).abs() > f64::EPSILON => {
+                Err(ExtractionError::SuperResolutionMismatch {
+                    expected: factor,
+                    found,
+                })
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Number of neutrons of each class, or None for an unclassified batch.
+    #[must_use]
+    pub fn class_counts(&self) -> Option<ClassCounts> {
+        if !self.has_classes() {
+            return None;
+        }
+        let mut counts = ClassCounts::default();
+        for &class in &self.event_class {
+            counts.add(class);
+        }
+        Some(counts)
+    }
+
+    /// Keep only the events whose class is in `classes`.
+    ///
+    /// Unclassified batches are left unchanged.
+    pub fn retain_classes(&mut self, classes: &[EventClass]) {
+        if !self.has_classes() {
+            return;
+        }
+        let keep: Vec<bool> = self
+            .event_class
+            .iter()
+            .map(|class| classes.contains(class))
+            .collect();
+        retain_by(&mut self.x, &keep);
+        retain_by(&mut self.y, &keep);
+        retain_by(&mut self.tof, &keep);
+        retain_by(&mut self.tot, &keep);
+        retain_by(&mut self.n_hits, &keep);
+        retain_by(&mut self.chip_id, &keep);
+        if self.has_energy() {
+            retain_by(&mut self.energy_kev, &keep);
+        }
+        if self.has_features() {
+            retain_by(&mut self.features, &keep);
+        }
+        retain_by(&mut self.event_class, &keep);
+    }
+
     /// Append a single neutron to the batch.
     pub fn push(&mut self, neutron: Neutron) {
         self.x.push(neutron.x);
@@ -162,6 +273,9 @@ impl NeutronBatch {
     }
 
     /// Append all neutrons from another batch.
+    ///
+    /// The batch keeps its super-resolution factor, taking `other`'s when it
+    /// has none.
     pub fn append(&mut self, other: &NeutronBatch) {
         self.x.extend_from_slice(&other.x);
         self.y.extend_from_slice(&other.y);
@@ -169,9 +283,16 @@ impl NeutronBatch {
         self.tot.extend_from_slice(&other.tot);
         self.n_hits.extend_from_slice(&other.n_hits);
         self.chip_id.extend_from_slice(&other.chip_id);
+        self.energy_kev.extend_from_slice(&other.energy_kev);
+        self.event_class.extend_from_slice(&other.event_class);
+        self.features.extend_from_slice(&other.features);
+        self.super_resolution_factor = self
+            .super_resolution_factor
+            .or(other.super_resolution_factor);
     }
 
-    /// Clear all neutron data from the batch.
+    /// Clear all neutron data from the batch, keeping its super-resolution
+    /// factor.
     pub fn clear(&mut self) {
         self.x.clear();
         self.y.clear();
@@ -179,9 +300,17 @@ impl NeutronBatch {
         self.tot.clear();
         self.n_hits.clear();
         self.chip_id.clear();
+        self.energy_kev.clear();
+        self.event_class.clear();
+        self.features.clear();
     }
 }
 
+fn retain_by<T>(column: &mut Vec<T>, keep: &[bool]) {
+    let mut flags = keep.iter();
+    column.retain(|_| flags.next().copied().unwrap_or(false));
+}
+
 impl NeutronStatistics {
     /// Calculate statistics from a slice of neutrons.
     pub fn from_neutrons(neutrons: &[Neutron]) -> Self {
@@ -265,6 +394,32 @@ mod tests {
         assert!((py - 200.0).abs() < f64::EPSILON);
     }
 
+    #[test]
+    fn test_batch_super_resolution() {
+        let mut batch = NeutronBatch::default();
+        batch.push(Neutron::new(800.0, 1600.0, 0, 0, 1, 0));
+        assert_eq!(batch.pixel_coords(0, 4.0), (200.0, 400.0));
+        assert!(batch.check_super_resolution(8.0).is_ok());
+
+        let mut extracted = NeutronBatch {
+            super_resolution_factor: Some(8.0),
+            ..NeutronBatch::default()
+        };
+        extracted.append(&batch);
+        assert_eq!(extracted.pixel_coords(0, 4.0), (100.0, 200.0));
+        assert!(extracted.check_super_resolution(8.0).is_ok());
+        assert!(matches!(
+            extracted.check_super_resolution(2.0),
+            Err(ExtractionError::SuperResolutionMismatch {
+                expected: 2.0,
+                found: 8.0,
+            })
+        ));
+
+        batch.append(&extracted);
+        assert_eq!(batch.super_resolution_factor, Some(8.0));
+    }
+
     #[test]
     fn test_cluster_size_category() {
         assert_eq!(
@@ -297,4 +452,23 @@ mod tests {
         assert!((stats.mean_tof - 1005.0).abs() < 0.01);
         assert!((stats.single_hit_fraction - 1.0 / 3.0).abs() < 0.01);
     }
+
+    #[test]
+    fn test_retain_classes() {
+        let mut batch = NeutronBatch::default();
+        for i in 0..3 {
+            batch.push(Neutron::new(f64::from(i), 0.0, i, 10, 1, 0));
+        }
+        let mut unclassified = batch.clone();
+        unclassified.retain_classes(&[EventClass::Gamma]);
+        assert_eq!(unclassified.len(), 3);
+        assert!(unclassified.class_counts().is_none());
+
+        batch.event_class = vec![EventClass::Neutron, EventClass::Gamma, EventClass::Neutron];
+        assert_eq!(batch.class_counts().unwrap().neutron, 2);
+        batch.retain_classes(&[EventClass::Neutron]);
+        assert_eq!(batch.tof, vec![0, 2]);
+        assert_eq!(batch.event_class.len(), 2);
+        assert!(batch.energy_kev.is_empty());
+    }
 }
@@ -0,0 +1,261 @@
+//! Pulse-resolved grouping of events.
+//!
+//! A [`PulseIndex`] records the start time of every pulse of a run, the
+//! extended TDC timestamp in 25 ns ticks (`event_time_zero` in `NeXus`
+//! terms), and numbers the pulses from 0 in time order. Readers build it from
+//! the TDC packets of a file before decoding, so pulse numbers stay stable
+//! when a pulse has no hits. Hits and neutrons are mapped to their pulse by
+//! the start time of the batch they were decoded in; per-event pulse numbers
+//! can then be grouped back into pulses with [`PulseIndex::pulses`].
+//!
+//! An [`EventIndex`] partitions a written event list by pulse the way
+//! `NXevent_data` does, with one `event_time_zero` and `event_index` entry
+//! per pulse.
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::ops::Range;
+
+/// Start times of the pulses of a run, numbered in time order.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct PulseIndex {
+    start_times_25ns: Vec<u64>,
+}
+
+impl PulseIndex {
+    /// Index of the pulses starting at `start_times_25ns`, in any order;
+    /// repeated times are one pulse.
+    #[must_use]
+    pub fn new(mut start_times_25ns: Vec<u64>) -> Self {
+        start_times_25ns.sort_unstable();
+        start_times_25ns.dedup();
+        Self { start_times_25ns }
+    }
+
+    /// Number of pulses.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.start_times_25ns.len()
+    }
+
+    /// Whether no pulse was recorded.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.start_times_25ns.is_empty()
+    }
+
+    /// Start times of the pulses in 25 ns ticks, in pulse order.
+    #[must_use]
+    pub fn start_times_25ns(&self) -> &[u64] {
+        &self.start_times_25ns
+    }
+
+    /// Start time of pulse `id` in 25 ns ticks.
+    #[must_use]
+    pub fn start_time_25ns(&self, id: u32) -> Option<u64> {
+        self.start_times_25ns
+            .get(usize::try_from(id).ok()?)
+            .copied()
+    }
+
+    /// Number of the pulse starting at `start_time_25ns`.
+    #[must_use]
+    pub fn id_of(&self, start_time_25ns: u64) -> Option<u32> {
+        let index = self.start_times_25ns.binary_search(&start_time_25ns).ok()?;
+        u32::try_from(index).ok()
+    }
+
+    /// Number of the pulse open at `time_25ns`: the last one starting at or
+    /// before it.
+    #[must_use]
+    pub fn pulse_at(&self, time_25ns: u64) -> Option<u32> {
+        let after = self
+            .start_times_25ns
+            .partition_point(|&start| start <= time_25ns);
+        u32::try_from(after.checked_sub(1)?).ok()
+    }
+
+    /// Iterates over the pulses of events numbered by `pulse_ids`, one item
+    /// per run of consecutive events of the same pulse.
+    #[must_use]
+    pub fn pulses<'a>(&'a self, pulse_ids: &'a [u32]) -> Pulses<'a> {
+        Pulses {
+            index: self,
+            pulse_ids,
+            position: 0,
+        }
+    }
+}
+
+/// Events of one pulse.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Pulse {
+    /// Pulse number.
+    pub id: u32,
+    /// Start time in 25 ns ticks, if the pulse is in the index.
+    pub start_time_25ns: Option<u64>,
+    /// Range of the pulse's events in the batch.
+    pub events: Range<usize>,
+}
+
+/// Iterator over the pulses of a batch, from [`PulseIndex::pulses`].
+#[derive(Clone, Debug)]
+pub struct Pulses<'a> {
+    index: &'a PulseIndex,
+    pulse_ids: &'a [u32],
+    position: usize,
+}
+
+impl Iterator for Pulses<'_> {
+    type Item = Pulse;
+
+    fn next(&mut self) -> Option<Pulse> {
+        let start = self.position;
+        let id = *self.pulse_ids.get(start)?;
+        let len = self.pulse_ids[start..]
+            .iter()
+            .take_while(|&&other| other == id)
+            .count();
+        self.position = start + len;
+        Some(Pulse {
+            id,
+            start_time_25ns: self.index.start_time_25ns(id),
+            events: start..self.position,
+        })
+    }
+}
+
+/// Partition of an event list by pulse: pulse `i` starts at
+/// `event_time_zero[i]` and owns the events from `event_index[i]` up to the
+/// next pulse's index (or the end of the list).
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct EventIndex {
+    time_zero_25ns: Vec<u64>,
+    first_event: Vec<u64>,
+    events: u64,
+}
+
+impl EventIndex {
+    /// Empty index.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Append `events` events of the pulse starting at `start_time_25ns`.
+    ///
+    /// Events of the last pulse may arrive in several calls; they extend it
+    /// instead of starting another pulse. A pulse without events still gets
+    /// its entry, with the same index as the next one.
+    pub fn push(&mut self, start_time_25ns: u64, events: usize) {
+        if self.time_zero_25ns.last() != Some(&start_time_25ns) {
+            self.time_zero_25ns.push(start_time_25ns);
+            self.first_event.push(self.events);
+        }
+        self.events += events as u64;
+    }
+
+    /// Number of pulses.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.time_zero_25ns.len()
+    }
+
+    /// Whether no pulse was recorded.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.time_zero_25ns.is_empty()
+    }
+
+    /// Total number of events.
+    #[must_use]
+    pub fn event_count(&self) -> u64 {
+        self.events
+    }
+
+    /// Start times of the pulses in 25 ns ticks, in list order.
+    #[must_use]
+    pub fn event_time_zero_25ns(&self) -> &[u64] {
+        &self.time_zero_25ns
+    }
+
+    /// Index of the first event of each pulse.
+    #[must_use]
+    pub fn event_index(&self) -> &[u64] {
+        &self.first_event
+    }
+
+    /// Range of the events of pulse `pulse` in the list.
+    #[must_use]
+    pub fn events(&self, pulse: usize) -> Option<Range<u64>> {
+        let start = *self.first_event.get(pulse)?;
+        let end = self
+            .first_event
+            .get(pulse + 1)
+            .copied()
+            .unwrap_or(self.events);
+        Some(start..end)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pulse_numbers_and_grouping() {
+        let index = PulseIndex::new(vec![3000, 1000, 2000, 2000]);
+        assert_eq!(index.start_times_25ns(), &[1000, 2000, 3000]);
+        assert_eq!(index.id_of(2000), Some(1));
+        assert_eq!(index.id_of(2500), None);
+        assert_eq!(index.pulse_at(2500), Some(1));
+        assert_eq!(index.pulse_at(999), None);
+        assert_eq!(index.start_time_25ns(2), Some(3000));
+        assert_eq!(index.start_time_25ns(3), None);
+
+        let pulse_ids = [0, 0, 2, 2, 2, 7];
+        let pulses: Vec<Pulse> = index.pulses(&pulse_ids).collect();
+        assert_eq!(
+            pulses,
+            vec![
+                Pulse {
+                    id: 0,
+                    start_time_25ns: Some(1000),
+                    events: 0..2,
+                },
+                Pulse {
+                    id: 2,
+                    start_time_25ns: Some(3000),
+                    events: 2..5,
+                },
+                Pulse {
+                    id: 7,
+                    start_time_25ns: None,
+                    events: 5..6,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_event_index_partitions_by_pulse() {
+        let mut index = EventIndex::new();
+        assert!(index.is_empty());
+        index.push(1000, 3);
+        index.push(2000, 0);
+        index.push(3000, 2);
+        index.push(3000, 1);
+        index.push(4000, 0);
+
+        assert_eq!(index.len(), 4);
+        assert_eq!(index.event_time_zero_25ns(), &[1000, 2000, 3000, 4000]);
+        assert_eq!(index.event_index(), &[0, 3, 3, 6]);
+        assert_eq!(index.event_count(), 6);
+        assert_eq!(index.events(0), Some(0..3));
+        assert_eq!(index.events(1), Some(3..3));
+        assert_eq!(index.events(2), Some(3..6));
+        assert_eq!(index.events(3), Some(6..6));
+        assert_eq!(index.events(4), None);
+    }
+}
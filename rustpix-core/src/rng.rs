@@ -0,0 +1,74 @@
+//! Small seeded random number generator.
+//!
+//! Synthetic data generators and tests only need reproducible, roughly
+//! uniform numbers, so the crates share this `SplitMix64` instead of
+//! depending on `rand`.
+
+/// `SplitMix64` generator: a 64-bit state advanced by a fixed increment and
+/// scrambled on output.
+#[derive(Clone, Debug)]
+pub struct SplitMix64 {
+    state: u64,
+}
+
+impl SplitMix64 {
+    /// Generator seeded with `seed`; equal seeds give equal sequences.
+    #[must_use]
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    /// Next 64 random bits.
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform in `0..n` (`0` when `n` is zero).
+    pub fn below<T>(&mut self, n: T) -> T
+    where
+        T: Copy + Into<u64> + TryFrom<u64>,
+    {
+        let value = self.next_u64() % n.into().max(1);
+        // `value < n` whenever `n > 0`, so the conversion cannot fail.
+        T::try_from(value).unwrap_or(n)
+    }
+
+    /// Uniform in (0, 1].
+    pub fn unit(&mut self) -> f64 {
+        let bits = u32::try_from(self.next_u64() >> 32).unwrap_or(u32::MAX);
+        (f64::from(bits) + 1.0) / (f64::from(u32::MAX) + 1.0)
+    }
+
+    /// Exponential with the given mean.
+    pub fn exponential(&mut self, mean: f64) -> f64 {
+        -self.unit().ln() * mean
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reference_sequence() {
+        let mut rng = SplitMix64::new(0);
+        assert_eq!(rng.next_u64(), 0xE220_A839_7B1D_CDAF);
+        assert_eq!(rng.next_u64(), 0x6E78_9E6A_A1B9_65F4);
+    }
+
+    #[test]
+    fn test_ranges() {
+        let mut rng = SplitMix64::new(42);
+        for _ in 0..1_000 {
+            assert!(rng.below(7u16) < 7);
+            assert_eq!(rng.below(0u8), 0);
+            let unit = rng.unit();
+            assert!(unit > 0.0 && unit <= 1.0);
+            assert!(rng.exponential(3.0) >= 0.0);
+        }
+    }
+}
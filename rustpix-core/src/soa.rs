@@ -3,7 +3,13 @@
 //! This module defines the `HitBatch` structure which stores hit data
 //! in parallel vectors (`SoA` layout) rather than an array of structs (`AoS`).
 //! This layout works better with modern CPU caches and SIMD instructions.
+//!
+//! `HitBatch` is the container every processing stage takes. Code that holds
+//! hits one at a time as [`HitRecord`] tuples converts with
+//! [`HitBatch::from_records`] (or `collect()`) and back with
+//! [`HitBatch::records`].
 
+use crate::error::ProcessingError;
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
 
@@ -25,11 +31,27 @@ pub struct HitBatch {
     pub chip_id: Vec<u8>,
     /// Cluster assignments (output of clustering).
     pub cluster_id: Vec<i32>,
+    /// Chip-local X before the chip transform.
+    ///
+    /// The raw columns are only filled when the decoder was asked to retain
+    /// raw hit fields; they are empty otherwise.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub raw_x: Vec<u16>,
+    /// Chip-local Y before the chip transform.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub raw_y: Vec<u16>,
+    /// Coarse `ToA` as read from the packet (25 ns units), before rollover
+    /// and `ToA` corrections.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub raw_toa: Vec<u32>,
 }
 
 /// Tuple-based hit representation used to push into a batch without `AoS` storage.
 pub type HitRecord = (u16, u16, u32, u16, u32, u8);
 
+/// Raw fields of a hit: chip-local x, chip-local y and uncorrected `ToA`.
+pub type RawHitFields = (u16, u16, u32);
+
 impl HitBatch {
     /// Creates a new empty batch with specified capacity.
     #[must_use]
@@ -42,7 +64,83 @@ impl HitBatch {
             timestamp: Vec::with_capacity(capacity),
             chip_id: Vec::with_capacity(capacity),
             cluster_id: Vec::with_capacity(capacity),
+            raw_x: Vec::new(),
+            raw_y: Vec::new(),
+            raw_toa: Vec::new(),
+        }
+    }
+
+    /// Builds a batch from `AoS` hit records, leaving every hit unclustered.
+    #[must_use]
+    pub fn from_records(records: &[HitRecord]) -> Self {
+        records.iter().copied().collect()
+    }
+
+    /// Builds a batch from equal-length columns, leaving every hit
+    /// unclustered.
+    ///
+    /// # Errors
+    /// Returns [`ProcessingError::Config`] if the columns differ in length.
+    pub fn from_columns(
+        x: Vec<u16>,
+        y: Vec<u16>,
+        tof: Vec<u32>,
+        tot: Vec<u16>,
+        timestamp: Vec<u32>,
+        chip_id: Vec<u8>,
+    ) -> Result<Self, ProcessingError> {
+        let len = x.len();
+        let lengths = [
+            y.len(),
+            tof.len(),
+            tot.len(),
+            timestamp.len(),
+            chip_id.len(),
+        ];
+        if lengths.iter().any(|&other| other != len) {
+            return Err(ProcessingError::Config(format!(
+                "hit columns differ in length: x has {len}, others {lengths:?}"
+            )));
         }
+        Ok(Self {
+            x,
+            y,
+            tof,
+            tot,
+            timestamp,
+            chip_id,
+            cluster_id: vec![-1; len],
+            ..Self::default()
+        })
+    }
+
+    /// Returns hit `index` as a record, or `None` past the end.
+    #[must_use]
+    pub fn get(&self, index: usize) -> Option<HitRecord> {
+        (index < self.len()).then(|| self.record(index))
+    }
+
+    /// Iterates over the hits as records, in order.
+    #[must_use]
+    pub fn records(&self) -> impl ExactSizeIterator<Item = HitRecord> + '_ {
+        (0..self.len()).map(|index| self.record(index))
+    }
+
+    /// Copies the hits out as `AoS` records.
+    #[must_use]
+    pub fn to_records(&self) -> Vec<HitRecord> {
+        self.records().collect()
+    }
+
+    fn record(&self, index: usize) -> HitRecord {
+        (
+            self.x[index],
+            self.y[index],
+            self.tof[index],
+            self.tot[index],
+            self.timestamp[index],
+            self.chip_id[index],
+        )
     }
 
     /// Returns the number of hits in the batch.
@@ -57,6 +155,12 @@ impl HitBatch {
         self.x.is_empty()
     }
 
+    /// Returns true when the batch carries raw hit fields.
+    #[must_use]
+    pub fn has_raw(&self) -> bool {
+        !self.raw_toa.is_empty()
+    }
+
     /// Clears all vectors in the batch.
     pub fn clear(&mut self) {
         self.x.clear();
@@ -66,6 +170,9 @@ impl HitBatch {
         self.timestamp.clear();
         self.chip_id.clear();
         self.cluster_id.clear();
+        self.raw_x.clear();
+        self.raw_y.clear();
+        self.raw_toa.clear();
     }
 
     /// Appends all hits from another batch to this one.
@@ -77,6 +184,9 @@ impl HitBatch {
         self.timestamp.extend_from_slice(&other.timestamp);
         self.chip_id.extend_from_slice(&other.chip_id);
         self.cluster_id.extend_from_slice(&other.cluster_id);
+        self.raw_x.extend_from_slice(&other.raw_x);
+        self.raw_y.extend_from_slice(&other.raw_y);
+        self.raw_toa.extend_from_slice(&other.raw_toa);
     }
 
     /// Pushes a single hit into the batch.
@@ -91,6 +201,27 @@ impl HitBatch {
         self.cluster_id.push(-1); // Default unclustered
     }
 
+    /// Pushes a single hit together with its raw fields.
+    pub fn push_with_raw(&mut self, hit: HitRecord, raw: RawHitFields) {
+        self.push(hit);
+        let (raw_x, raw_y, raw_toa) = raw;
+        self.raw_x.push(raw_x);
+        self.raw_y.push(raw_y);
+        self.raw_toa.push(raw_toa);
+    }
+
+    /// Shifts assigned cluster labels by `offset`; unclustered hits stay -1.
+    ///
+    /// Labels are local to the batch they were clustered in, so use this to
+    /// keep them unique when labelled batches are concatenated or exported.
+    pub fn offset_cluster_ids(&mut self, offset: i32) {
+        for id in &mut self.cluster_id {
+            if *id >= 0 {
+                *id = id.saturating_add(offset);
+            }
+        }
+    }
+
     /// Sorts all hits by TOF, keeping columns aligned.
     pub fn sort_by_tof(&mut self) {
         let len = self.len();
@@ -109,6 +240,12 @@ impl HitBatch {
         let mut chip_id = Vec::with_capacity(len);
         let mut cluster_id = Vec::with_capacity(len);
 
+        if self.has_raw() {
+            self.raw_x = indices.iter().map(|&i| self.raw_x[i]).collect();
+            self.raw_y = indices.iter().map(|&i| self.raw_y[i]).collect();
+            self.raw_toa = indices.iter().map(|&i| self.raw_toa[i]).collect();
+        }
+
         for i in indices {
             x.push(self.x[i]);
             y.push(self.y[i]);
@@ -127,6 +264,73 @@ impl HitBatch {
         self.chip_id = chip_id;
         self.cluster_id = cluster_id;
     }
+
+    /// Keeps only the hits whose pixel `(x, y)` passes `keep`, in order.
+    ///
+    /// Returns the number of hits removed.
+    pub fn retain_pixels(&mut self, mut keep: impl FnMut(u16, u16) -> bool) -> usize {
+        let (x, y) = (&self.x, &self.y);
+        let flags: Vec<bool> = (0..self.len()).map(|i| keep(x[i], y[i])).collect();
+        self.retain_flagged(&flags)
+    }
+
+    /// Keeps only the hits whose index passes `keep`, in order.
+    ///
+    /// Returns the number of hits removed.
+    pub fn retain_hits(&mut self, keep: impl FnMut(usize) -> bool) -> usize {
+        let flags: Vec<bool> = (0..self.len()).map(keep).collect();
+        self.retain_flagged(&flags)
+    }
+
+    fn retain_flagged(&mut self, flags: &[bool]) -> usize {
+        let removed = flags.iter().filter(|&&kept| !kept).count();
+        if removed == 0 {
+            return 0;
+        }
+        retain_by(&mut self.x, flags);
+        retain_by(&mut self.y, flags);
+        retain_by(&mut self.tof, flags);
+        retain_by(&mut self.tot, flags);
+        retain_by(&mut self.timestamp, flags);
+        retain_by(&mut self.chip_id, flags);
+        retain_by(&mut self.cluster_id, flags);
+        if self.has_raw() {
+            retain_by(&mut self.raw_x, flags);
+            retain_by(&mut self.raw_y, flags);
+            retain_by(&mut self.raw_toa, flags);
+        }
+        removed
+    }
+}
+
+fn retain_by<T>(column: &mut Vec<T>, keep: &[bool]) {
+    let mut flags = keep.iter();
+    column.retain(|_| flags.next().copied().unwrap_or(false));
+}
+
+impl FromIterator<HitRecord> for HitBatch {
+    fn from_iter<I: IntoIterator<Item = HitRecord>>(iter: I) -> Self {
+        let mut batch = Self::default();
+        batch.extend(iter);
+        batch
+    }
+}
+
+impl Extend<HitRecord> for HitBatch {
+    fn extend<I: IntoIterator<Item = HitRecord>>(&mut self, iter: I) {
+        let iter = iter.into_iter();
+        let additional = iter.size_hint().0;
+        self.x.reserve(additional);
+        self.y.reserve(additional);
+        self.tof.reserve(additional);
+        self.tot.reserve(additional);
+        self.timestamp.reserve(additional);
+        self.chip_id.reserve(additional);
+        self.cluster_id.reserve(additional);
+        for hit in iter {
+            self.push(hit);
+        }
+    }
 }
 
 #[cfg(test)]
@@ -146,8 +350,68 @@ mod tests {
         batch.push((11, 21, 1001, 6, 123_457, 0));
         assert_eq!(batch.len(), 2);
 
+        batch.cluster_id[1] = 3;
+        batch.offset_cluster_ids(10);
+        assert_eq!(batch.cluster_id, vec![-1, 13]);
+
         batch.clear();
         assert!(batch.is_empty());
         assert_eq!(batch.len(), 0);
     }
+
+    #[test]
+    fn test_raw_fields_follow_sort() {
+        let mut batch = HitBatch::default();
+        batch.push_with_raw((300, 20, 2000, 5, 7, 1), (44, 20, 9_000));
+        batch.push_with_raw((10, 21, 1000, 6, 8, 0), (10, 21, 8_000));
+        assert!(batch.has_raw());
+        batch.sort_by_tof();
+        assert_eq!(batch.x, vec![10, 300]);
+        assert_eq!(batch.raw_x, vec![10, 44]);
+        assert_eq!(batch.raw_toa, vec![8_000, 9_000]);
+
+        let mut plain = HitBatch::default();
+        plain.push((1, 1, 1, 1, 1, 0));
+        plain.sort_by_tof();
+        assert!(!plain.has_raw());
+    }
+
+    #[test]
+    fn test_retain_pixels() {
+        let mut batch = HitBatch::default();
+        batch.push_with_raw((1, 2, 100, 5, 7, 0), (1, 2, 90));
+        batch.push_with_raw((3, 4, 200, 6, 8, 0), (3, 4, 190));
+        batch.push_with_raw((1, 2, 300, 7, 9, 1), (1, 2, 290));
+        batch.cluster_id = vec![0, 1, 2];
+        assert_eq!(batch.retain_pixels(|x, y| (x, y) != (1, 2)), 2);
+        assert_eq!(batch.tof, vec![200]);
+        assert_eq!(batch.cluster_id, vec![1]);
+        assert_eq!(batch.raw_toa, vec![190]);
+        assert_eq!(batch.retain_pixels(|_, _| true), 0);
+        assert_eq!(batch.len(), 1);
+    }
+
+    #[test]
+    fn test_record_conversions() {
+        let records = vec![(1, 2, 100, 5, 7, 0), (3, 4, 200, 6, 8, 1)];
+        let batch = HitBatch::from_records(&records);
+        assert_eq!(batch.len(), 2);
+        assert_eq!(batch.cluster_id, vec![-1, -1]);
+        assert_eq!(batch.get(1), Some((3, 4, 200, 6, 8, 1)));
+        assert_eq!(batch.get(2), None);
+        assert_eq!(batch.to_records(), records);
+        assert_eq!(records.iter().copied().collect::<HitBatch>(), batch);
+
+        let columns = HitBatch::from_columns(
+            vec![1, 3],
+            vec![2, 4],
+            vec![100, 200],
+            vec![5, 6],
+            vec![7, 8],
+            vec![0, 1],
+        )
+        .unwrap();
+        assert_eq!(columns, batch);
+        assert!(HitBatch::from_columns(vec![1], vec![], vec![], vec![], vec![], vec![]).is_err());
+    }
 }
@@ -0,0 +1,169 @@
+//! Recoverable oddities found while processing.
+//!
+//! Processing keeps going past data that is unusual but not fatal: neutrons
+//! that land outside the detector are dropped, pulses go missing from the
+//! TDC stream, pixels saturate their `ToT` register, and pulses too dense to
+//! cut into parallel sections are clustered on one thread. These are not
+//! errors, but they should not vanish either, so they are collected in a
+//! [`Warnings`] tally that travels with the processing output and ends up in
+//! the report. Each kind keeps a count and the first message recorded for
+//! it, so a warning repeated for every pulse costs one entry.
+
+use std::fmt;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// Kind of recoverable oddity.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum WarningKind {
+    /// Events whose coordinates fell outside the detector and were dropped.
+    OutOfBounds,
+    /// Pulses missing from the TDC stream.
+    TdcGap,
+    /// Hits whose `ToT` reached the top of the register.
+    SaturatedPixel,
+    /// Pulses that could not be cut into parallel time sections and were
+    /// clustered whole.
+    UnsectionedPulse,
+}
+
+impl WarningKind {
+    /// All kinds, in report order.
+    pub const ALL: [Self; 4] = [
+        Self::OutOfBounds,
+        Self::TdcGap,
+        Self::SaturatedPixel,
+        Self::UnsectionedPulse,
+    ];
+
+    /// Snake-case name of the kind.
+    #[must_use]
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::OutOfBounds => "out_of_bounds",
+            Self::TdcGap => "tdc_gap",
+            Self::SaturatedPixel => "saturated_pixel",
+            Self::UnsectionedPulse => "unsectioned_pulse",
+        }
+    }
+}
+
+impl fmt::Display for WarningKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Tally of one warning kind.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Warning {
+    /// What went wrong.
+    pub kind: WarningKind,
+    /// Number of affected items (events, pulses or hits).
+    pub count: u64,
+    /// First message recorded for this kind.
+    pub message: String,
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "warning[{}]: {}", self.kind, self.message)?;
+        if self.count > 1 {
+            write!(f, " ({} total)", self.count)?;
+        }
+        Ok(())
+    }
+}
+
+/// Warnings collected while processing, one entry per kind.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Warnings {
+    entries: Vec<Warning>,
+}
+
+impl Warnings {
+    /// Record `count` occurrences of `kind`.
+    ///
+    /// `message` is only called for the first occurrence of a kind; later
+    /// ones add to its count. A zero count records nothing.
+    pub fn record(&mut self, kind: WarningKind, count: u64, message: impl FnOnce() -> String) {
+        if count == 0 {
+            return;
+        }
+        if let Some(entry) = self.entries.iter_mut().find(|entry| entry.kind == kind) {
+            entry.count = entry.count.saturating_add(count);
+            return;
+        }
+        self.entries.push(Warning {
+            kind,
+            count,
+            message: message(),
+        });
+        self.entries.sort_by_key(|entry| entry.kind);
+    }
+
+    /// Add the warnings of another tally, keeping the first message of each
+    /// kind.
+    pub fn merge(&mut self, other: &Self) {
+        for entry in &other.entries {
+            self.record(entry.kind, entry.count, || entry.message.clone());
+        }
+    }
+
+    /// Whether nothing was recorded.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Count recorded for `kind`.
+    #[must_use]
+    pub fn count(&self, kind: WarningKind) -> u64 {
+        self.entries
+            .iter()
+            .find(|entry| entry.kind == kind)
+            .map_or(0, |entry| entry.count)
+    }
+
+    /// Recorded warnings in [`WarningKind::ALL`] order.
+    pub fn iter(&self) -> impl Iterator<Item = &Warning> {
+        self.entries.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_and_merge_keep_first_message() {
+        let mut warnings = Warnings::default();
+        warnings.record(WarningKind::SaturatedPixel, 0, || unreachable!());
+        assert!(warnings.is_empty());
+
+        warnings.record(WarningKind::SaturatedPixel, 3, || "3 hits".to_string());
+        warnings.record(WarningKind::SaturatedPixel, 2, || unreachable!());
+        let mut other = Warnings::default();
+        other.record(WarningKind::OutOfBounds, 1, || "1 neutron".to_string());
+        other.record(WarningKind::SaturatedPixel, 4, || "4 hits".to_string());
+        warnings.merge(&other);
+
+        assert_eq!(warnings.count(WarningKind::SaturatedPixel), 9);
+        assert_eq!(warnings.count(WarningKind::TdcGap), 0);
+        let kinds: Vec<_> = warnings.iter().map(|warning| warning.kind).collect();
+        assert_eq!(
+            kinds,
+            vec![WarningKind::OutOfBounds, WarningKind::SaturatedPixel]
+        );
+        let saturated = warnings.iter().nth(1).unwrap();
+        assert_eq!(
+            saturated.to_string(),
+            "warning[saturated_pixel]: 3 hits (9 total)"
+        );
+    }
+}
@@ -3,11 +3,13 @@
 //! Contains the `RustpixApp` struct which manages the GUI state,
 //! data, and message handling.
 
+use std::borrow::Cow;
 use std::collections::{hash_map::DefaultHasher, HashMap};
 use std::fs::{self, File as StdFile};
 use std::hash::{Hash, Hasher};
 use std::io::{BufWriter, Write};
 use std::mem::size_of;
+use std::ops::Range;
 use std::path::{Path, PathBuf};
 use std::sync::mpsc::{channel, Receiver, Sender};
 use std::sync::Arc;
@@ -25,24 +27,38 @@ use sysinfo::{get_current_pid, Pid, System};
 use crate::histogram::Hyperstack3D;
 use crate::message::{AppMessage, PulseBounds};
 use crate::pipeline::{
-    load_file_worker, run_clustering_worker, AlgorithmType, ClusteringWorkerConfig,
+    detector_fingerprint, load_file_worker, run_clustering_worker, AlgorithmType, ChipHitStats,
+    ClusterLabels, ClusteringKey, ClusteringWorkerConfig, FileMetadata, StageCache,
 };
 use crate::state::{
-    ExportFormat, Hdf5ExportOptions, ProcessingState, Statistics, TiffBitDepth, TiffExportOptions,
-    TiffSpectraTiming, TiffStackBehavior, UiState, ViewMode, ZoomMode,
+    snapshot_to_json, write_payload, AutosaveRoi, AutosaveRoiShape, AutosaveSettings,
+    AutosaveSnapshot, AutosaveState, ExportFormat, Hdf5ExportOptions, ProcessingState, Statistics,
+    TiffBitDepth, TiffExportOptions, TiffSpectraTiming, TiffStackBehavior, TourState, UiState,
+    ViewMode, ZoomMode, AUTOSAVE_INTERVAL_SECS,
 };
+use crate::ui::{FourierCache, RoiGateThumbnail};
 use crate::util::{
     f64_to_usize_bounded, sanitize_export_base_name, u64_to_f64, usize_to_f32, usize_to_f64,
 };
-use crate::viewer::{generate_histogram_image_transformed, Colormap, Roi, RoiShape, RoiState};
+use crate::viewer::{
+    generate_float_image_transformed, generate_histogram_rows_scaled, Colormap, CustomLut, Roi,
+    RoiMaskExport, RoiShape, RoiState, TextureRefresh,
+};
+use rustpix_algorithms::{find_tof_hot_pixels, Connectivity, TofHotPixels};
+use rustpix_core::classification::{EventClass, EventClassifier};
+use rustpix_core::extraction::validate_super_resolution;
 use rustpix_core::neutron::NeutronBatch;
 use rustpix_core::soa::HitBatch;
 use rustpix_io::hdf5::{
-    write_combined_hdf5_batches, HistogramShape, HistogramWriteData, HistogramWriteOptions,
-    HitWriteOptions, NeutronEventBatch, NeutronWriteOptions, PixelMaskWriteData,
-    PixelMaskWriteOptions,
+    append_hdf5_event_batches, write_combined_hdf5_batches, HistogramShape, HistogramWriteData,
+    HistogramWriteOptions, HitWriteOptions, NeutronEventBatch, NeutronWriteOptions,
+    PixelMaskWriteData, PixelMaskWriteOptions,
+};
+use rustpix_io::{
+    write_fits_cube, write_fits_image, EventBatch, FitsOptions, FitsValue, MacroExport,
+    MacroExportFormat, MacroParameters, PixelMask, PixelMaskDetection,
 };
-use rustpix_io::EventBatch;
+use rustpix_tpx::instrument::{self, InstrumentProfile};
 use rustpix_tpx::DetectorConfig;
 use tiff::encoder::colortype::{Gray16, Gray32};
 use tiff::encoder::TiffEncoder as TiffFileEncoder;
@@ -64,12 +80,23 @@ pub(crate) struct PixelMaskData {
     pub hot_points: Vec<[f64; 2]>,
     pub dead_count: usize,
     pub hot_count: usize,
+    /// Pixels that are dead, hot or both.
+    pub excluded_count: usize,
+    /// Pixels hot only in some TOF bins, when per-TOF detection is on.
+    pub tof_hot: Option<TofHotPixels>,
     pub mean: f64,
     pub std_dev: f64,
     pub hot_sigma: f64,
     pub hot_threshold: f64,
 }
 
+impl PixelMaskData {
+    /// Whether the pixel at `idx` (`y * width + x`) is dead or hot.
+    pub(crate) fn is_masked(&self, idx: usize) -> bool {
+        self.dead_mask[idx] != 0 || self.hot_mask[idx] != 0
+    }
+}
+
 #[derive(Clone)]
 pub(crate) struct RoiSpectrumEntry {
     pub data: RoiSpectrumData,
@@ -105,7 +132,8 @@ struct RoiSpectrumPending {
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub(crate) enum DetectorProfileKind {
-    Venus,
+    /// A shipped instrument profile, by registry name.
+    Instrument(&'static str),
     Custom,
 }
 
@@ -120,7 +148,7 @@ pub(crate) struct DetectorProfile {
 impl Default for DetectorProfile {
     fn default() -> Self {
         Self {
-            kind: DetectorProfileKind::Venus,
+            kind: DetectorProfileKind::Instrument("venus"),
             custom_name: None,
             custom_path: None,
             custom_config: None,
@@ -131,7 +159,9 @@ impl Default for DetectorProfile {
 impl DetectorProfile {
     pub(crate) fn label(&self) -> String {
         match self.kind {
-            DetectorProfileKind::Venus => "VENUS (SNS)".to_string(),
+            DetectorProfileKind::Instrument(name) => {
+                instrument::find(name).map_or_else(|| name.to_string(), InstrumentProfile::label)
+            }
             DetectorProfileKind::Custom => self
                 .custom_name
                 .clone()
@@ -197,6 +227,12 @@ pub struct RustpixApp {
     pub(crate) dbscan_min_points: usize,
     /// Grid cell size (pixels) for grid clustering.
     pub(crate) grid_cell_size: usize,
+    /// Largest box gap (pixels) of clusters merged by hierarchical clustering.
+    pub(crate) hierarchical_merge_distance: f64,
+    /// Largest TOF gap (ns) of clusters merged by hierarchical clustering.
+    pub(crate) hierarchical_merge_window_ns: f64,
+    /// Pixel neighbourhood of connected-component clustering.
+    pub(crate) connectivity: Connectivity,
 
     /// Loaded hit batch data.
     pub(crate) hit_batch: Option<Arc<HitBatch>>,
@@ -237,10 +273,18 @@ pub struct RustpixApp {
     neutron_super_resolution_factor: f64,
     /// Super-resolution factor captured when clustering starts.
     processing_super_resolution_factor: f64,
+    /// Cluster labels reused when only extraction parameters change.
+    stage_cache: StageCache,
     /// Whether to weight extraction by TOT.
     pub(crate) weighted_by_tot: bool,
     /// Minimum TOT threshold for extraction.
     pub(crate) min_tot_threshold: u16,
+    /// Classifier labelling neutron, gamma and noise events during extraction.
+    pub(crate) event_classifier: Option<Arc<EventClassifier>>,
+    /// File name of the loaded classifier.
+    pub(crate) event_classifier_name: Option<String>,
+    /// Event class shown in the neutron view (None = all events).
+    pub(crate) neutron_class_filter: Option<EventClass>,
     /// UI display state.
     pub(crate) ui_state: UiState,
     /// ROI session state.
@@ -251,6 +295,8 @@ pub struct RustpixApp {
     roi_spectra_neutrons: RoiSpectraCache,
     /// Pending debounce state for ROI spectrum updates.
     roi_spectrum_pending: Option<RoiSpectrumPending>,
+    /// Thumbnails of TOF-gated ROIs, by ROI id.
+    pub(crate) roi_gate_thumbnails: HashMap<usize, RoiGateThumbnail>,
     /// Revision counter for hit hyperstack data changes.
     pub(crate) hit_data_revision: u64,
     /// Revision counter for neutron hyperstack data changes.
@@ -265,29 +311,67 @@ pub struct RustpixApp {
     pub(crate) processing: ProcessingState,
     /// Session statistics.
     pub(crate) statistics: Statistics,
+    /// Acquisition metadata of the open file.
+    pub(crate) file_metadata: Option<FileMetadata>,
+    /// Per-chip hit and saturation counts of the loaded file.
+    pub(crate) chip_hit_stats: Option<ChipHitStats>,
+    /// Cached dead/hot pixel map for the health dashboard.
+    pub(crate) health_map_texture: Option<egui::TextureHandle>,
+    /// Cached power spectrum / autocorrelation of the summed image.
+    pub(crate) fourier_cache: Option<FourierCache>,
 
     /// Cached histogram texture.
     pub(crate) texture: Option<egui::TextureHandle>,
+    /// Schedules band-wise refreshes of `texture` after restyling.
+    pub(crate) texture_refresh: TextureRefresh,
+    /// Top of the color scale of the running texture refresh pass.
+    pub(crate) texture_max_count: u64,
     /// Current colormap selection.
     pub(crate) colormap: Colormap,
+    /// Lookup tables imported this session, offered next to the built-in colormaps.
+    pub(crate) imported_luts: Vec<CustomLut>,
     /// Cached dead/hot pixel masks for hits view.
     pub(crate) pixel_masks: Option<PixelMaskData>,
     /// Hot pixel sigma threshold.
     pub(crate) hot_pixel_sigma: f64,
+    /// TOF bins judged together for pixels hot in part of the TOF range
+    /// (0 = off).
+    pub(crate) tof_hot_window: usize,
+    /// Pixel mask file used instead of detecting masks, with its contents.
+    pub(crate) loaded_pixel_mask: Option<(PathBuf, PixelMask)>,
     /// Detector configuration profile state.
     pub(crate) detector_profile: DetectorProfile,
     /// Memory telemetry for status bar display.
     memory_telemetry: MemoryTelemetry,
+    /// Crash-recovery autosave state.
+    pub(crate) autosave: AutosaveState,
+    /// Run clustering when the file given on the command line has loaded.
+    pub(crate) startup_auto_process: bool,
+    /// View requested on the command line, shown once its data is ready.
+    pub(crate) startup_view: Option<ViewMode>,
 }
 
 impl Default for RustpixApp {
     fn default() -> Self {
+        Self::with_autosave(AutosaveState::default())
+    }
+}
+
+impl RustpixApp {
+    /// Application without autosaving, for headless rendering: it neither
+    /// locks an autosave file nor looks for crashed sessions.
+    pub(crate) fn headless() -> Self {
+        Self::with_autosave(AutosaveState::disabled())
+    }
+
+    fn with_autosave(autosave: AutosaveState) -> Self {
         let (tx, rx) = channel();
         let mut ui_state = UiState::default();
         ui_state.spectrum.full_fov_visible = true;
         ui_state.pixel_health.show_hot_pixels = true;
         ui_state.pixel_health.exclude_masked_pixels = true;
         ui_state.cache.cache_hits_in_memory = true;
+        ui_state.tour = TourState::for_first_run();
         Self {
             selected_file: None,
             algo_type: AlgorithmType::Abs, // Default to ABS per design doc
@@ -297,6 +381,9 @@ impl Default for RustpixApp {
             max_cluster_size: None,
             dbscan_min_points: 2,
             grid_cell_size: 32,
+            hierarchical_merge_distance: 10.0,
+            hierarchical_merge_window_ns: 150.0,
+            connectivity: Connectivity::Eight,
 
             hit_batch: None,
             hit_pulse_bounds: None,
@@ -318,13 +405,18 @@ impl Default for RustpixApp {
             super_resolution_factor: 1.0,
             neutron_super_resolution_factor: 1.0,
             processing_super_resolution_factor: 1.0,
+            stage_cache: StageCache::default(),
             weighted_by_tot: false,
             min_tot_threshold: 0,
+            event_classifier: None,
+            event_classifier_name: None,
+            neutron_class_filter: None,
             ui_state,
             roi_state: RoiState::default(),
             roi_spectra_hits: RoiSpectraCache::default(),
             roi_spectra_neutrons: RoiSpectraCache::default(),
             roi_spectrum_pending: None,
+            roi_gate_thumbnails: HashMap::new(),
             hit_data_revision: 0,
             neutron_data_revision: 0,
             rx,
@@ -332,21 +424,32 @@ impl Default for RustpixApp {
 
             processing: ProcessingState::default(),
             statistics: Statistics::default(),
+            file_metadata: None,
+            chip_hit_stats: None,
+            health_map_texture: None,
+            fourier_cache: None,
 
             texture: None,
+            texture_refresh: TextureRefresh::default(),
+            texture_max_count: 1,
             colormap: Colormap::Grayscale,
+            imported_luts: Vec::new(),
             pixel_masks: None,
             hot_pixel_sigma: 5.0,
+            tof_hot_window: 0,
+            loaded_pixel_mask: None,
             detector_profile: DetectorProfile::default(),
             memory_telemetry: MemoryTelemetry::new(),
+            autosave,
+            startup_auto_process: false,
+            startup_view: None,
         }
     }
-}
 
-impl RustpixApp {
     /// Load a file asynchronously.
     pub fn load_file(&mut self, path: PathBuf) {
         self.reset_load_state(path.as_path());
+        self.ui_state.history.record_load(&path);
 
         let tx = self.tx.clone();
         let detector_config = self.current_detector_config();
@@ -411,16 +514,24 @@ impl RustpixApp {
         self.roi_spectra_hits = RoiSpectraCache::default();
         self.roi_spectra_neutrons = RoiSpectraCache::default();
         self.roi_spectrum_pending = None;
+        self.roi_gate_thumbnails.clear();
         self.hit_data_revision = self.hit_data_revision.wrapping_add(1);
         self.neutron_data_revision = self.neutron_data_revision.wrapping_add(1);
         self.texture = None;
         self.statistics.clear();
+        self.file_metadata = None;
+        self.chip_hit_stats = None;
         self.pixel_masks = None;
+        self.health_map_texture = None;
+        self.fourier_cache = None;
+        self.stage_cache.invalidate();
     }
 
     /// Cancel the current loading or processing operation.
     pub fn cancel_operation(&mut self) {
         self.processing.request_cancel();
+        self.startup_auto_process = false;
+        self.startup_view = None;
         if self.processing.is_loading {
             self.processing.is_loading = false;
             self.processing.status_text = "Load cancelled".to_string();
@@ -439,9 +550,33 @@ impl RustpixApp {
             self.processing.status_text.clear();
             self.processing.status_text.push_str("Clustering...");
             self.processing_super_resolution_factor = self.super_resolution_factor;
+            let parameters = self.macro_parameters();
+            self.ui_state.history.record_process(parameters);
 
             let tx = self.tx.clone();
             let algo_type = self.algo_type;
+            let detector_config = self.current_detector_config();
+            let clustering_key = ClusteringKey {
+                path: path.clone(),
+                detector_fingerprint: detector_fingerprint(&detector_config),
+                algorithm: algo_type,
+                radius: self.radius,
+                temporal_window_ns: self.temporal_window_ns,
+                min_cluster_size: self.min_cluster_size,
+                max_cluster_size: self.max_cluster_size,
+                dbscan_min_points: self.dbscan_min_points,
+                grid_cell_size: self.grid_cell_size,
+                hierarchical_merge_distance: self.hierarchical_merge_distance,
+                hierarchical_merge_window_ns: self.hierarchical_merge_window_ns,
+                connectivity: self.connectivity,
+            };
+            let cached_labels = self.stage_cache.labels_for(&clustering_key);
+            if cached_labels.is_some() {
+                self.processing.status_text.clear();
+                self.processing
+                    .status_text
+                    .push_str("Extracting (reusing cluster labels)...");
+            }
             let config = ClusteringWorkerConfig {
                 radius: self.radius,
                 temporal_window_ns: self.temporal_window_ns,
@@ -449,15 +584,21 @@ impl RustpixApp {
                 max_cluster_size: self.max_cluster_size,
                 dbscan_min_points: self.dbscan_min_points,
                 grid_cell_size: self.grid_cell_size,
-                detector_config: self.current_detector_config(),
+                hierarchical_merge_distance: self.hierarchical_merge_distance,
+                hierarchical_merge_window_ns: self.hierarchical_merge_window_ns,
+                connectivity: self.connectivity,
+                detector_config,
                 super_resolution_factor: self.super_resolution_factor,
                 weighted_by_tot: self.weighted_by_tot,
                 min_tot_threshold: self.min_tot_threshold,
+                event_classifier: self.event_classifier.clone(),
                 total_hits: self
                     .hit_batch
                     .as_ref()
                     .map_or(self.statistics.hit_count, |batch| batch.len()),
                 cancel_flag: self.processing.cancel_flag_clone(),
+                clustering_key,
+                cached_labels,
             };
 
             thread::spawn(move || run_clustering_worker(&path, &tx, algo_type, &config));
@@ -465,7 +606,7 @@ impl RustpixApp {
     }
 
     /// Get the active hyperstack based on view mode.
-    fn active_hyperstack(&self) -> Option<&Hyperstack3D> {
+    pub(crate) fn active_hyperstack(&self) -> Option<&Hyperstack3D> {
         match self.ui_state.view_mode {
             ViewMode::Hits => self.hyperstack.as_deref(),
             ViewMode::Neutrons => self.neutron_hyperstack.as_deref(),
@@ -473,7 +614,7 @@ impl RustpixApp {
     }
 
     /// Get the active 2D projection based on view mode.
-    fn active_counts(&self) -> Option<&[u64]> {
+    pub(crate) fn active_counts(&self) -> Option<&[u64]> {
         match self.ui_state.view_mode {
             ViewMode::Hits => self.hit_counts.as_deref(),
             ViewMode::Neutrons => self.neutron_counts.as_deref(),
@@ -608,34 +749,293 @@ impl RustpixApp {
         }
     }
 
+    /// Capture ROIs and settings for crash recovery.
+    pub(crate) fn autosave_snapshot(&self) -> AutosaveSnapshot {
+        let settings = AutosaveSettings {
+            algo_type: self.algo_type,
+            radius: self.radius,
+            temporal_window_ns: self.temporal_window_ns,
+            min_cluster_size: self.min_cluster_size,
+            max_cluster_size: self.max_cluster_size,
+            dbscan_min_points: self.dbscan_min_points,
+            grid_cell_size: self.grid_cell_size,
+            hierarchical_merge_distance: self.hierarchical_merge_distance,
+            hierarchical_merge_window_ns: self.hierarchical_merge_window_ns,
+            connectivity: self.connectivity,
+            tdc_frequency: self.tdc_frequency,
+            flight_path_m: self.flight_path_m,
+            tof_offset_ns: self.tof_offset_ns,
+            hit_tof_bins: self.hit_tof_bins,
+            neutron_tof_bins: self.neutron_tof_bins,
+            super_resolution_factor: self.super_resolution_factor,
+            weighted_by_tot: self.weighted_by_tot,
+            min_tot_threshold: self.min_tot_threshold,
+            hot_pixel_sigma: self.hot_pixel_sigma,
+            tof_hot_window: self.tof_hot_window,
+            pixel_mask_file: self
+                .loaded_pixel_mask
+                .as_ref()
+                .map(|(path, _)| path.clone()),
+            colormap: self.colormap.clone(),
+            log_scale: self.ui_state.histogram.log_scale,
+            transform: self.ui_state.histogram_view.transform,
+        };
+        let rois = self
+            .roi_state
+            .rois
+            .iter()
+            .map(|roi| AutosaveRoi {
+                id: roi.id,
+                name: roi.name.clone(),
+                color: [roi.color.r(), roi.color.g(), roi.color.b()],
+                shape: match &roi.shape {
+                    RoiShape::Rectangle { x1, y1, x2, y2 } => AutosaveRoiShape::Rectangle {
+                        x1: *x1,
+                        y1: *y1,
+                        x2: *x2,
+                        y2: *y2,
+                    },
+                    RoiShape::Polygon { vertices } => AutosaveRoiShape::Polygon {
+                        vertices: vertices.clone(),
+                    },
+                },
+                visible: roi.visibility.visible,
+                spectrum_visible: roi.visibility.spectrum_visible,
+                tof_gate: roi.tof_gate,
+            })
+            .collect();
+        AutosaveSnapshot::new(self.selected_file.clone(), settings, rois)
+    }
+
+    /// Save the file, settings and ROIs as a session file, in the autosave
+    /// format that `rustpix-gui --render` reads.
+    pub(crate) fn save_session(&self, path: &Path) -> std::io::Result<()> {
+        let payload = snapshot_to_json(&self.autosave_snapshot()).map_err(std::io::Error::other)?;
+        write_payload(path, &payload)
+    }
+
+    /// Restore a recovered session: reload its file, then apply settings and ROIs.
+    pub(crate) fn restore_autosave(&mut self, snapshot: AutosaveSnapshot) {
+        let settings = snapshot.settings;
+        self.algo_type = settings.algo_type;
+        self.radius = settings.radius;
+        self.temporal_window_ns = settings.temporal_window_ns;
+        self.min_cluster_size = settings.min_cluster_size;
+        self.max_cluster_size = settings.max_cluster_size;
+        self.dbscan_min_points = settings.dbscan_min_points;
+        self.grid_cell_size = settings.grid_cell_size;
+        self.hierarchical_merge_distance = settings.hierarchical_merge_distance;
+        self.hierarchical_merge_window_ns = settings.hierarchical_merge_window_ns;
+        self.connectivity = settings.connectivity;
+        self.tdc_frequency = settings.tdc_frequency;
+        self.flight_path_m = settings.flight_path_m;
+        self.tof_offset_ns = settings.tof_offset_ns;
+        self.hit_tof_bins = settings.hit_tof_bins;
+        self.neutron_tof_bins = settings.neutron_tof_bins;
+        if validate_super_resolution(settings.super_resolution_factor).is_ok() {
+            self.super_resolution_factor = settings.super_resolution_factor;
+        }
+        self.weighted_by_tot = settings.weighted_by_tot;
+        self.min_tot_threshold = settings.min_tot_threshold;
+        self.hot_pixel_sigma = settings.hot_pixel_sigma;
+        self.tof_hot_window = settings.tof_hot_window;
+        if let Some(path) = settings.pixel_mask_file.filter(|path| path.is_file()) {
+            if let Ok(mask) = PixelMask::load(&path) {
+                self.loaded_pixel_mask = Some((path, mask));
+            }
+        }
+        if let Colormap::Custom(lut) = &settings.colormap {
+            if !self.imported_luts.contains(lut) {
+                self.imported_luts.push(lut.clone());
+            }
+        }
+        self.colormap = settings.colormap;
+        self.ui_state.histogram.log_scale = settings.log_scale;
+
+        if let Some(path) = snapshot.selected_file.filter(|path| path.is_file()) {
+            self.load_file(path);
+        }
+
+        // ROI coordinates are stored under the saved transform, so set it directly.
+        self.ui_state.histogram_view.transform = settings.transform;
+        self.ui_state.histogram_view.needs_plot_reset = true;
+        self.texture = None;
+        let rois = snapshot
+            .rois
+            .into_iter()
+            .map(|roi| {
+                let shape = match roi.shape {
+                    AutosaveRoiShape::Rectangle { x1, y1, x2, y2 } => {
+                        RoiShape::Rectangle { x1, y1, x2, y2 }
+                    }
+                    AutosaveRoiShape::Polygon { vertices } => RoiShape::Polygon { vertices },
+                };
+                let [r, g, b] = roi.color;
+                let mut restored =
+                    Roi::new(roi.id, roi.name, egui::Color32::from_rgb(r, g, b), shape);
+                restored.visibility.visible = roi.visible;
+                restored.visibility.spectrum_visible = roi.spectrum_visible;
+                restored.tof_gate = roi.tof_gate;
+                restored
+            })
+            .collect();
+        self.roi_state.restore(rois);
+        self.roi_spectrum_pending = None;
+    }
+
+    /// Periodically write an autosave snapshot when the session state changed.
+    fn autosave_tick(&mut self, now: f64) {
+        if self.autosave.pending_recovery.is_some()
+            || now - self.autosave.last_attempt < AUTOSAVE_INTERVAL_SECS
+        {
+            return;
+        }
+        self.autosave.last_attempt = now;
+        if self.selected_file.is_none() && self.roi_state.rois.is_empty() {
+            return;
+        }
+        let snapshot = self.autosave_snapshot();
+        if self
+            .autosave
+            .last_saved
+            .as_ref()
+            .is_some_and(|last| last.same_content(&snapshot))
+        {
+            return;
+        }
+        let payload = match snapshot_to_json(&snapshot) {
+            Ok(payload) => payload,
+            Err(err) => {
+                log::warn!("Autosave serialization failed: {err}");
+                return;
+            }
+        };
+        match write_payload(&self.autosave.path, &payload) {
+            Ok(()) => {
+                self.autosave.last_saved = Some(snapshot);
+                // The restored session is safe in this session's autosave now.
+                if let Some(recovered) = self.autosave.recovered.take() {
+                    recovered.remove();
+                }
+            }
+            Err(err) => log::warn!("Autosave to {} failed: {err}", self.autosave.path.display()),
+        }
+    }
+
     /// Generate histogram image from current view (hits or neutrons).
     pub fn generate_histogram(&self) -> egui::ColorImage {
-        let counts = if self.ui_state.histogram.slicer_enabled {
-            // Get current TOF slice from active hyperstack
-            self.active_hyperstack()
-                .and_then(|hs| hs.slice_tof(self.ui_state.current_tof_bin))
-        } else {
-            // Full projection
-            self.active_counts()
-        };
+        if let Some(image) = self.generate_range_math_image() {
+            return image;
+        }
+        let (width, height) = self.current_data_dimensions();
+        let disp_h = self
+            .ui_state
+            .histogram_view
+            .transform
+            .display_size(width, height)
+            .1;
+        self.generate_histogram_rows(0..disp_h, self.histogram_max_count())
+    }
 
+    /// Generate display rows `rows` of the histogram image with `max_count`
+    /// at the top of the color scale, for refreshing the texture in bands.
+    pub(crate) fn generate_histogram_rows(
+        &self,
+        rows: Range<usize>,
+        max_count: u64,
+    ) -> egui::ColorImage {
         let (width, height) = self.current_data_dimensions();
         let transform = self.ui_state.histogram_view.transform;
-        let (disp_w, disp_h) = transform.display_size(width, height);
-
-        let Some(counts) = counts else {
-            return egui::ColorImage::new([disp_w.max(1), disp_h.max(1)], egui::Color32::BLACK);
+        let Some(counts) = self.histogram_counts() else {
+            let disp_w = transform.display_size(width, height).0;
+            return egui::ColorImage::new([disp_w.max(1), rows.len().max(1)], egui::Color32::BLACK);
         };
-        generate_histogram_image_transformed(
+        generate_histogram_rows_scaled(
             counts,
             width,
             height,
             transform,
-            self.colormap,
+            &self.colormap,
             self.ui_state.histogram.log_scale,
+            max_count,
+            rows,
+        )
+    }
+
+    /// Counts shown by the histogram: the current TOF slice with the slicer
+    /// on, the full projection otherwise.
+    fn histogram_counts(&self) -> Option<&[u64]> {
+        if self.ui_state.histogram.slicer_enabled {
+            self.active_hyperstack()
+                .and_then(|hs| hs.slice_tof(self.ui_state.current_tof_bin))
+        } else {
+            self.active_counts()
+        }
+    }
+
+    /// Top of the histogram's color scale.
+    pub(crate) fn histogram_max_count(&self) -> u64 {
+        let Some(counts) = self.histogram_counts() else {
+            return 1;
+        };
+        // Hot pixels would otherwise set the top of the color scale.
+        match self.excluded_pixel_mask() {
+            Some(mask) => counts
+                .iter()
+                .enumerate()
+                .filter(|&(idx, _)| !mask.is_masked(idx))
+                .map(|(_, &count)| count)
+                .max()
+                .unwrap_or(1),
+            None => counts.iter().max().copied().unwrap_or(1),
+        }
+    }
+
+    /// The dead/hot pixel mask to leave out of spectra, statistics and
+    /// autoscaling, if exclusion is on and the mask fits the current view.
+    pub(crate) fn excluded_pixel_mask(&self) -> Option<&PixelMaskData> {
+        if !self.ui_state.pixel_health.exclude_masked_pixels
+            || self.ui_state.view_mode != ViewMode::Hits
+        {
+            return None;
+        }
+        let hyperstack = self.active_hyperstack()?;
+        Self::active_pixel_mask(
+            self.pixel_masks.as_ref(),
+            hyperstack.width(),
+            hyperstack.height(),
+        )
+    }
+
+    /// Compute the per-pixel TOF range ratio/difference values, if enabled.
+    pub(crate) fn range_math_values(&self) -> Option<Vec<f32>> {
+        let math = self.ui_state.range_math;
+        if !math.enabled {
+            return None;
+        }
+        let hyperstack = self.active_hyperstack()?;
+        let a = hyperstack.project_xy_range(math.range_a.0, math.range_a.1);
+        let b = hyperstack.project_xy_range(math.range_b.0, math.range_b.1);
+        Some(
+            a.iter()
+                .zip(&b)
+                .map(|(&a, &b)| math.op.apply(a, b))
+                .collect(),
         )
     }
 
+    pub(crate) fn generate_range_math_image(&self) -> Option<egui::ColorImage> {
+        let values = self.range_math_values()?;
+        let (width, height) = self.current_data_dimensions();
+        Some(generate_float_image_transformed(
+            &values,
+            width,
+            height,
+            self.ui_state.histogram_view.transform,
+            &self.colormap,
+        ))
+    }
+
     pub(crate) fn update_pixel_masks(&mut self) {
         let Some(counts) = self.hit_counts.as_ref() else {
             self.pixel_masks = None;
@@ -653,6 +1053,16 @@ impl RustpixApp {
         }
 
         let sigma = self.hot_pixel_sigma.max(0.0);
+        let tof_hot = (self.tof_hot_window > 0).then(|| {
+            find_tof_hot_pixels(
+                hyperstack.data(),
+                width,
+                height,
+                hyperstack.n_tof_bins(),
+                self.tof_hot_window,
+                sigma,
+            )
+        });
         let mut sum = 0.0f64;
         let mut sumsq = 0.0f64;
         let mut n = 0.0f64;
@@ -674,32 +1084,42 @@ impl RustpixApp {
         let std_dev = variance.max(0.0).sqrt();
         let threshold = mean + sigma * std_dev;
 
-        let mut dead_mask = Vec::with_capacity(counts.len());
-        let mut hot_mask = Vec::with_capacity(counts.len());
-        let mut hot_points = Vec::new();
-        let mut dead_count = 0usize;
-        let mut hot_count = 0usize;
-
-        for (idx, &count) in counts.iter().enumerate() {
-            if count == 0 {
-                dead_mask.push(1);
-                hot_mask.push(0);
-                dead_count += 1;
-                continue;
-            }
-
-            dead_mask.push(0);
-            let is_hot = u64_to_f64(count) > threshold;
-            if is_hot {
-                hot_mask.push(1);
-                hot_count += 1;
+        // A loaded mask replaces detection when it fits the data.
+        let loaded = self
+            .loaded_pixel_mask
+            .as_ref()
+            .map(|(_, mask)| mask)
+            .filter(|mask| (mask.width, mask.height) == (width, height));
+        let (dead_mask, hot_mask, sigma, threshold) = if let Some(mask) = loaded {
+            let (sigma, threshold) = mask.detection.map_or((sigma, threshold), |detection| {
+                (detection.hot_sigma, detection.hot_threshold)
+            });
+            (mask.dead_flags(), mask.hot_flags(), sigma, threshold)
+        } else {
+            let dead_mask = counts.iter().map(|&count| u8::from(count == 0)).collect();
+            let hot_mask = counts
+                .iter()
+                .map(|&count| u8::from(count > 0 && u64_to_f64(count) > threshold))
+                .collect();
+            (dead_mask, hot_mask, sigma, threshold)
+        };
+        let dead_count = dead_mask.iter().filter(|&&flag| flag != 0).count();
+        let hot_count = hot_mask.iter().filter(|&&flag| flag != 0).count();
+        let excluded_count = dead_mask
+            .iter()
+            .zip(&hot_mask)
+            .filter(|(&dead, &hot)| dead != 0 || hot != 0)
+            .count();
+        let hot_points = hot_mask
+            .iter()
+            .enumerate()
+            .filter(|(_, &flag)| flag != 0)
+            .map(|(idx, _)| {
                 let x = usize_to_f64(idx % width) + 0.5;
                 let y = usize_to_f64(idx / width) + 0.5;
-                hot_points.push([x, y]);
-            } else {
-                hot_mask.push(0);
-            }
-        }
+                [x, y]
+            })
+            .collect();
 
         self.pixel_masks = Some(PixelMaskData {
             width,
@@ -709,11 +1129,14 @@ impl RustpixApp {
             hot_points,
             dead_count,
             hot_count,
+            excluded_count,
+            tof_hot,
             mean,
             std_dev,
             hot_sigma: sigma,
             hot_threshold: threshold,
         });
+        self.health_map_texture = None;
 
         self.update_masked_spectrum();
         if self.ui_state.pixel_health.exclude_masked_pixels {
@@ -744,12 +1167,46 @@ impl RustpixApp {
                 .custom_config
                 .clone()
                 .unwrap_or_else(DetectorConfig::venus_defaults),
-            DetectorProfileKind::Venus => DetectorConfig::venus_defaults(),
+            DetectorProfileKind::Instrument(name) => instrument::find(name)
+                .map_or_else(DetectorConfig::venus_defaults, |profile| {
+                    profile.detector.clone()
+                }),
         };
         config.tdc_frequency_hz = self.tdc_frequency;
+        config.flight_path_m = (self.flight_path_m > 0.0).then_some(self.flight_path_m);
+        config.tof_offset_ns = self.tof_offset_ns;
         config
     }
 
+    /// Take the flight path and TOF offset of `config` if it has a flight
+    /// path.
+    pub(crate) fn apply_beamline(&mut self, config: &DetectorConfig) {
+        if let Some(flight_path_m) = config.flight_path_m {
+            self.flight_path_m = flight_path_m;
+            self.tof_offset_ns = config.tof_offset_ns;
+        }
+    }
+
+    /// Current processing parameters as recorded in macros.
+    pub(crate) fn macro_parameters(&self) -> MacroParameters {
+        MacroParameters {
+            algorithm: self.algo_type.into(),
+            radius: self.radius,
+            temporal_window_ns: self.temporal_window_ns,
+            min_cluster_size: self.min_cluster_size,
+            max_cluster_size: self.max_cluster_size,
+            dbscan_min_points: self.dbscan_min_points,
+            grid_cell_size: self.grid_cell_size,
+            hierarchical_merge_distance: self.hierarchical_merge_distance,
+            hierarchical_merge_window_ns: self.hierarchical_merge_window_ns,
+            connectivity: self.connectivity,
+            super_resolution_factor: self.super_resolution_factor,
+            weighted_by_tot: self.weighted_by_tot,
+            min_tot_threshold: self.min_tot_threshold,
+            detector: self.current_detector_config(),
+        }
+    }
+
     pub(crate) fn memory_rss_bytes(&self) -> u64 {
         self.memory_telemetry.rss_bytes
     }
@@ -884,6 +1341,108 @@ impl RustpixApp {
         )
     }
 
+    /// The current dead/hot pixel masks as a [`PixelMask`] to save.
+    ///
+    /// Returns `None` until masks have been computed.
+    pub(crate) fn pixel_mask_export(&self) -> Option<PixelMask> {
+        let masks = self.pixel_masks.as_ref()?;
+        if let Some((_, mask)) = self
+            .loaded_pixel_mask
+            .as_ref()
+            .filter(|(_, mask)| (mask.width, mask.height) == (masks.width, masks.height))
+        {
+            return Some(mask.clone());
+        }
+        let detection = PixelMaskDetection {
+            hot_sigma: masks.hot_sigma,
+            hot_threshold: masks.hot_threshold,
+            mean: masks.mean,
+            std_dev: masks.std_dev,
+        };
+        let mask = PixelMask::from_flags(
+            masks.width,
+            masks.height,
+            &masks.dead_mask,
+            &masks.hot_mask,
+            Some(detection),
+        )
+        .ok()?;
+        Some(match &self.selected_file {
+            Some(path) => mask.with_source(path.display().to_string()),
+            None => mask,
+        })
+    }
+
+    /// Use the pixel mask in `path` instead of detecting masks, from now on
+    /// and for every file loaded later.
+    ///
+    /// # Errors
+    /// Returns an error if the file is not a pixel mask.
+    pub(crate) fn load_pixel_mask_file(&mut self, path: PathBuf) -> Result<()> {
+        let mask = PixelMask::load(&path)?;
+        self.loaded_pixel_mask = Some((path, mask));
+        self.update_pixel_masks();
+        Ok(())
+    }
+
+    /// Go back to detecting masks from the data.
+    pub(crate) fn clear_loaded_pixel_mask(&mut self) {
+        self.loaded_pixel_mask = None;
+        self.update_pixel_masks();
+    }
+
+    /// All ROIs mapped back to data pixel coordinates, for mask export.
+    ///
+    /// Returns `None` if there are no ROIs or no data grid yet.
+    pub(crate) fn roi_mask_export(&self) -> Option<RoiMaskExport> {
+        let hyperstack = self.active_hyperstack()?;
+        if self.roi_state.rois.is_empty() {
+            return None;
+        }
+        let (width, height) = (hyperstack.width(), hyperstack.height());
+        let (width_f, height_f) = (usize_to_f64(width), usize_to_f64(height));
+        let transform = self.ui_state.histogram_view.transform;
+        let to_data = |x: f64, y: f64| {
+            transform
+                .apply_inverse_f64(x, y, width_f, height_f)
+                .unwrap_or((x, y))
+        };
+        let rois = self
+            .roi_state
+            .rois
+            .iter()
+            .map(|roi| {
+                let shape = match &roi.shape {
+                    RoiShape::Rectangle { x1, y1, x2, y2 } => {
+                        let (ax, ay) = to_data(*x1, *y1);
+                        let (bx, by) = to_data(*x2, *y2);
+                        RoiShape::Rectangle {
+                            x1: ax.min(bx),
+                            y1: ay.min(by),
+                            x2: ax.max(bx),
+                            y2: ay.max(by),
+                        }
+                    }
+                    RoiShape::Polygon { vertices } => RoiShape::Polygon {
+                        vertices: vertices.iter().map(|&(x, y)| to_data(x, y)).collect(),
+                    },
+                };
+                (roi.name.clone(), shape)
+            })
+            .collect();
+        let (view, super_resolution_factor) = match self.ui_state.view_mode {
+            ViewMode::Hits => ("hits", 1.0),
+            ViewMode::Neutrons => ("neutrons", self.neutron_super_resolution_factor),
+        };
+        Some(RoiMaskExport {
+            width,
+            height,
+            view,
+            super_resolution_factor,
+            rois,
+        })
+    }
+
     /// Get width/height for the active view (display dimensions).
     pub fn current_dimensions(&self) -> (usize, usize) {
         let (width, height) = self.current_data_dimensions();
@@ -898,6 +1457,17 @@ impl RustpixApp {
             return;
         }
 
+        let datasets = &self.ui_state.export.options.datasets;
+        self.ui_state.history.record_export(MacroExport {
+            path: path.clone(),
+            format: MacroExportFormat::Hdf5,
+            hits: datasets.hits,
+            neutrons: datasets.neutrons,
+            histogram: datasets.histogram,
+            flight_path_m: Some(self.flight_path_m),
+            tof_offset_ns: Some(self.tof_offset_ns),
+        });
+
         let tx = self.tx.clone();
         let request = ExportHdf5Request {
             path,
@@ -942,9 +1512,21 @@ impl RustpixApp {
         if self.ui_state.export.in_progress {
             return;
         }
-        if format == ExportFormat::Hdf5 {
-            return;
-        }
+        let macro_format = match format {
+            ExportFormat::Hdf5 => return,
+            ExportFormat::TiffFolder => MacroExportFormat::TiffFolder,
+            ExportFormat::TiffStack => MacroExportFormat::TiffStack,
+            ExportFormat::Fits => MacroExportFormat::Fits,
+        };
+        self.ui_state.history.record_export(MacroExport {
+            path: folder.clone(),
+            format: macro_format,
+            hits: false,
+            neutrons: false,
+            histogram: false,
+            flight_path_m: None,
+            tof_offset_ns: Some(self.tof_offset_ns),
+        });
 
         let tx = self.tx.clone();
         let view_mode = self.ui_state.view_mode;
@@ -952,13 +1534,25 @@ impl RustpixApp {
             ViewMode::Hits => self.hyperstack.clone(),
             ViewMode::Neutrons => self.neutron_hyperstack.clone(),
         };
+        // The folder is named after the expanded base name template.
+        let mut options = self.ui_state.export.tiff.clone();
+        if let Some(name) = folder.file_name().and_then(|name| name.to_str()) {
+            options.base_name = name.to_string();
+        }
         let request = ExportTiffRequest {
             folder,
             format,
-            options: self.ui_state.export.tiff.clone(),
+            options,
             hyperstack,
             pixel_masks: self.pixel_masks.clone(),
             tof_offset_ns: self.tof_offset_ns,
+            flight_path_m: self.flight_path_m,
+            view_mode,
+            source_name: self
+                .selected_file
+                .as_ref()
+                .and_then(|path| path.file_name())
+                .map(|name| name.to_string_lossy().into_owned()),
             summed_counts: self.active_counts().map(<[u64]>::to_vec),
         };
 
@@ -1024,7 +1618,7 @@ impl RustpixApp {
         }
     }
 
-    fn active_data_revision(&self) -> u64 {
+    pub(crate) fn active_data_revision(&self) -> u64 {
         match self.ui_state.view_mode {
             ViewMode::Hits => self.hit_data_revision,
             ViewMode::Neutrons => self.neutron_data_revision,
@@ -1171,14 +1765,7 @@ impl RustpixApp {
         let transform = self.ui_state.histogram_view.transform;
         let (display_width, display_height) = transform.display_size(width, height);
         let n_bins = hyperstack.n_tof_bins();
-        let mask_data = if self.ui_state.pixel_health.exclude_masked_pixels
-            && self.ui_state.view_mode == ViewMode::Hits
-        {
-            self.pixel_masks.as_ref()
-        } else {
-            None
-        };
-        let mask = Self::active_pixel_mask(mask_data, width, height);
+        let mask = self.excluded_pixel_mask();
         let ctx = RoiSpectrumContext {
             hyperstack,
             data_width: width,
@@ -1340,10 +1927,8 @@ impl RustpixApp {
             ctx.transform
                 .apply_inverse(display_x, display_y, ctx.data_width, ctx.data_height)?;
         let idx = src_y * ctx.data_width + src_x;
-        if let Some(mask) = ctx.mask {
-            if mask.dead_mask[idx] == 1 || mask.hot_mask[idx] == 1 {
-                return None;
-            }
+        if ctx.mask.is_some_and(|mask| mask.is_masked(idx)) {
+            return None;
         }
         Some(idx)
     }
@@ -1372,7 +1957,7 @@ impl RustpixApp {
         self.hit_data_revision = self.hit_data_revision.wrapping_add(1);
         self.ui_state.current_tof_bin = 0;
         self.ui_state.histogram_view.needs_plot_reset = true;
-        self.texture = None;
+        self.texture_refresh.invalidate();
     }
 
     /// Rebuild the neutron hyperstack with current settings.
@@ -1392,8 +1977,9 @@ impl RustpixApp {
                     .map(|hs| (hs.width(), hs.height()))
             })
             .unwrap_or_else(|| self.current_detector_config().detector_dimensions());
+        let shown = filter_by_class(&self.neutrons, self.neutron_class_filter);
         let neutron_hs = Hyperstack3D::from_neutrons(
-            &self.neutrons,
+            &shown,
             bins,
             tof_max,
             width,
@@ -1406,7 +1992,7 @@ impl RustpixApp {
         self.neutron_data_revision = self.neutron_data_revision.wrapping_add(1);
         self.ui_state.current_tof_bin = 0;
         self.ui_state.histogram_view.needs_plot_reset = true;
-        self.texture = None;
+        self.texture_refresh.invalidate();
     }
 
     /// Handle pending messages from async workers.
@@ -1414,6 +2000,16 @@ impl RustpixApp {
         while let Ok(msg) = self.rx.try_recv() {
             match msg {
                 AppMessage::LoadProgress(p, s) => self.handle_load_progress(p, s),
+                AppMessage::FileMetadata(metadata) => {
+                    if self.processing.is_loading {
+                        self.file_metadata = Some(*metadata);
+                    }
+                }
+                AppMessage::ChipHitStats(stats) => {
+                    if self.processing.is_loading {
+                        self.chip_hit_stats = Some(*stats);
+                    }
+                }
                 AppMessage::ProcessingProgress(p, s) => self.handle_processing_progress(p, s),
                 AppMessage::LoadComplete(hit_count, batch, hyperstack, dur, _dbg, pulse_bounds) => {
                     self.handle_load_complete(
@@ -1425,11 +2021,14 @@ impl RustpixApp {
                         dur,
                     );
                 }
-                AppMessage::LoadError(e) => self.handle_load_error(&e),
-                AppMessage::ProcessingComplete(neutrons, dur) => {
-                    self.handle_processing_complete(neutrons, dur);
+                AppMessage::ProcessedNeutrons(neutrons, super_res_factor) => {
+                    self.handle_processed_neutrons(*neutrons, super_res_factor);
+                }
+                AppMessage::LoadError(e) => self.handle_load_error(ctx, &e),
+                AppMessage::ProcessingComplete(neutrons, dur, labels) => {
+                    self.handle_processing_complete(neutrons, dur, labels);
                 }
-                AppMessage::ProcessingError(e) => self.handle_processing_error(&e),
+                AppMessage::ProcessingError(e) => self.handle_processing_error(ctx, &e),
                 AppMessage::ExportProgress(progress, status) => {
                     self.handle_export_progress(progress, status);
                 }
@@ -1487,22 +2086,70 @@ impl RustpixApp {
 
         let img = self.generate_histogram();
         self.texture = Some(ctx.load_texture("hist", img, egui::TextureOptions::NEAREST));
+
+        if std::mem::take(&mut self.startup_auto_process) {
+            self.run_processing();
+        } else if self.startup_view.take() == Some(ViewMode::Hits) {
+            self.ui_state.view_mode = ViewMode::Hits;
+        }
+    }
+
+    /// Show neutrons read from a processed output in the neutron view.
+    ///
+    /// Files that do not record a super-resolution factor are assumed to
+    /// use the current setting.
+    fn handle_processed_neutrons(
+        &mut self,
+        mut neutrons: NeutronBatch,
+        super_res_factor: Option<f64>,
+    ) {
+        if self.processing.is_loading || self.hyperstack.is_none() {
+            return;
+        }
+        if let Some(factor) = super_res_factor.or(neutrons.super_resolution_factor) {
+            self.processing_super_resolution_factor = factor;
+        }
+        neutrons.super_resolution_factor = Some(self.processing_super_resolution_factor);
+        self.statistics.neutron_count = neutrons.len();
+        self.statistics.class_counts = neutrons.class_counts();
+        self.neutrons = Arc::new(neutrons);
+        self.neutron_super_resolution_factor = self.processing_super_resolution_factor;
+        self.rebuild_neutron_hyperstack();
+        self.ui_state.view_mode = ViewMode::Neutrons;
     }
 
-    fn handle_load_error(&mut self, error: &str) {
+    fn handle_load_error(&mut self, ctx: &egui::Context, error: &str) {
         self.processing.is_loading = false;
+        self.startup_auto_process = false;
+        self.startup_view = None;
         self.processing.status_text = format!("Error: {error}");
+        self.ui_state
+            .notifications
+            .error(format!("Load failed: {error}"), ctx.input(|i| i.time));
     }
 
-    fn handle_processing_complete(&mut self, neutrons: NeutronBatch, dur: Duration) {
+    fn handle_processing_complete(
+        &mut self,
+        neutrons: NeutronBatch,
+        dur: Duration,
+        labels: Option<Arc<ClusterLabels>>,
+    ) {
         if !self.processing.is_processing {
             return;
         }
+        if let Some(labels) = labels {
+            log::debug!(
+                "Cached cluster labels ({:.1} MB)",
+                usize_to_f64(labels.memory_bytes()) / 1_000_000.0
+            );
+            self.stage_cache.store(labels);
+        }
         self.processing.is_processing = false;
         self.processing.progress = 1.0;
         self.processing.status_text = "Ready".to_string();
 
         self.statistics.neutron_count = neutrons.len();
+        self.statistics.class_counts = neutrons.class_counts();
         self.statistics.cluster_duration = Some(dur);
         if !neutrons.is_empty() && self.statistics.hit_count > 0 {
             #[allow(clippy::cast_precision_loss)]
@@ -1512,10 +2159,13 @@ impl RustpixApp {
             }
         }
 
-        let super_res_factor = self.processing_super_resolution_factor;
+        let super_res_factor = neutrons
+            .super_resolution_factor
+            .unwrap_or(self.processing_super_resolution_factor);
         if let Some(hit_hs) = self.hyperstack.as_deref() {
+            let shown = filter_by_class(&neutrons, self.neutron_class_filter);
             let neutron_hs = Hyperstack3D::from_neutrons(
-                &neutrons,
+                &shown,
                 self.neutron_tof_bins.max(1),
                 hit_hs.tof_max(),
                 hit_hs.width(),
@@ -1530,11 +2180,18 @@ impl RustpixApp {
 
         self.neutrons = Arc::new(neutrons);
         self.neutron_super_resolution_factor = super_res_factor;
+        if let Some(view) = self.startup_view.take() {
+            self.ui_state.view_mode = view;
+        }
     }
 
-    fn handle_processing_error(&mut self, error: &str) {
+    fn handle_processing_error(&mut self, ctx: &egui::Context, error: &str) {
         self.processing.is_processing = false;
+        self.startup_view = None;
         self.processing.status_text = format!("Error: {error}");
+        self.ui_state
+            .notifications
+            .error(format!("Processing failed: {error}"), ctx.input(|i| i.time));
     }
 
     fn handle_export_progress(&mut self, progress: f32, status: String) {
@@ -1558,34 +2215,24 @@ impl RustpixApp {
             "Export complete (warnings)".to_string()
         };
         let size_mb = u64_to_f64(size_bytes) / (1024.0 * 1024.0);
-        self.ui_state.roi_status = Some((
+        let now = ctx.input(|i| i.time);
+        self.ui_state.notifications.info(
             format!("Saved export: {} ({size_mb:.1} MB)", path.display()),
-            ctx.input(|i| i.time + 4.0),
-        ));
-        if !warnings.is_empty() {
-            log::warn!("Export validation warnings:");
-            for warning in warnings {
-                log::warn!(" - {warning}");
-            }
-            let summary = if warnings.len() == 1 {
-                warnings[0].clone()
-            } else {
-                format!("{} (and {} more)", warnings[0], warnings.len() - 1)
-            };
-            self.ui_state.roi_warning = Some((
-                format!("Export validation: {summary}"),
-                ctx.input(|i| i.time + 6.0),
-            ));
+            now,
+        );
+        for warning in warnings {
+            self.ui_state
+                .notifications
+                .warn(format!("Export validation: {warning}"), now);
         }
     }
 
     fn handle_export_error(&mut self, ctx: &egui::Context, error: &str) {
         self.ui_state.export.in_progress = false;
         self.ui_state.export.status = "Export failed".to_string();
-        self.ui_state.roi_warning = Some((
-            format!("Export failed: {error}"),
-            ctx.input(|i| i.time + 6.0),
-        ));
+        self.ui_state
+            .notifications
+            .error(format!("Export failed: {error}"), ctx.input(|i| i.time));
     }
 }
 
@@ -1611,6 +2258,9 @@ struct ExportTiffRequest {
     hyperstack: Option<Arc<Hyperstack3D>>,
     pixel_masks: Option<PixelMaskData>,
     tof_offset_ns: f64,
+    flight_path_m: f64,
+    view_mode: ViewMode,
+    source_name: Option<String>,
     summed_counts: Option<Vec<u64>>,
 }
 
@@ -1639,18 +2289,29 @@ fn export_hdf5_worker(
         mask_payload.as_ref(),
     )?;
 
-    send_export_progress(tx, 0.85, "Writing HDF5");
-    write_combined_hdf5_batches(
-        &request.path,
-        hit_payload.as_deref().zip(hit_options.as_ref()),
-        neutron_payload.as_deref().zip(neutron_options.as_ref()),
-        histogram_payload.as_ref().zip(histogram_options.as_ref()),
-        mask_payload.as_ref().zip(mask_options.as_ref()),
-    )
-    .map_err(|err| {
-        remove_partial_file(&request.path);
-        anyhow!("HDF5 export failed: {err}")
-    })?;
+    if request.options.append {
+        send_export_progress(tx, 0.85, "Appending to HDF5");
+        // Never remove the target on failure: it holds earlier runs.
+        append_hdf5_event_batches(
+            &request.path,
+            hit_payload.as_deref().zip(hit_options.as_ref()),
+            neutron_payload.as_deref().zip(neutron_options.as_ref()),
+        )
+        .map_err(|err| anyhow!("HDF5 append failed: {err}"))?;
+    } else {
+        send_export_progress(tx, 0.85, "Writing HDF5");
+        write_combined_hdf5_batches(
+            &request.path,
+            hit_payload.as_deref().zip(hit_options.as_ref()),
+            neutron_payload.as_deref().zip(neutron_options.as_ref()),
+            histogram_payload.as_ref().zip(histogram_options.as_ref()),
+            mask_payload.as_ref().zip(mask_options.as_ref()),
+        )
+        .map_err(|err| {
+            remove_partial_file(&request.path);
+            anyhow!("HDF5 export failed: {err}")
+        })?;
+    }
 
     send_export_progress(tx, 0.95, "Validating export");
     let warnings = match validate_hdf5_export(&request.path, &request.options) {
@@ -1726,15 +2387,27 @@ fn export_tiff_worker(
         if request.options.include_summed_image {
             send_export_progress(tx, 0.2, "Writing summed image");
             let summed = build_summed_counts(hyperstack, request.summed_counts.as_deref());
-            let summed_path = request.folder.join(format!("{base_name}_SummedImg.tif"));
-            total_bytes += write_single_tiff_image(
-                &summed_path,
-                width,
-                height,
-                &summed,
-                request.options.bit_depth,
-                &mut clamped_any,
-            )?;
+            if request.format == ExportFormat::Fits {
+                let summed_path = request.folder.join(format!("{base_name}_SummedImg.fits"));
+                total_bytes += write_fits_image(
+                    &summed_path,
+                    &summed,
+                    hyperstack.width(),
+                    hyperstack.height(),
+                    &fits_export_options(request, hyperstack),
+                )?;
+                clamped_any |= summed.iter().any(|&count| count > u64::from(u32::MAX));
+            } else {
+                let summed_path = request.folder.join(format!("{base_name}_SummedImg.tif"));
+                total_bytes += write_single_tiff_image(
+                    &summed_path,
+                    width,
+                    height,
+                    &summed,
+                    request.options.bit_depth,
+                    &mut clamped_any,
+                )?;
+            }
         }
 
         match request.format {
@@ -1759,12 +2432,33 @@ fn export_tiff_worker(
                     &mut clamped_any,
                 )?;
             }
+            ExportFormat::Fits => {
+                send_export_progress(tx, 0.25, "Writing FITS cube");
+                let stack_path = request.folder.join(format!("{base_name}_stack.fits"));
+                total_bytes += write_fits_cube(
+                    &stack_path,
+                    hyperstack.data(),
+                    hyperstack.width(),
+                    hyperstack.height(),
+                    &fits_export_options(request, hyperstack),
+                )?;
+                clamped_any |= hyperstack
+                    .data()
+                    .iter()
+                    .any(|&count| count > u64::from(u32::MAX));
+            }
             ExportFormat::Hdf5 => {
                 return Err(anyhow!("Invalid export format for TIFF worker"));
             }
         }
 
-        add_clamp_warning(request.options.bit_depth, clamped_any, &mut warnings);
+        // FITS counts are unsigned 32-bit regardless of the TIFF bit depth.
+        let clamp_depth = if request.format == ExportFormat::Fits {
+            TiffBitDepth::Bit32
+        } else {
+            request.options.bit_depth
+        };
+        add_clamp_warning(clamp_depth, clamped_any, &mut warnings);
         send_export_progress(tx, 1.0, "Export complete");
         Ok((total_bytes, warnings))
     })();
@@ -1776,6 +2470,31 @@ fn export_tiff_worker(
     result
 }
 
+/// FITS axis calibration and acquisition metadata of an image export.
+fn fits_export_options(request: &ExportTiffRequest, hyperstack: &Hyperstack3D) -> FitsOptions {
+    let view = match request.view_mode {
+        ViewMode::Hits => "hits",
+        ViewMode::Neutrons => "neutrons",
+    };
+    let tof_bins = i64::try_from(hyperstack.n_tof_bins()).unwrap_or(i64::MAX);
+    let mut options = FitsOptions {
+        tof_bin_width_ns: hyperstack.bin_width() * 25.0,
+        tof_offset_ns: request.tof_offset_ns,
+        ..FitsOptions::default()
+    }
+    .with_card("VIEW", FitsValue::Str(view.to_string()), "counted events")
+    .with_card("TOFBINS", FitsValue::Int(tof_bins), "TOF bins")
+    .with_card(
+        "FLIGHTPT",
+        FitsValue::Float(request.flight_path_m),
+        "flight path [m]",
+    );
+    if let Some(name) = &request.source_name {
+        options = options.with_card("FILENAME", FitsValue::Str(name.clone()), "input file");
+    }
+    options
+}
+
 fn spectra_counts_for_export(
     request: &ExportTiffRequest,
     hyperstack: &Hyperstack3D,
@@ -2475,6 +3194,18 @@ fn slice_hit_batch(batch: &HitBatch, start: usize, len: usize) -> HitBatch {
         timestamp: batch.timestamp[start..end].to_vec(),
         chip_id: batch.chip_id[start..end].to_vec(),
         cluster_id: batch.cluster_id[start..end].to_vec(),
+        raw_x: batch
+            .raw_x
+            .get(start..end)
+            .map_or_else(Vec::new, <[u16]>::to_vec),
+        raw_y: batch
+            .raw_y
+            .get(start..end)
+            .map_or_else(Vec::new, <[u16]>::to_vec),
+        raw_toa: batch
+            .raw_toa
+            .get(start..end)
+            .map_or_else(Vec::new, <[u32]>::to_vec),
     }
 }
 
@@ -2535,7 +3266,26 @@ fn neutron_dimensions_from(
     Some((x_size, y_size))
 }
 
-fn hash_roi_shape(roi: &Roi) -> u64 {
+/// Neutrons of one class, or all of them when no class is selected or the
+/// batch was not classified.
+fn filter_by_class(neutrons: &NeutronBatch, class: Option<EventClass>) -> Cow<'_, NeutronBatch> {
+    match class {
+        Some(class) if neutrons.has_classes() => {
+            let mut filtered = neutrons.clone();
+            filtered.retain_classes(&[class]);
+            Cow::Owned(filtered)
+        }
+        _ => Cow::Borrowed(neutrons),
+    }
+}
+
+/// Read a JSON event classifier.
+pub(crate) fn load_event_classifier(path: &Path) -> Result<EventClassifier> {
+    let text = fs::read_to_string(path)?;
+    serde_json::from_str(&text).map_err(|err| anyhow!("invalid classifier: {err}"))
+}
+
+pub(crate) fn hash_roi_shape(roi: &Roi) -> u64 {
     let mut hasher = DefaultHasher::new();
     match &roi.shape {
         RoiShape::Rectangle { x1, y1, x2, y2 } => {
@@ -2561,7 +3311,7 @@ fn hash_f64(value: f64, hasher: &mut DefaultHasher) {
     value.to_bits().hash(hasher);
 }
 
-fn clamp_span(a: f64, b: f64, limit: usize) -> (usize, usize) {
+pub(crate) fn clamp_span(a: f64, b: f64, limit: usize) -> (usize, usize) {
     let min = a.min(b);
     let max = a.max(b);
     let max_f64 = usize_to_f64(limit);
@@ -2586,7 +3336,7 @@ fn polygon_area(vertices: &[(f64, f64)]) -> f64 {
     area2 * 0.5
 }
 
-fn point_in_polygon_xy(x: f64, y: f64, vertices: &[(f64, f64)]) -> bool {
+pub(crate) fn point_in_polygon_xy(x: f64, y: f64, vertices: &[(f64, f64)]) -> bool {
     if vertices.len() < 3 {
         return false;
     }
@@ -2611,12 +3361,7 @@ fn compute_masked_spectrum(hyperstack: &Hyperstack3D, mask: &PixelMaskData) -> O
     if mask.dead_mask.len() != expected || mask.hot_mask.len() != expected {
         return None;
     }
-    let mut active_indices = Vec::new();
-    for idx in 0..expected {
-        if mask.dead_mask[idx] == 0 && mask.hot_mask[idx] == 0 {
-            active_indices.push(idx);
-        }
-    }
+    let active_indices: Vec<usize> = (0..expected).filter(|&idx| !mask.is_masked(idx)).collect();
     let n_bins = hyperstack.n_tof_bins();
     let mut counts = vec![0u64; n_bins];
     for (tof_bin, total) in counts.iter_mut().enumerate() {
@@ -2627,6 +3372,14 @@ fn compute_masked_spectrum(hyperstack: &Hyperstack3D, mask: &PixelMaskData) -> O
         for idx in &active_indices {
             sum += slice[*idx];
         }
+        if let Some(tof_hot) = &mask.tof_hot {
+            for &idx in tof_hot.hot_in_bin(tof_bin) {
+                let idx = idx as usize;
+                if !mask.is_masked(idx) {
+                    sum = sum.saturating_sub(slice[idx]);
+                }
+            }
+        }
         *total = sum;
     }
     Some(counts)
@@ -2661,6 +3414,9 @@ fn neutron_batch_bytes(batch: &NeutronBatch) -> u64 {
         + vec_capacity_bytes(&batch.tot)
         + vec_capacity_bytes(&batch.n_hits)
         + vec_capacity_bytes(&batch.chip_id)
+        + vec_capacity_bytes(&batch.energy_kev)
+        + vec_capacity_bytes(&batch.event_class)
+        + vec_capacity_bytes(&batch.features)
 }
 
 fn roi_spectra_bytes(cache: &RoiSpectraCache) -> u64 {
@@ -2685,9 +3441,24 @@ impl eframe::App for RustpixApp {
         self.render_side_panel(ctx);
         self.render_central_panel(ctx);
         self.render_settings_windows(ctx);
+        self.render_health_dashboard(ctx);
+        self.render_radial_profile(ctx);
+        self.render_fourier_view(ctx);
+        self.render_energy_calibration(ctx);
+        self.render_command_history(ctx);
+        self.render_notifications(ctx);
+        self.render_recovery_dialog(ctx);
+        self.render_tour(ctx);
+        self.render_command_palette(ctx);
+        self.autosave_tick(ctx.input(|i| i.time));
 
         if self.processing.is_loading || self.processing.is_processing {
             ctx.request_repaint();
         }
     }
+
+    fn on_exit(&mut self, _gl: Option<&eframe::glow::Context>) {
+        // A clean shutdown leaves nothing to recover.
+        self.autosave.finish();
+    }
 }
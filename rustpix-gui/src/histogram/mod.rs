@@ -222,6 +222,30 @@ impl Hyperstack3D {
         result
     }
 
+    /// Sum projection over an inclusive range of TOF bins.
+    ///
+    /// Bounds are clamped to the available bins; an empty hyperstack yields zeros.
+    #[must_use]
+    pub fn project_xy_range(&self, first_bin: usize, last_bin: usize) -> Vec<u64> {
+        let xy_size = self.height * self.width;
+        let mut result = vec![0u64; xy_size];
+        if self.n_tof_bins == 0 {
+            return result;
+        }
+
+        let last = last_bin.min(self.n_tof_bins - 1);
+        let first = first_bin.min(last);
+        for tof_bin in first..=last {
+            let start = tof_bin * xy_size;
+            let end = start + xy_size;
+            for (i, &count) in self.data[start..end].iter().enumerate() {
+                result[i] += count;
+            }
+        }
+
+        result
+    }
+
     /// Get a slice of data at a specific TOF bin.
     ///
     /// Returns a borrowed slice of the XY plane at the given TOF index.
@@ -349,6 +373,20 @@ mod tests {
         assert_eq!(proj[0], 0);
     }
 
+    #[test]
+    fn test_project_xy_range() {
+        let mut hs = Hyperstack3D::new(4, 2, 2, 400);
+        hs.increment(0, 0, 0);
+        hs.increment(1, 0, 0);
+        hs.increment(3, 0, 0);
+        hs.increment(2, 1, 1);
+
+        assert_eq!(hs.project_xy_range(0, 1), vec![2, 0, 0, 0]);
+        assert_eq!(hs.project_xy_range(2, 3), vec![1, 0, 0, 1]);
+        // Out-of-range bounds are clamped to the last bin.
+        assert_eq!(hs.project_xy_range(3, 10), vec![1, 0, 0, 0]);
+    }
+
     #[test]
     fn test_slice_tof() {
         let mut hs = Hyperstack3D::new(3, 4, 4, 300);
@@ -6,6 +6,8 @@ mod app;
 mod histogram;
 mod message;
 mod pipeline;
+mod render;
+mod startup;
 mod state;
 mod ui;
 mod util;
@@ -13,9 +15,35 @@ mod viewer;
 
 use app::RustpixApp;
 use eframe::egui;
+use startup::{StartupOptions, USAGE};
 
 fn main() -> eframe::Result<()> {
     env_logger::init();
+    let startup = match StartupOptions::parse(std::env::args().skip(1)) {
+        Ok(options) if options.help => {
+            println!("{USAGE}");
+            return Ok(());
+        }
+        Ok(options) => options,
+        Err(err) => {
+            eprintln!("rustpix-gui: {err}\n\n{USAGE}");
+            std::process::exit(2);
+        }
+    };
+    if let Some(request) = startup.render_request() {
+        match render::render_session(&request) {
+            Ok(written) => {
+                for path in written {
+                    println!("{}", path.display());
+                }
+                return Ok(());
+            }
+            Err(err) => {
+                eprintln!("rustpix-gui: {err:#}");
+                std::process::exit(1);
+            }
+        }
+    }
     let mut viewport = egui::ViewportBuilder::default().with_inner_size([1200.0, 800.0]);
     if let Some(icon) = load_app_icon() {
         viewport = viewport.with_icon(icon);
@@ -31,7 +59,9 @@ fn main() -> eframe::Result<()> {
             // Apply custom styling based on system theme preference
             ui::theme::configure_style(&cc.egui_ctx);
             egui_extras::install_image_loaders(&cc.egui_ctx);
-            Ok(Box::new(RustpixApp::default()))
+            let mut app = RustpixApp::default();
+            app.apply_startup_options(startup);
+            Ok(Box::new(app))
         }),
     )
 }
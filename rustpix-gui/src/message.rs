@@ -4,12 +4,14 @@
 //! via channels to report progress, completion, and errors.
 
 use std::path::PathBuf;
+use std::sync::Arc;
 use std::time::Duration;
 
 use rustpix_core::neutron::NeutronBatch;
 use rustpix_core::soa::HitBatch;
 
 use crate::histogram::Hyperstack3D;
+use crate::pipeline::{ChipHitStats, ClusterLabels, FileMetadata};
 
 /// Pulse boundary metadata for cached hit batches.
 #[derive(Clone, Debug)]
@@ -27,6 +29,13 @@ pub enum AppMessage {
     /// File loading progress update.
     LoadProgress(f32, String),
 
+    /// Acquisition metadata, sent once sections are scanned and before hits
+    /// are processed.
+    FileMetadata(Box<FileMetadata>),
+
+    /// Per-chip hit and saturation counts, sent just before `LoadComplete`.
+    ChipHitStats(Box<ChipHitStats>),
+
     /// File loading completed successfully.
     ///
     /// Contains:
@@ -45,6 +54,12 @@ pub enum AppMessage {
         Option<Vec<PulseBounds>>,
     ),
 
+    /// Neutrons read from a processed output, sent just after `LoadComplete`.
+    ///
+    /// Contains the neutrons and the super-resolution factor of their
+    /// positions, when the file records it.
+    ProcessedNeutrons(Box<NeutronBatch>, Option<f64>),
+
     /// File loading failed.
     LoadError(String),
 
@@ -56,7 +71,9 @@ pub enum AppMessage {
     /// Contains:
     /// - `NeutronBatch`: Extracted neutron events
     /// - `Duration`: Time taken to process
-    ProcessingComplete(NeutronBatch, Duration),
+    /// - `Option<Arc<ClusterLabels>>`: Freshly computed cluster labels
+    ///   (`None` when cached labels were reused)
+    ProcessingComplete(NeutronBatch, Duration, Option<Arc<ClusterLabels>>),
 
     /// Clustering failed.
     ProcessingError(String),
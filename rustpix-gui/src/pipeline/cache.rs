@@ -0,0 +1,155 @@
+//! Stage-level caching for the clustering pipeline.
+//!
+//! Processing runs in two stages: clustering assigns a label to every hit,
+//! then extraction turns each labelled cluster into a neutron. Labels are
+//! keyed on the parameters that affect clustering only, so changing
+//! extraction settings (super-resolution, TOT weighting, TOT threshold)
+//! reuses the cached labels and re-runs just the extraction stage.
+
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use rustpix_algorithms::Connectivity;
+use rustpix_tpx::DetectorConfig;
+
+use super::AlgorithmType;
+
+/// Parameters that determine cluster labels.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ClusteringKey {
+    /// Source file.
+    pub path: PathBuf,
+    /// Fingerprint of the detector configuration used to decode hits.
+    pub detector_fingerprint: u64,
+    /// Clustering algorithm.
+    pub algorithm: AlgorithmType,
+    /// Spatial radius for clustering.
+    pub radius: f64,
+    /// Temporal window in nanoseconds.
+    pub temporal_window_ns: f64,
+    /// Minimum cluster size.
+    pub min_cluster_size: u16,
+    /// Maximum cluster size.
+    pub max_cluster_size: Option<u16>,
+    /// Minimum points for DBSCAN.
+    pub dbscan_min_points: usize,
+    /// Grid cell size in pixels.
+    pub grid_cell_size: usize,
+    /// Largest box gap of merged clusters (Hierarchical).
+    pub hierarchical_merge_distance: f64,
+    /// Largest TOF gap of merged clusters (Hierarchical).
+    pub hierarchical_merge_window_ns: f64,
+    /// Pixel neighbourhood (Connected Components).
+    pub connectivity: Connectivity,
+}
+
+/// Cluster labels for one time-ordered hit batch.
+#[derive(Clone, Debug)]
+pub struct BatchLabels {
+    /// Cluster id per hit (-1 = unclustered).
+    pub cluster_id: Vec<i32>,
+    /// Number of clusters in the batch.
+    pub num_clusters: usize,
+}
+
+/// Cluster labels for a whole file, in stream order.
+#[derive(Debug)]
+pub struct ClusterLabels {
+    /// Parameters the labels were computed with.
+    pub key: ClusteringKey,
+    /// Labels per streamed batch.
+    pub batches: Vec<BatchLabels>,
+}
+
+impl ClusterLabels {
+    /// Approximate heap size of the cached labels.
+    #[must_use]
+    pub fn memory_bytes(&self) -> usize {
+        self.batches
+            .iter()
+            .map(|batch| batch.cluster_id.len() * std::mem::size_of::<i32>())
+            .sum()
+    }
+}
+
+/// Cached output of the clustering stage.
+#[derive(Default)]
+pub struct StageCache {
+    /// Labels from the last completed clustering run.
+    labels: Option<Arc<ClusterLabels>>,
+}
+
+impl StageCache {
+    /// Cached labels, if they were computed with `key`.
+    #[must_use]
+    pub fn labels_for(&self, key: &ClusteringKey) -> Option<Arc<ClusterLabels>> {
+        self.labels
+            .as_ref()
+            .filter(|labels| labels.key == *key)
+            .map(Arc::clone)
+    }
+
+    /// Store labels from a completed clustering run.
+    pub fn store(&mut self, labels: Arc<ClusterLabels>) {
+        self.labels = Some(labels);
+    }
+
+    /// Drop all cached stages (new file loaded).
+    pub fn invalidate(&mut self) {
+        self.labels = None;
+    }
+}
+
+/// Fingerprint a detector configuration for cache keys.
+#[must_use]
+pub fn detector_fingerprint(config: &DetectorConfig) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    serde_json::to_string(config)
+        .unwrap_or_default()
+        .hash(&mut hasher);
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn clustering_key(radius: f64) -> ClusteringKey {
+        ClusteringKey {
+            path: PathBuf::from("run.tpx3"),
+            detector_fingerprint: 1,
+            algorithm: AlgorithmType::Abs,
+            radius,
+            temporal_window_ns: 75.0,
+            min_cluster_size: 1,
+            max_cluster_size: None,
+            dbscan_min_points: 2,
+            grid_cell_size: 32,
+            hierarchical_merge_distance: 10.0,
+            hierarchical_merge_window_ns: 150.0,
+            connectivity: Connectivity::Eight,
+        }
+    }
+
+    #[test]
+    fn labels_are_keyed_on_clustering_parameters() {
+        let mut cache = StageCache::default();
+        assert!(cache.labels_for(&clustering_key(5.0)).is_none());
+
+        cache.store(Arc::new(ClusterLabels {
+            key: clustering_key(5.0),
+            batches: vec![BatchLabels {
+                cluster_id: vec![0, 0, 1],
+                num_clusters: 2,
+            }],
+        }));
+        let labels = cache.labels_for(&clustering_key(5.0)).unwrap();
+        assert_eq!(labels.memory_bytes(), 12);
+        assert!(cache.labels_for(&clustering_key(6.0)).is_none());
+
+        cache.invalidate();
+        assert!(cache.labels_for(&clustering_key(5.0)).is_none());
+    }
+}
@@ -2,18 +2,25 @@
 //!
 //! This module handles neutron clustering in a background thread,
 //! processing time-ordered hit batches and extracting neutron events.
+//! When cluster labels from a previous run with the same clustering
+//! parameters are supplied, only the extraction stage is re-run.
 
 use std::path::Path;
 use std::sync::mpsc::Sender;
+use std::sync::Arc;
 use std::time::{Duration, Instant};
 
-use rustpix_algorithms::{cluster_and_extract_batch, AlgorithmParams, ClusteringAlgorithm};
+use rustpix_algorithms::{
+    cluster_batch, extract_batch, AlgorithmParams, ClusteringAlgorithm, Connectivity,
+};
+use rustpix_core::classification::EventClassifier;
 use rustpix_core::clustering::ClusteringConfig;
 use rustpix_core::extraction::ExtractionConfig;
 use rustpix_core::neutron::NeutronBatch;
 use rustpix_io::Tpx3FileReader;
 use rustpix_tpx::DetectorConfig;
 
+use super::cache::{BatchLabels, ClusterLabels, ClusteringKey};
 use super::AlgorithmType;
 use crate::message::AppMessage;
 use crate::util::usize_to_f32;
@@ -32,6 +39,12 @@ pub struct ClusteringWorkerConfig {
     pub dbscan_min_points: usize,
     /// Grid cell size in pixels (Grid algorithm).
     pub grid_cell_size: usize,
+    /// Largest box gap of merged clusters in pixels (Hierarchical).
+    pub hierarchical_merge_distance: f64,
+    /// Largest TOF gap of merged clusters in nanoseconds (Hierarchical).
+    pub hierarchical_merge_window_ns: f64,
+    /// Pixel neighbourhood linking hits (Connected Components).
+    pub connectivity: Connectivity,
     /// Detector configuration (chip layout + timing).
     pub detector_config: DetectorConfig,
     /// Super-resolution factor for extraction.
@@ -40,10 +53,16 @@ pub struct ClusteringWorkerConfig {
     pub weighted_by_tot: bool,
     /// Minimum TOT threshold for extraction.
     pub min_tot_threshold: u16,
+    /// Classifier labelling neutron, gamma and noise events.
+    pub event_classifier: Option<Arc<EventClassifier>>,
     /// Total hits for progress calculation.
     pub total_hits: usize,
     /// Cancellation flag shared with the UI.
     pub cancel_flag: std::sync::Arc<std::sync::atomic::AtomicBool>,
+    /// Cache key for the clustering stage of this run.
+    pub clustering_key: ClusteringKey,
+    /// Labels from a previous run with identical clustering parameters.
+    pub cached_labels: Option<Arc<ClusterLabels>>,
 }
 
 /// Run clustering in a background thread.
@@ -73,11 +92,7 @@ pub fn run_clustering_worker(
         }
     };
 
-    let algo = match algo_type {
-        AlgorithmType::Abs => ClusteringAlgorithm::Abs,
-        AlgorithmType::Dbscan => ClusteringAlgorithm::Dbscan,
-        AlgorithmType::Grid => ClusteringAlgorithm::Grid,
-    };
+    let algo = ClusteringAlgorithm::from(algo_type);
 
     let clustering = ClusteringConfig {
         radius: config.radius,
@@ -90,12 +105,19 @@ pub fn run_clustering_worker(
         abs_scan_interval: 100,
         dbscan_min_points: config.dbscan_min_points,
         grid_cell_size: config.grid_cell_size,
+        hierarchical_merge_distance: config.hierarchical_merge_distance,
+        hierarchical_merge_window_ns: config.hierarchical_merge_window_ns,
+        connectivity: config.connectivity,
+        section_hits: 0,
     };
 
     let extraction = ExtractionConfig {
         super_resolution_factor: config.super_resolution_factor,
         weighted_by_tot: config.weighted_by_tot,
         min_tot_threshold: config.min_tot_threshold,
+        tot_calibration: None,
+        event_classifier: config.event_classifier.clone(),
+        cluster_features: false,
     };
 
     let stream = match reader.stream_time_ordered() {
@@ -110,15 +132,43 @@ pub fn run_clustering_worker(
     let mut last_update = Instant::now();
     let mut neutrons = NeutronBatch::default();
     let total_hits = config.total_hits;
+    let mut cached = config.cached_labels.as_deref();
+    let mut computed: Vec<BatchLabels> = Vec::new();
 
-    for mut batch in stream {
+    for (index, mut batch) in stream.enumerate() {
         if cancel_flag.load(std::sync::atomic::Ordering::SeqCst) {
             return;
         }
         processed_hits = processed_hits.saturating_add(batch.len());
-        let res = cluster_and_extract_batch(&mut batch, algo, &clustering, &extraction, &params);
 
-        match res {
+        // Reuse cached labels while they line up with the streamed batches;
+        // on the first mismatch fall back to clustering for the rest.
+        let reused = cached
+            .and_then(|labels| labels.batches.get(index))
+            .filter(|labels| labels.cluster_id.len() == batch.len());
+        let num_clusters = if let Some(labels) = reused {
+            batch.cluster_id.clone_from(&labels.cluster_id);
+            labels.num_clusters
+        } else {
+            if let Some(labels) = cached.take() {
+                computed.extend(labels.batches.iter().take(index).cloned());
+            }
+            match cluster_batch(&mut batch, algo, &clustering, &params) {
+                Ok(num_clusters) => {
+                    computed.push(BatchLabels {
+                        cluster_id: batch.cluster_id.clone(),
+                        num_clusters,
+                    });
+                    num_clusters
+                }
+                Err(e) => {
+                    let _ = tx.send(AppMessage::ProcessingError(e.to_string()));
+                    return;
+                }
+            }
+        };
+
+        match extract_batch(&batch, num_clusters, &extraction) {
             Ok(n) => neutrons.append(&n),
             Err(e) => {
                 let _ = tx.send(AppMessage::ProcessingError(e.to_string()));
@@ -128,9 +178,14 @@ pub fn run_clustering_worker(
 
         if total_hits > 0 && last_update.elapsed() > Duration::from_millis(200) {
             let progress = (usize_to_f32(processed_hits) / usize_to_f32(total_hits)).min(0.95);
+            let stage = if cached.is_some() {
+                "Extracting"
+            } else {
+                "Processing"
+            };
             let _ = tx.send(AppMessage::ProcessingProgress(
                 progress,
-                format!("Processing... {:.0}%", progress * 100.0),
+                format!("{stage}... {:.0}%", progress * 100.0),
             ));
             last_update = Instant::now();
         }
@@ -139,5 +194,16 @@ pub fn run_clustering_worker(
     if cancel_flag.load(std::sync::atomic::Ordering::SeqCst) {
         return;
     }
-    let _ = tx.send(AppMessage::ProcessingComplete(neutrons, start.elapsed()));
+    // Labels are only sent back when clustering actually ran.
+    let labels = cached.is_none().then(|| {
+        Arc::new(ClusterLabels {
+            key: config.clustering_key.clone(),
+            batches: computed,
+        })
+    });
+    let _ = tx.send(AppMessage::ProcessingComplete(
+        neutrons,
+        start.elapsed(),
+        labels,
+    ));
 }
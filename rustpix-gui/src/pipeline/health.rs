@@ -0,0 +1,168 @@
+//! Per-chip counts for the detector health dashboard.
+//!
+//! Hit and saturation counts are accumulated while a file is loaded, since
+//! `ToT` is not kept in the hit hyperstack. Dead and hot pixel counts are
+//! derived afterwards from the pixel masks, split by each chip's footprint in
+//! global detector coordinates.
+
+use rustpix_core::soa::HitBatch;
+use rustpix_tpx::{DetectorConfig, OutOfBoundsCounts};
+
+/// Largest value of the 10-bit `ToT` counter; hits at this value saturated.
+pub const TOT_SATURATION: u16 = 0x3FF;
+
+/// Hit counts for one chip.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ChipHitCounts {
+    /// Hits recorded on the chip.
+    pub hits: u64,
+    /// Hits with a saturated `ToT`.
+    pub saturated: u64,
+    /// Hits the chip transform mapped outside the detector.
+    pub out_of_bounds: u64,
+}
+
+/// Hit counts for all chips, indexed by chip id.
+#[derive(Clone, Debug, Default)]
+pub struct ChipHitStats {
+    chips: Vec<ChipHitCounts>,
+}
+
+impl ChipHitStats {
+    /// Add the hits of `batch`.
+    pub fn accumulate(&mut self, batch: &HitBatch) {
+        for (&chip_id, &tot) in batch.chip_id.iter().zip(&batch.tot) {
+            let chip = usize::from(chip_id);
+            if chip >= self.chips.len() {
+                self.chips.resize(chip + 1, ChipHitCounts::default());
+            }
+            let counts = &mut self.chips[chip];
+            counts.hits += 1;
+            if tot >= TOT_SATURATION {
+                counts.saturated += 1;
+            }
+        }
+    }
+
+    /// Take the out-of-bounds hits counted by the decoders.
+    pub fn record_out_of_bounds(&mut self, counts: &OutOfBoundsCounts) {
+        for (chip_id, count) in counts.per_chip() {
+            let chip = usize::from(chip_id);
+            if chip >= self.chips.len() {
+                self.chips.resize(chip + 1, ChipHitCounts::default());
+            }
+            self.chips[chip].out_of_bounds = count;
+        }
+    }
+
+    /// Counts of every chip, by chip ID.
+    pub fn chips(&self) -> impl Iterator<Item = (usize, ChipHitCounts)> + '_ {
+        self.chips.iter().copied().enumerate()
+    }
+
+    /// Counts for `chip_id` (zero if the chip recorded no hits).
+    #[must_use]
+    pub fn chip(&self, chip_id: u8) -> ChipHitCounts {
+        self.chips
+            .get(usize::from(chip_id))
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Counts summed over all chips.
+    #[must_use]
+    pub fn total(&self) -> ChipHitCounts {
+        self.chips
+            .iter()
+            .fold(ChipHitCounts::default(), |total, chip| ChipHitCounts {
+                hits: total.hits + chip.hits,
+                saturated: total.saturated + chip.saturated,
+                out_of_bounds: total.out_of_bounds + chip.out_of_bounds,
+            })
+    }
+}
+
+/// Region of the detector image covered by one chip (inclusive bounds).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ChipFootprint {
+    /// Left column.
+    pub x0: usize,
+    /// Top row.
+    pub y0: usize,
+    /// Right column.
+    pub x1: usize,
+    /// Bottom row.
+    pub y1: usize,
+}
+
+impl ChipFootprint {
+    /// Footprint of `chip_id` under the configured transform and geometry.
+    #[must_use]
+    pub fn of(config: &DetectorConfig, chip_id: u8) -> Self {
+        let transform = config.chip_transform(chip_id);
+        let max_x = config.chip_size_x.saturating_sub(1);
+        let max_y = config.chip_size_y.saturating_sub(1);
+        let corners =
+            [(0, 0), (max_x, 0), (0, max_y), (max_x, max_y)].map(|(x, y)| transform.apply(x, y));
+        let xs = corners.map(|(x, _)| usize::from(x));
+        let ys = corners.map(|(_, y)| usize::from(y));
+        Self {
+            x0: xs.into_iter().min().unwrap_or(0),
+            y0: ys.into_iter().min().unwrap_or(0),
+            x1: xs.into_iter().max().unwrap_or(0),
+            y1: ys.into_iter().max().unwrap_or(0),
+        }
+    }
+
+    /// Count set entries of a row-major `mask` of the given `width` inside
+    /// the footprint.
+    #[must_use]
+    pub fn count_set(&self, mask: &[u8], width: usize) -> usize {
+        if width == 0 {
+            return 0;
+        }
+        let height = mask.len() / width;
+        (self.y0..=self.y1.min(height.saturating_sub(1)))
+            .map(|y| {
+                let row = &mask[y * width..(y + 1) * width];
+                row.get(self.x0..=self.x1.min(width - 1))
+                    .map_or(0, |span| span.iter().filter(|&&value| value != 0).count())
+            })
+            .sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_saturation_and_masks_per_chip() {
+        let mut batch = HitBatch::default();
+        for (chip, tot) in [(0u8, 10u16), (0, TOT_SATURATION), (2, 5)] {
+            batch.push((0, 0, 0, tot, 0, chip));
+        }
+        let mut stats = ChipHitStats::default();
+        stats.accumulate(&batch);
+        assert_eq!(
+            stats.chip(0),
+            ChipHitCounts {
+                hits: 2,
+                saturated: 1,
+                out_of_bounds: 0,
+            }
+        );
+        assert_eq!(stats.chip(1), ChipHitCounts::default());
+        assert_eq!(stats.total().hits, 3);
+
+        let config = DetectorConfig::venus_defaults();
+        let (width, height) = config.detector_dimensions();
+        let mut mask = vec![0u8; width * height];
+        let footprint = ChipFootprint::of(&config, 0);
+        mask[footprint.y0 * width + footprint.x0] = 1;
+        mask[footprint.y1 * width + footprint.x1] = 1;
+        assert_eq!(footprint.count_set(&mask, width), 2);
+        let other = ChipFootprint::of(&config, 2);
+        assert_eq!(other.count_set(&mask, width), 0);
+    }
+}
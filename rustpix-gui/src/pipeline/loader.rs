@@ -2,6 +2,8 @@
 //!
 //! This module handles TPX3 file loading in a background thread,
 //! including section scanning, TDC state tracking, and hit processing.
+//! Generic event CSVs from other detectors are loaded as a single pulse, and
+//! processed rustpix outputs are shown without reprocessing.
 
 use std::collections::BinaryHeap;
 use std::fmt::Write;
@@ -11,14 +13,20 @@ use std::time::{Duration, Instant};
 
 use rustpix_core::soa::HitBatch;
 use rustpix_io::scanner::PacketScanner;
+use rustpix_io::{
+    read_generic_csv_hits, read_generic_hdf5_hits, read_processed_file, GenericHitOptions,
+    ProcessedData,
+};
 use rustpix_tpx::ordering::{PulseBatch, PulseReader};
 use rustpix_tpx::section::{scan_section_tdc, Tpx3Section};
-use rustpix_tpx::{ChipTransform, DetectorConfig};
+use rustpix_tpx::{DetectorConfig, OutOfBoundsCounts, OutOfBoundsPolicy};
 
 use crate::histogram::Hyperstack3D;
 use crate::message::AppMessage;
 use crate::util::usize_to_f32;
 
+use super::{ChipHitStats, FileMetadata};
+
 /// Main entry point for file loading in a background thread.
 ///
 /// Opens a TPX3 file, memory-maps it, scans sections, processes hits,
@@ -35,6 +43,14 @@ pub fn load_file_worker(
     if cancel_flag.load(std::sync::atomic::Ordering::SeqCst) {
         return;
     }
+    if path.extension().is_some_and(|ext| {
+        ["csv", "h5", "hdf5", "nxs", "bin", "rpxd"]
+            .iter()
+            .any(|processed| ext.eq_ignore_ascii_case(processed))
+    }) {
+        load_processed_file(path, tx, &detector_config, n_tof_bins, cache_hits, start);
+        return;
+    }
     let file = match std::fs::File::open(path) {
         Ok(f) => f,
         Err(e) => {
@@ -71,8 +87,11 @@ pub fn load_file_worker(
     ));
 
     let tpx_sections = build_tpx_sections(&mmap, io_sections);
+    let metadata = FileMetadata::collect(path, &mmap, &tpx_sections);
+    let _ = tx.send(AppMessage::FileMetadata(Box::new(metadata)));
 
-    let det_config = detector_config;
+    let mut det_config = detector_config;
+    det_config.out_of_bounds_counts = OutOfBoundsCounts::default();
     let tdc_correction = det_config.tdc_correction_25ns();
     let debug_str = build_debug_info(&mmap, &tpx_sections, tdc_correction);
 
@@ -88,7 +107,7 @@ pub fn load_file_worker(
         detector_height,
         tdc_correction,
     );
-    let (full_batch, pulse_bounds, hit_count) = process_sections_to_batch(
+    let (full_batch, pulse_bounds, hit_count, mut chip_stats) = process_sections_to_batch(
         &mmap,
         &tpx_sections,
         &det_config,
@@ -101,6 +120,17 @@ pub fn load_file_worker(
         return;
     }
 
+    let out_of_bounds = &det_config.out_of_bounds_counts;
+    if det_config.out_of_bounds == OutOfBoundsPolicy::Error && out_of_bounds.total() > 0 {
+        let _ = tx.send(AppMessage::LoadError(format!(
+            "{} hits mapped outside the {detector_width}x{detector_height} detector \
+             ({out_of_bounds}); check the chip size and transforms",
+            out_of_bounds.total()
+        )));
+        return;
+    }
+    chip_stats.record_out_of_bounds(out_of_bounds);
+    let _ = tx.send(AppMessage::ChipHitStats(Box::new(chip_stats)));
     let _ = tx.send(AppMessage::LoadComplete(
         hit_count,
         full_batch.map(Box::new),
@@ -111,6 +141,165 @@ pub fn load_file_worker(
     ));
 }
 
+/// Load a processed rustpix output without reprocessing.
+///
+/// Neutron outputs are shown on an empty hit canvas of the detector size
+/// and sent as `ProcessedNeutrons` after `LoadComplete`; hit outputs keep
+/// their cluster labels. CSV and HDF5 files that are not rustpix outputs
+/// are loaded as generic hit lists.
+fn load_processed_file(
+    path: &Path,
+    tx: &Sender<AppMessage>,
+    detector_config: &DetectorConfig,
+    n_tof_bins: usize,
+    cache_hits: bool,
+    start: Instant,
+) {
+    let _ = tx.send(AppMessage::LoadProgress(
+        0.1,
+        "Reading processed output...".to_string(),
+    ));
+    match read_processed_file(path) {
+        Ok(Some(ProcessedData::Hits(hits))) => {
+            send_hit_list(
+                hits,
+                "Processed hit list",
+                detector_config,
+                n_tof_bins,
+                cache_hits,
+                tx,
+                start,
+            );
+        }
+        Ok(Some(ProcessedData::Neutrons {
+            neutrons,
+            super_resolution_factor,
+        })) => {
+            let (width, height) = detector_config.detector_dimensions();
+            let tof_max = neutrons
+                .tof
+                .iter()
+                .max()
+                .map_or(1, |&tof| tof.saturating_add(1));
+            let hyperstack = Hyperstack3D::new(n_tof_bins.max(1), width, height, tof_max);
+            let debug_str = format!(
+                "Processed neutron output: {} neutrons, TOF up to {tof_max} ticks\n",
+                neutrons.len()
+            );
+            let _ = tx.send(AppMessage::LoadComplete(
+                0,
+                None,
+                Box::new(hyperstack),
+                start.elapsed(),
+                debug_str,
+                None,
+            ));
+            let _ = tx.send(AppMessage::ProcessedNeutrons(
+                Box::new(neutrons),
+                super_resolution_factor,
+            ));
+        }
+        Ok(None) => load_hit_list(path, tx, detector_config, n_tof_bins, cache_hits, start),
+        Err(e) => {
+            let _ = tx.send(AppMessage::LoadError(format!("{}: {e}", path.display())));
+        }
+    }
+}
+
+/// Load the hits of a CSV or HDF5 hit list as a single pulse.
+///
+/// Columns are found by their default names.
+fn load_hit_list(
+    path: &Path,
+    tx: &Sender<AppMessage>,
+    detector_config: &DetectorConfig,
+    n_tof_bins: usize,
+    cache_hits: bool,
+    start: Instant,
+) {
+    let _ = tx.send(AppMessage::LoadProgress(
+        0.1,
+        "Reading hit list...".to_string(),
+    ));
+    let options = GenericHitOptions::default();
+    let hits = if path
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("csv"))
+    {
+        std::fs::File::open(path)
+            .map_err(rustpix_io::Error::from)
+            .and_then(|file| read_generic_csv_hits(std::io::BufReader::new(file), &options))
+    } else {
+        read_generic_hdf5_hits(path, None, &options)
+    };
+    let hits = match hits {
+        Ok(hits) => hits,
+        Err(e) => {
+            let _ = tx.send(AppMessage::LoadError(format!("{}: {e}", path.display())));
+            return;
+        }
+    };
+
+    send_hit_list(
+        hits,
+        "Generic event CSV",
+        detector_config,
+        n_tof_bins,
+        cache_hits,
+        tx,
+        start,
+    );
+}
+
+/// Send the hits of a file without TDC pulses as a single pulse.
+///
+/// The canvas grows to fit the hits and the TOF axis spans the latest hit,
+/// since such files have neither chip geometry nor TDC pulses.
+fn send_hit_list(
+    hits: HitBatch,
+    label: &str,
+    detector_config: &DetectorConfig,
+    n_tof_bins: usize,
+    cache_hits: bool,
+    tx: &Sender<AppMessage>,
+    start: Instant,
+) {
+    let (width, height) = detector_config.detector_dimensions();
+    let extent = |values: &[u16]| values.iter().max().map_or(0, |&v| usize::from(v) + 1);
+    let tof_max = hits
+        .tof
+        .iter()
+        .max()
+        .map_or(1, |&tof| tof.saturating_add(1));
+    let hyperstack = Hyperstack3D::from_hits(
+        &hits,
+        n_tof_bins.max(1),
+        tof_max,
+        width.max(extent(&hits.x)),
+        height.max(extent(&hits.y)),
+    );
+    let mut chip_stats = ChipHitStats::default();
+    chip_stats.accumulate(&hits);
+    let hit_count = hits.len();
+    let pulse_bounds = cache_hits.then(|| {
+        vec![crate::message::PulseBounds {
+            tdc_timestamp_25ns: 0,
+            start: 0,
+            len: hit_count,
+        }]
+    });
+    let debug_str = format!("{label}: {hit_count} hits, TOF up to {tof_max} ticks\n");
+    let _ = tx.send(AppMessage::ChipHitStats(Box::new(chip_stats)));
+    let _ = tx.send(AppMessage::LoadComplete(
+        hit_count,
+        cache_hits.then(|| Box::new(hits)),
+        Box::new(hyperstack),
+        start.elapsed(),
+        debug_str,
+        pulse_bounds,
+    ));
+}
+
 /// Scan sections in chunks with progress reporting.
 ///
 /// Processes the memory-mapped file in 50MB chunks, scanning for
@@ -246,6 +435,7 @@ fn process_sections_to_batch(
     Option<HitBatch>,
     Option<Vec<crate::message::PulseBounds>>,
     usize,
+    ChipHitStats,
 ) {
     let total_packets: usize = sections.iter().map(Tpx3Section::packet_count).sum();
     let mut full_batch = cache_hits.then(|| HitBatch::with_capacity(total_packets));
@@ -260,6 +450,7 @@ fn process_sections_to_batch(
 
     let progress_denominator = total_packets.max(1);
     let mut processed_hits = 0usize;
+    let mut chip_stats = ChipHitStats::default();
     let mut last_update = Instant::now();
     let mut receivers: Vec<Option<std::sync::mpsc::Receiver<PulseBatch>>> =
         Vec::with_capacity(max_chip + 1);
@@ -276,15 +467,20 @@ fn process_sections_to_batch(
             receivers[chip_id] = Some(rx_batch);
 
             let chip_sections = chip_sections.clone();
-            let transform = det_config
-                .chip_transforms
-                .get(chip_id)
-                .cloned()
-                .unwrap_or_else(ChipTransform::identity);
+            let transform = det_config.canvas_transform(u8::try_from(chip_id).unwrap_or(u8::MAX));
+            let toa_correction = det_config
+                .toa_correction(u8::try_from(chip_id).unwrap_or(u8::MAX))
+                .cloned();
+            let columns = det_config.chip_size_x.max(256);
+            let no_tdc_fallback = det_config.no_tdc_fallback;
+            let tdc_policy = det_config.tdc_correction_policy;
             scope.spawn(move || {
-                let transform_closure = move |_cid, x, y| transform.apply(x, y);
+                let transform_closure = move |_cid, x, y| transform.map(x, y);
                 let mut reader =
-                    PulseReader::new(mmap, &chip_sections, tdc_correction, transform_closure);
+                    PulseReader::new(mmap, &chip_sections, tdc_correction, transform_closure)
+                        .with_toa_correction(toa_correction.as_ref(), columns)
+                        .with_no_tdc_fallback(no_tdc_fallback)
+                        .with_tdc_correction(tdc_policy);
                 while let Some(batch) = reader.next_pulse() {
                     if cancel_flag.load(std::sync::atomic::Ordering::SeqCst) {
                         break;
@@ -344,6 +540,7 @@ fn process_sections_to_batch(
             }
             processed_hits = processed_hits.saturating_add(merged.len());
             hyperstack.accumulate_hits(&merged);
+            chip_stats.accumulate(&merged);
 
             if last_update.elapsed() > Duration::from_millis(200) {
                 let progress = 0.25
@@ -357,7 +554,7 @@ fn process_sections_to_batch(
         }
     });
 
-    (full_batch, pulse_bounds, processed_hits)
+    (full_batch, pulse_bounds, processed_hits, chip_stats)
 }
 
 fn recv_batch_with_cancel(
@@ -0,0 +1,250 @@
+//! Acquisition metadata gathered when a file is opened.
+//!
+//! A single pass over the scanned sections collects what can be known about
+//! an acquisition before any hits are processed: which chips are present,
+//! the span of the detector clock, and an estimate of the TDC (pulse)
+//! frequency. Clock values are 30-bit 25 ns counters that roll over every
+//! ~26.8 s, so they are extended per chip as the file is walked.
+
+use std::path::Path;
+use std::time::{Duration, SystemTime};
+
+use rustpix_tpx::section::Tpx3Section;
+use rustpix_tpx::tdc::TdcFrequencyEstimate;
+use rustpix_tpx::Tpx3Packet;
+
+/// Period of the 30-bit timestamp counters in 25 ns ticks.
+const CLOCK_PERIOD: u64 = 1 << 30;
+
+/// Which packets the reported time span comes from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ClockSource {
+    /// TDC (pulse trigger) packets.
+    Tdc,
+    /// Hit time-of-arrival, used when the file has no TDC packets.
+    Hits,
+}
+
+/// Acquisition information for an opened file.
+#[derive(Clone, Debug, Default)]
+pub struct FileMetadata {
+    /// File size in bytes.
+    pub size_bytes: u64,
+    /// Filesystem creation time (not available on every platform).
+    pub created: Option<SystemTime>,
+    /// Filesystem modification time.
+    pub modified: Option<SystemTime>,
+    /// Number of chip data sections.
+    pub sections: usize,
+    /// Chips with at least one section, ascending.
+    pub chip_ids: Vec<u8>,
+    /// TDC packets seen on the reference chip.
+    pub tdc_count: usize,
+    /// Hit packets across all chips.
+    pub hit_packets: usize,
+    /// First clock value in 25 ns ticks.
+    pub first_timestamp_25ns: Option<u64>,
+    /// Last clock value in 25 ns ticks, extended across rollovers.
+    pub last_timestamp_25ns: Option<u64>,
+    /// Source of the first/last timestamps.
+    pub clock_source: Option<ClockSource>,
+    /// Pulse frequency estimated from the median TDC interval.
+    pub tdc_frequency: Option<TdcFrequencyEstimate>,
+    /// Pulses missing from the reference chip's TDC stream, judged against
+    /// the median spacing.
+    pub missing_pulses: u64,
+}
+
+impl FileMetadata {
+    /// Collect metadata for `path` from its already scanned `sections`.
+    #[must_use]
+    pub fn collect(path: &Path, data: &[u8], sections: &[Tpx3Section]) -> Self {
+        let (created, modified) = std::fs::metadata(path).map_or((None, None), |meta| {
+            (meta.created().ok(), meta.modified().ok())
+        });
+
+        let mut chips: Vec<ChipClock> = Vec::new();
+        let mut chip_slots = [None::<usize>; 256];
+        for section in sections {
+            let slot = *chip_slots[usize::from(section.chip_id)].get_or_insert_with(|| {
+                chips.push(ChipClock::new(section.chip_id));
+                chips.len() - 1
+            });
+            chips[slot].scan(&data[section.start_offset..section.end_offset]);
+        }
+        chips.sort_by_key(|chip| chip.chip_id);
+
+        let mut metadata = Self {
+            size_bytes: data.len() as u64,
+            created,
+            modified,
+            sections: sections.len(),
+            chip_ids: chips.iter().map(|chip| chip.chip_id).collect(),
+            hit_packets: chips.iter().map(|chip| chip.hits.count).sum(),
+            ..Self::default()
+        };
+
+        // The chip with the most TDCs is the least likely to have dropped any.
+        let reference = chips.iter_mut().max_by_key(|chip| chip.tdc.count);
+        if let Some(chip) = reference.filter(|chip| chip.tdc.count > 0) {
+            metadata.tdc_count = chip.tdc.count;
+            metadata.first_timestamp_25ns = chip.tdc.first;
+            metadata.last_timestamp_25ns = chip.tdc.last;
+            metadata.clock_source = Some(ClockSource::Tdc);
+            metadata.tdc_frequency =
+                TdcFrequencyEstimate::from_intervals(chip.chip_id, &mut chip.tdc_intervals);
+            if let Some(estimate) = &metadata.tdc_frequency {
+                metadata.missing_pulses =
+                    count_missing_pulses(&chip.tdc_intervals, estimate.median_interval_25ns);
+            }
+        } else {
+            let first = chips.iter().filter_map(|chip| chip.hits.first).min();
+            let last = chips.iter().filter_map(|chip| chip.hits.last).max();
+            if first.is_some() {
+                metadata.first_timestamp_25ns = first;
+                metadata.last_timestamp_25ns = last;
+                metadata.clock_source = Some(ClockSource::Hits);
+            }
+        }
+
+        metadata
+    }
+
+    /// Time between the first and last clock value.
+    #[must_use]
+    pub fn duration(&self) -> Option<Duration> {
+        let (first, last) = (self.first_timestamp_25ns?, self.last_timestamp_25ns?);
+        Some(Duration::from_nanos(last.saturating_sub(first) * 25))
+    }
+}
+
+/// Count pulses missing from TDC `intervals` spaced about `period` apart.
+fn count_missing_pulses(intervals: &[u32], period: u32) -> u64 {
+    let period = u64::from(period.max(1));
+    intervals
+        .iter()
+        .map(|&interval| (u64::from(interval) + period / 2) / period)
+        .filter(|&periods| periods >= 2)
+        .map(|periods| periods - 1)
+        .sum()
+}
+
+/// Extends a wrapping 30-bit counter into a monotonic 64-bit value.
+#[derive(Default)]
+struct ExtendedClock {
+    count: usize,
+    first: Option<u64>,
+    last: Option<u64>,
+    epoch: u64,
+    previous: Option<u32>,
+}
+
+impl ExtendedClock {
+    /// Record a raw counter value and return its extended value.
+    fn record(&mut self, raw: u32) -> u64 {
+        if let Some(previous) = self.previous {
+            // Hits are only roughly ordered, so only a backwards jump of more
+            // than half the period counts as a rollover.
+            if u64::from(previous) > u64::from(raw) + CLOCK_PERIOD / 2 {
+                self.epoch += CLOCK_PERIOD;
+            }
+        }
+        self.previous = Some(raw);
+        let extended = self.epoch + u64::from(raw);
+        self.count += 1;
+        self.first.get_or_insert(extended);
+        self.last = Some(self.last.map_or(extended, |last| last.max(extended)));
+        extended
+    }
+}
+
+/// Clock state for one chip.
+struct ChipClock {
+    chip_id: u8,
+    tdc: ExtendedClock,
+    hits: ExtendedClock,
+    tdc_intervals: Vec<u32>,
+}
+
+impl ChipClock {
+    fn new(chip_id: u8) -> Self {
+        Self {
+            chip_id,
+            tdc: ExtendedClock::default(),
+            hits: ExtendedClock::default(),
+            tdc_intervals: Vec::new(),
+        }
+    }
+
+    fn scan(&mut self, section: &[u8]) {
+        for chunk in section.chunks_exact(8) {
+            let packet = Tpx3Packet::new(u64::from_le_bytes(chunk.try_into().unwrap()));
+            if packet.is_tdc() {
+                let previous = self.tdc.last;
+                let current = self.tdc.record(packet.tdc_timestamp());
+                if let Some(interval) = previous
+                    .and_then(|previous| current.checked_sub(previous))
+                    .and_then(|interval| u32::try_from(interval).ok())
+                    .filter(|&interval| interval > 0)
+                {
+                    self.tdc_intervals.push(interval);
+                }
+            } else if packet.is_hit() {
+                self.hits.record(packet.timestamp_coarse());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rustpix_tpx::section::discover_sections;
+
+    fn make_header(chip_id: u8) -> u64 {
+        Tpx3Packet::TPX3_HEADER_MAGIC | (u64::from(chip_id) << 32)
+    }
+
+    fn make_tdc(timestamp: u32) -> u64 {
+        0x6F00_0000_0000_0000 | (u64::from(timestamp) << 12)
+    }
+
+    fn make_hit(toa: u16) -> u64 {
+        0xB000_0000_0000_0000 | (u64::from(toa) << 30)
+    }
+
+    #[test]
+    fn collects_chips_span_and_tdc_frequency() {
+        // 60 Hz pulses: 666_667 ticks of 25 ns, crossing a 30-bit rollover.
+        let period = 666_667u32;
+        let start = (1u32 << 30) - period;
+        let tdcs: Vec<u32> = (0..4)
+            .map(|i| start.wrapping_add(i * period) & 0x3FFF_FFFF)
+            .collect();
+
+        let mut packets = vec![make_header(2)];
+        for &tdc in &tdcs {
+            packets.push(make_tdc(tdc));
+            packets.push(make_hit(10));
+        }
+        // One pulse dropped before the last TDC.
+        packets.extend([make_tdc((start + 5 * period) & 0x3FFF_FFFF), make_hit(10)]);
+        packets.extend([make_header(0), make_hit(20)]);
+        let data: Vec<u8> = packets.iter().flat_map(|p| p.to_le_bytes()).collect();
+        let sections = discover_sections(&data);
+
+        let metadata = FileMetadata::collect(Path::new("missing.tpx3"), &data, &sections);
+        assert_eq!(metadata.chip_ids, vec![0, 2]);
+        assert_eq!(metadata.sections, 2);
+        assert_eq!(metadata.tdc_count, 5);
+        assert_eq!(metadata.hit_packets, 6);
+        assert_eq!(metadata.missing_pulses, 1);
+        assert_eq!(metadata.clock_source, Some(ClockSource::Tdc));
+        assert_eq!(
+            metadata.duration(),
+            Some(Duration::from_nanos(5 * u64::from(period) * 25))
+        );
+        let hz = metadata.tdc_frequency.as_ref().unwrap().frequency_hz;
+        assert!((hz - 60.0).abs() < 0.01, "{hz}");
+    }
+}
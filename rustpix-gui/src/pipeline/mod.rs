@@ -1,13 +1,22 @@
 //! Processing pipeline modules for file loading and clustering.
 
+mod cache;
 mod clustering;
+mod health;
 mod loader;
+mod metadata;
 
+pub use cache::{detector_fingerprint, ClusterLabels, ClusteringKey, StageCache};
 pub use clustering::{run_clustering_worker, ClusteringWorkerConfig};
+pub use health::{ChipFootprint, ChipHitCounts, ChipHitStats};
 pub use loader::load_file_worker;
+pub use metadata::{ClockSource, FileMetadata};
+
+use rustpix_algorithms::ClusteringAlgorithm;
+use serde::{Deserialize, Serialize};
 
 /// Algorithm type selection for clustering.
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum AlgorithmType {
     /// Age-Based Spatial clustering (streaming).
     Abs,
@@ -15,6 +24,10 @@ pub enum AlgorithmType {
     Dbscan,
     /// Grid-based spatial partitioning (fastest).
     Grid,
+    /// Grid clusters merged by a time-then-space linkage.
+    Hierarchical,
+    /// Connected-component labeling of touching pixels.
+    ConnectedComponents,
 }
 
 impl std::fmt::Display for AlgorithmType {
@@ -23,6 +36,20 @@ impl std::fmt::Display for AlgorithmType {
             AlgorithmType::Abs => write!(f, "ABS (Adaptive Box Search)"),
             AlgorithmType::Dbscan => write!(f, "DBSCAN"),
             AlgorithmType::Grid => write!(f, "Grid (Spatial Partition)"),
+            AlgorithmType::Hierarchical => write!(f, "Hierarchical (Merged Tracks)"),
+            AlgorithmType::ConnectedComponents => write!(f, "Connected Components (Blobs)"),
+        }
+    }
+}
+
+impl From<AlgorithmType> for ClusteringAlgorithm {
+    fn from(algorithm: AlgorithmType) -> Self {
+        match algorithm {
+            AlgorithmType::Abs => Self::Abs,
+            AlgorithmType::Dbscan => Self::Dbscan,
+            AlgorithmType::Grid => Self::Grid,
+            AlgorithmType::Hierarchical => Self::Hierarchical,
+            AlgorithmType::ConnectedComponents => Self::ConnectedComponents,
         }
     }
 }
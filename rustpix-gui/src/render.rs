@@ -0,0 +1,131 @@
+//! Headless rendering of a saved session into report figures.
+//!
+//! `rustpix-gui --render session.json --output figures/` restores a session
+//! saved with "Save session…" the way crash recovery does (file, settings,
+//! ROIs) and writes the figures an experiment report needs without opening a
+//! window: the summed image, the same image with the ROIs outlined and the
+//! spectrum plot of the full field of view and every ROI. With
+//! `--auto-process` the file is also clustered and the neutron view rendered
+//! the same way.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::Duration;
+
+use anyhow::{bail, Context as _};
+use eframe::egui;
+use image::RgbaImage;
+
+use crate::app::RustpixApp;
+use crate::state::{read_snapshot, ViewMode};
+use crate::ui::theme::ThemeColors;
+
+/// Polling interval while the load and clustering workers run.
+const POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+/// A session to render and where to put the figures.
+pub(crate) struct RenderRequest {
+    /// Session file.
+    pub session: PathBuf,
+    /// Directory the figures are written to (created if missing).
+    pub output: PathBuf,
+    /// TOF bins of both histograms, overriding the session's.
+    pub tof_bins: Option<usize>,
+    /// Also cluster the file and render the neutron view.
+    pub neutrons: bool,
+}
+
+/// Render the figures of `request`, returning the files written.
+///
+/// # Errors
+/// Returns an error if the session or its data file cannot be read, loading
+/// or clustering fails, or a figure cannot be written.
+pub(crate) fn render_session(request: &RenderRequest) -> anyhow::Result<Vec<PathBuf>> {
+    let mut snapshot = read_snapshot(&request.session)
+        .with_context(|| format!("reading {}", request.session.display()))?
+        .with_context(|| {
+            format!(
+                "{} is not a session saved by this version of rustpix",
+                request.session.display()
+            )
+        })?;
+    match &snapshot.selected_file {
+        Some(path) if path.is_file() => {}
+        Some(path) => bail!("data file {} of the session not found", path.display()),
+        None => bail!("the session has no data file"),
+    }
+    if let Some(bins) = request.tof_bins {
+        snapshot.settings.hit_tof_bins = bins;
+        snapshot.settings.neutron_tof_bins = bins;
+    }
+
+    let ctx = egui::Context::default();
+    let mut app = RustpixApp::headless();
+    app.restore_autosave(snapshot);
+    app.ui_state.histogram.slicer_enabled = false;
+    app.wait_for_workers(&ctx);
+    if app.hyperstack.is_none() {
+        bail!("loading failed: {}", app.processing.status_text);
+    }
+
+    fs::create_dir_all(&request.output)
+        .with_context(|| format!("creating {}", request.output.display()))?;
+    let mut written = app.render_view_figures(&request.output, "hits")?;
+    if request.neutrons {
+        app.run_processing();
+        app.wait_for_workers(&ctx);
+        if !app.has_neutrons() {
+            bail!("clustering failed: {}", app.processing.status_text);
+        }
+        app.ui_state.view_mode = ViewMode::Neutrons;
+        written.extend(app.render_view_figures(&request.output, "neutrons")?);
+    }
+    Ok(written)
+}
+
+impl RustpixApp {
+    /// Handle worker messages until loading and clustering are done.
+    fn wait_for_workers(&mut self, ctx: &egui::Context) {
+        while self.processing.is_loading || self.processing.is_processing {
+            self.handle_messages(ctx);
+            thread::sleep(POLL_INTERVAL);
+        }
+    }
+
+    /// Write `<prefix>_image.png`, `<prefix>_rois.png` (if any ROI is
+    /// visible) and `<prefix>_spectrum.png` of the current view into `dir`.
+    fn render_view_figures(&mut self, dir: &Path, prefix: &str) -> anyhow::Result<Vec<PathBuf>> {
+        self.force_roi_spectra_update();
+        let mut written = Vec::new();
+        let mut save = |name: &str, image: &RgbaImage| -> anyhow::Result<()> {
+            let path = dir.join(format!("{prefix}_{name}.png"));
+            image
+                .save(&path)
+                .with_context(|| format!("writing {}", path.display()))?;
+            written.push(path);
+            Ok(())
+        };
+
+        let image = color_image_to_rgba(&self.generate_histogram())?;
+        save("image", &image)?;
+        if let Some(overlay) = self.roi_overlay_image(&image) {
+            save("rois", &overlay)?;
+        }
+        if let Some(plot) = self.spectrum_plot_image(ThemeColors::from_dark_mode(false)) {
+            save("spectrum", &plot)?;
+        }
+        Ok(written)
+    }
+}
+
+fn color_image_to_rgba(image: &egui::ColorImage) -> anyhow::Result<RgbaImage> {
+    let [width, height] = image.size;
+    let bytes = image
+        .pixels
+        .iter()
+        .flat_map(|pixel| pixel.to_array())
+        .collect();
+    RgbaImage::from_raw(u32::try_from(width)?, u32::try_from(height)?, bytes)
+        .context("image buffer does not match its size")
+}
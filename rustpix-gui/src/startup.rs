@@ -0,0 +1,221 @@
+//! Command-line flags that set up the GUI's initial state.
+//!
+//! Scripts can open the viewer ready to inspect, e.g.
+//! `rustpix-gui --file run.tpx3 --auto-process --view neutrons --tof-bins 300`
+//! loads the file, clusters it with the current settings once loading
+//! finishes and switches to the neutron view when that is done.
+//! `--render session.json` renders a saved session to PNG figures instead of
+//! opening a window (see [`crate::render`]).
+
+use std::path::PathBuf;
+
+use crate::app::RustpixApp;
+use crate::render::RenderRequest;
+use crate::state::ViewMode;
+
+/// Usage text printed for `--help` and after a bad flag.
+pub(crate) const USAGE: &str = "\
+Usage: rustpix-gui [OPTIONS] [FILE]
+
+Options:
+  -f, --file <FILE>     Load FILE on startup
+      --auto-process    Run clustering once the file is loaded
+      --view <VIEW>     View to show: hits or neutrons (neutrons needs --auto-process)
+      --tof-bins <N>    TOF bins of the hit and neutron histograms
+      --render <FILE>   Write figures of a saved session without opening a window
+  -o, --output <DIR>    Directory for --render figures [default: .]
+  -h, --help            Print this help";
+
+/// Initial state requested on the command line.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub(crate) struct StartupOptions {
+    /// File to load.
+    pub file: Option<PathBuf>,
+    /// Cluster the file once it is loaded.
+    pub auto_process: bool,
+    /// View to show once its data is ready.
+    pub view: Option<ViewMode>,
+    /// TOF bins of both histograms.
+    pub tof_bins: Option<usize>,
+    /// Session to render headlessly.
+    pub render: Option<PathBuf>,
+    /// Directory for rendered figures.
+    pub output: Option<PathBuf>,
+    /// Print the usage and exit.
+    pub help: bool,
+}
+
+impl StartupOptions {
+    /// Parse the arguments after the program name.
+    ///
+    /// # Errors
+    /// Returns a message naming the bad or missing argument.
+    pub(crate) fn parse<I>(args: I) -> Result<Self, String>
+    where
+        I: IntoIterator<Item = String>,
+    {
+        let mut options = Self::default();
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            let (flag, inline) = match arg.split_once('=') {
+                Some((flag, value)) if flag.starts_with("--") => (flag.to_string(), Some(value)),
+                _ => (arg.clone(), None),
+            };
+            let mut value = |name: &str| {
+                inline
+                    .map(str::to_string)
+                    .or_else(|| args.next())
+                    .ok_or_else(|| format!("{name} needs a value"))
+            };
+            match flag.as_str() {
+                "-h" | "--help" => options.help = true,
+                "-f" | "--file" => options.set_file(PathBuf::from(value("--file")?))?,
+                "--auto-process" => options.auto_process = true,
+                "--view" => options.view = Some(parse_view(&value("--view")?)?),
+                "--tof-bins" => options.tof_bins = Some(parse_tof_bins(&value("--tof-bins")?)?),
+                "--render" => options.render = Some(PathBuf::from(value("--render")?)),
+                "-o" | "--output" => options.output = Some(PathBuf::from(value("--output")?)),
+                _ if flag.starts_with('-') && flag.len() > 1 => {
+                    return Err(format!("unknown option '{flag}'"));
+                }
+                _ => options.set_file(PathBuf::from(arg))?,
+            }
+        }
+        if options.help {
+            return Ok(options);
+        }
+        if options.render.is_some() {
+            if options.file.is_some() || options.view.is_some() {
+                return Err("--render takes its file and views from the session".to_string());
+            }
+            return Ok(options);
+        }
+        if options.output.is_some() {
+            return Err("--output is only used with --render".to_string());
+        }
+        if options.file.is_none() && (options.auto_process || options.view.is_some()) {
+            return Err("--auto-process and --view need a file to load".to_string());
+        }
+        if options.view == Some(ViewMode::Neutrons) && !options.auto_process {
+            return Err("--view neutrons needs --auto-process".to_string());
+        }
+        Ok(options)
+    }
+
+    /// The headless render requested with `--render`, if any.
+    pub(crate) fn render_request(&self) -> Option<RenderRequest> {
+        self.render.clone().map(|session| RenderRequest {
+            session,
+            output: self.output.clone().unwrap_or_else(|| PathBuf::from(".")),
+            tof_bins: self.tof_bins,
+            neutrons: self.auto_process,
+        })
+    }
+
+    fn set_file(&mut self, path: PathBuf) -> Result<(), String> {
+        if self.file.is_some() {
+            return Err("only one file can be loaded on startup".to_string());
+        }
+        self.file = Some(path);
+        Ok(())
+    }
+}
+
+fn parse_view(value: &str) -> Result<ViewMode, String> {
+    match value.to_ascii_lowercase().as_str() {
+        "hits" => Ok(ViewMode::Hits),
+        "neutrons" => Ok(ViewMode::Neutrons),
+        _ => Err(format!("unknown view '{value}', expected hits or neutrons")),
+    }
+}
+
+fn parse_tof_bins(value: &str) -> Result<usize, String> {
+    match value.parse::<usize>() {
+        Ok(bins) if bins > 0 => Ok(bins),
+        _ => Err(format!(
+            "--tof-bins must be a positive integer, got '{value}'"
+        )),
+    }
+}
+
+impl RustpixApp {
+    /// Apply command-line startup options to a freshly created app.
+    ///
+    /// A startup file takes the place of any session offered for recovery.
+    pub(crate) fn apply_startup_options(&mut self, options: StartupOptions) {
+        if let Some(bins) = options.tof_bins {
+            self.hit_tof_bins = bins;
+            self.neutron_tof_bins = bins;
+        }
+        let Some(path) = options.file else {
+            return;
+        };
+        self.autosave.skip_recovery();
+        self.load_file(path);
+        self.startup_auto_process = options.auto_process;
+        self.startup_view = options.view;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<StartupOptions, String> {
+        StartupOptions::parse(args.iter().map(ToString::to_string))
+    }
+
+    #[test]
+    fn test_parse_full_startup() {
+        let options = parse(&[
+            "--file",
+            "run.tpx3",
+            "--auto-process",
+            "--view",
+            "neutrons",
+            "--tof-bins=300",
+        ])
+        .unwrap();
+        assert_eq!(options.file, Some(PathBuf::from("run.tpx3")));
+        assert!(options.auto_process);
+        assert_eq!(options.view, Some(ViewMode::Neutrons));
+        assert_eq!(options.tof_bins, Some(300));
+
+        let positional = parse(&["run.tpx3", "--view", "Hits"]).unwrap();
+        assert_eq!(positional.file, Some(PathBuf::from("run.tpx3")));
+        assert_eq!(positional.view, Some(ViewMode::Hits));
+        assert_eq!(parse(&[]).unwrap(), StartupOptions::default());
+    }
+
+    #[test]
+    fn test_parse_rejects_bad_arguments() {
+        assert!(parse(&["--tof-bins", "0"]).is_err());
+        assert!(parse(&["--tof-bins"]).is_err());
+        assert!(parse(&["--view", "clusters", "run.tpx3"]).is_err());
+        assert!(parse(&["--verbose"]).is_err());
+        assert!(parse(&["--auto-process"]).is_err());
+        assert!(parse(&["run.tpx3", "--view", "neutrons"]).is_err());
+        assert!(parse(&["a.tpx3", "b.tpx3"]).is_err());
+        assert!(parse(&["--help", "--auto-process"]).unwrap().help);
+    }
+
+    #[test]
+    fn test_parse_render() {
+        let options = parse(&["--render", "s.json", "-o", "figs", "--auto-process"]).unwrap();
+        let request = options.render_request().unwrap();
+        assert_eq!(request.session, PathBuf::from("s.json"));
+        assert_eq!(request.output, PathBuf::from("figs"));
+        assert!(request.neutrons);
+        assert_eq!(
+            parse(&["--render", "s.json"])
+                .unwrap()
+                .render_request()
+                .unwrap()
+                .output,
+            PathBuf::from(".")
+        );
+        assert!(parse(&[]).unwrap().render_request().is_none());
+        assert!(parse(&["--render", "s.json", "run.tpx3"]).is_err());
+        assert!(parse(&["run.tpx3", "--output", "figs"]).is_err());
+    }
+}
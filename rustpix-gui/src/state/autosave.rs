@@ -0,0 +1,493 @@
+//! Crash-safe autosave of ROIs and session settings.
+//!
+//! Every running instance writes its snapshots to its own file in the
+//! per-user data directory and holds an OS lock on a matching `.lock` file.
+//! The file is removed on clean shutdown, so an autosave whose lock nobody
+//! holds at startup was left by a session that ended unexpectedly, and its
+//! state can be offered for recovery. Autosaves of instances still running
+//! stay locked and are left alone.
+
+use std::fs::{self, File, OpenOptions, TryLockError};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use rustpix_algorithms::{AlgorithmParams, Connectivity};
+use serde::{Deserialize, Serialize};
+
+use super::ViewTransform;
+use crate::pipeline::AlgorithmType;
+use crate::viewer::Colormap;
+
+/// Snapshot format version; snapshots with a different version are ignored.
+pub const AUTOSAVE_VERSION: u32 = 1;
+/// Seconds between autosave attempts.
+pub const AUTOSAVE_INTERVAL_SECS: f64 = 120.0;
+
+/// Persisted processing and display settings.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct AutosaveSettings {
+    pub algo_type: AlgorithmType,
+    pub radius: f64,
+    pub temporal_window_ns: f64,
+    pub min_cluster_size: u16,
+    pub max_cluster_size: Option<u16>,
+    pub dbscan_min_points: usize,
+    pub grid_cell_size: usize,
+    #[serde(default = "default_merge_distance")]
+    pub hierarchical_merge_distance: f64,
+    #[serde(default = "default_merge_window_ns")]
+    pub hierarchical_merge_window_ns: f64,
+    #[serde(default)]
+    pub connectivity: Connectivity,
+    pub tdc_frequency: f64,
+    pub flight_path_m: f64,
+    pub tof_offset_ns: f64,
+    pub hit_tof_bins: usize,
+    pub neutron_tof_bins: usize,
+    pub super_resolution_factor: f64,
+    pub weighted_by_tot: bool,
+    pub min_tot_threshold: u16,
+    pub hot_pixel_sigma: f64,
+    /// TOF bins judged together for pixels hot in part of the TOF range
+    /// (0 = off).
+    #[serde(default)]
+    pub tof_hot_window: usize,
+    /// Pixel mask file used instead of detected masks.
+    #[serde(default)]
+    pub pixel_mask_file: Option<PathBuf>,
+    pub colormap: Colormap,
+    pub log_scale: bool,
+    pub transform: ViewTransform,
+}
+
+fn default_merge_distance() -> f64 {
+    AlgorithmParams::default().hierarchical_merge_distance
+}
+
+fn default_merge_window_ns() -> f64 {
+    AlgorithmParams::default().hierarchical_merge_window_ns
+}
+
+/// Persisted ROI shape (display coordinates under the saved transform).
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum AutosaveRoiShape {
+    Rectangle { x1: f64, y1: f64, x2: f64, y2: f64 },
+    Polygon { vertices: Vec<(f64, f64)> },
+}
+
+/// Persisted ROI definition.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct AutosaveRoi {
+    pub id: usize,
+    pub name: String,
+    pub color: [u8; 3],
+    pub shape: AutosaveRoiShape,
+    pub visible: bool,
+    pub spectrum_visible: bool,
+    /// Inclusive TOF bin range of the ROI's gated thumbnail.
+    #[serde(default)]
+    pub tof_gate: Option<(usize, usize)>,
+}
+
+/// Full autosave snapshot.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct AutosaveSnapshot {
+    pub version: u32,
+    /// Wall-clock save time (seconds since the Unix epoch).
+    pub saved_at_unix: u64,
+    pub selected_file: Option<PathBuf>,
+    pub settings: AutosaveSettings,
+    pub rois: Vec<AutosaveRoi>,
+}
+
+impl AutosaveSnapshot {
+    /// Build a snapshot stamped with the current time.
+    #[must_use]
+    pub fn new(
+        selected_file: Option<PathBuf>,
+        settings: AutosaveSettings,
+        rois: Vec<AutosaveRoi>,
+    ) -> Self {
+        let saved_at_unix = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs());
+        Self {
+            version: AUTOSAVE_VERSION,
+            saved_at_unix,
+            selected_file,
+            settings,
+            rois,
+        }
+    }
+
+    /// Whether two snapshots hold the same session state (ignoring save time).
+    #[must_use]
+    pub fn same_content(&self, other: &Self) -> bool {
+        self.selected_file == other.selected_file
+            && self.settings == other.settings
+            && self.rois == other.rois
+    }
+
+    /// Seconds elapsed since the snapshot was written.
+    #[must_use]
+    pub fn age_secs(&self) -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs())
+            .saturating_sub(self.saved_at_unix)
+    }
+}
+
+/// Autosave of a crashed session, locked while it is being dealt with.
+pub struct RecoveredAutosave {
+    /// The crashed session's autosave file.
+    pub path: PathBuf,
+    lock: File,
+}
+
+impl RecoveredAutosave {
+    /// Delete the autosave and its lock file.
+    pub fn remove(self) {
+        let Self { path, lock } = self;
+        remove_autosave(&path);
+        drop(lock);
+        let _ = fs::remove_file(lock_path(&path));
+    }
+}
+
+/// Autosave bookkeeping held by the application.
+pub struct AutosaveState {
+    /// This session's autosave file.
+    pub path: PathBuf,
+    /// Lock on this session's `.lock` file, held while it runs.
+    lock: Option<File>,
+    /// Time of the last autosave attempt (egui input time).
+    pub last_attempt: f64,
+    /// Snapshot from the last successful write (unchanged sessions are not rewritten).
+    pub last_saved: Option<AutosaveSnapshot>,
+    /// Snapshot found at startup, awaiting a restore/discard decision.
+    pub pending_recovery: Option<AutosaveSnapshot>,
+    /// Where `pending_recovery` came from; removed once it is discarded, or
+    /// once a restored session has been autosaved again.
+    pub recovered: Option<RecoveredAutosave>,
+}
+
+impl Default for AutosaveState {
+    fn default() -> Self {
+        Self::in_dir(&autosave_dir())
+    }
+}
+
+impl AutosaveState {
+    /// Start a session autosaving to a new file in `dir`, and look there for
+    /// the latest autosave of a crashed session.
+    #[must_use]
+    pub fn in_dir(dir: &Path) -> Self {
+        let saved_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_millis());
+        let path = dir.join(format!("session-{}-{saved_at}.json", std::process::id()));
+        let lock = match lock_session(&path) {
+            Ok(lock) => Some(lock),
+            Err(err) => {
+                log::warn!("Failed to lock autosave {}: {err}", path.display());
+                None
+            }
+        };
+        let (pending_recovery, recovered) = match find_crashed_session(dir) {
+            Some((snapshot, recovered)) => (Some(snapshot), Some(recovered)),
+            None => (None, None),
+        };
+        Self {
+            path,
+            lock,
+            last_attempt: 0.0,
+            last_saved: None,
+            pending_recovery,
+            recovered,
+        }
+    }
+
+    /// State of a session that never autosaves, such as a headless render:
+    /// nothing is locked and no autosave directory is scanned.
+    #[must_use]
+    pub fn disabled() -> Self {
+        Self {
+            path: PathBuf::new(),
+            lock: None,
+            last_attempt: 0.0,
+            last_saved: None,
+            pending_recovery: None,
+            recovered: None,
+        }
+    }
+
+    /// Remove this session's autosave and lock file on clean shutdown, and
+    /// the crashed session's once it was restored.
+    pub fn finish(&mut self) {
+        remove_autosave(&self.path);
+        if self.lock.take().is_some() {
+            let _ = fs::remove_file(lock_path(&self.path));
+        }
+        if self.pending_recovery.is_none() {
+            if let Some(recovered) = self.recovered.take() {
+                recovered.remove();
+            }
+        }
+    }
+
+    /// Leave the crashed session's autosave, if any, for a later start.
+    pub fn skip_recovery(&mut self) {
+        self.pending_recovery = None;
+        self.recovered = None;
+    }
+}
+
+/// Directory holding the autosaves of all sessions
+/// (`<local data dir>/rustpix/autosave`, or the temp directory when the
+/// platform has no data directory).
+#[must_use]
+pub fn autosave_dir() -> PathBuf {
+    dirs::data_local_dir()
+        .unwrap_or_else(std::env::temp_dir)
+        .join("rustpix")
+        .join("autosave")
+}
+
+/// Lock file marking the autosave at `path` as belonging to a live session.
+fn lock_path(path: &Path) -> PathBuf {
+    path.with_extension("lock")
+}
+
+/// Create and lock the lock file of the autosave at `path`.
+fn lock_session(path: &Path) -> io::Result<File> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let file = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(lock_path(path))?;
+    file.try_lock().map_err(io::Error::from)?;
+    Ok(file)
+}
+
+/// Latest autosave in `dir` whose session is no longer running, locked so
+/// that other instances starting meanwhile do not offer it too.
+///
+/// Lock files of ended sessions that never autosaved are removed.
+fn find_crashed_session(dir: &Path) -> Option<(AutosaveSnapshot, RecoveredAutosave)> {
+    let mut latest: Option<(AutosaveSnapshot, RecoveredAutosave)> = None;
+    for entry in fs::read_dir(dir).ok()?.flatten() {
+        let path = entry.path();
+        let Some(ext) = path.extension() else {
+            continue;
+        };
+        if ext == "lock" {
+            let autosave = path.with_extension("json");
+            if !autosave.exists() && try_lock_ended(&autosave).is_some() {
+                let _ = fs::remove_file(&path);
+            }
+            continue;
+        }
+        if ext != "json" {
+            continue;
+        }
+        let Some(lock) = try_lock_ended(&path) else {
+            continue;
+        };
+        let snapshot = match read_snapshot(&path) {
+            Ok(Some(snapshot)) => snapshot,
+            Ok(None) => continue,
+            Err(err) => {
+                log::warn!("Ignoring unreadable autosave {}: {err}", path.display());
+                continue;
+            }
+        };
+        if latest
+            .as_ref()
+            .is_none_or(|(newest, _)| snapshot.saved_at_unix > newest.saved_at_unix)
+        {
+            latest = Some((snapshot, RecoveredAutosave { path, lock }));
+        }
+    }
+    latest
+}
+
+/// Lock the lock file of the autosave at `path`; `None` while its session
+/// is still running.
+fn try_lock_ended(path: &Path) -> Option<File> {
+    let lock = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(lock_path(path))
+        .ok()?;
+    match lock.try_lock() {
+        Ok(()) => Some(lock),
+        Err(TryLockError::WouldBlock) => None,
+        Err(TryLockError::Error(err)) => {
+            log::warn!("Failed to lock autosave {}: {err}", path.display());
+            None
+        }
+    }
+}
+
+/// Serialize a snapshot to pretty JSON.
+///
+/// # Errors
+/// Returns an error if serialization fails.
+pub fn snapshot_to_json(snapshot: &AutosaveSnapshot) -> serde_json::Result<String> {
+    serde_json::to_string_pretty(snapshot)
+}
+
+/// Write serialized snapshot JSON atomically (temp file + rename).
+///
+/// # Errors
+/// Returns an error if the directory or file cannot be written.
+pub fn write_payload(path: &Path, payload: &str) -> io::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let tmp_path = path.with_extension("json.tmp");
+    fs::write(&tmp_path, payload)?;
+    fs::rename(&tmp_path, path)
+}
+
+/// Read a snapshot, returning `None` if absent or from another format version.
+///
+/// # Errors
+/// Returns an error if the file exists but cannot be read or parsed.
+pub fn read_snapshot(path: &Path) -> io::Result<Option<AutosaveSnapshot>> {
+    let text = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(err) => return Err(err),
+    };
+    let snapshot: AutosaveSnapshot = serde_json::from_str(&text)
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+    if snapshot.version != AUTOSAVE_VERSION {
+        return Ok(None);
+    }
+    Ok(Some(snapshot))
+}
+
+/// Remove the autosave file if present.
+pub fn remove_autosave(path: &Path) {
+    if let Err(err) = fs::remove_file(path) {
+        if err.kind() != io::ErrorKind::NotFound {
+            log::warn!("Failed to remove autosave {}: {err}", path.display());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        read_snapshot, snapshot_to_json, write_payload, AutosaveRoi, AutosaveRoiShape,
+        AutosaveSettings, AutosaveSnapshot, AutosaveState,
+    };
+    use crate::pipeline::AlgorithmType;
+    use crate::state::ViewTransform;
+    use crate::viewer::Colormap;
+    use rustpix_algorithms::Connectivity;
+    use std::path::PathBuf;
+
+    fn settings() -> AutosaveSettings {
+        AutosaveSettings {
+            algo_type: AlgorithmType::Dbscan,
+            radius: 4.0,
+            temporal_window_ns: 50.0,
+            min_cluster_size: 2,
+            max_cluster_size: Some(40),
+            dbscan_min_points: 3,
+            grid_cell_size: 16,
+            hierarchical_merge_distance: 12.0,
+            hierarchical_merge_window_ns: 200.0,
+            connectivity: Connectivity::Four,
+            tdc_frequency: 14.0,
+            flight_path_m: 25.0,
+            tof_offset_ns: 10.0,
+            hit_tof_bins: 300,
+            neutron_tof_bins: 400,
+            super_resolution_factor: 8.0,
+            weighted_by_tot: true,
+            min_tot_threshold: 5,
+            hot_pixel_sigma: 4.0,
+            tof_hot_window: 3,
+            pixel_mask_file: Some(PathBuf::from("/data/masks/detector.json")),
+            colormap: Colormap::Viridis,
+            log_scale: true,
+            transform: ViewTransform::default(),
+        }
+    }
+
+    #[test]
+    fn snapshot_round_trips_through_file() {
+        let dir = std::env::temp_dir().join(format!("rustpix-autosave-{}", std::process::id()));
+        let path = dir.join("autosave.json");
+        let snapshot = AutosaveSnapshot::new(
+            Some("run.tpx3".into()),
+            settings(),
+            vec![AutosaveRoi {
+                id: 3,
+                name: "Sample".to_string(),
+                color: [1, 2, 3],
+                shape: AutosaveRoiShape::Polygon {
+                    vertices: vec![(0.0, 0.0), (4.0, 0.0), (2.0, 3.0)],
+                },
+                visible: true,
+                spectrum_visible: false,
+                tof_gate: Some((2, 5)),
+            }],
+        );
+        let payload = snapshot_to_json(&snapshot).expect("serialize");
+        write_payload(&path, &payload).expect("write");
+
+        let restored = read_snapshot(&path).expect("read").expect("present");
+        assert_eq!(restored.rois.len(), 1);
+        assert_eq!(restored.rois[0].name, "Sample");
+        assert_eq!(restored.rois[0].tof_gate, Some((2, 5)));
+        assert_eq!(restored.settings.algo_type, AlgorithmType::Dbscan);
+        assert_eq!(restored.settings.colormap, Colormap::Viridis);
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn only_crashed_sessions_are_recovered() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let payload = |file: &str| {
+            let snapshot = AutosaveSnapshot::new(Some(file.into()), settings(), Vec::new());
+            snapshot_to_json(&snapshot).expect("serialize")
+        };
+
+        // A running instance: its lock is held, so its autosave is left alone.
+        let mut running = AutosaveState::in_dir(dir.path());
+        assert!(running.pending_recovery.is_none());
+        write_payload(&running.path, &payload("running.tpx3")).expect("write");
+        let second = AutosaveState::in_dir(dir.path());
+        assert!(second.pending_recovery.is_none());
+
+        // A crashed instance: an autosave nobody holds the lock of.
+        let crashed = dir.path().join("session-1-0.json");
+        write_payload(&crashed, &payload("crashed.tpx3")).expect("write");
+        let mut third = AutosaveState::in_dir(dir.path());
+        let snapshot = third.pending_recovery.take().expect("recovered");
+        assert_eq!(snapshot.selected_file, Some(PathBuf::from("crashed.tpx3")));
+        third.recovered.take().expect("source").remove();
+        assert!(!crashed.exists());
+
+        running.finish();
+        assert!(!running.path.exists());
+        assert!(AutosaveState::in_dir(dir.path()).pending_recovery.is_none());
+    }
+
+    #[test]
+    fn missing_snapshot_is_none() {
+        let path = std::env::temp_dir().join("rustpix-autosave-does-not-exist.json");
+        assert!(read_snapshot(&path).expect("read").is_none());
+    }
+}
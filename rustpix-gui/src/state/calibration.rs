@@ -0,0 +1,223 @@
+//! Energy calibration from known resonance and Bragg-edge positions.
+//!
+//! The user marks where features of known energy sit on the TOF spectrum.
+//! TOF is linear in `sqrt(m / 2E)`, so a straight-line fit through the marks
+//! gives the flight path (slope) and TOF offset (intercept) of the energy axis.
+
+use crate::util::{energy_ev_to_tof_us, usize_to_f64};
+
+/// Reference features offered by the calibration wizard: name and energy (eV).
+///
+/// Bragg edges are at `λ = 2d` (E = 81.804 meV·Å² / λ²).
+pub const REFERENCE_FEATURES: &[(&str, f64)] = &[
+    ("In-115 resonance", 1.457),
+    ("W-182 resonance", 4.15),
+    ("Ta-181 resonance", 4.28),
+    ("Au-197 resonance", 4.906),
+    ("Ag-109 resonance", 5.19),
+    ("Ag-107 resonance", 16.3),
+    ("W-186 resonance", 18.8),
+    ("Al (111) Bragg edge", 0.003_741),
+    ("Fe (110) Bragg edge", 0.004_978),
+    ("Al (200) Bragg edge", 0.004_989),
+    ("Fe (200) Bragg edge", 0.009_956),
+];
+
+/// A feature of known energy and where it was marked on the spectrum.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CalibrationMark {
+    /// Feature name.
+    pub label: String,
+    /// Known feature energy in eV.
+    pub energy_ev: f64,
+    /// Marked TOF in milliseconds; unset until placed on the spectrum.
+    pub tof_ms: Option<f64>,
+}
+
+/// Flight path and TOF offset fitted to the marks.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CalibrationFit {
+    /// Flight path in meters.
+    pub flight_path_m: f64,
+    /// TOF offset in nanoseconds.
+    pub tof_offset_ns: f64,
+    /// Whether the offset was fitted; with a single energy it is held fixed.
+    pub offset_fitted: bool,
+    /// RMS TOF residual of the marks in microseconds.
+    pub rms_residual_us: f64,
+}
+
+impl CalibrationFit {
+    /// Marked minus predicted TOF of a feature in microseconds.
+    #[must_use]
+    pub fn residual_us(&self, tof_ms: f64, energy_ev: f64) -> Option<f64> {
+        energy_ev_to_tof_us(energy_ev, self.flight_path_m, self.tof_offset_ns)
+            .map(|predicted| tof_ms * 1000.0 - predicted)
+    }
+}
+
+/// Energy calibration wizard state.
+#[derive(Debug, Default)]
+pub struct CalibrationState {
+    /// Features added by the user, in the order added.
+    pub marks: Vec<CalibrationMark>,
+    /// Mark placed by the next click on the spectrum.
+    pub picking: Option<usize>,
+    /// Index into [`REFERENCE_FEATURES`] of the feature to add next.
+    pub selected_reference: usize,
+    /// Energy of a custom feature to add, in eV.
+    pub custom_energy_ev: f64,
+}
+
+impl CalibrationState {
+    /// Add a feature and wait for it to be marked on the spectrum.
+    pub fn add_mark(&mut self, label: impl Into<String>, energy_ev: f64) {
+        self.marks.push(CalibrationMark {
+            label: label.into(),
+            energy_ev,
+            tof_ms: None,
+        });
+        self.picking = Some(self.marks.len() - 1);
+    }
+
+    /// Remove a feature, keeping the pending pick on the same mark.
+    pub fn remove_mark(&mut self, index: usize) {
+        if index >= self.marks.len() {
+            return;
+        }
+        self.marks.remove(index);
+        self.picking = match self.picking {
+            Some(picking) if picking == index => None,
+            Some(picking) if picking > index => Some(picking - 1),
+            picking => picking,
+        };
+    }
+
+    /// Place the pending mark at `tof_ms`. Returns whether a mark was waiting.
+    pub fn place(&mut self, tof_ms: f64) -> bool {
+        let Some(mark) = self
+            .picking
+            .take()
+            .and_then(|index| self.marks.get_mut(index))
+        else {
+            return false;
+        };
+        mark.tof_ms = Some(tof_ms);
+        true
+    }
+
+    /// Fit the placed marks, holding the offset at `fixed_offset_ns` when
+    /// it cannot be fitted.
+    #[must_use]
+    pub fn fit(&self, fixed_offset_ns: f64) -> Option<CalibrationFit> {
+        let points: Vec<(f64, f64)> = self
+            .marks
+            .iter()
+            .filter_map(|mark| mark.tof_ms.map(|tof_ms| (tof_ms, mark.energy_ev)))
+            .collect();
+        fit_calibration(&points, fixed_offset_ns)
+    }
+}
+
+/// Fit flight path and TOF offset to `(tof_ms, energy_ev)` points.
+///
+/// With two or more distinct energies both are fitted by least squares;
+/// otherwise only the flight path is, with the offset held at
+/// `fixed_offset_ns`. Returns `None` without usable points or if the fitted
+/// flight path is not positive.
+#[must_use]
+pub fn fit_calibration(points: &[(f64, f64)], fixed_offset_ns: f64) -> Option<CalibrationFit> {
+    // TOF (µs) = offset + flight path · `per_meter`, with `per_meter` in µs/m.
+    let samples: Vec<(f64, f64)> = points
+        .iter()
+        .filter(|(tof_ms, _)| tof_ms.is_finite())
+        .filter_map(|&(tof_ms, energy_ev)| {
+            energy_ev_to_tof_us(energy_ev, 1.0, 0.0).map(|per_meter| (per_meter, tof_ms * 1000.0))
+        })
+        .collect();
+    if samples.is_empty() {
+        return None;
+    }
+    let count = usize_to_f64(samples.len());
+    let mean_k = samples.iter().map(|(k, _)| k).sum::<f64>() / count;
+    let mean_t = samples.iter().map(|(_, t)| t).sum::<f64>() / count;
+    let spread: f64 = samples.iter().map(|(k, _)| (k - mean_k).powi(2)).sum();
+
+    let offset_fitted = samples.len() >= 2 && spread > 1e-12 * mean_k * mean_k;
+    let (flight_path_m, offset_us) = if offset_fitted {
+        let covariance: f64 = samples
+            .iter()
+            .map(|(k, t)| (k - mean_k) * (t - mean_t))
+            .sum();
+        let slope = covariance / spread;
+        (slope, mean_t - slope * mean_k)
+    } else {
+        let offset_us = fixed_offset_ns / 1000.0;
+        let numerator: f64 = samples.iter().map(|(k, t)| k * (t - offset_us)).sum();
+        let denominator: f64 = samples.iter().map(|(k, _)| k * k).sum();
+        (numerator / denominator, offset_us)
+    };
+    if !flight_path_m.is_finite() || flight_path_m <= 0.0 {
+        return None;
+    }
+
+    let squared: f64 = samples
+        .iter()
+        .map(|(k, t)| (t - offset_us - flight_path_m * k).powi(2))
+        .sum();
+    Some(CalibrationFit {
+        flight_path_m,
+        tof_offset_ns: offset_us * 1000.0,
+        offset_fitted,
+        rms_residual_us: (squared / count).sqrt(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tof_ms(energy_ev: f64, flight_path_m: f64, tof_offset_ns: f64) -> f64 {
+        energy_ev_to_tof_us(energy_ev, flight_path_m, tof_offset_ns).unwrap() / 1000.0
+    }
+
+    #[test]
+    fn fit_recovers_flight_path_and_offset() {
+        let points: Vec<(f64, f64)> = [1.457, 4.906, 0.004_978]
+            .iter()
+            .map(|&energy| (tof_ms(energy, 15.2, 1_200.0), energy))
+            .collect();
+        let fit = fit_calibration(&points, 0.0).unwrap();
+        assert!(fit.offset_fitted);
+        assert!((fit.flight_path_m - 15.2).abs() < 1e-6);
+        assert!((fit.tof_offset_ns - 1_200.0).abs() < 1e-3);
+        assert!(fit.rms_residual_us < 1e-6);
+        assert!(fit.residual_us(points[0].0, 1.457).unwrap().abs() < 1e-6);
+    }
+
+    #[test]
+    fn single_energy_holds_offset() {
+        let points = [(tof_ms(4.28, 10.0, 500.0), 4.28)];
+        let fit = fit_calibration(&points, 500.0).unwrap();
+        assert!(!fit.offset_fitted);
+        assert!((fit.flight_path_m - 10.0).abs() < 1e-9);
+        assert!((fit.tof_offset_ns - 500.0).abs() < 1e-9);
+
+        assert!(fit_calibration(&[], 0.0).is_none());
+        assert!(fit_calibration(&[(0.001, 4.28)], 5_000.0).is_none());
+    }
+
+    #[test]
+    fn picking_follows_marks() {
+        let mut state = CalibrationState::default();
+        state.add_mark("a", 1.0);
+        state.add_mark("b", 2.0);
+        assert_eq!(state.picking, Some(1));
+        state.remove_mark(0);
+        assert_eq!(state.picking, Some(0));
+        assert!(state.place(3.5));
+        assert!(!state.place(4.0));
+        assert_eq!(state.marks[0].tof_ms, Some(3.5));
+        assert!(state.fit(0.0).is_some());
+    }
+}
@@ -0,0 +1,206 @@
+//! Named export profiles.
+//!
+//! A profile captures the export dialog's format, options (including the
+//! HDF5 file name and TIFF base name templates) and destination folder so
+//! routine exports take one click. Profiles are stored as JSON in the user
+//! config directory.
+//!
+//! Name templates expand `{run}` (input file stem), `{date}` (`YYYYMMDD`),
+//! `{time}` (`HHMMSS`, UTC) and `{mode}` (`hits` or `neutrons`); the result
+//! is sanitized like a typed base name.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use super::config_dir;
+use super::ui::{ExportFormat, Hdf5ExportOptions, TiffExportOptions, ViewMode};
+use crate::util::{civil_from_days, sanitize_export_base_name};
+
+/// Tokens recognized in export name templates, for display.
+pub const EXPORT_TEMPLATE_TOKENS: &str = "{run} {date} {time} {mode}";
+
+/// A saved export configuration.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ExportProfile {
+    pub name: String,
+    pub format: ExportFormat,
+    /// Folder to export into without asking; `None` opens a file dialog.
+    pub destination: Option<PathBuf>,
+    pub hdf5: Hdf5ExportOptions,
+    pub tiff: TiffExportOptions,
+}
+
+/// All saved profiles, in the order they were first saved.
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct ExportProfiles {
+    pub profiles: Vec<ExportProfile>,
+}
+
+impl ExportProfiles {
+    /// Load profiles from the user config directory.
+    ///
+    /// A missing or unreadable file yields no profiles.
+    #[must_use]
+    pub fn load() -> Self {
+        let Some(path) = profiles_path() else {
+            return Self::default();
+        };
+        match read_profiles(&path) {
+            Ok(profiles) => profiles,
+            Err(err) => {
+                log::warn!(
+                    "Ignoring unreadable export profiles {}: {err}",
+                    path.display()
+                );
+                Self::default()
+            }
+        }
+    }
+
+    /// Write profiles to the user config directory.
+    ///
+    /// # Errors
+    /// Returns an error if there is no config directory or writing fails.
+    pub fn save(&self) -> io::Result<()> {
+        let path = profiles_path()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no user config directory"))?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let json = serde_json::to_string_pretty(self).map_err(io::Error::other)?;
+        fs::write(&path, json)
+    }
+
+    /// Profile with the given name.
+    #[must_use]
+    pub fn get(&self, name: &str) -> Option<&ExportProfile> {
+        self.profiles.iter().find(|profile| profile.name == name)
+    }
+
+    /// Add a profile, replacing any existing profile with the same name.
+    pub fn upsert(&mut self, profile: ExportProfile) {
+        match self.profiles.iter_mut().find(|p| p.name == profile.name) {
+            Some(existing) => *existing = profile,
+            None => self.profiles.push(profile),
+        }
+    }
+
+    /// Remove the profile with the given name, if any.
+    pub fn remove(&mut self, name: &str) {
+        self.profiles.retain(|profile| profile.name != name);
+    }
+}
+
+/// Export profile bookkeeping held by the export dialog.
+pub struct ExportProfileState {
+    pub profiles: ExportProfiles,
+    /// Profile last applied or saved, if any.
+    pub selected: Option<String>,
+    /// Name typed for the next "Save profile".
+    pub new_name: String,
+}
+
+impl Default for ExportProfileState {
+    fn default() -> Self {
+        Self {
+            profiles: ExportProfiles::load(),
+            selected: None,
+            new_name: String::new(),
+        }
+    }
+}
+
+fn profiles_path() -> Option<PathBuf> {
+    config_dir().map(|dir| dir.join("export_profiles.json"))
+}
+
+fn read_profiles(path: &Path) -> io::Result<ExportProfiles> {
+    match fs::read_to_string(path) {
+        Ok(json) => serde_json::from_str(&json).map_err(io::Error::other),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(ExportProfiles::default()),
+        Err(err) => Err(err),
+    }
+}
+
+/// Expand an export name template and sanitize the result.
+///
+/// `run` falls back to `run` when no input file is loaded. Unknown tokens
+/// are kept literally (and sanitized).
+#[must_use]
+pub fn expand_export_template(
+    template: &str,
+    run: Option<&str>,
+    mode: ViewMode,
+    now: SystemTime,
+) -> String {
+    let secs = now.duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+    let (year, month, day) = civil_from_days(i64::try_from(secs / 86_400).unwrap_or(0));
+    let rem = secs % 86_400;
+    let mode = match mode {
+        ViewMode::Hits => "hits",
+        ViewMode::Neutrons => "neutrons",
+    };
+    let expanded = template
+        .replace("{run}", run.unwrap_or("run"))
+        .replace("{date}", &format!("{year:04}{month:02}{day:02}"))
+        .replace(
+            "{time}",
+            &format!("{:02}{:02}{:02}", rem / 3600, (rem / 60) % 60, rem % 60),
+        )
+        .replace("{mode}", mode);
+    sanitize_export_base_name(&expanded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn export_template_expands_tokens() {
+        // 2024-03-05 07:08:09 UTC
+        let now = UNIX_EPOCH + Duration::from_secs(1_709_622_489);
+        assert_eq!(
+            expand_export_template("{run}_{date}", Some("Run_0042"), ViewMode::Hits, now),
+            "Run_0042_20240305"
+        );
+        assert_eq!(
+            expand_export_template("{mode}-{time} {x}", None, ViewMode::Neutrons, now),
+            "neutrons-070809__x"
+        );
+        assert_eq!(
+            expand_export_template("{run}", None, ViewMode::Hits, now),
+            "run"
+        );
+    }
+
+    #[test]
+    fn export_profiles_upsert_by_name() {
+        let profile = |name: &str, format| ExportProfile {
+            name: name.to_string(),
+            format,
+            destination: None,
+            hdf5: Hdf5ExportOptions::default(),
+            tiff: TiffExportOptions::default(),
+        };
+        let mut profiles = ExportProfiles::default();
+        profiles.upsert(profile("Imaging", ExportFormat::TiffStack));
+        profiles.upsert(profile("Events", ExportFormat::Hdf5));
+        profiles.upsert(profile("Imaging", ExportFormat::TiffFolder));
+        assert_eq!(profiles.profiles.len(), 2);
+        assert_eq!(
+            profiles.get("Imaging").map(|p| p.format),
+            Some(ExportFormat::TiffFolder)
+        );
+
+        let json = serde_json::to_string(&profiles).unwrap();
+        let mut restored: ExportProfiles = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored.profiles[1].name, "Events");
+        restored.remove("Events");
+        assert!(restored.get("Events").is_none());
+    }
+}
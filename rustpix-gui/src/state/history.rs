@@ -0,0 +1,116 @@
+//! Command history recorded as a replayable macro.
+//!
+//! Loads, processing runs and exports are appended as they happen, so the
+//! session can be saved as JSON and replayed with `rustpix run-macro`.
+
+use std::path::Path;
+
+use rustpix_io::{Macro, MacroExport, MacroParameters, MacroStep};
+
+/// Steps performed in this session.
+#[derive(Debug, Default)]
+pub struct CommandHistory {
+    steps: Vec<MacroStep>,
+    /// JSON of the last recorded parameters, to skip unchanged re-runs.
+    last_parameters: Option<String>,
+}
+
+impl CommandHistory {
+    /// Recorded steps, oldest first.
+    #[must_use]
+    pub fn steps(&self) -> &[MacroStep] {
+        &self.steps
+    }
+
+    /// Record opening a file.
+    pub fn record_load(&mut self, path: &Path) {
+        self.steps.push(MacroStep::Load {
+            path: path.to_path_buf(),
+        });
+    }
+
+    /// Record a processing run, preceded by its parameters when they changed.
+    pub fn record_process(&mut self, parameters: MacroParameters) {
+        let json = serde_json::to_string(&parameters).ok();
+        if json.is_none() || json != self.last_parameters {
+            self.steps
+                .push(MacroStep::SetParameters(Box::new(parameters)));
+            self.last_parameters = json;
+        }
+        self.steps.push(MacroStep::Process);
+    }
+
+    /// Record an export.
+    pub fn record_export(&mut self, export: MacroExport) {
+        self.steps.push(MacroStep::Export(export));
+    }
+
+    /// Forget all recorded steps.
+    pub fn clear(&mut self) {
+        self.steps.clear();
+        self.last_parameters = None;
+    }
+
+    /// The recorded steps as pretty-printed macro JSON.
+    ///
+    /// # Errors
+    /// Returns an error if serialization fails.
+    pub fn to_json(&self) -> serde_json::Result<String> {
+        serde_json::to_string_pretty(&Macro::new(self.steps.clone()))
+    }
+}
+
+/// One-line description of a step for the history list.
+#[must_use]
+pub fn describe_step(step: &MacroStep) -> String {
+    match step {
+        MacroStep::Load { path } => format!("Load {}", file_name(path)),
+        MacroStep::SetParameters(parameters) => format!(
+            "Set parameters: {:?}, radius {}, window {} ns",
+            parameters.algorithm, parameters.radius, parameters.temporal_window_ns
+        ),
+        MacroStep::Process => "Process".to_string(),
+        MacroStep::Export(export) => {
+            format!("Export {:?} to {}", export.format, file_name(&export.path))
+        }
+    }
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .unwrap_or(path.as_os_str())
+        .to_string_lossy()
+        .into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parameters(radius: f64) -> MacroParameters {
+        MacroParameters {
+            radius,
+            ..MacroParameters::default()
+        }
+    }
+
+    #[test]
+    fn unchanged_parameters_are_recorded_once() {
+        let mut history = CommandHistory::default();
+        history.record_load(Path::new("/data/run.tpx3"));
+        history.record_process(parameters(5.0));
+        history.record_process(parameters(5.0));
+        history.record_process(parameters(3.0));
+        let actions: Vec<String> = history.steps().iter().map(describe_step).collect();
+        assert_eq!(actions.len(), 6);
+        assert_eq!(actions[0], "Load run.tpx3");
+        assert!(actions[1].starts_with("Set parameters"));
+        assert_eq!(actions[2..4], ["Process", "Process"]);
+        assert!(actions[4].starts_with("Set parameters"));
+
+        let parsed: Macro = serde_json::from_str(&history.to_json().unwrap()).unwrap();
+        assert_eq!(parsed.steps.len(), 6);
+        history.clear();
+        assert!(history.steps().is_empty());
+    }
+}
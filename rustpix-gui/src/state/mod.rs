@@ -1,12 +1,37 @@
 //! Application state modules.
 
+use std::path::PathBuf;
+
+mod autosave;
+mod calibration;
+mod export_profiles;
+mod history;
+mod notifications;
+mod palette;
 mod processing;
 mod statistics;
+mod tour;
 mod ui;
 
+pub use autosave::{
+    read_snapshot, snapshot_to_json, write_payload, AutosaveRoi, AutosaveRoiShape,
+    AutosaveSettings, AutosaveSnapshot, AutosaveState, AUTOSAVE_INTERVAL_SECS,
+};
+pub use calibration::{CalibrationFit, CalibrationState, REFERENCE_FEATURES};
+pub use export_profiles::{expand_export_template, ExportProfile, EXPORT_TEMPLATE_TOKENS};
+pub use history::{describe_step, CommandHistory};
+pub use notifications::{Notification, NotificationLevel, Notifications};
+pub use palette::{fuzzy_score, CommandPaletteState};
 pub use processing::ProcessingState;
 pub use statistics::Statistics;
+pub use tour::{TourAnchor, TourState, TOUR_STEPS};
 pub use ui::{
-    ExportFormat, Hdf5ExportOptions, SpectrumXAxis, TiffBitDepth, TiffExportOptions,
-    TiffSpectraTiming, TiffStackBehavior, UiState, ViewMode, ViewTransform, ZoomMode,
+    ExportFormat, Hdf5ExportOptions, RangeMathOp, SpectrumXAxis, TiffBitDepth, TiffExportOptions,
+    TiffSpectraTiming, TiffStackBehavior, UiRadialProfile, UiState, ViewMode, ViewTransform,
+    ZoomMode,
 };
+
+/// Per-user configuration directory for rustpix.
+fn config_dir() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join("rustpix"))
+}
@@ -0,0 +1,209 @@
+//! Toast notifications and message history.
+
+use std::collections::VecDeque;
+
+/// Maximum number of notifications retained in the history panel.
+const HISTORY_LIMIT: usize = 200;
+
+/// Severity of a notification.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NotificationLevel {
+    Info,
+    Warning,
+    Error,
+}
+
+impl NotificationLevel {
+    /// How long a toast of this level stays on screen (seconds).
+    ///
+    /// Errors are sticky and stay until dismissed.
+    #[must_use]
+    pub fn display_secs(self) -> Option<f64> {
+        match self {
+            Self::Info => Some(4.0),
+            Self::Warning => Some(10.0),
+            Self::Error => None,
+        }
+    }
+
+    /// Short label used in the history panel.
+    #[must_use]
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Info => "Info",
+            Self::Warning => "Warning",
+            Self::Error => "Error",
+        }
+    }
+}
+
+/// A single notification message.
+#[derive(Clone, Debug)]
+pub struct Notification {
+    /// Unique identifier (monotonic).
+    pub id: u64,
+    /// Severity level.
+    pub level: NotificationLevel,
+    /// Message text.
+    pub message: String,
+    /// Time the notification was raised (egui input time, seconds).
+    pub created_at: f64,
+    /// Time at which the toast is hidden (None = until dismissed).
+    pub expires_at: Option<f64>,
+}
+
+/// Active toasts plus a bounded history of past notifications.
+#[derive(Default)]
+pub struct Notifications {
+    /// Toasts currently visible on screen.
+    active: Vec<Notification>,
+    /// All notifications raised this session (oldest first).
+    history: VecDeque<Notification>,
+    /// Number of history entries not yet viewed in the history panel.
+    unread: usize,
+    /// Next notification id.
+    next_id: u64,
+}
+
+impl Notifications {
+    /// Raise an informational toast.
+    pub fn info(&mut self, message: impl Into<String>, now: f64) {
+        self.push(NotificationLevel::Info, message, now);
+    }
+
+    /// Raise a warning toast.
+    pub fn warn(&mut self, message: impl Into<String>, now: f64) {
+        self.push(NotificationLevel::Warning, message, now);
+    }
+
+    /// Raise an error toast (stays until dismissed).
+    pub fn error(&mut self, message: impl Into<String>, now: f64) {
+        self.push(NotificationLevel::Error, message, now);
+    }
+
+    /// Raise a notification at the given level.
+    pub fn push(&mut self, level: NotificationLevel, message: impl Into<String>, now: f64) {
+        let message = message.into();
+        match level {
+            NotificationLevel::Info => log::info!("{message}"),
+            NotificationLevel::Warning => log::warn!("{message}"),
+            NotificationLevel::Error => log::error!("{message}"),
+        }
+        let notification = Notification {
+            id: self.next_id,
+            level,
+            message,
+            created_at: now,
+            expires_at: level.display_secs().map(|secs| now + secs),
+        };
+        self.next_id = self.next_id.wrapping_add(1);
+        self.active.push(notification.clone());
+        self.history.push_back(notification);
+        while self.history.len() > HISTORY_LIMIT {
+            self.history.pop_front();
+        }
+        self.unread = (self.unread + 1).min(self.history.len());
+    }
+
+    /// Drop toasts whose display time has elapsed.
+    pub fn prune(&mut self, now: f64) {
+        self.active
+            .retain(|n| n.expires_at.is_none_or(|expires| now <= expires));
+    }
+
+    /// Keep a toast on screen for at least `secs` more seconds (e.g. while hovered).
+    pub fn extend(&mut self, id: u64, now: f64, secs: f64) {
+        if let Some(n) = self.active.iter_mut().find(|n| n.id == id) {
+            if let Some(expires) = n.expires_at.as_mut() {
+                *expires = expires.max(now + secs);
+            }
+        }
+    }
+
+    /// Dismiss a visible toast (it remains in history).
+    pub fn dismiss(&mut self, id: u64) {
+        self.active.retain(|n| n.id != id);
+    }
+
+    /// Dismiss all visible toasts.
+    pub fn dismiss_all(&mut self) {
+        self.active.clear();
+    }
+
+    /// Clear the notification history.
+    pub fn clear_history(&mut self) {
+        self.history.clear();
+        self.unread = 0;
+    }
+
+    /// Mark the history as read.
+    pub fn mark_read(&mut self) {
+        self.unread = 0;
+    }
+
+    /// Toasts currently visible.
+    #[must_use]
+    pub fn active(&self) -> &[Notification] {
+        &self.active
+    }
+
+    /// Notification history (oldest first).
+    pub fn history(&self) -> impl DoubleEndedIterator<Item = &Notification> {
+        self.history.iter()
+    }
+
+    /// Number of history entries raised since the panel was last opened.
+    #[must_use]
+    pub fn unread(&self) -> usize {
+        self.unread
+    }
+
+    /// Whether the history is empty.
+    #[must_use]
+    pub fn is_history_empty(&self) -> bool {
+        self.history.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{NotificationLevel, Notifications};
+
+    #[test]
+    fn toasts_expire_by_level_and_stay_in_history() {
+        let mut notes = Notifications::default();
+        notes.info("saved", 0.0);
+        notes.warn("careful", 0.0);
+        notes.error("failed", 0.0);
+        assert_eq!(notes.active().len(), 3);
+
+        notes.prune(5.0);
+        let levels: Vec<_> = notes.active().iter().map(|n| n.level).collect();
+        assert_eq!(
+            levels,
+            vec![NotificationLevel::Warning, NotificationLevel::Error]
+        );
+
+        notes.prune(1_000.0);
+        assert_eq!(notes.active().len(), 1);
+        let id = notes.active()[0].id;
+        notes.dismiss(id);
+        assert!(notes.active().is_empty());
+        assert_eq!(notes.history().count(), 3);
+        assert_eq!(notes.unread(), 3);
+        notes.mark_read();
+        assert_eq!(notes.unread(), 0);
+    }
+
+    #[test]
+    fn hovering_extends_toast_lifetime() {
+        let mut notes = Notifications::default();
+        notes.info("hello", 0.0);
+        let id = notes.active()[0].id;
+        notes.extend(id, 3.5, 2.0);
+        notes.prune(5.0);
+        assert_eq!(notes.active().len(), 1);
+        notes.prune(6.0);
+        assert!(notes.active().is_empty());
+    }
+}
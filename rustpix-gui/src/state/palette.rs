@@ -0,0 +1,94 @@
+//! Command palette state and fuzzy matching.
+
+/// Command palette (Ctrl+P) state.
+#[derive(Debug, Default)]
+pub struct CommandPaletteState {
+    /// Whether the palette is open.
+    pub open: bool,
+    /// Current search query.
+    pub query: String,
+    /// Index of the highlighted entry in the filtered list.
+    pub selected: usize,
+    /// Request keyboard focus for the search field on the next frame.
+    pub focus_requested: bool,
+}
+
+impl CommandPaletteState {
+    /// Open the palette with an empty query.
+    pub fn open(&mut self) {
+        self.open = true;
+        self.query.clear();
+        self.selected = 0;
+        self.focus_requested = true;
+    }
+
+    /// Close the palette.
+    pub fn close(&mut self) {
+        self.open = false;
+        self.query.clear();
+        self.selected = 0;
+    }
+}
+
+/// Score how well `query` fuzzy-matches `label` (higher is better).
+///
+/// Every query character must appear in `label` in order (case-insensitive).
+/// Consecutive matches and matches at word starts score higher; returns `None`
+/// when the query is not a subsequence of the label.
+#[must_use]
+pub fn fuzzy_score(query: &str, label: &str) -> Option<i32> {
+    let query: Vec<char> = query
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .flat_map(char::to_lowercase)
+        .collect();
+    if query.is_empty() {
+        return Some(0);
+    }
+    let mut score = 0i32;
+    let mut qi = 0usize;
+    let mut prev_match: Option<usize> = None;
+    let mut prev_char: Option<char> = None;
+    for (li, ch) in label.chars().enumerate() {
+        if qi == query.len() {
+            break;
+        }
+        let lower = ch.to_lowercase().next().unwrap_or(ch);
+        if lower == query[qi] {
+            score += 1;
+            if prev_match.is_some_and(|p| p + 1 == li) {
+                score += 5;
+            }
+            if prev_char.is_none_or(|p| !p.is_alphanumeric()) {
+                score += 8;
+            }
+            prev_match = Some(li);
+            qi += 1;
+        }
+        prev_char = Some(ch);
+    }
+    (qi == query.len()).then_some(score)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::fuzzy_score;
+
+    #[test]
+    fn fuzzy_score_requires_ordered_subsequence() {
+        assert!(fuzzy_score("rot", "Rotate view clockwise").is_some());
+        assert!(fuzzy_score("rvc", "Rotate view clockwise").is_some());
+        assert!(fuzzy_score("tor", "Rotate view").is_none());
+        assert_eq!(fuzzy_score("", "Anything"), Some(0));
+    }
+
+    #[test]
+    fn fuzzy_score_prefers_word_starts_and_runs() {
+        let exact = fuzzy_score("export", "Export data…").unwrap();
+        let scattered = fuzzy_score("export", "Expand pixel ordering rate table").unwrap_or(0);
+        assert!(exact > scattered);
+        let word_start = fuzzy_score("db", "Algorithm: DBSCAN").unwrap();
+        let mid_word = fuzzy_score("db", "Hold bar").unwrap_or(0);
+        assert!(word_start > mid_word);
+    }
+}
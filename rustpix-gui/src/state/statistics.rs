@@ -2,6 +2,8 @@
 
 use std::time::Duration;
 
+use rustpix_core::classification::ClassCounts;
+
 /// Statistics for the current session.
 #[derive(Default)]
 pub struct Statistics {
@@ -17,6 +19,8 @@ pub struct Statistics {
     pub cluster_duration: Option<Duration>,
     /// Average cluster size (hits per neutron).
     pub avg_cluster_size: f64,
+    /// Events per class, when a classifier was set.
+    pub class_counts: Option<ClassCounts>,
 }
 
 impl Statistics {
@@ -0,0 +1,157 @@
+//! First-run guided tour definition and progress.
+//!
+//! The tour is a declarative list of steps; rendering lives in `ui::tour`.
+//! Completion is recorded with a marker file in the user config directory so
+//! the tour only starts automatically on the first launch.
+
+use std::fs;
+use std::path::PathBuf;
+
+use super::config_dir;
+
+/// Screen region a tour step points at.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TourAnchor {
+    /// File toolbar in the top bar (open/export).
+    TopBar,
+    /// Left control panel (clustering, view options).
+    Sidebar,
+    /// Central histogram image.
+    Histogram,
+    /// Spectrum panel below the histogram.
+    Spectrum,
+}
+
+/// A single step of the guided tour.
+#[derive(Clone, Copy, Debug)]
+pub struct TourStep {
+    pub title: &'static str,
+    pub body: &'static str,
+    pub anchor: TourAnchor,
+}
+
+/// Tour steps in order: open → process → slice → ROI → export.
+pub const TOUR_STEPS: &[TourStep] = &[
+    TourStep {
+        title: "Open a file",
+        body: "Use the folder button in the top bar to open a .tpx3 file. Hits are \
+               histogrammed into a TOF × Y × X hyperstack while loading.",
+        anchor: TourAnchor::TopBar,
+    },
+    TourStep {
+        title: "Process neutrons",
+        body: "Pick a clustering algorithm in the sidebar and click Run Clustering. \
+               Switch between Hits and Neutrons with the toggle in the top bar.",
+        anchor: TourAnchor::Sidebar,
+    },
+    TourStep {
+        title: "Slice in time-of-flight",
+        body: "Enable the TOF Slicer in the View section, then drag the marker in the \
+               spectrum or use the slider to step through TOF bins.",
+        anchor: TourAnchor::Spectrum,
+    },
+    TourStep {
+        title: "Draw regions of interest",
+        body: "Use the rectangle or polygon tools above the image to draw ROIs. Each ROI \
+               gets its own spectrum; right-click an ROI for more options.",
+        anchor: TourAnchor::Histogram,
+    },
+    TourStep {
+        title: "Export results",
+        body: "Use the export button in the top bar for HDF5 or TIFF output, or the \
+               PNG/CSV/Copy buttons on the spectrum toolbar.",
+        anchor: TourAnchor::TopBar,
+    },
+];
+
+/// Guided tour progress.
+#[derive(Debug, Default)]
+pub struct TourState {
+    /// Index of the current step, or `None` when the tour is not running.
+    step: Option<usize>,
+}
+
+impl TourState {
+    /// Start the tour if it has never been completed on this machine.
+    #[must_use]
+    pub fn for_first_run() -> Self {
+        let step = if tour_completed() { None } else { Some(0) };
+        Self { step }
+    }
+
+    /// Start (or restart) the tour from the first step.
+    pub fn start(&mut self) {
+        self.step = Some(0);
+    }
+
+    /// Current step, if the tour is running.
+    #[must_use]
+    pub fn current(&self) -> Option<(usize, &'static TourStep)> {
+        self.step
+            .and_then(|index| TOUR_STEPS.get(index).map(|step| (index, step)))
+    }
+
+    /// Advance to the next step, finishing after the last one.
+    pub fn advance(&mut self) {
+        match self.step {
+            Some(index) if index + 1 < TOUR_STEPS.len() => self.step = Some(index + 1),
+            Some(_) => self.finish(),
+            None => {}
+        }
+    }
+
+    /// Go back one step.
+    pub fn back(&mut self) {
+        if let Some(index) = self.step {
+            self.step = Some(index.saturating_sub(1));
+        }
+    }
+
+    /// End the tour and remember that it was seen.
+    pub fn finish(&mut self) {
+        self.step = None;
+        mark_tour_completed();
+    }
+}
+
+fn tour_marker_path() -> Option<PathBuf> {
+    config_dir().map(|dir| dir.join("tour_completed"))
+}
+
+fn tour_completed() -> bool {
+    tour_marker_path().is_some_and(|path| path.exists())
+}
+
+fn mark_tour_completed() {
+    let Some(path) = tour_marker_path() else {
+        return;
+    };
+    let result = path
+        .parent()
+        .map_or(Ok(()), fs::create_dir_all)
+        .and_then(|()| fs::write(&path, b""));
+    if let Err(err) = result {
+        log::warn!(
+            "Failed to record tour completion at {}: {err}",
+            path.display()
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{TourState, TOUR_STEPS};
+
+    #[test]
+    fn tour_steps_advance_and_rewind() {
+        let mut tour = TourState { step: Some(0) };
+        tour.back();
+        assert_eq!(tour.current().map(|(i, _)| i), Some(0));
+        tour.advance();
+        tour.advance();
+        assert_eq!(tour.current().map(|(i, _)| i), Some(2));
+        tour.back();
+        assert_eq!(tour.current().map(|(i, _)| i), Some(1));
+        assert!(TOUR_STEPS.len() >= 5);
+    }
+}
@@ -1,9 +1,15 @@
 //! UI state for panel visibility and view options.
 
 use std::fmt;
+use std::path::PathBuf;
 
 use eframe::egui::Rect;
 use egui_plot::{PlotBounds, PlotPoint};
+use serde::{Deserialize, Serialize};
+
+use super::export_profiles::ExportProfileState;
+use super::{CalibrationState, CommandHistory, CommandPaletteState, Notifications, TourState};
+
 /// Data source for the main viewer.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
 pub enum ViewMode {
@@ -42,6 +48,59 @@ impl fmt::Display for SpectrumXAxis {
     }
 }
 
+/// Image arithmetic between two TOF ranges.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RangeMathOp {
+    /// Range A divided by range B.
+    #[default]
+    Ratio,
+    /// Range A minus range B.
+    Difference,
+}
+
+impl fmt::Display for RangeMathOp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Ratio => write!(f, "Ratio (A / B)"),
+            Self::Difference => write!(f, "Difference (A − B)"),
+        }
+    }
+}
+
+impl RangeMathOp {
+    /// Combine per-pixel counts from range A and range B.
+    ///
+    /// Ratios with an empty denominator are reported as `NaN` so they can be
+    /// rendered as background instead of skewing the color scale.
+    #[must_use]
+    #[allow(clippy::cast_precision_loss)]
+    pub fn apply(self, a: u64, b: u64) -> f32 {
+        match self {
+            Self::Ratio => {
+                if b == 0 {
+                    f32::NAN
+                } else {
+                    a as f32 / b as f32
+                }
+            }
+            Self::Difference => a as f32 - b as f32,
+        }
+    }
+}
+
+/// Two-range TOF image math settings (e.g. below/above a Bragg edge).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub struct UiRangeMath {
+    /// Whether the histogram shows the range-math image instead of counts.
+    pub enabled: bool,
+    /// Operation applied between the two ranges.
+    pub op: RangeMathOp,
+    /// Inclusive TOF bin range A (first, last).
+    pub range_a: (usize, usize),
+    /// Inclusive TOF bin range B (first, last).
+    pub range_b: (usize, usize),
+}
+
 /// Zoom tool mode for plot navigation.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
 pub enum ZoomMode {
@@ -59,6 +118,8 @@ pub struct UiState {
     pub histogram: UiHistogramToggles,
     /// Histogram view flags.
     pub histogram_view: UiHistogramView,
+    /// TOF range ratio/difference settings.
+    pub range_math: UiRangeMath,
     /// Spectrum-specific toggles.
     pub spectrum: UiSpectrumToggles,
     /// Panel visibility toggles.
@@ -71,14 +132,26 @@ pub struct UiState {
     pub cache: UiCacheToggles,
     /// Export dialog state.
     pub export: UiExportState,
+    /// Radial profile window settings.
+    pub radial_profile: UiRadialProfile,
+    /// Show the autocorrelation instead of the power spectrum.
+    pub fourier_autocorrelation: bool,
+    /// Energy calibration wizard state.
+    pub calibration: CalibrationState,
+    /// Steps recorded for macro export.
+    pub history: CommandHistory,
     /// Current TOF bin index for slicer view.
     pub current_tof_bin: usize,
     /// Current data source (Hits or Neutrons).
     pub view_mode: ViewMode,
     /// Spectrum X-axis mode.
     pub spectrum_x_axis: SpectrumXAxis,
-    /// Transient ROI warning message (text, expires at time).
-    pub roi_warning: Option<(String, f64)>,
+    /// Toast notifications and message history.
+    pub notifications: Notifications,
+    /// Guided tour progress.
+    pub tour: TourState,
+    /// Command palette state.
+    pub command_palette: CommandPaletteState,
     /// Transient ROI status message (text, expires at time).
     pub roi_status: Option<(String, f64)>,
     /// Cached plot bounds for ROI hit-testing before plot interaction.
@@ -135,7 +208,7 @@ pub struct UiHistogramView {
     pub transform: ViewTransform,
 }
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default, Serialize, Deserialize)]
 pub enum Rotation {
     #[default]
     R0,
@@ -144,7 +217,7 @@ pub enum Rotation {
     R270,
 }
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub struct ViewTransform {
     pub rotation: Rotation,
     pub flip_h: bool,
@@ -317,9 +390,16 @@ impl ViewTransform {
 
 #[cfg(test)]
 mod tests {
-    use super::{Rotation, ViewTransform};
+    use super::{RangeMathOp, Rotation, ViewTransform};
     use std::collections::HashSet;
 
+    #[test]
+    fn range_math_ratio_and_difference() {
+        assert!((RangeMathOp::Ratio.apply(6, 3) - 2.0).abs() < f32::EPSILON);
+        assert!(RangeMathOp::Ratio.apply(6, 0).is_nan());
+        assert!((RangeMathOp::Difference.apply(2, 5) + 3.0).abs() < f32::EPSILON);
+    }
+
     fn assert_close(a: f64, b: f64) {
         assert!((a - b).abs() < 1e-9, "expected {a} ≈ {b}");
     }
@@ -397,6 +477,7 @@ pub struct UiSpectrumToggles {
     pub full_fov_visible: bool,
 }
 
+#[allow(clippy::struct_excessive_bools)]
 #[derive(Clone, Copy, Default)]
 pub struct UiPanelToggles {
     /// Whether to show advanced clustering parameters.
@@ -405,6 +486,16 @@ pub struct UiPanelToggles {
     pub show_app_settings: bool,
     /// Whether to show the spectrum settings window.
     pub show_spectrum_settings: bool,
+    /// Whether to show the detector health dashboard.
+    pub show_health_dashboard: bool,
+    /// Whether to show the radial profile window.
+    pub show_radial_profile: bool,
+    /// Whether to show the power spectrum / autocorrelation window.
+    pub show_fourier: bool,
+    /// Whether to show the energy calibration wizard.
+    pub show_energy_calibration: bool,
+    /// Whether to show the command history window.
+    pub show_command_history: bool,
 }
 
 #[allow(clippy::struct_excessive_bools)]
@@ -424,6 +515,8 @@ pub struct UiPanelPopups {
     pub show_pixel_health_help: bool,
     /// Whether the spectrum help panel is open.
     pub show_spectrum_help: bool,
+    /// Whether the notification history panel is open.
+    pub show_notifications: bool,
 }
 
 #[derive(Clone, Copy, Default)]
@@ -442,6 +535,39 @@ pub struct UiCacheToggles {
     pub cache_hits_in_memory: bool,
 }
 
+/// Radial profile / azimuthal integration settings.
+#[derive(Clone, Copy)]
+pub struct UiRadialProfile {
+    /// Center in data pixels; the image center when unset.
+    pub center: Option<(f64, f64)>,
+    /// Ring width in pixels.
+    pub bin_width: f64,
+    /// Integrate over azimuth instead of radius.
+    pub azimuthal: bool,
+    /// Number of azimuthal sectors.
+    pub sectors: usize,
+    /// Inner radius for azimuthal integration (pixels).
+    pub r_min: f64,
+    /// Outer radius for azimuthal integration (pixels).
+    pub r_max: f64,
+    /// Export one profile per TOF bin instead of the current view.
+    pub export_per_tof: bool,
+}
+
+impl Default for UiRadialProfile {
+    fn default() -> Self {
+        Self {
+            center: None,
+            bin_width: 1.0,
+            azimuthal: false,
+            sectors: 36,
+            r_min: 0.0,
+            r_max: 100.0,
+            export_per_tof: false,
+        }
+    }
+}
+
 #[derive(Default)]
 pub struct UiExportState {
     /// Whether the export dialog is open.
@@ -458,14 +584,19 @@ pub struct UiExportState {
     pub options: Hdf5ExportOptions,
     /// TIFF export configuration.
     pub tiff: TiffExportOptions,
+    /// Folder to export into without asking; `None` opens a file dialog.
+    pub destination: Option<PathBuf>,
+    /// Saved export profiles.
+    pub profiles: ExportProfileState,
 }
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default, Serialize, Deserialize)]
 pub enum ExportFormat {
     #[default]
     Hdf5,
     TiffFolder,
     TiffStack,
+    Fits,
 }
 
 impl fmt::Display for ExportFormat {
@@ -474,11 +605,12 @@ impl fmt::Display for ExportFormat {
             Self::Hdf5 => write!(f, "HDF5 (NeXus)"),
             Self::TiffFolder => write!(f, "TIFF Folder"),
             Self::TiffStack => write!(f, "TIFF Stack"),
+            Self::Fits => write!(f, "FITS Cube"),
         }
     }
 }
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default, Serialize, Deserialize)]
 pub enum TiffBitDepth {
     #[default]
     Bit16,
@@ -494,7 +626,7 @@ impl fmt::Display for TiffBitDepth {
     }
 }
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default, Serialize, Deserialize)]
 pub enum TiffSpectraTiming {
     #[default]
     BinCenter,
@@ -510,7 +642,7 @@ impl fmt::Display for TiffSpectraTiming {
     }
 }
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default, Serialize, Deserialize)]
 pub enum TiffStackBehavior {
     #[default]
     StandardOnly,
@@ -528,7 +660,8 @@ impl fmt::Display for TiffStackBehavior {
     }
 }
 
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
 #[allow(clippy::struct_excessive_bools)]
 pub struct TiffExportOptions {
     pub bit_depth: TiffBitDepth,
@@ -556,7 +689,8 @@ impl Default for TiffExportOptions {
     }
 }
 
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct Hdf5ExportOptions {
     pub datasets: Hdf5ExportDatasets,
     pub masks: Hdf5ExportMasks,
@@ -569,35 +703,39 @@ pub struct Hdf5ExportOptions {
     pub hist_chunk_y: usize,
     pub hist_chunk_x: usize,
     pub hist_chunk_tof: usize,
+    /// Append hit/neutron events to an existing file instead of overwriting.
+    pub append: bool,
+    /// File name template, without the `.h5` extension.
+    pub file_name: String,
 }
 
-#[derive(Clone, Copy, Default)]
+#[derive(Clone, Copy, Default, Serialize, Deserialize)]
 pub struct Hdf5ExportDatasets {
     pub hits: bool,
     pub neutrons: bool,
     pub histogram: bool,
 }
 
-#[derive(Clone, Copy, Default)]
+#[derive(Clone, Copy, Default, Serialize, Deserialize)]
 pub struct Hdf5ExportMasks {
     pub pixel_masks: bool,
 }
 
-#[derive(Clone, Copy, Default)]
+#[derive(Clone, Copy, Default, Serialize, Deserialize)]
 pub struct Hdf5ExportAdvancedFlags {
     pub enabled: bool,
     pub shuffle: bool,
     pub hist_chunk_override: bool,
 }
 
-#[derive(Clone, Copy, Default)]
+#[derive(Clone, Copy, Default, Serialize, Deserialize)]
 pub struct Hdf5ExportFields {
     pub xy: bool,
     pub tot: bool,
     pub chip_id: bool,
 }
 
-#[derive(Clone, Copy, Default)]
+#[derive(Clone, Copy, Default, Serialize, Deserialize)]
 pub struct Hdf5ExportClusterFields {
     pub cluster_id: bool,
     pub n_hits: bool,
@@ -632,6 +770,8 @@ impl Default for Hdf5ExportOptions {
             hist_chunk_y: 128,
             hist_chunk_x: 128,
             hist_chunk_tof: 64,
+            append: false,
+            file_name: "rustpix".to_string(),
         }
     }
 }
@@ -0,0 +1,230 @@
+//! Energy calibration wizard.
+//!
+//! Guides the user through marking features of known energy (resonances,
+//! Bragg edges) on the spectrum and fits the flight path and TOF offset used
+//! by the energy axis from them.
+
+use eframe::egui;
+
+use super::theme::{accent, ThemeColors};
+use crate::app::RustpixApp;
+use crate::state::{CalibrationFit, REFERENCE_FEATURES};
+
+impl RustpixApp {
+    /// Render the energy calibration window while it is open.
+    pub(crate) fn render_energy_calibration(&mut self, ctx: &egui::Context) {
+        if !self.ui_state.panels.show_energy_calibration {
+            return;
+        }
+        let mut open = true;
+        egui::Window::new("Energy Calibration")
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                let colors = ThemeColors::from_ui(ui);
+                Self::render_calibration_step(
+                    ui,
+                    &colors,
+                    "1",
+                    "Add features seen in the spectrum",
+                );
+                self.render_calibration_add(ui);
+                ui.add_space(8.0);
+
+                Self::render_calibration_step(
+                    ui,
+                    &colors,
+                    "2",
+                    "Click \"Mark\", then click the feature on the spectrum",
+                );
+                let fit = self.ui_state.calibration.fit(self.tof_offset_ns);
+                self.render_calibration_marks(ui, &colors, fit.as_ref());
+                ui.add_space(8.0);
+
+                Self::render_calibration_step(ui, &colors, "3", "Apply the fit");
+                self.render_calibration_fit(ui, &colors, fit.as_ref());
+            });
+        if !open {
+            self.ui_state.calibration.picking = None;
+        }
+        self.ui_state.panels.show_energy_calibration = open;
+    }
+
+    fn render_calibration_step(ui: &mut egui::Ui, colors: &ThemeColors, step: &str, text: &str) {
+        ui.label(
+            egui::RichText::new(format!("{step}. {text}"))
+                .size(11.0)
+                .color(colors.text_muted),
+        );
+    }
+
+    fn render_calibration_add(&mut self, ui: &mut egui::Ui) {
+        let calibration = &mut self.ui_state.calibration;
+        ui.horizontal(|ui| {
+            let selected = calibration
+                .selected_reference
+                .min(REFERENCE_FEATURES.len() - 1);
+            egui::ComboBox::from_id_salt("calibration_reference")
+                .width(220.0)
+                .selected_text(REFERENCE_FEATURES[selected].0)
+                .show_ui(ui, |ui| {
+                    for (index, (name, energy_ev)) in REFERENCE_FEATURES.iter().enumerate() {
+                        ui.selectable_value(
+                            &mut calibration.selected_reference,
+                            index,
+                            format!("{name} ({energy_ev} eV)"),
+                        );
+                    }
+                });
+            if ui.button("Add").clicked() {
+                let (name, energy_ev) = REFERENCE_FEATURES[selected];
+                calibration.add_mark(name, energy_ev);
+            }
+        });
+        ui.horizontal(|ui| {
+            ui.label("Custom (eV)");
+            ui.add(
+                egui::DragValue::new(&mut calibration.custom_energy_ev)
+                    .range(0.0..=1.0e6)
+                    .speed(0.01),
+            );
+            let energy_ev = calibration.custom_energy_ev;
+            if ui
+                .add_enabled(energy_ev > 0.0, egui::Button::new("Add custom"))
+                .clicked()
+            {
+                calibration.add_mark(format!("{energy_ev} eV"), energy_ev);
+            }
+        });
+    }
+
+    fn render_calibration_marks(
+        &mut self,
+        ui: &mut egui::Ui,
+        colors: &ThemeColors,
+        fit: Option<&CalibrationFit>,
+    ) {
+        let calibration = &mut self.ui_state.calibration;
+        if calibration.marks.is_empty() {
+            ui.label(
+                egui::RichText::new("No features added")
+                    .size(11.0)
+                    .color(colors.text_dim),
+            );
+            return;
+        }
+        let mut remove = None;
+        egui::Grid::new("calibration_marks")
+            .striped(true)
+            .num_columns(5)
+            .spacing(egui::vec2(12.0, 4.0))
+            .show(ui, |ui| {
+                for header in ["Feature", "Energy (eV)", "TOF (ms)", "Residual (µs)", ""] {
+                    ui.label(
+                        egui::RichText::new(header)
+                            .size(11.0)
+                            .color(colors.text_muted),
+                    );
+                }
+                ui.end_row();
+                for (index, mark) in calibration.marks.iter_mut().enumerate() {
+                    ui.label(&mark.label);
+                    ui.monospace(format!("{}", mark.energy_ev));
+                    match mark.tof_ms.as_mut() {
+                        Some(tof_ms) => {
+                            ui.add(
+                                egui::DragValue::new(tof_ms)
+                                    .range(0.0..=1_000.0)
+                                    .speed(0.001)
+                                    .max_decimals(4),
+                            );
+                        }
+                        None => {
+                            ui.label(egui::RichText::new("—").color(colors.text_dim));
+                        }
+                    }
+                    let residual = mark
+                        .tof_ms
+                        .zip(fit)
+                        .and_then(|(tof_ms, fit)| fit.residual_us(tof_ms, mark.energy_ev));
+                    match residual {
+                        Some(residual) => ui.monospace(format!("{residual:+.2}")),
+                        None => ui.label(""),
+                    };
+                    ui.horizontal(|ui| {
+                        let picking = calibration.picking == Some(index);
+                        let label = if picking { "Marking…" } else { "Mark" };
+                        if ui
+                            .selectable_label(picking, label)
+                            .on_hover_text("Click the feature on the spectrum")
+                            .clicked()
+                        {
+                            calibration.picking = if picking { None } else { Some(index) };
+                        }
+                        if ui.small_button("✕").on_hover_text("Remove").clicked() {
+                            remove = Some(index);
+                        }
+                    });
+                    ui.end_row();
+                }
+            });
+        if let Some(index) = remove {
+            calibration.remove_mark(index);
+        }
+    }
+
+    fn render_calibration_fit(
+        &mut self,
+        ui: &mut egui::Ui,
+        colors: &ThemeColors,
+        fit: Option<&CalibrationFit>,
+    ) {
+        let Some(fit) = fit.copied() else {
+            ui.label(
+                egui::RichText::new("Mark at least one feature to fit")
+                    .size(11.0)
+                    .color(colors.text_dim),
+            );
+            return;
+        };
+        egui::Grid::new("calibration_fit")
+            .num_columns(2)
+            .spacing(egui::vec2(12.0, 4.0))
+            .show(ui, |ui| {
+                ui.label("Flight path");
+                ui.monospace(format!("{:.4} m", fit.flight_path_m));
+                ui.end_row();
+                ui.label("TOF offset");
+                let offset = format!("{:.1} ns", fit.tof_offset_ns);
+                if fit.offset_fitted {
+                    ui.monospace(offset);
+                } else {
+                    ui.monospace(offset)
+                        .on_hover_text("Held at the current offset; mark two energies to fit it");
+                }
+                ui.end_row();
+                ui.label("RMS residual");
+                ui.monospace(format!("{:.2} µs", fit.rms_residual_us));
+                ui.end_row();
+            });
+        ui.add_space(4.0);
+        let apply = egui::Button::new(egui::RichText::new("Apply").color(egui::Color32::WHITE))
+            .fill(accent::BLUE);
+        if ui
+            .add(apply)
+            .on_hover_text("Use this flight path and TOF offset for the energy axis")
+            .clicked()
+        {
+            self.flight_path_m = fit.flight_path_m;
+            self.tof_offset_ns = fit.tof_offset_ns;
+            self.ui_state.notifications.info(
+                format!(
+                    "Energy calibration applied: {:.4} m, {:.1} ns",
+                    fit.flight_path_m, fit.tof_offset_ns
+                ),
+                ui.ctx().input(|i| i.time),
+            );
+        }
+    }
+}
@@ -0,0 +1,96 @@
+//! Command history window.
+//!
+//! Lists the steps recorded this session and saves them as a macro for
+//! headless replay with `rustpix run-macro`.
+
+use eframe::egui;
+use rfd::FileDialog;
+
+use super::theme::ThemeColors;
+use crate::app::RustpixApp;
+use crate::state::describe_step;
+
+impl RustpixApp {
+    /// Render the command history window while it is open.
+    pub(crate) fn render_command_history(&mut self, ctx: &egui::Context) {
+        if !self.ui_state.panels.show_command_history {
+            return;
+        }
+        let mut open = true;
+        egui::Window::new("Command History")
+            .open(&mut open)
+            .collapsible(false)
+            .default_width(380.0)
+            .show(ctx, |ui| {
+                let colors = ThemeColors::from_ui(ui);
+                let steps = self.ui_state.history.steps();
+                if steps.is_empty() {
+                    ui.label(
+                        egui::RichText::new("Load, process or export to record steps")
+                            .size(11.0)
+                            .color(colors.text_dim),
+                    );
+                } else {
+                    egui::ScrollArea::vertical()
+                        .max_height(260.0)
+                        .show(ui, |ui| {
+                            for (index, step) in steps.iter().enumerate() {
+                                ui.horizontal(|ui| {
+                                    ui.label(
+                                        egui::RichText::new(format!("{}.", index + 1))
+                                            .size(11.0)
+                                            .color(colors.text_muted),
+                                    );
+                                    ui.label(describe_step(step));
+                                });
+                            }
+                        });
+                }
+                ui.add_space(6.0);
+                let has_steps = !steps.is_empty();
+                ui.horizontal(|ui| {
+                    if ui
+                        .add_enabled(has_steps, egui::Button::new("Save as macro…"))
+                        .on_hover_text("Replay with `rustpix run-macro <file>`")
+                        .clicked()
+                    {
+                        self.save_command_history(ctx);
+                    }
+                    if ui
+                        .add_enabled(has_steps, egui::Button::new("Clear"))
+                        .clicked()
+                    {
+                        self.ui_state.history.clear();
+                    }
+                });
+            });
+        self.ui_state.panels.show_command_history = open;
+    }
+
+    fn save_command_history(&mut self, ctx: &egui::Context) {
+        let Some(path) = FileDialog::new()
+            .add_filter("JSON", &["json"])
+            .set_file_name("rustpix_macro.json")
+            .save_file()
+        else {
+            return;
+        };
+        let result = self
+            .ui_state
+            .history
+            .to_json()
+            .map_err(std::io::Error::from)
+            .and_then(|json| std::fs::write(&path, json));
+        let now = ctx.input(|i| i.time);
+        match result {
+            Ok(()) => self
+                .ui_state
+                .notifications
+                .info(format!("Macro saved to {}", path.display()), now),
+            Err(err) => self
+                .ui_state
+                .notifications
+                .error(format!("Saving macro failed: {err}"), now),
+        }
+    }
+}
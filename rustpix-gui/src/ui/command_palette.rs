@@ -0,0 +1,392 @@
+//! Command palette (Ctrl+P) listing application actions with fuzzy search.
+
+use eframe::egui::{self, Color32, Rounding, Stroke};
+use rfd::FileDialog;
+
+use super::theme::{accent, ThemeColors};
+use crate::app::RustpixApp;
+use crate::pipeline::AlgorithmType;
+use crate::state::{fuzzy_score, ViewMode};
+
+/// Maximum number of matches listed at once.
+const MAX_RESULTS: usize = 12;
+
+/// Actions reachable from the command palette.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum PaletteCommand {
+    OpenFile,
+    SaveSession,
+    ExportData,
+    RunClustering,
+    CancelOperation,
+    ViewHits,
+    ViewNeutrons,
+    AlgorithmAbs,
+    AlgorithmDbscan,
+    AlgorithmGrid,
+    AlgorithmHierarchical,
+    AlgorithmConnectedComponents,
+    RotateClockwise,
+    RotateCounterClockwise,
+    FlipHorizontal,
+    FlipVertical,
+    ResetTransform,
+    ToggleSlicer,
+    ToggleSpectrum,
+    ToggleLogScale,
+    ToggleGrid,
+    ToggleHotPixels,
+    HyperstackSettings,
+    SpectrumSettings,
+    EnergyCalibration,
+    HealthDashboard,
+    RadialProfile,
+    PowerSpectrum,
+    NotificationHistory,
+    CommandHistory,
+    ClearRois,
+    StartTour,
+}
+
+/// Palette entries: command, label, and optional shortcut hint.
+const COMMANDS: &[(PaletteCommand, &str, &str)] = &[
+    (PaletteCommand::OpenFile, "Open file…", ""),
+    (PaletteCommand::SaveSession, "Save session…", ""),
+    (PaletteCommand::ExportData, "Export data…", ""),
+    (PaletteCommand::RunClustering, "Run clustering", ""),
+    (
+        PaletteCommand::CancelOperation,
+        "Cancel loading/processing",
+        "",
+    ),
+    (PaletteCommand::ViewHits, "View: Hits", ""),
+    (PaletteCommand::ViewNeutrons, "View: Neutrons", ""),
+    (PaletteCommand::AlgorithmAbs, "Algorithm: ABS", ""),
+    (PaletteCommand::AlgorithmDbscan, "Algorithm: DBSCAN", ""),
+    (PaletteCommand::AlgorithmGrid, "Algorithm: Grid", ""),
+    (
+        PaletteCommand::AlgorithmHierarchical,
+        "Algorithm: Hierarchical",
+        "",
+    ),
+    (
+        PaletteCommand::AlgorithmConnectedComponents,
+        "Algorithm: Connected Components",
+        "",
+    ),
+    (
+        PaletteCommand::RotateClockwise,
+        "Rotate view clockwise",
+        "R",
+    ),
+    (
+        PaletteCommand::RotateCounterClockwise,
+        "Rotate view counter-clockwise",
+        "Shift+R",
+    ),
+    (
+        PaletteCommand::FlipHorizontal,
+        "Flip view horizontally",
+        "H",
+    ),
+    (PaletteCommand::FlipVertical, "Flip view vertically", "V"),
+    (PaletteCommand::ResetTransform, "Reset view transform", ""),
+    (PaletteCommand::ToggleSlicer, "Toggle TOF slicer", ""),
+    (PaletteCommand::ToggleSpectrum, "Toggle spectrum panel", ""),
+    (PaletteCommand::ToggleLogScale, "Toggle log scale", ""),
+    (PaletteCommand::ToggleGrid, "Toggle grid", ""),
+    (
+        PaletteCommand::ToggleHotPixels,
+        "Toggle hot pixel overlay",
+        "",
+    ),
+    (
+        PaletteCommand::HyperstackSettings,
+        "Open hyperstack settings",
+        "",
+    ),
+    (
+        PaletteCommand::SpectrumSettings,
+        "Open spectrum settings",
+        "",
+    ),
+    (
+        PaletteCommand::EnergyCalibration,
+        "Open energy calibration wizard",
+        "",
+    ),
+    (
+        PaletteCommand::HealthDashboard,
+        "Open detector health dashboard",
+        "",
+    ),
+    (PaletteCommand::RadialProfile, "Open radial profile", ""),
+    (
+        PaletteCommand::PowerSpectrum,
+        "Open power spectrum / autocorrelation",
+        "",
+    ),
+    (
+        PaletteCommand::NotificationHistory,
+        "Show notification history",
+        "",
+    ),
+    (
+        PaletteCommand::CommandHistory,
+        "Show command history / save macro",
+        "",
+    ),
+    (PaletteCommand::ClearRois, "Clear all ROIs", ""),
+    (PaletteCommand::StartTour, "Start guided tour", ""),
+];
+
+impl RustpixApp {
+    /// Open the palette on Ctrl/Cmd+P and render it while open.
+    pub(crate) fn render_command_palette(&mut self, ctx: &egui::Context) {
+        let toggle = ctx.input_mut(|i| i.consume_key(egui::Modifiers::COMMAND, egui::Key::P));
+        if toggle {
+            if self.ui_state.command_palette.open {
+                self.ui_state.command_palette.close();
+            } else {
+                self.ui_state.command_palette.open();
+            }
+        }
+        if !self.ui_state.command_palette.open {
+            return;
+        }
+
+        let matches = self.palette_matches();
+        let palette = &mut self.ui_state.command_palette;
+        if ctx.input_mut(|i| i.consume_key(egui::Modifiers::NONE, egui::Key::Escape)) {
+            palette.close();
+            return;
+        }
+        if ctx.input_mut(|i| i.consume_key(egui::Modifiers::NONE, egui::Key::ArrowDown)) {
+            palette.selected = (palette.selected + 1).min(matches.len().saturating_sub(1));
+        }
+        if ctx.input_mut(|i| i.consume_key(egui::Modifiers::NONE, egui::Key::ArrowUp)) {
+            palette.selected = palette.selected.saturating_sub(1);
+        }
+        palette.selected = palette.selected.min(matches.len().saturating_sub(1));
+        let enter = ctx.input(|i| i.key_pressed(egui::Key::Enter));
+
+        let colors = ThemeColors::from_ctx(ctx);
+        let mut chosen = None;
+        egui::Area::new(egui::Id::new("command_palette"))
+            .anchor(egui::Align2::CENTER_TOP, egui::vec2(0.0, 80.0))
+            .order(egui::Order::Foreground)
+            .show(ctx, |ui| {
+                egui::Frame::none()
+                    .fill(colors.bg_panel)
+                    .stroke(Stroke::new(1.0, colors.border_light))
+                    .rounding(Rounding::same(6.0))
+                    .inner_margin(egui::Margin::same(8.0))
+                    .show(ui, |ui| {
+                        ui.set_width(420.0);
+                        let response = ui.add(
+                            egui::TextEdit::singleline(&mut palette.query)
+                                .hint_text("Type a command…")
+                                .desired_width(f32::INFINITY),
+                        );
+                        if palette.focus_requested {
+                            response.request_focus();
+                            palette.focus_requested = false;
+                        }
+                        if response.changed() {
+                            palette.selected = 0;
+                        }
+                        ui.add_space(4.0);
+                        if matches.is_empty() {
+                            ui.label(
+                                egui::RichText::new("No matching commands")
+                                    .size(11.0)
+                                    .color(colors.text_muted),
+                            );
+                        }
+                        for (row, &(command, label, shortcut, enabled)) in
+                            matches.iter().enumerate()
+                        {
+                            let highlighted = row == palette.selected;
+                            let text_color = if !enabled {
+                                colors.text_dim
+                            } else if highlighted {
+                                Color32::WHITE
+                            } else {
+                                colors.text_primary
+                            };
+                            let button = egui::Button::new(
+                                egui::RichText::new(label).size(12.0).color(text_color),
+                            )
+                            .shortcut_text(shortcut)
+                            .fill(if highlighted {
+                                accent::BLUE
+                            } else {
+                                Color32::TRANSPARENT
+                            })
+                            .stroke(Stroke::NONE)
+                            .min_size(egui::vec2(ui.available_width(), 24.0));
+                            if ui.add_enabled(enabled, button).clicked() {
+                                chosen = Some(command);
+                            }
+                        }
+                    });
+            });
+
+        if enter {
+            if let Some(&(command, _, _, true)) = matches.get(palette.selected) {
+                chosen = Some(command);
+            }
+        }
+        if let Some(command) = chosen {
+            palette.close();
+            self.run_palette_command(command, ctx.input(|i| i.time));
+        }
+    }
+
+    /// Commands matching the current query, best first, with enabled state.
+    fn palette_matches(&self) -> Vec<(PaletteCommand, &'static str, &'static str, bool)> {
+        let query = &self.ui_state.command_palette.query;
+        let mut scored: Vec<_> = COMMANDS
+            .iter()
+            .enumerate()
+            .filter_map(|(order, &(command, label, shortcut))| {
+                fuzzy_score(query, label).map(|score| (score, order, command, label, shortcut))
+            })
+            .collect();
+        scored.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.cmp(&b.1)));
+        scored
+            .into_iter()
+            .take(MAX_RESULTS)
+            .map(|(_, _, command, label, shortcut)| {
+                (
+                    command,
+                    label,
+                    shortcut,
+                    self.palette_command_enabled(command),
+                )
+            })
+            .collect()
+    }
+
+    fn palette_command_enabled(&self, command: PaletteCommand) -> bool {
+        let busy = self.processing.is_loading || self.processing.is_processing;
+        let has_data = self.hyperstack.is_some();
+        match command {
+            PaletteCommand::OpenFile => !busy,
+            PaletteCommand::SaveSession => self.selected_file.is_some(),
+            PaletteCommand::ExportData => {
+                (has_data || !self.neutrons.is_empty()) && !self.ui_state.export.in_progress
+            }
+            PaletteCommand::RunClustering => {
+                !busy && self.selected_file.is_some() && self.statistics.hit_count > 0
+            }
+            PaletteCommand::CancelOperation => busy,
+            PaletteCommand::ViewNeutrons => self.has_neutrons(),
+            PaletteCommand::ToggleSlicer => self.n_tof_bins() > 0,
+            PaletteCommand::ClearRois => !self.roi_state.rois.is_empty(),
+            _ => true,
+        }
+    }
+
+    fn run_palette_command(&mut self, command: PaletteCommand, now: f64) {
+        match command {
+            PaletteCommand::OpenFile => {
+                if let Some(path) = FileDialog::new()
+                    .add_filter("TPX3", &["tpx3"])
+                    .add_filter("Hit list", &["csv", "h5", "hdf5"])
+                    .add_filter(
+                        "Processed output",
+                        &["csv", "bin", "rpxd", "h5", "hdf5", "nxs"],
+                    )
+                    .pick_file()
+                {
+                    self.load_file(path);
+                }
+            }
+            PaletteCommand::SaveSession => {
+                if let Some(path) = FileDialog::new()
+                    .add_filter("Session", &["json"])
+                    .set_file_name("session.json")
+                    .save_file()
+                {
+                    match self.save_session(&path) {
+                        Ok(()) => self
+                            .ui_state
+                            .notifications
+                            .info(format!("Saved session to {}", path.display()), now),
+                        Err(err) => self
+                            .ui_state
+                            .notifications
+                            .error(format!("Failed to save session: {err}"), now),
+                    }
+                }
+            }
+            PaletteCommand::ExportData => self.ui_state.export.show_dialog = true,
+            PaletteCommand::RunClustering => {
+                self.processing.reset_cancel();
+                self.run_processing();
+            }
+            PaletteCommand::CancelOperation => self.cancel_operation(),
+            PaletteCommand::ViewHits => self.set_view_mode(ViewMode::Hits),
+            PaletteCommand::ViewNeutrons => self.set_view_mode(ViewMode::Neutrons),
+            PaletteCommand::AlgorithmAbs => self.algo_type = AlgorithmType::Abs,
+            PaletteCommand::AlgorithmDbscan => self.algo_type = AlgorithmType::Dbscan,
+            PaletteCommand::AlgorithmGrid => self.algo_type = AlgorithmType::Grid,
+            PaletteCommand::AlgorithmHierarchical => {
+                self.algo_type = AlgorithmType::Hierarchical;
+            }
+            PaletteCommand::AlgorithmConnectedComponents => {
+                self.algo_type = AlgorithmType::ConnectedComponents;
+            }
+            PaletteCommand::RotateClockwise => self.rotate_histogram_cw(),
+            PaletteCommand::RotateCounterClockwise => self.rotate_histogram_ccw(),
+            PaletteCommand::FlipHorizontal => self.flip_histogram_horizontal(),
+            PaletteCommand::FlipVertical => self.flip_histogram_vertical(),
+            PaletteCommand::ResetTransform => self.reset_histogram_transform(),
+            PaletteCommand::ToggleSlicer => {
+                self.ui_state.histogram.slicer_enabled = !self.ui_state.histogram.slicer_enabled;
+                self.texture_refresh.invalidate();
+            }
+            PaletteCommand::ToggleSpectrum => {
+                self.ui_state.histogram.show = !self.ui_state.histogram.show;
+            }
+            PaletteCommand::ToggleLogScale => {
+                self.ui_state.histogram.log_scale = !self.ui_state.histogram.log_scale;
+                self.texture_refresh.invalidate();
+            }
+            PaletteCommand::ToggleGrid => {
+                self.ui_state.histogram_view.show_grid = !self.ui_state.histogram_view.show_grid;
+            }
+            PaletteCommand::ToggleHotPixels => {
+                self.ui_state.pixel_health.show_hot_pixels =
+                    !self.ui_state.pixel_health.show_hot_pixels;
+            }
+            PaletteCommand::HyperstackSettings => self.ui_state.panels.show_app_settings = true,
+            PaletteCommand::SpectrumSettings => {
+                self.ui_state.panels.show_spectrum_settings = true;
+            }
+            PaletteCommand::EnergyCalibration => {
+                self.ui_state.panels.show_energy_calibration = true;
+            }
+            PaletteCommand::HealthDashboard => {
+                self.ui_state.panels.show_health_dashboard = true;
+            }
+            PaletteCommand::RadialProfile => self.ui_state.panels.show_radial_profile = true,
+            PaletteCommand::PowerSpectrum => self.ui_state.panels.show_fourier = true,
+            PaletteCommand::NotificationHistory => {
+                self.ui_state.panel_popups.show_notifications = true;
+            }
+            PaletteCommand::CommandHistory => self.ui_state.panels.show_command_history = true,
+            PaletteCommand::ClearRois => self.roi_state.clear(),
+            PaletteCommand::StartTour => self.ui_state.tour.start(),
+        }
+    }
+
+    fn set_view_mode(&mut self, mode: ViewMode) {
+        if self.ui_state.view_mode != mode {
+            self.ui_state.view_mode = mode;
+            self.texture = None;
+            self.ui_state.current_tof_bin = 0;
+        }
+    }
+}
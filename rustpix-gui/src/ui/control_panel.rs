@@ -2,17 +2,27 @@
 
 use eframe::egui::{self, Color32, FontFamily, FontId, Rect, Rounding, Stroke};
 use rfd::FileDialog;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Instant, SystemTime};
 
 use super::theme::{accent, form_label, primary_button, ThemeColors};
-use crate::app::{DetectorProfile, DetectorProfileKind, RustpixApp};
+use crate::app::{load_event_classifier, DetectorProfile, DetectorProfileKind, RustpixApp};
 use crate::pipeline::AlgorithmType;
 use crate::state::{
-    ExportFormat, Hdf5ExportOptions, TiffBitDepth, TiffExportOptions, TiffSpectraTiming,
-    TiffStackBehavior, ViewMode,
+    expand_export_template, ExportFormat, ExportProfile, Hdf5ExportOptions, RangeMathOp,
+    TiffBitDepth, TiffExportOptions, TiffSpectraTiming, TiffStackBehavior, ViewMode,
+    EXPORT_TEMPLATE_TOKENS,
 };
 use crate::util::{format_bytes, format_number, sanitize_export_base_name};
-use crate::viewer::Colormap;
-use rustpix_tpx::{ChipTransform, DetectorConfig};
+use crate::viewer::{Colormap, CustomLut, RefreshStep};
+use rustpix_algorithms::{AlgorithmParams, Connectivity};
+use rustpix_core::classification::EventClass;
+use rustpix_core::extraction::SUPER_RESOLUTION_FACTORS;
+use rustpix_tpx::{
+    instrument, ChipTransform, DetectorConfig, OutOfBoundsPolicy, OutputGeometry,
+    TdcCorrectionPolicy,
+};
 
 #[derive(Clone, Copy)]
 enum FileToolbarIcon {
@@ -77,7 +87,15 @@ impl RustpixApp {
         if Self::file_toolbar_button(ui, colors, FileToolbarIcon::Open, can_load, "Open file")
             .clicked()
         {
-            if let Some(path) = FileDialog::new().add_filter("TPX3", &["tpx3"]).pick_file() {
+            if let Some(path) = FileDialog::new()
+                .add_filter("TPX3", &["tpx3"])
+                .add_filter("Hit list", &["csv", "h5", "hdf5"])
+                .add_filter(
+                    "Processed output",
+                    &["csv", "bin", "rpxd", "h5", "hdf5", "nxs"],
+                )
+                .pick_file()
+            {
                 self.load_file(path);
             }
         }
@@ -158,6 +176,72 @@ impl RustpixApp {
                 self.ui_state.panels.show_app_settings = !self.ui_state.panels.show_app_settings;
             }
 
+            let health_btn = egui::Button::new(
+                egui::RichText::new("Health")
+                    .size(11.0)
+                    .color(colors.text_muted),
+            )
+            .fill(Color32::TRANSPARENT)
+            .stroke(Stroke::new(1.0, colors.border_light))
+            .rounding(Rounding::same(4.0));
+            if ui
+                .add(health_btn)
+                .on_hover_text("Detector health dashboard")
+                .clicked()
+            {
+                self.ui_state.panels.show_health_dashboard =
+                    !self.ui_state.panels.show_health_dashboard;
+            }
+
+            let profile_btn = egui::Button::new(
+                egui::RichText::new("Profile")
+                    .size(11.0)
+                    .color(colors.text_muted),
+            )
+            .fill(Color32::TRANSPARENT)
+            .stroke(Stroke::new(1.0, colors.border_light))
+            .rounding(Rounding::same(4.0));
+            if ui
+                .add(profile_btn)
+                .on_hover_text("Radial profile and azimuthal integration")
+                .clicked()
+            {
+                self.ui_state.panels.show_radial_profile =
+                    !self.ui_state.panels.show_radial_profile;
+            }
+
+            let fft_btn = egui::Button::new(
+                egui::RichText::new("FFT")
+                    .size(11.0)
+                    .color(colors.text_muted),
+            )
+            .fill(Color32::TRANSPARENT)
+            .stroke(Stroke::new(1.0, colors.border_light))
+            .rounding(Rounding::same(4.0));
+            if ui
+                .add(fft_btn)
+                .on_hover_text("Power spectrum and autocorrelation of the summed image")
+                .clicked()
+            {
+                self.ui_state.panels.show_fourier = !self.ui_state.panels.show_fourier;
+            }
+
+            let tour_btn = egui::Button::new(
+                egui::RichText::new("? Tour")
+                    .size(11.0)
+                    .color(colors.text_muted),
+            )
+            .fill(Color32::TRANSPARENT)
+            .stroke(Stroke::new(1.0, colors.border_light))
+            .rounding(Rounding::same(4.0));
+            if ui
+                .add(tour_btn)
+                .on_hover_text("Start the guided tour")
+                .clicked()
+            {
+                self.ui_state.tour.start();
+            }
+
             self.render_view_mode_toggle(ui);
             self.render_cache_toggle(ui);
         });
@@ -292,7 +376,7 @@ impl RustpixApp {
     }
 
     /// Render the bottom status bar.
-    pub(crate) fn render_bottom_panel(&self, ctx: &egui::Context) {
+    pub(crate) fn render_bottom_panel(&mut self, ctx: &egui::Context) {
         let colors = ThemeColors::from_ctx(ctx);
 
         egui::TopBottomPanel::bottom("status_bar")
@@ -314,6 +398,7 @@ impl RustpixApp {
                     self.render_cursor_status(ui, colors);
                     self.render_roi_messages(ui, ctx, colors);
                     self.render_export_status(ui, colors);
+                    self.render_out_of_bounds_status(ui, colors);
                     self.render_bottom_right(ui);
                 });
             });
@@ -370,14 +455,6 @@ impl RustpixApp {
                 ctx.request_repaint();
             }
         }
-
-        if let Some((message, expires_at)) = &self.ui_state.roi_warning {
-            let now = ctx.input(|i| i.time);
-            if now <= *expires_at {
-                Self::status_separator(ui, colors);
-                ui.label(egui::RichText::new(message).size(11.0).color(accent::RED));
-            }
-        }
     }
 
     fn render_export_status(&self, ui: &mut egui::Ui, colors: ThemeColors) {
@@ -396,9 +473,47 @@ impl RustpixApp {
         }
     }
 
-    fn render_bottom_right(&self, ui: &mut egui::Ui) {
+    fn render_out_of_bounds_status(&self, ui: &mut egui::Ui, colors: ThemeColors) {
+        let Some(stats) = self.chip_hit_stats.as_ref() else {
+            return;
+        };
+        let total = stats.total().out_of_bounds;
+        if total == 0 {
+            return;
+        }
+        Self::status_separator(ui, colors);
+        let count = usize::try_from(total).unwrap_or(usize::MAX);
+        ui.label(
+            egui::RichText::new(format!("Out of bounds: {}", format_number(count)))
+                .size(11.0)
+                .color(accent::AMBER),
+        )
+        .on_hover_ui(|ui| {
+            ui.label(
+                egui::RichText::new("Hits mapped outside the detector")
+                    .size(12.0)
+                    .strong(),
+            );
+            for (chip_id, counts) in stats.chips() {
+                if counts.out_of_bounds > 0 {
+                    ui.label(format!("Chip {chip_id}: {}", counts.out_of_bounds));
+                }
+            }
+            ui.label(
+                egui::RichText::new("Check the chip size and transforms of the detector profile.")
+                    .size(10.0)
+                    .color(colors.text_muted),
+            );
+        });
+    }
+
+    fn render_bottom_right(&mut self, ui: &mut egui::Ui) {
         ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
             let colors = ThemeColors::from_ui(ui);
+            self.render_notification_bell(ui);
+            ui.add_space(8.0);
+            ui.label(egui::RichText::new("│").size(11.0).color(colors.text_dim));
+            ui.add_space(8.0);
             let memory_bytes = self.memory_rss_bytes();
             let memory_text = if memory_bytes > 0 {
                 format!("RAM: {}", format_bytes(memory_bytes))
@@ -574,6 +689,11 @@ impl RustpixApp {
                             app.render_statistics(ui);
                         });
 
+                        // Acquisition metadata of the open file
+                        self.render_section(ui, "File Info", true, None, |app, ui| {
+                            app.render_file_metadata(ui);
+                        });
+
                         // Clustering section
                         self.render_section(
                             ui,
@@ -782,20 +902,49 @@ impl RustpixApp {
             .selected_text(self.colormap.to_string())
             .width(ui.available_width() - 8.0)
             .show_ui(ui, |ui| {
-                for cmap in [
+                let builtin = [
                     Colormap::Grayscale,
                     Colormap::Green,
                     Colormap::Hot,
                     Colormap::Viridis,
-                ] {
+                ];
+                let imported = self.imported_luts.iter().cloned().map(Colormap::Custom);
+                for cmap in builtin.into_iter().chain(imported).collect::<Vec<_>>() {
+                    let label = cmap.to_string();
                     if ui
-                        .selectable_value(&mut self.colormap, cmap, cmap.to_string())
+                        .selectable_value(&mut self.colormap, cmap, label)
                         .clicked()
                     {
-                        self.texture = None;
+                        self.texture_refresh.invalidate();
                     }
                 }
             });
+        if ui
+            .small_button("Import LUT…")
+            .on_hover_text("Load an ImageJ .lut lookup table as a colormap")
+            .clicked()
+        {
+            if let Some(path) = FileDialog::new()
+                .add_filter("ImageJ LUT", &["lut", "txt"])
+                .pick_file()
+            {
+                match CustomLut::from_file(&path) {
+                    Ok(lut) => {
+                        self.imported_luts
+                            .retain(|existing| existing.name != lut.name);
+                        self.imported_luts.push(lut.clone());
+                        self.colormap = Colormap::Custom(lut);
+                        self.texture_refresh.invalidate();
+                    }
+                    Err(err) => {
+                        self.ui_state.notifications.error(
+                            format!("LUT import failed: {err}"),
+                            ui.ctx().input(|i| i.time),
+                        );
+                    }
+                }
+            }
+        }
 
         ui.add_space(12.0);
 
@@ -806,7 +955,7 @@ impl RustpixApp {
                 .checkbox(&mut self.ui_state.histogram.slicer_enabled, "TOF Slicer")
                 .changed()
             {
-                self.texture = None;
+                self.texture_refresh.invalidate();
             }
         });
 
@@ -816,19 +965,114 @@ impl RustpixApp {
             .checkbox(&mut self.ui_state.histogram.log_scale, "Log scale")
             .changed()
         {
-            self.texture = None;
+            self.texture_refresh.invalidate();
+        }
+
+        ui.add_space(12.0);
+        self.render_range_math_controls(ui, n_bins);
+    }
+
+    /// Render TOF range ratio/difference controls.
+    fn render_range_math_controls(&mut self, ui: &mut egui::Ui, n_bins: usize) {
+        let before = self.ui_state.range_math;
+        ui.add_enabled_ui(n_bins > 0, |ui| {
+            ui.checkbox(&mut self.ui_state.range_math.enabled, "TOF Range Math")
+                .on_hover_text("Show the ratio or difference of two TOF ranges");
+        });
+        if !self.ui_state.range_math.enabled || n_bins == 0 {
+            if before != self.ui_state.range_math {
+                self.texture_refresh.invalidate();
+            }
+            return;
+        }
+
+        let max_bin = n_bins - 1;
+        let math = &mut self.ui_state.range_math;
+        ui.add_space(4.0);
+        egui::ComboBox::from_id_salt("range_math_op")
+            .selected_text(math.op.to_string())
+            .width(ui.available_width() - 8.0)
+            .show_ui(ui, |ui| {
+                for op in [RangeMathOp::Ratio, RangeMathOp::Difference] {
+                    ui.selectable_value(&mut math.op, op, op.to_string());
+                }
+            });
+
+        for (label, range) in [
+            ("Range A", &mut math.range_a),
+            ("Range B", &mut math.range_b),
+        ] {
+            ui.horizontal(|ui| {
+                ui.label(form_label(label));
+                ui.add(egui::DragValue::new(&mut range.0).range(0..=max_bin));
+                ui.label("–");
+                ui.add(egui::DragValue::new(&mut range.1).range(0..=max_bin));
+            });
+            range.0 = range.0.min(max_bin);
+            range.1 = range.1.clamp(range.0, max_bin);
+        }
+
+        if before != self.ui_state.range_math {
+            self.texture_refresh.invalidate();
         }
     }
 
     /// Regenerate texture if needed.
+    ///
+    /// A missing texture (or one of another size) is built at once;
+    /// restyling only invalidates `texture_refresh`, which redraws the
+    /// texture in row bands over the next frames (see
+    /// [`TextureRefresh`](crate::viewer::TextureRefresh)).
     pub(crate) fn ensure_texture(&mut self, ctx: &egui::Context) {
         let has_data = match self.ui_state.view_mode {
             ViewMode::Hits => self.hit_counts.is_some(),
             ViewMode::Neutrons => self.neutron_counts.is_some(),
         };
-        if self.texture.is_none() && has_data {
+        if !has_data {
+            return;
+        }
+        let now = Instant::now();
+        let (width, height) = self.current_data_dimensions();
+        let (disp_w, disp_h) = self
+            .ui_state
+            .histogram_view
+            .transform
+            .display_size(width, height);
+        let fits = self
+            .texture
+            .as_ref()
+            .is_some_and(|texture| texture.size() == [disp_w.max(1), disp_h.max(1)]);
+        if !fits {
             let img = self.generate_histogram();
             self.texture = Some(ctx.load_texture("hist", img, egui::TextureOptions::NEAREST));
+            self.texture_refresh.complete(now);
+            return;
+        }
+
+        let busy = self.processing.is_loading || self.processing.is_processing;
+        match self.texture_refresh.next_step(now, busy, disp_h) {
+            RefreshStep::Idle => {}
+            RefreshStep::Wait(delay) => ctx.request_repaint_after(delay),
+            RefreshStep::Rows { rows, restart } => {
+                if let Some(image) = self.generate_range_math_image() {
+                    if let Some(texture) = self.texture.as_mut() {
+                        texture.set(image, egui::TextureOptions::NEAREST);
+                    }
+                    self.texture_refresh.complete(now);
+                    return;
+                }
+                if restart {
+                    self.texture_max_count = self.histogram_max_count();
+                }
+                let band = self.generate_histogram_rows(rows.clone(), self.texture_max_count);
+                if let Some(texture) = self.texture.as_mut() {
+                    texture.set_partial([0, rows.start], band, egui::TextureOptions::NEAREST);
+                }
+                self.texture_refresh.rows_done(rows, disp_h, now.elapsed());
+                if !self.texture_refresh.is_idle() {
+                    ctx.request_repaint();
+                }
+            }
         }
     }
 
@@ -861,6 +1105,16 @@ impl RustpixApp {
                     );
                     ui.selectable_value(&mut self.algo_type, AlgorithmType::Dbscan, "DBSCAN");
                     ui.selectable_value(&mut self.algo_type, AlgorithmType::Grid, "Grid");
+                    ui.selectable_value(
+                        &mut self.algo_type,
+                        AlgorithmType::Hierarchical,
+                        "Hierarchical",
+                    );
+                    ui.selectable_value(
+                        &mut self.algo_type,
+                        AlgorithmType::ConnectedComponents,
+                        "Connected Components",
+                    );
                 });
 
             if ui
@@ -909,6 +1163,16 @@ impl RustpixApp {
                     self.render_grid_control(ui);
                 }
 
+                if self.algo_type == AlgorithmType::Hierarchical {
+                    ui.add_space(4.0);
+                    self.render_hierarchical_control(ui);
+                }
+
+                if self.algo_type == AlgorithmType::ConnectedComponents {
+                    ui.add_space(4.0);
+                    self.render_connectivity_control(ui);
+                }
+
                 ui.add_space(4.0);
                 self.render_super_resolution_control(ui);
                 ui.add_space(6.0);
@@ -918,6 +1182,16 @@ impl RustpixApp {
             });
     }
 
+    /// Take the TDC frequency and, if the instrument has a fixed one, the
+    /// flight path of the shipped profile `name`.
+    fn apply_instrument_constants(&mut self, name: &str) {
+        let Some(profile) = instrument::find(name) else {
+            return;
+        };
+        self.tdc_frequency = profile.detector.tdc_frequency_hz;
+        self.apply_beamline(&profile.detector);
+    }
+
     #[allow(clippy::too_many_lines)]
     fn render_detector_profile_controls(&mut self, ui: &mut egui::Ui) {
         ui.horizontal(|ui| {
@@ -934,7 +1208,14 @@ impl RustpixApp {
                     .width(160.0)
                     .show_ui(ui, |ui| {
                         let mut kind = self.detector_profile.kind;
-                        ui.selectable_value(&mut kind, DetectorProfileKind::Venus, "VENUS (SNS)");
+                        for profile in instrument::profiles() {
+                            ui.selectable_value(
+                                &mut kind,
+                                DetectorProfileKind::Instrument(&profile.name),
+                                profile.label(),
+                            )
+                            .on_hover_text(profile.description.as_str());
+                        }
                         if self.detector_profile.has_custom() {
                             let custom_label = self
                                 .detector_profile
@@ -954,6 +1235,9 @@ impl RustpixApp {
                         }
                         if kind != self.detector_profile.kind {
                             self.detector_profile.kind = kind;
+                            if let DetectorProfileKind::Instrument(name) = kind {
+                                self.apply_instrument_constants(name);
+                            }
                         }
                     });
             });
@@ -972,16 +1256,17 @@ impl RustpixApp {
                             {
                                 self.tdc_frequency = config.tdc_frequency_hz;
                             }
+                            self.apply_beamline(&config);
                             self.detector_profile.custom_config = Some(config);
                             self.detector_profile.custom_path = Some(path.clone());
                             self.detector_profile.custom_name = name;
                             self.detector_profile.kind = DetectorProfileKind::Custom;
                         }
                         Err(err) => {
-                            self.ui_state.roi_warning = Some((
+                            self.ui_state.notifications.error(
                                 format!("Detector config load failed: {err}"),
-                                ui.ctx().input(|i| i.time + 6.0),
-                            ));
+                                ui.ctx().input(|i| i.time),
+                            );
                         }
                     }
                 }
@@ -993,10 +1278,10 @@ impl RustpixApp {
                 {
                     let config = self.current_detector_config();
                     if let Err(err) = config.to_file(&path) {
-                        self.ui_state.roi_warning = Some((
+                        self.ui_state.notifications.error(
                             format!("Detector config save failed: {err}"),
-                            ui.ctx().input(|i| i.time + 6.0),
-                        ));
+                            ui.ctx().input(|i| i.time),
+                        );
                     } else if self.detector_profile.kind == DetectorProfileKind::Custom {
                         let name = path.file_name().map(|n| n.to_string_lossy().to_string());
                         self.detector_profile.custom_path = Some(path.clone());
@@ -1007,10 +1292,8 @@ impl RustpixApp {
                 }
             }
             if ui.button("Reset to VENUS").clicked() {
-                self.detector_profile = DetectorProfile {
-                    kind: DetectorProfileKind::Venus,
-                    ..Default::default()
-                };
+                self.detector_profile = DetectorProfile::default();
+                self.apply_instrument_constants("venus");
             }
         });
 
@@ -1031,10 +1314,8 @@ impl RustpixApp {
             .show(ui, |ui| {
                 if self.detector_profile.custom_config.is_none() {
                     ui.label("No custom config loaded.");
-                    if ui.button("Create custom from VENUS").clicked() {
-                        self.detector_profile.custom_config =
-                            Some(DetectorConfig::venus_defaults());
-                        self.tdc_frequency = 60.0;
+                    if ui.button("Create custom from selected").clicked() {
+                        self.detector_profile.custom_config = Some(self.current_detector_config());
                         if self.detector_profile.custom_name.is_none() {
                             self.detector_profile.custom_name = Some("Custom".to_string());
                         }
@@ -1077,12 +1358,85 @@ impl RustpixApp {
                         }
                     });
 
-                    changed |= ui
-                        .checkbox(
-                            &mut config.enable_missing_tdc_correction,
-                            "Enable missing TDC correction",
-                        )
-                        .changed();
+                    ui.horizontal(|ui| {
+                        ui.label("Missing TDC correction");
+                        let label = |policy| match policy {
+                            TdcCorrectionPolicy::None => "None",
+                            TdcCorrectionPolicy::ExtrapolateLastPeriod => "Extrapolate last period",
+                            TdcCorrectionPolicy::InterpolateNeighbors => "Interpolate neighbors",
+                            TdcCorrectionPolicy::DropSection => "Drop until next TDC",
+                        };
+                        egui::ComboBox::from_id_salt("tdc_correction_select")
+                            .selected_text(label(config.tdc_correction_policy))
+                            .show_ui(ui, |ui| {
+                                for policy in [
+                                    TdcCorrectionPolicy::None,
+                                    TdcCorrectionPolicy::ExtrapolateLastPeriod,
+                                    TdcCorrectionPolicy::InterpolateNeighbors,
+                                    TdcCorrectionPolicy::DropSection,
+                                ] {
+                                    changed |= ui
+                                        .selectable_value(
+                                            &mut config.tdc_correction_policy,
+                                            policy,
+                                            label(policy),
+                                        )
+                                        .changed();
+                                }
+                            });
+                    });
+
+                    ui.horizontal(|ui| {
+                        ui.label("Output geometry");
+                        let label = |geometry| match geometry {
+                            OutputGeometry::Native => "Native transforms",
+                            OutputGeometry::Packed => "Packed (no gaps)",
+                            OutputGeometry::GapFilled => "Gap-filled",
+                        };
+                        egui::ComboBox::from_id_salt("output_geometry_select")
+                            .selected_text(label(config.output_geometry))
+                            .show_ui(ui, |ui| {
+                                for geometry in [
+                                    OutputGeometry::Native,
+                                    OutputGeometry::Packed,
+                                    OutputGeometry::GapFilled,
+                                ] {
+                                    changed |= ui
+                                        .selectable_value(
+                                            &mut config.output_geometry,
+                                            geometry,
+                                            label(geometry),
+                                        )
+                                        .changed();
+                                }
+                            });
+                    });
+
+                    ui.horizontal(|ui| {
+                        ui.label("Out-of-bounds hits");
+                        let label = |policy| match policy {
+                            OutOfBoundsPolicy::Clamp => "Clamp to edge",
+                            OutOfBoundsPolicy::Drop => "Drop",
+                            OutOfBoundsPolicy::Error => "Fail the load",
+                        };
+                        egui::ComboBox::from_id_salt("out_of_bounds_select")
+                            .selected_text(label(config.out_of_bounds))
+                            .show_ui(ui, |ui| {
+                                for policy in [
+                                    OutOfBoundsPolicy::Clamp,
+                                    OutOfBoundsPolicy::Drop,
+                                    OutOfBoundsPolicy::Error,
+                                ] {
+                                    changed |= ui
+                                        .selectable_value(
+                                            &mut config.out_of_bounds,
+                                            policy,
+                                            label(policy),
+                                        )
+                                        .changed();
+                                }
+                            });
+                    });
 
                     ui.add_space(6.0);
                     ui.horizontal(|ui| {
@@ -1492,6 +1846,76 @@ x,y are local chip coordinates (pixels).",
         });
     }
 
+    fn render_hierarchical_control(&mut self, ui: &mut egui::Ui) {
+        let colors = ThemeColors::from_ui(ui);
+        let rows = [
+            (
+                "Merge gap",
+                &mut self.hierarchical_merge_distance,
+                (1.0, 0.0, 100.0, " px"),
+                "Largest gap between the boxes of clusters that are merged",
+            ),
+            (
+                "Merge window",
+                &mut self.hierarchical_merge_window_ns,
+                (25.0, 0.0, 5000.0, " ns"),
+                "Largest gap between the TOF ranges of clusters that are merged",
+            ),
+        ];
+        for (label, value, (step, min, max, suffix), hover) in rows {
+            ui.horizontal(|ui| {
+                ui.label(
+                    egui::RichText::new(label)
+                        .size(10.0)
+                        .color(colors.text_muted),
+                )
+                .on_hover_text(hover);
+                ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                    if ui
+                        .add_enabled(
+                            *value < max,
+                            egui::Button::new("+").min_size(egui::vec2(18.0, 18.0)),
+                        )
+                        .clicked()
+                    {
+                        *value = (*value + step).min(max);
+                    }
+                    ui.add(
+                        egui::DragValue::new(&mut *value)
+                            .range(min..=max)
+                            .speed(step)
+                            .suffix(suffix),
+                    );
+                    if ui
+                        .add_enabled(
+                            *value > min,
+                            egui::Button::new("−").min_size(egui::vec2(18.0, 18.0)),
+                        )
+                        .clicked()
+                    {
+                        *value = (*value - step).max(min);
+                    }
+                });
+            });
+        }
+    }
+
+    fn render_connectivity_control(&mut self, ui: &mut egui::Ui) {
+        let colors = ThemeColors::from_ui(ui);
+        ui.horizontal(|ui| {
+            ui.label(
+                egui::RichText::new("Connectivity")
+                    .size(10.0)
+                    .color(colors.text_muted),
+            )
+            .on_hover_text("Link pixels sharing an edge (4) or also a corner (8)");
+            ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                ui.selectable_value(&mut self.connectivity, Connectivity::Eight, "8");
+                ui.selectable_value(&mut self.connectivity, Connectivity::Four, "4");
+            });
+        });
+    }
+
     fn render_super_resolution_control(&mut self, ui: &mut egui::Ui) {
         let colors = ThemeColors::from_ui(ui);
         ui.horizontal(|ui| {
@@ -1502,34 +1926,20 @@ x,y are local chip coordinates (pixels).",
             )
             .on_hover_text("Sub-pixel centroid scale (affects neutron coordinates + export)");
             ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
-                let step = 0.1;
-                let min = 1.0;
-                let max = 16.0;
-                if ui
-                    .add_enabled(
-                        self.super_resolution_factor < max,
-                        egui::Button::new("+").min_size(egui::vec2(18.0, 18.0)),
-                    )
-                    .clicked()
-                {
-                    self.super_resolution_factor = (self.super_resolution_factor + step).min(max);
-                }
-                ui.add(
-                    egui::DragValue::new(&mut self.super_resolution_factor)
-                        .range(min..=max)
-                        .speed(step)
-                        .suffix("×"),
-                )
-                .on_hover_text("Higher values increase spatial precision (more sub-pixels)");
-                if ui
-                    .add_enabled(
-                        self.super_resolution_factor > min,
-                        egui::Button::new("−").min_size(egui::vec2(18.0, 18.0)),
-                    )
-                    .clicked()
-                {
-                    self.super_resolution_factor = (self.super_resolution_factor - step).max(min);
-                }
+                egui::ComboBox::from_id_salt("super_resolution_select")
+                    .selected_text(format!("{}×", self.super_resolution_factor))
+                    .width(56.0)
+                    .show_ui(ui, |ui| {
+                        for factor in SUPER_RESOLUTION_FACTORS {
+                            ui.selectable_value(
+                                &mut self.super_resolution_factor,
+                                factor,
+                                format!("{factor}×"),
+                            );
+                        }
+                    })
+                    .response
+                    .on_hover_text("Higher values increase spatial precision (more sub-pixels)");
             });
         });
     }
@@ -1580,6 +1990,77 @@ x,y are local chip coordinates (pixels).",
                 }
             });
         });
+        self.render_classifier_controls(ui);
+    }
+
+    fn render_classifier_controls(&mut self, ui: &mut egui::Ui) {
+        let colors = ThemeColors::from_ui(ui);
+        ui.horizontal(|ui| {
+            ui.label(
+                egui::RichText::new("Classifier")
+                    .size(10.0)
+                    .color(colors.text_muted),
+            )
+            .on_hover_text(
+                "Label events as neutron, gamma or noise from cluster size, ToT and shape \
+                 (JSON thresholds or decision tree)",
+            );
+            ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                if self.event_classifier.is_some()
+                    && ui.small_button("✕").on_hover_text("Remove").clicked()
+                {
+                    self.event_classifier = None;
+                    self.event_classifier_name = None;
+                }
+                let label = self.event_classifier_name.as_deref().unwrap_or("Load…");
+                if ui.small_button(label).clicked() {
+                    if let Some(path) = FileDialog::new()
+                        .add_filter("Classifier", &["json"])
+                        .pick_file()
+                    {
+                        match load_event_classifier(&path) {
+                            Ok(classifier) => {
+                                self.event_classifier = Some(Arc::new(classifier));
+                                self.event_classifier_name = path
+                                    .file_name()
+                                    .map(|name| name.to_string_lossy().to_string());
+                            }
+                            Err(err) => {
+                                self.ui_state.notifications.error(
+                                    format!("Classifier load failed: {err}"),
+                                    ui.ctx().input(|i| i.time),
+                                );
+                            }
+                        }
+                    }
+                }
+            });
+        });
+
+        if !self.neutrons.has_classes() {
+            return;
+        }
+        let mut filter = self.neutron_class_filter;
+        ui.horizontal(|ui| {
+            ui.label(
+                egui::RichText::new("Show")
+                    .size(10.0)
+                    .color(colors.text_muted),
+            )
+            .on_hover_text("Event class shown in the neutron view");
+            egui::ComboBox::from_id_salt("neutron_class_filter")
+                .selected_text(filter.map_or("All events", EventClass::as_str))
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut filter, None, "All events");
+                    for class in EventClass::ALL {
+                        ui.selectable_value(&mut filter, Some(class), class.as_str());
+                    }
+                });
+        });
+        if filter != self.neutron_class_filter {
+            self.neutron_class_filter = filter;
+            self.rebuild_neutron_hyperstack();
+        }
     }
 
     fn render_clustering_reset(&mut self, ui: &mut egui::Ui) {
@@ -1590,6 +2071,10 @@ x,y are local chip coordinates (pixels).",
             self.max_cluster_size = None;
             self.dbscan_min_points = 2;
             self.grid_cell_size = 32;
+            let params = AlgorithmParams::default();
+            self.hierarchical_merge_distance = params.hierarchical_merge_distance;
+            self.hierarchical_merge_window_ns = params.hierarchical_merge_window_ns;
+            self.connectivity = params.connectivity;
             self.super_resolution_factor = 1.0;
             self.weighted_by_tot = false;
             self.min_tot_threshold = 0;
@@ -1638,6 +2123,7 @@ x,y are local chip coordinates (pixels).",
         self.render_pixel_health_header(ui, &colors);
         Self::render_pixel_health_counts(ui, &colors, dead_count, hot_count);
         self.render_pixel_health_overlays(ui);
+        self.render_pixel_mask_file(ui, &colors);
 
         if self.ui_state.pixel_health.show_pixel_health_settings {
             self.render_pixel_health_settings(ui, &colors, mean, std_dev, hot_threshold);
@@ -1716,6 +2202,101 @@ x,y are local chip coordinates (pixels).",
             self.update_masked_spectrum();
             self.hit_data_revision = self.hit_data_revision.wrapping_add(1);
         }
+        if let Some(mask) = self.excluded_pixel_mask() {
+            let colors = ThemeColors::from_ui(ui);
+            ui.label(
+                egui::RichText::new(format!(
+                    "{} pixels excluded from spectra, stats and scaling",
+                    format_number(mask.excluded_count)
+                ))
+                .size(10.0)
+                .color(colors.text_muted),
+            );
+        }
+    }
+
+    /// Save the current masks, or load a saved mask to use instead of
+    /// detecting one.
+    fn render_pixel_mask_file(&mut self, ui: &mut egui::Ui, colors: &ThemeColors) {
+        ui.add_space(8.0);
+        ui.horizontal(|ui| {
+            if ui
+                .button("Save mask…")
+                .on_hover_text(
+                    "Save the dead/hot pixels as JSON or TIFF for later sessions, \
+                     rustpix process --pixel-mask and rustpix.load_pixel_mask",
+                )
+                .clicked()
+            {
+                if let Some(path) = FileDialog::new()
+                    .add_filter("Pixel mask", &["json"])
+                    .add_filter("TIFF mask", &["tif", "tiff"])
+                    .set_file_name("pixel_mask.json")
+                    .save_file()
+                {
+                    if let Some(mask) = self.pixel_mask_export() {
+                        if let Err(err) = mask.save(&path) {
+                            self.ui_state.notifications.error(
+                                format!("Pixel mask save failed: {err}"),
+                                ui.ctx().input(|i| i.time),
+                            );
+                        }
+                    }
+                }
+            }
+            if ui
+                .button("Load mask…")
+                .on_hover_text(
+                    "Use a saved mask instead of detecting one, also for files loaded later",
+                )
+                .clicked()
+            {
+                if let Some(path) = FileDialog::new()
+                    .add_filter("Pixel mask", &["json", "tif", "tiff"])
+                    .pick_file()
+                {
+                    if let Err(err) = self.load_pixel_mask_file(path) {
+                        self.ui_state.notifications.error(
+                            format!("Pixel mask load failed: {err}"),
+                            ui.ctx().input(|i| i.time),
+                        );
+                    }
+                }
+            }
+        });
+
+        let Some((path, mask)) = &self.loaded_pixel_mask else {
+            return;
+        };
+        let name = path.file_name().map_or_else(
+            || path.display().to_string(),
+            |name| name.to_string_lossy().to_string(),
+        );
+        let fits = self
+            .pixel_masks
+            .as_ref()
+            .is_some_and(|data| (data.width, data.height) == (mask.width, mask.height));
+        let (text, color) = if fits {
+            (format!("Using {name}"), colors.text_dim)
+        } else {
+            (
+                format!(
+                    "{name} is {}x{}; detecting masks for this data",
+                    mask.width, mask.height
+                ),
+                accent::RED,
+            )
+        };
+        let created = format!("Created {}", mask.created);
+        let mut clear = false;
+        ui.horizontal(|ui| {
+            ui.label(egui::RichText::new(text).size(10.0).color(color))
+                .on_hover_text(created);
+            clear = ui.small_button("Clear").clicked();
+        });
+        if clear {
+            self.clear_loaded_pixel_mask();
+        }
     }
 
     fn render_pixel_health_settings(
@@ -1743,6 +2324,43 @@ x,y are local chip coordinates (pixels).",
                 self.update_pixel_masks();
             }
         });
+        ui.horizontal(|ui| {
+            ui.label(
+                egui::RichText::new("Per-TOF window")
+                    .size(11.0)
+                    .color(colors.text_muted),
+            );
+            let mut window = self.tof_hot_window;
+            let response = ui
+                .add(
+                    egui::DragValue::new(&mut window)
+                        .range(0..=self.hit_tof_bins)
+                        .suffix(" bins"),
+                )
+                .on_hover_text(
+                    "Also flag pixels that are hot only in some TOF bins, judged over this \
+                     many bins around each bin (0 = off)",
+                );
+            if response.changed() {
+                self.tof_hot_window = window;
+                self.update_pixel_masks();
+            }
+        });
+        if let Some(tof_hot) = self
+            .pixel_masks
+            .as_ref()
+            .and_then(|masks| masks.tof_hot.as_ref())
+        {
+            ui.label(
+                egui::RichText::new(format!(
+                    "{} pixels hot in some TOF bins ({} pixel-bins)",
+                    format_number(tof_hot.pixel_count()),
+                    format_number(tof_hot.len())
+                ))
+                .size(10.0)
+                .color(colors.text_dim),
+            );
+        }
         ui.add_space(6.0);
         if ui.button("Recompute masks").clicked() {
             self.update_pixel_masks();
@@ -1833,10 +2451,19 @@ x,y are local chip coordinates (pixels).",
                         ui.label("TOF offset (ns)");
                         ui.add(
                             egui::DragValue::new(&mut self.tof_offset_ns)
-                                .range(0.0..=1_000_000.0)
+                                .range(-1_000_000.0..=1_000_000.0)
                                 .speed(10.0),
                         );
                     });
+
+                    ui.add_space(4.0);
+                    if ui
+                        .button("Calibrate from spectrum…")
+                        .on_hover_text("Fit both from features of known energy")
+                        .clicked()
+                    {
+                        self.ui_state.panels.show_energy_calibration = true;
+                    }
                 });
             self.ui_state.panels.show_spectrum_settings = show_spectrum_settings;
         }
@@ -1866,7 +2493,9 @@ x,y are local chip coordinates (pixels).",
             .default_width(300.0)
             .show(ctx, |ui| {
                 ui.label(egui::RichText::new("Algorithm").strong());
-                ui.label("• ABS / DBSCAN / Grid: choose clustering method.");
+                ui.label("• ABS / DBSCAN / Grid / Hierarchical / Connected: choose method.");
+                ui.label("• Hierarchical merges nearby clusters of sparse tracks.");
+                ui.label("• Connected links touching pixels only (radius unused).");
                 ui.label("• Parameters control spatial radius + time window.");
                 ui.add_space(6.0);
                 ui.label(egui::RichText::new("Extraction").strong());
@@ -1902,6 +2531,10 @@ x,y are local chip coordinates (pixels).",
                 ui.add_space(6.0);
                 ui.label(egui::RichText::new("Log scale").strong());
                 ui.label("• Use log intensity for histogram display.");
+                ui.add_space(6.0);
+                ui.label(egui::RichText::new("TOF Range Math").strong());
+                ui.label("• Show A / B or A − B for two TOF bin ranges.");
+                ui.label("• Pixels with no counts in B are blank in ratio mode.");
             });
         self.ui_state.panel_popups.show_view_help = open;
     }
@@ -1943,13 +2576,20 @@ x,y are local chip coordinates (pixels).",
                 let view_mode = self.ui_state.view_mode;
                 let export_in_progress = self.ui_state.export.in_progress;
 
+                self.render_export_profiles(ui, &colors);
+                ui.add_space(8.0);
                 Self::render_export_format_selector(ui, &colors, &mut self.ui_state.export.format);
+                ui.add_space(6.0);
+                Self::render_export_destination(ui, &colors, &mut self.ui_state.export.destination);
                 ui.add_space(10.0);
 
                 let save_clicked = match self.ui_state.export.format {
                     ExportFormat::Hdf5 => {
+                        let file_name = self.export_name(&self.ui_state.export.options.file_name);
                         let options = &mut self.ui_state.export.options;
                         Self::apply_export_availability(options, availability);
+                        Self::render_hdf5_file_name(ui, &colors, options, &file_name);
+                        ui.add_space(8.0);
                         Self::render_export_header(ui, &colors, options);
                         ui.add_space(8.0);
                         Self::render_export_dataset_options(
@@ -1975,15 +2615,15 @@ x,y are local chip coordinates (pixels).",
                             export_in_progress,
                         )
                     }
-                    ExportFormat::TiffFolder | ExportFormat::TiffStack => {
+                    ExportFormat::TiffFolder | ExportFormat::TiffStack | ExportFormat::Fits => {
                         self.populate_default_tiff_base_name();
-                        let options = &mut self.ui_state.export.tiff;
-                        let base_name_ok =
-                            !sanitize_export_base_name(&options.base_name).is_empty();
+                        let base_name = self.export_name(&self.ui_state.export.tiff.base_name);
+                        let base_name_ok = !base_name.is_empty();
                         Self::render_tiff_export_options(
                             ui,
                             &colors,
-                            options,
+                            &mut self.ui_state.export.tiff,
+                            &base_name,
                             self.ui_state.export.format,
                             availability,
                         );
@@ -2001,17 +2641,34 @@ x,y are local chip coordinates (pixels).",
                 if save_clicked {
                     match self.ui_state.export.format {
                         ExportFormat::Hdf5 => {
-                            if let Some(path) =
-                                FileDialog::new().set_file_name("rustpix.h5").save_file()
-                            {
+                            let mut stem =
+                                self.export_name(&self.ui_state.export.options.file_name);
+                            if stem.is_empty() {
+                                stem = "rustpix".to_string();
+                            }
+                            let file_name = format!("{stem}.h5");
+                            let path = match self.ui_state.export.destination.as_ref() {
+                                Some(folder) => Some(folder.join(&file_name)),
+                                None if self.ui_state.export.options.append => FileDialog::new()
+                                    .add_filter("HDF5", &["h5", "hdf5", "nxs"])
+                                    .pick_file(),
+                                None => FileDialog::new().set_file_name(&file_name).save_file(),
+                            };
+                            if let Some(path) = path {
                                 self.start_export_hdf5(path);
                                 should_close = true;
                             }
                         }
-                        ExportFormat::TiffFolder | ExportFormat::TiffStack => {
-                            if let Some(parent) = FileDialog::new().pick_folder() {
+                        ExportFormat::TiffFolder | ExportFormat::TiffStack | ExportFormat::Fits => {
+                            let parent = self
+                                .ui_state
+                                .export
+                                .destination
+                                .clone()
+                                .or_else(|| FileDialog::new().pick_folder());
+                            if let Some(parent) = parent {
                                 let base_name =
-                                    sanitize_export_base_name(&self.ui_state.export.tiff.base_name);
+                                    self.export_name(&self.ui_state.export.tiff.base_name);
                                 if !base_name.is_empty() {
                                     let folder = parent.join(&base_name);
                                     self.start_export_tiff(folder, self.ui_state.export.format);
@@ -2028,6 +2685,163 @@ x,y are local chip coordinates (pixels).",
         self.ui_state.export.show_dialog = open;
     }
 
+    /// Profile picker with save/delete controls for named export profiles.
+    fn render_export_profiles(&mut self, ui: &mut egui::Ui, colors: &ThemeColors) {
+        ui.label(
+            egui::RichText::new("Profile")
+                .size(11.0)
+                .color(colors.text_primary),
+        );
+        ui.add_space(4.0);
+
+        let mut apply = None;
+        let mut delete = false;
+        let mut save = false;
+        let state = &mut self.ui_state.export.profiles;
+        ui.horizontal(|ui| {
+            egui::ComboBox::from_id_salt("export_profile")
+                .selected_text(state.selected.as_deref().unwrap_or("Custom"))
+                .width(ui.available_width() - 70.0)
+                .show_ui(ui, |ui| {
+                    for profile in &state.profiles.profiles {
+                        let selected = state.selected.as_deref() == Some(profile.name.as_str());
+                        if ui.selectable_label(selected, profile.name.as_str()).clicked() {
+                            apply = Some(profile.name.clone());
+                        }
+                    }
+                });
+            delete = ui
+                .add_enabled(state.selected.is_some(), egui::Button::new("Delete"))
+                .clicked();
+        });
+        ui.horizontal(|ui| {
+            ui.add(
+                egui::TextEdit::singleline(&mut state.new_name)
+                    .hint_text("Profile name")
+                    .desired_width(ui.available_width() - 70.0),
+            );
+            save = ui
+                .add_enabled(!state.new_name.trim().is_empty(), egui::Button::new("Save"))
+                .on_hover_text("Save the current export settings under this name")
+                .clicked();
+        });
+
+        if let Some(name) = apply {
+            self.apply_export_profile(&name);
+        }
+        if save {
+            let name = self.ui_state.export.profiles.new_name.trim().to_string();
+            let export = &mut self.ui_state.export;
+            export.profiles.profiles.upsert(ExportProfile {
+                name: name.clone(),
+                format: export.format,
+                destination: export.destination.clone(),
+                hdf5: export.options.clone(),
+                tiff: export.tiff.clone(),
+            });
+            export.profiles.selected = Some(name);
+            export.profiles.new_name.clear();
+            self.save_export_profiles(ui.input(|i| i.time));
+        }
+        if delete {
+            let profiles = &mut self.ui_state.export.profiles;
+            if let Some(name) = profiles.selected.take() {
+                profiles.profiles.remove(&name);
+            }
+            self.save_export_profiles(ui.input(|i| i.time));
+        }
+    }
+
+    fn apply_export_profile(&mut self, name: &str) {
+        let export = &mut self.ui_state.export;
+        let Some(profile) = export.profiles.profiles.get(name).cloned() else {
+            return;
+        };
+        export.format = profile.format;
+        export.destination = profile.destination;
+        export.options = profile.hdf5;
+        export.tiff = profile.tiff;
+        export.profiles.selected = Some(profile.name);
+    }
+
+    fn save_export_profiles(&mut self, now: f64) {
+        if let Err(err) = self.ui_state.export.profiles.profiles.save() {
+            self.ui_state
+                .notifications
+                .error(format!("Failed to save export profiles: {err}"), now);
+        }
+    }
+
+    /// Expand an export name template for the loaded run and view mode.
+    fn export_name(&self, template: &str) -> String {
+        let run = self
+            .selected_file
+            .as_ref()
+            .and_then(|path| path.file_stem())
+            .and_then(|stem| stem.to_str());
+        expand_export_template(template, run, self.ui_state.view_mode, SystemTime::now())
+    }
+
+    fn render_export_destination(
+        ui: &mut egui::Ui,
+        colors: &ThemeColors,
+        destination: &mut Option<PathBuf>,
+    ) {
+        ui.horizontal(|ui| {
+            ui.label("Destination");
+            let text = destination.as_ref().map_or_else(
+                || "Ask when saving".to_string(),
+                |dir| dir.display().to_string(),
+            );
+            ui.label(
+                egui::RichText::new(text)
+                    .size(10.0)
+                    .color(colors.text_muted),
+            );
+            ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                if destination.is_some() && ui.small_button("Clear").clicked() {
+                    *destination = None;
+                }
+                if ui
+                    .small_button("Choose...")
+                    .on_hover_text("Export into this folder without a file dialog")
+                    .clicked()
+                {
+                    if let Some(dir) = FileDialog::new().pick_folder() {
+                        *destination = Some(dir);
+                    }
+                }
+            });
+        });
+    }
+
+    fn render_hdf5_file_name(
+        ui: &mut egui::Ui,
+        colors: &ThemeColors,
+        options: &mut Hdf5ExportOptions,
+        file_name: &str,
+    ) {
+        ui.horizontal(|ui| {
+            ui.label("File name");
+            ui.add(
+                egui::TextEdit::singleline(&mut options.file_name)
+                    .desired_width(ui.available_width() - 70.0),
+            )
+            .on_hover_text(format!("Tokens: {EXPORT_TEMPLATE_TOKENS}"));
+        });
+        ui.add_space(2.0);
+        let stem = if file_name.is_empty() {
+            "rustpix"
+        } else {
+            file_name
+        };
+        ui.label(
+            egui::RichText::new(format!("Saves \"{stem}.h5\"."))
+                .size(10.0)
+                .color(colors.text_muted),
+        );
+    }
+
     fn render_export_format_selector(
         ui: &mut egui::Ui,
         colors: &ThemeColors,
@@ -2047,6 +2861,7 @@ x,y are local chip coordinates (pixels).",
                     ExportFormat::Hdf5,
                     ExportFormat::TiffFolder,
                     ExportFormat::TiffStack,
+                    ExportFormat::Fits,
                 ] {
                     ui.selectable_value(format, option, option.to_string());
                 }
@@ -2057,21 +2872,30 @@ x,y are local chip coordinates (pixels).",
         ui: &mut egui::Ui,
         colors: &ThemeColors,
         options: &mut TiffExportOptions,
+        base_name: &str,
         format: ExportFormat,
         availability: ExportAvailability,
     ) {
+        let title = if format == ExportFormat::Fits {
+            "FITS export options"
+        } else {
+            "TIFF export options"
+        };
         ui.label(
-            egui::RichText::new("TIFF export options")
+            egui::RichText::new(title)
                 .size(11.0)
                 .color(colors.text_primary),
         );
         ui.add_space(6.0);
 
-        Self::render_tiff_bit_depth(ui, options);
-        ui.add_space(4.0);
+        // FITS counts are always unsigned 32-bit.
+        if format != ExportFormat::Fits {
+            Self::render_tiff_bit_depth(ui, options);
+            ui.add_space(4.0);
+        }
         Self::render_tiff_spectra_options(ui, options);
         ui.add_space(8.0);
-        Self::render_tiff_base_name(ui, colors, options);
+        Self::render_tiff_base_name(ui, colors, options, base_name);
         if format == ExportFormat::TiffStack {
             Self::render_tiff_stack_behavior(ui, colors, options);
         }
@@ -2133,21 +2957,23 @@ x,y are local chip coordinates (pixels).",
         ui: &mut egui::Ui,
         colors: &ThemeColors,
         options: &mut TiffExportOptions,
+        base_name: &str,
     ) {
         ui.horizontal(|ui| {
             ui.label("Base name");
             ui.add(
                 egui::TextEdit::singleline(&mut options.base_name)
                     .desired_width(ui.available_width() - 70.0),
-            );
+            )
+            .on_hover_text(format!("Tokens: {EXPORT_TEMPLATE_TOKENS}"));
         });
         ui.add_space(2.0);
         ui.label(
-            egui::RichText::new("A folder named after the base name will be created.")
+            egui::RichText::new(format!("Creates folder \"{base_name}\"."))
                 .size(10.0)
                 .color(colors.text_muted),
         );
-        if sanitize_export_base_name(&options.base_name).is_empty() {
+        if base_name.is_empty() {
             ui.add_space(2.0);
             ui.label(
                 egui::RichText::new("Base name is required.")
@@ -2219,6 +3045,7 @@ x,y are local chip coordinates (pixels).",
         let label = match format {
             ExportFormat::TiffFolder => "Export TIFF Folder...",
             ExportFormat::TiffStack => "Export TIFF Stack...",
+            ExportFormat::Fits => "Export FITS...",
             ExportFormat::Hdf5 => "Save HDF5...",
         };
         ui.add_enabled(can_export, egui::Button::new(label))
@@ -2330,16 +3157,28 @@ x,y are local chip coordinates (pixels).",
             egui::Checkbox::new(&mut options.datasets.neutrons, neutrons_label),
         );
 
+        // Histograms and masks describe one run, so they are not appended.
+        if options.append {
+            options.datasets.histogram = false;
+            options.masks.pixel_masks = false;
+        }
         let hist_label = format!("Histogram ({view_mode})");
         ui.add_enabled(
-            availability.histogram.is_available(),
+            availability.histogram.is_available() && !options.append,
             egui::Checkbox::new(&mut options.datasets.histogram, hist_label),
         );
 
         ui.add_enabled(
-            availability.masks.is_available(),
+            availability.masks.is_available() && !options.append,
             egui::Checkbox::new(&mut options.masks.pixel_masks, "Pixel masks"),
         );
+
+        ui.add_space(4.0);
+        ui.checkbox(&mut options.append, "Append events to existing file")
+            .on_hover_text(
+                "Extend the hit/neutron datasets of an existing HDF5 file (e.g. one file per \
+                 sample over a measurement series). Layout and metadata must match.",
+            );
     }
 
     fn render_export_deflate_warning(ui: &mut egui::Ui) {
@@ -2432,9 +3271,11 @@ x,y are local chip coordinates (pixels).",
         let can_export = any_selected && deflate_ok && !export_in_progress;
 
         let label = match format {
+            ExportFormat::Hdf5 if options.append => "Append to HDF5...",
             ExportFormat::Hdf5 => "Save HDF5...",
             ExportFormat::TiffFolder => "Export TIFF Folder...",
             ExportFormat::TiffStack => "Export TIFF Stack...",
+            ExportFormat::Fits => "Export FITS...",
         };
         ui.add_enabled(can_export, egui::Button::new(label))
             .clicked()
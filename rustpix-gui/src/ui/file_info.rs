@@ -0,0 +1,121 @@
+//! Acquisition metadata panel.
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use eframe::egui;
+use rustpix_tpx::tdc::DEFAULT_TDC_FREQUENCY_TOLERANCE;
+
+use super::theme::ThemeColors;
+use crate::app::RustpixApp;
+use crate::pipeline::ClockSource;
+use crate::util::{civil_from_days, format_bytes, format_number, u64_to_f64};
+
+/// Format a timestamp as `YYYY-MM-DD HH:MM:SS UTC`.
+fn format_utc(time: SystemTime) -> Option<String> {
+    let secs = time.duration_since(UNIX_EPOCH).ok()?.as_secs();
+    let days = i64::try_from(secs / 86_400).ok()?;
+    let rem = secs % 86_400;
+
+    let (year, month, day) = civil_from_days(days);
+
+    Some(format!(
+        "{year:04}-{month:02}-{day:02} {:02}:{:02}:{:02} UTC",
+        rem / 3600,
+        (rem / 60) % 60,
+        rem % 60
+    ))
+}
+
+/// Format a 25 ns clock value as seconds.
+fn format_clock(ticks: u64) -> String {
+    format!("{:.3} s", u64_to_f64(ticks) * 25e-9)
+}
+
+impl RustpixApp {
+    /// Render acquisition metadata for the open file.
+    pub(crate) fn render_file_metadata(&mut self, ui: &mut egui::Ui) {
+        let colors = ThemeColors::from_ui(ui);
+        let Some(metadata) = self.file_metadata.as_ref() else {
+            ui.label(
+                egui::RichText::new("No file open")
+                    .size(11.0)
+                    .color(colors.text_dim),
+            );
+            return;
+        };
+
+        Self::stat_row(ui, "Size", &format_bytes(metadata.size_bytes), false);
+        let created = metadata
+            .created
+            .map(|time| ("Created", time))
+            .or_else(|| metadata.modified.map(|time| ("Modified", time)));
+        if let Some((label, time)) = created {
+            if let Some(text) = format_utc(time) {
+                Self::stat_row(ui, label, &text, false);
+            }
+        }
+
+        let chips = metadata
+            .chip_ids
+            .iter()
+            .map(u8::to_string)
+            .collect::<Vec<_>>()
+            .join(", ");
+        Self::stat_row(
+            ui,
+            "Chips",
+            if chips.is_empty() { "none" } else { &chips },
+            false,
+        );
+        Self::stat_row(ui, "Sections", &format_number(metadata.sections), false);
+        Self::stat_row(
+            ui,
+            "Hit packets",
+            &format_number(metadata.hit_packets),
+            false,
+        );
+
+        if let (Some(first), Some(last)) =
+            (metadata.first_timestamp_25ns, metadata.last_timestamp_25ns)
+        {
+            let source = match metadata.clock_source {
+                Some(ClockSource::Hits) => " (hits)",
+                _ => "",
+            };
+            Self::stat_row(ui, &format!("First{source}"), &format_clock(first), false);
+            Self::stat_row(ui, &format!("Last{source}"), &format_clock(last), false);
+        }
+        if let Some(duration) = metadata.duration() {
+            Self::stat_row(
+                ui,
+                "Duration",
+                &format!("~{:.2} s", duration.as_secs_f64()),
+                true,
+            );
+        }
+
+        if metadata.tdc_count > 0 {
+            Self::stat_row(ui, "TDC pulses", &format_number(metadata.tdc_count), false);
+        }
+        let Some(estimate) = metadata.tdc_frequency.as_ref() else {
+            return;
+        };
+        let measured = estimate.frequency_hz;
+        Self::stat_row(ui, "TDC freq", &format!("~{measured:.2} Hz"), false);
+        let configured = self.tdc_frequency;
+        if estimate.disagrees_with(configured, DEFAULT_TDC_FREQUENCY_TOLERANCE) {
+            ui.label(
+                egui::RichText::new(format!("Configured TDC frequency is {configured:.2} Hz"))
+                    .size(10.0)
+                    .color(colors.text_dim),
+            );
+            if ui
+                .small_button("Use measured frequency")
+                .on_hover_text("Set the TDC frequency to the value measured from this file")
+                .clicked()
+            {
+                self.tdc_frequency = (measured * 100.0).round() / 100.0;
+            }
+        }
+    }
+}
@@ -0,0 +1,178 @@
+//! 2D power spectrum and autocorrelation window.
+//!
+//! Shows the FFT power spectrum (log scale) or the autocorrelation of the
+//! summed image of the current view, with the strongest periodic peaks, to
+//! quantify periodic detector artifacts and grating patterns.
+
+use eframe::egui;
+use rustpix_algorithms::{autocorrelation, periodic_peaks, power_spectrum, PeriodicPeak};
+
+use super::theme::ThemeColors;
+use crate::app::RustpixApp;
+use crate::state::{ViewMode, ViewTransform};
+use crate::util::usize_to_f32;
+use crate::viewer::generate_float_image_transformed;
+
+/// Longest edge of the map in points.
+const MAP_SIZE: f32 = 320.0;
+/// Number of periodic peaks listed.
+const MAX_PEAKS: usize = 8;
+
+/// Map texture and peaks computed for one data revision.
+pub(crate) struct FourierCache {
+    /// View mode, data revision and map kind the cache was built for.
+    key: (ViewMode, u64, bool),
+    width: usize,
+    height: usize,
+    texture: egui::TextureHandle,
+    peaks: Vec<PeriodicPeak>,
+}
+
+impl RustpixApp {
+    /// Render the power spectrum / autocorrelation window while it is open.
+    pub(crate) fn render_fourier_view(&mut self, ctx: &egui::Context) {
+        if !self.ui_state.panels.show_fourier {
+            return;
+        }
+        let mut open = true;
+        egui::Window::new("Power Spectrum")
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                let colors = ThemeColors::from_ui(ui);
+                ui.horizontal(|ui| {
+                    let mode = &mut self.ui_state.fourier_autocorrelation;
+                    ui.radio_value(mode, false, "Power spectrum");
+                    ui.radio_value(mode, true, "Autocorrelation");
+                });
+                ui.add_space(6.0);
+
+                if !self.update_fourier_cache(ui.ctx()) {
+                    ui.label(
+                        egui::RichText::new("Load data to compute the power spectrum")
+                            .size(11.0)
+                            .color(colors.text_muted),
+                    );
+                    return;
+                }
+                let Some(cache) = self.fourier_cache.as_ref() else {
+                    return;
+                };
+                Self::render_fourier_map(ui, cache, &colors);
+                ui.add_space(6.0);
+                Self::render_fourier_peaks(ui, &cache.peaks, &colors);
+            });
+        self.ui_state.panels.show_fourier = open;
+    }
+
+    /// Recompute the cached map when the data or map kind changed. Returns
+    /// whether there is data to show.
+    fn update_fourier_cache(&mut self, ctx: &egui::Context) -> bool {
+        let (width, height) = self.current_data_dimensions();
+        let Some(counts) = self
+            .active_counts()
+            .filter(|counts| !counts.is_empty() && counts.len() == width * height)
+        else {
+            self.fourier_cache = None;
+            return false;
+        };
+        let autocorrelate = self.ui_state.fourier_autocorrelation;
+        let key = (
+            self.ui_state.view_mode,
+            self.active_data_revision(),
+            autocorrelate,
+        );
+        if self
+            .fourier_cache
+            .as_ref()
+            .is_some_and(|cache| cache.key == key)
+        {
+            return true;
+        }
+
+        let spectrum = power_spectrum(counts, width, height);
+        let peaks = periodic_peaks(&spectrum, MAX_PEAKS);
+        #[allow(clippy::cast_possible_truncation)]
+        let values: Vec<f32> = if autocorrelate {
+            autocorrelation(counts, width, height)
+                .values
+                .iter()
+                .map(|&value| value as f32)
+                .collect()
+        } else {
+            spectrum
+                .values
+                .iter()
+                .map(|&power| power.ln_1p() as f32)
+                .collect()
+        };
+        let image = generate_float_image_transformed(
+            &values,
+            width,
+            height,
+            ViewTransform::default(),
+            &self.colormap,
+        );
+        self.fourier_cache = Some(FourierCache {
+            key,
+            width,
+            height,
+            texture: ctx.load_texture("fourier_map", image, egui::TextureOptions::NEAREST),
+            peaks,
+        });
+        true
+    }
+
+    fn render_fourier_map(ui: &mut egui::Ui, cache: &FourierCache, colors: &ThemeColors) {
+        let (width, height) = (usize_to_f32(cache.width), usize_to_f32(cache.height));
+        let scale = MAP_SIZE / width.max(height);
+        let response = ui.image((cache.texture.id(), egui::vec2(width, height) * scale));
+        if let Some(pos) = response.hover_pos() {
+            // Offset from the zero frequency / zero lag at the map center.
+            let center = egui::vec2(
+                usize_to_f32(cache.width / 2),
+                usize_to_f32(cache.height / 2),
+            );
+            let offset = ((pos - response.rect.min) / scale).floor() - center;
+            response.on_hover_text(format!("offset ({:+}, {:+})", offset.x, offset.y));
+        }
+        ui.label(
+            egui::RichText::new("Center is zero frequency (spectrum) or zero lag (correlation)")
+                .size(10.0)
+                .color(colors.text_dim),
+        );
+    }
+
+    fn render_fourier_peaks(ui: &mut egui::Ui, peaks: &[PeriodicPeak], colors: &ThemeColors) {
+        if peaks.is_empty() {
+            ui.label(
+                egui::RichText::new("No periodic peaks")
+                    .size(11.0)
+                    .color(colors.text_muted),
+            );
+            return;
+        }
+        egui::Grid::new("fourier_peaks")
+            .striped(true)
+            .num_columns(4)
+            .spacing(egui::vec2(16.0, 4.0))
+            .show(ui, |ui| {
+                for header in ["Period (px)", "Angle (deg)", "Frequency (1/px)", "Power"] {
+                    ui.label(
+                        egui::RichText::new(header)
+                            .size(11.0)
+                            .color(colors.text_muted),
+                    );
+                }
+                ui.end_row();
+                for peak in peaks {
+                    ui.monospace(format!("{:.2}", peak.period));
+                    ui.monospace(format!("{:.1}", peak.angle_deg));
+                    ui.monospace(format!("({:+.4}, {:+.4})", peak.fx, peak.fy));
+                    ui.monospace(format!("{:.1}%", peak.fraction * 100.0));
+                    ui.end_row();
+                }
+            });
+    }
+}
@@ -0,0 +1,339 @@
+//! Detector health dashboard.
+//!
+//! One window with what the daily detector check looks at: per-chip hit
+//! rates, dead/hot pixel counts with a mask map, TDC health and `ToT`
+//! saturation. Values flagged in red are over the warning thresholds below.
+
+use eframe::egui::{self, Color32};
+use rustpix_tpx::tdc::DEFAULT_TDC_FREQUENCY_TOLERANCE;
+
+use super::theme::{accent, ThemeColors};
+use crate::app::RustpixApp;
+use crate::pipeline::{ChipFootprint, ChipHitCounts};
+use crate::util::{format_number, u64_to_f64, usize_to_f64};
+
+/// Fraction of saturated hits on a chip before it is flagged.
+const SATURATION_WARN_FRACTION: f64 = 0.01;
+/// Fraction of dead pixels on a chip before it is flagged.
+const DEAD_WARN_FRACTION: f64 = 0.01;
+/// Edge length of the pixel mask map in points.
+const MAP_SIZE: f32 = 256.0;
+
+/// One row of the per-chip table.
+struct ChipRow {
+    chip_id: u8,
+    counts: ChipHitCounts,
+    dead: Option<usize>,
+    hot: Option<usize>,
+}
+
+fn fraction(part: u64, whole: u64) -> f64 {
+    if whole == 0 {
+        0.0
+    } else {
+        u64_to_f64(part) / u64_to_f64(whole)
+    }
+}
+
+fn format_rate(per_second: f64) -> String {
+    if per_second >= 1e6 {
+        format!("{:.2} M/s", per_second / 1e6)
+    } else if per_second >= 1e3 {
+        format!("{:.1} k/s", per_second / 1e3)
+    } else {
+        format!("{per_second:.0} /s")
+    }
+}
+
+fn format_count(count: u64) -> String {
+    format_number(usize::try_from(count).unwrap_or(usize::MAX))
+}
+
+impl RustpixApp {
+    /// Render the detector health dashboard window while it is open.
+    pub(crate) fn render_health_dashboard(&mut self, ctx: &egui::Context) {
+        if !self.ui_state.panels.show_health_dashboard {
+            return;
+        }
+        let mut open = true;
+        egui::Window::new("Detector Health")
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(true)
+            .default_width(620.0)
+            .show(ctx, |ui| {
+                let colors = ThemeColors::from_ui(ui);
+                if self.chip_hit_stats.is_none() && self.file_metadata.is_none() {
+                    ui.label(
+                        egui::RichText::new("Load a file to check detector health")
+                            .size(11.0)
+                            .color(colors.text_muted),
+                    );
+                    return;
+                }
+                self.render_health_chip_table(ui, &colors);
+                ui.add_space(8.0);
+                ui.separator();
+                ui.horizontal_top(|ui| {
+                    self.render_health_map(ui, &colors);
+                    ui.add_space(12.0);
+                    ui.vertical(|ui| {
+                        self.render_health_tdc(ui, &colors);
+                        ui.add_space(8.0);
+                        self.render_health_saturation(ui, &colors);
+                    });
+                });
+            });
+        self.ui_state.panels.show_health_dashboard = open;
+    }
+
+    fn health_chip_rows(&self) -> Vec<ChipRow> {
+        let config = self.current_detector_config();
+        let chip_ids: Vec<u8> = match self.file_metadata.as_ref() {
+            Some(metadata) if !metadata.chip_ids.is_empty() => metadata.chip_ids.clone(),
+            _ => (0..config.chip_transforms.len())
+                .filter_map(|chip| u8::try_from(chip).ok())
+                .collect(),
+        };
+        chip_ids
+            .into_iter()
+            .map(|chip_id| {
+                let footprint = ChipFootprint::of(&config, chip_id);
+                let mask = self.pixel_masks.as_ref();
+                ChipRow {
+                    chip_id,
+                    counts: self
+                        .chip_hit_stats
+                        .as_ref()
+                        .map(|stats| stats.chip(chip_id))
+                        .unwrap_or_default(),
+                    dead: mask.map(|mask| footprint.count_set(&mask.dead_mask, mask.width)),
+                    hot: mask.map(|mask| footprint.count_set(&mask.hot_mask, mask.width)),
+                }
+            })
+            .collect()
+    }
+
+    fn render_health_chip_table(&self, ui: &mut egui::Ui, colors: &ThemeColors) {
+        let rows = self.health_chip_rows();
+        let total_hits: u64 = rows.iter().map(|row| row.counts.hits).sum();
+        let seconds = self
+            .file_metadata
+            .as_ref()
+            .and_then(crate::pipeline::FileMetadata::duration)
+            .map(|duration| duration.as_secs_f64())
+            .filter(|&seconds| seconds > 0.0);
+        let config = self.current_detector_config();
+        let chip_pixels =
+            usize::from(config.chip_size_x).saturating_mul(usize::from(config.chip_size_y));
+
+        egui::Grid::new("health_chip_table")
+            .striped(true)
+            .num_columns(7)
+            .spacing(egui::vec2(16.0, 4.0))
+            .show(ui, |ui| {
+                for header in ["Chip", "Hits", "Rate", "Share", "Saturated", "Dead", "Hot"] {
+                    ui.label(
+                        egui::RichText::new(header)
+                            .size(11.0)
+                            .color(colors.text_muted),
+                    );
+                }
+                ui.end_row();
+
+                for row in &rows {
+                    let counts = row.counts;
+                    ui.label(row.chip_id.to_string());
+                    ui.label(format_count(counts.hits));
+                    ui.label(seconds.map_or_else(
+                        || "–".to_string(),
+                        |seconds| format_rate(u64_to_f64(counts.hits) / seconds),
+                    ));
+                    ui.label(format!("{:.1}%", fraction(counts.hits, total_hits) * 100.0));
+
+                    let saturated = fraction(counts.saturated, counts.hits);
+                    ui.label(
+                        egui::RichText::new(format!("{:.2}%", saturated * 100.0)).color(
+                            if saturated > SATURATION_WARN_FRACTION {
+                                accent::RED
+                            } else {
+                                colors.text_primary
+                            },
+                        ),
+                    );
+
+                    match row.dead {
+                        Some(dead) => {
+                            let dead_fraction = if chip_pixels == 0 {
+                                0.0
+                            } else {
+                                usize_to_f64(dead) / usize_to_f64(chip_pixels)
+                            };
+                            ui.label(egui::RichText::new(format_number(dead)).color(
+                                if dead_fraction > DEAD_WARN_FRACTION {
+                                    accent::RED
+                                } else {
+                                    colors.text_primary
+                                },
+                            ));
+                        }
+                        None => {
+                            ui.label("–");
+                        }
+                    }
+                    match row.hot {
+                        Some(hot) if hot > 0 => {
+                            ui.label(egui::RichText::new(format_number(hot)).color(accent::AMBER));
+                        }
+                        Some(hot) => {
+                            ui.label(format_number(hot));
+                        }
+                        None => {
+                            ui.label("–");
+                        }
+                    }
+                    ui.end_row();
+                }
+            });
+        if seconds.is_none() {
+            ui.label(
+                egui::RichText::new("Rates need the acquisition duration from TDC or hit clocks")
+                    .size(10.0)
+                    .color(colors.text_dim),
+            );
+        }
+    }
+
+    fn render_health_map(&mut self, ui: &mut egui::Ui, colors: &ThemeColors) {
+        ui.vertical(|ui| {
+            ui.label(
+                egui::RichText::new("Pixel mask")
+                    .size(11.0)
+                    .color(colors.text_muted),
+            );
+            let Some(mask) = self.pixel_masks.as_ref() else {
+                ui.label(
+                    egui::RichText::new("No pixel mask")
+                        .size(11.0)
+                        .color(colors.text_dim),
+                );
+                return;
+            };
+            let texture = self.health_map_texture.get_or_insert_with(|| {
+                let alive = Color32::from_gray(0x50);
+                let pixels = mask
+                    .dead_mask
+                    .iter()
+                    .zip(&mask.hot_mask)
+                    .map(|(&dead, &hot)| {
+                        if dead != 0 {
+                            Color32::BLACK
+                        } else if hot != 0 {
+                            accent::RED
+                        } else {
+                            alive
+                        }
+                    })
+                    .collect();
+                let image = egui::ColorImage {
+                    size: [mask.width, mask.height],
+                    pixels,
+                };
+                ui.ctx()
+                    .load_texture("health_map", image, egui::TextureOptions::NEAREST)
+            });
+            ui.image((texture.id(), egui::vec2(MAP_SIZE, MAP_SIZE)));
+            ui.label(
+                egui::RichText::new(format!("black: dead • red: hot (> {:.1}σ)", mask.hot_sigma))
+                    .size(10.0)
+                    .color(colors.text_dim),
+            );
+        });
+    }
+
+    fn render_health_tdc(&mut self, ui: &mut egui::Ui, colors: &ThemeColors) {
+        ui.label(
+            egui::RichText::new("TDC")
+                .size(11.0)
+                .color(colors.text_muted),
+        );
+        let configured = self.tdc_frequency;
+        Self::stat_row(ui, "Configured", &format!("{configured:.2} Hz"), false);
+        let Some(metadata) = self.file_metadata.as_ref() else {
+            return;
+        };
+        if metadata.tdc_count == 0 {
+            ui.label(
+                egui::RichText::new("No TDC packets in this file")
+                    .size(11.0)
+                    .color(accent::RED),
+            );
+            return;
+        }
+        Self::stat_row(ui, "Pulses", &format_number(metadata.tdc_count), false);
+        let missing = metadata.missing_pulses;
+        ui.horizontal(|ui| {
+            ui.label(
+                egui::RichText::new("Dropped")
+                    .size(11.0)
+                    .color(colors.text_muted),
+            );
+            ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                ui.label(egui::RichText::new(format_count(missing)).size(11.0).color(
+                    if missing > 0 {
+                        accent::AMBER
+                    } else {
+                        colors.text_primary
+                    },
+                ));
+            });
+        });
+        let Some(estimate) = metadata.tdc_frequency.as_ref() else {
+            return;
+        };
+        let measured = estimate.frequency_hz;
+        let mismatch = estimate.disagrees_with(configured, DEFAULT_TDC_FREQUENCY_TOLERANCE);
+        ui.horizontal(|ui| {
+            ui.label(
+                egui::RichText::new("Measured")
+                    .size(11.0)
+                    .color(colors.text_muted),
+            );
+            ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                ui.label(
+                    egui::RichText::new(format!("~{measured:.2} Hz"))
+                        .size(11.0)
+                        .color(if mismatch { accent::RED } else { accent::GREEN }),
+                );
+            });
+        });
+        if mismatch
+            && ui
+                .small_button("Use measured frequency")
+                .on_hover_text("Set the TDC frequency to the value measured from this file")
+                .clicked()
+        {
+            self.tdc_frequency = (measured * 100.0).round() / 100.0;
+        }
+    }
+
+    fn render_health_saturation(&self, ui: &mut egui::Ui, colors: &ThemeColors) {
+        ui.label(
+            egui::RichText::new("Saturation")
+                .size(11.0)
+                .color(colors.text_muted),
+        );
+        let Some(stats) = self.chip_hit_stats.as_ref() else {
+            return;
+        };
+        let total = stats.total();
+        Self::stat_row(ui, "Saturated hits", &format_count(total.saturated), false);
+        let saturated = fraction(total.saturated, total.hits);
+        Self::stat_row(
+            ui,
+            "Fraction",
+            &format!("{:.3}%", saturated * 100.0),
+            saturated > SATURATION_WARN_FRACTION,
+        );
+    }
+}
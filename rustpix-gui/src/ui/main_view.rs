@@ -10,17 +10,21 @@ use egui_plot::{
 };
 use image::{Rgba, RgbaImage};
 use rfd::FileDialog;
+use rustpix_algorithms::{compare_spectra, SpectrumComparison};
 
 use super::theme::{accent, ThemeColors};
-use crate::app::{RoiSpectrumEntry, RustpixApp};
+use crate::app::{RoiSpectrumData, RoiSpectrumEntry, RustpixApp};
 use crate::state::{SpectrumXAxis, ViewMode, ZoomMode};
 use crate::util::{
-    energy_ev_to_tof_ms, f64_to_usize_bounded, tof_ms_to_energy_ev, u64_to_f64, usize_to_f64,
+    energy_ev_to_tof_ms, f64_to_usize_bounded, format_number, tof_ms_to_energy_ev, u64_to_f64,
+    usize_to_f64,
 };
-use crate::viewer::{Roi, RoiSelectionMode};
+use crate::viewer::{Roi, RoiSelectionMode, RoiShape};
 
 /// Unique ID for the main histogram plot (used for state persistence).
 const HISTOGRAM_PLOT_ID: &str = "histogram_plot";
+/// Significance level of the two-spectrum consistency badge.
+const CONSISTENCY_ALPHA: f64 = 0.05;
 
 #[derive(Clone, Copy)]
 enum RoiToolbarIcon {
@@ -119,6 +123,7 @@ struct SpectrumToolbarActions {
     reset_clicked: bool,
     export_png_clicked: bool,
     export_csv_clicked: bool,
+    copy_tsv_clicked: bool,
 }
 
 struct SpectrumLineStats {
@@ -147,6 +152,29 @@ struct SpectrumPlotData {
     tof_offset_ns: f64,
 }
 
+/// TOF in milliseconds at a spectrum plot x coordinate.
+fn spectrum_x_to_tof_ms(data: &SpectrumPlotData, x_plot: f64) -> Option<f64> {
+    let x_axis = if data.log_x {
+        10_f64.powf(x_plot)
+    } else {
+        x_plot
+    };
+    match data.axis {
+        SpectrumXAxis::ToFMs => Some(x_axis),
+        SpectrumXAxis::EnergyEv => {
+            energy_ev_to_tof_ms(x_axis, data.flight_path_m, data.tof_offset_ns)
+        }
+    }
+}
+
+/// Plot x coordinate of a click on the spectrum.
+fn spectrum_click_x(plot_response: &egui_plot::PlotResponse<()>, pos: egui::Pos2) -> f64 {
+    let plot_bounds = plot_response.transform.bounds();
+    let plot_rect = plot_response.response.rect;
+    let x_frac = f64::from(pos.x - plot_rect.left()) / f64::from(plot_rect.width());
+    plot_bounds.min()[0] + x_frac * (plot_bounds.max()[0] - plot_bounds.min()[0])
+}
+
 struct HistogramGeometry {
     data_width_f64: f64,
     data_height_f64: f64,
@@ -377,7 +405,7 @@ impl RustpixApp {
     fn finish_central_panel(&mut self, inputs: &CentralPanelInputs, state: &CentralPanelState) {
         if let Some(bin) = state.new_tof_bin {
             self.ui_state.current_tof_bin = bin;
-            self.texture = None;
+            self.texture_refresh.invalidate();
         }
 
         if inputs.plot_flags.needs_plot_reset || state.reset_view_clicked {
@@ -599,6 +627,24 @@ impl RustpixApp {
                     state.reset_view_clicked = true;
                 }
 
+                let copy_btn = egui::Button::new(
+                    egui::RichText::new("📋 Copy")
+                        .size(11.0)
+                        .color(colors.text_muted),
+                )
+                .min_size(egui::vec2(0.0, 28.0))
+                .fill(Color32::TRANSPARENT)
+                .stroke(Stroke::new(1.0, colors.border_light))
+                .rounding(Rounding::same(4.0));
+
+                if ui
+                    .add(copy_btn)
+                    .on_hover_text("Copy image to clipboard")
+                    .clicked()
+                {
+                    self.handle_copy_histogram_image(ui.ctx());
+                }
+
                 ui.add_space(6.0);
                 Self::toolbar_divider(ui);
                 ui.add_space(8.0);
@@ -1312,6 +1358,20 @@ impl RustpixApp {
         self.ui_state.roi_last_plot_rect = Some(plot_ui.response().rect);
     }
 
+    fn handle_copy_histogram_image(&mut self, ctx: &egui::Context) {
+        let now = ctx.input(|i| i.time);
+        match self.copy_histogram_image() {
+            Ok(()) => self
+                .ui_state
+                .notifications
+                .info("Copied image to clipboard", now),
+            Err(err) => self
+                .ui_state
+                .notifications
+                .error(format!("Copy image failed: {err}"), now),
+        }
+    }
+
     fn notify_roi_error(&mut self, ctx: &egui::Context, err: crate::viewer::RoiCommitError) {
         let message = match err {
             crate::viewer::RoiCommitError::TooFewPoints => {
@@ -1321,8 +1381,9 @@ impl RustpixApp {
                 "Polygon edges cannot self-intersect".to_string()
             }
         };
-        let expires_at = ctx.input(|i| i.time) + 2.5;
-        self.ui_state.roi_warning = Some((message, expires_at));
+        self.ui_state
+            .notifications
+            .warn(message, ctx.input(|i| i.time));
     }
 
     fn draw_zoom_rect(plot_ui: &egui_plot::PlotUi, response: &egui::Response, rect: Rect) {
@@ -1583,8 +1644,9 @@ impl RustpixApp {
         };
 
         self.render_spectrum_plot(ui, &plot_data, inputs, spectrum_reset_clicked);
-        self.handle_spectrum_exports(&plot_data, inputs, &toolbar_actions, colors);
-        self.render_spectrum_legend_if_needed(ui, &plot_data.legend_items);
+        self.handle_spectrum_exports(ctx, &plot_data, inputs, &toolbar_actions, colors);
+        let comparison = self.visible_spectra_comparison(inputs.spectrum.as_deref());
+        self.render_spectrum_legend_if_needed(ui, &plot_data.legend_items, comparison.as_ref());
     }
 
     fn ensure_energy_axis(&mut self) -> bool {
@@ -1806,6 +1868,22 @@ impl RustpixApp {
         {
             actions.export_csv_clicked = true;
         }
+
+        let copy_btn = egui::Button::new(
+            egui::RichText::new("📋 Copy")
+                .size(10.0)
+                .color(colors.text_dim),
+        )
+        .fill(Color32::TRANSPARENT)
+        .stroke(Stroke::new(1.0, colors.border_light))
+        .rounding(Rounding::same(4.0));
+        if ui
+            .add_enabled(has_visible_spectrum, copy_btn)
+            .on_hover_text("Copy spectrum data to clipboard (tab-separated)")
+            .clicked()
+        {
+            actions.copy_tsv_clicked = true;
+        }
     }
 
     fn render_spectrum_reset_controls(
@@ -1908,8 +1986,15 @@ impl RustpixApp {
                     x_min = x_min.min(stats.x_min);
                     x_max = x_max.max(stats.x_max);
                     y_max = y_max.max(stats.y_max);
-                    legend_items.push(("Full FOV".to_string(), colors.text_muted));
-                    lines.push(("Full FOV".to_string(), colors.text_muted, points));
+                    let label = match self.excluded_pixel_mask() {
+                        Some(mask) => format!(
+                            "Full FOV ({} masked px excluded)",
+                            format_number(mask.excluded_count)
+                        ),
+                        None => "Full FOV".to_string(),
+                    };
+                    legend_items.push((label.clone(), colors.text_muted));
+                    lines.push((label, colors.text_muted, points));
                 }
             }
         }
@@ -2176,16 +2261,84 @@ impl RustpixApp {
                 plot_ui.ctx().set_cursor_icon(icon);
             }
 
+            if response.hovered() && !zoom_active && self.ui_state.calibration.picking.is_some() {
+                plot_ui.ctx().set_cursor_icon(egui::CursorIcon::Crosshair);
+            }
+
             self.handle_spectrum_zoom(plot_ui, &response, zoom_mode, &mut zoom_start);
             self.draw_spectrum_slice_marker(plot_ui, data, inputs);
-            self.handle_spectrum_slice_drag(plot_ui, data, inputs, zoom_active);
+            self.draw_calibration_marks(plot_ui, data);
+            if self.ui_state.calibration.picking.is_none() {
+                self.handle_spectrum_slice_drag(plot_ui, data, inputs, zoom_active);
+            }
         });
 
         self.ui_state.spectrum_zoom_start = zoom_start;
         self.ui_state.spectrum_last_plot_bounds = Some(*plot_response.transform.bounds());
         self.ui_state.spectrum_last_plot_rect = Some(plot_response.response.rect);
 
-        self.handle_spectrum_slice_click(&plot_response, data, inputs, zoom_active);
+        if !self.handle_calibration_click(&plot_response, data, zoom_active) {
+            self.handle_spectrum_slice_click(&plot_response, data, inputs, zoom_active);
+        }
+    }
+
+    /// Draw the features placed in the energy calibration wizard.
+    fn draw_calibration_marks(&self, plot_ui: &mut egui_plot::PlotUi, data: &SpectrumPlotData) {
+        if !self.ui_state.panels.show_energy_calibration {
+            return;
+        }
+        for mark in &self.ui_state.calibration.marks {
+            let Some(tof_ms) = mark.tof_ms else {
+                continue;
+            };
+            let x = match data.axis {
+                SpectrumXAxis::ToFMs => Some(tof_ms),
+                SpectrumXAxis::EnergyEv => {
+                    tof_ms_to_energy_ev(tof_ms, data.flight_path_m, data.tof_offset_ns)
+                }
+            };
+            let Some(mut x) = x else {
+                continue;
+            };
+            if data.log_x {
+                if x <= 0.0 {
+                    continue;
+                }
+                x = x.log10();
+            }
+            plot_ui.vline(
+                VLine::new(x)
+                    .color(accent::GREEN)
+                    .width(1.0)
+                    .style(egui_plot::LineStyle::Dotted { spacing: 4.0 })
+                    .name(mark.label.as_str()),
+            );
+        }
+    }
+
+    /// Place the pending calibration mark at the clicked TOF. Returns whether
+    /// the click was used.
+    fn handle_calibration_click(
+        &mut self,
+        plot_response: &egui_plot::PlotResponse<()>,
+        data: &SpectrumPlotData,
+        zoom_active: bool,
+    ) -> bool {
+        if zoom_active
+            || self.ui_state.calibration.picking.is_none()
+            || !plot_response.response.clicked()
+        {
+            return false;
+        }
+        let Some(pos) = plot_response.response.interact_pointer_pos() else {
+            return false;
+        };
+        let Some(tof_ms) = spectrum_x_to_tof_ms(data, spectrum_click_x(plot_response, pos)) else {
+            return false;
+        };
+        self.ui_state
+            .calibration
+            .place(tof_ms.clamp(0.0, data.max_ms))
     }
 
     fn handle_spectrum_zoom(
@@ -2292,17 +2445,7 @@ impl RustpixApp {
         let drag_delta = plot_ui.pointer_coordinate_drag_delta();
         if drag_delta.x.abs() > 0.0 {
             if let Some(coord) = plot_ui.pointer_coordinate() {
-                let mut x_axis = coord.x;
-                if data.log_x {
-                    x_axis = 10_f64.powf(x_axis);
-                }
-                let x_ms = match data.axis {
-                    SpectrumXAxis::ToFMs => Some(x_axis),
-                    SpectrumXAxis::EnergyEv => {
-                        energy_ev_to_tof_ms(x_axis, data.flight_path_m, data.tof_offset_ns)
-                    }
-                };
-                let Some(x_ms) = x_ms else {
+                let Some(x_ms) = spectrum_x_to_tof_ms(data, coord.x) else {
                     return;
                 };
                 #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
@@ -2335,22 +2478,8 @@ impl RustpixApp {
             return;
         }
         if let Some(pos) = plot_response.response.interact_pointer_pos() {
-            let plot_bounds = plot_response.transform.bounds();
-            let plot_rect = plot_response.response.rect;
-            let x_frac = f64::from(pos.x - plot_rect.left()) / f64::from(plot_rect.width());
-            let x_plot =
-                plot_bounds.min()[0] + x_frac * (plot_bounds.max()[0] - plot_bounds.min()[0]);
-            let mut x_axis = x_plot;
-            if data.log_x {
-                x_axis = 10_f64.powf(x_axis);
-            }
-            let x_ms = match data.axis {
-                SpectrumXAxis::ToFMs => Some(x_axis),
-                SpectrumXAxis::EnergyEv => {
-                    energy_ev_to_tof_ms(x_axis, data.flight_path_m, data.tof_offset_ns)
-                }
-            };
-            let Some(x_ms) = x_ms else {
+            let Some(x_ms) = spectrum_x_to_tof_ms(data, spectrum_click_x(plot_response, pos))
+            else {
                 return;
             };
             #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
@@ -2394,6 +2523,7 @@ impl RustpixApp {
 
     fn handle_spectrum_exports(
         &mut self,
+        ctx: &egui::Context,
         data: &SpectrumPlotData,
         inputs: &SpectrumPanelInputs,
         actions: &SpectrumToolbarActions,
@@ -2415,10 +2545,34 @@ impl RustpixApp {
                 data.bin_width_ms,
                 axis_config,
             ) {
-                log::error!("Failed to export spectrum CSV: {err}");
+                self.ui_state.notifications.error(
+                    format!("Failed to export spectrum CSV: {err}"),
+                    ctx.input(|i| i.time),
+                );
             }
         }
 
+        if actions.copy_tsv_clicked {
+            self.force_roi_spectra_update();
+            let axis_config = SpectrumAxisConfig {
+                axis: data.axis,
+                flight_path_m: data.flight_path_m,
+                tof_offset_ns: data.tof_offset_ns,
+            };
+            let text = Self::spectrum_tsv(
+                inputs.spectrum.as_deref(),
+                &self.roi_state.rois,
+                self.roi_spectra_map(),
+                self.ui_state.spectrum.full_fov_visible,
+                data.bin_width_ms,
+                axis_config,
+            );
+            ctx.copy_text(text);
+            self.ui_state
+                .notifications
+                .info("Copied spectrum data to clipboard", ctx.input(|i| i.time));
+        }
+
         if actions.export_png_clicked && !data.lines.is_empty() {
             let export_config = SpectrumExportConfig {
                 axis: data.axis,
@@ -2428,7 +2582,10 @@ impl RustpixApp {
             if let Err(err) =
                 Self::export_spectrum_png(&data.lines, data.export_bounds, colors, &export_config)
             {
-                log::error!("Failed to export spectrum PNG: {err}");
+                self.ui_state.notifications.error(
+                    format!("Failed to export spectrum PNG: {err}"),
+                    ctx.input(|i| i.time),
+                );
             }
         }
     }
@@ -2437,10 +2594,29 @@ impl RustpixApp {
         &self,
         ui: &mut egui::Ui,
         legend_items: &[(String, Color32)],
+        comparison: Option<&SpectrumComparison>,
     ) {
         if !legend_items.is_empty() {
             ui.add_space(4.0);
-            Self::render_spectrum_legend(ui, legend_items);
+            Self::render_spectrum_legend(ui, legend_items, comparison);
+        }
+    }
+
+    /// Chi-square / KS comparison of the visible spectra when exactly two
+    /// are shown (full FOV and one ROI, or two ROIs).
+    fn visible_spectra_comparison(&self, spectrum: Option<&[u64]>) -> Option<SpectrumComparison> {
+        let full = spectrum.filter(|_| self.ui_state.spectrum.full_fov_visible);
+        let mut visible: Vec<&[u64]> = full.into_iter().collect();
+        for roi in &self.roi_state.rois {
+            if roi.visibility.spectrum_visible {
+                if let Some(data) = self.roi_spectrum_data(roi.id) {
+                    visible.push(&data.counts);
+                }
+            }
+        }
+        match visible.as_slice() {
+            [a, b] => compare_spectra(a, b),
+            _ => None,
         }
     }
 
@@ -2477,11 +2653,34 @@ impl RustpixApp {
         if self.roi_state.rois.is_empty() {
             Self::render_roi_data_empty(ui, &colors);
         } else {
+            self.update_roi_gate_thumbnails(ui.ctx());
             self.render_roi_data_list(ui, &colors);
         }
 
         ui.separator();
         self.render_roi_visibility_buttons(ui);
+
+        if let Some(export) = self.roi_mask_export() {
+            if ui
+                .button("Export ROI mask…")
+                .on_hover_text("Save ROIs as JSON shapes or a PNG mask for rustpix.load_roi_mask")
+                .clicked()
+            {
+                if let Some(path) = FileDialog::new()
+                    .add_filter("ROI shapes", &["json"])
+                    .add_filter("PNG mask", &["png"])
+                    .set_file_name("roi_mask.json")
+                    .save_file()
+                {
+                    if let Err(err) = export.write(&path) {
+                        self.ui_state.notifications.error(
+                            format!("ROI mask export failed: {err}"),
+                            ui.ctx().input(|i| i.time),
+                        );
+                    }
+                }
+            }
+        }
     }
 
     fn sync_roi_rename_id(&mut self) {
@@ -2503,7 +2702,11 @@ impl RustpixApp {
     }
 
     fn render_roi_data_list(&mut self, ui: &mut egui::Ui, colors: &ThemeColors) {
+        let max_bin = self
+            .active_hyperstack()
+            .and_then(|hs| hs.n_tof_bins().checked_sub(1));
         let (ui_state, roi_state) = (&mut self.ui_state, &mut self.roi_state);
+        let thumbnails = &self.roi_gate_thumbnails;
         ui.add_space(6.0);
         for roi in &mut roi_state.rois {
             ui.horizontal(|ui| {
@@ -2546,6 +2749,17 @@ impl RustpixApp {
                     }
                 }
             });
+            if let Some(max_bin) = max_bin {
+                ui.indent(("roi_gate", roi.id), |ui| {
+                    Self::render_roi_gate(
+                        ui,
+                        roi,
+                        max_bin,
+                        ui_state.current_tof_bin,
+                        thumbnails.get(&roi.id),
+                    );
+                });
+            }
         }
     }
 
@@ -2617,6 +2831,7 @@ impl RustpixApp {
                 ui.label(egui::RichText::new("Zoom & export").strong());
                 ui.label("• Zoom with buttons or selection box.");
                 ui.label("• Export PNG/CSV from the toolbar.");
+                ui.label("• Copy pastes tab-separated data into spreadsheets.");
             });
         self.ui_state.panel_popups.show_spectrum_help = open;
     }
@@ -2724,7 +2939,11 @@ impl RustpixApp {
         }
     }
 
-    fn render_spectrum_legend(ui: &mut egui::Ui, items: &[(String, Color32)]) {
+    fn render_spectrum_legend(
+        ui: &mut egui::Ui,
+        items: &[(String, Color32)],
+        comparison: Option<&SpectrumComparison>,
+    ) {
         let colors = ThemeColors::from_ui(ui);
         egui::Frame::none()
             .fill(colors.bg_panel)
@@ -2748,10 +2967,45 @@ impl RustpixApp {
                         );
                         ui.add_space(6.0);
                     }
+                    if let Some(comparison) = comparison {
+                        Self::render_spectrum_comparison_badge(ui, comparison);
+                    }
                 });
             });
     }
 
+    fn render_spectrum_comparison_badge(ui: &mut egui::Ui, comparison: &SpectrumComparison) {
+        let colors = ThemeColors::from_ui(ui);
+        let (verdict, color) = if comparison.consistent(CONSISTENCY_ALPHA) {
+            ("consistent", accent::GREEN)
+        } else {
+            ("inconsistent", accent::RED)
+        };
+        ui.add_space(6.0);
+        ui.label(
+            egui::RichText::new(format!(
+                "χ²/ndf {:.2} (p {:.2}) · KS p {:.2}",
+                comparison.reduced_chi_square(),
+                comparison.chi_square_p,
+                comparison.ks_p
+            ))
+            .size(10.0)
+            .color(colors.text_muted),
+        );
+        ui.label(
+            egui::RichText::new(verdict)
+                .size(10.0)
+                .strong()
+                .color(color),
+        )
+        .on_hover_text(format!(
+            "Shape comparison of the two visible spectra: chi-square with Poisson \
+                 errors ({} ndf) and Kolmogorov-Smirnov (D = {:.4}). Consistent when \
+                 both p-values are at least {CONSISTENCY_ALPHA}.",
+            comparison.degrees_of_freedom, comparison.ks_statistic
+        ));
+    }
+
     fn render_histogram_zoom_group(&mut self, ui: &mut egui::Ui) {
         let mut mode = self.ui_state.hist_zoom_mode;
         mode = Self::zoom_mode_button(ui, mode, ZoomMode::In, "Zoom in");
@@ -2902,35 +3156,30 @@ impl RustpixApp {
         painter.add(egui::Shape::convex_polygon(points, color, Stroke::NONE));
     }
 
-    fn export_spectrum_csv(
+    fn visible_roi_spectra<'a>(
+        rois: &'a [Roi],
+        roi_spectra: &'a HashMap<usize, RoiSpectrumEntry>,
+    ) -> Vec<(&'a Roi, &'a RoiSpectrumData)> {
+        rois.iter()
+            .filter(|roi| roi.visibility.spectrum_visible)
+            .filter_map(|roi| roi_spectra.get(&roi.id).map(|entry| (roi, &entry.data)))
+            .collect()
+    }
+
+    /// Build the spectrum table (header columns and formatted rows) shared by
+    /// CSV export and clipboard copy.
+    fn spectrum_table(
         full: Option<&[u64]>,
-        rois: &[Roi],
-        roi_spectra: &HashMap<usize, RoiSpectrumEntry>,
+        visible_rois: &[(&Roi, &RoiSpectrumData)],
         full_visible: bool,
         bin_width_ms: f64,
         axis_config: SpectrumAxisConfig,
-    ) -> anyhow::Result<()> {
-        let Some(path) = FileDialog::new().set_file_name("spectrum.csv").save_file() else {
-            return Ok(());
-        };
-
-        let mut file = File::create(path)?;
-        let axis = axis_config.axis;
+    ) -> (Vec<String>, Vec<Vec<String>>) {
         let flight_path_m = axis_config.flight_path_m;
         let tof_offset_ns = axis_config.tof_offset_ns;
         let include_energy = flight_path_m > 0.0;
         let include_full = full_visible && full.is_some();
         let full = full.unwrap_or(&[]);
-        let mut visible_rois = Vec::new();
-        for roi in rois {
-            if !roi.visibility.spectrum_visible {
-                continue;
-            }
-            let Some(entry) = roi_spectra.get(&roi.id) else {
-                continue;
-            };
-            visible_rois.push((roi, &entry.data));
-        }
 
         let mut header_cols = Vec::new();
         header_cols.push("TOF (ms)".to_string());
@@ -2940,9 +3189,66 @@ impl RustpixApp {
         if include_full {
             header_cols.push("Full FOV (counts)".to_string());
         }
-        for (roi, _) in &visible_rois {
+        for (roi, _) in visible_rois {
             header_cols.push(format!("{} (counts)", roi.name));
         }
+
+        let mut max_bins = full.len();
+        for (_, data) in visible_rois {
+            max_bins = max_bins.max(data.counts.len());
+        }
+
+        let mut rows = Vec::with_capacity(max_bins);
+        for i in 0..max_bins {
+            let tof_ms = usize_to_f64(i) * bin_width_ms;
+            let energy = if include_energy {
+                tof_ms_to_energy_ev(tof_ms, flight_path_m, tof_offset_ns)
+            } else {
+                None
+            };
+            if include_energy && energy.is_none() {
+                continue;
+            }
+            let mut row = Vec::new();
+            row.push(format!("{tof_ms:.6}"));
+            if let Some(energy) = energy {
+                row.push(format!("{energy:.6}"));
+            }
+            if include_full {
+                let count = full.get(i).copied().unwrap_or(0);
+                row.push(count.to_string());
+            }
+            for (_, data) in visible_rois {
+                let count = data.counts.get(i).copied().unwrap_or(0);
+                row.push(count.to_string());
+            }
+            rows.push(row);
+        }
+
+        (header_cols, rows)
+    }
+
+    fn export_spectrum_csv(
+        full: Option<&[u64]>,
+        rois: &[Roi],
+        roi_spectra: &HashMap<usize, RoiSpectrumEntry>,
+        full_visible: bool,
+        bin_width_ms: f64,
+        axis_config: SpectrumAxisConfig,
+    ) -> anyhow::Result<()> {
+        let Some(path) = FileDialog::new().set_file_name("spectrum.csv").save_file() else {
+            return Ok(());
+        };
+
+        let mut file = File::create(path)?;
+        let axis = axis_config.axis;
+        let flight_path_m = axis_config.flight_path_m;
+        let tof_offset_ns = axis_config.tof_offset_ns;
+        let include_energy = flight_path_m > 0.0;
+        let visible_rois = Self::visible_roi_spectra(rois, roi_spectra);
+        let (header_cols, rows) =
+            Self::spectrum_table(full, &visible_rois, full_visible, bin_width_ms, axis_config);
+
         writeln!(file, "# Spectrum axis: {axis}")?;
         if include_energy {
             writeln!(file, "# Flight path (m): {flight_path_m:.4}")?;
@@ -2984,40 +3290,52 @@ impl RustpixApp {
             writeln!(file, "#")?;
         }
 
-        let mut max_bins = full.len();
-        for (_, data) in &visible_rois {
-            max_bins = max_bins.max(data.counts.len());
-        }
-
-        for i in 0..max_bins {
-            let tof_ms = usize_to_f64(i) * bin_width_ms;
-            let energy = if include_energy {
-                tof_ms_to_energy_ev(tof_ms, flight_path_m, tof_offset_ns)
-            } else {
-                None
-            };
-            if include_energy && energy.is_none() {
-                continue;
-            }
-            let mut row = Vec::new();
-            row.push(format!("{tof_ms:.6}"));
-            if let Some(energy) = energy {
-                row.push(format!("{energy:.6}"));
-            }
-            if include_full {
-                let count = full.get(i).copied().unwrap_or(0);
-                row.push(count.to_string());
-            }
-            for (_, data) in &visible_rois {
-                let count = data.counts.get(i).copied().unwrap_or(0);
-                row.push(count.to_string());
-            }
+        for row in rows {
             writeln!(file, "{}", row.join(","))?;
         }
 
         Ok(())
     }
 
+    /// Format the visible spectra as tab-separated text for pasting into spreadsheets.
+    fn spectrum_tsv(
+        full: Option<&[u64]>,
+        rois: &[Roi],
+        roi_spectra: &HashMap<usize, RoiSpectrumEntry>,
+        full_visible: bool,
+        bin_width_ms: f64,
+        axis_config: SpectrumAxisConfig,
+    ) -> String {
+        let visible_rois = Self::visible_roi_spectra(rois, roi_spectra);
+        let (header_cols, rows) =
+            Self::spectrum_table(full, &visible_rois, full_visible, bin_width_ms, axis_config);
+        let mut text = header_cols.join("\t");
+        text.push('\n');
+        for row in rows {
+            text.push_str(&row.join("\t"));
+            text.push('\n');
+        }
+        text
+    }
+
+    /// Copy the displayed histogram image to the system clipboard.
+    fn copy_histogram_image(&self) -> anyhow::Result<()> {
+        let image = self.generate_histogram();
+        let [width, height] = image.size;
+        let bytes: Vec<u8> = image
+            .pixels
+            .iter()
+            .flat_map(|color| color.to_array())
+            .collect();
+        let mut clipboard = arboard::Clipboard::new()?;
+        clipboard.set_image(arboard::ImageData {
+            width,
+            height,
+            bytes: bytes.into(),
+        })?;
+        Ok(())
+    }
+
     fn export_spectrum_png(
         lines: &[(String, Color32, Vec<[f64; 2]>)],
         bounds: PlotBounds,
@@ -3027,6 +3345,16 @@ impl RustpixApp {
         let Some(path) = FileDialog::new().set_file_name("spectrum.png").save_file() else {
             return Ok(());
         };
+        Self::spectrum_png(lines, bounds, colors, export).save(path)?;
+        Ok(())
+    }
+
+    fn spectrum_png(
+        lines: &[(String, Color32, Vec<[f64; 2]>)],
+        bounds: PlotBounds,
+        colors: ThemeColors,
+        export: &SpectrumExportConfig,
+    ) -> RgbaImage {
         let (mut img, geometry) = Self::spectrum_export_canvas(bounds, colors);
         Self::draw_spectrum_grid(&mut img, &geometry);
         Self::draw_spectrum_axes(&mut img, &geometry);
@@ -3034,9 +3362,72 @@ impl RustpixApp {
         Self::draw_spectrum_axis_labels(&mut img, &geometry, export);
         Self::draw_spectrum_lines(&mut img, &geometry, lines);
         Self::draw_spectrum_legend(&mut img, &geometry, lines);
+        img
+    }
 
-        img.save(path)?;
-        Ok(())
+    /// The spectrum plot the PNG button exports, of the full-FOV and visible
+    /// ROI spectra of the current view; `None` if none of them has data.
+    pub(crate) fn spectrum_plot_image(&self, colors: ThemeColors) -> Option<RgbaImage> {
+        let spectrum = self.tof_spectrum().map(<[u64]>::to_vec);
+        let mut new_tof_bin = None;
+        let inputs = SpectrumPanelInputs {
+            spectrum: &spectrum,
+            slicer_enabled: false,
+            current_tof_bin: 0,
+            n_bins: self.n_tof_bins(),
+            new_tof_bin: &mut new_tof_bin,
+        };
+        let data = self.build_spectrum_plot_data(&colors, &inputs)?;
+        let export = SpectrumExportConfig {
+            axis: data.axis,
+            log_x: data.log_x,
+            log_y: data.log_y,
+        };
+        Some(Self::spectrum_png(
+            &data.lines,
+            data.export_bounds,
+            colors,
+            &export,
+        ))
+    }
+
+    /// `image` with the outline and name of every visible ROI drawn in the
+    /// ROI's color; `None` if no ROI is visible.
+    pub(crate) fn roi_overlay_image(&self, image: &RgbaImage) -> Option<RgbaImage> {
+        let mut overlay = image.clone();
+        let mut drawn = false;
+        for roi in self
+            .roi_state
+            .rois
+            .iter()
+            .filter(|roi| roi.visibility.visible)
+        {
+            let corners = match &roi.shape {
+                RoiShape::Rectangle { x1, y1, x2, y2 } => {
+                    vec![(*x1, *y1), (*x2, *y1), (*x2, *y2), (*x1, *y2)]
+                }
+                RoiShape::Polygon { vertices } => vertices.clone(),
+            };
+            let points: Vec<(i32, i32)> = corners
+                .iter()
+                .map(|&(x, y)| (round_to_i32_clamped(x), round_to_i32_clamped(y)))
+                .collect();
+            let color = Self::color32_to_rgba(roi.color);
+            for (i, &(x0, y0)) in points.iter().enumerate() {
+                let (x1, y1) = points[(i + 1) % points.len()];
+                Self::draw_line(&mut overlay, x0, y0, x1, y1, color);
+            }
+            let (min_x, _, min_y, _) = roi.bounds();
+            Self::draw_text(
+                &mut overlay,
+                round_to_i32_clamped(min_x) + 2,
+                round_to_i32_clamped(min_y) + 2,
+                &roi.name.to_uppercase(),
+                color,
+            );
+            drawn = true;
+        }
+        drawn.then_some(overlay)
     }
 
     fn spectrum_export_canvas(
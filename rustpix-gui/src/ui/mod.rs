@@ -1,12 +1,37 @@
 //! UI rendering modules.
 //!
 //! Contains the UI rendering logic split into separate modules:
+//! - `calibration`: Energy calibration wizard
+//! - `command_history`: Recorded steps and macro export
+//! - `command_palette`: Ctrl+P action search
 //! - `control_panel`: Left sidebar, top bar, and bottom status bar
+//! - `file_info`: Acquisition metadata panel for the open file
+//! - `fourier`: Power spectrum and autocorrelation window
+//! - `health_dashboard`: Per-chip detector health window
 //! - `main_view`: Central panel with histogram image, slicer, and spectrum
+//! - `notifications`: Toast overlay and notification history
+//! - `radial_profile`: Radial profile and azimuthal integration window
+//! - `recovery`: Crash-recovery prompt for autosaved sessions
+//! - `roi_gate`: Per-ROI TOF gates and gated thumbnails
+//! - `tour`: First-run guided tour overlay
 //! - `statistics`: Statistics display panel
 //! - `theme`: Application theme and styling
 
+mod calibration;
+mod command_history;
+mod command_palette;
 mod control_panel;
+mod file_info;
+mod fourier;
+mod health_dashboard;
 mod main_view;
+mod notifications;
+mod radial_profile;
+mod recovery;
+mod roi_gate;
 mod statistics;
 pub mod theme;
+mod tour;
+
+pub(crate) use fourier::FourierCache;
+pub(crate) use roi_gate::RoiGateThumbnail;
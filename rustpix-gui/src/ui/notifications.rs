@@ -0,0 +1,208 @@
+//! Toast notifications overlay and notification history panel.
+
+use eframe::egui::{self, Color32, Rounding, Stroke};
+
+use super::theme::{accent, ThemeColors};
+use crate::app::RustpixApp;
+use crate::state::NotificationLevel;
+
+/// Maximum number of toasts stacked on screen at once.
+const MAX_VISIBLE_TOASTS: usize = 5;
+/// Extra display time granted while a toast is hovered (seconds).
+const HOVER_EXTEND_SECS: f64 = 2.0;
+
+fn level_color(level: NotificationLevel) -> Color32 {
+    match level {
+        NotificationLevel::Info => accent::BLUE,
+        NotificationLevel::Warning => accent::AMBER,
+        NotificationLevel::Error => accent::RED,
+    }
+}
+
+fn level_icon(level: NotificationLevel) -> &'static str {
+    match level {
+        NotificationLevel::Info => "ℹ",
+        NotificationLevel::Warning => "⚠",
+        NotificationLevel::Error => "✖",
+    }
+}
+
+fn format_age(seconds: f64) -> String {
+    if seconds < 60.0 {
+        "just now".to_string()
+    } else if seconds < 3600.0 {
+        format!("{:.0} min ago", (seconds / 60.0).floor())
+    } else {
+        format!("{:.0} h ago", (seconds / 3600.0).floor())
+    }
+}
+
+impl RustpixApp {
+    /// Render active toasts (bottom-right) and the notification history window.
+    pub(crate) fn render_notifications(&mut self, ctx: &egui::Context) {
+        let now = ctx.input(|i| i.time);
+        self.ui_state.notifications.prune(now);
+        self.render_toasts(ctx, now);
+        self.render_notification_history(ctx, now);
+    }
+
+    fn render_toasts(&mut self, ctx: &egui::Context, now: f64) {
+        let active = self.ui_state.notifications.active();
+        if active.is_empty() {
+            return;
+        }
+        let colors = ThemeColors::from_ctx(ctx);
+        let toasts: Vec<_> = active
+            .iter()
+            .rev()
+            .take(MAX_VISIBLE_TOASTS)
+            .cloned()
+            .collect();
+        let mut dismissed = Vec::new();
+        let mut hovered = Vec::new();
+
+        egui::Area::new(egui::Id::new("notification_toasts"))
+            .anchor(egui::Align2::RIGHT_BOTTOM, egui::vec2(-16.0, -40.0))
+            .order(egui::Order::Foreground)
+            .interactable(true)
+            .show(ctx, |ui| {
+                ui.set_max_width(360.0);
+                for toast in &toasts {
+                    let color = level_color(toast.level);
+                    let response = egui::Frame::none()
+                        .fill(colors.bg_panel)
+                        .stroke(Stroke::new(1.0, color))
+                        .rounding(Rounding::same(6.0))
+                        .inner_margin(egui::Margin::symmetric(10.0, 8.0))
+                        .show(ui, |ui| {
+                            ui.horizontal(|ui| {
+                                ui.label(
+                                    egui::RichText::new(level_icon(toast.level))
+                                        .size(13.0)
+                                        .color(color),
+                                );
+                                ui.add(
+                                    egui::Label::new(
+                                        egui::RichText::new(&toast.message)
+                                            .size(11.0)
+                                            .color(colors.text_primary),
+                                    )
+                                    .wrap(),
+                                );
+                                if ui.small_button("✕").on_hover_text("Dismiss").clicked() {
+                                    dismissed.push(toast.id);
+                                }
+                            });
+                        })
+                        .response;
+                    if response.contains_pointer() {
+                        hovered.push(toast.id);
+                    }
+                    ui.add_space(6.0);
+                }
+            });
+
+        for id in hovered {
+            self.ui_state
+                .notifications
+                .extend(id, now, HOVER_EXTEND_SECS);
+        }
+        for id in dismissed {
+            self.ui_state.notifications.dismiss(id);
+        }
+        // Keep repainting so expiring toasts disappear without user input.
+        ctx.request_repaint_after(std::time::Duration::from_millis(250));
+    }
+
+    fn render_notification_history(&mut self, ctx: &egui::Context, now: f64) {
+        if !self.ui_state.panel_popups.show_notifications {
+            return;
+        }
+        self.ui_state.notifications.mark_read();
+        let mut open = true;
+        let mut clear_clicked = false;
+        egui::Window::new("Notifications")
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(true)
+            .default_width(420.0)
+            .show(ctx, |ui| {
+                let colors = ThemeColors::from_ui(ui);
+                ui.horizontal(|ui| {
+                    let has_history = !self.ui_state.notifications.is_history_empty();
+                    if ui
+                        .add_enabled(has_history, egui::Button::new("Clear history"))
+                        .clicked()
+                    {
+                        clear_clicked = true;
+                    }
+                    if ui
+                        .add_enabled(
+                            !self.ui_state.notifications.active().is_empty(),
+                            egui::Button::new("Dismiss toasts"),
+                        )
+                        .clicked()
+                    {
+                        self.ui_state.notifications.dismiss_all();
+                    }
+                });
+                ui.separator();
+                if self.ui_state.notifications.is_history_empty() {
+                    ui.label(egui::RichText::new("No notifications yet.").color(colors.text_muted));
+                    return;
+                }
+                egui::ScrollArea::vertical()
+                    .max_height(320.0)
+                    .show(ui, |ui| {
+                        for note in self.ui_state.notifications.history().rev() {
+                            let color = level_color(note.level);
+                            ui.horizontal_wrapped(|ui| {
+                                ui.label(
+                                    egui::RichText::new(level_icon(note.level))
+                                        .size(12.0)
+                                        .color(color),
+                                )
+                                .on_hover_text(note.level.label());
+                                ui.label(
+                                    egui::RichText::new(format_age(now - note.created_at))
+                                        .size(10.0)
+                                        .color(colors.text_dim),
+                                );
+                                ui.label(egui::RichText::new(&note.message).size(11.0));
+                            });
+                        }
+                    });
+            });
+        if clear_clicked {
+            self.ui_state.notifications.clear_history();
+        }
+        self.ui_state.panel_popups.show_notifications = open;
+    }
+
+    /// Status bar button that opens the notification history.
+    pub(crate) fn render_notification_bell(&mut self, ui: &mut egui::Ui) {
+        let colors = ThemeColors::from_ui(ui);
+        let unread = self.ui_state.notifications.unread();
+        let text = if unread > 0 {
+            format!("🔔 {unread}")
+        } else {
+            "🔔".to_string()
+        };
+        let color = if unread > 0 {
+            accent::BLUE
+        } else {
+            colors.text_muted
+        };
+        let button = egui::Button::new(egui::RichText::new(text).size(11.0).color(color))
+            .fill(Color32::TRANSPARENT)
+            .stroke(Stroke::NONE);
+        if ui
+            .add(button)
+            .on_hover_text("Notification history")
+            .clicked()
+        {
+            self.ui_state.panel_popups.show_notifications =
+                !self.ui_state.panel_popups.show_notifications;
+        }
+    }
+}
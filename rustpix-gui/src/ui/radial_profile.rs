@@ -0,0 +1,269 @@
+//! Radial profile and azimuthal integration window.
+//!
+//! Integrates the image currently shown (full projection, or the TOF slice
+//! when the slicer is on) into rings or sectors around a center, for quick
+//! small-angle scattering checks. The CSV export can instead write one
+//! profile per TOF bin of the active hyperstack.
+
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use eframe::egui;
+use egui_plot::{Line, Plot, PlotPoints};
+use rfd::FileDialog;
+use rustpix_algorithms::{
+    azimuthal_profile, find_beam_spot, radial_profile, BeamSpotConfig, IntegratedProfile,
+};
+
+use super::theme::{accent, ThemeColors};
+use crate::app::RustpixApp;
+use crate::state::UiRadialProfile;
+use crate::util::usize_to_f64;
+
+/// Nanoseconds per TOF tick.
+const NS_PER_TICK: f64 = 25.0;
+
+fn integrate(
+    settings: &UiRadialProfile,
+    counts: &[u64],
+    width: usize,
+    height: usize,
+    center: (f64, f64),
+) -> IntegratedProfile {
+    if settings.azimuthal {
+        azimuthal_profile(
+            counts,
+            width,
+            height,
+            center,
+            settings.r_min..settings.r_max,
+            settings.sectors.max(1),
+        )
+    } else {
+        radial_profile(counts, width, height, center, settings.bin_width.max(0.1))
+    }
+}
+
+impl RustpixApp {
+    /// Render the radial profile window while it is open.
+    pub(crate) fn render_radial_profile(&mut self, ctx: &egui::Context) {
+        if !self.ui_state.panels.show_radial_profile {
+            return;
+        }
+        let mut open = true;
+        egui::Window::new("Radial Profile")
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(true)
+            .default_width(520.0)
+            .show(ctx, |ui| {
+                let colors = ThemeColors::from_ui(ui);
+                let (width, height) = self.current_data_dimensions();
+                if self
+                    .current_counts()
+                    .is_none_or(|counts| counts.len() != width * height)
+                {
+                    ui.label(
+                        egui::RichText::new("Load data to compute a radial profile")
+                            .size(11.0)
+                            .color(colors.text_muted),
+                    );
+                    return;
+                }
+                let center = self
+                    .ui_state
+                    .radial_profile
+                    .center
+                    .unwrap_or((usize_to_f64(width) / 2.0, usize_to_f64(height) / 2.0));
+                let spot = self.render_radial_profile_controls(ui, center);
+                let settings = self.ui_state.radial_profile;
+                let Some(counts) = self.current_counts() else {
+                    return;
+                };
+                let profile = integrate(&settings, counts, width, height, center);
+                let spot_center = spot.then(|| {
+                    find_beam_spot(counts, width, height, &BeamSpotConfig::default())
+                        .map(|spot| (spot.x, spot.y))
+                });
+
+                ui.add_space(6.0);
+                let x_label = if settings.azimuthal {
+                    "Azimuth (deg)"
+                } else {
+                    "Radius (pixels)"
+                };
+                let points: Vec<[f64; 2]> = profile
+                    .centers
+                    .iter()
+                    .zip(profile.mean())
+                    .map(|(&x, y)| [x, y])
+                    .collect();
+                Plot::new("radial_profile_plot")
+                    .height(220.0)
+                    .x_axis_label(x_label)
+                    .y_axis_label("Counts / pixel")
+                    .include_y(0.0)
+                    .show(ui, |plot_ui| {
+                        plot_ui.line(
+                            Line::new(PlotPoints::new(points))
+                                .color(accent::BLUE)
+                                .name("Mean counts per pixel"),
+                        );
+                    });
+
+                ui.add_space(6.0);
+                ui.horizontal(|ui| {
+                    ui.checkbox(
+                        &mut self.ui_state.radial_profile.export_per_tof,
+                        "Per TOF bin",
+                    )
+                    .on_hover_text("Export one profile per TOF bin of the hyperstack");
+                    if ui.button("Export CSV…").clicked() {
+                        self.export_radial_profile_csv(ui.ctx(), center);
+                    }
+                });
+
+                match spot_center {
+                    Some(Some(center)) => self.ui_state.radial_profile.center = Some(center),
+                    Some(None) => self.ui_state.notifications.error(
+                        "No beam spot found in the current image",
+                        ui.ctx().input(|i| i.time),
+                    ),
+                    None => {}
+                }
+            });
+        self.ui_state.panels.show_radial_profile = open;
+    }
+
+    /// Center and binning controls. Returns whether "Beam spot" was clicked.
+    fn render_radial_profile_controls(&mut self, ui: &mut egui::Ui, center: (f64, f64)) -> bool {
+        let settings = &mut self.ui_state.radial_profile;
+        let mut spot = false;
+        ui.horizontal(|ui| {
+            let (mut x, mut y) = center;
+            ui.label("Center");
+            let changed = ui
+                .add(egui::DragValue::new(&mut x).speed(0.5).prefix("x "))
+                .changed()
+                | ui.add(egui::DragValue::new(&mut y).speed(0.5).prefix("y "))
+                    .changed();
+            if changed {
+                settings.center = Some((x, y));
+            }
+            if ui
+                .button("Beam spot")
+                .on_hover_text("Center on the beam spot of the current image")
+                .clicked()
+            {
+                spot = true;
+            }
+            if ui.button("Image center").clicked() {
+                settings.center = None;
+            }
+        });
+        ui.horizontal(|ui| {
+            ui.radio_value(&mut settings.azimuthal, false, "Radial");
+            ui.radio_value(&mut settings.azimuthal, true, "Azimuthal");
+            ui.separator();
+            if settings.azimuthal {
+                ui.add(
+                    egui::DragValue::new(&mut settings.sectors)
+                        .range(1..=360)
+                        .prefix("sectors "),
+                );
+                ui.add(
+                    egui::DragValue::new(&mut settings.r_min)
+                        .range(0.0..=f64::MAX)
+                        .prefix("r ≥ "),
+                );
+                ui.add(
+                    egui::DragValue::new(&mut settings.r_max)
+                        .range(0.0..=f64::MAX)
+                        .prefix("r < "),
+                );
+            } else {
+                ui.add(
+                    egui::DragValue::new(&mut settings.bin_width)
+                        .speed(0.1)
+                        .range(0.1..=64.0)
+                        .prefix("ring width ")
+                        .suffix(" px"),
+                );
+            }
+        });
+        spot
+    }
+
+    fn export_radial_profile_csv(&mut self, ctx: &egui::Context, center: (f64, f64)) {
+        let Some(path) = FileDialog::new()
+            .add_filter("CSV", &["csv"])
+            .set_file_name("radial_profile.csv")
+            .save_file()
+        else {
+            return;
+        };
+        let settings = self.ui_state.radial_profile;
+        let (width, height) = self.current_data_dimensions();
+        let result = match (settings.export_per_tof, self.active_hyperstack()) {
+            (true, Some(hyperstack)) => {
+                let profiles: Vec<IntegratedProfile> = (0..hyperstack.n_tof_bins())
+                    .filter_map(|bin| hyperstack.slice_tof(bin))
+                    .map(|slice| integrate(&settings, slice, width, height, center))
+                    .collect();
+                write_profile_csv(
+                    &path,
+                    &profiles,
+                    settings.azimuthal,
+                    Some(hyperstack.bin_width()),
+                )
+            }
+            _ => match self.current_counts() {
+                Some(counts) => write_profile_csv(
+                    &path,
+                    &[integrate(&settings, counts, width, height, center)],
+                    settings.azimuthal,
+                    None,
+                ),
+                None => return,
+            },
+        };
+        if let Err(err) = result {
+            self.ui_state.notifications.error(
+                format!("Radial profile export failed: {err}"),
+                ctx.input(|i| i.time),
+            );
+        }
+    }
+}
+
+/// Write profiles as CSV. With `tof_bin_ticks`, each profile is one TOF bin
+/// and its bin index and range (ns) lead every row.
+fn write_profile_csv(
+    path: &Path,
+    profiles: &[IntegratedProfile],
+    azimuthal: bool,
+    tof_bin_ticks: Option<f64>,
+) -> std::io::Result<()> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    let axis = if azimuthal { "angle_deg" } else { "radius_px" };
+    if tof_bin_ticks.is_some() {
+        write!(writer, "tof_bin,tof_start_ns,tof_end_ns,")?;
+    }
+    writeln!(writer, "{axis},counts,pixels,mean")?;
+    for (bin, profile) in profiles.iter().enumerate() {
+        let mean = profile.mean();
+        for (i, &center) in profile.centers.iter().enumerate() {
+            if let Some(ticks) = tof_bin_ticks {
+                let start = usize_to_f64(bin) * ticks * NS_PER_TICK;
+                write!(writer, "{bin},{start},{},", start + ticks * NS_PER_TICK)?;
+            }
+            writeln!(
+                writer,
+                "{center},{},{},{}",
+                profile.counts[i], profile.pixels[i], mean[i]
+            )?;
+        }
+    }
+    writer.flush()
+}
@@ -0,0 +1,71 @@
+//! Session recovery dialog shown when an autosave from a crashed session exists.
+
+use eframe::egui;
+
+use super::theme::ThemeColors;
+use crate::app::RustpixApp;
+
+fn format_age(secs: u64) -> String {
+    match secs {
+        0..=59 => format!("{secs} s ago"),
+        60..=3599 => format!("{} min ago", secs / 60),
+        3600..=86_399 => format!("{} h ago", secs / 3600),
+        _ => format!("{} days ago", secs / 86_400),
+    }
+}
+
+impl RustpixApp {
+    /// Offer to restore (or discard) a session left behind by an unclean exit.
+    pub(crate) fn render_recovery_dialog(&mut self, ctx: &egui::Context) {
+        let Some(snapshot) = self.autosave.pending_recovery.as_ref() else {
+            return;
+        };
+        let mut restore = false;
+        let mut discard = false;
+        egui::Window::new("Recover previous session?")
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+            .show(ctx, |ui| {
+                let colors = ThemeColors::from_ui(ui);
+                ui.label("Rustpix did not shut down cleanly last time.");
+                ui.label(
+                    egui::RichText::new(format!(
+                        "Autosave from {}",
+                        format_age(snapshot.age_secs())
+                    ))
+                    .color(colors.text_muted),
+                );
+                ui.add_space(6.0);
+                if let Some(path) = &snapshot.selected_file {
+                    ui.label(format!("File: {}", path.display()));
+                }
+                ui.label(format!("ROIs: {}", snapshot.rois.len()));
+                ui.add_space(8.0);
+                ui.horizontal(|ui| {
+                    if ui.button("Restore").clicked() {
+                        restore = true;
+                    }
+                    if ui.button("Discard").clicked() {
+                        discard = true;
+                    }
+                });
+            });
+
+        if restore {
+            if let Some(snapshot) = self.autosave.pending_recovery.take() {
+                let roi_count = snapshot.rois.len();
+                self.restore_autosave(snapshot);
+                self.ui_state.notifications.info(
+                    format!("Recovered previous session ({roi_count} ROIs)"),
+                    ctx.input(|i| i.time),
+                );
+            }
+        } else if discard {
+            self.autosave.pending_recovery = None;
+            if let Some(recovered) = self.autosave.recovered.take() {
+                recovered.remove();
+            }
+        }
+    }
+}
@@ -0,0 +1,209 @@
+//! Per-ROI TOF gates and their gated thumbnails.
+//!
+//! A ROI can carry its own TOF gate, an inclusive range of bins independent
+//! of the slice shown in the main view. Its thumbnail sums the image over
+//! that range, cropped to the ROI and scaled to its own brightest pixel, and
+//! sits next to the ROI in the spectrum data list, so regions that are
+//! interesting at different wavelengths can be watched side by side.
+
+use eframe::egui;
+
+use crate::app::{clamp_span, hash_roi_shape, point_in_polygon_xy, PixelMaskData, RustpixApp};
+use crate::histogram::Hyperstack3D;
+use crate::state::{ViewMode, ViewTransform};
+use crate::util::{usize_to_f32, usize_to_f64};
+use crate::viewer::{generate_histogram_image_transformed, Colormap, Roi, RoiShape};
+
+/// Longest edge of a thumbnail in points.
+const THUMBNAIL_SIZE: f32 = 56.0;
+
+/// Inputs a thumbnail was rendered from.
+#[derive(Clone, PartialEq)]
+struct ThumbnailKey {
+    view_mode: ViewMode,
+    data_revision: u64,
+    transform: ViewTransform,
+    shape_hash: u64,
+    gate: (usize, usize),
+    colormap: Colormap,
+    log_scale: bool,
+}
+
+/// Gated thumbnail of one ROI.
+pub(crate) struct RoiGateThumbnail {
+    key: ThumbnailKey,
+    width: usize,
+    height: usize,
+    texture: egui::TextureHandle,
+}
+
+impl RustpixApp {
+    /// Re-render the thumbnails of gated ROIs whose shape, gate or data
+    /// changed, and drop those of ROIs that are gone or no longer gated.
+    pub(crate) fn update_roi_gate_thumbnails(&mut self, ctx: &egui::Context) {
+        let Some(hyperstack) = self.active_hyperstack() else {
+            self.roi_gate_thumbnails.clear();
+            return;
+        };
+        let transform = self.ui_state.histogram_view.transform;
+        let mask = self.excluded_pixel_mask();
+        let mut updates = Vec::new();
+        for roi in &self.roi_state.rois {
+            let Some(gate) = roi.tof_gate else {
+                continue;
+            };
+            let key = ThumbnailKey {
+                view_mode: self.ui_state.view_mode,
+                data_revision: self.active_data_revision(),
+                transform,
+                shape_hash: hash_roi_shape(roi),
+                gate,
+                colormap: self.colormap.clone(),
+                log_scale: self.ui_state.histogram.log_scale,
+            };
+            if self
+                .roi_gate_thumbnails
+                .get(&roi.id)
+                .is_some_and(|thumbnail| thumbnail.key == key)
+            {
+                continue;
+            }
+            let image = gated_roi_image(roi, hyperstack, mask, &key);
+            updates.push((roi.id, key, image));
+        }
+
+        let rois = &self.roi_state.rois;
+        self.roi_gate_thumbnails.retain(|id, _| {
+            rois.iter()
+                .any(|roi| roi.id == *id && roi.tof_gate.is_some())
+        });
+        for (id, key, image) in updates {
+            let Some(image) = image else {
+                self.roi_gate_thumbnails.remove(&id);
+                continue;
+            };
+            let [width, height] = image.size;
+            let texture = ctx.load_texture(
+                format!("roi_gate_{id}"),
+                image,
+                egui::TextureOptions::NEAREST,
+            );
+            self.roi_gate_thumbnails.insert(
+                id,
+                RoiGateThumbnail {
+                    key,
+                    width,
+                    height,
+                    texture,
+                },
+            );
+        }
+    }
+
+    /// Gate toggle, bin range and thumbnail of one ROI in the spectrum data
+    /// list. `max_bin` is the last TOF bin of the current view; a new gate
+    /// starts at `current_bin`.
+    pub(crate) fn render_roi_gate(
+        ui: &mut egui::Ui,
+        roi: &mut Roi,
+        max_bin: usize,
+        current_bin: usize,
+        thumbnail: Option<&RoiGateThumbnail>,
+    ) {
+        let before = roi.tof_gate;
+        ui.horizontal(|ui| {
+            ui.vertical(|ui| {
+                let mut gated = roi.tof_gate.is_some();
+                if ui
+                    .checkbox(&mut gated, "TOF gate")
+                    .on_hover_text("Show this ROI summed over its own range of TOF bins")
+                    .changed()
+                {
+                    let bin = current_bin.min(max_bin);
+                    roi.tof_gate = gated.then_some((bin, bin));
+                }
+                if let Some(gate) = roi.tof_gate.as_mut() {
+                    ui.horizontal(|ui| {
+                        ui.add(egui::DragValue::new(&mut gate.0).range(0..=max_bin));
+                        ui.label("–");
+                        ui.add(egui::DragValue::new(&mut gate.1).range(0..=max_bin));
+                    });
+                    gate.0 = gate.0.min(max_bin);
+                    gate.1 = gate.1.clamp(gate.0, max_bin);
+                }
+            });
+            if let (Some(gate), Some(thumbnail)) = (roi.tof_gate, thumbnail) {
+                let (width, height) = (
+                    usize_to_f32(thumbnail.width),
+                    usize_to_f32(thumbnail.height),
+                );
+                let scale = THUMBNAIL_SIZE / width.max(height);
+                ui.image((thumbnail.texture.id(), egui::vec2(width, height) * scale))
+                    .on_hover_text(format!("TOF bins {}–{}", gate.0, gate.1));
+            }
+        });
+        if roi.tof_gate != before {
+            ui.ctx().request_repaint();
+        }
+    }
+}
+
+/// Image of the counts inside `roi` summed over its gate, cropped to the
+/// ROI's bounding box in display coordinates. Pixels outside the ROI, and
+/// those excluded by `mask`, are transparent. Returns `None` if the ROI lies
+/// outside the image.
+fn gated_roi_image(
+    roi: &Roi,
+    hyperstack: &Hyperstack3D,
+    mask: Option<&PixelMaskData>,
+    key: &ThumbnailKey,
+) -> Option<egui::ColorImage> {
+    let (width, height) = (hyperstack.width(), hyperstack.height());
+    let (display_width, display_height) = key.transform.display_size(width, height);
+    let (min_x, max_x, min_y, max_y) = roi.bounds();
+    let (x_start, x_end) = clamp_span(min_x, max_x, display_width);
+    let (y_start, y_end) = clamp_span(min_y, max_y, display_height);
+    if x_start >= x_end || y_start >= y_end {
+        return None;
+    }
+
+    let counts = hyperstack.project_xy_range(key.gate.0, key.gate.1);
+    let (crop_width, crop_height) = (x_end - x_start, y_end - y_start);
+    let mut cropped = vec![0u64; crop_width * crop_height];
+    let mut inside = vec![false; crop_width * crop_height];
+    let masked = |idx: usize| mask.is_some_and(|mask| mask.is_masked(idx));
+    for y in y_start..y_end {
+        for x in x_start..x_end {
+            let covered = match &roi.shape {
+                RoiShape::Rectangle { .. } => true,
+                RoiShape::Polygon { vertices } => {
+                    point_in_polygon_xy(usize_to_f64(x) + 0.5, usize_to_f64(y) + 0.5, vertices)
+                }
+            };
+            let idx = (y - y_start) * crop_width + (x - x_start);
+            if let Some((src_x, src_y)) = key
+                .transform
+                .apply_inverse(x, y, width, height)
+                .filter(|&(src_x, src_y)| covered && !masked(src_y * width + src_x))
+            {
+                cropped[idx] = counts[src_y * width + src_x];
+                inside[idx] = true;
+            }
+        }
+    }
+
+    let mut image = generate_histogram_image_transformed(
+        &cropped,
+        crop_width,
+        crop_height,
+        ViewTransform::default(),
+        &key.colormap,
+        key.log_scale,
+    );
+    for (pixel, keep) in image.pixels.iter_mut().zip(inside) {
+        if !keep {
+            *pixel = egui::Color32::TRANSPARENT;
+        }
+    }
+    Some(image)
+}
@@ -8,7 +8,7 @@ use crate::util::{format_number, format_number_si};
 
 impl RustpixApp {
     /// Render a single stat row with label on left and value on right.
-    fn stat_row(ui: &mut egui::Ui, label: &str, value: &str, highlight: bool) {
+    pub(super) fn stat_row(ui: &mut egui::Ui, label: &str, value: &str, highlight: bool) {
         ui.horizontal(|ui| {
             ui.label(stat_label(label));
             ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
@@ -29,6 +29,21 @@ impl RustpixApp {
             // Hit statistics
             Self::stat_row(ui, "Hits", &format_number(self.statistics.hit_count), false);
 
+            // Hits left once masked pixels are excluded
+            if let (Some(mask), Some(spectrum)) = (
+                self.excluded_pixel_mask(),
+                self.masked_tof_spectrum.as_deref(),
+            ) {
+                let unmasked = usize::try_from(spectrum.iter().sum::<u64>()).unwrap_or(usize::MAX);
+                Self::stat_row(ui, "  unmasked", &format_number(unmasked), false);
+                Self::stat_row(
+                    ui,
+                    "  excluded px",
+                    &format_number(mask.excluded_count),
+                    false,
+                );
+            }
+
             // TOF range
             let max_ms = self.statistics.tof_range_ms(self.tdc_frequency);
             Self::stat_row(ui, "TOF range", &format!("0.0 – {max_ms:.2} ms"), false);
@@ -72,13 +87,25 @@ impl RustpixApp {
                 ui.add_space(8.0);
 
                 // Neutron count (highlighted)
+                // With a classifier every cluster is kept and labelled.
+                let label = if self.statistics.class_counts.is_some() {
+                    "Events"
+                } else {
+                    "Neutrons"
+                };
                 Self::stat_row(
                     ui,
-                    "Neutrons",
+                    label,
                     &format_number(self.statistics.neutron_count),
                     true,
                 );
 
+                if let Some(classes) = self.statistics.class_counts {
+                    Self::stat_row(ui, "  neutron", &format_number(classes.neutron), false);
+                    Self::stat_row(ui, "  gamma", &format_number(classes.gamma), false);
+                    Self::stat_row(ui, "  noise", &format_number(classes.noise), false);
+                }
+
                 // Average cluster size
                 Self::stat_row(
                     ui,
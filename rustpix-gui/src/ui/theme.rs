@@ -59,6 +59,7 @@ pub mod accent {
     pub const BLUE: Color32 = Color32::from_rgb(0x4a, 0x9e, 0xff);
     pub const GREEN: Color32 = Color32::from_rgb(0x10, 0xb9, 0x81);
     pub const RED: Color32 = Color32::from_rgb(0xef, 0x44, 0x44);
+    pub const AMBER: Color32 = Color32::from_rgb(0xf5, 0x9e, 0x0b);
 }
 
 /// Theme-aware color accessor.
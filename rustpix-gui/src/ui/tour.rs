@@ -0,0 +1,94 @@
+//! Guided tour overlay.
+
+use eframe::egui::{self, Rounding, Stroke};
+
+use super::theme::{accent, ThemeColors};
+use crate::app::RustpixApp;
+use crate::state::{TourAnchor, TOUR_STEPS};
+
+fn anchor_placement(anchor: TourAnchor) -> (egui::Align2, egui::Vec2) {
+    match anchor {
+        TourAnchor::TopBar => (egui::Align2::LEFT_TOP, egui::vec2(24.0, 64.0)),
+        TourAnchor::Sidebar => (egui::Align2::LEFT_CENTER, egui::vec2(300.0, 0.0)),
+        TourAnchor::Histogram => (egui::Align2::CENTER_TOP, egui::vec2(120.0, 120.0)),
+        TourAnchor::Spectrum => (egui::Align2::CENTER_BOTTOM, egui::vec2(120.0, -80.0)),
+    }
+}
+
+impl RustpixApp {
+    /// Render the current guided tour step, if the tour is running.
+    pub(crate) fn render_tour(&mut self, ctx: &egui::Context) {
+        if self.autosave.pending_recovery.is_some() {
+            return;
+        }
+        let Some((index, step)) = self.ui_state.tour.current() else {
+            return;
+        };
+        let colors = ThemeColors::from_ctx(ctx);
+        let (align, offset) = anchor_placement(step.anchor);
+        let last = index + 1 == TOUR_STEPS.len();
+        let mut back = false;
+        let mut next = false;
+        let mut skip = false;
+
+        egui::Area::new(egui::Id::new("guided_tour"))
+            .anchor(align, offset)
+            .order(egui::Order::Foreground)
+            .show(ctx, |ui| {
+                egui::Frame::none()
+                    .fill(colors.bg_panel)
+                    .stroke(Stroke::new(1.5, accent::BLUE))
+                    .rounding(Rounding::same(6.0))
+                    .inner_margin(egui::Margin::same(12.0))
+                    .show(ui, |ui| {
+                        ui.set_max_width(320.0);
+                        ui.label(
+                            egui::RichText::new(format!(
+                                "Step {} of {}",
+                                index + 1,
+                                TOUR_STEPS.len()
+                            ))
+                            .size(10.0)
+                            .color(colors.text_muted),
+                        );
+                        ui.label(
+                            egui::RichText::new(step.title)
+                                .size(14.0)
+                                .strong()
+                                .color(accent::BLUE),
+                        );
+                        ui.add_space(4.0);
+                        ui.label(egui::RichText::new(step.body).size(11.0));
+                        ui.add_space(8.0);
+                        ui.horizontal(|ui| {
+                            if ui.button("Skip tour").clicked() {
+                                skip = true;
+                            }
+                            ui.with_layout(
+                                egui::Layout::right_to_left(egui::Align::Center),
+                                |ui| {
+                                    let next_label = if last { "Finish" } else { "Next ›" };
+                                    if ui.button(next_label).clicked() {
+                                        next = true;
+                                    }
+                                    if ui
+                                        .add_enabled(index > 0, egui::Button::new("‹ Back"))
+                                        .clicked()
+                                    {
+                                        back = true;
+                                    }
+                                },
+                            );
+                        });
+                    });
+            });
+
+        if skip {
+            self.ui_state.tour.finish();
+        } else if next {
+            self.ui_state.tour.advance();
+        } else if back {
+            self.ui_state.tour.back();
+        }
+    }
+}
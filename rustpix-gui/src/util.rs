@@ -21,6 +21,28 @@ pub fn u64_to_f64(value: u64) -> f64 {
     value as f64
 }
 
+/// Calendar date `(year, month, day)` of a day count since the Unix epoch.
+///
+/// Civil-from-days in the proleptic Gregorian calendar.
+#[must_use]
+pub fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let shifted = days + 719_468;
+    let era = shifted.div_euclid(146_097);
+    let day_of_era = shifted.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
 /// Sanitize a base name for export folder/file creation.
 #[must_use]
 pub fn sanitize_export_base_name(value: &str) -> String {
@@ -129,47 +151,7 @@ pub fn format_bytes(bytes: u64) -> String {
     }
 }
 
-/// Neutron mass in kilograms.
-const NEUTRON_MASS_KG: f64 = 1.674_927_498e-27;
-/// Elementary charge in joules per eV.
-const EV_J: f64 = 1.602_176_634e-19;
-
-/// Convert TOF (µs) to neutron energy (eV).
-///
-/// Returns `None` if the input is invalid or results in non-physical values.
-#[must_use]
-pub fn tof_us_to_energy_ev(tof_us: f64, flight_path_m: f64, tof_offset_ns: f64) -> Option<f64> {
-    if !tof_us.is_finite() || !flight_path_m.is_finite() || flight_path_m <= 0.0 {
-        return None;
-    }
-    let offset_us = tof_offset_ns / 1000.0;
-    let t_us = tof_us - offset_us;
-    if t_us <= 0.0 {
-        return None;
-    }
-    let time_seconds = t_us * 1e-6;
-    let v = flight_path_m / time_seconds;
-    let e_j = 0.5 * NEUTRON_MASS_KG * v * v;
-    Some(e_j / EV_J)
-}
-
-/// Convert neutron energy (eV) to TOF (µs).
-///
-/// Returns `None` if the input is invalid or results in non-physical values.
-#[must_use]
-pub fn energy_ev_to_tof_us(energy_ev: f64, flight_path_m: f64, tof_offset_ns: f64) -> Option<f64> {
-    if !energy_ev.is_finite()
-        || energy_ev <= 0.0
-        || !flight_path_m.is_finite()
-        || flight_path_m <= 0.0
-    {
-        return None;
-    }
-    let e_j = energy_ev * EV_J;
-    let time_seconds = flight_path_m * (NEUTRON_MASS_KG / (2.0 * e_j)).sqrt();
-    let offset_us = tof_offset_ns / 1000.0;
-    Some(time_seconds * 1e6 + offset_us)
-}
+pub use rustpix_tpx::conversion::{energy_ev_to_tof_us, tof_us_to_energy_ev};
 
 /// Convert TOF (ms) to neutron energy (eV).
 #[must_use]
@@ -1,11 +1,15 @@
 //! Colormap definitions and application logic.
 
+use std::path::Path;
+
+use anyhow::{anyhow, Result};
 use eframe::egui::Color32;
+use serde::{Deserialize, Serialize};
 
 use crate::util::f32_to_u8;
 
 /// Available colormaps for histogram visualization.
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum Colormap {
     /// Green (Matrix style) - black to bright green.
     Green,
@@ -15,6 +19,139 @@ pub enum Colormap {
     Grayscale,
     /// Viridis (approximate) - blue to teal to green to yellow.
     Viridis,
+    /// Imported lookup table (e.g. an `ImageJ` `.lut` file).
+    Custom(CustomLut),
+}
+
+/// A 256-entry RGB lookup table loaded from a file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CustomLut {
+    /// Display name (the file stem).
+    pub name: String,
+    /// 256 RGB colors, from the lowest to the highest value.
+    pub colors: Vec<[u8; 3]>,
+}
+
+impl CustomLut {
+    /// Number of entries in a lookup table.
+    pub const ENTRIES: usize = 256;
+
+    /// Load an `ImageJ` lookup table.
+    ///
+    /// Accepts the binary formats `ImageJ` reads (768 bytes of red, green and
+    /// blue planes, optionally after a 32-byte NIH Image `ICOL` header) and
+    /// text tables with `r g b` or `index r g b` rows. Tables with fewer than
+    /// 256 rows are interpolated, as `ImageJ` does.
+    ///
+    /// # Errors
+    /// Returns an error if the file cannot be read or is not a lookup table.
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let bytes = std::fs::read(path)?;
+        let name = path.file_stem().map_or_else(
+            || "Custom".to_string(),
+            |stem| stem.to_string_lossy().into_owned(),
+        );
+        Self::from_bytes(name, &bytes)
+    }
+
+    /// Parse an `ImageJ` lookup table from its file contents.
+    ///
+    /// # Errors
+    /// Returns an error if the contents are not a lookup table.
+    pub fn from_bytes(name: String, bytes: &[u8]) -> Result<Self> {
+        const PLANES: usize = 3 * CustomLut::ENTRIES;
+        const NIH_HEADER: usize = 32;
+        let planes = match bytes.len() {
+            PLANES => Some(bytes),
+            len if len >= NIH_HEADER + PLANES && bytes.starts_with(b"ICOL") => {
+                Some(&bytes[NIH_HEADER..NIH_HEADER + PLANES])
+            }
+            _ => None,
+        };
+        let colors = match planes {
+            Some(planes) => (0..Self::ENTRIES)
+                .map(|i| {
+                    [
+                        planes[i],
+                        planes[Self::ENTRIES + i],
+                        planes[2 * Self::ENTRIES + i],
+                    ]
+                })
+                .collect(),
+            None => interpolate(&parse_text_lut(bytes)?),
+        };
+        Ok(Self { name, colors })
+    }
+
+    fn color(&self, val: f32) -> [u8; 4] {
+        #[allow(clippy::cast_precision_loss)]
+        let last = (self.colors.len().max(1) - 1) as f32;
+        let index = f32_to_u8((val.clamp(0.0, 1.0) * last).round());
+        let [r, g, b] = self
+            .colors
+            .get(usize::from(index))
+            .copied()
+            .unwrap_or_default();
+        [r, g, b, 255]
+    }
+}
+
+/// Rows of a text lookup table; a non-numeric header line is skipped.
+fn parse_text_lut(bytes: &[u8]) -> Result<Vec<[u8; 3]>> {
+    let text = std::str::from_utf8(bytes).map_err(|_| anyhow!("not an ImageJ lookup table"))?;
+    let mut rows = Vec::new();
+    for (line_no, line) in text.lines().enumerate() {
+        let fields: Vec<&str> = line
+            .split(|c: char| c.is_whitespace() || c == ',')
+            .filter(|field| !field.is_empty())
+            .collect();
+        if fields.is_empty() {
+            continue;
+        }
+        let Ok(values) = fields
+            .iter()
+            .map(|field| field.parse::<f64>())
+            .collect::<std::result::Result<Vec<_>, _>>()
+        else {
+            if rows.is_empty() {
+                continue;
+            }
+            return Err(anyhow!("line {}: expected numbers", line_no + 1));
+        };
+        let rgb = match values[..] {
+            [r, g, b] | [_, r, g, b] => [r, g, b],
+            _ => return Err(anyhow!("line {}: expected 3 or 4 columns", line_no + 1)),
+        };
+        if rgb.iter().any(|v| !(0.0..=255.0).contains(v)) {
+            return Err(anyhow!("line {}: values must be 0-255", line_no + 1));
+        }
+        #[allow(clippy::cast_possible_truncation)]
+        rows.push(rgb.map(|v| f32_to_u8(v as f32)));
+    }
+    if !(2..=CustomLut::ENTRIES).contains(&rows.len()) {
+        return Err(anyhow!("expected 2 to 256 colors, found {}", rows.len()));
+    }
+    Ok(rows)
+}
+
+/// Stretch `rows` linearly to 256 colors.
+fn interpolate(rows: &[[u8; 3]]) -> Vec<[u8; 3]> {
+    #[allow(clippy::cast_precision_loss)]
+    let scale = (rows.len() - 1) as f32 / (CustomLut::ENTRIES - 1) as f32;
+    (0..CustomLut::ENTRIES)
+        .map(|i| {
+            #[allow(clippy::cast_precision_loss)]
+            let position = i as f32 * scale;
+            #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+            let lower = (position.floor() as usize).min(rows.len() - 1);
+            let upper = (lower + 1).min(rows.len() - 1);
+            let t = position - position.floor();
+            std::array::from_fn(|c| {
+                let (a, b) = (f32::from(rows[lower][c]), f32::from(rows[upper][c]));
+                f32_to_u8((a + (b - a) * t).round())
+            })
+        })
+        .collect()
 }
 
 impl std::fmt::Display for Colormap {
@@ -24,6 +161,7 @@ impl std::fmt::Display for Colormap {
             Colormap::Hot => write!(f, "Hot (Thermal)"),
             Colormap::Grayscale => write!(f, "Grayscale"),
             Colormap::Viridis => write!(f, "Viridis"),
+            Colormap::Custom(lut) => write!(f, "{} (LUT)", lut.name),
         }
     }
 }
@@ -37,7 +175,7 @@ impl Colormap {
     /// # Returns
     /// RGBA color as `[r, g, b, a]` bytes
     #[must_use]
-    pub fn apply(self, val: f32) -> [u8; 4] {
+    pub fn apply(&self, val: f32) -> [u8; 4] {
         match self {
             Colormap::Green => {
                 let v = f32_to_u8(val * 255.0);
@@ -69,6 +207,7 @@ impl Colormap {
                 let b = f32_to_u8(255.0 * (1.0 - val));
                 [r, g, b, 255]
             }
+            Colormap::Custom(lut) => lut.color(val),
         }
     }
 
@@ -77,8 +216,46 @@ impl Colormap {
     /// # Arguments
     /// * `val` - Normalized value between 0.0 and 1.0
     #[must_use]
-    pub fn color_at(self, val: f32) -> Color32 {
+    pub fn color_at(&self, val: f32) -> Color32 {
         let [r, g, b, a] = self.apply(val);
         Color32::from_rgba_unmultiplied(r, g, b, a)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_custom_lut_binary_and_text() {
+        let mut planes = vec![0u8; 768];
+        for i in 0..=255u8 {
+            planes[usize::from(i)] = i;
+            planes[512 + usize::from(i)] = 255 - i;
+        }
+        let raw = CustomLut::from_bytes("fire".to_string(), &planes).unwrap();
+        assert_eq!(raw.colors[10], [10, 0, 245]);
+
+        let mut nih = b"ICOL".to_vec();
+        nih.resize(32, 0);
+        nih.extend_from_slice(&planes);
+        assert_eq!(
+            CustomLut::from_bytes("fire".to_string(), &nih).unwrap(),
+            raw
+        );
+
+        let text = "Index\tRed\tGreen\tBlue\n0\t0\t0\t0\n1\t255\t128\t0\n";
+        let lut = CustomLut::from_bytes("ramp".to_string(), text.as_bytes()).unwrap();
+        assert_eq!(lut.colors.len(), 256);
+        assert_eq!(lut.colors[0], [0, 0, 0]);
+        assert_eq!(lut.colors[255], [255, 128, 0]);
+        assert_eq!(lut.colors[128], [128, 64, 0]);
+
+        let cmap = Colormap::Custom(lut);
+        assert_eq!(cmap.apply(1.0), [255, 128, 0, 255]);
+        assert_eq!(cmap.to_string(), "ramp (LUT)");
+
+        assert!(CustomLut::from_bytes("bad".to_string(), b"1 2\n3 4\n").is_err());
+        assert!(CustomLut::from_bytes("bad".to_string(), &[0u8; 100]).is_err());
+    }
+}
@@ -1,9 +1,16 @@
 //! Visualization modules for histogram display.
 
 mod colormap;
+mod refresh;
 mod roi;
+mod roi_mask;
 mod texture;
 
-pub use colormap::Colormap;
+pub use colormap::{Colormap, CustomLut};
+pub use refresh::{RefreshStep, TextureRefresh};
 pub use roi::{Roi, RoiCommitError, RoiHandle, RoiSelectionMode, RoiShape, RoiState};
-pub use texture::generate_histogram_image_transformed;
+pub use roi_mask::RoiMaskExport;
+pub use texture::{
+    generate_float_image_transformed, generate_histogram_image_transformed,
+    generate_histogram_rows_scaled,
+};
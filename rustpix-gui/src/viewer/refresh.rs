@@ -0,0 +1,209 @@
+//! Scheduling of histogram texture refreshes.
+//!
+//! Regenerating the histogram texture is the most expensive thing a frame
+//! can do (a colormap lookup per display pixel, millions of them with
+//! super-resolution), and while files load or cluster it competes with the
+//! worker threads for the CPU. [`TextureRefresh`] keeps it from stalling
+//! frames:
+//!
+//! - Invalidations only mark the texture dirty, so any number of changes
+//!   between refreshes (a dragged TOF slider, several settings at once)
+//!   coalesce into one pass.
+//! - A pass regenerates the image in bands of rows sized to a per-frame
+//!   time budget from the measured cost per row; rows not redone yet keep
+//!   the previous image until a later frame reaches them.
+//! - While loading or processing runs, passes start at most every
+//!   [`BUSY_REFRESH_INTERVAL`] and get the smaller [`BUSY_FRAME_BUDGET`], so
+//!   input stays responsive and the workers keep the cores.
+
+use std::ops::Range;
+use std::time::{Duration, Instant};
+
+/// Time a frame may spend regenerating texture rows.
+pub const FRAME_BUDGET: Duration = Duration::from_millis(20);
+/// Time a frame may spend regenerating texture rows while busy.
+pub const BUSY_FRAME_BUDGET: Duration = Duration::from_millis(8);
+/// Shortest time between the starts of two passes while busy.
+pub const BUSY_REFRESH_INTERVAL: Duration = Duration::from_millis(250);
+/// Rows of the first band, before the cost per row is known.
+const FIRST_BAND_ROWS: usize = 32;
+
+/// What the texture needs this frame.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RefreshStep {
+    /// Up to date.
+    Idle,
+    /// Dirty, but throttled; ask again after this long.
+    Wait(Duration),
+    /// Regenerate these rows; `restart` is set on the first band of a
+    /// pass, when values shared by all bands (the color scale) are taken.
+    Rows {
+        /// Display rows to regenerate.
+        rows: Range<usize>,
+        /// Whether a new pass starts with this band.
+        restart: bool,
+    },
+}
+
+/// Refresh scheduler of one texture; see the module docs.
+#[derive(Debug, Default)]
+pub struct TextureRefresh {
+    /// Invalidated since the current pass started.
+    dirty: bool,
+    /// Next row of the running pass.
+    next_row: Option<usize>,
+    /// Start of the last pass.
+    pass_started: Option<Instant>,
+    /// Measured cost of regenerating one row.
+    row_cost: Option<Duration>,
+}
+
+impl TextureRefresh {
+    /// Mark the whole texture out of date.
+    pub fn invalidate(&mut self) {
+        self.dirty = true;
+    }
+
+    /// Whether no refresh is pending or running.
+    #[must_use]
+    pub fn is_idle(&self) -> bool {
+        !self.dirty && self.next_row.is_none()
+    }
+
+    /// Record that the whole texture was just regenerated at once.
+    pub fn complete(&mut self, now: Instant) {
+        self.dirty = false;
+        self.next_row = None;
+        self.pass_started = Some(now);
+    }
+
+    /// Work for this frame on a texture of `rows` display rows.
+    pub fn next_step(&mut self, now: Instant, busy: bool, rows: usize) -> RefreshStep {
+        let mut restart = false;
+        if self.dirty {
+            let since_pass = self.pass_started.map_or(Duration::MAX, |started| {
+                now.saturating_duration_since(started)
+            });
+            if busy && since_pass < BUSY_REFRESH_INTERVAL {
+                return RefreshStep::Wait(BUSY_REFRESH_INTERVAL.saturating_sub(since_pass));
+            }
+            // A running pass shows data that is out of date; start over.
+            self.dirty = false;
+            self.next_row = Some(0);
+            self.pass_started = Some(now);
+            restart = true;
+        }
+        let Some(start) = self.next_row.filter(|&start| start < rows) else {
+            self.next_row = None;
+            return RefreshStep::Idle;
+        };
+        let budget = if busy {
+            BUSY_FRAME_BUDGET
+        } else {
+            FRAME_BUDGET
+        };
+        let band = self.row_cost.map_or(FIRST_BAND_ROWS, |cost| {
+            let band = budget.as_nanos() / cost.as_nanos().max(1);
+            usize::try_from(band).unwrap_or(usize::MAX).max(1)
+        });
+        RefreshStep::Rows {
+            rows: start..start.saturating_add(band).min(rows),
+            restart,
+        }
+    }
+
+    /// Record that `rows` of a texture of `total` rows were regenerated in
+    /// `elapsed`.
+    pub fn rows_done(&mut self, rows: Range<usize>, total: usize, elapsed: Duration) {
+        let count = u32::try_from(rows.len()).unwrap_or(u32::MAX);
+        if count > 0 {
+            let cost = elapsed / count;
+            // Smooth over bands, which differ in how many pixels are empty.
+            self.row_cost = Some(self.row_cost.map_or(cost, |old| (old + cost) / 2));
+        }
+        if self.next_row == Some(rows.start) {
+            self.next_row = (rows.end < total).then_some(rows.end);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rows(step: &RefreshStep) -> Range<usize> {
+        match step {
+            RefreshStep::Rows { rows, .. } => rows.clone(),
+            other => panic!("expected rows, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_pass_is_split_into_budgeted_bands() {
+        let mut refresh = TextureRefresh::default();
+        let now = Instant::now();
+        assert_eq!(refresh.next_step(now, false, 100), RefreshStep::Idle);
+
+        refresh.invalidate();
+        refresh.invalidate();
+        let first = refresh.next_step(now, false, 100);
+        assert!(matches!(first, RefreshStep::Rows { restart: true, .. }));
+        assert_eq!(rows(&first), 0..FIRST_BAND_ROWS);
+        // 1 ms per row: 20 rows fit the frame budget.
+        refresh.rows_done(rows(&first), 100, Duration::from_millis(32));
+        let second = refresh.next_step(now, false, 100);
+        assert_eq!(
+            second,
+            RefreshStep::Rows {
+                rows: 32..52,
+                restart: false
+            }
+        );
+        refresh.rows_done(32..52, 100, Duration::from_millis(20));
+
+        // A change mid-pass restarts it once.
+        refresh.invalidate();
+        let restarted = refresh.next_step(now, false, 100);
+        assert_eq!(rows(&restarted), 0..20);
+        let mut done = 0;
+        let mut step = restarted;
+        while let RefreshStep::Rows { rows, .. } = step {
+            refresh.rows_done(rows.clone(), 100, Duration::from_millis(20));
+            done = rows.end;
+            step = refresh.next_step(now, false, 100);
+        }
+        assert_eq!(done, 100);
+        assert!(refresh.is_idle());
+    }
+
+    #[test]
+    fn test_busy_refreshes_are_throttled() {
+        let mut refresh = TextureRefresh::default();
+        let start = Instant::now();
+        refresh.complete(start);
+        refresh.invalidate();
+        let later = start + Duration::from_millis(100);
+        assert_eq!(
+            refresh.next_step(later, true, 10),
+            RefreshStep::Wait(Duration::from_millis(150))
+        );
+        // Interaction is not held back when nothing else runs.
+        assert!(matches!(
+            refresh.next_step(later, false, 10),
+            RefreshStep::Rows { restart: true, .. }
+        ));
+
+        refresh.rows_done(0..10, 10, Duration::from_millis(10));
+        refresh.invalidate();
+        let after = later + BUSY_REFRESH_INTERVAL;
+        let step = refresh.next_step(after, true, 10);
+        // 1 ms per row within the busy budget.
+        assert_eq!(
+            step,
+            RefreshStep::Rows {
+                rows: 0..8,
+                restart: true
+            }
+        );
+    }
+}
@@ -22,6 +22,8 @@ pub struct Roi {
     pub shape: RoiShape,
     pub visibility: RoiVisibility,
     pub selection: RoiSelection,
+    /// Inclusive TOF bin range of the ROI's gated thumbnail, if any.
+    pub tof_gate: Option<(usize, usize)>,
 }
 
 #[derive(Debug, Clone)]
@@ -143,6 +145,14 @@ impl RoiState {
         self.touch();
     }
 
+    /// Replace all ROIs (e.g. when recovering a session) and continue numbering after them.
+    pub fn restore(&mut self, rois: Vec<Roi>) {
+        self.clear();
+        self.next_id = rois.iter().map(|roi| roi.id).max().unwrap_or(0) + 1;
+        self.rois = rois;
+        self.touch();
+    }
+
     /// Delete the currently selected ROI.
     pub fn delete_selected(&mut self) -> bool {
         let Some(selected_id) = self
@@ -267,6 +277,7 @@ impl RoiState {
                 selected: false,
                 edit_mode: false,
             },
+            tof_gate: None,
         };
 
         self.rois.push(roi);
@@ -325,6 +336,7 @@ impl RoiState {
                 selected: false,
                 edit_mode: false,
             },
+            tof_gate: None,
         };
 
         self.rois.push(roi);
@@ -698,6 +710,26 @@ impl RoiState {
 }
 
 impl Roi {
+    /// Build a ROI with default visibility and selection state.
+    #[must_use]
+    pub fn new(id: usize, name: String, color: Color32, shape: RoiShape) -> Self {
+        Self {
+            id,
+            name,
+            color,
+            shape,
+            visibility: RoiVisibility {
+                visible: true,
+                spectrum_visible: true,
+            },
+            selection: RoiSelection {
+                selected: false,
+                edit_mode: false,
+            },
+            tof_gate: None,
+        }
+    }
+
     fn plot_points(&self) -> Vec<[f64; 2]> {
         match &self.shape {
             RoiShape::Rectangle { x1, y1, x2, y2 } => {
@@ -714,7 +746,7 @@ impl Roi {
         PlotPoint::new(min_x + 2.0, max_y - 2.0)
     }
 
-    fn bounds(&self) -> (f64, f64, f64, f64) {
+    pub(crate) fn bounds(&self) -> (f64, f64, f64, f64) {
         match &self.shape {
             RoiShape::Rectangle { x1, y1, x2, y2 } => {
                 let min_x = x1.min(*x2);
@@ -0,0 +1,204 @@
+//! ROI mask export for scripted analysis (`rustpix.load_roi_mask`).
+//!
+//! ROIs are written in data pixel coordinates (the view transform undone)
+//! either as a JSON shape list or as a PNG mask. Pixels are selected the same
+//! way as for ROI spectra: every pixel a rectangle overlaps, and every pixel
+//! whose center lies inside a polygon.
+
+use std::path::Path;
+
+use anyhow::Result;
+use image::GrayImage;
+
+use super::RoiShape;
+use crate::util::{f64_to_usize_bounded, usize_to_f64};
+
+/// Identifies the JSON layout for readers.
+const FORMAT: &str = "rustpix-roi-mask";
+
+/// ROIs over a data grid, ready to export.
+#[derive(Debug, Clone)]
+pub struct RoiMaskExport {
+    /// Grid width in data pixels.
+    pub width: usize,
+    /// Grid height in data pixels.
+    pub height: usize,
+    /// "hits" or "neutrons".
+    pub view: &'static str,
+    /// Data pixels per detector pixel.
+    pub super_resolution_factor: f64,
+    /// ROI names and shapes in data pixel coordinates.
+    pub rois: Vec<(String, RoiShape)>,
+}
+
+impl RoiMaskExport {
+    /// The JSON document for this export.
+    #[must_use]
+    pub fn to_json(&self) -> serde_json::Value {
+        let rois: Vec<serde_json::Value> = self
+            .rois
+            .iter()
+            .map(|(name, shape)| match shape {
+                RoiShape::Rectangle { x1, y1, x2, y2 } => serde_json::json!({
+                    "name": name,
+                    "kind": "rectangle",
+                    "x1": x1.min(*x2),
+                    "y1": y1.min(*y2),
+                    "x2": x1.max(*x2),
+                    "y2": y1.max(*y2),
+                }),
+                RoiShape::Polygon { vertices } => serde_json::json!({
+                    "name": name,
+                    "kind": "polygon",
+                    "vertices": vertices.iter().map(|&(x, y)| [x, y]).collect::<Vec<_>>(),
+                }),
+            })
+            .collect();
+        serde_json::json!({
+            "format": FORMAT,
+            "version": 1,
+            "width": self.width,
+            "height": self.height,
+            "view": self.view,
+            "super_resolution_factor": self.super_resolution_factor,
+            "rois": rois,
+        })
+    }
+
+    /// Pixels inside any ROI, laid out as `[y][x]`.
+    #[must_use]
+    pub fn rasterize(&self) -> Vec<bool> {
+        let mut mask = vec![false; self.width * self.height];
+        for (_, shape) in &self.rois {
+            match shape {
+                RoiShape::Rectangle { x1, y1, x2, y2 } => {
+                    let (x_start, x_end) = span(*x1, *x2, self.width);
+                    let (y_start, y_end) = span(*y1, *y2, self.height);
+                    for y in y_start..y_end {
+                        mask[y * self.width + x_start..y * self.width + x_end].fill(true);
+                    }
+                }
+                RoiShape::Polygon { vertices } => {
+                    let (min_x, max_x) = vertices
+                        .iter()
+                        .fold((f64::INFINITY, f64::NEG_INFINITY), |(lo, hi), &(x, _)| {
+                            (lo.min(x), hi.max(x))
+                        });
+                    let (min_y, max_y) = vertices
+                        .iter()
+                        .fold((f64::INFINITY, f64::NEG_INFINITY), |(lo, hi), &(_, y)| {
+                            (lo.min(y), hi.max(y))
+                        });
+                    let (x_start, x_end) = span(min_x, max_x, self.width);
+                    let (y_start, y_end) = span(min_y, max_y, self.height);
+                    for y in y_start..y_end {
+                        for x in x_start..x_end {
+                            let center = (usize_to_f64(x) + 0.5, usize_to_f64(y) + 0.5);
+                            if polygon_contains(center, vertices) {
+                                mask[y * self.width + x] = true;
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        mask
+    }
+
+    /// Write to `path`: a PNG mask (255 inside) for `.png`, JSON otherwise.
+    ///
+    /// # Errors
+    /// Returns an error if the file cannot be written.
+    pub fn write(&self, path: &Path) -> Result<()> {
+        let is_png = path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("png"));
+        if is_png {
+            let pixels = self
+                .rasterize()
+                .into_iter()
+                .map(|inside| if inside { 255 } else { 0 })
+                .collect();
+            let image = GrayImage::from_raw(
+                u32::try_from(self.width)?,
+                u32::try_from(self.height)?,
+                pixels,
+            )
+            .ok_or_else(|| anyhow::anyhow!("ROI mask size mismatch"))?;
+            image.save(path)?;
+        } else {
+            std::fs::write(path, serde_json::to_string_pretty(&self.to_json())?)?;
+        }
+        Ok(())
+    }
+}
+
+/// Pixel range `[start, end)` covered by `a..b`, clamped to `0..limit`.
+fn span(a: f64, b: f64, limit: usize) -> (usize, usize) {
+    let clamp = |value: f64| {
+        f64_to_usize_bounded(value.clamp(0.0, usize_to_f64(limit)), limit + 1).unwrap_or(0)
+    };
+    (clamp(a.min(b).floor()), clamp(a.max(b).ceil()))
+}
+
+fn polygon_contains((x, y): (f64, f64), vertices: &[(f64, f64)]) -> bool {
+    if vertices.len() < 3 {
+        return false;
+    }
+    let mut inside = false;
+    let mut j = vertices.len() - 1;
+    for (i, &(xi, yi)) in vertices.iter().enumerate() {
+        let (xj, yj) = vertices[j];
+        if (yi > y) != (yj > y) && x < (xj - xi) * (y - yi) / (yj - yi) + xi {
+            inside = !inside;
+        }
+        j = i;
+    }
+    inside
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rasterize_and_json() {
+        let export = RoiMaskExport {
+            width: 8,
+            height: 6,
+            view: "hits",
+            super_resolution_factor: 1.0,
+            rois: vec![
+                (
+                    "box".to_string(),
+                    RoiShape::Rectangle {
+                        x1: 2.5,
+                        y1: 1.0,
+                        x2: 0.5,
+                        y2: 2.0,
+                    },
+                ),
+                (
+                    "tri".to_string(),
+                    RoiShape::Polygon {
+                        vertices: vec![(4.0, 3.0), (8.0, 3.0), (8.0, 7.0)],
+                    },
+                ),
+            ],
+        };
+        let mask = export.rasterize();
+        let inside: Vec<usize> = (0..mask.len()).filter(|&i| mask[i]).collect();
+        // Rectangle covers x 0..3 on row 1; triangle keeps centers below the
+        // diagonal from (4, 3) to (8, 7).
+        assert_eq!(inside, vec![8, 9, 10, 28, 29, 30, 31, 37, 38, 39, 46, 47]);
+
+        let json = export.to_json();
+        assert_eq!(json["format"], FORMAT);
+        assert_eq!(json["rois"][0]["x1"], 0.5);
+        assert_eq!(
+            json["rois"][1]["vertices"][2],
+            serde_json::json!([8.0, 7.0])
+        );
+    }
+}
@@ -1,5 +1,7 @@
 //! Texture generation for histogram visualization.
 
+use std::ops::Range;
+
 use egui::ColorImage;
 
 use crate::state::ViewTransform;
@@ -18,11 +20,55 @@ pub fn generate_histogram_image_transformed(
     width: usize,
     height: usize,
     transform: ViewTransform,
-    colormap: Colormap,
+    colormap: &Colormap,
+    log_scale: bool,
+) -> ColorImage {
+    let max_count = counts.iter().max().copied().unwrap_or(1);
+    generate_histogram_image_scaled(
+        counts, width, height, transform, colormap, log_scale, max_count,
+    )
+}
+
+/// Generate a color image from hit counts, scaled so that `max_count` is
+/// the top of the colormap; brighter pixels saturate.
+#[must_use]
+pub fn generate_histogram_image_scaled(
+    counts: &[u64],
+    width: usize,
+    height: usize,
+    transform: ViewTransform,
+    colormap: &Colormap,
     log_scale: bool,
+    max_count: u64,
 ) -> ColorImage {
-    let max_count_u64 = counts.iter().max().copied().unwrap_or(1);
-    let max_count = u64_to_f32(max_count_u64.max(1));
+    let (_, disp_h) = transform.display_size(width.max(1), height.max(1));
+    generate_histogram_rows_scaled(
+        counts,
+        width,
+        height,
+        transform,
+        colormap,
+        log_scale,
+        max_count,
+        0..disp_h,
+    )
+}
+
+/// Generate display rows `rows` of the image of
+/// [`generate_histogram_image_scaled`], to update a texture in bands.
+#[must_use]
+#[allow(clippy::too_many_arguments)]
+pub fn generate_histogram_rows_scaled(
+    counts: &[u64],
+    width: usize,
+    height: usize,
+    transform: ViewTransform,
+    colormap: &Colormap,
+    log_scale: bool,
+    max_count: u64,
+    rows: Range<usize>,
+) -> ColorImage {
+    let max_count = u64_to_f32(max_count.max(1));
     let max_log = if log_scale {
         (max_count + 1.0).log10()
     } else {
@@ -30,12 +76,13 @@ pub fn generate_histogram_image_transformed(
     };
 
     let (disp_w, disp_h) = transform.display_size(width.max(1), height.max(1));
-    let pixel_count = disp_w.saturating_mul(disp_h);
+    let rows = rows.start.min(disp_h)..rows.end.min(disp_h);
+    let pixel_count = disp_w.saturating_mul(rows.len());
     let mut pixels = vec![0u8; pixel_count * 4];
 
-    for y in 0..disp_h {
+    for (row, y) in rows.clone().enumerate() {
         for x in 0..disp_w {
-            let idx = y * disp_w + x;
+            let idx = row * disp_w + x;
             let count = transform
                 .apply_inverse(x, y, width, height)
                 .and_then(|(sx, sy)| counts.get(sy * width + sx).copied())
@@ -48,7 +95,7 @@ pub fn generate_histogram_image_transformed(
                     let log_val = (u64_to_f32(count) + 1.0).log10() / max_log;
                     log_val.clamp(0.0, 1.0)
                 } else {
-                    (u64_to_f32(count) / max_count).sqrt()
+                    (u64_to_f32(count) / max_count).sqrt().min(1.0)
                 };
                 let rgba = colormap.apply(val);
                 let offset = idx * 4;
@@ -57,5 +104,49 @@ pub fn generate_histogram_image_transformed(
         }
     }
 
+    ColorImage::from_rgba_unmultiplied([disp_w, rows.len()], &pixels)
+}
+
+/// Generate a color image from floating-point values (e.g. ratio/difference maps).
+///
+/// Values are linearly normalized between the finite minimum and maximum;
+/// non-finite values are drawn as background.
+#[must_use]
+pub fn generate_float_image_transformed(
+    values: &[f32],
+    width: usize,
+    height: usize,
+    transform: ViewTransform,
+    colormap: &Colormap,
+) -> ColorImage {
+    let (min, max) = values
+        .iter()
+        .copied()
+        .filter(|v| v.is_finite())
+        .fold((f32::INFINITY, f32::NEG_INFINITY), |(lo, hi), v| {
+            (lo.min(v), hi.max(v))
+        });
+    let span = if max > min { max - min } else { 1.0 };
+
+    let (disp_w, disp_h) = transform.display_size(width.max(1), height.max(1));
+    let pixel_count = disp_w.saturating_mul(disp_h);
+    let mut pixels = vec![0u8; pixel_count * 4];
+
+    for y in 0..disp_h {
+        for x in 0..disp_w {
+            let offset = (y * disp_w + x) * 4;
+            let value = transform
+                .apply_inverse(x, y, width, height)
+                .and_then(|(sx, sy)| values.get(sy * width + sx).copied())
+                .unwrap_or(f32::NAN);
+            let rgba = if value.is_finite() {
+                colormap.apply(((value - min) / span).clamp(0.0, 1.0))
+            } else {
+                [0, 0, 0, 255]
+            };
+            pixels[offset..offset + 4].copy_from_slice(&rgba);
+        }
+    }
+
     ColorImage::from_rgba_unmultiplied([disp_w, disp_h], &pixels)
 }
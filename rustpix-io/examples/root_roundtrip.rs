@@ -0,0 +1,65 @@
+//! Writes `neutrons.root` and `hits.root` with known values, for
+//! `scripts/root_roundtrip.py` to read back with uproot.
+//!
+//! Usage: `cargo run -p rustpix-io --features root --example root_roundtrip -- <dir>`
+
+use rustpix_core::neutron::NeutronBatch;
+use rustpix_core::soa::HitBatch;
+use rustpix_io::{RootHitWriter, RootNeutronWriter};
+use std::path::PathBuf;
+
+/// More than two baskets of 8000 entries, ending in a partial one.
+const NEUTRONS: u32 = 20_003;
+const HITS: u32 = 16_001;
+
+/// Neutrons are written in two batches split mid-basket.
+const FIRST_BATCH: u32 = 12_345;
+
+fn neutrons(range: std::ops::Range<u32>) -> NeutronBatch {
+    let mut batch = NeutronBatch::default();
+    for i in range {
+        batch.x.push(f64::from(i) * 0.25);
+        batch.y.push(f64::from(i % 517) + 0.5);
+        batch.tof.push(i * 37);
+        batch.tot.push(u16::try_from(i % 1000).unwrap_or(0));
+        batch.n_hits.push(u16::try_from(1 + i % 9).unwrap_or(0));
+        batch.chip_id.push(u8::try_from(i % 4).unwrap_or(0));
+    }
+    batch
+}
+
+fn hits() -> HitBatch {
+    let mut batch = HitBatch::default();
+    for i in 0..HITS {
+        let x = u16::try_from(i % 514).unwrap_or(0);
+        let y = u16::try_from(i * 7 % 514).unwrap_or(0);
+        let tot = u16::try_from(i % 1024).unwrap_or(0);
+        let chip_id = u8::try_from(i % 4).unwrap_or(0);
+        batch.push((x, y, i * 11, tot, i, chip_id));
+        // Clusters of three consecutive hits; every tenth hit stays
+        // unclustered.
+        if i % 10 != 9 {
+            if let Some(cluster_id) = batch.cluster_id.last_mut() {
+                *cluster_id = i32::try_from(i / 3).unwrap_or(0);
+            }
+        }
+    }
+    batch
+}
+
+fn main() -> rustpix_io::Result<()> {
+    let dir = PathBuf::from(std::env::args().nth(1).unwrap_or_else(|| ".".to_string()));
+    std::fs::create_dir_all(&dir)?;
+
+    let mut writer = RootNeutronWriter::create(dir.join("neutrons.root"))?;
+    writer.write_neutrons(&neutrons(0..FIRST_BATCH))?;
+    writer.write_neutrons(&neutrons(FIRST_BATCH..NEUTRONS))?;
+    writer.finish()?;
+
+    let mut writer = RootHitWriter::create(dir.join("hits.root"))?;
+    writer.write_hits(&hits())?;
+    writer.finish()?;
+
+    println!("wrote {}", dir.display());
+    Ok(())
+}
@@ -0,0 +1,373 @@
+//! Apache Arrow record batches and IPC files for hits and neutrons.
+//!
+//! Columns and units follow the Parquet export (TOF and timestamps in 25 ns
+//! ticks, neutron coordinates in super-resolution space) but keep the
+//! unsigned types of the in-memory batches. Converting an owned batch moves
+//! its column buffers into the record batch without copying, so they can be
+//! handed to pyarrow or Polars as they are.
+
+use crate::Result;
+use arrow_array::{
+    ArrayRef, Float64Array, Int32Array, RecordBatch, UInt16Array, UInt32Array, UInt8Array,
+};
+use arrow_ipc::writer::FileWriter;
+use arrow_schema::{DataType, Field, Schema, SchemaRef};
+use rustpix_core::features::ClusterFeatures;
+use rustpix_core::neutron::NeutronBatch;
+use rustpix_core::soa::HitBatch;
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+use std::sync::Arc;
+
+/// Schema of hit record batches; `cluster_id` is -1 for hits that were not
+/// clustered, and the `raw_*` columns are null unless raw hit fields were
+/// kept.
+#[must_use]
+pub fn hit_schema() -> SchemaRef {
+    Arc::new(Schema::new(vec![
+        Field::new("x", DataType::UInt16, false),
+        Field::new("y", DataType::UInt16, false),
+        Field::new("tof", DataType::UInt32, false),
+        Field::new("tot", DataType::UInt16, false),
+        Field::new("timestamp", DataType::UInt32, false),
+        Field::new("chip_id", DataType::UInt8, false),
+        Field::new("cluster_id", DataType::Int32, false),
+        Field::new("raw_x", DataType::UInt16, true),
+        Field::new("raw_y", DataType::UInt16, true),
+        Field::new("raw_toa", DataType::UInt32, true),
+    ]))
+}
+
+/// Schema of neutron record batches; `energy_kev` is null without a `ToT`
+/// calibration, and the cluster shape columns after it are null unless
+/// extraction computed cluster features.
+#[must_use]
+pub fn neutron_schema() -> SchemaRef {
+    Arc::new(Schema::new(vec![
+        Field::new("x", DataType::Float64, false),
+        Field::new("y", DataType::Float64, false),
+        Field::new("tof", DataType::UInt32, false),
+        Field::new("tot", DataType::UInt16, false),
+        Field::new("n_hits", DataType::UInt16, false),
+        Field::new("chip_id", DataType::UInt8, false),
+        Field::new("energy_kev", DataType::Float64, true),
+        Field::new("rms_radius", DataType::Float64, true),
+        Field::new("tot_weighted_rms_radius", DataType::Float64, true),
+        Field::new("major_axis", DataType::Float64, true),
+        Field::new("minor_axis", DataType::Float64, true),
+        Field::new("eccentricity", DataType::Float64, true),
+        Field::new("time_spread", DataType::UInt32, true),
+    ]))
+}
+
+/// Convert hits to a record batch, moving the column buffers.
+///
+/// # Errors
+/// Returns an error if the columns have different lengths.
+pub fn hit_record_batch(batch: HitBatch) -> Result<RecordBatch> {
+    let len = batch.len();
+    let HitBatch {
+        x,
+        y,
+        tof,
+        tot,
+        timestamp,
+        chip_id,
+        mut cluster_id,
+        raw_x,
+        raw_y,
+        raw_toa,
+    } = batch;
+    if cluster_id.len() != len {
+        cluster_id = vec![-1; len];
+    }
+    let (raw_x, raw_y, raw_toa) = if raw_toa.len() == len {
+        (
+            UInt16Array::from(raw_x),
+            UInt16Array::from(raw_y),
+            UInt32Array::from(raw_toa),
+        )
+    } else {
+        (
+            UInt16Array::new_null(len),
+            UInt16Array::new_null(len),
+            UInt32Array::new_null(len),
+        )
+    };
+    let columns: Vec<ArrayRef> = vec![
+        Arc::new(UInt16Array::from(x)),
+        Arc::new(UInt16Array::from(y)),
+        Arc::new(UInt32Array::from(tof)),
+        Arc::new(UInt16Array::from(tot)),
+        Arc::new(UInt32Array::from(timestamp)),
+        Arc::new(UInt8Array::from(chip_id)),
+        Arc::new(Int32Array::from(cluster_id)),
+        Arc::new(raw_x),
+        Arc::new(raw_y),
+        Arc::new(raw_toa),
+    ];
+    Ok(RecordBatch::try_new(hit_schema(), columns)?)
+}
+
+/// Convert neutrons to a record batch, moving the column buffers.
+///
+/// # Errors
+/// Returns an error if the columns have different lengths.
+pub fn neutron_record_batch(batch: NeutronBatch) -> Result<RecordBatch> {
+    let len = batch.len();
+    let NeutronBatch {
+        x,
+        y,
+        tof,
+        tot,
+        n_hits,
+        chip_id,
+        energy_kev,
+        event_class: _,
+        features,
+        super_resolution_factor: _,
+    } = batch;
+    let energy_kev = if energy_kev.len() == len {
+        Float64Array::from(energy_kev)
+    } else {
+        Float64Array::new_null(len)
+    };
+    let with_features = features.len() == len;
+    let feature = |value: fn(&ClusterFeatures) -> f64| -> ArrayRef {
+        if with_features {
+            Arc::new(Float64Array::from_iter_values(features.iter().map(value)))
+        } else {
+            Arc::new(Float64Array::new_null(len))
+        }
+    };
+    let feature_columns = [
+        feature(|f| f.rms_radius),
+        feature(|f| f.tot_weighted_rms_radius),
+        feature(|f| f.major_axis),
+        feature(|f| f.minor_axis),
+        feature(|f| f.eccentricity),
+    ];
+    let time_spread = if with_features {
+        UInt32Array::from_iter_values(features.iter().map(|f| f.time_spread))
+    } else {
+        UInt32Array::new_null(len)
+    };
+    let mut columns: Vec<ArrayRef> = vec![
+        Arc::new(Float64Array::from(x)),
+        Arc::new(Float64Array::from(y)),
+        Arc::new(UInt32Array::from(tof)),
+        Arc::new(UInt16Array::from(tot)),
+        Arc::new(UInt16Array::from(n_hits)),
+        Arc::new(UInt8Array::from(chip_id)),
+        Arc::new(energy_kev),
+    ];
+    columns.extend(feature_columns);
+    columns.push(Arc::new(time_spread));
+    Ok(RecordBatch::try_new(neutron_schema(), columns)?)
+}
+
+/// Streaming Arrow IPC file writer for hits, including cluster labels.
+///
+/// Each non-empty batch becomes one record batch in the file. Call
+/// [`finish`](Self::finish) to write the file footer; a file dropped without
+/// it is not readable.
+pub struct ArrowIpcHitWriter {
+    writer: FileWriter<BufWriter<File>>,
+}
+
+impl ArrowIpcHitWriter {
+    /// Create a hit file at `path`.
+    ///
+    /// # Errors
+    /// Returns an error if the file cannot be created.
+    pub fn create<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Ok(Self {
+            writer: create_file(path.as_ref(), &hit_schema())?,
+        })
+    }
+
+    /// Append a batch.
+    ///
+    /// # Errors
+    /// Returns an error if writing fails.
+    pub fn write_hits(&mut self, batch: &HitBatch) -> Result<()> {
+        if batch.is_empty() {
+            return Ok(());
+        }
+        self.writer.write(&hit_record_batch(batch.clone())?)?;
+        Ok(())
+    }
+
+    /// Write the file footer; later writes fail.
+    ///
+    /// # Errors
+    /// Returns an error if writing fails.
+    pub fn finish(&mut self) -> Result<()> {
+        self.writer.finish()?;
+        Ok(())
+    }
+}
+
+/// Streaming Arrow IPC file writer for neutrons.
+///
+/// Each non-empty batch becomes one record batch in the file. Call
+/// [`finish`](Self::finish) to write the file footer; a file dropped without
+/// it is not readable.
+pub struct ArrowIpcNeutronWriter {
+    writer: FileWriter<BufWriter<File>>,
+}
+
+impl ArrowIpcNeutronWriter {
+    /// Create a neutron file at `path`.
+    ///
+    /// # Errors
+    /// Returns an error if the file cannot be created.
+    pub fn create<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Ok(Self {
+            writer: create_file(path.as_ref(), &neutron_schema())?,
+        })
+    }
+
+    /// Append a batch.
+    ///
+    /// # Errors
+    /// Returns an error if writing fails.
+    pub fn write_neutrons(&mut self, batch: &NeutronBatch) -> Result<()> {
+        if batch.is_empty() {
+            return Ok(());
+        }
+        self.writer.write(&neutron_record_batch(batch.clone())?)?;
+        Ok(())
+    }
+
+    /// Write the file footer; later writes fail.
+    ///
+    /// # Errors
+    /// Returns an error if writing fails.
+    pub fn finish(&mut self) -> Result<()> {
+        self.writer.finish()?;
+        Ok(())
+    }
+}
+
+fn create_file(path: &Path, schema: &Schema) -> Result<FileWriter<BufWriter<File>>> {
+    Ok(FileWriter::try_new(
+        BufWriter::new(File::create(path)?),
+        schema,
+    )?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow_array::cast::AsArray;
+    use arrow_array::types::{Float64Type, Int32Type, UInt32Type};
+    use arrow_array::Array;
+    use arrow_ipc::reader::FileReader;
+    use rustpix_core::neutron::Neutron;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_hit_record_batch_moves_buffers() {
+        let mut batch = HitBatch::default();
+        for i in 0..3u16 {
+            batch.push((i, i + 1, 4_000_000_000 + u32::from(i), 10 * i, 7, 3));
+        }
+        batch.cluster_id = vec![0, 1, 1];
+        let tof_ptr = batch.tof.as_ptr();
+
+        let record_batch = hit_record_batch(batch).unwrap();
+        assert_eq!(record_batch.num_rows(), 3);
+        assert_eq!(record_batch.schema(), hit_schema());
+        let tof = record_batch.column(2).as_primitive::<UInt32Type>();
+        assert_eq!(tof.values().as_ptr(), tof_ptr);
+        assert_eq!(tof.value(2), 4_000_000_002);
+        let cluster_id = record_batch.column(6).as_primitive::<Int32Type>();
+        assert_eq!(cluster_id.values().as_ref(), &[0, 1, 1]);
+
+        let mut unlabelled = HitBatch::default();
+        unlabelled.push((9, 9, 1, 1, 1, 0));
+        unlabelled.cluster_id.clear();
+        let record_batch = hit_record_batch(unlabelled).unwrap();
+        let cluster_id = record_batch.column(6).as_primitive::<Int32Type>();
+        assert_eq!(cluster_id.values().as_ref(), &[-1]);
+        assert_eq!(record_batch.column(9).null_count(), 1);
+
+        let mut raw = HitBatch::default();
+        raw.push_with_raw((300, 9, 1, 1, 1, 1), (44, 9, 0x3FFF_FFFF));
+        let record_batch = hit_record_batch(raw).unwrap();
+        let raw_toa = record_batch.column(9).as_primitive::<UInt32Type>();
+        assert_eq!(raw_toa.null_count(), 0);
+        assert_eq!(raw_toa.value(0), 0x3FFF_FFFF);
+    }
+
+    #[test]
+    fn test_neutron_ipc_round_trip_with_optional_energy() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("neutrons.arrow");
+        let mut calibrated = NeutronBatch::default();
+        calibrated.push(Neutron::new(1.5, 2.5, 100, 20, 3, 1));
+        calibrated.energy_kev.push(450.0);
+        let mut uncalibrated = NeutronBatch::default();
+        uncalibrated.push(Neutron::new(3.0, 4.0, 200, 30, 2, 0));
+
+        let mut writer = ArrowIpcNeutronWriter::create(&path).unwrap();
+        writer.write_neutrons(&calibrated).unwrap();
+        writer.write_neutrons(&NeutronBatch::default()).unwrap();
+        writer.write_neutrons(&uncalibrated).unwrap();
+        writer.finish().unwrap();
+
+        let reader = FileReader::try_new(File::open(&path).unwrap(), None).unwrap();
+        assert_eq!(reader.schema(), neutron_schema());
+        let batches: Vec<RecordBatch> = reader.map(std::result::Result::unwrap).collect();
+        assert_eq!(batches.len(), 2);
+        let energy = batches[0].column(6).as_primitive::<Float64Type>();
+        assert_eq!(energy.value(0).to_bits(), 450.0f64.to_bits());
+        assert_eq!(batches[1].column(6).null_count(), 1);
+        assert_eq!(batches[0].column(9).null_count(), 1);
+        let tof = batches[1].column(2).as_primitive::<UInt32Type>();
+        assert_eq!(tof.value(0), 200);
+    }
+
+    #[test]
+    fn test_neutron_record_batch_with_features() {
+        let mut batch = NeutronBatch::default();
+        batch.push(Neutron::new(1.5, 2.5, 100, 20, 3, 1));
+        batch.features.push(ClusterFeatures {
+            major_axis: 2.0,
+            eccentricity: 0.5,
+            time_spread: 7,
+            ..ClusterFeatures::default()
+        });
+
+        let record_batch = neutron_record_batch(batch).unwrap();
+        let schema = record_batch.schema();
+        let major = record_batch
+            .column(schema.index_of("major_axis").unwrap())
+            .as_primitive::<Float64Type>();
+        assert_eq!(major.value(0).to_bits(), 2.0f64.to_bits());
+        let spread = record_batch
+            .column(schema.index_of("time_spread").unwrap())
+            .as_primitive::<UInt32Type>();
+        assert_eq!(spread.value(0), 7);
+        assert_eq!(record_batch.column(6).null_count(), 1);
+    }
+
+    #[test]
+    fn test_hit_ipc_round_trip() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("hits.arrow");
+        let mut batch = HitBatch::default();
+        batch.push((1, 2, 3, 4, 5, 6));
+
+        let mut writer = ArrowIpcHitWriter::create(&path).unwrap();
+        writer.write_hits(&batch).unwrap();
+        writer.write_hits(&batch).unwrap();
+        writer.finish().unwrap();
+
+        let reader = FileReader::try_new(File::open(&path).unwrap(), None).unwrap();
+        let rows: usize = reader.map(|batch| batch.unwrap().num_rows()).sum();
+        assert_eq!(rows, 2);
+    }
+}
@@ -0,0 +1,163 @@
+//! Portable binary record encoding for neutron output.
+//!
+//! All multi-byte fields are encoded explicitly as little-endian and records
+//! are packed byte-by-byte, so files are identical regardless of host
+//! endianness or struct layout. Note that the on-disk record (28 bytes) is
+//! smaller than the in-memory `Neutron` (32 bytes with `repr(C)` alignment
+//! padding); records must never be read or written by casting memory.
+
+use crate::{Error, Result};
+use rustpix_core::neutron::{Neutron, NeutronBatch};
+use std::io::{ErrorKind, Read};
+
+/// Size of one record in the rustpix binary neutron format.
+pub const NEUTRON_RECORD_BYTES: usize = 28;
+
+/// Size of one record in the legacy C++ event layout.
+pub const LEGACY_RECORD_BYTES: usize = 40;
+
+/// Encode a neutron as a rustpix binary record.
+///
+/// Layout: `f64` x, `f64` y, `u32` tof, `u16` tot, `u16` `n_hits`, `u8`
+/// `chip_id`, 3 reserved zero bytes; all little-endian.
+#[must_use]
+pub fn encode_neutron_record(
+    x: f64,
+    y: f64,
+    tof: u32,
+    tot: u16,
+    n_hits: u16,
+    chip_id: u8,
+) -> [u8; NEUTRON_RECORD_BYTES] {
+    let mut record = [0u8; NEUTRON_RECORD_BYTES];
+    record[0..8].copy_from_slice(&x.to_le_bytes());
+    record[8..16].copy_from_slice(&y.to_le_bytes());
+    record[16..20].copy_from_slice(&tof.to_le_bytes());
+    record[20..22].copy_from_slice(&tot.to_le_bytes());
+    record[22..24].copy_from_slice(&n_hits.to_le_bytes());
+    record[24] = chip_id;
+    record
+}
+
+/// Decode a rustpix binary record.
+#[must_use]
+pub fn decode_neutron_record(record: &[u8; NEUTRON_RECORD_BYTES]) -> Neutron {
+    Neutron::new(
+        f64::from_le_bytes(to_array(&record[0..8])),
+        f64::from_le_bytes(to_array(&record[8..16])),
+        u32::from_le_bytes(to_array(&record[16..20])),
+        u16::from_le_bytes(to_array(&record[20..22])),
+        u16::from_le_bytes(to_array(&record[22..24])),
+        record[24],
+    )
+}
+
+/// Encode a neutron in the legacy C++ event layout.
+///
+/// Layout: `f64` x, `f64` y (pixels), `f64` tof (ns), `f64` tot, `i32`
+/// `n_hits`, 4 bytes of struct padding; all little-endian.
+#[must_use]
+pub fn encode_legacy_record(
+    x_px: f64,
+    y_px: f64,
+    tof_ns: f64,
+    tot: f64,
+    n_hits: i32,
+) -> [u8; LEGACY_RECORD_BYTES] {
+    let mut record = [0u8; LEGACY_RECORD_BYTES];
+    record[0..8].copy_from_slice(&x_px.to_le_bytes());
+    record[8..16].copy_from_slice(&y_px.to_le_bytes());
+    record[16..24].copy_from_slice(&tof_ns.to_le_bytes());
+    record[24..32].copy_from_slice(&tot.to_le_bytes());
+    record[32..36].copy_from_slice(&n_hits.to_le_bytes());
+    record
+}
+
+/// Read all records of a rustpix binary neutron file.
+///
+/// # Errors
+/// Returns an error if reading fails or the stream ends mid-record.
+pub fn read_neutron_batch_binary<R: Read>(mut reader: R) -> Result<NeutronBatch> {
+    let mut batch = NeutronBatch::default();
+    let mut record = [0u8; NEUTRON_RECORD_BYTES];
+    loop {
+        match read_record(&mut reader, &mut record)? {
+            0 => return Ok(batch),
+            NEUTRON_RECORD_BYTES => batch.push(decode_neutron_record(&record)),
+            partial => {
+                return Err(Error::InvalidFormat(format!(
+                    "truncated neutron record after {} records ({partial} of \
+                     {NEUTRON_RECORD_BYTES} bytes)",
+                    batch.len()
+                )))
+            }
+        }
+    }
+}
+
+/// Fill `record`, returning the number of bytes read (short only at EOF).
+pub(crate) fn read_record<R: Read>(reader: &mut R, record: &mut [u8]) -> Result<usize> {
+    let mut filled = 0;
+    while filled < record.len() {
+        match reader.read(&mut record[filled..]) {
+            Ok(0) => break,
+            Ok(n) => filled += n,
+            Err(err) if err.kind() == ErrorKind::Interrupted => {}
+            Err(err) => return Err(err.into()),
+        }
+    }
+    Ok(filled)
+}
+
+pub(crate) fn to_array<const N: usize>(bytes: &[u8]) -> [u8; N] {
+    let mut out = [0u8; N];
+    out.copy_from_slice(bytes);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_neutron_record_golden_bytes() {
+        // Fixed expected bytes: must hold on little- and big-endian hosts.
+        let record = encode_neutron_record(1.5, -2.0, 0x0102_0304, 0x0506, 0x0708, 9);
+        let expected: [u8; NEUTRON_RECORD_BYTES] = [
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xF8, 0x3F, // 1.5
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xC0, // -2.0
+            0x04, 0x03, 0x02, 0x01, // tof
+            0x06, 0x05, // tot
+            0x08, 0x07, // n_hits
+            0x09, // chip_id
+            0x00, 0x00, 0x00, // reserved
+        ];
+        assert_eq!(record, expected);
+        assert_eq!(
+            decode_neutron_record(&expected),
+            Neutron::new(1.5, -2.0, 0x0102_0304, 0x0506, 0x0708, 9)
+        );
+    }
+
+    #[test]
+    fn test_legacy_record_golden_bytes() {
+        let record = encode_legacy_record(2.0, 3.0, 1000.0, 100.0, -2);
+        assert_eq!(&record[0..8], &[0, 0, 0, 0, 0, 0, 0x00, 0x40]);
+        assert_eq!(&record[32..36], &[0xFE, 0xFF, 0xFF, 0xFF]);
+        assert_eq!(&record[36..40], &[0; 4]);
+    }
+
+    #[test]
+    fn test_read_neutron_batch_binary_round_trip_and_truncation() {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(&encode_neutron_record(1.0, 2.0, 3, 4, 5, 6));
+        bytes.extend_from_slice(&encode_neutron_record(7.0, 8.0, 9, 10, 11, 12));
+        let batch = read_neutron_batch_binary(bytes.as_slice()).unwrap();
+        assert_eq!(batch.len(), 2);
+        assert_eq!(batch.x, vec![1.0, 7.0]);
+        assert_eq!(batch.chip_id, vec![6, 12]);
+
+        bytes.extend_from_slice(&[0u8; 5]);
+        assert!(read_neutron_batch_binary(bytes.as_slice()).is_err());
+    }
+}
@@ -0,0 +1,697 @@
+//! Chunked, checksummed output container for hits and neutrons.
+//!
+//! A container is a short file header followed by independent blocks. Each
+//! block holds one hit or neutron batch in columnar little-endian layout,
+//! optionally zstd-compressed, and carries CRC-32 checksums of both its
+//! header and stored payload. Because blocks are self-describing:
+//!
+//! - readers can index a file by walking block headers and decode only the
+//!   blocks they need (partial reads);
+//! - every block can be verified independently;
+//! - a writer interrupted mid-block leaves a valid prefix, which
+//!   [`ContainerWriter::resume`] truncates to before appending again.
+//!
+//! ```text
+//! file header  : "RPXC" | version u16 | reserved u16
+//! block header : "BLK1" | kind u8 | codec u8 | reserved u16 | records u32
+//!                | raw_len u32 | stored_len u32 | payload_crc u32 | header_crc u32
+//! block payload: stored_len bytes
+//! ```
+
+use crate::{Error, Result};
+use rustpix_core::neutron::NeutronBatch;
+use rustpix_core::soa::HitBatch;
+use std::fs::{File, OpenOptions};
+use std::io::{BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+
+const FILE_MAGIC: [u8; 4] = *b"RPXC";
+const FILE_VERSION: u16 = 1;
+const FILE_HEADER_BYTES: usize = 8;
+const BLOCK_MAGIC: [u8; 4] = *b"BLK1";
+const BLOCK_HEADER_BYTES: usize = 28;
+
+/// Bytes per hit in the uncompressed columnar payload.
+const HIT_BYTES: usize = 2 + 2 + 4 + 2 + 4 + 1 + 4;
+/// Bytes per neutron in the uncompressed columnar payload.
+const NEUTRON_BYTES: usize = 8 + 8 + 4 + 2 + 2 + 1;
+
+/// Payload type of a block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlockKind {
+    /// A `HitBatch`.
+    Hits,
+    /// A `NeutronBatch`.
+    Neutrons,
+}
+
+impl BlockKind {
+    fn code(self) -> u8 {
+        match self {
+            Self::Hits => 1,
+            Self::Neutrons => 2,
+        }
+    }
+
+    fn from_code(code: u8) -> Option<Self> {
+        match code {
+            1 => Some(Self::Hits),
+            2 => Some(Self::Neutrons),
+            _ => None,
+        }
+    }
+}
+
+/// Block payload compression.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Compression {
+    /// Store payloads uncompressed.
+    #[default]
+    None,
+    /// Compress payloads with zstd at the given level (requires the `zstd` feature).
+    Zstd(i32),
+}
+
+impl Compression {
+    fn code(self) -> u8 {
+        match self {
+            Self::None => 0,
+            Self::Zstd(_) => 1,
+        }
+    }
+}
+
+/// Location and metadata of one block.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlockInfo {
+    /// Byte offset of the block header.
+    pub offset: u64,
+    /// Payload type.
+    pub kind: BlockKind,
+    /// Whether the payload is zstd-compressed.
+    pub compressed: bool,
+    /// Number of hits or neutrons in the block.
+    pub records: u32,
+    /// Uncompressed payload size.
+    pub raw_len: u32,
+    /// Stored (possibly compressed) payload size.
+    pub stored_len: u32,
+    payload_crc: u32,
+}
+
+impl BlockInfo {
+    /// Offset one past the end of the block.
+    #[must_use]
+    pub fn end(&self) -> u64 {
+        self.offset + BLOCK_HEADER_BYTES as u64 + u64::from(self.stored_len)
+    }
+}
+
+/// Decoded block contents.
+#[derive(Debug, Clone, PartialEq)]
+pub enum BlockData {
+    /// Hit batch.
+    Hits(HitBatch),
+    /// Neutron batch.
+    Neutrons(NeutronBatch),
+}
+
+/// Outcome of verifying every block in a container.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct VerifyReport {
+    /// Blocks whose checksum and decoding succeeded.
+    pub valid_blocks: usize,
+    /// Indices of blocks that failed verification.
+    pub corrupt_blocks: Vec<usize>,
+    /// Bytes after the last complete block header/payload (e.g. an interrupted write).
+    pub trailing_bytes: u64,
+}
+
+impl VerifyReport {
+    /// Whether every block verified and no trailing data was found.
+    #[must_use]
+    pub fn is_clean(&self) -> bool {
+        self.corrupt_blocks.is_empty() && self.trailing_bytes == 0
+    }
+}
+
+/// Appends checksummed blocks to a container file.
+///
+/// Each block is flushed as soon as it is written, so an interrupted run
+/// loses at most the block in flight.
+pub struct ContainerWriter {
+    writer: BufWriter<File>,
+    compression: Compression,
+    blocks_written: usize,
+}
+
+impl ContainerWriter {
+    /// Create a new container, replacing any existing file.
+    ///
+    /// # Errors
+    /// Returns an error if the file cannot be created.
+    pub fn create<P: AsRef<Path>>(path: P, compression: Compression) -> Result<Self> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        writer.write_all(&FILE_MAGIC)?;
+        writer.write_all(&FILE_VERSION.to_le_bytes())?;
+        writer.write_all(&0u16.to_le_bytes())?;
+        writer.flush()?;
+        Ok(Self {
+            writer,
+            compression,
+            blocks_written: 0,
+        })
+    }
+
+    /// Reopen an existing container for appending.
+    ///
+    /// A block left incomplete by an interrupted write is truncated first.
+    /// Returns the writer together with the intact blocks, so callers can
+    /// skip work that is already on disk.
+    ///
+    /// # Errors
+    /// Returns an error if the file is not a container, cannot be reopened,
+    /// or has a damaged block header (truncating would discard later blocks).
+    pub fn resume<P: AsRef<Path>>(
+        path: P,
+        compression: Compression,
+    ) -> Result<(Self, Vec<BlockInfo>)> {
+        let path = path.as_ref();
+        let scan = {
+            let mut file = File::open(path)?;
+            read_file_header(&mut file)?;
+            scan_blocks(&mut file)?
+        };
+        if scan.damaged {
+            return Err(Error::InvalidFormat(format!(
+                "damaged block header at offset {}; refusing to truncate",
+                scan.valid_end
+            )));
+        }
+        let (blocks, valid_end) = (scan.blocks, scan.valid_end);
+        let file = OpenOptions::new().write(true).open(path)?;
+        file.set_len(valid_end)?;
+        let mut writer = BufWriter::new(file);
+        writer.seek(SeekFrom::End(0))?;
+        Ok((
+            Self {
+                writer,
+                compression,
+                blocks_written: blocks.len(),
+            },
+            blocks,
+        ))
+    }
+
+    /// Number of blocks in the container, including resumed ones.
+    #[must_use]
+    pub fn blocks_written(&self) -> usize {
+        self.blocks_written
+    }
+
+    /// Append a hit batch as one block.
+    ///
+    /// # Errors
+    /// Returns an error if encoding, compression, or writing fails.
+    pub fn write_hits(&mut self, batch: &HitBatch) -> Result<()> {
+        self.write_block(BlockKind::Hits, batch.len(), &encode_hits(batch))
+    }
+
+    /// Append a neutron batch as one block.
+    ///
+    /// # Errors
+    /// Returns an error if encoding, compression, or writing fails.
+    pub fn write_neutrons(&mut self, batch: &NeutronBatch) -> Result<()> {
+        self.write_block(BlockKind::Neutrons, batch.len(), &encode_neutrons(batch))
+    }
+
+    /// Flush and sync the container to disk.
+    ///
+    /// # Errors
+    /// Returns an error if flushing or syncing fails.
+    pub fn finish(mut self) -> Result<()> {
+        self.sync()
+    }
+
+    /// Flush and sync the blocks written so far, keeping the writer open.
+    ///
+    /// # Errors
+    /// Returns an error if flushing or syncing fails.
+    pub fn sync(&mut self) -> Result<()> {
+        self.writer.flush()?;
+        self.writer.get_ref().sync_all()?;
+        Ok(())
+    }
+
+    fn write_block(&mut self, kind: BlockKind, records: usize, raw: &[u8]) -> Result<()> {
+        let stored = compress(self.compression, raw)?;
+        let records = to_u32(records, "record count")?;
+        let raw_len = to_u32(raw.len(), "block size")?;
+        let stored_len = to_u32(stored.len(), "block size")?;
+
+        let mut header = [0u8; BLOCK_HEADER_BYTES];
+        header[0..4].copy_from_slice(&BLOCK_MAGIC);
+        header[4] = kind.code();
+        header[5] = self.compression.code();
+        header[8..12].copy_from_slice(&records.to_le_bytes());
+        header[12..16].copy_from_slice(&raw_len.to_le_bytes());
+        header[16..20].copy_from_slice(&stored_len.to_le_bytes());
+        header[20..24].copy_from_slice(&crc32fast::hash(&stored).to_le_bytes());
+        let header_crc = crc32fast::hash(&header[..24]);
+        header[24..28].copy_from_slice(&header_crc.to_le_bytes());
+
+        self.writer.write_all(&header)?;
+        self.writer.write_all(&stored)?;
+        self.writer.flush()?;
+        self.blocks_written += 1;
+        Ok(())
+    }
+}
+
+/// Random-access reader over container blocks.
+pub struct ContainerReader {
+    file: File,
+    blocks: Vec<BlockInfo>,
+    trailing_bytes: u64,
+}
+
+impl ContainerReader {
+    /// Open a container and index its blocks from their headers.
+    ///
+    /// Payloads are not read until requested. Indexing stops at the first
+    /// damaged or incomplete block header.
+    ///
+    /// # Errors
+    /// Returns an error if the file cannot be read or is not a container.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let mut file = File::open(path)?;
+        read_file_header(&mut file)?;
+        let scan = scan_blocks(&mut file)?;
+        let trailing_bytes = file.metadata()?.len().saturating_sub(scan.valid_end);
+        Ok(Self {
+            file,
+            blocks: scan.blocks,
+            trailing_bytes,
+        })
+    }
+
+    /// Indexed blocks in file order.
+    #[must_use]
+    pub fn blocks(&self) -> &[BlockInfo] {
+        &self.blocks
+    }
+
+    /// Bytes after the last intact block (non-zero after an interrupted write).
+    #[must_use]
+    pub fn trailing_bytes(&self) -> u64 {
+        self.trailing_bytes
+    }
+
+    /// Read, verify, and decode one block.
+    ///
+    /// # Errors
+    /// Returns an error if the index is out of range, the checksum does not
+    /// match, or the payload cannot be decoded.
+    pub fn read_block(&mut self, index: usize) -> Result<BlockData> {
+        let info = self
+            .blocks
+            .get(index)
+            .cloned()
+            .ok_or_else(|| Error::InvalidFormat(format!("block {index} out of range")))?;
+        self.file
+            .seek(SeekFrom::Start(info.offset + BLOCK_HEADER_BYTES as u64))?;
+        let mut stored = vec![0u8; info.stored_len as usize];
+        self.file.read_exact(&mut stored)?;
+        if crc32fast::hash(&stored) != info.payload_crc {
+            return Err(Error::InvalidFormat(format!(
+                "block {index}: payload checksum mismatch"
+            )));
+        }
+        let raw = if info.compressed {
+            decompress(&stored, info.raw_len as usize)?
+        } else {
+            stored
+        };
+        if raw.len() != info.raw_len as usize {
+            return Err(Error::InvalidFormat(format!(
+                "block {index}: expected {} bytes, decoded {}",
+                info.raw_len,
+                raw.len()
+            )));
+        }
+        let records = info.records as usize;
+        match info.kind {
+            BlockKind::Hits => decode_hits(&raw, records).map(BlockData::Hits),
+            BlockKind::Neutrons => decode_neutrons(&raw, records).map(BlockData::Neutrons),
+        }
+        .ok_or_else(|| Error::InvalidFormat(format!("block {index}: payload size mismatch")))
+    }
+
+    /// Read every neutron block into a single batch.
+    ///
+    /// # Errors
+    /// Returns an error if any neutron block fails verification.
+    pub fn read_all_neutrons(&mut self) -> Result<NeutronBatch> {
+        let mut all = NeutronBatch::default();
+        for index in 0..self.blocks.len() {
+            if self.blocks[index].kind == BlockKind::Neutrons {
+                if let BlockData::Neutrons(batch) = self.read_block(index)? {
+                    all.append(&batch);
+                }
+            }
+        }
+        Ok(all)
+    }
+
+    /// Verify every block's checksum and payload.
+    ///
+    /// # Errors
+    /// Returns an error only for I/O failures; corrupt blocks are reported.
+    pub fn verify(&mut self) -> Result<VerifyReport> {
+        let mut report = VerifyReport {
+            trailing_bytes: self.trailing_bytes,
+            ..VerifyReport::default()
+        };
+        for index in 0..self.blocks.len() {
+            match self.read_block(index) {
+                Ok(_) => report.valid_blocks += 1,
+                Err(Error::Io(err)) => return Err(Error::Io(err)),
+                Err(_) => report.corrupt_blocks.push(index),
+            }
+        }
+        Ok(report)
+    }
+}
+
+fn read_file_header(file: &mut File) -> Result<()> {
+    let mut header = [0u8; FILE_HEADER_BYTES];
+    file.read_exact(&mut header)
+        .map_err(|_| Error::InvalidFormat("missing container header".to_string()))?;
+    if header[0..4] != FILE_MAGIC {
+        return Err(Error::InvalidFormat("not a rustpix container".to_string()));
+    }
+    let version = u16::from_le_bytes([header[4], header[5]]);
+    if version != FILE_VERSION {
+        return Err(Error::InvalidFormat(format!(
+            "unsupported container version {version}"
+        )));
+    }
+    Ok(())
+}
+
+/// Result of walking block headers.
+struct BlockScan {
+    /// Intact blocks in file order.
+    blocks: Vec<BlockInfo>,
+    /// End offset of the last intact block.
+    valid_end: u64,
+    /// Whether scanning stopped at a damaged header rather than a short tail.
+    damaged: bool,
+}
+
+/// Walk block headers until the end of file or the first bad block.
+fn scan_blocks(file: &mut File) -> Result<BlockScan> {
+    let file_len = file.metadata()?.len();
+    let mut blocks = Vec::new();
+    let mut offset = FILE_HEADER_BYTES as u64;
+    let mut header = [0u8; BLOCK_HEADER_BYTES];
+    let mut damaged = false;
+    while offset + BLOCK_HEADER_BYTES as u64 <= file_len {
+        file.seek(SeekFrom::Start(offset))?;
+        file.read_exact(&mut header)?;
+        let Some(info) = parse_block_header(&header, offset) else {
+            damaged = true;
+            break;
+        };
+        if info.end() > file_len {
+            break;
+        }
+        offset = info.end();
+        blocks.push(info);
+    }
+    Ok(BlockScan {
+        blocks,
+        valid_end: offset,
+        damaged,
+    })
+}
+
+fn parse_block_header(header: &[u8; BLOCK_HEADER_BYTES], offset: u64) -> Option<BlockInfo> {
+    let u32_at = |at: usize| {
+        u32::from_le_bytes([header[at], header[at + 1], header[at + 2], header[at + 3]])
+    };
+    if header[0..4] != BLOCK_MAGIC || crc32fast::hash(&header[..24]) != u32_at(24) {
+        return None;
+    }
+    let compressed = match header[5] {
+        0 => false,
+        1 => true,
+        _ => return None,
+    };
+    Some(BlockInfo {
+        offset,
+        kind: BlockKind::from_code(header[4])?,
+        compressed,
+        records: u32_at(8),
+        raw_len: u32_at(12),
+        stored_len: u32_at(16),
+        payload_crc: u32_at(20),
+    })
+}
+
+fn encode_hits(batch: &HitBatch) -> Vec<u8> {
+    let mut out = Vec::with_capacity(batch.len() * HIT_BYTES);
+    out.extend(batch.x.iter().flat_map(|v| v.to_le_bytes()));
+    out.extend(batch.y.iter().flat_map(|v| v.to_le_bytes()));
+    out.extend(batch.tof.iter().flat_map(|v| v.to_le_bytes()));
+    out.extend(batch.tot.iter().flat_map(|v| v.to_le_bytes()));
+    out.extend(batch.timestamp.iter().flat_map(|v| v.to_le_bytes()));
+    out.extend_from_slice(&batch.chip_id);
+    out.extend(batch.cluster_id.iter().flat_map(|v| v.to_le_bytes()));
+    out
+}
+
+fn encode_neutrons(batch: &NeutronBatch) -> Vec<u8> {
+    let mut out = Vec::with_capacity(batch.len() * NEUTRON_BYTES);
+    out.extend(batch.x.iter().flat_map(|v| v.to_le_bytes()));
+    out.extend(batch.y.iter().flat_map(|v| v.to_le_bytes()));
+    out.extend(batch.tof.iter().flat_map(|v| v.to_le_bytes()));
+    out.extend(batch.tot.iter().flat_map(|v| v.to_le_bytes()));
+    out.extend(batch.n_hits.iter().flat_map(|v| v.to_le_bytes()));
+    out.extend_from_slice(&batch.chip_id);
+    out
+}
+
+/// Sequential column decoder over a payload.
+struct Columns<'a> {
+    data: &'a [u8],
+    records: usize,
+}
+
+impl<'a> Columns<'a> {
+    fn take<const N: usize, T>(&mut self, decode: fn([u8; N]) -> T) -> Vec<T> {
+        let (column, rest) = self.data.split_at(self.records * N);
+        self.data = rest;
+        column
+            .chunks_exact(N)
+            .map(|chunk| {
+                let mut bytes = [0u8; N];
+                bytes.copy_from_slice(chunk);
+                decode(bytes)
+            })
+            .collect()
+    }
+
+    fn bytes(&mut self) -> &'a [u8] {
+        let (column, rest) = self.data.split_at(self.records);
+        self.data = rest;
+        column
+    }
+}
+
+fn decode_hits(raw: &[u8], records: usize) -> Option<HitBatch> {
+    if raw.len() != records.checked_mul(HIT_BYTES)? {
+        return None;
+    }
+    let mut cols = Columns { data: raw, records };
+    Some(HitBatch {
+        x: cols.take(u16::from_le_bytes),
+        y: cols.take(u16::from_le_bytes),
+        tof: cols.take(u32::from_le_bytes),
+        tot: cols.take(u16::from_le_bytes),
+        timestamp: cols.take(u32::from_le_bytes),
+        chip_id: cols.bytes().to_vec(),
+        cluster_id: cols.take(i32::from_le_bytes),
+        raw_x: Vec::new(),
+        raw_y: Vec::new(),
+        raw_toa: Vec::new(),
+    })
+}
+
+fn decode_neutrons(raw: &[u8], records: usize) -> Option<NeutronBatch> {
+    if raw.len() != records.checked_mul(NEUTRON_BYTES)? {
+        return None;
+    }
+    let mut cols = Columns { data: raw, records };
+    Some(NeutronBatch {
+        x: cols.take(f64::from_le_bytes),
+        y: cols.take(f64::from_le_bytes),
+        tof: cols.take(u32::from_le_bytes),
+        tot: cols.take(u16::from_le_bytes),
+        n_hits: cols.take(u16::from_le_bytes),
+        chip_id: cols.bytes().to_vec(),
+        energy_kev: Vec::new(),
+        event_class: Vec::new(),
+        features: Vec::new(),
+        super_resolution_factor: None,
+    })
+}
+
+fn to_u32(value: usize, what: &str) -> Result<u32> {
+    u32::try_from(value)
+        .map_err(|_| Error::InvalidFormat(format!("{what} {value} exceeds block limit")))
+}
+
+#[cfg(feature = "zstd")]
+fn compress(compression: Compression, raw: &[u8]) -> Result<Vec<u8>> {
+    match compression {
+        Compression::None => Ok(raw.to_vec()),
+        Compression::Zstd(level) => Ok(zstd::bulk::compress(raw, level)?),
+    }
+}
+
+#[cfg(not(feature = "zstd"))]
+fn compress(compression: Compression, raw: &[u8]) -> Result<Vec<u8>> {
+    match compression {
+        Compression::None => Ok(raw.to_vec()),
+        Compression::Zstd(_) => Err(Error::InvalidFormat(
+            "zstd compression requires the `zstd` feature".to_string(),
+        )),
+    }
+}
+
+#[cfg(feature = "zstd")]
+fn decompress(stored: &[u8], raw_len: usize) -> Result<Vec<u8>> {
+    Ok(zstd::bulk::decompress(stored, raw_len)?)
+}
+
+#[cfg(not(feature = "zstd"))]
+fn decompress(_stored: &[u8], _raw_len: usize) -> Result<Vec<u8>> {
+    Err(Error::InvalidFormat(
+        "zstd-compressed block requires the `zstd` feature".to_string(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rustpix_core::neutron::Neutron;
+    use tempfile::NamedTempFile;
+
+    fn sample_neutrons(offset: u32) -> NeutronBatch {
+        let mut batch = NeutronBatch::default();
+        batch.push(Neutron::new(1.5, 2.5, 1000 + offset, 100, 5, 0));
+        batch.push(Neutron::new(10.25, 20.75, 2000 + offset, 200, 8, 3));
+        batch
+    }
+
+    fn sample_hits() -> HitBatch {
+        let mut batch = HitBatch::default();
+        batch.push((10, 20, 300, 40, 5000, 1));
+        batch.push((11, 21, 301, 41, 5001, 2));
+        batch.cluster_id[1] = 7;
+        batch
+    }
+
+    #[test]
+    fn test_container_round_trip_and_partial_read() {
+        let file = NamedTempFile::new().unwrap();
+        let mut writer = ContainerWriter::create(file.path(), Compression::None).unwrap();
+        writer.write_hits(&sample_hits()).unwrap();
+        writer.write_neutrons(&sample_neutrons(0)).unwrap();
+        writer.write_neutrons(&sample_neutrons(10)).unwrap();
+        writer.finish().unwrap();
+
+        let mut reader = ContainerReader::open(file.path()).unwrap();
+        assert_eq!(reader.blocks().len(), 3);
+        assert_eq!(reader.blocks()[0].kind, BlockKind::Hits);
+        assert_eq!(
+            reader.read_block(2).unwrap(),
+            BlockData::Neutrons(sample_neutrons(10))
+        );
+        assert_eq!(
+            reader.read_block(0).unwrap(),
+            BlockData::Hits(sample_hits())
+        );
+        assert_eq!(reader.read_all_neutrons().unwrap().len(), 4);
+        assert!(reader.verify().unwrap().is_clean());
+    }
+
+    #[test]
+    fn test_container_detects_corruption() {
+        let file = NamedTempFile::new().unwrap();
+        let mut writer = ContainerWriter::create(file.path(), Compression::None).unwrap();
+        writer.write_neutrons(&sample_neutrons(0)).unwrap();
+        writer.write_neutrons(&sample_neutrons(10)).unwrap();
+        writer.finish().unwrap();
+
+        let mut bytes = std::fs::read(file.path()).unwrap();
+        let payload_start = FILE_HEADER_BYTES + BLOCK_HEADER_BYTES;
+        bytes[payload_start] ^= 0xFF;
+        std::fs::write(file.path(), &bytes).unwrap();
+
+        let mut reader = ContainerReader::open(file.path()).unwrap();
+        let report = reader.verify().unwrap();
+        assert_eq!(report.valid_blocks, 1);
+        assert_eq!(report.corrupt_blocks, vec![0]);
+        assert!(reader.read_block(0).is_err());
+    }
+
+    #[test]
+    fn test_container_resume_truncates_partial_block() {
+        let file = NamedTempFile::new().unwrap();
+        let mut writer = ContainerWriter::create(file.path(), Compression::None).unwrap();
+        writer.write_neutrons(&sample_neutrons(0)).unwrap();
+        writer.write_neutrons(&sample_neutrons(10)).unwrap();
+        writer.finish().unwrap();
+
+        // Simulate a crash mid-way through the second block.
+        let len = std::fs::metadata(file.path()).unwrap().len();
+        let handle = OpenOptions::new().write(true).open(file.path()).unwrap();
+        handle.set_len(len - 7).unwrap();
+        drop(handle);
+        assert_eq!(
+            ContainerReader::open(file.path()).unwrap().blocks().len(),
+            1
+        );
+
+        let (mut writer, blocks) = ContainerWriter::resume(file.path(), Compression::None).unwrap();
+        assert_eq!(blocks.len(), 1);
+        writer.write_neutrons(&sample_neutrons(20)).unwrap();
+        writer.finish().unwrap();
+
+        let mut reader = ContainerReader::open(file.path()).unwrap();
+        assert_eq!(reader.blocks().len(), 2);
+        assert!(reader.verify().unwrap().is_clean());
+        assert_eq!(
+            reader.read_block(1).unwrap(),
+            BlockData::Neutrons(sample_neutrons(20))
+        );
+    }
+
+    #[cfg(feature = "zstd")]
+    #[test]
+    fn test_container_zstd_round_trip() {
+        let file = NamedTempFile::new().unwrap();
+        let mut writer = ContainerWriter::create(file.path(), Compression::Zstd(3)).unwrap();
+        writer.write_neutrons(&sample_neutrons(0)).unwrap();
+        writer.finish().unwrap();
+
+        let mut reader = ContainerReader::open(file.path()).unwrap();
+        assert!(reader.blocks()[0].compressed);
+        assert_eq!(
+            reader.read_block(0).unwrap(),
+            BlockData::Neutrons(sample_neutrons(0))
+        );
+    }
+}
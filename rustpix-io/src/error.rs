@@ -28,4 +28,29 @@ pub enum Error {
     #[cfg(feature = "hdf5")]
     #[error("hdf5 error: {0}")]
     Hdf5(#[from] hdf5::Error),
+
+    /// Arrow error.
+    #[cfg(feature = "arrow")]
+    #[error("arrow error: {0}")]
+    Arrow(#[from] arrow_schema::ArrowError),
+
+    /// Parquet error.
+    #[cfg(feature = "parquet")]
+    #[error("parquet error: {0}")]
+    Parquet(#[from] parquet::errors::ParquetError),
+
+    /// Object storage error.
+    #[cfg(feature = "object-store")]
+    #[error("object store error: {0}")]
+    ObjectStore(String),
+
+    /// `ZeroMQ` error.
+    #[cfg(feature = "zmq")]
+    #[error("zmq error: {0}")]
+    Zmq(#[from] zmq::Error),
+
+    /// Kafka client or broker error.
+    #[cfg(feature = "kafka")]
+    #[error("kafka error: {0}")]
+    Kafka(#[from] rdkafka::error::KafkaError),
 }
@@ -0,0 +1,335 @@
+//! FITS images of neutron counts.
+//!
+//! [`write_fits_image`] writes a 2D projection and [`write_fits_cube`] a 3D
+//! TOF hyperstack as the primary HDU of a FITS file, as unsigned 32-bit
+//! integers (`BITPIX = 32` with `BZERO = 2147483648`). Axes are X (fastest),
+//! Y and TOF, matching the `[tof][y][x]` layout of [`TofHistogram`], and carry
+//! WCS keywords (`CTYPEn`, `CUNITn`, `CRPIXn`, `CRVALn`, `CDELTn`) for the
+//! pixel pitch and TOF bin width, so astronomy tooling (astropy, DS9) shows
+//! physical coordinates. Acquisition metadata is added as extra header cards.
+//!
+//! [`TofHistogram`]: crate::TofHistogram
+
+use crate::{Error, Result};
+use std::fmt::Write as _;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// FITS block size; headers and data are padded to a multiple of it.
+const BLOCK: usize = 2880;
+
+/// Length of one header card.
+const CARD: usize = 80;
+
+/// Timepix pixel pitch in millimetres.
+pub const TIMEPIX_PIXEL_SIZE_MM: f64 = 0.055;
+
+/// Value of an extra header card.
+#[derive(Debug, Clone, PartialEq)]
+pub enum FitsValue {
+    /// Quoted string (at most 68 characters are kept).
+    Str(String),
+    /// Integer.
+    Int(i64),
+    /// Floating-point number.
+    Float(f64),
+    /// Logical `T`/`F`.
+    Bool(bool),
+}
+
+/// Axis calibration and metadata written to the FITS header.
+#[derive(Debug, Clone)]
+pub struct FitsOptions {
+    /// Pixel pitch in millimetres (`CDELT1`/`CDELT2`).
+    pub pixel_size_mm: f64,
+    /// Width of a TOF bin in nanoseconds (`CDELT3` of cubes, in seconds).
+    pub tof_bin_width_ns: f64,
+    /// TOF of the lower edge of the first bin in nanoseconds.
+    pub tof_offset_ns: f64,
+    /// Extra cards as `(keyword, value, comment)`; keywords are upper-cased
+    /// and cut to 8 characters.
+    pub cards: Vec<(String, FitsValue, String)>,
+}
+
+impl Default for FitsOptions {
+    fn default() -> Self {
+        Self {
+            pixel_size_mm: TIMEPIX_PIXEL_SIZE_MM,
+            tof_bin_width_ns: 25.0,
+            tof_offset_ns: 0.0,
+            cards: Vec::new(),
+        }
+    }
+}
+
+impl FitsOptions {
+    /// Adds an extra header card.
+    #[must_use]
+    pub fn with_card(mut self, keyword: &str, value: FitsValue, comment: &str) -> Self {
+        self.cards
+            .push((keyword.to_string(), value, comment.to_string()));
+        self
+    }
+}
+
+/// Writes a `width` × `height` image (row-major counts) as a 2D FITS file.
+///
+/// Counts above `u32::MAX` are clamped. Returns the file size in bytes.
+///
+/// # Errors
+/// Returns an error if `counts` does not match the size or writing fails.
+pub fn write_fits_image<P: AsRef<Path>>(
+    path: P,
+    counts: &[u64],
+    width: usize,
+    height: usize,
+    options: &FitsOptions,
+) -> Result<u64> {
+    if width * height == 0 || counts.len() != width * height {
+        return Err(Error::InvalidFormat(format!(
+            "{} counts do not make a {width}x{height} image",
+            counts.len()
+        )));
+    }
+    write_fits(path.as_ref(), counts, &[width, height], options)
+}
+
+/// Writes `[tof][y][x]` counts as a 3D FITS cube of `width` × `height`
+/// planes, one per TOF bin.
+///
+/// Counts above `u32::MAX` are clamped. Returns the file size in bytes.
+///
+/// # Errors
+/// Returns an error if `counts` is not a whole number of planes or writing
+/// fails.
+pub fn write_fits_cube<P: AsRef<Path>>(
+    path: P,
+    counts: &[u64],
+    width: usize,
+    height: usize,
+    options: &FitsOptions,
+) -> Result<u64> {
+    let pixels = width * height;
+    if pixels == 0 || counts.is_empty() || !counts.len().is_multiple_of(pixels) {
+        return Err(Error::InvalidFormat(format!(
+            "{} counts do not make whole {width}x{height} planes",
+            counts.len()
+        )));
+    }
+    write_fits(
+        path.as_ref(),
+        counts,
+        &[width, height, counts.len() / pixels],
+        options,
+    )
+}
+
+fn write_fits(path: &Path, counts: &[u64], axes: &[usize], options: &FitsOptions) -> Result<u64> {
+    let header = header(axes, options);
+    let mut writer = BufWriter::new(File::create(path)?);
+    writer.write_all(header.as_bytes())?;
+    for &count in counts {
+        // Unsigned values are stored offset by BZERO as signed integers.
+        let stored = u32::try_from(count).unwrap_or(u32::MAX) ^ 0x8000_0000;
+        writer.write_all(&stored.to_be_bytes())?;
+    }
+    let data_bytes = counts.len() * 4;
+    let padding = data_bytes.next_multiple_of(BLOCK) - data_bytes;
+    writer.write_all(&vec![0u8; padding])?;
+    writer.flush()?;
+    Ok((header.len() + data_bytes + padding) as u64)
+}
+
+/// Header of the primary HDU, padded to a whole block.
+fn header(axes: &[usize], options: &FitsOptions) -> String {
+    let mut header = String::new();
+    let mut card = |keyword: &str, value: &FitsValue, comment: &str| {
+        let value = match value {
+            FitsValue::Str(text) => {
+                let text: String = text.replace('\'', "''").chars().take(68).collect();
+                format!("{:<20}", format!("'{text:<8}'"))
+            }
+            FitsValue::Int(value) => format!("{value:>20}"),
+            FitsValue::Float(value) => format!("{:>20}", format_float(*value)),
+            FitsValue::Bool(value) => format!("{:>20}", if *value { "T" } else { "F" }),
+        };
+        let keyword: String = keyword.to_ascii_uppercase().chars().take(8).collect();
+        let mut line = format!("{keyword:<8}= {value}");
+        if !comment.is_empty() {
+            let _ = write!(line, " / {comment}");
+        }
+        let line: String = line
+            .chars()
+            .map(|c| {
+                if c.is_ascii() && !c.is_ascii_control() {
+                    c
+                } else {
+                    '?'
+                }
+            })
+            .take(CARD)
+            .collect();
+        let _ = write!(header, "{line:<CARD$}");
+    };
+    let int = |value: usize| FitsValue::Int(i64::try_from(value).unwrap_or(i64::MAX));
+    let text = |value: &str| FitsValue::Str(value.to_string());
+
+    card(
+        "SIMPLE",
+        &FitsValue::Bool(true),
+        "conforms to FITS standard",
+    );
+    card("BITPIX", &FitsValue::Int(32), "32-bit integers");
+    card("NAXIS", &int(axes.len()), "number of axes");
+    for (i, &len) in axes.iter().enumerate() {
+        card(&format!("NAXIS{}", i + 1), &int(len), "");
+    }
+    card("BZERO", &FitsValue::Int(1 << 31), "unsigned 32-bit counts");
+    card("BSCALE", &FitsValue::Int(1), "");
+    card("BUNIT", &text("counts"), "neutron counts per pixel");
+
+    // Pixel centers sit at integer detector coordinates.
+    let size = options.pixel_size_mm;
+    for (axis, name) in [(1, "X"), (2, "Y")] {
+        card(&format!("CTYPE{axis}"), &text(name), "detector axis");
+        card(&format!("CUNIT{axis}"), &text("mm"), "");
+        card(&format!("CRPIX{axis}"), &FitsValue::Float(1.0), "");
+        card(&format!("CRVAL{axis}"), &FitsValue::Float(0.0), "");
+        card(
+            &format!("CDELT{axis}"),
+            &FitsValue::Float(size),
+            "pixel pitch",
+        );
+    }
+    if axes.len() == 3 {
+        let width_s = options.tof_bin_width_ns / 1e9;
+        let first_center_s = (options.tof_offset_ns + options.tof_bin_width_ns / 2.0) / 1e9;
+        card("CTYPE3", &text("TOF"), "time of flight");
+        card("CUNIT3", &text("s"), "");
+        card("CRPIX3", &FitsValue::Float(1.0), "");
+        card(
+            "CRVAL3",
+            &FitsValue::Float(first_center_s),
+            "center of first bin",
+        );
+        card("CDELT3", &FitsValue::Float(width_s), "TOF bin width");
+    }
+    card("PIXSIZE", &FitsValue::Float(size), "pixel pitch [mm]");
+    card(
+        "TOFBIN",
+        &FitsValue::Float(options.tof_bin_width_ns),
+        "TOF bin width [ns]",
+    );
+    card(
+        "TOFOFFS",
+        &FitsValue::Float(options.tof_offset_ns),
+        "TOF of first bin edge [ns]",
+    );
+    card("ORIGIN", &text("rustpix"), "");
+    card("DATE", &text(&utc_now()), "file creation date (UTC)");
+    for (keyword, value, comment) in &options.cards {
+        card(keyword, value, comment);
+    }
+
+    let _ = write!(header, "{:<CARD$}", "END");
+    let padded = header.len().next_multiple_of(BLOCK);
+    format!("{header:<padded$}")
+}
+
+/// Free-format real with a decimal point and an upper-case exponent, as
+/// FITS requires (`0.055`, `1.0E-7`).
+fn format_float(value: f64) -> String {
+    if !value.is_finite() {
+        return "0.0".to_string();
+    }
+    let text = format!("{value:?}");
+    match text.split_once('e') {
+        Some((mantissa, exponent)) if mantissa.contains('.') => format!("{mantissa}E{exponent}"),
+        Some((mantissa, exponent)) => format!("{mantissa}.0E{exponent}"),
+        None => text,
+    }
+}
+
+/// The current UTC time as `YYYY-MM-DDThh:mm:ss`.
+pub(crate) fn utc_now() -> String {
+    let [year, month, day, hour, minute, second] = utc_now_fields();
+    format!("{year:04}-{month:02}-{day:02}T{hour:02}:{minute:02}:{second:02}")
+}
+
+/// The current UTC year, month, day, hour, minute and second.
+pub(crate) fn utc_now_fields() -> [i64; 6] {
+    let secs = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |since| since.as_secs());
+    let days = i64::try_from(secs / 86_400).unwrap_or(0);
+    let rest = i64::try_from(secs % 86_400).unwrap_or(0);
+    // Civil date from days since 1970-01-01 (Howard Hinnant's algorithm).
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    [year, month, day, rest / 3600, rest / 60 % 60, rest % 60]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn card_value<'a>(header: &'a str, keyword: &str) -> Option<&'a str> {
+        header
+            .as_bytes()
+            .chunks(CARD)
+            .map(|card| std::str::from_utf8(card).unwrap())
+            .find(|card| card[..8].trim_end() == keyword)
+            .map(|card| card[10..].split(" / ").next().unwrap().trim())
+    }
+
+    #[test]
+    fn test_fits_cube_layout() {
+        let file = tempfile::NamedTempFile::new().unwrap();
+        let counts: Vec<u64> = vec![0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, u64::MAX];
+        let options = FitsOptions {
+            tof_bin_width_ns: 100.0,
+            ..FitsOptions::default()
+        }
+        .with_card("filename", FitsValue::Str("run_042.tpx3".into()), "input");
+        let bytes = write_fits_cube(file.path(), &counts, 3, 2, &options).unwrap();
+
+        let data = std::fs::read(file.path()).unwrap();
+        assert_eq!(bytes, data.len() as u64);
+        assert_eq!(data.len() % BLOCK, 0);
+        let header = std::str::from_utf8(&data[..BLOCK]).unwrap();
+        assert_eq!(card_value(header, "SIMPLE"), Some("T"));
+        assert_eq!(card_value(header, "NAXIS"), Some("3"));
+        assert_eq!(card_value(header, "NAXIS1"), Some("3"));
+        assert_eq!(card_value(header, "NAXIS3"), Some("2"));
+        assert_eq!(card_value(header, "CTYPE3"), Some("'TOF     '"));
+        assert_eq!(card_value(header, "CDELT3"), Some("1.0E-7"));
+        assert_eq!(card_value(header, "CDELT1"), Some("0.055"));
+        assert_eq!(card_value(header, "FILENAME"), Some("'run_042.tpx3'"));
+        assert!(header.contains(&format!("{:<80}", "END")));
+
+        let value = |i: usize| {
+            let raw =
+                i32::from_be_bytes(data[BLOCK + i * 4..BLOCK + i * 4 + 4].try_into().unwrap());
+            i64::from(raw) + (1 << 31)
+        };
+        assert_eq!(value(0), 0);
+        assert_eq!(value(7), 7);
+        assert_eq!(value(11), i64::from(u32::MAX));
+
+        assert!(write_fits_image(file.path(), &counts, 3, 2, &options).is_err());
+        write_fits_image(file.path(), &counts[..6], 3, 2, &options).unwrap();
+        let data = std::fs::read(file.path()).unwrap();
+        let header = std::str::from_utf8(&data[..BLOCK]).unwrap();
+        assert_eq!(card_value(header, "NAXIS"), Some("2"));
+        assert_eq!(card_value(header, "CTYPE3"), None);
+    }
+}
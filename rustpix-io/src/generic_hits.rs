@@ -0,0 +1,288 @@
+//! Hits from generic event-mode detectors, read from CSV or HDF5.
+//!
+//! Clustering, extraction and histogramming only need a position, a time and
+//! an amplitude per hit, so detectors other than Timepix, and hit lists
+//! exported by other acquisition software, can use them. A file has one
+//! column each for `x`, `y` and `t` and, optionally, `amplitude`; by default
+//! these are found by name (`tof`/`time` and `tot`/`amp` are accepted as
+//! aliases), or [`HitColumns`] names them explicitly. Positions are pixel
+//! indices; times are converted to the 25 ns ticks used for TOF, and
+//! amplitudes are stored in the `ToT` column. A file is one acquisition with
+//! no TDC pulses, so its hits are returned as a single batch sorted by time.
+//!
+//! Hits are stored in a [`HitBatch`], whose positions and `ToT` are `u16`.
+//! Positions must therefore round to 0..=65535: negative coordinates, or
+//! those of detectors wider than 65536 pixels, are rejected rather than
+//! wrapped, and have to be offset or rebinned before import. Times must fit
+//! 32-bit ticks (about 107 s), and amplitudes are clamped to 65535.
+//!
+//! CSV files have a header line naming the columns. HDF5 files hold one 1D
+//! dataset per column in a group (`read_generic_hdf5_hits`, with the `hdf5`
+//! feature).
+
+use crate::{Error, Result};
+use rustpix_core::soa::{HitBatch, HitRecord};
+use std::io::BufRead;
+use std::str::FromStr;
+
+/// Length of a TOF tick in nanoseconds.
+const TICK_NS: f64 = 25.0;
+
+/// Names recognised for each field when no [`HitColumns`] are given.
+const X_NAMES: &[&str] = &["x"];
+const Y_NAMES: &[&str] = &["y"];
+const T_NAMES: &[&str] = &["t", "time", "tof"];
+const AMPLITUDE_NAMES: &[&str] = &["amplitude", "amp", "tot"];
+
+/// Column names of the hit fields in an imported file.
+///
+/// Parsed from `field=column` pairs separated by commas, e.g.
+/// `x=col,y=row,t=timestamp,amplitude=adc`. Fields left out keep the
+/// default names `x`, `y` and `t`; without `amplitude`, hits get 0.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HitColumns {
+    /// Column of the x pixel index.
+    pub x: String,
+    /// Column of the y pixel index.
+    pub y: String,
+    /// Column of the hit time.
+    pub t: String,
+    /// Column of the amplitude, if any.
+    pub amplitude: Option<String>,
+}
+
+impl Default for HitColumns {
+    fn default() -> Self {
+        Self {
+            x: "x".to_string(),
+            y: "y".to_string(),
+            t: "t".to_string(),
+            amplitude: None,
+        }
+    }
+}
+
+impl FromStr for HitColumns {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let mut columns = Self::default();
+        for pair in s.split(',').map(str::trim).filter(|pair| !pair.is_empty()) {
+            let Some((field, column)) = pair.split_once('=') else {
+                return Err(format!("expected field=column, got {pair:?}"));
+            };
+            let column = column.trim().to_string();
+            match field.trim().to_ascii_lowercase().as_str() {
+                "x" => columns.x = column,
+                "y" => columns.y = column,
+                "t" => columns.t = column,
+                "amplitude" => columns.amplitude = Some(column),
+                other => {
+                    return Err(format!(
+                        "unknown field {other:?}; expected x, y, t or amplitude"
+                    ))
+                }
+            }
+        }
+        Ok(columns)
+    }
+}
+
+/// Units and columns of a generic hit file.
+#[derive(Clone, Debug, PartialEq)]
+pub struct GenericHitOptions {
+    /// Nanoseconds per unit of the `t` column.
+    pub time_unit_ns: f64,
+    /// Factor applied to `amplitude` before it is stored as `ToT`.
+    pub amplitude_scale: f64,
+    /// Column names; `None` finds the columns by their usual names.
+    pub columns: Option<HitColumns>,
+}
+
+impl Default for GenericHitOptions {
+    fn default() -> Self {
+        Self {
+            time_unit_ns: 1.0,
+            amplitude_scale: 1.0,
+            columns: None,
+        }
+    }
+}
+
+/// Positions of the hit fields among a file's columns.
+pub(crate) struct ColumnIndices {
+    pub(crate) x: usize,
+    pub(crate) y: usize,
+    pub(crate) t: usize,
+    pub(crate) amplitude: Option<usize>,
+}
+
+impl GenericHitOptions {
+    /// Locates the hit fields among `names`, ignoring case.
+    pub(crate) fn find_columns(&self, names: &[String]) -> Result<ColumnIndices> {
+        let find = |wanted: &[&str]| {
+            names
+                .iter()
+                .position(|name| wanted.iter().any(|w| name.eq_ignore_ascii_case(w)))
+        };
+        let Some(columns) = &self.columns else {
+            let (Some(x), Some(y), Some(t)) = (find(X_NAMES), find(Y_NAMES), find(T_NAMES)) else {
+                return Err(Error::InvalidFormat(
+                    "hit columns must include x, y and t".to_string(),
+                ));
+            };
+            return Ok(ColumnIndices {
+                x,
+                y,
+                t,
+                amplitude: find(AMPLITUDE_NAMES),
+            });
+        };
+        let require = |name: &str| {
+            find(&[name])
+                .ok_or_else(|| Error::InvalidFormat(format!("no hit column named {name:?}")))
+        };
+        Ok(ColumnIndices {
+            x: require(&columns.x)?,
+            y: require(&columns.y)?,
+            t: require(&columns.t)?,
+            amplitude: columns.amplitude.as_deref().map(require).transpose()?,
+        })
+    }
+
+    /// Converts one row to a hit, or returns the name of the field that is
+    /// out of range.
+    ///
+    /// Positions are rounded to pixels and times to ticks; amplitudes are
+    /// scaled, rounded and clamped to the `u16` range.
+    pub(crate) fn hit(
+        &self,
+        x: f64,
+        y: f64,
+        t: f64,
+        amplitude: f64,
+    ) -> std::result::Result<HitRecord, &'static str> {
+        let pixel = |value: f64, name| {
+            let value = value.round();
+            if (0.0..=f64::from(u16::MAX)).contains(&value) {
+                #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+                Ok(value as u16)
+            } else {
+                Err(name)
+            }
+        };
+        let x = pixel(x, "x")?;
+        let y = pixel(y, "y")?;
+        let ticks = (t * self.time_unit_ns / TICK_NS).round();
+        if !(0.0..=f64::from(u32::MAX)).contains(&ticks) {
+            return Err("t");
+        }
+        #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+        let tof = ticks as u32;
+        let amplitude = (amplitude * self.amplitude_scale)
+            .round()
+            .clamp(0.0, f64::from(u16::MAX));
+        #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+        let tot = amplitude as u16;
+        Ok((x, y, tof, tot, tof, 0))
+    }
+}
+
+/// Reads the hits of a generic `x, y, t, amplitude` CSV.
+///
+/// Rows without an amplitude column get 0. Blank lines are skipped.
+///
+/// # Errors
+/// Returns an error if the input cannot be read, the header lacks a
+/// required column, or a row is malformed or out of range.
+pub fn read_generic_csv_hits<R: BufRead>(
+    reader: R,
+    options: &GenericHitOptions,
+) -> Result<HitBatch> {
+    let mut lines = reader.lines();
+    let header = lines
+        .next()
+        .transpose()?
+        .ok_or_else(|| Error::InvalidFormat("empty event CSV".to_string()))?;
+    let names: Vec<String> = header
+        .split(',')
+        .map(|name| name.trim().to_string())
+        .collect();
+    let columns = options.find_columns(&names)?;
+
+    let mut batch = HitBatch::default();
+    for (line_no, line) in lines.enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+        let row = line_no + 2;
+        let value = |col: usize, name: &str| -> Result<f64> {
+            fields
+                .get(col)
+                .and_then(|field| field.parse::<f64>().ok())
+                .filter(|value| value.is_finite())
+                .ok_or_else(|| Error::InvalidFormat(format!("line {row}: malformed {name}")))
+        };
+        let amplitude = match columns.amplitude {
+            Some(col) => value(col, "amplitude")?,
+            None => 0.0,
+        };
+        let hit = options
+            .hit(
+                value(columns.x, "x")?,
+                value(columns.y, "y")?,
+                value(columns.t, "t")?,
+                amplitude,
+            )
+            .map_err(|name| Error::InvalidFormat(format!("line {row}: {name} out of range")))?;
+        batch.push(hit);
+    }
+    batch.sort_by_tof();
+    Ok(batch)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_generic_csv_hits() {
+        let csv = "T, X, Y, Amplitude\n250.0,10,20,7.6\n\n50,11.2,20,70000\n";
+        let options = GenericHitOptions::default();
+        let hits = read_generic_csv_hits(csv.as_bytes(), &options).unwrap();
+        assert_eq!(hits.x, vec![11, 10]);
+        assert_eq!(hits.y, vec![20, 20]);
+        assert_eq!(hits.tof, vec![2, 10]);
+        assert_eq!(hits.tot, vec![u16::MAX, 8]);
+
+        let err = read_generic_csv_hits("x,y\n1,2\n".as_bytes(), &options).unwrap_err();
+        assert!(err.to_string().contains("x, y and t"));
+        let err = read_generic_csv_hits("x,y,t\n-3,2,1\n".as_bytes(), &options).unwrap_err();
+        assert!(err.to_string().contains("line 2: x out of range"));
+    }
+
+    #[test]
+    fn test_column_mapping() {
+        let columns: HitColumns = "x=col, y=row, t=toa_us, amplitude=adc".parse().unwrap();
+        let options = GenericHitOptions {
+            time_unit_ns: 1000.0,
+            amplitude_scale: 0.5,
+            columns: Some(columns),
+        };
+        let csv = "event,row,col,toa_us,adc,tof\n0,3,4,1.5,9,7\n";
+        let hits = read_generic_csv_hits(csv.as_bytes(), &options).unwrap();
+        assert_eq!((hits.x[0], hits.y[0]), (4, 3));
+        assert_eq!(hits.tof, vec![60]);
+        assert_eq!(hits.tot, vec![5]);
+
+        let options = GenericHitOptions {
+            columns: Some("t=toa".parse().unwrap()),
+            ..GenericHitOptions::default()
+        };
+        let err = read_generic_csv_hits("x,y,t\n1,2,3\n".as_bytes(), &options).unwrap_err();
+        assert!(err.to_string().contains("no hit column named \"toa\""));
+        assert!("z=depth".parse::<HitColumns>().is_err());
+    }
+}
@@ -1,19 +1,21 @@
 //! HDF5/NeXus event I/O (`NXevent_data`).
 
 use crate::out_of_core::OutOfCoreConfig;
+use crate::readback::ProcessedData;
 use crate::reader::EventBatch;
-use crate::{Error, Result};
+use crate::{Error, GenericHitOptions, Result};
 use hdf5::types::{H5Type, VarLenUnicode};
 use hdf5::{Dataset, File, Group};
 use ndarray::{s, Array4, ArrayView, ArrayView1, ArrayView2, ArrayView4, Zip};
 use rustpix_core::neutron::NeutronBatch;
+use rustpix_core::soa::HitBatch;
 use rustpix_tpx::DetectorConfig;
 use std::collections::{HashMap, HashSet};
 use std::mem::size_of;
 use std::path::Path;
 use std::str::FromStr;
 
-const NS_PER_TICK: u64 = 25;
+pub(crate) const NS_PER_TICK: u64 = 25;
 const HISTOGRAM_AXES: [&str; 4] = ["rot_angle", "y", "x", "time_of_flight"];
 
 /// Streaming writer for hit events in `NXevent_data`.
@@ -56,6 +58,39 @@ impl Hdf5HitSink {
         })
     }
 
+    /// Open a hit sink that appends to `path`, creating the file if needed.
+    ///
+    /// New events go after those already in `entry/hits` and the group's
+    /// `run_count` is incremented, so a measurement series accumulates into
+    /// one file.
+    ///
+    /// # Errors
+    /// Returns an error if HDF5 I/O fails or the existing file does not match
+    /// `options` (detector size, optional fields or conversion metadata).
+    pub fn append<P: AsRef<Path>>(path: P, options: HitWriteOptions) -> Result<Self> {
+        let file = open_or_create_file(path.as_ref())?;
+        let (hits, existed) = open_event_group_for_append(
+            &file,
+            "hits",
+            options.x_size,
+            options.y_size,
+            options.flight_path_m,
+            options.tof_offset_ns,
+            options.energy_axis_kind.as_deref(),
+        )?;
+        let writer = if existed {
+            HitEventWriter::open(&hits, &options)?
+        } else {
+            HitEventWriter::new(&hits, &options)?
+        };
+        add_run_count(&hits, existed, 1)?;
+        Ok(Self {
+            _file: file,
+            writer,
+            options,
+        })
+    }
+
     /// Append a hit batch.
     ///
     /// # Errors
@@ -105,6 +140,41 @@ impl Hdf5NeutronSink {
         })
     }
 
+    /// Open a neutron sink that appends to `path`, creating the file if
+    /// needed.
+    ///
+    /// New events go after those already in `entry/neutrons` and the group's
+    /// `run_count` is incremented, so a measurement series accumulates into
+    /// one file.
+    ///
+    /// # Errors
+    /// Returns an error if HDF5 I/O fails or the existing file does not match
+    /// `options` (detector size, super-resolution factor, optional fields or
+    /// conversion metadata).
+    pub fn append<P: AsRef<Path>>(path: P, options: NeutronWriteOptions) -> Result<Self> {
+        let file = open_or_create_file(path.as_ref())?;
+        let (neutrons, existed) = open_event_group_for_append(
+            &file,
+            "neutrons",
+            options.x_size,
+            options.y_size,
+            options.flight_path_m,
+            options.tof_offset_ns,
+            options.energy_axis_kind.as_deref(),
+        )?;
+        let writer = if existed {
+            NeutronEventWriter::open(&neutrons, &options)?
+        } else {
+            NeutronEventWriter::new(&neutrons, &options)?
+        };
+        add_run_count(&neutrons, existed, 1)?;
+        Ok(Self {
+            _file: file,
+            writer,
+            options,
+        })
+    }
+
     /// Append a neutron batch.
     ///
     /// # Errors
@@ -155,8 +225,8 @@ impl HitWriteOptions {
             chunk_events: 100_000,
             compression: Some(1),
             shuffle: true,
-            flight_path_m: None,
-            tof_offset_ns: None,
+            flight_path_m: config.flight_path_m,
+            tof_offset_ns: config.flight_path_m.map(|_| config.tof_offset_ns),
             energy_axis_kind: Some("tof".to_string()),
             include_xy: true,
             include_tot: true,
@@ -210,8 +280,8 @@ impl NeutronWriteOptions {
             chunk_events: 100_000,
             compression: Some(1),
             shuffle: true,
-            flight_path_m: None,
-            tof_offset_ns: None,
+            flight_path_m: config.flight_path_m,
+            tof_offset_ns: config.flight_path_m.map(|_| config.tof_offset_ns),
             energy_axis_kind: Some("tof".to_string()),
             include_xy: true,
             include_tot: true,
@@ -310,6 +380,128 @@ pub struct NeutronEventData {
     pub attrs: EventAttributes,
 }
 
+impl HitEventData {
+    /// Converts the events to a hit batch, with times in 25 ns ticks.
+    ///
+    /// Positions come from `x`/`y`, or from `event_id` and `x_size` when the
+    /// file has no coordinates. Timestamps are the low 32 bits of the pulse
+    /// time plus TOF.
+    ///
+    /// # Errors
+    /// Returns an error if the file has neither coordinates nor `x_size`.
+    pub fn to_hit_batch(&self) -> Result<HitBatch> {
+        let (x, y) = match (&self.x, &self.y) {
+            (Some(x), Some(y)) => (x.clone(), y.clone()),
+            _ => {
+                let (x, y) = event_id_pixels(&self.event_id, &self.attrs)?;
+                let to_u16 = |v: u32| u16::try_from(v).unwrap_or(u16::MAX);
+                (
+                    x.into_iter().map(to_u16).collect(),
+                    y.into_iter().map(to_u16).collect(),
+                )
+            }
+        };
+        let pulses = pulse_of_events(&self.event_index, self.event_id.len());
+        let mut batch = HitBatch::with_capacity(self.event_id.len());
+        for (i, &offset_ns) in self.event_time_offset_ns.iter().enumerate() {
+            let zero_ns = self.event_time_zero_ns.get(pulses[i]).copied().unwrap_or(0);
+            #[allow(clippy::cast_possible_truncation)]
+            let timestamp = (zero_ns.saturating_add(offset_ns) / NS_PER_TICK) as u32;
+            batch.push((
+                x[i],
+                y[i],
+                ticks_u32(offset_ns),
+                self.time_over_threshold_ns
+                    .as_ref()
+                    .map_or(0, |tot| ticks_u16(tot[i])),
+                timestamp,
+                self.chip_id.as_ref().map_or(0, |chip| chip[i]),
+            ));
+        }
+        if let Some(cluster_id) = &self.cluster_id {
+            batch.cluster_id.clone_from(cluster_id);
+        }
+        Ok(batch)
+    }
+}
+
+impl NeutronEventData {
+    /// Converts the events to a neutron batch, with times in 25 ns ticks.
+    ///
+    /// Positions come from `x`/`y`, or from `event_id` and `x_size` (scaled
+    /// by the super-resolution factor) when the file has no coordinates.
+    ///
+    /// # Errors
+    /// Returns an error if the file has neither coordinates nor `x_size`.
+    pub fn to_neutron_batch(&self) -> Result<NeutronBatch> {
+        let (x, y) = match (&self.x, &self.y) {
+            (Some(x), Some(y)) => (x.clone(), y.clone()),
+            _ => {
+                let scale = self.attrs.super_resolution_factor.unwrap_or(1.0);
+                let (x, y) = event_id_pixels(&self.event_id, &self.attrs)?;
+                let to_f64 = |v: u32| f64::from(v) * scale;
+                (
+                    x.into_iter().map(to_f64).collect(),
+                    y.into_iter().map(to_f64).collect(),
+                )
+            }
+        };
+        let mut batch = NeutronBatch::with_capacity(self.event_id.len());
+        for (i, &offset_ns) in self.event_time_offset_ns.iter().enumerate() {
+            batch.push(rustpix_core::neutron::Neutron::new(
+                x[i],
+                y[i],
+                ticks_u32(offset_ns),
+                self.time_over_threshold_ns
+                    .as_ref()
+                    .map_or(0, |tot| ticks_u16(tot[i])),
+                self.n_hits.as_ref().map_or(0, |n_hits| n_hits[i]),
+                self.chip_id.as_ref().map_or(0, |chip| chip[i]),
+            ));
+        }
+        batch.super_resolution_factor = self.attrs.super_resolution_factor;
+        Ok(batch)
+    }
+}
+
+/// Pixel coordinates encoded in event IDs (`y * x_size + x`).
+fn event_id_pixels(event_id: &[i32], attrs: &EventAttributes) -> Result<(Vec<u32>, Vec<u32>)> {
+    let x_size = attrs
+        .x_size
+        .filter(|&size| size > 0)
+        .ok_or_else(|| Error::InvalidFormat("events have no x/y and no x_size".to_string()))?;
+    Ok(event_id
+        .iter()
+        .map(|&id| {
+            let id = u32::try_from(id).unwrap_or(0);
+            (id % x_size, id / x_size)
+        })
+        .unzip())
+}
+
+/// Index of the pulse of each event, from the pulse start indices.
+fn pulse_of_events(event_index: &[i32], count: usize) -> Vec<usize> {
+    let mut pulses = vec![0; count];
+    for (pulse, window) in event_index.windows(2).enumerate() {
+        let start = usize::try_from(window[0]).unwrap_or(0).min(count);
+        let end = usize::try_from(window[1]).unwrap_or(0).clamp(start, count);
+        pulses[start..end].fill(pulse);
+    }
+    if let Some(&last) = event_index.last() {
+        let start = usize::try_from(last).unwrap_or(0).min(count);
+        pulses[start..].fill(event_index.len() - 1);
+    }
+    pulses
+}
+
+fn ticks_u32(ns: u64) -> u32 {
+    u32::try_from(ns / NS_PER_TICK).unwrap_or(u32::MAX)
+}
+
+fn ticks_u16(ns: u64) -> u16 {
+    u16::try_from(ns / NS_PER_TICK).unwrap_or(u16::MAX)
+}
+
 /// Event group attributes.
 #[derive(Clone, Debug, Default)]
 pub struct EventAttributes {
@@ -325,6 +517,8 @@ pub struct EventAttributes {
     pub energy_axis_kind: Option<String>,
     /// Super-resolution factor for neutron coordinates.
     pub super_resolution_factor: Option<f64>,
+    /// Number of runs appended into the group (absent for single-run files).
+    pub run_count: Option<u32>,
 }
 
 /// Neutron event batch with pulse timestamp.
@@ -336,7 +530,7 @@ pub struct NeutronEventBatch {
     pub neutrons: NeutronBatch,
 }
 
-fn detector_size(config: &DetectorConfig) -> (u32, u32) {
+pub(crate) fn detector_size(config: &DetectorConfig) -> (u32, u32) {
     if config.chip_transforms.is_empty() {
         return (u32::from(config.chip_size_x), u32::from(config.chip_size_y));
     }
@@ -346,7 +540,7 @@ fn detector_size(config: &DetectorConfig) -> (u32, u32) {
     let max_local_x = config.chip_size_x.saturating_sub(1);
     let max_local_y = config.chip_size_y.saturating_sub(1);
 
-    for transform in &config.chip_transforms {
+    for transform in &config.output_transforms() {
         let corners = [
             transform.apply(0, 0),
             transform.apply(max_local_x, 0),
@@ -423,12 +617,6 @@ fn merge_meta_from(
     tof_offset_ns: Option<f64>,
     energy_axis_kind: Option<&str>,
 ) -> Result<()> {
-    fn floats_differ(a: f64, b: f64) -> bool {
-        let diff = (a - b).abs();
-        let scale = a.abs().max(b.abs()).max(1.0);
-        diff > (1e-9f64).max(1e-6f64 * scale)
-    }
-
     if let Some(value) = flight_path_m {
         if meta
             .flight_path_m
@@ -469,6 +657,167 @@ fn merge_meta_from(
     Ok(())
 }
 
+fn floats_differ(a: f64, b: f64) -> bool {
+    let diff = (a - b).abs();
+    let scale = a.abs().max(b.abs()).max(1.0);
+    diff > (1e-9f64).max(1e-6f64 * scale)
+}
+
+fn open_or_create_file(path: &Path) -> Result<File> {
+    if path.exists() {
+        return Ok(File::open_rw(path)?);
+    }
+    let file = File::create(path)?;
+    set_attr_str_file(&file, "rustpix_format_version", "0.1")?;
+    Ok(file)
+}
+
+/// Open `entry/<name>` for appending, or create it (and the entry) if the
+/// file does not have it yet. Returns the group and whether it existed.
+///
+/// An existing group must have the same detector size; conversion metadata
+/// missing from the file is added, and conflicting metadata is an error.
+fn open_event_group_for_append(
+    file: &File,
+    name: &str,
+    x_size: u32,
+    y_size: u32,
+    flight_path_m: Option<f64>,
+    tof_offset_ns: Option<f64>,
+    energy_axis_kind: Option<&str>,
+) -> Result<(Group, bool)> {
+    let entry = match file.group("entry") {
+        Ok(entry) => {
+            update_conversion_attrs(&entry, flight_path_m, tof_offset_ns, energy_axis_kind)?;
+            entry
+        }
+        Err(_) => create_entry(file, flight_path_m, tof_offset_ns, energy_axis_kind)?,
+    };
+    let Ok(group) = entry.group(name) else {
+        let group = create_event_group(
+            &entry,
+            name,
+            x_size,
+            y_size,
+            flight_path_m,
+            tof_offset_ns,
+            energy_axis_kind,
+        )?;
+        return Ok((group, false));
+    };
+    for (attr, expected) in [("x_size", x_size), ("y_size", y_size)] {
+        if let Some(existing) = read_attr_opt::<u32>(&group, attr)? {
+            if existing != expected {
+                return Err(Error::InvalidFormat(format!(
+                    "cannot append to {name}: {attr} is {existing} in the file, {expected} in the new data"
+                )));
+            }
+        }
+    }
+    update_conversion_attrs(&group, flight_path_m, tof_offset_ns, energy_axis_kind)?;
+    Ok((group, true))
+}
+
+/// Like `set_conversion_attrs`, but for a group that may already carry the
+/// attributes: missing ones are added, differing ones are an error.
+fn update_conversion_attrs(
+    group: &Group,
+    flight_path_m: Option<f64>,
+    tof_offset_ns: Option<f64>,
+    energy_axis_kind: Option<&str>,
+) -> Result<()> {
+    for (name, value, label) in [
+        ("flight_path_m", flight_path_m, "flight path"),
+        ("tof_offset_ns", tof_offset_ns, "TOF offset"),
+    ] {
+        let Some(value) = value else {
+            continue;
+        };
+        match read_attr_opt::<f64>(group, name)? {
+            Some(existing) if floats_differ(existing, value) => {
+                return Err(Error::InvalidFormat(format!(
+                    "conflicting {label} metadata"
+                )));
+            }
+            Some(_) => {}
+            None => group.new_attr::<f64>().create(name)?.write_scalar(&value)?,
+        }
+    }
+    if let Some(kind) = energy_axis_kind {
+        match read_attr_opt_string(group, "energy_axis_kind")? {
+            Some(existing) if existing != kind => {
+                return Err(Error::InvalidFormat(
+                    "conflicting energy axis metadata".to_string(),
+                ));
+            }
+            Some(_) => {}
+            None => set_attr_str_group(group, "energy_axis_kind", kind)?,
+        }
+    }
+    Ok(())
+}
+
+/// Add `runs` to the event group's `run_count`. Groups written before run
+/// counting are taken to hold one run.
+fn add_run_count(group: &Group, existed: bool, runs: u32) -> Result<()> {
+    let previous = read_attr_opt::<u32>(group, "run_count")?.unwrap_or(u32::from(existed));
+    let count = previous.saturating_add(runs);
+    match group.attr("run_count") {
+        Ok(attr) => attr.write_scalar(&count)?,
+        Err(_) => group
+            .new_attr::<u32>()
+            .create("run_count")?
+            .write_scalar(&count)?,
+    }
+    Ok(())
+}
+
+/// Open an optional dataset of an existing event group. It must exist
+/// exactly when `include` is set, so all event columns stay aligned.
+fn open_optional_dataset(group: &Group, name: &str, include: bool) -> Result<Option<Dataset>> {
+    match (group.dataset(name), include) {
+        (Ok(dataset), true) => Ok(Some(dataset)),
+        (Err(_), false) => Ok(None),
+        (Ok(_), false) => Err(Error::InvalidFormat(format!(
+            "cannot append: the file has {name} but the new data does not"
+        ))),
+        (Err(_), true) => Err(Error::InvalidFormat(format!(
+            "cannot append: the new data has {name} but the file does not"
+        ))),
+    }
+}
+
+/// Append an optional column, which must be present in both the writer and
+/// the data or in neither.
+fn append_column<T: H5Type>(
+    dataset: Option<&Dataset>,
+    values: Option<&[T]>,
+    offset: usize,
+    name: &str,
+) -> Result<()> {
+    match (dataset, values) {
+        (Some(dataset), Some(values)) => append_slice(dataset, offset, values),
+        (None, None) => Ok(()),
+        _ => Err(Error::InvalidFormat(format!(
+            "cannot merge: {name} is present in only one file"
+        ))),
+    }
+}
+
+/// Shift a source file's `event_index` by the events already written.
+fn offset_event_index(event_index: &[i32], event_count: usize) -> Result<Vec<i32>> {
+    let overflow = || {
+        Error::InvalidFormat(
+            "event_index exceeds i32 range; split file or reduce events".to_string(),
+        )
+    };
+    let offset = i32::try_from(event_count).map_err(|_| overflow())?;
+    event_index
+        .iter()
+        .map(|&index| index.checked_add(offset).ok_or_else(overflow))
+        .collect()
+}
+
 fn create_event_group(
     entry: &Group,
     name: &str,
@@ -675,6 +1024,143 @@ pub fn write_combined_hdf5<P: AsRef<Path>>(
     )
 }
 
+/// Appends hit and/or neutron events to an HDF5/NeXus file, creating it if
+/// it does not exist.
+///
+/// Each event group's datasets are extended and its `run_count` incremented;
+/// see [`Hdf5HitSink::append`] and [`Hdf5NeutronSink::append`] for the
+/// compatibility checks. Histograms and pixel masks are per-run products and
+/// cannot be appended.
+///
+/// # Errors
+/// Returns an error if HDF5 I/O fails, nothing is selected, or the existing
+/// file does not match the options.
+pub fn append_hdf5_event_batches<P: AsRef<Path>>(
+    path: P,
+    hits: Option<(&[EventBatch], &HitWriteOptions)>,
+    neutrons: Option<(&[NeutronEventBatch], &NeutronWriteOptions)>,
+) -> Result<()> {
+    if hits.is_none() && neutrons.is_none() {
+        return Err(Error::InvalidFormat(
+            "no HDF5 event payloads selected".to_string(),
+        ));
+    }
+    if let Some((batches, options)) = hits {
+        let mut sink = Hdf5HitSink::append(path.as_ref(), options.clone())?;
+        for batch in batches {
+            sink.write_hits(batch)?;
+        }
+    }
+    if let Some((batches, options)) = neutrons {
+        let mut sink = Hdf5NeutronSink::append(path.as_ref(), options.clone())?;
+        for batch in batches {
+            sink.write_neutrons(batch)?;
+        }
+    }
+    Ok(())
+}
+
+/// Merges the hit and neutron events of `source` into `dest`, creating
+/// `dest` if it does not exist.
+///
+/// Events are copied as stored (no re-encoding), with `event_index` shifted
+/// past the events already in `dest`, and `run_count` grows by the source's
+/// run count. The source event groups are read into memory.
+///
+/// # Errors
+/// Returns an error if HDF5 I/O fails, `source` has no event groups, or the
+/// two files are not compatible (detector size, super-resolution factor,
+/// optional fields or conversion metadata).
+pub fn merge_hdf5_events<P: AsRef<Path>, Q: AsRef<Path>>(dest: P, source: Q) -> Result<()> {
+    let source_file = File::open(source)?;
+    let source_entry = source_file.group("entry")?;
+    let hits = match source_entry.group("hits") {
+        Ok(group) => Some(read_hit_event_group(&source_entry, &group)?),
+        Err(_) => None,
+    };
+    let neutrons = match source_entry.group("neutrons") {
+        Ok(group) => Some(read_neutron_event_group(&source_entry, &group)?),
+        Err(_) => None,
+    };
+    if hits.is_none() && neutrons.is_none() {
+        return Err(Error::InvalidFormat(
+            "source file has no hit or neutron events".to_string(),
+        ));
+    }
+
+    let file = open_or_create_file(dest.as_ref())?;
+    if let Some(data) = &hits {
+        let (x_size, y_size) = source_size(&data.attrs, "hits")?;
+        let options = HitWriteOptions {
+            x_size,
+            y_size,
+            flight_path_m: data.attrs.flight_path_m,
+            tof_offset_ns: data.attrs.tof_offset_ns,
+            energy_axis_kind: data.attrs.energy_axis_kind.clone(),
+            include_xy: data.x.is_some(),
+            include_tot: data.time_over_threshold_ns.is_some(),
+            include_chip_id: data.chip_id.is_some(),
+            include_cluster_id: data.cluster_id.is_some(),
+            ..HitWriteOptions::from_detector_config(&DetectorConfig::default())
+        };
+        let (group, existed) = open_event_group_for_append(
+            &file,
+            "hits",
+            x_size,
+            y_size,
+            options.flight_path_m,
+            options.tof_offset_ns,
+            options.energy_axis_kind.as_deref(),
+        )?;
+        let mut writer = if existed {
+            HitEventWriter::open(&group, &options)?
+        } else {
+            HitEventWriter::new(&group, &options)?
+        };
+        writer.append_data(data)?;
+        add_run_count(&group, existed, data.attrs.run_count.unwrap_or(1))?;
+    }
+    if let Some(data) = &neutrons {
+        let (x_size, y_size) = source_size(&data.attrs, "neutrons")?;
+        let options = NeutronWriteOptions {
+            x_size,
+            y_size,
+            super_resolution_factor: data.attrs.super_resolution_factor.unwrap_or(1.0),
+            flight_path_m: data.attrs.flight_path_m,
+            tof_offset_ns: data.attrs.tof_offset_ns,
+            energy_axis_kind: data.attrs.energy_axis_kind.clone(),
+            include_xy: data.x.is_some(),
+            include_tot: data.time_over_threshold_ns.is_some(),
+            include_chip_id: data.chip_id.is_some(),
+            include_n_hits: data.n_hits.is_some(),
+            ..NeutronWriteOptions::from_detector_config(&DetectorConfig::default())
+        };
+        let (group, existed) = open_event_group_for_append(
+            &file,
+            "neutrons",
+            x_size,
+            y_size,
+            options.flight_path_m,
+            options.tof_offset_ns,
+            options.energy_axis_kind.as_deref(),
+        )?;
+        let mut writer = if existed {
+            NeutronEventWriter::open(&group, &options)?
+        } else {
+            NeutronEventWriter::new(&group, &options)?
+        };
+        writer.append_data(data)?;
+        add_run_count(&group, existed, data.attrs.run_count.unwrap_or(1))?;
+    }
+    Ok(())
+}
+
+fn source_size(attrs: &EventAttributes, name: &str) -> Result<(u32, u32)> {
+    attrs.x_size.zip(attrs.y_size).ok_or_else(|| {
+        Error::InvalidFormat(format!("source {name} have no x_size/y_size attributes"))
+    })
+}
+
 /// Reads hit events from an HDF5/NeXus file.
 ///
 /// # Errors
@@ -697,6 +1183,108 @@ pub fn read_neutrons_hdf5<P: AsRef<Path>>(path: P) -> Result<NeutronEventData> {
     read_neutron_event_group(&entry, &neutrons)
 }
 
+/// Reads the neutrons of a rustpix HDF5 file, or its hits when it has no
+/// neutrons. Returns `None` if the file has neither group.
+pub(crate) fn read_processed_hdf5(path: &Path) -> Result<Option<ProcessedData>> {
+    let file = File::open(path)?;
+    let Ok(entry) = file.group("entry") else {
+        return Ok(None);
+    };
+    if let Ok(group) = entry.group("neutrons") {
+        let data = read_neutron_event_group(&entry, &group)?;
+        return Ok(Some(ProcessedData::Neutrons {
+            neutrons: data.to_neutron_batch()?,
+            super_resolution_factor: data.attrs.super_resolution_factor,
+        }));
+    }
+    if let Ok(group) = entry.group("hits") {
+        let data = read_hit_event_group(&entry, &group)?;
+        return Ok(Some(ProcessedData::Hits(data.to_hit_batch()?)));
+    }
+    Ok(None)
+}
+
+/// Reads a hit list exported by other software from an HDF5 file.
+///
+/// The hits are 1D datasets of equal length in `group`, one per column, as
+/// described in [`crate::GenericHitOptions`]; any numeric type is read.
+/// Without a group, the first group (breadth first from the root) that has
+/// the columns is used.
+///
+/// # Errors
+/// Returns an error if HDF5 I/O fails, no group has the columns, the
+/// columns differ in length, or a value is out of range.
+pub fn read_generic_hdf5_hits<P: AsRef<Path>>(
+    path: P,
+    group: Option<&str>,
+    options: &GenericHitOptions,
+) -> Result<HitBatch> {
+    let file = File::open(path)?;
+    let (group, names, columns) = match group {
+        Some(name) => {
+            let group = file.group(name)?;
+            let names = dataset_names(&group)?;
+            let columns = options.find_columns(&names).map_err(|err| match err {
+                Error::InvalidFormat(message) => {
+                    Error::InvalidFormat(format!("group {name}: {message}"))
+                }
+                err => err,
+            })?;
+            (group, names, columns)
+        }
+        None => {
+            let mut queue = std::collections::VecDeque::from([file.group("/")?]);
+            loop {
+                let Some(group) = queue.pop_front() else {
+                    return Err(Error::InvalidFormat(
+                        "no group has the hit columns x, y and t".to_string(),
+                    ));
+                };
+                let names = dataset_names(&group)?;
+                if let Ok(columns) = options.find_columns(&names) {
+                    break (group, names, columns);
+                }
+                queue.extend(group.groups()?);
+            }
+        }
+    };
+
+    let read = |index: usize| read_dataset_vec_f64(&group, &names[index]);
+    let x = read(columns.x)?;
+    let y = read(columns.y)?;
+    let t = read(columns.t)?;
+    let amplitude = columns.amplitude.map(read).transpose()?;
+    if y.len() != x.len()
+        || t.len() != x.len()
+        || amplitude.as_ref().is_some_and(|a| a.len() != x.len())
+    {
+        return Err(Error::InvalidFormat(format!(
+            "{}: hit columns differ in length",
+            group.name()
+        )));
+    }
+
+    let mut batch = HitBatch::with_capacity(x.len());
+    for (i, ((&x, &y), &t)) in x.iter().zip(&y).zip(&t).enumerate() {
+        let amplitude = amplitude.as_ref().map_or(0.0, |a| a[i]);
+        let hit = options.hit(x, y, t, amplitude).map_err(|name| {
+            Error::InvalidFormat(format!("{}: row {i}: {name} out of range", group.name()))
+        })?;
+        batch.push(hit);
+    }
+    batch.sort_by_tof();
+    Ok(batch)
+}
+
+/// Names of the datasets directly in `group`.
+fn dataset_names(group: &Group) -> Result<Vec<String>> {
+    Ok(group
+        .member_names()?
+        .into_iter()
+        .filter(|name| group.dataset(name).is_ok())
+        .collect())
+}
+
 fn read_hit_event_group(entry: &Group, group: &Group) -> Result<HitEventData> {
     let event_id = read_dataset_vec::<i32>(group, "event_id")?;
     let event_time_offset_ns = read_dataset_vec::<u64>(group, "event_time_offset")?;
@@ -761,6 +1349,7 @@ fn read_event_attrs(entry: &Group, group: &Group) -> Result<EventAttributes> {
         tof_offset_ns: read_attr_opt::<f64>(entry, "tof_offset_ns")?,
         energy_axis_kind: read_attr_opt_string(entry, "energy_axis_kind")?,
         super_resolution_factor: read_attr_opt::<f64>(group, "super_resolution_factor")?,
+        run_count: read_attr_opt::<u32>(group, "run_count")?,
     };
 
     if let Some(value) = read_attr_opt::<f64>(group, "flight_path_m")? {
@@ -779,6 +1368,75 @@ fn read_event_attrs(entry: &Group, group: &Group) -> Result<EventAttributes> {
     Ok(attrs)
 }
 
+/// Pulse structure of one event group, read back to validate a file.
+///
+/// Covers both the rustpix schema (`entry/hits`, `entry/neutrons`) and
+/// `NeXus` files from [`crate::NexusEventWriter`] (`entry/*_events`). Times
+/// are in the units stored in the file.
+#[derive(Clone, Debug)]
+pub struct EventGroupLayout {
+    /// Group name under `entry`.
+    pub name: String,
+    /// Length of `event_id`.
+    pub events: usize,
+    /// Lengths of the other per-event datasets, by name.
+    pub columns: Vec<(String, usize)>,
+    /// First event of each pulse.
+    pub event_index: Vec<i64>,
+    /// Pulse times.
+    pub event_time_zero: Vec<f64>,
+    /// Event times of flight.
+    pub event_time_offset: Vec<f64>,
+    /// Number of runs appended into the group (absent for single-run files);
+    /// pulse times restart at each appended run.
+    pub run_count: Option<u32>,
+}
+
+/// Reads the pulse structure of every event group under `entry`.
+///
+/// # Errors
+/// Returns an error if the file has no `entry` group or an event group
+/// lacks `event_id`, `event_index`, `event_time_zero` or
+/// `event_time_offset`.
+pub fn read_event_group_layouts<P: AsRef<Path>>(path: P) -> Result<Vec<EventGroupLayout>> {
+    let file = File::open(path)?;
+    let entry = file.group("entry")?;
+    let mut layouts = Vec::new();
+    for group in entry.groups()? {
+        if group.dataset("event_index").is_err() {
+            continue;
+        }
+        let name = group
+            .name()
+            .rsplit('/')
+            .next()
+            .unwrap_or_default()
+            .to_string();
+        let mut columns = Vec::new();
+        for column in group.member_names()? {
+            if matches!(
+                column.as_str(),
+                "event_id" | "event_index" | "event_time_zero"
+            ) {
+                continue;
+            }
+            if let Ok(dataset) = group.dataset(&column) {
+                columns.push((column, dataset.size()));
+            }
+        }
+        layouts.push(EventGroupLayout {
+            name,
+            events: group.dataset("event_id")?.size(),
+            columns,
+            event_index: read_dataset_vec_i64(&group, "event_index")?,
+            event_time_zero: read_dataset_vec_f64(&group, "event_time_zero")?,
+            event_time_offset: read_dataset_vec_f64(&group, "event_time_offset")?,
+            run_count: read_attr_opt::<u32>(&group, "run_count")?,
+        });
+    }
+    Ok(layouts)
+}
+
 struct HitEventWriter {
     event_id: Dataset,
     event_time_offset: Dataset,
@@ -920,6 +1578,69 @@ impl HitEventWriter {
         })
     }
 
+    /// Writer for an existing hit group, positioned after its events.
+    fn open(group: &Group, options: &HitWriteOptions) -> Result<Self> {
+        let event_id = group.dataset("event_id")?;
+        let event_time_zero = group.dataset("event_time_zero")?;
+        let event_count = event_id.size();
+        let pulse_count = event_time_zero.size();
+        Ok(Self {
+            event_id,
+            event_time_offset: group.dataset("event_time_offset")?,
+            event_time_zero,
+            event_index: group.dataset("event_index")?,
+            time_over_threshold: open_optional_dataset(
+                group,
+                "time_over_threshold",
+                options.include_tot,
+            )?,
+            chip_id: open_optional_dataset(group, "chip_id", options.include_chip_id)?,
+            cluster_id: open_optional_dataset(group, "cluster_id", options.include_cluster_id)?,
+            x: open_optional_dataset(group, "x", options.include_xy)?,
+            y: open_optional_dataset(group, "y", options.include_xy)?,
+            event_count,
+            pulse_count,
+        })
+    }
+
+    /// Append events read from another file, as stored.
+    fn append_data(&mut self, data: &HitEventData) -> Result<()> {
+        let event_index = offset_event_index(&data.event_index, self.event_count)?;
+        let start = self.event_count;
+        append_slice(&self.event_id, start, &data.event_id)?;
+        append_slice(&self.event_time_offset, start, &data.event_time_offset_ns)?;
+        append_column(
+            self.time_over_threshold.as_ref(),
+            data.time_over_threshold_ns.as_deref(),
+            start,
+            "time_over_threshold",
+        )?;
+        append_column(
+            self.chip_id.as_ref(),
+            data.chip_id.as_deref(),
+            start,
+            "chip_id",
+        )?;
+        append_column(
+            self.cluster_id.as_ref(),
+            data.cluster_id.as_deref(),
+            start,
+            "cluster_id",
+        )?;
+        append_column(self.x.as_ref(), data.x.as_deref(), start, "x")?;
+        append_column(self.y.as_ref(), data.y.as_deref(), start, "y")?;
+        append_slice(
+            &self.event_time_zero,
+            self.pulse_count,
+            &data.event_time_zero_ns,
+        )?;
+        append_slice(&self.event_index, self.pulse_count, &event_index)?;
+
+        self.event_count += data.event_id.len();
+        self.pulse_count += event_index.len();
+        Ok(())
+    }
+
     fn append_batch(&mut self, batch: &EventBatch, options: &HitWriteOptions) -> Result<()> {
         let count = batch.hits.len();
         if count == 0 {
@@ -1001,7 +1722,7 @@ struct NeutronEventWriter {
     pulse_count: usize,
 }
 
-fn normalize_super_resolution(value: f64) -> f64 {
+pub(crate) fn normalize_super_resolution(value: f64) -> f64 {
     if value.is_finite() && value > 0.0 {
         value
     } else {
@@ -1141,6 +1862,77 @@ impl NeutronEventWriter {
         })
     }
 
+    /// Writer for an existing neutron group, positioned after its events.
+    fn open(group: &Group, options: &NeutronWriteOptions) -> Result<Self> {
+        let super_res = normalize_super_resolution(options.super_resolution_factor);
+        if let Some(existing) = read_attr_opt::<f64>(group, "super_resolution_factor")? {
+            if floats_differ(existing, super_res) {
+                return Err(Error::InvalidFormat(format!(
+                    "cannot append: super_resolution_factor is {existing} in the file, {super_res} in the new data"
+                )));
+            }
+        }
+        let event_id = group.dataset("event_id")?;
+        let event_time_zero = group.dataset("event_time_zero")?;
+        let event_count = event_id.size();
+        let pulse_count = event_time_zero.size();
+        Ok(Self {
+            event_id,
+            event_time_offset: group.dataset("event_time_offset")?,
+            event_time_zero,
+            event_index: group.dataset("event_index")?,
+            time_over_threshold: open_optional_dataset(
+                group,
+                "time_over_threshold",
+                options.include_tot,
+            )?,
+            chip_id: open_optional_dataset(group, "chip_id", options.include_chip_id)?,
+            n_hits: open_optional_dataset(group, "n_hits", options.include_n_hits)?,
+            x: open_optional_dataset(group, "x", options.include_xy)?,
+            y: open_optional_dataset(group, "y", options.include_xy)?,
+            event_count,
+            pulse_count,
+        })
+    }
+
+    /// Append events read from another file, as stored.
+    fn append_data(&mut self, data: &NeutronEventData) -> Result<()> {
+        let event_index = offset_event_index(&data.event_index, self.event_count)?;
+        let start = self.event_count;
+        append_slice(&self.event_id, start, &data.event_id)?;
+        append_slice(&self.event_time_offset, start, &data.event_time_offset_ns)?;
+        append_column(
+            self.time_over_threshold.as_ref(),
+            data.time_over_threshold_ns.as_deref(),
+            start,
+            "time_over_threshold",
+        )?;
+        append_column(
+            self.chip_id.as_ref(),
+            data.chip_id.as_deref(),
+            start,
+            "chip_id",
+        )?;
+        append_column(
+            self.n_hits.as_ref(),
+            data.n_hits.as_deref(),
+            start,
+            "n_hits",
+        )?;
+        append_column(self.x.as_ref(), data.x.as_deref(), start, "x")?;
+        append_column(self.y.as_ref(), data.y.as_deref(), start, "y")?;
+        append_slice(
+            &self.event_time_zero,
+            self.pulse_count,
+            &data.event_time_zero_ns,
+        )?;
+        append_slice(&self.event_index, self.pulse_count, &event_index)?;
+
+        self.event_count += data.event_id.len();
+        self.pulse_count += event_index.len();
+        Ok(())
+    }
+
     fn append_batch(
         &mut self,
         batch: &NeutronEventBatch,
@@ -1167,6 +1959,10 @@ impl NeutronEventWriter {
         let mut event_id = Vec::with_capacity(count);
 
         let super_res = normalize_super_resolution(options.super_resolution_factor);
+        batch
+            .neutrons
+            .check_super_resolution(super_res)
+            .map_err(rustpix_core::Error::from)?;
 
         for (&x, &y) in batch.neutrons.x.iter().zip(batch.neutrons.y.iter()) {
             if !x.is_finite() || !y.is_finite() {
@@ -2078,7 +2874,7 @@ fn derive_energy_axis_ev(tof_ns: &[f64], flight_path_m: f64, tof_offset_ns: f64)
         .collect()
 }
 
-fn create_extendable_dataset<T: H5Type>(
+pub(crate) fn create_extendable_dataset<T: H5Type>(
     group: &Group,
     name: &str,
     chunk_events: usize,
@@ -2101,7 +2897,7 @@ fn create_extendable_dataset<T: H5Type>(
     Ok(builder.create(name)?)
 }
 
-fn append_slice<T: H5Type>(dataset: &Dataset, offset: usize, data: &[T]) -> Result<()> {
+pub(crate) fn append_slice<T: H5Type>(dataset: &Dataset, offset: usize, data: &[T]) -> Result<()> {
     if data.is_empty() {
         return Ok(());
     }
@@ -2125,7 +2921,7 @@ fn axis_mode_for_len(dim: usize, axis_len: usize) -> Result<&'static str> {
     }
 }
 
-fn set_dataset_units(dataset: &Dataset, units: &str) -> Result<()> {
+pub(crate) fn set_dataset_units(dataset: &Dataset, units: &str) -> Result<()> {
     let value = to_var_len_unicode(units)?;
     dataset
         .new_attr::<VarLenUnicode>()
@@ -2143,7 +2939,7 @@ fn set_axis_mode(dataset: &Dataset, mode: &str) -> Result<()> {
     Ok(())
 }
 
-fn set_attr_str_file(file: &File, name: &str, value: &str) -> Result<()> {
+pub(crate) fn set_attr_str_file(file: &File, name: &str, value: &str) -> Result<()> {
     let value = to_var_len_unicode(value)?;
     file.new_attr::<VarLenUnicode>()
         .create(name)?
@@ -2151,7 +2947,7 @@ fn set_attr_str_file(file: &File, name: &str, value: &str) -> Result<()> {
     Ok(())
 }
 
-fn set_attr_str_group(group: &Group, name: &str, value: &str) -> Result<()> {
+pub(crate) fn set_attr_str_group(group: &Group, name: &str, value: &str) -> Result<()> {
     let value = to_var_len_unicode(value)?;
     group
         .new_attr::<VarLenUnicode>()
@@ -2218,6 +3014,31 @@ fn read_dataset_vec_opt_f64(group: &Group, name: &str) -> Result<Option<Vec<f64>
     )))
 }
 
+fn read_dataset_vec_f64(group: &Group, name: &str) -> Result<Vec<f64>> {
+    let dataset = group.dataset(name)?;
+    if let Ok(values) = dataset.read_raw::<f64>() {
+        return Ok(values);
+    }
+    if let Ok(values) = dataset.read_raw::<f32>() {
+        return Ok(values.into_iter().map(f64::from).collect());
+    }
+    let values = dataset.read_raw::<u64>()?;
+    #[allow(clippy::cast_precision_loss)]
+    Ok(values.into_iter().map(|value| value as f64).collect())
+}
+
+fn read_dataset_vec_i64(group: &Group, name: &str) -> Result<Vec<i64>> {
+    let dataset = group.dataset(name)?;
+    if let Ok(values) = dataset.read_raw::<i64>() {
+        return Ok(values);
+    }
+    let values = dataset.read_raw::<u64>()?;
+    Ok(values
+        .into_iter()
+        .map(|value| i64::try_from(value).unwrap_or(i64::MAX))
+        .collect())
+}
+
 fn read_attr_opt<T: H5Type + Clone>(group: &Group, name: &str) -> Result<Option<T>> {
     match group.attr(name) {
         Ok(attr) => Ok(Some(attr.read_scalar::<T>()?)),
@@ -2235,7 +3056,7 @@ fn read_attr_opt_string(group: &Group, name: &str) -> Result<Option<String>> {
     }
 }
 
-fn to_var_len_unicode(value: &str) -> Result<VarLenUnicode> {
+pub(crate) fn to_var_len_unicode(value: &str) -> Result<VarLenUnicode> {
     VarLenUnicode::from_str(value)
         .map_err(|e| Error::InvalidFormat(format!("invalid utf-8 attribute: {e}")))
 }
@@ -2244,7 +3065,6 @@ fn to_var_len_unicode(value: &str) -> Result<VarLenUnicode> {
 mod tests {
     use super::*;
     use rustpix_core::neutron::NeutronBatch;
-    use rustpix_core::soa::HitBatch;
     use tempfile::NamedTempFile;
 
     #[test]
@@ -2470,6 +3290,57 @@ mod tests {
         assert_eq!(data.event_index, vec![0, 1]);
     }
 
+    #[test]
+    fn test_hdf5_neutron_append_and_merge() {
+        let run = |tdc: u64, x: f64| NeutronEventBatch {
+            tdc_timestamp_25ns: tdc,
+            neutrons: NeutronBatch {
+                x: vec![x, x + 1.0],
+                y: vec![5.0, 6.0],
+                tof: vec![30, 40],
+                tot: vec![7, 9],
+                n_hits: vec![2, 3],
+                chip_id: vec![0, 1],
+                energy_kev: Vec::new(),
+                event_class: Vec::new(),
+                features: Vec::new(),
+            },
+        };
+        let mut options = NeutronWriteOptions::from_detector_config(&DetectorConfig::default());
+        options.compression = None;
+        options.flight_path_m = Some(25.0);
+
+        let dir = tempfile::tempdir().unwrap();
+        let series = dir.path().join("series.h5");
+        // The first append creates the file.
+        append_hdf5_event_batches(&series, None, Some((&[run(1, 10.0)], &options))).unwrap();
+        append_hdf5_event_batches(&series, None, Some((&[run(2, 20.0)], &options))).unwrap();
+
+        let data = read_neutrons_hdf5(&series).unwrap();
+        assert_eq!(data.event_id.len(), 4);
+        assert_eq!(data.event_index, vec![0, 2]);
+        assert_eq!(data.event_time_zero_ns, vec![NS_PER_TICK, 2 * NS_PER_TICK]);
+        assert!((data.x.as_ref().unwrap()[2] - 20.0).abs() < 1e-9);
+        assert_eq!(data.attrs.run_count, Some(2));
+
+        // Merging a single-run file shifts its event_index.
+        let other = dir.path().join("other.h5");
+        write_neutrons_hdf5(&other, vec![run(3, 30.0)], &options).unwrap();
+        merge_hdf5_events(&series, &other).unwrap();
+        let data = read_neutrons_hdf5(&series).unwrap();
+        assert_eq!(data.event_index, vec![0, 2, 4]);
+        assert_eq!(data.n_hits.as_ref().unwrap().len(), 6);
+        assert_eq!(data.attrs.run_count, Some(3));
+
+        // Incompatible layouts and metadata are rejected.
+        let mut narrower = options.clone();
+        narrower.include_n_hits = false;
+        assert!(Hdf5NeutronSink::append(&series, narrower).is_err());
+        let mut moved = options.clone();
+        moved.flight_path_m = Some(10.0);
+        assert!(Hdf5NeutronSink::append(&series, moved).is_err());
+    }
+
     #[test]
     #[allow(clippy::too_many_lines)]
     fn test_hdf5_combined_export_with_masks() {
@@ -2670,6 +3541,43 @@ mod tests {
         assert!(matches!(err, Error::InvalidFormat(_)));
     }
 
+    #[test]
+    fn test_read_generic_hdf5_hits() {
+        let tmp = NamedTempFile::new().unwrap();
+        {
+            let file = File::create(tmp.path()).unwrap();
+            let events = file
+                .create_group("data")
+                .unwrap()
+                .create_group("events")
+                .unwrap();
+            let col = events.new_dataset::<u16>().shape(2).create("col").unwrap();
+            col.write(ArrayView1::from(&[4u16, 5][..])).unwrap();
+            let row = events.new_dataset::<i32>().shape(2).create("row").unwrap();
+            row.write(ArrayView1::from(&[3i32, 2][..])).unwrap();
+            let toa = events
+                .new_dataset::<f64>()
+                .shape(2)
+                .create("toa_us")
+                .unwrap();
+            toa.write(ArrayView1::from(&[1.5f64, 0.5][..])).unwrap();
+        }
+
+        let options = GenericHitOptions {
+            time_unit_ns: 1000.0,
+            columns: Some("x=col,y=row,t=toa_us".parse().unwrap()),
+            ..GenericHitOptions::default()
+        };
+        let hits = read_generic_hdf5_hits(tmp.path(), None, &options).unwrap();
+        assert_eq!(hits.x, vec![5, 4]);
+        assert_eq!(hits.y, vec![2, 3]);
+        assert_eq!(hits.tof, vec![20, 60]);
+        assert_eq!(hits.tot, vec![0, 0]);
+
+        let err = read_generic_hdf5_hits(tmp.path(), Some("data"), &options).unwrap_err();
+        assert!(err.to_string().contains("group data: no hit column"));
+    }
+
     #[test]
     fn test_hdf5_event_index_overflow() {
         let file = NamedTempFile::new().unwrap();
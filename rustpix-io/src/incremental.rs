@@ -0,0 +1,224 @@
+//! Incremental reading of TPX3 files that are still being written.
+//!
+//! [`IncrementalTpx3Reader`] re-maps the file on every [`poll`], finds the
+//! first section that can still hold pulses it has not returned, and decodes
+//! only from there. The newest pulses are held back until more data
+//! arrives, because other chips (and late hits) may still add to them; once
+//! the file is complete, a final poll returns everything that is left.
+//!
+//! Each poll only decodes the safely parseable prefix of the file (see
+//! [`parseable_len`]): a packet the writer is still in the middle of, or
+//! space it has reserved but not filled yet, is read by a later poll. A
+//! file that gets shorter was replaced or truncated, and polling it fails
+//! instead of returning pulses twice.
+//!
+//! [`poll`]: IncrementalTpx3Reader::poll
+
+use crate::reader::{EventBatch, MappedFileReader};
+use crate::{Error, Result};
+use rustpix_tpx::ordering::{read_pulses_with_contexts, section_pulse_contexts, PulseContext};
+use rustpix_tpx::section::{discover_sections, parseable_len, Tpx3Section};
+use rustpix_tpx::DetectorConfig;
+use std::path::{Path, PathBuf};
+
+/// Pulses held back from a poll while the file may still grow.
+pub(crate) const HELD_BACK_PULSES: usize = 2;
+
+/// Reader that returns each pulse of a growing TPX3 file once.
+pub struct IncrementalTpx3Reader {
+    /// File being read.
+    path: PathBuf,
+    /// Detector configuration used for parsing.
+    config: DetectorConfig,
+    /// Extended TDC timestamp of the last pulse returned.
+    emitted_through: Option<u64>,
+    /// Bytes of the file seen by the last poll.
+    bytes_read: usize,
+}
+
+impl IncrementalTpx3Reader {
+    /// Creates a reader for `path` with default configuration.
+    ///
+    /// Nothing is read until the first [`Self::poll`].
+    #[must_use]
+    pub fn new<P: AsRef<Path>>(path: P) -> Self {
+        Self {
+            path: path.as_ref().to_path_buf(),
+            config: DetectorConfig::default(),
+            emitted_through: None,
+            bytes_read: 0,
+        }
+    }
+
+    /// Sets the detector configuration.
+    #[must_use]
+    pub fn with_config(mut self, config: DetectorConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Returns the file path.
+    #[must_use]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns the number of bytes seen by the last poll.
+    #[must_use]
+    pub fn bytes_read(&self) -> usize {
+        self.bytes_read
+    }
+
+    /// Reads the file as it is now and returns the pulses not returned before,
+    /// in TDC order.
+    ///
+    /// Unless `finished` is set, the newest pulses are kept for a later poll.
+    /// A trailing partial packet or unwritten tail is ignored. Hits before a
+    /// chip's first TDC are dropped.
+    ///
+    /// # Errors
+    /// Returns an error if the file cannot be opened or mapped, or is
+    /// shorter than at the last poll.
+    pub fn poll(&mut self, finished: bool) -> Result<Vec<EventBatch>> {
+        let reader = MappedFileReader::open(&self.path)?;
+        let data = reader.as_bytes();
+        let data = &data[..parseable_len(data)];
+        if data.len() < self.bytes_read {
+            return Err(Error::InvalidFormat(format!(
+                "{}: file shrank from {} to {} bytes while being read; it was replaced or truncated",
+                self.path.display(),
+                self.bytes_read,
+                data.len()
+            )));
+        }
+        self.bytes_read = data.len();
+
+        let sections = discover_sections(data);
+        let contexts = section_pulse_contexts(data, &sections);
+        let start = self
+            .emitted_through
+            .map_or(0, |emitted| resume_section(&sections, &contexts, emitted));
+        let pulses =
+            read_pulses_with_contexts(data, &sections[start..], &contexts[start..], &self.config);
+
+        let mut pulses: Vec<EventBatch> = pulses
+            .into_iter()
+            .filter(|pulse| {
+                self.emitted_through
+                    .is_none_or(|emitted| pulse.tdc_timestamp > emitted)
+            })
+            .map(|pulse| EventBatch {
+                tdc_timestamp_25ns: pulse.tdc_timestamp,
+                hits: pulse.hits,
+            })
+            .collect();
+        if !finished {
+            pulses.truncate(pulses.len().saturating_sub(HELD_BACK_PULSES));
+        }
+        if let Some(last) = pulses.last() {
+            self.emitted_through = Some(last.tdc_timestamp_25ns);
+        }
+        Ok(pulses)
+    }
+}
+
+/// First section that can hold hits of pulses after `emitted`.
+///
+/// For each chip, that is its last section that starts at or before the
+/// pulse `emitted` (earlier sections only hold older pulses); the earliest
+/// of those over all chips is where decoding resumes.
+pub(crate) fn resume_section(
+    sections: &[Tpx3Section],
+    contexts: &[PulseContext],
+    emitted: u64,
+) -> usize {
+    let mut last_before = [None::<usize>; 256];
+    for (i, (section, context)) in sections.iter().zip(contexts).enumerate() {
+        let started_before = context
+            .current
+            .is_none_or(|(tdc, epoch)| (epoch << 30) | u64::from(tdc) <= emitted);
+        if started_before {
+            last_before[usize::from(section.chip_id)] = Some(i);
+        }
+    }
+    last_before.into_iter().flatten().min().unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Tpx3FileReader;
+    use rustpix_tpx::Tpx3Packet;
+    use std::io::Write;
+
+    #[test]
+    fn test_incremental_reads_match_whole_file() {
+        let header = Tpx3Packet::TPX3_HEADER_MAGIC;
+        let tdc = |timestamp: u64| 0x6F00_0000_0000_0000 | (timestamp << 12);
+        let hit =
+            |toa: u64, addr: u64| 0xB000_0000_0000_0000 | (toa << 30) | (20 << 20) | (addr << 44);
+        let mut packets = Vec::new();
+        for pulse in 0..6u64 {
+            let start = 1000 + pulse * 2000;
+            packets.extend([header, tdc(start), hit(start + 100, 1), hit(start + 150, 2)]);
+        }
+        let data: Vec<u8> = packets.iter().flat_map(|p| p.to_le_bytes()).collect();
+
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        let mut reader = IncrementalTpx3Reader::new(file.path());
+        let mut hits = 0;
+        let mut stamps = Vec::new();
+        // Append in uneven pieces, including a partial packet.
+        for piece in data.chunks(45) {
+            file.write_all(piece).unwrap();
+            file.flush().unwrap();
+            for pulse in reader.poll(false).unwrap() {
+                hits += pulse.hits.len();
+                stamps.push(pulse.tdc_timestamp_25ns);
+            }
+        }
+        assert!(stamps.len() < 6, "newest pulses are held back");
+        for pulse in reader.poll(true).unwrap() {
+            hits += pulse.hits.len();
+            stamps.push(pulse.tdc_timestamp_25ns);
+        }
+
+        let whole = Tpx3FileReader::open(file.path()).unwrap();
+        assert_eq!(hits, whole.read_batch().unwrap().len());
+        assert_eq!(stamps.len(), 6);
+        assert!(stamps.windows(2).all(|w| w[0] < w[1]));
+        assert!(reader.poll(true).unwrap().is_empty());
+        assert_eq!(reader.bytes_read(), data.len());
+    }
+
+    #[test]
+    fn test_unwritten_tail_is_read_later_and_shrinking_fails() {
+        let header = Tpx3Packet::TPX3_HEADER_MAGIC;
+        let tdc = |timestamp: u64| 0x6F00_0000_0000_0000 | (timestamp << 12);
+        let hit = |toa: u64| 0xB000_0000_0000_0000 | (toa << 30) | (20 << 20);
+        let packets = [header, tdc(1000), hit(1100), tdc(3000), hit(3100)];
+        let bytes: Vec<u8> = packets.iter().flat_map(|p| p.to_le_bytes()).collect();
+
+        // The writer reserved space for the last two packets.
+        let mut data = bytes[..24].to_vec();
+        data.extend_from_slice(&[0; 16]);
+        let file = tempfile::NamedTempFile::new().unwrap();
+        std::fs::write(file.path(), &data).unwrap();
+        let mut reader = IncrementalTpx3Reader::new(file.path());
+        let first = reader.poll(true).unwrap();
+        assert_eq!(first.len(), 1);
+        assert_eq!(reader.bytes_read(), 24);
+
+        std::fs::write(file.path(), &bytes).unwrap();
+        let second = reader.poll(true).unwrap();
+        assert_eq!(second.len(), 1);
+        assert_eq!(second[0].hits.len(), 1);
+
+        std::fs::write(file.path(), &bytes[..16]).unwrap();
+        let Err(err) = reader.poll(true) else {
+            panic!("a shrunk file is polled without error");
+        };
+        let err = err.to_string();
+        assert!(err.contains("shrank from 40 to 16 bytes"), "{err}");
+    }
+}
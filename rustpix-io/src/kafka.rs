@@ -0,0 +1,744 @@
+//! Kafka consumer for ESS event streams.
+//!
+//! At ESS, detector events are published to Kafka as flatbuffers in the
+//! `ev42` or `ev44` schema: per message, one or more pulse times with the
+//! time of flight and pixel ID of each event. [`KafkaEventConsumer`] reads
+//! such a topic as a member of a consumer group, maps pixel IDs to image
+//! coordinates with a [`PixelLayout`] and returns one [`EventBatch`] per
+//! pulse, ready for clustering like the pulses of a TPX3 file.
+//!
+//! The consumer is built on librdkafka (through `rdkafka`), so compressed
+//! record batches (gzip, snappy, lz4, zstd), TLS and SASL work as configured
+//! in [`KafkaConfig::properties`]. Offsets are committed to the group only
+//! by [`KafkaEventConsumer::commit`], after the caller has written what it
+//! made of the returned pulses, so a restarted reduction resumes from the
+//! first message it had not finished.
+//!
+//! The messages are decoded with bindings for the upstream schemas, which
+//! are vendored in `schemas/`.
+
+use crate::reader::EventBatch;
+use crate::{Error, Result};
+use rdkafka::config::ClientConfig;
+use rdkafka::consumer::{BaseConsumer, CommitMode, Consumer};
+use rdkafka::error::{KafkaError, RDKafkaErrorCode};
+use rdkafka::Message;
+use rustpix_core::soa::HitBatch;
+use std::time::Duration;
+
+/// Client ID sent to the brokers.
+const CLIENT_ID: &str = "rustpix";
+
+/// Payload bytes after which a poll returns what it has gathered.
+const MAX_POLL_BYTES: usize = 64 << 20;
+
+/// Length of a TOF tick in nanoseconds.
+const TICK_NS: i64 = 25;
+
+/// Where to start reading when no offset has been committed.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum StartOffset {
+    /// Oldest message still on the broker.
+    #[default]
+    Earliest,
+    /// Next message published.
+    Latest,
+}
+
+/// Mapping from ESS pixel IDs to image coordinates.
+///
+/// Pixel IDs are numbered row by row from `first_pixel_id`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PixelLayout {
+    /// Pixels per row.
+    pub width: u32,
+    /// ID of the pixel at (0, 0).
+    pub first_pixel_id: i64,
+}
+
+impl PixelLayout {
+    /// Image coordinates of `pixel_id`, or `None` if it lies outside the
+    /// `u16` coordinate range.
+    #[must_use]
+    pub fn position(&self, pixel_id: i64) -> Option<(u16, u16)> {
+        let index = u64::try_from(pixel_id.checked_sub(self.first_pixel_id)?).ok()?;
+        let width = u64::from(self.width.max(1));
+        Some((
+            u16::try_from(index % width).ok()?,
+            u16::try_from(index / width).ok()?,
+        ))
+    }
+}
+
+/// Events of one pulse in an event message.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PulseEvents {
+    /// Pulse time in nanoseconds since the Unix epoch.
+    pub pulse_time_ns: i64,
+    /// Time of flight of each event in nanoseconds.
+    pub time_of_flight_ns: Vec<i64>,
+    /// Pixel ID of each event.
+    pub pixel_id: Vec<i64>,
+}
+
+/// A decoded `ev42` or `ev44` message.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct EventMessage {
+    /// Name of the detector or simulation that produced the events.
+    pub source_name: String,
+    /// Sequence number set by the producer.
+    pub message_id: i64,
+    /// Events grouped by pulse, in message order.
+    pub pulses: Vec<PulseEvents>,
+}
+
+impl EventMessage {
+    /// Hits of each pulse, with coordinates from `layout`.
+    ///
+    /// Times of flight become 25 ns ticks and the `ToT` column is zero, as
+    /// the schemas carry no amplitude. Returns the batches and the number of
+    /// events dropped for a negative time or a pixel outside the layout.
+    #[must_use]
+    pub fn to_batches(&self, layout: &PixelLayout) -> (Vec<EventBatch>, u64) {
+        let mut dropped = 0;
+        let batches = self
+            .pulses
+            .iter()
+            .map(|pulse| {
+                let mut hits = HitBatch::with_capacity(pulse.pixel_id.len());
+                for (&tof, &pixel) in pulse.time_of_flight_ns.iter().zip(&pulse.pixel_id) {
+                    let tof = u32::try_from(tof / TICK_NS).ok().filter(|_| tof >= 0);
+                    match (tof, layout.position(pixel)) {
+                        (Some(tof), Some((x, y))) => hits.push((x, y, tof, 0, tof, 0)),
+                        _ => dropped += 1,
+                    }
+                }
+                hits.sort_by_tof();
+                EventBatch {
+                    tdc_timestamp_25ns: u64::try_from(pulse.pulse_time_ns / TICK_NS).unwrap_or(0),
+                    hits,
+                }
+            })
+            .collect();
+        (batches, dropped)
+    }
+}
+
+/// Decodes an `ev42` or `ev44` flatbuffer.
+///
+/// Returns `Ok(None)` for buffers of another schema, which share a topic
+/// with event data at some instruments.
+///
+/// # Errors
+/// Returns an error if an `ev42`/`ev44` buffer is malformed.
+pub fn decode_event_message(buf: &[u8]) -> Result<Option<EventMessage>> {
+    if flatbuffers::buffer_has_identifier(buf, "ev42", false) {
+        decode_ev42(buf).map(Some)
+    } else if flatbuffers::buffer_has_identifier(buf, "ev44", false) {
+        decode_ev44(buf).map(Some)
+    } else {
+        Ok(None)
+    }
+}
+
+/// `ev42`: one pulse per message, unsigned times and pixel IDs.
+fn decode_ev42(buf: &[u8]) -> Result<EventMessage> {
+    let message = flatbuffers::root::<schema::EventMessage>(buf).map_err(malformed)?;
+    let time_of_flight: Vec<i64> = message
+        .time_of_flight()
+        .map(|values| values.iter().map(i64::from).collect())
+        .unwrap_or_default();
+    let pixel_id: Vec<i64> = message
+        .detector_id()
+        .map(|values| values.iter().map(i64::from).collect())
+        .unwrap_or_default();
+    if time_of_flight.len() != pixel_id.len() {
+        return Err(malformed(
+            "ev42 time_of_flight and detector_id lengths differ",
+        ));
+    }
+    Ok(EventMessage {
+        source_name: message.source_name().unwrap_or_default().to_string(),
+        message_id: message.message_id().cast_signed(),
+        pulses: vec![PulseEvents {
+            pulse_time_ns: message.pulse_time().cast_signed(),
+            time_of_flight_ns: time_of_flight,
+            pixel_id,
+        }],
+    })
+}
+
+/// `ev44`: several pulses per message, each starting at an index into the
+/// event arrays.
+fn decode_ev44(buf: &[u8]) -> Result<EventMessage> {
+    let message = flatbuffers::root::<schema::Event44Message>(buf).map_err(malformed)?;
+    let reference_time: Vec<i64> = message.reference_time().iter().collect();
+    let reference_index: Vec<i32> = message.reference_time_index().iter().collect();
+    let time_of_flight: Vec<i64> = message
+        .time_of_flight()
+        .map(|values| values.iter().map(i64::from).collect())
+        .unwrap_or_default();
+    let pixel_id: Vec<i64> = message
+        .pixel_id()
+        .map(|values| values.iter().map(i64::from).collect())
+        .unwrap_or_default();
+    if reference_time.len() != reference_index.len() || time_of_flight.len() != pixel_id.len() {
+        return Err(malformed("ev44 array lengths differ"));
+    }
+
+    let events = time_of_flight.len();
+    let mut pulses = Vec::with_capacity(reference_time.len());
+    for (i, &pulse_time_ns) in reference_time.iter().enumerate() {
+        let start = usize::try_from(reference_index[i]).unwrap_or(usize::MAX);
+        let end = reference_index
+            .get(i + 1)
+            .map_or(events, |&end| usize::try_from(end).unwrap_or(usize::MAX));
+        if start > end || end > events {
+            return Err(malformed("ev44 reference_time_index out of order"));
+        }
+        pulses.push(PulseEvents {
+            pulse_time_ns,
+            time_of_flight_ns: time_of_flight[start..end].to_vec(),
+            pixel_id: pixel_id[start..end].to_vec(),
+        });
+    }
+    Ok(EventMessage {
+        source_name: message.source_name().to_string(),
+        message_id: message.message_id(),
+        pulses,
+    })
+}
+
+fn malformed(what: impl std::fmt::Display) -> Error {
+    Error::InvalidFormat(format!("event message: {what}"))
+}
+
+/// Bindings for `schemas/ev42_events.fbs` and `schemas/ev44_events.fbs`.
+///
+/// Written by hand in the shape `flatc --rust` generates: each table has a
+/// verifier, and its accessors only run on tables that
+/// [`flatbuffers::root`] verified.
+mod schema {
+    use flatbuffers::{
+        Follow, ForwardsUOffset, InvalidFlatbuffer, Table, VOffsetT, Vector, Verifiable, Verifier,
+    };
+
+    /// Field in vtable slot `slot` of a verified table.
+    fn field<'a, T: Follow<'a> + 'a>(table: &Table<'a>, slot: VOffsetT) -> Option<T::Inner> {
+        // SAFETY: Tables are only reached through `flatbuffers::root`, whose
+        // verifier checked that each slot read here holds a `T`.
+        #[allow(unsafe_code)]
+        unsafe {
+            table.get::<T>(slot, None)
+        }
+    }
+
+    /// `ev42` root table.
+    pub struct EventMessage<'a> {
+        table: Table<'a>,
+    }
+
+    impl<'a> EventMessage<'a> {
+        const SOURCE_NAME: VOffsetT = 4;
+        const MESSAGE_ID: VOffsetT = 6;
+        const PULSE_TIME: VOffsetT = 8;
+        const TIME_OF_FLIGHT: VOffsetT = 10;
+        const DETECTOR_ID: VOffsetT = 12;
+
+        pub fn source_name(&self) -> Option<&'a str> {
+            field::<ForwardsUOffset<&str>>(&self.table, Self::SOURCE_NAME)
+        }
+
+        pub fn message_id(&self) -> u64 {
+            field::<u64>(&self.table, Self::MESSAGE_ID).unwrap_or(0)
+        }
+
+        pub fn pulse_time(&self) -> u64 {
+            field::<u64>(&self.table, Self::PULSE_TIME).unwrap_or(0)
+        }
+
+        pub fn time_of_flight(&self) -> Option<Vector<'a, u32>> {
+            field::<ForwardsUOffset<Vector<'a, u32>>>(&self.table, Self::TIME_OF_FLIGHT)
+        }
+
+        pub fn detector_id(&self) -> Option<Vector<'a, u32>> {
+            field::<ForwardsUOffset<Vector<'a, u32>>>(&self.table, Self::DETECTOR_ID)
+        }
+    }
+
+    #[allow(unsafe_code)]
+    impl<'a> Follow<'a> for EventMessage<'a> {
+        type Inner = Self;
+
+        unsafe fn follow(buf: &'a [u8], loc: usize) -> Self {
+            Self {
+                // SAFETY: Forwarded from the caller of `follow`.
+                table: unsafe { Table::new(buf, loc) },
+            }
+        }
+    }
+
+    impl Verifiable for EventMessage<'_> {
+        fn run_verifier(v: &mut Verifier, pos: usize) -> Result<(), InvalidFlatbuffer> {
+            v.visit_table(pos)?
+                .visit_field::<ForwardsUOffset<&str>>("source_name", Self::SOURCE_NAME, false)?
+                .visit_field::<u64>("message_id", Self::MESSAGE_ID, false)?
+                .visit_field::<u64>("pulse_time", Self::PULSE_TIME, false)?
+                .visit_field::<ForwardsUOffset<Vector<'_, u32>>>(
+                    "time_of_flight",
+                    Self::TIME_OF_FLIGHT,
+                    false,
+                )?
+                .visit_field::<ForwardsUOffset<Vector<'_, u32>>>(
+                    "detector_id",
+                    Self::DETECTOR_ID,
+                    false,
+                )?
+                .finish();
+            Ok(())
+        }
+    }
+
+    /// `ev44` root table.
+    pub struct Event44Message<'a> {
+        table: Table<'a>,
+    }
+
+    impl<'a> Event44Message<'a> {
+        const SOURCE_NAME: VOffsetT = 4;
+        const MESSAGE_ID: VOffsetT = 6;
+        const REFERENCE_TIME: VOffsetT = 8;
+        const REFERENCE_TIME_INDEX: VOffsetT = 10;
+        const TIME_OF_FLIGHT: VOffsetT = 12;
+        const PIXEL_ID: VOffsetT = 14;
+
+        pub fn source_name(&self) -> &'a str {
+            // Required, so present in every verified table.
+            field::<ForwardsUOffset<&str>>(&self.table, Self::SOURCE_NAME).unwrap_or_default()
+        }
+
+        pub fn message_id(&self) -> i64 {
+            field::<i64>(&self.table, Self::MESSAGE_ID).unwrap_or(0)
+        }
+
+        pub fn reference_time(&self) -> Vector<'a, i64> {
+            field::<ForwardsUOffset<Vector<'a, i64>>>(&self.table, Self::REFERENCE_TIME)
+                .unwrap_or_default()
+        }
+
+        pub fn reference_time_index(&self) -> Vector<'a, i32> {
+            field::<ForwardsUOffset<Vector<'a, i32>>>(&self.table, Self::REFERENCE_TIME_INDEX)
+                .unwrap_or_default()
+        }
+
+        pub fn time_of_flight(&self) -> Option<Vector<'a, i32>> {
+            field::<ForwardsUOffset<Vector<'a, i32>>>(&self.table, Self::TIME_OF_FLIGHT)
+        }
+
+        pub fn pixel_id(&self) -> Option<Vector<'a, i32>> {
+            field::<ForwardsUOffset<Vector<'a, i32>>>(&self.table, Self::PIXEL_ID)
+        }
+    }
+
+    #[allow(unsafe_code)]
+    impl<'a> Follow<'a> for Event44Message<'a> {
+        type Inner = Self;
+
+        unsafe fn follow(buf: &'a [u8], loc: usize) -> Self {
+            Self {
+                // SAFETY: Forwarded from the caller of `follow`.
+                table: unsafe { Table::new(buf, loc) },
+            }
+        }
+    }
+
+    impl Verifiable for Event44Message<'_> {
+        fn run_verifier(v: &mut Verifier, pos: usize) -> Result<(), InvalidFlatbuffer> {
+            v.visit_table(pos)?
+                .visit_field::<ForwardsUOffset<&str>>("source_name", Self::SOURCE_NAME, true)?
+                .visit_field::<i64>("message_id", Self::MESSAGE_ID, false)?
+                .visit_field::<ForwardsUOffset<Vector<'_, i64>>>(
+                    "reference_time",
+                    Self::REFERENCE_TIME,
+                    true,
+                )?
+                .visit_field::<ForwardsUOffset<Vector<'_, i32>>>(
+                    "reference_time_index",
+                    Self::REFERENCE_TIME_INDEX,
+                    true,
+                )?
+                .visit_field::<ForwardsUOffset<Vector<'_, i32>>>(
+                    "time_of_flight",
+                    Self::TIME_OF_FLIGHT,
+                    false,
+                )?
+                .visit_field::<ForwardsUOffset<Vector<'_, i32>>>("pixel_id", Self::PIXEL_ID, false)?
+                .finish();
+            Ok(())
+        }
+    }
+}
+
+/// Settings of a [`KafkaEventConsumer`].
+#[derive(Clone, Debug)]
+pub struct KafkaConfig {
+    /// Brokers to bootstrap from, as comma-separated `host:port`.
+    pub bootstrap: String,
+    /// Topic with the event messages.
+    pub topic: String,
+    /// Consumer group. The brokers keep the group's committed offsets, and
+    /// consumers of one group share the topic's partitions between them.
+    pub group_id: String,
+    /// Pixel ID to image coordinate mapping.
+    pub layout: PixelLayout,
+    /// Where to start without a committed offset, or after the committed
+    /// offset has expired on the broker.
+    pub start: StartOffset,
+    /// Longest time a poll waits for new messages.
+    pub max_wait: Duration,
+    /// Further librdkafka properties, applied last, e.g.
+    /// `security.protocol`, `ssl.ca.location`, `sasl.mechanism`,
+    /// `sasl.username` and `sasl.password` for TLS and SASL.
+    pub properties: Vec<(String, String)>,
+}
+
+impl KafkaConfig {
+    /// Read `topic` in consumer group `group_id`, from the earliest message
+    /// if the group has no committed offset.
+    #[must_use]
+    pub fn new(
+        bootstrap: impl Into<String>,
+        topic: impl Into<String>,
+        group_id: impl Into<String>,
+        layout: PixelLayout,
+    ) -> Self {
+        Self {
+            bootstrap: bootstrap.into(),
+            topic: topic.into(),
+            group_id: group_id.into(),
+            layout,
+            start: StartOffset::Earliest,
+            max_wait: Duration::from_millis(500),
+            properties: Vec::new(),
+        }
+    }
+
+    /// Adds a librdkafka property; see [`KafkaConfig::properties`].
+    #[must_use]
+    pub fn with_property(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.properties.push((key.into(), value.into()));
+        self
+    }
+
+    /// librdkafka settings of the consumer.
+    fn client_config(&self) -> ClientConfig {
+        let mut client = ClientConfig::new();
+        client
+            .set("bootstrap.servers", &self.bootstrap)
+            .set("group.id", &self.group_id)
+            .set("client.id", CLIENT_ID)
+            .set("enable.auto.commit", "false")
+            .set(
+                "auto.offset.reset",
+                match self.start {
+                    StartOffset::Earliest => "earliest",
+                    StartOffset::Latest => "latest",
+                },
+            );
+        for (key, value) in &self.properties {
+            client.set(key, value);
+        }
+        client
+    }
+}
+
+/// Reads pulses of ESS event data from a Kafka topic.
+pub struct KafkaEventConsumer {
+    config: KafkaConfig,
+    consumer: BaseConsumer,
+    /// Events dropped for a negative time or a pixel outside the layout.
+    dropped: u64,
+    /// Error met after some pulses of a poll, reported by the next poll.
+    deferred: Option<Error>,
+}
+
+impl KafkaEventConsumer {
+    /// Joins the configured consumer group and subscribes to the topic.
+    ///
+    /// Partitions are assigned and the brokers contacted in the background,
+    /// during the first polls.
+    ///
+    /// # Errors
+    /// Returns an error if the configuration is rejected by librdkafka.
+    pub fn connect(config: KafkaConfig) -> Result<Self> {
+        let consumer: BaseConsumer = config.client_config().create()?;
+        consumer.subscribe(&[config.topic.as_str()])?;
+        Ok(Self {
+            config,
+            consumer,
+            dropped: 0,
+            deferred: None,
+        })
+    }
+
+    /// Events dropped so far for a negative time of flight or a pixel ID
+    /// outside the layout.
+    #[must_use]
+    pub fn dropped_events(&self) -> u64 {
+        self.dropped
+    }
+
+    /// Returns the pulses of the messages that arrived, in delivery order.
+    ///
+    /// Waits up to the configured `max_wait` for a first message, then takes
+    /// whatever else is ready, up to 64 MiB of messages. Returns no pulses if
+    /// none arrived. Messages of other schemas are skipped.
+    ///
+    /// # Errors
+    /// Returns an error if a message is malformed, or librdkafka reports an
+    /// error such as a lost broker connection. An error met after some
+    /// pulses is returned by the next poll instead. The consumer can be
+    /// polled again after an error: malformed messages are skipped, and
+    /// librdkafka keeps retrying connections itself.
+    pub fn poll(&mut self) -> Result<Vec<EventBatch>> {
+        if let Some(err) = self.deferred.take() {
+            return Err(err);
+        }
+        let mut pulses: Vec<EventBatch> = Vec::new();
+        let mut wait = self.config.max_wait;
+        let mut bytes = 0;
+        while bytes < MAX_POLL_BYTES {
+            let Some(message) = self.consumer.poll(wait) else {
+                break;
+            };
+            wait = Duration::ZERO;
+            let decoded = message.map_err(Error::from).and_then(|message| {
+                bytes += message.payload_len();
+                message.payload().map_or(Ok(None), decode_event_message)
+            });
+            let event = match decoded {
+                Ok(Some(event)) => event,
+                Ok(None) => continue,
+                // Return the pulses read so far first; the failed message
+                // is skipped either way.
+                Err(err) if !pulses.is_empty() => {
+                    self.deferred = Some(err);
+                    break;
+                }
+                Err(err) => return Err(err),
+            };
+            let (batches, dropped) = event.to_batches(&self.config.layout);
+            self.dropped += dropped;
+            for batch in batches {
+                // Producers may split a pulse over consecutive messages.
+                match pulses.last_mut() {
+                    Some(last) if last.tdc_timestamp_25ns == batch.tdc_timestamp_25ns => {
+                        last.hits.append(&batch.hits);
+                        last.hits.sort_by_tof();
+                    }
+                    _ => pulses.push(batch),
+                }
+            }
+        }
+        Ok(pulses)
+    }
+
+    /// Commits the position after the pulses returned so far to the
+    /// consumer group, so a restarted consumer continues from there.
+    ///
+    /// Call it once the returned pulses have been written; anything polled
+    /// after the last commit is read again after a restart.
+    ///
+    /// # Errors
+    /// Returns an error if the brokers reject the commit.
+    pub fn commit(&self) -> Result<()> {
+        match self.consumer.commit_consumer_state(CommitMode::Sync) {
+            // Nothing was consumed since the last commit.
+            Ok(()) | Err(KafkaError::ConsumerCommit(RDKafkaErrorCode::NoOffset)) => Ok(()),
+            Err(err) => Err(err.into()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flatbuffers::FlatBufferBuilder;
+
+    fn ev42(source_name: &str, pulse_time: u64, events: &[(u32, u32)]) -> Vec<u8> {
+        let mut fbb = FlatBufferBuilder::new();
+        let name = fbb.create_string(source_name);
+        let tof = fbb.create_vector(&events.iter().map(|e| e.0).collect::<Vec<_>>());
+        let ids = fbb.create_vector(&events.iter().map(|e| e.1).collect::<Vec<_>>());
+        let table = fbb.start_table();
+        fbb.push_slot_always(4, name);
+        fbb.push_slot(8, pulse_time, 0);
+        fbb.push_slot_always(10, tof);
+        fbb.push_slot_always(12, ids);
+        let root = fbb.end_table(table);
+        fbb.finish(root, Some("ev42"));
+        fbb.finished_data().to_vec()
+    }
+
+    fn ev44(message_id: i64, pulses: &[(i64, &[(i32, i32)])]) -> Vec<u8> {
+        let mut index = Vec::new();
+        let mut tof = Vec::new();
+        let mut pixel = Vec::new();
+        for (_, events) in pulses {
+            index.push(i32::try_from(tof.len()).unwrap());
+            for &(t, p) in *events {
+                tof.push(t);
+                pixel.push(p);
+            }
+        }
+        let mut fbb = FlatBufferBuilder::new();
+        let name = fbb.create_string("loki");
+        let times = fbb.create_vector(&pulses.iter().map(|p| p.0).collect::<Vec<_>>());
+        let index = fbb.create_vector(&index);
+        let tof = fbb.create_vector(&tof);
+        let pixel = fbb.create_vector(&pixel);
+        let table = fbb.start_table();
+        fbb.push_slot_always(4, name);
+        fbb.push_slot(6, message_id, 0);
+        fbb.push_slot_always(8, times);
+        fbb.push_slot_always(10, index);
+        fbb.push_slot_always(12, tof);
+        fbb.push_slot_always(14, pixel);
+        let root = fbb.end_table(table);
+        fbb.finish(root, Some("ev44"));
+        fbb.finished_data().to_vec()
+    }
+
+    #[test]
+    fn test_decode_ev42_and_ev44() {
+        let layout = PixelLayout {
+            width: 4,
+            first_pixel_id: 1,
+        };
+        let ev42 = ev42("dream", 1_000_000, &[(500, 6), (100, 1), (75, 0)]);
+        let message = decode_event_message(&ev42).unwrap().unwrap();
+        assert_eq!(message.source_name, "dream");
+        let (batches, dropped) = message.to_batches(&layout);
+        assert_eq!(dropped, 1, "pixel ID 0 is before the first pixel");
+        assert_eq!(batches[0].tdc_timestamp_25ns, 40_000);
+        assert_eq!(
+            (&batches[0].hits.x, &batches[0].hits.y, &batches[0].hits.tof),
+            (&vec![0, 1], &vec![0, 1], &vec![4, 20])
+        );
+
+        let ev44 = ev44(7, &[(2500, &[(50, 1)]), (5000, &[(25, 2), (75, 3)])]);
+        let message = decode_event_message(&ev44).unwrap().unwrap();
+        assert_eq!(message.message_id, 7);
+        let (batches, _) = message.to_batches(&layout);
+        let stamps: Vec<_> = batches.iter().map(|b| b.tdc_timestamp_25ns).collect();
+        assert_eq!(stamps, vec![100, 200]);
+        assert_eq!(batches[1].hits.x, vec![1, 2]);
+        assert!(decode_event_message(b"\0\0\0\0f144").unwrap().is_none());
+    }
+
+    #[test]
+    fn test_decode_rejects_malformed_messages() {
+        let mut truncated = ev44(1, &[(2500, &[(50, 1)])]);
+        truncated.truncate(truncated.len() / 2);
+        assert!(decode_event_message(&truncated).is_err());
+        assert!(decode_event_message(b"\0\0\0\0ev44").is_err());
+
+        let mut fbb = FlatBufferBuilder::new();
+        let name = fbb.create_string("loki");
+        let times = fbb.create_vector(&[2500i64, 5000]);
+        let index = fbb.create_vector(&[1i32, 0]);
+        let values = fbb.create_vector(&[50i32]);
+        let table = fbb.start_table();
+        fbb.push_slot_always(4, name);
+        fbb.push_slot_always(8, times);
+        fbb.push_slot_always(10, index);
+        fbb.push_slot_always(12, values);
+        fbb.push_slot_always(14, values);
+        let root = fbb.end_table(table);
+        fbb.finish(root, Some("ev44"));
+        let err = decode_event_message(fbb.finished_data()).unwrap_err();
+        assert!(err.to_string().contains("out of order"), "{err}");
+    }
+
+    #[test]
+    fn test_client_config() {
+        let layout = PixelLayout {
+            width: 4,
+            first_pixel_id: 0,
+        };
+        let config = KafkaConfig {
+            start: StartOffset::Latest,
+            ..KafkaConfig::new("broker:9093", "loki_detector", "reduction", layout)
+        }
+        .with_property("security.protocol", "sasl_ssl")
+        .with_property("enable.auto.commit", "true");
+        let client = config.client_config();
+        assert_eq!(client.get("group.id"), Some("reduction"));
+        assert_eq!(client.get("auto.offset.reset"), Some("latest"));
+        assert_eq!(client.get("security.protocol"), Some("sasl_ssl"));
+        assert_eq!(client.get("enable.auto.commit"), Some("true"));
+    }
+
+    /// Runs against the broker in `RUSTPIX_KAFKA_BOOTSTRAP`, as in CI.
+    #[test]
+    #[ignore = "needs a Kafka broker"]
+    fn test_kafka_consumer_group_resumes_after_commit() {
+        use rdkafka::producer::{BaseProducer, BaseRecord, Producer};
+        use std::time::{SystemTime, UNIX_EPOCH};
+
+        let bootstrap = std::env::var("RUSTPIX_KAFKA_BOOTSTRAP").unwrap();
+        let unique = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_nanos();
+        let topic = format!("rustpix-test-{unique}");
+        let producer: BaseProducer = ClientConfig::new()
+            .set("bootstrap.servers", &bootstrap)
+            .set("compression.type", "zstd")
+            .create()
+            .unwrap();
+        let produce = |messages: &[Vec<u8>]| {
+            for message in messages {
+                producer
+                    .send(BaseRecord::<(), _>::to(&topic).payload(message))
+                    .unwrap();
+            }
+            producer.flush(Duration::from_secs(10)).unwrap();
+        };
+        // Poll until `count` pulses arrived; the group is joined lazily.
+        let poll = |consumer: &mut KafkaEventConsumer, count: usize| {
+            let mut pulses = Vec::new();
+            for _ in 0..60 {
+                pulses.extend(consumer.poll().unwrap());
+                if pulses.len() >= count {
+                    break;
+                }
+            }
+            pulses
+        };
+
+        let layout = PixelLayout {
+            width: 4,
+            first_pixel_id: 0,
+        };
+        let config = KafkaConfig::new(bootstrap.as_str(), topic.as_str(), "rustpix-test", layout)
+            .with_property("topic.metadata.refresh.interval.ms", "500");
+        produce(&[
+            ev44(0, &[(2500, &[(50, 0), (75, 1)])]),
+            ev44(1, &[(5000, &[(50, 5)])]),
+        ]);
+        let mut consumer = KafkaEventConsumer::connect(config.clone()).unwrap();
+        let pulses = poll(&mut consumer, 2);
+        let hits: Vec<usize> = pulses.iter().map(|pulse| pulse.hits.len()).collect();
+        assert_eq!(hits, vec![2, 1]);
+        assert_eq!(pulses[1].hits.y, vec![1]);
+        consumer.commit().unwrap();
+        drop(consumer);
+
+        produce(&[ev44(2, &[(7500, &[(25, 6)])])]);
+        let mut resumed = KafkaEventConsumer::connect(config).unwrap();
+        let pulses = poll(&mut resumed, 1);
+        let stamps: Vec<_> = pulses.iter().map(|b| b.tdc_timestamp_25ns).collect();
+        assert_eq!(stamps, vec![300]);
+    }
+}
@@ -5,28 +5,108 @@
 //!
 #![warn(missing_docs)]
 
+#[cfg(feature = "arrow")]
+mod arrow;
+mod binary;
+pub mod container;
 mod error;
+mod fits;
+mod generic_hits;
 #[cfg(feature = "hdf5")]
 pub mod hdf5;
+mod incremental;
+#[cfg(feature = "kafka")]
+mod kafka;
+#[cfg(feature = "serde")]
+mod macro_script;
+#[cfg(feature = "hdf5")]
+pub mod nexus;
 pub mod out_of_core;
 mod out_of_core_pipeline;
+mod packed;
+#[cfg(feature = "parquet")]
+mod parquet;
+#[cfg(feature = "serde")]
+mod pixel_mask;
+#[cfg(feature = "zmq")]
+mod publish;
+mod readback;
 mod reader;
+#[cfg(feature = "object-store")]
+pub mod remote;
+#[cfg(feature = "root")]
+mod root;
+pub mod run;
 pub mod scanner;
+mod socket;
+mod tiff;
+mod write_behind;
 mod writer;
 
+#[cfg(feature = "arrow")]
+pub use arrow::{
+    hit_record_batch, hit_schema, neutron_record_batch, neutron_schema, ArrowIpcHitWriter,
+    ArrowIpcNeutronWriter,
+};
+pub use binary::{
+    decode_neutron_record, encode_legacy_record, encode_neutron_record, read_neutron_batch_binary,
+    LEGACY_RECORD_BYTES, NEUTRON_RECORD_BYTES,
+};
+pub use container::{
+    BlockData, BlockInfo, BlockKind, Compression, ContainerReader, ContainerWriter, VerifyReport,
+};
 pub use error::{Error, Result};
+pub use fits::{write_fits_cube, write_fits_image, FitsOptions, FitsValue, TIMEPIX_PIXEL_SIZE_MM};
+pub use generic_hits::{read_generic_csv_hits, GenericHitOptions, HitColumns};
 #[cfg(feature = "hdf5")]
 pub use hdf5::{
-    write_combined_hdf5, write_combined_hdf5_batches, Hdf5HistogramSink, Hdf5HitSink,
-    Hdf5NeutronSink, HistogramAxisData, HistogramBin, PixelMaskWriteData, PixelMaskWriteOptions,
+    append_hdf5_event_batches, merge_hdf5_events, read_generic_hdf5_hits, write_combined_hdf5,
+    write_combined_hdf5_batches, Hdf5HistogramSink, Hdf5HitSink, Hdf5NeutronSink,
+    HistogramAxisData, HistogramBin, PixelMaskWriteData, PixelMaskWriteOptions,
+};
+pub use incremental::IncrementalTpx3Reader;
+#[cfg(feature = "kafka")]
+pub use kafka::{
+    decode_event_message, EventMessage, KafkaConfig, KafkaEventConsumer, PixelLayout, PulseEvents,
+    StartOffset,
+};
+#[cfg(feature = "serde")]
+pub use macro_script::{
+    Macro, MacroExport, MacroExportFormat, MacroParameters, MacroStep, MACRO_FORMAT_VERSION,
+};
+#[cfg(feature = "hdf5")]
+pub use nexus::{NexusEventWriter, NexusWriteOptions};
+pub use out_of_core::{
+    pulse_batches, pulse_batches_from, ChunkStats, OutOfCoreConfig, PulseBatchGroup, PulseBatcher,
+    PulseSlice,
 };
-pub use out_of_core::{pulse_batches, OutOfCoreConfig, PulseBatchGroup, PulseBatcher, PulseSlice};
 pub use out_of_core_pipeline::{
-    out_of_core_neutron_stream, out_of_core_neutron_stream_handle, OutOfCoreNeutronStream,
-    OutOfCoreNeutronStreamHandle, PulseNeutronBatch, ThreadedOutOfCoreNeutronStream,
+    out_of_core_neutron_stream, out_of_core_neutron_stream_from, out_of_core_neutron_stream_handle,
+    OutOfCoreNeutronStream, OutOfCoreNeutronStreamHandle, PulseNeutronBatch, StageTimings,
+    ThreadedOutOfCoreNeutronStream,
+};
+pub use packed::{
+    encode_packed_neutrons, read_neutron_batch_packed, DEFAULT_PACKED_POSITION_SCALE,
+};
+#[cfg(feature = "parquet")]
+pub use parquet::{ParquetHitWriter, ParquetNeutronWriter, ParquetWriteOptions};
+#[cfg(feature = "serde")]
+pub use pixel_mask::{PixelMask, PixelMaskDetection, PIXEL_MASK_FORMAT, PIXEL_MASK_FORMAT_VERSION};
+#[cfg(feature = "zmq")]
+pub use publish::{NeutronPublisher, DEFAULT_TOPIC};
+pub use readback::{
+    read_hit_batch_csv, read_neutron_batch_csv, read_neutron_batch_csv_with_pulse_ids,
+    read_processed_file, ProcessedData,
 };
 pub use reader::{
-    EventBatch, MappedFileReader, TimeOrderedEventStream, TimeOrderedHitStream, Tpx3FileReader,
+    EventBatch, HitIter, MappedFileReader, ReadMode, SectionIndex, StreamPosition,
+    TimeOrderedEventStream, TimeOrderedHitStream, Tpx3FileReader, NO_MMAP_ENV,
 };
+#[cfg(feature = "root")]
+pub use root::{RootHitWriter, RootNeutronWriter};
+pub use run::{RunEventStream, Tpx3RunReader, DEFAULT_PULSE_TOLERANCE_25NS};
 pub use scanner::PacketScanner;
+pub use socket::Tpx3SocketReader;
+pub use tiff::{write_tiff_stack, TiffBitDepth, TiffStackSummary, TiffStackWriter, TofHistogram};
+pub use write_behind::{WriteBehind, WriteBehindStats};
 pub use writer::DataFileWriter;
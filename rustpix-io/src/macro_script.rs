@@ -0,0 +1,263 @@
+//! Recorded processing macros.
+//!
+//! A macro is the list of steps of an interactive session (load a file, set
+//! the processing parameters, process, export) in JSON form. The GUI records
+//! them and `rustpix run-macro` replays them headlessly, so an exploration can
+//! be reproduced as a batch job.
+
+use rustpix_algorithms::{AlgorithmParams, ClusteringAlgorithm, Connectivity};
+use rustpix_core::clustering::ClusteringConfig;
+use rustpix_core::extraction::ExtractionConfig;
+use rustpix_tpx::DetectorConfig;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+/// Version written to new macros.
+pub const MACRO_FORMAT_VERSION: u32 = 1;
+
+/// A replayable list of session steps.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Macro {
+    /// Format version, [`MACRO_FORMAT_VERSION`] for current macros.
+    pub version: u32,
+    /// Steps in the order they were performed.
+    pub steps: Vec<MacroStep>,
+}
+
+impl Macro {
+    /// Macro of the current format version.
+    #[must_use]
+    pub fn new(steps: Vec<MacroStep>) -> Self {
+        Self {
+            version: MACRO_FORMAT_VERSION,
+            steps,
+        }
+    }
+}
+
+/// One recorded action, tagged by `action` in JSON.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum MacroStep {
+    /// Open a TPX3 file.
+    Load {
+        /// File path.
+        path: PathBuf,
+    },
+    /// Set the parameters used by later `process` steps.
+    SetParameters(Box<MacroParameters>),
+    /// Cluster the loaded hits and extract neutrons.
+    Process,
+    /// Write the current results.
+    Export(MacroExport),
+}
+
+/// Detector, clustering and extraction parameters of a `process` step.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct MacroParameters {
+    /// Clustering algorithm.
+    pub algorithm: ClusteringAlgorithm,
+    /// Spatial radius for clustering (pixels).
+    pub radius: f64,
+    /// Temporal window for clustering (nanoseconds).
+    pub temporal_window_ns: f64,
+    /// Minimum cluster size.
+    pub min_cluster_size: u16,
+    /// Maximum cluster size (None = unlimited).
+    pub max_cluster_size: Option<u16>,
+    /// DBSCAN minimum points for a seed cluster.
+    pub dbscan_min_points: usize,
+    /// Grid cell size (pixels).
+    pub grid_cell_size: usize,
+    /// Hierarchical: largest box gap of merged clusters (pixels).
+    #[serde(default = "default_merge_distance")]
+    pub hierarchical_merge_distance: f64,
+    /// Hierarchical: largest TOF gap of merged clusters (nanoseconds).
+    #[serde(default = "default_merge_window_ns")]
+    pub hierarchical_merge_window_ns: f64,
+    /// Connected components: pixel neighbourhood linking hits.
+    #[serde(default)]
+    pub connectivity: Connectivity,
+    /// Sub-pixel resolution multiplier for extraction.
+    pub super_resolution_factor: f64,
+    /// Weight centroids by TOT values.
+    pub weighted_by_tot: bool,
+    /// Minimum TOT threshold (0 = disabled).
+    pub min_tot_threshold: u16,
+    /// Detector layout and timing, including the TDC frequency.
+    pub detector: DetectorConfig,
+}
+
+impl Default for MacroParameters {
+    /// ABS clustering with the library defaults on a VENUS detector.
+    fn default() -> Self {
+        let clustering = ClusteringConfig::default();
+        let extraction = ExtractionConfig::default();
+        let params = AlgorithmParams::default();
+        Self {
+            algorithm: ClusteringAlgorithm::Abs,
+            radius: clustering.radius,
+            temporal_window_ns: clustering.temporal_window_ns,
+            min_cluster_size: clustering.min_cluster_size,
+            max_cluster_size: clustering.max_cluster_size,
+            dbscan_min_points: params.dbscan_min_points,
+            grid_cell_size: params.grid_cell_size,
+            hierarchical_merge_distance: params.hierarchical_merge_distance,
+            hierarchical_merge_window_ns: params.hierarchical_merge_window_ns,
+            connectivity: params.connectivity,
+            super_resolution_factor: extraction.super_resolution_factor,
+            weighted_by_tot: extraction.weighted_by_tot,
+            min_tot_threshold: extraction.min_tot_threshold,
+            detector: DetectorConfig::venus_defaults(),
+        }
+    }
+}
+
+impl MacroParameters {
+    /// Clustering configuration of these parameters.
+    #[must_use]
+    pub fn clustering(&self) -> ClusteringConfig {
+        ClusteringConfig {
+            radius: self.radius,
+            temporal_window_ns: self.temporal_window_ns,
+            min_cluster_size: self.min_cluster_size,
+            max_cluster_size: self.max_cluster_size,
+        }
+    }
+
+    /// Extraction configuration of these parameters.
+    #[must_use]
+    pub fn extraction(&self) -> ExtractionConfig {
+        ExtractionConfig {
+            super_resolution_factor: self.super_resolution_factor,
+            weighted_by_tot: self.weighted_by_tot,
+            min_tot_threshold: self.min_tot_threshold,
+            tot_calibration: None,
+            event_classifier: None,
+            cluster_features: false,
+        }
+    }
+
+    /// Algorithm-specific parameters.
+    #[must_use]
+    pub fn algorithm_params(&self) -> AlgorithmParams {
+        AlgorithmParams {
+            dbscan_min_points: self.dbscan_min_points,
+            grid_cell_size: self.grid_cell_size,
+            hierarchical_merge_distance: self.hierarchical_merge_distance,
+            hierarchical_merge_window_ns: self.hierarchical_merge_window_ns,
+            connectivity: self.connectivity,
+            ..AlgorithmParams::default()
+        }
+    }
+}
+
+fn default_merge_distance() -> f64 {
+    AlgorithmParams::default().hierarchical_merge_distance
+}
+
+fn default_merge_window_ns() -> f64 {
+    AlgorithmParams::default().hierarchical_merge_window_ns
+}
+
+/// Output format of an `export` step.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MacroExportFormat {
+    /// HDF5 file with `NeXus` layout.
+    Hdf5,
+    /// Folder of TIFF images, one per TOF bin.
+    TiffFolder,
+    /// Single multi-page TIFF.
+    TiffStack,
+    /// FITS cube with WCS axis keywords.
+    Fits,
+}
+
+/// An `export` step.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[allow(clippy::struct_excessive_bools)]
+pub struct MacroExport {
+    /// Output file, or folder for TIFF exports.
+    pub path: PathBuf,
+    /// Output format.
+    pub format: MacroExportFormat,
+    /// Include hits (HDF5).
+    #[serde(default)]
+    pub hits: bool,
+    /// Include neutrons (HDF5).
+    #[serde(default)]
+    pub neutrons: bool,
+    /// Include the histogram (HDF5).
+    #[serde(default)]
+    pub histogram: bool,
+    /// Flight path in meters stored with the events.
+    #[serde(default)]
+    pub flight_path_m: Option<f64>,
+    /// TOF offset in nanoseconds stored with the events.
+    #[serde(default)]
+    pub tof_offset_ns: Option<f64>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_macro_json_layout() {
+        let json = r#"{
+            "version": 1,
+            "steps": [
+                {"action": "load", "path": "run.tpx3"},
+                {"action": "process"},
+                {"action": "export", "path": "run.h5", "format": "hdf5", "neutrons": true}
+            ]
+        }"#;
+        let parsed: Macro = serde_json::from_str(json).unwrap();
+        assert_eq!(parsed.version, MACRO_FORMAT_VERSION);
+        assert_eq!(parsed.steps.len(), 3);
+        assert!(matches!(&parsed.steps[0], MacroStep::Load { path } if path.ends_with("run.tpx3")));
+        let MacroStep::Export(export) = &parsed.steps[2] else {
+            panic!("expected an export step");
+        };
+        assert_eq!(export.format, MacroExportFormat::Hdf5);
+        assert!(export.neutrons && !export.hits && !export.histogram);
+        assert_eq!(export.flight_path_m, None);
+    }
+
+    #[test]
+    fn test_parameters_round_trip() {
+        let parameters = MacroParameters {
+            algorithm: ClusteringAlgorithm::Dbscan,
+            radius: 3.5,
+            temporal_window_ns: 100.0,
+            min_cluster_size: 2,
+            max_cluster_size: Some(40),
+            dbscan_min_points: 4,
+            grid_cell_size: 16,
+            hierarchical_merge_distance: 12.0,
+            hierarchical_merge_window_ns: 300.0,
+            connectivity: Connectivity::Four,
+            super_resolution_factor: 8.0,
+            weighted_by_tot: true,
+            min_tot_threshold: 10,
+            detector: DetectorConfig::venus_defaults(),
+        };
+        let recorded = Macro::new(vec![MacroStep::SetParameters(Box::new(parameters))]);
+        let json = serde_json::to_string(&recorded).unwrap();
+        assert!(json.contains(r#""action":"set_parameters""#));
+        assert!(json.contains(r#""algorithm":"dbscan""#));
+
+        let parsed: Macro = serde_json::from_str(&json).unwrap();
+        let MacroStep::SetParameters(parameters) = &parsed.steps[0] else {
+            panic!("expected a set_parameters step");
+        };
+        assert_eq!(parameters.max_cluster_size, Some(40));
+        assert_eq!(parameters.algorithm_params().dbscan_min_points, 4);
+        let params = parameters.algorithm_params();
+        assert!((params.hierarchical_merge_window_ns - 300.0).abs() < f64::EPSILON);
+        assert_eq!(params.connectivity, Connectivity::Four);
+        assert!((parameters.clustering().radius - 3.5).abs() < f64::EPSILON);
+        assert!(parameters.extraction().weighted_by_tot);
+    }
+}
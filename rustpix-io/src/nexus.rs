@@ -0,0 +1,430 @@
+//! NeXus event files in the layout Mantid's `LoadEventNexus` reads.
+//!
+//! Unlike the rustpix HDF5 schema (see [`crate::hdf5`]), which keeps integer
+//! nanoseconds and extra per-event columns, this writes only the standard
+//! `NXevent_data` fields with the types and units Mantid expects:
+//!
+//! ```text
+//! /entry                     (NXentry)
+//!   start_time               ISO 8601 string
+//!   instrument/name          (NXinstrument; attributes `flight_path_m` and
+//!                            `tof_offset_ns` when the flight path is known)
+//!   neutron_events/          (NXevent_data)
+//!   hit_events/              (NXevent_data)
+//!     event_id               u32, y * x_size + x
+//!     event_time_offset      f32, microsecond
+//!     event_time_zero        f64, second (attribute `offset` = start_time)
+//!     event_index            u64
+//! ```
+//!
+//! Event groups are created on the first batch written to them, so a file
+//! holds neutrons, hits or both. Mantid names the banks `neutron` and `hit`;
+//! load one with `LoadEventNexus(..., BankName="neutron")`.
+
+use crate::hdf5::{
+    append_slice, create_extendable_dataset, detector_size, normalize_super_resolution,
+    set_attr_str_file, set_attr_str_group, set_dataset_units, to_var_len_unicode, NS_PER_TICK,
+};
+use crate::reader::EventBatch;
+use crate::{Error, Result};
+use hdf5::types::VarLenUnicode;
+use hdf5::{Dataset, File, Group};
+use rustpix_core::neutron::NeutronBatch;
+use rustpix_tpx::DetectorConfig;
+use std::path::Path;
+
+/// Start time used when none is given: Mantid's epoch, so pulse times read
+/// back equal the raw TDC timestamps.
+pub const DEFAULT_START_TIME: &str = "1990-01-01T00:00:00";
+
+/// NeXus event file write configuration.
+#[derive(Clone, Debug)]
+pub struct NexusWriteOptions {
+    /// Detector X size in pixels.
+    pub x_size: u32,
+    /// Detector Y size in pixels.
+    pub y_size: u32,
+    /// Super-resolution factor used for neutron coordinates.
+    pub super_resolution_factor: f64,
+    /// Chunk size along the event dimension.
+    pub chunk_events: usize,
+    /// Optional gzip compression level (0-9).
+    pub compression: Option<u8>,
+    /// Instrument name written to `entry/instrument/name`; Mantid uses it to
+    /// find the instrument definition.
+    pub instrument_name: String,
+    /// Run start (ISO 8601) that pulse times are relative to.
+    pub start_time: String,
+    /// Flight path in metres, written with the TOF offset as attributes of
+    /// `entry/instrument` so energy axes can be reproduced.
+    pub flight_path_m: Option<f64>,
+    /// TOF offset in nanoseconds (written only with a flight path).
+    pub tof_offset_ns: f64,
+}
+
+impl NexusWriteOptions {
+    /// Build write options from detector config and defaults.
+    #[must_use]
+    pub fn from_detector_config(config: &DetectorConfig) -> Self {
+        let (x_size, y_size) = detector_size(config);
+        Self {
+            x_size,
+            y_size,
+            super_resolution_factor: 1.0,
+            chunk_events: 100_000,
+            compression: Some(1),
+            instrument_name: "TPX3".to_string(),
+            start_time: DEFAULT_START_TIME.to_string(),
+            flight_path_m: config.flight_path_m,
+            tof_offset_ns: config.tof_offset_ns,
+        }
+    }
+}
+
+/// Streaming writer for NeXus event files loadable by Mantid.
+pub struct NexusEventWriter {
+    file: File,
+    entry: Group,
+    neutrons: Option<EventBank>,
+    hits: Option<EventBank>,
+    options: NexusWriteOptions,
+}
+
+impl NexusEventWriter {
+    /// Create a new NeXus event file at `path`.
+    ///
+    /// # Errors
+    /// Returns an error if the HDF5 file or groups cannot be created.
+    pub fn create<P: AsRef<Path>>(path: P, options: NexusWriteOptions) -> Result<Self> {
+        let file = File::create(path)?;
+        set_attr_str_file(&file, "NX_class", "NXroot")?;
+
+        let entry = file.create_group("entry")?;
+        set_attr_str_group(&entry, "NX_class", "NXentry")?;
+        write_string_dataset(&entry, "start_time", &options.start_time)?;
+        let instrument = entry.create_group("instrument")?;
+        set_attr_str_group(&instrument, "NX_class", "NXinstrument")?;
+        write_string_dataset(&instrument, "name", &options.instrument_name)?;
+        if let Some(flight_path_m) = options.flight_path_m {
+            for (name, value) in [
+                ("flight_path_m", flight_path_m),
+                ("tof_offset_ns", options.tof_offset_ns),
+            ] {
+                instrument
+                    .new_attr::<f64>()
+                    .create(name)?
+                    .write_scalar(&value)?;
+            }
+        }
+
+        Ok(Self {
+            file,
+            entry,
+            neutrons: None,
+            hits: None,
+            options,
+        })
+    }
+
+    /// Append the neutrons of one pulse.
+    ///
+    /// # Errors
+    /// Returns an error if HDF5 I/O fails or a neutron lies outside the
+    /// detector.
+    pub fn write_neutrons(
+        &mut self,
+        tdc_timestamp_25ns: u64,
+        neutrons: &NeutronBatch,
+    ) -> Result<()> {
+        if neutrons.is_empty() {
+            return Ok(());
+        }
+        let super_res = normalize_super_resolution(self.options.super_resolution_factor);
+        neutrons
+            .check_super_resolution(super_res)
+            .map_err(rustpix_core::Error::from)?;
+        let event_id = neutrons
+            .x
+            .iter()
+            .zip(&neutrons.y)
+            .map(|(&x, &y)| {
+                let (x, y) = ((x / super_res).round(), (y / super_res).round());
+                if !(x.is_finite() && y.is_finite() && x >= 0.0 && y >= 0.0) {
+                    return Err(Error::InvalidFormat(
+                        "neutron x/y must be finite and non-negative".to_string(),
+                    ));
+                }
+                #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+                pixel_id(x as u32, y as u32, &self.options)
+            })
+            .collect::<Result<Vec<u32>>>()?;
+        let bank = match &mut self.neutrons {
+            Some(bank) => bank,
+            None => self.neutrons.insert(EventBank::new(
+                &self.entry,
+                "neutron_events",
+                &self.options,
+            )?),
+        };
+        bank.append(tdc_timestamp_25ns, &event_id, &neutrons.tof)
+    }
+
+    /// Append the hits of one pulse.
+    ///
+    /// # Errors
+    /// Returns an error if HDF5 I/O fails or a hit lies outside the detector.
+    pub fn write_hits(&mut self, batch: &EventBatch) -> Result<()> {
+        if batch.hits.is_empty() {
+            return Ok(());
+        }
+        let event_id = batch
+            .hits
+            .x
+            .iter()
+            .zip(&batch.hits.y)
+            .map(|(&x, &y)| pixel_id(u32::from(x), u32::from(y), &self.options))
+            .collect::<Result<Vec<u32>>>()?;
+        let bank = match &mut self.hits {
+            Some(bank) => bank,
+            None => self
+                .hits
+                .insert(EventBank::new(&self.entry, "hit_events", &self.options)?),
+        };
+        bank.append(batch.tdc_timestamp_25ns, &event_id, &batch.hits.tof)
+    }
+
+    /// Flush buffered data to disk.
+    ///
+    /// # Errors
+    /// Returns an error if HDF5 I/O fails.
+    pub fn flush(&self) -> Result<()> {
+        Ok(self.file.flush()?)
+    }
+}
+
+fn pixel_id(x: u32, y: u32, options: &NexusWriteOptions) -> Result<u32> {
+    if x >= options.x_size || y >= options.y_size {
+        return Err(Error::InvalidFormat(format!(
+            "pixel ({x}, {y}) out of detector bounds {}x{}",
+            options.x_size, options.y_size
+        )));
+    }
+    Ok(y * options.x_size + x)
+}
+
+fn write_string_dataset(group: &Group, name: &str, value: &str) -> Result<()> {
+    let value: VarLenUnicode = to_var_len_unicode(value)?;
+    group
+        .new_dataset::<VarLenUnicode>()
+        .shape(())
+        .create(name)?
+        .write_scalar(&value)?;
+    Ok(())
+}
+
+/// One `NXevent_data` group being filled.
+struct EventBank {
+    event_id: Dataset,
+    event_time_offset: Dataset,
+    event_time_zero: Dataset,
+    event_index: Dataset,
+    event_count: usize,
+    pulse_count: usize,
+}
+
+impl EventBank {
+    fn new(entry: &Group, name: &str, options: &NexusWriteOptions) -> Result<Self> {
+        let group = entry.create_group(name)?;
+        set_attr_str_group(&group, "NX_class", "NXevent_data")?;
+        let event_id = create_extendable_dataset::<u32>(
+            &group,
+            "event_id",
+            options.chunk_events,
+            options.compression,
+            true,
+        )?;
+        let event_time_offset = create_extendable_dataset::<f32>(
+            &group,
+            "event_time_offset",
+            options.chunk_events,
+            options.compression,
+            true,
+        )?;
+        let event_time_zero = create_extendable_dataset::<f64>(
+            &group,
+            "event_time_zero",
+            options.chunk_events,
+            options.compression,
+            true,
+        )?;
+        let event_index = create_extendable_dataset::<u64>(
+            &group,
+            "event_index",
+            options.chunk_events,
+            options.compression,
+            true,
+        )?;
+
+        set_dataset_units(&event_time_offset, "microsecond")?;
+        set_dataset_units(&event_time_zero, "second")?;
+        let offset = to_var_len_unicode(&options.start_time)?;
+        event_time_zero
+            .new_attr::<VarLenUnicode>()
+            .create("offset")?
+            .write_scalar(&offset)?;
+
+        Ok(Self {
+            event_id,
+            event_time_offset,
+            event_time_zero,
+            event_index,
+            event_count: 0,
+            pulse_count: 0,
+        })
+    }
+
+    fn append(&mut self, tdc_timestamp_25ns: u64, event_id: &[u32], tof: &[u32]) -> Result<()> {
+        #[allow(clippy::cast_precision_loss, clippy::cast_possible_truncation)]
+        let event_time_offset: Vec<f32> = tof
+            .iter()
+            .map(|&tof| (u64::from(tof) * NS_PER_TICK) as f32 / 1_000.0)
+            .collect();
+        #[allow(clippy::cast_precision_loss)]
+        let pulse_time_s = tdc_timestamp_25ns.saturating_mul(NS_PER_TICK) as f64 * 1e-9;
+
+        append_slice(&self.event_id, self.event_count, event_id)?;
+        append_slice(
+            &self.event_time_offset,
+            self.event_count,
+            &event_time_offset,
+        )?;
+        append_slice(&self.event_time_zero, self.pulse_count, &[pulse_time_s])?;
+        append_slice(
+            &self.event_index,
+            self.pulse_count,
+            &[self.event_count as u64],
+        )?;
+
+        self.event_count += event_id.len();
+        self.pulse_count += 1;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rustpix_core::soa::HitBatch;
+    use tempfile::NamedTempFile;
+
+    fn options() -> NexusWriteOptions {
+        NexusWriteOptions {
+            x_size: 16,
+            y_size: 16,
+            super_resolution_factor: 8.0,
+            chunk_events: 4,
+            compression: None,
+            instrument_name: "TEST".to_string(),
+            start_time: DEFAULT_START_TIME.to_string(),
+            flight_path_m: Some(25.0),
+            tof_offset_ns: 1_500.0,
+        }
+    }
+
+    #[test]
+    fn test_nexus_neutron_and_hit_layout() {
+        let file = NamedTempFile::new().unwrap();
+        let mut writer = NexusEventWriter::create(file.path(), options()).unwrap();
+
+        let mut neutrons = NeutronBatch::with_capacity(3);
+        neutrons.x.extend_from_slice(&[8.0, 24.0, 16.0]);
+        neutrons.y.extend_from_slice(&[0.0, 8.0, 120.0]);
+        neutrons.tof.extend_from_slice(&[40, 400, 4000]);
+        neutrons.tot.extend_from_slice(&[1, 2, 3]);
+        neutrons.n_hits.extend_from_slice(&[1, 1, 1]);
+        neutrons.chip_id.extend_from_slice(&[0, 0, 0]);
+        writer.write_neutrons(40_000, &neutrons).unwrap();
+        writer
+            .write_neutrons(80_000, &NeutronBatch::default())
+            .unwrap();
+        writer.write_neutrons(80_000, &neutrons).unwrap();
+
+        let mut hits = HitBatch::default();
+        hits.push((3, 2, 80, 5, 0, 0));
+        writer
+            .write_hits(&EventBatch {
+                tdc_timestamp_25ns: 40_000,
+                hits,
+            })
+            .unwrap();
+        writer.flush().unwrap();
+        drop(writer);
+
+        let file = File::open(file.path()).unwrap();
+        let bank = file.group("entry/neutron_events").unwrap();
+        assert_eq!(
+            bank.dataset("event_id").unwrap().read_raw::<u32>().unwrap(),
+            vec![1, 19, 242, 1, 19, 242]
+        );
+        let tof = bank
+            .dataset("event_time_offset")
+            .unwrap()
+            .read_raw::<f32>()
+            .unwrap();
+        assert!((tof[0] - 1.0).abs() < 1e-6);
+        assert!((tof[2] - 100.0).abs() < 1e-4);
+        let pulses = bank
+            .dataset("event_time_zero")
+            .unwrap()
+            .read_raw::<f64>()
+            .unwrap();
+        assert_eq!(pulses.len(), 2);
+        assert!((pulses[0] - 1e-3).abs() < 1e-12);
+        assert!((pulses[1] - 2e-3).abs() < 1e-12);
+        assert_eq!(
+            bank.dataset("event_index")
+                .unwrap()
+                .read_raw::<u64>()
+                .unwrap(),
+            vec![0, 3]
+        );
+
+        let hits = file.group("entry/hit_events").unwrap();
+        assert_eq!(
+            hits.dataset("event_id").unwrap().read_raw::<u32>().unwrap(),
+            vec![35]
+        );
+        let name = file
+            .dataset("entry/instrument/name")
+            .unwrap()
+            .read_scalar::<VarLenUnicode>()
+            .unwrap();
+        assert_eq!(name.as_str(), "TEST");
+        let instrument = file.group("entry/instrument").unwrap();
+        let flight_path: f64 = instrument
+            .attr("flight_path_m")
+            .unwrap()
+            .read_scalar()
+            .unwrap();
+        assert!((flight_path - 25.0).abs() < 1e-12);
+        let offset: f64 = instrument
+            .attr("tof_offset_ns")
+            .unwrap()
+            .read_scalar()
+            .unwrap();
+        assert!((offset - 1_500.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_nexus_rejects_out_of_bounds() {
+        let file = NamedTempFile::new().unwrap();
+        let mut writer = NexusEventWriter::create(file.path(), options()).unwrap();
+        let mut hits = HitBatch::default();
+        hits.push((16, 0, 1, 1, 0, 0));
+        let batch = EventBatch {
+            tdc_timestamp_25ns: 0,
+            hits,
+        };
+        assert!(writer.write_hits(&batch).is_err());
+    }
+}
@@ -1,6 +1,6 @@
 //! Out-of-core batching utilities for pulse-ordered TPX3 streams.
 
-use crate::reader::{EventBatch, TimeOrderedEventStream, Tpx3FileReader};
+use crate::reader::{EventBatch, StreamPosition, TimeOrderedEventStream, Tpx3FileReader};
 use crate::{Error, Result};
 use rustpix_core::soa::HitBatch;
 use std::collections::VecDeque;
@@ -22,6 +22,12 @@ pub struct OutOfCoreConfig {
     pub queue_depth: usize,
     /// Enable async pipeline stage execution.
     pub async_io: bool,
+    /// Target number of hits per processing chunk.
+    ///
+    /// When set, chunk boundaries are chosen by hit count so dense bursts are
+    /// split into evenly sized chunks; the memory budget still applies as an
+    /// upper bound.
+    pub target_hits_per_chunk: Option<usize>,
 }
 
 impl Default for OutOfCoreConfig {
@@ -32,6 +38,7 @@ impl Default for OutOfCoreConfig {
             parallelism: None,
             queue_depth: 2,
             async_io: false,
+            target_hits_per_chunk: None,
         }
     }
 }
@@ -78,6 +85,15 @@ impl OutOfCoreConfig {
         self
     }
 
+    /// Set the target number of hits per processing chunk.
+    ///
+    /// Values less than 1 are clamped to 1.
+    #[must_use]
+    pub fn with_target_hits_per_chunk(mut self, hits: usize) -> Self {
+        self.target_hits_per_chunk = Some(hits.max(1));
+        self
+    }
+
     /// Fallible variant of [`Self::with_parallelism`].
     ///
     /// # Errors
@@ -124,6 +140,20 @@ impl OutOfCoreConfig {
         self.async_io || self.parallelism.unwrap_or(1) > 1
     }
 
+    /// Resolve the maximum number of hits per chunk.
+    ///
+    /// This is the memory-budget limit, lowered to `target_hits_per_chunk`
+    /// when that is set.
+    ///
+    /// # Errors
+    /// Returns an error if the memory budget cannot be resolved.
+    pub fn resolve_max_hits(&self) -> Result<usize> {
+        let budget_hits = max_hits_for_budget(self.resolve_budget_bytes()?, bytes_per_hit());
+        Ok(self
+            .target_hits_per_chunk
+            .map_or(budget_hits, |target| target.clamp(1, budget_hits)))
+    }
+
     /// Resolve the target memory budget in bytes.
     ///
     /// # Errors
@@ -180,6 +210,59 @@ impl PulseSlice {
     }
 }
 
+/// Size statistics of the chunks (pulse slices) that were processed.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ChunkStats {
+    /// Number of chunks.
+    pub chunks: usize,
+    /// Hits across all chunks, including overlap hits shared between chunks.
+    pub total_hits: usize,
+    /// Hits in the smallest chunk.
+    pub min_hits: usize,
+    /// Hits in the largest chunk.
+    pub max_hits: usize,
+}
+
+impl ChunkStats {
+    /// Record one chunk of `hits` hits.
+    pub fn record(&mut self, hits: usize) {
+        self.min_hits = if self.chunks == 0 {
+            hits
+        } else {
+            self.min_hits.min(hits)
+        };
+        self.max_hits = self.max_hits.max(hits);
+        self.total_hits = self.total_hits.saturating_add(hits);
+        self.chunks += 1;
+    }
+
+    /// Combine with statistics from other chunks.
+    pub fn merge(&mut self, other: &Self) {
+        if other.chunks == 0 {
+            return;
+        }
+        self.min_hits = if self.chunks == 0 {
+            other.min_hits
+        } else {
+            self.min_hits.min(other.min_hits)
+        };
+        self.max_hits = self.max_hits.max(other.max_hits);
+        self.total_hits = self.total_hits.saturating_add(other.total_hits);
+        self.chunks += other.chunks;
+    }
+
+    /// Mean hits per chunk.
+    #[must_use]
+    #[allow(clippy::cast_precision_loss)]
+    pub fn mean_hits(&self) -> f64 {
+        if self.chunks == 0 {
+            0.0
+        } else {
+            self.total_hits as f64 / self.chunks as f64
+        }
+    }
+}
+
 /// A bounded batch of pulse slices.
 #[derive(Clone, Debug, Default)]
 pub struct PulseBatchGroup {
@@ -228,14 +311,13 @@ where
     /// Create a new batcher from a pulse-ordered event stream.
     ///
     /// `overlap_tof` is in 25ns ticks and is used only when a single pulse must
-    /// be split to respect the memory budget.
+    /// be split to respect the memory budget or hit target.
     ///
     /// # Errors
     /// Returns an error if the memory budget cannot be resolved.
     pub fn new(source: I, config: &OutOfCoreConfig, overlap_tof: u32) -> Result<Self> {
         let bytes_per_hit = bytes_per_hit();
-        let budget = config.resolve_budget_bytes()?;
-        let max_hits = max_hits_for_budget(budget, bytes_per_hit);
+        let max_hits = config.resolve_max_hits()?;
         Ok(Self {
             source,
             queue: VecDeque::new(),
@@ -299,6 +381,22 @@ pub fn pulse_batches(
     PulseBatcher::new(stream, config, overlap_tof)
 }
 
+/// Reader-backed batcher over the pulses after `position`, to continue an
+/// interrupted run.
+///
+/// # Errors
+/// Returns an error if the stream cannot be resumed at `position` or the
+/// memory budget fails.
+pub fn pulse_batches_from(
+    reader: &Tpx3FileReader,
+    position: &StreamPosition,
+    config: &OutOfCoreConfig,
+    overlap_tof: u32,
+) -> Result<PulseBatcher<TimeOrderedEventStream>> {
+    let stream = reader.stream_time_ordered_events_from(position)?;
+    PulseBatcher::new(stream, config, overlap_tof)
+}
+
 fn bytes_per_hit() -> usize {
     size_of::<u16>() * 2
         + size_of::<u32>() * 2
@@ -396,6 +494,11 @@ fn slice_hits(batch: &HitBatch, start: usize, end: usize) -> HitBatch {
     sliced
         .cluster_id
         .extend_from_slice(&batch.cluster_id[start..end]);
+    if batch.has_raw() {
+        sliced.raw_x.extend_from_slice(&batch.raw_x[start..end]);
+        sliced.raw_y.extend_from_slice(&batch.raw_y[start..end]);
+        sliced.raw_toa.extend_from_slice(&batch.raw_toa[start..end]);
+    }
     sliced
 }
 
@@ -464,4 +567,33 @@ mod tests {
         assert_eq!(total_hits, 6);
         assert!(batch_count >= 2);
     }
+
+    #[test]
+    fn batcher_splits_dense_pulses_by_target_hits() {
+        let config = OutOfCoreConfig::default()
+            .with_memory_budget_bytes(1 << 30)
+            .with_target_hits_per_chunk(3);
+        let tofs: Vec<u32> = (0..10).collect();
+        let pulses = vec![
+            EventBatch {
+                tdc_timestamp_25ns: 0,
+                hits: make_hit_batch(&[0]),
+            },
+            EventBatch {
+                tdc_timestamp_25ns: 1,
+                hits: make_hit_batch(&tofs),
+            },
+        ];
+
+        let mut stats = ChunkStats::default();
+        for group in PulseBatcher::new(pulses.into_iter(), &config, 0).unwrap() {
+            assert!(group.total_hits() <= 3);
+            for slice in &group.slices {
+                stats.record(slice.len());
+            }
+        }
+        assert_eq!(stats.total_hits, 11);
+        assert_eq!(stats.chunks, 5);
+        assert_eq!((stats.min_hits, stats.max_hits), (1, 3));
+    }
 }
@@ -1,21 +1,52 @@
 //! Out-of-core processing pipeline for pulse-bounded streams.
 
 use crate::out_of_core::{
-    pulse_batches, OutOfCoreConfig, PulseBatchGroup, PulseBatcher, PulseSlice,
+    pulse_batches, pulse_batches_from, ChunkStats, OutOfCoreConfig, PulseBatchGroup, PulseBatcher,
+    PulseSlice,
 };
-use crate::reader::{TimeOrderedEventStream, Tpx3FileReader};
+use crate::reader::{StreamPosition, TimeOrderedEventStream, Tpx3FileReader};
 use crate::{Error, Result};
 use rayon::prelude::*;
-use rustpix_algorithms::{cluster_and_extract_batch, AlgorithmParams, ClusteringAlgorithm};
+use rustpix_algorithms::{
+    cluster_batch, extract_batch, is_unsectionable, AlgorithmParams, ClusteringAlgorithm,
+};
 use rustpix_core::clustering::ClusteringConfig;
 use rustpix_core::extraction::ExtractionConfig;
 use rustpix_core::neutron::NeutronBatch;
 use rustpix_core::soa::HitBatch;
+use rustpix_core::warnings::{WarningKind, Warnings};
+use rustpix_tpx::TOT_SATURATED;
 use std::collections::VecDeque;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{mpsc, Arc};
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, Instant};
+
+/// Time spent in each processing stage.
+///
+/// When slices are processed in parallel these are summed over workers, so
+/// they can exceed the wall time of the pulse.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct StageTimings {
+    /// Time spent assigning cluster labels.
+    pub cluster: Duration,
+    /// Time spent extracting neutrons from labelled clusters.
+    pub extract: Duration,
+}
+
+impl StageTimings {
+    /// Total time across stages.
+    #[must_use]
+    pub fn total(&self) -> Duration {
+        self.cluster + self.extract
+    }
+
+    /// Add another set of timings to this one.
+    pub fn accumulate(&mut self, other: &Self) {
+        self.cluster += other.cluster;
+        self.extract += other.extract;
+    }
+}
 
 /// Neutron output for a single pulse.
 #[derive(Clone, Debug)]
@@ -26,12 +57,21 @@ pub struct PulseNeutronBatch {
     pub hits_processed: usize,
     /// Neutrons extracted from this pulse.
     pub neutrons: NeutronBatch,
+    /// Time spent clustering and extracting this pulse.
+    pub timings: StageTimings,
+    /// Sizes of the chunks this pulse was processed in.
+    pub chunks: ChunkStats,
+    /// Recoverable oddities in this pulse, such as saturated hits.
+    pub warnings: Warnings,
 }
 
 struct SliceOutput {
     tdc_timestamp_25ns: u64,
     hits_processed: usize,
     neutrons: NeutronBatch,
+    timings: StageTimings,
+    chunks: ChunkStats,
+    warnings: Warnings,
 }
 
 /// Stream handle that can be single-threaded or threaded.
@@ -106,6 +146,12 @@ where
     current_neutrons: NeutronBatch,
     /// Count of emitted hits for the current pulse.
     current_hits: usize,
+    /// Stage timings accumulated for the current pulse.
+    current_timings: StageTimings,
+    /// Chunk statistics accumulated for the current pulse.
+    current_chunks: ChunkStats,
+    /// Warnings accumulated for the current pulse.
+    current_warnings: Warnings,
     /// Completed pulse outputs waiting to be returned.
     pending: VecDeque<PulseNeutronBatch>,
     /// Whether the stream has been fully drained.
@@ -135,6 +181,9 @@ where
             current_tdc: None,
             current_neutrons: NeutronBatch::default(),
             current_hits: 0,
+            current_timings: StageTimings::default(),
+            current_chunks: ChunkStats::default(),
+            current_warnings: Warnings::default(),
             pending: VecDeque::new(),
             finished: false,
         }
@@ -151,31 +200,19 @@ where
     }
 
     fn process_slice(&mut self, slice: PulseSlice) -> Result<()> {
-        let mut hits = slice.hits;
-        let mut neutrons = cluster_and_extract_batch(
-            &mut hits,
+        let output = process_slice_output(
+            slice,
             self.algorithm,
             &self.clustering,
             &self.extraction,
             &self.params,
-        )
-        .map_err(Error::CoreError)?;
-
-        let emitted_hits = count_emitted_hits(&hits, slice.emit_cutoff_tof);
-        if slice.emit_cutoff_tof != u32::MAX {
-            neutrons = filter_neutrons_by_tof(&neutrons, slice.emit_cutoff_tof);
-        }
-
-        self.append_neutrons(slice.tdc_timestamp_25ns, &neutrons, emitted_hits);
+        )?;
+        self.append_output(&output);
         Ok(())
     }
 
-    fn append_neutrons(
-        &mut self,
-        tdc_timestamp_25ns: u64,
-        neutrons: &NeutronBatch,
-        emitted_hits: usize,
-    ) {
+    fn append_output(&mut self, output: &SliceOutput) {
+        let tdc_timestamp_25ns = output.tdc_timestamp_25ns;
         let current = self.current_tdc.unwrap_or(tdc_timestamp_25ns);
         if current != tdc_timestamp_25ns {
             if self.current_hits > 0 || !self.current_neutrons.is_empty() {
@@ -183,6 +220,9 @@ where
                     tdc_timestamp_25ns: current,
                     hits_processed: self.current_hits,
                     neutrons: std::mem::take(&mut self.current_neutrons),
+                    timings: std::mem::take(&mut self.current_timings),
+                    chunks: std::mem::take(&mut self.current_chunks),
+                    warnings: std::mem::take(&mut self.current_warnings),
                 });
             }
             self.current_tdc = Some(tdc_timestamp_25ns);
@@ -191,8 +231,11 @@ where
             self.current_tdc = Some(tdc_timestamp_25ns);
         }
 
-        self.current_neutrons.append(neutrons);
-        self.current_hits = self.current_hits.saturating_add(emitted_hits);
+        self.current_neutrons.append(&output.neutrons);
+        self.current_hits = self.current_hits.saturating_add(output.hits_processed);
+        self.current_timings.accumulate(&output.timings);
+        self.current_chunks.merge(&output.chunks);
+        self.current_warnings.merge(&output.warnings);
     }
 
     fn flush_current(&mut self) {
@@ -202,10 +245,16 @@ where
                     tdc_timestamp_25ns: tdc,
                     hits_processed: self.current_hits,
                     neutrons: std::mem::take(&mut self.current_neutrons),
+                    timings: std::mem::take(&mut self.current_timings),
+                    chunks: std::mem::take(&mut self.current_chunks),
+                    warnings: std::mem::take(&mut self.current_warnings),
                 });
             }
         }
         self.current_hits = 0;
+        self.current_timings = StageTimings::default();
+        self.current_chunks = ChunkStats::default();
+        self.current_warnings = Warnings::default();
     }
 }
 
@@ -275,29 +324,58 @@ pub fn out_of_core_neutron_stream_handle(
     params: &AlgorithmParams,
     memory: &OutOfCoreConfig,
 ) -> Result<OutOfCoreNeutronStreamHandle> {
-    let overlap_tof = clustering.window_tof();
-    let batcher = pulse_batches(reader, memory, overlap_tof)?;
+    let batcher = pulse_batches(reader, memory, clustering.window_tof())?;
+    Ok(stream_handle(
+        batcher, algorithm, clustering, extraction, params, memory,
+    ))
+}
+
+/// Build an out-of-core neutron stream over the pulses of `reader` after
+/// `position`, to continue an interrupted run.
+///
+/// # Errors
+/// Returns an error if the stream cannot be resumed at `position` or the
+/// memory budget is invalid.
+pub fn out_of_core_neutron_stream_from(
+    reader: &Tpx3FileReader,
+    position: &StreamPosition,
+    algorithm: ClusteringAlgorithm,
+    clustering: &ClusteringConfig,
+    extraction: &ExtractionConfig,
+    params: &AlgorithmParams,
+    memory: &OutOfCoreConfig,
+) -> Result<Box<dyn Iterator<Item = Result<PulseNeutronBatch>>>> {
+    let batcher = pulse_batches_from(reader, position, memory, clustering.window_tof())?;
+    Ok(Box::new(stream_handle(
+        batcher, algorithm, clustering, extraction, params, memory,
+    )))
+}
+
+fn stream_handle(
+    batcher: PulseBatcher<TimeOrderedEventStream>,
+    algorithm: ClusteringAlgorithm,
+    clustering: &ClusteringConfig,
+    extraction: &ExtractionConfig,
+    params: &AlgorithmParams,
+    memory: &OutOfCoreConfig,
+) -> OutOfCoreNeutronStreamHandle {
     if memory.use_threaded_pipeline() {
-        Ok(OutOfCoreNeutronStreamHandle::Threaded(
-            build_threaded_stream(
-                batcher,
-                algorithm,
-                clustering.clone(),
-                extraction.clone(),
-                params.clone(),
-                memory.effective_parallelism(),
-                memory.effective_queue_depth(),
-            ),
+        OutOfCoreNeutronStreamHandle::Threaded(build_threaded_stream(
+            batcher,
+            algorithm,
+            clustering.clone(),
+            extraction.clone(),
+            params.clone(),
+            memory.effective_parallelism(),
+            memory.effective_queue_depth(),
         ))
     } else {
-        Ok(OutOfCoreNeutronStreamHandle::Single(Box::new(
-            OutOfCoreNeutronStream::new(
-                batcher,
-                algorithm,
-                clustering.clone(),
-                extraction.clone(),
-                params.clone(),
-            ),
+        OutOfCoreNeutronStreamHandle::Single(Box::new(OutOfCoreNeutronStream::new(
+            batcher,
+            algorithm,
+            clustering.clone(),
+            extraction.clone(),
+            params.clone(),
         )))
     }
 }
@@ -433,6 +511,9 @@ fn process_group(
         tdc_timestamp_25ns: 0,
         hits_processed: 0,
         neutrons: NeutronBatch::default(),
+        timings: StageTimings::default(),
+        chunks: ChunkStats::default(),
+        warnings: Warnings::default(),
     };
 
     for output in outputs {
@@ -446,6 +527,9 @@ fn process_group(
                 tdc_timestamp_25ns: output.tdc_timestamp_25ns,
                 hits_processed: 0,
                 neutrons: NeutronBatch::default(),
+                timings: StageTimings::default(),
+                chunks: ChunkStats::default(),
+                warnings: Warnings::default(),
             };
             current_tdc = Some(output.tdc_timestamp_25ns);
         }
@@ -454,6 +538,9 @@ fn process_group(
         current_batch.hits_processed = current_batch
             .hits_processed
             .saturating_add(output.hits_processed);
+        current_batch.timings.accumulate(&output.timings);
+        current_batch.chunks.merge(&output.chunks);
+        current_batch.warnings.merge(&output.warnings);
     }
 
     if current_tdc.is_some()
@@ -472,25 +559,51 @@ fn process_slice_output(
     extraction: &ExtractionConfig,
     params: &AlgorithmParams,
 ) -> Result<SliceOutput> {
+    let mut chunks = ChunkStats::default();
+    chunks.record(slice.len());
     let mut hits = slice.hits;
-    let mut neutrons =
-        cluster_and_extract_batch(&mut hits, algorithm, clustering, extraction, params)
-            .map_err(Error::CoreError)?;
+    let unsectionable = is_unsectionable(&hits, algorithm, clustering, params);
+    let start = Instant::now();
+    let num_clusters =
+        cluster_batch(&mut hits, algorithm, clustering, params).map_err(Error::CoreError)?;
+    let cluster = start.elapsed();
+    let start = Instant::now();
+    let mut neutrons = extract_batch(&hits, num_clusters, extraction).map_err(Error::CoreError)?;
+    let extract = start.elapsed();
 
     let emitted_hits = count_emitted_hits(&hits, slice.emit_cutoff_tof);
     if slice.emit_cutoff_tof != u32::MAX {
         neutrons = filter_neutrons_by_tof(&neutrons, slice.emit_cutoff_tof);
     }
 
+    let mut warnings = Warnings::default();
+    let saturated = hits.tot[..emitted_hits]
+        .iter()
+        .filter(|&&tot| tot >= TOT_SATURATED)
+        .count();
+    warnings.record(WarningKind::SaturatedPixel, saturated as u64, || {
+        format!("{saturated} hits saturated the ToT register in one pulse")
+    });
+    if unsectionable {
+        let hits = hits.len();
+        warnings.record(WarningKind::UnsectionedPulse, 1, || {
+            format!("a pulse of {hits} hits could not be cut into sections")
+        });
+    }
+
     Ok(SliceOutput {
         tdc_timestamp_25ns: slice.tdc_timestamp_25ns,
         hits_processed: emitted_hits,
         neutrons,
+        timings: StageTimings { cluster, extract },
+        chunks,
+        warnings,
     })
 }
 
 fn filter_neutrons_by_tof(neutrons: &NeutronBatch, cutoff_tof: u32) -> NeutronBatch {
     let mut filtered = NeutronBatch::with_capacity(neutrons.len());
+    filtered.super_resolution_factor = neutrons.super_resolution_factor;
     for i in 0..neutrons.len() {
         if neutrons.tof[i] <= cutoff_tof {
             push_neutron(&mut filtered, neutrons, i);
@@ -506,6 +619,15 @@ fn push_neutron(dest: &mut NeutronBatch, src: &NeutronBatch, idx: usize) {
     dest.tot.push(src.tot[idx]);
     dest.n_hits.push(src.n_hits[idx]);
     dest.chip_id.push(src.chip_id[idx]);
+    if let Some(&energy) = src.energy_kev.get(idx) {
+        dest.energy_kev.push(energy);
+    }
+    if let Some(&class) = src.event_class.get(idx) {
+        dest.event_class.push(class);
+    }
+    if let Some(&features) = src.features.get(idx) {
+        dest.features.push(features);
+    }
 }
 
 fn count_emitted_hits(hits: &HitBatch, cutoff: u32) -> usize {
@@ -519,13 +641,11 @@ fn count_emitted_hits(hits: &HitBatch, cutoff: u32) -> usize {
 mod tests {
     use super::*;
     use crate::reader::EventBatch;
+    use rustpix_algorithms::cluster_and_extract_batch;
     use rustpix_core::soa::HitRecord;
 
     fn make_event_batch(tdc: u64, hits: &[HitRecord]) -> EventBatch {
-        let mut batch = HitBatch::with_capacity(hits.len());
-        for &hit in hits {
-            batch.push(hit);
-        }
+        let mut batch = HitBatch::from_records(hits);
         batch.sort_by_tof();
         EventBatch {
             tdc_timestamp_25ns: tdc,
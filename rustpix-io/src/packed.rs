@@ -0,0 +1,367 @@
+//! Delta-encoded, bit-packed neutron blocks (research format, `.rpxd`).
+//!
+//! Each written batch becomes one self-contained block. Events are sorted
+//! by TOF within the block, so TOF is stored as deltas from the previous
+//! event; every other column is stored relative to its block minimum. Each
+//! column is then bit-packed at the smallest width that holds its largest
+//! value. Positions are quantized to `1 / position_scale` of a coordinate
+//! unit (super-resolution space), so they round-trip to within half a
+//! quantum; all other fields are lossless.
+//!
+//! ```text
+//! block header : "RPXD" | records u32 | payload_len u32 | position_scale f64
+//!                | 6 x (base i64 | width u8)   (tof, x, y, tot, n_hits, chip_id)
+//! block payload: each column packed LSB-first, byte-aligned per column
+//! ```
+//!
+//! All fields are little-endian. Event order within a batch is not kept.
+
+use crate::binary::{read_record, to_array};
+use crate::{Error, Result};
+use rustpix_core::neutron::NeutronBatch;
+use std::io::Read;
+
+const BLOCK_MAGIC: [u8; 4] = *b"RPXD";
+const COLUMNS: usize = 6;
+const COLUMN_HEADER_BYTES: usize = 9;
+const BLOCK_HEADER_BYTES: usize = 4 + 4 + 4 + 8 + COLUMNS * COLUMN_HEADER_BYTES;
+
+/// Quantized positions beyond this magnitude would lose integer precision.
+const MAX_QUANTIZED: f64 = 9_007_199_254_740_992.0; // 2^53
+
+/// Default position quantum: 1/64 of a coordinate unit.
+pub const DEFAULT_PACKED_POSITION_SCALE: f64 = 64.0;
+
+/// One column: values stored as `base + packed[i]` (or running sums of the
+/// packed deltas for TOF).
+struct Column {
+    base: i64,
+    width: u32,
+    values: Vec<u64>,
+}
+
+impl Column {
+    /// Frame-of-reference encoding relative to the column minimum.
+    fn relative(values: impl Iterator<Item = i64> + Clone) -> Self {
+        let base = values.clone().min().unwrap_or(0);
+        #[allow(clippy::cast_sign_loss)]
+        let values: Vec<u64> = values
+            .map(|value| value.wrapping_sub(base) as u64)
+            .collect();
+        Self::with_values(base, values)
+    }
+
+    fn with_values(base: i64, values: Vec<u64>) -> Self {
+        let max = values.iter().copied().max().unwrap_or(0);
+        Self {
+            base,
+            width: u64::BITS - max.leading_zeros(),
+            values,
+        }
+    }
+}
+
+/// Encode `batch` as one packed block.
+///
+/// # Errors
+/// Returns an error if `position_scale` is not positive and finite, a
+/// position is not finite or too large to quantize, or the batch has more
+/// than `u32::MAX` events.
+pub fn encode_packed_neutrons(batch: &NeutronBatch, position_scale: f64) -> Result<Vec<u8>> {
+    if !(position_scale.is_finite() && position_scale > 0.0) {
+        return Err(Error::InvalidFormat(format!(
+            "packed position scale must be positive, got {position_scale}"
+        )));
+    }
+    let records = u32::try_from(batch.len())
+        .map_err(|_| Error::InvalidFormat("too many events for one packed block".to_string()))?;
+
+    let mut order: Vec<usize> = (0..batch.len()).collect();
+    order.sort_by_key(|&i| batch.tof[i]);
+
+    let quantize = |value: f64| -> Result<i64> {
+        let quantized = (value * position_scale).round();
+        if !quantized.is_finite() || quantized.abs() > MAX_QUANTIZED {
+            return Err(Error::InvalidFormat(format!(
+                "position {value} cannot be packed at scale {position_scale}"
+            )));
+        }
+        #[allow(clippy::cast_possible_truncation)]
+        Ok(quantized as i64)
+    };
+    let x = order
+        .iter()
+        .map(|&i| quantize(batch.x[i]))
+        .collect::<Result<Vec<_>>>()?;
+    let y = order
+        .iter()
+        .map(|&i| quantize(batch.y[i]))
+        .collect::<Result<Vec<_>>>()?;
+
+    let first_tof = order.first().map_or(0, |&i| batch.tof[i]);
+    let mut previous = first_tof;
+    let tof_deltas = order
+        .iter()
+        .map(|&i| {
+            let delta = batch.tof[i] - previous;
+            previous = batch.tof[i];
+            u64::from(delta)
+        })
+        .collect();
+
+    let columns = [
+        Column::with_values(i64::from(first_tof), tof_deltas),
+        Column::relative(x.iter().copied()),
+        Column::relative(y.iter().copied()),
+        Column::relative(order.iter().map(|&i| i64::from(batch.tot[i]))),
+        Column::relative(order.iter().map(|&i| i64::from(batch.n_hits[i]))),
+        Column::relative(order.iter().map(|&i| i64::from(batch.chip_id[i]))),
+    ];
+
+    let mut payload = Vec::new();
+    for column in &columns {
+        pack(&column.values, column.width, &mut payload);
+    }
+    let payload_len = u32::try_from(payload.len())
+        .map_err(|_| Error::InvalidFormat("packed block exceeds 4 GiB".to_string()))?;
+
+    let mut block = Vec::with_capacity(BLOCK_HEADER_BYTES + payload.len());
+    block.extend_from_slice(&BLOCK_MAGIC);
+    block.extend_from_slice(&records.to_le_bytes());
+    block.extend_from_slice(&payload_len.to_le_bytes());
+    block.extend_from_slice(&position_scale.to_le_bytes());
+    for column in &columns {
+        block.extend_from_slice(&column.base.to_le_bytes());
+        #[allow(clippy::cast_possible_truncation)]
+        block.push(column.width as u8);
+    }
+    block.extend_from_slice(&payload);
+    Ok(block)
+}
+
+/// Read all blocks of a packed neutron file.
+///
+/// # Errors
+/// Returns an error if reading fails, a block is truncated, or a block is
+/// not a valid packed block.
+pub fn read_neutron_batch_packed<R: Read>(mut reader: R) -> Result<NeutronBatch> {
+    let mut batch = NeutronBatch::default();
+    let mut header = [0u8; BLOCK_HEADER_BYTES];
+    loop {
+        match read_record(&mut reader, &mut header)? {
+            0 => return Ok(batch),
+            BLOCK_HEADER_BYTES => {}
+            partial => {
+                return Err(Error::InvalidFormat(format!(
+                    "truncated packed block header after {} events ({partial} of \
+                     {BLOCK_HEADER_BYTES} bytes)",
+                    batch.len()
+                )))
+            }
+        }
+        if header[0..4] != BLOCK_MAGIC {
+            return Err(Error::InvalidFormat(format!(
+                "not a packed neutron block after {} events",
+                batch.len()
+            )));
+        }
+        let payload_len = u32::from_le_bytes(to_array(&header[8..12])) as usize;
+        let mut payload = vec![0u8; payload_len];
+        if read_record(&mut reader, &mut payload)? != payload_len {
+            return Err(Error::InvalidFormat(format!(
+                "truncated packed block payload after {} events",
+                batch.len()
+            )));
+        }
+        decode_block(&header, &payload, &mut batch)?;
+    }
+}
+
+fn decode_block(
+    header: &[u8; BLOCK_HEADER_BYTES],
+    payload: &[u8],
+    batch: &mut NeutronBatch,
+) -> Result<()> {
+    let records = u32::from_le_bytes(to_array(&header[4..8])) as usize;
+    let position_scale = f64::from_le_bytes(to_array(&header[12..20]));
+    let invalid = || Error::InvalidFormat("corrupt packed neutron block".to_string());
+    if !(position_scale.is_finite() && position_scale > 0.0) {
+        return Err(invalid());
+    }
+
+    let mut offset = 0;
+    let mut columns = Vec::with_capacity(COLUMNS);
+    for column in 0..COLUMNS {
+        let start = 20 + column * COLUMN_HEADER_BYTES;
+        let base = i64::from_le_bytes(to_array(&header[start..start + 8]));
+        let width = u32::from(header[start + 8]);
+        if width > u64::BITS {
+            return Err(invalid());
+        }
+        let len = (records * width as usize).div_ceil(8);
+        let bytes = payload.get(offset..offset + len).ok_or_else(invalid)?;
+        offset += len;
+        columns.push((base, unpack(bytes, records, width)));
+    }
+    if offset != payload.len() {
+        return Err(invalid());
+    }
+
+    let value = |column: usize, i: usize| -> i64 {
+        let (base, values) = &columns[column];
+        #[allow(clippy::cast_possible_wrap)]
+        base.wrapping_add(values[i] as i64)
+    };
+    let (tof_base, tof_deltas) = &columns[0];
+    let mut tof = u64::try_from(*tof_base).map_err(|_| invalid())?;
+    for (i, &delta) in tof_deltas.iter().enumerate() {
+        tof = tof.checked_add(delta).ok_or_else(invalid)?;
+        batch.tof.push(u32::try_from(tof).map_err(|_| invalid())?);
+        #[allow(clippy::cast_precision_loss)]
+        batch.x.push(value(1, i) as f64 / position_scale);
+        #[allow(clippy::cast_precision_loss)]
+        batch.y.push(value(2, i) as f64 / position_scale);
+        batch
+            .tot
+            .push(u16::try_from(value(3, i)).map_err(|_| invalid())?);
+        batch
+            .n_hits
+            .push(u16::try_from(value(4, i)).map_err(|_| invalid())?);
+        batch
+            .chip_id
+            .push(u8::try_from(value(5, i)).map_err(|_| invalid())?);
+    }
+    Ok(())
+}
+
+/// Append `values` packed LSB-first at `width` bits each, padded to a byte.
+fn pack(values: &[u64], width: u32, out: &mut Vec<u8>) {
+    if width == 0 {
+        return;
+    }
+    let mut acc: u128 = 0;
+    let mut bits = 0;
+    for &value in values {
+        acc |= u128::from(value) << bits;
+        bits += width;
+        while bits >= 8 {
+            #[allow(clippy::cast_possible_truncation)]
+            out.push(acc as u8);
+            acc >>= 8;
+            bits -= 8;
+        }
+    }
+    if bits > 0 {
+        #[allow(clippy::cast_possible_truncation)]
+        out.push(acc as u8);
+    }
+}
+
+/// Inverse of [`pack`].
+fn unpack(bytes: &[u8], count: usize, width: u32) -> Vec<u64> {
+    if width == 0 {
+        return vec![0; count];
+    }
+    let mask = if width == u64::BITS {
+        u64::MAX
+    } else {
+        (1u64 << width) - 1
+    };
+    let mut out = Vec::with_capacity(count);
+    let mut bytes = bytes.iter();
+    let mut acc: u128 = 0;
+    let mut bits = 0;
+    for _ in 0..count {
+        while bits < width {
+            acc |= u128::from(bytes.next().copied().unwrap_or(0)) << bits;
+            bits += 8;
+        }
+        #[allow(clippy::cast_possible_truncation)]
+        out.push(acc as u64 & mask);
+        acc >>= width;
+        bits -= width;
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::NEUTRON_RECORD_BYTES;
+    use rustpix_core::neutron::Neutron;
+
+    #[test]
+    fn test_pack_roundtrip_widths() {
+        for width in [1, 7, 13, 33, 64] {
+            let mask = if width == 64 {
+                u64::MAX
+            } else {
+                (1u64 << width) - 1
+            };
+            let values: Vec<u64> = (0..50u64)
+                .map(|i| i.wrapping_mul(0x9E37_79B9_7F4A_7C15) & mask)
+                .collect();
+            let mut bytes = Vec::new();
+            pack(&values, width, &mut bytes);
+            assert_eq!(bytes.len(), (50 * width as usize).div_ceil(8));
+            assert_eq!(unpack(&bytes, values.len(), width), values);
+        }
+    }
+
+    #[test]
+    fn test_packed_roundtrip_sorted_by_tof() {
+        let mut batch = NeutronBatch::default();
+        for i in 0..1000u32 {
+            let tof = (i * 7919) % 600_000;
+            batch.push(Neutron::new(
+                f64::from(i % 512) * 8.0 + 0.25,
+                f64::from((i * 31) % 512) * 8.0 + 0.5,
+                tof,
+                u16::try_from(i % 900).unwrap(),
+                u16::try_from(i % 9 + 1).unwrap(),
+                u8::try_from(i % 4).unwrap(),
+            ));
+        }
+        let mut data = encode_packed_neutrons(&batch, DEFAULT_PACKED_POSITION_SCALE).unwrap();
+        // A second block, and an empty one, read back in order.
+        data.extend(encode_packed_neutrons(&batch, 1.0).unwrap());
+        data.extend(encode_packed_neutrons(&NeutronBatch::default(), 1.0).unwrap());
+        let decoded = read_neutron_batch_packed(data.as_slice()).unwrap();
+        assert_eq!(decoded.len(), 2000);
+        assert!(data.len() * 3 < 2000 * NEUTRON_RECORD_BYTES);
+
+        let mut expected: Vec<(u32, u16, u16, u8, i64, i64)> = (0..batch.len())
+            .map(|i| {
+                #[allow(clippy::cast_possible_truncation)]
+                let q = |v: f64| (v * 64.0).round() as i64;
+                (
+                    batch.tof[i],
+                    batch.tot[i],
+                    batch.n_hits[i],
+                    batch.chip_id[i],
+                    q(batch.x[i]),
+                    q(batch.y[i]),
+                )
+            })
+            .collect();
+        expected.sort_by_key(|event| event.0);
+        for (i, event) in expected.iter().enumerate() {
+            assert_eq!(decoded.tof[i], event.0);
+            assert_eq!(decoded.tot[i], event.1);
+            assert_eq!(decoded.n_hits[i], event.2);
+            assert_eq!(decoded.chip_id[i], event.3);
+        }
+        let mut x_sorted: Vec<f64> = decoded.x[..1000].to_vec();
+        let mut x_expected: Vec<f64> = batch.x.clone();
+        x_sorted.sort_by(f64::total_cmp);
+        x_expected.sort_by(f64::total_cmp);
+        for (decoded_x, original_x) in x_sorted.iter().zip(&x_expected) {
+            assert!((decoded_x - original_x).abs() <= 0.5 / 64.0);
+        }
+        assert!(decoded.tof.windows(2).take(999).all(|w| w[0] <= w[1]));
+
+        let truncated = &data[..data.len() / 2];
+        assert!(read_neutron_batch_packed(truncated).is_err());
+        assert!(encode_packed_neutrons(&batch, 0.0).is_err());
+    }
+}
@@ -0,0 +1,510 @@
+//! Apache Parquet export of hits and neutrons.
+//!
+//! Columns follow the CSV output with the same raw units (TOF and
+//! timestamps in 25 ns ticks, neutron coordinates in super-resolution
+//! space), so files load directly into Polars, pandas or Spark. Rows are
+//! buffered per column and written as a row group every
+//! [`ParquetWriteOptions::row_group_rows`] rows, so memory stays bounded
+//! however long the run is.
+
+use crate::{Error, Result};
+use parquet::basic::{Compression, ZstdLevel};
+use parquet::data_type::{DoubleType, Int32Type};
+use parquet::file::properties::WriterProperties;
+use parquet::file::writer::SerializedFileWriter;
+use parquet::schema::parser::parse_message_type;
+use rustpix_core::neutron::NeutronBatch;
+use rustpix_core::soa::HitBatch;
+use std::fs::File;
+use std::path::Path;
+use std::sync::Arc;
+
+/// Hit columns; `cluster_id` is -1 for hits that were not clustered, and
+/// the `raw_*` columns are null unless raw hit fields were kept.
+const HIT_SCHEMA: &str = "message hits {
+    REQUIRED INT32 x (INTEGER(16, false));
+    REQUIRED INT32 y (INTEGER(16, false));
+    REQUIRED INT32 tof (INTEGER(32, false));
+    REQUIRED INT32 tot (INTEGER(16, false));
+    REQUIRED INT32 timestamp (INTEGER(32, false));
+    REQUIRED INT32 chip_id (INTEGER(8, false));
+    REQUIRED INT32 cluster_id;
+    OPTIONAL INT32 raw_x (INTEGER(16, false));
+    OPTIONAL INT32 raw_y (INTEGER(16, false));
+    OPTIONAL INT32 raw_toa (INTEGER(32, false));
+}";
+
+/// Neutron columns; `energy_kev` is null without a `ToT` calibration.
+const NEUTRON_SCHEMA: &str = "message neutrons {
+    REQUIRED DOUBLE x;
+    REQUIRED DOUBLE y;
+    REQUIRED INT32 tof (INTEGER(32, false));
+    REQUIRED INT32 tot (INTEGER(16, false));
+    REQUIRED INT32 n_hits (INTEGER(16, false));
+    REQUIRED INT32 chip_id (INTEGER(8, false));
+    OPTIONAL DOUBLE energy_kev;
+}";
+
+/// Parquet write configuration.
+#[derive(Clone, Debug)]
+pub struct ParquetWriteOptions {
+    /// Rows per row group; also the number of rows buffered in memory.
+    pub row_group_rows: usize,
+    /// Optional zstd compression level (1-22).
+    pub compression: Option<i32>,
+}
+
+impl Default for ParquetWriteOptions {
+    fn default() -> Self {
+        Self {
+            row_group_rows: 1 << 20,
+            compression: Some(3),
+        }
+    }
+}
+
+/// Streaming Parquet writer for hits, including cluster labels.
+///
+/// Call [`finish`](Self::finish) to write the last row group and the file
+/// footer; a file dropped without it is not readable.
+pub struct ParquetHitWriter {
+    table: TableWriter,
+    x: Vec<i32>,
+    y: Vec<i32>,
+    tof: Vec<i32>,
+    tot: Vec<i32>,
+    timestamp: Vec<i32>,
+    chip_id: Vec<i32>,
+    cluster_id: Vec<i32>,
+    /// Raw fields of the rows that have them.
+    raw_x: Vec<i32>,
+    raw_y: Vec<i32>,
+    raw_toa: Vec<i32>,
+    /// Definition level per row: 1 if it has raw fields, 0 for null.
+    raw_defined: Vec<i16>,
+}
+
+impl ParquetHitWriter {
+    /// Create a hit file at `path`.
+    ///
+    /// # Errors
+    /// Returns an error if the file cannot be created or the options are
+    /// invalid.
+    pub fn create<P: AsRef<Path>>(path: P, options: &ParquetWriteOptions) -> Result<Self> {
+        Ok(Self {
+            table: TableWriter::create(path.as_ref(), HIT_SCHEMA, options)?,
+            x: Vec::new(),
+            y: Vec::new(),
+            tof: Vec::new(),
+            tot: Vec::new(),
+            timestamp: Vec::new(),
+            chip_id: Vec::new(),
+            cluster_id: Vec::new(),
+            raw_x: Vec::new(),
+            raw_y: Vec::new(),
+            raw_toa: Vec::new(),
+            raw_defined: Vec::new(),
+        })
+    }
+
+    /// Append a batch, writing row groups as they fill up.
+    ///
+    /// # Errors
+    /// Returns an error if writing a row group fails.
+    pub fn write_hits(&mut self, batch: &HitBatch) -> Result<()> {
+        let labelled = batch.cluster_id.len() == batch.len();
+        let raw = batch.has_raw();
+        let mut start = 0;
+        while start < batch.len() {
+            let end = (start + self.table.row_group_rows - self.x.len()).min(batch.len());
+            let rows = start..end;
+            self.x
+                .extend(batch.x[rows.clone()].iter().map(|&v| i32::from(v)));
+            self.y
+                .extend(batch.y[rows.clone()].iter().map(|&v| i32::from(v)));
+            self.tof
+                .extend(batch.tof[rows.clone()].iter().map(|&v| unsigned(v)));
+            self.tot
+                .extend(batch.tot[rows.clone()].iter().map(|&v| i32::from(v)));
+            self.timestamp
+                .extend(batch.timestamp[rows.clone()].iter().map(|&v| unsigned(v)));
+            self.chip_id
+                .extend(batch.chip_id[rows.clone()].iter().map(|&v| i32::from(v)));
+            if labelled {
+                self.cluster_id
+                    .extend_from_slice(&batch.cluster_id[rows.clone()]);
+            } else {
+                self.cluster_id.resize(self.x.len(), -1);
+            }
+            if raw {
+                self.raw_x
+                    .extend(batch.raw_x[rows.clone()].iter().map(|&v| i32::from(v)));
+                self.raw_y
+                    .extend(batch.raw_y[rows.clone()].iter().map(|&v| i32::from(v)));
+                self.raw_toa
+                    .extend(batch.raw_toa[rows].iter().map(|&v| unsigned(v)));
+                self.raw_defined.resize(self.x.len(), 1);
+            } else {
+                self.raw_defined.resize(self.x.len(), 0);
+            }
+            start = end;
+            if self.x.len() >= self.table.row_group_rows {
+                self.flush()?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Write the buffered rows and the file footer; later writes fail.
+    ///
+    /// # Errors
+    /// Returns an error if writing fails.
+    pub fn finish(&mut self) -> Result<()> {
+        self.flush()?;
+        self.table.finish()
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        if self.x.is_empty() {
+            return Ok(());
+        }
+        self.table.write_row_group(&[
+            ColumnData::Int32(&self.x),
+            ColumnData::Int32(&self.y),
+            ColumnData::Int32(&self.tof),
+            ColumnData::Int32(&self.tot),
+            ColumnData::Int32(&self.timestamp),
+            ColumnData::Int32(&self.chip_id),
+            ColumnData::Int32(&self.cluster_id),
+            ColumnData::NullableInt32(&self.raw_x, &self.raw_defined),
+            ColumnData::NullableInt32(&self.raw_y, &self.raw_defined),
+            ColumnData::NullableInt32(&self.raw_toa, &self.raw_defined),
+        ])?;
+        for column in [
+            &mut self.x,
+            &mut self.y,
+            &mut self.tof,
+            &mut self.tot,
+            &mut self.timestamp,
+            &mut self.chip_id,
+            &mut self.cluster_id,
+            &mut self.raw_x,
+            &mut self.raw_y,
+            &mut self.raw_toa,
+        ] {
+            column.clear();
+        }
+        self.raw_defined.clear();
+        Ok(())
+    }
+}
+
+/// Streaming Parquet writer for neutrons.
+///
+/// Call [`finish`](Self::finish) to write the last row group and the file
+/// footer; a file dropped without it is not readable.
+pub struct ParquetNeutronWriter {
+    table: TableWriter,
+    x: Vec<f64>,
+    y: Vec<f64>,
+    tof: Vec<i32>,
+    tot: Vec<i32>,
+    n_hits: Vec<i32>,
+    chip_id: Vec<i32>,
+    /// Energies of the rows that have one.
+    energy_kev: Vec<f64>,
+    /// Definition level per row: 1 if it has an energy, 0 for null.
+    energy_defined: Vec<i16>,
+}
+
+impl ParquetNeutronWriter {
+    /// Create a neutron file at `path`.
+    ///
+    /// # Errors
+    /// Returns an error if the file cannot be created or the options are
+    /// invalid.
+    pub fn create<P: AsRef<Path>>(path: P, options: &ParquetWriteOptions) -> Result<Self> {
+        Ok(Self {
+            table: TableWriter::create(path.as_ref(), NEUTRON_SCHEMA, options)?,
+            x: Vec::new(),
+            y: Vec::new(),
+            tof: Vec::new(),
+            tot: Vec::new(),
+            n_hits: Vec::new(),
+            chip_id: Vec::new(),
+            energy_kev: Vec::new(),
+            energy_defined: Vec::new(),
+        })
+    }
+
+    /// Append a batch, writing row groups as they fill up.
+    ///
+    /// # Errors
+    /// Returns an error if writing a row group fails.
+    pub fn write_neutrons(&mut self, batch: &NeutronBatch) -> Result<()> {
+        let calibrated = batch.energy_kev.len() == batch.len();
+        let mut start = 0;
+        while start < batch.len() {
+            let end = (start + self.table.row_group_rows - self.x.len()).min(batch.len());
+            let rows = start..end;
+            self.x.extend_from_slice(&batch.x[rows.clone()]);
+            self.y.extend_from_slice(&batch.y[rows.clone()]);
+            self.tof
+                .extend(batch.tof[rows.clone()].iter().map(|&v| unsigned(v)));
+            self.tot
+                .extend(batch.tot[rows.clone()].iter().map(|&v| i32::from(v)));
+            self.n_hits
+                .extend(batch.n_hits[rows.clone()].iter().map(|&v| i32::from(v)));
+            self.chip_id
+                .extend(batch.chip_id[rows.clone()].iter().map(|&v| i32::from(v)));
+            if calibrated {
+                self.energy_kev.extend_from_slice(&batch.energy_kev[rows]);
+                self.energy_defined.resize(self.x.len(), 1);
+            } else {
+                self.energy_defined.resize(self.x.len(), 0);
+            }
+            start = end;
+            if self.x.len() >= self.table.row_group_rows {
+                self.flush()?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Write the buffered rows and the file footer; later writes fail.
+    ///
+    /// # Errors
+    /// Returns an error if writing fails.
+    pub fn finish(&mut self) -> Result<()> {
+        self.flush()?;
+        self.table.finish()
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        if self.x.is_empty() {
+            return Ok(());
+        }
+        self.table.write_row_group(&[
+            ColumnData::Double(&self.x),
+            ColumnData::Double(&self.y),
+            ColumnData::Int32(&self.tof),
+            ColumnData::Int32(&self.tot),
+            ColumnData::Int32(&self.n_hits),
+            ColumnData::Int32(&self.chip_id),
+            ColumnData::NullableDouble(&self.energy_kev, &self.energy_defined),
+        ])?;
+        self.x.clear();
+        self.y.clear();
+        self.energy_kev.clear();
+        self.energy_defined.clear();
+        for column in [
+            &mut self.tof,
+            &mut self.tot,
+            &mut self.n_hits,
+            &mut self.chip_id,
+        ] {
+            column.clear();
+        }
+        Ok(())
+    }
+}
+
+/// Store an unsigned 32-bit value in Parquet's signed `INT32` physical type.
+fn unsigned(value: u32) -> i32 {
+    i32::from_ne_bytes(value.to_ne_bytes())
+}
+
+/// Column values for one row group.
+enum ColumnData<'a> {
+    Int32(&'a [i32]),
+    Double(&'a [f64]),
+    /// Values of the non-null rows and a definition level per row.
+    NullableInt32(&'a [i32], &'a [i16]),
+    /// Values of the non-null rows and a definition level per row.
+    NullableDouble(&'a [f64], &'a [i16]),
+}
+
+/// Row-group writer shared by the hit and neutron writers.
+struct TableWriter {
+    writer: SerializedFileWriter<File>,
+    row_group_rows: usize,
+}
+
+impl TableWriter {
+    fn create(path: &Path, schema: &str, options: &ParquetWriteOptions) -> Result<Self> {
+        if options.row_group_rows == 0 {
+            return Err(Error::InvalidFormat(
+                "parquet row groups must hold at least one row".to_string(),
+            ));
+        }
+        let compression = match options.compression {
+            Some(level) => Compression::ZSTD(ZstdLevel::try_new(level)?),
+            None => Compression::UNCOMPRESSED,
+        };
+        let properties = WriterProperties::builder()
+            .set_compression(compression)
+            .set_created_by(format!("rustpix {}", env!("CARGO_PKG_VERSION")))
+            .build();
+        let writer = SerializedFileWriter::new(
+            File::create(path)?,
+            Arc::new(parse_message_type(schema)?),
+            Arc::new(properties),
+        )?;
+        Ok(Self {
+            writer,
+            row_group_rows: options.row_group_rows,
+        })
+    }
+
+    fn write_row_group(&mut self, columns: &[ColumnData<'_>]) -> Result<()> {
+        let mut row_group = self.writer.next_row_group()?;
+        for column in columns {
+            let mut writer = row_group.next_column()?.ok_or_else(|| {
+                Error::InvalidFormat("more columns than the parquet schema".to_string())
+            })?;
+            match *column {
+                ColumnData::Int32(values) => {
+                    writer
+                        .typed::<Int32Type>()
+                        .write_batch(values, None, None)?;
+                }
+                ColumnData::NullableInt32(values, levels) => {
+                    writer
+                        .typed::<Int32Type>()
+                        .write_batch(values, Some(levels), None)?;
+                }
+                ColumnData::Double(values) => {
+                    writer
+                        .typed::<DoubleType>()
+                        .write_batch(values, None, None)?;
+                }
+                ColumnData::NullableDouble(values, levels) => {
+                    writer
+                        .typed::<DoubleType>()
+                        .write_batch(values, Some(levels), None)?;
+                }
+            }
+            writer.close()?;
+        }
+        row_group.close()?;
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        self.writer.finish()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use parquet::file::reader::{FileReader, SerializedFileReader};
+    use parquet::record::Field;
+    use rustpix_core::neutron::Neutron;
+    use tempfile::tempdir;
+
+    fn read_rows(path: &Path) -> (usize, Vec<parquet::record::Row>) {
+        let reader = SerializedFileReader::new(File::open(path).unwrap()).unwrap();
+        let row_groups = reader.num_row_groups();
+        let rows = reader
+            .get_row_iter(None)
+            .unwrap()
+            .map(std::result::Result::unwrap)
+            .collect();
+        (row_groups, rows)
+    }
+
+    #[test]
+    fn test_hits_round_trip_in_row_groups() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("hits.parquet");
+        let mut batch = HitBatch::default();
+        for i in 0..5u16 {
+            batch.push((i, i + 1, 4_000_000_000 + u32::from(i), 10 * i, 7, 3));
+        }
+        batch.cluster_id = vec![0, 0, 1, -1, 1];
+        let options = ParquetWriteOptions {
+            row_group_rows: 2,
+            ..ParquetWriteOptions::default()
+        };
+        let mut writer = ParquetHitWriter::create(&path, &options).unwrap();
+        writer.write_hits(&batch).unwrap();
+        let mut unlabelled = HitBatch::default();
+        unlabelled.push((9, 9, 1, 1, 1, 0));
+        unlabelled.cluster_id.clear();
+        writer.write_hits(&unlabelled).unwrap();
+        writer.finish().unwrap();
+
+        let (row_groups, rows) = read_rows(&path);
+        assert_eq!(row_groups, 3);
+        assert_eq!(rows.len(), 6);
+        let columns: Vec<_> = rows[4].get_column_iter().collect();
+        assert_eq!(columns[0].0, "x");
+        assert_eq!(columns[1].1, &Field::UShort(5));
+        assert_eq!(columns[2].1, &Field::UInt(4_000_000_004));
+        assert_eq!(columns[6].1, &Field::Int(1));
+        let last: Vec<_> = rows[5].get_column_iter().collect();
+        assert_eq!(last[6].1, &Field::Int(-1));
+        assert_eq!(last[9].1, &Field::Null);
+    }
+
+    #[test]
+    fn test_hits_with_raw_fields() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("hits.parquet");
+        let mut raw = HitBatch::default();
+        raw.push_with_raw((300, 9, 1, 1, 1, 1), (44, 9, 0x3FFF_FFFF));
+        let mut plain = HitBatch::default();
+        plain.push((1, 2, 3, 4, 5, 0));
+
+        let mut writer = ParquetHitWriter::create(&path, &ParquetWriteOptions::default()).unwrap();
+        writer.write_hits(&raw).unwrap();
+        writer.write_hits(&plain).unwrap();
+        writer.finish().unwrap();
+
+        let (_, rows) = read_rows(&path);
+        let first: Vec<_> = rows[0].get_column_iter().collect();
+        assert_eq!(first[7], (&"raw_x".to_string(), &Field::UShort(44)));
+        assert_eq!(first[9].1, &Field::UInt(0x3FFF_FFFF));
+        let second: Vec<_> = rows[1].get_column_iter().collect();
+        assert_eq!(second[7].1, &Field::Null);
+    }
+
+    #[test]
+    fn test_neutrons_with_optional_energy() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("neutrons.parquet");
+        let mut calibrated = NeutronBatch::default();
+        calibrated.push(Neutron::new(1.5, 2.5, 100, 20, 3, 1));
+        calibrated.energy_kev.push(450.0);
+        let mut uncalibrated = NeutronBatch::default();
+        uncalibrated.push(Neutron::new(3.0, 4.0, 200, 30, 2, 0));
+
+        let mut writer =
+            ParquetNeutronWriter::create(&path, &ParquetWriteOptions::default()).unwrap();
+        writer.write_neutrons(&calibrated).unwrap();
+        writer.write_neutrons(&uncalibrated).unwrap();
+        writer.finish().unwrap();
+
+        let (row_groups, rows) = read_rows(&path);
+        assert_eq!(row_groups, 1);
+        let first: Vec<_> = rows[0].get_column_iter().collect();
+        assert_eq!(first[0].1, &Field::Double(1.5));
+        assert_eq!(first[4].1, &Field::UShort(3));
+        assert_eq!(first[6].1, &Field::Double(450.0));
+        let second: Vec<_> = rows[1].get_column_iter().collect();
+        assert_eq!(second[2].1, &Field::UInt(200));
+        assert_eq!(second[6].1, &Field::Null);
+    }
+
+    #[test]
+    fn test_rejects_empty_row_groups() {
+        let dir = tempdir().unwrap();
+        let options = ParquetWriteOptions {
+            row_group_rows: 0,
+            ..ParquetWriteOptions::default()
+        };
+        assert!(ParquetHitWriter::create(dir.path().join("hits.parquet"), &options).is_err());
+    }
+}
@@ -0,0 +1,370 @@
+//! Hot and dead pixel masks saved for reuse across sessions.
+//!
+//! A [`PixelMask`] lists the dead and hot pixels of a detector together with
+//! how they were found (the hot-pixel threshold and the count statistics it
+//! was derived from) and when, so a mask validated once can be applied to
+//! every run of an experiment. Masks are saved as JSON, or as a single-page
+//! 16-bit TIFF (0 good, 1 dead, 2 hot) with the metadata in the `ImageJ`
+//! description; when loading a TIFF from elsewhere, other non-zero values
+//! count as hot.
+
+use crate::fits::utc_now;
+use crate::tiff::{read_tiff_page, TiffStackWriter};
+use crate::{Error, Result, TiffBitDepth};
+use rustpix_core::soa::HitBatch;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// Identifies the JSON layout for readers.
+pub const PIXEL_MASK_FORMAT: &str = "rustpix-pixel-mask";
+
+/// Version written to new masks.
+pub const PIXEL_MASK_FORMAT_VERSION: u32 = 1;
+
+/// `ImageJ` description key holding the metadata of a TIFF mask.
+const TIFF_PROPERTY: &str = "rustpix_pixel_mask";
+
+/// TIFF pixel values.
+const TIFF_DEAD: u64 = 1;
+const TIFF_HOT: u64 = 2;
+
+/// How the hot pixels of a mask were detected.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct PixelMaskDetection {
+    /// Sigma threshold used for hot pixel detection.
+    pub hot_sigma: f64,
+    /// Absolute count threshold used for hot pixel detection.
+    pub hot_threshold: f64,
+    /// Mean count of the live pixels.
+    pub mean: f64,
+    /// Standard deviation of the counts of the live pixels.
+    pub std_dev: f64,
+}
+
+/// Dead and hot pixels of a detector, with where they came from.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PixelMask {
+    /// Always [`PIXEL_MASK_FORMAT`].
+    pub format: String,
+    /// Format version, [`PIXEL_MASK_FORMAT_VERSION`] for current masks.
+    pub version: u32,
+    /// Detector X size in pixels.
+    pub width: usize,
+    /// Detector Y size in pixels.
+    pub height: usize,
+    /// UTC creation time, `YYYY-MM-DDThh:mm:ssZ`.
+    #[serde(default)]
+    pub created: String,
+    /// Data the mask was detected from, if known.
+    #[serde(default)]
+    pub source: Option<String>,
+    /// Detection parameters, if the mask was detected rather than drawn.
+    #[serde(default)]
+    pub detection: Option<PixelMaskDetection>,
+    /// Dead pixels as `[x, y]`.
+    #[serde(default)]
+    pub dead_pixels: Vec<[u16; 2]>,
+    /// Hot pixels as `[x, y]`.
+    #[serde(default)]
+    pub hot_pixels: Vec<[u16; 2]>,
+}
+
+/// Metadata of a TIFF mask: everything but the pixel lists.
+#[derive(Serialize, Deserialize)]
+struct TiffMetadata {
+    #[serde(default)]
+    created: String,
+    #[serde(default)]
+    source: Option<String>,
+    #[serde(default)]
+    detection: Option<PixelMaskDetection>,
+}
+
+impl PixelMask {
+    /// Mask of `width` × `height` pixels from row-major `[y][x]` dead and hot
+    /// flags (non-zero = masked), created now.
+    ///
+    /// # Errors
+    /// Returns an error if a flag grid is not `width * height` long or the
+    /// detector is wider or taller than 65536 pixels.
+    pub fn from_flags(
+        width: usize,
+        height: usize,
+        dead: &[u8],
+        hot: &[u8],
+        detection: Option<PixelMaskDetection>,
+    ) -> Result<Self> {
+        if dead.len() != width * height || hot.len() != width * height {
+            return Err(Error::InvalidFormat(format!(
+                "pixel mask flags do not match a {width}x{height} detector"
+            )));
+        }
+        if width > usize::from(u16::MAX) + 1 || height > usize::from(u16::MAX) + 1 {
+            return Err(Error::InvalidFormat(format!(
+                "{width}x{height} detector is too large for a pixel mask"
+            )));
+        }
+        let pixels = |flags: &[u8]| -> Vec<[u16; 2]> {
+            flags
+                .iter()
+                .enumerate()
+                .filter(|(_, &flag)| flag != 0)
+                .map(|(index, _)| {
+                    let coordinate = |value: usize| u16::try_from(value).unwrap_or(u16::MAX);
+                    [coordinate(index % width), coordinate(index / width)]
+                })
+                .collect()
+        };
+        Ok(Self {
+            format: PIXEL_MASK_FORMAT.to_string(),
+            version: PIXEL_MASK_FORMAT_VERSION,
+            width,
+            height,
+            created: format!("{}Z", utc_now()),
+            source: None,
+            detection,
+            dead_pixels: pixels(dead),
+            hot_pixels: pixels(hot),
+        })
+    }
+
+    /// The mask with `source` recorded as the data it was detected from.
+    #[must_use]
+    pub fn with_source(mut self, source: impl Into<String>) -> Self {
+        self.source = Some(source.into());
+        self
+    }
+
+    /// Dead pixel flags as `[y][x]` (1 = dead, 0 = ok).
+    #[must_use]
+    pub fn dead_flags(&self) -> Vec<u8> {
+        self.flags(&self.dead_pixels)
+    }
+
+    /// Hot pixel flags as `[y][x]` (1 = hot, 0 = ok).
+    #[must_use]
+    pub fn hot_flags(&self) -> Vec<u8> {
+        self.flags(&self.hot_pixels)
+    }
+
+    /// Number of masked pixels, dead or hot.
+    #[must_use]
+    pub fn masked_count(&self) -> usize {
+        self.dead_flags()
+            .iter()
+            .zip(self.hot_flags())
+            .filter(|&(&dead, hot)| dead != 0 || hot != 0)
+            .count()
+    }
+
+    /// Test telling whether a pixel `(x, y)` is outside the mask, for
+    /// [`HitBatch::retain_pixels`]. Pixels beyond the mask's extent are kept.
+    pub fn keep_filter(&self) -> impl Fn(u16, u16) -> bool {
+        let width = self.width;
+        let masked: Vec<bool> = self
+            .dead_flags()
+            .iter()
+            .zip(self.hot_flags())
+            .map(|(&dead, hot)| dead != 0 || hot != 0)
+            .collect();
+        move |x, y| {
+            let (x, y) = (usize::from(x), usize::from(y));
+            x >= width || !masked.get(y * width + x).copied().unwrap_or(false)
+        }
+    }
+
+    /// Removes the hits of `batch` on masked pixels; returns how many.
+    pub fn remove_hits(&self, batch: &mut HitBatch) -> usize {
+        batch.retain_pixels(self.keep_filter())
+    }
+
+    /// Writes the mask: a TIFF for `.tif`/`.tiff`, JSON otherwise.
+    ///
+    /// # Errors
+    /// Returns an error if the file cannot be written.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let path = path.as_ref();
+        if !is_tiff(path) {
+            let json = serde_json::to_string_pretty(self)
+                .map_err(|err| Error::InvalidFormat(err.to_string()))?;
+            std::fs::write(path, json)?;
+            return Ok(());
+        }
+        let metadata = TiffMetadata {
+            created: self.created.clone(),
+            source: self.source.clone(),
+            detection: self.detection,
+        };
+        let metadata = serde_json::to_string(&metadata)
+            .map_err(|err| Error::InvalidFormat(err.to_string()))?;
+        let size = |value: usize| {
+            u32::try_from(value)
+                .map_err(|_| Error::InvalidFormat(format!("TIFF dimension {value} exceeds u32")))
+        };
+        let mut stack = TiffStackWriter::create_with_properties(
+            path,
+            size(self.width)?,
+            size(self.height)?,
+            1,
+            TiffBitDepth::Bit16,
+            &format!("{TIFF_PROPERTY}={metadata}\n"),
+        )?;
+        let page: Vec<u64> = self
+            .dead_flags()
+            .iter()
+            .zip(self.hot_flags())
+            .map(|(&dead, hot)| match (dead, hot) {
+                (_, 1) => TIFF_HOT,
+                (1, _) => TIFF_DEAD,
+                _ => 0,
+            })
+            .collect();
+        stack.write_page(&page)?;
+        stack.finish()?;
+        Ok(())
+    }
+
+    /// Reads a mask written by [`Self::save`]; TIFFs are recognized by their
+    /// extension.
+    ///
+    /// # Errors
+    /// Returns an error if the file cannot be read, is not a pixel mask, was
+    /// written by a newer version, or lists pixels outside its extent.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let invalid =
+            |message: String| Error::InvalidFormat(format!("{}: {message}", path.display()));
+        let mask = if is_tiff(path) {
+            let page = read_tiff_page(path).map_err(|err| invalid(err.to_string()))?;
+            let metadata = page
+                .description
+                .as_deref()
+                .and_then(|text| {
+                    text.lines()
+                        .find_map(|line| line.strip_prefix(&format!("{TIFF_PROPERTY}=")))
+                })
+                .map(serde_json::from_str::<TiffMetadata>)
+                .transpose()
+                .map_err(|err| invalid(err.to_string()))?;
+            let dead: Vec<u8> = page
+                .pixels
+                .iter()
+                .map(|&value| u8::from(u64::from(value) == TIFF_DEAD))
+                .collect();
+            let hot: Vec<u8> = page
+                .pixels
+                .iter()
+                .map(|&value| u8::from(value != 0 && u64::from(value) != TIFF_DEAD))
+                .collect();
+            let mut mask = Self::from_flags(page.width, page.height, &dead, &hot, None)
+                .map_err(|err| invalid(err.to_string()))?;
+            mask.created = String::new();
+            if let Some(metadata) = metadata {
+                mask.created = metadata.created;
+                mask.source = metadata.source;
+                mask.detection = metadata.detection;
+            }
+            mask
+        } else {
+            let text = std::fs::read_to_string(path)?;
+            let mask: Self = serde_json::from_str(&text).map_err(|err| invalid(err.to_string()))?;
+            if mask.format != PIXEL_MASK_FORMAT {
+                return Err(invalid(format!("not a {PIXEL_MASK_FORMAT} file")));
+            }
+            mask
+        };
+        if mask.version > PIXEL_MASK_FORMAT_VERSION {
+            return Err(invalid(format!(
+                "unsupported pixel mask version {}",
+                mask.version
+            )));
+        }
+        let outside = mask
+            .dead_pixels
+            .iter()
+            .chain(&mask.hot_pixels)
+            .find(|[x, y]| usize::from(*x) >= mask.width || usize::from(*y) >= mask.height);
+        if let Some([x, y]) = outside {
+            return Err(invalid(format!(
+                "pixel ({x}, {y}) lies outside the {}x{} mask",
+                mask.width, mask.height
+            )));
+        }
+        Ok(mask)
+    }
+
+    fn flags(&self, pixels: &[[u16; 2]]) -> Vec<u8> {
+        let mut flags = vec![0; self.width * self.height];
+        for &[x, y] in pixels {
+            let (x, y) = (usize::from(x), usize::from(y));
+            if x < self.width && y < self.height {
+                flags[y * self.width + x] = 1;
+            }
+        }
+        flags
+    }
+}
+
+fn is_tiff(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("tif") || ext.eq_ignore_ascii_case("tiff"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> PixelMask {
+        let mut dead = vec![0; 4 * 3];
+        let mut hot = vec![0; 4 * 3];
+        dead[0] = 1;
+        hot[4 + 2] = 1;
+        hot[2 * 4 + 3] = 1;
+        let detection = PixelMaskDetection {
+            hot_sigma: 5.0,
+            hot_threshold: 120.5,
+            mean: 40.0,
+            std_dev: 16.1,
+        };
+        PixelMask::from_flags(4, 3, &dead, &hot, Some(detection))
+            .unwrap()
+            .with_source("run_0001.tpx3")
+    }
+
+    #[test]
+    fn test_pixel_mask_round_trips() {
+        let dir = tempfile::tempdir().unwrap();
+        let mask = sample();
+        assert_eq!(mask.dead_pixels, vec![[0, 0]]);
+        assert_eq!(mask.hot_pixels, vec![[2, 1], [3, 2]]);
+        assert_eq!(mask.masked_count(), 3);
+        assert!(mask.created.ends_with('Z'));
+
+        for name in ["mask.json", "mask.tiff"] {
+            let path = dir.path().join(name);
+            mask.save(&path).unwrap();
+            assert_eq!(PixelMask::load(&path).unwrap(), mask, "{name}");
+        }
+
+        let json = dir.path().join("other.json");
+        std::fs::write(&json, r#"{"format": "rustpix-roi-mask", "version": 1}"#).unwrap();
+        assert!(PixelMask::load(&json).is_err());
+        let mut outside = mask.clone();
+        outside.hot_pixels.push([4, 0]);
+        outside.save(&json).unwrap();
+        let err = PixelMask::load(&json).unwrap_err();
+        assert!(err.to_string().contains("outside"));
+    }
+
+    #[test]
+    fn test_remove_hits_on_masked_pixels() {
+        let mask = sample();
+        let mut batch = HitBatch::default();
+        for (x, y) in [(0, 0), (1, 0), (2, 1), (3, 2), (9, 9)] {
+            batch.push((x, y, 100, 5, 1000, 0));
+        }
+        assert_eq!(mask.remove_hits(&mut batch), 3);
+        assert_eq!(batch.x, vec![1, 9]);
+    }
+}
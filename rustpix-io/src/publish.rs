@@ -0,0 +1,303 @@
+//! Publishing reduced neutrons over `ZeroMQ`.
+//!
+//! [`NeutronPublisher`] is a libzmq `XPUB` socket bound to a TCP endpoint, so
+//! live-visualization services can subscribe with any `ZeroMQ` library
+//! (`zmq.SUB` in pyzmq, for example). Each pulse of neutrons is one
+//! two-frame message: the topic, `neutrons` unless changed, and a msgpack
+//! map of columns in the raw units of the CSV output:
+//!
+//! ```text
+//! {"pulse_tdc_25ns": u64, "super_resolution_factor": f64,
+//!  "x": [f64], "y": [f64], "tof": [u32], "tot": [u16],
+//!  "n_hits": [u16], "chip_id": [u8]}
+//! ```
+//!
+//! As with any `PUB` socket, messages go only to subscribers connected at
+//! the time, filtered by topic prefix. The `XPUB` side also sees the
+//! subscriptions, so pulses nobody subscribed to are not encoded. Up to
+//! `SEND_HIGH_WATER_MARK` messages are queued per subscriber; beyond that a
+//! subscriber that stops reading misses pulses, so a stalled viewer cannot
+//! hold up the reduction.
+
+use crate::Result;
+use rustpix_core::neutron::NeutronBatch;
+use std::io::ErrorKind;
+use std::net::SocketAddr;
+use std::sync::{Mutex, PoisonError};
+
+/// Topic of published neutron messages.
+pub const DEFAULT_TOPIC: &str = "neutrons";
+
+/// Messages queued per subscriber before further pulses are dropped for it.
+const SEND_HIGH_WATER_MARK: i32 = 1000;
+
+/// `ZeroMQ` `PUB` socket that publishes neutron batches.
+pub struct NeutronPublisher {
+    /// `XPUB` socket with the topic prefixes currently subscribed to.
+    socket: Mutex<(zmq::Socket, Vec<Vec<u8>>)>,
+    local_addr: SocketAddr,
+    topic: Vec<u8>,
+    super_resolution_factor: f64,
+}
+
+impl NeutronPublisher {
+    /// Binds a publisher to a `tcp://host:port` endpoint; `*` as the host
+    /// listens on all interfaces and port 0 picks a free port.
+    ///
+    /// # Errors
+    /// Returns an error if the endpoint is not a TCP endpoint or cannot be
+    /// bound.
+    pub fn bind(endpoint: &str) -> Result<Self> {
+        if !endpoint.starts_with("tcp://") {
+            return Err(std::io::Error::new(
+                ErrorKind::InvalidInput,
+                "only tcp:// endpoints are supported",
+            )
+            .into());
+        }
+        let socket = zmq::Context::new().socket(zmq::XPUB)?;
+        socket.set_sndhwm(SEND_HIGH_WATER_MARK)?;
+        // Queued pulses are not worth delaying shutdown for.
+        socket.set_linger(0)?;
+        socket.bind(endpoint)?;
+        let bound = socket
+            .get_last_endpoint()?
+            .map_err(|_| std::io::Error::new(ErrorKind::InvalidData, "bound endpoint"))?;
+        let local_addr = bound
+            .trim_start_matches("tcp://")
+            .parse()
+            .map_err(|_| std::io::Error::new(ErrorKind::InvalidData, bound.clone()))?;
+        Ok(Self {
+            socket: Mutex::new((socket, Vec::new())),
+            local_addr,
+            topic: DEFAULT_TOPIC.as_bytes().to_vec(),
+            super_resolution_factor: 1.0,
+        })
+    }
+
+    /// Sets the topic frame of published messages.
+    #[must_use]
+    pub fn with_topic(mut self, topic: &str) -> Self {
+        self.topic = topic.as_bytes().to_vec();
+        self
+    }
+
+    /// Sets the super-resolution factor sent with each message, so
+    /// subscribers can convert coordinates to pixels. Batches that record
+    /// their own factor are sent with it instead.
+    #[must_use]
+    pub fn with_super_resolution_factor(mut self, factor: f64) -> Self {
+        self.super_resolution_factor = factor;
+        self
+    }
+
+    /// Returns the address the publisher is bound to.
+    #[must_use]
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    /// Returns true if a connected subscriber has a subscription that
+    /// matches the topic.
+    #[must_use]
+    pub fn has_subscribers(&self) -> bool {
+        let mut guard = self.socket.lock().unwrap_or_else(PoisonError::into_inner);
+        let (socket, subscriptions) = &mut *guard;
+        update_subscriptions(socket, subscriptions);
+        wanted(subscriptions, &self.topic)
+    }
+
+    /// Publishes the neutrons of one pulse to the matching subscribers.
+    ///
+    /// Pulses a subscriber has no room for are dropped for that subscriber;
+    /// that is not an error for the publisher.
+    pub fn publish(&self, pulse_tdc_25ns: u64, neutrons: &NeutronBatch) {
+        let mut guard = self.socket.lock().unwrap_or_else(PoisonError::into_inner);
+        let (socket, subscriptions) = &mut *guard;
+        update_subscriptions(socket, subscriptions);
+        if !wanted(subscriptions, &self.topic) {
+            return;
+        }
+        let factor = neutrons
+            .super_resolution_factor
+            .unwrap_or(self.super_resolution_factor);
+        let body = encode_neutrons(pulse_tdc_25ns, factor, neutrons);
+        // `XPUB` never blocks on send; a failure only means the pulse was
+        // not queued, which subscribers already tolerate.
+        let _ = socket.send_multipart([self.topic.as_slice(), body.as_slice()], 0);
+    }
+}
+
+/// Applies the subscribe (`1` + prefix) and cancel (`0` + prefix) messages
+/// the `XPUB` socket has received since the last call.
+///
+/// The socket reports a prefix when its first subscriber subscribes and
+/// cancels it when the last one leaves, so `subscriptions` holds each
+/// active prefix once.
+fn update_subscriptions(socket: &zmq::Socket, subscriptions: &mut Vec<Vec<u8>>) {
+    while let Ok(message) = socket.recv_bytes(zmq::DONTWAIT) {
+        match message.split_first() {
+            Some((1, prefix)) => subscriptions.push(prefix.to_vec()),
+            Some((0, prefix)) => subscriptions.retain(|entry| entry != prefix),
+            _ => {}
+        }
+    }
+}
+
+fn wanted(subscriptions: &[Vec<u8>], topic: &[u8]) -> bool {
+    subscriptions.iter().any(|prefix| topic.starts_with(prefix))
+}
+
+/// The msgpack body of one published pulse.
+fn encode_neutrons(
+    pulse_tdc_25ns: u64,
+    super_resolution_factor: f64,
+    neutrons: &NeutronBatch,
+) -> Vec<u8> {
+    let mut out = Vec::with_capacity(128 + neutrons.len() * 24);
+    // A map of 8 entries.
+    out.push(0x88);
+    msgpack_str(&mut out, "pulse_tdc_25ns");
+    msgpack_uint(&mut out, pulse_tdc_25ns);
+    msgpack_str(&mut out, "super_resolution_factor");
+    msgpack_f64(&mut out, super_resolution_factor);
+    msgpack_column(&mut out, "x", &neutrons.x, msgpack_f64);
+    msgpack_column(&mut out, "y", &neutrons.y, msgpack_f64);
+    msgpack_column(&mut out, "tof", &neutrons.tof, |out, v| {
+        msgpack_uint(out, u64::from(v));
+    });
+    msgpack_column(&mut out, "tot", &neutrons.tot, |out, v| {
+        msgpack_uint(out, u64::from(v));
+    });
+    msgpack_column(&mut out, "n_hits", &neutrons.n_hits, |out, v| {
+        msgpack_uint(out, u64::from(v));
+    });
+    msgpack_column(&mut out, "chip_id", &neutrons.chip_id, |out, v| {
+        msgpack_uint(out, u64::from(v));
+    });
+    out
+}
+
+/// A key followed by an array of `values`.
+fn msgpack_column<T: Copy>(
+    out: &mut Vec<u8>,
+    name: &str,
+    values: &[T],
+    encode: impl Fn(&mut Vec<u8>, T),
+) {
+    msgpack_str(out, name);
+    if let Ok(len @ 0..16) = u8::try_from(values.len()) {
+        out.push(0x90 | len);
+    } else if let Ok(len) = u16::try_from(values.len()) {
+        out.push(0xDC);
+        out.extend_from_slice(&len.to_be_bytes());
+    } else {
+        out.push(0xDD);
+        let len = u32::try_from(values.len()).unwrap_or(u32::MAX);
+        out.extend_from_slice(&len.to_be_bytes());
+    }
+    for &value in values {
+        encode(out, value);
+    }
+}
+
+/// A key as a fixstr, or a str 8 if longer than 31 bytes.
+fn msgpack_str(out: &mut Vec<u8>, value: &str) {
+    match u8::try_from(value.len()) {
+        Ok(len @ 0..32) => out.push(0xA0 | len),
+        len => out.extend_from_slice(&[0xD9, len.unwrap_or(u8::MAX)]),
+    }
+    out.extend_from_slice(value.as_bytes());
+}
+
+/// An unsigned integer in its smallest encoding.
+fn msgpack_uint(out: &mut Vec<u8>, value: u64) {
+    if let Ok(value @ 0..0x80) = u8::try_from(value) {
+        out.push(value);
+    } else if let Ok(value) = u8::try_from(value) {
+        out.extend_from_slice(&[0xCC, value]);
+    } else if let Ok(value) = u16::try_from(value) {
+        out.push(0xCD);
+        out.extend_from_slice(&value.to_be_bytes());
+    } else if let Ok(value) = u32::try_from(value) {
+        out.push(0xCE);
+        out.extend_from_slice(&value.to_be_bytes());
+    } else {
+        out.push(0xCF);
+        out.extend_from_slice(&value.to_be_bytes());
+    }
+}
+
+fn msgpack_f64(out: &mut Vec<u8>, value: f64) {
+    out.push(0xCB);
+    out.extend_from_slice(&value.to_be_bytes());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+    use std::time::{Duration, Instant};
+
+    /// Connects a libzmq `SUB` socket subscribed to `prefix`.
+    fn subscribe(context: &zmq::Context, addr: SocketAddr, prefix: &[u8]) -> zmq::Socket {
+        let socket = context.socket(zmq::SUB).unwrap();
+        socket.set_rcvtimeo(5_000).unwrap();
+        socket.connect(&format!("tcp://{addr}")).unwrap();
+        socket.set_subscribe(prefix).unwrap();
+        socket
+    }
+
+    fn wait_for(publisher: &NeutronPublisher, subscribed: bool, what: &str) {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while publisher.has_subscribers() != subscribed {
+            assert!(Instant::now() < deadline, "{what}");
+            thread::sleep(Duration::from_millis(5));
+        }
+    }
+
+    #[test]
+    fn test_publish_to_libzmq_subscribers() {
+        let publisher = NeutronPublisher::bind("tcp://127.0.0.1:0")
+            .unwrap()
+            .with_super_resolution_factor(8.0);
+        let context = zmq::Context::new();
+        let other = subscribe(&context, publisher.local_addr(), b"hits");
+        thread::sleep(Duration::from_millis(50));
+        assert!(!publisher.has_subscribers());
+        let subscriber = subscribe(&context, publisher.local_addr(), b"neut");
+        wait_for(&publisher, true, "subscription not received");
+
+        let neutrons = NeutronBatch {
+            x: vec![1.5],
+            y: vec![2.0],
+            tof: vec![300],
+            tot: vec![40],
+            n_hits: vec![3],
+            chip_id: vec![2],
+            ..NeutronBatch::default()
+        };
+        publisher.publish(70_000, &neutrons);
+
+        let frames = subscriber.recv_multipart(0).unwrap();
+        assert_eq!(frames.len(), 2);
+        assert_eq!(frames[0], b"neutrons");
+        let mut expected = vec![0x88];
+        expected.extend_from_slice(b"\xAEpulse_tdc_25ns\xCE");
+        expected.extend_from_slice(&70_000u32.to_be_bytes());
+        expected.extend_from_slice(b"\xB7super_resolution_factor\xCB");
+        expected.extend_from_slice(&8.0f64.to_be_bytes());
+        expected.extend_from_slice(b"\xA1x\x91\xCB");
+        expected.extend_from_slice(&1.5f64.to_be_bytes());
+        expected.extend_from_slice(b"\xA1y\x91\xCB");
+        expected.extend_from_slice(&2.0f64.to_be_bytes());
+        expected.extend_from_slice(b"\xA3tof\x91\xCD\x01\x2C");
+        expected.extend_from_slice(b"\xA3tot\x91\x28\xA6n_hits\x91\x03\xA7chip_id\x91\x02");
+        assert_eq!(frames[1], expected);
+        assert!(other.recv_bytes(zmq::DONTWAIT).is_err());
+
+        drop(subscriber);
+        wait_for(&publisher, false, "disconnect not noticed");
+        assert!(NeutronPublisher::bind("ipc:///tmp/neutrons").is_err());
+    }
+}
@@ -0,0 +1,310 @@
+//! Reading processed rustpix outputs back into batches.
+//!
+//! Neutron outputs are read from CSV (`x,y,tof,tot,n_hits,chip_id`, as
+//! written by [`crate::DataFileWriter::write_neutron_batch_csv`]), binary and
+//! packed files, and the hit lists written alongside them from CSV with a
+//! `cluster_id` column. With the `hdf5` feature, the `entry/neutrons` and
+//! `entry/hits` groups of rustpix HDF5 files are read too. Positions and
+//! times come back in the units they were processed in: pixels (scaled by
+//! the super-resolution factor for neutrons) and 25 ns ticks.
+
+use crate::{Error, Result};
+use rustpix_core::neutron::{Neutron, NeutronBatch};
+use rustpix_core::soa::HitBatch;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read};
+use std::path::Path;
+
+/// Contents of a processed rustpix output.
+#[derive(Clone, Debug)]
+pub enum ProcessedData {
+    /// Extracted neutrons.
+    Neutrons {
+        /// The neutrons, in file order.
+        neutrons: NeutronBatch,
+        /// Super-resolution factor of the positions, if the file records it.
+        super_resolution_factor: Option<f64>,
+    },
+    /// Clustered hits, with their cluster labels.
+    Hits(HitBatch),
+}
+
+/// Reads a processed rustpix output, chosen by its extension and contents.
+///
+/// CSV files are neutrons when the header has an `n_hits` column and hits
+/// when it has `cluster_id`; `.rpxd` files are packed neutrons, `.rpxc`
+/// containers are read for their neutron blocks, and other extensions are
+/// binary neutrons. With the `hdf5` feature, `.h5`,
+/// `.hdf5` and `.nxs` files are read from their `entry` group, preferring
+/// neutrons. Returns `None` for CSV and HDF5 files that are not rustpix
+/// outputs, such as hit lists from other software.
+///
+/// # Errors
+/// Returns an error if the file cannot be read or is malformed.
+pub fn read_processed_file<P: AsRef<Path>>(path: P) -> Result<Option<ProcessedData>> {
+    let path = path.as_ref();
+    let extension = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(str::to_ascii_lowercase)
+        .unwrap_or_default();
+    let neutrons = |neutrons| ProcessedData::Neutrons {
+        neutrons,
+        super_resolution_factor: None,
+    };
+    match extension.as_str() {
+        "csv" => {
+            let mut reader = BufReader::new(File::open(path)?);
+            let mut header = String::new();
+            reader.read_line(&mut header)?;
+            let columns = header_columns(&header);
+            if columns.iter().any(|name| name == "n_hits") {
+                let reader = header.as_bytes().chain(reader);
+                Ok(Some(neutrons(read_neutron_batch_csv(reader)?)))
+            } else if columns.iter().any(|name| name == "cluster_id") {
+                let reader = header.as_bytes().chain(reader);
+                Ok(Some(ProcessedData::Hits(read_hit_batch_csv(reader)?)))
+            } else {
+                Ok(None)
+            }
+        }
+        #[cfg(feature = "hdf5")]
+        "h5" | "hdf5" | "nxs" => crate::hdf5::read_processed_hdf5(path),
+        #[cfg(not(feature = "hdf5"))]
+        "h5" | "hdf5" | "nxs" => Ok(None),
+        "rpxd" => {
+            let reader = BufReader::new(File::open(path)?);
+            Ok(Some(neutrons(crate::read_neutron_batch_packed(reader)?)))
+        }
+        "rpxc" => Ok(Some(neutrons(
+            crate::ContainerReader::open(path)?.read_all_neutrons()?,
+        ))),
+        _ => {
+            let reader = BufReader::new(File::open(path)?);
+            Ok(Some(neutrons(crate::read_neutron_batch_binary(reader)?)))
+        }
+    }
+}
+
+/// Lowercased, trimmed names of a CSV header line.
+fn header_columns(header: &str) -> Vec<String> {
+    header
+        .split(',')
+        .map(|name| name.trim().to_ascii_lowercase())
+        .collect()
+}
+
+/// Reads the header line as lowercased column names.
+fn read_header<R: BufRead>(lines: &mut std::io::Lines<R>) -> Result<Vec<String>> {
+    let header = lines
+        .next()
+        .transpose()?
+        .ok_or_else(|| Error::InvalidFormat("empty CSV".to_string()))?;
+    Ok(header_columns(&header))
+}
+
+/// Reads a neutron CSV written by rustpix.
+///
+/// The header must name `x`, `y` and `tof` columns; `tot`, `n_hits` and
+/// `chip_id` are optional and default to 0. Blank lines are skipped.
+///
+/// # Errors
+/// Returns an error if the input cannot be read, the header lacks a
+/// required column, or a row is malformed.
+pub fn read_neutron_batch_csv<R: BufRead>(reader: R) -> Result<NeutronBatch> {
+    read_neutron_batch_csv_with_pulse_ids(reader).map(|(batch, _)| batch)
+}
+
+/// Reads a neutron CSV like [`read_neutron_batch_csv`], together with its
+/// `pulse_id` column.
+///
+/// The ids are `None` if the file has no `pulse_id` column; an empty id
+/// (an event of a synthetic pulse) is read as `None`.
+///
+/// # Errors
+/// Returns an error if the input cannot be read, the header lacks a
+/// required column, or a row is malformed.
+pub fn read_neutron_batch_csv_with_pulse_ids<R: BufRead>(
+    reader: R,
+) -> Result<(NeutronBatch, Option<Vec<Option<u32>>>)> {
+    let mut lines = reader.lines();
+    let columns = read_header(&mut lines)?;
+    let find = |name: &str| columns.iter().position(|column| column == name);
+    let (Some(x_col), Some(y_col), Some(tof_col)) = (find("x"), find("y"), find("tof")) else {
+        return Err(Error::InvalidFormat(
+            "header must contain x, y and tof columns".to_string(),
+        ));
+    };
+    let tot_column = find("tot");
+    let n_hits_col = find("n_hits");
+    let chip_col = find("chip_id");
+    let pulse_col = find("pulse_id");
+
+    let mut batch = NeutronBatch::default();
+    let mut pulse_ids = pulse_col.map(|_| Vec::new());
+    for (line_no, line) in lines.enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+        let bad = || Error::InvalidFormat(format!("line {}: malformed row", line_no + 2));
+        let field = |col: usize| fields.get(col).copied().ok_or_else(bad);
+        let optional = |col: Option<usize>| col.and_then(|c| fields.get(c).copied());
+        batch.push(Neutron::new(
+            field(x_col)?.parse().map_err(|_| bad())?,
+            field(y_col)?.parse().map_err(|_| bad())?,
+            field(tof_col)?.parse().map_err(|_| bad())?,
+            optional(tot_column)
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(0),
+            optional(n_hits_col)
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(0),
+            optional(chip_col).and_then(|v| v.parse().ok()).unwrap_or(0),
+        ));
+        if let Some(ids) = pulse_ids.as_mut() {
+            let id = optional(pulse_col).unwrap_or_default();
+            ids.push(if id.is_empty() {
+                None
+            } else {
+                Some(id.parse().map_err(|_| bad())?)
+            });
+        }
+    }
+    Ok((batch, pulse_ids))
+}
+
+/// Reads a hit CSV written by rustpix, with its cluster labels.
+///
+/// The header must name `x`, `y`, `tof` and `cluster_id` columns; `tot`,
+/// `timestamp` and `chip_id` are optional and default to 0. Raw hit columns
+/// are ignored. Blank lines are skipped.
+///
+/// # Errors
+/// Returns an error if the input cannot be read, the header lacks a
+/// required column, or a row is malformed.
+pub fn read_hit_batch_csv<R: BufRead>(reader: R) -> Result<HitBatch> {
+    let mut lines = reader.lines();
+    let columns = read_header(&mut lines)?;
+    let find = |name: &str| columns.iter().position(|column| column == name);
+    let (Some(x_col), Some(y_col), Some(tof_col), Some(cluster_col)) =
+        (find("x"), find("y"), find("tof"), find("cluster_id"))
+    else {
+        return Err(Error::InvalidFormat(
+            "header must contain x, y, tof and cluster_id columns".to_string(),
+        ));
+    };
+    let tot_column = find("tot");
+    let timestamp_col = find("timestamp");
+    let chip_col = find("chip_id");
+
+    let mut batch = HitBatch::default();
+    for (line_no, line) in lines.enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+        let bad = || Error::InvalidFormat(format!("line {}: malformed row", line_no + 2));
+        let field = |col: usize| fields.get(col).copied().ok_or_else(bad);
+        let optional = |col: Option<usize>| col.and_then(|c| fields.get(c).copied());
+        batch.push((
+            field(x_col)?.parse().map_err(|_| bad())?,
+            field(y_col)?.parse().map_err(|_| bad())?,
+            field(tof_col)?.parse().map_err(|_| bad())?,
+            optional(tot_column)
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(0),
+            optional(timestamp_col)
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(0),
+            optional(chip_col).and_then(|v| v.parse().ok()).unwrap_or(0),
+        ));
+        let cluster = field(cluster_col)?.parse().map_err(|_| bad())?;
+        if let Some(last) = batch.cluster_id.last_mut() {
+            *last = cluster;
+        }
+    }
+    Ok(batch)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DataFileWriter;
+
+    #[test]
+    fn test_csv_outputs_read_back() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let mut neutrons = NeutronBatch::default();
+        neutrons.push(Neutron::new(12.5, 40.0, 1200, 30, 4, 1));
+        neutrons.push(Neutron::new(0.125, 7.75, 90, 5, 1, 0));
+        let neutron_path = dir.path().join("neutrons.csv");
+        let mut writer = DataFileWriter::create(&neutron_path).unwrap();
+        writer.write_neutron_batch_csv(&neutrons, true).unwrap();
+        drop(writer);
+
+        let mut hits = HitBatch::default();
+        hits.push((3, 4, 100, 12, 5000, 2));
+        hits.push((5, 4, 101, 8, 5001, 2));
+        hits.cluster_id = vec![0, -1];
+        let hit_path = dir.path().join("hits.csv");
+        let mut writer = DataFileWriter::create(&hit_path).unwrap();
+        writer.write_hit_batch_csv(&hits, true).unwrap();
+        drop(writer);
+
+        let Some(ProcessedData::Neutrons {
+            neutrons: read,
+            super_resolution_factor: None,
+        }) = read_processed_file(&neutron_path).unwrap()
+        else {
+            panic!("expected neutrons");
+        };
+        assert_eq!(read.x, neutrons.x);
+        assert_eq!(read.y, neutrons.y);
+        assert_eq!(read.tof, neutrons.tof);
+        assert_eq!(read.n_hits, neutrons.n_hits);
+        assert_eq!(read.chip_id, neutrons.chip_id);
+
+        let Some(ProcessedData::Hits(read)) = read_processed_file(&hit_path).unwrap() else {
+            panic!("expected hits");
+        };
+        assert_eq!(read.x, hits.x);
+        assert_eq!(read.tof, hits.tof);
+        assert_eq!(read.timestamp, hits.timestamp);
+        assert_eq!(read.cluster_id, hits.cluster_id);
+
+        let generic = dir.path().join("events.csv");
+        std::fs::write(&generic, "t,x,y\n1,2,3\n").unwrap();
+        assert!(read_processed_file(&generic).unwrap().is_none());
+
+        let err = read_neutron_batch_csv("x,y,tof\n1,2\n".as_bytes()).unwrap_err();
+        assert!(err.to_string().contains("line 2: malformed row"));
+    }
+
+    #[test]
+    fn test_csv_pulse_ids_read_back() {
+        let mut neutrons = NeutronBatch::default();
+        neutrons.push(Neutron::new(1.0, 2.0, 30, 4, 1, 0));
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("neutrons.csv");
+        let mut writer = DataFileWriter::create(&path).unwrap();
+        writer
+            .write_neutron_batch_csv_with_pulse_id(&neutrons, Some(7), true)
+            .unwrap();
+        writer
+            .write_neutron_batch_csv_with_pulse_id(&neutrons, None, false)
+            .unwrap();
+        drop(writer);
+        let buffer = std::fs::read(&path).unwrap();
+
+        let (read, ids) = read_neutron_batch_csv_with_pulse_ids(buffer.as_slice()).unwrap();
+        assert_eq!(read.len(), 2);
+        assert_eq!(ids, Some(vec![Some(7), None]));
+        let (_, ids) =
+            read_neutron_batch_csv_with_pulse_ids("x,y,tof\n1,2,3\n".as_bytes()).unwrap();
+        assert_eq!(ids, None);
+    }
+}
@@ -1,23 +1,111 @@
 //! Memory-mapped file readers.
 //!
 
+use crate::incremental::resume_section;
 use crate::{Error, Result};
 use memmap2::Mmap;
-use rustpix_core::soa::HitBatch;
-use rustpix_tpx::ordering::TimeOrderedStream;
-use rustpix_tpx::section::discover_sections;
-use rustpix_tpx::{DetectorConfig, Tpx3Packet};
+use rustpix_core::pulse::PulseIndex;
+use rustpix_core::soa::{HitBatch, HitRecord};
+use rustpix_tpx::frame::{
+    count_frame_packets, read_frames, FrameImage, FramePacketCounts, FrameValue,
+};
+use rustpix_tpx::ordering::{
+    continue_pulse_contexts, read_time_ordered_parallel, section_pulse_index, PulseContext,
+    TimeOrderedStream,
+};
+use rustpix_tpx::projection::{read_columns_parallel, HitColumns};
+use rustpix_tpx::section::{discover_sections, Tpx3Section};
+use rustpix_tpx::tdc::{
+    diagnose_tdc_sections, estimate_tdc_frequency, find_pulse_gaps, summarize_tdc_coverage,
+    PulseGapReport, TdcCoverage, TdcDiagnostics, TdcFrequencyEstimate,
+};
+use rustpix_tpx::validation::{validate_sections, ValidationConfig, ValidationReport};
+use rustpix_tpx::{DetectorConfig, NoTdcFallback, Tpx3Packet};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
 use std::fs::File;
+use std::io::{BufReader, Read};
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
+/// Buffer size for [`ReadMode::Buffered`] reads.
+const BUFFERED_READ_BYTES: usize = 8 * 1024 * 1024;
+
+/// Prefix of the file scanned by [`Tpx3FileReader::estimate_tdc_frequency`].
+const TDC_ESTIMATE_BYTES: usize = 256 * 1024 * 1024;
+
+/// Backing storage for reader contents.
+enum FileData {
+    /// Memory-mapped local file.
+    Mapped(Mmap),
+    /// In-memory buffer (e.g. an object fetched from remote storage).
+    Owned(Vec<u8>),
+}
+
+impl std::ops::Deref for FileData {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match self {
+            Self::Mapped(mmap) => mmap,
+            Self::Owned(bytes) => bytes,
+        }
+    }
+}
+
+/// Environment variable that makes [`ReadMode::Auto`] skip memory mapping.
+pub const NO_MMAP_ENV: &str = "RUSTPIX_NO_MMAP";
+
+/// How file contents are accessed.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ReadMode {
+    /// Memory-map the file, using buffered reads instead when mapping is
+    /// unreliable: Windows UNC network paths, `RUSTPIX_NO_MMAP` set, or a
+    /// failed mapping.
+    #[default]
+    Auto,
+    /// Always memory-map the file.
+    Mmap,
+    /// Read the whole file into memory with buffered I/O.
+    Buffered,
+}
+
+impl ReadMode {
+    /// Resolve `Auto` to a concrete mode for `path`.
+    fn resolve(self, path: &Path) -> Self {
+        match self {
+            Self::Auto if no_mmap_requested() || is_network_path(path) => Self::Buffered,
+            mode => mode,
+        }
+    }
+}
+
+fn no_mmap_requested() -> bool {
+    std::env::var_os(NO_MMAP_ENV).is_some_and(|value| !value.is_empty() && value != "0")
+}
+
+/// UNC paths (`\\server\share`, or `\\?\UNC\server\share` in long-path
+/// form) are network mounts on Windows, where mapped pages can fault on
+/// access long after a successful `mmap`.
+fn is_network_path(path: &Path) -> bool {
+    use std::path::{Component, Prefix};
+
+    match path.components().next() {
+        Some(Component::Prefix(prefix)) => {
+            matches!(prefix.kind(), Prefix::UNC(..) | Prefix::VerbatimUNC(..))
+        }
+        _ => false,
+    }
+}
+
 /// A memory-mapped file reader.
 ///
 /// Uses memmap2 to efficiently access file contents without
-/// loading the entire file into memory.
+/// loading the entire file into memory. See [`ReadMode`] for the buffered
+/// fallback.
 pub struct MappedFileReader {
-    /// Memory-mapped file contents.
-    mmap: Arc<Mmap>,
+    /// Memory-mapped (or in-memory) file contents.
+    mmap: Arc<FileData>,
     /// Path to the underlying file.
     path: PathBuf,
 }
@@ -28,17 +116,79 @@ impl MappedFileReader {
     /// # Errors
     /// Returns an error if the file cannot be opened or memory-mapped.
     pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
-        let file = File::open(&path)?;
-        // SAFETY: The file is opened read-only and we assume it is not modified concurrently.
-        // This is the standard safety contract for memory mapping.
+        Self::open_with(path, ReadMode::Auto)
+    }
+
+    /// Opens a file using the given read mode.
+    ///
+    /// # Errors
+    /// Returns an error if the file cannot be opened, mapped (in
+    /// [`ReadMode::Mmap`]) or read.
+    pub fn open_with<P: AsRef<Path>>(path: P, mode: ReadMode) -> Result<Self> {
+        let path = path.as_ref();
+        let mut file = File::open(path)?;
+        let data = match mode.resolve(path) {
+            ReadMode::Buffered => FileData::Owned(read_buffered(&mut file)?),
+            resolved => {
+                // SAFETY: The file is opened read-only and we assume it is not modified concurrently.
+                // This is the standard safety contract for memory mapping.
+                #[allow(unsafe_code)]
+                let mapped = unsafe { Mmap::map(&file) };
+                match mapped {
+                    Ok(mmap) => FileData::Mapped(mmap),
+                    Err(_) if resolved == ReadMode::Auto => {
+                        FileData::Owned(read_buffered(&mut file)?)
+                    }
+                    Err(err) => return Err(err.into()),
+                }
+            }
+        };
+        Ok(Self {
+            mmap: Arc::new(data),
+            path: path.to_path_buf(),
+        })
+    }
+
+    /// Wraps an in-memory buffer, e.g. data fetched from object storage.
+    ///
+    /// `path` is only used to label error messages.
+    #[must_use]
+    pub fn from_bytes(bytes: Vec<u8>, path: impl Into<PathBuf>) -> Self {
+        Self {
+            mmap: Arc::new(FileData::Owned(bytes)),
+            path: path.into(),
+        }
+    }
+
+    /// Maps an already open file, e.g. an unnamed staging file.
+    ///
+    /// `path` is only used to label error messages.
+    ///
+    /// # Errors
+    /// Returns an error if the file cannot be memory-mapped.
+    pub fn from_file(file: &File, path: impl Into<PathBuf>) -> Result<Self> {
+        // SAFETY: As in `open_with`; the caller owns the file and does not
+        // modify it while the mapping is alive.
         #[allow(unsafe_code)]
-        let mmap = unsafe { Mmap::map(&file)? };
+        let mmap = unsafe { Mmap::map(file) }?;
         Ok(Self {
-            mmap: Arc::new(mmap),
-            path: path.as_ref().to_path_buf(),
+            mmap: Arc::new(FileData::Mapped(mmap)),
+            path: path.into(),
         })
     }
 
+    /// Returns true if the contents are memory-mapped rather than buffered.
+    #[must_use]
+    pub fn is_mapped(&self) -> bool {
+        matches!(*self.mmap, FileData::Mapped(_))
+    }
+
+    /// Returns the path the reader was opened with.
+    #[must_use]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
     /// Returns the file contents as a byte slice.
     #[must_use]
     pub fn as_bytes(&self) -> &[u8] {
@@ -65,8 +215,15 @@ impl MappedFileReader {
     }
 }
 
+fn read_buffered(file: &mut File) -> Result<Vec<u8>> {
+    let len = file.metadata().map_or(0, |meta| meta.len());
+    let mut bytes = Vec::with_capacity(usize::try_from(len).unwrap_or(0));
+    BufReader::with_capacity(BUFFERED_READ_BYTES, file).read_to_end(&mut bytes)?;
+    Ok(bytes)
+}
+
 #[derive(Clone)]
-struct SharedMmap(Arc<Mmap>);
+struct SharedMmap(Arc<FileData>);
 
 impl AsRef<[u8]> for SharedMmap {
     fn as_ref(&self) -> &[u8] {
@@ -88,6 +245,34 @@ impl Iterator for TimeOrderedHitStream {
     }
 }
 
+/// Lazy, time-ordered iterator over individual hits.
+///
+/// Hits are decoded one pulse at a time, so memory stays bounded by the
+/// largest pulse rather than the file. TDC state is carried across sections
+/// by the underlying pulse-ordered stream.
+pub struct HitIter {
+    /// Underlying pulse-ordered stream.
+    inner: TimeOrderedStream<SharedMmap>,
+    /// Hits of the current pulse.
+    batch: HitBatch,
+    /// Next hit of `batch` to yield.
+    index: usize,
+}
+
+impl Iterator for HitIter {
+    type Item = HitRecord;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.index >= self.batch.len() {
+            self.batch = self.inner.next()?;
+            self.index = 0;
+        }
+        let hit = self.batch.get(self.index);
+        self.index += 1;
+        hit
+    }
+}
+
 /// A pulse-ordered event batch with its TDC timestamp (25ns ticks).
 pub struct EventBatch {
     /// Pulse TDC timestamp in 25ns ticks.
@@ -100,16 +285,80 @@ pub struct EventBatch {
 pub struct TimeOrderedEventStream {
     /// Underlying pulse-ordered stream.
     inner: TimeOrderedStream<SharedMmap>,
+    /// Pulses up to this extended TDC timestamp are skipped.
+    skip_through: Option<u64>,
 }
 
 impl Iterator for TimeOrderedEventStream {
     type Item = EventBatch;
 
     fn next(&mut self) -> Option<Self::Item> {
-        self.inner.next_pulse_batch().map(|batch| EventBatch {
-            tdc_timestamp_25ns: batch.tdc_timestamp,
-            hits: batch.hits,
-        })
+        loop {
+            let batch = self.inner.next_pulse_batch()?;
+            if self
+                .skip_through
+                .is_some_and(|skip| batch.tdc_timestamp <= skip)
+            {
+                continue;
+            }
+            return Some(EventBatch {
+                tdc_timestamp_25ns: batch.tdc_timestamp,
+                hits: batch.hits,
+            });
+        }
+    }
+}
+
+/// Where a file's time-ordered pulse stream resumes after a given pulse,
+/// e.g. to continue processing after an interrupted run.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct StreamPosition {
+    /// Byte offset of the first section that can hold later pulses.
+    pub offset: usize,
+    /// Pulse state of each chip before its first section at or after
+    /// `offset`.
+    pub chips: Vec<(u8, PulseContext)>,
+    /// Extended TDC timestamp of the last pulse already consumed, in 25 ns
+    /// ticks.
+    pub emitted_through: u64,
+}
+
+/// Sections of a file and the pulse state each starts from, for taking
+/// [`StreamPosition`]s while its pulses are streamed, with the file's
+/// pulses.
+pub struct SectionIndex {
+    sections: Vec<Tpx3Section>,
+    contexts: Vec<PulseContext>,
+    pulses: PulseIndex,
+}
+
+impl SectionIndex {
+    /// Pulses of the file, numbered in time order.
+    #[must_use]
+    pub fn pulses(&self) -> &PulseIndex {
+        &self.pulses
+    }
+
+    /// Position of the stream once the pulse at `emitted_through` has been
+    /// consumed.
+    #[must_use]
+    pub fn position(&self, emitted_through: u64) -> StreamPosition {
+        let start = resume_section(&self.sections, &self.contexts, emitted_through);
+        let mut chips: Vec<(u8, PulseContext)> = Vec::new();
+        for (section, context) in self.sections[start..].iter().zip(&self.contexts[start..]) {
+            if !chips.iter().any(|(chip, _)| *chip == section.chip_id) {
+                chips.push((section.chip_id, *context));
+            }
+        }
+        StreamPosition {
+            offset: self
+                .sections
+                .get(start)
+                .map_or(0, |section| section.start_offset),
+            chips,
+            emitted_through,
+        }
     }
 }
 
@@ -127,13 +376,51 @@ impl Tpx3FileReader {
     /// # Errors
     /// Returns an error if the file cannot be opened or memory-mapped.
     pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
-        let reader = MappedFileReader::open(path)?;
+        Self::open_with(path, ReadMode::Auto)
+    }
+
+    /// Opens a TPX3 file with the given read mode and default configuration.
+    ///
+    /// # Errors
+    /// Returns an error if the file cannot be opened or read.
+    pub fn open_with<P: AsRef<Path>>(path: P, mode: ReadMode) -> Result<Self> {
+        let reader = MappedFileReader::open_with(path, mode)?;
         Ok(Self {
             reader,
             config: DetectorConfig::default(),
         })
     }
 
+    /// Returns true if the file is memory-mapped rather than buffered.
+    #[must_use]
+    pub fn is_mapped(&self) -> bool {
+        self.reader.is_mapped()
+    }
+
+    /// Wraps TPX3 data already held in memory with default configuration.
+    ///
+    /// `path` is only used to label error messages.
+    #[must_use]
+    pub fn from_bytes(bytes: Vec<u8>, path: impl Into<PathBuf>) -> Self {
+        Self {
+            reader: MappedFileReader::from_bytes(bytes, path),
+            config: DetectorConfig::default(),
+        }
+    }
+
+    /// Maps an already open TPX3 file with default configuration.
+    ///
+    /// `path` is only used to label error messages.
+    ///
+    /// # Errors
+    /// Returns an error if the file cannot be memory-mapped.
+    pub fn from_file(file: &File, path: impl Into<PathBuf>) -> Result<Self> {
+        Ok(Self {
+            reader: MappedFileReader::from_file(file, path)?,
+            config: DetectorConfig::default(),
+        })
+    }
+
     /// Sets the detector configuration.
     #[must_use]
     pub fn with_config(mut self, config: DetectorConfig) -> Self {
@@ -141,6 +428,12 @@ impl Tpx3FileReader {
         self
     }
 
+    /// Returns the detector configuration.
+    #[must_use]
+    pub fn config(&self) -> &DetectorConfig {
+        &self.config
+    }
+
     /// Returns the file size in bytes.
     #[must_use]
     pub fn file_size(&self) -> usize {
@@ -155,8 +448,8 @@ impl Tpx3FileReader {
 
     /// Reads and parses all hits from the file into a `HitBatch` (`SoA`).
     ///
-    /// This uses the pulse-based time-ordered stream to ensure correct
-    /// temporal ordering across pulses and chips.
+    /// Sections are decoded in parallel and merged pulse by pulse to ensure
+    /// correct temporal ordering across pulses and chips.
     ///
     /// # Errors
     /// Returns an error if the file size is invalid or the data cannot be parsed.
@@ -164,10 +457,11 @@ impl Tpx3FileReader {
         self.read_batch_time_ordered()
     }
 
-    /// Reads hits using the efficient time-ordered stream.
+    /// Reads hits in time order, parsing sections in parallel.
     ///
-    /// This uses a pulse-based K-way merge to produce time-ordered hits
-    /// without loading the entire file or performing a global sort.
+    /// Section discovery and TDC propagation run sequentially, then sections
+    /// are decoded on the rayon thread pool and merged pulse by pulse, without
+    /// a global sort. Use [`Self::stream_time_ordered`] to bound memory instead.
     ///
     /// This is functionally equivalent to `read_batch()` and is retained
     /// for clarity.
@@ -185,13 +479,34 @@ impl Tpx3FileReader {
 
         let data = self.reader.as_bytes();
         let sections = discover_sections(data);
+        Ok(read_time_ordered_parallel(data, &sections, &self.config))
+    }
 
-        let stream = TimeOrderedStream::new(data, &sections, &self.config);
-        let mut batch = HitBatch::default();
-        for pulse_batch in stream {
-            batch.append(&pulse_batch);
+    /// Reads only the requested hit columns of the whole file.
+    ///
+    /// Skipping the time columns avoids pulse ordering and TOF computation
+    /// entirely, for workflows that only histogram positions; see
+    /// [`read_columns_parallel`] for how hits are ordered then.
+    ///
+    /// # Errors
+    /// Returns an error if the file size is invalid.
+    pub fn read_batch_columns(&self, columns: HitColumns) -> Result<HitBatch> {
+        if !self.reader.len().is_multiple_of(8) {
+            return Err(Error::InvalidFormat(format!(
+                "file size {} is not a multiple of 8 (file: {})",
+                self.reader.len(),
+                self.reader.path.display()
+            )));
         }
-        Ok(batch)
+
+        let data = self.reader.as_bytes();
+        let sections = discover_sections(data);
+        Ok(read_columns_parallel(
+            data,
+            &sections,
+            &self.config,
+            columns,
+        ))
     }
 
     /// Returns a time-ordered stream of hit batches (pulse-merged).
@@ -216,6 +531,23 @@ impl Tpx3FileReader {
         Ok(TimeOrderedHitStream { inner: stream })
     }
 
+    /// Returns a lazy, time-ordered iterator over hits.
+    ///
+    /// Yields the same hits in the same order as [`Self::read_batch`] (up to
+    /// the order of hits with equal TOF within a pulse), as
+    /// `(x, y, tof, tot, timestamp, chip_id)` records, without collecting
+    /// the whole file in memory.
+    ///
+    /// # Errors
+    /// Returns an error if the file size is invalid.
+    pub fn iter_hits(&self) -> Result<HitIter> {
+        Ok(HitIter {
+            inner: self.stream_time_ordered()?.inner,
+            batch: HitBatch::default(),
+            index: 0,
+        })
+    }
+
     /// Returns a time-ordered stream of event batches (pulse-merged with TDC).
     ///
     /// # Errors
@@ -235,7 +567,178 @@ impl Tpx3FileReader {
             &sections,
             &self.config,
         );
-        Ok(TimeOrderedEventStream { inner: stream })
+        Ok(TimeOrderedEventStream {
+            inner: stream,
+            skip_through: None,
+        })
+    }
+
+    /// Index the pulse state of every section, for
+    /// [`SectionIndex::position`], and the pulses of the file.
+    ///
+    /// This scans the TDC packets of the whole file.
+    #[must_use]
+    pub fn section_index(&self) -> SectionIndex {
+        let data = self.reader.as_bytes();
+        let data = &data[..data.len() / 8 * 8];
+        let sections = discover_sections(data);
+        let (contexts, pulses) = section_pulse_index(data, &sections);
+        SectionIndex {
+            sections,
+            contexts,
+            pulses,
+        }
+    }
+
+    /// Pulses of the file, numbered in time order from its TDC packets.
+    ///
+    /// Synthetic pulses ([`NoTdcFallback::FirstHit`]) are not included.
+    #[must_use]
+    pub fn pulse_index(&self) -> PulseIndex {
+        self.section_index().pulses
+    }
+
+    /// Returns the time-ordered stream of event batches after `position`.
+    ///
+    /// Decoding starts at the section `position` names, so the sections
+    /// before it are not read. With [`NoTdcFallback::FirstHit`], synthetic
+    /// pulses depend on every earlier hit, so the file is decoded from the
+    /// start and the consumed pulses skipped.
+    ///
+    /// # Errors
+    /// Returns an error if the file size is invalid or no section starts at
+    /// the position's offset (e.g. the file changed since it was taken).
+    pub fn stream_time_ordered_events_from(
+        &self,
+        position: &StreamPosition,
+    ) -> Result<TimeOrderedEventStream> {
+        let mut stream = self.stream_time_ordered_events()?;
+        stream.skip_through = Some(position.emitted_through);
+        if self.config.no_tdc_fallback != NoTdcFallback::Drop {
+            return Ok(stream);
+        }
+
+        let data = self.reader.as_bytes();
+        let sections = discover_sections(data);
+        let start = sections
+            .iter()
+            .position(|section| section.start_offset >= position.offset)
+            .unwrap_or(sections.len());
+        if sections
+            .get(start)
+            .is_some_and(|section| section.start_offset != position.offset)
+        {
+            return Err(Error::InvalidFormat(format!(
+                "no section starts at offset {} (file: {})",
+                position.offset,
+                self.reader.path.display()
+            )));
+        }
+        let mut chips = [PulseContext::default(); 256];
+        for &(chip, context) in &position.chips {
+            chips[usize::from(chip)] = context;
+        }
+        let contexts = continue_pulse_contexts(data, &sections[start..], &mut chips);
+        stream.inner = TimeOrderedStream::with_contexts(
+            SharedMmap(self.reader.mmap.clone()),
+            &sections[start..],
+            &contexts,
+            &self.config,
+        );
+        Ok(stream)
+    }
+
+    /// Checks packet-level invariants (hit ordering within sections and pixel
+    /// bounds) against the configured detector.
+    ///
+    /// # Errors
+    /// Returns an error if the file size is invalid.
+    pub fn validate(&self, options: &ValidationConfig) -> Result<ValidationReport> {
+        if !self.reader.len().is_multiple_of(8) {
+            return Err(Error::InvalidFormat(format!(
+                "file size {} is not a multiple of 8 (file: {})",
+                self.reader.len(),
+                self.reader.path.display()
+            )));
+        }
+
+        let data = self.reader.as_bytes();
+        let sections = discover_sections(data);
+        Ok(validate_sections(data, &sections, &self.config, options))
+    }
+
+    /// Estimate the TDC frequency from the spacing of TDC packets in the
+    /// first 256 MiB of the file.
+    ///
+    /// Returns `None` if that part has fewer than two TDC packets on any chip.
+    #[must_use]
+    pub fn estimate_tdc_frequency(&self) -> Option<TdcFrequencyEstimate> {
+        let data = self.reader.as_bytes();
+        let len = data.len().min(TDC_ESTIMATE_BYTES) / 8 * 8;
+        let data = &data[..len];
+        estimate_tdc_frequency(data, &discover_sections(data))
+    }
+
+    /// List dropped pulses, using the configured TDC frequency as the
+    /// expected pulse period.
+    ///
+    /// Returns `None` if the file has no TDC packets.
+    #[must_use]
+    pub fn pulse_gaps(&self) -> Option<PulseGapReport> {
+        let data = self.reader.as_bytes();
+        let len = data.len() / 8 * 8;
+        let data = &data[..len];
+        find_pulse_gaps(
+            data,
+            &discover_sections(data),
+            self.config.tdc_correction_25ns(),
+        )
+    }
+
+    /// Count missing, duplicate and irregular TDCs per section, using the
+    /// configured TDC frequency as the expected pulse period.
+    ///
+    /// Returns `None` if the configured frequency gives no period.
+    #[must_use]
+    pub fn tdc_diagnostics(&self) -> Option<TdcDiagnostics> {
+        let data = self.reader.as_bytes();
+        let len = data.len() / 8 * 8;
+        let data = &data[..len];
+        diagnose_tdc_sections(
+            data,
+            &discover_sections(data),
+            self.config.tdc_correction_25ns(),
+        )
+    }
+
+    /// Count packets, hits and TDCs, to flag empty files and files whose
+    /// hits have no TDC reference.
+    #[must_use]
+    pub fn tdc_coverage(&self) -> TdcCoverage {
+        let data = self.reader.as_bytes();
+        let len = data.len() / 8 * 8;
+        let data = &data[..len];
+        summarize_tdc_coverage(data, &discover_sections(data))
+    }
+
+    /// Count frame-based pixel, hit and end-of-readout packets, to tell
+    /// frame-mode acquisitions from data-driven ones.
+    #[must_use]
+    pub fn frame_packet_counts(&self) -> FramePacketCounts {
+        let data = self.reader.as_bytes();
+        let len = data.len() / 8 * 8;
+        let data = &data[..len];
+        count_frame_packets(data, &discover_sections(data))
+    }
+
+    /// Read a frame-mode file as one detector image per frame, mapped with
+    /// the configured chip transforms.
+    #[must_use]
+    pub fn read_frames(&self, value: FrameValue) -> Vec<FrameImage> {
+        let data = self.reader.as_bytes();
+        let len = data.len() / 8 * 8;
+        let data = &data[..len];
+        read_frames(data, &discover_sections(data), &self.config, value)
     }
 
     /// Returns an iterator over raw packets.
@@ -254,6 +757,7 @@ impl Tpx3FileReader {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use rustpix_tpx::tdc::CoverageFlag;
     use std::io::Write;
     use tempfile::NamedTempFile;
 
@@ -270,6 +774,65 @@ mod tests {
         assert_eq!(reader.as_bytes(), &data[..]);
     }
 
+    #[test]
+    fn test_open_non_ascii_and_long_paths() {
+        let root = tempfile::tempdir().unwrap();
+        let mut dir = root.path().join("beam time 2024").join("測定データ");
+        // Nest past the 260-character Windows MAX_PATH limit.
+        while dir.as_os_str().len() < 300 {
+            dir.push("サンプル 試料");
+        }
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("run 1 ラン.tpx3");
+        let data: Vec<u8> = (0..64).collect();
+        std::fs::write(&path, &data).unwrap();
+
+        for mode in [ReadMode::Auto, ReadMode::Mmap, ReadMode::Buffered] {
+            let reader = MappedFileReader::open_with(&path, mode).unwrap();
+            assert_eq!(reader.as_bytes(), &data[..]);
+            assert_eq!(reader.path(), path.as_path());
+        }
+        assert!(!is_network_path(&path));
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn test_network_path_detection() {
+        assert!(is_network_path(Path::new(r"\\server\share\run.tpx3")));
+        assert!(is_network_path(Path::new(r"\\?\UNC\server\share\run.tpx3")));
+        assert!(!is_network_path(Path::new(r"\\?\C:\data\run.tpx3")));
+        assert!(!is_network_path(Path::new(r"C:\data\run.tpx3")));
+    }
+
+    #[test]
+    fn test_buffered_read_mode() {
+        let mut file = NamedTempFile::new().unwrap();
+        let data: Vec<u8> = (0..64).collect();
+        file.write_all(&data).unwrap();
+        file.flush().unwrap();
+
+        let buffered = MappedFileReader::open_with(file.path(), ReadMode::Buffered).unwrap();
+        assert!(!buffered.is_mapped());
+        assert_eq!(buffered.as_bytes(), &data[..]);
+
+        let mapped = MappedFileReader::open_with(file.path(), ReadMode::Mmap).unwrap();
+        assert!(mapped.is_mapped());
+        assert_eq!(mapped.as_bytes(), buffered.as_bytes());
+    }
+
+    #[test]
+    fn test_from_unnamed_file() {
+        let mut file = tempfile::tempfile().unwrap();
+        let data: Vec<u8> = (0..64).collect();
+        file.write_all(&data).unwrap();
+        file.flush().unwrap();
+
+        let reader = MappedFileReader::from_file(&file, "s3://bucket/run.tpx3").unwrap();
+        assert!(reader.is_mapped());
+        assert_eq!(reader.as_bytes(), &data[..]);
+        assert_eq!(reader.path(), Path::new("s3://bucket/run.tpx3"));
+    }
+
     #[test]
     fn test_tpx3_file_reader_empty() {
         let file = NamedTempFile::new().unwrap();
@@ -280,6 +843,8 @@ mod tests {
 
         let batch = reader.read_batch().unwrap();
         assert!(batch.is_empty());
+        assert_eq!(reader.tdc_coverage().flags(), vec![CoverageFlag::Empty]);
+        assert_eq!(reader.stream_time_ordered_events().unwrap().count(), 0);
     }
 
     #[test]
@@ -290,5 +855,148 @@ mod tests {
 
         let reader = Tpx3FileReader::open(file.path()).unwrap();
         assert!(reader.read_batch().is_err());
+        assert!(reader.iter_hits().is_err());
+    }
+
+    #[test]
+    fn test_synthetic_file_recovers_ground_truth() {
+        use rustpix_algorithms::{
+            cluster_batch, extract_batch, AlgorithmParams, ClusteringAlgorithm, ClusteringConfig,
+        };
+        use rustpix_core::extraction::ExtractionConfig;
+        use rustpix_tpx::synthetic::{generate, Footprint, SyntheticTpx3Config};
+
+        let detector = DetectorConfig::venus_defaults();
+        let config = SyntheticTpx3Config {
+            footprint: Footprint::Square,
+            // Roll over mid-file.
+            first_tdc: 0x3FFF_FFFF - 2_000_000,
+            ..SyntheticTpx3Config::default()
+        };
+        let synthetic = generate(&config, &detector);
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("synthetic.tpx3");
+        synthetic.write(&path).unwrap();
+
+        let reader = Tpx3FileReader::open(&path).unwrap().with_config(detector);
+        let mut expected: Vec<(u32, u16, u16)> = synthetic
+            .neutrons
+            .iter()
+            .map(|n| (n.tof, pixel(n.x), pixel(n.y)))
+            .collect();
+        expected.sort_unstable();
+        let extraction = ExtractionConfig {
+            super_resolution_factor: 1.0,
+            ..ExtractionConfig::default()
+        };
+        for algorithm in [
+            ClusteringAlgorithm::Abs,
+            ClusteringAlgorithm::Dbscan,
+            ClusteringAlgorithm::Grid,
+            ClusteringAlgorithm::ConnectedComponents,
+        ] {
+            let mut batch = reader.read_batch().unwrap();
+            assert_eq!(batch.len(), synthetic.hits);
+            let clusters = cluster_batch(
+                &mut batch,
+                algorithm,
+                &ClusteringConfig::default(),
+                &AlgorithmParams::default(),
+            )
+            .unwrap();
+            let neutrons = extract_batch(&batch, clusters, &extraction).unwrap();
+            let mut found: Vec<(u32, u16, u16)> = (0..neutrons.len())
+                .map(|i| (neutrons.tof[i], pixel(neutrons.x[i]), pixel(neutrons.y[i])))
+                .collect();
+            found.sort_unstable();
+            assert_eq!(found, expected, "{algorithm:?}");
+        }
+    }
+
+    /// Integer pixel of a centroid that should sit on a pixel.
+    fn pixel(value: f64) -> u16 {
+        assert!((value - value.round()).abs() < 1e-9, "{value}");
+        format!("{value:.0}").parse().unwrap()
+    }
+
+    #[test]
+    fn test_iter_hits_matches_read_batch() {
+        // Two sections of chip 0 with a TDC in each, so the second section's
+        // hits depend on TDC state carried over from the first.
+        let header = Tpx3Packet::TPX3_HEADER_MAGIC;
+        let tdc = |timestamp: u64| 0x6F00_0000_0000_0000 | (timestamp << 12);
+        let hit =
+            |toa: u64, addr: u64| 0xB000_0000_0000_0000 | (toa << 30) | (20 << 20) | (addr << 44);
+        let packets = [
+            header,
+            tdc(1000),
+            hit(100, 1),
+            hit(50, 2),
+            header,
+            tdc(500_000),
+            hit(10, 300),
+        ];
+        let mut data = Vec::new();
+        for packet in packets {
+            data.extend_from_slice(&packet.to_le_bytes());
+        }
+        let reader = Tpx3FileReader::from_bytes(data, "memory.tpx3");
+
+        let batch = reader.read_batch().unwrap();
+        let hits: Vec<HitRecord> = reader.iter_hits().unwrap().collect();
+        assert!(!hits.is_empty());
+        assert_eq!(hits.len(), batch.len());
+        for (i, hit) in hits.iter().enumerate() {
+            assert_eq!(hit.0, batch.x[i]);
+            assert_eq!(hit.2, batch.tof[i]);
+            assert_eq!(hit.4, batch.timestamp[i]);
+        }
+    }
+
+    #[test]
+    fn test_stream_resumes_from_position() {
+        // Two chips, one section per chip and pulse, so a position after the
+        // third pulse skips the first sections of both chips.
+        let header = |chip: u64| Tpx3Packet::TPX3_HEADER_MAGIC | (chip << 32);
+        let tdc = |timestamp: u64| 0x6F00_0000_0000_0000 | (timestamp << 12);
+        let hit =
+            |toa: u64, addr: u64| 0xB000_0000_0000_0000 | (toa << 30) | (20 << 20) | (addr << 44);
+        let mut packets = Vec::new();
+        for pulse in 0..6u64 {
+            let start = 1000 + pulse * 2000;
+            for chip in 0..2 {
+                packets.extend([
+                    header(chip),
+                    tdc(start),
+                    hit(start + 100, 1 + chip),
+                    hit(start + 150, 5),
+                ]);
+            }
+        }
+        let data: Vec<u8> = packets.iter().flat_map(|p| p.to_le_bytes()).collect();
+        let reader = Tpx3FileReader::from_bytes(data, "memory.tpx3");
+
+        let all: Vec<EventBatch> = reader.stream_time_ordered_events().unwrap().collect();
+        assert_eq!(all.len(), 6);
+        let position = reader.section_index().position(all[2].tdc_timestamp_25ns);
+        assert!(position.offset > 0);
+        assert_eq!(position.chips.len(), 2);
+
+        let resumed: Vec<EventBatch> = reader
+            .stream_time_ordered_events_from(&position)
+            .unwrap()
+            .collect();
+        assert_eq!(resumed.len(), 3);
+        for (resumed, expected) in resumed.iter().zip(&all[3..]) {
+            assert_eq!(resumed.tdc_timestamp_25ns, expected.tdc_timestamp_25ns);
+            assert_eq!(resumed.hits.tof, expected.hits.tof);
+            assert_eq!(resumed.hits.x, expected.hits.x);
+        }
+
+        let moved = StreamPosition {
+            offset: position.offset + 8,
+            ..position
+        };
+        assert!(reader.stream_time_ordered_events_from(&moved).is_err());
     }
 }
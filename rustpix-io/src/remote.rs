@@ -0,0 +1,232 @@
+//! Object-store (`s3://`) input and output.
+//!
+//! Remote `.tpx3` objects are fetched with ranged GETs into an unnamed local
+//! staging file, which is memory-mapped and exposed through the regular
+//! [`Tpx3FileReader`], so section discovery never holds the object in memory.
+//! Outputs are written to a local file first and uploaded with a multipart
+//! upload once complete. The transfers work on any [`ObjectStore`];
+//! [`resolve`] builds the S3 one for an `s3://` URI.
+//!
+//! Credentials and endpoints come from the standard AWS environment variables
+//! (`AWS_ACCESS_KEY_ID`, `AWS_SECRET_ACCESS_KEY`, `AWS_REGION`, ...). Set
+//! `AWS_ENDPOINT` (and `AWS_ALLOW_HTTP=true` for plain HTTP) to target
+//! S3-compatible on-prem storage such as `MinIO` or Ceph.
+
+use crate::{Error, Result, Tpx3FileReader};
+use object_store::aws::AmazonS3Builder;
+use object_store::path::Path as ObjectPath;
+use object_store::{ObjectStore, WriteMultipart};
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::Path;
+
+/// URI scheme handled by this module.
+const S3_SCHEME: &str = "s3://";
+
+/// Range size used when downloading objects.
+const DOWNLOAD_CHUNK_BYTES: usize = 16 * 1024 * 1024;
+
+/// Part size used for multipart uploads.
+const UPLOAD_CHUNK_BYTES: usize = 16 * 1024 * 1024;
+
+/// Maximum number of parts in flight during an upload.
+const UPLOAD_CONCURRENCY: usize = 4;
+
+/// Returns true if `uri` names an object in remote storage.
+#[must_use]
+pub fn is_remote_uri(uri: &str) -> bool {
+    uri.starts_with(S3_SCHEME)
+}
+
+/// Download `path` from `store` into an unnamed local file with ranged
+/// GETs.
+///
+/// The object is fetched [`DOWNLOAD_CHUNK_BYTES`] at a time, so memory use
+/// stays bounded regardless of the object size. The returned file is
+/// rewound and is deleted when closed.
+///
+/// # Errors
+/// Returns an error if a request fails or the staging file cannot be
+/// written.
+pub fn download_object(store: &dyn ObjectStore, path: &ObjectPath) -> Result<File> {
+    download_in_chunks(store, path, DOWNLOAD_CHUNK_BYTES)
+}
+
+fn download_in_chunks(
+    store: &dyn ObjectStore,
+    path: &ObjectPath,
+    chunk_bytes: usize,
+) -> Result<File> {
+    let mut file = tempfile::tempfile()?;
+    block_on(async {
+        let size = store.head(path).await.map_err(store_error)?.size;
+        let mut start = 0;
+        while start < size {
+            let end = size.min(start + chunk_bytes);
+            let bytes = store
+                .get_range(path, start..end)
+                .await
+                .map_err(store_error)?;
+            file.write_all(&bytes)?;
+            start = end;
+        }
+        Ok(())
+    })?;
+    file.flush()?;
+    file.seek(SeekFrom::Start(0))?;
+    Ok(file)
+}
+
+/// Open a remote `.tpx3` object with default configuration.
+///
+/// # Errors
+/// Returns an error if the URI is invalid, the object cannot be downloaded
+/// or it is not a whole number of 8-byte packets.
+pub fn open_tpx3(uri: &str) -> Result<Tpx3FileReader> {
+    let (store, path) = resolve(uri)?;
+    let file = download_object(store.as_ref(), &path)?;
+    let len = file.metadata()?.len();
+    if len % 8 != 0 {
+        return Err(Error::InvalidFormat(format!(
+            "{uri}: size {len} is not a multiple of 8"
+        )));
+    }
+    Tpx3FileReader::from_file(&file, uri)
+}
+
+/// Upload a local file to `path` in `store` with a multipart upload.
+///
+/// # Errors
+/// Returns an error if the file cannot be read or the upload fails. A failed
+/// upload is aborted so no partial object is left behind.
+pub fn upload_file<P: AsRef<Path>>(
+    store: &dyn ObjectStore,
+    local: P,
+    path: &ObjectPath,
+) -> Result<()> {
+    upload_in_chunks(store, local.as_ref(), path, UPLOAD_CHUNK_BYTES)
+}
+
+fn upload_in_chunks(
+    store: &dyn ObjectStore,
+    local: &Path,
+    path: &ObjectPath,
+    chunk_bytes: usize,
+) -> Result<()> {
+    let mut file = File::open(local)?;
+    block_on(async move {
+        let upload = store.put_multipart(path).await.map_err(store_error)?;
+        let mut writer = WriteMultipart::new_with_chunk_size(upload, chunk_bytes);
+        let mut buffer = vec![0u8; chunk_bytes];
+        loop {
+            let read = match file.read(&mut buffer) {
+                Ok(0) => break,
+                Ok(n) => n,
+                Err(err) if err.kind() == std::io::ErrorKind::Interrupted => continue,
+                Err(err) => {
+                    let _ = writer.abort().await;
+                    return Err(err.into());
+                }
+            };
+            writer.write(&buffer[..read]);
+            if let Err(err) = writer.wait_for_capacity(UPLOAD_CONCURRENCY).await {
+                let _ = writer.abort().await;
+                return Err(store_error(err));
+            }
+        }
+        writer.finish().await.map_err(store_error)?;
+        Ok(())
+    })
+}
+
+/// Split `s3://bucket/key` and build an S3 store for the bucket, configured
+/// from the environment, with the object's path in it.
+///
+/// # Errors
+/// Returns an error if the URI is not `s3://bucket/key` or the store cannot
+/// be configured.
+pub fn resolve(uri: &str) -> Result<(Box<dyn ObjectStore>, ObjectPath)> {
+    let rest = uri
+        .strip_prefix(S3_SCHEME)
+        .ok_or_else(|| Error::ObjectStore(format!("unsupported URI: {uri}")))?;
+    let (bucket, key) = rest
+        .split_once('/')
+        .filter(|(bucket, key)| !bucket.is_empty() && !key.is_empty())
+        .ok_or_else(|| Error::ObjectStore(format!("expected s3://bucket/key, got {uri}")))?;
+    let store = AmazonS3Builder::from_env()
+        .with_bucket_name(bucket)
+        .build()
+        .map_err(store_error)?;
+    let path = ObjectPath::parse(key).map_err(|err| Error::ObjectStore(err.to_string()))?;
+    Ok((Box::new(store), path))
+}
+
+fn block_on<F: std::future::Future<Output = Result<T>>, T>(future: F) -> Result<T> {
+    tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()?
+        .block_on(future)
+}
+
+#[allow(clippy::needless_pass_by_value)]
+fn store_error(err: object_store::Error) -> Error {
+    Error::ObjectStore(err.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use object_store::memory::InMemory;
+
+    #[test]
+    fn test_remote_uri_parsing() {
+        assert!(is_remote_uri("s3://bucket/run.tpx3"));
+        assert!(!is_remote_uri("/data/run.tpx3"));
+        assert!(resolve("s3://bucket").is_err());
+        assert!(resolve("s3:///key").is_err());
+        assert!(resolve("file:///key").is_err());
+    }
+
+    #[test]
+    fn test_download_in_ranged_chunks() {
+        let store = InMemory::new();
+        for size in [0, 7, 8, 16, 17] {
+            let data: Vec<u8> = (0..size).map(|i| u8::try_from(i).unwrap()).collect();
+            let path = ObjectPath::from(format!("run_{size}.tpx3"));
+            block_on(async {
+                store
+                    .put(&path, data.clone().into())
+                    .await
+                    .map_err(store_error)
+            })
+            .unwrap();
+            let mut file = download_in_chunks(&store, &path, 8).unwrap();
+            let mut read = Vec::new();
+            file.read_to_end(&mut read).unwrap();
+            assert_eq!(read, data, "{size} bytes");
+        }
+        let missing = ObjectPath::from("missing.tpx3");
+        assert!(download_object(&store, &missing).is_err());
+    }
+
+    #[test]
+    fn test_multipart_upload() {
+        let store = InMemory::new();
+        let dir = tempfile::tempdir().unwrap();
+        for size in [0, 8, 29] {
+            let data: Vec<u8> = (0..size).map(|i| u8::try_from(i).unwrap()).collect();
+            let local = dir.path().join(format!("neutrons_{size}.bin"));
+            std::fs::write(&local, &data).unwrap();
+            let path = ObjectPath::from(format!("out/neutrons_{size}.bin"));
+            upload_in_chunks(&store, &local, &path, 8).unwrap();
+            let stored = block_on(async {
+                let object = store.get(&path).await.map_err(store_error)?;
+                object.bytes().await.map_err(store_error)
+            })
+            .unwrap();
+            assert_eq!(stored.as_ref(), data.as_slice(), "{size} bytes");
+        }
+        let missing = dir.path().join("missing.bin");
+        assert!(upload_file(&store, &missing, &ObjectPath::from("out/missing.bin")).is_err());
+    }
+}
@@ -0,0 +1,1090 @@
+//! ROOT `TTree` export of hits and neutrons.
+//!
+//! A minimal writer for the ROOT file format, so reduced data opens directly
+//! in ROOT or uproot without needing ROOT on the machine that writes it.
+//! Each file holds one tree (`neutrons` or `hits`) with the branches `x`,
+//! `y`, `tof`, `tot_sum`, `cluster_size` and `chip_id`, in the same raw
+//! units as the CSV output (TOF in 25 ns ticks, neutron coordinates in
+//! super-resolution space).
+//!
+//! Entries are buffered per branch and written as uncompressed baskets every
+//! `BASKET_ENTRIES` entries, at the same entries in every branch, so memory
+//! stays bounded however long the run is. The tree, the keys list and the
+//! file header are written by `finish`. The file carries no class streamer
+//! info: `TTree` v20, `TBranch` v13 and the `TLeaf` classes are read with
+//! the dictionaries built into ROOT 6 and uproot.
+
+use crate::fits::utc_now_fields;
+use crate::{Error, Result};
+use rustpix_core::neutron::NeutronBatch;
+use rustpix_core::soa::HitBatch;
+use rustpix_core::SplitMix64;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufWriter, Seek, SeekFrom, Write};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Entries per basket, and so per cluster of the tree.
+const BASKET_ENTRIES: usize = 8000;
+
+/// Basket size hint recorded in each branch, as ROOT's default.
+const BASKET_SIZE: i32 = 32_000;
+
+/// Offset of the top directory record.
+const BEGIN: u64 = 100;
+
+/// Seeks beyond this need the 64-bit variants of keys and headers.
+const START_BIG_FILE: u64 = 2_000_000_000;
+
+/// File format version written to the header (ROOT 6.22 layout).
+const FILE_VERSION: i32 = 62_206;
+
+/// Bytes of the `TBasket` fields that follow a basket's key header.
+const BASKET_HEADER: usize = 2 + 4 + 4 + 4 + 4 + 1;
+
+/// Bytes of a `TDirectory` record, with room for 64-bit seeks.
+const DIRECTORY_RECORD: usize = 60;
+
+const BYTE_COUNT_MASK: u32 = 0x4000_0000;
+const CLASS_MASK: u32 = 0x8000_0000;
+const NEW_CLASS_TAG: u32 = 0xFFFF_FFFF;
+/// Added to buffer offsets in object and class references.
+const MAP_OFFSET: u32 = 2;
+/// `TObject::fBits` of a heap object (`kNotDeleted | kIsOnHeap`).
+const OBJECT_BITS: u32 = 0x0300_0000;
+
+/// A default `ROOT::TIOFeatures`: byte count, version 0, class checksum and
+/// no feature bits.
+const IO_FEATURES: [u8; 11] = [
+    0x40, 0x00, 0x00, 0x07, 0x00, 0x00, 0x1a, 0xa1, 0x2f, 0x10, 0x00,
+];
+
+/// Streaming ROOT writer for neutrons, as a `neutrons` tree.
+///
+/// `cluster_size` is the number of hits in each neutron's cluster.
+/// Call [`finish`](Self::finish) to write the last baskets, the tree and
+/// the file header; a file dropped without it is not readable.
+pub struct RootNeutronWriter {
+    tree: TreeWriter,
+}
+
+impl RootNeutronWriter {
+    /// Create a neutron file at `path`.
+    ///
+    /// # Errors
+    /// Returns an error if the file cannot be created.
+    pub fn create<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Ok(Self {
+            tree: TreeWriter::create(
+                path.as_ref(),
+                "neutrons",
+                "rustpix neutrons",
+                &[
+                    ("x", Leaf::Double),
+                    ("y", Leaf::Double),
+                    ("tof", Leaf::UInt),
+                    ("tot_sum", Leaf::UShort),
+                    ("cluster_size", Leaf::UShort),
+                    ("chip_id", Leaf::UChar),
+                ],
+            )?,
+        })
+    }
+
+    /// Append a batch, writing baskets as they fill up.
+    ///
+    /// # Errors
+    /// Returns an error if writing a basket fails or the file is finished.
+    pub fn write_neutrons(&mut self, batch: &NeutronBatch) -> Result<()> {
+        let mut start = 0;
+        while start < batch.len() {
+            let end = (start + self.tree.room()?).min(batch.len());
+            let rows = start..end;
+            self.tree.extend(0, batch.x[rows.clone()].iter().copied());
+            self.tree.extend(1, batch.y[rows.clone()].iter().copied());
+            self.tree.extend(2, batch.tof[rows.clone()].iter().copied());
+            self.tree.extend(3, batch.tot[rows.clone()].iter().copied());
+            self.tree
+                .extend(4, batch.n_hits[rows.clone()].iter().copied());
+            self.tree.extend(5, batch.chip_id[rows].iter().copied());
+            self.tree.advance(end - start)?;
+            start = end;
+        }
+        Ok(())
+    }
+
+    /// Write the buffered entries, the tree and the file header; later
+    /// writes fail.
+    ///
+    /// # Errors
+    /// Returns an error if writing fails.
+    pub fn finish(&mut self) -> Result<()> {
+        self.tree.finish()
+    }
+}
+
+/// Streaming ROOT writer for hits, as a `hits` tree.
+///
+/// `tot_sum` is the hit's own `ToT` and `cluster_size` the number of hits
+/// in its cluster within the batch, 1 for hits that were not clustered.
+/// Call [`finish`](Self::finish) to write the last baskets, the tree and
+/// the file header; a file dropped without it is not readable.
+pub struct RootHitWriter {
+    tree: TreeWriter,
+}
+
+impl RootHitWriter {
+    /// Create a hit file at `path`.
+    ///
+    /// # Errors
+    /// Returns an error if the file cannot be created.
+    pub fn create<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Ok(Self {
+            tree: TreeWriter::create(
+                path.as_ref(),
+                "hits",
+                "rustpix hits",
+                &[
+                    ("x", Leaf::UShort),
+                    ("y", Leaf::UShort),
+                    ("tof", Leaf::UInt),
+                    ("tot_sum", Leaf::UShort),
+                    ("cluster_size", Leaf::UShort),
+                    ("chip_id", Leaf::UChar),
+                ],
+            )?,
+        })
+    }
+
+    /// Append a batch, writing baskets as they fill up.
+    ///
+    /// # Errors
+    /// Returns an error if writing a basket fails or the file is finished.
+    pub fn write_hits(&mut self, batch: &HitBatch) -> Result<()> {
+        let labelled = batch.cluster_id.len() == batch.len();
+        let mut sizes: HashMap<i32, u16> = HashMap::new();
+        if labelled {
+            for &id in batch.cluster_id.iter().filter(|&&id| id >= 0) {
+                let size = sizes.entry(id).or_insert(0);
+                *size = size.saturating_add(1);
+            }
+        }
+        let cluster_size = |row: usize| {
+            if labelled {
+                sizes.get(&batch.cluster_id[row]).copied().unwrap_or(1)
+            } else {
+                1
+            }
+        };
+        let mut start = 0;
+        while start < batch.len() {
+            let end = (start + self.tree.room()?).min(batch.len());
+            let rows = start..end;
+            self.tree.extend(0, batch.x[rows.clone()].iter().copied());
+            self.tree.extend(1, batch.y[rows.clone()].iter().copied());
+            self.tree.extend(2, batch.tof[rows.clone()].iter().copied());
+            self.tree.extend(3, batch.tot[rows.clone()].iter().copied());
+            self.tree.extend(4, rows.clone().map(cluster_size));
+            self.tree.extend(5, batch.chip_id[rows].iter().copied());
+            self.tree.advance(end - start)?;
+            start = end;
+        }
+        Ok(())
+    }
+
+    /// Write the buffered entries, the tree and the file header; later
+    /// writes fail.
+    ///
+    /// # Errors
+    /// Returns an error if writing fails.
+    pub fn finish(&mut self) -> Result<()> {
+        self.tree.finish()
+    }
+}
+
+/// Leaf types used by the trees.
+#[derive(Clone, Copy)]
+enum Leaf {
+    Double,
+    UInt,
+    UShort,
+    UChar,
+}
+
+impl Leaf {
+    fn class(self) -> &'static str {
+        match self {
+            Self::Double => "TLeafD",
+            Self::UInt => "TLeafI",
+            Self::UShort => "TLeafS",
+            Self::UChar => "TLeafB",
+        }
+    }
+
+    /// Type code of the branch title (`x/D`).
+    fn code(self) -> char {
+        match self {
+            Self::Double => 'D',
+            Self::UInt => 'i',
+            Self::UShort => 's',
+            Self::UChar => 'b',
+        }
+    }
+
+    fn size(self) -> usize {
+        match self {
+            Self::Double => 8,
+            Self::UInt => 4,
+            Self::UShort => 2,
+            Self::UChar => 1,
+        }
+    }
+}
+
+/// Values stored big-endian in baskets.
+trait Value: Copy {
+    fn put(self, out: &mut Vec<u8>);
+}
+
+macro_rules! impl_value {
+    ($($ty:ty),*) => {
+        $(impl Value for $ty {
+            fn put(self, out: &mut Vec<u8>) {
+                out.extend_from_slice(&self.to_be_bytes());
+            }
+        })*
+    };
+}
+
+impl_value!(f64, u32, u16, u8);
+
+/// One branch with its buffered values and written baskets.
+struct Branch {
+    name: &'static str,
+    leaf: Leaf,
+    data: Vec<u8>,
+    baskets: Vec<BasketInfo>,
+}
+
+/// Where a basket was written and the first entry it holds.
+struct BasketInfo {
+    seek: u64,
+    bytes: u64,
+    first_entry: u64,
+}
+
+/// A key written to the file.
+struct WrittenKey {
+    seek: u64,
+    bytes: u64,
+    /// The key header, as repeated in the keys list.
+    header: Vec<u8>,
+}
+
+/// Shared basket, tree and file bookkeeping of the hit and neutron writers.
+struct TreeWriter {
+    out: Output,
+    name: &'static str,
+    title: &'static str,
+    branches: Vec<Branch>,
+    /// Entries in written baskets.
+    entries: u64,
+    /// Entries buffered in `branches` but not yet written.
+    buffered: usize,
+    finished: bool,
+}
+
+impl TreeWriter {
+    fn create(
+        path: &Path,
+        name: &'static str,
+        title: &'static str,
+        branches: &[(&'static str, Leaf)],
+    ) -> Result<Self> {
+        let file_name = path
+            .file_name()
+            .map_or_else(String::new, |name| name.to_string_lossy().into_owned());
+        let mut out = Output {
+            file: BufWriter::new(File::create(path)?),
+            position: 0,
+            file_name,
+            datime: datime(),
+            uuid: uuid(),
+        };
+        // The header and top directory are written by `finish`, once the
+        // seeks they hold are known.
+        let reserved = BEGIN + to_u64(out.directory_key_len() + out.directory_len());
+        out.file.write_all(&vec![0; to_usize(reserved)])?;
+        out.position = reserved;
+        Ok(Self {
+            out,
+            name,
+            title,
+            branches: branches
+                .iter()
+                .map(|&(name, leaf)| Branch {
+                    name,
+                    leaf,
+                    data: Vec::with_capacity(BASKET_ENTRIES * leaf.size()),
+                    baskets: Vec::new(),
+                })
+                .collect(),
+            entries: 0,
+            buffered: 0,
+            finished: false,
+        })
+    }
+
+    /// Entries that fit in the current baskets.
+    fn room(&self) -> Result<usize> {
+        if self.finished {
+            return Err(Error::InvalidFormat(
+                "ROOT file is already finished".to_string(),
+            ));
+        }
+        Ok(BASKET_ENTRIES - self.buffered)
+    }
+
+    fn extend<T: Value>(&mut self, branch: usize, values: impl Iterator<Item = T>) {
+        let data = &mut self.branches[branch].data;
+        for value in values {
+            value.put(data);
+        }
+    }
+
+    /// Count `rows` entries just added to every branch.
+    fn advance(&mut self, rows: usize) -> Result<()> {
+        self.buffered += rows;
+        if self.buffered >= BASKET_ENTRIES {
+            self.write_baskets()?;
+        }
+        Ok(())
+    }
+
+    fn write_baskets(&mut self) -> Result<()> {
+        if self.buffered == 0 {
+            return Ok(());
+        }
+        for branch in &mut self.branches {
+            let keylen = self.out.key_len("TBasket", branch.name, self.name) + BASKET_HEADER;
+            let last = to_i32(keylen + branch.data.len())?;
+            let mut basket = Buffer::new(0);
+            basket.i16(3);
+            basket.i32(last);
+            basket.i32(to_i32(branch.leaf.size())?);
+            basket.i32(to_i32(self.buffered)?);
+            basket.i32(last);
+            basket.u8(0);
+            let key = self.out.write_key(
+                "TBasket",
+                branch.name,
+                self.name,
+                &basket.data,
+                &branch.data,
+            )?;
+            branch.baskets.push(BasketInfo {
+                seek: key.seek,
+                bytes: key.bytes,
+                first_entry: self.entries,
+            });
+            branch.data.clear();
+        }
+        self.entries += to_u64(self.buffered);
+        self.buffered = 0;
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        if self.finished {
+            return Ok(());
+        }
+        self.write_baskets()?;
+        self.finished = true;
+
+        let keylen = self.out.key_len("TTree", self.name, self.title);
+        let mut tree = Buffer::new(to_u32(keylen)?);
+        self.stream_tree(&mut tree)?;
+        let tree = self
+            .out
+            .write_key("TTree", self.name, self.title, &[], &tree.data)?;
+
+        let mut list = Buffer::new(0);
+        let start = list.begin(5);
+        list.object_header();
+        list.string("");
+        list.i32(0);
+        list.end(start);
+        let info = self.out.write_key(
+            "TList",
+            "StreamerInfo",
+            "Doubly linked list",
+            &[],
+            &list.data,
+        )?;
+
+        let mut keys = Buffer::new(0);
+        keys.i32(1);
+        keys.bytes(&tree.header);
+        let file_name = self.out.file_name.clone();
+        let keys = self
+            .out
+            .write_key("TFile", &file_name, "", &[], &keys.data)?;
+
+        let free = self.out.write_free_segments()?;
+        self.out.write_header(&info, &keys, &free)
+    }
+
+    /// Stream the `TTree` (v20) with its branches and leaves.
+    fn stream_tree(&self, buf: &mut Buffer) -> Result<()> {
+        let zip_bytes: u64 = self
+            .branches
+            .iter()
+            .flat_map(|branch| &branch.baskets)
+            .map(|basket| basket.bytes)
+            .sum();
+        let start = buf.begin(20);
+        buf.named(self.name, self.title);
+        buf.att_line();
+        buf.att_fill();
+        buf.att_marker();
+        buf.i64(to_i64(self.entries)?);
+        buf.i64(to_i64(zip_bytes)?); // fTotBytes
+        buf.i64(to_i64(zip_bytes)?); // fZipBytes
+        buf.i64(to_i64(zip_bytes)?); // fSavedBytes
+        buf.i64(0); // fFlushedBytes
+        buf.f64(1.0); // fWeight
+        buf.i32(0); // fTimerInterval
+        buf.i32(25); // fScanField
+        buf.i32(0); // fUpdate
+        buf.i32(1000); // fDefaultEntryOffsetLen
+        buf.i32(0); // fNClusterRange
+        buf.i64(1_000_000_000_000); // fMaxEntries
+        buf.i64(1_000_000_000_000); // fMaxEntryLoop
+        buf.i64(0); // fMaxVirtualSize
+        buf.i64(-300_000_000); // fAutoSave
+        buf.i64(to_i64(to_u64(BASKET_ENTRIES))?); // fAutoFlush
+        buf.i64(1_000_000); // fEstimate
+        buf.u8(0); // fClusterRangeEnd
+        buf.u8(0); // fClusterSize
+        buf.bytes(&IO_FEATURES);
+
+        let branches = buf.begin_array(self.branches.len())?;
+        let mut leaves = Vec::with_capacity(self.branches.len());
+        for branch in &self.branches {
+            leaves.push(self.stream_branch(buf, branch)?);
+        }
+        buf.end(branches);
+        let array = buf.begin_array(leaves.len())?;
+        for leaf in leaves {
+            buf.u32(leaf);
+        }
+        buf.end(array);
+
+        buf.u32(0); // fAliases
+        buf.i32(0); // fIndexValues
+        buf.i32(0); // fIndex
+        buf.u32(0); // fTreeIndex
+        buf.u32(0); // fFriends
+        buf.u32(0); // fUserInfo
+        buf.u32(0); // fBranchRef
+        buf.end(start);
+        Ok(())
+    }
+
+    /// Stream a `TBranch` (v13) and its leaf; returns the reference to the
+    /// leaf for the tree's leaf list.
+    fn stream_branch(&self, buf: &mut Buffer, branch: &Branch) -> Result<u32> {
+        let baskets = branch.baskets.len();
+        let bytes: u64 = branch.baskets.iter().map(|basket| basket.bytes).sum();
+        let entries = to_i64(self.entries)?;
+        let cntpos = buf.begin_object("TBranch");
+        let start = buf.begin(13);
+        buf.named(
+            branch.name,
+            &format!("{}/{}", branch.name, branch.leaf.code()),
+        );
+        buf.att_fill();
+        buf.i32(0); // fCompress
+        buf.i32(BASKET_SIZE);
+        buf.i32(0); // fEntryOffsetLen
+        buf.i32(to_i32(baskets)?); // fWriteBasket
+        buf.i64(entries); // fEntryNumber
+        buf.bytes(&IO_FEATURES);
+        buf.i32(0); // fOffset
+        buf.i32(to_i32(baskets + 1)?); // fMaxBaskets
+        buf.i32(0); // fSplitLevel
+        buf.i64(entries);
+        buf.i64(0); // fFirstEntry
+        buf.i64(to_i64(bytes)?); // fTotBytes
+        buf.i64(to_i64(bytes)?); // fZipBytes
+        let sub_branches = buf.begin_array(0)?;
+        buf.end(sub_branches);
+        let leaves = buf.begin_array(1)?;
+        let leaf = stream_leaf(buf, branch.name, branch.leaf)?;
+        buf.end(leaves);
+        let basket_objects = buf.begin_array(0)?;
+        buf.end(basket_objects);
+        buf.u8(1);
+        for basket in &branch.baskets {
+            buf.i32(to_i32(basket.bytes)?);
+        }
+        buf.i32(0);
+        buf.u8(1);
+        for basket in &branch.baskets {
+            buf.i64(to_i64(basket.first_entry)?);
+        }
+        buf.i64(entries);
+        buf.u8(1);
+        for basket in &branch.baskets {
+            buf.i64(to_i64(basket.seek)?);
+        }
+        buf.i64(0);
+        buf.string(""); // fFileName
+        buf.end(start);
+        buf.end(cntpos);
+        Ok(leaf)
+    }
+}
+
+/// Stream a leaf (`TLeaf` v2 in a `TLeafD`/`I`/`S`/`B` v1); returns the
+/// reference to it.
+fn stream_leaf(buf: &mut Buffer, name: &str, leaf: Leaf) -> Result<u32> {
+    let cntpos = buf.begin_object(leaf.class());
+    let start = buf.begin(1);
+    let base = buf.begin(2);
+    buf.named(name, name);
+    buf.i32(1); // fLen
+    buf.i32(to_i32(leaf.size())?); // fLenType
+    buf.i32(0); // fOffset
+    buf.u8(0); // fIsRange
+    buf.u8(u8::from(!matches!(leaf, Leaf::Double))); // fIsUnsigned
+    buf.u32(0); // fLeafCount
+    buf.end(base);
+    // fMinimum and fMaximum, which ROOT only keeps for counter leaves.
+    buf.bytes(&vec![0; 2 * leaf.size()]);
+    buf.end(start);
+    buf.end(cntpos);
+    buf.reference(cntpos)
+}
+
+/// The file being written, with its position and identity.
+struct Output {
+    file: BufWriter<File>,
+    position: u64,
+    file_name: String,
+    datime: u32,
+    uuid: [u8; 16],
+}
+
+impl Output {
+    /// Length of a key header written at the current position.
+    fn key_len(&self, class: &str, name: &str, title: &str) -> usize {
+        let seeks = if self.position > START_BIG_FILE {
+            16
+        } else {
+            8
+        };
+        18 + seeks + string_len(class) + string_len(name) + string_len(title)
+    }
+
+    /// Key header of the top directory, at `BEGIN`.
+    fn directory_key_len(&self) -> usize {
+        18 + 8 + string_len("TFile") + string_len(&self.file_name) + string_len("")
+    }
+
+    /// The directory's name and title, and its record.
+    fn directory_len(&self) -> usize {
+        string_len(&self.file_name) + string_len("") + DIRECTORY_RECORD
+    }
+
+    /// Write a key whose header ends with `extra`, followed by `payload`.
+    fn write_key(
+        &mut self,
+        class: &str,
+        name: &str,
+        title: &str,
+        extra: &[u8],
+        payload: &[u8],
+    ) -> Result<WrittenKey> {
+        let seek = self.position;
+        let keylen = self.key_len(class, name, title) + extra.len();
+        let bytes = to_u64(keylen + payload.len());
+        let mut header = Buffer::new(0);
+        header.key(
+            seek,
+            bytes,
+            to_u64(payload.len()),
+            keylen,
+            BEGIN,
+            self.datime,
+            [class, name, title],
+        )?;
+        self.file.write_all(&header.data)?;
+        self.file.write_all(extra)?;
+        self.file.write_all(payload)?;
+        self.position += bytes;
+        Ok(WrittenKey {
+            seek,
+            bytes,
+            header: header.data,
+        })
+    }
+
+    /// Write the free segments record: everything past the end of the file.
+    fn write_free_segments(&mut self) -> Result<WrittenKey> {
+        let file_name = self.file_name.clone();
+        let keylen = to_u64(self.key_len("TFile", &file_name, ""));
+        let small_end = self.position + keylen + 10;
+        let mut free = Buffer::new(0);
+        if small_end > START_BIG_FILE {
+            let end = self.position + keylen + 18;
+            free.i16(1001);
+            free.i64(to_i64(end)?);
+            free.i64(to_i64(end + 1_000_000_000)?);
+        } else {
+            free.i16(1);
+            free.i32(to_i32(small_end)?);
+            free.i32(to_i32(START_BIG_FILE)?);
+        }
+        self.write_key("TFile", &file_name, "", &[], &free.data)
+    }
+
+    /// Write the file header and the top directory over the space reserved
+    /// at creation.
+    fn write_header(
+        &mut self,
+        info: &WrittenKey,
+        keys: &WrittenKey,
+        free: &WrittenKey,
+    ) -> Result<()> {
+        let end = self.position;
+        let big = end > START_BIG_FILE;
+        let keylen = self.directory_key_len();
+        let nbytes_name = to_i32(keylen + string_len(&self.file_name) + string_len(""))?;
+
+        let mut header = Buffer::new(0);
+        header.bytes(b"root");
+        header.i32(if big {
+            FILE_VERSION + 1_000_000
+        } else {
+            FILE_VERSION
+        });
+        header.i32(to_i32(BEGIN)?);
+        if big {
+            header.i64(to_i64(end)?);
+            header.i64(to_i64(free.seek)?);
+        } else {
+            header.i32(to_i32(end)?);
+            header.i32(to_i32(free.seek)?);
+        }
+        header.i32(to_i32(free.bytes)?);
+        header.i32(1); // nfree
+        header.i32(nbytes_name);
+        header.u8(if big { 8 } else { 4 });
+        header.i32(0); // fCompress
+        if big {
+            header.i64(to_i64(info.seek)?);
+        } else {
+            header.i32(to_i32(info.seek)?);
+        }
+        header.i32(to_i32(info.bytes)?);
+        header.uuid(&self.uuid);
+        header.data.resize(to_usize(BEGIN), 0);
+
+        let objlen = self.directory_len();
+        header.key(
+            BEGIN,
+            to_u64(keylen + objlen),
+            to_u64(objlen),
+            keylen,
+            0,
+            self.datime,
+            ["TFile", &self.file_name, ""],
+        )?;
+        header.string(&self.file_name);
+        header.string("");
+        let big_keys = keys.seek > START_BIG_FILE;
+        header.i16(if big_keys { 1005 } else { 5 });
+        header.u32(self.datime);
+        header.u32(self.datime);
+        header.i32(to_i32(keys.bytes)?);
+        header.i32(nbytes_name);
+        if big_keys {
+            header.i64(to_i64(BEGIN)?);
+            header.i64(0);
+            header.i64(to_i64(keys.seek)?);
+        } else {
+            header.i32(to_i32(BEGIN)?);
+            header.i32(0);
+            header.i32(to_i32(keys.seek)?);
+        }
+        header.uuid(&self.uuid);
+        header.data.resize(to_usize(BEGIN) + keylen + objlen, 0);
+
+        self.file.seek(SeekFrom::Start(0))?;
+        self.file.write_all(&header.data)?;
+        self.file.flush()?;
+        Ok(())
+    }
+}
+
+/// Big-endian object buffer with ROOT's byte counts and class references.
+struct Buffer {
+    data: Vec<u8>,
+    /// Length of the key header in front of the buffer, which references
+    /// count from.
+    displacement: u32,
+    /// Classes written so far and their references.
+    classes: Vec<(&'static str, u32)>,
+}
+
+impl Buffer {
+    fn new(displacement: u32) -> Self {
+        Self {
+            data: Vec::new(),
+            displacement,
+            classes: Vec::new(),
+        }
+    }
+
+    fn bytes(&mut self, bytes: &[u8]) {
+        self.data.extend_from_slice(bytes);
+    }
+
+    fn u8(&mut self, value: u8) {
+        self.data.push(value);
+    }
+
+    fn i16(&mut self, value: i16) {
+        self.bytes(&value.to_be_bytes());
+    }
+
+    fn i32(&mut self, value: i32) {
+        self.bytes(&value.to_be_bytes());
+    }
+
+    fn u32(&mut self, value: u32) {
+        self.bytes(&value.to_be_bytes());
+    }
+
+    fn i64(&mut self, value: i64) {
+        self.bytes(&value.to_be_bytes());
+    }
+
+    fn f32(&mut self, value: f32) {
+        self.bytes(&value.to_be_bytes());
+    }
+
+    fn f64(&mut self, value: f64) {
+        self.bytes(&value.to_be_bytes());
+    }
+
+    /// A `TString`: one length byte, or 255 and a 32-bit length.
+    fn string(&mut self, value: &str) {
+        match u8::try_from(value.len()) {
+            Ok(len) if len < 255 => self.u8(len),
+            _ => {
+                self.u8(255);
+                self.i32(i32::try_from(value.len()).unwrap_or(i32::MAX));
+            }
+        }
+        self.bytes(value.as_bytes());
+    }
+
+    fn uuid(&mut self, uuid: &[u8; 16]) {
+        self.i16(1);
+        self.bytes(uuid);
+    }
+
+    /// Reserve a byte count and write a class version; returns the
+    /// position to pass to [`end`](Self::end).
+    fn begin(&mut self, version: i16) -> usize {
+        let start = self.data.len();
+        self.u32(0);
+        self.i16(version);
+        start
+    }
+
+    /// Fill in the byte count reserved at `start`.
+    fn end(&mut self, start: usize) {
+        let count = u32::try_from(self.data.len() - start - 4).unwrap_or(0) | BYTE_COUNT_MASK;
+        self.data[start..start + 4].copy_from_slice(&count.to_be_bytes());
+    }
+
+    /// Start an object written through a pointer: a byte count, then the
+    /// class name the first time it appears or a reference to it after.
+    fn begin_object(&mut self, class: &'static str) -> usize {
+        let start = self.data.len();
+        self.u32(0);
+        if let Some(&(_, tag)) = self.classes.iter().find(|(name, _)| *name == class) {
+            self.u32(tag | CLASS_MASK);
+        } else {
+            let tag = self.offset(self.data.len());
+            self.u32(NEW_CLASS_TAG);
+            self.bytes(class.as_bytes());
+            self.u8(0);
+            self.classes.push((class, tag));
+        }
+        start
+    }
+
+    /// Reference to an object that began at `start`.
+    fn reference(&self, start: usize) -> Result<u32> {
+        let tag = self.offset(start);
+        if tag & (BYTE_COUNT_MASK | CLASS_MASK) != 0 {
+            return Err(Error::InvalidFormat(
+                "ROOT tree metadata is too large".to_string(),
+            ));
+        }
+        Ok(tag)
+    }
+
+    fn offset(&self, position: usize) -> u32 {
+        u32::try_from(position)
+            .unwrap_or(u32::MAX)
+            .saturating_add(self.displacement + MAP_OFFSET)
+    }
+
+    fn object_header(&mut self) {
+        self.i16(1);
+        self.u32(0);
+        self.u32(OBJECT_BITS);
+    }
+
+    fn named(&mut self, name: &str, title: &str) {
+        let start = self.begin(1);
+        self.object_header();
+        self.string(name);
+        self.string(title);
+        self.end(start);
+    }
+
+    fn att_line(&mut self) {
+        let start = self.begin(2);
+        self.i16(1); // fLineColor
+        self.i16(1); // fLineStyle
+        self.i16(1); // fLineWidth
+        self.end(start);
+    }
+
+    fn att_fill(&mut self) {
+        let start = self.begin(2);
+        self.i16(0); // fFillColor
+        self.i16(1001); // fFillStyle
+        self.end(start);
+    }
+
+    fn att_marker(&mut self) {
+        let start = self.begin(2);
+        self.i16(1); // fMarkerColor
+        self.i16(1); // fMarkerStyle
+        self.f32(1.0); // fMarkerSize
+        self.end(start);
+    }
+
+    /// Start a `TObjArray` (v3) of `len` objects; the caller writes them
+    /// and ends it.
+    fn begin_array(&mut self, len: usize) -> Result<usize> {
+        let start = self.begin(3);
+        self.object_header();
+        self.string("");
+        self.i32(to_i32(len)?);
+        self.i32(0); // fLowerBound
+        Ok(start)
+    }
+
+    /// A `TKey` header, with 64-bit seeks past `START_BIG_FILE`.
+    #[allow(clippy::too_many_arguments)]
+    fn key(
+        &mut self,
+        seek: u64,
+        nbytes: u64,
+        objlen: u64,
+        keylen: usize,
+        parent: u64,
+        datime: u32,
+        [class, name, title]: [&str; 3],
+    ) -> Result<()> {
+        let big = seek > START_BIG_FILE;
+        self.i32(to_i32(nbytes)?);
+        self.i16(if big { 1004 } else { 4 });
+        self.i32(to_i32(objlen)?);
+        self.u32(datime);
+        self.i16(i16::try_from(keylen).map_err(|_| {
+            Error::InvalidFormat(format!("ROOT key header of {keylen} bytes is too long"))
+        })?);
+        self.i16(1); // fCycle
+        if big {
+            self.i64(to_i64(seek)?);
+            self.i64(to_i64(parent)?);
+        } else {
+            self.i32(to_i32(seek)?);
+            self.i32(to_i32(parent)?);
+        }
+        self.string(class);
+        self.string(name);
+        self.string(title);
+        Ok(())
+    }
+}
+
+/// Bytes of `value` as a `TString`.
+fn string_len(value: &str) -> usize {
+    value.len() + if value.len() < 255 { 1 } else { 5 }
+}
+
+/// The current time as a `TDatime`.
+fn datime() -> u32 {
+    let [year, month, day, hour, minute, second] = utc_now_fields();
+    let packed = (year - 1995) << 26 | month << 22 | day << 17 | hour << 12 | minute << 6 | second;
+    u32::try_from(packed).unwrap_or(0)
+}
+
+/// A random-enough file UUID from the clock and process id.
+fn uuid() -> [u8; 16] {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |since| since.as_nanos());
+    let mut rng = SplitMix64::new(
+        u64::try_from(nanos & u128::from(u64::MAX)).unwrap_or(0)
+            ^ u64::from(std::process::id()).rotate_left(32),
+    );
+    let mut uuid = [0; 16];
+    for chunk in uuid.chunks_mut(8) {
+        chunk.copy_from_slice(&rng.next_u64().to_be_bytes());
+    }
+    uuid
+}
+
+fn too_large() -> Error {
+    Error::InvalidFormat("value does not fit the ROOT file layout".to_string())
+}
+
+fn to_i32<T: TryInto<i32>>(value: T) -> Result<i32> {
+    value.try_into().map_err(|_| too_large())
+}
+
+fn to_i64<T: TryInto<i64>>(value: T) -> Result<i64> {
+    value.try_into().map_err(|_| too_large())
+}
+
+fn to_u32<T: TryInto<u32>>(value: T) -> Result<u32> {
+    value.try_into().map_err(|_| too_large())
+}
+
+fn to_u64(value: usize) -> u64 {
+    u64::try_from(value).unwrap_or(u64::MAX)
+}
+
+fn to_usize(value: u64) -> usize {
+    usize::try_from(value).unwrap_or(usize::MAX)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn be_i32(data: &[u8], at: usize) -> i32 {
+        i32::from_be_bytes(data[at..at + 4].try_into().unwrap())
+    }
+
+    /// Class, name and title of the key at `seek`, and where its data starts.
+    fn key_at(data: &[u8], seek: usize) -> (String, String, String, usize) {
+        let keylen = usize::from(u16::from_be_bytes([data[seek + 14], data[seek + 15]]));
+        let mut at = seek + 26;
+        let mut strings = Vec::new();
+        for _ in 0..3 {
+            let len = usize::from(data[at]);
+            strings.push(String::from_utf8(data[at + 1..at + 1 + len].to_vec()).unwrap());
+            at += 1 + len;
+        }
+        let title = strings.pop().unwrap();
+        let name = strings.pop().unwrap();
+        let class = strings.pop().unwrap();
+        (class, name, title, seek + keylen)
+    }
+
+    #[test]
+    fn test_root_neutron_file_layout() {
+        let file = tempfile::NamedTempFile::new().unwrap();
+        let mut batch = NeutronBatch::default();
+        for i in 0..BASKET_ENTRIES + 3 {
+            batch.x.push(f64::from(u32::try_from(i).unwrap()) + 0.5);
+            batch.y.push(2.0);
+            batch.tof.push(100);
+            batch.tot.push(7);
+            batch.n_hits.push(3);
+            batch.chip_id.push(1);
+        }
+        let mut writer = RootNeutronWriter::create(file.path()).unwrap();
+        writer.write_neutrons(&batch).unwrap();
+        writer.finish().unwrap();
+        assert!(writer.write_neutrons(&batch).is_err());
+
+        let data = std::fs::read(file.path()).unwrap();
+        assert_eq!(&data[..4], b"root");
+        assert_eq!(be_i32(&data, 4), FILE_VERSION);
+        assert_eq!(be_i32(&data, 8), 100);
+        assert_eq!(usize::try_from(be_i32(&data, 12)).unwrap(), data.len());
+
+        // Top directory: the keys list holds the tree's key.
+        let (class, _, _, dir) = key_at(&data, 100);
+        assert_eq!(class, "TFile");
+        let name_len = usize::from(data[dir]);
+        let record = dir + 1 + name_len + 1;
+        assert_eq!(data[record + 1], 5);
+        let seek_keys = usize::try_from(be_i32(&data, record + 26)).unwrap();
+        let (_, _, _, keys) = key_at(&data, seek_keys);
+        assert_eq!(be_i32(&data, keys), 1);
+        let (class, name, title, _) = key_at(&data, keys + 4);
+        assert_eq!(
+            (class.as_str(), name.as_str(), title.as_str()),
+            ("TTree", "neutrons", "rustpix neutrons")
+        );
+        let tree_seek = usize::try_from(be_i32(&data, keys + 4 + 18)).unwrap();
+        assert_eq!(&data[tree_seek..tree_seek + 18], &data[keys + 4..keys + 22]);
+
+        // The first basket is the first branch's; entries are big-endian.
+        let first_basket = dir + name_len + 2 + DIRECTORY_RECORD;
+        let (class, name, title, values) = key_at(&data, first_basket);
+        assert_eq!(
+            (class.as_str(), name.as_str(), title.as_str()),
+            ("TBasket", "x", "neutrons")
+        );
+        assert_eq!(
+            be_i32(&data, values - 9),
+            i32::try_from(BASKET_ENTRIES).unwrap()
+        );
+        assert_eq!(&data[values..values + 8], &0.5f64.to_be_bytes());
+        assert_eq!(&data[values + 8..values + 16], &1.5f64.to_be_bytes());
+        let nbytes = usize::try_from(be_i32(&data, first_basket)).unwrap();
+        assert_eq!(nbytes - (values - first_basket), 8 * BASKET_ENTRIES);
+        let (class, name, _, _) = key_at(&data, first_basket + nbytes);
+        assert_eq!((class.as_str(), name.as_str()), ("TBasket", "y"));
+    }
+
+    #[test]
+    fn test_root_hit_cluster_sizes() {
+        let file = tempfile::NamedTempFile::new().unwrap();
+        let mut batch = HitBatch::default();
+        for (i, id) in [0, 1, 0, -1].into_iter().enumerate() {
+            batch.push((u16::try_from(i).unwrap(), 0, 10, 5, 0, 0));
+            batch.cluster_id[i] = id;
+        }
+        let mut writer = RootHitWriter::create(file.path()).unwrap();
+        writer.write_hits(&batch).unwrap();
+        writer.finish().unwrap();
+
+        let data = std::fs::read(file.path()).unwrap();
+        let mut seek = 100;
+        loop {
+            let (class, name, _, values) = key_at(&data, seek);
+            if class == "TBasket" && name == "cluster_size" {
+                assert_eq!(&data[values..values + 8], &[0, 2, 0, 1, 0, 2, 0, 1]);
+                break;
+            }
+            seek += usize::try_from(be_i32(&data, seek)).unwrap();
+        }
+    }
+}
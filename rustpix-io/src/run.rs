@@ -0,0 +1,301 @@
+//! Runs split across several TPX3 files.
+//!
+//! Acquisitions are often written as one file per chip or quad
+//! (`run_000.tpx3`, `run_001.tpx3`, ...). [`Tpx3RunReader`] decodes each file
+//! with its own detector configuration, so every file gets its own chip
+//! transforms, and merges the per-file pulse streams into one stream ordered
+//! by TDC timestamp. Pulses whose TDC timestamps agree within a tolerance are
+//! treated as the same pulse and their hits are merged by TOF.
+
+use crate::reader::{EventBatch, TimeOrderedEventStream, Tpx3FileReader};
+use crate::{Error, Result};
+use rustpix_core::pulse::PulseIndex;
+use rustpix_core::soa::HitBatch;
+use rustpix_tpx::DetectorConfig;
+use std::iter::Peekable;
+use std::path::Path;
+
+/// Default TDC timestamp difference (25ns ticks, 1 µs) within which pulses of
+/// different files are merged.
+pub const DEFAULT_PULSE_TOLERANCE_25NS: u64 = 40;
+
+/// Reader over the files of one run, merged into a single time-ordered stream.
+pub struct Tpx3RunReader {
+    /// Per-file readers, each carrying its own detector configuration.
+    files: Vec<Tpx3FileReader>,
+    /// TDC timestamp difference within which pulses are merged.
+    pulse_tolerance_25ns: u64,
+}
+
+impl Tpx3RunReader {
+    /// Opens the files of a run with default configuration.
+    ///
+    /// # Errors
+    /// Returns an error if `paths` is empty or a file cannot be opened.
+    pub fn open<P: AsRef<Path>>(paths: &[P]) -> Result<Self> {
+        let files = paths
+            .iter()
+            .map(Tpx3FileReader::open)
+            .collect::<Result<Vec<_>>>()?;
+        Self::from_readers(files)
+    }
+
+    /// Combines already opened files into a run.
+    ///
+    /// Each reader keeps its own detector configuration, so per-file chip
+    /// transforms are set with [`Tpx3FileReader::with_config`] beforehand.
+    ///
+    /// # Errors
+    /// Returns an error if `files` is empty.
+    pub fn from_readers(files: Vec<Tpx3FileReader>) -> Result<Self> {
+        if files.is_empty() {
+            return Err(Error::InvalidFormat(
+                "a run needs at least one TPX3 file".to_string(),
+            ));
+        }
+        Ok(Self {
+            files,
+            pulse_tolerance_25ns: DEFAULT_PULSE_TOLERANCE_25NS,
+        })
+    }
+
+    /// Sets the same detector configuration for every file.
+    #[must_use]
+    pub fn with_config(mut self, config: &DetectorConfig) -> Self {
+        self.files = self
+            .files
+            .into_iter()
+            .map(|file| file.with_config(config.clone()))
+            .collect();
+        self
+    }
+
+    /// Sets the detector configuration of the file at `index`.
+    ///
+    /// Out-of-range indices are ignored.
+    #[must_use]
+    pub fn with_file_config(mut self, index: usize, config: DetectorConfig) -> Self {
+        if index < self.files.len() {
+            let file = self.files.remove(index);
+            self.files.insert(index, file.with_config(config));
+        }
+        self
+    }
+
+    /// Sets the TDC timestamp difference (25ns ticks) within which pulses
+    /// of different files are merged.
+    #[must_use]
+    pub fn with_pulse_tolerance_25ns(mut self, ticks: u64) -> Self {
+        self.pulse_tolerance_25ns = ticks;
+        self
+    }
+
+    /// Returns the per-file readers in run order.
+    #[must_use]
+    pub fn files(&self) -> &[Tpx3FileReader] {
+        &self.files
+    }
+
+    /// Returns the total size of all files in bytes.
+    #[must_use]
+    pub fn file_size(&self) -> usize {
+        self.files.iter().map(Tpx3FileReader::file_size).sum()
+    }
+
+    /// Reads all hits of the run in global time order.
+    ///
+    /// # Errors
+    /// Returns an error if a file size is invalid.
+    pub fn read_batch(&self) -> Result<HitBatch> {
+        let mut batch = HitBatch::default();
+        for event in self.stream_time_ordered_events()? {
+            batch.append(&event.hits);
+        }
+        Ok(batch)
+    }
+
+    /// Returns a stream of pulses merged across all files, ordered by TDC
+    /// timestamp.
+    ///
+    /// # Errors
+    /// Returns an error if a file size is invalid.
+    pub fn stream_time_ordered_events(&self) -> Result<RunEventStream> {
+        let streams = self
+            .files
+            .iter()
+            .map(|file| Ok(file.stream_time_ordered_events()?.peekable()))
+            .collect::<Result<Vec<_>>>()?;
+        Ok(RunEventStream {
+            streams,
+            pulse_tolerance_25ns: self.pulse_tolerance_25ns,
+        })
+    }
+
+    /// Pulses of the run, numbered in time order from the TDC packets of
+    /// its files. Pulses within the pulse tolerance of an earlier one are
+    /// merged into it, as the stream merges them.
+    #[must_use]
+    pub fn pulse_index(&self) -> PulseIndex {
+        let mut start_times: Vec<u64> = self
+            .files
+            .iter()
+            .flat_map(|file| file.pulse_index().start_times_25ns().to_vec())
+            .collect();
+        start_times.sort_unstable();
+        let mut merged: Vec<u64> = Vec::with_capacity(start_times.len());
+        for time in start_times {
+            let within = merged
+                .last()
+                .is_some_and(|&first| time <= first.saturating_add(self.pulse_tolerance_25ns));
+            if !within {
+                merged.push(time);
+            }
+        }
+        PulseIndex::new(merged)
+    }
+}
+
+/// Pulse stream merged across the files of a run.
+///
+/// Yields one [`EventBatch`] per pulse, stamped with the earliest TDC
+/// timestamp of the files that saw it, with hits sorted by TOF.
+pub struct RunEventStream {
+    /// Per-file pulse streams.
+    streams: Vec<Peekable<TimeOrderedEventStream>>,
+    /// TDC timestamp difference within which pulses are merged.
+    pulse_tolerance_25ns: u64,
+}
+
+impl Iterator for RunEventStream {
+    type Item = EventBatch;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let tdc_timestamp_25ns = self
+            .streams
+            .iter_mut()
+            .filter_map(|stream| stream.peek().map(|event| event.tdc_timestamp_25ns))
+            .min()?;
+        let limit = tdc_timestamp_25ns.saturating_add(self.pulse_tolerance_25ns);
+
+        let mut hits = HitBatch::default();
+        let mut merged = 0;
+        for stream in &mut self.streams {
+            if let Some(event) = stream.next_if(|event| event.tdc_timestamp_25ns <= limit) {
+                if merged == 0 {
+                    hits = event.hits;
+                } else {
+                    hits.append(&event.hits);
+                }
+                merged += 1;
+            }
+        }
+        if merged > 1 {
+            hits.sort_by_tof();
+        }
+        Some(EventBatch {
+            tdc_timestamp_25ns,
+            hits,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rustpix_tpx::{ChipTransform, Tpx3Packet};
+
+    fn tpx3_bytes(packets: &[u64]) -> Vec<u8> {
+        packets
+            .iter()
+            .flat_map(|packet| packet.to_le_bytes())
+            .collect()
+    }
+
+    fn tdc(timestamp: u64) -> u64 {
+        0x6F00_0000_0000_0000 | (timestamp << 12)
+    }
+
+    fn hit(toa: u64, addr: u64) -> u64 {
+        0xB000_0000_0000_0000 | (toa << 30) | (20 << 20) | (addr << 44)
+    }
+
+    #[test]
+    fn test_run_merges_pulses_in_time_order() {
+        let header = Tpx3Packet::TPX3_HEADER_MAGIC;
+        // Both files see the pulses at 1000 and 10_000; the second file
+        // reports the first pulse two ticks late and has an extra pulse.
+        let first = tpx3_bytes(&[header, tdc(1000), hit(1100, 1), header, tdc(10_000)]);
+        let second = tpx3_bytes(&[
+            header,
+            tdc(1002),
+            hit(1060, 2),
+            header,
+            tdc(5000),
+            hit(6000, 3),
+            header,
+            tdc(10_000),
+        ]);
+        let run = Tpx3RunReader::from_readers(vec![
+            Tpx3FileReader::from_bytes(first, "run_000.tpx3"),
+            Tpx3FileReader::from_bytes(second, "run_001.tpx3"),
+        ])
+        .unwrap();
+
+        let pulses: Vec<EventBatch> = run.stream_time_ordered_events().unwrap().collect();
+        let stamps: Vec<u64> = pulses.iter().map(|p| p.tdc_timestamp_25ns).collect();
+        assert_eq!(stamps, vec![1000, 5000]);
+        assert_eq!(pulses[0].hits.len(), 2);
+        // The index also numbers the pulse without hits.
+        assert_eq!(run.pulse_index().start_times_25ns(), &[1000, 5000, 10_000]);
+        assert!(pulses[0].hits.tof.windows(2).all(|w| w[0] <= w[1]));
+
+        let merged = run.read_batch().unwrap();
+        let separate: usize = run
+            .files()
+            .iter()
+            .map(|file| file.read_batch().unwrap().len())
+            .sum();
+        assert_eq!(merged.len(), separate);
+
+        let strict = Tpx3RunReader::from_readers(vec![
+            Tpx3FileReader::from_bytes(tpx3_bytes(&[header, tdc(1000), hit(1100, 1)]), "a.tpx3"),
+            Tpx3FileReader::from_bytes(tpx3_bytes(&[header, tdc(1002), hit(1100, 2)]), "b.tpx3"),
+        ])
+        .unwrap()
+        .with_pulse_tolerance_25ns(0);
+        assert_eq!(strict.stream_time_ordered_events().unwrap().count(), 2);
+    }
+
+    #[test]
+    fn test_run_applies_per_file_transforms() {
+        let header = Tpx3Packet::TPX3_HEADER_MAGIC;
+        let file = || tpx3_bytes(&[header, tdc(1000), hit(1100, 0)]);
+        let shifted = DetectorConfig {
+            chip_transforms: vec![ChipTransform {
+                tx: 256,
+                ..ChipTransform::identity()
+            }],
+            ..DetectorConfig::default()
+        };
+        let run = Tpx3RunReader::from_readers(vec![
+            Tpx3FileReader::from_bytes(file(), "run_000.tpx3"),
+            Tpx3FileReader::from_bytes(file(), "run_001.tpx3"),
+        ])
+        .unwrap()
+        .with_config(&DetectorConfig {
+            chip_transforms: vec![ChipTransform::identity()],
+            ..DetectorConfig::default()
+        })
+        .with_file_config(1, shifted);
+
+        let mut xs = run.read_batch().unwrap().x;
+        xs.sort_unstable();
+        assert_eq!(xs.len(), 2);
+        assert_eq!(xs[1] - xs[0], 256);
+    }
+
+    #[test]
+    fn test_empty_run_is_rejected() {
+        assert!(Tpx3RunReader::from_readers(Vec::new()).is_err());
+    }
+}
@@ -112,7 +112,7 @@ mod tests {
             return;
         }
 
-        let reader = MappedFileReader::open(path.to_str().unwrap()).unwrap();
+        let reader = MappedFileReader::open(&path).unwrap();
         let (sections, consumed) = PacketScanner::scan_sections(reader.as_bytes(), true);
 
         let sections_len = sections.len();
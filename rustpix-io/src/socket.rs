@@ -0,0 +1,264 @@
+//! Reading TPX3 data from a Serval or SPIDR TCP stream.
+//!
+//! Serval (and the SPIDR readout behind it) can send the raw TPX3 packet
+//! stream to a TCP destination instead of a file: the same chunk headers,
+//! TDC and hit packets, in the same order. Network reads end anywhere, so
+//! [`Tpx3SocketReader`] keeps a trailing partial packet for the next read
+//! and decodes the received bytes with the section-aware processor used for
+//! files. As with [`IncrementalTpx3Reader`], the newest pulses are held back
+//! until more data arrives; once a pulse is returned, the sections before it
+//! are discarded, with each chip's pulse state carried over, so memory stays
+//! bounded however long the stream runs.
+//!
+//! [`IncrementalTpx3Reader`]: crate::IncrementalTpx3Reader
+
+use crate::incremental::{resume_section, HELD_BACK_PULSES};
+use crate::reader::EventBatch;
+use crate::Result;
+use rustpix_tpx::ordering::{continue_pulse_contexts, read_pulses_with_contexts, PulseContext};
+use rustpix_tpx::section::discover_sections;
+use rustpix_tpx::{DetectorConfig, Tpx3Packet};
+use std::io::{ErrorKind, Read};
+use std::net::{TcpStream, ToSocketAddrs};
+
+/// Bytes requested from the stream per read.
+const READ_CHUNK: usize = 1 << 20;
+
+/// Size of a TPX3 packet; a section starts right after its header packet.
+const PACKET_SIZE: usize = 8;
+
+/// Reader that returns each pulse of a live TPX3 stream once.
+pub struct Tpx3SocketReader<S = TcpStream> {
+    /// Source of the packet stream.
+    stream: S,
+    /// Detector configuration used for parsing.
+    config: DetectorConfig,
+    /// Received bytes not yet discarded, starting at a chunk header.
+    buffer: Vec<u8>,
+    /// Pulse state of each chip at the start of `buffer`.
+    chips: [PulseContext; 256],
+    /// Extended TDC timestamp of the last pulse returned.
+    emitted_through: Option<u64>,
+    /// Bytes received since the reader was created.
+    bytes_received: u64,
+    /// Whether the stream has ended.
+    finished: bool,
+}
+
+impl Tpx3SocketReader<TcpStream> {
+    /// Connects to the TCP stream of a Serval or SPIDR server at `addr`.
+    ///
+    /// # Errors
+    /// Returns an error if the connection cannot be made.
+    pub fn connect(addr: impl ToSocketAddrs) -> Result<Self> {
+        let stream = TcpStream::connect(addr)?;
+        stream.set_nodelay(true)?;
+        Ok(Self::new(stream))
+    }
+}
+
+impl<S: Read> Tpx3SocketReader<S> {
+    /// Creates a reader for an already open stream with default
+    /// configuration.
+    #[must_use]
+    pub fn new(stream: S) -> Self {
+        Self {
+            stream,
+            config: DetectorConfig::default(),
+            buffer: Vec::new(),
+            chips: [PulseContext::default(); 256],
+            emitted_through: None,
+            bytes_received: 0,
+            finished: false,
+        }
+    }
+
+    /// Sets the detector configuration.
+    #[must_use]
+    pub fn with_config(mut self, config: DetectorConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Returns the underlying stream, e.g. to set a read timeout.
+    #[must_use]
+    pub fn get_ref(&self) -> &S {
+        &self.stream
+    }
+
+    /// Returns the number of bytes received so far.
+    #[must_use]
+    pub fn bytes_received(&self) -> u64 {
+        self.bytes_received
+    }
+
+    /// Returns the number of received bytes still buffered.
+    #[must_use]
+    pub fn buffered_bytes(&self) -> usize {
+        self.buffer.len()
+    }
+
+    /// Returns whether the stream has ended and every pulse was returned.
+    #[must_use]
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    /// Reads once from the stream and returns the pulses completed since the
+    /// last poll, in TDC order.
+    ///
+    /// Blocks until data arrives, unless the stream has a read timeout, in
+    /// which case a timed-out read returns no pulses. When the stream ends,
+    /// the held-back pulses are returned and later polls return nothing.
+    /// Hits before a chip's first TDC are dropped.
+    ///
+    /// # Errors
+    /// Returns an error if reading from the stream fails.
+    pub fn poll(&mut self) -> Result<Vec<EventBatch>> {
+        if self.finished {
+            return Ok(Vec::new());
+        }
+        let start = self.buffer.len();
+        self.buffer.resize(start + READ_CHUNK, 0);
+        let read = loop {
+            match self.stream.read(&mut self.buffer[start..]) {
+                Ok(read) => break read,
+                Err(err) if err.kind() == ErrorKind::Interrupted => {}
+                Err(err) => {
+                    self.buffer.truncate(start);
+                    return match err.kind() {
+                        ErrorKind::WouldBlock | ErrorKind::TimedOut => Ok(Vec::new()),
+                        _ => Err(err.into()),
+                    };
+                }
+            }
+        };
+        self.buffer.truncate(start + read);
+        self.bytes_received += read as u64;
+        self.finished = read == 0;
+        Ok(self.drain_pulses())
+    }
+
+    /// Decodes the buffered packets, returns the pulses that are complete
+    /// and discards the sections no later pulse can use.
+    fn drain_pulses(&mut self) -> Vec<EventBatch> {
+        let whole = self.buffer.len() / PACKET_SIZE * PACKET_SIZE;
+        let data = &self.buffer[..whole];
+        let sections = discover_sections(data);
+        let mut chips = self.chips;
+        let contexts = continue_pulse_contexts(data, &sections, &mut chips);
+        let pulses = read_pulses_with_contexts(data, &sections, &contexts, &self.config);
+
+        let mut pulses: Vec<EventBatch> = pulses
+            .into_iter()
+            .filter(|pulse| {
+                self.emitted_through
+                    .is_none_or(|emitted| pulse.tdc_timestamp > emitted)
+            })
+            .map(|pulse| EventBatch {
+                tdc_timestamp_25ns: pulse.tdc_timestamp,
+                hits: pulse.hits,
+            })
+            .collect();
+        if !self.finished {
+            pulses.truncate(pulses.len().saturating_sub(HELD_BACK_PULSES));
+        }
+        if let Some(last) = pulses.last() {
+            self.emitted_through = Some(last.tdc_timestamp_25ns);
+        }
+
+        if sections.is_empty() {
+            // Packets before the first chunk header belong to no chip; a
+            // header with nothing after it yet is kept.
+            let header = data
+                .chunks_exact(PACKET_SIZE)
+                .position(|chunk| {
+                    let mut bytes = [0u8; PACKET_SIZE];
+                    bytes.copy_from_slice(chunk);
+                    Tpx3Packet::new(u64::from_le_bytes(bytes)).is_header()
+                })
+                .map_or(whole, |index| index * PACKET_SIZE);
+            self.buffer.drain(..header);
+            return pulses;
+        }
+        let keep = self
+            .emitted_through
+            .map_or(0, |emitted| resume_section(&sections, &contexts, emitted));
+        let mut seen = [false; 256];
+        for (section, context) in sections[keep..].iter().zip(&contexts[keep..]) {
+            let chip = usize::from(section.chip_id);
+            if !seen[chip] {
+                seen[chip] = true;
+                chips[chip] = *context;
+            }
+        }
+        self.chips = chips;
+        self.buffer
+            .drain(..sections[keep].start_offset - PACKET_SIZE);
+        pulses
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Tpx3FileReader;
+    use std::io::Write;
+    use std::net::TcpListener;
+
+    #[test]
+    fn test_socket_stream_matches_whole_file() {
+        let header = |chip: u64| Tpx3Packet::TPX3_HEADER_MAGIC | (chip << 32);
+        let tdc = |timestamp: u64| 0x6F00_0000_0000_0000 | (timestamp << 12);
+        let hit =
+            |toa: u64, addr: u64| 0xB000_0000_0000_0000 | (toa << 30) | (20 << 20) | (addr << 44);
+        let mut packets = Vec::new();
+        for pulse in 0..40u64 {
+            // Past the 30-bit TDC rollover halfway through.
+            let start = (0x3FFF_0000 + pulse * 2000) & 0x3FFF_FFFF;
+            for chip in 0..2 {
+                packets.extend([header(chip), tdc(start)]);
+                packets.extend([hit(start + 100, 1), hit(start + 150, 2)]);
+            }
+        }
+        let data: Vec<u8> = packets.iter().flat_map(|p| p.to_le_bytes()).collect();
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let sent = data.clone();
+        let server = std::thread::spawn(move || {
+            let (mut socket, _) = listener.accept().unwrap();
+            // Uneven frames that split packets.
+            for frame in sent.chunks(45) {
+                socket.write_all(frame).unwrap();
+            }
+        });
+
+        let mut reader = Tpx3SocketReader::connect(addr).unwrap();
+        let mut hits = 0;
+        let mut stamps = Vec::new();
+        let mut max_buffered = 0;
+        while !reader.is_finished() {
+            for pulse in reader.poll().unwrap() {
+                hits += pulse.hits.len();
+                stamps.push(pulse.tdc_timestamp_25ns);
+            }
+            max_buffered = max_buffered.max(reader.buffered_bytes());
+        }
+        server.join().unwrap();
+
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        file.write_all(&data).unwrap();
+        file.flush().unwrap();
+        let whole = Tpx3FileReader::open(file.path()).unwrap();
+        assert_eq!(hits, whole.read_batch().unwrap().len());
+        assert_eq!(stamps.len(), 40);
+        assert!(stamps.windows(2).all(|w| w[0] < w[1]));
+        assert_eq!(reader.bytes_received(), data.len() as u64);
+        assert!(
+            max_buffered < data.len() / 2,
+            "decoded sections are dropped"
+        );
+        assert!(reader.poll().unwrap().is_empty());
+    }
+}
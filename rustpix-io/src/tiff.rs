@@ -0,0 +1,762 @@
+//! Multi-page TIFF stacks of TOF-binned counts.
+//!
+//! [`TiffStackWriter`] writes one uncompressed grayscale page per TOF bin
+//! (16- or 32-bit unsigned) with an `ImageJ` description on the first page, so
+//! ImageJ/Fiji opens the file as a stack. Stacks larger than 4 GB are written
+//! as `BigTIFF`. [`TofHistogram`] bins neutrons into the `[tof][y][x]` counts
+//! such a stack is written from.
+
+use crate::{Error, Result};
+use rustpix_algorithms::{find_tof_hot_pixels, TofHotPixels};
+use rustpix_core::neutron::NeutronBatch;
+use rustpix_core::warnings::{WarningKind, Warnings};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+/// Pixel type of the pages of a TIFF stack.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TiffBitDepth {
+    /// 16-bit unsigned; counts above 65535 are clamped.
+    #[default]
+    Bit16,
+    /// 32-bit unsigned; counts above 4294967295 are clamped.
+    Bit32,
+}
+
+impl TiffBitDepth {
+    fn bytes(self) -> u64 {
+        match self {
+            Self::Bit16 => 2,
+            Self::Bit32 => 4,
+        }
+    }
+}
+
+/// Result of writing a TIFF stack.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TiffStackSummary {
+    /// Number of pages written.
+    pub pages: usize,
+    /// Size of the file in bytes.
+    pub bytes: u64,
+    /// Whether any count was clamped to the largest pixel value.
+    pub clamped: bool,
+}
+
+// TIFF tags, in the ascending order IFD entries must use.
+const TAG_IMAGE_WIDTH: u16 = 256;
+const TAG_IMAGE_LENGTH: u16 = 257;
+const TAG_BITS_PER_SAMPLE: u16 = 258;
+const TAG_COMPRESSION: u16 = 259;
+const TAG_PHOTOMETRIC: u16 = 262;
+const TAG_IMAGE_DESCRIPTION: u16 = 270;
+const TAG_STRIP_OFFSETS: u16 = 273;
+const TAG_SAMPLES_PER_PIXEL: u16 = 277;
+const TAG_ROWS_PER_STRIP: u16 = 278;
+const TAG_STRIP_BYTE_COUNTS: u16 = 279;
+const TAG_PLANAR_CONFIGURATION: u16 = 284;
+const TAG_SAMPLE_FORMAT: u16 = 339;
+
+// Field types.
+const TYPE_ASCII: u16 = 2;
+const TYPE_SHORT: u16 = 3;
+const TYPE_LONG: u16 = 4;
+const TYPE_LONG8: u16 = 16;
+
+/// Streaming writer of a TIFF stack with a fixed number of pages.
+///
+/// Pages are written in order with [`Self::write_page`]; each page's data is
+/// followed by its directory, so nothing is buffered beyond one page.
+pub struct TiffStackWriter {
+    writer: BufWriter<File>,
+    width: u32,
+    height: u32,
+    pages: usize,
+    bit_depth: TiffBitDepth,
+    big_tiff: bool,
+    /// `ImageJ` description, written before the first page.
+    description: Vec<u8>,
+    written_pages: usize,
+    /// Bytes written so far.
+    position: u64,
+    clamped: bool,
+}
+
+impl TiffStackWriter {
+    /// Creates a stack of `pages` images of `width` × `height` pixels.
+    ///
+    /// # Errors
+    /// Returns an error if the stack is empty or the file cannot be created.
+    pub fn create<P: AsRef<Path>>(
+        path: P,
+        width: u32,
+        height: u32,
+        pages: usize,
+        bit_depth: TiffBitDepth,
+    ) -> Result<Self> {
+        Self::create_with_properties(path, width, height, pages, bit_depth, "")
+    }
+
+    /// Like [`Self::create`], with `properties` (`key=value` lines) appended
+    /// to the `ImageJ` description.
+    pub(crate) fn create_with_properties<P: AsRef<Path>>(
+        path: P,
+        width: u32,
+        height: u32,
+        pages: usize,
+        bit_depth: TiffBitDepth,
+        properties: &str,
+    ) -> Result<Self> {
+        if width == 0 || height == 0 || pages == 0 {
+            return Err(Error::InvalidFormat(format!(
+                "TIFF stack needs at least one {width}x{height} page, got {pages}"
+            )));
+        }
+        let mut description = format!(
+            "ImageJ=1.53\nimages={pages}\nslices={pages}\nhyperstack=true\nmode=grayscale\n{properties}"
+        )
+        .into_bytes();
+        description.push(0);
+        if !description.len().is_multiple_of(2) {
+            description.push(0);
+        }
+        let page_bytes = u64::from(width) * u64::from(height) * bit_depth.bytes();
+        let stack_bytes = page_bytes.saturating_mul(u64::try_from(pages).unwrap_or(u64::MAX));
+        let mut stack = Self {
+            writer: BufWriter::new(File::create(path)?),
+            width,
+            height,
+            pages,
+            bit_depth,
+            // Leave room for the directories and description.
+            big_tiff: stack_bytes > u64::from(u32::MAX) - (1 << 20),
+            description,
+            written_pages: 0,
+            position: 0,
+            clamped: false,
+        };
+        stack.write_header()?;
+        Ok(stack)
+    }
+
+    /// Writes the next page from `width * height` counts in row-major order.
+    ///
+    /// # Errors
+    /// Returns an error if the page has the wrong size, all pages were
+    /// already written, or writing fails.
+    pub fn write_page(&mut self, counts: &[u64]) -> Result<()> {
+        let pixels =
+            usize::try_from(u64::from(self.width) * u64::from(self.height)).unwrap_or(usize::MAX);
+        if counts.len() != pixels {
+            return Err(Error::InvalidFormat(format!(
+                "TIFF page has {} pixels, expected {pixels}",
+                counts.len()
+            )));
+        }
+        if self.written_pages == self.pages {
+            return Err(Error::InvalidFormat(format!(
+                "TIFF stack already has all {} pages",
+                self.pages
+            )));
+        }
+
+        let data_offset = self.position;
+        let max = match self.bit_depth {
+            TiffBitDepth::Bit16 => u64::from(u16::MAX),
+            TiffBitDepth::Bit32 => u64::from(u32::MAX),
+        };
+        for &count in counts {
+            self.clamped |= count > max;
+            let value = count.min(max);
+            match self.bit_depth {
+                TiffBitDepth::Bit16 => {
+                    self.put(&u16::try_from(value).unwrap_or(u16::MAX).to_le_bytes())?;
+                }
+                TiffBitDepth::Bit32 => {
+                    self.put(&u32::try_from(value).unwrap_or(u32::MAX).to_le_bytes())?;
+                }
+            }
+        }
+        let first = self.written_pages == 0;
+        self.written_pages += 1;
+        self.write_directory(data_offset, first)
+    }
+
+    /// Flushes the file once all pages are written.
+    ///
+    /// # Errors
+    /// Returns an error if pages are missing or flushing fails.
+    pub fn finish(mut self) -> Result<TiffStackSummary> {
+        if self.written_pages != self.pages {
+            return Err(Error::InvalidFormat(format!(
+                "TIFF stack has {} of {} pages",
+                self.written_pages, self.pages
+            )));
+        }
+        self.writer.flush()?;
+        Ok(TiffStackSummary {
+            pages: self.pages,
+            bytes: self.position,
+            clamped: self.clamped,
+        })
+    }
+
+    fn put(&mut self, bytes: &[u8]) -> Result<()> {
+        self.writer.write_all(bytes)?;
+        self.position += bytes.len() as u64;
+        Ok(())
+    }
+
+    /// Writes the file header, the description, and points at the first
+    /// directory, which follows the first page's data.
+    fn write_header(&mut self) -> Result<()> {
+        let header_bytes: u64 = if self.big_tiff { 16 } else { 8 };
+        let description_bytes = self.description.len() as u64;
+        let first_directory = header_bytes + description_bytes + self.page_bytes();
+        self.put(b"II")?;
+        if self.big_tiff {
+            self.put(&43u16.to_le_bytes())?;
+            self.put(&8u16.to_le_bytes())?;
+            self.put(&0u16.to_le_bytes())?;
+            self.put(&first_directory.to_le_bytes())?;
+        } else {
+            self.put(&42u16.to_le_bytes())?;
+            self.put(&offset_u32(first_directory)?.to_le_bytes())?;
+        }
+        let description = std::mem::take(&mut self.description);
+        self.put(&description)?;
+        self.description = description;
+        Ok(())
+    }
+
+    /// Writes the directory of the page whose data starts at `data_offset`.
+    fn write_directory(&mut self, data_offset: u64, first: bool) -> Result<()> {
+        let bits = self.bit_depth.bytes() * 8;
+        let header_bytes: u64 = if self.big_tiff { 16 } else { 8 };
+        let offset_type = if self.big_tiff { TYPE_LONG8 } else { TYPE_LONG };
+        let mut entries = vec![
+            (TAG_IMAGE_WIDTH, TYPE_LONG, 1, u64::from(self.width)),
+            (TAG_IMAGE_LENGTH, TYPE_LONG, 1, u64::from(self.height)),
+            (TAG_BITS_PER_SAMPLE, TYPE_SHORT, 1, bits),
+            (TAG_COMPRESSION, TYPE_SHORT, 1, 1),
+            (TAG_PHOTOMETRIC, TYPE_SHORT, 1, 1),
+        ];
+        if first {
+            entries.push((
+                TAG_IMAGE_DESCRIPTION,
+                TYPE_ASCII,
+                self.description.len() as u64,
+                header_bytes,
+            ));
+        }
+        entries.extend([
+            (TAG_STRIP_OFFSETS, offset_type, 1, data_offset),
+            (TAG_SAMPLES_PER_PIXEL, TYPE_SHORT, 1, 1),
+            (TAG_ROWS_PER_STRIP, TYPE_LONG, 1, u64::from(self.height)),
+            (TAG_STRIP_BYTE_COUNTS, offset_type, 1, self.page_bytes()),
+            (TAG_PLANAR_CONFIGURATION, TYPE_SHORT, 1, 1),
+            (TAG_SAMPLE_FORMAT, TYPE_SHORT, 1, 1),
+        ]);
+
+        let count = entries.len() as u64;
+        let directory_bytes = if self.big_tiff {
+            8 + 20 * count + 8
+        } else {
+            2 + 12 * count + 4
+        };
+        // The next page's data follows this directory, then its directory.
+        let next = if self.written_pages == self.pages {
+            0
+        } else {
+            self.position + directory_bytes + self.page_bytes()
+        };
+
+        if self.big_tiff {
+            self.put(&count.to_le_bytes())?;
+        } else {
+            self.put(&u16::try_from(count).unwrap_or(u16::MAX).to_le_bytes())?;
+        }
+        for (tag, field_type, values, value) in entries {
+            self.put(&tag.to_le_bytes())?;
+            self.put(&field_type.to_le_bytes())?;
+            if self.big_tiff {
+                self.put(&values.to_le_bytes())?;
+                self.put(&value_field(field_type, value))?;
+            } else {
+                self.put(&offset_u32(values)?.to_le_bytes())?;
+                self.put(&value_field(field_type, offset_u32(value)?.into())[..4])?;
+            }
+        }
+        if self.big_tiff {
+            self.put(&next.to_le_bytes())?;
+        } else {
+            self.put(&offset_u32(next)?.to_le_bytes())?;
+        }
+        Ok(())
+    }
+
+    fn page_bytes(&self) -> u64 {
+        u64::from(self.width) * u64::from(self.height) * self.bit_depth.bytes()
+    }
+}
+
+/// Left-justified value of an IFD entry (SHORT values sit in the first two
+/// bytes, LONG in the first four).
+fn value_field(field_type: u16, value: u64) -> [u8; 8] {
+    let mut field = [0u8; 8];
+    if field_type == TYPE_SHORT {
+        field[..2].copy_from_slice(&u16::try_from(value).unwrap_or(u16::MAX).to_le_bytes());
+    } else {
+        field.copy_from_slice(&value.to_le_bytes());
+    }
+    field
+}
+
+fn offset_u32(value: u64) -> Result<u32> {
+    u32::try_from(value)
+        .map_err(|_| Error::InvalidFormat(format!("TIFF offset {value} exceeds 4 GB")))
+}
+
+/// Dense neutron-count histogram laid out as `[tof][y][x]`.
+#[derive(Debug, Clone)]
+pub struct TofHistogram {
+    tof_bins: usize,
+    width: usize,
+    height: usize,
+    /// Upper TOF edge in 25 ns ticks.
+    tof_max: u32,
+    /// Divisor mapping neutron positions to detector pixels.
+    super_resolution_factor: f64,
+    counts: Vec<u64>,
+    /// Neutrons dropped for falling outside the detector.
+    warnings: Warnings,
+}
+
+impl TofHistogram {
+    /// Creates an empty histogram of `tof_bins` bins over `0..tof_max`
+    /// (25 ns ticks) on a `width` × `height` detector.
+    #[must_use]
+    pub fn new(tof_bins: usize, tof_max: u32, width: usize, height: usize) -> Self {
+        let tof_bins = tof_bins.max(1);
+        Self {
+            tof_bins,
+            width,
+            height,
+            tof_max: tof_max.max(1),
+            super_resolution_factor: 1.0,
+            counts: vec![0; tof_bins * width * height],
+            warnings: Warnings::default(),
+        }
+    }
+
+    /// Sets the super-resolution factor the neutron positions were scaled by,
+    /// for batches that do not record their own.
+    #[must_use]
+    pub fn with_super_resolution_factor(mut self, factor: f64) -> Self {
+        self.super_resolution_factor = if factor > 0.0 { factor } else { 1.0 };
+        self
+    }
+
+    /// Counts every neutron of `batch`.
+    ///
+    /// Positions are divided by the batch's super-resolution factor (or the
+    /// histogram's, if the batch has none) and rounded to the nearest pixel.
+    /// Neutrons outside the detector are dropped with an
+    /// [`WarningKind::OutOfBounds`] warning; TOFs past `tof_max` go to the
+    /// last bin.
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    pub fn accumulate(&mut self, batch: &NeutronBatch) {
+        let tof_bins = u64::try_from(self.tof_bins).unwrap_or(u64::MAX);
+        let mut dropped = 0u64;
+        for i in 0..batch.len() {
+            let (x, y) = batch.pixel_coords(i, self.super_resolution_factor);
+            let (x, y) = (x.round(), y.round());
+            if x < 0.0 || y < 0.0 {
+                dropped += 1;
+                continue;
+            }
+            let (x, y) = (x as usize, y as usize);
+            if x >= self.width || y >= self.height {
+                dropped += 1;
+                continue;
+            }
+            let scaled = u64::from(batch.tof[i]) * tof_bins / u64::from(self.tof_max);
+            let bin = usize::try_from(scaled)
+                .unwrap_or(usize::MAX)
+                .min(self.tof_bins - 1);
+            let idx = (bin * self.height + y) * self.width + x;
+            self.counts[idx] = self.counts[idx].saturating_add(1);
+        }
+        let (width, height) = (self.width, self.height);
+        self.warnings.record(WarningKind::OutOfBounds, dropped, || {
+            format!(
+                "{dropped} neutrons fell outside the {width}x{height} detector and were dropped"
+            )
+        });
+    }
+
+    /// Returns the warnings recorded while accumulating.
+    #[must_use]
+    pub fn warnings(&self) -> &Warnings {
+        &self.warnings
+    }
+
+    /// Returns the number of TOF bins.
+    #[must_use]
+    pub fn tof_bins(&self) -> usize {
+        self.tof_bins
+    }
+
+    /// Returns the detector width in pixels.
+    #[must_use]
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Returns the detector height in pixels.
+    #[must_use]
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Returns all counts in `[tof][y][x]` order.
+    #[must_use]
+    pub fn counts(&self) -> &[u64] {
+        &self.counts
+    }
+
+    /// Returns the image of one TOF bin.
+    #[must_use]
+    pub fn page(&self, tof_bin: usize) -> Option<&[u64]> {
+        let pixels = self.width * self.height;
+        self.counts.get(tof_bin * pixels..(tof_bin + 1) * pixels)
+    }
+
+    /// Returns the width of one TOF bin in nanoseconds.
+    #[must_use]
+    pub fn tof_bin_width_ns(&self) -> f64 {
+        f64::from(self.tof_max) * 25.0 / u32::try_from(self.tof_bins).map_or(f64::MAX, f64::from)
+    }
+
+    /// Zeroes pixels that are hot only in some TOF bins, judged over
+    /// `window` bins at `sigma` (see [`find_tof_hot_pixels`]). Returns them
+    /// with the number of counts removed.
+    pub fn mask_tof_hot_pixels(&mut self, window: usize, sigma: f64) -> (TofHotPixels, u64) {
+        let hot = find_tof_hot_pixels(
+            &self.counts,
+            self.width,
+            self.height,
+            self.tof_bins,
+            window,
+            sigma,
+        );
+        let removed = hot.apply(&mut self.counts);
+        (hot, removed)
+    }
+
+    /// Returns the counts summed over TOF as one `[y][x]` image.
+    #[must_use]
+    pub fn projection(&self) -> Vec<u64> {
+        let mut image = vec![0u64; self.width * self.height];
+        for page in self.counts.chunks(image.len().max(1)) {
+            for (sum, &count) in image.iter_mut().zip(page) {
+                *sum = sum.saturating_add(count);
+            }
+        }
+        image
+    }
+
+    /// Writes the histogram as a TIFF stack, one page per TOF bin.
+    ///
+    /// # Errors
+    /// Returns an error if the detector size does not fit a TIFF or the file
+    /// cannot be written.
+    pub fn write_tiff<P: AsRef<Path>>(
+        &self,
+        path: P,
+        bit_depth: TiffBitDepth,
+    ) -> Result<TiffStackSummary> {
+        write_tiff_stack(path, &self.counts, self.width, self.height, bit_depth)
+    }
+}
+
+/// Writes `[page][y][x]` counts as a TIFF stack of `width` × `height` pages.
+///
+/// # Errors
+/// Returns an error if `counts` is not a whole number of pages, the size does
+/// not fit a TIFF, or the file cannot be written.
+pub fn write_tiff_stack<P: AsRef<Path>>(
+    path: P,
+    counts: &[u64],
+    width: usize,
+    height: usize,
+    bit_depth: TiffBitDepth,
+) -> Result<TiffStackSummary> {
+    let pixels = width * height;
+    if pixels == 0 || counts.is_empty() || !counts.len().is_multiple_of(pixels) {
+        return Err(Error::InvalidFormat(format!(
+            "{} counts do not make whole {width}x{height} pages",
+            counts.len()
+        )));
+    }
+    let size = |value: usize| {
+        u32::try_from(value)
+            .map_err(|_| Error::InvalidFormat(format!("TIFF dimension {value} exceeds u32")))
+    };
+    let mut stack = TiffStackWriter::create(
+        path,
+        size(width)?,
+        size(height)?,
+        counts.len() / pixels,
+        bit_depth,
+    )?;
+    for page in counts.chunks(pixels) {
+        stack.write_page(page)?;
+    }
+    stack.finish()
+}
+
+/// First page of a TIFF file, as read by [`read_tiff_page`].
+#[cfg(feature = "serde")]
+#[derive(Debug, Clone)]
+pub(crate) struct TiffPage {
+    pub width: usize,
+    pub height: usize,
+    /// Pixel values in row-major order.
+    pub pixels: Vec<u32>,
+    /// The `ImageDescription` text, if any.
+    pub description: Option<String>,
+}
+
+/// Reads the first page of an uncompressed grayscale TIFF of 8-, 16- or
+/// 32-bit unsigned pixels, such as one written by [`TiffStackWriter`].
+///
+/// # Errors
+/// Returns an error if the file cannot be read or uses a layout other than
+/// the one above (`BigTIFF`, compression, several samples per pixel).
+#[cfg(feature = "serde")]
+pub(crate) fn read_tiff_page(path: &Path) -> Result<TiffPage> {
+    let data = std::fs::read(path)?;
+    let invalid = |message: &str| Error::InvalidFormat(format!("TIFF: {message}"));
+    let little_endian = match data.get(..2) {
+        Some(b"II") => true,
+        Some(b"MM") => false,
+        _ => return Err(invalid("not a TIFF file")),
+    };
+    let bytes = |offset: usize, len: usize| {
+        data.get(offset..offset + len)
+            .ok_or_else(|| invalid("truncated file"))
+    };
+    let u16_at = |offset: usize| -> Result<u16> {
+        let field = bytes(offset, 2)?.try_into().unwrap_or_default();
+        Ok(if little_endian {
+            u16::from_le_bytes(field)
+        } else {
+            u16::from_be_bytes(field)
+        })
+    };
+    let u32_at = |offset: usize| -> Result<u32> {
+        let field = bytes(offset, 4)?.try_into().unwrap_or_default();
+        Ok(if little_endian {
+            u32::from_le_bytes(field)
+        } else {
+            u32::from_be_bytes(field)
+        })
+    };
+    let offset = |value: u32| usize::try_from(value).map_err(|_| invalid("offset too large"));
+    match u16_at(2)? {
+        42 => {}
+        43 => return Err(invalid("BigTIFF is not supported")),
+        _ => return Err(invalid("not a TIFF file")),
+    }
+
+    let directory = offset(u32_at(4)?)?;
+    let (mut width, mut height, mut bits) = (0, 0, 1);
+    let (mut strip_offsets, mut strip_byte_counts) = (Vec::new(), Vec::new());
+    let mut description = None;
+    for entry in 0..usize::from(u16_at(directory)?) {
+        let at = directory + 2 + entry * 12;
+        let (tag, field_type) = (u16_at(at)?, u16_at(at + 2)?);
+        let count = offset(u32_at(at + 4)?)?;
+        let value_size = match field_type {
+            TYPE_SHORT => 2,
+            TYPE_LONG => 4,
+            _ => 1,
+        };
+        // Values that do not fit the entry are stored at an offset.
+        let values_at = if count * value_size > 4 {
+            offset(u32_at(at + 8)?)?
+        } else {
+            at + 8
+        };
+        let value = |index: usize| -> Result<usize> {
+            match field_type {
+                TYPE_SHORT => Ok(usize::from(u16_at(values_at + 2 * index)?)),
+                TYPE_LONG => offset(u32_at(values_at + 4 * index)?),
+                _ => Err(invalid("unexpected field type")),
+            }
+        };
+        let values = || (0..count).map(value).collect::<Result<Vec<_>>>();
+        match tag {
+            TAG_IMAGE_WIDTH => width = value(0)?,
+            TAG_IMAGE_LENGTH => height = value(0)?,
+            TAG_BITS_PER_SAMPLE => bits = value(0)?,
+            TAG_COMPRESSION if value(0)? != 1 => {
+                return Err(invalid("compressed images are not supported"))
+            }
+            TAG_SAMPLES_PER_PIXEL if value(0)? != 1 => {
+                return Err(invalid("only grayscale images are supported"))
+            }
+            TAG_IMAGE_DESCRIPTION if field_type == TYPE_ASCII => {
+                let text = bytes(values_at, count)?;
+                let text = text.split(|&byte| byte == 0).next().unwrap_or_default();
+                description = Some(String::from_utf8_lossy(text).into_owned());
+            }
+            TAG_STRIP_OFFSETS => strip_offsets = values()?,
+            TAG_STRIP_BYTE_COUNTS => strip_byte_counts = values()?,
+            _ => {}
+        }
+    }
+    if !matches!(bits, 8 | 16 | 32) {
+        return Err(invalid(&format!("{bits}-bit pixels are not supported")));
+    }
+    let sample_bytes = bits / 8;
+    let mut raw = Vec::with_capacity(width * height * sample_bytes);
+    for (&start, &len) in strip_offsets.iter().zip(&strip_byte_counts) {
+        raw.extend_from_slice(bytes(start, len)?);
+    }
+    // Also catches strip offsets without byte counts.
+    if raw.len() < width * height * sample_bytes {
+        return Err(invalid("image data is shorter than the image"));
+    }
+    raw.truncate(width * height * sample_bytes);
+    let pixels = samples(&raw, sample_bytes, little_endian);
+    Ok(TiffPage {
+        width,
+        height,
+        pixels,
+        description,
+    })
+}
+
+/// Unsigned samples of `sample_bytes` bytes each.
+#[cfg(feature = "serde")]
+fn samples(raw: &[u8], sample_bytes: usize, little_endian: bool) -> Vec<u32> {
+    raw.chunks_exact(sample_bytes)
+        .map(|sample| match *sample {
+            [value] => u32::from(value),
+            [a, b] if little_endian => u32::from(u16::from_le_bytes([a, b])),
+            [a, b] => u32::from(u16::from_be_bytes([a, b])),
+            [a, b, c, d] if little_endian => u32::from_le_bytes([a, b, c, d]),
+            [a, b, c, d] => u32::from_be_bytes([a, b, c, d]),
+            _ => 0,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn u16_at(data: &[u8], offset: usize) -> u16 {
+        u16::from_le_bytes([data[offset], data[offset + 1]])
+    }
+
+    fn u32_at(data: &[u8], offset: usize) -> u32 {
+        u32::from_le_bytes(data[offset..offset + 4].try_into().unwrap())
+    }
+
+    /// Walks the directory chain and returns each page's first pixel.
+    fn first_pixels(data: &[u8]) -> Vec<u16> {
+        let mut pixels = Vec::new();
+        let mut ifd = u32_at(data, 4) as usize;
+        while ifd != 0 {
+            let count = usize::from(u16_at(data, ifd));
+            for entry in 0..count {
+                let at = ifd + 2 + entry * 12;
+                if u16_at(data, at) == TAG_STRIP_OFFSETS {
+                    pixels.push(u16_at(data, u32_at(data, at + 8) as usize));
+                }
+            }
+            ifd = u32_at(data, ifd + 2 + count * 12) as usize;
+        }
+        pixels
+    }
+
+    #[test]
+    fn test_tiff_stack_pages_and_clamping() {
+        let file = tempfile::NamedTempFile::new().unwrap();
+        let counts: Vec<u64> = vec![1, 0, 0, 0, 2, 0, 0, 0, 70_000, 0, 0, 0];
+        let summary = write_tiff_stack(file.path(), &counts, 2, 2, TiffBitDepth::Bit16).unwrap();
+        assert_eq!(summary.pages, 3);
+        assert!(summary.clamped);
+
+        let data = std::fs::read(file.path()).unwrap();
+        assert_eq!(summary.bytes, data.len() as u64);
+        assert_eq!(&data[..4], b"II\x2a\x00");
+        assert!(String::from_utf8_lossy(&data).contains("ImageJ=1.53\nimages=3\n"));
+        assert_eq!(first_pixels(&data), vec![1, 2, u16::MAX]);
+
+        assert!(write_tiff_stack(file.path(), &counts[..5], 2, 2, TiffBitDepth::Bit16).is_err());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_read_tiff_page() {
+        let file = tempfile::NamedTempFile::new().unwrap();
+        let mut stack = TiffStackWriter::create_with_properties(
+            file.path(),
+            3,
+            2,
+            1,
+            TiffBitDepth::Bit16,
+            "note=flat field\n",
+        )
+        .unwrap();
+        stack.write_page(&[0, 1, 2, 3, 4, 70_000]).unwrap();
+        stack.finish().unwrap();
+
+        let page = read_tiff_page(file.path()).unwrap();
+        assert_eq!((page.width, page.height), (3, 2));
+        assert_eq!(page.pixels, vec![0, 1, 2, 3, 4, 65_535]);
+        let description = page.description.unwrap();
+        assert!(description.starts_with("ImageJ=1.53\n"));
+        assert!(description.ends_with("note=flat field\n"));
+
+        std::fs::write(file.path(), b"not a tiff").unwrap();
+        assert!(read_tiff_page(file.path()).is_err());
+    }
+
+    #[test]
+    fn test_tof_histogram_bins_neutrons() {
+        let mut batch = NeutronBatch::default();
+        for (x, y, tof) in [
+            (2.0, 4.0, 10),
+            (2.2, 4.2, 999),
+            (1.0, 0.0, 5000),
+            (9.0, 0.0, 1),
+        ] {
+            batch.x.push(x * 2.0);
+            batch.y.push(y * 2.0);
+            batch.tof.push(tof);
+            batch.tot.push(1);
+            batch.n_hits.push(1);
+            batch.chip_id.push(0);
+        }
+        let mut histogram = TofHistogram::new(2, 1000, 4, 5).with_super_resolution_factor(2.0);
+        histogram.accumulate(&batch);
+
+        let first = histogram.page(0).unwrap();
+        let last = histogram.page(1).unwrap();
+        assert_eq!(first[4 * 4 + 2], 1);
+        assert_eq!(last[4 * 4 + 2], 1);
+        // Past tof_max lands in the last bin; off-detector is dropped.
+        assert_eq!(last[1], 1);
+        assert_eq!(histogram.counts().iter().sum::<u64>(), 3);
+        assert_eq!(histogram.warnings().count(WarningKind::OutOfBounds), 1);
+        assert!(histogram.page(2).is_none());
+        assert_eq!(histogram.projection()[4 * 4 + 2], 2);
+        assert!((histogram.tof_bin_width_ns() - 12_500.0).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,193 @@
+//! Write-behind output on a dedicated thread.
+//!
+//! Write jobs go through a bounded queue to a writer thread that owns the
+//! sink, so slow destinations (network filesystems, object-store staging)
+//! only stall processing once the queue is full. [`WriteBehindStats`]
+//! records how deep the queue got and how long producers were held back.
+
+use crate::{Error, Result};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{self, SyncSender, TrySendError};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+type Job<T> = Box<dyn FnOnce(&mut T) -> Result<()> + Send>;
+
+/// Queue and backpressure statistics for a [`WriteBehind`] writer.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct WriteBehindStats {
+    /// Configured queue capacity.
+    pub queue_depth: usize,
+    /// Jobs submitted.
+    pub jobs: usize,
+    /// Most jobs waiting in the queue at once.
+    pub max_queued: usize,
+    /// Submissions that found the queue full.
+    pub blocked_submits: usize,
+    /// Total time producers waited for queue space.
+    pub blocked: Duration,
+    /// Time the writer thread spent running jobs.
+    pub write_time: Duration,
+}
+
+/// Runs write jobs against a sink on a background thread, in submit order.
+pub struct WriteBehind<T: Send + 'static> {
+    sender: Option<SyncSender<Job<T>>>,
+    handle: Option<thread::JoinHandle<(T, Result<()>, Duration)>>,
+    queued: Arc<AtomicUsize>,
+    stats: WriteBehindStats,
+}
+
+impl<T: Send + 'static> WriteBehind<T> {
+    /// Move `sink` to a new writer thread with room for `queue_depth` jobs.
+    ///
+    /// # Errors
+    /// Returns an error if the thread cannot be spawned.
+    pub fn spawn(mut sink: T, queue_depth: usize) -> Result<Self> {
+        let queue_depth = queue_depth.max(1);
+        let (sender, receiver) = mpsc::sync_channel::<Job<T>>(queue_depth);
+        let queued = Arc::new(AtomicUsize::new(0));
+        let worker_queued = Arc::clone(&queued);
+        let handle = thread::Builder::new()
+            .name("rustpix-writer".to_string())
+            .spawn(move || {
+                let mut busy = Duration::ZERO;
+                for job in receiver {
+                    worker_queued.fetch_sub(1, Ordering::Relaxed);
+                    let start = Instant::now();
+                    let result = job(&mut sink);
+                    busy += start.elapsed();
+                    if result.is_err() {
+                        // Dropping the receiver makes the next submit fail.
+                        return (sink, result, busy);
+                    }
+                }
+                (sink, Ok(()), busy)
+            })?;
+
+        Ok(Self {
+            sender: Some(sender),
+            handle: Some(handle),
+            queued,
+            stats: WriteBehindStats {
+                queue_depth,
+                ..WriteBehindStats::default()
+            },
+        })
+    }
+
+    /// Queue a job, blocking while the queue is full.
+    ///
+    /// # Errors
+    /// Returns the error of an earlier job if the writer has stopped.
+    pub fn submit<F>(&mut self, job: F) -> Result<()>
+    where
+        F: FnOnce(&mut T) -> Result<()> + Send + 'static,
+    {
+        let Some(sender) = self.sender.as_ref() else {
+            return Err(writer_stopped());
+        };
+        let queued = self.queued.fetch_add(1, Ordering::Relaxed) + 1;
+        self.stats.max_queued = self
+            .stats
+            .max_queued
+            .max(queued.min(self.stats.queue_depth));
+        self.stats.jobs += 1;
+
+        let sent = match sender.try_send(Box::new(job)) {
+            Ok(()) => Ok(()),
+            Err(TrySendError::Full(job)) => {
+                self.stats.blocked_submits += 1;
+                let start = Instant::now();
+                let sent = sender.send(job).map_err(|_| ());
+                self.stats.blocked += start.elapsed();
+                sent
+            }
+            Err(TrySendError::Disconnected(_)) => Err(()),
+        };
+        if sent.is_err() {
+            self.sender = None;
+            return match self.join() {
+                Ok(_) => Err(writer_stopped()),
+                Err(err) => Err(err),
+            };
+        }
+        Ok(())
+    }
+
+    /// Current statistics.
+    #[must_use]
+    pub fn stats(&self) -> WriteBehindStats {
+        self.stats
+    }
+
+    /// Wait for queued jobs to finish and return the sink.
+    ///
+    /// # Errors
+    /// Returns the first job error, if any.
+    pub fn finish(mut self) -> Result<(T, WriteBehindStats)> {
+        self.sender = None;
+        let sink = self.join()?;
+        Ok((sink, self.stats))
+    }
+
+    fn join(&mut self) -> Result<T> {
+        let handle = self.handle.take().ok_or_else(writer_stopped)?;
+        let (sink, result, busy) = handle
+            .join()
+            .map_err(|_| Error::Io(std::io::Error::other("writer thread panicked")))?;
+        self.stats.write_time = busy;
+        result.map(|()| sink)
+    }
+}
+
+impl<T: Send + 'static> Drop for WriteBehind<T> {
+    fn drop(&mut self) {
+        self.sender = None;
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+fn writer_stopped() -> Error {
+    Error::Io(std::io::Error::other("write-behind writer has stopped"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_jobs_run_in_order_and_errors_surface() {
+        let mut writer = WriteBehind::spawn(Vec::new(), 2).unwrap();
+        for i in 0..10 {
+            writer
+                .submit(move |sink: &mut Vec<i32>| {
+                    sink.push(i);
+                    Ok(())
+                })
+                .unwrap();
+        }
+        let (sink, stats) = writer.finish().unwrap();
+        assert_eq!(sink, (0..10).collect::<Vec<_>>());
+        assert_eq!(stats.jobs, 10);
+        assert_eq!(stats.queue_depth, 2);
+        assert!(stats.max_queued <= 2);
+
+        let mut failing = WriteBehind::spawn((), 1).unwrap();
+        failing
+            .submit(|()| Err(Error::InvalidFormat("disk full".to_string())))
+            .unwrap();
+        let mut result = Ok(());
+        for _ in 0..4 {
+            result = failing.submit(|()| Ok(()));
+            if result.is_err() {
+                break;
+            }
+        }
+        let err = result.err().or_else(|| failing.finish().err()).unwrap();
+        assert!(err.to_string().contains("disk full"));
+    }
+}
@@ -1,15 +1,19 @@
 //! File writers for processed data.
 //!
 
-use crate::Result;
+use crate::binary::{encode_legacy_record, encode_neutron_record};
+use crate::packed::encode_packed_neutrons;
+use crate::{Error, Result};
 use rustpix_core::neutron::{Neutron, NeutronBatch};
-use std::fs::File;
-use std::io::{BufWriter, Write};
+use rustpix_core::pulse::EventIndex;
+use rustpix_core::soa::HitBatch;
+use std::fs::{File, OpenOptions};
+use std::io::{BufWriter, Seek, SeekFrom, Write};
 use std::path::Path;
 
 /// Writer for processed data output.
 ///
-/// Writes processed neutron data to files in various formats.
+/// Writes processed neutron (and labelled hit) data to files in various formats.
 pub struct DataFileWriter {
     writer: BufWriter<File>,
 }
@@ -25,6 +29,38 @@ impl DataFileWriter {
         Ok(Self { writer })
     }
 
+    /// Reopens a file written by an interrupted run to append to it,
+    /// discarding everything after its first `len` bytes.
+    ///
+    /// # Errors
+    /// Returns an error if the file cannot be opened or is shorter than
+    /// `len`.
+    pub fn append_at<P: AsRef<Path>>(path: P, len: u64) -> Result<Self> {
+        let path = path.as_ref();
+        let mut file = OpenOptions::new().write(true).open(path)?;
+        let existing = file.metadata()?.len();
+        if existing < len {
+            return Err(Error::InvalidFormat(format!(
+                "{} has {existing} bytes, expected at least {len}",
+                path.display()
+            )));
+        }
+        file.set_len(len)?;
+        file.seek(SeekFrom::End(0))?;
+        Ok(Self {
+            writer: BufWriter::new(file),
+        })
+    }
+
+    /// Flushes the writer and returns the length of the file so far.
+    ///
+    /// # Errors
+    /// Returns an error if flushing fails.
+    pub fn flushed_len(&mut self) -> Result<u64> {
+        self.writer.flush()?;
+        Ok(self.writer.get_mut().stream_position()?)
+    }
+
     /// Writes neutrons as CSV.
     ///
     /// # Errors
@@ -55,13 +91,9 @@ impl DataFileWriter {
     /// Returns an error if writing to the underlying file fails.
     pub fn write_neutrons_binary(&mut self, neutrons: &[Neutron]) -> Result<()> {
         for n in neutrons {
-            self.writer.write_all(&n.x.to_le_bytes())?;
-            self.writer.write_all(&n.y.to_le_bytes())?;
-            self.writer.write_all(&n.tof.to_le_bytes())?;
-            self.writer.write_all(&n.tot.to_le_bytes())?;
-            self.writer.write_all(&n.n_hits.to_le_bytes())?;
-            self.writer.write_all(&[n.chip_id])?;
-            self.writer.write_all(&[0u8; 3])?; // Reserved/padding
+            self.writer.write_all(&encode_neutron_record(
+                n.x, n.y, n.tof, n.tot, n.n_hits, n.chip_id,
+            ))?;
         }
 
         self.writer.flush()?;
@@ -76,13 +108,41 @@ impl DataFileWriter {
         &mut self,
         batch: &NeutronBatch,
         include_header: bool,
+    ) -> Result<()> {
+        self.write_neutron_rows_csv(batch, include_header, None)
+    }
+
+    /// Writes the neutrons of one pulse as CSV, with a trailing `pulse_id`
+    /// column that is left empty if the pulse is unknown.
+    ///
+    /// # Errors
+    /// Returns an error if writing to the underlying file fails.
+    pub fn write_neutron_batch_csv_with_pulse_id(
+        &mut self,
+        batch: &NeutronBatch,
+        pulse_id: Option<u32>,
+        include_header: bool,
+    ) -> Result<()> {
+        let pulse_id = pulse_id.map_or_else(String::new, |id| id.to_string());
+        self.write_neutron_rows_csv(batch, include_header, Some(&pulse_id))
+    }
+
+    fn write_neutron_rows_csv(
+        &mut self,
+        batch: &NeutronBatch,
+        include_header: bool,
+        pulse_id: Option<&str>,
     ) -> Result<()> {
         if include_header {
-            writeln!(self.writer, "x,y,tof,tot,n_hits,chip_id")?;
+            write!(self.writer, "x,y,tof,tot,n_hits,chip_id")?;
+            if pulse_id.is_some() {
+                write!(self.writer, ",pulse_id")?;
+            }
+            writeln!(self.writer)?;
         }
 
         for i in 0..batch.len() {
-            writeln!(
+            write!(
                 self.writer,
                 "{},{},{},{},{},{}",
                 batch.x[i],
@@ -92,6 +152,100 @@ impl DataFileWriter {
                 batch.n_hits[i],
                 batch.chip_id[i]
             )?;
+            if let Some(pulse_id) = pulse_id {
+                write!(self.writer, ",{pulse_id}")?;
+            }
+            writeln!(self.writer)?;
+        }
+
+        self.writer.flush()?;
+        Ok(())
+    }
+
+    /// Writes a hit batch as CSV, including cluster labels.
+    ///
+    /// Batches that kept raw hit fields get extra `raw_x,raw_y,raw_toa`
+    /// columns.
+    ///
+    /// # Errors
+    /// Returns an error if writing to the underlying file fails.
+    pub fn write_hit_batch_csv(&mut self, batch: &HitBatch, include_header: bool) -> Result<()> {
+        self.write_hit_rows_csv(batch, include_header, None)
+    }
+
+    /// Writes the hits of one pulse as CSV like
+    /// [`Self::write_hit_batch_csv`], with a trailing `pulse_id` column that
+    /// is left empty if the pulse is unknown.
+    ///
+    /// # Errors
+    /// Returns an error if writing to the underlying file fails.
+    pub fn write_hit_batch_csv_with_pulse_id(
+        &mut self,
+        batch: &HitBatch,
+        pulse_id: Option<u32>,
+        include_header: bool,
+    ) -> Result<()> {
+        let pulse_id = pulse_id.map_or_else(String::new, |id| id.to_string());
+        self.write_hit_rows_csv(batch, include_header, Some(&pulse_id))
+    }
+
+    fn write_hit_rows_csv(
+        &mut self,
+        batch: &HitBatch,
+        include_header: bool,
+        pulse_id: Option<&str>,
+    ) -> Result<()> {
+        let raw = batch.has_raw();
+        if include_header {
+            write!(self.writer, "x,y,tof,tot,timestamp,chip_id,cluster_id")?;
+            if raw {
+                write!(self.writer, ",raw_x,raw_y,raw_toa")?;
+            }
+            if pulse_id.is_some() {
+                write!(self.writer, ",pulse_id")?;
+            }
+            writeln!(self.writer)?;
+        }
+
+        for i in 0..batch.len() {
+            write!(
+                self.writer,
+                "{},{},{},{},{},{},{}",
+                batch.x[i],
+                batch.y[i],
+                batch.tof[i],
+                batch.tot[i],
+                batch.timestamp[i],
+                batch.chip_id[i],
+                batch.cluster_id[i]
+            )?;
+            if raw {
+                write!(
+                    self.writer,
+                    ",{},{},{}",
+                    batch.raw_x[i], batch.raw_y[i], batch.raw_toa[i]
+                )?;
+            }
+            if let Some(pulse_id) = pulse_id {
+                write!(self.writer, ",{pulse_id}")?;
+            }
+            writeln!(self.writer)?;
+        }
+
+        self.writer.flush()?;
+        Ok(())
+    }
+
+    /// Writes the pulse partition of an event list as CSV: one
+    /// `event_time_zero_ns,event_index` row per pulse, with the pulse start
+    /// time in nanoseconds and the index of its first event.
+    ///
+    /// # Errors
+    /// Returns an error if writing to the underlying file fails.
+    pub fn write_event_index_csv(&mut self, index: &EventIndex) -> Result<()> {
+        writeln!(self.writer, "event_time_zero_ns,event_index")?;
+        for (&time_zero, &first) in index.event_time_zero_25ns().iter().zip(index.event_index()) {
+            writeln!(self.writer, "{},{first}", time_zero * 25)?;
         }
 
         self.writer.flush()?;
@@ -104,13 +258,77 @@ impl DataFileWriter {
     /// Returns an error if writing to the underlying file fails.
     pub fn write_neutron_batch_binary(&mut self, batch: &NeutronBatch) -> Result<()> {
         for i in 0..batch.len() {
-            self.writer.write_all(&batch.x[i].to_le_bytes())?;
-            self.writer.write_all(&batch.y[i].to_le_bytes())?;
-            self.writer.write_all(&batch.tof[i].to_le_bytes())?;
-            self.writer.write_all(&batch.tot[i].to_le_bytes())?;
-            self.writer.write_all(&batch.n_hits[i].to_le_bytes())?;
-            self.writer.write_all(&[batch.chip_id[i]])?;
-            self.writer.write_all(&[0u8; 3])?; // Reserved/padding
+            self.writer.write_all(&encode_neutron_record(
+                batch.x[i],
+                batch.y[i],
+                batch.tof[i],
+                batch.tot[i],
+                batch.n_hits[i],
+                batch.chip_id[i],
+            ))?;
+        }
+
+        self.writer.flush()?;
+        Ok(())
+    }
+
+    /// Writes neutron batch as one delta-encoded, bit-packed block (`.rpxd`).
+    ///
+    /// Events are sorted by TOF within the block and positions are quantized
+    /// to `1 / position_scale`; see [`crate::read_neutron_batch_packed`].
+    /// Empty batches write nothing.
+    ///
+    /// # Errors
+    /// Returns an error if the batch cannot be encoded or writing fails.
+    pub fn write_neutron_batch_packed(
+        &mut self,
+        batch: &NeutronBatch,
+        position_scale: f64,
+    ) -> Result<()> {
+        if batch.is_empty() {
+            return Ok(());
+        }
+        self.writer
+            .write_all(&encode_packed_neutrons(batch, position_scale)?)?;
+        self.writer.flush()?;
+        Ok(())
+    }
+
+    /// Writes neutron batch in the legacy C++ (mcpevent2hist) event layout.
+    ///
+    /// Each record follows the C++ neutron struct definition:
+    /// `f64` x, `f64` y (detector pixels), `f64` tof (ns), `f64` tot,
+    /// `i32` `n_hits` and 4 bytes of struct padding, all little-endian.
+    /// The layout is tested against a fixture packed from that definition,
+    /// not against files written by mcpevent2hist itself.
+    ///
+    /// Total: 40 bytes per neutron. Positions are divided by
+    /// `super_resolution_factor` since the C++ tools work in pixel units.
+    ///
+    /// # Errors
+    /// Returns an error if writing to the underlying file fails, or the
+    /// batch was extracted with a different super-resolution factor.
+    pub fn write_neutron_batch_legacy(
+        &mut self,
+        batch: &NeutronBatch,
+        super_resolution_factor: f64,
+    ) -> Result<()> {
+        let scale = if super_resolution_factor > 0.0 {
+            super_resolution_factor
+        } else {
+            1.0
+        };
+        batch
+            .check_super_resolution(scale)
+            .map_err(rustpix_core::Error::from)?;
+        for i in 0..batch.len() {
+            self.writer.write_all(&encode_legacy_record(
+                batch.x[i] / scale,
+                batch.y[i] / scale,
+                f64::from(batch.tof[i]) * 25.0,
+                f64::from(batch.tot[i]),
+                i32::from(batch.n_hits[i]),
+            ))?;
         }
 
         self.writer.flush()?;
@@ -130,6 +348,7 @@ impl DataFileWriter {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::binary::LEGACY_RECORD_BYTES;
     use tempfile::NamedTempFile;
 
     #[test]
@@ -150,6 +369,73 @@ mod tests {
         assert!(content.contains("10.3,20.7,2000,200,8,1"));
     }
 
+    #[test]
+    fn test_write_hit_batch_csv_includes_cluster_id() {
+        let file = NamedTempFile::new().unwrap();
+        let mut writer = DataFileWriter::create(file.path()).unwrap();
+
+        let mut hits = HitBatch::default();
+        hits.push((1, 2, 300, 4, 500, 0));
+        hits.push((7, 8, 900, 10, 1100, 1));
+        hits.cluster_id[1] = 5;
+        writer.write_hit_batch_csv(&hits, true).unwrap();
+
+        let content = std::fs::read_to_string(file.path()).unwrap();
+        assert_eq!(
+            content,
+            "x,y,tof,tot,timestamp,chip_id,cluster_id\n1,2,300,4,500,0,-1\n7,8,900,10,1100,1,5\n"
+        );
+    }
+
+    #[test]
+    fn test_write_hit_batch_csv_raw_fields() {
+        let file = NamedTempFile::new().unwrap();
+        let mut writer = DataFileWriter::create(file.path()).unwrap();
+
+        let mut hits = HitBatch::default();
+        hits.push_with_raw((257, 2, 300, 4, 500, 1), (1, 2, 0x3FFF_FFF0));
+        writer.write_hit_batch_csv(&hits, true).unwrap();
+
+        let content = std::fs::read_to_string(file.path()).unwrap();
+        assert_eq!(
+            content,
+            "x,y,tof,tot,timestamp,chip_id,cluster_id,raw_x,raw_y,raw_toa\n257,2,300,4,500,1,-1,1,2,1073741808\n"
+        );
+    }
+
+    #[test]
+    fn test_write_csv_with_pulse_id() {
+        let file = NamedTempFile::new().unwrap();
+        let mut writer = DataFileWriter::create(file.path()).unwrap();
+
+        let mut neutrons = NeutronBatch::default();
+        neutrons.push(Neutron::new(1.5, 2.5, 1000, 100, 5, 0));
+        writer
+            .write_neutron_batch_csv_with_pulse_id(&neutrons, Some(7), true)
+            .unwrap();
+        writer
+            .write_neutron_batch_csv_with_pulse_id(&neutrons, None, false)
+            .unwrap();
+        let content = std::fs::read_to_string(file.path()).unwrap();
+        assert_eq!(
+            content,
+            "x,y,tof,tot,n_hits,chip_id,pulse_id\n1.5,2.5,1000,100,5,0,7\n1.5,2.5,1000,100,5,0,\n"
+        );
+
+        let file = NamedTempFile::new().unwrap();
+        let mut writer = DataFileWriter::create(file.path()).unwrap();
+        let mut hits = HitBatch::default();
+        hits.push((1, 2, 300, 4, 500, 0));
+        writer
+            .write_hit_batch_csv_with_pulse_id(&hits, Some(3), true)
+            .unwrap();
+        let content = std::fs::read_to_string(file.path()).unwrap();
+        assert_eq!(
+            content,
+            "x,y,tof,tot,timestamp,chip_id,cluster_id,pulse_id\n1,2,300,4,500,0,-1,3\n"
+        );
+    }
+
     #[test]
     fn test_write_neutrons_binary() {
         let file = NamedTempFile::new().unwrap();
@@ -163,4 +449,45 @@ mod tests {
         // 8 (f64) + 8 (f64) + 4 (u32) + 2 (u16) + 2 (u16) + 1 (u8) + 3 (reserved) = 28 bytes
         assert_eq!(data.len(), 28);
     }
+
+    #[test]
+    fn test_write_neutron_batch_legacy_layout() {
+        let file = NamedTempFile::new().unwrap();
+        let mut writer = DataFileWriter::create(file.path()).unwrap();
+
+        let mut batch = NeutronBatch::default();
+        batch.push(Neutron::new(16.0, 24.0, 40, 100, 5, 0));
+        writer.write_neutron_batch_legacy(&batch, 8.0).unwrap();
+
+        let data = std::fs::read(file.path()).unwrap();
+        assert_eq!(data.len(), LEGACY_RECORD_BYTES);
+        let f64_at = |at: usize| f64::from_le_bytes(data[at..at + 8].try_into().unwrap());
+        assert!((f64_at(0) - 2.0).abs() < f64::EPSILON);
+        assert!((f64_at(8) - 3.0).abs() < f64::EPSILON);
+        assert!((f64_at(16) - 1000.0).abs() < f64::EPSILON);
+        assert!((f64_at(24) - 100.0).abs() < f64::EPSILON);
+        assert_eq!(i32::from_le_bytes(data[32..36].try_into().unwrap()), 5);
+        assert_eq!(&data[36..40], &[0u8; 4]);
+
+        batch.super_resolution_factor = Some(4.0);
+        assert!(writer.write_neutron_batch_legacy(&batch, 8.0).is_err());
+    }
+
+    #[test]
+    fn test_write_event_index_csv() {
+        let file = NamedTempFile::new().unwrap();
+        let mut writer = DataFileWriter::create(file.path()).unwrap();
+
+        let mut index = EventIndex::new();
+        index.push(40, 2);
+        index.push(80, 0);
+        index.push(120, 1);
+        writer.write_event_index_csv(&index).unwrap();
+
+        let content = std::fs::read_to_string(file.path()).unwrap();
+        assert_eq!(
+            content,
+            "event_time_zero_ns,event_index\n1000,0\n2000,2\n3000,2\n"
+        );
+    }
 }
@@ -0,0 +1,23 @@
+//! Byte-level check of the legacy C++ (mcpevent2hist) event layout against
+//! a fixture packed by `scripts/legacy_fixture.py` from the C struct
+//! definition.
+
+use rustpix_core::neutron::{Neutron, NeutronBatch};
+use rustpix_io::DataFileWriter;
+
+const FIXTURE: &[u8] = include_bytes!("data/legacy_events.bin");
+
+#[test]
+fn test_legacy_writer_matches_struct_fixture() {
+    let mut batch = NeutronBatch::default();
+    batch.push(Neutron::new(16.0, 24.0, 40, 100, 5, 0));
+    batch.push(Neutron::new(2047.0, 1.0, 666_666, 65_535, 1, 3));
+    batch.push(Neutron::new(0.0, 4092.0, 1, 0, 65_535, 1));
+
+    let file = tempfile::NamedTempFile::new().unwrap();
+    let mut writer = DataFileWriter::create(file.path()).unwrap();
+    writer.write_neutron_batch_legacy(&batch, 8.0).unwrap();
+    drop(writer);
+
+    assert_eq!(std::fs::read(file.path()).unwrap(), FIXTURE);
+}
@@ -0,0 +1,67 @@
+//! Region, TOF, `ToT` and chip cuts applied to hits as they are read.
+
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use rustpix_core::filter::HitFilter;
+
+type Rectangle = (f64, f64, f64, f64);
+
+/// Cuts applied to hits as they are read, before anything else sees them.
+///
+/// `rectangles` are `(x_min, y_min, x_max, y_max)` tuples covering
+/// `x_min <= x < x_max`, `polygons` lists of at least three `(x, y)`
+/// vertices, both in detector pixels; hits in any of them are kept.
+/// `tof_range_ns` and `tot_range` are inclusive `(min, max)` windows and
+/// `chips` the chip ids to keep. Cuts left as None do not apply.
+#[pyclass(name = "HitFilter")]
+#[derive(Clone)]
+pub struct PyHitFilter {
+    pub(crate) inner: HitFilter,
+}
+
+#[pymethods]
+impl PyHitFilter {
+    #[new]
+    #[pyo3(signature = (rectangles=None, polygons=None, tof_range_ns=None, tot_range=None, chips=None))]
+    fn new(
+        rectangles: Option<Vec<Rectangle>>,
+        polygons: Option<Vec<Vec<(f64, f64)>>>,
+        tof_range_ns: Option<(f64, f64)>,
+        tot_range: Option<(u16, u16)>,
+        chips: Option<Vec<u8>>,
+    ) -> PyResult<Self> {
+        let mut filter = HitFilter::new();
+        for (x_min, y_min, x_max, y_max) in rectangles.unwrap_or_default() {
+            filter = filter.with_rectangle(x_min, y_min, x_max, y_max);
+        }
+        for vertices in polygons.unwrap_or_default() {
+            filter = filter.with_polygon(vertices);
+        }
+        if let Some((min, max)) = tof_range_ns {
+            filter = filter.with_tof_range_ns(min, max);
+        }
+        if let Some((min, max)) = tot_range {
+            filter = filter.with_tot_range(min, max);
+        }
+        if let Some(chips) = chips {
+            filter = filter.with_chips(chips);
+        }
+        filter
+            .validate()
+            .map_err(|err| PyValueError::new_err(err.to_string()))?;
+        Ok(Self { inner: filter })
+    }
+
+    fn __repr__(&self) -> String {
+        let tof_range_ns = self
+            .inner
+            .tof_range
+            .map(|(min, max)| (u64::from(min) * 25, u64::from(max) * 25));
+        format!(
+            "HitFilter(regions={}, tof_range_ns={tof_range_ns:?}, tot_range={:?}, chips={:?})",
+            self.inner.regions.len(),
+            self.inner.tot_range,
+            self.inner.chips
+        )
+    }
+}
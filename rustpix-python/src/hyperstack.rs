@@ -0,0 +1,314 @@
+//! TOF × Y × X count histograms for notebook exploration.
+//!
+//! `hyperstack(batch)` bins hits or neutrons in process; the resulting
+//! `Hyperstack` hands out images over TOF ranges and ROI spectra as NumPy
+//! arrays, and `show()` opens an ipywidgets viewer (TOF range slider and
+//! ROI box) on top of those two calls.
+
+use numpy::{PyArray1, PyArrayMethods};
+use pyo3::exceptions::{PyImportError, PyValueError};
+use pyo3::ffi::c_str;
+use pyo3::prelude::*;
+use rustpix_algorithms::find_tof_hot_pixels;
+use rustpix_tpx::DetectorConfig;
+
+use crate::{PyHitBatch, PyNeutronBatch};
+
+/// ipywidgets viewer, loaded on first `Hyperstack.show()`.
+const VIEWER_SOURCE: &std::ffi::CStr = c_str!(include_str!("viewer.py"));
+
+/// Largest stack `hyperstack()` allocates, at 4 bytes per count.
+const MAX_STACK_BYTES: u64 = 4 << 30;
+
+/// Dense count histogram laid out as `[tof][y][x]`.
+#[pyclass(name = "Hyperstack", frozen)]
+pub struct PyHyperstack {
+    tof_bins: usize,
+    width: usize,
+    height: usize,
+    /// Upper TOF edge in 25 ns ticks.
+    tof_max: u32,
+    counts: Vec<u32>,
+}
+
+impl PyHyperstack {
+    /// An empty stack, failing if it would exceed [`MAX_STACK_BYTES`].
+    fn new(tof_bins: usize, width: usize, height: usize, tof_max: u32) -> PyResult<Self> {
+        let len = tof_bins
+            .checked_mul(width)
+            .and_then(|len| len.checked_mul(height))
+            .filter(|&len| {
+                u64::try_from(len).is_ok_and(|len| len.saturating_mul(4) <= MAX_STACK_BYTES)
+            })
+            .ok_or_else(|| {
+                PyValueError::new_err(format!(
+                    "a stack of {tof_bins} TOF bins of {width}x{height} pixels exceeds the {} GiB limit; use fewer tof_bins",
+                    MAX_STACK_BYTES >> 30
+                ))
+            })?;
+        Ok(Self {
+            tof_bins,
+            width,
+            height,
+            tof_max,
+            counts: vec![0; len],
+        })
+    }
+
+    /// Count one event; events outside the detector or TOF range are dropped.
+    fn fill(&mut self, x: usize, y: usize, tof: u32) {
+        if x >= self.width || y >= self.height || tof >= self.tof_max {
+            return;
+        }
+        let tof_bins = u64::try_from(self.tof_bins).unwrap_or(u64::MAX);
+        let scaled = u64::from(tof) * tof_bins / u64::from(self.tof_max);
+        let bin = usize::try_from(scaled).unwrap_or(usize::MAX);
+        let idx = (bin * self.height + y) * self.width + x;
+        self.counts[idx] = self.counts[idx].saturating_add(1);
+    }
+
+    fn tof_range(&self, tof_range: Option<(usize, usize)>) -> PyResult<(usize, usize)> {
+        let (first, last) = tof_range.unwrap_or((0, self.tof_bins));
+        if first > last || last > self.tof_bins {
+            return Err(PyValueError::new_err(format!(
+                "tof_range ({first}, {last}) is outside 0..{}",
+                self.tof_bins
+            )));
+        }
+        Ok((first, last))
+    }
+}
+
+#[pymethods]
+impl PyHyperstack {
+    /// Array shape as (tof_bins, height, width).
+    #[getter]
+    fn shape(&self) -> (usize, usize, usize) {
+        (self.tof_bins, self.height, self.width)
+    }
+
+    /// TOF bin edges in nanoseconds (`tof_bins + 1` values).
+    #[allow(clippy::cast_precision_loss)]
+    fn tof_edges_ns(&self, py: Python<'_>) -> PyObject {
+        let bin_width = f64::from(self.tof_max) * 25.0 / self.tof_bins as f64;
+        let edges: Vec<f64> = (0..=self.tof_bins).map(|i| i as f64 * bin_width).collect();
+        PyArray1::from_vec(py, edges).into_any().unbind()
+    }
+
+    /// Full stack as a uint32 array of shape (tof_bins, height, width).
+    fn to_numpy(&self, py: Python<'_>) -> PyResult<PyObject> {
+        let array = PyArray1::from_slice(py, &self.counts).reshape([
+            self.tof_bins,
+            self.height,
+            self.width,
+        ])?;
+        Ok(array.into_any().unbind())
+    }
+
+    /// Counts summed over TOF bins `first..last`, shape (height, width).
+    ///
+    /// All bins by default.
+    #[pyo3(signature = (tof_range=None))]
+    fn image(&self, py: Python<'_>, tof_range: Option<(usize, usize)>) -> PyResult<PyObject> {
+        let (first, last) = self.tof_range(tof_range)?;
+        let plane = self.width * self.height;
+        let mut image = vec![0u64; plane];
+        for slice in self.counts[first * plane..last * plane].chunks_exact(plane) {
+            for (total, &count) in image.iter_mut().zip(slice) {
+                *total += u64::from(count);
+            }
+        }
+        let array = PyArray1::from_vec(py, image).reshape([self.height, self.width])?;
+        Ok(array.into_any().unbind())
+    }
+
+    /// Counts per TOF bin inside `roi = (x0, y0, x1, y1)` (half-open).
+    ///
+    /// The whole detector by default; the ROI is clipped to it.
+    #[pyo3(signature = (roi=None))]
+    fn spectrum(
+        &self,
+        py: Python<'_>,
+        roi: Option<(usize, usize, usize, usize)>,
+    ) -> PyResult<PyObject> {
+        let (x0, y0, x1, y1) = roi.unwrap_or((0, 0, self.width, self.height));
+        let (x1, y1) = (x1.min(self.width), y1.min(self.height));
+        if x0 > x1 || y0 > y1 {
+            return Err(PyValueError::new_err(format!(
+                "roi ({x0}, {y0}, {x1}, {y1}) has negative extent"
+            )));
+        }
+        let plane = self.width * self.height;
+        let spectrum: Vec<u64> = self
+            .counts
+            .chunks_exact(plane)
+            .map(|slice| {
+                (y0..y1)
+                    .flat_map(|y| &slice[y * self.width + x0..y * self.width + x1])
+                    .map(|&count| u64::from(count))
+                    .sum()
+            })
+            .collect();
+        Ok(PyArray1::from_vec(py, spectrum).into_any().unbind())
+    }
+
+    /// Open an ipywidgets viewer: image over a TOF range, spectrum of an ROI.
+    ///
+    /// Requires `ipywidgets` and `matplotlib`.
+    fn show(slf: Bound<'_, Self>) -> PyResult<PyObject> {
+        let py = slf.py();
+        let viewer = PyModule::from_code(
+            py,
+            VIEWER_SOURCE,
+            c_str!("rustpix_viewer.py"),
+            c_str!("rustpix_viewer"),
+        )
+        .map_err(|err| {
+            PyImportError::new_err(format!(
+                "ipywidgets and matplotlib are required for Hyperstack.show() (import failed: {err})"
+            ))
+        })?;
+        Ok(viewer.getattr("show")?.call1((slf,))?.unbind())
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "Hyperstack(tof_bins={}, height={}, width={}, tof_max_ns={})",
+            self.tof_bins,
+            self.height,
+            self.width,
+            u64::from(self.tof_max) * 25
+        )
+    }
+}
+
+/// Bin a HitBatch or NeutronBatch into a TOF × Y × X `Hyperstack`.
+///
+/// The detector size comes from the batch's detector config. TOF spans
+/// `0..tof_max_ns` (default: one TDC period) in `tof_bins` equal bins (200
+/// by default, as in the GUI); events outside it are dropped. Stacks over
+/// 4 GiB raise `ValueError`. Neutron positions are divided by the
+/// super-resolution factor they were extracted with, so both batch kinds
+/// histogram on the pixel grid.
+///
+/// With `tof_hot_sigma`, pixels that are hot only in some TOF bins (more
+/// than that many standard deviations above the mean of the `tof_hot_window`
+/// bins around each bin) are zeroed in those bins.
+#[pyfunction]
+#[pyo3(signature = (batch, tof_bins=200, tof_max_ns=None, tof_hot_sigma=None, tof_hot_window=1))]
+#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+pub fn hyperstack(
+    batch: &Bound<'_, PyAny>,
+    tof_bins: usize,
+    tof_max_ns: Option<f64>,
+    tof_hot_sigma: Option<f64>,
+    tof_hot_window: usize,
+) -> PyResult<PyHyperstack> {
+    if tof_bins == 0 {
+        return Err(PyValueError::new_err("tof_bins must be >= 1"));
+    }
+    if tof_hot_window == 0 {
+        return Err(PyValueError::new_err("tof_hot_window must be >= 1"));
+    }
+    let mask_hot = |mut stack: PyHyperstack| {
+        if let Some(sigma) = tof_hot_sigma {
+            find_tof_hot_pixels(
+                &stack.counts,
+                stack.width,
+                stack.height,
+                stack.tof_bins,
+                tof_hot_window,
+                sigma,
+            )
+            .apply(&mut stack.counts);
+        }
+        stack
+    };
+    let tof_max = |detector: &DetectorConfig| -> PyResult<u32> {
+        match tof_max_ns {
+            Some(ns) if ns.is_finite() && ns >= 25.0 => Ok((ns / 25.0).round() as u32),
+            Some(ns) => Err(PyValueError::new_err(format!(
+                "tof_max_ns must be at least 25 ns, got {ns}"
+            ))),
+            None => Ok(detector.tdc_correction_25ns().max(1)),
+        }
+    };
+
+    if let Ok(hits) = batch.downcast::<PyHitBatch>() {
+        let hits = hits.borrow();
+        let data = hits
+            .batch
+            .as_ref()
+            .ok_or_else(|| PyValueError::new_err("HitBatch data has already been moved"))?;
+        let detector = &hits.metadata.detector;
+        let (width, height) = detector.detector_dimensions();
+        let mut stack = PyHyperstack::new(tof_bins, width, height, tof_max(detector)?)?;
+        for i in 0..data.len() {
+            stack.fill(usize::from(data.x[i]), usize::from(data.y[i]), data.tof[i]);
+        }
+        return Ok(mask_hot(stack));
+    }
+
+    if let Ok(neutrons) = batch.downcast::<PyNeutronBatch>() {
+        let neutrons = neutrons.borrow();
+        let data = neutrons
+            .batch
+            .as_ref()
+            .ok_or_else(|| PyValueError::new_err("NeutronBatch data has already been moved"))?;
+        let detector = &neutrons.metadata.detector;
+        let scale = data.super_resolution_factor.unwrap_or_else(|| {
+            neutrons
+                .metadata
+                .extraction
+                .as_ref()
+                .cloned()
+                .unwrap_or_default()
+                .super_resolution_factor
+                .max(f64::MIN_POSITIVE)
+        });
+        let (width, height) = detector.detector_dimensions();
+        let mut stack = PyHyperstack::new(tof_bins, width, height, tof_max(detector)?)?;
+        for i in 0..data.len() {
+            let (x, y) = (data.x[i] / scale, data.y[i] / scale);
+            if x < 0.0 || y < 0.0 {
+                continue;
+            }
+            stack.fill(x as usize, y as usize, data.tof[i]);
+        }
+        return Ok(mask_hot(stack));
+    }
+
+    Err(PyValueError::new_err(
+        "hyperstack() expects a HitBatch or NeutronBatch",
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fill_bins_by_tof_edges() {
+        // Four bins of 25 ticks over 0..100 on a 2x1 detector.
+        let mut stack = PyHyperstack::new(4, 2, 1, 100).unwrap();
+        for tof in [0, 24, 25, 99, 100] {
+            stack.fill(1, 0, tof);
+        }
+        stack.fill(2, 0, 10);
+        stack.fill(0, 1, 10);
+        assert_eq!(stack.counts, vec![0, 2, 0, 1, 0, 0, 0, 1]);
+    }
+
+    #[test]
+    fn test_tof_range_and_size_limit() {
+        let stack = PyHyperstack::new(4, 2, 1, 100).unwrap();
+        assert_eq!(stack.tof_range(None).unwrap(), (0, 4));
+        assert_eq!(stack.tof_range(Some((1, 3))).unwrap(), (1, 3));
+        assert_eq!(stack.tof_range(Some((4, 4))).unwrap(), (4, 4));
+        assert!(stack.tof_range(Some((3, 1))).is_err());
+        assert!(stack.tof_range(Some((0, 5))).is_err());
+
+        assert!(PyHyperstack::new(20_000, 514, 514, 100).is_err());
+        assert!(PyHyperstack::new(usize::MAX, 2, 2, 100).is_err());
+    }
+}
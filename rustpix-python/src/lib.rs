@@ -1,22 +1,49 @@
 //! Thin Python bindings for rustpix.
 
-use numpy::PyArray1;
+mod hit_filter;
+mod hyperstack;
+mod pixel_mask;
+mod roi_mask;
+
+use arrow::array::{
+    ArrayRef, RecordBatch, RecordBatchIterator, RecordBatchReader, UInt32Array, UInt64Array,
+};
+use arrow::datatypes::{Field, Schema};
+use arrow::pyarrow::IntoPyArrow;
+use numpy::{PyArray1, PyArrayMethods, PyReadonlyArray1, PyUntypedArrayMethods};
 use pyo3::exceptions::{PyImportError, PyNotImplementedError, PyRuntimeError, PyValueError};
 use pyo3::prelude::*;
-use pyo3::types::{PyDict, PyList};
+use pyo3::types::PyDict;
 
 use rustpix_algorithms::{
     cluster_and_extract_batch, cluster_and_extract_stream, cluster_and_extract_stream_iter,
-    AlgorithmParams, ClusteringAlgorithm,
+    AlgorithmParams, ClusteringAlgorithm, Connectivity,
 };
+use rustpix_core::calibration::TotCalibration;
 use rustpix_core::clustering::ClusteringConfig;
-use rustpix_core::extraction::ExtractionConfig;
+use rustpix_core::extraction::{validate_super_resolution, ExtractionConfig, NeutronHitIndex};
+use rustpix_core::features::ClusterFeatures;
+use rustpix_core::filter::HitFilter;
 use rustpix_core::neutron::NeutronBatch;
-use rustpix_core::soa::HitBatch;
+use rustpix_core::soa::{HitBatch, HitRecord};
 use rustpix_io::{
-    out_of_core_neutron_stream, OutOfCoreConfig, TimeOrderedHitStream, Tpx3FileReader,
+    hit_record_batch, neutron_record_batch, out_of_core_neutron_stream, OutOfCoreConfig, ReadMode,
+    TimeOrderedHitStream, Tpx3FileReader,
+};
+use rustpix_tpx::frame::FrameValue;
+use rustpix_tpx::instrument::{self, InstrumentProfile};
+use rustpix_tpx::projection::HitColumns;
+use rustpix_tpx::tdc::DEFAULT_TDC_FREQUENCY_TOLERANCE;
+use rustpix_tpx::{
+    toa_ticks_to_ns, tot_counts_to_ns, ChipTransform, DetectorConfig, OutputGeometry,
+    TdcCorrectionPolicy, TimeUnits, TOA_TICK_NS,
 };
-use rustpix_tpx::{ChipTransform, DetectorConfig};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use hit_filter::PyHitFilter;
 
 type ChipTransformTuple = (i32, i32, i32, i32, i32, i32);
 type NeutronStreamItem = std::result::Result<NeutronBatch, String>;
@@ -28,7 +55,7 @@ struct BatchMetadata {
     clustering: Option<ClusteringConfig>,
     extraction: Option<ExtractionConfig>,
     algorithm: Option<String>,
-    source_path: Option<String>,
+    source_path: Option<PathBuf>,
     time_ordered: bool,
 }
 
@@ -74,23 +101,39 @@ impl PyDetectorConfig {
     #[pyo3(signature = (
         tdc_frequency_hz=None,
         enable_missing_tdc_correction=None,
+        tdc_correction_policy=None,
         chip_size_x=None,
         chip_size_y=None,
-        chip_transforms=None
+        chip_transforms=None,
+        time_units=None,
+        output_geometry=None,
+        retain_raw_hits=None,
+        flight_path_m=None,
+        tof_offset_ns=None
     ))]
+    #[allow(clippy::too_many_arguments)]
     fn new(
         tdc_frequency_hz: Option<f64>,
         enable_missing_tdc_correction: Option<bool>,
+        tdc_correction_policy: Option<&str>,
         chip_size_x: Option<u16>,
         chip_size_y: Option<u16>,
         chip_transforms: Option<Vec<ChipTransformTuple>>,
-    ) -> Self {
+        time_units: Option<&str>,
+        output_geometry: Option<&str>,
+        retain_raw_hits: Option<bool>,
+        flight_path_m: Option<f64>,
+        tof_offset_ns: Option<f64>,
+    ) -> PyResult<Self> {
         let mut config = DetectorConfig::default();
         if let Some(value) = tdc_frequency_hz {
             config.tdc_frequency_hz = value;
         }
         if let Some(value) = enable_missing_tdc_correction {
-            config.enable_missing_tdc_correction = value;
+            config.tdc_correction_policy = TdcCorrectionPolicy::from_enabled(value);
+        }
+        if let Some(policy) = tdc_correction_policy {
+            config.tdc_correction_policy = parse_tdc_correction_policy(policy)?;
         }
         if let Some(value) = chip_size_x {
             config.chip_size_x = value;
@@ -104,7 +147,31 @@ impl PyDetectorConfig {
                 .map(|(a, b, c, d, tx, ty)| ChipTransform { a, b, c, d, tx, ty })
                 .collect();
         }
-        Self { inner: config }
+        if let Some(units) = time_units {
+            config.time_units = parse_time_units(units)?;
+        }
+        if let Some(geometry) = output_geometry {
+            config.output_geometry = parse_output_geometry(geometry)?;
+            config
+                .validate_transforms()
+                .map_err(|err| PyValueError::new_err(err.to_string()))?;
+        }
+        if let Some(value) = retain_raw_hits {
+            config.retain_raw_hits = value;
+        }
+        if let Some(value) = flight_path_m {
+            if !(value.is_finite() && value > 0.0) {
+                return Err(PyValueError::new_err("flight_path_m must be positive"));
+            }
+            config.flight_path_m = Some(value);
+        }
+        if let Some(value) = tof_offset_ns {
+            if !value.is_finite() {
+                return Err(PyValueError::new_err("tof_offset_ns must be finite"));
+            }
+            config.tof_offset_ns = value;
+        }
+        Ok(Self { inner: config })
     }
 
     #[staticmethod]
@@ -121,11 +188,78 @@ impl PyDetectorConfig {
             .map_err(|err| PyValueError::new_err(err.to_string()))
     }
 
+    /// Detector configuration of an instrument profile, by name (e.g.
+    /// "venus", see `instrument_profiles()`) or profile JSON file.
+    #[staticmethod]
+    fn from_instrument(name: &str) -> PyResult<Self> {
+        InstrumentProfile::lookup(name)
+            .map(|profile| Self {
+                inner: profile.detector,
+            })
+            .map_err(|err| PyValueError::new_err(err.to_string()))
+    }
+
+    /// Load a detector configuration from a JSON file (str or os.PathLike).
+    #[staticmethod]
+    fn from_file(path: PathBuf) -> PyResult<Self> {
+        DetectorConfig::from_file(&path)
+            .map(|config| Self { inner: config })
+            .map_err(|err| PyValueError::new_err(format!("{}: {err}", path.display())))
+    }
+
+    /// Save the configuration as JSON (str or os.PathLike).
+    fn to_file(&self, path: PathBuf) -> PyResult<()> {
+        self.inner
+            .to_file(&path)
+            .map_err(|err| PyRuntimeError::new_err(format!("{}: {err}", path.display())))
+    }
+
+    /// Neutron energies (eV) of TOF values in microseconds, using the
+    /// flight path and TOF offset; NaN where the TOF is before the offset.
+    fn tof_to_energy<'py>(
+        &self,
+        py: Python<'py>,
+        tof_us: PyReadonlyArray1<'_, f64>,
+    ) -> PyResult<Bound<'py, PyArray1<f64>>> {
+        self.convert_tof(py, &tof_us, DetectorConfig::tof_us_to_energy_ev)
+    }
+
+    /// Neutron wavelengths (Å) of TOF values in microseconds, using the
+    /// flight path and TOF offset; NaN where the TOF is before the offset.
+    fn tof_to_wavelength<'py>(
+        &self,
+        py: Python<'py>,
+        tof_us: PyReadonlyArray1<'_, f64>,
+    ) -> PyResult<Bound<'py, PyArray1<f64>>> {
+        self.convert_tof(py, &tof_us, DetectorConfig::tof_us_to_wavelength_angstrom)
+    }
+
     fn to_dict(&self, py: Python<'_>) -> PyResult<PyObject> {
         detector_config_to_dict(py, &self.inner)
     }
 }
 
+impl PyDetectorConfig {
+    fn convert_tof<'py>(
+        &self,
+        py: Python<'py>,
+        tof_us: &PyReadonlyArray1<'_, f64>,
+        convert: fn(&DetectorConfig, f64) -> Option<f64>,
+    ) -> PyResult<Bound<'py, PyArray1<f64>>> {
+        if self.inner.flight_path_m.is_none() {
+            return Err(PyValueError::new_err(
+                "DetectorConfig has no flight_path_m for energy conversion",
+            ));
+        }
+        let values = tof_us
+            .to_vec()?
+            .into_iter()
+            .map(|tof| convert(&self.inner, tof).unwrap_or(f64::NAN))
+            .collect();
+        Ok(PyArray1::from_vec(py, values))
+    }
+}
+
 #[pyclass(name = "ClusteringConfig")]
 #[derive(Clone)]
 struct PyClusteringConfig {
@@ -178,15 +312,25 @@ struct PyExtractionConfig {
 
 #[pymethods]
 impl PyExtractionConfig {
+    /// `super_resolution_factor` is 1, 2, 4, 8 or 16 sub-pixels per detector
+    /// pixel. `tot_calibration` is a per-pixel ToT calibration file
+    /// (`x y a b c t` rows, `* a b c t` for a default); with it, neutron
+    /// batches gain an `energy_kev` column. With `cluster_features=True` they also gain
+    /// `rms_radius`, `tot_weighted_rms_radius`, `major_axis`, `minor_axis`,
+    /// `eccentricity` and `time_spread` columns.
     #[new]
-    #[pyo3(signature = (super_resolution_factor=None, weighted_by_tot=None, min_tot_threshold=None))]
+    #[pyo3(signature = (super_resolution_factor=None, weighted_by_tot=None, min_tot_threshold=None, tot_calibration=None, cluster_features=None))]
     fn new(
         super_resolution_factor: Option<f64>,
         weighted_by_tot: Option<bool>,
         min_tot_threshold: Option<u16>,
-    ) -> Self {
+        tot_calibration: Option<PathBuf>,
+        cluster_features: Option<bool>,
+    ) -> PyResult<Self> {
         let mut config = ExtractionConfig::default();
         if let Some(value) = super_resolution_factor {
+            validate_super_resolution(value)
+                .map_err(|err| PyValueError::new_err(err.to_string()))?;
             config.super_resolution_factor = value;
         }
         if let Some(value) = weighted_by_tot {
@@ -195,7 +339,15 @@ impl PyExtractionConfig {
         if let Some(value) = min_tot_threshold {
             config.min_tot_threshold = value;
         }
-        Self { inner: config }
+        if let Some(path) = tot_calibration {
+            let calibration = TotCalibration::from_file(&path)
+                .map_err(|err| PyValueError::new_err(format!("{}: {err}", path.display())))?;
+            config.tot_calibration = Some(Arc::new(calibration));
+        }
+        if let Some(value) = cluster_features {
+            config.cluster_features = value;
+        }
+        Ok(Self { inner: config })
     }
 
     #[staticmethod]
@@ -216,8 +368,79 @@ struct PyHitBatch {
     metadata: BatchMetadata,
 }
 
+impl PyHitBatch {
+    fn unattached(batch: HitBatch, detector_config: Option<PyRef<'_, PyDetectorConfig>>) -> Self {
+        Self {
+            batch: Some(batch),
+            metadata: BatchMetadata {
+                detector: detector_config
+                    .as_ref()
+                    .map(|cfg| cfg.inner.clone())
+                    .unwrap_or_default(),
+                clustering: None,
+                extraction: None,
+                algorithm: None,
+                source_path: None,
+                time_ordered: false,
+            },
+        }
+    }
+}
+
 #[pymethods]
 impl PyHitBatch {
+    /// Build a batch from `(x, y, tof, tot, timestamp, chip_id)` tuples.
+    ///
+    /// Times are raw: `tof` and `timestamp` in 25 ns ticks, `tot` in
+    /// counts. Hits are copied into columns once, so pass the batch to
+    /// `cluster_hits` rather than converting per call.
+    #[staticmethod]
+    #[pyo3(signature = (records, detector_config=None))]
+    fn from_records(
+        records: Vec<HitRecord>,
+        detector_config: Option<PyRef<'_, PyDetectorConfig>>,
+    ) -> Self {
+        Self::unattached(HitBatch::from_records(&records), detector_config)
+    }
+
+    /// Build a batch from equal-length NumPy columns in raw units.
+    ///
+    /// `x`, `y` and `tot` are uint16, `tof` and `timestamp` uint32 (25 ns
+    /// ticks) and `chip_id` uint8; `timestamp` and `chip_id` default to
+    /// zeros.
+    #[staticmethod]
+    #[pyo3(signature = (x, y, tof, tot, timestamp=None, chip_id=None, detector_config=None))]
+    fn from_numpy(
+        x: PyReadonlyArray1<'_, u16>,
+        y: PyReadonlyArray1<'_, u16>,
+        tof: PyReadonlyArray1<'_, u32>,
+        tot: PyReadonlyArray1<'_, u16>,
+        timestamp: Option<PyReadonlyArray1<'_, u32>>,
+        chip_id: Option<PyReadonlyArray1<'_, u8>>,
+        detector_config: Option<PyRef<'_, PyDetectorConfig>>,
+    ) -> PyResult<Self> {
+        let len = x.len();
+        let batch = HitBatch::from_columns(
+            x.to_vec()?,
+            y.to_vec()?,
+            tof.to_vec()?,
+            tot.to_vec()?,
+            timestamp.map_or_else(|| Ok(vec![0; len]), |array| array.to_vec())?,
+            chip_id.map_or_else(|| Ok(vec![0; len]), |array| array.to_vec())?,
+        )
+        .map_err(|err| PyValueError::new_err(err.to_string()))?;
+        Ok(Self::unattached(batch, detector_config))
+    }
+
+    /// Copy the hits out as `(x, y, tof, tot, timestamp, chip_id)` tuples
+    /// in raw units; the batch keeps its data.
+    fn to_records(&self) -> PyResult<Vec<HitRecord>> {
+        self.batch
+            .as_ref()
+            .map(HitBatch::to_records)
+            .ok_or_else(|| PyValueError::new_err("HitBatch data has already been moved"))
+    }
+
     fn len(&self) -> usize {
         self.batch.as_ref().map_or(0, HitBatch::len)
     }
@@ -246,16 +469,26 @@ impl PyHitBatch {
             timestamp,
             chip_id,
             cluster_id,
+            raw_x,
+            raw_y,
+            raw_toa,
         } = batch;
 
+        let [tof, tot, timestamp] =
+            hit_time_arrays(py, self.metadata.detector.time_units, tof, tot, timestamp);
         let dict = PyDict::new(py);
         dict.set_item("x", PyArray1::from_vec(py, x))?;
         dict.set_item("y", PyArray1::from_vec(py, y))?;
-        dict.set_item("tof", PyArray1::from_vec(py, tof))?;
-        dict.set_item("tot", PyArray1::from_vec(py, tot))?;
-        dict.set_item("timestamp", PyArray1::from_vec(py, timestamp))?;
+        dict.set_item("tof", tof)?;
+        dict.set_item("tot", tot)?;
+        dict.set_item("timestamp", timestamp)?;
         dict.set_item("chip_id", PyArray1::from_vec(py, chip_id))?;
         dict.set_item("cluster_id", PyArray1::from_vec(py, cluster_id))?;
+        if !raw_toa.is_empty() {
+            dict.set_item("raw_x", PyArray1::from_vec(py, raw_x))?;
+            dict.set_item("raw_y", PyArray1::from_vec(py, raw_y))?;
+            dict.set_item("raw_toa", PyArray1::from_vec(py, raw_toa))?;
+        }
         Ok(dict.into_any().unbind())
     }
 
@@ -267,31 +500,18 @@ impl PyHitBatch {
             .take()
             .ok_or_else(|| PyValueError::new_err("HitBatch data has already been moved"))?;
 
-        let HitBatch {
-            x,
-            y,
-            tof,
-            tot,
-            timestamp,
-            chip_id,
-            cluster_id,
-        } = batch;
-
-        let arrays = vec![
-            PyArray1::from_vec(py, x).into_any().unbind(),
-            PyArray1::from_vec(py, y).into_any().unbind(),
-            PyArray1::from_vec(py, tof).into_any().unbind(),
-            PyArray1::from_vec(py, tot).into_any().unbind(),
-            PyArray1::from_vec(py, timestamp).into_any().unbind(),
-            PyArray1::from_vec(py, chip_id).into_any().unbind(),
-            PyArray1::from_vec(py, cluster_id).into_any().unbind(),
-        ];
-
-        pyarrow_table_from_numpy(
-            py,
-            &arrays,
-            &["x", "y", "tof", "tot", "timestamp", "chip_id", "cluster_id"],
-        )
+        let raw = batch.has_raw();
+        let mut record_batch = hit_arrow_batch(batch, self.metadata.detector.time_units)?;
+        if !raw {
+            for name in ["raw_toa", "raw_y", "raw_x"] {
+                let index = record_batch
+                    .schema()
+                    .index_of(name)
+                    .map_err(runtime_error)?;
+                record_batch.remove_column(index);
+            }
+        }
+        pyarrow_table(py, record_batch)
     }
 
     fn __repr__(&self) -> String {
@@ -299,6 +519,16 @@ impl PyHitBatch {
     }
 }
 
+/// Neutron columns only filled when extraction computed cluster features.
+const FEATURE_COLUMNS: [&str; 6] = [
+    "rms_radius",
+    "tot_weighted_rms_radius",
+    "major_axis",
+    "minor_axis",
+    "eccentricity",
+    "time_spread",
+];
+
 #[pyclass(name = "NeutronBatch")]
 struct PyNeutronBatch {
     batch: Option<NeutronBatch>,
@@ -319,6 +549,20 @@ impl PyNeutronBatch {
         self.metadata.to_pydict(py)
     }
 
+    /// Super-resolution factor the positions were scaled by; divide `x` and
+    /// `y` by it for detector pixels. None if unknown.
+    fn super_resolution_factor(&self) -> Option<f64> {
+        self.batch
+            .as_ref()
+            .and_then(|batch| batch.super_resolution_factor)
+            .or_else(|| {
+                self.metadata
+                    .extraction
+                    .as_ref()
+                    .map(|extraction| extraction.super_resolution_factor)
+            })
+    }
+
     /// Convert neutrons to NumPy arrays (SoA layout).
     #[pyo3(name = "to_numpy")]
     fn take_numpy(&mut self, py: Python<'_>) -> PyResult<PyObject> {
@@ -334,6 +578,10 @@ impl PyNeutronBatch {
             tot,
             n_hits,
             chip_id,
+            energy_kev,
+            event_class: _,
+            features,
+            super_resolution_factor: _,
         } = batch;
 
         let dict = PyDict::new(py);
@@ -343,6 +591,24 @@ impl PyNeutronBatch {
         dict.set_item("tot", PyArray1::from_vec(py, tot))?;
         dict.set_item("n_hits", PyArray1::from_vec(py, n_hits))?;
         dict.set_item("chip_id", PyArray1::from_vec(py, chip_id))?;
+        if !energy_kev.is_empty() {
+            dict.set_item("energy_kev", PyArray1::from_vec(py, energy_kev))?;
+        }
+        if !features.is_empty() {
+            let column = |value: fn(&ClusterFeatures) -> f64| {
+                PyArray1::from_vec(py, features.iter().map(value).collect())
+            };
+            dict.set_item("rms_radius", column(|f| f.rms_radius))?;
+            dict.set_item(
+                "tot_weighted_rms_radius",
+                column(|f| f.tot_weighted_rms_radius),
+            )?;
+            dict.set_item("major_axis", column(|f| f.major_axis))?;
+            dict.set_item("minor_axis", column(|f| f.minor_axis))?;
+            dict.set_item("eccentricity", column(|f| f.eccentricity))?;
+            let time_spread: Vec<u32> = features.iter().map(|f| f.time_spread).collect();
+            dict.set_item("time_spread", PyArray1::from_vec(py, time_spread))?;
+        }
         Ok(dict.into_any().unbind())
     }
 
@@ -354,25 +620,24 @@ impl PyNeutronBatch {
             .take()
             .ok_or_else(|| PyValueError::new_err("NeutronBatch data has already been moved"))?;
 
-        let NeutronBatch {
-            x,
-            y,
-            tof,
-            tot,
-            n_hits,
-            chip_id,
-        } = batch;
-
-        let arrays = vec![
-            PyArray1::from_vec(py, x).into_any().unbind(),
-            PyArray1::from_vec(py, y).into_any().unbind(),
-            PyArray1::from_vec(py, tof).into_any().unbind(),
-            PyArray1::from_vec(py, tot).into_any().unbind(),
-            PyArray1::from_vec(py, n_hits).into_any().unbind(),
-            PyArray1::from_vec(py, chip_id).into_any().unbind(),
-        ];
-
-        pyarrow_table_from_numpy(py, &arrays, &["x", "y", "tof", "tot", "n_hits", "chip_id"])
+        let calibrated = batch.has_energy();
+        let with_features = batch.has_features();
+        let mut record_batch = neutron_record_batch(batch).map_err(runtime_error)?;
+        let mut absent = Vec::new();
+        if !calibrated {
+            absent.push("energy_kev");
+        }
+        if !with_features {
+            absent.extend(FEATURE_COLUMNS);
+        }
+        for name in absent {
+            let index = record_batch
+                .schema()
+                .index_of(name)
+                .map_err(runtime_error)?;
+            record_batch.remove_column(index);
+        }
+        pyarrow_table(py, record_batch)
     }
 
     fn __repr__(&self) -> String {
@@ -383,6 +648,7 @@ impl PyNeutronBatch {
 #[pyclass(name = "HitBatchStream", unsendable)]
 struct PyHitBatchStream {
     stream: TimeOrderedHitStream,
+    filter: Option<HitFilter>,
     metadata: BatchMetadata,
 }
 
@@ -393,7 +659,11 @@ impl PyHitBatchStream {
     }
 
     fn __next__(&mut self) -> Option<PyHitBatch> {
-        self.stream.next().map(|batch| PyHitBatch {
+        let mut batch = self.stream.next()?;
+        if let Some(filter) = &self.filter {
+            filter.apply(&mut batch);
+        }
+        Some(PyHitBatch {
             batch: Some(batch),
             metadata: self.metadata.clone(),
         })
@@ -425,26 +695,38 @@ impl PyNeutronBatchStream {
 }
 
 #[pyfunction]
-#[pyo3(signature = (path, detector_config=None, output_path=None))]
-/// Read TPX3 hits as a single batch (always time-ordered).
+#[pyo3(signature = (path, detector_config=None, output_path=None, columns=None, hit_filter=None))]
+/// Read TPX3 hits as a single batch.
+///
+/// Hits are time-ordered unless `columns` leaves out the time columns
+/// (`tof`, `timestamp`), e.g. `columns=["x", "y"]` for imaging. Skipped
+/// columns are zero-filled and hits then come in file order, which is
+/// considerably faster to parse. Hits failing `hit_filter` (a `HitFilter`)
+/// are dropped; cuts on skipped columns see them as zero.
 fn read_tpx3_hits(
-    path: &str,
+    path: PathBuf,
     detector_config: Option<PyRef<'_, PyDetectorConfig>>,
-    output_path: Option<&str>,
+    output_path: Option<PathBuf>,
+    columns: Option<Vec<String>>,
+    hit_filter: Option<PyRef<'_, PyHitFilter>>,
 ) -> PyResult<PyHitBatch> {
-    ensure_hdf5_disabled(output_path)?;
+    ensure_hdf5_disabled(output_path.as_deref())?;
     let config = detector_config
         .as_ref()
         .map(|cfg| cfg.inner.clone())
         .unwrap_or_default();
+    let columns = columns
+        .as_deref()
+        .map_or(Ok(HitColumns::ALL), parse_hit_columns)?;
 
-    let reader = Tpx3FileReader::open(path)
-        .map_err(|err| PyRuntimeError::new_err(err.to_string()))?
-        .with_config(config.clone());
+    let reader = open_tpx3(&path)?.with_config(config.clone());
 
-    let batch = reader
-        .read_batch()
+    let mut batch = reader
+        .read_batch_columns(columns)
         .map_err(|err| PyRuntimeError::new_err(err.to_string()))?;
+    if let Some(filter) = &hit_filter {
+        filter.inner.apply(&mut batch);
+    }
 
     Ok(PyHitBatch {
         batch: Some(batch),
@@ -453,12 +735,42 @@ fn read_tpx3_hits(
             clustering: None,
             extraction: None,
             algorithm: None,
-            source_path: Some(path.to_string()),
-            time_ordered: true,
+            source_path: Some(path),
+            time_ordered: columns.time,
         },
     })
 }
 
+#[pyfunction]
+#[pyo3(signature = (path, detector_config=None, hit_filter=None))]
+/// Read TPX3 hits as a PyArrow Table (always time-ordered).
+///
+/// The table shares the Rust buffers through the Arrow C data interface, so
+/// no per-column copies are made; `polars.from_arrow` wraps it the same way.
+/// Hits failing `hit_filter` (a `HitFilter`) are dropped.
+fn read_tpx3_file_arrow(
+    py: Python<'_>,
+    path: PathBuf,
+    detector_config: Option<PyRef<'_, PyDetectorConfig>>,
+    hit_filter: Option<PyRef<'_, PyHitFilter>>,
+) -> PyResult<PyObject> {
+    let config = detector_config
+        .as_ref()
+        .map(|cfg| cfg.inner.clone())
+        .unwrap_or_default();
+    let time_units = config.time_units;
+
+    let mut batch = open_tpx3(&path)?
+        .with_config(config)
+        .read_batch()
+        .map_err(runtime_error)?;
+    if let Some(filter) = &hit_filter {
+        filter.inner.apply(&mut batch);
+    }
+
+    pyarrow_table(py, hit_arrow_batch(batch, time_units)?)
+}
+
 /// Process a TPX3 file into neutrons.
 ///
 /// By default this returns a streaming iterator (`NeutronBatchStream`) that yields
@@ -472,11 +784,12 @@ fn read_tpx3_hits(
 /// - parallelism (int): worker threads for slice processing.
 /// - queue_depth (int): bounded queue depth for pipeline stages.
 /// - async_io (bool): enable async reader/worker pipeline.
+/// - target_hits_per_chunk (int): split chunks by hit count (memory budget still applies).
 #[pyfunction]
 #[pyo3(signature = (path, detector_config=None, clustering_config=None, extraction_config=None, collect=false, **kwargs))]
 fn process_tpx3_neutrons(
     py: Python<'_>,
-    path: &str,
+    path: PathBuf,
     detector_config: Option<PyRef<'_, PyDetectorConfig>>,
     clustering_config: Option<PyRef<'_, PyClusteringConfig>>,
     extraction_config: Option<PyRef<'_, PyExtractionConfig>>,
@@ -502,9 +815,7 @@ fn process_tpx3_neutrons(
     let params = processing.selection.params;
     let algo = processing.selection.algorithm;
 
-    let reader = Tpx3FileReader::open(path)
-        .map_err(|err| PyRuntimeError::new_err(err.to_string()))?
-        .with_config(detector.clone());
+    let reader = open_tpx3(&path)?.with_config(detector.clone());
 
     if !collect && !processing.time_ordered {
         return Err(PyValueError::new_err(
@@ -542,7 +853,7 @@ fn process_tpx3_neutrons(
                 clustering: Some(clustering),
                 extraction: Some(extraction),
                 algorithm: Some(processing.selection.name),
-                source_path: Some(path.to_string()),
+                source_path: Some(path),
                 time_ordered: processing.time_ordered,
             },
         };
@@ -564,7 +875,7 @@ fn process_tpx3_neutrons(
                 clustering: Some(clustering),
                 extraction: Some(extraction),
                 algorithm: Some(processing.selection.name),
-                source_path: Some(path.to_string()),
+                source_path: Some(path),
                 time_ordered: true,
             },
         };
@@ -572,6 +883,11 @@ fn process_tpx3_neutrons(
     }
 }
 
+/// Cluster a HitBatch and extract neutrons.
+///
+/// Cluster labels are written into the batch's `cluster_id` column (-1 for
+/// unclustered hits), so `batch.to_numpy()` can regroup hits by event
+/// afterwards without re-clustering.
 #[pyfunction]
 #[pyo3(signature = (batch, clustering_config=None, extraction_config=None, **kwargs))]
 fn cluster_hits(
@@ -617,6 +933,47 @@ fn cluster_hits(
     })
 }
 
+/// Map neutrons from `cluster_hits` back to their constituent hits.
+///
+/// Call on a HitBatch that was clustered by `cluster_hits`, with the same
+/// extraction config. Returns a dict with `offsets` (one more entry than
+/// neutrons) and `hit_indices`; neutron `i` was built from the hits
+/// `hit_indices[offsets[i]:offsets[i + 1]]`.
+#[pyfunction]
+#[pyo3(signature = (batch, extraction_config=None))]
+fn neutron_hit_index(
+    py: Python<'_>,
+    batch: PyRef<'_, PyHitBatch>,
+    extraction_config: Option<PyRef<'_, PyExtractionConfig>>,
+) -> PyResult<PyObject> {
+    let hits = batch
+        .batch
+        .as_ref()
+        .ok_or_else(|| PyValueError::new_err("HitBatch data has already been moved"))?;
+    let extraction = extraction_config
+        .as_ref()
+        .map(|cfg| cfg.inner.clone())
+        .unwrap_or_default();
+    // Labels are dense from 0, so the highest label bounds the cluster count.
+    let num_clusters = hits
+        .cluster_id
+        .iter()
+        .copied()
+        .max()
+        .and_then(|max| usize::try_from(max).ok())
+        .map_or(0, |max| max + 1);
+
+    let NeutronHitIndex {
+        offsets,
+        hit_indices,
+    } = NeutronHitIndex::build(hits, num_clusters, &extraction);
+
+    let dict = PyDict::new(py);
+    dict.set_item("offsets", PyArray1::from_vec(py, offsets))?;
+    dict.set_item("hit_indices", PyArray1::from_vec(py, hit_indices))?;
+    Ok(dict.into_any().unbind())
+}
+
 #[pyfunction]
 #[pyo3(signature = (path, detector_config=None, clustering_config=None, extraction_config=None, **kwargs))]
 /// Stream TPX3 neutrons in pulse-bounded batches.
@@ -628,8 +985,9 @@ fn cluster_hits(
 /// - parallelism (int): worker threads for slice processing
 /// - queue_depth (int): bounded queue depth for pipeline stages
 /// - async_io (bool): enable async reader/worker pipeline
+/// - target_hits_per_chunk (int): split chunks by hit count (memory budget still applies)
 fn stream_tpx3_neutrons(
-    path: &str,
+    path: PathBuf,
     detector_config: Option<PyRef<'_, PyDetectorConfig>>,
     clustering_config: Option<PyRef<'_, PyClusteringConfig>>,
     extraction_config: Option<PyRef<'_, PyExtractionConfig>>,
@@ -656,9 +1014,7 @@ fn stream_tpx3_neutrons(
     let params = selection.params;
     let algo = selection.algorithm;
 
-    let reader = Tpx3FileReader::open(path)
-        .map_err(|err| PyRuntimeError::new_err(err.to_string()))?
-        .with_config(detector.clone());
+    let reader = open_tpx3(&path)?.with_config(detector.clone());
 
     let stream = build_neutron_stream(
         &reader,
@@ -676,26 +1032,25 @@ fn stream_tpx3_neutrons(
             clustering: Some(clustering),
             extraction: Some(extraction),
             algorithm: Some(selection.name),
-            source_path: Some(path.to_string()),
+            source_path: Some(path),
             time_ordered: true,
         },
     })
 }
 
 #[pyfunction]
-#[pyo3(signature = (path, detector_config=None))]
+#[pyo3(signature = (path, detector_config=None, hit_filter=None))]
 fn stream_tpx3_hits(
-    path: &str,
+    path: PathBuf,
     detector_config: Option<PyRef<'_, PyDetectorConfig>>,
+    hit_filter: Option<PyRef<'_, PyHitFilter>>,
 ) -> PyResult<PyHitBatchStream> {
     let detector = detector_config
         .as_ref()
         .map(|cfg| cfg.inner.clone())
         .unwrap_or_default();
 
-    let reader = Tpx3FileReader::open(path)
-        .map_err(|err| PyRuntimeError::new_err(err.to_string()))?
-        .with_config(detector.clone());
+    let reader = open_tpx3(&path)?.with_config(detector.clone());
 
     let stream = reader
         .stream_time_ordered()
@@ -703,17 +1058,256 @@ fn stream_tpx3_hits(
 
     Ok(PyHitBatchStream {
         stream,
+        filter: hit_filter.map(|filter| filter.inner.clone()),
         metadata: BatchMetadata {
             detector,
             clustering: None,
             extraction: None,
             algorithm: None,
-            source_path: Some(path.to_string()),
+            source_path: Some(path),
             time_ordered: true,
         },
     })
 }
 
+/// Measure the TDC frequency of a TPX3 file from its TDC packet spacing.
+///
+/// Returns None if the file has fewer than two TDC packets on any chip;
+/// otherwise a dict with the measured `frequency_hz`, the `configured_hz`
+/// from `detector_config` (default 60 Hz), their `relative_difference`, and
+/// `agrees` (difference within `tolerance`).
+#[pyfunction]
+#[pyo3(signature = (path, detector_config=None, tolerance=DEFAULT_TDC_FREQUENCY_TOLERANCE))]
+fn estimate_tdc_frequency(
+    py: Python<'_>,
+    path: PathBuf,
+    detector_config: Option<PyRef<'_, PyDetectorConfig>>,
+    tolerance: f64,
+) -> PyResult<Option<PyObject>> {
+    let configured = detector_config
+        .as_ref()
+        .map_or_else(DetectorConfig::default, |cfg| cfg.inner.clone())
+        .tdc_frequency_hz;
+    let Some(estimate) = open_tpx3(&path)?.estimate_tdc_frequency() else {
+        return Ok(None);
+    };
+
+    let dict = PyDict::new(py);
+    dict.set_item("frequency_hz", estimate.frequency_hz)?;
+    dict.set_item(
+        "median_interval_ns",
+        toa_ticks_to_ns(estimate.median_interval_25ns),
+    )?;
+    dict.set_item("intervals", estimate.intervals)?;
+    dict.set_item("chip_id", estimate.chip_id)?;
+    dict.set_item("configured_hz", configured)?;
+    dict.set_item(
+        "relative_difference",
+        estimate.relative_difference(configured),
+    )?;
+    dict.set_item("agrees", !estimate.disagrees_with(configured, tolerance))?;
+    Ok(Some(dict.into_any().unbind()))
+}
+
+/// List pulses missing from a TPX3 file's TDC stream.
+///
+/// The expected period comes from `detector_config.tdc_frequency_hz`
+/// (default 60 Hz). Returns None if the file has no TDC packets; otherwise a
+/// dict with `pulses`, `missing_pulses`, `lost_time_s`, `period_s`,
+/// `chip_id` and `gaps`, a list of `(after_s, missing)` tuples giving the
+/// time since the first TDC of the last pulse before each gap.
+#[pyfunction]
+#[pyo3(signature = (path, detector_config=None))]
+fn find_dropped_pulses(
+    py: Python<'_>,
+    path: PathBuf,
+    detector_config: Option<PyRef<'_, PyDetectorConfig>>,
+) -> PyResult<Option<PyObject>> {
+    let config = detector_config
+        .as_ref()
+        .map(|cfg| cfg.inner.clone())
+        .unwrap_or_default();
+    let Some(report) = open_tpx3(&path)?.with_config(config).pulse_gaps() else {
+        return Ok(None);
+    };
+
+    let ticks_to_s =
+        |ticks: u64| Duration::from_nanos(ticks.saturating_mul(TOA_TICK_NS)).as_secs_f64();
+    let gaps: Vec<(f64, u32)> = report
+        .gaps
+        .iter()
+        .map(|gap| (ticks_to_s(gap.after_25ns), gap.missing))
+        .collect();
+
+    let dict = PyDict::new(py);
+    dict.set_item("pulses", report.pulses)?;
+    dict.set_item("missing_pulses", report.missing_pulses())?;
+    dict.set_item("lost_time_s", report.lost_time().as_secs_f64())?;
+    dict.set_item("period_s", ticks_to_s(u64::from(report.period_25ns)))?;
+    dict.set_item("chip_id", report.chip_id)?;
+    dict.set_item("gaps", gaps)?;
+    Ok(Some(dict.into_any().unbind()))
+}
+
+/// Count missing, duplicate and irregular TDC pulses per section of a TPX3
+/// file.
+///
+/// The expected period comes from `detector_config.tdc_frequency_hz`
+/// (default 60 Hz). Returns None if that gives no period; otherwise a dict
+/// with the file totals `tdcs`, `missing`, `duplicate` and `irregular`,
+/// `period_s`, and `sections`, a list of dicts with the same counts plus
+/// `section`, `chip_id` and `start_offset` for every section with a TDC.
+#[pyfunction]
+#[pyo3(signature = (path, detector_config=None))]
+fn tdc_diagnostics(
+    py: Python<'_>,
+    path: PathBuf,
+    detector_config: Option<PyRef<'_, PyDetectorConfig>>,
+) -> PyResult<Option<PyObject>> {
+    let config = detector_config
+        .as_ref()
+        .map(|cfg| cfg.inner.clone())
+        .unwrap_or_default();
+    let Some(report) = open_tpx3(&path)?.with_config(config).tdc_diagnostics() else {
+        return Ok(None);
+    };
+
+    let sections = report
+        .sections
+        .iter()
+        .map(|section| {
+            let dict = PyDict::new(py);
+            dict.set_item("section", section.section)?;
+            dict.set_item("chip_id", section.chip_id)?;
+            dict.set_item("start_offset", section.start_offset)?;
+            dict.set_item("tdcs", section.tdcs)?;
+            dict.set_item("missing", section.missing)?;
+            dict.set_item("duplicate", section.duplicate)?;
+            dict.set_item("irregular", section.irregular)?;
+            Ok(dict)
+        })
+        .collect::<PyResult<Vec<_>>>()?;
+
+    let dict = PyDict::new(py);
+    dict.set_item("tdcs", report.tdcs())?;
+    dict.set_item("missing", report.missing())?;
+    dict.set_item("duplicate", report.duplicate())?;
+    dict.set_item("irregular", report.irregular())?;
+    dict.set_item(
+        "period_s",
+        Duration::from_nanos(u64::from(report.period_25ns) * TOA_TICK_NS).as_secs_f64(),
+    )?;
+    dict.set_item("sections", sections)?;
+    Ok(Some(dict.into_any().unbind()))
+}
+
+/// Read a frame-mode (shutter) TPX3 file as an image stack.
+///
+/// `value` selects what each pixel holds: "event_count" (default),
+/// "integrated_tot" or "packets". Returns a uint32 array of shape
+/// (frames, height, width), with chips placed by `detector_config`.
+#[pyfunction]
+#[pyo3(signature = (path, detector_config=None, value="event_count"))]
+fn read_frames(
+    py: Python<'_>,
+    path: PathBuf,
+    detector_config: Option<PyRef<'_, PyDetectorConfig>>,
+    value: &str,
+) -> PyResult<PyObject> {
+    let value = match value.to_lowercase().as_str() {
+        "event_count" => FrameValue::EventCount,
+        "integrated_tot" | "itot" => FrameValue::IntegratedTot,
+        "packets" => FrameValue::Packets,
+        _ => {
+            return Err(PyValueError::new_err(format!(
+            "Unknown frame value '{value}'. Expected one of: event_count, integrated_tot, packets"
+        )))
+        }
+    };
+    let config = detector_config
+        .as_ref()
+        .map(|cfg| cfg.inner.clone())
+        .unwrap_or_default();
+    let (width, height) = config.detector_dimensions();
+    let frames = open_tpx3(&path)?.with_config(config).read_frames(value);
+
+    let count = frames.len();
+    let values: Vec<u32> = frames.into_iter().flat_map(|frame| frame.values).collect();
+    let array = PyArray1::from_vec(py, values).reshape([count, height, width])?;
+    Ok(array.into_any().unbind())
+}
+
+/// Read mode used when opening files; see `set_read_mode`.
+static READ_MODE: AtomicU8 = AtomicU8::new(READ_MODE_AUTO);
+
+const READ_MODE_AUTO: u8 = 0;
+const READ_MODE_MMAP: u8 = 1;
+const READ_MODE_BUFFERED: u8 = 2;
+
+/// Choose how TPX3 files are opened.
+///
+/// - "auto" (default): memory-map, falling back to buffered reads for
+///   Windows network (UNC) paths, when RUSTPIX_NO_MMAP is set, or when
+///   mapping fails.
+/// - "mmap": always memory-map.
+/// - "buffered": read files into memory with standard buffered I/O, for
+///   filesystems where mmap misbehaves.
+#[pyfunction]
+fn set_read_mode(mode: &str) -> PyResult<()> {
+    let value = match mode.to_lowercase().as_str() {
+        "auto" => READ_MODE_AUTO,
+        "mmap" => READ_MODE_MMAP,
+        "buffered" => READ_MODE_BUFFERED,
+        _ => {
+            return Err(PyValueError::new_err(format!(
+                "Unknown read mode '{mode}'. Expected one of: auto, mmap, buffered"
+            )))
+        }
+    };
+    READ_MODE.store(value, Ordering::Relaxed);
+    Ok(())
+}
+
+/// Return the current read mode ("auto", "mmap" or "buffered").
+#[pyfunction]
+fn get_read_mode() -> &'static str {
+    match READ_MODE.load(Ordering::Relaxed) {
+        READ_MODE_MMAP => "mmap",
+        READ_MODE_BUFFERED => "buffered",
+        _ => "auto",
+    }
+}
+
+/// The built-in instrument profiles, as a list of dicts with `name`,
+/// `title`, `facility`, `description`, `flight_path_m` (None if not fixed),
+/// `pixel_pitch_mm` and `detector` (as `DetectorConfig.to_dict()`).
+#[pyfunction]
+fn instrument_profiles(py: Python<'_>) -> PyResult<Vec<PyObject>> {
+    instrument::profiles()
+        .iter()
+        .map(|profile| {
+            let dict = PyDict::new(py);
+            dict.set_item("name", &profile.name)?;
+            dict.set_item("title", &profile.title)?;
+            dict.set_item("facility", &profile.facility)?;
+            dict.set_item("description", &profile.description)?;
+            dict.set_item("flight_path_m", profile.detector.flight_path_m)?;
+            dict.set_item("pixel_pitch_mm", profile.pixel_pitch_mm)?;
+            dict.set_item("detector", detector_config_to_dict(py, &profile.detector)?)?;
+            Ok(dict.into_any().unbind())
+        })
+        .collect()
+}
+
+fn open_tpx3(path: &Path) -> PyResult<Tpx3FileReader> {
+    let mode = match READ_MODE.load(Ordering::Relaxed) {
+        READ_MODE_MMAP => ReadMode::Mmap,
+        READ_MODE_BUFFERED => ReadMode::Buffered,
+        _ => ReadMode::Auto,
+    };
+    Tpx3FileReader::open_with(path, mode).map_err(|err| PyRuntimeError::new_err(err.to_string()))
+}
+
 #[pymodule]
 fn rustpix(_py: Python<'_>, m: &Bound<'_, PyModule>) -> PyResult<()> {
     m.add_class::<PyDetectorConfig>()?;
@@ -723,16 +1317,34 @@ fn rustpix(_py: Python<'_>, m: &Bound<'_, PyModule>) -> PyResult<()> {
     m.add_class::<PyNeutronBatch>()?;
     m.add_class::<PyHitBatchStream>()?;
     m.add_class::<PyNeutronBatchStream>()?;
+    m.add_class::<hyperstack::PyHyperstack>()?;
+    m.add_class::<PyHitFilter>()?;
 
     m.add_function(wrap_pyfunction!(read_tpx3_hits, m)?)?;
+    m.add_function(wrap_pyfunction!(read_tpx3_file_arrow, m)?)?;
     m.add_function(wrap_pyfunction!(process_tpx3_neutrons, m)?)?;
     m.add_function(wrap_pyfunction!(cluster_hits, m)?)?;
+    m.add_function(wrap_pyfunction!(neutron_hit_index, m)?)?;
+    m.add_function(wrap_pyfunction!(estimate_tdc_frequency, m)?)?;
+    m.add_function(wrap_pyfunction!(find_dropped_pulses, m)?)?;
+    m.add_function(wrap_pyfunction!(tdc_diagnostics, m)?)?;
+    m.add_function(wrap_pyfunction!(read_frames, m)?)?;
+    m.add_function(wrap_pyfunction!(set_read_mode, m)?)?;
+    m.add_function(wrap_pyfunction!(get_read_mode, m)?)?;
+    m.add_function(wrap_pyfunction!(instrument_profiles, m)?)?;
     m.add_function(wrap_pyfunction!(stream_tpx3_neutrons, m)?)?;
     m.add_function(wrap_pyfunction!(stream_tpx3_hits, m)?)?;
+    m.add_function(wrap_pyfunction!(hyperstack::hyperstack, m)?)?;
+    m.add_function(wrap_pyfunction!(roi_mask::load_roi_mask, m)?)?;
+    m.add_function(wrap_pyfunction!(roi_mask::roi_mask_contains, m)?)?;
+    m.add_function(wrap_pyfunction!(roi_mask::apply_roi_mask, m)?)?;
+    m.add_function(wrap_pyfunction!(pixel_mask::load_pixel_mask, m)?)?;
+    m.add_function(wrap_pyfunction!(pixel_mask::save_pixel_mask, m)?)?;
+    m.add_function(wrap_pyfunction!(pixel_mask::apply_pixel_mask, m)?)?;
     Ok(())
 }
 
-fn ensure_hdf5_disabled(output_path: Option<&str>) -> PyResult<()> {
+fn ensure_hdf5_disabled(output_path: Option<&Path>) -> PyResult<()> {
     if output_path.is_some() {
         return Err(PyNotImplementedError::new_err(
             "HDF5 output is not implemented yet",
@@ -750,7 +1362,7 @@ struct AlgorithmSelection {
 struct ProcessingKwargs {
     selection: AlgorithmSelection,
     time_ordered: bool,
-    output_path: Option<String>,
+    output_path: Option<PathBuf>,
     out_of_core: OutOfCoreKwargs,
 }
 
@@ -761,6 +1373,7 @@ struct OutOfCoreKwargs {
     parallelism: Option<usize>,
     queue_depth: Option<usize>,
     async_io: Option<bool>,
+    target_hits_per_chunk: Option<usize>,
 }
 
 impl OutOfCoreKwargs {
@@ -770,6 +1383,7 @@ impl OutOfCoreKwargs {
             || self.parallelism.is_some()
             || self.queue_depth.is_some()
             || self.async_io.is_some()
+            || self.target_hits_per_chunk.is_some()
     }
 
     fn resolve_enabled(&self) -> bool {
@@ -792,6 +1406,9 @@ impl OutOfCoreKwargs {
                 return Err(PyValueError::new_err("queue_depth must be >= 1"));
             }
         }
+        if self.target_hits_per_chunk == Some(0) {
+            return Err(PyValueError::new_err("target_hits_per_chunk must be >= 1"));
+        }
         Ok(())
     }
 
@@ -812,6 +1429,9 @@ impl OutOfCoreKwargs {
         if let Some(async_io) = self.async_io {
             memory = memory.with_async_io(async_io);
         }
+        if let Some(hits) = self.target_hits_per_chunk {
+            memory = memory.with_target_hits_per_chunk(hits);
+        }
         memory
     }
 }
@@ -843,6 +1463,20 @@ fn parse_algorithm_kwargs(kwargs: Option<&Bound<'_, PyDict>>) -> PyResult<Algori
         if let Some(value) = extract_kwarg::<usize>(kwargs, "grid_cell_size")? {
             params.grid_cell_size = value;
         }
+        if let Some(value) = extract_kwarg::<f64>(kwargs, "merge_distance")? {
+            params.hierarchical_merge_distance = value;
+        }
+        if let Some(value) = extract_kwarg::<f64>(kwargs, "merge_window_ns")? {
+            params.hierarchical_merge_window_ns = value;
+        }
+        if let Some(value) = extract_kwarg::<u8>(kwargs, "connectivity")? {
+            params.connectivity = Connectivity::from_neighbors(value).ok_or_else(|| {
+                PyValueError::new_err(format!("connectivity must be 4 or 8, got {value}"))
+            })?;
+        }
+        if let Some(value) = extract_kwarg::<usize>(kwargs, "section_hits")? {
+            params.section_hits = value;
+        }
     }
 
     let normalized = name.to_ascii_lowercase();
@@ -855,9 +1489,9 @@ fn parse_algorithm_kwargs(kwargs: Option<&Bound<'_, PyDict>>) -> PyResult<Algori
     })
 }
 
-fn parse_output_path(kwargs: Option<&Bound<'_, PyDict>>) -> PyResult<Option<String>> {
+fn parse_output_path(kwargs: Option<&Bound<'_, PyDict>>) -> PyResult<Option<PathBuf>> {
     if let Some(kwargs) = kwargs {
-        extract_kwarg::<String>(kwargs, "output_path")
+        extract_kwarg::<PathBuf>(kwargs, "output_path")
     } else {
         Ok(None)
     }
@@ -870,6 +1504,7 @@ fn parse_out_of_core_kwargs(kwargs: Option<&Bound<'_, PyDict>>) -> PyResult<OutO
     let mut parallelism = None;
     let mut queue_depth = None;
     let mut async_io = None;
+    let mut target_hits_per_chunk = None;
 
     if let Some(kwargs) = kwargs {
         if let Some(value) = extract_kwarg::<bool>(kwargs, "out_of_core")? {
@@ -890,6 +1525,9 @@ fn parse_out_of_core_kwargs(kwargs: Option<&Bound<'_, PyDict>>) -> PyResult<OutO
         if let Some(value) = extract_kwarg::<bool>(kwargs, "async_io")? {
             async_io = Some(value);
         }
+        if let Some(value) = extract_kwarg::<usize>(kwargs, "target_hits_per_chunk")? {
+            target_hits_per_chunk = Some(value);
+        }
     }
 
     Ok(OutOfCoreKwargs {
@@ -899,6 +1537,7 @@ fn parse_out_of_core_kwargs(kwargs: Option<&Bound<'_, PyDict>>) -> PyResult<OutO
         parallelism,
         queue_depth,
         async_io,
+        target_hits_per_chunk,
     })
 }
 
@@ -965,22 +1604,134 @@ fn parse_algorithm(name: &str) -> PyResult<ClusteringAlgorithm> {
         "abs" => Ok(ClusteringAlgorithm::Abs),
         "dbscan" => Ok(ClusteringAlgorithm::Dbscan),
         "grid" => Ok(ClusteringAlgorithm::Grid),
+        "streaming" => Ok(ClusteringAlgorithm::Streaming),
+        "hierarchical" => Ok(ClusteringAlgorithm::Hierarchical),
+        "connected_components" => Ok(ClusteringAlgorithm::ConnectedComponents),
         _ => Err(PyValueError::new_err(format!(
-            "Unknown algorithm '{}'. Expected one of: abs, dbscan, grid",
+            "Unknown algorithm '{}'. Expected one of: abs, dbscan, grid, streaming, hierarchical, \
+             connected_components",
             name
         ))),
     }
 }
 
+fn parse_hit_columns(names: &[String]) -> PyResult<HitColumns> {
+    let mut columns = HitColumns::POSITIONS;
+    for name in names {
+        match name.to_lowercase().as_str() {
+            "x" | "y" | "chip_id" | "cluster_id" => {}
+            "tot" => columns.tot = true,
+            "tof" | "timestamp" => columns.time = true,
+            _ => {
+                return Err(PyValueError::new_err(format!(
+                    "Unknown hit column '{name}'. Expected any of: x, y, tot, tof, timestamp, chip_id, cluster_id"
+                )))
+            }
+        }
+    }
+    Ok(columns)
+}
+
+fn parse_time_units(name: &str) -> PyResult<TimeUnits> {
+    match name.to_lowercase().as_str() {
+        "raw" => Ok(TimeUnits::Raw),
+        "ns" => Ok(TimeUnits::Nanoseconds),
+        _ => Err(PyValueError::new_err(format!(
+            "Unknown time_units '{name}'. Expected one of: raw, ns"
+        ))),
+    }
+}
+
+fn parse_tdc_correction_policy(name: &str) -> PyResult<TdcCorrectionPolicy> {
+    match name.to_lowercase().as_str() {
+        "none" => Ok(TdcCorrectionPolicy::None),
+        "extrapolate_last_period" => Ok(TdcCorrectionPolicy::ExtrapolateLastPeriod),
+        "interpolate_neighbors" => Ok(TdcCorrectionPolicy::InterpolateNeighbors),
+        "drop_section" => Ok(TdcCorrectionPolicy::DropSection),
+        _ => Err(PyValueError::new_err(format!(
+            "Unknown tdc_correction_policy '{name}'. Expected one of: none, \
+             extrapolate_last_period, interpolate_neighbors, drop_section"
+        ))),
+    }
+}
+
+fn parse_output_geometry(name: &str) -> PyResult<OutputGeometry> {
+    match name.to_lowercase().as_str() {
+        "native" => Ok(OutputGeometry::Native),
+        "packed" => Ok(OutputGeometry::Packed),
+        "gap_filled" => Ok(OutputGeometry::GapFilled),
+        _ => Err(PyValueError::new_err(format!(
+            "Unknown output_geometry '{name}'. Expected one of: native, packed, gap_filled"
+        ))),
+    }
+}
+
+/// Build the `tof`, `tot` and `timestamp` arrays in the configured units.
+fn hit_time_arrays(
+    py: Python<'_>,
+    units: TimeUnits,
+    tof: Vec<u32>,
+    tot: Vec<u16>,
+    timestamp: Vec<u32>,
+) -> [PyObject; 3] {
+    match units {
+        TimeUnits::Raw => [
+            PyArray1::from_vec(py, tof).into_any().unbind(),
+            PyArray1::from_vec(py, tot).into_any().unbind(),
+            PyArray1::from_vec(py, timestamp).into_any().unbind(),
+        ],
+        TimeUnits::Nanoseconds => {
+            let ticks_to_ns =
+                |ticks: Vec<u32>| ticks.into_iter().map(toa_ticks_to_ns).collect::<Vec<_>>();
+            let tot: Vec<u32> = tot.into_iter().map(tot_counts_to_ns).collect();
+            [
+                PyArray1::from_vec(py, ticks_to_ns(tof)).into_any().unbind(),
+                PyArray1::from_vec(py, tot).into_any().unbind(),
+                PyArray1::from_vec(py, ticks_to_ns(timestamp))
+                    .into_any()
+                    .unbind(),
+            ]
+        }
+    }
+}
+
 fn detector_config_to_dict(py: Python<'_>, config: &DetectorConfig) -> PyResult<PyObject> {
     let dict = PyDict::new(py);
     dict.set_item("tdc_frequency_hz", config.tdc_frequency_hz)?;
     dict.set_item(
         "enable_missing_tdc_correction",
-        config.enable_missing_tdc_correction,
+        config.tdc_correction_policy.is_enabled(),
+    )?;
+    dict.set_item(
+        "tdc_correction_policy",
+        match config.tdc_correction_policy {
+            TdcCorrectionPolicy::None => "none",
+            TdcCorrectionPolicy::ExtrapolateLastPeriod => "extrapolate_last_period",
+            TdcCorrectionPolicy::InterpolateNeighbors => "interpolate_neighbors",
+            TdcCorrectionPolicy::DropSection => "drop_section",
+        },
     )?;
     dict.set_item("chip_size_x", config.chip_size_x)?;
     dict.set_item("chip_size_y", config.chip_size_y)?;
+    dict.set_item(
+        "time_units",
+        match config.time_units {
+            TimeUnits::Raw => "raw",
+            TimeUnits::Nanoseconds => "ns",
+        },
+    )?;
+    dict.set_item(
+        "output_geometry",
+        match config.output_geometry {
+            OutputGeometry::Native => "native",
+            OutputGeometry::Packed => "packed",
+            OutputGeometry::GapFilled => "gap_filled",
+        },
+    )?;
+    dict.set_item("detector_dimensions", config.detector_dimensions())?;
+    dict.set_item("retain_raw_hits", config.retain_raw_hits)?;
+    dict.set_item("flight_path_m", config.flight_path_m)?;
+    dict.set_item("tof_offset_ns", config.tof_offset_ns)?;
 
     let transforms: Vec<(i32, i32, i32, i32, i32, i32)> = config
         .chip_transforms
@@ -988,6 +1739,17 @@ fn detector_config_to_dict(py: Python<'_>, config: &DetectorConfig) -> PyResult<
         .map(|t| (t.a, t.b, t.c, t.d, t.tx, t.ty))
         .collect();
     dict.set_item("chip_transforms", transforms)?;
+
+    let toa_corrections = PyDict::new(py);
+    for (chip_id, correction) in config.toa_corrections.iter().enumerate() {
+        if let Some(correction) = correction {
+            let entry = PyDict::new(py);
+            entry.set_item("offset_ns", correction.offset_ns)?;
+            entry.set_item("column_offsets_ns", correction.column_offsets_ns.clone())?;
+            toa_corrections.set_item(chip_id, entry)?;
+        }
+    }
+    dict.set_item("toa_corrections", toa_corrections)?;
     Ok(dict.into_any().unbind())
 }
 
@@ -1005,33 +1767,61 @@ fn extraction_config_to_dict(py: Python<'_>, config: &ExtractionConfig) -> PyRes
     dict.set_item("super_resolution_factor", config.super_resolution_factor)?;
     dict.set_item("weighted_by_tot", config.weighted_by_tot)?;
     dict.set_item("min_tot_threshold", config.min_tot_threshold)?;
+    dict.set_item("tot_calibration", config.tot_calibration.is_some())?;
+    dict.set_item("cluster_features", config.cluster_features)?;
     Ok(dict.into_any().unbind())
 }
 
-fn pyarrow_table_from_numpy(
-    py: Python<'_>,
-    arrays: &[PyObject],
-    names: &[&str],
-) -> PyResult<PyObject> {
-    let pyarrow = PyModule::import(py, "pyarrow").map_err(|err| {
+/// Hits as an Arrow record batch, with the time columns in `units`.
+fn hit_arrow_batch(batch: HitBatch, units: TimeUnits) -> PyResult<RecordBatch> {
+    match units {
+        TimeUnits::Raw => hit_record_batch(batch).map_err(runtime_error),
+        TimeUnits::Nanoseconds => {
+            let ticks_to_ns = |ticks: &[u32]| {
+                UInt64Array::from_iter_values(ticks.iter().map(|&tick| toa_ticks_to_ns(tick)))
+            };
+            let columns: [(&str, ArrayRef); 3] = [
+                ("tof", Arc::new(ticks_to_ns(&batch.tof))),
+                (
+                    "tot",
+                    Arc::new(UInt32Array::from_iter_values(
+                        batch.tot.iter().map(|&counts| tot_counts_to_ns(counts)),
+                    )),
+                ),
+                ("timestamp", Arc::new(ticks_to_ns(&batch.timestamp))),
+            ];
+            let raw = hit_record_batch(batch).map_err(runtime_error)?;
+            let schema = raw.schema();
+            let mut fields: Vec<Field> = schema
+                .fields()
+                .iter()
+                .map(|field| field.as_ref().clone())
+                .collect();
+            let mut arrays = raw.columns().to_vec();
+            for (name, column) in columns {
+                let index = schema.index_of(name).map_err(runtime_error)?;
+                fields[index] = Field::new(name, column.data_type().clone(), false);
+                arrays[index] = column;
+            }
+            RecordBatch::try_new(Arc::new(Schema::new(fields)), arrays).map_err(runtime_error)
+        }
+    }
+}
+
+/// Hand a record batch to pyarrow through the Arrow C data interface, which
+/// shares the Rust buffers instead of copying them.
+fn pyarrow_table(py: Python<'_>, batch: RecordBatch) -> PyResult<PyObject> {
+    PyModule::import(py, "pyarrow").map_err(|err| {
         PyImportError::new_err(format!(
-            "pyarrow is required for to_arrow (import failed: {err})"
+            "pyarrow is required for Arrow export (import failed: {err})"
         ))
     })?;
-    let array_fn = pyarrow.getattr("array")?;
-    let kwargs = PyDict::new(py);
-    kwargs.set_item("copy", false)?;
-
-    let mut arrow_arrays = Vec::with_capacity(arrays.len());
-    for array in arrays {
-        arrow_arrays.push(array_fn.call((array,), Some(&kwargs))?);
-    }
+    let schema = batch.schema();
+    let reader: Box<dyn RecordBatchReader + Send> =
+        Box::new(RecordBatchIterator::new(vec![Ok(batch)], schema));
+    reader.into_pyarrow(py)?.call_method0(py, "read_all")
+}
 
-    let arrays_list = PyList::new(py, arrow_arrays)?;
-    let names_list = PyList::new(py, names)?;
-    let table = pyarrow
-        .getattr("Table")?
-        .getattr("from_arrays")?
-        .call((arrays_list, names_list), None)?;
-    Ok(table.into_any().unbind())
+fn runtime_error(err: impl std::fmt::Display) -> PyErr {
+    PyRuntimeError::new_err(err.to_string())
 }
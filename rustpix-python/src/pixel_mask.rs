@@ -0,0 +1,134 @@
+//! Hot/dead pixel masks saved from the GUI ("Save mask…") or the CLI's
+//! `--pixel-mask` files, as JSON or TIFF.
+
+use numpy::{PyArray1, PyArrayMethods, PyReadonlyArray2, PyUntypedArrayMethods};
+use pyo3::exceptions::{PyRuntimeError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+use rustpix_io::{PixelMask, PixelMaskDetection};
+use std::path::PathBuf;
+
+use crate::roi_mask;
+
+/// Load a pixel mask.
+///
+/// Returns a dict with `dead`, `hot` and `mask` (dead or hot) boolean
+/// arrays of shape (height, width), `width`, `height`, `created` (UTC,
+/// ISO 8601), `source` (the data it was detected from, or None) and
+/// `detection`, a dict of `hot_sigma`, `hot_threshold`, `mean` and
+/// `std_dev`, or None for masks that were not detected.
+#[pyfunction]
+pub fn load_pixel_mask(py: Python<'_>, path: PathBuf) -> PyResult<PyObject> {
+    let mask = PixelMask::load(&path).map_err(|err| PyRuntimeError::new_err(err.to_string()))?;
+    let shape = [mask.height, mask.width];
+    let to_array = |flags: &[u8]| {
+        let flags: Vec<bool> = flags.iter().map(|&flag| flag != 0).collect();
+        PyArray1::from_vec(py, flags).reshape(shape)
+    };
+    let (dead, hot) = (mask.dead_flags(), mask.hot_flags());
+    let masked: Vec<u8> = dead.iter().zip(&hot).map(|(&d, &h)| d | h).collect();
+
+    let dict = PyDict::new(py);
+    dict.set_item("dead", to_array(&dead)?)?;
+    dict.set_item("hot", to_array(&hot)?)?;
+    dict.set_item("mask", to_array(&masked)?)?;
+    dict.set_item("width", mask.width)?;
+    dict.set_item("height", mask.height)?;
+    dict.set_item("created", &mask.created)?;
+    dict.set_item("source", &mask.source)?;
+    let detection = mask
+        .detection
+        .map(|detection| -> PyResult<_> {
+            let dict = PyDict::new(py);
+            dict.set_item("hot_sigma", detection.hot_sigma)?;
+            dict.set_item("hot_threshold", detection.hot_threshold)?;
+            dict.set_item("mean", detection.mean)?;
+            dict.set_item("std_dev", detection.std_dev)?;
+            Ok(dict)
+        })
+        .transpose()?;
+    dict.set_item("detection", detection)?;
+    Ok(dict.into_any().unbind())
+}
+
+/// Save a pixel mask from boolean `dead` and `hot` arrays of shape
+/// (height, width).
+///
+/// `path` ending in `.tif`/`.tiff` writes a 16-bit TIFF (0 good, 1 dead,
+/// 2 hot), anything else JSON. `detection` is an optional dict of
+/// `hot_sigma`, `hot_threshold`, `mean` and `std_dev` recording how the
+/// mask was found; `source` names the data it was found in.
+#[pyfunction]
+#[pyo3(signature = (path, dead, hot, detection=None, source=None))]
+pub fn save_pixel_mask(
+    path: PathBuf,
+    dead: PyReadonlyArray2<'_, bool>,
+    hot: PyReadonlyArray2<'_, bool>,
+    detection: Option<&Bound<'_, PyDict>>,
+    source: Option<String>,
+) -> PyResult<()> {
+    if dead.shape() != hot.shape() {
+        return Err(PyValueError::new_err(
+            "dead and hot must have the same shape",
+        ));
+    }
+    let [height, width] = [dead.shape()[0], dead.shape()[1]];
+    let flags = |array: &PyReadonlyArray2<'_, bool>| -> Vec<u8> {
+        array
+            .as_array()
+            .iter()
+            .map(|&flag| u8::from(flag))
+            .collect()
+    };
+    let detection = detection
+        .map(|dict| -> PyResult<_> {
+            let value = |key: &str| -> PyResult<f64> {
+                dict.get_item(key)?
+                    .ok_or_else(|| PyValueError::new_err(format!("detection has no '{key}'")))?
+                    .extract()
+            };
+            Ok(PixelMaskDetection {
+                hot_sigma: value("hot_sigma")?,
+                hot_threshold: value("hot_threshold")?,
+                mean: value("mean")?,
+                std_dev: value("std_dev")?,
+            })
+        })
+        .transpose()?;
+    let mut mask = PixelMask::from_flags(width, height, &flags(&dead), &flags(&hot), detection)
+        .map_err(|err| PyValueError::new_err(err.to_string()))?;
+    if let Some(source) = source {
+        mask = mask.with_source(source);
+    }
+    mask.save(&path)
+        .map_err(|err| PyRuntimeError::new_err(format!("{}: {err}", path.display())))
+}
+
+/// Drop the events on masked pixels from a dict of equal-length arrays.
+///
+/// `mask` is a boolean (height, width) array such as `load_pixel_mask(path)
+/// ["mask"]`; `arrays` is what `batch.to_numpy()` returns (or any dict with
+/// `x` and `y` arrays), and every array in it is filtered. Use `scale` when
+/// the events are on a finer grid than the mask, e.g. the super-resolution
+/// factor for neutrons.
+#[pyfunction]
+#[pyo3(signature = (arrays, mask, scale=1.0))]
+pub fn apply_pixel_mask(
+    py: Python<'_>,
+    arrays: &Bound<'_, PyDict>,
+    mask: PyReadonlyArray2<'_, bool>,
+    scale: f64,
+) -> PyResult<PyObject> {
+    let column = |name: &str| {
+        arrays
+            .get_item(name)?
+            .ok_or_else(|| PyValueError::new_err(format!("arrays has no '{name}' column")))
+    };
+    let masked = roi_mask::contains(py, &mask, &column("x")?, &column("y")?, scale)?;
+    let keep = masked.call_method0("__invert__")?;
+    let filtered = PyDict::new(py);
+    for (key, value) in arrays.iter() {
+        filtered.set_item(key, value.get_item(&keep)?)?;
+    }
+    Ok(filtered.into_any().unbind())
+}
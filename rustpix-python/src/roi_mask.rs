@@ -0,0 +1,292 @@
+//! ROI masks exported from the GUI ("Export ROI mask…").
+//!
+//! Masks come either as JSON shape lists, rasterized here with the same
+//! rules the GUI uses for ROI spectra (every pixel a rectangle overlaps,
+//! every pixel whose center lies inside a polygon), or as PNG masks where
+//! any non-zero pixel is inside.
+
+use numpy::{PyArray1, PyArrayMethods, PyReadonlyArray2, PyUntypedArrayMethods};
+use pyo3::exceptions::{PyRuntimeError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+use std::path::{Path, PathBuf};
+
+/// A mask laid out as `[y][x]`.
+struct Mask {
+    width: usize,
+    height: usize,
+    pixels: Vec<bool>,
+}
+
+/// Load a GUI-exported ROI mask as a boolean array of shape (height, width).
+///
+/// `path` is a `.json` shape file or a `.png` mask. With `shape`
+/// (height, width), the mask is produced at that size instead: JSON shapes
+/// are scaled to it before rasterizing, PNG masks are resampled
+/// nearest-neighbour. `roi` selects a single ROI by name from a JSON file;
+/// by default all ROIs are combined.
+#[pyfunction]
+#[pyo3(signature = (path, shape=None, roi=None))]
+pub fn load_roi_mask(
+    py: Python<'_>,
+    path: PathBuf,
+    shape: Option<(usize, usize)>,
+    roi: Option<&str>,
+) -> PyResult<PyObject> {
+    let is_png = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("png"));
+    let mask = if is_png {
+        if roi.is_some() {
+            return Err(PyValueError::new_err(
+                "roi= selects a shape from a JSON file; PNG masks have no names",
+            ));
+        }
+        load_png(&path, shape)?
+    } else {
+        load_json(&path, shape, roi)?
+    };
+    let array = PyArray1::from_vec(py, mask.pixels).reshape([mask.height, mask.width])?;
+    Ok(array.into_any().unbind())
+}
+
+/// Return a boolean array telling which events fall inside `mask`.
+///
+/// Event `i` is inside if pixel `(floor(y[i] / scale), floor(x[i] / scale))`
+/// of the mask is set; events outside the mask's extent are not. Use
+/// `scale` when the events are on a finer grid than the mask, e.g. the
+/// super-resolution factor for neutrons against a mask drawn on hits.
+#[pyfunction]
+#[pyo3(signature = (mask, x, y, scale=1.0))]
+pub fn roi_mask_contains(
+    py: Python<'_>,
+    mask: PyReadonlyArray2<'_, bool>,
+    x: &Bound<'_, PyAny>,
+    y: &Bound<'_, PyAny>,
+    scale: f64,
+) -> PyResult<PyObject> {
+    Ok(contains(py, &mask, x, y, scale)?.into_any().unbind())
+}
+
+/// Keep only the events inside `mask` from a dict of equal-length arrays.
+///
+/// `arrays` is what `batch.to_numpy()` returns (or any dict with `x` and
+/// `y` arrays); every array in it is filtered. See `roi_mask_contains` for
+/// `scale`.
+#[pyfunction]
+#[pyo3(signature = (arrays, mask, scale=1.0))]
+pub fn apply_roi_mask(
+    py: Python<'_>,
+    arrays: &Bound<'_, PyDict>,
+    mask: PyReadonlyArray2<'_, bool>,
+    scale: f64,
+) -> PyResult<PyObject> {
+    let column = |name: &str| {
+        arrays
+            .get_item(name)?
+            .ok_or_else(|| PyValueError::new_err(format!("arrays has no '{name}' column")))
+    };
+    let inside = contains(py, &mask, &column("x")?, &column("y")?, scale)?;
+    let filtered = PyDict::new(py);
+    for (key, value) in arrays.iter() {
+        filtered.set_item(key, value.get_item(&inside)?)?;
+    }
+    Ok(filtered.into_any().unbind())
+}
+
+/// Whether each event `(x[i], y[i])` falls on a set pixel of `mask`.
+pub(crate) fn contains<'py>(
+    py: Python<'py>,
+    mask: &PyReadonlyArray2<'py, bool>,
+    x: &Bound<'py, PyAny>,
+    y: &Bound<'py, PyAny>,
+    scale: f64,
+) -> PyResult<Bound<'py, PyArray1<bool>>> {
+    if !(scale.is_finite() && scale > 0.0) {
+        return Err(PyValueError::new_err("scale must be positive"));
+    }
+    let numpy = py.import("numpy")?;
+    let as_f64 = |values: &Bound<'py, PyAny>| -> PyResult<Vec<f64>> {
+        let array = numpy.call_method1("asarray", (values, "float64"))?;
+        Ok(array
+            .downcast::<PyArray1<f64>>()?
+            .readonly()
+            .as_slice()?
+            .to_vec())
+    };
+    let (x, y) = (as_f64(x)?, as_f64(y)?);
+    if x.len() != y.len() {
+        return Err(PyValueError::new_err("x and y must have the same length"));
+    }
+    let [height, width] = [mask.shape()[0], mask.shape()[1]];
+    let mask = mask.as_array();
+    let inside: Vec<bool> = x
+        .iter()
+        .zip(&y)
+        .map(|(&x, &y)| {
+            let (column, row) = ((x / scale).floor(), (y / scale).floor());
+            pixel_index(column, width)
+                .zip(pixel_index(row, height))
+                .is_some_and(|(column, row)| mask[[row, column]])
+        })
+        .collect();
+    Ok(PyArray1::from_vec(py, inside))
+}
+
+/// `value` as an index below `limit`, if it is one.
+fn pixel_index(value: f64, limit: usize) -> Option<usize> {
+    #[allow(clippy::cast_precision_loss)]
+    let in_range = value >= 0.0 && value < limit as f64;
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    in_range.then(|| value as usize)
+}
+
+fn load_png(path: &Path, shape: Option<(usize, usize)>) -> PyResult<Mask> {
+    let image = image::open(path)
+        .map_err(|err| PyRuntimeError::new_err(format!("{}: {err}", path.display())))?
+        .into_luma8();
+    let (width, height) = (image.width() as usize, image.height() as usize);
+    let (out_height, out_width) = shape.unwrap_or((height, width));
+    let mut pixels = Vec::with_capacity(out_width * out_height);
+    for row in 0..out_height {
+        let src_y = row * height / out_height.max(1);
+        for column in 0..out_width {
+            let src_x = column * width / out_width.max(1);
+            #[allow(clippy::cast_possible_truncation)]
+            let value = image.get_pixel(src_x as u32, src_y as u32).0[0];
+            pixels.push(value > 0);
+        }
+    }
+    Ok(Mask {
+        width: out_width,
+        height: out_height,
+        pixels,
+    })
+}
+
+fn load_json(path: &Path, shape: Option<(usize, usize)>, roi: Option<&str>) -> PyResult<Mask> {
+    let invalid = |message: String| PyValueError::new_err(format!("{}: {message}", path.display()));
+    let text = std::fs::read_to_string(path)
+        .map_err(|err| PyRuntimeError::new_err(format!("{}: {err}", path.display())))?;
+    let doc: serde_json::Value =
+        serde_json::from_str(&text).map_err(|err| invalid(err.to_string()))?;
+    let dimension = |key: &str| {
+        doc[key]
+            .as_u64()
+            .and_then(|value| usize::try_from(value).ok())
+            .ok_or_else(|| invalid(format!("missing '{key}'")))
+    };
+    let (width, height) = (dimension("width")?, dimension("height")?);
+    let (out_height, out_width) = shape.unwrap_or((height, width));
+    #[allow(clippy::cast_precision_loss)]
+    let (sx, sy) = (
+        out_width as f64 / width.max(1) as f64,
+        out_height as f64 / height.max(1) as f64,
+    );
+
+    let rois = doc["rois"]
+        .as_array()
+        .ok_or_else(|| invalid("missing 'rois'".to_string()))?;
+    let selected: Vec<&serde_json::Value> = rois
+        .iter()
+        .filter(|entry| roi.is_none_or(|name| entry["name"].as_str() == Some(name)))
+        .collect();
+    if let (Some(name), true) = (roi, selected.is_empty()) {
+        return Err(invalid(format!("no ROI named '{name}'")));
+    }
+
+    let mut mask = Mask {
+        width: out_width,
+        height: out_height,
+        pixels: vec![false; out_width * out_height],
+    };
+    for entry in selected {
+        let number = |key: &str| {
+            entry[key]
+                .as_f64()
+                .ok_or_else(|| invalid(format!("ROI field '{key}' is not a number")))
+        };
+        match entry["kind"].as_str() {
+            Some("rectangle") => mask.fill_rect(
+                number("x1")? * sx,
+                number("y1")? * sy,
+                number("x2")? * sx,
+                number("y2")? * sy,
+            ),
+            Some("polygon") => {
+                let vertices = entry["vertices"]
+                    .as_array()
+                    .ok_or_else(|| invalid("polygon without 'vertices'".to_string()))?
+                    .iter()
+                    .map(|vertex| match (vertex[0].as_f64(), vertex[1].as_f64()) {
+                        (Some(x), Some(y)) => Ok((x * sx, y * sy)),
+                        _ => Err(invalid("polygon vertex is not [x, y]".to_string())),
+                    })
+                    .collect::<PyResult<Vec<_>>>()?;
+                mask.fill_polygon(&vertices);
+            }
+            _ => return Err(invalid("unknown ROI kind".to_string())),
+        }
+    }
+    Ok(mask)
+}
+
+impl Mask {
+    fn fill_rect(&mut self, x1: f64, y1: f64, x2: f64, y2: f64) {
+        let (x_start, x_end) = span(x1, x2, self.width);
+        let (y_start, y_end) = span(y1, y2, self.height);
+        for y in y_start..y_end {
+            self.pixels[y * self.width + x_start..y * self.width + x_end].fill(true);
+        }
+    }
+
+    fn fill_polygon(&mut self, vertices: &[(f64, f64)]) {
+        if vertices.len() < 3 {
+            return;
+        }
+        let fold = |pick: fn(&(f64, f64)) -> f64| {
+            vertices
+                .iter()
+                .map(pick)
+                .fold((f64::INFINITY, f64::NEG_INFINITY), |(lo, hi), v| {
+                    (lo.min(v), hi.max(v))
+                })
+        };
+        let (min_x, max_x) = fold(|v| v.0);
+        let (min_y, max_y) = fold(|v| v.1);
+        let (x_start, x_end) = span(min_x, max_x, self.width);
+        let (y_start, y_end) = span(min_y, max_y, self.height);
+        for y in y_start..y_end {
+            for x in x_start..x_end {
+                #[allow(clippy::cast_precision_loss)]
+                let center = (x as f64 + 0.5, y as f64 + 0.5);
+                if polygon_contains(center, vertices) {
+                    self.pixels[y * self.width + x] = true;
+                }
+            }
+        }
+    }
+}
+
+/// Pixel range `[start, end)` covered by `a..b`, clamped to `0..limit`.
+fn span(a: f64, b: f64, limit: usize) -> (usize, usize) {
+    #[allow(clippy::cast_precision_loss)]
+    let max = limit as f64;
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    let clamp = |value: f64| value.clamp(0.0, max) as usize;
+    (clamp(a.min(b).floor()), clamp(a.max(b).ceil()))
+}
+
+fn polygon_contains((x, y): (f64, f64), vertices: &[(f64, f64)]) -> bool {
+    let mut inside = false;
+    let mut j = vertices.len() - 1;
+    for (i, &(xi, yi)) in vertices.iter().enumerate() {
+        let (xj, yj) = vertices[j];
+        if (yi > y) != (yj > y) && x < (xj - xi) * (y - yi) / (yj - yi) + xi {
+            inside = !inside;
+        }
+        j = i;
+    }
+    inside
+}
@@ -0,0 +1,83 @@
+"""ipywidgets viewer behind ``rustpix.Hyperstack.show()``.
+
+Embedded in the extension module and loaded on first use, so importing
+rustpix does not require ipywidgets or matplotlib.
+"""
+
+import ipywidgets as widgets
+import matplotlib.pyplot as plt
+from IPython.display import display
+from matplotlib.patches import Rectangle
+
+
+def show(stack):
+    """Image over a TOF bin range next to the spectrum of an ROI box."""
+    n_bins, height, width = stack.shape
+    edges = stack.tof_edges_ns()
+    centers_us = (edges[:-1] + edges[1:]) / 2000.0
+
+    tof_range = widgets.IntRangeSlider(
+        value=(0, n_bins),
+        min=0,
+        max=n_bins,
+        description="TOF bins",
+        continuous_update=False,
+        layout=widgets.Layout(width="95%"),
+    )
+    roi_x = widgets.IntRangeSlider(
+        value=(0, width), min=0, max=width, description="ROI x", continuous_update=False
+    )
+    roi_y = widgets.IntRangeSlider(
+        value=(0, height), min=0, max=height, description="ROI y", continuous_update=False
+    )
+    log_scale = widgets.Checkbox(value=False, description="Log spectrum")
+    output = widgets.Output()
+
+    with plt.ioff():
+        fig, (image_ax, spectrum_ax) = plt.subplots(1, 2, figsize=(11, 4.5))
+    image = image_ax.imshow(stack.image(), origin="lower", cmap="viridis")
+    fig.colorbar(image, ax=image_ax, label="counts")
+    box = Rectangle((0, 0), width, height, fill=False, edgecolor="red", linewidth=1.5)
+    image_ax.add_patch(box)
+    image_ax.set_xlabel("x (pixel)")
+    image_ax.set_ylabel("y (pixel)")
+    (line,) = spectrum_ax.step(centers_us, stack.spectrum(), where="mid")
+    bands = [spectrum_ax.axvspan(edges[0] / 1000.0, edges[-1] / 1000.0, alpha=0.15)]
+    spectrum_ax.set_xlabel("TOF (µs)")
+    spectrum_ax.set_ylabel("counts in ROI")
+
+    def update_image(_change=None):
+        first, last = tof_range.value
+        counts = stack.image((first, last))
+        image.set_data(counts)
+        image.set_clim(0, max(int(counts.max()), 1))
+        bands.pop().remove()
+        bands.append(spectrum_ax.axvspan(edges[first] / 1000.0, edges[last] / 1000.0, alpha=0.15))
+        image_ax.set_title(f"TOF {edges[first] / 1000:.1f}–{edges[last] / 1000:.1f} µs")
+        _redraw(output, fig)
+
+    def update_spectrum(_change=None):
+        (x0, x1), (y0, y1) = roi_x.value, roi_y.value
+        counts = stack.spectrum((x0, y0, x1, y1))
+        line.set_ydata(counts)
+        box.set_bounds(x0 - 0.5, y0 - 0.5, x1 - x0, y1 - y0)
+        spectrum_ax.set_yscale("log" if log_scale.value else "linear")
+        spectrum_ax.set_ylim(0.5 if log_scale.value else 0, max(int(counts.max()), 1) * 1.1)
+        spectrum_ax.set_title(f"ROI x {x0}–{x1}, y {y0}–{y1}")
+        _redraw(output, fig)
+
+    tof_range.observe(update_image, names="value")
+    roi_x.observe(update_spectrum, names="value")
+    roi_y.observe(update_spectrum, names="value")
+    log_scale.observe(update_spectrum, names="value")
+    update_image()
+    update_spectrum()
+
+    return widgets.VBox([tof_range, widgets.HBox([roi_x, roi_y, log_scale]), output])
+
+
+def _redraw(output, fig):
+    """Replace the rendered figure in ``output``."""
+    output.clear_output(wait=True)
+    with output:
+        display(fig)
@@ -0,0 +1,122 @@
+//! Time-of-flight to neutron energy and wavelength.
+//!
+//! A neutron detected `tof` after the pulse, over a flight path `L`, has
+//! travelled for `tof - offset`, where the offset is the delay between the
+//! TDC and the neutrons leaving the moderator. Its speed `v = L / t` gives
+//! the energy `E = m v² / 2` and the de Broglie wavelength `λ = h / (m v)`.
+//! [`crate::DetectorConfig`] carries `L` and the offset so these axes can be
+//! reproduced from the config alone.
+
+/// Neutron mass in kilograms.
+pub const NEUTRON_MASS_KG: f64 = 1.674_927_498e-27;
+/// Elementary charge in joules per eV.
+pub const EV_J: f64 = 1.602_176_634e-19;
+/// Planck constant in joule seconds.
+pub const PLANCK_J_S: f64 = 6.626_070_15e-34;
+
+/// Flight time in seconds of a neutron detected at `tof_us`, if positive.
+fn flight_time_s(tof_us: f64, flight_path_m: f64, tof_offset_ns: f64) -> Option<f64> {
+    if !tof_us.is_finite() || !flight_path_m.is_finite() || flight_path_m <= 0.0 {
+        return None;
+    }
+    let t_us = tof_us - tof_offset_ns / 1000.0;
+    (t_us > 0.0).then_some(t_us * 1e-6)
+}
+
+/// Convert TOF (µs) to neutron energy (eV).
+///
+/// Returns `None` if the input is invalid or results in non-physical values.
+#[must_use]
+pub fn tof_us_to_energy_ev(tof_us: f64, flight_path_m: f64, tof_offset_ns: f64) -> Option<f64> {
+    let time_seconds = flight_time_s(tof_us, flight_path_m, tof_offset_ns)?;
+    let v = flight_path_m / time_seconds;
+    let e_j = 0.5 * NEUTRON_MASS_KG * v * v;
+    Some(e_j / EV_J)
+}
+
+/// Convert neutron energy (eV) to TOF (µs).
+///
+/// Returns `None` if the input is invalid or results in non-physical values.
+#[must_use]
+pub fn energy_ev_to_tof_us(energy_ev: f64, flight_path_m: f64, tof_offset_ns: f64) -> Option<f64> {
+    if !energy_ev.is_finite()
+        || energy_ev <= 0.0
+        || !flight_path_m.is_finite()
+        || flight_path_m <= 0.0
+    {
+        return None;
+    }
+    let e_j = energy_ev * EV_J;
+    let time_seconds = flight_path_m * (NEUTRON_MASS_KG / (2.0 * e_j)).sqrt();
+    let offset_us = tof_offset_ns / 1000.0;
+    Some(time_seconds * 1e6 + offset_us)
+}
+
+/// Convert TOF (µs) to neutron wavelength (Å).
+///
+/// Returns `None` if the input is invalid or results in non-physical values.
+#[must_use]
+pub fn tof_us_to_wavelength_angstrom(
+    tof_us: f64,
+    flight_path_m: f64,
+    tof_offset_ns: f64,
+) -> Option<f64> {
+    let time_seconds = flight_time_s(tof_us, flight_path_m, tof_offset_ns)?;
+    Some(PLANCK_J_S * time_seconds / (NEUTRON_MASS_KG * flight_path_m) * 1e10)
+}
+
+/// Convert neutron wavelength (Å) to TOF (µs).
+///
+/// Returns `None` if the input is invalid or results in non-physical values.
+#[must_use]
+pub fn wavelength_angstrom_to_tof_us(
+    wavelength_angstrom: f64,
+    flight_path_m: f64,
+    tof_offset_ns: f64,
+) -> Option<f64> {
+    if !wavelength_angstrom.is_finite()
+        || wavelength_angstrom <= 0.0
+        || !flight_path_m.is_finite()
+        || flight_path_m <= 0.0
+    {
+        return None;
+    }
+    let time_seconds = wavelength_angstrom * 1e-10 * NEUTRON_MASS_KG * flight_path_m / PLANCK_J_S;
+    Some(time_seconds * 1e6 + tof_offset_ns / 1000.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_relative_eq;
+
+    #[test]
+    fn test_thermal_neutron() {
+        // 2200 m/s: 25.3 meV and 1.798 Å; 25 m takes 11.36 ms.
+        let tof_us = 25.0 / 2200.0 * 1e6 + 1.5;
+        let energy = tof_us_to_energy_ev(tof_us, 25.0, 1_500.0).unwrap();
+        assert_relative_eq!(energy, 0.025_30, max_relative = 1e-3);
+        let wavelength = tof_us_to_wavelength_angstrom(tof_us, 25.0, 1_500.0).unwrap();
+        assert_relative_eq!(wavelength, 1.798, max_relative = 1e-3);
+
+        assert_relative_eq!(
+            energy_ev_to_tof_us(energy, 25.0, 1_500.0).unwrap(),
+            tof_us,
+            max_relative = 1e-12
+        );
+        assert_relative_eq!(
+            wavelength_angstrom_to_tof_us(wavelength, 25.0, 1_500.0).unwrap(),
+            tof_us,
+            max_relative = 1e-12
+        );
+    }
+
+    #[test]
+    fn test_rejects_non_physical_input() {
+        assert!(tof_us_to_energy_ev(1_000.0, 0.0, 0.0).is_none());
+        assert!(tof_us_to_energy_ev(1.0, 25.0, 2_000.0).is_none());
+        assert!(tof_us_to_wavelength_angstrom(f64::NAN, 25.0, 0.0).is_none());
+        assert!(energy_ev_to_tof_us(-1.0, 25.0, 0.0).is_none());
+        assert!(wavelength_angstrom_to_tof_us(1.0, f64::INFINITY, 0.0).is_none());
+    }
+}
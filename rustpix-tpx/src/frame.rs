@@ -0,0 +1,231 @@
+//! Frame-based (shutter) readout.
+//!
+//! In frame mode the chips integrate over a shutter window and then read out
+//! the whole pixel matrix as 0xA* pixel packets, followed by an
+//! end-of-readout packet. Each pixel packet carries the value integrated
+//! over the frame (event count or integrated `ToT`) rather than a single
+//! hit, so frames are turned into images instead of hit lists.
+//!
+//! Frames are counted per chip: the n-th frame of every chip belongs to the
+//! same shutter window, so they are combined into one detector image using
+//! the chip transforms.
+
+use crate::packet::Tpx3Packet;
+use crate::section::Tpx3Section;
+use crate::{ChipTransform, DetectorConfig};
+
+const PACKET_SIZE: usize = 8;
+
+/// Value accumulated per pixel in a frame image.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum FrameValue {
+    /// 10-bit event count (event count & iToT mode).
+    #[default]
+    EventCount,
+    /// 14-bit integrated `ToT` (event count & iToT mode).
+    IntegratedTot,
+    /// Number of pixel packets, whatever the acquisition mode.
+    Packets,
+}
+
+impl FrameValue {
+    fn of(self, packet: Tpx3Packet) -> u32 {
+        match self {
+            Self::EventCount => u32::from(packet.event_count()),
+            Self::IntegratedTot => u32::from(packet.integrated_tot()),
+            Self::Packets => 1,
+        }
+    }
+}
+
+/// One frame as a detector image.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FrameImage {
+    /// Frame number, starting at 0.
+    pub index: usize,
+    /// Image width in pixels.
+    pub width: usize,
+    /// Image height in pixels.
+    pub height: usize,
+    /// Pixel packets in the frame.
+    pub pixel_packets: usize,
+    /// Values laid out as `[y][x]`.
+    pub values: Vec<u32>,
+}
+
+impl FrameImage {
+    fn new(index: usize, width: usize, height: usize) -> Self {
+        Self {
+            index,
+            width,
+            height,
+            pixel_packets: 0,
+            values: vec![0; width * height],
+        }
+    }
+
+    /// Sum of all pixel values.
+    #[must_use]
+    pub fn total(&self) -> u64 {
+        self.values.iter().map(|&value| u64::from(value)).sum()
+    }
+}
+
+/// Packet counts used to tell frame-mode files from data-driven ones.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct FramePacketCounts {
+    /// Frame-based pixel packets (0xA*).
+    pub frame_pixels: usize,
+    /// Data-driven hit packets (0xB*).
+    pub hits: usize,
+    /// End-of-readout packets.
+    pub end_of_readout: usize,
+}
+
+impl FramePacketCounts {
+    /// Returns true if the data is mostly frame-based pixel packets.
+    #[must_use]
+    pub fn is_frame_mode(&self) -> bool {
+        self.frame_pixels > self.hits
+    }
+}
+
+/// Count frame-based pixel, hit and end-of-readout packets in `sections`.
+#[must_use]
+pub fn count_frame_packets(data: &[u8], sections: &[Tpx3Section]) -> FramePacketCounts {
+    let mut counts = FramePacketCounts::default();
+    for packet in section_packets(data, sections).map(|(_, packet)| packet) {
+        if packet.is_frame_pixel() {
+            counts.frame_pixels += 1;
+        } else if packet.is_hit() {
+            counts.hits += 1;
+        } else if packet.is_end_of_readout() {
+            counts.end_of_readout += 1;
+        }
+    }
+    counts
+}
+
+/// Build one detector image per frame from frame-based pixel packets.
+///
+/// Pixel packets after a chip's last end-of-readout form a final frame.
+/// Frames in which a chip read out nothing are kept as empty images, so
+/// frame indices match shutter windows. Pixels outside the chip size or the
+/// detector are skipped.
+#[must_use]
+pub fn read_frames(
+    data: &[u8],
+    sections: &[Tpx3Section],
+    config: &DetectorConfig,
+    value: FrameValue,
+) -> Vec<FrameImage> {
+    let (width, height) = config.detector_dimensions();
+    let transforms: Vec<ChipTransform> = (0..=u8::MAX)
+        .map(|chip_id| config.chip_transform(chip_id))
+        .collect();
+    let mut chip_frame = [0usize; 256];
+    let mut frames: Vec<FrameImage> = Vec::new();
+    let ensure = |frames: &mut Vec<FrameImage>, index: usize| {
+        while frames.len() <= index {
+            frames.push(FrameImage::new(frames.len(), width, height));
+        }
+    };
+
+    for (chip_id, packet) in section_packets(data, sections) {
+        let chip = usize::from(chip_id);
+        if packet.is_end_of_readout() {
+            ensure(&mut frames, chip_frame[chip]);
+            chip_frame[chip] += 1;
+            continue;
+        }
+        if !packet.is_frame_pixel() {
+            continue;
+        }
+        let (local_x, local_y) = packet.pixel_coordinates();
+        if local_x >= config.chip_size_x || local_y >= config.chip_size_y {
+            continue;
+        }
+        ensure(&mut frames, chip_frame[chip]);
+        let frame = &mut frames[chip_frame[chip]];
+        frame.pixel_packets += 1;
+        let (x, y) = transforms[chip].apply(local_x, local_y);
+        let (x, y) = (usize::from(x), usize::from(y));
+        if x < width && y < height {
+            let pixel = &mut frame.values[y * width + x];
+            *pixel = pixel.saturating_add(value.of(packet));
+        }
+    }
+    frames
+}
+
+fn section_packets<'a>(
+    data: &'a [u8],
+    sections: &'a [Tpx3Section],
+) -> impl Iterator<Item = (u8, Tpx3Packet)> + 'a {
+    sections.iter().flat_map(move |section| {
+        data[section.start_offset..section.end_offset]
+            .chunks_exact(PACKET_SIZE)
+            .map(move |chunk| {
+                let mut bytes = [0u8; PACKET_SIZE];
+                bytes.copy_from_slice(chunk);
+                (section.chip_id, Tpx3Packet::from_bytes(bytes))
+            })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::section::discover_sections;
+
+    fn make_header(chip_id: u8) -> u64 {
+        Tpx3Packet::TPX3_HEADER_MAGIC | (u64::from(chip_id) << 32)
+    }
+
+    /// Frame pixel at local (0, 0) with the given iToT and event count.
+    fn make_frame_pixel(itot: u16, count: u16) -> u64 {
+        0xA000_0000_0000_0000 | (u64::from(itot) << 30) | (u64::from(count) << 20)
+    }
+
+    const END_OF_READOUT: u64 = 0x71A0_0000_0000_0000;
+
+    #[test]
+    fn test_read_frames_per_chip_and_value() {
+        let packets = [
+            make_header(3),
+            make_frame_pixel(100, 4),
+            END_OF_READOUT,
+            // Chip 3 reads out nothing in frame 1.
+            END_OF_READOUT,
+            make_frame_pixel(7, 1),
+            make_header(0),
+            make_frame_pixel(50, 2),
+            END_OF_READOUT,
+            make_frame_pixel(60, 3),
+            END_OF_READOUT,
+        ];
+        let data: Vec<u8> = packets.iter().flat_map(|p| p.to_le_bytes()).collect();
+        let sections = discover_sections(&data);
+
+        let counts = count_frame_packets(&data, &sections);
+        assert_eq!(counts.frame_pixels, 4);
+        assert_eq!(counts.end_of_readout, 4);
+        assert!(counts.is_frame_mode());
+
+        let config = DetectorConfig::venus_defaults();
+        let frames = read_frames(&data, &sections, &config, FrameValue::EventCount);
+        assert_eq!(frames.len(), 3);
+        let width = frames[0].width;
+        // Chip 3 is identity, chip 0 is shifted by 258 in x.
+        assert_eq!(frames[0].values[0], 4);
+        assert_eq!(frames[0].values[258], 2);
+        assert_eq!(frames[1].total(), 3);
+        assert_eq!(frames[1].values[258], 3);
+        assert_eq!(frames[2].pixel_packets, 1);
+        assert_eq!(frames[2].values[0], 1);
+        assert_eq!(frames[0].values.len(), width * frames[0].height);
+
+        let itot = read_frames(&data, &sections, &config, FrameValue::IntegratedTot);
+        assert_eq!(itot[0].total(), 150);
+    }
+}
@@ -41,6 +41,47 @@ pub fn calculate_tof(timestamp: u32, tdc_timestamp: u32, tdc_correction_25ns: u3
     }
 }
 
+/// Fine `ToA` steps per coarse 25 ns tick (1.5625 ns each).
+pub const FINE_TOA_STEPS: i64 = 16;
+
+/// Apply a timing offset to a hit's coarse timestamp using its fine `ToA`.
+///
+/// The hit time `coarse · 16 − fine_toa − offset` (in 1.5625 ns steps) is
+/// rounded to the nearest 25 ns tick and wrapped to the 30-bit timestamp
+/// range, so [`correct_timestamp_rollover`] still applies afterwards.
+#[inline]
+#[must_use]
+pub fn correct_toa(timestamp_coarse: u32, fine_toa: u8, offset_steps: i64) -> u32 {
+    const TIMESTAMP_RANGE: i64 = 1 << 30;
+    let fine = i64::from(timestamp_coarse) * FINE_TOA_STEPS - i64::from(fine_toa) - offset_steps;
+    let ticks = (fine + FINE_TOA_STEPS / 2).div_euclid(FINE_TOA_STEPS);
+    u32::try_from(ticks.rem_euclid(TIMESTAMP_RANGE)).unwrap_or(0)
+}
+
+/// Duration of one coarse `ToA` (and TOF) tick in nanoseconds.
+pub const TOA_TICK_NS: u64 = 25;
+
+/// Duration of one `ToT` count in nanoseconds.
+pub const TOT_TICK_NS: u32 = 25;
+
+/// Largest `ToT` count the 10-bit register holds; hits at this value have
+/// saturated and their true `ToT` is unknown.
+pub const TOT_SATURATED: u16 = 0x3FF;
+
+/// Convert a `ToA` / TOF value from 25 ns ticks to nanoseconds.
+#[inline]
+#[must_use]
+pub fn toa_ticks_to_ns(ticks: u32) -> u64 {
+    u64::from(ticks) * TOA_TICK_NS
+}
+
+/// Convert a `ToT` value from register counts to nanoseconds.
+#[inline]
+#[must_use]
+pub fn tot_counts_to_ns(counts: u16) -> u32 {
+    u32::from(counts) * TOT_TICK_NS
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -66,4 +107,25 @@ mod tests {
         let tof = calculate_tof(timestamp, tdc_timestamp, correction);
         assert_eq!(tof, 500);
     }
+
+    #[test]
+    fn test_correct_toa() {
+        // No fine time and no offset keeps the coarse tick.
+        assert_eq!(correct_toa(1000, 0, 0), 1000);
+        // Fine ToA counts back from the coarse edge: 9 steps is past half a tick.
+        assert_eq!(correct_toa(1000, 7, 0), 1000);
+        assert_eq!(correct_toa(1000, 9, 0), 999);
+        // A 20 ns (12.8 step) offset moves the hit one tick earlier.
+        assert_eq!(correct_toa(1000, 0, 13), 999);
+        assert_eq!(correct_toa(1000, 0, -13), 1001);
+        // Corrections wrap within the 30-bit timestamp range.
+        assert_eq!(correct_toa(0, 0, 16), (1 << 30) - 1);
+    }
+
+    #[test]
+    fn test_tick_conversion() {
+        assert_eq!(toa_ticks_to_ns(666_667), 16_666_675);
+        assert_eq!(toa_ticks_to_ns(u32::MAX), u64::from(u32::MAX) * 25);
+        assert_eq!(tot_counts_to_ns(1023), 25_575);
+    }
 }
@@ -0,0 +1,242 @@
+//! Registry of named instrument profiles.
+//!
+//! A profile bundles the [`DetectorConfig`] of an instrument (TDC frequency,
+//! chip layout and transforms, and the flight path for energy and wavelength
+//! conversion where it is fixed) with the pixel pitch for image calibration.
+//! The profiles shipped with the crate live as JSON files in
+//! `rustpix-tpx/instruments/` and are compiled in, so `--instrument venus`
+//! in the CLI, the GUI's detector menu and
+//! `DetectorConfig.from_instrument("venus")` in Python all agree.
+//!
+//! Facilities add theirs by dropping a `<name>.json` file next to the others
+//! and listing it in `BUILTIN` (see `instruments/README.md`); until then a
+//! profile file can be used directly with [`InstrumentProfile::lookup`].
+
+use std::fs;
+use std::path::Path;
+use std::sync::OnceLock;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{DetectorConfig, JsonConfig, JsonDetector};
+
+/// Profiles shipped with the crate, as `(name, JSON)`.
+const BUILTIN: &[(&str, &str)] = &[
+    ("venus", include_str!("../instruments/venus.json")),
+    ("snap", include_str!("../instruments/snap.json")),
+    ("mars", include_str!("../instruments/mars.json")),
+    ("odin", include_str!("../instruments/odin.json")),
+];
+
+/// A named instrument: its detector configuration and beamline constants.
+#[derive(Clone, Debug)]
+pub struct InstrumentProfile {
+    /// Registry key: lowercase letters, digits, `-` and `_` (e.g. `venus`).
+    pub name: String,
+    /// Display name (e.g. `VENUS`).
+    pub title: String,
+    /// Facility the instrument belongs to (e.g. `SNS`).
+    pub facility: String,
+    /// One-line description.
+    pub description: String,
+    /// Pixel pitch in millimetres.
+    pub pixel_pitch_mm: f64,
+    /// Detector configuration (TDC frequency, chip layout and transforms,
+    /// and the flight path if fixed for the instrument).
+    pub detector: DetectorConfig,
+}
+
+#[derive(Deserialize, Serialize)]
+struct JsonProfile {
+    name: String,
+    title: String,
+    facility: String,
+    #[serde(default)]
+    description: String,
+    pixel_pitch_mm: f64,
+    detector: JsonDetector,
+}
+
+impl InstrumentProfile {
+    /// Label shown in menus, e.g. `VENUS (SNS)`.
+    #[must_use]
+    pub fn label(&self) -> String {
+        format!("{} ({})", self.title, self.facility)
+    }
+
+    /// Parse a profile from JSON.
+    ///
+    /// The `detector` section uses the detector config schema of
+    /// [`DetectorConfig::from_json`].
+    ///
+    /// # Errors
+    /// Returns an error if the JSON is invalid or a value is out of range.
+    pub fn from_json(json: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let profile: JsonProfile = serde_json::from_str(json)?;
+        let detector = DetectorConfig::from_json_config(JsonConfig {
+            detector: profile.detector,
+        })?;
+        let profile = Self {
+            name: profile.name,
+            title: profile.title,
+            facility: profile.facility,
+            description: profile.description,
+            pixel_pitch_mm: profile.pixel_pitch_mm,
+            detector,
+        };
+        profile.validate()?;
+        Ok(profile)
+    }
+
+    /// Load a profile from a JSON file.
+    ///
+    /// # Errors
+    /// Returns an error if the file cannot be read or is not a valid profile.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, Box<dyn std::error::Error>> {
+        Self::from_json(&fs::read_to_string(path)?)
+    }
+
+    /// Serialize the profile to JSON, in the format of the shipped files.
+    ///
+    /// # Errors
+    /// Returns an error if serialization fails.
+    pub fn to_json_string(&self) -> Result<String, Box<dyn std::error::Error>> {
+        let profile = JsonProfile {
+            name: self.name.clone(),
+            title: self.title.clone(),
+            facility: self.facility.clone(),
+            description: self.description.clone(),
+            pixel_pitch_mm: self.pixel_pitch_mm,
+            detector: self.detector.to_json_detector()?,
+        };
+        Ok(serde_json::to_string_pretty(&profile)?)
+    }
+
+    /// Find a shipped profile by name, or else load `spec` as a profile
+    /// file.
+    ///
+    /// # Errors
+    /// Returns an error naming the shipped profiles if `spec` is neither a
+    /// shipped profile nor an existing file, or if the file is invalid.
+    pub fn lookup(spec: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        if let Some(profile) = find(spec) {
+            return Ok(profile.clone());
+        }
+        let path = Path::new(spec);
+        if path.is_file() {
+            return Self::from_file(path).map_err(|err| format!("{spec}: {err}").into());
+        }
+        Err(format!(
+            "unknown instrument '{spec}' (available: {})",
+            names().collect::<Vec<_>>().join(", ")
+        )
+        .into())
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        let valid_name = !self.name.is_empty()
+            && self
+                .name
+                .chars()
+                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_');
+        if !valid_name {
+            return Err(format!(
+                "instrument name '{}' must be lowercase letters, digits, '-' or '_'",
+                self.name
+            ));
+        }
+        if !(self.pixel_pitch_mm.is_finite() && self.pixel_pitch_mm > 0.0) {
+            return Err(format!("{}: pixel_pitch_mm must be positive", self.name));
+        }
+        let frequency = self.detector.tdc_frequency_hz;
+        if !(frequency.is_finite() && frequency > 0.0) {
+            return Err(format!("{}: tdc_frequency_hz must be positive", self.name));
+        }
+        Ok(())
+    }
+}
+
+/// All profiles shipped with the crate, in registry order.
+///
+/// # Panics
+/// Panics if a shipped profile file is invalid (caught by the tests).
+#[must_use]
+pub fn profiles() -> &'static [InstrumentProfile] {
+    static PROFILES: OnceLock<Vec<InstrumentProfile>> = OnceLock::new();
+    PROFILES.get_or_init(|| {
+        BUILTIN
+            .iter()
+            .map(|(name, json)| {
+                InstrumentProfile::from_json(json)
+                    .unwrap_or_else(|err| panic!("shipped instrument profile {name}: {err}"))
+            })
+            .collect()
+    })
+}
+
+/// Names of the shipped profiles, in registry order.
+pub fn names() -> impl Iterator<Item = &'static str> {
+    BUILTIN.iter().map(|(name, _)| *name)
+}
+
+/// The shipped profile called `name` (case-insensitive).
+#[must_use]
+pub fn find(name: &str) -> Option<&'static InstrumentProfile> {
+    profiles()
+        .iter()
+        .find(|profile| profile.name.eq_ignore_ascii_case(name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shipped_profiles_are_valid() {
+        assert_eq!(profiles().len(), BUILTIN.len());
+        for ((name, _), profile) in BUILTIN.iter().zip(profiles()) {
+            assert_eq!(&profile.name, name, "file name and profile name differ");
+            assert!(profile.detector.validate_transforms().is_ok());
+        }
+        let venus = find("VENUS").unwrap();
+        assert_eq!(venus.label(), "VENUS (SNS)");
+        assert_eq!(
+            venus.detector.detector_dimensions(),
+            DetectorConfig::venus_defaults().detector_dimensions()
+        );
+        assert_eq!(venus.detector.flight_path_m, Some(25.0));
+        assert!((find("odin").unwrap().detector.tdc_frequency_hz - 14.0).abs() < 1e-9);
+        assert!(find("unknown").is_none());
+    }
+
+    #[test]
+    fn test_profile_round_trip_and_lookup() {
+        let mut profile = find("snap").unwrap().clone();
+        profile.name = "my-beamline".to_string();
+        profile.detector.flight_path_m = Some(21.5);
+        let json = profile.to_json_string().unwrap();
+        let decoded = InstrumentProfile::from_json(&json).unwrap();
+        assert_eq!(decoded.name, "my-beamline");
+        assert_eq!(decoded.detector.flight_path_m, Some(21.5));
+        assert_eq!(
+            decoded.detector.detector_dimensions(),
+            profile.detector.detector_dimensions()
+        );
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("mine.json");
+        fs::write(&path, &json).unwrap();
+        let loaded = InstrumentProfile::lookup(path.to_str().unwrap()).unwrap();
+        assert_eq!(loaded.name, "my-beamline");
+        assert_eq!(InstrumentProfile::lookup("Mars").unwrap().name, "mars");
+        let err = InstrumentProfile::lookup("nowhere")
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("venus, snap, mars, odin"), "{err}");
+
+        let bad = json.replace("\"my-beamline\"", "\"My Beamline\"");
+        assert!(InstrumentProfile::from_json(&bad).is_err());
+        let bad = json.replace("\"pixel_pitch_mm\": 0.055", "\"pixel_pitch_mm\": 0.0");
+        assert!(InstrumentProfile::from_json(&bad).is_err());
+    }
+}
@@ -9,6 +9,8 @@
 //!
 //! - [`Tpx3Packet`] - Low-level packet parser with bit field extraction
 //! - `Tpx3Processor` - Section-aware file processor
+//! - [`synthetic`] - Seeded TPX3 files with ground-truth neutrons, for
+//!   validating the parser and clustering without facility data
 //!
 //! # Processing Pipeline
 //!
@@ -16,18 +18,31 @@
 //! 2. **Phase 2 (Parallel)**: Process sections into hits
 //!
 
+pub mod conversion;
+pub mod frame;
 mod hit;
+pub mod instrument;
 pub mod ordering;
 mod packet;
+pub mod projection;
 pub mod section;
-
-pub use hit::{calculate_tof, correct_timestamp_rollover};
+pub mod synthetic;
+pub mod tdc;
+pub mod validation;
+
+pub use hit::{
+    calculate_tof, correct_timestamp_rollover, correct_toa, toa_ticks_to_ns, tot_counts_to_ns,
+    FINE_TOA_STEPS, TOA_TICK_NS, TOT_SATURATED, TOT_TICK_NS,
+};
 pub use packet::Tpx3Packet;
 
 use serde::{Deserialize, Serialize};
+use std::fmt;
 use std::fs::File;
 use std::io::BufReader;
 use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
 
 /// Affine transformation for chip coordinate mapping.
 ///
@@ -130,19 +145,337 @@ impl ChipTransform {
     }
 }
 
+/// What to do with a hit that a chip transform maps outside the detector
+/// canvas ([`DetectorConfig::detector_dimensions`]).
+///
+/// Validated transforms keep every in-chip pixel on the canvas, so this only
+/// happens with a misconfigured geometry, e.g. a chip size smaller than the
+/// pixel addresses the chip reports.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OutOfBoundsPolicy {
+    /// Move the hit to the nearest canvas pixel.
+    #[default]
+    Clamp,
+    /// Drop the hit.
+    Drop,
+    /// Drop the hit; callers check [`OutOfBoundsCounts`] and abort the run.
+    Error,
+}
+
+/// Per-chip count of hits mapped outside the detector canvas.
+///
+/// Clones share the same counters, so the decoders of a file can count into
+/// the handle its caller keeps.
+#[derive(Clone, Debug)]
+pub struct OutOfBoundsCounts {
+    chips: Arc<[AtomicU64; 256]>,
+}
+
+impl Default for OutOfBoundsCounts {
+    fn default() -> Self {
+        Self {
+            chips: Arc::new(std::array::from_fn(|_| AtomicU64::new(0))),
+        }
+    }
+}
+
+impl OutOfBoundsCounts {
+    /// Count one out-of-bounds hit on `chip_id`.
+    #[inline]
+    pub fn record(&self, chip_id: u8) {
+        self.chips[usize::from(chip_id)].fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Out-of-bounds hits counted for `chip_id`.
+    #[must_use]
+    pub fn chip(&self, chip_id: u8) -> u64 {
+        self.chips[usize::from(chip_id)].load(Ordering::Relaxed)
+    }
+
+    /// Chips with out-of-bounds hits and their counts, by chip ID.
+    #[must_use]
+    pub fn per_chip(&self) -> Vec<(u8, u64)> {
+        (0..=u8::MAX)
+            .map(|chip_id| (chip_id, self.chip(chip_id)))
+            .filter(|&(_, count)| count > 0)
+            .collect()
+    }
+
+    /// Out-of-bounds hits counted over all chips.
+    #[must_use]
+    pub fn total(&self) -> u64 {
+        self.chips
+            .iter()
+            .map(|count| count.load(Ordering::Relaxed))
+            .sum()
+    }
+}
+
+/// Lists the chips with out-of-bounds hits, e.g. `chip 0: 12, chip 2: 3`.
+impl fmt::Display for OutOfBoundsCounts {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, (chip_id, count)) in self.per_chip().into_iter().enumerate() {
+            if i > 0 {
+                f.write_str(", ")?;
+            }
+            write!(f, "chip {chip_id}: {count}")?;
+        }
+        Ok(())
+    }
+}
+
+/// A chip transform restricted to the detector canvas.
+///
+/// Hits that land outside the canvas are counted against the chip whatever
+/// the policy.
+#[derive(Clone, Debug)]
+pub struct CanvasTransform {
+    transform: ChipTransform,
+    width: i32,
+    height: i32,
+    policy: OutOfBoundsPolicy,
+    chip_id: u8,
+    counts: OutOfBoundsCounts,
+}
+
+impl CanvasTransform {
+    /// Restrict `transform` to a `width` × `height` canvas.
+    #[must_use]
+    pub fn new(
+        transform: ChipTransform,
+        (width, height): (usize, usize),
+        policy: OutOfBoundsPolicy,
+    ) -> Self {
+        Self {
+            transform,
+            width: i32::try_from(width).unwrap_or(i32::MAX).max(1),
+            height: i32::try_from(height).unwrap_or(i32::MAX).max(1),
+            policy,
+            chip_id: 0,
+            counts: OutOfBoundsCounts::default(),
+        }
+    }
+
+    /// Count out-of-bounds hits into `counts` under `chip_id`.
+    #[must_use]
+    pub fn with_counts(mut self, chip_id: u8, counts: OutOfBoundsCounts) -> Self {
+        self.chip_id = chip_id;
+        self.counts = counts;
+        self
+    }
+
+    /// Map local coordinates to the canvas, or `None` if they fall outside
+    /// it and the policy is not [`OutOfBoundsPolicy::Clamp`].
+    #[inline]
+    #[must_use]
+    pub fn map(&self, x: u16, y: u16) -> Option<(u16, u16)> {
+        let t = &self.transform;
+        let (x, y) = (i32::from(x), i32::from(y));
+        let gx = t.a * x + t.b * y + t.tx;
+        let gy = t.c * x + t.d * y + t.ty;
+        if !(0..self.width).contains(&gx) || !(0..self.height).contains(&gy) {
+            self.counts.record(self.chip_id);
+            if self.policy != OutOfBoundsPolicy::Clamp {
+                return None;
+            }
+        }
+        let gx = gx.clamp(0, self.width - 1);
+        let gy = gy.clamp(0, self.height - 1);
+        Some((
+            u16::try_from(gx).unwrap_or(u16::MAX),
+            u16::try_from(gy).unwrap_or(u16::MAX),
+        ))
+    }
+}
+
+/// Units for the time columns of emitted hits.
+///
+/// Parsing and clustering always work on raw register ticks; this only
+/// controls how ToA/TOF and `ToT` are presented when hits are handed out.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TimeUnits {
+    /// ToA/TOF in 25 ns ticks (`u32`) and `ToT` in register counts (`u16`).
+    #[default]
+    #[serde(rename = "raw")]
+    Raw,
+    /// ToA/TOF in nanoseconds (`u64`) and `ToT` in nanoseconds (`u32`).
+    #[serde(rename = "ns")]
+    Nanoseconds,
+}
+
+/// TOF reference for hits that arrive before any TDC packet of their chip.
+///
+/// Without a TDC there is no pulse to measure TOF from, so such hits are
+/// dropped by default. Files recorded without a chopper or trigger signal
+/// have no TDC packets at all and can use [`NoTdcFallback::FirstHit`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NoTdcFallback {
+    /// Drop the hits.
+    #[default]
+    Drop,
+    /// Start synthetic pulses at the configured TDC frequency from the
+    /// chip's first hit, until a real TDC arrives.
+    FirstHit,
+}
+
+/// TOF correction for hits recorded after a missing TDC packet.
+///
+/// A dropped TDC leaves the hits of the following pulse measured from the
+/// pulse before it, so their raw TOF exceeds the TDC period. The policies
+/// differ in where they assume the missing pulses started. See
+/// [`tdc::diagnose_tdc_sections`] for how often a file needs them.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TdcCorrectionPolicy {
+    /// Keep the TOF measured from the last TDC received.
+    None,
+    /// Assume the missing pulse started one configured period after the
+    /// last TDC, and subtract that period.
+    #[default]
+    ExtrapolateLastPeriod,
+    /// Space the missing pulses evenly between the TDCs either side of the
+    /// gap, so a source running slightly off the configured frequency does
+    /// not shift the TOF of the pulses in the gap.
+    InterpolateNeighbors,
+    /// Drop hits between a missing TDC and the next one received.
+    DropSection,
+}
+
+impl TdcCorrectionPolicy {
+    /// Policy of the binary `enable_missing_tdc_correction` setting.
+    #[must_use]
+    pub fn from_enabled(enabled: bool) -> Self {
+        if enabled {
+            Self::ExtrapolateLastPeriod
+        } else {
+            Self::None
+        }
+    }
+
+    /// Whether any correction is applied.
+    #[must_use]
+    pub fn is_enabled(self) -> bool {
+        self != Self::None
+    }
+}
+
+/// Gap in pixels between chips in the [`OutputGeometry::GapFilled`] layout.
+pub const CANONICAL_CHIP_GAP: u16 = 4;
+
+/// Output pixel geometry for multi-chip detectors.
+///
+/// The canonical layouts re-place each chip on a regular grid (cell column
+/// and row taken from where the chip transform puts it), so a 2×2 quad of
+/// 256-pixel chips becomes 512×512 or 516×516 regardless of the gap the
+/// native transforms encode.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OutputGeometry {
+    /// Use the chip transforms as configured.
+    #[default]
+    Native,
+    /// Chips butted together with no gap (512×512 for a quad).
+    Packed,
+    /// Physical gaps of [`CANONICAL_CHIP_GAP`] pixels (516×516 for a quad).
+    GapFilled,
+}
+
+impl OutputGeometry {
+    /// Gap between chips, or `None` to keep the native placement.
+    #[must_use]
+    pub fn chip_gap(self) -> Option<u16> {
+        match self {
+            Self::Native => None,
+            Self::Packed => Some(0),
+            Self::GapFilled => Some(CANONICAL_CHIP_GAP),
+        }
+    }
+}
+
+/// Per-chip `ToA` timing correction.
+///
+/// Offsets are in nanoseconds and are subtracted from each hit's time of
+/// arrival (coarse `ToA` refined by fine `ToA`) before the hit is emitted,
+/// so a positive offset moves hits earlier.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct ToaCorrection {
+    /// Offset applied to every pixel of the chip.
+    #[serde(default)]
+    pub offset_ns: f64,
+    /// Additional offset per local pixel column; columns past the end of the
+    /// table get none.
+    #[serde(default)]
+    pub column_offsets_ns: Vec<f64>,
+}
+
+impl ToaCorrection {
+    /// Offsets per local column in fine `ToA` steps (1.5625 ns), covering
+    /// `columns` columns.
+    #[must_use]
+    pub fn column_steps(&self, columns: u16) -> Vec<i64> {
+        #[allow(clippy::cast_precision_loss)]
+        let step_ns = TOA_TICK_NS as f64 / FINE_TOA_STEPS as f64;
+        (0..usize::from(columns))
+            .map(|column| {
+                let offset = self.offset_ns + self.column_offsets_ns.get(column).unwrap_or(&0.0);
+                #[allow(clippy::cast_possible_truncation)]
+                let steps = (offset / step_ns).round() as i64;
+                steps
+            })
+            .collect()
+    }
+}
+
 /// Detector configuration for TPX3 processing.
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct DetectorConfig {
     /// TDC frequency in Hz (default: 60.0 for SNS).
     pub tdc_frequency_hz: f64,
-    /// Enable missing TDC correction.
-    pub enable_missing_tdc_correction: bool,
+    /// TOF correction for hits after a missing TDC.
+    #[serde(default)]
+    pub tdc_correction_policy: TdcCorrectionPolicy,
     /// Chip size X in pixels (default: 256).
     pub chip_size_x: u16,
     /// Chip size Y in pixels (default: 256).
     pub chip_size_y: u16,
     /// Per-chip affine transforms.
     pub chip_transforms: Vec<ChipTransform>,
+    /// Units for emitted hit times (default: raw ticks).
+    #[serde(default)]
+    pub time_units: TimeUnits,
+    /// Output pixel geometry (default: native transforms).
+    #[serde(default)]
+    pub output_geometry: OutputGeometry,
+    /// Per-chip `ToA` corrections, indexed by chip ID (empty: none).
+    #[serde(default)]
+    pub toa_corrections: Vec<Option<ToaCorrection>>,
+    /// TOF reference for hits before the first TDC (default: drop them).
+    #[serde(default)]
+    pub no_tdc_fallback: NoTdcFallback,
+    /// Handling of hits mapped outside the detector canvas (default: clamp).
+    #[serde(default)]
+    pub out_of_bounds: OutOfBoundsPolicy,
+    /// Source-to-detector flight path in metres, for energy and wavelength
+    /// axes (default: unknown).
+    #[serde(default)]
+    pub flight_path_m: Option<f64>,
+    /// Delay between the TDC and the neutrons leaving the source in
+    /// nanoseconds, subtracted from TOF before conversion (default: 0).
+    #[serde(default)]
+    pub tof_offset_ns: f64,
+    /// Where decoders count hits mapped outside the canvas.
+    ///
+    /// Shared by clones of the config; not part of the detector JSON schema.
+    #[serde(skip)]
+    pub out_of_bounds_counts: OutOfBoundsCounts,
+    /// Keep each hit's chip-local coordinates and uncorrected `ToA` in the
+    /// `raw_*` columns of decoded batches, for debugging geometry and timing.
+    ///
+    /// A processing option, not part of the detector JSON schema.
+    #[serde(skip)]
+    pub retain_raw_hits: bool,
 }
 
 impl Default for DetectorConfig {
@@ -163,6 +496,8 @@ struct JsonDetector {
     timing: JsonTiming,
     chip_layout: JsonChipLayout,
     chip_transformations: Option<Vec<JsonChipTransform>>,
+    #[serde(skip_serializing_if = "JsonBeamline::is_unset")]
+    beamline: JsonBeamline,
 }
 
 #[derive(Deserialize, Serialize)]
@@ -170,6 +505,13 @@ struct JsonDetector {
 struct JsonTiming {
     tdc_frequency_hz: f64,
     enable_missing_tdc_correction: bool,
+    /// Overrides `enable_missing_tdc_correction` when present.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tdc_correction_policy: Option<TdcCorrectionPolicy>,
+    time_units: TimeUnits,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    toa_corrections: Vec<JsonToaCorrection>,
+    no_tdc_fallback: NoTdcFallback,
 }
 
 impl Default for JsonTiming {
@@ -177,6 +519,10 @@ impl Default for JsonTiming {
         Self {
             tdc_frequency_hz: 60.0,
             enable_missing_tdc_correction: true,
+            tdc_correction_policy: None,
+            time_units: TimeUnits::Raw,
+            toa_corrections: Vec::new(),
+            no_tdc_fallback: NoTdcFallback::Drop,
         }
     }
 }
@@ -186,6 +532,8 @@ impl Default for JsonTiming {
 struct JsonChipLayout {
     chip_size_x: u16,
     chip_size_y: u16,
+    output_geometry: OutputGeometry,
+    out_of_bounds: OutOfBoundsPolicy,
 }
 
 impl Default for JsonChipLayout {
@@ -193,10 +541,33 @@ impl Default for JsonChipLayout {
         Self {
             chip_size_x: 256,
             chip_size_y: 256,
+            output_geometry: OutputGeometry::Native,
+            out_of_bounds: OutOfBoundsPolicy::Clamp,
         }
     }
 }
 
+#[derive(Deserialize, Serialize, Default)]
+#[serde(default)]
+struct JsonBeamline {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    flight_path_m: Option<f64>,
+    tof_offset_ns: f64,
+}
+
+impl JsonBeamline {
+    fn is_unset(&self) -> bool {
+        self.flight_path_m.is_none() && self.tof_offset_ns == 0.0
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+struct JsonToaCorrection {
+    chip_id: u8,
+    #[serde(flatten)]
+    correction: ToaCorrection,
+}
+
 #[derive(Deserialize, Serialize)]
 struct JsonChipTransform {
     chip_id: u8,
@@ -254,10 +625,19 @@ impl DetectorConfig {
 
         Self {
             tdc_frequency_hz: 60.0,
-            enable_missing_tdc_correction: true,
+            tdc_correction_policy: TdcCorrectionPolicy::ExtrapolateLastPeriod,
             chip_size_x: 256,
             chip_size_y: 256,
             chip_transforms: transforms,
+            time_units: TimeUnits::Raw,
+            output_geometry: OutputGeometry::Native,
+            toa_corrections: Vec::new(),
+            no_tdc_fallback: NoTdcFallback::Drop,
+            out_of_bounds: OutOfBoundsPolicy::Clamp,
+            flight_path_m: None,
+            tof_offset_ns: 0.0,
+            out_of_bounds_counts: OutOfBoundsCounts::default(),
+            retain_raw_hits: false,
         }
     }
 
@@ -290,6 +670,13 @@ impl DetectorConfig {
     /// # Errors
     /// Returns an error if serialization fails.
     pub fn to_json_string(&self) -> Result<String, Box<dyn std::error::Error>> {
+        let json_config = JsonConfig {
+            detector: self.to_json_detector()?,
+        };
+        Ok(serde_json::to_string_pretty(&json_config)?)
+    }
+
+    fn to_json_detector(&self) -> Result<JsonDetector, Box<dyn std::error::Error>> {
         let transforms = {
             let transforms = self
                 .chip_transforms
@@ -314,21 +701,37 @@ impl DetectorConfig {
             Some(transforms)
         };
 
-        let json_config = JsonConfig {
-            detector: JsonDetector {
-                timing: JsonTiming {
-                    tdc_frequency_hz: self.tdc_frequency_hz,
-                    enable_missing_tdc_correction: self.enable_missing_tdc_correction,
-                },
-                chip_layout: JsonChipLayout {
-                    chip_size_x: self.chip_size_x,
-                    chip_size_y: self.chip_size_y,
-                },
-                chip_transformations: transforms,
+        Ok(JsonDetector {
+            timing: JsonTiming {
+                tdc_frequency_hz: self.tdc_frequency_hz,
+                enable_missing_tdc_correction: self.tdc_correction_policy.is_enabled(),
+                tdc_correction_policy: Some(self.tdc_correction_policy),
+                time_units: self.time_units,
+                toa_corrections: self
+                    .toa_corrections
+                    .iter()
+                    .enumerate()
+                    .filter_map(|(chip_id, correction)| {
+                        Some(JsonToaCorrection {
+                            chip_id: u8::try_from(chip_id).ok()?,
+                            correction: correction.clone()?,
+                        })
+                    })
+                    .collect(),
+                no_tdc_fallback: self.no_tdc_fallback,
             },
-        };
-
-        Ok(serde_json::to_string_pretty(&json_config)?)
+            chip_layout: JsonChipLayout {
+                chip_size_x: self.chip_size_x,
+                chip_size_y: self.chip_size_y,
+                output_geometry: self.output_geometry,
+                out_of_bounds: self.out_of_bounds,
+            },
+            chip_transformations: transforms,
+            beamline: JsonBeamline {
+                flight_path_m: self.flight_path_m,
+                tof_offset_ns: self.tof_offset_ns,
+            },
+        })
     }
 
     /// Save configuration to a JSON file (C++ compatible schema).
@@ -380,16 +783,45 @@ impl DetectorConfig {
             }
         };
 
+        let mut toa_corrections = Vec::new();
+        for entry in detector.timing.toa_corrections {
+            let chip = usize::from(entry.chip_id);
+            if toa_corrections.len() <= chip {
+                toa_corrections.resize(chip + 1, None);
+            }
+            toa_corrections[chip] = Some(entry.correction);
+        }
+
         let config = Self {
             tdc_frequency_hz: detector.timing.tdc_frequency_hz,
-            enable_missing_tdc_correction: detector.timing.enable_missing_tdc_correction,
+            tdc_correction_policy: detector.timing.tdc_correction_policy.unwrap_or_else(|| {
+                TdcCorrectionPolicy::from_enabled(detector.timing.enable_missing_tdc_correction)
+            }),
             chip_size_x,
             chip_size_y,
             chip_transforms: transforms,
+            time_units: detector.timing.time_units,
+            output_geometry: detector.chip_layout.output_geometry,
+            toa_corrections,
+            no_tdc_fallback: detector.timing.no_tdc_fallback,
+            out_of_bounds: detector.chip_layout.out_of_bounds,
+            flight_path_m: detector.beamline.flight_path_m,
+            tof_offset_ns: detector.beamline.tof_offset_ns,
+            out_of_bounds_counts: OutOfBoundsCounts::default(),
+            retain_raw_hits: false,
         };
 
         // Validate transforms once at load time (not per-hit)
         config.validate_transforms()?;
+        if config
+            .flight_path_m
+            .is_some_and(|length| !(length.is_finite() && length > 0.0))
+        {
+            return Err("beamline.flight_path_m must be positive".into());
+        }
+        if !config.tof_offset_ns.is_finite() {
+            return Err("beamline.tof_offset_ns must be finite".into());
+        }
 
         Ok(config)
     }
@@ -407,15 +839,68 @@ impl DetectorConfig {
                 .validate_bounds(self.chip_size_x, self.chip_size_y)
                 .map_err(|e| format!("Chip {i} transform invalid: {e}"))?;
         }
+        if self.output_geometry != OutputGeometry::Native {
+            for (i, transform) in self.output_transforms().iter().enumerate() {
+                transform
+                    .validate_bounds(self.chip_size_x, self.chip_size_y)
+                    .map_err(|e| format!("Chip {i} transform invalid in output geometry: {e}"))?;
+            }
+        }
         Ok(())
     }
 
+    /// Energy (eV) of a neutron detected at `tof_us` (µs after the TDC),
+    /// or `None` without a flight path or for a non-physical TOF.
+    #[must_use]
+    pub fn tof_us_to_energy_ev(&self, tof_us: f64) -> Option<f64> {
+        conversion::tof_us_to_energy_ev(tof_us, self.flight_path_m?, self.tof_offset_ns)
+    }
+
+    /// TOF (µs after the TDC) of a neutron of `energy_ev`, or `None`
+    /// without a flight path or for a non-physical energy.
+    #[must_use]
+    pub fn energy_ev_to_tof_us(&self, energy_ev: f64) -> Option<f64> {
+        conversion::energy_ev_to_tof_us(energy_ev, self.flight_path_m?, self.tof_offset_ns)
+    }
+
+    /// Wavelength (Å) of a neutron detected at `tof_us` (µs after the TDC),
+    /// or `None` without a flight path or for a non-physical TOF.
+    #[must_use]
+    pub fn tof_us_to_wavelength_angstrom(&self, tof_us: f64) -> Option<f64> {
+        conversion::tof_us_to_wavelength_angstrom(tof_us, self.flight_path_m?, self.tof_offset_ns)
+    }
+
+    /// TOF (µs after the TDC) of a neutron of `wavelength_angstrom`, or
+    /// `None` without a flight path or for a non-physical wavelength.
+    #[must_use]
+    pub fn wavelength_angstrom_to_tof_us(&self, wavelength_angstrom: f64) -> Option<f64> {
+        conversion::wavelength_angstrom_to_tof_us(
+            wavelength_angstrom,
+            self.flight_path_m?,
+            self.tof_offset_ns,
+        )
+    }
+
     /// TDC period in seconds.
     #[must_use]
     pub fn tdc_period_seconds(&self) -> f64 {
         1.0 / self.tdc_frequency_hz
     }
 
+    /// Whether a missing-TDC correction is applied.
+    #[deprecated(since = "1.1.0", note = "use `tdc_correction_policy`")]
+    #[must_use]
+    pub fn enable_missing_tdc_correction(&self) -> bool {
+        self.tdc_correction_policy.is_enabled()
+    }
+
+    /// Enable or disable the missing-TDC correction, choosing
+    /// [`TdcCorrectionPolicy::ExtrapolateLastPeriod`] when enabled.
+    #[deprecated(since = "1.1.0", note = "set `tdc_correction_policy`")]
+    pub fn set_enable_missing_tdc_correction(&mut self, enabled: bool) {
+        self.tdc_correction_policy = TdcCorrectionPolicy::from_enabled(enabled);
+    }
+
     /// TDC correction value in 25ns units.
     #[must_use]
     pub fn tdc_correction_25ns(&self) -> u32 {
@@ -431,19 +916,89 @@ impl DetectorConfig {
             .unwrap_or(u32::MAX)
     }
 
+    /// `ToA` correction configured for a chip, if any.
+    #[must_use]
+    pub fn toa_correction(&self, chip_id: u8) -> Option<&ToaCorrection> {
+        self.toa_corrections.get(usize::from(chip_id))?.as_ref()
+    }
+
     /// Map local chip coordinates to global detector coordinates.
     ///
-    /// Uses the configured affine transform for the given chip ID.
-    /// If chip ID is out of bounds, returns local coordinates as-is (identity).
+    /// Uses the transform from [`Self::chip_transform`], so the configured
+    /// output geometry is applied. For per-hit mapping in hot loops, resolve
+    /// the transform once per chip instead.
     #[must_use]
     pub fn map_chip_to_global(&self, chip_id: u8, x: u16, y: u16) -> (u16, u16) {
-        if let Some(transform) = self.chip_transforms.get(chip_id as usize) {
-            transform.apply(x, y)
-        } else {
-            (x, y)
+        self.chip_transform(chip_id).apply(x, y)
+    }
+
+    /// Transform for a chip with the output geometry applied.
+    ///
+    /// Chips without a configured transform use the identity.
+    #[must_use]
+    pub fn chip_transform(&self, chip_id: u8) -> ChipTransform {
+        let transform = self
+            .chip_transforms
+            .get(usize::from(chip_id))
+            .cloned()
+            .unwrap_or_else(ChipTransform::identity);
+        match self.output_geometry.chip_gap() {
+            Some(gap) => self.place_on_grid(transform, gap),
+            None => transform,
         }
     }
 
+    /// Transform for a chip restricted to the detector canvas under the
+    /// configured [`OutOfBoundsPolicy`], counting into
+    /// `out_of_bounds_counts`.
+    #[must_use]
+    pub fn canvas_transform(&self, chip_id: u8) -> CanvasTransform {
+        CanvasTransform::new(
+            self.chip_transform(chip_id),
+            self.detector_dimensions(),
+            self.out_of_bounds,
+        )
+        .with_counts(chip_id, self.out_of_bounds_counts.clone())
+    }
+
+    /// All chip transforms with the output geometry applied.
+    #[must_use]
+    pub fn output_transforms(&self) -> Vec<ChipTransform> {
+        (0..self.chip_transforms.len())
+            .map(|chip_id| self.chip_transform(u8::try_from(chip_id).unwrap_or(u8::MAX)))
+            .collect()
+    }
+
+    /// Move a chip so its footprint starts on a grid cell of pitch
+    /// `chip size + gap`, keeping its orientation.
+    fn place_on_grid(&self, mut transform: ChipTransform, gap: u16) -> ChipTransform {
+        let (min_x, min_y) = self.footprint_origin(&transform);
+        let size_x = i32::from(self.chip_size_x.max(1));
+        let size_y = i32::from(self.chip_size_y.max(1));
+        let col = min_x.max(0) / size_x;
+        let row = min_y.max(0) / size_y;
+        transform.tx += col * (size_x + i32::from(gap)) - min_x;
+        transform.ty += row * (size_y + i32::from(gap)) - min_y;
+        transform
+    }
+
+    /// Lowest global coordinates a transform maps the chip to.
+    fn footprint_origin(&self, transform: &ChipTransform) -> (i32, i32) {
+        let max_x = i32::from(self.chip_size_x.saturating_sub(1));
+        let max_y = i32::from(self.chip_size_y.saturating_sub(1));
+        [(0, 0), (max_x, 0), (0, max_y), (max_x, max_y)]
+            .into_iter()
+            .map(|(x, y)| {
+                (
+                    transform.a * x + transform.b * y + transform.tx,
+                    transform.c * x + transform.d * y + transform.ty,
+                )
+            })
+            .fold((i32::MAX, i32::MAX), |(min_x, min_y), (gx, gy)| {
+                (min_x.min(gx), min_y.min(gy))
+            })
+    }
+
     /// Calculate detector dimensions from chip layout and transforms.
     ///
     /// Returns `(width, height)` in pixels sized to include all transformed
@@ -461,7 +1016,7 @@ impl DetectorConfig {
         let mut max_global_x = 0i32;
         let mut max_global_y = 0i32;
 
-        for transform in &self.chip_transforms {
+        for transform in &self.output_transforms() {
             for (x, y) in corners {
                 let gx = transform.a * x + transform.b * y + transform.tx;
                 let gy = transform.c * x + transform.d * y + transform.ty;
@@ -496,7 +1051,10 @@ mod tests {
     fn test_venus_defaults() {
         let config = DetectorConfig::venus_defaults();
         assert_f64_eq(config.tdc_frequency_hz, 60.0);
-        assert!(config.enable_missing_tdc_correction);
+        assert_eq!(
+            config.tdc_correction_policy,
+            TdcCorrectionPolicy::ExtrapolateLastPeriod
+        );
         assert_eq!(config.chip_transforms.len(), 4);
     }
 
@@ -507,6 +1065,28 @@ mod tests {
         assert_eq!((width, height), (514, 514));
     }
 
+    #[test]
+    fn test_canonical_output_geometry() {
+        let mut config = DetectorConfig::venus_defaults();
+
+        config.output_geometry = OutputGeometry::Packed;
+        assert_eq!(config.detector_dimensions(), (512, 512));
+        // Chip 0 (top-right cell) starts at x=256; chip 1 keeps its rotation.
+        assert_eq!(config.map_chip_to_global(0, 0, 0), (256, 0));
+        assert_eq!(config.map_chip_to_global(1, 0, 0), (511, 511));
+        assert_eq!(config.map_chip_to_global(3, 10, 20), (10, 20));
+
+        config.output_geometry = OutputGeometry::GapFilled;
+        assert_eq!(config.detector_dimensions(), (516, 516));
+        assert_eq!(config.map_chip_to_global(0, 0, 0), (260, 0));
+        assert_eq!(config.map_chip_to_global(2, 255, 255), (0, 260));
+        assert!(config.validate_transforms().is_ok());
+
+        let json = r#"{ "detector": { "chip_layout": { "output_geometry": "gap_filled" } } }"#;
+        let decoded = DetectorConfig::from_json(json).expect("Should parse geometry");
+        assert_eq!(decoded.detector_dimensions(), (516, 516));
+    }
+
     #[test]
     fn test_tdc_correction() {
         let config = DetectorConfig::venus_defaults();
@@ -571,7 +1151,7 @@ mod tests {
         let config = DetectorConfig::from_json(json).expect("Failed to parse JSON");
 
         assert_f64_eq(config.tdc_frequency_hz, 14.0);
-        assert!(!config.enable_missing_tdc_correction);
+        assert_eq!(config.tdc_correction_policy, TdcCorrectionPolicy::None);
         assert_eq!(config.chip_size_x, 256);
         assert_eq!(config.chip_size_y, 256);
         assert_eq!(config.chip_transforms.len(), 2);
@@ -602,10 +1182,71 @@ mod tests {
         let config = DetectorConfig::from_json(json).expect("Should parse partial config");
 
         assert_f64_eq(config.tdc_frequency_hz, 14.0); // Changed
-        assert!(config.enable_missing_tdc_correction); // Default: true
+        assert_eq!(
+            config.tdc_correction_policy,
+            TdcCorrectionPolicy::ExtrapolateLastPeriod
+        ); // Default: correction enabled
         assert_eq!(config.chip_size_x, 256); // Default
         assert_eq!(config.chip_size_y, 256); // Default
         assert_eq!(config.chip_transforms.len(), 4); // VENUS defaults
+        assert_eq!(config.time_units, TimeUnits::Raw); // Default
+    }
+
+    #[test]
+    fn test_json_time_units() {
+        let json = r#"{ "detector": { "timing": { "time_units": "ns" } } }"#;
+        let config = DetectorConfig::from_json(json).expect("Should parse time units");
+        assert_eq!(config.time_units, TimeUnits::Nanoseconds);
+
+        let json = r#"{ "detector": { "timing": { "time_units": "ms" } } }"#;
+        assert!(DetectorConfig::from_json(json).is_err());
+    }
+
+    #[test]
+    fn test_json_tdc_correction_policy() {
+        let json = r#"{ "detector": { "timing": {
+            "enable_missing_tdc_correction": true,
+            "tdc_correction_policy": "drop_section"
+        } } }"#;
+        let config = DetectorConfig::from_json(json).expect("Should parse policy");
+        assert_eq!(
+            config.tdc_correction_policy,
+            TdcCorrectionPolicy::DropSection
+        );
+
+        let json = config.to_json_string().unwrap();
+        assert!(json.contains(r#""enable_missing_tdc_correction": true"#));
+        let decoded = DetectorConfig::from_json(&json).unwrap();
+        assert_eq!(
+            decoded.tdc_correction_policy,
+            TdcCorrectionPolicy::DropSection
+        );
+    }
+
+    #[test]
+    fn test_json_beamline() {
+        let json = r#"{ "detector": { "beamline": {
+            "flight_path_m": 25.0,
+            "tof_offset_ns": 1500.0
+        } } }"#;
+        let config = DetectorConfig::from_json(json).expect("Should parse beamline");
+        assert_eq!(config.flight_path_m, Some(25.0));
+        assert_f64_eq(config.tof_offset_ns, 1500.0);
+        let energy = config.tof_us_to_energy_ev(11_365.136).unwrap();
+        assert!((energy - 0.0253).abs() < 1e-4, "{energy}");
+        let tof_us = config.wavelength_angstrom_to_tof_us(1.798).unwrap();
+        assert!((tof_us - 11_365.1).abs() < 5.0, "{tof_us}");
+
+        let decoded = DetectorConfig::from_json(&config.to_json_string().unwrap()).unwrap();
+        assert_eq!(decoded.flight_path_m, Some(25.0));
+        assert_f64_eq(decoded.tof_offset_ns, 1500.0);
+
+        let defaults = DetectorConfig::venus_defaults();
+        assert!(!defaults.to_json_string().unwrap().contains("beamline"));
+        assert!(defaults.tof_us_to_energy_ev(10_000.0).is_none());
+
+        let json = r#"{ "detector": { "beamline": { "flight_path_m": -1.0 } } }"#;
+        assert!(DetectorConfig::from_json(json).is_err());
     }
 
     #[test]
@@ -649,7 +1290,7 @@ mod tests {
     fn test_json_roundtrip_serialization() {
         let config = DetectorConfig {
             tdc_frequency_hz: 14.0,
-            enable_missing_tdc_correction: false,
+            tdc_correction_policy: TdcCorrectionPolicy::InterpolateNeighbors,
             chip_size_x: 128,
             chip_size_y: 64,
             chip_transforms: vec![
@@ -670,18 +1311,38 @@ mod tests {
                     ty: 63,
                 },
             ],
+            time_units: TimeUnits::Nanoseconds,
+            output_geometry: OutputGeometry::GapFilled,
+            toa_corrections: vec![
+                None,
+                Some(ToaCorrection {
+                    offset_ns: -3.5,
+                    column_offsets_ns: vec![0.0, 12.5, 20.0],
+                }),
+            ],
+            no_tdc_fallback: NoTdcFallback::FirstHit,
+            out_of_bounds: OutOfBoundsPolicy::Drop,
+            flight_path_m: Some(14.5),
+            tof_offset_ns: -250.0,
+            out_of_bounds_counts: OutOfBoundsCounts::default(),
+            retain_raw_hits: false,
         };
 
         let json = config.to_json_string().expect("serialize config");
         let decoded = DetectorConfig::from_json(&json).expect("roundtrip decode");
 
         assert_f64_eq(decoded.tdc_frequency_hz, config.tdc_frequency_hz);
-        assert_eq!(
-            decoded.enable_missing_tdc_correction,
-            config.enable_missing_tdc_correction
-        );
+        assert_eq!(decoded.tdc_correction_policy, config.tdc_correction_policy);
         assert_eq!(decoded.chip_size_x, config.chip_size_x);
         assert_eq!(decoded.chip_size_y, config.chip_size_y);
+        assert_eq!(decoded.time_units, TimeUnits::Nanoseconds);
+        assert_eq!(decoded.output_geometry, OutputGeometry::GapFilled);
+        assert_eq!(decoded.toa_corrections, config.toa_corrections);
+        assert!(decoded.toa_correction(0).is_none());
+        assert_eq!(decoded.no_tdc_fallback, NoTdcFallback::FirstHit);
+        assert_eq!(decoded.out_of_bounds, OutOfBoundsPolicy::Drop);
+        assert_eq!(decoded.flight_path_m, Some(14.5));
+        assert_f64_eq(decoded.tof_offset_ns, -250.0);
         assert_eq!(decoded.chip_transforms.len(), config.chip_transforms.len());
         for (actual, expected) in decoded
             .chip_transforms
@@ -692,6 +1353,33 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_toa_correction_column_steps() {
+        let correction = ToaCorrection {
+            offset_ns: 3.125,
+            column_offsets_ns: vec![0.0, 20.0],
+        };
+        // 1.5625 ns steps: 3.125 ns is 2 steps, 23.125 ns rounds to 15.
+        assert_eq!(correction.column_steps(3), vec![2, 15, 2]);
+    }
+
+    #[test]
+    fn test_file_roundtrip_non_ascii_path() {
+        let root = tempfile::tempdir().unwrap();
+        let dir = root.path().join("VENUS 設定").join("ユーザー フォルダ");
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("検出器 config.json");
+
+        let config = DetectorConfig {
+            output_geometry: OutputGeometry::Packed,
+            ..DetectorConfig::venus_defaults()
+        };
+        config.to_file(&path).expect("write config");
+        let decoded = DetectorConfig::from_file(&path).expect("read config");
+        assert_eq!(decoded.output_geometry, OutputGeometry::Packed);
+        assert_eq!(decoded.chip_transforms.len(), config.chip_transforms.len());
+    }
+
     #[test]
     fn test_json_serialization_schema() {
         let config = DetectorConfig::venus_defaults();
@@ -736,10 +1424,19 @@ mod tests {
     fn test_json_empty_transforms_serialization() {
         let config = DetectorConfig {
             tdc_frequency_hz: 42.0,
-            enable_missing_tdc_correction: true,
+            tdc_correction_policy: TdcCorrectionPolicy::ExtrapolateLastPeriod,
             chip_size_x: 256,
             chip_size_y: 256,
             chip_transforms: Vec::new(),
+            time_units: TimeUnits::Raw,
+            output_geometry: OutputGeometry::Native,
+            toa_corrections: Vec::new(),
+            no_tdc_fallback: NoTdcFallback::Drop,
+            out_of_bounds: OutOfBoundsPolicy::Clamp,
+            flight_path_m: None,
+            tof_offset_ns: 0.0,
+            out_of_bounds_counts: OutOfBoundsCounts::default(),
+            retain_raw_hits: false,
         };
 
         let json = config.to_json_string().expect("serialize config");
@@ -761,6 +1458,37 @@ mod tests {
         assert!(decoded.chip_transforms.is_empty());
     }
 
+    #[test]
+    #[allow(deprecated)]
+    fn test_deprecated_tdc_correction_accessors() {
+        let mut config = DetectorConfig::default();
+        config.set_enable_missing_tdc_correction(false);
+        assert_eq!(config.tdc_correction_policy, TdcCorrectionPolicy::None);
+        assert!(!config.enable_missing_tdc_correction());
+
+        config.set_enable_missing_tdc_correction(true);
+        assert_eq!(
+            config.tdc_correction_policy,
+            TdcCorrectionPolicy::ExtrapolateLastPeriod
+        );
+        config.tdc_correction_policy = TdcCorrectionPolicy::InterpolateNeighbors;
+        assert!(config.enable_missing_tdc_correction());
+    }
+
+    #[test]
+    fn test_processing_options_not_serialized() {
+        let config = DetectorConfig {
+            retain_raw_hits: true,
+            ..DetectorConfig::default()
+        };
+        let value = serde_json::to_value(&config).expect("serialize config");
+        assert!(value.get("retain_raw_hits").is_none());
+        assert!(value.get("out_of_bounds_counts").is_none());
+
+        let decoded: DetectorConfig = serde_json::from_value(value).expect("decode");
+        assert!(!decoded.retain_raw_hits);
+    }
+
     #[test]
     fn test_venus_transforms_valid() {
         // VENUS defaults should always pass validation
@@ -14,17 +14,39 @@
 //!    (hits arriving after TDC boundary but belonging to previous pulse).
 //! 3. `TimeOrderedStream` uses a Min-Heap to merge these pulse batches based on
 //!    their TDC timestamp.
+//!
+//! # Parallel loading
+//! [`read_time_ordered_parallel`] produces the same result for a whole file:
+//! 1. Phase 1 (sequential): propagate the pulse state of each chip across its
+//!    sections, from the TDC packets collected per section in parallel. The
+//!    pulses met on the way can be recorded as a [`PulseIndex`]
+//!    ([`section_pulse_index`]).
+//! 2. Phase 2 (parallel): decode every section with its own `PulseReader`,
+//!    started from that state, into pulse fragments.
+//! 3. Fragments are merged in TDC order and sorted per pulse like the stream.
+//!
+//! # Missing TDCs
+//! Hits recorded after a dropped TDC are corrected according to the
+//! configured [`TdcCorrectionPolicy`]. Interpolation needs the TDC that ends
+//! the gap, so those hits are corrected when the pulse is sealed; the last
+//! pulse of the data, which no TDC ends, is extrapolated instead.
 
-use crate::hit::{calculate_tof, correct_timestamp_rollover};
+use crate::hit::{calculate_tof, correct_timestamp_rollover, correct_toa};
 use crate::packet::Tpx3Packet;
 use crate::section::Tpx3Section;
-use crate::DetectorConfig;
-use rustpix_core::soa::HitBatch;
+use crate::{DetectorConfig, NoTdcFallback, TdcCorrectionPolicy, ToaCorrection};
+use rayon::prelude::*;
+use rustpix_core::pulse::PulseIndex;
+use rustpix_core::soa::{HitBatch, HitRecord, RawHitFields};
+use serde::{Deserialize, Serialize};
 use std::cmp::Ordering;
 use std::collections::BinaryHeap;
 use std::collections::VecDeque;
 use std::sync::Arc;
 
+/// Mask of the 30-bit TDC counter.
+const TDC_MASK: u32 = 0x3FFF_FFFF;
+
 /// A batch of hits belonging to a single pulse (TDC period) from one chip.
 #[derive(Debug, Clone)]
 pub struct PulseBatch {
@@ -81,6 +103,122 @@ impl Ord for PulseBatch {
     }
 }
 
+/// Pulse state of a chip at the start of a section.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PulseContext {
+    /// Active pulse as `(tdc_timestamp, tdc_epoch)`.
+    pub current: Option<(u32, u64)>,
+    /// Previous pulse, still open for late hits, as `(tdc_timestamp, tdc_epoch)`.
+    pub previous: Option<(u32, u64)>,
+}
+
+impl PulseContext {
+    /// Advance past a TDC packet, the way `PulseReader` does.
+    fn advance(&mut self, tdc: u32) {
+        let epoch = match self.current {
+            Some((last, epoch)) => {
+                self.previous = self.current;
+                if tdc < last {
+                    epoch + 1
+                } else {
+                    epoch
+                }
+            }
+            None => 0,
+        };
+        self.current = Some((tdc, epoch));
+    }
+}
+
+/// Pulse context of every section, in section order.
+///
+/// The TDC packets of each section are collected in parallel; the contexts
+/// are then propagated sequentially across the sections of each chip.
+#[must_use]
+pub fn section_pulse_contexts(data: &[u8], sections: &[Tpx3Section]) -> Vec<PulseContext> {
+    let mut chips = [PulseContext::default(); 256];
+    continue_pulse_contexts(data, sections, &mut chips)
+}
+
+/// Pulse context of every section of data that continues a stream, e.g. a
+/// network buffer whose older data has been discarded.
+///
+/// `chips` holds the pulse state of each chip before `sections` and is
+/// advanced past them.
+#[must_use]
+pub fn continue_pulse_contexts(
+    data: &[u8],
+    sections: &[Tpx3Section],
+    chips: &mut [PulseContext; 256],
+) -> Vec<PulseContext> {
+    propagate_pulse_contexts(data, sections, chips, None)
+}
+
+/// Pulse context of every section, in section order, and the index of the
+/// pulses the sections' TDC packets start.
+///
+/// This is Phase 1 of parallel loading with the pulses recorded on the way.
+/// Pulses are keyed by their extended TDC timestamp, which is how the pulses
+/// of different chips are merged, so a pulse seen by several chips is one
+/// pulse of the index.
+#[must_use]
+pub fn section_pulse_index(
+    data: &[u8],
+    sections: &[Tpx3Section],
+) -> (Vec<PulseContext>, PulseIndex) {
+    let mut chips = [PulseContext::default(); 256];
+    let mut start_times = Vec::new();
+    let contexts = propagate_pulse_contexts(data, sections, &mut chips, Some(&mut start_times));
+    (contexts, PulseIndex::new(start_times))
+}
+
+/// Advances `chips` past the TDC packets of `sections`, collected per
+/// section in parallel, recording the extended TDC timestamp of every pulse
+/// in `start_times` if given.
+fn propagate_pulse_contexts(
+    data: &[u8],
+    sections: &[Tpx3Section],
+    chips: &mut [PulseContext; 256],
+    mut start_times: Option<&mut Vec<u64>>,
+) -> Vec<PulseContext> {
+    let section_tdcs: Vec<Vec<u32>> = sections
+        .par_iter()
+        .map(|section| {
+            data[section.start_offset..section.end_offset]
+                .chunks_exact(8)
+                .map(|chunk| {
+                    let mut bytes = [0u8; 8];
+                    bytes.copy_from_slice(chunk);
+                    Tpx3Packet::new(u64::from_le_bytes(bytes))
+                })
+                .filter(Tpx3Packet::is_tdc)
+                .map(|packet| packet.tdc_timestamp())
+                .collect()
+        })
+        .collect();
+
+    sections
+        .iter()
+        .zip(section_tdcs)
+        .map(|(section, tdcs)| {
+            let state = &mut chips[usize::from(section.chip_id)];
+            let context = *state;
+            for tdc in tdcs {
+                state.advance(tdc);
+                if let (Some(times), Some((tdc, epoch))) =
+                    (start_times.as_deref_mut(), state.current)
+                {
+                    times.push((epoch << 30) | u64::from(tdc));
+                }
+            }
+            context
+        })
+        .collect()
+}
+
+/// Maps a chip's local pixel to global coordinates, or `None` to drop it.
+type ChipMap = dyn Fn(u8, u16, u16) -> Option<(u16, u16)> + Send + Sync + 'static;
+
 /// Reads a stream of sections for a single chip and yields sorted `PulseBatch` values.
 ///
 /// Implements a 1-pulse lookahead to handle "late hits" and independent timestamp rollovers.
@@ -104,7 +242,21 @@ where
     ready_queue: VecDeque<PulseBatch>,
 
     tdc_correction: u32,
-    chip_transform: Arc<dyn Fn(u8, u16, u16) -> (u16, u16) + Send + Sync + 'static>,
+    tdc_policy: TdcCorrectionPolicy,
+    // Interpolated pulse spacing after `prev_batch`'s TDC, when TDCs are
+    // missing before the current one.
+    prev_spacing: Option<u32>,
+    // First TDC of the chip after the last section, if known.
+    next_tdc: Option<u32>,
+    chip_transform: Arc<ChipMap>,
+    // Per-column ToA offsets in fine steps, when a correction is configured.
+    toa_offsets: Option<Vec<i64>>,
+    // Reference for hits before the first TDC.
+    no_tdc_fallback: NoTdcFallback,
+    // True while the active pulse was started by a hit, not a TDC.
+    synthetic_pulse: bool,
+    // Fill the raw columns of emitted batches.
+    retain_raw: bool,
 }
 
 impl<D> PulseReader<D>
@@ -112,11 +264,15 @@ where
     D: AsRef<[u8]> + Clone,
 {
     /// Create a pulse reader for a single chip's section stream.
+    ///
+    /// `chip_transform` maps local to global coordinates, returning `None`
+    /// for hits to drop as out of bounds (see
+    /// [`crate::CanvasTransform::map`]).
     pub fn new(
         data: D,
         sections: &[Tpx3Section],
         tdc_correction: u32,
-        chip_transform: impl Fn(u8, u16, u16) -> (u16, u16) + Send + Sync + 'static,
+        chip_transform: impl Fn(u8, u16, u16) -> Option<(u16, u16)> + Send + Sync + 'static,
     ) -> Self {
         let owned_sections = sections.to_vec();
 
@@ -135,11 +291,150 @@ where
             tdc_epoch: 0,
             last_tdc: initial_tdc,
             tdc_correction,
+            tdc_policy: TdcCorrectionPolicy::default(),
+            prev_spacing: None,
+            next_tdc: None,
             chip_transform: Arc::new(chip_transform),
+            toa_offsets: None,
+            no_tdc_fallback: NoTdcFallback::Drop,
+            synthetic_pulse: false,
+            retain_raw: false,
+        }
+    }
+
+    /// Apply a per-chip `ToA` correction to every hit this reader emits.
+    ///
+    /// `columns` is the number of local pixel columns to cover.
+    #[must_use]
+    pub fn with_toa_correction(mut self, correction: Option<&ToaCorrection>, columns: u16) -> Self {
+        self.toa_offsets = correction.map(|correction| correction.column_steps(columns));
+        self
+    }
+
+    /// Set how hits before the chip's first TDC are handled.
+    #[must_use]
+    pub fn with_no_tdc_fallback(mut self, fallback: NoTdcFallback) -> Self {
+        self.no_tdc_fallback = fallback;
+        self
+    }
+
+    /// Set how hits after a missing TDC are corrected.
+    #[must_use]
+    pub fn with_tdc_correction(mut self, policy: TdcCorrectionPolicy) -> Self {
+        self.tdc_policy = policy;
+        self
+    }
+
+    /// The first TDC of the chip after the reader's sections, so the last
+    /// pulse can be interpolated under
+    /// [`TdcCorrectionPolicy::InterpolateNeighbors`].
+    #[must_use]
+    pub fn with_next_tdc(mut self, next_tdc: Option<u32>) -> Self {
+        self.next_tdc = next_tdc;
+        self
+    }
+
+    /// Keep each hit's chip-local coordinates and uncorrected `ToA` in the
+    /// `raw_*` columns of emitted batches.
+    #[must_use]
+    pub fn with_raw_hits(mut self, retain: bool) -> Self {
+        self.retain_raw = retain;
+        self
+    }
+
+    /// Start from the pulse state the chip had before the first section, so a
+    /// section can be decoded without the ones before it.
+    #[must_use]
+    pub fn with_context(mut self, context: PulseContext) -> Self {
+        let chip_id = reader_chip_id(&self);
+        self.curr_tdc = context.current.map(|(tdc, _)| tdc);
+        self.last_tdc = self.curr_tdc;
+        self.tdc_epoch = context.current.map_or(0, |(_, epoch)| epoch);
+        self.prev_spacing = context
+            .previous
+            .zip(context.current)
+            .and_then(|((previous, _), (current, _))| self.interpolated_spacing(previous, current));
+        self.prev_batch = context.previous.map(|(tdc, epoch)| PulseBatch {
+            chip_id,
+            tdc_timestamp: tdc,
+            tdc_epoch: epoch,
+            hits: HitBatch::default(),
+        });
+        self
+    }
+
+    /// Coarse timestamp of a hit, with the `ToA` correction applied if set.
+    #[inline]
+    fn hit_timestamp(&self, packet: Tpx3Packet, column: u16) -> u32 {
+        match &self.toa_offsets {
+            Some(offsets) => correct_toa(
+                packet.timestamp_coarse(),
+                packet.fine_toa(),
+                offsets.get(usize::from(column)).copied().unwrap_or(0),
+            ),
+            None => packet.timestamp_coarse(),
+        }
+    }
+
+    /// TOF of a hit at `timestamp` in the pulse started at `tdc`, or `None`
+    /// if the TDC correction policy drops it. `spacing` is the interpolated
+    /// pulse spacing after `tdc`, if known.
+    #[inline]
+    fn tof(&self, timestamp: u32, tdc: u32, spacing: Option<u32>) -> Option<u32> {
+        let raw = timestamp.wrapping_sub(tdc);
+        match self.tdc_policy {
+            TdcCorrectionPolicy::None => Some(raw),
+            TdcCorrectionPolicy::ExtrapolateLastPeriod => {
+                Some(calculate_tof(timestamp, tdc, self.tdc_correction))
+            }
+            TdcCorrectionPolicy::InterpolateNeighbors => Some(match spacing {
+                Some(spacing) => raw % spacing,
+                None => calculate_tof(timestamp, tdc, self.tdc_correction),
+            }),
+            TdcCorrectionPolicy::DropSection => (raw <= self.tdc_correction).then_some(raw),
+        }
+    }
+
+    /// Spacing of the pulses between TDCs at `from` and `to` when at least
+    /// one TDC is missing between them, under
+    /// [`TdcCorrectionPolicy::InterpolateNeighbors`].
+    fn interpolated_spacing(&self, from: u32, to: u32) -> Option<u32> {
+        if self.tdc_policy != TdcCorrectionPolicy::InterpolateNeighbors || self.tdc_correction == 0
+        {
+            return None;
         }
+        let interval = to.wrapping_sub(from) & TDC_MASK;
+        let periods = (interval + self.tdc_correction / 2) / self.tdc_correction;
+        (periods >= 2).then(|| interval / periods)
+    }
+
+    /// Start of a synthetic pulse needed before `packet` can be assigned.
+    ///
+    /// Under [`NoTdcFallback::FirstHit`], the first hit without a TDC opens a
+    /// pulse at its own timestamp, and later hits open pulses whole TDC
+    /// periods after it, until a real TDC takes over.
+    fn synthetic_pulse_start(&self, packet: Tpx3Packet) -> Option<u32> {
+        if self.no_tdc_fallback != NoTdcFallback::FirstHit || !packet.is_hit() {
+            return None;
+        }
+        let raw_ts = self.hit_timestamp(packet, packet.pixel_coordinates().0);
+        let Some(curr_tdc) = self.curr_tdc else {
+            return Some(raw_ts);
+        };
+        if !self.synthetic_pulse || self.tdc_correction == 0 {
+            return None;
+        }
+        let ts = correct_timestamp_rollover(raw_ts, curr_tdc);
+        if ts < curr_tdc {
+            // Late hit of the previous pulse.
+            return None;
+        }
+        let periods = (ts - curr_tdc) / self.tdc_correction;
+        (periods > 0).then(|| curr_tdc.wrapping_add(periods * self.tdc_correction) & TDC_MASK)
     }
 
     /// Return the next pulse batch from this chip, if available.
+    #[allow(clippy::too_many_lines)]
     pub fn next_pulse(&mut self) -> Option<PulseBatch> {
         const PACKET_SIZE: usize = 8;
 
@@ -163,10 +458,16 @@ where
                 bytes.copy_from_slice(packet_bytes);
                 let raw = u64::from_le_bytes(bytes);
                 let packet = Tpx3Packet::new(raw);
-                self.packet_idx += 1;
 
-                if packet.is_tdc() {
-                    let new_tdc = packet.tdc_timestamp();
+                // A hit that opens a synthetic pulse is read again once the
+                // pulse has started.
+                let synthetic_tdc = self.synthetic_pulse_start(packet);
+                if synthetic_tdc.is_none() {
+                    self.packet_idx += 1;
+                }
+
+                if packet.is_tdc() || synthetic_tdc.is_some() {
+                    let new_tdc = synthetic_tdc.unwrap_or_else(|| packet.tdc_timestamp());
                     let rollover = self.last_tdc.is_some_and(|last| new_tdc < last);
 
                     // TDC marks the start of a new pulse (or end of previous).
@@ -182,12 +483,18 @@ where
                             self.ready_queue.push_back(prev);
                         }
 
-                        // 2. Promote `curr` to `prev`
+                        // 2. Promote `curr` to `prev`, now that the TDC
+                        //    ending it is known.
+                        let mut hits = std::mem::take(&mut self.curr_batch);
+                        self.prev_spacing = self.interpolated_spacing(old_tdc, new_tdc);
+                        if let Some(spacing) = self.prev_spacing {
+                            reinterpolate(&mut hits, old_tdc, spacing);
+                        }
                         let batch = PulseBatch {
                             chip_id: section.chip_id, // Approximation: assumes pulse doesn't cross chips differently
                             tdc_timestamp: old_tdc,
                             tdc_epoch: self.tdc_epoch,
-                            hits: std::mem::take(&mut self.curr_batch),
+                            hits,
                         };
                         self.prev_batch = Some(batch);
                     }
@@ -198,6 +505,7 @@ where
                     }
                     self.curr_tdc = Some(new_tdc);
                     self.last_tdc = Some(new_tdc);
+                    self.synthetic_pulse = synthetic_tdc.is_some();
 
                     // If we have items in ready_queue, return immediately.
                     // This pauses parsing, preserving state.
@@ -211,13 +519,23 @@ where
                     // 2. `prev_batch` (the pulse before that, for late hits)
 
                     let (local_x, local_y) = packet.pixel_coordinates();
-                    let (gx, gy) = (self.chip_transform)(section.chip_id, local_x, local_y);
-                    let raw_ts = packet.timestamp_coarse();
+                    let Some((gx, gy)) = (self.chip_transform)(section.chip_id, local_x, local_y)
+                    else {
+                        continue;
+                    };
+                    let raw_ts = self.hit_timestamp(packet, local_x);
                     let tot = packet.tot();
                     let chip = section.chip_id;
+                    let raw = self
+                        .retain_raw
+                        .then(|| (local_x, local_y, packet.timestamp_coarse()));
 
                     // Logic to assign hit
                     let mut assigned_to_prev = false;
+                    let prev_tof = self.prev_batch.as_ref().and_then(|prev| {
+                        let ts_prev = correct_timestamp_rollover(raw_ts, prev.tdc_timestamp);
+                        self.tof(ts_prev, prev.tdc_timestamp, self.prev_spacing)
+                    });
 
                     if let Some(ref mut prev) = self.prev_batch {
                         // Check fit with prev
@@ -257,8 +575,13 @@ where
                             if ts_curr < curr_tdc {
                                 // Definitely prev
                                 // Calculate correct TOF relative to prev
-                                let tof = calculate_tof(ts_prev, prev_tdc, self.tdc_correction);
-                                prev.hits.push((gx, gy, tof, tot, ts_prev, chip));
+                                if let Some(tof) = prev_tof {
+                                    push_hit(
+                                        &mut prev.hits,
+                                        (gx, gy, tof, tot, ts_prev, chip),
+                                        raw,
+                                    );
+                                }
                                 assigned_to_prev = true;
                             } else {
                                 // It is >= curr_tdc.
@@ -283,8 +606,13 @@ where
                         // Assign to current
                         if let Some(curr_tdc) = self.curr_tdc {
                             let ts_curr = correct_timestamp_rollover(raw_ts, curr_tdc);
-                            let tof = calculate_tof(ts_curr, curr_tdc, self.tdc_correction);
-                            self.curr_batch.push((gx, gy, tof, tot, ts_curr, chip));
+                            if let Some(tof) = self.tof(ts_curr, curr_tdc, None) {
+                                push_hit(
+                                    &mut self.curr_batch,
+                                    (gx, gy, tof, tot, ts_curr, chip),
+                                    raw,
+                                );
+                            }
                         }
                     }
                 }
@@ -304,6 +632,12 @@ where
 
         if let Some(curr_tdc) = self.curr_tdc.take() {
             if !self.curr_batch.is_empty() {
+                let spacing = self
+                    .next_tdc
+                    .and_then(|next| self.interpolated_spacing(curr_tdc, next));
+                if let Some(spacing) = spacing {
+                    reinterpolate(&mut self.curr_batch, curr_tdc, spacing);
+                }
                 self.curr_batch.sort_by_tof();
                 self.ready_queue.push_back(PulseBatch {
                     chip_id: last_chip,
@@ -333,12 +667,41 @@ where
 {
     /// Construct a time-ordered stream from per-chip sections.
     pub fn new(data: D, sections: &[Tpx3Section], config: &DetectorConfig) -> Self {
+        Self::build(data, sections, None, config)
+    }
+
+    /// Construct a time-ordered stream from a tail of a file's sections.
+    ///
+    /// `contexts[i]` is the pulse state of `sections[i]`'s chip before that
+    /// section (see [`continue_pulse_contexts`]); each chip starts from the
+    /// context of its first section. Hits before a chip's first TDC are
+    /// dropped.
+    pub fn with_contexts(
+        data: D,
+        sections: &[Tpx3Section],
+        contexts: &[PulseContext],
+        config: &DetectorConfig,
+    ) -> Self {
+        Self::build(data, sections, Some(contexts), config)
+    }
+
+    fn build(
+        data: D,
+        sections: &[Tpx3Section],
+        contexts: Option<&[PulseContext]>,
+        config: &DetectorConfig,
+    ) -> Self {
         // Group sections by chip
         let max_chip = sections.iter().map(|s| s.chip_id).max().unwrap_or(0);
         let mut sections_by_chip: Vec<Vec<Tpx3Section>> = vec![Vec::new(); (max_chip + 1) as usize];
+        let mut first_contexts = vec![None; (max_chip + 1) as usize];
 
-        for section in sections {
-            sections_by_chip[section.chip_id as usize].push(section.clone());
+        for (i, section) in sections.iter().enumerate() {
+            let chip = section.chip_id as usize;
+            if sections_by_chip[chip].is_empty() {
+                first_contexts[chip] = contexts.and_then(|contexts| contexts.get(i).copied());
+            }
+            sections_by_chip[chip].push(section.clone());
         }
 
         let tdc_correction = config.tdc_correction_25ns();
@@ -350,20 +713,26 @@ where
                 continue;
             }
 
-            let transform = config
-                .chip_transforms
-                .get(chip_id)
-                .cloned()
-                .unwrap_or_else(crate::ChipTransform::identity);
+            let transform = config.canvas_transform(u8::try_from(chip_id).unwrap_or(u8::MAX));
 
-            let transform_closure = move |_cid, x, y| transform.apply(x, y);
+            let transform_closure = move |_cid, x, y| transform.map(x, y);
 
             let mut reader = PulseReader::new(
                 data.clone(),
                 &chip_sections,
                 tdc_correction,
                 transform_closure,
-            );
+            )
+            .with_toa_correction(
+                config.toa_correction(u8::try_from(chip_id).unwrap_or(u8::MAX)),
+                config.chip_size_x.max(256),
+            )
+            .with_no_tdc_fallback(config.no_tdc_fallback)
+            .with_tdc_correction(config.tdc_correction_policy)
+            .with_raw_hits(config.retain_raw_hits);
+            if let Some(context) = first_contexts[chip_id] {
+                reader = reader.with_context(context);
+            }
 
             if let Some(batch) = reader.next_pulse() {
                 heap.push(batch);
@@ -431,6 +800,147 @@ where
     }
 }
 
+/// Decode all hits in time order, parsing sections in parallel.
+///
+/// Yields the same hits in the same pulse order as collecting a
+/// [`TimeOrderedStream`]; only hits with equal TOF within a pulse may come
+/// out in a different order.
+///
+/// Synthetic pulses ([`NoTdcFallback::FirstHit`]) depend on every earlier
+/// hit of the chip, so with that fallback the file is decoded sequentially.
+#[must_use]
+pub fn read_time_ordered_parallel(
+    data: &[u8],
+    sections: &[Tpx3Section],
+    config: &DetectorConfig,
+) -> HitBatch {
+    if config.no_tdc_fallback != NoTdcFallback::Drop {
+        let mut batch = HitBatch::default();
+        for pulse in TimeOrderedStream::new(data, sections, config) {
+            batch.append(&pulse);
+        }
+        return batch;
+    }
+    let contexts = section_pulse_contexts(data, sections);
+    let pulses = read_pulses_with_contexts(data, sections, &contexts, config);
+    let mut batch = HitBatch::with_capacity(pulses.iter().map(|p| p.hits.len()).sum());
+    for pulse in &pulses {
+        batch.append(&pulse.hits);
+    }
+    batch
+}
+
+/// Decode sections in parallel into merged pulses, in TDC order.
+///
+/// `contexts[i]` is the pulse state of `sections[i]`'s chip before that
+/// section (see [`section_pulse_contexts`]), so `sections` may be any tail of
+/// a file's sections, e.g. to resume decoding a file that is still being
+/// written. Hits before a chip's first TDC are dropped. Under
+/// [`TdcCorrectionPolicy::InterpolateNeighbors`], the pulse a section ends
+/// in is interpolated up to the chip's next TDC in a later section.
+#[must_use]
+pub fn read_pulses_with_contexts(
+    data: &[u8],
+    sections: &[Tpx3Section],
+    contexts: &[PulseContext],
+    config: &DetectorConfig,
+) -> Vec<MergedPulseBatch> {
+    let tdc_correction = config.tdc_correction_25ns();
+    let columns = config.chip_size_x.max(256);
+    let next_tdcs = if config.tdc_correction_policy == TdcCorrectionPolicy::InterpolateNeighbors {
+        following_tdcs(data, sections)
+    } else {
+        vec![None; sections.len()]
+    };
+
+    let section_fragments: Vec<Vec<PulseBatch>> = sections
+        .par_iter()
+        .zip(contexts)
+        .zip(next_tdcs)
+        .map(|((section, &context), next_tdc)| {
+            let transform = config.canvas_transform(section.chip_id);
+            let mut reader = PulseReader::new(
+                data,
+                std::slice::from_ref(section),
+                tdc_correction,
+                move |_cid, x, y| transform.map(x, y),
+            )
+            .with_toa_correction(config.toa_correction(section.chip_id), columns)
+            .with_tdc_correction(config.tdc_correction_policy)
+            .with_next_tdc(next_tdc)
+            .with_raw_hits(config.retain_raw_hits)
+            .with_context(context);
+            std::iter::from_fn(|| reader.next_pulse())
+                .filter(|pulse| !pulse.hits.is_empty())
+                .collect()
+        })
+        .collect();
+
+    // Stable sort keeps the fragments of one chip's pulse in section order.
+    let mut fragments: Vec<PulseBatch> = section_fragments.into_iter().flatten().collect();
+    fragments.sort_by_key(|pulse| (pulse.extended_tdc(), pulse.chip_id));
+
+    fragments
+        .chunk_by(|a, b| a.extended_tdc() == b.extended_tdc())
+        .collect::<Vec<_>>()
+        .into_par_iter()
+        .map(|group| {
+            let mut merged = HitBatch::with_capacity(group.iter().map(|p| p.hits.len()).sum());
+            for pulse in group {
+                merged.append(&pulse.hits);
+            }
+            merged.sort_by_tof();
+            MergedPulseBatch {
+                tdc_timestamp: group[0].extended_tdc(),
+                hits: merged,
+            }
+        })
+        .collect()
+}
+
+/// First TDC of each section's chip in the sections after it.
+fn following_tdcs(data: &[u8], sections: &[Tpx3Section]) -> Vec<Option<u32>> {
+    let first_tdcs: Vec<Option<u32>> = sections
+        .par_iter()
+        .map(|section| {
+            data[section.start_offset..section.end_offset]
+                .chunks_exact(8)
+                .map(|chunk| {
+                    let mut bytes = [0u8; 8];
+                    bytes.copy_from_slice(chunk);
+                    Tpx3Packet::from_bytes(bytes)
+                })
+                .find(Tpx3Packet::is_tdc)
+                .map(|packet| packet.tdc_timestamp())
+        })
+        .collect();
+    let mut next = [None; 256];
+    let mut following = vec![None; sections.len()];
+    for (index, section) in sections.iter().enumerate().rev() {
+        let chip = usize::from(section.chip_id);
+        following[index] = next[chip];
+        if first_tdcs[index].is_some() {
+            next[chip] = first_tdcs[index];
+        }
+    }
+    following
+}
+
+/// Recompute the TOF of `hits` in the pulse started at `tdc` with the pulse
+/// spacing interpolated across missing TDCs.
+fn reinterpolate(hits: &mut HitBatch, tdc: u32, spacing: u32) {
+    for (tof, &timestamp) in hits.tof.iter_mut().zip(&hits.timestamp) {
+        *tof = timestamp.wrapping_sub(tdc) % spacing;
+    }
+}
+
+fn push_hit(batch: &mut HitBatch, hit: HitRecord, raw: Option<RawHitFields>) {
+    match raw {
+        Some(raw) => batch.push_with_raw(hit, raw),
+        None => batch.push(hit),
+    }
+}
+
 fn reader_chip_id<D>(reader: &PulseReader<D>) -> u8
 where
     D: AsRef<[u8]> + Clone,
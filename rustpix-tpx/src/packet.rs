@@ -15,6 +15,13 @@
 /// - TDC packets (ID 0x6F):
 ///   - Bits 12-41: 30-bit TDC timestamp
 ///   - Bits 56-63: Packet type ID
+///
+/// - Frame-based pixel packets (ID 0xA*), same layout as hit packets; in
+///   event count & iToT mode bits 20-29 hold the event count and bits
+///   30-43 the integrated `ToT`
+///
+/// - End of readout (top 16 bits 0x71A0 or 0x71B0), sent by a chip after
+///   its pixel matrix has been read out
 #[derive(Clone, Copy, Debug)]
 pub struct Tpx3Packet(u64);
 
@@ -57,6 +64,21 @@ impl Tpx3Packet {
         (self.0 >> 60) & 0xF == 0xB
     }
 
+    /// Check if this is a frame-based pixel packet (ID 0xA*).
+    #[inline]
+    #[must_use]
+    pub const fn is_frame_pixel(&self) -> bool {
+        (self.0 >> 60) & 0xF == 0xA
+    }
+
+    /// Check if this is an end-of-readout packet, which closes a frame.
+    #[inline]
+    #[must_use]
+    pub const fn is_end_of_readout(&self) -> bool {
+        let command = (self.0 >> 48) & 0xFFFF;
+        command == 0x71A0 || command == 0x71B0
+    }
+
     /// Get packet type identifier.
     #[inline]
     #[must_use]
@@ -92,6 +114,20 @@ impl Tpx3Packet {
         ((self.0 >> 20) & 0x3FF) as u16
     }
 
+    /// Get 10-bit event count of a frame-based pixel packet.
+    #[inline]
+    #[must_use]
+    pub const fn event_count(&self) -> u16 {
+        self.tot()
+    }
+
+    /// Get 14-bit integrated `ToT` of a frame-based pixel packet.
+    #[inline]
+    #[must_use]
+    pub const fn integrated_tot(&self) -> u16 {
+        self.toa()
+    }
+
     /// Get 4-bit fine `ToA`.
     #[inline]
     #[must_use]
@@ -147,6 +183,41 @@ impl Tpx3Packet {
         Self::new(u64::from_le_bytes(bytes))
     }
 
+    /// Build a chip header packet announcing a chunk of `chunk_bytes` bytes
+    /// (bits 48-63) from chip `chip_id`.
+    #[must_use]
+    pub fn header(chip_id: u8, chunk_bytes: u16) -> Self {
+        Self::new(
+            Self::TPX3_HEADER_MAGIC | (u64::from(chip_id) << 32) | (u64::from(chunk_bytes) << 48),
+        )
+    }
+
+    /// Build a TDC packet with a 30-bit `timestamp` (higher bits are dropped).
+    #[must_use]
+    pub fn tdc(timestamp: u32) -> Self {
+        Self::new(0x6F00_0000_0000_0000 | (u64::from(timestamp & 0x3FFF_FFFF) << 12))
+    }
+
+    /// Build a hit packet at chip-local pixel (`x`, `y`) with a 30-bit
+    /// coarse `timestamp` (25 ns ticks) and a 10-bit `tot`; the inverse of
+    /// [`Self::pixel_coordinates`] and [`Self::timestamp_coarse`].
+    #[must_use]
+    pub fn hit(x: u16, y: u16, timestamp: u32, tot: u16) -> Self {
+        let dcol = u64::from(x & 0xFE);
+        let spix = u64::from(y & 0xFC);
+        let pix = u64::from(((x & 1) << 2) | (y & 3));
+        let address = (dcol << 8) | (spix << 1) | pix;
+        let toa = u64::from(timestamp & 0x3FFF);
+        let spidr = u64::from((timestamp >> 14) & 0xFFFF);
+        Self::new(
+            0xB000_0000_0000_0000
+                | (address << 44)
+                | (toa << 30)
+                | (u64::from(tot & 0x3FF) << 20)
+                | spidr,
+        )
+    }
+
     /// Alias for `is_hit` - checks if this is pixel data.
     #[inline]
     #[must_use]
@@ -206,6 +277,26 @@ mod tests {
         assert_eq!(y, 0);
     }
 
+    #[test]
+    fn test_packet_builders_round_trip() {
+        let header = Tpx3Packet::header(3, 64);
+        assert!(header.is_header());
+        assert_eq!(header.chip_id(), 3);
+
+        let tdc = Tpx3Packet::tdc(0x4000_0123);
+        assert!(tdc.is_tdc());
+        assert_eq!(tdc.tdc_timestamp(), 0x123);
+
+        for (x, y) in [(0, 0), (1, 2), (254, 3), (255, 255), (128, 77)] {
+            let hit = Tpx3Packet::hit(x, y, 0x2ABC_DEF1, 1023);
+            assert!(hit.is_hit());
+            assert_eq!(hit.pixel_coordinates(), (x, y));
+            assert_eq!(hit.timestamp_coarse(), 0x2ABC_DEF1);
+            assert_eq!(hit.tot(), 1023);
+            assert_eq!(hit.fine_toa(), 0);
+        }
+    }
+
     #[test]
     fn test_tdc_timestamp_extraction() {
         // TDC packet with timestamp value
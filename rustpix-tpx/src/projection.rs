@@ -0,0 +1,103 @@
+//! Column projection for hit parsing.
+//!
+//! Imaging workflows that only histogram hit positions do not need arrival
+//! times. Without the time columns, sections are decoded independently with
+//! no TDC tracking, pulse ordering, rollover correction or TOF computation,
+//! which is most of the parsing cost.
+
+use crate::ordering::read_time_ordered_parallel;
+use crate::packet::Tpx3Packet;
+use crate::section::Tpx3Section;
+use crate::DetectorConfig;
+use rayon::prelude::*;
+use rustpix_core::soa::HitBatch;
+
+/// Hit columns to decode; `x`, `y` and `chip_id` are always decoded.
+///
+/// Columns left out are filled with zeros.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct HitColumns {
+    /// Decode `tot`.
+    pub tot: bool,
+    /// Decode `tof` and `timestamp`.
+    pub time: bool,
+}
+
+impl HitColumns {
+    /// Every column.
+    pub const ALL: Self = Self {
+        tot: true,
+        time: true,
+    };
+
+    /// Positions only.
+    pub const POSITIONS: Self = Self {
+        tot: false,
+        time: false,
+    };
+}
+
+impl Default for HitColumns {
+    fn default() -> Self {
+        Self::ALL
+    }
+}
+
+/// Read the requested hit columns of a whole file.
+///
+/// With the time columns this is [`read_time_ordered_parallel`]. Without
+/// them, hits come in file order, sections are decoded in parallel, and
+/// hits are kept whether or not a TDC preceded them.
+#[must_use]
+pub fn read_columns_parallel(
+    data: &[u8],
+    sections: &[Tpx3Section],
+    config: &DetectorConfig,
+    columns: HitColumns,
+) -> HitBatch {
+    if columns.time {
+        let mut batch = read_time_ordered_parallel(data, sections, config);
+        if !columns.tot {
+            batch.tot.fill(0);
+        }
+        return batch;
+    }
+
+    let parts: Vec<HitBatch> = sections
+        .par_iter()
+        .map(|section| decode_positions(data, section, config, columns.tot))
+        .collect();
+    let mut batch = HitBatch::with_capacity(parts.iter().map(HitBatch::len).sum());
+    for part in &parts {
+        batch.append(part);
+    }
+    batch
+}
+
+/// Decode the positions (and optionally `ToT`) of one section's hits.
+fn decode_positions(
+    data: &[u8],
+    section: &Tpx3Section,
+    config: &DetectorConfig,
+    with_tot: bool,
+) -> HitBatch {
+    let transform = config.canvas_transform(section.chip_id);
+    let chip = section.chip_id;
+    let section_data = &data[section.start_offset..section.end_offset];
+    let mut batch = HitBatch::with_capacity(section_data.len() / 8);
+    for bytes in section_data.chunks_exact(8) {
+        let mut raw = [0u8; 8];
+        raw.copy_from_slice(bytes);
+        let packet = Tpx3Packet::new(u64::from_le_bytes(raw));
+        if !packet.is_hit() {
+            continue;
+        }
+        let (local_x, local_y) = packet.pixel_coordinates();
+        let Some((x, y)) = transform.map(local_x, local_y) else {
+            continue;
+        };
+        let tot = if with_tot { packet.tot() } else { 0 };
+        batch.push((x, y, 0, tot, 0, chip));
+    }
+    batch
+}
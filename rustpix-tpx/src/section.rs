@@ -177,6 +177,22 @@ pub fn scan_section_tdc(data: &[u8], section: &Tpx3Section) -> Option<u32> {
     final_tdc
 }
 
+/// Length of the prefix of a file that is still being written that can be
+/// parsed safely: whole packets, without trailing all-zero packets.
+///
+/// A writer may extend a file before filling it, and network filesystems
+/// can show the new size before the data, so the tail of a growing file
+/// may read as zeros for a while. No TPX3 packet is all zeros, so such a
+/// tail is left for a later read instead of being taken as data.
+#[must_use]
+pub fn parseable_len(data: &[u8]) -> usize {
+    let mut len = data.len() / PACKET_SIZE * PACKET_SIZE;
+    while len >= PACKET_SIZE && data[len - PACKET_SIZE..len].iter().all(|&byte| byte == 0) {
+        len -= PACKET_SIZE;
+    }
+    len
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -288,4 +304,24 @@ mod tests {
         assert_eq!(batch.tof[0], 100);
         assert_eq!(batch.chip_id[0], 0);
     }
+
+    #[test]
+    fn test_parseable_len_skips_partial_and_unwritten_tail() {
+        let mut data = Vec::new();
+        data.extend_from_slice(&make_header(0).to_le_bytes());
+        data.extend_from_slice(&make_tdc(1000).to_le_bytes());
+        assert_eq!(parseable_len(&data), 16);
+
+        // Half of the next packet, then space the writer has not filled.
+        data.extend_from_slice(&make_hit(1100, 10, 0).to_le_bytes()[..5]);
+        assert_eq!(parseable_len(&data), 16);
+        data.truncate(16);
+        data.extend_from_slice(&[0; 24]);
+        assert_eq!(parseable_len(&data), 16);
+
+        data.truncate(16);
+        data.extend_from_slice(&make_hit(1100, 10, 0).to_le_bytes());
+        assert_eq!(parseable_len(&data), 24);
+        assert_eq!(parseable_len(&[0; 7]), 0);
+    }
 }
@@ -0,0 +1,299 @@
+//! Synthetic TPX3 files with known ground truth.
+//!
+//! [`generate`] writes a valid raw TPX3 stream for a [`DetectorConfig`]: for
+//! every pulse and chip, a chip header and the pulse's TDC packet followed by
+//! the chip's hits, split into chunks of at most
+//! [`SyntheticTpx3Config::chunk_packets`] packets. Each neutron lands on a
+//! random chip and leaves a [`Footprint`] of hits that is symmetric around
+//! its center pixel, with the highest `ToT` at the center, so the
+//! `ToT`-weighted centroid is the center pixel and the representative TOF is
+//! the center hit's. The ring hits arrive one tick later, as lower charges
+//! do.
+//!
+//! Every neutron gets its own equal slice of the pulse, so neutrons are
+//! [`NEUTRON_GAP_TICKS`] apart in time as long as a slice is longer than
+//! that, and every clustering algorithm should recover them one to one. The
+//! generator is seeded, so a config always gives the same file.
+
+use std::io;
+use std::path::Path;
+
+use crate::{DetectorConfig, Tpx3Packet};
+use rustpix_core::SplitMix64;
+
+/// Least TOF distance between the hits of two neutrons of a pulse (25 ns
+/// ticks), well above the default 75 ns clustering window.
+pub const NEUTRON_GAP_TICKS: u32 = 8;
+
+/// Most packets after one chip header; the chunk size field counts bytes in
+/// 16 bits.
+const MAX_CHUNK_PACKETS: usize = 8_191;
+
+/// Timestamps wrap at 30 bits.
+const TIMESTAMP_MASK: u32 = 0x3FFF_FFFF;
+
+/// Hits a neutron leaves around its center pixel.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Footprint {
+    /// The center pixel only.
+    Single,
+    /// The center and its four edge neighbours.
+    #[default]
+    Cross,
+    /// The 3x3 square around the center.
+    Square,
+}
+
+impl Footprint {
+    /// Number of hits per neutron.
+    #[must_use]
+    pub fn hits(self) -> usize {
+        self.offsets().count()
+    }
+
+    /// Offsets from the center with the `ToT` divisor of each.
+    fn offsets(self) -> impl Iterator<Item = (i32, i32, u16)> {
+        const CROSS: [(i32, i32, u16); 5] =
+            [(0, 0, 1), (0, -1, 2), (-1, 0, 2), (1, 0, 2), (0, 1, 2)];
+        const CORNERS: [(i32, i32, u16); 4] = [(-1, -1, 4), (1, -1, 4), (-1, 1, 4), (1, 1, 4)];
+        let (cross, corners) = match self {
+            Self::Single => (1, 0),
+            Self::Cross => (5, 0),
+            Self::Square => (5, 4),
+        };
+        CROSS
+            .into_iter()
+            .take(cross)
+            .chain(CORNERS.into_iter().take(corners))
+    }
+}
+
+/// Configuration of a synthetic TPX3 file.
+#[derive(Clone, Debug)]
+pub struct SyntheticTpx3Config {
+    /// Number of TDC pulses.
+    pub pulses: usize,
+    /// Neutrons per pulse.
+    pub neutrons_per_pulse: usize,
+    /// Hits of every neutron.
+    pub footprint: Footprint,
+    /// Timestamp of the first TDC (25 ns ticks); set it close to 2^30 to
+    /// cover timestamp rollover.
+    pub first_tdc: u32,
+    /// Most packets after a chip header (at most 8191).
+    pub chunk_packets: usize,
+    /// Random seed.
+    pub seed: u64,
+}
+
+impl Default for SyntheticTpx3Config {
+    /// Ten pulses of 100 neutrons with a cross footprint.
+    fn default() -> Self {
+        Self {
+            pulses: 10,
+            neutrons_per_pulse: 100,
+            footprint: Footprint::Cross,
+            first_tdc: 1_000,
+            chunk_packets: 1_024,
+            seed: 0x5EED,
+        }
+    }
+}
+
+/// A neutron written to a synthetic file.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TrueNeutron {
+    /// Index of its pulse.
+    pub pulse: usize,
+    /// Chip it landed on.
+    pub chip_id: u8,
+    /// Global X of the center pixel.
+    pub x: f64,
+    /// Global Y of the center pixel.
+    pub y: f64,
+    /// TOF of the center hit (25 ns ticks).
+    pub tof: u32,
+    /// Number of hits.
+    pub hits: usize,
+}
+
+/// A synthetic TPX3 stream and what went into it.
+#[derive(Clone, Debug, Default)]
+pub struct SyntheticTpx3 {
+    /// Raw file contents.
+    pub data: Vec<u8>,
+    /// TDC timestamp of each pulse as written (wrapped to 30 bits).
+    pub tdc_timestamps: Vec<u32>,
+    /// Neutrons in pulse and TOF order.
+    pub neutrons: Vec<TrueNeutron>,
+    /// Number of hit packets.
+    pub hits: usize,
+}
+
+impl SyntheticTpx3 {
+    /// Write the stream to a `.tpx3` file.
+    ///
+    /// # Errors
+    /// Returns an error if the file cannot be written.
+    pub fn write<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        std::fs::write(path, &self.data)
+    }
+}
+
+/// Generate a TPX3 stream for `detector`; see the module docs.
+///
+/// The pulse period follows `detector.tdc_frequency_hz`, and positions are
+/// mapped with the detector's chip transforms (and output geometry), as the
+/// readers do. Detectors without transforms get a single chip 0.
+#[must_use]
+pub fn generate(config: &SyntheticTpx3Config, detector: &DetectorConfig) -> SyntheticTpx3 {
+    let mut rng = SplitMix64::new(config.seed);
+    let chips = u8::try_from(detector.chip_transforms.len().clamp(1, 255)).unwrap_or(u8::MAX);
+    let period = detector.tdc_correction_25ns().max(1);
+    let slot = period / u32::try_from(config.neutrons_per_pulse.max(1)).unwrap_or(u32::MAX);
+    let spread = slot.saturating_sub(NEUTRON_GAP_TICKS).max(1);
+    let chunk_packets = config.chunk_packets.clamp(1, MAX_CHUNK_PACKETS);
+    // Centers stay a pixel off the chip edge so every footprint is whole.
+    let span_x = detector.chip_size_x.saturating_sub(2).max(1);
+    let span_y = detector.chip_size_y.saturating_sub(2).max(1);
+
+    let mut out = SyntheticTpx3::default();
+    let mut chip_hits: Vec<Vec<Tpx3Packet>> = vec![Vec::new(); usize::from(chips)];
+    for pulse in 0..config.pulses {
+        let pulse_offset = u32::try_from(pulse)
+            .unwrap_or(u32::MAX)
+            .wrapping_mul(period);
+        let tdc = config.first_tdc.wrapping_add(pulse_offset) & TIMESTAMP_MASK;
+        out.tdc_timestamps.push(tdc);
+
+        for index in 0..config.neutrons_per_pulse {
+            let slot_start = u32::try_from(index)
+                .unwrap_or(u32::MAX)
+                .saturating_mul(slot);
+            let chip_id = rng.below(chips);
+            let center_x = 1 + rng.below(span_x);
+            let center_y = 1 + rng.below(span_y);
+            let tof = (slot_start + rng.below(spread)).min(period.saturating_sub(2));
+            let tot = 60 + rng.below(240u16);
+
+            let timestamp = tdc.wrapping_add(tof);
+            for (dx, dy, divisor) in config.footprint.offsets() {
+                let x = offset(center_x, dx);
+                let y = offset(center_y, dy);
+                let delay = u32::from(divisor > 1);
+                let time = timestamp.wrapping_add(delay) & TIMESTAMP_MASK;
+                chip_hits[usize::from(chip_id)].push(Tpx3Packet::hit(x, y, time, tot / divisor));
+            }
+            let (x, y) = detector.map_chip_to_global(chip_id, center_x, center_y);
+            out.neutrons.push(TrueNeutron {
+                pulse,
+                chip_id,
+                x: f64::from(x),
+                y: f64::from(y),
+                tof,
+                hits: config.footprint.hits(),
+            });
+        }
+
+        for (chip_id, hits) in (0..chips).zip(chip_hits.iter_mut()) {
+            out.hits += hits.len();
+            let mut packets = std::iter::once(Tpx3Packet::tdc(tdc)).chain(hits.drain(..));
+            loop {
+                let chunk: Vec<Tpx3Packet> = packets.by_ref().take(chunk_packets).collect();
+                if chunk.is_empty() {
+                    break;
+                }
+                let bytes = u16::try_from(chunk.len() * 8).unwrap_or(u16::MAX);
+                push_packet(&mut out.data, Tpx3Packet::header(chip_id, bytes));
+                for packet in chunk {
+                    push_packet(&mut out.data, packet);
+                }
+            }
+        }
+    }
+    out
+}
+
+fn push_packet(data: &mut Vec<u8>, packet: Tpx3Packet) {
+    data.extend_from_slice(&packet.raw().to_le_bytes());
+}
+
+fn offset(center: u16, delta: i32) -> u16 {
+    u16::try_from(i32::from(center) + delta).unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::section::{discover_sections, process_section_into_batch};
+    use rustpix_core::soa::HitBatch;
+
+    fn parse(data: &[u8], detector: &DetectorConfig) -> HitBatch {
+        let mut batch = HitBatch::default();
+        let correction = detector.tdc_correction_25ns();
+        for section in discover_sections(data) {
+            process_section_into_batch(
+                data,
+                &section,
+                correction,
+                |chip, x, y| detector.map_chip_to_global(chip, x, y),
+                &mut batch,
+            );
+        }
+        batch
+    }
+
+    #[test]
+    fn test_generated_hits_match_ground_truth() {
+        let detector = DetectorConfig::venus_defaults();
+        let config = SyntheticTpx3Config {
+            footprint: Footprint::Square,
+            chunk_packets: 100,
+            ..SyntheticTpx3Config::default()
+        };
+        let file = generate(&config, &detector);
+        assert_eq!(file.neutrons.len(), 1_000);
+        assert_eq!(file.hits, 9_000);
+        assert_eq!(file.tdc_timestamps.len(), 10);
+        assert!(file.neutrons.windows(2).all(|pair| {
+            pair[0].pulse < pair[1].pulse || pair[1].tof >= pair[0].tof + NEUTRON_GAP_TICKS
+        }));
+
+        let batch = parse(&file.data, &detector);
+        assert_eq!(batch.len(), file.hits);
+        // Every neutron's center hit is where the ground truth puts it.
+        for neutron in &file.neutrons {
+            let found = (0..batch.len()).any(|i| {
+                (f64::from(batch.x[i]) - neutron.x).abs() < 0.5
+                    && (f64::from(batch.y[i]) - neutron.y).abs() < 0.5
+                    && batch.tof[i] == neutron.tof
+                    && batch.chip_id[i] == neutron.chip_id
+            });
+            assert!(found, "{neutron:?}");
+        }
+
+        assert_eq!(generate(&config, &detector).data, file.data);
+    }
+
+    #[test]
+    fn test_timestamps_roll_over() {
+        let detector = DetectorConfig::venus_defaults();
+        let config = SyntheticTpx3Config {
+            pulses: 4,
+            neutrons_per_pulse: 10,
+            first_tdc: TIMESTAMP_MASK - 1_000_000,
+            ..SyntheticTpx3Config::default()
+        };
+        let file = generate(&config, &detector);
+        assert!(file.tdc_timestamps[3] < file.tdc_timestamps[0]);
+
+        let batch = parse(&file.data, &detector);
+        let mut tofs = batch.tof.clone();
+        tofs.sort_unstable();
+        let mut expected: Vec<u32> = file.neutrons.iter().map(|n| n.tof).collect();
+        expected.sort_unstable();
+        // Center hits carry the true TOF, the ring hits one tick more.
+        assert!(expected.iter().all(|tof| tofs.binary_search(tof).is_ok()));
+        assert!(tofs.iter().all(|&tof| tof < detector.tdc_correction_25ns()));
+    }
+}
@@ -0,0 +1,621 @@
+//! TDC frequency estimation from inter-TDC spacing.
+//!
+//! The configured TDC frequency sets the TOF correction applied to every
+//! hit, so a mismatch (e.g. a 30 Hz chopper run processed with the 60 Hz
+//! default) silently folds the spectrum. The estimator walks the TDC packets
+//! of each chip, takes the spacing between consecutive pulses (with 30-bit
+//! rollover handled), and reports the median spacing of the chip with the
+//! most pulses. The median ignores the occasional doubled interval left by a
+//! dropped TDC.
+//!
+//! [`find_pulse_gaps`] uses the same spacing to list dropped pulses: an
+//! interval of about k periods means k - 1 pulses are missing. Gaps longer
+//! than the 30-bit counter period (~26.8 s) alias and cannot be measured
+//! from TDCs alone.
+//!
+//! [`diagnose_tdc_sections`] classifies every TDC interval per section, as
+//! missing pulses, duplicate TDCs (less than half a period after the last)
+//! or irregular spacing (off a whole number of periods), to judge which
+//! [`crate::TdcCorrectionPolicy`] a file needs.
+//!
+//! [`summarize_tdc_coverage`] counts hits and TDCs per file so empty files,
+//! files with TDCs but no hits, and hits that no TDC precedes can be
+//! flagged before processing instead of silently producing nothing.
+
+use crate::packet::Tpx3Packet;
+use crate::section::Tpx3Section;
+use std::fmt;
+use std::time::Duration;
+
+const PACKET_SIZE: usize = 8;
+
+/// Mask of the 30-bit TDC counter.
+const TDC_MASK: u32 = 0x3FFF_FFFF;
+
+/// Length of one TDC tick in seconds.
+const TICK_SECONDS: f64 = 25e-9;
+
+/// Default relative tolerance before a measured frequency counts as a
+/// disagreement with the configured one.
+pub const DEFAULT_TDC_FREQUENCY_TOLERANCE: f64 = 0.01;
+
+/// Relative deviation from a whole number of periods beyond which a TDC
+/// interval counts as irregular.
+const IRREGULAR_INTERVAL_TOLERANCE: f64 = 0.01;
+
+/// Intervals kept per chip; enough for a stable median without scanning
+/// whole multi-hour files.
+const MAX_INTERVALS_PER_CHIP: usize = 4096;
+
+/// TDC frequency measured from the data.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TdcFrequencyEstimate {
+    /// Chip whose TDC stream was used.
+    pub chip_id: u8,
+    /// Median spacing between consecutive TDCs in 25 ns ticks.
+    pub median_interval_25ns: u32,
+    /// Number of intervals the median was taken over.
+    pub intervals: usize,
+    /// Estimated frequency in Hz.
+    pub frequency_hz: f64,
+}
+
+impl TdcFrequencyEstimate {
+    /// Build an estimate from TDC intervals (25 ns ticks) of one chip.
+    ///
+    /// Reorders `intervals`. Returns `None` if there are no intervals.
+    #[must_use]
+    pub fn from_intervals(chip_id: u8, intervals: &mut [u32]) -> Option<Self> {
+        if intervals.is_empty() {
+            return None;
+        }
+        let mid = intervals.len() / 2;
+        let median = *intervals.select_nth_unstable(mid).1;
+        Some(Self {
+            chip_id,
+            median_interval_25ns: median,
+            intervals: intervals.len(),
+            frequency_hz: 1.0 / (f64::from(median) * TICK_SECONDS),
+        })
+    }
+
+    /// Relative difference `|measured - configured| / configured`.
+    #[must_use]
+    pub fn relative_difference(&self, configured_hz: f64) -> f64 {
+        if configured_hz > 0.0 {
+            ((self.frequency_hz - configured_hz) / configured_hz).abs()
+        } else {
+            f64::INFINITY
+        }
+    }
+
+    /// Returns true if the measured frequency is off by more than
+    /// `tolerance` (relative) from `configured_hz`.
+    #[must_use]
+    pub fn disagrees_with(&self, configured_hz: f64, tolerance: f64) -> bool {
+        self.relative_difference(configured_hz) > tolerance
+    }
+}
+
+/// Estimate the TDC frequency from the TDC packets in `sections`.
+///
+/// Returns `None` if no chip has two or more TDC packets.
+#[must_use]
+pub fn estimate_tdc_frequency(
+    data: &[u8],
+    sections: &[Tpx3Section],
+) -> Option<TdcFrequencyEstimate> {
+    let mut previous: [Option<u32>; 256] = [None; 256];
+    let mut intervals: Vec<Vec<u32>> = vec![Vec::new(); 256];
+
+    for section in sections {
+        let chip = usize::from(section.chip_id);
+        if intervals[chip].len() >= MAX_INTERVALS_PER_CHIP {
+            continue;
+        }
+        let section_data = &data[section.start_offset..section.end_offset];
+        for chunk in section_data.chunks_exact(PACKET_SIZE) {
+            let mut bytes = [0u8; PACKET_SIZE];
+            bytes.copy_from_slice(chunk);
+            let packet = Tpx3Packet::from_bytes(bytes);
+            if !packet.is_tdc() {
+                continue;
+            }
+            let tdc = packet.tdc_timestamp();
+            if let Some(last) = previous[chip] {
+                let interval = tdc.wrapping_sub(last) & TDC_MASK;
+                if interval > 0 {
+                    intervals[chip].push(interval);
+                }
+            }
+            previous[chip] = Some(tdc);
+        }
+    }
+
+    let (chip_id, chip_intervals) = intervals
+        .iter_mut()
+        .enumerate()
+        .max_by_key(|(_, chip_intervals)| chip_intervals.len())?;
+    TdcFrequencyEstimate::from_intervals(u8::try_from(chip_id).ok()?, chip_intervals)
+}
+
+/// Missing pulses after one TDC.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PulseGap {
+    /// Time of the last pulse before the gap, in 25 ns ticks since the first
+    /// TDC of the run.
+    pub after_25ns: u64,
+    /// Number of pulses missing in the gap.
+    pub missing: u32,
+}
+
+/// Dropped-pulse diagnostics for one run.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PulseGapReport {
+    /// Chip whose TDC stream was used.
+    pub chip_id: u8,
+    /// Expected pulse period in 25 ns ticks.
+    pub period_25ns: u32,
+    /// TDC packets seen on that chip.
+    pub pulses: usize,
+    /// Gaps in time order.
+    pub gaps: Vec<PulseGap>,
+}
+
+impl PulseGapReport {
+    /// Total number of missing pulses.
+    #[must_use]
+    pub fn missing_pulses(&self) -> u64 {
+        self.gaps.iter().map(|gap| u64::from(gap.missing)).sum()
+    }
+
+    /// Beam time covered by the missing pulses.
+    #[must_use]
+    pub fn lost_time(&self) -> Duration {
+        Duration::from_nanos(self.missing_pulses() * u64::from(self.period_25ns) * 25)
+    }
+}
+
+/// Per-chip state while looking for gaps.
+#[derive(Clone, Default)]
+struct PulseTrack {
+    previous: Option<u32>,
+    elapsed: u64,
+    pulses: usize,
+    gaps: Vec<PulseGap>,
+}
+
+/// List pulses missing from the TDC stream, given the expected period in
+/// 25 ns ticks (see [`crate::DetectorConfig::tdc_correction_25ns`]).
+///
+/// Intervals of at least 1.5 periods count as gaps, with the number of
+/// missing pulses rounded to the nearest whole period. The chip with the
+/// most TDC packets is used. Returns `None` if the period is zero or no chip
+/// has a TDC packet.
+#[must_use]
+pub fn find_pulse_gaps(
+    data: &[u8],
+    sections: &[Tpx3Section],
+    period_25ns: u32,
+) -> Option<PulseGapReport> {
+    if period_25ns == 0 {
+        return None;
+    }
+    let period = f64::from(period_25ns);
+    let mut tracks = vec![PulseTrack::default(); 256];
+
+    for section in sections {
+        let track = &mut tracks[usize::from(section.chip_id)];
+        let section_data = &data[section.start_offset..section.end_offset];
+        for chunk in section_data.chunks_exact(PACKET_SIZE) {
+            let mut bytes = [0u8; PACKET_SIZE];
+            bytes.copy_from_slice(chunk);
+            let packet = Tpx3Packet::from_bytes(bytes);
+            if !packet.is_tdc() {
+                continue;
+            }
+            let tdc = packet.tdc_timestamp();
+            track.pulses += 1;
+            if let Some(last) = track.previous {
+                let interval = tdc.wrapping_sub(last) & TDC_MASK;
+                let periods = (f64::from(interval) / period).round();
+                if periods >= 2.0 {
+                    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+                    let missing = periods as u32 - 1;
+                    track.gaps.push(PulseGap {
+                        after_25ns: track.elapsed,
+                        missing,
+                    });
+                }
+                track.elapsed += u64::from(interval);
+            }
+            track.previous = Some(tdc);
+        }
+    }
+
+    let (chip_id, track) = tracks
+        .into_iter()
+        .enumerate()
+        .filter(|(_, track)| track.pulses > 0)
+        .max_by_key(|(_, track)| track.pulses)?;
+    Some(PulseGapReport {
+        chip_id: u8::try_from(chip_id).ok()?,
+        period_25ns,
+        pulses: track.pulses,
+        gaps: track.gaps,
+    })
+}
+
+/// TDC irregularities of one section.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SectionTdcDiagnostics {
+    /// Index of the section in the file.
+    pub section: usize,
+    /// Chip of the section.
+    pub chip_id: u8,
+    /// Byte offset of the section's first packet.
+    pub start_offset: usize,
+    /// TDC packets in the section.
+    pub tdcs: usize,
+    /// Pulses missing before the section's TDCs.
+    pub missing: u64,
+    /// TDCs less than half a period after the previous one.
+    pub duplicate: usize,
+    /// TDCs more than 1% of a period off a whole number of periods after
+    /// the previous one.
+    pub irregular: usize,
+}
+
+impl SectionTdcDiagnostics {
+    /// Whether any TDC of the section is missing, duplicate or irregular.
+    #[must_use]
+    pub fn has_anomalies(&self) -> bool {
+        self.missing > 0 || self.duplicate > 0 || self.irregular > 0
+    }
+}
+
+/// Per-section TDC diagnostics of a file.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct TdcDiagnostics {
+    /// Expected pulse period in 25 ns ticks.
+    pub period_25ns: u32,
+    /// Sections with at least one TDC packet, in file order.
+    pub sections: Vec<SectionTdcDiagnostics>,
+}
+
+impl TdcDiagnostics {
+    /// TDC packets in the file.
+    #[must_use]
+    pub fn tdcs(&self) -> usize {
+        self.sections.iter().map(|section| section.tdcs).sum()
+    }
+
+    /// Pulses missing from the file.
+    #[must_use]
+    pub fn missing(&self) -> u64 {
+        self.sections.iter().map(|section| section.missing).sum()
+    }
+
+    /// Duplicate TDCs in the file.
+    #[must_use]
+    pub fn duplicate(&self) -> usize {
+        self.sections.iter().map(|section| section.duplicate).sum()
+    }
+
+    /// Irregularly spaced TDCs in the file.
+    #[must_use]
+    pub fn irregular(&self) -> usize {
+        self.sections.iter().map(|section| section.irregular).sum()
+    }
+
+    /// Sections with missing, duplicate or irregular TDCs.
+    pub fn anomalous_sections(&self) -> impl Iterator<Item = &SectionTdcDiagnostics> {
+        self.sections
+            .iter()
+            .filter(|section| section.has_anomalies())
+    }
+}
+
+/// Classify the TDC intervals of every section, given the expected period
+/// in 25 ns ticks.
+///
+/// Each TDC is compared with the previous TDC of its chip, which may be in
+/// an earlier section. An interval of about k periods (k ≥ 2) counts k - 1
+/// missing pulses; one under half a period is a duplicate; one more than
+/// 1% of a period off its nearest whole number of periods is irregular.
+/// Returns `None` if the period is zero.
+#[must_use]
+pub fn diagnose_tdc_sections(
+    data: &[u8],
+    sections: &[Tpx3Section],
+    period_25ns: u32,
+) -> Option<TdcDiagnostics> {
+    if period_25ns == 0 {
+        return None;
+    }
+    let period = f64::from(period_25ns);
+    let mut previous: [Option<u32>; 256] = [None; 256];
+    let mut diagnostics = TdcDiagnostics {
+        period_25ns,
+        sections: Vec::new(),
+    };
+
+    for (index, section) in sections.iter().enumerate() {
+        let chip = usize::from(section.chip_id);
+        let mut entry = SectionTdcDiagnostics {
+            section: index,
+            chip_id: section.chip_id,
+            start_offset: section.start_offset,
+            ..SectionTdcDiagnostics::default()
+        };
+        let section_data = &data[section.start_offset..section.end_offset];
+        for chunk in section_data.chunks_exact(PACKET_SIZE) {
+            let mut bytes = [0u8; PACKET_SIZE];
+            bytes.copy_from_slice(chunk);
+            let packet = Tpx3Packet::from_bytes(bytes);
+            if !packet.is_tdc() {
+                continue;
+            }
+            let tdc = packet.tdc_timestamp();
+            entry.tdcs += 1;
+            if let Some(last) = previous[chip] {
+                let interval = f64::from(tdc.wrapping_sub(last) & TDC_MASK);
+                let periods = (interval / period).round();
+                if periods < 1.0 {
+                    entry.duplicate += 1;
+                } else {
+                    if (interval - periods * period).abs() > IRREGULAR_INTERVAL_TOLERANCE * period {
+                        entry.irregular += 1;
+                    }
+                    if periods >= 2.0 {
+                        #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+                        let missing = periods as u64 - 1;
+                        entry.missing += missing;
+                    }
+                }
+            }
+            previous[chip] = Some(tdc);
+        }
+        if entry.tdcs > 0 {
+            diagnostics.sections.push(entry);
+        }
+    }
+    Some(diagnostics)
+}
+
+/// Hit and TDC counts of a file.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct TdcCoverage {
+    /// Packets in the file, headers included.
+    pub packets: usize,
+    /// Data-driven hit packets.
+    pub hits: usize,
+    /// TDC packets.
+    pub tdcs: usize,
+    /// Hits that arrive before the first TDC of their chip.
+    pub hits_before_tdc: usize,
+}
+
+/// Condition of a file that makes its output empty or incomplete.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CoverageFlag {
+    /// The file has no packets.
+    Empty,
+    /// The file has packets but no hits.
+    NoHits,
+    /// The file has hits but no TDC packets.
+    NoTdc,
+    /// Some hits come before the first TDC of their chip.
+    HitsBeforeTdc(usize),
+}
+
+impl fmt::Display for CoverageFlag {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Empty => write!(f, "file is empty"),
+            Self::NoHits => write!(f, "file has no hits"),
+            Self::NoTdc => write!(f, "file has hits but no TDC packets"),
+            Self::HitsBeforeTdc(hits) => write!(f, "{hits} hits before the first TDC"),
+        }
+    }
+}
+
+impl TdcCoverage {
+    /// Conditions to report, empty for a file with TDC-referenced hits only.
+    ///
+    /// [`CoverageFlag::NoTdc`] replaces [`CoverageFlag::HitsBeforeTdc`],
+    /// since then every hit comes before a TDC.
+    #[must_use]
+    pub fn flags(&self) -> Vec<CoverageFlag> {
+        if self.packets == 0 {
+            vec![CoverageFlag::Empty]
+        } else if self.hits == 0 {
+            vec![CoverageFlag::NoHits]
+        } else if self.tdcs == 0 {
+            vec![CoverageFlag::NoTdc]
+        } else if self.hits_before_tdc > 0 {
+            vec![CoverageFlag::HitsBeforeTdc(self.hits_before_tdc)]
+        } else {
+            Vec::new()
+        }
+    }
+}
+
+/// Count the hits and TDCs of a file.
+///
+/// `data` is the whole file; trailing bytes short of a packet are ignored.
+#[must_use]
+pub fn summarize_tdc_coverage(data: &[u8], sections: &[Tpx3Section]) -> TdcCoverage {
+    let mut coverage = TdcCoverage {
+        packets: data.len() / PACKET_SIZE,
+        ..TdcCoverage::default()
+    };
+    let mut seen_tdc = [false; 256];
+    for section in sections {
+        let chip = usize::from(section.chip_id);
+        seen_tdc[chip] |= section.initial_tdc.is_some();
+        let section_data = &data[section.start_offset..section.end_offset];
+        for chunk in section_data.chunks_exact(PACKET_SIZE) {
+            let mut bytes = [0u8; PACKET_SIZE];
+            bytes.copy_from_slice(chunk);
+            let packet = Tpx3Packet::from_bytes(bytes);
+            if packet.is_tdc() {
+                coverage.tdcs += 1;
+                seen_tdc[chip] = true;
+            } else if packet.is_hit() {
+                coverage.hits += 1;
+                if !seen_tdc[chip] {
+                    coverage.hits_before_tdc += 1;
+                }
+            }
+        }
+    }
+    coverage
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::section::discover_sections;
+
+    fn make_header(chip_id: u8) -> u64 {
+        Tpx3Packet::TPX3_HEADER_MAGIC | (u64::from(chip_id) << 32)
+    }
+
+    fn make_tdc(timestamp: u32) -> u64 {
+        0x6F00_0000_0000_0000 | (u64::from(timestamp) << 12)
+    }
+
+    #[test]
+    fn test_estimate_handles_rollover_and_dropped_tdc() {
+        // 14 Hz source: 2_857_143 ticks, starting just before a rollover,
+        // with the fourth pulse missing.
+        let period = 2_857_143u32;
+        let start = TDC_MASK - period;
+        let mut packets = vec![make_header(1)];
+        for i in [0u32, 1, 2, 4, 5, 6] {
+            packets.push(make_tdc(start.wrapping_add(i * period) & TDC_MASK));
+        }
+        packets.extend([make_header(0), make_tdc(100)]);
+        let data: Vec<u8> = packets.iter().flat_map(|p| p.to_le_bytes()).collect();
+        let sections = discover_sections(&data);
+
+        let estimate = estimate_tdc_frequency(&data, &sections).unwrap();
+        assert_eq!(estimate.chip_id, 1);
+        assert_eq!(estimate.intervals, 5);
+        assert_eq!(estimate.median_interval_25ns, period);
+        assert!((estimate.frequency_hz - 14.0).abs() < 1e-3);
+        assert!(estimate.disagrees_with(60.0, DEFAULT_TDC_FREQUENCY_TOLERANCE));
+        assert!(!estimate.disagrees_with(14.0, DEFAULT_TDC_FREQUENCY_TOLERANCE));
+    }
+
+    #[test]
+    fn test_find_pulse_gaps() {
+        let period = 666_667u32;
+        let mut packets = vec![make_header(0)];
+        // Pulses 3 and 7-8 are missing.
+        for i in [0u32, 1, 2, 4, 5, 6, 9] {
+            packets.push(make_tdc(i * period));
+        }
+        let data: Vec<u8> = packets.iter().flat_map(|p| p.to_le_bytes()).collect();
+        let sections = discover_sections(&data);
+
+        let report = find_pulse_gaps(&data, &sections, period).unwrap();
+        assert_eq!(report.pulses, 7);
+        assert_eq!(
+            report.gaps,
+            vec![
+                PulseGap {
+                    after_25ns: 2 * u64::from(period),
+                    missing: 1,
+                },
+                PulseGap {
+                    after_25ns: 6 * u64::from(period),
+                    missing: 2,
+                },
+            ]
+        );
+        assert_eq!(report.missing_pulses(), 3);
+        assert_eq!(
+            report.lost_time(),
+            Duration::from_nanos(3 * u64::from(period) * 25)
+        );
+    }
+
+    #[test]
+    fn test_diagnose_tdc_sections() {
+        let period = 666_667u32;
+        // Pulse 3 missing, a duplicate of pulse 5, and pulse 6 arriving 5%
+        // of a period late; the second section continues chip 0.
+        let data: Vec<u8> = [
+            make_header(0),
+            make_tdc(0),
+            make_tdc(period),
+            make_tdc(2 * period),
+            make_tdc(4 * period),
+            make_header(1),
+            make_tdc(100),
+            make_header(0),
+            make_tdc(5 * period),
+            make_tdc(5 * period + 10),
+            make_tdc(6 * period + period / 20),
+        ]
+        .iter()
+        .flat_map(|p| p.to_le_bytes())
+        .collect();
+        let sections = discover_sections(&data);
+
+        let report = diagnose_tdc_sections(&data, &sections, period).unwrap();
+        assert_eq!(report.sections.len(), 3);
+        assert_eq!(report.tdcs(), 8);
+        assert_eq!(report.missing(), 1);
+        assert_eq!(report.duplicate(), 1);
+        assert_eq!(report.irregular(), 1);
+        assert_eq!(report.sections[0].missing, 1);
+        assert!(!report.sections[1].has_anomalies());
+        let anomalous: Vec<usize> = report.anomalous_sections().map(|s| s.section).collect();
+        assert_eq!(anomalous, vec![0, 2]);
+        assert!(diagnose_tdc_sections(&data, &sections, 0).is_none());
+    }
+
+    #[test]
+    fn test_tdc_coverage_flags() {
+        let hit = 0xB000_0000_0000_0000u64;
+        let summarize = |packets: &[u64]| {
+            let data: Vec<u8> = packets.iter().flat_map(|p| p.to_le_bytes()).collect();
+            summarize_tdc_coverage(&data, &discover_sections(&data))
+        };
+
+        assert_eq!(summarize(&[]).flags(), vec![CoverageFlag::Empty]);
+        assert_eq!(
+            summarize(&[make_header(0), make_tdc(100)]).flags(),
+            vec![CoverageFlag::NoHits]
+        );
+        assert_eq!(
+            summarize(&[make_header(0), hit, hit]).flags(),
+            vec![CoverageFlag::NoTdc]
+        );
+
+        // Chip 1 never sees a TDC; chip 0's second section inherits one.
+        let coverage = summarize(&[
+            make_header(0),
+            hit,
+            make_tdc(100),
+            hit,
+            make_header(1),
+            hit,
+            make_header(0),
+            hit,
+        ]);
+        assert_eq!(coverage.hits, 4);
+        assert_eq!(coverage.tdcs, 1);
+        assert_eq!(coverage.flags(), vec![CoverageFlag::HitsBeforeTdc(2)]);
+    }
+
+    #[test]
+    fn test_estimate_requires_two_tdcs() {
+        let packets = [make_header(0), make_tdc(100)];
+        let data: Vec<u8> = packets.iter().flat_map(|p| p.to_le_bytes()).collect();
+        assert!(estimate_tdc_frequency(&data, &discover_sections(&data)).is_none());
+    }
+}
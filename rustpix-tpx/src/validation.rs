@@ -0,0 +1,331 @@
+//! Invariant checks for diagnosing new detector setups.
+//!
+//! Validation re-walks the raw sections and records packets that break the
+//! assumptions the processing pipeline relies on: hit timestamps that go
+//! backwards within a section, and pixel coordinates that fall outside the
+//! chip or the assembled detector. Cluster labels produced later in the
+//! pipeline can be checked with [`ValidationReport::check_labels`].
+//!
+//! Every violation keeps the byte offset of the offending packet so it can
+//! be inspected with a hex dump of the file.
+
+use crate::hit::correct_timestamp_rollover;
+use crate::packet::Tpx3Packet;
+use crate::section::Tpx3Section;
+use crate::DetectorConfig;
+use std::fmt;
+
+const PACKET_SIZE: usize = 8;
+
+/// Options for a validation pass.
+#[derive(Clone, Debug)]
+pub struct ValidationConfig {
+    /// How far (in 25 ns ticks) a hit timestamp may fall behind the previous
+    /// hit of the same section before it is reported.
+    pub timestamp_tolerance_25ns: u32,
+    /// Maximum number of violations kept with full detail; all are counted.
+    pub max_reported: usize,
+}
+
+impl Default for ValidationConfig {
+    fn default() -> Self {
+        Self {
+            timestamp_tolerance_25ns: 0,
+            max_reported: 100,
+        }
+    }
+}
+
+/// A broken invariant.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ViolationKind {
+    /// Hit timestamp earlier than the previous hit in the same section.
+    TimestampDecrease {
+        /// Previous hit timestamp (25 ns ticks, rollover corrected).
+        previous: u32,
+        /// This hit's timestamp (25 ns ticks, rollover corrected).
+        current: u32,
+    },
+    /// Local pixel coordinates outside the configured chip size.
+    LocalOutOfBounds {
+        /// Local X.
+        x: u16,
+        /// Local Y.
+        y: u16,
+    },
+    /// Global coordinates outside the detector after the chip transform.
+    GlobalOutOfBounds {
+        /// Global X.
+        x: u16,
+        /// Global Y.
+        y: u16,
+    },
+    /// Cluster label outside `-1..num_clusters`.
+    LabelOutOfRange {
+        /// Hit index within the checked batch.
+        hit: usize,
+        /// Offending label.
+        label: i32,
+        /// Number of clusters reported for the batch.
+        num_clusters: usize,
+    },
+}
+
+/// One recorded violation.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Violation {
+    /// Byte offset of the packet in the file (`None` for label checks).
+    pub offset: Option<usize>,
+    /// Chip the packet belongs to (`None` for label checks).
+    pub chip_id: Option<u8>,
+    /// What went wrong.
+    pub kind: ViolationKind,
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(offset) = self.offset {
+            write!(f, "offset {offset:#x}")?;
+        }
+        if let Some(chip_id) = self.chip_id {
+            write!(f, " chip {chip_id}")?;
+        }
+        if self.offset.is_some() || self.chip_id.is_some() {
+            write!(f, ": ")?;
+        }
+        match self.kind {
+            ViolationKind::TimestampDecrease { previous, current } => write!(
+                f,
+                "hit timestamp {current} is before previous hit at {previous}"
+            ),
+            ViolationKind::LocalOutOfBounds { x, y } => {
+                write!(f, "local pixel ({x}, {y}) outside chip")
+            }
+            ViolationKind::GlobalOutOfBounds { x, y } => {
+                write!(f, "global pixel ({x}, {y}) outside detector")
+            }
+            ViolationKind::LabelOutOfRange {
+                hit,
+                label,
+                num_clusters,
+            } => write!(
+                f,
+                "hit {hit} has cluster label {label} (num_clusters = {num_clusters})"
+            ),
+        }
+    }
+}
+
+/// Result of a validation pass.
+#[derive(Clone, Debug, Default)]
+pub struct ValidationReport {
+    /// Sections walked.
+    pub sections_checked: usize,
+    /// Hit packets checked.
+    pub hits_checked: usize,
+    /// Cluster labels checked.
+    pub labels_checked: usize,
+    /// Timestamp ordering violations.
+    pub timestamp_violations: usize,
+    /// Coordinate violations (local or global).
+    pub bounds_violations: usize,
+    /// Cluster label violations.
+    pub label_violations: usize,
+    /// The first violations found, up to the configured limit.
+    pub violations: Vec<Violation>,
+    max_reported: usize,
+}
+
+impl ValidationReport {
+    /// Create an empty report keeping up to `max_reported` violations.
+    #[must_use]
+    pub fn new(max_reported: usize) -> Self {
+        Self {
+            max_reported,
+            ..Self::default()
+        }
+    }
+
+    /// Total violations of all kinds.
+    #[must_use]
+    pub fn total_violations(&self) -> usize {
+        self.timestamp_violations + self.bounds_violations + self.label_violations
+    }
+
+    /// Returns true if no invariant was broken.
+    #[must_use]
+    pub fn is_clean(&self) -> bool {
+        self.total_violations() == 0
+    }
+
+    /// Check that every label is `-1` or a cluster index below `num_clusters`.
+    pub fn check_labels(&mut self, labels: &[i32], num_clusters: usize) {
+        self.labels_checked += labels.len();
+        for (hit, &label) in labels.iter().enumerate() {
+            let in_range = label == -1 || usize::try_from(label).is_ok_and(|l| l < num_clusters);
+            if !in_range {
+                self.label_violations += 1;
+                self.push(Violation {
+                    offset: None,
+                    chip_id: None,
+                    kind: ViolationKind::LabelOutOfRange {
+                        hit,
+                        label,
+                        num_clusters,
+                    },
+                });
+            }
+        }
+    }
+
+    fn record(&mut self, offset: usize, chip_id: u8, kind: ViolationKind) {
+        match kind {
+            ViolationKind::TimestampDecrease { .. } => self.timestamp_violations += 1,
+            ViolationKind::LocalOutOfBounds { .. } | ViolationKind::GlobalOutOfBounds { .. } => {
+                self.bounds_violations += 1;
+            }
+            ViolationKind::LabelOutOfRange { .. } => self.label_violations += 1,
+        }
+        self.push(Violation {
+            offset: Some(offset),
+            chip_id: Some(chip_id),
+            kind,
+        });
+    }
+
+    fn push(&mut self, violation: Violation) {
+        if self.violations.len() < self.max_reported {
+            self.violations.push(violation);
+        }
+    }
+}
+
+/// Walk `sections` of `data` and check packet-level invariants.
+#[must_use]
+pub fn validate_sections(
+    data: &[u8],
+    sections: &[Tpx3Section],
+    config: &DetectorConfig,
+    options: &ValidationConfig,
+) -> ValidationReport {
+    let mut report = ValidationReport::new(options.max_reported);
+    let (width, height) = config.detector_dimensions();
+
+    for section in sections {
+        report.sections_checked += 1;
+        let transform = config.chip_transform(section.chip_id);
+        let mut current_tdc = section.initial_tdc;
+        let mut last_timestamp: Option<u32> = None;
+
+        let section_data = &data[section.start_offset..section.end_offset];
+        for (i, chunk) in section_data.chunks_exact(PACKET_SIZE).enumerate() {
+            let offset = section.start_offset + i * PACKET_SIZE;
+            let mut bytes = [0u8; PACKET_SIZE];
+            bytes.copy_from_slice(chunk);
+            let packet = Tpx3Packet::from_bytes(bytes);
+
+            if packet.is_tdc() {
+                let tdc = packet.tdc_timestamp();
+                // A TDC going backwards is a 30-bit rollover; hit timestamps
+                // restart with it.
+                if current_tdc.is_some_and(|previous| tdc < previous) {
+                    last_timestamp = None;
+                }
+                current_tdc = Some(tdc);
+                continue;
+            }
+            if !packet.is_hit() {
+                continue;
+            }
+            report.hits_checked += 1;
+            let chip_id = section.chip_id;
+
+            let (x, y) = packet.pixel_coordinates();
+            if x >= config.chip_size_x || y >= config.chip_size_y {
+                report.record(offset, chip_id, ViolationKind::LocalOutOfBounds { x, y });
+            } else {
+                let (gx, gy) = transform.apply(x, y);
+                if usize::from(gx) >= width || usize::from(gy) >= height {
+                    report.record(
+                        offset,
+                        chip_id,
+                        ViolationKind::GlobalOutOfBounds { x: gx, y: gy },
+                    );
+                }
+            }
+
+            let raw = packet.timestamp_coarse();
+            let timestamp = current_tdc.map_or(raw, |tdc| correct_timestamp_rollover(raw, tdc));
+            if let Some(previous) = last_timestamp {
+                if timestamp.saturating_add(options.timestamp_tolerance_25ns) < previous {
+                    report.record(
+                        offset,
+                        chip_id,
+                        ViolationKind::TimestampDecrease {
+                            previous,
+                            current: timestamp,
+                        },
+                    );
+                }
+            }
+            last_timestamp = Some(last_timestamp.map_or(timestamp, |last| last.max(timestamp)));
+        }
+    }
+
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::section::discover_sections;
+
+    fn make_header(chip_id: u8) -> u64 {
+        Tpx3Packet::TPX3_HEADER_MAGIC | (u64::from(chip_id) << 32)
+    }
+
+    fn make_tdc(timestamp: u32) -> u64 {
+        0x6F00_0000_0000_0000 | (u64::from(timestamp) << 12)
+    }
+
+    fn make_hit(toa: u16, addr: u16) -> u64 {
+        0xB000_0000_0000_0000 | (u64::from(toa) << 30) | (u64::from(addr) << 44)
+    }
+
+    #[test]
+    fn test_validate_sections_reports_offsets() {
+        let packets = [
+            make_header(3),
+            make_tdc(100),
+            make_hit(300, 0),
+            make_hit(200, 0),
+            make_hit(400, 0),
+        ];
+        let data: Vec<u8> = packets.iter().flat_map(|p| p.to_le_bytes()).collect();
+        let sections = discover_sections(&data);
+        let config = DetectorConfig::venus_defaults();
+
+        let report = validate_sections(&data, &sections, &config, &ValidationConfig::default());
+        assert_eq!(report.hits_checked, 3);
+        assert_eq!(report.timestamp_violations, 1);
+        assert_eq!(report.bounds_violations, 0);
+        assert_eq!(report.violations[0].offset, Some(24));
+        assert_eq!(report.violations[0].chip_id, Some(3));
+
+        let tolerant = ValidationConfig {
+            timestamp_tolerance_25ns: 100,
+            ..ValidationConfig::default()
+        };
+        assert!(validate_sections(&data, &sections, &config, &tolerant).is_clean());
+    }
+
+    #[test]
+    fn test_check_labels() {
+        let mut report = ValidationReport::new(1);
+        report.check_labels(&[-1, 0, 1, 2, -5], 2);
+        assert_eq!(report.labels_checked, 5);
+        assert_eq!(report.label_violations, 2);
+        assert_eq!(report.violations.len(), 1);
+        assert!(report.violations[0].to_string().contains("label 2"));
+    }
+}
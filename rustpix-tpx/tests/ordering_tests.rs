@@ -1,8 +1,12 @@
 use rustpix_core::soa::HitBatch;
-use rustpix_tpx::ordering::TimeOrderedStream;
+use rustpix_tpx::ordering::{read_time_ordered_parallel, section_pulse_index, TimeOrderedStream};
+use rustpix_tpx::projection::{read_columns_parallel, HitColumns};
 use rustpix_tpx::section::discover_sections;
-use rustpix_tpx::DetectorConfig;
 use rustpix_tpx::Tpx3Packet;
+use rustpix_tpx::{
+    ChipTransform, DetectorConfig, NoTdcFallback, OutOfBoundsCounts, OutOfBoundsPolicy,
+    TdcCorrectionPolicy, ToaCorrection,
+};
 
 // Helper to create a TPX3 header packet
 fn make_header(chip_id: u8) -> u64 {
@@ -122,6 +126,45 @@ fn test_interleaved_ordering() {
     assert_eq!(hits.chip_id[3], 0);
 }
 
+#[test]
+fn test_toa_correction_per_column() {
+    // Two hits at the same coarse time, in local columns 0 and 2 of chip 0.
+    // Address bits: dcol = (addr >> 8) & 0xFE, so 0x0200 is column 2.
+    let mut data = Vec::new();
+    data.extend_from_slice(&make_header(0).to_le_bytes());
+    data.extend_from_slice(&make_tdc(1000).to_le_bytes());
+    data.extend_from_slice(&make_hit(1100, 10, 0x0000).to_le_bytes());
+    data.extend_from_slice(&make_hit(1100, 10, 0x0200).to_le_bytes());
+    data.extend_from_slice(&make_tdc(2000).to_le_bytes());
+    let sections = discover_sections(&data);
+
+    // Column 2 runs 50 ns late; the whole chip runs 25 ns early.
+    let config = DetectorConfig {
+        toa_corrections: vec![Some(ToaCorrection {
+            offset_ns: -25.0,
+            column_offsets_ns: vec![0.0, 0.0, 50.0],
+        })],
+        ..DetectorConfig::default()
+    };
+
+    let hits = collect_batches(TimeOrderedStream::new(&data, &sections, &config));
+    assert_eq!(hits.len(), 2);
+    let tof_for_column = |column: u16| {
+        let x = config.map_chip_to_global(0, column, 0).0;
+        hits.tof[hits.x.iter().position(|&hx| hx == x).unwrap()]
+    };
+    assert_eq!(tof_for_column(0), 101);
+    assert_eq!(tof_for_column(2), 99);
+
+    // Without corrections both hits keep the coarse time.
+    let plain = collect_batches(TimeOrderedStream::new(
+        &data,
+        &sections,
+        &DetectorConfig::default(),
+    ));
+    assert_eq!(plain.tof, vec![100, 100]);
+}
+
 #[test]
 fn test_independent_rollover() {
     let mut data = Vec::new();
@@ -243,6 +286,272 @@ fn test_tdc_rollover_ordering() {
     assert_eq!(hits.tof[2], 30);
 }
 
+#[test]
+fn test_parallel_read_matches_stream() {
+    // Two chips in interleaved sections. Pulses span section boundaries,
+    // late hits arrive in the section after the next TDC, and the TDC rolls
+    // over halfway through.
+    let tdcs: [u32; 4] = [0x3FFF_0000, 0x3FFF_8000, 0x0000_1000, 0x0000_9000];
+    let mut data = Vec::new();
+    for (pulse, &tdc) in tdcs.iter().enumerate() {
+        for chip in 0u8..2 {
+            let offset = 10 * u32::from(chip) + 100 * u32::try_from(pulse).unwrap();
+            data.extend_from_slice(&make_header(chip).to_le_bytes());
+            // Late hit of the previous pulse, after its closing TDC.
+            if pulse > 0 {
+                let late = tdc.wrapping_sub(50 + offset) & 0x3FFF_FFFF;
+                data.extend_from_slice(&make_hit(late, 5, 1).to_le_bytes());
+            }
+            data.extend_from_slice(&make_hit(tdc + 300 + offset, 5, 2).to_le_bytes());
+            data.extend_from_slice(&make_tdc(tdc).to_le_bytes());
+            data.extend_from_slice(&make_hit(tdc + 20 + offset, 5, 3).to_le_bytes());
+            data.extend_from_slice(&make_hit(tdc + 7 + offset, 5, 4).to_le_bytes());
+        }
+    }
+
+    let sections = discover_sections(&data);
+    assert_eq!(sections.len(), 8);
+    let config = DetectorConfig::default();
+    let expected = collect_batches(TimeOrderedStream::new(&data, &sections, &config));
+    let hits = read_time_ordered_parallel(&data, &sections, &config);
+
+    assert!(expected.len() > 20);
+    assert_eq!(hits.tof, expected.tof);
+    assert_eq!(hits.timestamp, expected.timestamp);
+    assert_eq!(hits.chip_id, expected.chip_id);
+    assert_eq!(hits.x, expected.x);
+    assert_eq!(hits.y, expected.y);
+
+    // Phase 1 numbers the pulses the stream yields, across the rollover.
+    let (_, index) = section_pulse_index(&data, &sections);
+    let mut stream = TimeOrderedStream::new(&data, &sections, &config);
+    let pulses: Vec<u64> = std::iter::from_fn(|| stream.next_pulse_batch())
+        .map(|pulse| pulse.tdc_timestamp)
+        .collect();
+    assert_eq!(index.start_times_25ns(), pulses.as_slice());
+    assert_eq!(index.id_of((1 << 30) | 0x1000), Some(2));
+}
+
+#[test]
+fn test_no_tdc_fallback_first_hit() {
+    let period = DetectorConfig::default().tdc_correction_25ns();
+    let start = 5000;
+    let mut data = Vec::new();
+    data.extend_from_slice(&make_header(0).to_le_bytes());
+    for timestamp in [
+        start,
+        start + 100,
+        start + period + 10,
+        start + 2 * period + 20,
+    ] {
+        data.extend_from_slice(&make_hit(timestamp, 5, 0).to_le_bytes());
+    }
+    // A real TDC takes over from the synthetic pulses.
+    let tdc = start + 3 * period + 500;
+    data.extend_from_slice(&make_tdc(tdc).to_le_bytes());
+    data.extend_from_slice(&make_hit(tdc + 30, 5, 0).to_le_bytes());
+    let sections = discover_sections(&data);
+
+    let dropped = read_time_ordered_parallel(&data, &sections, &DetectorConfig::default());
+    assert_eq!(dropped.tof, vec![30]);
+
+    let config = DetectorConfig {
+        no_tdc_fallback: NoTdcFallback::FirstHit,
+        ..DetectorConfig::default()
+    };
+    let mut stream = TimeOrderedStream::new(&data, &sections, &config);
+    let pulses: Vec<u64> = std::iter::from_fn(|| stream.next_pulse_batch())
+        .map(|pulse| pulse.tdc_timestamp)
+        .collect();
+    let start = u64::from(start);
+    let period = u64::from(period);
+    assert_eq!(
+        pulses,
+        vec![start, start + period, start + 2 * period, u64::from(tdc)]
+    );
+
+    let hits = read_time_ordered_parallel(&data, &sections, &config);
+    assert_eq!(hits.tof, vec![0, 100, 10, 20, 30]);
+}
+
+#[test]
+fn test_tdc_correction_policies() {
+    // Two TDCs are missing after the first, and the source runs 300 ticks
+    // slower than the configured period. The pulse after the gap is in its
+    // own section.
+    let period = DetectorConfig::default().tdc_correction_25ns();
+    let actual = period + 300;
+    let mut data = Vec::new();
+    data.extend_from_slice(&make_header(0).to_le_bytes());
+    data.extend_from_slice(&make_tdc(1000).to_le_bytes());
+    for timestamp in [1100, 1050 + actual, 1070 + 2 * actual] {
+        data.extend_from_slice(&make_hit(timestamp, 5, 0).to_le_bytes());
+    }
+    data.extend_from_slice(&make_header(0).to_le_bytes());
+    data.extend_from_slice(&make_tdc(1000 + 3 * actual).to_le_bytes());
+    data.extend_from_slice(&make_hit(1040 + 3 * actual, 5, 0).to_le_bytes());
+    let sections = discover_sections(&data);
+    let config = |policy| DetectorConfig {
+        tdc_correction_policy: policy,
+        ..DetectorConfig::default()
+    };
+
+    let expected = [
+        (
+            TdcCorrectionPolicy::None,
+            vec![100, actual + 50, 2 * actual + 70, 40],
+        ),
+        (
+            TdcCorrectionPolicy::ExtrapolateLastPeriod,
+            vec![100, 350, period + 670, 40],
+        ),
+        (
+            TdcCorrectionPolicy::InterpolateNeighbors,
+            vec![50, 70, 100, 40],
+        ),
+        (TdcCorrectionPolicy::DropSection, vec![100, 40]),
+    ];
+    for (policy, tofs) in expected {
+        let config = config(policy);
+        let streamed = collect_batches(TimeOrderedStream::new(&data, &sections, &config));
+        assert_eq!(streamed.tof, tofs, "{policy:?}");
+        let parallel = read_time_ordered_parallel(&data, &sections, &config);
+        assert_eq!(parallel.tof, tofs, "{policy:?} (parallel)");
+    }
+}
+
+#[test]
+fn test_out_of_bounds_policy() {
+    // Column 200 is off the canvas of a chip configured as 128 pixels wide.
+    let mut data = Vec::new();
+    data.extend_from_slice(&make_header(0).to_le_bytes());
+    data.extend_from_slice(&make_tdc(1000).to_le_bytes());
+    data.extend_from_slice(&make_hit(1100, 5, 0x0210).to_le_bytes());
+    data.extend_from_slice(&make_hit(1200, 5, 0xC810).to_le_bytes());
+    let sections = discover_sections(&data);
+    let config = |policy| DetectorConfig {
+        chip_size_x: 128,
+        chip_size_y: 128,
+        chip_transforms: Vec::new(),
+        out_of_bounds: policy,
+        out_of_bounds_counts: OutOfBoundsCounts::default(),
+        ..DetectorConfig::default()
+    };
+
+    let clamp = config(OutOfBoundsPolicy::Clamp);
+    let hits = collect_batches(TimeOrderedStream::new(&data, &sections, &clamp));
+    assert_eq!((hits.x, hits.y), (vec![2, 127], vec![8, 8]));
+    assert_eq!(clamp.out_of_bounds_counts.per_chip(), vec![(0, 1)]);
+
+    let drop = config(OutOfBoundsPolicy::Drop);
+    let hits = collect_batches(TimeOrderedStream::new(&data, &sections, &drop));
+    assert_eq!(hits.x, vec![2]);
+    let hits = read_time_ordered_parallel(&data, &sections, &drop);
+    assert_eq!(hits.x, vec![2]);
+    assert_eq!(drop.out_of_bounds_counts.per_chip(), vec![(0, 2)]);
+}
+
+#[test]
+fn test_raw_hit_fields() {
+    // The second pulse starts just after the 30-bit ToA rollover.
+    let tdcs: [u32; 2] = [0x3FFF_FF00, 0x0000_0100];
+    let mut data = Vec::new();
+    for &tdc in &tdcs {
+        for chip in 0u8..2 {
+            data.extend_from_slice(&make_header(chip).to_le_bytes());
+            data.extend_from_slice(&make_tdc(tdc).to_le_bytes());
+            let hit = (tdc + 40 + u32::from(chip)) & 0x3FFF_FFFF;
+            data.extend_from_slice(&make_hit(hit, 5, 0x1234).to_le_bytes());
+        }
+    }
+    let sections = discover_sections(&data);
+    let plain_config = DetectorConfig::default();
+    let plain = collect_batches(TimeOrderedStream::new(&data, &sections, &plain_config));
+    assert!(!plain.has_raw());
+
+    let config = DetectorConfig {
+        retain_raw_hits: true,
+        ..DetectorConfig::default()
+    };
+    let hits = collect_batches(TimeOrderedStream::new(&data, &sections, &config));
+    assert_eq!(hits.len(), 4);
+    assert_eq!(hits.tof, plain.tof);
+    assert_eq!(hits.raw_toa.len(), hits.len());
+    for i in 0..hits.len() {
+        let transform = config.chip_transform(hits.chip_id[i]);
+        assert_eq!(
+            transform.apply(hits.raw_x[i], hits.raw_y[i]),
+            (hits.x[i], hits.y[i])
+        );
+        assert_eq!(hits.raw_toa[i], hits.timestamp[i] & 0x3FFF_FFFF);
+    }
+    assert!(hits.raw_toa.iter().any(|&toa| toa < 0x1000));
+
+    let parallel = read_time_ordered_parallel(&data, &sections, &config);
+    assert_eq!(parallel.raw_x, hits.raw_x);
+    assert_eq!(parallel.raw_toa, hits.raw_toa);
+}
+
+#[test]
+fn test_position_projection() {
+    let mut data = Vec::new();
+    data.extend_from_slice(&make_header(1).to_le_bytes());
+    // Before the first TDC: dropped by the time-ordered read only.
+    data.extend_from_slice(&make_hit(500, 7, 0x0201).to_le_bytes());
+    data.extend_from_slice(&make_tdc(1000).to_le_bytes());
+    data.extend_from_slice(&make_hit(1300, 8, 0x0400).to_le_bytes());
+    data.extend_from_slice(&make_hit(1100, 9, 0x0000).to_le_bytes());
+    let sections = discover_sections(&data);
+    let config = DetectorConfig {
+        chip_transforms: vec![
+            ChipTransform::identity(),
+            ChipTransform {
+                a: 1,
+                b: 0,
+                c: 0,
+                d: 1,
+                tx: 256,
+                ty: 0,
+            },
+        ],
+        ..DetectorConfig::default()
+    };
+
+    let full = read_time_ordered_parallel(&data, &sections, &config);
+    let positions = read_columns_parallel(&data, &sections, &config, HitColumns::POSITIONS);
+    assert_eq!(positions.len(), full.len() + 1);
+    // File order, with the pre-TDC hit first.
+    assert_eq!(&positions.x[1..], &[full.x[1], full.x[0]]);
+    assert_eq!(&positions.y[1..], &[full.y[1], full.y[0]]);
+    assert!(positions.x.iter().all(|&x| x >= 256));
+    assert!(positions.chip_id.iter().all(|&chip| chip == 1));
+    assert!(positions
+        .tof
+        .iter()
+        .chain(&positions.timestamp)
+        .all(|&t| t == 0));
+    assert!(positions.tot.iter().all(|&tot| tot == 0));
+
+    let with_tot = HitColumns {
+        tot: true,
+        time: false,
+    };
+    let hits = read_columns_parallel(&data, &sections, &config, with_tot);
+    assert_eq!(hits.tot, [7, 8, 9]);
+
+    let without_tot = HitColumns {
+        tot: false,
+        time: true,
+    };
+    let hits = read_columns_parallel(&data, &sections, &config, without_tot);
+    assert_eq!(hits.tof, full.tof);
+    assert!(hits.tot.iter().all(|&tot| tot == 0));
+    assert_eq!(
+        read_columns_parallel(&data, &sections, &config, HitColumns::ALL),
+        full
+    );
+}
+
 #[test]
 #[ignore = "Run with `cargo test -- --ignored` to benchmark"]
 fn test_performance_synthetic() {
@@ -285,4 +594,11 @@ fn test_performance_synthetic() {
     // Throughput
     let hits_per_sec = f64::from(u32::try_from(count).unwrap()) / elapsed.as_secs_f64();
     println!("Throughput: {:.2} M hits/s", hits_per_sec / 1e6);
+
+    let start = std::time::Instant::now();
+    let positions = read_columns_parallel(&data, &sections, &config, HitColumns::POSITIONS);
+    let elapsed = start.elapsed();
+    assert_eq!(u64::try_from(positions.len()).unwrap(), expected_count);
+    let hits_per_sec = f64::from(u32::try_from(count).unwrap()) / elapsed.as_secs_f64();
+    println!("Positions only: {:.2} M hits/s", hits_per_sec / 1e6);
 }
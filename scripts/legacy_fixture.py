@@ -0,0 +1,44 @@
+#!/usr/bin/env python3
+"""Regenerate rustpix-io/tests/data/legacy_events.bin.
+
+The fixture is the legacy C++ neutron struct laid out by ctypes, which
+follows the platform C ABI (natural alignment, trailing padding), filled
+with the neutrons that rustpix-io/tests/legacy_format.rs writes. It is an
+independent check of the byte layout; it was not produced by mcpevent2hist.
+
+Usage:
+    python scripts/legacy_fixture.py
+"""
+
+import ctypes
+from pathlib import Path
+
+
+class LegacyNeutron(ctypes.LittleEndianStructure):
+    _fields_ = [
+        ("x", ctypes.c_double),
+        ("y", ctypes.c_double),
+        ("tof", ctypes.c_double),
+        ("tot", ctypes.c_double),
+        ("n_hits", ctypes.c_int32),
+    ]
+
+
+# (x, y, tof, tot, n_hits) in pixels and ns, as the C++ tools write them.
+NEUTRONS = [
+    (2.0, 3.0, 1000.0, 100.0, 5),
+    (255.875, 0.125, 16_666_650.0, 65_535.0, 1),
+    (0.0, 511.5, 25.0, 0.0, 65_535),
+]
+
+
+def main():
+    assert ctypes.sizeof(LegacyNeutron) == 40
+    data = b"".join(bytes(LegacyNeutron(*neutron)) for neutron in NEUTRONS)
+    path = Path(__file__).resolve().parent.parent / "rustpix-io/tests/data/legacy_events.bin"
+    path.write_bytes(data)
+    print(f"Wrote {len(NEUTRONS)} records to {path}")
+
+
+if __name__ == "__main__":
+    main()
@@ -0,0 +1,74 @@
+#!/usr/bin/env python3
+"""Read the ROOT files of the root_roundtrip example back with uproot.
+
+The example writes known values through rustpix-io's own ROOT writer;
+this script checks that an independent reader sees the same trees,
+branch types and entries.
+
+Usage:
+    cargo run -p rustpix-io --features root --example root_roundtrip -- out
+    python scripts/root_roundtrip.py out
+"""
+
+import sys
+from pathlib import Path
+
+import numpy as np
+import uproot
+
+# Must match rustpix-io/examples/root_roundtrip.rs.
+NEUTRONS = 20_003
+HITS = 16_001
+
+
+def check(path, tree_name, expected):
+    with uproot.open(path) as file:
+        assert file.keys() == [f"{tree_name};1"], file.keys()
+        tree = file[tree_name]
+        entries = len(expected["tof"])
+        assert tree.num_entries == entries, tree.num_entries
+        assert list(tree.keys()) == list(expected), tree.keys()
+        arrays = tree.arrays(library="np")
+        for name, values in expected.items():
+            actual = arrays[name]
+            assert actual.dtype == values.dtype, (tree_name, name, actual.dtype)
+            mismatch = np.flatnonzero(actual != values)
+            assert mismatch.size == 0, (tree_name, name, mismatch[:5])
+    print(f"{path}: {tree_name} matches ({entries} entries)")
+
+
+def neutrons():
+    i = np.arange(NEUTRONS, dtype=np.uint32)
+    return {
+        "x": i.astype(np.float64) * 0.25,
+        "y": (i % 517).astype(np.float64) + 0.5,
+        "tof": i * np.uint32(37),
+        "tot_sum": (i % 1000).astype(np.uint16),
+        "cluster_size": (1 + i % 9).astype(np.uint16),
+        "chip_id": (i % 4).astype(np.uint8),
+    }
+
+
+def hits():
+    i = np.arange(HITS, dtype=np.uint32)
+    labelled = i % 10 != 9
+    ids = i // 3
+    counts = np.bincount(ids[labelled], minlength=ids.max() + 1)
+    return {
+        "x": (i % 514).astype(np.uint16),
+        "y": (i * 7 % 514).astype(np.uint16),
+        "tof": i * np.uint32(11),
+        "tot_sum": (i % 1024).astype(np.uint16),
+        "cluster_size": np.where(labelled, counts[ids], 1).astype(np.uint16),
+        "chip_id": (i % 4).astype(np.uint8),
+    }
+
+
+def main():
+    directory = Path(sys.argv[1] if len(sys.argv) > 1 else ".")
+    check(directory / "neutrons.root", "neutrons", neutrons())
+    check(directory / "hits.root", "hits", hits())
+
+
+if __name__ == "__main__":
+    main()